@@ -0,0 +1,57 @@
+/*
+ * C interface to pi, built with the `ffi` feature:
+ *
+ *     cargo rustc --release --lib --features ffi --crate-type cdylib
+ *
+ * Values cross the boundary as UTF-8 JSON strings. Strings returned by pi belong to the
+ * caller and are released with pi_string_free. Failed calls return NULL (or -1) and leave
+ * a message for pi_last_error. A session must only be used from the thread that created it.
+ */
+#ifndef PI_H
+#define PI_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define PI_FFI_VERSION 1
+
+typedef struct PiSession PiSession;
+
+/* Called with each event as a JSON object; the string is only valid during the call. */
+typedef void (*PiEventCallback)(const char *event_json, void *user_data);
+
+uint32_t pi_ffi_version(void);
+
+/* Message of the last failed call on this thread, or NULL. Valid until the next call. */
+const char *pi_last_error(void);
+
+/*
+ * config_json is NULL or an object with any of: provider, model, apiKey, tools (array),
+ * systemPrompt, appendSystemPrompt, modelsPath.
+ */
+PiSession *pi_session_new(const char *config_json);
+
+/* Returns {"text", "stopReason", "usage", "messages"}, or NULL when the run failed. */
+char *pi_session_prompt(PiSession *session, const char *text);
+
+/* Replaces the event callback; NULL removes it. */
+int pi_session_set_event_callback(PiSession *session, PiEventCallback callback, void *user_data);
+
+/* The conversation so far as a JSON array of messages. */
+char *pi_session_messages(PiSession *session);
+
+/* Stops the running prompt. Unlike the other calls, this one may be made from an event
+   callback. */
+int pi_session_abort(PiSession *session);
+
+void pi_session_free(PiSession *session);
+void pi_string_free(char *text);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* PI_H */
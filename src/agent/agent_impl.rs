@@ -1,14 +1,19 @@
 use std::cell::{Cell, RefCell};
 use std::collections::HashSet;
 use std::rc::Rc;
+use std::sync::mpsc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::core::messages::{AssistantMessage, ContentBlock, UserContent, UserMessage};
+use crate::core::messages::{
+    AssistantMessage, ContentBlock, UserContent, UserMessage, STOP_REASON_ABORTED,
+};
+use crate::error::Error;
 
 use super::{
-    agent_loop, agent_loop_continue, AgentContext, AgentEvent, AgentLoopConfig, AgentMessage,
-    AgentTool, ConvertToLlmFn, CustomMessage, ListenerFn, LlmContext, Model, StreamEvents,
-    StreamFn, TransformContextFn,
+    agent_loop_continue_with_sink, agent_loop_with_sink, AgentContext, AgentEvent, AgentEventSink,
+    AgentLoopConfig, AgentLoopError, AgentMessage, AgentTool, CancellationToken, ConvertToLlmFn,
+    CustomMessage, EventBus, LlmContext, LoopLimits, Model, OutputFilterFn, StreamEvents, StreamFn,
+    ToolUpdateThrottle, TransformContextFn,
 };
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -66,6 +71,59 @@ pub enum QueueMode {
     OneAtATime,
 }
 
+/// Decides which queue is drained first when steering and follow-up messages are both pending.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum QueuePriority {
+    /// Steering messages are delivered at the next check point (after each tool call or turn);
+    /// follow-ups wait until the agent would otherwise stop.
+    #[default]
+    SteeringFirst,
+    /// Messages are delivered in the order they were queued. A steering message queued behind
+    /// a follow-up waits until that follow-up has been delivered.
+    Fifo,
+}
+
+impl QueuePriority {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            QueuePriority::SteeringFirst => "steering-first",
+            QueuePriority::Fifo => "fifo",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "steering-first" => Some(QueuePriority::SteeringFirst),
+            "fifo" => Some(QueuePriority::Fifo),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueueKind {
+    Steering,
+    FollowUp,
+}
+
+/// A queued message together with the queue it is waiting in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PendingMessage {
+    /// Stays the same while the message waits, so it can be removed or edited by id.
+    pub id: u64,
+    pub kind: QueueKind,
+    pub message: AgentMessage,
+}
+
+/// Queued messages tagged with a sequence number shared by both queues.
+type MessageQueue = Rc<RefCell<Vec<(u64, AgentMessage)>>>;
+
+fn take_queue_seq(next_seq: &Cell<u64>) -> u64 {
+    let seq = next_seq.get();
+    next_seq.set(seq + 1);
+    seq
+}
+
 #[derive(Default)]
 pub struct AgentOptions {
     pub initial_state: Option<AgentStateOverride>,
@@ -73,24 +131,29 @@ pub struct AgentOptions {
     pub transform_context: Option<Box<TransformContextFn>>,
     pub steering_mode: Option<QueueMode>,
     pub follow_up_mode: Option<QueueMode>,
+    pub queue_priority: Option<QueuePriority>,
     pub stream_fn: Option<Box<StreamFn>>,
-    pub abort_flag: Option<Rc<Cell<bool>>>,
+    pub abort_flag: Option<CancellationToken>,
 }
 
-type ListenerEntry = (usize, Box<ListenerFn>);
-
 pub struct Agent {
     state: Rc<RefCell<AgentState>>,
-    listeners: Rc<RefCell<Vec<ListenerEntry>>>,
-    next_listener_id: Rc<RefCell<usize>>,
+    events: EventBus<AgentEvent>,
     convert_to_llm: Rc<RefCell<Box<ConvertToLlmFn>>>,
     transform_context: Option<Rc<RefCell<Box<TransformContextFn>>>>,
-    steering_queue: Rc<RefCell<Vec<AgentMessage>>>,
-    follow_up_queue: Rc<RefCell<Vec<AgentMessage>>>,
+    steering_queue: MessageQueue,
+    follow_up_queue: MessageQueue,
+    next_queue_seq: Rc<Cell<u64>>,
     steering_mode: QueueMode,
     follow_up_mode: QueueMode,
+    queue_priority: QueuePriority,
     stream_fn: Rc<RefCell<Box<StreamFn>>>,
-    aborted: Rc<Cell<bool>>,
+    /// Typed error behind `state.error`, kept apart because `AgentState` is `Clone`.
+    error: Rc<RefCell<Option<Error>>>,
+    aborted: CancellationToken,
+    output_filter: Option<Rc<OutputFilterFn>>,
+    tool_update_throttle: Option<ToolUpdateThrottle>,
+    loop_limits: LoopLimits,
 }
 
 impl Agent {
@@ -101,6 +164,7 @@ impl Agent {
             transform_context,
             steering_mode,
             follow_up_mode,
+            queue_priority,
             stream_fn,
             abort_flag,
         } = opts;
@@ -149,20 +213,25 @@ impl Agent {
         let convert_to_llm = convert_to_llm.unwrap_or_else(|| Box::new(default_convert_to_llm));
         let transform_context = transform_context.map(|transform| Rc::new(RefCell::new(transform)));
         let stream_fn = stream_fn.unwrap_or_else(|| Box::new(default_stream_fn));
-        let aborted = abort_flag.unwrap_or_else(|| Rc::new(Cell::new(false)));
+        let aborted = abort_flag.unwrap_or_default();
 
         Self {
             state: Rc::new(RefCell::new(state)),
-            listeners: Rc::new(RefCell::new(Vec::new())),
-            next_listener_id: Rc::new(RefCell::new(0)),
+            events: EventBus::new(),
             convert_to_llm: Rc::new(RefCell::new(convert_to_llm)),
             transform_context,
             steering_queue: Rc::new(RefCell::new(Vec::new())),
             follow_up_queue: Rc::new(RefCell::new(Vec::new())),
+            next_queue_seq: Rc::new(Cell::new(0)),
             steering_mode: steering_mode.unwrap_or(QueueMode::OneAtATime),
             follow_up_mode: follow_up_mode.unwrap_or(QueueMode::OneAtATime),
+            queue_priority: queue_priority.unwrap_or_default(),
             stream_fn: Rc::new(RefCell::new(stream_fn)),
+            error: Rc::new(RefCell::new(None)),
             aborted,
+            output_filter: None,
+            tool_update_throttle: None,
+            loop_limits: LoopLimits::default(),
         }
     }
 
@@ -170,23 +239,18 @@ impl Agent {
         self.state.borrow().clone()
     }
 
+    /// Calls `listener` on this thread for every event until the returned closure is called.
     pub fn subscribe<F>(&self, listener: F) -> impl FnOnce()
     where
         F: Fn(&AgentEvent) + 'static,
     {
-        let id = {
-            let mut next_id = self.next_listener_id.borrow_mut();
-            let id = *next_id;
-            *next_id += 1;
-            id
-        };
-        self.listeners.borrow_mut().push((id, Box::new(listener)));
-        let listeners = self.listeners.clone();
-        move || {
-            listeners
-                .borrow_mut()
-                .retain(|(listener_id, _)| *listener_id != id);
-        }
+        self.events.subscribe(listener)
+    }
+
+    /// Every event from now on, for a consumer on another thread. Dropping the receiver
+    /// unsubscribes.
+    pub fn subscribe_channel(&self) -> mpsc::Receiver<AgentEvent> {
+        self.events.channel()
     }
 
     pub fn set_system_prompt(&self, value: &str) {
@@ -217,6 +281,37 @@ impl Agent {
         self.follow_up_mode
     }
 
+    pub fn set_queue_priority(&mut self, priority: QueuePriority) {
+        self.queue_priority = priority;
+    }
+
+    pub fn set_output_filter(&mut self, filter: Option<Rc<OutputFilterFn>>) {
+        self.output_filter = filter;
+    }
+
+    pub fn set_tool_update_throttle(&mut self, throttle: Option<ToolUpdateThrottle>) {
+        self.tool_update_throttle = throttle;
+    }
+
+    /// Replaces the stream function with `wrap(current)`, e.g. to record its responses.
+    pub fn wrap_stream_fn(&self, wrap: impl FnOnce(Box<StreamFn>) -> Box<StreamFn>) {
+        let mut stream_fn = self.stream_fn.borrow_mut();
+        let current = std::mem::replace(&mut *stream_fn, Box::new(default_stream_fn));
+        *stream_fn = wrap(current);
+    }
+
+    pub fn set_loop_limits(&mut self, limits: LoopLimits) {
+        self.loop_limits = limits;
+    }
+
+    pub fn loop_limits(&self) -> LoopLimits {
+        self.loop_limits
+    }
+
+    pub fn get_queue_priority(&self) -> QueuePriority {
+        self.queue_priority
+    }
+
     pub fn set_tools(&self, tools: Vec<AgentTool>) {
         self.state.borrow_mut().tools = tools;
     }
@@ -234,11 +329,79 @@ impl Agent {
     }
 
     pub fn steer(&self, message: AgentMessage) {
-        self.steering_queue.borrow_mut().push(message);
+        let seq = self.next_queue_seq();
+        self.steering_queue.borrow_mut().push((seq, message));
     }
 
     pub fn follow_up(&self, message: AgentMessage) {
-        self.follow_up_queue.borrow_mut().push(message);
+        let seq = self.next_queue_seq();
+        self.follow_up_queue.borrow_mut().push((seq, message));
+    }
+
+    fn next_queue_seq(&self) -> u64 {
+        take_queue_seq(&self.next_queue_seq)
+    }
+
+    /// Queues steering messages from a listener that runs while a prompt holds the agent.
+    pub fn steering_sender(&self) -> impl Fn(AgentMessage) + 'static {
+        let queue = self.steering_queue.clone();
+        let next_seq = self.next_queue_seq.clone();
+        move |message| {
+            let seq = take_queue_seq(&next_seq);
+            queue.borrow_mut().push((seq, message));
+        }
+    }
+
+    /// Returns all queued messages in the order the current priority will deliver them.
+    pub fn pending_queue(&self) -> Vec<PendingMessage> {
+        let steering = self.steering_queue.borrow();
+        let follow_up = self.follow_up_queue.borrow();
+        let mut pending = steering
+            .iter()
+            .map(|(seq, message)| (*seq, QueueKind::Steering, message))
+            .chain(
+                follow_up
+                    .iter()
+                    .map(|(seq, message)| (*seq, QueueKind::FollowUp, message)),
+            )
+            .collect::<Vec<_>>();
+        if self.queue_priority == QueuePriority::Fifo {
+            pending.sort_by_key(|(seq, _, _)| *seq);
+        }
+        pending
+            .into_iter()
+            .map(|(id, kind, message)| PendingMessage {
+                id,
+                kind,
+                message: message.clone(),
+            })
+            .collect()
+    }
+
+    /// Takes the queued message with `id` out of its queue.
+    pub fn remove_pending(&self, id: u64) -> Option<PendingMessage> {
+        for (kind, queue) in [
+            (QueueKind::Steering, &self.steering_queue),
+            (QueueKind::FollowUp, &self.follow_up_queue),
+        ] {
+            let mut queue = queue.borrow_mut();
+            if let Some(index) = queue.iter().position(|(seq, _)| *seq == id) {
+                let (_, message) = queue.remove(index);
+                return Some(PendingMessage { id, kind, message });
+            }
+        }
+        None
+    }
+
+    /// Replaces the queued message with `id`, keeping its place in the queue.
+    pub fn replace_pending(&self, id: u64, message: AgentMessage) -> bool {
+        for queue in [&self.steering_queue, &self.follow_up_queue] {
+            if let Some((_, queued)) = queue.borrow_mut().iter_mut().find(|(seq, _)| *seq == id) {
+                *queued = message;
+                return true;
+            }
+        }
+        false
     }
 
     pub fn clear_steering_queue(&self) {
@@ -262,8 +425,14 @@ impl Agent {
         self.follow_up_queue.borrow().len()
     }
 
+    /// Token that aborts the running turn when cancelled, usable while the agent is borrowed
+    /// and from other threads.
+    pub fn abort_flag(&self) -> CancellationToken {
+        self.aborted.clone()
+    }
+
     pub fn abort(&self) {
-        self.aborted.set(true);
+        self.aborted.cancel();
         let mut state = self.state.borrow_mut();
         state.is_streaming = false;
         state.stream_message = None;
@@ -276,11 +445,12 @@ impl Agent {
             if state.is_streaming {
                 return Err(AgentError::AlreadyStreaming);
             }
-            self.aborted.set(false);
+            self.aborted.reset();
             state.is_streaming = true;
             state.stream_message = None;
             state.error = None;
         }
+        self.error.replace(None);
 
         let messages = build_prompt_messages(input.into());
         let state_snapshot = self.state.borrow().clone();
@@ -292,13 +462,15 @@ impl Agent {
         };
 
         let config = self.build_loop_config();
-        let stream_fn = self.stream_fn.clone();
-
-        let stream = agent_loop(messages, context, config, &mut *stream_fn.borrow_mut());
-
-        apply_events(&self.state, &self.listeners, stream.events());
-
-        let was_aborted = self.aborted.get();
+        agent_loop_with_sink(
+            messages,
+            context,
+            config,
+            &mut self.error_recording_stream_fn(),
+            Some(self.live_event_sink()),
+        );
+
+        let was_aborted = self.aborted.is_cancelled();
         let keep_streaming = if was_aborted {
             false
         } else {
@@ -310,10 +482,14 @@ impl Agent {
         state.stream_message = None;
         if was_aborted {
             let error_message = "Request was aborted".to_string();
-            let aborted_message = aborted_assistant_message(&state.model, &error_message);
-            state
-                .messages
-                .push(AgentMessage::Assistant(aborted_message));
+            if !ends_with_aborted_message(&state.messages) {
+                let aborted_message = aborted_assistant_message(&state.model, &error_message);
+                state
+                    .messages
+                    .push(AgentMessage::Assistant(aborted_message));
+            }
+            self.error
+                .replace(Some(Error::Aborted(error_message.clone())));
             state.error = Some(error_message);
         }
 
@@ -333,7 +509,8 @@ impl Agent {
                 return Err(AgentError::LastMessageAssistant);
             }
         }
-        self.aborted.set(false);
+        self.aborted.reset();
+        self.error.replace(None);
 
         let state_snapshot = self.state.borrow().clone();
         let context = AgentContext {
@@ -343,14 +520,15 @@ impl Agent {
         };
 
         let config = self.build_loop_config();
-        let stream_fn = self.stream_fn.clone();
-
-        let stream = agent_loop_continue(context, config, &mut *stream_fn.borrow_mut())
-            .map_err(|err| AgentError::Loop(err.to_string()))?;
-
-        apply_events(&self.state, &self.listeners, stream.events());
-
-        let was_aborted = self.aborted.get();
+        agent_loop_continue_with_sink(
+            context,
+            config,
+            &mut self.error_recording_stream_fn(),
+            Some(self.live_event_sink()),
+        )
+        .map_err(AgentError::Loop)?;
+
+        let was_aborted = self.aborted.is_cancelled();
         let keep_streaming = if was_aborted {
             false
         } else {
@@ -361,16 +539,55 @@ impl Agent {
         state.stream_message = None;
         if was_aborted {
             let error_message = "Request was aborted".to_string();
-            let aborted_message = aborted_assistant_message(&state.model, &error_message);
-            state
-                .messages
-                .push(AgentMessage::Assistant(aborted_message));
+            if !ends_with_aborted_message(&state.messages) {
+                let aborted_message = aborted_assistant_message(&state.model, &error_message);
+                state
+                    .messages
+                    .push(AgentMessage::Assistant(aborted_message));
+            }
+            self.error
+                .replace(Some(Error::Aborted(error_message.clone())));
             state.error = Some(error_message);
         }
 
         Ok(())
     }
 
+    /// The typed error behind `state().error`: what the provider reported when the last reply
+    /// ended with an `error` stop reason, or [`Error::Aborted`] after an abort. Taking it
+    /// leaves `None` until the next failure.
+    pub fn take_error(&self) -> Option<Error> {
+        self.error.take()
+    }
+
+    /// The stream function, keeping the typed error of each reply that fails. Stream
+    /// functions that only produce an error message get a [`Error::Provider`] without status.
+    fn error_recording_stream_fn(
+        &self,
+    ) -> impl FnMut(&Model, &LlmContext, &mut StreamEvents) -> AssistantMessage {
+        let stream_fn = self.stream_fn.clone();
+        let error = self.error.clone();
+        move |model, context, events| {
+            let message = (stream_fn.borrow_mut())(model, context, events);
+            let failure = events.take_error();
+            if message.stop_reason == "error" {
+                error.replace(Some(failure.unwrap_or_else(|| Error::Provider {
+                    status: None,
+                    message: message.error_message.clone().unwrap_or_default(),
+                })));
+            }
+            message
+        }
+    }
+
+    /// Applies loop events to the agent state and notifies listeners while the loop is
+    /// still running, so subscribers can render streaming output.
+    fn live_event_sink(&self) -> AgentEventSink {
+        let state = self.state.clone();
+        let events = self.events.clone();
+        Box::new(move |event| apply_event(&state, &events, event))
+    }
+
     fn build_loop_config(&self) -> AgentLoopConfig {
         let convert_to_llm = self.convert_to_llm.clone();
         let transform_context = self.transform_context.clone();
@@ -388,38 +605,19 @@ impl Agent {
                 as Box<TransformContextFn>
         });
 
-        let steering = Box::new(move || match steering_mode {
-            QueueMode::OneAtATime => {
-                let mut queue = steering_queue.borrow_mut();
-                if queue.is_empty() {
-                    Vec::new()
-                } else {
-                    vec![queue.remove(0)]
-                }
-            }
-            QueueMode::All => {
-                let mut queue = steering_queue.borrow_mut();
-                let items = queue.clone();
-                queue.clear();
-                items
-            }
-        });
+        let fifo = self.queue_priority == QueuePriority::Fifo;
+        let steering = {
+            let steering_queue = steering_queue.clone();
+            let follow_up_queue = follow_up_queue.clone();
+            Box::new(move || {
+                let before = fifo.then(|| first_queued_seq(&follow_up_queue)).flatten();
+                take_queued(&steering_queue, steering_mode, before)
+            })
+        };
 
-        let follow_up = Box::new(move || match follow_up_mode {
-            QueueMode::OneAtATime => {
-                let mut queue = follow_up_queue.borrow_mut();
-                if queue.is_empty() {
-                    Vec::new()
-                } else {
-                    vec![queue.remove(0)]
-                }
-            }
-            QueueMode::All => {
-                let mut queue = follow_up_queue.borrow_mut();
-                let items = queue.clone();
-                queue.clear();
-                items
-            }
+        let follow_up = Box::new(move || {
+            let before = fifo.then(|| first_queued_seq(&steering_queue)).flatten();
+            take_queued(&follow_up_queue, follow_up_mode, before)
         });
 
         AgentLoopConfig {
@@ -428,6 +626,10 @@ impl Agent {
             transform_context: transform,
             get_steering_messages: Some(steering),
             get_follow_up_messages: Some(follow_up),
+            abort_flag: Some(self.aborted.clone()),
+            output_filter: self.output_filter.clone(),
+            tool_update_throttle: self.tool_update_throttle,
+            limits: self.loop_limits,
         }
     }
 }
@@ -438,7 +640,7 @@ pub enum AgentError {
     AlreadyStreamingContinue,
     NoMessages,
     LastMessageAssistant,
-    Loop(String),
+    Loop(AgentLoopError),
 }
 
 impl std::fmt::Display for AgentError {
@@ -456,7 +658,7 @@ impl std::fmt::Display for AgentError {
             AgentError::LastMessageAssistant => {
                 write!(f, "Cannot continue from message role: assistant")
             }
-            AgentError::Loop(message) => write!(f, "{message}"),
+            AgentError::Loop(err) => write!(f, "{err}"),
         }
     }
 }
@@ -493,6 +695,24 @@ impl From<Vec<AgentMessage>> for PromptInput {
     }
 }
 
+fn first_queued_seq(queue: &MessageQueue) -> Option<u64> {
+    queue.borrow().first().map(|(seq, _)| *seq)
+}
+
+/// Takes messages from the front of a queue, stopping at the first one queued at or after `before`.
+fn take_queued(queue: &MessageQueue, mode: QueueMode, before: Option<u64>) -> Vec<AgentMessage> {
+    let mut queue = queue.borrow_mut();
+    let available = queue
+        .iter()
+        .take_while(|(seq, _)| before.is_none_or(|before| *seq < before))
+        .count();
+    let count = match mode {
+        QueueMode::OneAtATime => available.min(1),
+        QueueMode::All => available,
+    };
+    queue.drain(..count).map(|(_, message)| message).collect()
+}
+
 fn build_prompt_messages(input: PromptInput) -> Vec<AgentMessage> {
     match input {
         PromptInput::Text(text) => vec![AgentMessage::User(UserMessage {
@@ -504,48 +724,40 @@ fn build_prompt_messages(input: PromptInput) -> Vec<AgentMessage> {
     }
 }
 
-fn apply_events(
-    state: &Rc<RefCell<AgentState>>,
-    listeners: &Rc<RefCell<Vec<ListenerEntry>>>,
-    events: &[AgentEvent],
-) {
-    for event in events {
-        {
-            let mut state = state.borrow_mut();
-            match event {
-                AgentEvent::MessageStart { message } | AgentEvent::MessageUpdate { message } => {
-                    state.stream_message = Some(message.clone());
-                }
-                AgentEvent::MessageEnd { message } => {
-                    state.stream_message = None;
-                    state.messages.push(message.clone());
-                }
-                AgentEvent::ToolExecutionStart { tool_call_id, .. } => {
-                    state.pending_tool_calls.insert(tool_call_id.clone());
-                }
-                AgentEvent::ToolExecutionEnd { tool_call_id, .. } => {
-                    state.pending_tool_calls.remove(tool_call_id);
-                }
-                AgentEvent::TurnEnd {
-                    message: AgentMessage::Assistant(assistant),
-                    ..
-                } => {
-                    if assistant.error_message.is_some() {
-                        state.error = assistant.error_message.clone();
-                    }
-                }
-                AgentEvent::AgentEnd { .. } => {
-                    state.is_streaming = false;
-                    state.stream_message = None;
+fn apply_event(state: &Rc<RefCell<AgentState>>, events: &EventBus<AgentEvent>, event: &AgentEvent) {
+    {
+        let mut state = state.borrow_mut();
+        match event {
+            AgentEvent::MessageStart { message } | AgentEvent::MessageUpdate { message, .. } => {
+                state.stream_message = Some(message.clone());
+            }
+            AgentEvent::MessageEnd { message } => {
+                state.stream_message = None;
+                state.messages.push(message.clone());
+            }
+            AgentEvent::ToolExecutionStart { tool_call_id, .. } => {
+                state.pending_tool_calls.insert(tool_call_id.clone());
+            }
+            AgentEvent::ToolExecutionEnd { tool_call_id, .. } => {
+                state.pending_tool_calls.remove(tool_call_id);
+            }
+            AgentEvent::TurnEnd {
+                message: AgentMessage::Assistant(assistant),
+                ..
+            } => {
+                if assistant.error_message.is_some() {
+                    state.error = assistant.error_message.clone();
                 }
-                _ => {}
             }
-        }
-
-        for (_, listener) in listeners.borrow().iter() {
-            listener(event);
+            AgentEvent::AgentEnd { .. } => {
+                state.is_streaming = false;
+                state.stream_message = None;
+            }
+            _ => {}
         }
     }
+
+    events.emit(event);
 }
 
 fn should_keep_streaming(messages: &[AgentMessage]) -> bool {
@@ -584,7 +796,14 @@ fn default_stream_fn(
     }
 }
 
-fn aborted_assistant_message(model: &Model, error_message: &str) -> AssistantMessage {
+fn ends_with_aborted_message(messages: &[AgentMessage]) -> bool {
+    matches!(
+        messages.last(),
+        Some(AgentMessage::Assistant(message)) if message.is_aborted()
+    )
+}
+
+pub(super) fn aborted_assistant_message(model: &Model, error_message: &str) -> AssistantMessage {
     AssistantMessage {
         content: vec![ContentBlock::Text {
             text: String::new(),
@@ -594,7 +813,7 @@ fn aborted_assistant_message(model: &Model, error_message: &str) -> AssistantMes
         provider: model.provider.clone(),
         model: model.id.clone(),
         usage: default_usage(),
-        stop_reason: "aborted".to_string(),
+        stop_reason: STOP_REASON_ABORTED.to_string(),
         error_message: Some(error_message.to_string()),
         timestamp: now_millis(),
     }
@@ -0,0 +1,96 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::sync::mpsc;
+
+/// Fans events out to subscribers. Callbacks added with [`EventBus::subscribe`] run on the
+/// emitting thread as each event happens, so they can render or record it in place;
+/// [`EventBus::channel`] hands out a `Send` receiver, so a GUI or server can consume the
+/// same events on a thread of its own. Clones share their subscribers.
+pub struct EventBus<E> {
+    subscribers: Rc<Subscribers<E>>,
+}
+
+type Listener<E> = Rc<dyn Fn(&E)>;
+
+struct Subscribers<E> {
+    listeners: RefCell<Vec<(usize, Listener<E>)>>,
+    channels: RefCell<Vec<mpsc::Sender<E>>>,
+    next_id: Cell<usize>,
+}
+
+impl<E> Clone for EventBus<E> {
+    fn clone(&self) -> Self {
+        Self {
+            subscribers: self.subscribers.clone(),
+        }
+    }
+}
+
+impl<E: Clone + Send + 'static> Default for EventBus<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: Clone + Send + 'static> EventBus<E> {
+    pub fn new() -> Self {
+        Self {
+            subscribers: Rc::new(Subscribers {
+                listeners: RefCell::new(Vec::new()),
+                channels: RefCell::new(Vec::new()),
+                next_id: Cell::new(0),
+            }),
+        }
+    }
+
+    /// Calls `listener` for every event until the returned closure is called.
+    pub fn subscribe<F>(&self, listener: F) -> impl FnOnce()
+    where
+        F: Fn(&E) + 'static,
+    {
+        let id = self.subscribers.next_id.get();
+        self.subscribers.next_id.set(id + 1);
+        self.subscribers
+            .listeners
+            .borrow_mut()
+            .push((id, Rc::new(listener)));
+        let subscribers = self.subscribers.clone();
+        move || {
+            subscribers
+                .listeners
+                .borrow_mut()
+                .retain(|(listener_id, _)| *listener_id != id);
+        }
+    }
+
+    /// A receiver for every event from now on. Dropping it unsubscribes.
+    pub fn channel(&self) -> mpsc::Receiver<E> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.channels.borrow_mut().push(sender);
+        receiver
+    }
+
+    pub fn emit(&self, event: &E) {
+        // Listeners may subscribe or unsubscribe while handling the event.
+        let listeners = self
+            .subscribers
+            .listeners
+            .borrow()
+            .iter()
+            .map(|(_, listener)| listener.clone())
+            .collect::<Vec<_>>();
+        for listener in listeners {
+            listener(event);
+        }
+        self.subscribers
+            .channels
+            .borrow_mut()
+            .retain(|sender| sender.send(event.clone()).is_ok());
+    }
+
+    /// Drops every subscriber. Channel receivers see the bus disconnect.
+    pub fn clear(&self) {
+        self.subscribers.listeners.borrow_mut().clear();
+        self.subscribers.channels.borrow_mut().clear();
+    }
+}
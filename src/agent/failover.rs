@@ -0,0 +1,37 @@
+//! Failover chains: a turn that ends in a provider error (a failed request, a timeout, a
+//! rate limit) is sent again to the next stream function in priority order.
+
+use super::StreamFn;
+
+/// Tries `primary`, then each of `fallbacks`, until one answers without an error. The
+/// returned message names the model that actually answered; when every model fails it is
+/// the last model's error, listing each failure.
+pub fn failover_stream_fn(primary: Box<StreamFn>, fallbacks: Vec<Box<StreamFn>>) -> Box<StreamFn> {
+    let mut chain = vec![primary];
+    chain.extend(fallbacks);
+    Box::new(move |model, context, events| {
+        let mut failures = Vec::new();
+        let mut index = 0;
+        loop {
+            let mut message = (chain[index])(model, context, events);
+            // An aborted turn is the user's doing; another model would not help.
+            if message.stop_reason != "error" || message.is_aborted() || events.is_aborted() {
+                return message;
+            }
+            failures.push(format!(
+                "{}/{}: {}",
+                message.provider,
+                message.model,
+                message.error_message.as_deref().unwrap_or("request failed")
+            ));
+            index += 1;
+            if index == chain.len() {
+                if failures.len() > 1 {
+                    message.error_message =
+                        Some(format!("All models failed. {}", failures.join("; ")));
+                }
+                return message;
+            }
+        }
+    })
+}
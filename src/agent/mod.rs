@@ -1,6 +1,9 @@
 use std::cell::{Cell, RefCell};
 use std::rc::Rc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use serde_json::Value;
 
@@ -10,10 +13,20 @@ use crate::core::messages::{
 };
 
 mod agent_impl;
+mod cancel;
+mod event_bus;
+mod failover;
+mod replay;
 
 pub use agent_impl::{
     custom_message, get_model, Agent, AgentError, AgentOptions, AgentState, AgentStateOverride,
-    QueueMode, ThinkingLevel,
+    PendingMessage, QueueKind, QueueMode, QueuePriority, ThinkingLevel,
+};
+pub use cancel::CancellationToken;
+pub use event_bus::EventBus;
+pub use failover::failover_stream_fn;
+pub use replay::{
+    load_fixture_responses, recording_stream_fn, replay_stream_fn, FIXTURE_RESPONSES_FILE,
 };
 
 #[derive(Clone, Debug, PartialEq)]
@@ -30,20 +43,168 @@ pub struct AgentToolResult {
     pub details: Value,
 }
 
-pub type ToolExecute = dyn Fn(&str, &Value) -> Result<AgentToolResult, String>;
+/// A failed tool call: `message` is what the model sees, `details` what clients get in the
+/// tool result, e.g. `{"code": "stale_read", ...}`. Plain string errors carry no details.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ToolError {
+    pub message: String,
+    pub details: Value,
+}
+
+impl ToolError {
+    pub fn with_details(message: impl Into<String>, details: Value) -> Self {
+        Self {
+            message: message.into(),
+            details,
+        }
+    }
+}
+
+impl From<String> for ToolError {
+    fn from(message: String) -> Self {
+        Self {
+            message,
+            details: Value::Null,
+        }
+    }
+}
+
+impl From<&str> for ToolError {
+    fn from(message: &str) -> Self {
+        message.to_string().into()
+    }
+}
+
+/// Tool entry point: `(tool_call_id, args, on_update)`. `on_update` receives partial results
+/// while the tool runs; partials are cumulative, each one replacing the last.
+pub type ToolExecute =
+    dyn Fn(&str, &Value, &mut dyn FnMut(AgentToolResult)) -> Result<AgentToolResult, ToolError>;
 pub type ConvertToLlmFn = dyn FnMut(&[AgentMessage]) -> Vec<AgentMessage>;
 pub type TransformContextFn = dyn FnMut(&[AgentMessage]) -> Vec<AgentMessage>;
 pub type SteeringFn = dyn FnMut() -> Vec<AgentMessage>;
 pub type ListenerFn = dyn Fn(&AgentEvent);
 
+/// Limits on `ToolExecutionUpdate` events. Updates arriving within `interval` of the last
+/// emitted one are dropped (the next update or `ToolExecutionEnd` supersedes them), and text
+/// in each update is cut to its last `max_bytes` bytes. `ToolExecutionEnd` is never limited.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ToolUpdateThrottle {
+    pub interval: Duration,
+    pub max_bytes: usize,
+}
+
+impl Default for ToolUpdateThrottle {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_millis(100),
+            max_bytes: 16 * 1024,
+        }
+    }
+}
+
+impl ToolUpdateThrottle {
+    /// Cut each text block of `partial` to its last `max_bytes` bytes.
+    pub fn limit(&self, mut partial: AgentToolResult) -> AgentToolResult {
+        for block in partial.content.iter_mut() {
+            if let ContentBlock::Text { text, .. } = block {
+                if text.len() > self.max_bytes {
+                    let mut start = text.len() - self.max_bytes;
+                    while !text.is_char_boundary(start) {
+                        start += 1;
+                    }
+                    *text = format!("...{}", &text[start..]);
+                }
+            }
+        }
+        partial
+    }
+}
+
+/// Guards for unattended runs: the loop stops with a `LimitReached` event instead of
+/// sending another request once a run has made `max_turns` model calls or has been running
+/// for `max_duration`. The turn limit is checked between turns; the time limit also cancels
+/// the run's token when it expires, so a request or tool call in flight is stopped too.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LoopLimits {
+    pub max_turns: Option<usize>,
+    pub max_duration: Option<Duration>,
+}
+
+impl LoopLimits {
+    /// Why a run that has made `turns` model calls in `elapsed` must stop, if it must.
+    pub fn exceeded(&self, turns: usize, elapsed: Duration) -> Option<String> {
+        if let Some(max) = self.max_turns.filter(|max| turns >= *max) {
+            return Some(format!("Turn limit reached ({max} turns)"));
+        }
+        self.max_duration
+            .filter(|max| elapsed >= *max)
+            .map(time_limit_reason)
+    }
+}
+
+fn time_limit_reason(max: Duration) -> String {
+    format!("Time limit reached ({}s)", max.as_secs())
+}
+
+/// Cancels the run's token once `max_duration` has passed. Dropping it stops the timer.
+struct Deadline {
+    fired: Arc<AtomicBool>,
+    _stop: Option<mpsc::Sender<()>>,
+}
+
+impl Deadline {
+    fn start(limit: Option<Duration>, token: Option<&CancellationToken>) -> Self {
+        let fired = Arc::new(AtomicBool::new(false));
+        let (Some(limit), Some(token)) = (limit, token) else {
+            return Self { fired, _stop: None };
+        };
+        let (stop, stopped) = mpsc::channel::<()>();
+        let fired_ref = fired.clone();
+        let token = token.clone();
+        thread::spawn(move || {
+            if let Err(mpsc::RecvTimeoutError::Timeout) = stopped.recv_timeout(limit) {
+                fired_ref.store(true, Ordering::SeqCst);
+                token.cancel();
+            }
+        });
+        Self {
+            fired,
+            _stop: Some(stop),
+        }
+    }
+
+    fn fired(&self) -> bool {
+        self.fired.load(Ordering::SeqCst)
+    }
+}
+
 #[derive(Clone)]
 pub struct AgentTool {
     pub name: String,
     pub label: String,
     pub description: String,
+    /// JSON schema for the arguments; the agent loop checks each call against it before
+    /// `execute` runs.
+    pub parameters: Option<Value>,
     pub execute: Rc<ToolExecute>,
 }
 
+impl AgentTool {
+    /// Checks `args` against the tool's schema, listing every problem at once so the model
+    /// can fix them in one retry.
+    pub fn check_arguments(&self, args: &Value) -> Result<(), String> {
+        let Some(schema) = &self.parameters else {
+            return Ok(());
+        };
+        let errors = crate::tools::schema::validate(schema, args);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(crate::tools::schema::format_errors(&self.name, &errors))
+        }
+    }
+}
+
 impl std::fmt::Debug for AgentTool {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("AgentTool")
@@ -112,19 +273,66 @@ pub struct LlmContext {
 
 pub struct StreamEvents {
     handler: Box<dyn FnMut(AssistantMessageEvent)>,
+    abort_flag: Option<CancellationToken>,
+    error: Option<crate::Error>,
 }
 
 impl StreamEvents {
     pub fn new(handler: Box<dyn FnMut(AssistantMessageEvent)>) -> Self {
-        Self { handler }
+        Self {
+            handler,
+            abort_flag: None,
+            error: None,
+        }
+    }
+
+    pub fn with_abort_flag(mut self, abort_flag: Option<CancellationToken>) -> Self {
+        self.abort_flag = abort_flag;
+        self
     }
 
     pub fn emit(&mut self, event: AssistantMessageEvent) {
         (self.handler)(event);
     }
+
+    /// Records the typed error behind a reply that ends with an `error` stop reason, so
+    /// callers can tell a rate limit from bad credentials without reading the message.
+    pub fn set_error(&mut self, error: crate::Error) {
+        self.error = Some(error);
+    }
+
+    pub fn take_error(&mut self) -> Option<crate::Error> {
+        self.error.take()
+    }
+
+    /// Providers check this between reads and stop streaming once the turn is aborted.
+    pub fn is_aborted(&self) -> bool {
+        self.abort_flag
+            .as_ref()
+            .is_some_and(|flag| flag.is_cancelled())
+    }
+
+    /// Emit the final event for a streamed message and return it. After an abort the partial
+    /// message is marked aborted and reported with an `Error` event instead of `Done`.
+    pub fn finish(&mut self, mut message: AssistantMessage) -> AssistantMessage {
+        if self.is_aborted() {
+            message.mark_aborted();
+            self.emit(AssistantMessageEvent::Error {
+                message: message.clone(),
+            });
+        } else {
+            self.emit(AssistantMessageEvent::Done {
+                message: message.clone(),
+            });
+        }
+        message
+    }
 }
 
 pub type StreamFn = dyn FnMut(&Model, &LlmContext, &mut StreamEvents) -> AssistantMessage;
+/// Rewrites assistant output before it is emitted or stored. The flag is `true` for
+/// streaming partials and `false` for the final message.
+pub type OutputFilterFn = dyn Fn(AssistantMessage, bool) -> AssistantMessage;
 
 pub struct AgentLoopConfig {
     pub model: Model,
@@ -132,6 +340,26 @@ pub struct AgentLoopConfig {
     pub transform_context: Option<Box<TransformContextFn>>,
     pub get_steering_messages: Option<Box<SteeringFn>>,
     pub get_follow_up_messages: Option<Box<SteeringFn>>,
+    pub abort_flag: Option<CancellationToken>,
+    pub output_filter: Option<Rc<OutputFilterFn>>,
+    pub tool_update_throttle: Option<ToolUpdateThrottle>,
+    pub limits: LoopLimits,
+}
+
+impl AgentLoopConfig {
+    fn is_aborted(&self) -> bool {
+        self.abort_flag
+            .as_ref()
+            .is_some_and(|flag| flag.is_cancelled())
+    }
+}
+
+/// Arguments JSON a tool call delta appended, as the provider streamed it. Parsed arguments
+/// only change once the JSON is complete, so this is how a client follows a long call.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArgumentsDelta {
+    pub content_index: usize,
+    pub json: String,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -150,6 +378,8 @@ pub enum AgentEvent {
     },
     MessageUpdate {
         message: AgentMessage,
+        /// Set when the update came from a tool call delta.
+        arguments_delta: Option<ArgumentsDelta>,
     },
     MessageEnd {
         message: AgentMessage,
@@ -171,6 +401,18 @@ pub enum AgentEvent {
         result: AgentToolResult,
         is_error: bool,
     },
+    /// The run was cancelled. `message` is the last assistant message, with any content
+    /// streamed before the abort. Always followed by `AgentEnd`.
+    RunAborted {
+        message: AgentMessage,
+    },
+    /// The run hit a [`LoopLimits`] guard while the model still wanted to call tools, or
+    /// the time limit cut a request or tool call short. `message` is the last assistant
+    /// message. Always followed by `AgentEnd`.
+    LimitReached {
+        message: AgentMessage,
+        reason: String,
+    },
 }
 
 impl AgentEvent {
@@ -186,6 +428,8 @@ impl AgentEvent {
             AgentEvent::ToolExecutionStart { .. } => "tool_execution_start",
             AgentEvent::ToolExecutionUpdate { .. } => "tool_execution_update",
             AgentEvent::ToolExecutionEnd { .. } => "tool_execution_end",
+            AgentEvent::RunAborted { .. } => "run_aborted",
+            AgentEvent::LimitReached { .. } => "limit_reached",
         }
     }
 }
@@ -209,21 +453,37 @@ impl std::fmt::Display for AgentLoopError {
 
 impl std::error::Error for AgentLoopError {}
 
-#[derive(Debug)]
+/// Receives each loop event as it happens, before the loop returns.
+pub type AgentEventSink = Box<dyn FnMut(&AgentEvent)>;
+
 pub struct AgentStream {
     events: Vec<AgentEvent>,
     result: Vec<AgentMessage>,
+    sink: Option<AgentEventSink>,
+}
+
+impl std::fmt::Debug for AgentStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AgentStream")
+            .field("events", &self.events)
+            .field("result", &self.result)
+            .finish()
+    }
 }
 
 impl AgentStream {
-    fn new() -> Self {
+    fn new(sink: Option<AgentEventSink>) -> Self {
         Self {
             events: Vec::new(),
             result: Vec::new(),
+            sink,
         }
     }
 
     fn push(&mut self, event: AgentEvent) {
+        if let Some(sink) = self.sink.as_mut() {
+            sink(&event);
+        }
         self.events.push(event);
     }
 
@@ -241,15 +501,28 @@ impl AgentStream {
 }
 
 pub fn agent_loop<F>(
+    prompts: Vec<AgentMessage>,
+    context: AgentContext,
+    config: AgentLoopConfig,
+    stream_fn: &mut F,
+) -> AgentStream
+where
+    F: FnMut(&Model, &LlmContext, &mut StreamEvents) -> AssistantMessage,
+{
+    agent_loop_with_sink(prompts, context, config, stream_fn, None)
+}
+
+pub fn agent_loop_with_sink<F>(
     prompts: Vec<AgentMessage>,
     mut context: AgentContext,
     mut config: AgentLoopConfig,
     stream_fn: &mut F,
+    sink: Option<AgentEventSink>,
 ) -> AgentStream
 where
     F: FnMut(&Model, &LlmContext, &mut StreamEvents) -> AssistantMessage,
 {
-    let mut stream = AgentStream::new();
+    let mut stream = AgentStream::new(sink);
     let mut new_messages = prompts.clone();
     context.messages.extend(prompts.clone());
 
@@ -274,9 +547,21 @@ where
 }
 
 pub fn agent_loop_continue<F>(
+    context: AgentContext,
+    config: AgentLoopConfig,
+    stream_fn: &mut F,
+) -> Result<AgentStream, AgentLoopError>
+where
+    F: FnMut(&Model, &LlmContext, &mut StreamEvents) -> AssistantMessage,
+{
+    agent_loop_continue_with_sink(context, config, stream_fn, None)
+}
+
+pub fn agent_loop_continue_with_sink<F>(
     context: AgentContext,
     mut config: AgentLoopConfig,
     stream_fn: &mut F,
+    sink: Option<AgentEventSink>,
 ) -> Result<AgentStream, AgentLoopError>
 where
     F: FnMut(&Model, &LlmContext, &mut StreamEvents) -> AssistantMessage,
@@ -289,7 +574,7 @@ where
         return Err(AgentLoopError::LastMessageAssistant);
     }
 
-    let mut stream = AgentStream::new();
+    let mut stream = AgentStream::new(sink);
     let mut new_messages = Vec::new();
     let mut current_context = context;
 
@@ -317,6 +602,22 @@ fn run_loop<F>(
     F: FnMut(&Model, &LlmContext, &mut StreamEvents) -> AssistantMessage,
 {
     let mut first_turn = true;
+    let mut turns = 0;
+    let started = Instant::now();
+    if config.limits.max_duration.is_some() && config.abort_flag.is_none() {
+        config.abort_flag = Some(CancellationToken::new());
+    }
+    let max_duration = config.limits.max_duration;
+    let deadline = Deadline::start(max_duration, config.abort_flag.as_ref());
+    let cancelled = |message: AssistantMessage| match max_duration {
+        Some(max) if deadline.fired() => AgentEvent::LimitReached {
+            message: AgentMessage::Assistant(message),
+            reason: time_limit_reason(max),
+        },
+        _ => AgentEvent::RunAborted {
+            message: AgentMessage::Assistant(message),
+        },
+    };
     let mut pending_messages = config
         .get_steering_messages
         .as_mut()
@@ -348,13 +649,17 @@ fn run_loop<F>(
             }
 
             let message = stream_assistant_response(current_context, config, stream_fn, stream);
+            turns += 1;
             new_messages.push(AgentMessage::Assistant(message.clone()));
 
-            if message.stop_reason == "error" || message.stop_reason == "aborted" {
+            if message.stop_reason == "error" || message.is_aborted() {
                 stream.push(AgentEvent::TurnEnd {
-                    message: AgentMessage::Assistant(message),
+                    message: AgentMessage::Assistant(message.clone()),
                     tool_results: Vec::new(),
                 });
+                if message.is_aborted() {
+                    stream.push(cancelled(message));
+                }
                 stream.push(AgentEvent::AgentEnd {
                     messages: new_messages.clone(),
                 });
@@ -371,6 +676,8 @@ fn run_loop<F>(
                     &current_context.tools,
                     &message,
                     &mut config.get_steering_messages,
+                    config.abort_flag.as_ref(),
+                    config.tool_update_throttle,
                     stream,
                 );
                 tool_results.extend(tool_execution.tool_results.clone());
@@ -385,10 +692,33 @@ fn run_loop<F>(
             }
 
             stream.push(AgentEvent::TurnEnd {
-                message: AgentMessage::Assistant(message),
+                message: AgentMessage::Assistant(message.clone()),
                 tool_results,
             });
 
+            if config.is_aborted() {
+                stream.push(cancelled(message));
+                stream.push(AgentEvent::AgentEnd {
+                    messages: new_messages.clone(),
+                });
+                stream.end(new_messages.clone());
+                return;
+            }
+
+            if has_more_tool_calls {
+                if let Some(reason) = config.limits.exceeded(turns, started.elapsed()) {
+                    stream.push(AgentEvent::LimitReached {
+                        message: AgentMessage::Assistant(message),
+                        reason,
+                    });
+                    stream.push(AgentEvent::AgentEnd {
+                        messages: new_messages.clone(),
+                    });
+                    stream.end(new_messages.clone());
+                    return;
+                }
+            }
+
             if let Some(steering) = steering_after_tools.take() {
                 if !steering.is_empty() {
                     pending_messages = steering;
@@ -448,9 +778,25 @@ where
     let saw_event_ref = saw_event.clone();
     let started_ref = started.clone();
     let last_partial_ref = last_partial.clone();
+    let output_filter = config.output_filter.clone();
+    let filter_partial = move |partial: AssistantMessage| match output_filter.as_ref() {
+        Some(filter) => filter(partial, true),
+        None => partial,
+    };
 
     let handle_event = move |event: AssistantMessageEvent| {
         saw_event_ref.set(true);
+        let arguments_delta = match &event {
+            AssistantMessageEvent::ToolCallDelta {
+                delta,
+                content_index,
+                ..
+            } => Some(ArgumentsDelta {
+                content_index: *content_index,
+                json: delta.clone(),
+            }),
+            _ => None,
+        };
         let partial = match event {
             AssistantMessageEvent::Start { partial }
             | AssistantMessageEvent::TextStart { partial, .. }
@@ -462,10 +808,18 @@ where
             | AssistantMessageEvent::ToolCallStart { partial, .. }
             | AssistantMessageEvent::ToolCallDelta { partial, .. }
             | AssistantMessageEvent::ToolCallEnd { partial, .. } => Some(partial),
-            AssistantMessageEvent::Done { message } | AssistantMessageEvent::Error { message } => {
+            AssistantMessageEvent::Done { message } => {
                 last_partial_ref.replace(Some(message));
                 None
             }
+            AssistantMessageEvent::Error { message } => {
+                // A dropped connection reports an empty error; keep what streamed before it.
+                let mut last_partial = last_partial_ref.borrow_mut();
+                if last_partial.is_none() || !message.content.is_empty() {
+                    *last_partial = Some(message);
+                }
+                None
+            }
         };
 
         let Some(partial) = partial else {
@@ -473,7 +827,7 @@ where
         };
 
         last_partial_ref.replace(Some(partial.clone()));
-        let agent_message = AgentMessage::Assistant(partial.clone());
+        let agent_message = AgentMessage::Assistant(filter_partial(partial));
         unsafe {
             let stream = &mut *stream_ptr;
             if !started_ref.get() {
@@ -484,12 +838,29 @@ where
             }
             stream.push(AgentEvent::MessageUpdate {
                 message: agent_message,
+                arguments_delta,
             });
         }
     };
 
-    let mut stream_events = StreamEvents::new(Box::new(handle_event));
-    let message = stream_fn(&config.model, &llm_context, &mut stream_events);
+    let mut stream_events =
+        StreamEvents::new(Box::new(handle_event)).with_abort_flag(config.abort_flag.clone());
+    let mut message = stream_fn(&config.model, &llm_context, &mut stream_events);
+    if config.is_aborted() || message.is_aborted() {
+        // Every provider ends a cancelled turn the same way: keep whatever streamed before
+        // the abort (even if the dropped connection surfaced as an error), never run its
+        // tool calls.
+        if message.stop_reason == "error" {
+            if let Some(partial) = last_partial.borrow().as_ref() {
+                message.content = partial.content.clone();
+                message.usage = partial.usage.clone();
+            }
+        }
+        message.mark_aborted();
+    }
+    if let Some(filter) = config.output_filter.as_ref() {
+        message = filter(message, false);
+    }
     context
         .messages
         .push(AgentMessage::Assistant(message.clone()));
@@ -500,17 +871,22 @@ where
         });
         stream.push(AgentEvent::MessageUpdate {
             message: AgentMessage::Assistant(message.clone()),
+            arguments_delta: None,
         });
     } else if !started.get() {
-        let partial = last_partial
-            .borrow()
-            .clone()
-            .unwrap_or_else(|| message.clone());
+        let partial = match last_partial.borrow().clone() {
+            Some(partial) => match config.output_filter.as_ref() {
+                Some(filter) => filter(partial, true),
+                None => partial,
+            },
+            None => message.clone(),
+        };
         stream.push(AgentEvent::MessageStart {
             message: AgentMessage::Assistant(partial.clone()),
         });
         stream.push(AgentEvent::MessageUpdate {
             message: AgentMessage::Assistant(partial),
+            arguments_delta: None,
         });
     }
     stream.push(AgentEvent::MessageEnd {
@@ -529,6 +905,8 @@ fn execute_tool_calls(
     tools: &[AgentTool],
     assistant_message: &AssistantMessage,
     get_steering_messages: &mut Option<Box<dyn FnMut() -> Vec<AgentMessage>>>,
+    abort_flag: Option<&CancellationToken>,
+    throttle: Option<ToolUpdateThrottle>,
     stream: &mut AgentStream,
 ) -> ToolExecutionResult {
     let tool_calls = extract_tool_calls(assistant_message);
@@ -536,6 +914,13 @@ fn execute_tool_calls(
     let mut steering_messages: Option<Vec<AgentMessage>> = None;
 
     for (index, tool_call) in tool_calls.iter().enumerate() {
+        if abort_flag.is_some_and(|flag| flag.is_cancelled()) {
+            for skipped in tool_calls.iter().skip(index) {
+                results.push(skip_tool_call(skipped, "Tool execution aborted.", stream));
+            }
+            break;
+        }
+
         let tool = tools.iter().find(|tool| tool.name == tool_call.name);
 
         stream.push(AgentEvent::ToolExecutionStart {
@@ -544,18 +929,43 @@ fn execute_tool_calls(
             args: tool_call.arguments.clone(),
         });
 
+        let mut last_update: Option<Instant> = None;
+        let mut on_update = |partial: AgentToolResult| {
+            let now = Instant::now();
+            let partial = match throttle {
+                Some(throttle) => {
+                    if last_update.is_some_and(|last| now - last < throttle.interval) {
+                        return;
+                    }
+                    throttle.limit(partial)
+                }
+                None => partial,
+            };
+            last_update = Some(now);
+            stream.push(AgentEvent::ToolExecutionUpdate {
+                tool_call_id: tool_call.id.clone(),
+                tool_name: tool_call.name.clone(),
+                args: tool_call.arguments.clone(),
+                partial_result: partial,
+            });
+        };
+
         let mut is_error = false;
         let result = match tool {
-            Some(tool) => match (tool.execute)(&tool_call.id, &tool_call.arguments) {
+            Some(tool) => match tool
+                .check_arguments(&tool_call.arguments)
+                .map_err(ToolError::from)
+                .and_then(|()| (tool.execute)(&tool_call.id, &tool_call.arguments, &mut on_update))
+            {
                 Ok(result) => result,
                 Err(err) => {
                     is_error = true;
                     AgentToolResult {
                         content: vec![ContentBlock::Text {
-                            text: err,
+                            text: err.message,
                             text_signature: None,
                         }],
-                        details: Value::Null,
+                        details: err.details,
                     }
                 }
             },
@@ -600,7 +1010,11 @@ fn execute_tool_calls(
             if !steering.is_empty() {
                 steering_messages = Some(steering);
                 for skipped in tool_calls.iter().skip(index + 1) {
-                    results.push(skip_tool_call(skipped, stream));
+                    results.push(skip_tool_call(
+                        skipped,
+                        "Skipped due to queued user message.",
+                        stream,
+                    ));
                 }
                 break;
             }
@@ -613,10 +1027,14 @@ fn execute_tool_calls(
     }
 }
 
-fn skip_tool_call(tool_call: &ToolCall, stream: &mut AgentStream) -> ToolResultMessage {
+fn skip_tool_call(
+    tool_call: &ToolCall,
+    reason: &str,
+    stream: &mut AgentStream,
+) -> ToolResultMessage {
     let result = AgentToolResult {
         content: vec![ContentBlock::Text {
-            text: "Skipped due to queued user message.".to_string(),
+            text: reason.to_string(),
             text_signature: None,
         }],
         details: Value::Null,
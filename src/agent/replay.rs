@@ -0,0 +1,73 @@
+//! Recorded provider responses: a stream function that saves each response as it passes
+//! (`--record-fixtures`) and one that answers from saved responses without calling a
+//! provider (`pi replay`, integration tests).
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::core::messages::AssistantMessage;
+
+use super::agent_impl::aborted_assistant_message;
+use super::StreamFn;
+
+/// The file in a fixtures directory that holds the responses, one JSON message per line.
+pub const FIXTURE_RESPONSES_FILE: &str = "responses.jsonl";
+
+/// Passes every turn to `inner` and appends the response it gives to `dir`'s responses file.
+pub fn recording_stream_fn(mut inner: Box<StreamFn>, dir: PathBuf) -> Box<StreamFn> {
+    Box::new(move |model, context, events| {
+        let message = inner(model, context, events);
+        if let Err(err) = append_fixture_response(&dir, &message) {
+            eprintln!(
+                "Warning: Failed to record fixture in {}: {err}",
+                dir.display()
+            );
+        }
+        message
+    })
+}
+
+/// Answers each turn with the next of `responses`, in order. Once they run out every turn
+/// is an error, so a replay that diverges from the recording stops instead of looping.
+pub fn replay_stream_fn(responses: Vec<AssistantMessage>) -> Box<StreamFn> {
+    let responses = RefCell::new(VecDeque::from(responses));
+    Box::new(move |model, _context, events| {
+        let message = responses.borrow_mut().pop_front().unwrap_or_else(|| {
+            let mut message =
+                aborted_assistant_message(model, "No more recorded responses to replay");
+            message.stop_reason = "error".to_string();
+            message
+        });
+        events.finish(message)
+    })
+}
+
+pub fn load_fixture_responses(dir: &Path) -> Result<Vec<AssistantMessage>, String> {
+    let path = dir.join(FIXTURE_RESPONSES_FILE);
+    let content = fs::read_to_string(&path)
+        .map_err(|err| format!("Failed to read {}: {err}", path.display()))?;
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line)
+                .map_err(|err| format!("{}:{}: {err}", path.display(), index + 1))
+        })
+        .collect()
+}
+
+fn append_fixture_response(dir: &Path, message: &AssistantMessage) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    let mut line = serde_json::to_string(message).map_err(|err| err.to_string())?;
+    line.push('\n');
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(FIXTURE_RESPONSES_FILE))
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .map_err(|err| err.to_string())
+}
@@ -1,5 +1,6 @@
 use crate::core::messages::{
     AssistantMessage, ContentBlock, Cost, ToolResultMessage, Usage, UserContent, UserMessage,
+    STOP_REASON_ABORTED,
 };
 use serde_json::{json, Value};
 use std::cell::Cell;
@@ -168,7 +169,7 @@ impl AssistantMessageEventStream {
                 let message = assistant_message(
                     &self.model,
                     vec![text_block(&text)],
-                    "aborted",
+                    STOP_REASON_ABORTED,
                     Some("Request was aborted"),
                 );
                 self.result = message.clone();
@@ -277,7 +278,7 @@ pub fn complete(model: &Model, context: &Context, options: StreamOptions) -> Ass
             return assistant_message(
                 model,
                 vec![text_block("Request was aborted")],
-                "aborted",
+                STOP_REASON_ABORTED,
                 Some("Request was aborted"),
             );
         }
@@ -613,7 +614,7 @@ fn build_events(model: &Model, blocks: &[ContentBlock]) -> Vec<AssistantMessageE
                     content_index: index,
                 });
             }
-            ContentBlock::Image { .. } => {}
+            ContentBlock::Image { .. } | ContentBlock::Diff { .. } => {}
         }
     }
 
@@ -667,6 +668,7 @@ fn content_len(block: &ContentBlock) -> usize {
         ContentBlock::Thinking { thinking, .. } => thinking.len(),
         ContentBlock::ToolCall { arguments, .. } => arguments.to_string().len(),
         ContentBlock::Image { .. } => 0,
+        ContentBlock::Diff { diff, .. } => diff.len(),
     }
 }
 
@@ -685,9 +687,8 @@ fn tool_result_summary(context: &Context) -> Option<ToolResultSummary> {
             found = true;
             for block in &result.content {
                 match block {
-                    ContentBlock::Text { text, .. } => texts.push(text.clone()),
                     ContentBlock::Image { .. } => has_image = true,
-                    _ => {}
+                    block => texts.extend(block.as_text()),
                 }
             }
         }
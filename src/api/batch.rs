@@ -0,0 +1,304 @@
+//! Asynchronous provider APIs for offline runs: Anthropic message batches and OpenAI
+//! background responses. Both are cheaper than streaming and are polled for their results.
+
+use super::request_hook::post_json;
+use super::request_log;
+use super::{
+    build_anthropic_headers, build_openai_headers, build_system_content, AnthropicErrorResponse,
+    AnthropicMessage, AnthropicRequest, AnthropicResponse, OpenAIError, OpenAIErrorResponse,
+    OpenAIInputItem, OpenAIOutputItem, OpenAIRequest, OpenAIResponse,
+};
+use reqwest::blocking::Response;
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+pub struct BatchCallOptions<'a> {
+    pub model: &'a str,
+    pub api_key: &'a str,
+    pub use_oauth: bool,
+    pub base_url: &'a str,
+    pub extra_headers: Option<&'a HashMap<String, String>>,
+    pub system: Option<&'a str>,
+    pub max_tokens: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct AnthropicBatchRequest {
+    custom_id: String,
+    params: AnthropicRequest,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AnthropicBatchCounts {
+    #[serde(default)]
+    pub processing: u64,
+    #[serde(default)]
+    pub succeeded: u64,
+    #[serde(default)]
+    pub errored: u64,
+    #[serde(default)]
+    pub canceled: u64,
+    #[serde(default)]
+    pub expired: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnthropicBatch {
+    pub id: String,
+    /// `in_progress`, `canceling` or `ended`.
+    pub processing_status: String,
+    #[serde(default)]
+    pub request_counts: AnthropicBatchCounts,
+    #[serde(default)]
+    pub results_url: Option<String>,
+}
+
+/// One line of a batch's results: the reply for `custom_id`, or why there is none.
+pub struct AnthropicBatchResult {
+    pub custom_id: String,
+    pub result: Result<AnthropicResponse, String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OpenAIBackgroundResponse {
+    pub id: String,
+    /// `queued`, `in_progress`, `completed`, `incomplete`, `failed` or `cancelled`.
+    #[serde(default)]
+    pub status: Option<String>,
+    #[serde(default)]
+    pub output: Vec<OpenAIOutputItem>,
+    #[serde(default)]
+    error: Option<OpenAIError>,
+}
+
+impl OpenAIBackgroundResponse {
+    pub fn is_finished(&self) -> bool {
+        !matches!(self.status.as_deref(), Some("queued") | Some("in_progress"))
+    }
+
+    pub fn error_message(&self) -> Option<String> {
+        match (&self.error, self.status.as_deref()) {
+            (Some(error), _) => Some(error.message.clone()),
+            (None, Some(status @ ("failed" | "cancelled"))) => Some(format!("Response {status}")),
+            _ => None,
+        }
+    }
+
+    pub fn into_response(self) -> OpenAIResponse {
+        OpenAIResponse {
+            output: self.output,
+            status: self.status,
+        }
+    }
+}
+
+fn endpoint(base_url: &str, path: &str) -> String {
+    format!("{}/{path}", base_url.trim_end_matches('/'))
+}
+
+fn get(url: &str, headers: HeaderMap) -> Result<Response, String> {
+    reqwest::blocking::Client::new()
+        .get(url)
+        .headers(headers)
+        .send()
+        .map_err(|err| format!("Request failed: {err}"))
+}
+
+fn anthropic_error(response: Response) -> String {
+    let status = response.status();
+    let text = response.text().unwrap_or_default();
+    match serde_json::from_str::<AnthropicErrorResponse>(&text) {
+        Ok(error_response) => format!("Anthropic error: {}", error_response.error.message),
+        Err(_) => format!("Anthropic error: {} {}", status.as_u16(), text),
+    }
+}
+
+fn openai_error(response: Response) -> String {
+    let status = response.status();
+    let text = response.text().unwrap_or_default();
+    match serde_json::from_str::<OpenAIErrorResponse>(&text) {
+        Ok(error_response) => format!("OpenAI error: {}", error_response.error.message),
+        Err(_) => format!("OpenAI error: {} {}", status.as_u16(), text),
+    }
+}
+
+/// Submits one message batch with a request per `(custom_id, messages)` pair.
+pub fn submit_anthropic_batch(
+    requests: Vec<(String, Vec<AnthropicMessage>)>,
+    options: &BatchCallOptions<'_>,
+) -> Result<AnthropicBatch, String> {
+    let result = send_anthropic_batch(requests, options);
+    request_log::finish(&result);
+    result
+}
+
+fn send_anthropic_batch(
+    requests: Vec<(String, Vec<AnthropicMessage>)>,
+    options: &BatchCallOptions<'_>,
+) -> Result<AnthropicBatch, String> {
+    let requests = requests
+        .into_iter()
+        .map(|(custom_id, messages)| AnthropicBatchRequest {
+            custom_id,
+            params: AnthropicRequest {
+                model: options.model.to_string(),
+                max_tokens: options.max_tokens,
+                messages,
+                system: build_system_content(options.system, options.use_oauth),
+                tools: None,
+                stream: None,
+            },
+        })
+        .collect::<Vec<_>>();
+    let headers =
+        build_anthropic_headers(options.api_key, options.use_oauth, options.extra_headers)?;
+    let url = endpoint(options.base_url, "messages/batches");
+    let body = json!({ "requests": requests });
+    let response = post_json(
+        "anthropic",
+        "anthropic-messages",
+        options.model,
+        &url,
+        headers,
+        &body,
+        None,
+    )
+    .map_err(|err| err.to_string())?;
+    if !response.status().is_success() {
+        return Err(anthropic_error(response));
+    }
+    response
+        .json::<AnthropicBatch>()
+        .map_err(|err| format!("Failed to parse batch: {err}"))
+}
+
+pub fn get_anthropic_batch(
+    id: &str,
+    options: &BatchCallOptions<'_>,
+) -> Result<AnthropicBatch, String> {
+    let headers =
+        build_anthropic_headers(options.api_key, options.use_oauth, options.extra_headers)?;
+    let response = get(
+        &endpoint(options.base_url, &format!("messages/batches/{id}")),
+        headers,
+    )?;
+    if !response.status().is_success() {
+        return Err(anthropic_error(response));
+    }
+    response
+        .json::<AnthropicBatch>()
+        .map_err(|err| format!("Failed to parse batch: {err}"))
+}
+
+/// Downloads the results of an ended batch.
+pub fn fetch_anthropic_batch_results(
+    batch: &AnthropicBatch,
+    options: &BatchCallOptions<'_>,
+) -> Result<Vec<AnthropicBatchResult>, String> {
+    let url = batch.results_url.clone().unwrap_or_else(|| {
+        endpoint(
+            options.base_url,
+            &format!("messages/batches/{}/results", batch.id),
+        )
+    });
+    let headers =
+        build_anthropic_headers(options.api_key, options.use_oauth, options.extra_headers)?;
+    let response = get(&url, headers)?;
+    if !response.status().is_success() {
+        return Err(anthropic_error(response));
+    }
+    let text = response
+        .text()
+        .map_err(|err| format!("Failed to read batch results: {err}"))?;
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(parse_anthropic_batch_result)
+        .collect()
+}
+
+fn parse_anthropic_batch_result(line: &str) -> Result<AnthropicBatchResult, String> {
+    let value: Value =
+        serde_json::from_str(line).map_err(|err| format!("Invalid batch result: {err}"))?;
+    let custom_id = value["custom_id"].as_str().unwrap_or_default().to_string();
+    let result = &value["result"];
+    let result = match result["type"].as_str() {
+        Some("succeeded") => serde_json::from_value::<AnthropicResponse>(result["message"].clone())
+            .map_err(|err| format!("Invalid batch result: {err}")),
+        Some("errored") => {
+            let error = &result["error"]["error"];
+            Err(error["message"]
+                .as_str()
+                .unwrap_or("Request errored")
+                .to_string())
+        }
+        Some(other) => Err(format!("Request {other}")),
+        None => Err("Missing result".to_string()),
+    };
+    Ok(AnthropicBatchResult { custom_id, result })
+}
+
+/// Starts a background response; it runs on OpenAI's side and is polled with
+/// [`get_openai_response`].
+pub fn submit_openai_background(
+    input: Vec<OpenAIInputItem>,
+    options: &BatchCallOptions<'_>,
+) -> Result<OpenAIBackgroundResponse, String> {
+    let result = send_openai_background(input, options);
+    request_log::finish(&result);
+    result
+}
+
+fn send_openai_background(
+    input: Vec<OpenAIInputItem>,
+    options: &BatchCallOptions<'_>,
+) -> Result<OpenAIBackgroundResponse, String> {
+    let request = OpenAIRequest {
+        model: options.model.to_string(),
+        input,
+        tools: None,
+        stream: None,
+        seed: None,
+    };
+    let mut body = serde_json::to_value(&request)
+        .map_err(|err| format!("Failed to serialize request: {err}"))?;
+    body["background"] = Value::Bool(true);
+    body["store"] = Value::Bool(true);
+    body["max_output_tokens"] = json!(options.max_tokens);
+    let headers = build_openai_headers(options.api_key, options.extra_headers)?;
+    let url = endpoint(options.base_url, "responses");
+    let response = post_json(
+        "openai",
+        "openai-responses",
+        options.model,
+        &url,
+        headers,
+        &body,
+        None,
+    )
+    .map_err(|err| err.to_string())?;
+    if !response.status().is_success() {
+        return Err(openai_error(response));
+    }
+    response
+        .json::<OpenAIBackgroundResponse>()
+        .map_err(|err| format!("Failed to parse response: {err}"))
+}
+
+pub fn get_openai_response(
+    id: &str,
+    options: &BatchCallOptions<'_>,
+) -> Result<OpenAIBackgroundResponse, String> {
+    let headers = build_openai_headers(options.api_key, options.extra_headers)?;
+    let response = get(
+        &endpoint(options.base_url, &format!("responses/{id}")),
+        headers,
+    )?;
+    if !response.status().is_success() {
+        return Err(openai_error(response));
+    }
+    response
+        .json::<OpenAIBackgroundResponse>()
+        .map_err(|err| format!("Failed to parse response: {err}"))
+}
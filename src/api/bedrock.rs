@@ -0,0 +1,516 @@
+//! Amazon Bedrock provider: Claude models through the Bedrock runtime
+//! `invoke-with-response-stream` API, signed with AWS Signature Version 4.
+//!
+//! The request body is the Anthropic Messages format with Bedrock's `anthropic_version`. The
+//! response is an AWS event stream (binary frames) whose `chunk` events carry the usual
+//! Anthropic stream events, base64-encoded.
+
+use crate::agent::StreamEvents;
+use crate::ai::AssistantMessageEvent;
+use crate::api::request_hook::{post_json, ProviderRequest, RequestHook};
+use crate::api::{
+    assistant_error_message, error_response, request_log, stream_error, AnthropicMessage,
+    AnthropicStreamState, AnthropicSystemContent, AnthropicTool, StreamBody,
+};
+use crate::coding_agent::Model as RegistryModel;
+use crate::core::messages::AssistantMessage;
+use crate::error::Error;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+pub const BEDROCK_API: &str = "bedrock";
+pub const DEFAULT_REGION: &str = "us-east-1";
+
+const ANTHROPIC_VERSION: &str = "bedrock-2023-05-31";
+const SERVICE: &str = "bedrock";
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// Anthropic model ids and the Bedrock model ids they are published under.
+pub const BEDROCK_MODEL_IDS: &[(&str, &str)] = &[
+    ("claude-opus-4-5", "anthropic.claude-opus-4-5-20251101-v1:0"),
+    (
+        "claude-sonnet-4-5",
+        "anthropic.claude-sonnet-4-5-20250929-v1:0",
+    ),
+    (
+        "claude-haiku-4-5",
+        "anthropic.claude-haiku-4-5-20251001-v1:0",
+    ),
+    ("claude-opus-4-1", "anthropic.claude-opus-4-1-20250805-v1:0"),
+    ("claude-opus-4-0", "anthropic.claude-opus-4-20250514-v1:0"),
+    (
+        "claude-sonnet-4-0",
+        "anthropic.claude-sonnet-4-20250514-v1:0",
+    ),
+    (
+        "claude-3-7-sonnet-latest",
+        "anthropic.claude-3-7-sonnet-20250219-v1:0",
+    ),
+    (
+        "claude-3-5-haiku-latest",
+        "anthropic.claude-3-5-haiku-20241022-v1:0",
+    ),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+    pub region: String,
+}
+
+pub struct BedrockCallOptions<'a> {
+    /// Registry model id; mapped with [`bedrock_model_id`].
+    pub model: &'a str,
+    pub credentials: &'a AwsCredentials,
+    pub tools: &'a [AnthropicTool],
+    /// Empty for the regional `bedrock-runtime` endpoint.
+    pub base_url: &'a str,
+    pub extra_headers: Option<&'a HashMap<String, String>>,
+    pub system: Option<&'a str>,
+    pub max_tokens: Option<u32>,
+    pub request_hook: Option<&'a dyn RequestHook>,
+}
+
+#[derive(Serialize)]
+struct BedrockRequest {
+    anthropic_version: &'static str,
+    max_tokens: u32,
+    messages: Vec<AnthropicMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<Vec<AnthropicSystemContent>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<AnthropicTool>>,
+}
+
+#[derive(Deserialize)]
+struct BedrockErrorResponse {
+    #[serde(alias = "Message")]
+    message: String,
+}
+
+/// The Bedrock model id for `model_id`. Anthropic ids (aliases or dated) are mapped and given
+/// the cross-region inference profile prefix for `region` (`us.`, `eu.`, `apac.`), which
+/// current Claude models require for on-demand use. Ids that already look like Bedrock ids
+/// or ARNs are used as given.
+pub fn bedrock_model_id(model_id: &str, region: &str) -> String {
+    if model_id.contains('.') || model_id.starts_with("arn:") {
+        return model_id.to_string();
+    }
+    let mapped = BEDROCK_MODEL_IDS
+        .iter()
+        .find(|(id, _)| *id == model_id)
+        .map(|(_, bedrock_id)| bedrock_id.to_string())
+        .or_else(|| {
+            let date = model_id.rsplit('-').next()?;
+            let dated = model_id.starts_with("claude-")
+                && date.len() == 8
+                && date.chars().all(|c| c.is_ascii_digit());
+            dated.then(|| format!("anthropic.{model_id}-v1:0"))
+        });
+    let Some(mapped) = mapped else {
+        return model_id.to_string();
+    };
+    match inference_profile_prefix(region) {
+        Some(prefix) => format!("{prefix}.{mapped}"),
+        None => mapped,
+    }
+}
+
+fn inference_profile_prefix(region: &str) -> Option<&'static str> {
+    if region.starts_with("us-") {
+        Some("us")
+    } else if region.starts_with("eu-") {
+        Some("eu")
+    } else if region.starts_with("ap-") {
+        Some("apac")
+    } else {
+        None
+    }
+}
+
+pub fn stream_bedrock(
+    model: &RegistryModel,
+    messages: Vec<AnthropicMessage>,
+    options: BedrockCallOptions<'_>,
+    events: &mut StreamEvents,
+) -> Result<AssistantMessage, Error> {
+    let result = send_bedrock_stream(model, messages, options, events);
+    request_log::finish_message(&result);
+    result
+}
+
+fn send_bedrock_stream(
+    model: &RegistryModel,
+    messages: Vec<AnthropicMessage>,
+    options: BedrockCallOptions<'_>,
+    events: &mut StreamEvents,
+) -> Result<AssistantMessage, Error> {
+    let credentials = options.credentials;
+    let request = BedrockRequest {
+        anthropic_version: ANTHROPIC_VERSION,
+        max_tokens: options.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+        messages,
+        system: options.system.map(|text| {
+            vec![AnthropicSystemContent {
+                content_type: "text".to_string(),
+                text: text.to_string(),
+                cache_control: None,
+            }]
+        }),
+        tools: if options.tools.is_empty() {
+            None
+        } else {
+            Some(options.tools.to_vec())
+        },
+    };
+
+    let mut headers = HeaderMap::new();
+    headers.insert("content-type", HeaderValue::from_static("application/json"));
+    headers.insert(
+        "accept",
+        HeaderValue::from_static("application/vnd.amazon.eventstream"),
+    );
+    if let Some(extra) = options.extra_headers {
+        for (key, value) in extra {
+            let header_name = HeaderName::from_bytes(key.as_bytes())
+                .map_err(|err| Error::Request(format!("Invalid header name \"{key}\": {err}")))?;
+            let header_value = HeaderValue::from_str(value)
+                .map_err(|err| Error::Request(format!("Invalid header value: {err}")))?;
+            headers.insert(header_name, header_value);
+        }
+    }
+
+    let base_url = if options.base_url.is_empty() {
+        format!(
+            "https://bedrock-runtime.{}.amazonaws.com",
+            credentials.region
+        )
+    } else {
+        options.base_url.trim_end_matches('/').to_string()
+    };
+    let model_id = bedrock_model_id(options.model, &credentials.region);
+    let endpoint = format!(
+        "{base_url}/model/{}/invoke-with-response-stream",
+        uri_encode(&model_id, true)
+    );
+
+    let signer = SigV4Hook {
+        credentials,
+        inner: options.request_hook,
+    };
+    let response = post_json(
+        &model.provider,
+        &model.api,
+        &model_id,
+        &endpoint,
+        headers,
+        &request,
+        Some(&signer),
+    )?;
+
+    if !response.status().is_success() {
+        return Err(error_response(response, |status, text| {
+            let message = serde_json::from_str::<BedrockErrorResponse>(text)
+                .map(|error| error.message)
+                .unwrap_or_else(|_| text.to_string());
+            format!("Bedrock error ({status}): {message}")
+        }));
+    }
+
+    let mut state = AnthropicStreamState::start(model, events);
+    let mut decoder = EventStreamDecoder::default();
+    let body = StreamBody::new(response);
+    while let Some(chunk) = body.next_chunk(events)? {
+        request_log::capture(&chunk);
+        let frames = decoder.feed(&chunk).map_err(|message| Error::Provider {
+            status: None,
+            message,
+        })?;
+        for frame in frames {
+            if frame.header(":message-type") == Some("exception") {
+                let payload = serde_json::from_slice::<Value>(&frame.payload).unwrap_or_default();
+                let message = payload
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .unwrap_or("Bedrock stream error");
+                let kind = frame.header(":exception-type").unwrap_or("exception");
+                let message = format!("{kind}: {message}");
+                let error_message = assistant_error_message(model, &message);
+                events.set_error(stream_error(kind, &message));
+                events.emit(AssistantMessageEvent::Error {
+                    message: error_message.clone(),
+                });
+                return Ok(error_message);
+            }
+            if frame.header(":event-type") != Some("chunk") {
+                continue;
+            }
+            let Some(value) = decode_chunk(&frame.payload) else {
+                continue;
+            };
+            let event_type = value.get("type").and_then(Value::as_str).unwrap_or("");
+            if let Some(error_message) = state.handle_event(model, events, event_type, &value) {
+                return Ok(error_message);
+            }
+        }
+    }
+
+    Ok(state.finish(events))
+}
+
+/// A `chunk` payload is `{"bytes": "<base64 Anthropic event JSON>"}`.
+fn decode_chunk(payload: &[u8]) -> Option<Value> {
+    let payload: Value = serde_json::from_slice(payload).ok()?;
+    let bytes = STANDARD.decode(payload.get("bytes")?.as_str()?).ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+/// Runs the caller's hook, then signs the request as it will be sent.
+struct SigV4Hook<'a> {
+    credentials: &'a AwsCredentials,
+    inner: Option<&'a dyn RequestHook>,
+}
+
+impl RequestHook for SigV4Hook<'_> {
+    fn on_request(&self, request: &mut ProviderRequest) -> Result<(), String> {
+        if let Some(inner) = self.inner {
+            inner.on_request(request)?;
+        }
+        sign_request(request, self.credentials, SERVICE, Utc::now())
+    }
+}
+
+/// Adds AWS Signature Version 4 headers (`x-amz-date`, `x-amz-security-token` for temporary
+/// credentials, and `authorization`) to `request`. Every header already on the request is
+/// signed, along with `host`.
+pub fn sign_request(
+    request: &mut ProviderRequest,
+    credentials: &AwsCredentials,
+    service: &str,
+    now: DateTime<Utc>,
+) -> Result<(), String> {
+    let url = url::Url::parse(&request.url)
+        .map_err(|err| format!("Invalid request URL \"{}\": {err}", request.url))?;
+    let host = match (url.host_str(), url.port()) {
+        (Some(host), Some(port)) => format!("{host}:{port}"),
+        (Some(host), None) => host.to_string(),
+        (None, _) => return Err(format!("Request URL has no host: {}", request.url)),
+    };
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = &amz_date[..8];
+
+    request.headers.remove("authorization");
+    request
+        .headers
+        .insert("x-amz-date".to_string(), amz_date.clone());
+    if let Some(token) = &credentials.session_token {
+        request
+            .headers
+            .insert("x-amz-security-token".to_string(), token.clone());
+    }
+
+    let mut signed = request
+        .headers
+        .iter()
+        .filter(|(name, _)| name.as_str() != "host")
+        .map(|(name, value)| (name.to_ascii_lowercase(), canonical_header_value(value)))
+        .collect::<Vec<_>>();
+    signed.push(("host".to_string(), host));
+    signed.sort();
+    let canonical_headers = signed
+        .iter()
+        .map(|(name, value)| format!("{name}:{value}\n"))
+        .collect::<String>();
+    let signed_headers = signed
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+
+    let canonical_uri = url
+        .path()
+        .split('/')
+        .map(|segment| uri_encode(segment, true))
+        .collect::<Vec<_>>()
+        .join("/");
+    let mut query = url
+        .query_pairs()
+        .map(|(key, value)| (uri_encode(&key, true), uri_encode(&value, true)))
+        .collect::<Vec<_>>();
+    query.sort();
+    let canonical_query = query
+        .iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect::<Vec<_>>()
+        .join("&");
+
+    let canonical_request = format!(
+        "{}\n{canonical_uri}\n{canonical_query}\n{canonical_headers}\n{signed_headers}\n{}",
+        request.method.to_uppercase(),
+        hex::encode(Sha256::digest(request.body.as_bytes()))
+    );
+    let scope = format!("{date}/{}/{service}/aws4_request", credentials.region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let secret = format!("AWS4{}", credentials.secret_access_key);
+    let key = hmac_sha256(secret.as_bytes(), date.as_bytes());
+    let key = hmac_sha256(&key, credentials.region.as_bytes());
+    let key = hmac_sha256(&key, service.as_bytes());
+    let key = hmac_sha256(&key, b"aws4_request");
+    let signature = hex::encode(hmac_sha256(&key, string_to_sign.as_bytes()));
+
+    request.headers.insert(
+        "authorization".to_string(),
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+            credentials.access_key_id
+        ),
+    );
+    Ok(())
+}
+
+fn canonical_header_value(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Percent-encodes everything but RFC 3986 unreserved characters (and `/` unless
+/// `encode_slash`), as SigV4 requires.
+fn uri_encode(value: &str, encode_slash: bool) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    const BLOCK_SIZE: usize = 64;
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let mut inner = Sha256::new();
+    inner.update(block.map(|byte| byte ^ 0x36));
+    inner.update(data);
+    let mut outer = Sha256::new();
+    outer.update(block.map(|byte| byte ^ 0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().into()
+}
+
+struct EventStreamFrame {
+    headers: HashMap<String, String>,
+    payload: Vec<u8>,
+}
+
+impl EventStreamFrame {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(String::as_str)
+    }
+}
+
+/// Splits `application/vnd.amazon.eventstream` bytes into frames: a 12-byte prelude (total
+/// length, headers length, prelude CRC), headers, payload and a trailing message CRC.
+#[derive(Default)]
+struct EventStreamDecoder {
+    buffer: Vec<u8>,
+}
+
+impl EventStreamDecoder {
+    fn feed(&mut self, bytes: &[u8]) -> Result<Vec<EventStreamFrame>, String> {
+        self.buffer.extend_from_slice(bytes);
+        let mut frames = Vec::new();
+        while self.buffer.len() >= 12 {
+            let total_len = read_u32(&self.buffer[0..4]) as usize;
+            let headers_len = read_u32(&self.buffer[4..8]) as usize;
+            if read_u32(&self.buffer[8..12]) != crc32(&self.buffer[..8]) {
+                return Err("Bedrock event stream: prelude checksum mismatch".to_string());
+            }
+            if total_len < 16 + headers_len {
+                return Err("Bedrock event stream: invalid frame length".to_string());
+            }
+            if self.buffer.len() < total_len {
+                break;
+            }
+            let frame = self.buffer.drain(..total_len).collect::<Vec<_>>();
+            if read_u32(&frame[total_len - 4..]) != crc32(&frame[..total_len - 4]) {
+                return Err("Bedrock event stream: message checksum mismatch".to_string());
+            }
+            frames.push(EventStreamFrame {
+                headers: parse_headers(&frame[12..12 + headers_len])?,
+                payload: frame[12 + headers_len..total_len - 4].to_vec(),
+            });
+        }
+        Ok(frames)
+    }
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// String headers by name; values of other types are skipped.
+fn parse_headers(mut bytes: &[u8]) -> Result<HashMap<String, String>, String> {
+    let truncated = || "Bedrock event stream: truncated header".to_string();
+    let mut headers = HashMap::new();
+    while !bytes.is_empty() {
+        let name_len = bytes[0] as usize;
+        let name = bytes.get(1..1 + name_len).ok_or_else(truncated)?;
+        let name = String::from_utf8_lossy(name).into_owned();
+        let value_type = *bytes.get(1 + name_len).ok_or_else(truncated)?;
+        let rest = &bytes[2 + name_len..];
+        let value_len = match value_type {
+            0 | 1 => 0,
+            2 => 1,
+            3 => 2,
+            4 => 4,
+            5 | 8 => 8,
+            9 => 16,
+            6 | 7 => {
+                let len = rest.get(..2).ok_or_else(truncated)?;
+                2 + u16::from_be_bytes([len[0], len[1]]) as usize
+            }
+            other => return Err(format!("Bedrock event stream: unknown header type {other}")),
+        };
+        let value = rest.get(..value_len).ok_or_else(truncated)?;
+        if value_type == 7 {
+            headers.insert(name, String::from_utf8_lossy(&value[2..]).into_owned());
+        }
+        bytes = &rest[value_len..];
+    }
+    Ok(headers)
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
@@ -0,0 +1,116 @@
+// Google Generative AI provider: Gemini models on the public Gemini API
+// (generativelanguage.googleapis.com), authenticated with an API key (`GEMINI_API_KEY`).
+// Requests and responses use the same shapes as Cloud Code Assist, without its envelope.
+
+use crate::agent::{LlmContext, StreamEvents};
+use crate::api::google_gemini_cli::{
+    build_gemini_messages, build_generation_config, convert_tools, read_gemini_stream,
+    GeminiCliTool, GeminiResponse, GeminiSystemInstruction, GeminiTextPart, GenerateContentRequest,
+};
+use crate::api::request_hook::{post_json, RequestHook};
+use crate::api::{error_response, request_log};
+use crate::coding_agent::Model as RegistryModel;
+use crate::core::messages::AssistantMessage;
+use crate::error::Error;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::collections::HashMap;
+
+pub const GOOGLE_GENERATIVE_AI_API: &str = "google-generative-ai";
+pub const DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+pub struct GoogleCallOptions<'a> {
+    pub model: &'a str,
+    pub api_key: &'a str,
+    pub tools: &'a [GeminiCliTool],
+    pub base_url: &'a str,
+    pub extra_headers: Option<&'a HashMap<String, String>>,
+    pub system: Option<&'a str>,
+    pub thinking_enabled: bool,
+    pub seed: Option<u64>,
+    pub request_hook: Option<&'a dyn RequestHook>,
+}
+
+fn build_headers(
+    api_key: &str,
+    extra_headers: Option<&HashMap<String, String>>,
+) -> Result<HeaderMap, String> {
+    let mut headers = HeaderMap::new();
+    headers.insert("content-type", HeaderValue::from_static("application/json"));
+    headers.insert("accept", HeaderValue::from_static("text/event-stream"));
+    headers.insert(
+        "x-goog-api-key",
+        HeaderValue::from_str(api_key).map_err(|err| format!("Invalid Gemini API key: {err}"))?,
+    );
+    if let Some(extra) = extra_headers {
+        for (key, value) in extra {
+            let header_name = HeaderName::from_bytes(key.as_bytes())
+                .map_err(|err| format!("Invalid header name \"{key}\": {err}"))?;
+            let header_value = HeaderValue::from_str(value)
+                .map_err(|err| format!("Invalid header value: {err}"))?;
+            headers.insert(header_name, header_value);
+        }
+    }
+    Ok(headers)
+}
+
+pub fn stream_google_generative_ai(
+    model: &RegistryModel,
+    context: &LlmContext,
+    options: GoogleCallOptions<'_>,
+    events: &mut StreamEvents,
+) -> Result<AssistantMessage, Error> {
+    let result = send_google_stream(model, context, options, events);
+    request_log::finish_message(&result);
+    result
+}
+
+fn send_google_stream(
+    model: &RegistryModel,
+    context: &LlmContext,
+    options: GoogleCallOptions<'_>,
+    events: &mut StreamEvents,
+) -> Result<AssistantMessage, Error> {
+    let request_body = GenerateContentRequest {
+        contents: build_gemini_messages(model, context),
+        system_instruction: options.system.map(|text| GeminiSystemInstruction {
+            parts: vec![GeminiTextPart {
+                text: text.to_string(),
+            }],
+        }),
+        generation_config: build_generation_config(model, options.thinking_enabled, options.seed),
+        tools: convert_tools(options.tools),
+        tool_config: None,
+    };
+
+    let headers = build_headers(options.api_key, options.extra_headers).map_err(Error::Request)?;
+    let base_url = if options.base_url.is_empty() {
+        DEFAULT_BASE_URL
+    } else {
+        options.base_url
+    };
+    let endpoint = format!(
+        "{}/models/{}:streamGenerateContent?alt=sse",
+        base_url.trim_end_matches('/'),
+        options.model
+    );
+
+    let response = post_json(
+        &model.provider,
+        &model.api,
+        options.model,
+        &endpoint,
+        headers,
+        &request_body,
+        options.request_hook,
+    )?;
+
+    if !response.status().is_success() {
+        return Err(error_response(response, |status, text| {
+            format!("Gemini API error ({status}): {text}")
+        }));
+    }
+
+    read_gemini_stream(model, response, events, |data| {
+        serde_json::from_str::<GeminiResponse>(data).ok()
+    })
+}
@@ -3,15 +3,17 @@
 
 use crate::agent::{AgentMessage, LlmContext, StreamEvents};
 use crate::ai::AssistantMessageEvent;
+use crate::api::request_hook::{post_json, RequestHook};
+use crate::api::{error_response, request_log, StreamBody, Utf8Decoder};
 use crate::coding_agent::Model as RegistryModel;
 use crate::core::messages::{
     AssistantMessage, ContentBlock, Cost, ToolResultMessage, Usage, UserContent,
 };
-use reqwest::blocking::Client;
+use crate::error::Error;
+use reqwest::blocking::{Client, Response};
 use reqwest::header::{HeaderMap, HeaderValue};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use std::io::Read;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -76,6 +78,8 @@ pub struct GeminiGenerationConfig {
     pub temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub thinking_config: Option<GeminiThinkingConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -255,6 +259,8 @@ pub struct GeminiCliCallOptions<'a> {
     pub base_url: &'a str,
     pub system: Option<&'a str>,
     pub thinking_enabled: bool,
+    pub seed: Option<u64>,
+    pub request_hook: Option<&'a dyn RequestHook>,
 }
 
 struct SseEvent {
@@ -430,7 +436,39 @@ fn build_headers(access_token: &str) -> Result<HeaderMap, String> {
     Ok(headers)
 }
 
-fn convert_tools(tools: &[GeminiCliTool]) -> Option<Vec<GeminiToolDeclaration>> {
+/// Thinking (for reasoning models, when enabled) and the sampling seed, if either applies.
+pub(crate) fn build_generation_config(
+    model: &RegistryModel,
+    thinking_enabled: bool,
+    seed: Option<u64>,
+) -> Option<GeminiGenerationConfig> {
+    let mut generation_config = None;
+    if thinking_enabled && model.reasoning {
+        generation_config = Some(GeminiGenerationConfig {
+            max_output_tokens: None,
+            temperature: None,
+            thinking_config: Some(GeminiThinkingConfig {
+                include_thoughts: true,
+                thinking_level: None,
+                thinking_budget: None,
+            }),
+            seed: None,
+        });
+    }
+    if let Some(seed) = seed {
+        generation_config
+            .get_or_insert(GeminiGenerationConfig {
+                max_output_tokens: None,
+                temperature: None,
+                thinking_config: None,
+                seed: None,
+            })
+            .seed = Some(seed);
+    }
+    generation_config
+}
+
+pub(crate) fn convert_tools(tools: &[GeminiCliTool]) -> Option<Vec<GeminiToolDeclaration>> {
     if tools.is_empty() {
         return None;
     }
@@ -451,22 +489,20 @@ pub fn stream_google_gemini_cli(
     context: &LlmContext,
     options: GeminiCliCallOptions<'_>,
     events: &mut StreamEvents,
-) -> Result<AssistantMessage, String> {
-    let contents = build_gemini_messages(model, context);
-
-    let mut generation_config = None;
-    if options.thinking_enabled && model.reasoning {
-        generation_config = Some(GeminiGenerationConfig {
-            max_output_tokens: None,
-            temperature: None,
-            thinking_config: Some(GeminiThinkingConfig {
-                include_thoughts: true,
-                thinking_level: None,
-                thinking_budget: None,
-            }),
-        });
-    }
+) -> Result<AssistantMessage, Error> {
+    let result = send_gemini_cli_stream(model, context, options, events);
+    request_log::finish_message(&result);
+    result
+}
 
+fn send_gemini_cli_stream(
+    model: &RegistryModel,
+    context: &LlmContext,
+    options: GeminiCliCallOptions<'_>,
+    events: &mut StreamEvents,
+) -> Result<AssistantMessage, Error> {
+    let contents = build_gemini_messages(model, context);
+    let generation_config = build_generation_config(model, options.thinking_enabled, options.seed);
     let system_instruction = options.system.map(|text| GeminiSystemInstruction {
         parts: vec![GeminiTextPart {
             text: text.to_string(),
@@ -487,7 +523,7 @@ pub fn stream_google_gemini_cli(
         request_id: Some(format!("pi-{}-{}", now_millis(), rand_alphanumeric(9))),
     };
 
-    let headers = build_headers(options.access_token)?;
+    let headers = build_headers(options.access_token).map_err(Error::Request)?;
     let base_url = if options.base_url.is_empty() {
         DEFAULT_ENDPOINT
     } else {
@@ -498,24 +534,37 @@ pub fn stream_google_gemini_cli(
         base_url.trim_end_matches('/')
     );
 
-    let client = Client::new();
-    let mut response = client
-        .post(&endpoint)
-        .headers(headers)
-        .json(&request_body)
-        .send()
-        .map_err(|e| format!("Request failed: {e}"))?;
+    let response = post_json(
+        &model.provider,
+        &model.api,
+        options.model,
+        &endpoint,
+        headers,
+        &request_body,
+        options.request_hook,
+    )?;
 
-    let status = response.status();
-    if !status.is_success() {
-        let text = response.text().unwrap_or_default();
-        return Err(format!(
-            "Cloud Code Assist API error ({}): {}",
-            status.as_u16(),
-            text
-        ));
+    if !response.status().is_success() {
+        return Err(error_response(response, |status, text| {
+            format!("Cloud Code Assist API error ({status}): {text}")
+        }));
     }
 
+    read_gemini_stream(model, response, events, |data| {
+        serde_json::from_str::<CloudCodeAssistResponseChunk>(data)
+            .ok()?
+            .response
+    })
+}
+
+/// Streams a `streamGenerateContent?alt=sse` response into `events`. `parse_chunk` unwraps
+/// each event's payload, which differs between Cloud Code Assist and the public Gemini API.
+pub(crate) fn read_gemini_stream(
+    model: &RegistryModel,
+    response: Response,
+    events: &mut StreamEvents,
+    parse_chunk: impl Fn(&str) -> Option<GeminiResponse>,
+) -> Result<AssistantMessage, Error> {
     let mut partial = stream_partial_message(model);
     emit_event(
         events,
@@ -525,30 +574,21 @@ pub fn stream_google_gemini_cli(
     );
 
     let mut parser = SseParser::new();
-    let mut buf = [0u8; 8192];
+    let mut decoder = Utf8Decoder::default();
     let mut current_text_index: Option<usize> = None;
     let mut current_thinking_index: Option<usize> = None;
 
-    loop {
-        let read = response
-            .read(&mut buf)
-            .map_err(|e| format!("Stream read failed: {e}"))?;
-        if read == 0 {
-            break;
-        }
+    let body = StreamBody::new(response);
+    while let Some(chunk) = body.next_chunk(events)? {
+        request_log::capture(&chunk);
 
-        let chunk = String::from_utf8_lossy(&buf[..read]);
+        let chunk = decoder.decode(&chunk);
         for event in parser.feed(&chunk) {
             if event.data == "[DONE]" {
                 continue;
             }
 
-            let value: CloudCodeAssistResponseChunk = match serde_json::from_str(&event.data) {
-                Ok(v) => v,
-                Err(_) => continue,
-            };
-
-            let Some(response_data) = value.response else {
+            let Some(response_data) = parse_chunk(&event.data) else {
                 continue;
             };
 
@@ -792,13 +832,7 @@ pub fn stream_google_gemini_cli(
     }
 
     apply_stream_stop_reason(&mut partial);
-    emit_event(
-        events,
-        AssistantMessageEvent::Done {
-            message: partial.clone(),
-        },
-    );
-    Ok(partial)
+    Ok(events.finish(partial))
 }
 
 pub fn build_gemini_messages(model: &RegistryModel, context: &LlmContext) -> Vec<GeminiContent> {
@@ -937,7 +971,7 @@ fn assistant_blocks_to_gemini_parts(blocks: &[ContentBlock]) -> Vec<GeminiPart>
                     thought_signature: thought_signature.clone(),
                 }));
             }
-            ContentBlock::Image { .. } => {
+            ContentBlock::Image { .. } | ContentBlock::Diff { .. } => {
                 // Images in assistant messages not typical, skip
             }
         }
@@ -947,7 +981,7 @@ fn assistant_blocks_to_gemini_parts(blocks: &[ContentBlock]) -> Vec<GeminiPart>
 
 fn tool_result_to_gemini_parts(
     result: &ToolResultMessage,
-    _supports_images: bool,
+    supports_images: bool,
 ) -> Vec<GeminiPart> {
     let mut parts = Vec::new();
 
@@ -955,10 +989,7 @@ fn tool_result_to_gemini_parts(
     let text_content: String = result
         .content
         .iter()
-        .filter_map(|c| match c {
-            ContentBlock::Text { text, .. } => Some(text.as_str()),
-            _ => None,
-        })
+        .filter_map(ContentBlock::as_text)
         .collect::<Vec<_>>()
         .join("\n");
 
@@ -979,9 +1010,20 @@ fn tool_result_to_gemini_parts(
         },
     }));
 
-    // Add images in separate user message if present (for older models)
-    // Gemini 3 supports multimodal function responses but for simplicity we skip
-    // This matches the TS behavior for non-Gemini-3 models
+    // Function responses are text only; images (e.g. from `read`) follow as inline data in
+    // the same user turn.
+    if supports_images {
+        for block in &result.content {
+            if let ContentBlock::Image { data, mime_type } = block {
+                parts.push(GeminiPart::InlineData(GeminiInlineDataPart {
+                    inline_data: GeminiInlineData {
+                        mime_type: mime_type.clone(),
+                        data: data.clone(),
+                    },
+                }));
+            }
+        }
+    }
 
     parts
 }
@@ -1103,7 +1145,10 @@ pub fn discover_gemini_project(access_token: &str) -> Result<String, String> {
 // They are intentionally public, similar to how Chrome's OAuth client ID is public.
 fn google_oauth_client_id() -> String {
     // Split to avoid secret scanner false positives
-    let parts = ["NjgxMjU1ODA5Mzk1LW9vOGZ0Mm9wcmRybnA5", "ZTNhcWY2YXYzaG1kaWIxMzVqLmFwcHMuZ29vZ2xldXNlcmNvbnRlbnQuY29t"];
+    let parts = [
+        "NjgxMjU1ODA5Mzk1LW9vOGZ0Mm9wcmRybnA5",
+        "ZTNhcWY2YXYzaG1kaWIxMzVqLmFwcHMuZ29vZ2xldXNlcmNvbnRlbnQuY29t",
+    ];
     let encoded = parts.join("");
     String::from_utf8(
         base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &encoded)
@@ -0,0 +1,316 @@
+//! The `mock` API: scripted replies from a JSON fixture instead of a provider, for tests and
+//! offline demos. A mock model's `baseUrl` is the fixture path:
+//!
+//! ```json
+//! {
+//!   "delayMs": 20,
+//!   "responses": [
+//!     { "content": [
+//!         { "type": "text", "text": "Let me look." },
+//!         { "type": "toolCall", "name": "read", "arguments": { "path": "README.md" } }
+//!     ] },
+//!     { "content": [{ "type": "text", "text": ["Done", "."] }], "usage": { "output": 2 } },
+//!     { "error": "overloaded_error: try again later" }
+//!   ]
+//! }
+//! ```
+//!
+//! Each turn takes the next response. Text given as a string streams word by word; a list
+//! streams one delta per item. `delayMs` is the pause before each delta.
+
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{assistant_error_message, calculate_cost, emit_event, stream_partial_message};
+use crate::agent::StreamEvents;
+use crate::ai::AssistantMessageEvent;
+use crate::coding_agent::Model as RegistryModel;
+use crate::core::messages::{AssistantMessage, ContentBlock};
+
+pub const MOCK_API: &str = "mock";
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MockFixture {
+    #[serde(default)]
+    pub delay_ms: u64,
+    pub responses: Vec<MockResponse>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MockResponse {
+    #[serde(default)]
+    pub content: Vec<MockBlock>,
+    /// Defaults to `toolUse` when the response calls a tool and `stop` otherwise.
+    pub stop_reason: Option<String>,
+    /// Answers the turn with this provider error instead of `content`.
+    pub error: Option<String>,
+    /// Overrides the fixture's `delayMs` for this response.
+    pub delay_ms: Option<u64>,
+    #[serde(default)]
+    pub usage: MockUsage,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum MockBlock {
+    Text {
+        text: MockDeltas,
+    },
+    Thinking {
+        thinking: MockDeltas,
+    },
+    ToolCall {
+        id: Option<String>,
+        name: String,
+        #[serde(default)]
+        arguments: Value,
+    },
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum MockDeltas {
+    Whole(String),
+    Deltas(Vec<String>),
+}
+
+impl MockDeltas {
+    fn deltas(&self) -> Vec<&str> {
+        match self {
+            MockDeltas::Whole(text) => text.split_inclusive(char::is_whitespace).collect(),
+            MockDeltas::Deltas(deltas) => deltas.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MockUsage {
+    pub input: i64,
+    pub output: i64,
+    pub cache_read: i64,
+    pub cache_write: i64,
+}
+
+pub fn load_mock_fixture(path: &Path) -> Result<MockFixture, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read mock fixture {}: {err}", path.display()))?;
+    serde_json::from_str(&content)
+        .map_err(|err| format!("Invalid mock fixture {}: {err}", path.display()))
+}
+
+/// Plays a fixture's responses in order, one per turn.
+pub struct MockProvider {
+    path: PathBuf,
+    fixture: MockFixture,
+    turn: usize,
+}
+
+impl MockProvider {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        Ok(Self::new(path, load_mock_fixture(path)?))
+    }
+
+    pub fn new(path: &Path, fixture: MockFixture) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            fixture,
+            turn: 0,
+        }
+    }
+
+    pub fn stream(&mut self, model: &RegistryModel, events: &mut StreamEvents) -> AssistantMessage {
+        let turn = self.turn;
+        self.turn += 1;
+        let Some(response) = self.fixture.responses.get(turn) else {
+            return assistant_error_message(
+                model,
+                &format!(
+                    "Mock fixture {} has no response for turn {} (it scripts {})",
+                    self.path.display(),
+                    turn + 1,
+                    self.fixture.responses.len()
+                ),
+            );
+        };
+        if let Some(error) = &response.error {
+            return assistant_error_message(model, error);
+        }
+        let delay = Duration::from_millis(response.delay_ms.unwrap_or(self.fixture.delay_ms));
+        stream_mock_response(model, response, turn, delay, events)
+    }
+}
+
+fn stream_mock_response(
+    model: &RegistryModel,
+    response: &MockResponse,
+    turn: usize,
+    delay: Duration,
+    events: &mut StreamEvents,
+) -> AssistantMessage {
+    let mut partial = stream_partial_message(model);
+    emit_event(
+        events,
+        AssistantMessageEvent::Start {
+            partial: partial.clone(),
+        },
+    );
+    'blocks: for (index, block) in response.content.iter().enumerate() {
+        let content_index = partial.content.len();
+        match block {
+            MockBlock::Text { text } => {
+                partial.content.push(ContentBlock::Text {
+                    text: String::new(),
+                    text_signature: None,
+                });
+                emit_event(
+                    events,
+                    AssistantMessageEvent::TextStart {
+                        partial: partial.clone(),
+                        content_index,
+                    },
+                );
+                for delta in text.deltas() {
+                    if !pause(delay, events) {
+                        break 'blocks;
+                    }
+                    if let Some(ContentBlock::Text { text, .. }) = partial.content.last_mut() {
+                        text.push_str(delta);
+                    }
+                    emit_event(
+                        events,
+                        AssistantMessageEvent::TextDelta {
+                            delta: delta.to_string(),
+                            partial: partial.clone(),
+                            content_index,
+                        },
+                    );
+                }
+                emit_event(
+                    events,
+                    AssistantMessageEvent::TextEnd {
+                        partial: partial.clone(),
+                        content_index,
+                    },
+                );
+            }
+            MockBlock::Thinking { thinking } => {
+                partial.content.push(ContentBlock::Thinking {
+                    thinking: String::new(),
+                    thinking_signature: None,
+                });
+                emit_event(
+                    events,
+                    AssistantMessageEvent::ThinkingStart {
+                        partial: partial.clone(),
+                        content_index,
+                    },
+                );
+                for delta in thinking.deltas() {
+                    if !pause(delay, events) {
+                        break 'blocks;
+                    }
+                    if let Some(ContentBlock::Thinking { thinking, .. }) =
+                        partial.content.last_mut()
+                    {
+                        thinking.push_str(delta);
+                    }
+                    emit_event(
+                        events,
+                        AssistantMessageEvent::ThinkingDelta {
+                            delta: delta.to_string(),
+                            partial: partial.clone(),
+                            content_index,
+                        },
+                    );
+                }
+                emit_event(
+                    events,
+                    AssistantMessageEvent::ThinkingEnd {
+                        partial: partial.clone(),
+                        content_index,
+                    },
+                );
+            }
+            MockBlock::ToolCall {
+                id,
+                name,
+                arguments,
+            } => {
+                if !pause(delay, events) {
+                    break;
+                }
+                let arguments = match arguments {
+                    Value::Null => Value::Object(Default::default()),
+                    arguments => arguments.clone(),
+                };
+                partial.content.push(ContentBlock::ToolCall {
+                    // Generated ids are stable so replayed sessions compare equal.
+                    id: id
+                        .clone()
+                        .unwrap_or_else(|| format!("mock_{}_{index}", turn + 1)),
+                    name: name.clone(),
+                    arguments: arguments.clone(),
+                    thought_signature: None,
+                });
+                emit_event(
+                    events,
+                    AssistantMessageEvent::ToolCallStart {
+                        partial: partial.clone(),
+                        content_index,
+                    },
+                );
+                emit_event(
+                    events,
+                    AssistantMessageEvent::ToolCallDelta {
+                        delta: arguments.to_string(),
+                        partial: partial.clone(),
+                        content_index,
+                    },
+                );
+                emit_event(
+                    events,
+                    AssistantMessageEvent::ToolCallEnd {
+                        partial: partial.clone(),
+                        content_index,
+                    },
+                );
+            }
+        }
+    }
+
+    let usage = &response.usage;
+    partial.usage.input = usage.input;
+    partial.usage.output = usage.output;
+    partial.usage.cache_read = usage.cache_read;
+    partial.usage.cache_write = usage.cache_write;
+    partial.usage.total_tokens =
+        Some(usage.input + usage.output + usage.cache_read + usage.cache_write);
+    calculate_cost(model, &mut partial.usage);
+    let calls_tool = partial
+        .content
+        .iter()
+        .any(|block| matches!(block, ContentBlock::ToolCall { .. }));
+    partial.stop_reason = response
+        .stop_reason
+        .clone()
+        .unwrap_or_else(|| if calls_tool { "toolUse" } else { "stop" }.to_string());
+    events.finish(partial)
+}
+
+/// Waits before the next delta; false once the turn is aborted.
+fn pause(delay: Duration, events: &StreamEvents) -> bool {
+    if events.is_aborted() {
+        return false;
+    }
+    if !delay.is_zero() {
+        thread::sleep(delay);
+    }
+    !events.is_aborted()
+}
@@ -1,5 +1,12 @@
+pub mod batch;
+pub mod bedrock;
+pub mod google;
 pub mod google_gemini_cli;
+pub mod mock;
 pub mod openai_codex;
+pub mod openai_completions;
+pub mod request_hook;
+pub mod request_log;
 
 use crate::agent::{AgentMessage, LlmContext, StreamEvents};
 use crate::ai::AssistantMessageEvent;
@@ -7,12 +14,16 @@ use crate::coding_agent::Model as RegistryModel;
 use crate::core::messages::{
     AssistantMessage, ContentBlock, Cost, ToolResultMessage, Usage, UserContent,
 };
-use reqwest::blocking::Client;
+use crate::error::Error;
+use request_hook::{post_json, RequestHook};
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 use std::collections::HashMap;
 use std::io::Read;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
 
 #[derive(Debug, Serialize, Clone)]
 pub struct AnthropicRequest {
@@ -115,6 +126,8 @@ pub struct OpenAIRequest {
     pub tools: Option<Vec<OpenAITool>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -229,6 +242,7 @@ pub struct AnthropicCallOptions<'a> {
     pub base_url: &'a str,
     pub extra_headers: Option<&'a HashMap<String, String>>,
     pub system: Option<&'a str>,
+    pub request_hook: Option<&'a dyn RequestHook>,
 }
 
 pub struct OpenAICallOptions<'a> {
@@ -237,6 +251,8 @@ pub struct OpenAICallOptions<'a> {
     pub tools: &'a [OpenAITool],
     pub base_url: &'a str,
     pub extra_headers: Option<&'a HashMap<String, String>>,
+    pub seed: Option<u64>,
+    pub request_hook: Option<&'a dyn RequestHook>,
 }
 
 fn build_anthropic_headers(
@@ -345,7 +361,16 @@ fn build_system_content(
 pub fn call_anthropic(
     messages: Vec<AnthropicMessage>,
     options: AnthropicCallOptions<'_>,
-) -> Result<AnthropicResponse, String> {
+) -> Result<AnthropicResponse, Error> {
+    let result = send_anthropic(messages, options);
+    request_log::finish(&result);
+    result
+}
+
+fn send_anthropic(
+    messages: Vec<AnthropicMessage>,
+    options: AnthropicCallOptions<'_>,
+) -> Result<AnthropicResponse, Error> {
     let request = AnthropicRequest {
         model: options.model.to_string(),
         max_tokens: 1024,
@@ -360,34 +385,45 @@ pub fn call_anthropic(
     };
 
     let headers =
-        build_anthropic_headers(options.api_key, options.use_oauth, options.extra_headers)?;
+        build_anthropic_headers(options.api_key, options.use_oauth, options.extra_headers)
+            .map_err(Error::Request)?;
     let endpoint = format!("{}/messages", options.base_url.trim_end_matches('/'));
-    let client = Client::new();
-    let response = client
-        .post(endpoint)
-        .headers(headers)
-        .json(&request)
-        .send()
-        .map_err(|err| format!("Request failed: {err}"))?;
-
-    let status = response.status();
-    if !status.is_success() {
-        let text = response.text().unwrap_or_default();
-        if let Ok(error_response) = serde_json::from_str::<AnthropicErrorResponse>(&text) {
-            return Err(format!("Anthropic error: {}", error_response.error.message));
-        }
-        return Err(format!("Anthropic error: {} {}", status.as_u16(), text));
+    let response = post_json(
+        "anthropic",
+        "anthropic-messages",
+        options.model,
+        &endpoint,
+        headers,
+        &request,
+        options.request_hook,
+    )?;
+
+    if !response.status().is_success() {
+        return Err(error_response(response, |status, text| {
+            serde_json::from_str::<AnthropicErrorResponse>(text)
+                .map(|error_response| format!("Anthropic error: {}", error_response.error.message))
+                .unwrap_or_else(|_| format!("Anthropic error: {status} {text}"))
+        }));
     }
 
-    response
-        .json::<AnthropicResponse>()
-        .map_err(|err| format!("Failed to parse response: {err}"))
+    let text = response.text().map_err(response_read_failed)?;
+    request_log::capture(text.as_bytes());
+    serde_json::from_str::<AnthropicResponse>(&text).map_err(response_parse_failed)
 }
 
 pub fn call_openai(
     input: Vec<OpenAIInputItem>,
     options: OpenAICallOptions<'_>,
-) -> Result<OpenAIResponse, String> {
+) -> Result<OpenAIResponse, Error> {
+    let result = send_openai(input, options);
+    request_log::finish(&result);
+    result
+}
+
+fn send_openai(
+    input: Vec<OpenAIInputItem>,
+    options: OpenAICallOptions<'_>,
+) -> Result<OpenAIResponse, Error> {
     let request = OpenAIRequest {
         model: options.model.to_string(),
         input,
@@ -397,30 +433,138 @@ pub fn call_openai(
             Some(options.tools.to_vec())
         },
         stream: Some(false),
+        seed: options.seed,
     };
 
-    let headers = build_openai_headers(options.api_key, options.extra_headers)?;
+    let headers =
+        build_openai_headers(options.api_key, options.extra_headers).map_err(Error::Request)?;
     let endpoint = format!("{}/responses", options.base_url.trim_end_matches('/'));
-    let client = Client::new();
-    let response = client
-        .post(endpoint)
-        .headers(headers)
-        .json(&request)
-        .send()
-        .map_err(|err| format!("Request failed: {err}"))?;
-
-    let status = response.status();
-    if !status.is_success() {
-        let text = response.text().unwrap_or_default();
-        if let Ok(error_response) = serde_json::from_str::<OpenAIErrorResponse>(&text) {
-            return Err(format!("OpenAI error: {}", error_response.error.message));
+    let response = post_json(
+        "openai",
+        "openai-responses",
+        options.model,
+        &endpoint,
+        headers,
+        &request,
+        options.request_hook,
+    )?;
+
+    if !response.status().is_success() {
+        return Err(error_response(response, |status, text| {
+            serde_json::from_str::<OpenAIErrorResponse>(text)
+                .map(|error_response| format!("OpenAI error: {}", error_response.error.message))
+                .unwrap_or_else(|_| format!("OpenAI error: {status} {text}"))
+        }));
+    }
+
+    let text = response.text().map_err(response_read_failed)?;
+    request_log::capture(text.as_bytes());
+    serde_json::from_str::<OpenAIResponse>(&text).map_err(response_parse_failed)
+}
+
+/// Turns a non-success reply into an [`Error`] classified by status. `describe` gets the
+/// status code and body and writes the provider's message.
+pub(crate) fn error_response(
+    response: reqwest::blocking::Response,
+    describe: impl FnOnce(u16, &str) -> String,
+) -> Error {
+    let status = response.status().as_u16();
+    let retry_after = retry_after(response.headers());
+    let text = response.text().unwrap_or_default();
+    Error::from_status(status, retry_after, describe(status, &text))
+}
+
+/// A `retry-after` header given in seconds.
+pub(crate) fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get("retry-after")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+fn response_read_failed(err: reqwest::Error) -> Error {
+    Error::Provider {
+        status: None,
+        message: format!("Failed to read response: {err}"),
+    }
+}
+
+fn response_parse_failed(err: serde_json::Error) -> Error {
+    Error::Provider {
+        status: None,
+        message: format!("Failed to parse response: {err}"),
+    }
+}
+
+pub(crate) fn stream_read_failed(err: std::io::Error) -> Error {
+    Error::Provider {
+        status: None,
+        message: format!("Stream read failed: {err}"),
+    }
+}
+
+/// How often a stream waiting on the network checks whether its turn was aborted.
+const ABORT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A streaming response body read on its own thread, so the stream loop waits on a channel
+/// it can give up on the moment the turn is aborted instead of on a blocking `read` that
+/// only returns when the provider sends more. The reader thread ends with the connection.
+pub(crate) struct StreamBody {
+    chunks: mpsc::Receiver<std::io::Result<Vec<u8>>>,
+}
+
+impl StreamBody {
+    pub(crate) fn new(mut response: impl Read + Send + 'static) -> Self {
+        let (sender, chunks) = mpsc::sync_channel(16);
+        thread::spawn(move || {
+            let mut buf = [0u8; 8192];
+            loop {
+                let chunk = response.read(&mut buf).map(|read| buf[..read].to_vec());
+                let more = matches!(&chunk, Ok(bytes) if !bytes.is_empty());
+                if sender.send(chunk).is_err() || !more {
+                    break;
+                }
+            }
+        });
+        Self { chunks }
+    }
+
+    /// The next chunk of the body, or `None` once it ends or `events` is aborted.
+    pub(crate) fn next_chunk(&self, events: &StreamEvents) -> Result<Option<Vec<u8>>, Error> {
+        loop {
+            if events.is_aborted() {
+                return Ok(None);
+            }
+            match self.chunks.recv_timeout(ABORT_POLL_INTERVAL) {
+                Ok(Ok(chunk)) => return Ok(Some(chunk).filter(|chunk| !chunk.is_empty())),
+                Ok(Err(_)) if events.is_aborted() => return Ok(None),
+                Ok(Err(err)) => return Err(stream_read_failed(err)),
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => return Ok(None),
+            }
         }
-        return Err(format!("OpenAI error: {} {}", status.as_u16(), text));
     }
+}
 
-    response
-        .json::<OpenAIResponse>()
-        .map_err(|err| format!("Failed to parse response: {err}"))
+/// Decodes a body chunk by chunk. A character whose bytes are split across two network
+/// chunks is held back until the rest arrives instead of turning into U+FFFD.
+#[derive(Default)]
+pub(crate) struct Utf8Decoder {
+    pending: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    pub(crate) fn decode(&mut self, chunk: &[u8]) -> String {
+        self.pending.extend_from_slice(chunk);
+        let complete = match std::str::from_utf8(&self.pending) {
+            Err(err) if err.error_len().is_none() => err.valid_up_to(),
+            _ => self.pending.len(),
+        };
+        let text = String::from_utf8_lossy(&self.pending[..complete]).into_owned();
+        self.pending.drain(..complete);
+        text
+    }
 }
 
 struct SseEvent {
@@ -532,7 +676,18 @@ pub fn stream_anthropic(
     messages: Vec<AnthropicMessage>,
     options: AnthropicCallOptions<'_>,
     events: &mut StreamEvents,
-) -> Result<AssistantMessage, String> {
+) -> Result<AssistantMessage, Error> {
+    let result = send_anthropic_stream(model, messages, options, events);
+    request_log::finish_message(&result);
+    result
+}
+
+fn send_anthropic_stream(
+    model: &RegistryModel,
+    messages: Vec<AnthropicMessage>,
+    options: AnthropicCallOptions<'_>,
+    events: &mut StreamEvents,
+) -> Result<AssistantMessage, Error> {
     let request = AnthropicRequest {
         model: options.model.to_string(),
         max_tokens: 1024,
@@ -547,44 +702,35 @@ pub fn stream_anthropic(
     };
 
     let headers =
-        build_anthropic_headers(options.api_key, options.use_oauth, options.extra_headers)?;
+        build_anthropic_headers(options.api_key, options.use_oauth, options.extra_headers)
+            .map_err(Error::Request)?;
     let endpoint = format!("{}/messages", options.base_url.trim_end_matches('/'));
-    let client = Client::new();
-    let mut response = client
-        .post(&endpoint)
-        .headers(headers)
-        .json(&request)
-        .send()
-        .map_err(|err| format!("Request failed: {err}"))?;
-
-    let status = response.status();
-    if !status.is_success() {
-        let text = response.text().unwrap_or_default();
-        if let Ok(error_response) = serde_json::from_str::<AnthropicErrorResponse>(&text) {
-            return Err(format!("Anthropic error: {}", error_response.error.message));
-        }
-        return Err(format!("Anthropic error: {} {}", status.as_u16(), text));
+    let response = post_json(
+        &model.provider,
+        &model.api,
+        options.model,
+        &endpoint,
+        headers,
+        &request,
+        options.request_hook,
+    )?;
+
+    if !response.status().is_success() {
+        return Err(error_response(response, |status, text| {
+            serde_json::from_str::<AnthropicErrorResponse>(text)
+                .map(|error_response| format!("Anthropic error: {}", error_response.error.message))
+                .unwrap_or_else(|_| format!("Anthropic error: {status} {text}"))
+        }));
     }
 
-    let mut partial = stream_partial_message(model);
-    let mut tool_buffers: Vec<Option<String>> = Vec::new();
-    emit_event(
-        events,
-        AssistantMessageEvent::Start {
-            partial: partial.clone(),
-        },
-    );
+    let mut state = AnthropicStreamState::start(model, events);
 
     let mut parser = SseParser::new();
-    let mut buf = [0u8; 8192];
-    loop {
-        let read = response
-            .read(&mut buf)
-            .map_err(|err| format!("Stream read failed: {err}"))?;
-        if read == 0 {
-            break;
-        }
-        let chunk = String::from_utf8_lossy(&buf[..read]);
+    let mut decoder = Utf8Decoder::default();
+    let body = StreamBody::new(response);
+    while let Some(chunk) = body.next_chunk(events)? {
+        request_log::capture(&chunk);
+        let chunk = decoder.decode(&chunk);
         for event in parser.feed(&chunk) {
             let event_name = event.name.unwrap_or_default();
             if event.data == "[DONE]" {
@@ -594,172 +740,252 @@ pub fn stream_anthropic(
                 Ok(value) => value,
                 Err(_) => continue,
             };
-            match event_name.as_str() {
-                "message_start" => {
-                    // Extract initial usage from message_start event
-                    if let Some((input, output, cache_read, cache_write)) =
-                        extract_anthropic_usage(&value, "message")
-                    {
-                        partial.usage.input = input;
-                        partial.usage.output = output;
-                        partial.usage.cache_read = cache_read;
-                        partial.usage.cache_write = cache_write;
-                        partial.usage.total_tokens =
-                            Some(input + output + cache_read + cache_write);
-                        calculate_cost(model, &mut partial.usage);
-                    }
+            if let Some(error_message) = state.handle_event(model, events, &event_name, &value) {
+                return Ok(error_message);
+            }
+        }
+    }
+
+    Ok(state.finish(events))
+}
+
+/// Accumulates an Anthropic Messages event stream into an assistant message, emitting stream
+/// events as it goes. Bedrock delivers the same events inside AWS event-stream frames.
+pub(crate) struct AnthropicStreamState {
+    partial: AssistantMessage,
+    tool_buffers: Vec<Option<String>>,
+}
+
+impl AnthropicStreamState {
+    pub(crate) fn start(model: &RegistryModel, events: &mut StreamEvents) -> Self {
+        let partial = stream_partial_message(model);
+        emit_event(
+            events,
+            AssistantMessageEvent::Start {
+                partial: partial.clone(),
+            },
+        );
+        Self {
+            partial,
+            tool_buffers: Vec::new(),
+        }
+    }
+
+    /// Applies one event. Returns the error message when the stream reports a failure.
+    pub(crate) fn handle_event(
+        &mut self,
+        model: &RegistryModel,
+        events: &mut StreamEvents,
+        event_type: &str,
+        value: &Value,
+    ) -> Option<AssistantMessage> {
+        let Self {
+            partial,
+            tool_buffers,
+        } = self;
+        match event_type {
+            "message_start" => {
+                // Extract initial usage from message_start event
+                if let Some((input, output, cache_read, cache_write)) =
+                    extract_anthropic_usage(value, "message")
+                {
+                    partial.usage.input = input;
+                    partial.usage.output = output;
+                    partial.usage.cache_read = cache_read;
+                    partial.usage.cache_write = cache_write;
+                    partial.usage.total_tokens = Some(input + output + cache_read + cache_write);
+                    calculate_cost(model, &mut partial.usage);
                 }
-                "message_delta" => {
-                    if let Some(reason) = value
-                        .get("delta")
-                        .and_then(|delta| delta.get("stop_reason"))
-                        .and_then(Value::as_str)
-                    {
-                        partial.stop_reason = map_anthropic_stop_reason(reason);
-                    }
-                    // Extract final usage from message_delta event
-                    if let Some((input, output, cache_read, cache_write)) =
-                        extract_anthropic_usage(&value, "")
-                    {
-                        partial.usage.input = input;
-                        partial.usage.output = output;
-                        partial.usage.cache_read = cache_read;
-                        partial.usage.cache_write = cache_write;
-                        partial.usage.total_tokens =
-                            Some(input + output + cache_read + cache_write);
-                        calculate_cost(model, &mut partial.usage);
-                    }
+            }
+            "message_delta" => {
+                if let Some(reason) = value
+                    .get("delta")
+                    .and_then(|delta| delta.get("stop_reason"))
+                    .and_then(Value::as_str)
+                {
+                    partial.stop_reason = map_anthropic_stop_reason(reason);
                 }
-                "content_block_start" => {
-                    let index = value
-                        .get("index")
-                        .and_then(Value::as_u64)
-                        .unwrap_or(partial.content.len() as u64)
-                        as usize;
-                    let block = value.get("content_block").unwrap_or(&Value::Null);
-                    let block_type = block.get("type").and_then(Value::as_str).unwrap_or("");
-                    while partial.content.len() < index {
-                        partial.content.push(ContentBlock::Text {
-                            text: String::new(),
-                            text_signature: None,
-                        });
-                        tool_buffers.push(None);
+                // Extract final usage from message_delta event
+                if let Some((input, output, cache_read, cache_write)) =
+                    extract_anthropic_usage(value, "")
+                {
+                    partial.usage.input = input;
+                    partial.usage.output = output;
+                    partial.usage.cache_read = cache_read;
+                    partial.usage.cache_write = cache_write;
+                    partial.usage.total_tokens = Some(input + output + cache_read + cache_write);
+                    calculate_cost(model, &mut partial.usage);
+                }
+            }
+            "content_block_start" => {
+                let index = value
+                    .get("index")
+                    .and_then(Value::as_u64)
+                    .unwrap_or(partial.content.len() as u64) as usize;
+                let block = value.get("content_block").unwrap_or(&Value::Null);
+                let block_type = block.get("type").and_then(Value::as_str).unwrap_or("");
+                while partial.content.len() < index {
+                    partial.content.push(ContentBlock::Text {
+                        text: String::new(),
+                        text_signature: None,
+                    });
+                    tool_buffers.push(None);
+                }
+                let new_block = match block_type {
+                    "text" => ContentBlock::Text {
+                        text: String::new(),
+                        text_signature: None,
+                    },
+                    "thinking" => ContentBlock::Thinking {
+                        thinking: String::new(),
+                        thinking_signature: None,
+                    },
+                    "tool_use" => ContentBlock::ToolCall {
+                        id: block
+                            .get("id")
+                            .and_then(Value::as_str)
+                            .unwrap_or("")
+                            .to_string(),
+                        name: block
+                            .get("name")
+                            .and_then(Value::as_str)
+                            .unwrap_or("")
+                            .to_string(),
+                        arguments: empty_object(),
+                        thought_signature: None,
+                    },
+                    _ => ContentBlock::Text {
+                        text: String::new(),
+                        text_signature: None,
+                    },
+                };
+                if index >= partial.content.len() {
+                    partial.content.push(new_block);
+                    tool_buffers.push(if block_type == "tool_use" {
+                        Some(String::new())
+                    } else {
+                        None
+                    });
+                } else {
+                    partial.content[index] = new_block;
+                    if block_type == "tool_use" {
+                        if tool_buffers.len() <= index {
+                            tool_buffers.resize(index + 1, None);
+                        }
+                        tool_buffers[index] = Some(String::new());
                     }
-                    let new_block = match block_type {
-                        "text" => ContentBlock::Text {
-                            text: String::new(),
-                            text_signature: None,
-                        },
-                        "thinking" => ContentBlock::Thinking {
-                            thinking: String::new(),
-                            thinking_signature: None,
+                }
+                match block_type {
+                    "text" => emit_event(
+                        events,
+                        AssistantMessageEvent::TextStart {
+                            partial: partial.clone(),
+                            content_index: index,
                         },
-                        "tool_use" => ContentBlock::ToolCall {
-                            id: block
-                                .get("id")
-                                .and_then(Value::as_str)
-                                .unwrap_or("")
-                                .to_string(),
-                            name: block
-                                .get("name")
-                                .and_then(Value::as_str)
-                                .unwrap_or("")
-                                .to_string(),
-                            arguments: empty_object(),
-                            thought_signature: None,
+                    ),
+                    "thinking" => emit_event(
+                        events,
+                        AssistantMessageEvent::ThinkingStart {
+                            partial: partial.clone(),
+                            content_index: index,
                         },
-                        _ => ContentBlock::Text {
-                            text: String::new(),
-                            text_signature: None,
+                    ),
+                    "tool_use" => emit_event(
+                        events,
+                        AssistantMessageEvent::ToolCallStart {
+                            partial: partial.clone(),
+                            content_index: index,
                         },
-                    };
-                    if index >= partial.content.len() {
-                        partial.content.push(new_block);
-                        tool_buffers.push(if block_type == "tool_use" {
-                            Some(String::new())
-                        } else {
-                            None
-                        });
-                    } else {
-                        partial.content[index] = new_block;
-                        if block_type == "tool_use" {
-                            if tool_buffers.len() <= index {
-                                tool_buffers.resize(index + 1, None);
-                            }
-                            tool_buffers[index] = Some(String::new());
+                    ),
+                    _ => {}
+                }
+            }
+            "content_block_delta" => {
+                let index = value.get("index").and_then(Value::as_u64).unwrap_or(0) as usize;
+                let delta = value.get("delta").unwrap_or(&Value::Null);
+                let delta_type = delta.get("type").and_then(Value::as_str).unwrap_or("");
+                match delta_type {
+                    "text_delta" => {
+                        let text = delta.get("text").and_then(Value::as_str).unwrap_or("");
+                        if let Some(ContentBlock::Text { text: current, .. }) =
+                            partial.content.get_mut(index)
+                        {
+                            current.push_str(text);
+                        }
+                        emit_event(
+                            events,
+                            AssistantMessageEvent::TextDelta {
+                                delta: text.to_string(),
+                                partial: partial.clone(),
+                                content_index: index,
+                            },
+                        );
+                    }
+                    "thinking_delta" => {
+                        let chunk = delta.get("thinking").and_then(Value::as_str).unwrap_or("");
+                        if let Some(ContentBlock::Thinking { thinking, .. }) =
+                            partial.content.get_mut(index)
+                        {
+                            thinking.push_str(chunk);
                         }
+                        emit_event(
+                            events,
+                            AssistantMessageEvent::ThinkingDelta {
+                                delta: chunk.to_string(),
+                                partial: partial.clone(),
+                                content_index: index,
+                            },
+                        );
                     }
-                    match block_type {
-                        "text" => emit_event(
+                    "input_json_delta" => {
+                        let chunk = delta
+                            .get("partial_json")
+                            .and_then(Value::as_str)
+                            .unwrap_or("");
+                        if let Some(buffer) = tool_buffers.get_mut(index).and_then(Option::as_mut) {
+                            buffer.push_str(chunk);
+                            let parsed = parse_partial_json(buffer);
+                            if let Some(ContentBlock::ToolCall { arguments, .. }) =
+                                partial.content.get_mut(index)
+                            {
+                                *arguments = parsed;
+                            }
+                        }
+                        emit_event(
                             events,
-                            AssistantMessageEvent::TextStart {
+                            AssistantMessageEvent::ToolCallDelta {
+                                delta: chunk.to_string(),
                                 partial: partial.clone(),
                                 content_index: index,
                             },
-                        ),
-                        "thinking" => emit_event(
+                        );
+                    }
+                    _ => {}
+                }
+            }
+            "content_block_stop" => {
+                let index = value.get("index").and_then(Value::as_u64).unwrap_or(0) as usize;
+                let block = partial.content.get(index).cloned();
+                if let Some(block) = block {
+                    match block {
+                        ContentBlock::Text { .. } => emit_event(
                             events,
-                            AssistantMessageEvent::ThinkingStart {
+                            AssistantMessageEvent::TextEnd {
                                 partial: partial.clone(),
                                 content_index: index,
                             },
                         ),
-                        "tool_use" => emit_event(
+                        ContentBlock::Thinking { .. } => emit_event(
                             events,
-                            AssistantMessageEvent::ToolCallStart {
+                            AssistantMessageEvent::ThinkingEnd {
                                 partial: partial.clone(),
                                 content_index: index,
                             },
                         ),
-                        _ => {}
-                    }
-                }
-                "content_block_delta" => {
-                    let index = value.get("index").and_then(Value::as_u64).unwrap_or(0) as usize;
-                    let delta = value.get("delta").unwrap_or(&Value::Null);
-                    let delta_type = delta.get("type").and_then(Value::as_str).unwrap_or("");
-                    match delta_type {
-                        "text_delta" => {
-                            let text = delta.get("text").and_then(Value::as_str).unwrap_or("");
-                            if let Some(ContentBlock::Text { text: current, .. }) =
-                                partial.content.get_mut(index)
-                            {
-                                current.push_str(text);
-                            }
-                            emit_event(
-                                events,
-                                AssistantMessageEvent::TextDelta {
-                                    delta: text.to_string(),
-                                    partial: partial.clone(),
-                                    content_index: index,
-                                },
-                            );
-                        }
-                        "thinking_delta" => {
-                            let chunk = delta.get("thinking").and_then(Value::as_str).unwrap_or("");
-                            if let Some(ContentBlock::Thinking { thinking, .. }) =
-                                partial.content.get_mut(index)
-                            {
-                                thinking.push_str(chunk);
-                            }
-                            emit_event(
-                                events,
-                                AssistantMessageEvent::ThinkingDelta {
-                                    delta: chunk.to_string(),
-                                    partial: partial.clone(),
-                                    content_index: index,
-                                },
-                            );
-                        }
-                        "input_json_delta" => {
-                            let chunk = delta
-                                .get("partial_json")
-                                .and_then(Value::as_str)
-                                .unwrap_or("");
+                        ContentBlock::ToolCall { .. } => {
                             if let Some(buffer) =
                                 tool_buffers.get_mut(index).and_then(Option::as_mut)
                             {
-                                buffer.push_str(chunk);
                                 let parsed = parse_partial_json(buffer);
                                 if let Some(ContentBlock::ToolCall { arguments, .. }) =
                                     partial.content.get_mut(index)
@@ -769,8 +995,7 @@ pub fn stream_anthropic(
                             }
                             emit_event(
                                 events,
-                                AssistantMessageEvent::ToolCallDelta {
-                                    delta: chunk.to_string(),
+                                AssistantMessageEvent::ToolCallEnd {
                                     partial: partial.clone(),
                                     content_index: index,
                                 },
@@ -779,76 +1004,33 @@ pub fn stream_anthropic(
                         _ => {}
                     }
                 }
-                "content_block_stop" => {
-                    let index = value.get("index").and_then(Value::as_u64).unwrap_or(0) as usize;
-                    let block = partial.content.get(index).cloned();
-                    if let Some(block) = block {
-                        match block {
-                            ContentBlock::Text { .. } => emit_event(
-                                events,
-                                AssistantMessageEvent::TextEnd {
-                                    partial: partial.clone(),
-                                    content_index: index,
-                                },
-                            ),
-                            ContentBlock::Thinking { .. } => emit_event(
-                                events,
-                                AssistantMessageEvent::ThinkingEnd {
-                                    partial: partial.clone(),
-                                    content_index: index,
-                                },
-                            ),
-                            ContentBlock::ToolCall { .. } => {
-                                if let Some(buffer) =
-                                    tool_buffers.get_mut(index).and_then(Option::as_mut)
-                                {
-                                    let parsed = parse_partial_json(buffer);
-                                    if let Some(ContentBlock::ToolCall { arguments, .. }) =
-                                        partial.content.get_mut(index)
-                                    {
-                                        *arguments = parsed;
-                                    }
-                                }
-                                emit_event(
-                                    events,
-                                    AssistantMessageEvent::ToolCallEnd {
-                                        partial: partial.clone(),
-                                        content_index: index,
-                                    },
-                                );
-                            }
-                            _ => {}
-                        }
-                    }
-                }
-                "error" => {
-                    let message = value
-                        .get("error")
-                        .and_then(|error| error.get("message"))
-                        .and_then(Value::as_str)
-                        .unwrap_or("Anthropic stream error");
-                    let error_message = assistant_error_message(model, message);
-                    emit_event(
-                        events,
-                        AssistantMessageEvent::Error {
-                            message: error_message.clone(),
-                        },
-                    );
-                    return Ok(error_message);
-                }
-                _ => {}
             }
+            "error" => {
+                let message = value
+                    .get("error")
+                    .and_then(|error| error.get("message"))
+                    .and_then(Value::as_str)
+                    .unwrap_or("Anthropic stream error");
+                let kind = value.pointer("/error/type").and_then(Value::as_str);
+                let error_message = assistant_error_message(model, message);
+                events.set_error(stream_error(kind.unwrap_or_default(), message));
+                emit_event(
+                    events,
+                    AssistantMessageEvent::Error {
+                        message: error_message.clone(),
+                    },
+                );
+                return Some(error_message);
+            }
+            _ => {}
         }
+        None
     }
 
-    apply_stream_stop_reason(&mut partial);
-    emit_event(
-        events,
-        AssistantMessageEvent::Done {
-            message: partial.clone(),
-        },
-    );
-    Ok(partial)
+    pub(crate) fn finish(mut self, events: &mut StreamEvents) -> AssistantMessage {
+        apply_stream_stop_reason(&mut self.partial);
+        events.finish(self.partial)
+    }
 }
 
 pub fn stream_openai_responses(
@@ -856,7 +1038,18 @@ pub fn stream_openai_responses(
     input: Vec<OpenAIInputItem>,
     options: OpenAICallOptions<'_>,
     events: &mut StreamEvents,
-) -> Result<AssistantMessage, String> {
+) -> Result<AssistantMessage, Error> {
+    let result = send_openai_responses_stream(model, input, options, events);
+    request_log::finish_message(&result);
+    result
+}
+
+fn send_openai_responses_stream(
+    model: &RegistryModel,
+    input: Vec<OpenAIInputItem>,
+    options: OpenAICallOptions<'_>,
+    events: &mut StreamEvents,
+) -> Result<AssistantMessage, Error> {
     let request = OpenAIRequest {
         model: options.model.to_string(),
         input,
@@ -866,25 +1059,28 @@ pub fn stream_openai_responses(
             Some(options.tools.to_vec())
         },
         stream: Some(true),
+        seed: options.seed,
     };
 
-    let headers = build_openai_headers(options.api_key, options.extra_headers)?;
+    let headers =
+        build_openai_headers(options.api_key, options.extra_headers).map_err(Error::Request)?;
     let endpoint = format!("{}/responses", options.base_url.trim_end_matches('/'));
-    let client = Client::new();
-    let mut response = client
-        .post(endpoint)
-        .headers(headers)
-        .json(&request)
-        .send()
-        .map_err(|err| format!("Request failed: {err}"))?;
-
-    let status = response.status();
-    if !status.is_success() {
-        let text = response.text().unwrap_or_default();
-        if let Ok(error_response) = serde_json::from_str::<OpenAIErrorResponse>(&text) {
-            return Err(format!("OpenAI error: {}", error_response.error.message));
-        }
-        return Err(format!("OpenAI error: {} {}", status.as_u16(), text));
+    let response = post_json(
+        &model.provider,
+        &model.api,
+        options.model,
+        &endpoint,
+        headers,
+        &request,
+        options.request_hook,
+    )?;
+
+    if !response.status().is_success() {
+        return Err(error_response(response, |status, text| {
+            serde_json::from_str::<OpenAIErrorResponse>(text)
+                .map(|error_response| format!("OpenAI error: {}", error_response.error.message))
+                .unwrap_or_else(|_| format!("OpenAI error: {status} {text}"))
+        }));
     }
 
     let mut partial = stream_partial_message(model);
@@ -899,15 +1095,11 @@ pub fn stream_openai_responses(
     );
 
     let mut parser = SseParser::new();
-    let mut buf = [0u8; 8192];
-    loop {
-        let read = response
-            .read(&mut buf)
-            .map_err(|err| format!("Stream read failed: {err}"))?;
-        if read == 0 {
-            break;
-        }
-        let chunk = String::from_utf8_lossy(&buf[..read]);
+    let mut decoder = Utf8Decoder::default();
+    let body = StreamBody::new(response);
+    while let Some(chunk) = body.next_chunk(events)? {
+        request_log::capture(&chunk);
+        let chunk = decoder.decode(&chunk);
         for event in parser.feed(&chunk) {
             let event_name = event.name.unwrap_or_default();
             if event.data == "[DONE]" {
@@ -1112,7 +1304,9 @@ pub fn stream_openai_responses(
                         .and_then(|error| error.get("message"))
                         .and_then(Value::as_str)
                         .unwrap_or("OpenAI stream error");
+                    let code = value.pointer("/error/code").and_then(Value::as_str);
                     let error_message = assistant_error_message(model, message);
+                    events.set_error(stream_error(code.unwrap_or_default(), message));
                     emit_event(
                         events,
                         AssistantMessageEvent::Error {
@@ -1130,13 +1324,7 @@ pub fn stream_openai_responses(
         partial.stop_reason = reason;
     }
     apply_stream_stop_reason(&mut partial);
-    emit_event(
-        events,
-        AssistantMessageEvent::Done {
-            message: partial.clone(),
-        },
-    );
-    Ok(partial)
+    Ok(events.finish(partial))
 }
 
 pub fn build_anthropic_messages(context: &LlmContext) -> Vec<AnthropicMessage> {
@@ -1217,6 +1405,9 @@ fn assistant_blocks_to_anthropic_blocks(blocks: &[ContentBlock]) -> Vec<Anthropi
                     data: data.clone(),
                 },
             },
+            ContentBlock::Diff { .. } => AnthropicContentBlock::Text {
+                text: block.as_text().unwrap_or_default(),
+            },
         })
         .collect()
 }
@@ -1225,11 +1416,10 @@ fn tool_result_to_anthropic_blocks(result: &ToolResultMessage) -> Vec<AnthropicC
     let content = result
         .content
         .iter()
-        .filter_map(|block| match block {
-            ContentBlock::Text { text, .. } => {
-                Some(AnthropicToolResultContent::Text { text: text.clone() })
-            }
-            _ => None,
+        .filter_map(|block| {
+            block
+                .as_text()
+                .map(|text| AnthropicToolResultContent::Text { text })
         })
         .collect::<Vec<_>>();
 
@@ -1294,6 +1484,23 @@ pub fn assistant_error_message(model: &RegistryModel, message: &str) -> Assistan
     }
 }
 
+/// Error for a failure the provider reported inside the stream, classified by the error type
+/// or code it sent along with the message.
+pub(crate) fn stream_error(kind: &str, message: &str) -> Error {
+    let message = message.to_string();
+    match kind {
+        "rate_limit_error" | "rate_limit_exceeded" | "throttlingException" => Error::RateLimited {
+            retry_after: None,
+            message,
+        },
+        "authentication_error" | "permission_error" | "invalid_api_key" => Error::Auth { message },
+        _ => Error::Provider {
+            status: None,
+            message,
+        },
+    }
+}
+
 fn empty_usage() -> Usage {
     Usage {
         input: 0,
@@ -1476,7 +1683,7 @@ fn openai_assistant_items(assistant: &AssistantMessage) -> Vec<OpenAIInputItem>
                     arguments: openai_arguments_string(arguments),
                 });
             }
-            ContentBlock::Image { .. } => {}
+            ContentBlock::Image { .. } | ContentBlock::Diff { .. } => {}
         }
     }
 
@@ -1545,13 +1752,11 @@ fn split_openai_tool_call_id(value: &str) -> (String, String) {
 }
 
 fn tool_result_text(content: &[ContentBlock]) -> String {
-    let mut text = String::new();
-    for block in content {
-        if let ContentBlock::Text { text: chunk, .. } = block {
-            text.push_str(chunk);
-        }
-    }
-    text
+    content
+        .iter()
+        .filter_map(ContentBlock::as_text)
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 pub fn openai_assistant_message_from_response(
@@ -1607,7 +1812,7 @@ pub fn openai_assistant_message_from_response(
 
 #[cfg(test)]
 mod tests {
-    use super::SseParser;
+    use super::{SseParser, Utf8Decoder};
 
     #[test]
     fn sse_parser_handles_complete_event() {
@@ -1628,4 +1833,23 @@ mod tests {
         assert_eq!(events[0].name.as_deref(), Some("chunk"));
         assert_eq!(events[0].data, "part1");
     }
+
+    #[test]
+    fn utf8_decoder_holds_back_a_character_split_across_chunks() {
+        let bytes = "data: \"héllo ✓\"\n\n".as_bytes();
+        let mut decoder = Utf8Decoder::default();
+        let mut parser = SseParser::new();
+        let mut data = Vec::new();
+        // Every split point, including ones inside `é` and `✓`.
+        for split in 0..=bytes.len() {
+            let first = decoder.decode(&bytes[..split]);
+            let second = decoder.decode(&bytes[split..]);
+            assert!(!first.contains('\u{FFFD}') && !second.contains('\u{FFFD}'));
+            data.extend(parser.feed(&first).into_iter().map(|event| event.data));
+            data.extend(parser.feed(&second).into_iter().map(|event| event.data));
+        }
+        assert_eq!(data.len(), bytes.len() + 1);
+        assert!(data.iter().all(|data| data == "\"héllo ✓\""));
+        assert_eq!(decoder.decode(&[0xff, b'a']), "\u{FFFD}a");
+    }
 }
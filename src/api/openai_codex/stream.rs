@@ -13,16 +13,17 @@ use super::response_handler::{parse_codex_error, parse_sse_chunk};
 
 use crate::agent::{LlmContext, StreamEvents};
 use crate::ai::AssistantMessageEvent;
+use crate::api::request_hook::{post_json, SharedRequestHook};
+use crate::api::{request_log, retry_after, stream_error, StreamBody, Utf8Decoder};
 use crate::coding_agent::Model as RegistryModel;
 use crate::core::messages::{AssistantMessage, ContentBlock, Cost, Usage};
+use crate::error::Error;
 
 use base64::Engine;
-use reqwest::blocking::Client;
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Map, Value};
 use std::collections::HashMap;
-use std::io::Read;
 
 /// Options for the OpenAI Codex streaming call
 #[derive(Debug, Clone, Default)]
@@ -39,6 +40,8 @@ pub struct CodexStreamOptions {
     pub codex_mode: Option<bool>,
     /// Extra headers to send with the request
     pub extra_headers: Option<HashMap<String, String>>,
+    /// Hook that can rewrite the request before it is sent
+    pub request_hook: Option<SharedRequestHook>,
 }
 
 /// Tool definition for the Codex API
@@ -389,7 +392,7 @@ pub fn codex_context_to_input_items(model: &RegistryModel, context: &LlmContext)
                                 "arguments": serde_json::to_string(arguments).unwrap_or_else(|_| "{}".to_string())
                             }));
                         }
-                        ContentBlock::Image { .. } => {
+                        ContentBlock::Image { .. } | ContentBlock::Diff { .. } => {
                             // Images in assistant messages are not supported
                         }
                     }
@@ -416,10 +419,7 @@ pub fn codex_context_to_input_items(model: &RegistryModel, context: &LlmContext)
                 let text_result: String = result
                     .content
                     .iter()
-                    .filter_map(|c| match c {
-                        ContentBlock::Text { text, .. } => Some(text.clone()),
-                        _ => None,
-                    })
+                    .filter_map(ContentBlock::as_text)
                     .collect::<Vec<_>>()
                     .join("\n");
 
@@ -479,9 +479,22 @@ pub fn stream_openai_codex_responses(
     tools: &[CodexTool],
     options: CodexStreamOptions,
     events: &mut StreamEvents,
-) -> Result<AssistantMessage, String> {
+) -> Result<AssistantMessage, Error> {
+    let result = send_codex_stream(model, context, api_key, tools, options, events);
+    request_log::finish_message(&result);
+    result
+}
+
+fn send_codex_stream(
+    model: &RegistryModel,
+    context: &LlmContext,
+    api_key: &str,
+    tools: &[CodexTool],
+    options: CodexStreamOptions,
+    events: &mut StreamEvents,
+) -> Result<AssistantMessage, Error> {
     // Extract account ID from JWT token
-    let account_id = get_account_id(api_key)?;
+    let account_id = get_account_id(api_key).map_err(Error::Request)?;
 
     // Build the base URL
     let base_url = if model.base_url.is_empty() {
@@ -564,23 +577,30 @@ pub fn stream_openai_codex_responses(
         &account_id,
         api_key,
         body.prompt_cache_key.as_deref(),
-    )?;
+    )
+    .map_err(Error::Request)?;
 
     // Make the request
-    let client = Client::new();
-    let mut response = client
-        .post(&url)
-        .headers(header_map)
-        .json(&body)
-        .send()
-        .map_err(|e| format!("Request failed: {}", e))?;
+    let response = post_json(
+        &model.provider,
+        &model.api,
+        &model.id,
+        &url,
+        header_map,
+        &body,
+        options.request_hook.as_deref(),
+    )?;
 
     let status = response.status();
     if !status.is_success() {
         let response_headers = response.headers().clone();
         let text = response.text().unwrap_or_default();
         let error_info = parse_codex_error(status.as_u16(), &response_headers, &text);
-        return Err(error_info.friendly_message.unwrap_or(error_info.message));
+        return Err(Error::from_status(
+            status.as_u16(),
+            retry_after(&response_headers),
+            error_info.friendly_message.unwrap_or(error_info.message),
+        ));
     }
 
     // Initialize the partial message
@@ -598,18 +618,13 @@ pub fn stream_openai_codex_responses(
 
     // Parse the SSE stream
     let mut buffer = String::new();
-    let mut buf = [0u8; 8192];
-
-    loop {
-        let read = response
-            .read(&mut buf)
-            .map_err(|e| format!("Stream read failed: {}", e))?;
+    let mut decoder = Utf8Decoder::default();
 
-        if read == 0 {
-            break;
-        }
+    let body = StreamBody::new(response);
+    while let Some(chunk) = body.next_chunk(events)? {
+        request_log::capture(&chunk);
 
-        let chunk = String::from_utf8_lossy(&buf[..read]);
+        let chunk = decoder.decode(&chunk);
         buffer.push_str(&chunk);
 
         // Handle CRLF normalization
@@ -952,6 +967,7 @@ pub fn stream_openai_codex_responses(
                     };
 
                     let error_message = assistant_error_message(model, &error_msg);
+                    events.set_error(stream_error(code, &error_msg));
                     emit_event(
                         events,
                         AssistantMessageEvent::Error {
@@ -962,6 +978,7 @@ pub fn stream_openai_codex_responses(
                 }
                 "response.failed" => {
                     let error_message = assistant_error_message(model, "Unknown error");
+                    events.set_error(stream_error("", "Unknown error"));
                     emit_event(
                         events,
                         AssistantMessageEvent::Error {
@@ -993,14 +1010,7 @@ pub fn stream_openai_codex_responses(
         partial.stop_reason = "toolUse".to_string();
     }
 
-    emit_event(
-        events,
-        AssistantMessageEvent::Done {
-            message: partial.clone(),
-        },
-    );
-
-    Ok(partial)
+    Ok(events.finish(partial))
 }
 
 #[cfg(test)]
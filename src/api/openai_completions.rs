@@ -0,0 +1,638 @@
+// OpenAI Chat Completions provider (`openai-completions`): the OpenAI-compatible
+// `{baseUrl}/chat/completions` endpoint served by xAI, Mistral and most hosted providers.
+// Providers differ in which request fields they accept; see `CompletionsCompat`.
+
+use super::{
+    apply_stream_stop_reason, calculate_cost, empty_object, parse_partial_json,
+    split_openai_tool_call_id, stream_partial_message, tool_result_text, SseParser,
+};
+use crate::agent::{AgentMessage, LlmContext, StreamEvents};
+use crate::ai::AssistantMessageEvent;
+use crate::api::request_hook::{post_json, RequestHook};
+use crate::api::{
+    assistant_error_message, error_response, request_log, stream_error, StreamBody, Utf8Decoder,
+};
+use crate::coding_agent::Model as RegistryModel;
+use crate::core::messages::{AssistantMessage, ContentBlock, UserContent};
+use crate::error::Error;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+pub const OPENAI_COMPLETIONS_API: &str = "openai-completions";
+
+/// Sent between a tool result and a user message for providers that reject that order.
+const TOOL_RESULT_BRIDGE: &str = "I have processed the tool results.";
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CompletionsTool {
+    pub name: String,
+    pub description: String,
+    pub parameters: Value,
+}
+
+pub struct CompletionsCallOptions<'a> {
+    pub model: &'a str,
+    pub api_key: &'a str,
+    pub tools: &'a [CompletionsTool],
+    pub base_url: &'a str,
+    pub extra_headers: Option<&'a HashMap<String, String>>,
+    pub system: Option<&'a str>,
+    pub seed: Option<u64>,
+    pub request_hook: Option<&'a dyn RequestHook>,
+}
+
+/// Request differences between OpenAI-compatible providers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompletionsCompat {
+    /// System prompts go in a `developer` message for reasoning models.
+    pub supports_developer_role: bool,
+    pub supports_store: bool,
+    /// Usage is only reported when asked for with `stream_options.include_usage`.
+    pub supports_usage_option: bool,
+    /// `max_completion_tokens` or `max_tokens`.
+    pub max_tokens_field: &'static str,
+    /// `seed` or Mistral's `random_seed`.
+    pub seed_field: &'static str,
+    /// Tool messages carry the tool `name`.
+    pub requires_tool_result_name: bool,
+    /// A user message may not directly follow a tool message.
+    pub requires_assistant_after_tool_result: bool,
+    /// Tool call ids are exactly nine alphanumeric characters.
+    pub requires_mistral_tool_ids: bool,
+}
+
+impl CompletionsCompat {
+    pub fn for_model(model: &RegistryModel) -> Self {
+        let is_provider = |provider: &str, host: &str| {
+            model.provider == provider || model.base_url.contains(host)
+        };
+        let openai = Self {
+            supports_developer_role: true,
+            supports_store: true,
+            supports_usage_option: true,
+            max_tokens_field: "max_completion_tokens",
+            seed_field: "seed",
+            requires_tool_result_name: false,
+            requires_assistant_after_tool_result: false,
+            requires_mistral_tool_ids: false,
+        };
+        if is_provider("mistral", "api.mistral.ai") {
+            Self {
+                supports_developer_role: false,
+                supports_store: false,
+                supports_usage_option: false,
+                max_tokens_field: "max_tokens",
+                seed_field: "random_seed",
+                requires_tool_result_name: true,
+                requires_assistant_after_tool_result: true,
+                requires_mistral_tool_ids: true,
+            }
+        } else if is_provider("openai", "api.openai.com") {
+            openai
+        } else {
+            Self {
+                supports_developer_role: false,
+                supports_store: false,
+                max_tokens_field: "max_tokens",
+                ..openai
+            }
+        }
+    }
+}
+
+fn build_headers(
+    api_key: &str,
+    extra_headers: Option<&HashMap<String, String>>,
+) -> Result<HeaderMap, String> {
+    let mut headers = HeaderMap::new();
+    headers.insert("content-type", HeaderValue::from_static("application/json"));
+    headers.insert("accept", HeaderValue::from_static("text/event-stream"));
+    let value = HeaderValue::from_str(&format!("Bearer {api_key}"))
+        .map_err(|err| format!("Invalid API key: {err}"))?;
+    headers.insert("authorization", value);
+    if let Some(extra) = extra_headers {
+        for (key, value) in extra {
+            let header_name = HeaderName::from_bytes(key.as_bytes())
+                .map_err(|err| format!("Invalid header name \"{key}\": {err}"))?;
+            let header_value = HeaderValue::from_str(value)
+                .map_err(|err| format!("Invalid header value: {err}"))?;
+            headers.insert(header_name, header_value);
+        }
+    }
+    Ok(headers)
+}
+
+pub fn build_completions_request(
+    model: &RegistryModel,
+    context: &LlmContext,
+    options: &CompletionsCallOptions<'_>,
+    compat: &CompletionsCompat,
+) -> Value {
+    let mut request = Map::new();
+    request.insert("model".to_string(), json!(options.model));
+    request.insert(
+        "messages".to_string(),
+        Value::Array(build_completions_messages(
+            model,
+            context,
+            options.system,
+            compat,
+        )),
+    );
+    request.insert("stream".to_string(), json!(true));
+    if compat.supports_usage_option {
+        request.insert(
+            "stream_options".to_string(),
+            json!({ "include_usage": true }),
+        );
+    }
+    if compat.supports_store {
+        request.insert("store".to_string(), json!(false));
+    }
+    if model.max_tokens > 0 {
+        request.insert(compat.max_tokens_field.to_string(), json!(model.max_tokens));
+    }
+    if let Some(seed) = options.seed {
+        request.insert(compat.seed_field.to_string(), json!(seed));
+    }
+    if !options.tools.is_empty() {
+        let tools = options
+            .tools
+            .iter()
+            .map(|tool| json!({ "type": "function", "function": tool }))
+            .collect();
+        request.insert("tools".to_string(), Value::Array(tools));
+    }
+    Value::Object(request)
+}
+
+fn build_completions_messages(
+    model: &RegistryModel,
+    context: &LlmContext,
+    system: Option<&str>,
+    compat: &CompletionsCompat,
+) -> Vec<Value> {
+    let supports_images = model.input.iter().any(|entry| entry == "image");
+    let mut messages = Vec::new();
+    if let Some(system) = system.filter(|text| !text.trim().is_empty()) {
+        let role = if compat.supports_developer_role && model.reasoning {
+            "developer"
+        } else {
+            "system"
+        };
+        messages.push(json!({ "role": role, "content": system }));
+    }
+
+    let mut pending_images = Vec::new();
+    for message in &context.messages {
+        if !matches!(message, AgentMessage::ToolResult(_)) {
+            push_tool_images(&mut messages, &mut pending_images, compat);
+        }
+        match message {
+            AgentMessage::User(user) => {
+                let content = match &user.content {
+                    UserContent::Text(text) if text.trim().is_empty() => continue,
+                    UserContent::Text(text) => json!(text),
+                    UserContent::Blocks(blocks) => {
+                        let parts = blocks
+                            .iter()
+                            .filter_map(|block| content_part(block, supports_images))
+                            .collect::<Vec<_>>();
+                        if parts.is_empty() {
+                            continue;
+                        }
+                        Value::Array(parts)
+                    }
+                };
+                push_user(&mut messages, content, compat);
+            }
+            AgentMessage::Assistant(assistant) => {
+                let text = assistant
+                    .content
+                    .iter()
+                    .filter_map(|block| match block {
+                        ContentBlock::Text { text, .. } => Some(text.as_str()),
+                        _ => None,
+                    })
+                    .collect::<String>();
+                let tool_calls = assistant
+                    .content
+                    .iter()
+                    .filter_map(|block| match block {
+                        ContentBlock::ToolCall {
+                            id,
+                            name,
+                            arguments,
+                            ..
+                        } => Some(json!({
+                            "id": tool_call_id(id, compat),
+                            "type": "function",
+                            "function": { "name": name, "arguments": arguments.to_string() },
+                        })),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                if text.is_empty() && tool_calls.is_empty() {
+                    continue;
+                }
+                let mut entry = Map::new();
+                entry.insert("role".to_string(), json!("assistant"));
+                entry.insert(
+                    "content".to_string(),
+                    if text.is_empty() {
+                        Value::Null
+                    } else {
+                        json!(text)
+                    },
+                );
+                if !tool_calls.is_empty() {
+                    entry.insert("tool_calls".to_string(), Value::Array(tool_calls));
+                }
+                messages.push(Value::Object(entry));
+            }
+            AgentMessage::ToolResult(result) => {
+                let mut entry = Map::new();
+                entry.insert("role".to_string(), json!("tool"));
+                entry.insert(
+                    "tool_call_id".to_string(),
+                    json!(tool_call_id(&result.tool_call_id, compat)),
+                );
+                entry.insert(
+                    "content".to_string(),
+                    json!(tool_result_text(&result.content)),
+                );
+                if compat.requires_tool_result_name {
+                    entry.insert("name".to_string(), json!(result.tool_name));
+                }
+                messages.push(Value::Object(entry));
+                if supports_images {
+                    pending_images.extend(
+                        result
+                            .content
+                            .iter()
+                            .filter(|block| matches!(block, ContentBlock::Image { .. }))
+                            .filter_map(|block| content_part(block, true)),
+                    );
+                }
+            }
+            AgentMessage::Custom(_) => {}
+        }
+    }
+    push_tool_images(&mut messages, &mut pending_images, compat);
+    messages
+}
+
+/// Tool messages only hold text, so images from tool results follow in a user message.
+fn push_tool_images(
+    messages: &mut Vec<Value>,
+    images: &mut Vec<Value>,
+    compat: &CompletionsCompat,
+) {
+    if images.is_empty() {
+        return;
+    }
+    let mut content =
+        vec![json!({ "type": "text", "text": "Images from the tool results above:" })];
+    content.append(images);
+    push_user(messages, Value::Array(content), compat);
+}
+
+fn push_user(messages: &mut Vec<Value>, content: Value, compat: &CompletionsCompat) {
+    let after_tool = messages
+        .last()
+        .is_some_and(|message| message["role"] == "tool");
+    if after_tool && compat.requires_assistant_after_tool_result {
+        messages.push(json!({ "role": "assistant", "content": TOOL_RESULT_BRIDGE }));
+    }
+    messages.push(json!({ "role": "user", "content": content }));
+}
+
+fn content_part(block: &ContentBlock, supports_images: bool) -> Option<Value> {
+    match block {
+        ContentBlock::Text { text, .. } => Some(json!({ "type": "text", "text": text })),
+        ContentBlock::Image { data, mime_type } if supports_images => Some(json!({
+            "type": "image_url",
+            "image_url": { "url": format!("data:{mime_type};base64,{data}") },
+        })),
+        _ => None,
+    }
+}
+
+/// Tool call ids as this provider accepts them. Ids from the Responses API
+/// (`call_id|item_id`) keep their call id; Mistral ids are derived from a hash, so a call and
+/// its result still match.
+fn tool_call_id(id: &str, compat: &CompletionsCompat) -> String {
+    let (call_id, _) = split_openai_tool_call_id(id);
+    if !compat.requires_mistral_tool_ids {
+        return call_id;
+    }
+    if call_id.len() == 9 && call_id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return call_id;
+    }
+    hex::encode(Sha256::digest(call_id.as_bytes()))[..9].to_string()
+}
+
+fn map_finish_reason(reason: &str) -> String {
+    match reason {
+        "length" => "length",
+        "tool_calls" | "function_call" => "toolUse",
+        "content_filter" => "error",
+        _ => "stop",
+    }
+    .to_string()
+}
+
+/// The message of an error response body: `{"error": {"message"}}`, `{"error": "..."}`,
+/// `{"message"}` or `{"detail"}`, else the body itself.
+fn error_text(body: &str) -> String {
+    let Ok(value) = serde_json::from_str::<Value>(body) else {
+        return body.to_string();
+    };
+    let message = [
+        value.pointer("/error/message"),
+        value.get("error"),
+        value.get("message"),
+        value.get("detail"),
+    ]
+    .into_iter()
+    .flatten()
+    .find_map(Value::as_str)
+    .map(str::to_string);
+    message.unwrap_or_else(|| body.to_string())
+}
+
+pub fn stream_openai_completions(
+    model: &RegistryModel,
+    context: &LlmContext,
+    options: CompletionsCallOptions<'_>,
+    events: &mut StreamEvents,
+) -> Result<AssistantMessage, Error> {
+    let result = send_completions_stream(model, context, options, events);
+    request_log::finish_message(&result);
+    result
+}
+
+fn send_completions_stream(
+    model: &RegistryModel,
+    context: &LlmContext,
+    options: CompletionsCallOptions<'_>,
+    events: &mut StreamEvents,
+) -> Result<AssistantMessage, Error> {
+    let compat = CompletionsCompat::for_model(model);
+    let request = build_completions_request(model, context, &options, &compat);
+    let headers = build_headers(options.api_key, options.extra_headers).map_err(Error::Request)?;
+    let endpoint = format!(
+        "{}/chat/completions",
+        options.base_url.trim_end_matches('/')
+    );
+    let response = post_json(
+        &model.provider,
+        &model.api,
+        options.model,
+        &endpoint,
+        headers,
+        &request,
+        options.request_hook,
+    )?;
+
+    if !response.status().is_success() {
+        return Err(error_response(response, |status, text| {
+            format!(
+                "{} API error ({status}): {}",
+                model.provider,
+                error_text(text)
+            )
+        }));
+    }
+
+    let mut stream = CompletionsStream {
+        partial: stream_partial_message(model),
+        current: None,
+        tool_blocks: HashMap::new(),
+        tool_buffers: HashMap::new(),
+    };
+    events.emit(AssistantMessageEvent::Start {
+        partial: stream.partial.clone(),
+    });
+
+    let mut parser = SseParser::new();
+    let mut decoder = Utf8Decoder::default();
+    let body = StreamBody::new(response);
+    while let Some(chunk) = body.next_chunk(events)? {
+        request_log::capture(&chunk);
+        let chunk = decoder.decode(&chunk);
+        for event in parser.feed(&chunk) {
+            if event.data == "[DONE]" {
+                continue;
+            }
+            let Ok(value) = serde_json::from_str::<Value>(&event.data) else {
+                continue;
+            };
+            if value.get("error").is_some() {
+                let message = error_text(&event.data);
+                let kind = ["/error/code", "/error/type"]
+                    .into_iter()
+                    .find_map(|pointer| value.pointer(pointer)?.as_str())
+                    .unwrap_or_default();
+                let error_message = assistant_error_message(model, &message);
+                events.set_error(stream_error(kind, &message));
+                events.emit(AssistantMessageEvent::Error {
+                    message: error_message.clone(),
+                });
+                return Ok(error_message);
+            }
+            stream.handle_chunk(model, events, &value);
+        }
+    }
+
+    stream.close_current(events);
+    apply_stream_stop_reason(&mut stream.partial);
+    Ok(events.finish(stream.partial))
+}
+
+struct CompletionsStream {
+    partial: AssistantMessage,
+    /// Content index of the block receiving deltas.
+    current: Option<usize>,
+    /// Content index for each streamed tool call index.
+    tool_blocks: HashMap<u64, usize>,
+    tool_buffers: HashMap<usize, String>,
+}
+
+impl CompletionsStream {
+    fn handle_chunk(&mut self, model: &RegistryModel, events: &mut StreamEvents, value: &Value) {
+        if let Some(usage) = value.get("usage").filter(|usage| usage.is_object()) {
+            let count = |pointer: &str| usage.pointer(pointer).and_then(Value::as_i64);
+            let cached = count("/prompt_tokens_details/cached_tokens").unwrap_or(0);
+            let prompt = count("/prompt_tokens").unwrap_or(0);
+            let usage = &mut self.partial.usage;
+            usage.input = (prompt - cached).max(0);
+            usage.output = count("/completion_tokens").unwrap_or(0);
+            usage.cache_read = cached;
+            usage.total_tokens = Some(usage.input + usage.output + usage.cache_read);
+            calculate_cost(model, usage);
+        }
+        let Some(choice) = value.pointer("/choices/0") else {
+            return;
+        };
+        let delta = choice.get("delta").unwrap_or(&Value::Null);
+        for key in ["reasoning_content", "reasoning"] {
+            if let Some(text) = delta.get(key).and_then(Value::as_str) {
+                if !text.is_empty() {
+                    self.push_thinking(events, text);
+                }
+            }
+        }
+        if let Some(text) = delta.get("content").and_then(Value::as_str) {
+            if !text.is_empty() {
+                self.push_text(events, text);
+            }
+        }
+        if let Some(tool_calls) = delta.get("tool_calls").and_then(Value::as_array) {
+            for tool_call in tool_calls {
+                self.push_tool_call(events, tool_call);
+            }
+        }
+        if let Some(reason) = choice.get("finish_reason").and_then(Value::as_str) {
+            self.partial.stop_reason = map_finish_reason(reason);
+        }
+    }
+
+    fn push_text(&mut self, events: &mut StreamEvents, text: &str) {
+        let index = match self.current {
+            Some(index) if matches!(self.partial.content[index], ContentBlock::Text { .. }) => {
+                index
+            }
+            _ => self.open(
+                events,
+                ContentBlock::Text {
+                    text: String::new(),
+                    text_signature: None,
+                },
+            ),
+        };
+        if let ContentBlock::Text { text: current, .. } = &mut self.partial.content[index] {
+            current.push_str(text);
+        }
+        events.emit(AssistantMessageEvent::TextDelta {
+            delta: text.to_string(),
+            partial: self.partial.clone(),
+            content_index: index,
+        });
+    }
+
+    fn push_thinking(&mut self, events: &mut StreamEvents, text: &str) {
+        let index = match self.current {
+            Some(index) if matches!(self.partial.content[index], ContentBlock::Thinking { .. }) => {
+                index
+            }
+            _ => self.open(
+                events,
+                ContentBlock::Thinking {
+                    thinking: String::new(),
+                    thinking_signature: None,
+                },
+            ),
+        };
+        if let ContentBlock::Thinking { thinking, .. } = &mut self.partial.content[index] {
+            thinking.push_str(text);
+        }
+        events.emit(AssistantMessageEvent::ThinkingDelta {
+            delta: text.to_string(),
+            partial: self.partial.clone(),
+            content_index: index,
+        });
+    }
+
+    fn push_tool_call(&mut self, events: &mut StreamEvents, tool_call: &Value) {
+        let stream_index = tool_call.get("index").and_then(Value::as_u64).unwrap_or(0);
+        let index = match self.tool_blocks.get(&stream_index) {
+            Some(index) => *index,
+            None => {
+                let function = tool_call.get("function").unwrap_or(&Value::Null);
+                let index = self.open(
+                    events,
+                    ContentBlock::ToolCall {
+                        id: tool_call
+                            .get("id")
+                            .and_then(Value::as_str)
+                            .unwrap_or("")
+                            .to_string(),
+                        name: function
+                            .get("name")
+                            .and_then(Value::as_str)
+                            .unwrap_or("")
+                            .to_string(),
+                        arguments: empty_object(),
+                        thought_signature: None,
+                    },
+                );
+                self.tool_blocks.insert(stream_index, index);
+                index
+            }
+        };
+        let chunk = tool_call
+            .pointer("/function/arguments")
+            .and_then(Value::as_str)
+            .unwrap_or("");
+        let buffer = self.tool_buffers.entry(index).or_default();
+        buffer.push_str(chunk);
+        let parsed = parse_partial_json(buffer);
+        if let ContentBlock::ToolCall { arguments, .. } = &mut self.partial.content[index] {
+            *arguments = parsed;
+        }
+        events.emit(AssistantMessageEvent::ToolCallDelta {
+            delta: chunk.to_string(),
+            partial: self.partial.clone(),
+            content_index: index,
+        });
+    }
+
+    /// Ends the current block and starts `block`, returning its index.
+    fn open(&mut self, events: &mut StreamEvents, block: ContentBlock) -> usize {
+        self.close_current(events);
+        let index = self.partial.content.len();
+        self.partial.content.push(block);
+        self.current = Some(index);
+        let partial = self.partial.clone();
+        events.emit(match &self.partial.content[index] {
+            ContentBlock::Thinking { .. } => AssistantMessageEvent::ThinkingStart {
+                partial,
+                content_index: index,
+            },
+            ContentBlock::ToolCall { .. } => AssistantMessageEvent::ToolCallStart {
+                partial,
+                content_index: index,
+            },
+            _ => AssistantMessageEvent::TextStart {
+                partial,
+                content_index: index,
+            },
+        });
+        index
+    }
+
+    fn close_current(&mut self, events: &mut StreamEvents) {
+        let Some(index) = self.current.take() else {
+            return;
+        };
+        let partial = self.partial.clone();
+        events.emit(match &self.partial.content[index] {
+            ContentBlock::Thinking { .. } => AssistantMessageEvent::ThinkingEnd {
+                partial,
+                content_index: index,
+            },
+            ContentBlock::ToolCall { .. } => AssistantMessageEvent::ToolCallEnd {
+                partial,
+                content_index: index,
+            },
+            _ => AssistantMessageEvent::TextEnd {
+                partial,
+                content_index: index,
+            },
+        });
+    }
+}
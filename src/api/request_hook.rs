@@ -0,0 +1,158 @@
+//! Per-request hook for rewriting outgoing provider requests before they are sent, e.g. to
+//! add HMAC signatures or tenant headers, or to point the request at a custom gateway.
+
+use super::request_log;
+use crate::error::Error;
+use reqwest::blocking::{Client, Response};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Deref;
+use std::rc::Rc;
+
+/// An outgoing provider request as seen by a [`RequestHook`]. Header names are lowercase.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderRequest {
+    pub provider: String,
+    pub api: String,
+    pub model: String,
+    pub method: String,
+    pub url: String,
+    pub headers: BTreeMap<String, String>,
+    /// Serialized JSON body. Hooks that sign the request should sign these exact bytes.
+    pub body: String,
+}
+
+pub trait RequestHook {
+    /// Mutate `request` in place. An error aborts the request.
+    fn on_request(&self, request: &mut ProviderRequest) -> Result<(), String>;
+}
+
+impl<F> RequestHook for F
+where
+    F: Fn(&mut ProviderRequest) -> Result<(), String>,
+{
+    fn on_request(&self, request: &mut ProviderRequest) -> Result<(), String> {
+        self(request)
+    }
+}
+
+/// Cloneable handle to a hook, for call options that own their fields.
+#[derive(Clone)]
+pub struct SharedRequestHook(Rc<dyn RequestHook>);
+
+impl SharedRequestHook {
+    pub fn new(hook: impl RequestHook + 'static) -> Self {
+        Self(Rc::new(hook))
+    }
+}
+
+impl Deref for SharedRequestHook {
+    type Target = dyn RequestHook;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
+
+impl fmt::Debug for SharedRequestHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedRequestHook")
+    }
+}
+
+/// POST `body` as JSON to `url`, letting `hook` rewrite the request first.
+pub(crate) fn post_json<T: Serialize>(
+    provider: &str,
+    api: &str,
+    model: &str,
+    url: &str,
+    headers: HeaderMap,
+    body: &T,
+    hook: Option<&dyn RequestHook>,
+) -> Result<Response, Error> {
+    let client = Client::new();
+    let Some(hook) = hook else {
+        if request_log::is_enabled() {
+            let body = serde_json::to_string(body).unwrap_or_default();
+            request_log::begin(provider, api, model, url, &header_strings(&headers), &body);
+        }
+        let response = client
+            .post(url)
+            .headers(headers)
+            .json(body)
+            .send()
+            .map_err(request_failed);
+        request_log::record_response(&response);
+        return response;
+    };
+
+    let mut request = ProviderRequest {
+        provider: provider.to_string(),
+        api: api.to_string(),
+        model: model.to_string(),
+        method: "POST".to_string(),
+        url: url.to_string(),
+        headers: header_strings(&headers),
+        body: serde_json::to_string(body)
+            .map_err(|err| Error::Request(format!("Failed to serialize request: {err}")))?,
+    };
+    request
+        .headers
+        .entry("content-type".to_string())
+        .or_insert_with(|| "application/json".to_string());
+    hook.on_request(&mut request)
+        .map_err(|err| Error::Request(format!("Request hook failed: {err}")))?;
+
+    let mut headers = HeaderMap::new();
+    for (key, value) in &request.headers {
+        let name = HeaderName::from_bytes(key.as_bytes())
+            .map_err(|err| Error::Request(format!("Invalid header name \"{key}\": {err}")))?;
+        let value = HeaderValue::from_str(value)
+            .map_err(|err| Error::Request(format!("Invalid header value for \"{key}\": {err}")))?;
+        headers.insert(name, value);
+    }
+    let method = reqwest::Method::from_bytes(request.method.as_bytes()).map_err(|err| {
+        Error::Request(format!(
+            "Invalid request method \"{}\": {err}",
+            request.method
+        ))
+    })?;
+    request_log::begin(
+        &request.provider,
+        &request.api,
+        &request.model,
+        &request.url,
+        &request.headers,
+        &request.body,
+    );
+    let response = client
+        .request(method, &request.url)
+        .headers(headers)
+        .body(request.body)
+        .send()
+        .map_err(request_failed);
+    request_log::record_response(&response);
+    response
+}
+
+fn request_failed(err: reqwest::Error) -> Error {
+    Error::Provider {
+        status: None,
+        message: format!("Request failed: {err}"),
+    }
+}
+
+fn header_strings(headers: &HeaderMap) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .filter_map(|(name, value)| {
+            value
+                .to_str()
+                .ok()
+                .map(|value| (name.as_str().to_string(), value.to_string()))
+        })
+        .collect()
+}
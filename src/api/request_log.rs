@@ -0,0 +1,258 @@
+//! Opt-in log of provider requests. Every request sent through [`post_json`] appends one
+//! redacted JSONL record (endpoint, model, latency, token counts, stop reason, error) to the
+//! request log; a trace file, when set, also gets the request headers and the full request and
+//! response bodies.
+//!
+//! [`post_json`]: super::request_hook::post_json
+
+use crate::core::messages::AssistantMessage;
+use chrono::{SecondsFormat, Utc};
+use reqwest::blocking::Response;
+use serde::Serialize;
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+const REDACTED: &str = "[redacted]";
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RequestLogger {
+    /// Redacted summaries, one line per request.
+    pub log_path: Option<PathBuf>,
+    /// Full records with bodies, for debugging prompt construction.
+    pub trace_path: Option<PathBuf>,
+}
+
+static REQUEST_LOGGER: OnceLock<RequestLogger> = OnceLock::new();
+
+thread_local! {
+    /// The request in flight on this thread, between `begin` and `finish`.
+    static PENDING: RefCell<Option<PendingRequest>> = const { RefCell::new(None) };
+}
+
+struct PendingRequest {
+    started: Instant,
+    timestamp: String,
+    provider: String,
+    api: String,
+    model: String,
+    endpoint: String,
+    status: Option<u16>,
+    request_headers: BTreeMap<String, String>,
+    request_body: String,
+    response_body: Vec<u8>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RequestLogRecord<'a> {
+    timestamp: &'a str,
+    provider: &'a str,
+    api: &'a str,
+    model: &'a str,
+    endpoint: &'a str,
+    status: Option<u16>,
+    latency_ms: u64,
+    input_tokens: Option<i64>,
+    output_tokens: Option<i64>,
+    cache_read_tokens: Option<i64>,
+    cache_write_tokens: Option<i64>,
+    stop_reason: Option<&'a str>,
+    error: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_headers: Option<&'a BTreeMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_body: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_body: Option<String>,
+}
+
+impl RequestLogger {
+    /// Turns request logging on for the rest of the process. It can only be set up once.
+    pub fn install(self) -> Result<(), String> {
+        REQUEST_LOGGER
+            .set(self)
+            .map_err(|_| "Request logging is already configured".to_string())
+    }
+}
+
+pub fn is_enabled() -> bool {
+    REQUEST_LOGGER.get().is_some()
+}
+
+pub(crate) fn begin(
+    provider: &str,
+    api: &str,
+    model: &str,
+    url: &str,
+    headers: &BTreeMap<String, String>,
+    body: &str,
+) {
+    let Some(logger) = REQUEST_LOGGER.get() else {
+        return;
+    };
+    let pending = PendingRequest {
+        started: Instant::now(),
+        timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+        provider: provider.to_string(),
+        api: api.to_string(),
+        model: model.to_string(),
+        endpoint: redact_url(url),
+        status: None,
+        request_headers: redact_headers(headers),
+        request_body: if logger.trace_path.is_some() {
+            body.to_string()
+        } else {
+            String::new()
+        },
+        response_body: Vec::new(),
+    };
+    PENDING.with(|slot| *slot.borrow_mut() = Some(pending));
+}
+
+pub(crate) fn record_response<E>(response: &Result<Response, E>) {
+    if let Ok(response) = response {
+        with_pending(|pending| pending.status = Some(response.status().as_u16()));
+    }
+}
+
+/// Keeps a chunk of the response body for the trace file.
+pub(crate) fn capture(chunk: &[u8]) {
+    if REQUEST_LOGGER
+        .get()
+        .is_some_and(|logger| logger.trace_path.is_some())
+    {
+        with_pending(|pending| pending.response_body.extend_from_slice(chunk));
+    }
+}
+
+/// Writes the record for the request in flight, taking tokens and the stop reason from the
+/// reply it produced.
+pub(crate) fn finish_message<E: Display>(result: &Result<AssistantMessage, E>) {
+    match result {
+        Ok(message) => write_pending(Some(message), message.error_message.as_deref()),
+        Err(err) => write_pending(None, Some(&err.to_string())),
+    }
+}
+
+/// Writes the record for a request whose reply is not an assistant message.
+pub(crate) fn finish<T, E: Display>(result: &Result<T, E>) {
+    let error = result.as_ref().err().map(ToString::to_string);
+    write_pending(None, error.as_deref());
+}
+
+fn with_pending(update: impl FnOnce(&mut PendingRequest)) {
+    PENDING.with(|slot| {
+        if let Some(pending) = slot.borrow_mut().as_mut() {
+            update(pending);
+        }
+    });
+}
+
+fn write_pending(message: Option<&AssistantMessage>, error: Option<&str>) {
+    let Some(logger) = REQUEST_LOGGER.get() else {
+        return;
+    };
+    let Some(pending) = PENDING.with(|slot| slot.borrow_mut().take()) else {
+        return;
+    };
+    let usage = message.map(|message| &message.usage);
+    let mut record = RequestLogRecord {
+        timestamp: &pending.timestamp,
+        provider: &pending.provider,
+        api: &pending.api,
+        model: &pending.model,
+        endpoint: &pending.endpoint,
+        status: pending.status,
+        latency_ms: pending.started.elapsed().as_millis() as u64,
+        input_tokens: usage.map(|usage| usage.input),
+        output_tokens: usage.map(|usage| usage.output),
+        cache_read_tokens: usage.map(|usage| usage.cache_read),
+        cache_write_tokens: usage.map(|usage| usage.cache_write),
+        stop_reason: message.map(|message| message.stop_reason.as_str()),
+        error,
+        request_headers: None,
+        request_body: None,
+        response_body: None,
+    };
+    if let Some(path) = &logger.log_path {
+        append_record(path, &record);
+    }
+    if let Some(path) = &logger.trace_path {
+        record.request_headers = Some(&pending.request_headers);
+        record.request_body = Some(
+            serde_json::from_str(&pending.request_body)
+                .unwrap_or_else(|_| Value::String(pending.request_body.clone())),
+        );
+        record.response_body = Some(String::from_utf8_lossy(&pending.response_body).into_owned());
+        append_record(path, &record);
+    }
+}
+
+/// Logging never fails a request; problems are reported and the record is dropped.
+fn append_record(path: &Path, record: &RequestLogRecord<'_>) {
+    let result = (|| {
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+        let mut line = serde_json::to_string(record).map_err(|err| err.to_string())?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(|err| err.to_string())
+    })();
+    if let Err(err) = result {
+        eprintln!("Warning: Failed to write {}: {err}", path.display());
+    }
+}
+
+fn is_secret_name(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name == "authorization"
+        || name == "cookie"
+        || ["key", "token", "secret", "signature"]
+            .iter()
+            .any(|word| name.contains(word))
+}
+
+fn redact_headers(headers: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if is_secret_name(name) {
+                REDACTED.to_string()
+            } else {
+                value.clone()
+            };
+            (name.clone(), value)
+        })
+        .collect()
+}
+
+/// Blanks query parameters that carry credentials, such as `?key=`.
+fn redact_url(url: &str) -> String {
+    let Some((base, query)) = url.split_once('?') else {
+        return url.to_string();
+    };
+    let query = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((name, _)) if is_secret_name(name) => format!("{name}={REDACTED}"),
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+    format!("{base}?{query}")
+}
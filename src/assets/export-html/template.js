@@ -13,6 +13,8 @@
       }
       const data = JSON.parse(new TextDecoder('utf-8').decode(bytes));
       const { header, entries, leafId: defaultLeafId, systemPrompt, tools } = data;
+      // Which custom messages (notes, hook output) to show: 'displayed', 'all' or 'hidden'.
+      const customMessages = data.customMessages || 'displayed';
 
       // ============================================================
       // URL PARAMETER HANDLING
@@ -699,6 +701,18 @@
           return result.content.filter(c => c.type === 'image');
         };
 
+        const renderResultDiffs = () => {
+          if (!result) return '';
+          return result.content.filter(c => c.type === 'diff').map(block => {
+            let diffHtml = `<div class="tool-diff"><div class="diff-context">${escapeHtml(block.path)} <span class="diff-added">+${block.added || 0}</span> <span class="diff-removed">-${block.removed || 0}</span></div>`;
+            for (const line of block.diff.split('\n')) {
+              const cls = line.match(/^\+/) ? 'diff-added' : line.match(/^-/) ? 'diff-removed' : 'diff-context';
+              diffHtml += `<div class="${cls}">${escapeHtml(replaceTabs(line))}</div>`;
+            }
+            return diffHtml + '</div>';
+          }).join('');
+        };
+
         const renderResultImages = () => {
           const images = getResultImages();
           if (images.length === 0) return '';
@@ -756,6 +770,7 @@
             if (result) {
               const output = getResultText().trim();
               if (output) html += `<div class="tool-output"><div>${escapeHtml(output)}</div></div>`;
+              html += renderResultDiffs();
             }
             break;
           }
@@ -763,7 +778,10 @@
             const filePath = args.file_path || args.path || '';
             html += `<div class="tool-header"><span class="tool-name">edit</span> <span class="tool-path">${escapeHtml(shortenPath(filePath))}</span></div>`;
 
-            if (result?.details?.diff) {
+            const diffs = renderResultDiffs();
+            if (diffs) {
+              html += diffs;
+            } else if (result?.details?.diff) {
               const diffLines = result.details.diff.split('\n');
               html += '<div class="tool-diff">';
               for (const line of diffLines) {
@@ -783,6 +801,7 @@
             if (result) {
               const output = getResultText();
               if (output) html += formatExpandableOutput(output, 10);
+              html += renderResultDiffs();
             }
           }
         }
@@ -950,6 +969,10 @@
             return html;
           }
 
+          if (msg.role === 'custom' || msg.role === 'hookMessage') {
+            return renderCustomMessage(entryId, tsHtml, msg.customType, msg.content, msg.display);
+          }
+
           if (msg.role === 'toolResult') return '';
         }
 
@@ -972,16 +995,23 @@
           </div>`;
         }
 
-        if (entry.type === 'custom_message' && entry.display) {
-          return `<div class="hook-message" id="${entryId}">${tsHtml}
-            <div class="hook-type">[${escapeHtml(entry.customType)}]</div>
-            <div class="markdown-content">${safeMarkedParse(typeof entry.content === 'string' ? entry.content : JSON.stringify(entry.content))}</div>
-          </div>`;
+        if (entry.type === 'custom_message') {
+          return renderCustomMessage(entryId, tsHtml, entry.customType, entry.content, entry.display);
         }
 
         return '';
       }
 
+      function renderCustomMessage(entryId, tsHtml, customType, content, display) {
+        if (customMessages === 'hidden' || (customMessages === 'displayed' && !display)) return '';
+        const text = extractContent(content);
+        const hiddenClass = display ? '' : ' hidden-from-ui';
+        return `<div class="hook-message${hiddenClass}" id="${entryId}">${tsHtml}
+          <div class="hook-type">[${escapeHtml(customType || 'custom')}]</div>
+          <div class="markdown-content">${safeMarkedParse(text)}</div>
+        </div>`;
+      }
+
       // ============================================================
       // HEADER / STATS
       // ============================================================
@@ -989,6 +1019,7 @@
       function computeStats(entryList) {
         let userMessages = 0, assistantMessages = 0, toolResults = 0;
         let customMessages = 0, compactions = 0, branchSummaries = 0, toolCalls = 0;
+        let linesAdded = 0, linesRemoved = 0;
         const tokens = { input: 0, output: 0, cacheRead: 0, cacheWrite: 0 };
         const cost = { input: 0, output: 0, cacheRead: 0, cacheWrite: 0 };
         const models = new Set();
@@ -1014,7 +1045,13 @@
               }
               toolCalls += msg.content.filter(c => c.type === 'toolCall').length;
             }
-            if (msg.role === 'toolResult') toolResults++;
+            if (msg.role === 'toolResult') {
+              toolResults++;
+              for (const block of msg.content.filter(c => c.type === 'diff')) {
+                linesAdded += block.added || 0;
+                linesRemoved += block.removed || 0;
+              }
+            }
           } else if (entry.type === 'compaction') {
             compactions++;
           } else if (entry.type === 'branch_summary') {
@@ -1024,7 +1061,7 @@
           }
         }
 
-        return { userMessages, assistantMessages, toolResults, customMessages, compactions, branchSummaries, toolCalls, tokens, cost, models: Array.from(models) };
+        return { userMessages, assistantMessages, toolResults, customMessages, compactions, branchSummaries, toolCalls, linesAdded, linesRemoved, tokens, cost, models: Array.from(models) };
       }
 
       const globalStats = computeStats(entries);
@@ -1055,6 +1092,7 @@
               <div class="info-item"><span class="info-label">Models:</span><span class="info-value">${globalStats.models.join(', ') || 'unknown'}</span></div>
               <div class="info-item"><span class="info-label">Messages:</span><span class="info-value">${msgParts.join(', ') || '0'}</span></div>
               <div class="info-item"><span class="info-label">Tool Calls:</span><span class="info-value">${globalStats.toolCalls}</span></div>
+              <div class="info-item"><span class="info-label">Changes:</span><span class="info-value">+${globalStats.linesAdded} -${globalStats.linesRemoved}</span></div>
               <div class="info-item"><span class="info-label">Tokens:</span><span class="info-value">${tokenParts.join(' ') || '0'}</span></div>
               <div class="info-item"><span class="info-label">Cost:</span><span class="info-value">$${totalCost.toFixed(3)}</span></div>
             </div>
@@ -1182,8 +1220,68 @@
         return text.replace(/<(?=[a-zA-Z\/])/g, '&lt;');
       }
 
+      // Render LaTeX with KaTeX as MathML, falling back to the escaped source
+      function renderMath(tex, displayMode) {
+        if (typeof katex !== 'undefined') {
+          try {
+            return katex.renderToString(tex, { displayMode, throwOnError: false, output: 'mathml' });
+          } catch {
+            // Fall through to the raw source below
+          }
+        }
+        const source = escapeHtml(tex);
+        return displayMode ? `<pre class="math-source">${source}</pre>` : `<code>${source}</code>`;
+      }
+
+      // Math extensions: $$...$$ and \[...\] blocks, $...$ and \(...\) inline.
+      // Inline $ requires non-space inside both delimiters and no digit after the
+      // closing one, so prices like "$5 and $10" stay plain text.
+      const mathExtensions = [
+        {
+          name: 'blockMath',
+          level: 'block',
+          start(src) {
+            const match = src.match(/\$\$|\\\[/);
+            return match ? match.index : undefined;
+          },
+          tokenizer(src) {
+            const match = /^\$\$([\s\S]+?)\$\$[^\S\n]*(?:\n|$)/.exec(src)
+              || /^\\\[([\s\S]+?)\\\][^\S\n]*(?:\n|$)/.exec(src);
+            if (match) {
+              return { type: 'blockMath', raw: match[0], text: match[1].trim() };
+            }
+          },
+          renderer(token) {
+            return `<div class="math-display">${renderMath(token.text, true)}</div>`;
+          }
+        },
+        {
+          name: 'inlineMath',
+          level: 'inline',
+          start(src) {
+            const match = src.match(/\$|\\\(/);
+            return match ? match.index : undefined;
+          },
+          tokenizer(src) {
+            let match = /^\$\$([\s\S]+?)\$\$/.exec(src);
+            if (match) {
+              return { type: 'inlineMath', raw: match[0], text: match[1].trim(), displayMode: true };
+            }
+            match = /^\$(?!\s)((?:\\.|[^\\$\n])+?)(?<!\s)\$(?!\d)/.exec(src)
+              || /^\\\(([\s\S]+?)\\\)/.exec(src);
+            if (match) {
+              return { type: 'inlineMath', raw: match[0], text: match[1].trim(), displayMode: false };
+            }
+          },
+          renderer(token) {
+            return renderMath(token.text, token.displayMode);
+          }
+        }
+      ];
+
       // Configure marked with syntax highlighting and HTML escaping for text
       marked.use({
+        extensions: mathExtensions,
         breaks: true,
         gfm: true,
         renderer: {
@@ -1208,8 +1306,12 @@
             }
             return `<pre><code class="hljs">${highlighted}</code></pre>`;
           },
-          // Text content: escape HTML tags
+          // Text content: escape HTML tags. List item and table text tokens carry
+          // nested inline tokens (bold, code, math) that must still be rendered.
           text(token) {
+            if (token.tokens) {
+              return this.parser.parseInline(token.tokens);
+            }
             return escapeHtmlTags(escapeHtml(token.text));
           },
           // Inline code: escape HTML
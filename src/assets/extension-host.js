@@ -1,8 +1,115 @@
 const path = require("node:path");
 const readline = require("node:readline");
-const { createRequire } = require("node:module");
+const Module = require("node:module");
+const { createRequire } = Module;
 const { pathToFileURL } = require("node:url");
 
+const CAPABILITIES = ["fs.read", "fs.write", "network", "ui", "flags", "prompt"];
+
+// Capabilities granted per extension path; null grants everything (no permission manifest).
+let grants = null;
+const extensionPaths = new Set();
+
+function isGranted(owner, capability) {
+  if (grants === null) return true;
+  if (owner) return Boolean(grants[owner] && grants[owner].includes(capability));
+  // Unattributed calls are allowed only if every extension could have made them.
+  return [...extensionPaths].every(
+    (extPath) => grants[extPath] && grants[extPath].includes(capability),
+  );
+}
+
+function requireCapability(owner, capability) {
+  if (!isGranted(owner, capability)) {
+    const name = owner ? path.basename(owner) : "An extension";
+    throw new Error(`${name} was not granted the "${capability}" capability`);
+  }
+}
+
+// The extension whose require() chain reached this module, if any.
+function moduleOwner(mod) {
+  for (let current = mod; current; current = current.parent) {
+    if (extensionPaths.has(current.filename)) return current.filename;
+  }
+  return null;
+}
+
+function stackOwner() {
+  const stack = new Error().stack || "";
+  for (const line of stack.split("\n")) {
+    for (const extPath of extensionPaths) {
+      if (line.includes(extPath)) return extPath;
+    }
+  }
+  return null;
+}
+
+const FS_WRITE_METHOD =
+  /^(write|append|mkdir|mkdtemp|rm|unlink|rename|copy|cp|symlink|link|l?chmod|l?chown|f?truncate|l?utimes|futimes|createWriteStream)/;
+
+function fsCapability(method, args) {
+  if (FS_WRITE_METHOD.test(method)) return "fs.write";
+  const flags = args[1];
+  if (/^open/.test(method) && typeof flags === "string" && /[wa+]/.test(flags)) {
+    return "fs.write";
+  }
+  return "fs.read";
+}
+
+// Node's permission model, which pi starts this process under with flags built from the
+// grants, is what holds fs.read, fs.write and network. These checks on require() and fetch
+// name the extension and capability behind a refused call. ui, flags and prompt go through
+// the host API.
+const networkCapability = () => "network";
+// A child process can do anything, so it needs every capability it could use.
+const GUARDED_MODULES = {
+  fs: fsCapability,
+  "fs/promises": fsCapability,
+  http: networkCapability,
+  https: networkCapability,
+  http2: networkCapability,
+  net: networkCapability,
+  tls: networkCapability,
+  dgram: networkCapability,
+  child_process: () => ["fs.read", "fs.write", "network"],
+};
+
+function guardModule(target, owner, capabilityFor) {
+  return new Proxy(target, {
+    get(object, property, receiver) {
+      const value = Reflect.get(object, property, receiver);
+      if (property === "promises" && value && typeof value === "object") {
+        return guardModule(value, owner, capabilityFor);
+      }
+      if (typeof value !== "function") return value;
+      return function (...args) {
+        const needed = capabilityFor(String(property), args);
+        for (const capability of [].concat(needed)) {
+          requireCapability(owner, capability);
+        }
+        return new.target ? Reflect.construct(value, args) : value.apply(object, args);
+      };
+    },
+  });
+}
+
+const originalLoad = Module._load;
+Module._load = function (request, parent, isMain) {
+  const loaded = originalLoad.call(this, request, parent, isMain);
+  const name = request.startsWith("node:") ? request.slice(5) : request;
+  const capabilityFor = GUARDED_MODULES[name];
+  if (!capabilityFor || grants === null) return loaded;
+  return guardModule(loaded, moduleOwner(parent), capabilityFor);
+};
+
+if (typeof globalThis.fetch === "function") {
+  const originalFetch = globalThis.fetch;
+  globalThis.fetch = function (...args) {
+    requireCapability(stackOwner(), "network");
+    return originalFetch.apply(this, args);
+  };
+}
+
 function createNoOpUI() {
   return {
     select: async () => undefined,
@@ -25,9 +132,9 @@ function createNoOpUI() {
 let nextUiRequestId = 0;
 const pendingUiRequests = new Map();
 
-function sendUiRequest(message, waitForResponse) {
+function sendUiRequestFor(extensionPath, message, waitForResponse) {
   const id = String((nextUiRequestId += 1));
-  const payload = { type: "extension_ui_request", id, ...message };
+  const payload = { type: "extension_ui_request", id, extensionPath, ...message };
 
   if (!waitForResponse) {
     process.stdout.write(JSON.stringify(payload) + "\n");
@@ -40,7 +147,8 @@ function sendUiRequest(message, waitForResponse) {
   });
 }
 
-function createRpcUI() {
+function createRpcUI(extensionPath) {
+  const sendUiRequest = sendUiRequestFor.bind(null, extensionPath);
   return {
     select: async (title, options) => {
       const response = await sendUiRequest(
@@ -124,15 +232,22 @@ function createExtensionApi(extensionPath, registry) {
       handlers[event].push(handler);
     },
     registerTool(tool) {
-      if (tool && tool.name) {
-        registry.tools.push({
-          name: tool.name,
-          label: tool.label,
-          description: tool.description,
-          parameters: tool.parameters,
-        });
-        registry.toolHandlers[tool.name] = tool;
+      if (!tool || !tool.name) return;
+      const declared = registry.tools.find((entry) => entry.name === tool.name);
+      if (declared) {
+        // Implements a tool from the manifest; the manifest's schema wins where both set it.
+        const merged = { ...tool, ...registry.toolHandlers[tool.name] };
+        merged.execute = tool.execute || merged.execute;
+        registry.toolHandlers[tool.name] = merged;
+        return;
       }
+      registry.tools.push({
+        name: tool.name,
+        label: tool.label,
+        description: tool.description,
+        parameters: tool.parameters,
+      });
+      registry.toolHandlers[tool.name] = tool;
     },
     registerCommand(name, options) {
       if (!name) return;
@@ -149,7 +264,7 @@ function createExtensionApi(extensionPath, registry) {
       });
     },
     registerFlag(name, options) {
-      if (!name || !options) return;
+      if (!name || !options || !isGranted(extensionPath, "flags")) return;
       registry.flags.push({
         name,
         description: options.description,
@@ -161,8 +276,20 @@ function createExtensionApi(extensionPath, registry) {
       }
     },
     getFlag(name) {
+      if (!isGranted(extensionPath, "flags")) return undefined;
       return registry.flagValues[name];
     },
+    // Replaces (or adds) a named system prompt section; "" removes it.
+    registerPromptSection(name, text) {
+      if (!name || typeof text !== "string" || !isGranted(extensionPath, "prompt")) return;
+      registry.promptSections[name] = text;
+    },
+    setToolDescription(name, description) {
+      if (!name || typeof description !== "string" || !isGranted(extensionPath, "prompt")) {
+        return;
+      }
+      registry.toolDescriptions[name] = description;
+    },
     registerMessageRenderer(customType) {
       if (!customType) return;
       registry.messageRenderers.push({ customType });
@@ -222,7 +349,7 @@ async function loadModule(extensionPath) {
   }
 }
 
-async function loadExtension(extensionPath) {
+async function loadExtension(extensionPath, state) {
   const registry = {
     path: extensionPath,
     handlers: {},
@@ -233,27 +360,71 @@ async function loadExtension(extensionPath) {
     flagValues: {},
     shortcuts: [],
     messageRenderers: [],
+    promptSections: {},
+    toolDescriptions: {},
   };
 
+  extensionPaths.add(extensionPath);
   const mod = await loadModule(extensionPath);
 
   const factory = mod && (mod.default || mod);
-  if (typeof factory !== "function") {
+  const declaredTools = (mod && mod.tools) || (factory && factory.tools);
+  if (typeof factory !== "function" && declaredTools === undefined) {
     throw new Error(`Extension ${extensionPath} does not export a function`);
   }
 
   const api = createExtensionApi(extensionPath, registry);
-  factory(api);
+  const declared = parseDeclaredTools(declaredTools);
+  for (const tool of declared) {
+    registry.tools.push({
+      name: tool.name,
+      label: tool.label,
+      description: tool.description,
+      parameters: tool.parameters,
+    });
+    registry.toolHandlers[tool.name] = tool;
+  }
+  if (typeof factory === "function") {
+    factory(api);
+  }
+  for (const tool of declared) {
+    if (typeof registry.toolHandlers[tool.name].execute !== "function") {
+      throw new Error(`Tool ${tool.name} is declared but has no execute function`);
+    }
+  }
   return registry;
 }
 
-function createContext(payload) {
+// Tools an extension declares up front with `module.exports.tools`: name, description and
+// JSON schema, plus `execute` unless the factory registers it with `pi.registerTool`.
+function parseDeclaredTools(declared) {
+  if (declared === undefined) return [];
+  if (!Array.isArray(declared)) {
+    throw new Error("tools must be an array of tool definitions");
+  }
+  return declared.map((tool) => {
+    if (!tool || typeof tool.name !== "string" || !tool.name) {
+      throw new Error("Every declared tool needs a name");
+    }
+    if (typeof tool.description !== "string") {
+      throw new Error(`Tool ${tool.name} needs a description`);
+    }
+    const schema = tool.parameters;
+    if (schema !== undefined && (!schema || schema.type !== "object")) {
+      throw new Error(`Tool ${tool.name} parameters must be a JSON schema of type "object"`);
+    }
+    return tool;
+  });
+}
+
+function createContext(payload, extensionPath) {
   const data = payload || {};
+  const hasUI = Boolean(data.hasUI) && isGranted(extensionPath, "ui");
   const sessionEntries = Array.isArray(data.sessionEntries) ? data.sessionEntries : [];
   const model = data.model;
   return {
-    ui: createRpcUI(),
-    hasUI: Boolean(data.hasUI),
+    ui: isGranted(extensionPath, "ui") ? createRpcUI(extensionPath) : createNoOpUI(),
+    hasUI,
     cwd: data.cwd || process.cwd(),
     sessionManager: {
       getEntries() {
@@ -279,11 +450,11 @@ function createContext(payload) {
 async function emitEvent(extensions, event, context) {
   let result;
   const errors = [];
-  const ctx = createContext(context);
 
   for (const ext of extensions) {
     const handlers = ext.handlers[event.type] || [];
     if (!handlers.length) continue;
+    const ctx = createContext(context, ext.path);
 
     for (const handler of handlers) {
       try {
@@ -303,6 +474,22 @@ async function emitEvent(extensions, event, context) {
         if (event.type === "tool_result" && handlerResult) {
           result = handlerResult;
         }
+        if (event.type === "assistant_output" && handlerResult) {
+          result = { ...result, ...handlerResult };
+          if (result.block) {
+            return { result, errors };
+          }
+          if (Array.isArray(handlerResult.content)) {
+            event.content = handlerResult.content;
+          }
+        }
+        if (event.type === "provider_request" && handlerResult) {
+          const { headers, ...rest } = handlerResult;
+          Object.assign(event.request, rest);
+          if (headers) {
+            event.request.headers = { ...event.request.headers, ...headers };
+          }
+        }
       } catch (err) {
         errors.push({
           extensionPath: ext.path,
@@ -313,6 +500,10 @@ async function emitEvent(extensions, event, context) {
     }
   }
 
+  if (event.type === "provider_request") {
+    // Handlers may also mutate event.request in place.
+    result = event.request;
+  }
   return { result, errors };
 }
 
@@ -324,6 +515,9 @@ function sanitizeExtensions(extensions) {
     flags: ext.flags,
     shortcuts: ext.shortcuts,
     messageRenderers: ext.messageRenderers,
+    promptSections: ext.promptSections,
+    toolDescriptions: ext.toolDescriptions,
+    capabilities: grants === null ? CAPABILITIES : grants[ext.path] || [],
     handlerCounts: Object.fromEntries(
       Object.entries(ext.handlers).map(([key, value]) => [key, value.length]),
     ),
@@ -346,12 +540,13 @@ function applyFlags(extensions, flags) {
 
 async function handleMessage(message, state) {
   if (message.type === "init") {
+    grants = message.grants === undefined ? null : message.grants;
     const extensions = [];
     const errors = [];
     for (const extPath of message.extensions || []) {
       try {
         const resolved = path.resolve(extPath);
-        const ext = await loadExtension(resolved);
+        const ext = await loadExtension(resolved, state);
         extensions.push(ext);
       } catch (err) {
         errors.push({
@@ -365,6 +560,9 @@ async function handleMessage(message, state) {
       {},
       ...extensions.map((ext) => ext.toolHandlers || {}),
     );
+    state.toolOwners = Object.fromEntries(
+      extensions.flatMap((ext) => ext.tools.map((tool) => [tool.name, ext.path])),
+    );
     return {
       ok: true,
       extensions: sanitizeExtensions(extensions),
@@ -383,11 +581,22 @@ async function handleMessage(message, state) {
       return { ok: false, error: `Tool ${message.name} not found` };
     }
     try {
-      const ctx = createContext(message.context);
+      const ctx = createContext(message.context, state.toolOwners[message.name]);
+      // Partial results stream back ahead of the response to this request.
+      const onUpdate = (partial) => {
+        process.stdout.write(
+          JSON.stringify({
+            type: "tool_update",
+            id: message.id,
+            toolCallId: message.toolCallId,
+            partial: partial ?? null,
+          }) + "\n",
+        );
+      };
       const result = await tool.execute(
         message.toolCallId,
         message.input ?? {},
-        undefined,
+        onUpdate,
         ctx,
         createAbortSignal(),
       );
@@ -417,7 +626,7 @@ async function handleMessage(message, state) {
 }
 
 async function main() {
-  const state = { extensions: [], toolHandlers: {} };
+  const state = { extensions: [], toolHandlers: {}, toolOwners: {} };
   const rl = readline.createInterface({
     input: process.stdin,
     crlfDelay: Infinity,
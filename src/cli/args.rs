@@ -1,8 +1,15 @@
+use crate::coding_agent::{CustomMessageDisplay, SettingsScope};
+use crate::tools::{GIT_TOOL_NAMES, NOTEBOOK_TOOL_NAMES};
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Mode {
     Text,
     Json,
     Rpc,
+    /// The RPC commands as JSON-RPC 2.0, for editor integrations.
+    JsonRpc,
+    /// The Agent Client Protocol, for editors such as Zed.
+    Acp,
 }
 
 impl Mode {
@@ -11,6 +18,25 @@ impl Mode {
             "text" => Some(Self::Text),
             "json" => Some(Self::Json),
             "rpc" => Some(Self::Rpc),
+            "jsonrpc" => Some(Self::JsonRpc),
+            "acp" => Some(Self::Acp),
+            _ => None,
+        }
+    }
+}
+
+/// `--ui`: force the full-screen interface or the line-based one instead of detecting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UiMode {
+    Full,
+    Line,
+}
+
+impl UiMode {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "full" => Some(Self::Full),
+            "line" => Some(Self::Line),
             _ => None,
         }
     }
@@ -27,7 +53,7 @@ pub enum ThinkingLevel {
 }
 
 impl ThinkingLevel {
-    fn parse(value: &str) -> Option<Self> {
+    pub(crate) fn parse(value: &str) -> Option<Self> {
         match value {
             "off" => Some(Self::Off),
             "minimal" => Some(Self::Minimal),
@@ -46,6 +72,299 @@ pub enum ListModels {
     Pattern(String),
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SessionsCommand {
+    List,
+    Search(String),
+}
+
+/// `pi auth login [provider]`, `pi auth logout <provider>`, `pi auth status`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AuthCommand {
+    Login(Option<String>),
+    Logout(Option<String>),
+    Status,
+}
+
+/// `pi config get [key]` or `pi config set <key> <value>`, with `--project` or `--global`
+/// choosing the settings file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigCommand {
+    pub action: Option<String>,
+    pub key: Option<String>,
+    pub value: Option<String>,
+    pub scope: Option<SettingsScope>,
+}
+
+/// `pi models add <provider>/<id> [--option value ...]`, `pi models remove <provider>/<id>`
+/// or `pi models show [provider[/id]]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModelsCommand {
+    pub action: Option<String>,
+    pub target: Option<String>,
+    /// `--name value` pairs in order; a flag without a value is `"true"`.
+    pub options: Vec<(String, String)>,
+}
+
+/// `pi extensions list` or `pi extensions reset <path>`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExtensionsCommand {
+    pub action: Option<String>,
+    pub target: Option<String>,
+}
+
+/// `pi batch list`, `pi batch status <id>` or `pi batch fetch <id> [--output dir]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BatchCommand {
+    pub action: Option<String>,
+    pub target: Option<String>,
+    pub output: Option<String>,
+}
+
+/// `pi compare --models a,b,c "prompt" [--output report.md]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompareCommand {
+    /// Also write a markdown report here.
+    pub output: Option<String>,
+}
+
+/// `pi stats [--json]`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StatsCommand {
+    pub json: bool,
+}
+
+/// `pi replay-turn --session <file> --entry <id> [--model <model>] [--dry-run]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplayTurnCommand {
+    pub entry: Option<String>,
+    /// Print the rebuilt context and recorded response without calling the model.
+    pub dry_run: bool,
+}
+
+/// `pi tool run <tool> [--arg value ...]`: run a built-in tool directly, without a model.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ToolRunCommand {
+    pub tool: String,
+    pub params: serde_json::Value,
+    /// `--dangerously-allow-all`: run without the workspace sandbox.
+    pub allow_all: bool,
+}
+
+/// A flag `parse_args` understands, for shell completions. Keep in step with the parser.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FlagSpec {
+    pub long: &'static str,
+    pub short: Option<&'static str>,
+    /// What the flag's value is: `"file"` and `"dir"` complete paths; `None` for switches.
+    pub value: Option<&'static str>,
+    /// The accepted values, when there is a fixed set.
+    pub choices: &'static [&'static str],
+    pub description: &'static str,
+}
+
+const fn flag(long: &'static str, description: &'static str) -> FlagSpec {
+    FlagSpec {
+        long,
+        short: None,
+        value: None,
+        choices: &[],
+        description,
+    }
+}
+
+const fn value_flag(
+    long: &'static str,
+    value: &'static str,
+    description: &'static str,
+) -> FlagSpec {
+    FlagSpec {
+        long,
+        short: None,
+        value: Some(value),
+        choices: &[],
+        description,
+    }
+}
+
+const fn choice_flag(
+    long: &'static str,
+    choices: &'static [&'static str],
+    description: &'static str,
+) -> FlagSpec {
+    FlagSpec {
+        long,
+        short: None,
+        value: Some("value"),
+        choices,
+        description,
+    }
+}
+
+const fn short(spec: FlagSpec, short: &'static str) -> FlagSpec {
+    FlagSpec {
+        short: Some(short),
+        ..spec
+    }
+}
+
+pub const FLAGS: &[FlagSpec] = &[
+    short(flag("help", "Show help"), "h"),
+    short(flag("version", "Show version"), "v"),
+    choice_flag(
+        "mode",
+        &["text", "json", "rpc", "jsonrpc", "acp"],
+        "Output mode",
+    ),
+    choice_flag(
+        "ui",
+        &["full", "line"],
+        "Force the full-screen or line-based UI",
+    ),
+    short(flag("continue", "Continue the previous session"), "c"),
+    short(flag("resume", "Select a session to resume"), "r"),
+    choice_flag(
+        "provider",
+        &[
+            "anthropic",
+            "openai",
+            "openai-codex",
+            "google",
+            "google-gemini-cli",
+            "google-antigravity",
+            "amazon-bedrock",
+            "xai",
+            "mistral",
+        ],
+        "Provider name",
+    ),
+    value_flag("model", "model", "Model id"),
+    value_flag(
+        "models",
+        "patterns",
+        "Comma-separated model patterns with fallbacks",
+    ),
+    value_flag("api-key", "key", "Override the provider API key"),
+    value_flag("auth-profile", "name", "Credential profile from auth.json"),
+    value_flag(
+        "system-prompt",
+        "file",
+        "Custom system prompt (text or file)",
+    ),
+    value_flag(
+        "append-system-prompt",
+        "file",
+        "Append to the system prompt (text or file)",
+    ),
+    value_flag("tools", "tools", "Comma-separated tool allowlist"),
+    choice_flag(
+        "thinking",
+        &["off", "minimal", "low", "medium", "high", "xhigh"],
+        "Thinking level",
+    ),
+    value_flag("seed", "n", "Sampling seed"),
+    value_flag(
+        "max-cost",
+        "usd",
+        "Stop once the session has cost this much",
+    ),
+    value_flag(
+        "record-fixtures",
+        "dir",
+        "Save every provider response to <dir> for offline replay",
+    ),
+    value_flag("max-turns", "n", "Stop a run after this many model calls"),
+    value_flag(
+        "max-duration",
+        "duration",
+        "Stop a run after this long (e.g. 300, 90s, 10m)",
+    ),
+    value_flag(
+        "trace-llm",
+        "file",
+        "Append provider requests and responses to a file",
+    ),
+    value_flag(
+        "auto-compact-threshold",
+        "percent",
+        "Auto-compact at this context usage",
+    ),
+    value_flag("persona", "name", "Use a named persona"),
+    flag("dangerously-allow-all", "Disable the workspace sandbox"),
+    flag("no-session", "Do not save the session"),
+    value_flag("session", "file", "Use this session file"),
+    value_flag("session-dir", "dir", "Directory for session files"),
+    short(flag("print", "Print mode (single-shot)"), "p"),
+    flag(
+        "batch",
+        "Send each message through the provider's batch API",
+    ),
+    value_flag("batch-output", "dir", "Where --batch writes results"),
+    flag("list-models", "List available models"),
+    flag("list-tools", "List the tools the agent can use"),
+    flag("list-skills", "List discovered skills"),
+    value_flag("export", "file", "Export a session file to HTML"),
+    flag(
+        "export-new-only",
+        "Only export entries added since the last export",
+    ),
+    choice_flag(
+        "export-custom-messages",
+        &["displayed", "all", "hidden"],
+        "Custom messages to include in the export",
+    ),
+    value_flag(
+        "export-theme",
+        "name",
+        "Export theme: dark, light, auto or a custom theme",
+    ),
+    flag("sessions", "List or search sessions"),
+    value_flag("idle-exit", "seconds", "RPC mode: exit when idle this long"),
+    value_flag(
+        "parent-pid",
+        "pid",
+        "RPC mode: exit when this process exits",
+    ),
+    flag("serve", "Run RPC mode as a local daemon"),
+    flag("mirror", "Show the session live in a browser"),
+    value_flag("port", "n", "Port for --serve or --mirror"),
+    short(
+        value_flag("extension", "file", "Load an extension file"),
+        "e",
+    ),
+    flag("no-skills", "Disable skills"),
+    value_flag("skills", "patterns", "Comma-separated skill filters"),
+    value_flag("context-dir", "dir", "Preload files under a directory"),
+    value_flag(
+        "context-glob",
+        "globs",
+        "Globs selecting --context-dir files",
+    ),
+    flag(
+        "no-project-context",
+        "Do not load AGENTS.md and CLAUDE.md files",
+    ),
+];
+
+/// `pi <subcommand>`, with a description for completions.
+pub const SUBCOMMANDS: &[(&str, &str)] = &[
+    ("auth", "Log in, log out or show credentials"),
+    ("batch", "List, check or fetch batch jobs"),
+    ("blame", "Show which session changed a file"),
+    ("compare", "Run a prompt against several models"),
+    ("completions", "Print shell completions"),
+    ("config", "Show or change settings"),
+    ("extensions", "List extensions or reset their permissions"),
+    ("models", "Add, remove or show custom models"),
+    (
+        "replay",
+        "Replay a recorded session without calling a provider",
+    ),
+    ("replay-turn", "Replay a recorded turn"),
+    ("stats", "Show token, cost and tool usage across sessions"),
+    ("tool", "Run a built-in tool directly"),
+];
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum ExtensionFlagType {
     Bool,
@@ -71,6 +390,7 @@ pub struct Args {
     pub help: bool,
     pub version: bool,
     pub mode: Option<Mode>,
+    pub ui: Option<UiMode>,
     pub no_session: bool,
     pub session: Option<String>,
     pub session_dir: Option<String>,
@@ -79,15 +399,87 @@ pub struct Args {
     pub extensions: Option<Vec<String>>,
     pub print: bool,
     pub export: Option<String>,
+    pub export_new_only: bool,
+    pub export_custom_messages: Option<CustomMessageDisplay>,
+    pub export_theme: Option<String>,
     pub no_skills: bool,
+    pub no_project_context: bool,
+    /// `--context-dir`: files to preload at session start.
+    pub context_dir: Option<String>,
+    /// `--context-glob`: which files under the context dir to preload.
+    pub context_globs: Option<Vec<String>>,
     pub skills: Option<Vec<String>>,
     pub list_models: Option<ListModels>,
+    pub list_tools: bool,
+    pub list_skills: bool,
+    /// `pi completions <shell>`.
+    pub completions: Option<String>,
+    pub sessions: Option<SessionsCommand>,
+    pub tool_run: Option<ToolRunCommand>,
+    /// `pi blame <file>`.
+    pub blame: Option<String>,
+    /// `pi replay <session-file|fixtures-dir>`.
+    pub replay: Option<String>,
+    /// `--record-fixtures <dir>`: append each provider response to `<dir>/responses.jsonl`.
+    pub record_fixtures: Option<String>,
+    pub auth: Option<AuthCommand>,
+    pub replay_turn: Option<ReplayTurnCommand>,
+    pub compare: Option<CompareCommand>,
+    pub stats: Option<StatsCommand>,
+    pub config: Option<ConfigCommand>,
+    pub models_command: Option<ModelsCommand>,
+    pub extensions_command: Option<ExtensionsCommand>,
+    pub batch_command: Option<BatchCommand>,
+    /// `--batch`: print mode through the provider's batch API, one result file per prompt.
+    pub batch: bool,
+    pub batch_output: Option<String>,
+    pub seed: Option<u64>,
+    pub auto_compact_threshold: Option<f64>,
+    /// `--max-cost <usd>`: stop the session once it has cost this much.
+    pub max_cost: Option<f64>,
+    /// `--max-turns <n>`: stop a run once it has made this many model calls.
+    pub max_turns: Option<usize>,
+    /// `--max-duration <duration>`: stop a run once it has been going this many seconds.
+    pub max_duration: Option<u64>,
+    /// `--trace-llm <file>`: append every provider request and response, bodies included.
+    pub trace_llm: Option<String>,
+    /// RPC mode: exit after this many seconds without commands.
+    pub idle_exit: Option<u64>,
+    /// RPC mode: exit once this process has exited.
+    pub parent_pid: Option<u32>,
+    /// `--serve`: RPC mode over WebSocket/SSE instead of stdio.
+    pub serve: bool,
+    /// `--mirror`: serve a live HTML view of the session on localhost.
+    pub mirror: bool,
+    pub port: Option<u16>,
+    pub persona: Option<String>,
+    pub auth_profile: Option<String>,
+    pub dangerously_allow_all: bool,
     pub messages: Vec<String>,
     pub file_args: Vec<String>,
+    /// `-`: read stdin into the first message (print mode), even from a terminal.
+    pub stdin: bool,
+    /// `--no-stdin`: leave piped stdin alone in print mode.
+    pub no_stdin: bool,
     pub extension_flags: std::collections::HashMap<String, ExtensionFlagValue>,
 }
 
-const VALID_TOOLS: [&str; 7] = ["read", "bash", "edit", "write", "grep", "find", "ls"];
+const VALID_TOOLS: [&str; 14] = [
+    "read",
+    "bash",
+    "edit",
+    "write",
+    "grep",
+    "find",
+    "ls",
+    "git_status",
+    "git_diff",
+    "git_log",
+    "git_commit",
+    "git_stash",
+    "notebook_read",
+    "notebook_edit",
+];
 
 pub fn is_valid_thinking_level(level: &str) -> bool {
     ThinkingLevel::parse(level).is_some()
@@ -109,6 +501,7 @@ pub fn parse_args(
         help: false,
         version: false,
         mode: None,
+        ui: None,
         no_session: false,
         session: None,
         session_dir: None,
@@ -117,14 +510,119 @@ pub fn parse_args(
         extensions: None,
         print: false,
         export: None,
+        export_new_only: false,
+        export_custom_messages: None,
+        export_theme: None,
         no_skills: false,
+        no_project_context: false,
+        context_dir: None,
+        context_globs: None,
         skills: None,
         list_models: None,
+        list_tools: false,
+        list_skills: false,
+        completions: None,
+        sessions: None,
+        tool_run: None,
+        blame: None,
+        replay: None,
+        record_fixtures: None,
+        auth: None,
+        replay_turn: None,
+        compare: None,
+        stats: None,
+        config: None,
+        models_command: None,
+        extensions_command: None,
+        batch_command: None,
+        batch: false,
+        batch_output: None,
+        seed: None,
+        auto_compact_threshold: None,
+        max_cost: None,
+        max_turns: None,
+        max_duration: None,
+        trace_llm: None,
+        idle_exit: None,
+        parent_pid: None,
+        serve: false,
+        mirror: false,
+        port: None,
+        persona: None,
+        auth_profile: None,
+        dangerously_allow_all: false,
+        stdin: false,
+        no_stdin: false,
         messages: Vec::new(),
         file_args: Vec::new(),
         extension_flags: std::collections::HashMap::new(),
     };
 
+    if args.len() >= 2 && args[0] == "tool" && args[1] == "run" {
+        result.tool_run = Some(parse_tool_run_args(&args[2..]));
+        return result;
+    }
+    if !args.is_empty() && args[0] == "completions" {
+        result.completions = Some(args.get(1).cloned().unwrap_or_default());
+        return result;
+    }
+    if !args.is_empty() && args[0] == "config" {
+        result.config = Some(parse_config_args(&args[1..]));
+        return result;
+    }
+    if !args.is_empty() && args[0] == "models" {
+        result.models_command = Some(parse_models_args(&args[1..]));
+        return result;
+    }
+    if !args.is_empty() && args[0] == "extensions" {
+        result.extensions_command = Some(ExtensionsCommand {
+            action: args.get(1).cloned(),
+            target: args.get(2).cloned(),
+        });
+        return result;
+    }
+    let subcommand_args;
+    let args = if args.len() >= 2 && args[0] == "blame" {
+        result.blame = Some(args[1].clone());
+        &args[2..]
+    } else if args.len() >= 2 && args[0] == "replay" {
+        result.replay = Some(args[1].clone());
+        &args[2..]
+    } else if !args.is_empty() && args[0] == "auth" {
+        let provider = args.get(2).filter(|arg| !arg.starts_with('-')).cloned();
+        let consumed = 2 + usize::from(provider.is_some());
+        let (command, consumed) = match args.get(1).map(String::as_str) {
+            Some("login") => (AuthCommand::Login(provider), consumed),
+            Some("logout") => (AuthCommand::Logout(provider), consumed),
+            Some("status") => (AuthCommand::Status, 2),
+            _ => (AuthCommand::Status, 1),
+        };
+        result.auth = Some(command);
+        &args[consumed..]
+    } else if !args.is_empty() && args[0] == "replay-turn" {
+        let (command, rest) = parse_replay_turn_args(&args[1..]);
+        result.replay_turn = Some(command);
+        subcommand_args = rest;
+        &subcommand_args[..]
+    } else if !args.is_empty() && args[0] == "batch" {
+        let (command, rest) = parse_batch_args(&args[1..]);
+        result.batch_command = Some(command);
+        subcommand_args = rest;
+        &subcommand_args[..]
+    } else if !args.is_empty() && args[0] == "compare" {
+        let (command, rest) = parse_compare_args(&args[1..]);
+        result.compare = Some(command);
+        subcommand_args = rest;
+        &subcommand_args[..]
+    } else if !args.is_empty() && args[0] == "stats" {
+        let (command, rest) = parse_stats_args(&args[1..]);
+        result.stats = Some(command);
+        subcommand_args = rest;
+        &subcommand_args[..]
+    } else {
+        args
+    };
+
     let mut i = 0;
     while i < args.len() {
         let arg = args[i].as_str();
@@ -142,6 +640,10 @@ pub fn parse_args(
                 }
                 i += 1;
             }
+            "--ui" if i + 1 < args.len() => {
+                result.ui = UiMode::parse(&args[i + 1]);
+                i += 1;
+            }
             "--continue" | "-c" => {
                 result.continue_session = true;
             }
@@ -171,6 +673,9 @@ pub fn parse_args(
             "--no-session" => {
                 result.no_session = true;
             }
+            "--dangerously-allow-all" => {
+                result.dangerously_allow_all = true;
+            }
             "--session" if i + 1 < args.len() => {
                 result.session = Some(args[i + 1].clone());
                 i += 1;
@@ -194,7 +699,11 @@ pub fn parse_args(
                     .collect::<Vec<_>>();
                 let mut valid = Vec::new();
                 for name in tool_names {
-                    if VALID_TOOLS.contains(&name) {
+                    if name == "git" {
+                        valid.extend(GIT_TOOL_NAMES.iter().map(|name| name.to_string()));
+                    } else if name == "notebook" {
+                        valid.extend(NOTEBOOK_TOOL_NAMES.iter().map(|name| name.to_string()));
+                    } else if VALID_TOOLS.contains(&name) {
                         valid.push(name.to_string());
                     } else {
                         eprintln!(
@@ -217,6 +726,107 @@ pub fn parse_args(
                 }
                 i += 1;
             }
+            "--seed" if i + 1 < args.len() => {
+                let value = &args[i + 1];
+                match value.parse::<u64>() {
+                    Ok(seed) => result.seed = Some(seed),
+                    Err(_) => eprintln!(
+                        "Warning: Invalid seed \"{value}\". Expected a non-negative integer"
+                    ),
+                }
+                i += 1;
+            }
+            "--max-cost" if i + 1 < args.len() => {
+                let value = &args[i + 1];
+                match value.trim_start_matches('$').parse::<f64>() {
+                    Ok(cost) if cost > 0.0 => result.max_cost = Some(cost),
+                    _ => eprintln!(
+                        "Warning: Invalid max cost \"{value}\". Expected a positive amount in USD"
+                    ),
+                }
+                i += 1;
+            }
+            "--record-fixtures" if i + 1 < args.len() => {
+                result.record_fixtures = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--max-turns" if i + 1 < args.len() => {
+                let value = &args[i + 1];
+                match value.parse::<usize>() {
+                    Ok(turns) if turns > 0 => result.max_turns = Some(turns),
+                    _ => eprintln!(
+                        "Warning: Invalid max turns \"{value}\". Expected a positive integer"
+                    ),
+                }
+                i += 1;
+            }
+            "--max-duration" if i + 1 < args.len() => {
+                let value = &args[i + 1];
+                match parse_duration_secs(value) {
+                    Some(seconds) if seconds > 0 => result.max_duration = Some(seconds),
+                    _ => eprintln!(
+                        "Warning: Invalid max duration \"{value}\". Expected seconds or a duration such as 90s, 10m or 1h"
+                    ),
+                }
+                i += 1;
+            }
+            "--trace-llm" if i + 1 < args.len() => {
+                result.trace_llm = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--auto-compact-threshold" if i + 1 < args.len() => {
+                let value = &args[i + 1];
+                match value.trim_end_matches('%').parse::<f64>() {
+                    Ok(percent) if percent > 0.0 && percent <= 100.0 => {
+                        result.auto_compact_threshold = Some(percent)
+                    }
+                    _ => eprintln!(
+                        "Warning: Invalid auto-compact threshold \"{value}\". Expected a percentage between 0 and 100"
+                    ),
+                }
+                i += 1;
+            }
+            "--idle-exit" if i + 1 < args.len() => {
+                let value = &args[i + 1];
+                match value.parse::<u64>() {
+                    Ok(seconds) if seconds > 0 => result.idle_exit = Some(seconds),
+                    _ => eprintln!(
+                        "Warning: Invalid idle exit \"{value}\". Expected a positive number of seconds"
+                    ),
+                }
+                i += 1;
+            }
+            "--parent-pid" if i + 1 < args.len() => {
+                let value = &args[i + 1];
+                match value.parse::<u32>() {
+                    Ok(pid) => result.parent_pid = Some(pid),
+                    Err(_) => eprintln!("Warning: Invalid parent pid \"{value}\""),
+                }
+                i += 1;
+            }
+            "--serve" => result.serve = true,
+            "--mirror" => result.mirror = true,
+            "--batch" => result.batch = true,
+            "--batch-output" if i + 1 < args.len() => {
+                result.batch_output = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--port" if i + 1 < args.len() => {
+                let value = &args[i + 1];
+                match value.parse::<u16>() {
+                    Ok(port) => result.port = Some(port),
+                    Err(_) => eprintln!("Warning: Invalid port \"{value}\""),
+                }
+                i += 1;
+            }
+            "--persona" if i + 1 < args.len() => {
+                result.persona = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--auth-profile" if i + 1 < args.len() => {
+                result.auth_profile = Some(args[i + 1].clone());
+                i += 1;
+            }
             "--print" | "-p" => {
                 result.print = true;
             }
@@ -224,6 +834,23 @@ pub fn parse_args(
                 result.export = Some(args[i + 1].clone());
                 i += 1;
             }
+            "--export-new-only" => {
+                result.export_new_only = true;
+            }
+            "--export-custom-messages" if i + 1 < args.len() => {
+                let value = &args[i + 1];
+                match CustomMessageDisplay::parse(value) {
+                    Some(display) => result.export_custom_messages = Some(display),
+                    None => eprintln!(
+                        "Warning: Invalid --export-custom-messages \"{value}\". Valid values: displayed, all, hidden"
+                    ),
+                }
+                i += 1;
+            }
+            "--export-theme" if i + 1 < args.len() => {
+                result.export_theme = Some(args[i + 1].clone());
+                i += 1;
+            }
             "--extension" | "-e" if i + 1 < args.len() => {
                 result
                     .extensions
@@ -234,6 +861,25 @@ pub fn parse_args(
             "--no-skills" => {
                 result.no_skills = true;
             }
+            "--no-project-context" => {
+                result.no_project_context = true;
+            }
+            "--no-stdin" => {
+                result.no_stdin = true;
+            }
+            "--context-dir" if i + 1 < args.len() => {
+                result.context_dir = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--context-glob" if i + 1 < args.len() => {
+                result.context_globs.get_or_insert_with(Vec::new).extend(
+                    args[i + 1]
+                        .split(',')
+                        .map(|value| value.trim().to_string())
+                        .filter(|value| !value.is_empty()),
+                );
+                i += 1;
+            }
             "--skills" if i + 1 < args.len() => {
                 let skills = args[i + 1]
                     .split(',')
@@ -253,6 +899,20 @@ pub fn parse_args(
                     result.list_models = Some(ListModels::All);
                 }
             }
+            "--list-tools" => result.list_tools = true,
+            "--list-skills" => result.list_skills = true,
+            "--sessions" => {
+                if i + 2 < args.len() && args[i + 1] == "search" {
+                    result.sessions = Some(SessionsCommand::Search(args[i + 2].clone()));
+                    i += 2;
+                } else {
+                    if i + 1 < args.len() && args[i + 1] == "list" {
+                        i += 1;
+                    }
+                    result.sessions = Some(SessionsCommand::List);
+                }
+            }
+            "-" => result.stdin = true,
             _ if arg.starts_with('@') => {
                 result
                     .file_args
@@ -292,3 +952,211 @@ pub fn parse_args(
 
     result
 }
+
+/// Tool arguments come as `--kebab-name value` pairs and map to the tool's camelCase
+/// parameters. Integers and `true`/`false` become JSON numbers and booleans, a flag without
+/// a value is `true`, and `--json '{...}'` supplies parameters verbatim.
+/// Takes the replay-only flags; the rest (`--session`, `--model`, ...) parse as usual.
+/// Seconds from `300`, `90s`, `10m` or `1h`.
+fn parse_duration_secs(value: &str) -> Option<u64> {
+    let (number, unit) = match value.strip_suffix(['s', 'm', 'h']) {
+        Some(number) => (number, &value[number.len()..]),
+        None => (value, "s"),
+    };
+    let number = number.parse::<u64>().ok()?;
+    match unit {
+        "m" => number.checked_mul(60),
+        "h" => number.checked_mul(3600),
+        _ => Some(number),
+    }
+}
+
+fn parse_config_args(args: &[String]) -> ConfigCommand {
+    let mut scope = None;
+    let mut positional = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--global" => scope = Some(SettingsScope::Global),
+            "--project" => scope = Some(SettingsScope::Project),
+            _ => positional.push(arg.clone()),
+        }
+    }
+    let mut positional = positional.into_iter();
+    ConfigCommand {
+        action: positional.next(),
+        key: positional.next(),
+        value: positional.next(),
+        scope,
+    }
+}
+
+fn parse_models_args(args: &[String]) -> ModelsCommand {
+    let mut positional = Vec::new();
+    let mut options = Vec::new();
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].strip_prefix("--") {
+            Some(name) => {
+                let value = match args.get(i + 1) {
+                    Some(next) if !next.starts_with("--") => {
+                        i += 1;
+                        next.clone()
+                    }
+                    _ => "true".to_string(),
+                };
+                options.push((name.to_string(), value));
+            }
+            None => positional.push(args[i].clone()),
+        }
+        i += 1;
+    }
+    let mut positional = positional.into_iter();
+    ModelsCommand {
+        action: positional.next(),
+        target: positional.next(),
+        options,
+    }
+}
+
+fn parse_batch_args(args: &[String]) -> (BatchCommand, Vec<String>) {
+    let mut command = BatchCommand {
+        action: None,
+        target: None,
+        output: None,
+    };
+    let mut rest = Vec::new();
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--output" | "-o" if i + 1 < args.len() => {
+                command.output = Some(args[i + 1].clone());
+                i += 1;
+            }
+            arg if !arg.starts_with('-') && command.action.is_none() => {
+                command.action = Some(arg.to_string());
+            }
+            arg if !arg.starts_with('-') && command.target.is_none() => {
+                command.target = Some(arg.to_string());
+            }
+            _ => rest.push(args[i].clone()),
+        }
+        i += 1;
+    }
+    (command, rest)
+}
+
+fn parse_compare_args(args: &[String]) -> (CompareCommand, Vec<String>) {
+    let mut command = CompareCommand { output: None };
+    let mut rest = Vec::new();
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--output" | "-o" if i + 1 < args.len() => {
+                command.output = Some(args[i + 1].clone());
+                i += 1;
+            }
+            _ => rest.push(args[i].clone()),
+        }
+        i += 1;
+    }
+    (command, rest)
+}
+
+fn parse_stats_args(args: &[String]) -> (StatsCommand, Vec<String>) {
+    let mut command = StatsCommand::default();
+    let mut rest = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--json" => command.json = true,
+            _ => rest.push(arg.clone()),
+        }
+    }
+    (command, rest)
+}
+
+fn parse_replay_turn_args(args: &[String]) -> (ReplayTurnCommand, Vec<String>) {
+    let mut command = ReplayTurnCommand {
+        entry: None,
+        dry_run: false,
+    };
+    let mut rest = Vec::new();
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--entry" if i + 1 < args.len() => {
+                command.entry = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--dry-run" => command.dry_run = true,
+            _ => rest.push(args[i].clone()),
+        }
+        i += 1;
+    }
+    (command, rest)
+}
+
+fn parse_tool_run_args(args: &[String]) -> ToolRunCommand {
+    let mut tool = String::new();
+    let mut params = serde_json::Map::new();
+    let mut allow_all = false;
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i].as_str();
+        match arg.strip_prefix("--") {
+            Some("dangerously-allow-all") => allow_all = true,
+            Some("json") if i + 1 < args.len() => {
+                if let Ok(serde_json::Value::Object(object)) =
+                    serde_json::from_str::<serde_json::Value>(&args[i + 1])
+                {
+                    params.extend(object);
+                }
+                i += 1;
+            }
+            Some(name) => {
+                let value = match args.get(i + 1) {
+                    Some(next) if !next.starts_with("--") => {
+                        i += 1;
+                        tool_run_value(next)
+                    }
+                    _ => serde_json::Value::Bool(true),
+                };
+                params.insert(kebab_to_camel(name), value);
+            }
+            None if tool.is_empty() => tool = arg.to_string(),
+            None => {}
+        }
+        i += 1;
+    }
+    ToolRunCommand {
+        tool,
+        params: serde_json::Value::Object(params),
+        allow_all,
+    }
+}
+
+fn tool_run_value(value: &str) -> serde_json::Value {
+    match value {
+        "true" => serde_json::Value::Bool(true),
+        "false" => serde_json::Value::Bool(false),
+        _ => value
+            .parse::<i64>()
+            .map(serde_json::Value::from)
+            .unwrap_or_else(|_| serde_json::Value::String(value.to_string())),
+    }
+}
+
+fn kebab_to_camel(name: &str) -> String {
+    let mut camel = String::new();
+    let mut upper = false;
+    for ch in name.chars() {
+        if ch == '-' {
+            upper = true;
+        } else if upper {
+            camel.extend(ch.to_uppercase());
+            upper = false;
+        } else {
+            camel.push(ch);
+        }
+    }
+    camel
+}
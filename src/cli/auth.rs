@@ -1,7 +1,17 @@
-use crate::coding_agent::{AuthCredential, AuthStorage};
+use crate::api::bedrock::{self, AwsCredentials};
+use crate::coding_agent::{
+    anthropic_refresh_token, github_refresh_copilot_token, openai_codex_refresh_token,
+    AuthCredential, AuthStorage,
+};
 use crate::config;
 use serde_json::Value;
+use std::collections::HashMap;
 use std::env;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Tokens this close to expiry are refreshed before use so they cannot lapse mid-request.
+const REFRESH_MARGIN_MS: i64 = 60_000;
 
 pub fn env_var_non_empty(key: &str) -> Option<String> {
     env::var(key).ok().and_then(|value| {
@@ -44,6 +54,11 @@ pub fn apply_env_api_keys_for_availability(auth_storage: &mut AuthStorage) {
         "mistral",
         env_var_non_empty("MISTRAL_API_KEY"),
     );
+    apply_env_key_if_missing(
+        auth_storage,
+        "amazon-bedrock",
+        env_var_non_empty("AWS_ACCESS_KEY_ID").or_else(|| env_var_non_empty("AWS_PROFILE")),
+    );
     apply_env_key_if_missing(
         auth_storage,
         "github-copilot",
@@ -53,12 +68,88 @@ pub fn apply_env_api_keys_for_availability(auth_storage: &mut AuthStorage) {
     );
 }
 
+/// Reads a stored credential, refreshing and saving an OAuth token that has expired. A
+/// failed refresh returns the stale token so the provider reports the auth error.
 fn read_auth_credential(provider: &str) -> Option<AuthCredential> {
     let path = config::get_auth_path();
-    let content = std::fs::read_to_string(path).ok()?;
+    let content = std::fs::read_to_string(&path).ok()?;
     let data: Value = serde_json::from_str(&content).ok()?;
     let entry = data.get(provider)?;
-    serde_json::from_value(entry.clone()).ok()
+    let credential: AuthCredential = serde_json::from_value(entry.clone()).ok()?;
+    if !oauth_needs_refresh(&credential, now_millis()) {
+        return Some(credential);
+    }
+    match refresh_oauth_credential(provider, &credential) {
+        Ok(refreshed) => {
+            AuthStorage::new(path).set(provider, refreshed.clone());
+            Some(refreshed)
+        }
+        Err(_) => Some(credential),
+    }
+}
+
+pub fn oauth_needs_refresh(credential: &AuthCredential, now_ms: i64) -> bool {
+    matches!(
+        credential,
+        AuthCredential::OAuth {
+            refresh: Some(_),
+            expires: Some(expires),
+            ..
+        } if *expires <= now_ms + REFRESH_MARGIN_MS
+    )
+}
+
+/// Exchanges the credential's refresh token for a new access token.
+pub fn refresh_oauth_credential(
+    provider: &str,
+    credential: &AuthCredential,
+) -> Result<AuthCredential, String> {
+    let AuthCredential::OAuth {
+        refresh: Some(refresh),
+        enterprise_url,
+        project_id,
+        email,
+        account_id,
+        ..
+    } = credential
+    else {
+        return Err(format!("No refresh token stored for {provider}"));
+    };
+    let refreshed = match provider {
+        "anthropic" => anthropic_refresh_token(refresh)?,
+        "openai-codex" => openai_codex_refresh_token(refresh)?,
+        "github-copilot" => github_refresh_copilot_token(refresh, enterprise_url.as_deref())?,
+        "google-gemini-cli" => {
+            let project_id = project_id
+                .as_deref()
+                .ok_or("No project ID stored for google-gemini-cli")?;
+            crate::api::google_gemini_cli::refresh_google_cloud_token(refresh, project_id)?
+        }
+        _ => return Err(format!("Token refresh is not supported for {provider}")),
+    };
+    let mut updated = refreshed.to_auth_credential();
+    if let AuthCredential::OAuth {
+        enterprise_url: updated_enterprise_url,
+        project_id: updated_project_id,
+        email: updated_email,
+        account_id: updated_account_id,
+        ..
+    } = &mut updated
+    {
+        // Refresh responses omit details captured at login.
+        *updated_enterprise_url = updated_enterprise_url.take().or(enterprise_url.clone());
+        *updated_project_id = updated_project_id.take().or(project_id.clone());
+        *updated_email = updated_email.take().or(email.clone());
+        *updated_account_id = updated_account_id.take().or(account_id.clone());
+    }
+    Ok(updated)
+}
+
+pub(crate) fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as i64)
+        .unwrap_or(0)
 }
 
 fn apply_env_key_if_missing(auth_storage: &mut AuthStorage, provider: &str, key: Option<String>) {
@@ -138,6 +229,214 @@ pub fn resolve_openai_codex_credentials(api_key_override: Option<&str>) -> Resul
     Err("Missing OpenAI Codex credentials. Set OPENAI_CODEX_API_KEY or add openai-codex to auth.json.".to_string())
 }
 
+/// Gemini API key for `google-generative-ai` models: auth.json's "google" entry, then
+/// GEMINI_API_KEY.
+pub fn resolve_google_credentials(api_key_override: Option<&str>) -> Result<String, String> {
+    if let Some(key) = api_key_override {
+        return Ok(key.to_string());
+    }
+
+    if let Some(credential) = read_auth_credential("google") {
+        match credential {
+            AuthCredential::ApiKey { key } => return Ok(key),
+            AuthCredential::OAuth { access, .. } => return Ok(access),
+        }
+    }
+
+    if let Some(key) = env_var_non_empty("GEMINI_API_KEY") {
+        return Ok(key);
+    }
+
+    Err("Missing Google credentials. Set GEMINI_API_KEY.".to_string())
+}
+
+/// Environment variable holding the API key for an OpenAI-compatible provider.
+pub fn openai_compatible_env_var(provider: &str) -> Option<&'static str> {
+    match provider {
+        "openai" => Some("OPENAI_API_KEY"),
+        "xai" => Some("XAI_API_KEY"),
+        "mistral" => Some("MISTRAL_API_KEY"),
+        "groq" => Some("GROQ_API_KEY"),
+        "cerebras" => Some("CEREBRAS_API_KEY"),
+        "openrouter" => Some("OPENROUTER_API_KEY"),
+        "zai" => Some("ZAI_API_KEY"),
+        _ => None,
+    }
+}
+
+/// API key for an `openai-completions` model: auth.json's entry for `provider`, then the
+/// provider's environment variable (XAI_API_KEY, MISTRAL_API_KEY, ...).
+pub fn resolve_openai_compatible_credentials(
+    provider: &str,
+    api_key_override: Option<&str>,
+) -> Result<String, String> {
+    if let Some(key) = api_key_override {
+        return Ok(key.to_string());
+    }
+
+    if let Some(credential) = read_auth_credential(provider) {
+        match credential {
+            AuthCredential::ApiKey { key } => return Ok(key),
+            AuthCredential::OAuth { access, .. } => return Ok(access),
+        }
+    }
+
+    let env_var = openai_compatible_env_var(provider);
+    if let Some(key) = env_var.and_then(env_var_non_empty) {
+        return Ok(key);
+    }
+
+    Err(match env_var {
+        Some(env_var) => format!("Missing {provider} credentials. Set {env_var}."),
+        None => format!(
+            "Missing {provider} credentials. Pass --api-key or add {provider} to auth.json."
+        ),
+    })
+}
+
+/// AWS credentials for `bedrock` models. `--api-key` and auth.json's "amazon-bedrock" entry
+/// hold JSON (`{"accessKeyId", "secretAccessKey", "sessionToken", "region", "profile"}`);
+/// otherwise AWS_ACCESS_KEY_ID/AWS_SECRET_ACCESS_KEY/AWS_SESSION_TOKEN, then the AWS_PROFILE
+/// (or default) profile in ~/.aws/credentials. The region comes from the JSON, AWS_REGION,
+/// AWS_DEFAULT_REGION or the profile's ~/.aws/config entry, in that order.
+pub fn resolve_bedrock_credentials(
+    api_key_override: Option<&str>,
+) -> Result<AwsCredentials, String> {
+    let stored = match api_key_override {
+        Some(key) => Some(key.to_string()),
+        None => match read_auth_credential("amazon-bedrock") {
+            Some(AuthCredential::ApiKey { key }) => Some(key),
+            Some(AuthCredential::OAuth { .. }) => {
+                return Err(
+                    "amazon-bedrock credentials must be an AWS key pair, not OAuth.".to_string(),
+                )
+            }
+            None => None,
+        },
+    };
+    let stored = stored
+        .map(|key| {
+            serde_json::from_str::<Value>(&key).map_err(|_| {
+                "Invalid amazon-bedrock credentials format. Expected JSON with 'accessKeyId' and 'secretAccessKey', or 'profile'.".to_string()
+            })
+        })
+        .transpose()?;
+    let field = |name: &str| {
+        stored
+            .as_ref()
+            .and_then(|value| value.get(name))
+            .and_then(Value::as_str)
+            .filter(|value| !value.trim().is_empty())
+            .map(str::to_string)
+    };
+
+    let profile = field("profile")
+        .or_else(|| env_var_non_empty("AWS_PROFILE"))
+        .unwrap_or_else(|| "default".to_string());
+    let region = field("region")
+        .or_else(|| env_var_non_empty("AWS_REGION"))
+        .or_else(|| env_var_non_empty("AWS_DEFAULT_REGION"))
+        .or_else(|| {
+            let section = if profile == "default" {
+                profile.clone()
+            } else {
+                format!("profile {profile}")
+            };
+            read_aws_profile(&aws_file("AWS_CONFIG_FILE", "config"), &section).remove("region")
+        })
+        .unwrap_or_else(|| bedrock::DEFAULT_REGION.to_string());
+
+    if let (Some(access_key_id), Some(secret_access_key)) =
+        (field("accessKeyId"), field("secretAccessKey"))
+    {
+        return Ok(AwsCredentials {
+            access_key_id,
+            secret_access_key,
+            session_token: field("sessionToken"),
+            region,
+        });
+    }
+    if stored.is_some() && field("profile").is_none() {
+        return Err(
+            "amazon-bedrock credentials need 'accessKeyId' and 'secretAccessKey', or 'profile'."
+                .to_string(),
+        );
+    }
+
+    if stored.is_none() {
+        if let (Some(access_key_id), Some(secret_access_key)) = (
+            env_var_non_empty("AWS_ACCESS_KEY_ID"),
+            env_var_non_empty("AWS_SECRET_ACCESS_KEY"),
+        ) {
+            return Ok(AwsCredentials {
+                access_key_id,
+                secret_access_key,
+                session_token: env_var_non_empty("AWS_SESSION_TOKEN"),
+                region,
+            });
+        }
+    }
+
+    let mut entries = read_aws_profile(
+        &aws_file("AWS_SHARED_CREDENTIALS_FILE", "credentials"),
+        &profile,
+    );
+    if let (Some(access_key_id), Some(secret_access_key)) = (
+        entries.remove("aws_access_key_id"),
+        entries.remove("aws_secret_access_key"),
+    ) {
+        return Ok(AwsCredentials {
+            access_key_id,
+            secret_access_key,
+            session_token: entries.remove("aws_session_token"),
+            region,
+        });
+    }
+
+    Err(format!(
+        "Missing AWS credentials for amazon-bedrock. Set AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY, or add profile \"{profile}\" to ~/.aws/credentials."
+    ))
+}
+
+fn aws_file(env_key: &str, name: &str) -> PathBuf {
+    env_var_non_empty(env_key)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            env::var_os("HOME")
+                .or_else(|| env::var_os("USERPROFILE"))
+                .map(PathBuf::from)
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join(".aws")
+                .join(name)
+        })
+}
+
+/// `key = value` pairs from the `[section]` of an AWS ini file.
+fn read_aws_profile(path: &Path, section: &str) -> HashMap<String, String> {
+    let mut entries = HashMap::new();
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return entries;
+    };
+    let mut in_section = false;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|rest| rest.strip_suffix(']'))
+        {
+            in_section = name.trim() == section;
+        } else if in_section {
+            if let Some((key, value)) = line.split_once('=') {
+                entries.insert(key.trim().to_string(), value.trim().to_string());
+            }
+        }
+    }
+    entries
+}
+
 /// Gemini CLI credentials structure from ~/.gemini/oauth_creds.json
 #[derive(serde::Deserialize)]
 struct GeminiCliOAuthCreds {
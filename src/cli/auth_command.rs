@@ -0,0 +1,227 @@
+//! `pi auth login|logout|status`: run a provider's OAuth flow from the terminal and manage
+//! the credentials stored in auth.json.
+
+use crate::cli::args::AuthCommand;
+use crate::coding_agent::{
+    anthropic_exchange_code, anthropic_get_auth_url, get_oauth_providers, github_poll_for_token,
+    github_refresh_copilot_token, github_start_device_flow, open_browser,
+    openai_codex_exchange_code, openai_codex_get_auth_url, openai_codex_login_with_input,
+    AuthCredential, AuthStorage, OAuthCallbackServer, OAuthCredentials,
+};
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How long to wait for the browser to hit the local callback before asking for a paste.
+const CALLBACK_TIMEOUT_SECS: u64 = 300;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AuthState {
+    ApiKey,
+    OAuth {
+        expires: Option<i64>,
+    },
+    /// Not stored, but available from an environment variable.
+    Environment,
+    Missing,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuthStatus {
+    /// auth.json key: the provider, or `provider:profile` for a named profile.
+    pub provider: String,
+    pub state: AuthState,
+}
+
+pub fn run_auth_command(command: &AuthCommand, storage: &mut AuthStorage) -> Result<(), String> {
+    match command {
+        AuthCommand::Login(provider) => {
+            let provider = match provider {
+                Some(provider) => provider.clone(),
+                None => choose_provider()?,
+            };
+            let credentials = login(&provider)?;
+            storage.set(&provider, credentials.to_auth_credential());
+            println!(
+                "Logged in to {provider}. Credentials saved to {}",
+                storage.path().display()
+            );
+            Ok(())
+        }
+        AuthCommand::Logout(None) => Err("Usage: pi auth logout <provider>".to_string()),
+        AuthCommand::Logout(Some(provider)) => {
+            if logout(storage, provider) {
+                println!("Logged out of {provider}");
+                Ok(())
+            } else {
+                Err(format!("No stored credentials for {provider}"))
+            }
+        }
+        AuthCommand::Status => {
+            print_auth_status(&auth_status(storage), now_millis());
+            Ok(())
+        }
+    }
+}
+
+/// Removes the stored credential for `provider`; false when there was none.
+pub fn logout(storage: &mut AuthStorage, provider: &str) -> bool {
+    if !storage.has(provider) {
+        return false;
+    }
+    storage.remove(provider);
+    true
+}
+
+/// Every stored credential plus each OAuth provider, sorted by provider.
+pub fn auth_status(storage: &AuthStorage) -> Vec<AuthStatus> {
+    let mut providers = storage.list();
+    for provider in get_oauth_providers() {
+        if !providers.contains(&provider.id) {
+            providers.push(provider.id);
+        }
+    }
+    providers.sort();
+    providers
+        .into_iter()
+        .map(|provider| {
+            let state = match storage.get(&provider) {
+                Some(AuthCredential::ApiKey { .. }) => AuthState::ApiKey,
+                Some(AuthCredential::OAuth { expires, .. }) => {
+                    AuthState::OAuth { expires: *expires }
+                }
+                None if storage.has_auth(&provider) => AuthState::Environment,
+                None => AuthState::Missing,
+            };
+            AuthStatus { provider, state }
+        })
+        .collect()
+}
+
+fn print_auth_status(statuses: &[AuthStatus], now: i64) {
+    let width = statuses
+        .iter()
+        .map(|status| status.provider.len())
+        .max()
+        .unwrap_or(0);
+    for status in statuses {
+        let description = match status.state {
+            AuthState::ApiKey => "api key".to_string(),
+            AuthState::OAuth { expires: None } => "oauth".to_string(),
+            AuthState::OAuth {
+                expires: Some(expires),
+            } if expires <= now => "oauth, expired (refreshed on next use)".to_string(),
+            AuthState::OAuth {
+                expires: Some(expires),
+            } => format!("oauth, expires in {}", format_remaining(expires - now)),
+            AuthState::Environment => "from environment".to_string(),
+            AuthState::Missing => "not logged in".to_string(),
+        };
+        println!("{:<width$}  {description}", status.provider);
+    }
+}
+
+fn format_remaining(millis: i64) -> String {
+    let minutes = millis / 60_000;
+    if minutes >= 24 * 60 {
+        format!("{}d {}h", minutes / (24 * 60), minutes % (24 * 60) / 60)
+    } else if minutes >= 60 {
+        format!("{}h {}m", minutes / 60, minutes % 60)
+    } else {
+        format!("{minutes}m")
+    }
+}
+
+fn login(provider: &str) -> Result<OAuthCredentials, String> {
+    match provider {
+        "anthropic" => {
+            let (url, verifier) = anthropic_get_auth_url();
+            show_auth_url(&url);
+            let code = prompt_line("Paste the authorization code (code#state): ")?;
+            anthropic_exchange_code(&code, &verifier)
+        }
+        "openai-codex" => {
+            let (url, verifier, state) = openai_codex_get_auth_url();
+            let server = OAuthCallbackServer::start(&state);
+            show_auth_url(&url);
+            if server.is_available() {
+                println!("Waiting for the browser to finish login...");
+                if let Some(code) = server.wait_for_code(CALLBACK_TIMEOUT_SECS) {
+                    return openai_codex_exchange_code(&code, &verifier);
+                }
+                server.cancel();
+                println!("No callback received.");
+            }
+            let input = prompt_line("Paste the redirect URL or code: ")?;
+            openai_codex_login_with_input(&input, &verifier, &state)
+        }
+        "github-copilot" => {
+            let device = github_start_device_flow("github.com")?;
+            println!(
+                "Open {} and enter the code {}",
+                device.verification_uri, device.user_code
+            );
+            open_browser(&device.verification_uri);
+            let token = github_poll_for_token(
+                "github.com",
+                &device.device_code,
+                device.interval,
+                device.expires_in,
+                Arc::new(AtomicBool::new(false)),
+            )?;
+            github_refresh_copilot_token(&token, None)
+        }
+        _ => Err(format!(
+            "Unknown OAuth provider: {provider}. Available: {}",
+            get_oauth_providers()
+                .iter()
+                .map(|provider| provider.id.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+fn choose_provider() -> Result<String, String> {
+    let providers = get_oauth_providers();
+    for (index, provider) in providers.iter().enumerate() {
+        println!("  {}. {} ({})", index + 1, provider.name, provider.id);
+    }
+    let choice = prompt_line("Provider: ")?;
+    choice
+        .parse::<usize>()
+        .ok()
+        .and_then(|index| index.checked_sub(1))
+        .and_then(|index| providers.get(index))
+        .or_else(|| providers.iter().find(|provider| provider.id == choice))
+        .map(|provider| provider.id.clone())
+        .ok_or_else(|| format!("Unknown provider: {choice}"))
+}
+
+fn show_auth_url(url: &str) {
+    println!("Open this URL to log in:\n\n  {url}\n");
+    open_browser(url);
+}
+
+fn prompt_line(prompt: &str) -> Result<String, String> {
+    print!("{prompt}");
+    io::stdout().flush().map_err(|err| err.to_string())?;
+    let mut line = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .map_err(|err| err.to_string())?;
+    let line = line.trim().to_string();
+    if line.is_empty() {
+        return Err("Login cancelled".to_string());
+    }
+    Ok(line)
+}
+
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as i64)
+        .unwrap_or(0)
+}
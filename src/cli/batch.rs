@@ -0,0 +1,444 @@
+//! `pi -p --batch`: send each prompt through the provider's batch API (Anthropic message
+//! batches, OpenAI background responses) instead of streaming it, wait for the results and
+//! write one file per prompt. Jobs are recorded under the agent dir, so `pi batch status|fetch`
+//! can pick them up after pi exits.
+
+use crate::agent::{AgentMessage, LlmContext};
+use crate::api::batch::{
+    fetch_anthropic_batch_results, get_anthropic_batch, get_openai_response,
+    submit_anthropic_batch, submit_openai_background, BatchCallOptions,
+};
+use crate::api::{
+    assistant_message_from_anthropic, build_anthropic_messages,
+    openai_assistant_message_from_response, openai_context_to_input_items,
+};
+use crate::cli::args::BatchCommand;
+use crate::cli::auth::{resolve_anthropic_credentials, resolve_openai_credentials};
+use crate::cli::replay_turn::response_text;
+use crate::coding_agent::Model as RegistryModel;
+use crate::core::messages::{UserContent, UserMessage};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const BATCH_APIS: [&str; 2] = ["anthropic-messages", "openai-responses"];
+pub const BATCH_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+const USAGE: &str = concat!(
+    "Usage: pi batch list\n",
+    "       pi batch status <id>\n",
+    "       pi batch fetch <id> [--output <dir>]"
+);
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchJob {
+    pub id: String,
+    pub model: RegistryModel,
+    pub created_at: i64,
+    /// Where results are written unless `pi batch fetch --output` says otherwise.
+    pub output_dir: String,
+    /// The Anthropic message batch, for `anthropic-messages` models.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch_id: Option<String>,
+    pub requests: Vec<BatchRequest>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchRequest {
+    pub custom_id: String,
+    pub prompt: String,
+    /// The background response, for `openai-responses` models.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_id: Option<String>,
+}
+
+/// A prompt's custom id and its reply text, or the reason it has none.
+pub type BatchResult = (String, Result<String, String>);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BatchProgress {
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub finished: bool,
+}
+
+impl BatchProgress {
+    pub fn summary(&self) -> String {
+        let state = if self.finished { "ended" } else { "running" };
+        format!(
+            "{state}: {} succeeded, {} failed, {} pending of {}",
+            self.succeeded,
+            self.failed,
+            self.total.saturating_sub(self.succeeded + self.failed),
+            self.total
+        )
+    }
+}
+
+impl BatchJob {
+    pub fn path(dir: &Path, id: &str) -> PathBuf {
+        dir.join(format!("{id}.json"))
+    }
+
+    pub fn load(dir: &Path, id: &str) -> Result<Self, String> {
+        let path = Self::path(dir, id);
+        let content =
+            fs::read_to_string(&path).map_err(|_| format!("No batch job \"{id}\" recorded"))?;
+        serde_json::from_str(&content)
+            .map_err(|err| format!("Failed to parse {}: {err}", path.display()))
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<(), String> {
+        fs::create_dir_all(dir)
+            .map_err(|err| format!("Failed to create {}: {err}", dir.display()))?;
+        let path = Self::path(dir, &self.id);
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|err| format!("Failed to serialize batch job: {err}"))?;
+        fs::write(&path, content)
+            .map_err(|err| format!("Failed to write {}: {err}", path.display()))
+    }
+
+    /// Recorded jobs, newest first.
+    pub fn list(dir: &Path) -> Vec<Self> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut jobs = entries
+            .flatten()
+            .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+            .filter_map(|content| serde_json::from_str::<Self>(&content).ok())
+            .collect::<Vec<_>>();
+        jobs.sort_by_key(|job| std::cmp::Reverse(job.created_at));
+        jobs
+    }
+}
+
+struct Credentials {
+    api_key: String,
+    use_oauth: bool,
+}
+
+fn resolve_credentials(
+    model: &RegistryModel,
+    api_key_override: Option<&str>,
+) -> Result<Credentials, String> {
+    match model.api.as_str() {
+        "anthropic-messages" => {
+            let (api_key, use_oauth) = resolve_anthropic_credentials(api_key_override)?;
+            Ok(Credentials { api_key, use_oauth })
+        }
+        "openai-responses" => Ok(Credentials {
+            api_key: resolve_openai_credentials(api_key_override)?,
+            use_oauth: false,
+        }),
+        other => Err(format!(
+            "--batch does not support \"{other}\" models (supported: {})",
+            BATCH_APIS.join(", ")
+        )),
+    }
+}
+
+fn call_options<'a>(
+    model: &'a RegistryModel,
+    credentials: &'a Credentials,
+    system: Option<&'a str>,
+) -> BatchCallOptions<'a> {
+    let base_url = match (model.base_url.as_str(), model.api.as_str()) {
+        ("", "anthropic-messages") => "https://api.anthropic.com/v1",
+        ("", _) => "https://api.openai.com/v1",
+        (base_url, _) => base_url,
+    };
+    BatchCallOptions {
+        model: &model.id,
+        api_key: &credentials.api_key,
+        use_oauth: credentials.use_oauth,
+        base_url,
+        extra_headers: model.headers.as_ref(),
+        system,
+        max_tokens: u32::try_from(model.max_tokens)
+            .ok()
+            .filter(|max_tokens| *max_tokens > 0)
+            .unwrap_or(1024),
+    }
+}
+
+fn prompt_context(system_prompt: &str, prompt: &str) -> LlmContext {
+    LlmContext {
+        system_prompt: system_prompt.to_string(),
+        messages: vec![AgentMessage::User(UserMessage {
+            content: UserContent::Text(prompt.to_string()),
+            timestamp: now_millis(),
+        })],
+    }
+}
+
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// Submits every prompt as its own single-turn request (tools are not offered, since nothing
+/// runs them) and returns the job to record.
+pub fn submit_batch(
+    model: &RegistryModel,
+    system_prompt: &str,
+    prompts: &[String],
+    output_dir: Option<&str>,
+    api_key_override: Option<&str>,
+) -> Result<BatchJob, String> {
+    if prompts.is_empty() {
+        return Err("--batch needs at least one prompt".to_string());
+    }
+    let credentials = resolve_credentials(model, api_key_override)?;
+    let system = Some(system_prompt).filter(|system| !system.trim().is_empty());
+    let options = call_options(model, &credentials, system);
+    let mut requests = prompts
+        .iter()
+        .enumerate()
+        .map(|(index, prompt)| BatchRequest {
+            custom_id: format!("prompt-{:03}", index + 1),
+            prompt: prompt.clone(),
+            response_id: None,
+        })
+        .collect::<Vec<_>>();
+
+    let mut batch_id = None;
+    if model.api == "anthropic-messages" {
+        let messages = requests
+            .iter()
+            .map(|request| {
+                let context = prompt_context("", &request.prompt);
+                (
+                    request.custom_id.clone(),
+                    build_anthropic_messages(&context),
+                )
+            })
+            .collect();
+        batch_id = Some(submit_anthropic_batch(messages, &options)?.id);
+    } else {
+        // OpenAI takes the system prompt as an input item rather than a request field.
+        for request in &mut requests {
+            let context = prompt_context(system_prompt, &request.prompt);
+            let input = openai_context_to_input_items(model, &context);
+            request.response_id = Some(submit_openai_background(input, &options)?.id);
+        }
+    }
+
+    let id = uuid::Uuid::new_v4().simple().to_string()[..12].to_string();
+    Ok(BatchJob {
+        output_dir: output_dir
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("pi-batch-{id}")),
+        id,
+        model: model.clone(),
+        created_at: now_millis(),
+        batch_id,
+        requests,
+    })
+}
+
+pub fn batch_progress(
+    job: &BatchJob,
+    api_key_override: Option<&str>,
+) -> Result<BatchProgress, String> {
+    let credentials = resolve_credentials(&job.model, api_key_override)?;
+    let options = call_options(&job.model, &credentials, None);
+    let total = job.requests.len();
+    if let Some(batch_id) = &job.batch_id {
+        let batch = get_anthropic_batch(batch_id, &options)?;
+        let counts = batch.request_counts;
+        return Ok(BatchProgress {
+            total,
+            succeeded: counts.succeeded as usize,
+            failed: (counts.errored + counts.canceled + counts.expired) as usize,
+            finished: batch.processing_status == "ended",
+        });
+    }
+    let mut progress = BatchProgress {
+        total,
+        ..Default::default()
+    };
+    for request in &job.requests {
+        let Some(response_id) = &request.response_id else {
+            progress.failed += 1;
+            continue;
+        };
+        let response = get_openai_response(response_id, &options)?;
+        if !response.is_finished() {
+            continue;
+        }
+        if response.error_message().is_some() {
+            progress.failed += 1;
+        } else {
+            progress.succeeded += 1;
+        }
+    }
+    progress.finished = progress.succeeded + progress.failed == total;
+    Ok(progress)
+}
+
+/// Polls until the job has finished, reporting progress after every check.
+pub fn wait_for_batch(
+    job: &BatchJob,
+    api_key_override: Option<&str>,
+    interval: Duration,
+    mut on_progress: impl FnMut(&BatchProgress),
+) -> Result<BatchProgress, String> {
+    loop {
+        let progress = batch_progress(job, api_key_override)?;
+        on_progress(&progress);
+        if progress.finished {
+            return Ok(progress);
+        }
+        thread::sleep(interval);
+    }
+}
+
+/// Every prompt's result, in submission order.
+pub fn fetch_batch(
+    job: &BatchJob,
+    api_key_override: Option<&str>,
+) -> Result<Vec<BatchResult>, String> {
+    let credentials = resolve_credentials(&job.model, api_key_override)?;
+    let options = call_options(&job.model, &credentials, None);
+    if let Some(batch_id) = &job.batch_id {
+        let batch = get_anthropic_batch(batch_id, &options)?;
+        if batch.processing_status != "ended" {
+            return Err(format!("Batch {} is still running", job.id));
+        }
+        let mut results = fetch_anthropic_batch_results(&batch, &options)?;
+        return Ok(job
+            .requests
+            .iter()
+            .map(|request| {
+                let result = match results
+                    .iter()
+                    .position(|result| result.custom_id == request.custom_id)
+                {
+                    Some(index) => results.swap_remove(index).result.map(|response| {
+                        response_text(&assistant_message_from_anthropic(&job.model, response))
+                    }),
+                    None => Err("No result returned".to_string()),
+                };
+                (request.custom_id.clone(), result)
+            })
+            .collect());
+    }
+    let mut results = Vec::new();
+    for request in &job.requests {
+        let Some(response_id) = &request.response_id else {
+            results.push((request.custom_id.clone(), Err("Not submitted".to_string())));
+            continue;
+        };
+        let response = get_openai_response(response_id, &options)?;
+        if !response.is_finished() {
+            return Err(format!("Batch {} is still running", job.id));
+        }
+        let result = match response.error_message() {
+            Some(error) => Err(error),
+            None => openai_assistant_message_from_response(&job.model, response.into_response())
+                .map(|message| response_text(&message)),
+        };
+        results.push((request.custom_id.clone(), result));
+    }
+    Ok(results)
+}
+
+/// Writes `<id>.md` for every reply and `<id>.error.txt` for every failure.
+pub fn write_batch_results(
+    output_dir: &Path,
+    results: &[BatchResult],
+) -> Result<Vec<PathBuf>, String> {
+    fs::create_dir_all(output_dir)
+        .map_err(|err| format!("Failed to create {}: {err}", output_dir.display()))?;
+    let mut paths = Vec::new();
+    for (custom_id, result) in results {
+        let (path, content) = match result {
+            Ok(text) => (output_dir.join(format!("{custom_id}.md")), text),
+            Err(error) => (output_dir.join(format!("{custom_id}.error.txt")), error),
+        };
+        fs::write(&path, content)
+            .map_err(|err| format!("Failed to write {}: {err}", path.display()))?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// Runs `pi -p --batch`: submit, record, wait and write the results.
+pub fn run_batch_prompts(
+    model: &RegistryModel,
+    system_prompt: &str,
+    prompts: &[String],
+    output_dir: Option<&str>,
+    api_key_override: Option<&str>,
+    jobs_dir: &Path,
+) -> Result<(), String> {
+    let job = submit_batch(model, system_prompt, prompts, output_dir, api_key_override)?;
+    job.save(jobs_dir)?;
+    eprintln!(
+        "Submitted batch {} ({} prompts to {}); check later with `pi batch status {}`",
+        job.id,
+        job.requests.len(),
+        job.model.id,
+        job.id
+    );
+    wait_for_batch(&job, api_key_override, BATCH_POLL_INTERVAL, |progress| {
+        eprintln!("Batch {} {}", job.id, progress.summary());
+    })?;
+    let results = fetch_batch(&job, api_key_override)?;
+    for path in write_batch_results(Path::new(&job.output_dir), &results)? {
+        println!("{}", path.display());
+    }
+    Ok(())
+}
+
+pub fn run_batch_command(
+    command: &BatchCommand,
+    jobs_dir: &Path,
+    api_key_override: Option<&str>,
+) -> Result<(), String> {
+    match command.action.as_deref() {
+        None | Some("list") => {
+            let jobs = BatchJob::list(jobs_dir);
+            if jobs.is_empty() {
+                println!("No batch jobs recorded");
+            }
+            for job in jobs {
+                println!(
+                    "{}  {}/{}  {} prompts  -> {}",
+                    job.id,
+                    job.model.provider,
+                    job.model.id,
+                    job.requests.len(),
+                    job.output_dir
+                );
+            }
+            Ok(())
+        }
+        Some("status") => {
+            let id = command.target.as_deref().ok_or_else(|| USAGE.to_string())?;
+            let job = BatchJob::load(jobs_dir, id)?;
+            let progress = batch_progress(&job, api_key_override)?;
+            println!("Batch {id} {}", progress.summary());
+            Ok(())
+        }
+        Some("fetch") => {
+            let id = command.target.as_deref().ok_or_else(|| USAGE.to_string())?;
+            let job = BatchJob::load(jobs_dir, id)?;
+            let output_dir = command.output.as_deref().unwrap_or(&job.output_dir);
+            let results = fetch_batch(&job, api_key_override)?;
+            for path in write_batch_results(Path::new(output_dir), &results)? {
+                println!("{}", path.display());
+            }
+            Ok(())
+        }
+        Some(_) => Err(USAGE.to_string()),
+    }
+}
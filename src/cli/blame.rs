@@ -0,0 +1,226 @@
+//! `pi blame <file>`: git blame annotated with the agent session and turn that wrote each
+//! line, found by matching blamed lines against the write/edit tool calls recorded in the
+//! project's sessions.
+
+use crate::coding_agent::run_git;
+use crate::coding_agent::sandbox::normalize_path;
+use crate::core::messages::{parse_timestamp_millis, AgentMessage, ContentBlock};
+use crate::core::session_manager::{load_entries_from_file, FileEntry, SessionManager};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AgentAttribution {
+    pub session_id: String,
+    pub session_path: PathBuf,
+    /// 1-based index of the user prompt the change answered.
+    pub turn: usize,
+    pub tool_call_id: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlameLine {
+    pub line_number: usize,
+    pub commit: String,
+    pub author: String,
+    pub content: String,
+    /// Set when a session's write or edit produced this line before it was committed.
+    pub agent: Option<AgentAttribution>,
+}
+
+/// A successful write or edit of the blamed file.
+struct AgentChange {
+    attribution: AgentAttribution,
+    timestamp: i64,
+    lines: HashSet<String>,
+}
+
+pub fn blame_file(
+    path: &Path,
+    cwd: &Path,
+    session_dir: Option<&str>,
+) -> Result<Vec<BlameLine>, String> {
+    let target = normalize_path(&cwd.join(path));
+    let blame_cwd = target.parent().unwrap_or(cwd);
+    let output = run_git(
+        blame_cwd,
+        &["blame", "--line-porcelain", "--", &target.to_string_lossy()],
+    )?;
+    let changes = collect_agent_changes(&target, cwd, session_dir);
+
+    let mut lines = parse_line_porcelain(&output);
+    for index in 0..lines.len() {
+        let (line, author_time) = &lines[index];
+        let trimmed = line.content.trim();
+        let agent = if trimmed.chars().any(char::is_alphanumeric) {
+            // Git times have second resolution; a change may land in the same second.
+            let committed_by = (author_time + 1) * 1000;
+            changes
+                .iter()
+                .filter(|change| change.timestamp <= committed_by && change.lines.contains(trimmed))
+                .max_by_key(|change| change.timestamp)
+                .map(|change| change.attribution.clone())
+        } else {
+            // Braces and blank lines match everywhere; credit them like the line above.
+            index
+                .checked_sub(1)
+                .map(|previous| &lines[previous].0)
+                .filter(|previous| previous.commit == line.commit)
+                .and_then(|previous| previous.agent.clone())
+        };
+        lines[index].0.agent = agent;
+    }
+    Ok(lines.into_iter().map(|(line, _)| line).collect())
+}
+
+pub fn print_blame(path: &Path, cwd: &Path, session_dir: Option<&str>) -> Result<(), String> {
+    let lines = blame_file(path, cwd, session_dir)?;
+    let labels = lines
+        .iter()
+        .map(|line| match &line.agent {
+            Some(agent) => format!(
+                "agent {}#{}",
+                agent.session_id.chars().take(8).collect::<String>(),
+                agent.turn
+            ),
+            None => line.author.clone(),
+        })
+        .collect::<Vec<_>>();
+    let label_width = labels
+        .iter()
+        .map(|label| label.chars().count())
+        .max()
+        .unwrap_or(0);
+    let number_width = lines.len().to_string().len();
+    for (line, label) in lines.iter().zip(&labels) {
+        println!(
+            "{} {label:<label_width$} {:>number_width$}| {}",
+            line.commit.chars().take(8).collect::<String>(),
+            line.line_number,
+            line.content
+        );
+    }
+    let agent_lines = lines.iter().filter(|line| line.agent.is_some()).count();
+    println!(
+        "\n{agent_lines} of {} lines written by agent sessions",
+        lines.len()
+    );
+    Ok(())
+}
+
+/// Blamed lines with each line's author time (Unix seconds).
+fn parse_line_porcelain(output: &str) -> Vec<(BlameLine, i64)> {
+    let mut lines = Vec::new();
+    let mut commit = String::new();
+    let mut line_number = 0;
+    let mut author = String::new();
+    let mut author_time = 0;
+    let mut expect_header = true;
+    for raw in output.lines() {
+        if let Some(content) = raw.strip_prefix('\t') {
+            lines.push((
+                BlameLine {
+                    line_number,
+                    commit: commit.clone(),
+                    author: author.clone(),
+                    content: content.to_string(),
+                    agent: None,
+                },
+                author_time,
+            ));
+            expect_header = true;
+        } else if expect_header {
+            let mut parts = raw.split_whitespace();
+            commit = parts.next().unwrap_or_default().to_string();
+            line_number = parts
+                .nth(1)
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(0);
+            expect_header = false;
+        } else if let Some(value) = raw.strip_prefix("author ") {
+            author = value.to_string();
+        } else if let Some(value) = raw.strip_prefix("author-time ") {
+            author_time = value.parse().unwrap_or(0);
+        }
+    }
+    lines
+}
+
+fn collect_agent_changes(target: &Path, cwd: &Path, session_dir: Option<&str>) -> Vec<AgentChange> {
+    let mut changes = Vec::new();
+    for session in SessionManager::list(cwd, session_dir.map(PathBuf::from)) {
+        let entries = load_entries_from_file(&session.path);
+        let session_cwd = match entries.first() {
+            Some(FileEntry::Session(header)) => PathBuf::from(&header.cwd),
+            _ => continue,
+        };
+        let mut turn = 0;
+        let mut pending = HashMap::new();
+        for entry in entries {
+            let FileEntry::Message(entry) = entry else {
+                continue;
+            };
+            match entry.message {
+                AgentMessage::User(_) => turn += 1,
+                AgentMessage::Assistant(message) => {
+                    for block in message.content {
+                        let ContentBlock::ToolCall {
+                            id,
+                            name,
+                            arguments,
+                            ..
+                        } = block
+                        else {
+                            continue;
+                        };
+                        let Some(written) = written_text(&name, &arguments) else {
+                            continue;
+                        };
+                        let tool_path = arguments.get("path").and_then(Value::as_str);
+                        let Some(tool_path) = tool_path else {
+                            continue;
+                        };
+                        if normalize_path(&session_cwd.join(tool_path)) == target {
+                            pending.insert(id, (turn, written));
+                        }
+                    }
+                }
+                AgentMessage::ToolResult(result) if !result.is_error => {
+                    let Some((turn, written)) = pending.remove(&result.tool_call_id) else {
+                        continue;
+                    };
+                    changes.push(AgentChange {
+                        attribution: AgentAttribution {
+                            session_id: session.id.clone(),
+                            session_path: session.path.clone(),
+                            turn,
+                            tool_call_id: result.tool_call_id,
+                        },
+                        timestamp: parse_timestamp_millis(&entry.timestamp),
+                        lines: written
+                            .lines()
+                            .map(str::trim)
+                            .filter(|line| !line.is_empty())
+                            .map(str::to_string)
+                            .collect(),
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+    changes
+}
+
+fn written_text(tool_name: &str, arguments: &Value) -> Option<String> {
+    let key = match tool_name {
+        "write" => "content",
+        "edit" => "newText",
+        _ => return None,
+    };
+    arguments
+        .get(key)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
@@ -0,0 +1,69 @@
+//! `--list-tools` and `--list-skills`: what the agent can use, for scripts and users.
+
+use crate::coding_agent::extension_host::ExtensionTool;
+use crate::coding_agent::Skill;
+use crate::tools::{default_tool_names, default_tools, GIT_TOOL_NAMES, NOTEBOOK_TOOL_NAMES};
+
+/// Built-in tools (marking the ones enabled without `--tools`) and the tools extensions
+/// register, one per line with its description.
+pub fn format_tool_list(extension_tools: &[ExtensionTool]) -> String {
+    let defaults = default_tool_names();
+    let mut rows = default_tools()
+        .into_iter()
+        .map(|tool| {
+            let note = if defaults.iter().any(|name| name == tool.name) {
+                "default"
+            } else if GIT_TOOL_NAMES.contains(&tool.name) {
+                "--tools git"
+            } else if NOTEBOOK_TOOL_NAMES.contains(&tool.name) {
+                "--tools notebook"
+            } else {
+                "--tools"
+            };
+            (
+                tool.name.to_string(),
+                format!("{} ({note})", tool.description),
+            )
+        })
+        .collect::<Vec<_>>();
+    for tool in extension_tools {
+        let description = tool.description.as_deref().unwrap_or("");
+        rows.push((tool.name.clone(), format!("{description} (extension)")));
+    }
+    format_rows(&rows)
+}
+
+pub fn format_skill_list(skills: &[Skill]) -> String {
+    if skills.is_empty() {
+        return "No skills found.\n".to_string();
+    }
+    let rows = skills
+        .iter()
+        .map(|skill| {
+            let arguments = skill
+                .arguments
+                .iter()
+                .map(|argument| match argument.required {
+                    true => format!(" <{}>", argument.name),
+                    false => format!(" [{}]", argument.name),
+                })
+                .collect::<String>();
+            (
+                format!("{}{arguments}", skill.name),
+                format!("{} ({})", skill.description, skill.file_path),
+            )
+        })
+        .collect::<Vec<_>>();
+    format_rows(&rows)
+}
+
+fn format_rows(rows: &[(String, String)]) -> String {
+    let width = rows
+        .iter()
+        .map(|(name, _)| name.chars().count())
+        .max()
+        .unwrap_or(0);
+    rows.iter()
+        .map(|(name, description)| format!("{name:<width$}  {description}\n"))
+        .collect()
+}
@@ -0,0 +1,189 @@
+//! `pi compare --models a,b,c "prompt"`: send one prompt to several models at once and show
+//! the replies side by side with each model's latency and cost.
+
+use crate::agent::{AgentMessage, LlmContext};
+use crate::cli::replay_turn::response_text;
+use crate::coding_agent::Model as RegistryModel;
+use crate::core::messages::{AssistantMessage, UserContent, UserMessage};
+use crate::tui::utils::{visible_width, wrap_text_with_ansi};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Narrowest column before the comparison falls back to one response after another.
+const MIN_COLUMN_WIDTH: usize = 24;
+
+#[derive(Clone, Debug)]
+pub struct CompareResult {
+    pub model: RegistryModel,
+    pub response: Result<AssistantMessage, String>,
+    pub latency: Duration,
+}
+
+impl CompareResult {
+    pub fn label(&self) -> String {
+        format!("{}/{}", self.model.provider, self.model.id)
+    }
+
+    /// Dollars spent on the reply, as priced by the provider or else by the registry.
+    pub fn cost(&self) -> f64 {
+        let model = &self.model;
+        let Ok(message) = &self.response else {
+            return 0.0;
+        };
+        match &message.usage.cost {
+            Some(cost) => cost.total,
+            None => {
+                let usage = &message.usage;
+                (model.cost.input * usage.input as f64
+                    + model.cost.output * usage.output as f64
+                    + model.cost.cache_read * usage.cache_read as f64
+                    + model.cost.cache_write * usage.cache_write as f64)
+                    / 1_000_000.0
+            }
+        }
+    }
+
+    fn summary(&self) -> String {
+        format!("{:.1}s · ${:.4}", self.latency.as_secs_f64(), self.cost())
+    }
+
+    fn body(&self) -> String {
+        match &self.response {
+            Ok(message) => response_text(message),
+            Err(err) => format!("[error] {err}"),
+        }
+    }
+}
+
+/// The single-message context every model is sent.
+pub fn compare_context(system_prompt: String, prompt: &str) -> LlmContext {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as i64)
+        .unwrap_or(0);
+    LlmContext {
+        system_prompt,
+        messages: vec![AgentMessage::User(UserMessage {
+            content: UserContent::Text(prompt.to_string()),
+            timestamp,
+        })],
+    }
+}
+
+/// Runs `complete` for every model on its own thread; results keep the order of `models`.
+pub fn run_compare<F>(models: &[RegistryModel], complete: F) -> Vec<CompareResult>
+where
+    F: Fn(&RegistryModel) -> Result<AssistantMessage, String> + Sync,
+{
+    thread::scope(|scope| {
+        let handles = models
+            .iter()
+            .map(|model| {
+                let complete = &complete;
+                scope.spawn(move || {
+                    let started = Instant::now();
+                    let response = complete(model);
+                    (response, started.elapsed())
+                })
+            })
+            .collect::<Vec<_>>();
+        models
+            .iter()
+            .zip(handles)
+            .map(|(model, handle)| {
+                let (response, latency) = handle
+                    .join()
+                    .unwrap_or_else(|_| (Err("Request panicked".to_string()), Duration::ZERO));
+                CompareResult {
+                    model: model.clone(),
+                    response,
+                    latency,
+                }
+            })
+            .collect()
+    })
+}
+
+/// Side-by-side columns that fit `width`; stacked sections when the columns would be too
+/// narrow to read.
+pub fn render_columns(results: &[CompareResult], width: usize) -> Vec<String> {
+    if results.is_empty() {
+        return Vec::new();
+    }
+    let separator = " │ ";
+    let column_width =
+        width.saturating_sub(visible_width(separator) * (results.len() - 1)) / results.len();
+    if column_width < MIN_COLUMN_WIDTH {
+        return render_stacked(results, width);
+    }
+
+    let columns = results
+        .iter()
+        .map(|result| {
+            let mut lines = wrap_text_with_ansi(&result.label(), column_width);
+            lines.extend(wrap_text_with_ansi(&result.summary(), column_width));
+            lines.push("─".repeat(column_width));
+            lines.extend(wrap_text_with_ansi(&result.body(), column_width));
+            lines
+        })
+        .collect::<Vec<_>>();
+    let height = columns.iter().map(Vec::len).max().unwrap_or(0);
+    (0..height)
+        .map(|row| {
+            columns
+                .iter()
+                .map(|column| {
+                    let cell = column.get(row).map(String::as_str).unwrap_or("");
+                    let padding = column_width.saturating_sub(visible_width(cell));
+                    format!("{cell}{}", " ".repeat(padding))
+                })
+                .collect::<Vec<_>>()
+                .join(separator)
+                .trim_end()
+                .to_string()
+        })
+        .collect()
+}
+
+fn render_stacked(results: &[CompareResult], width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for result in results {
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        lines.push(format!("── {} ({})", result.label(), result.summary()));
+        lines.extend(wrap_text_with_ansi(&result.body(), width.max(1)));
+    }
+    lines
+}
+
+/// A markdown report: a summary table followed by each model's full response.
+pub fn render_markdown(prompt: &str, results: &[CompareResult]) -> String {
+    let mut report = String::from("# Model comparison\n\n");
+    report.push_str(&format!("> {}\n\n", prompt.replace('\n', "\n> ")));
+    report.push_str("| Model | Latency | Cost | Tokens (in/out) | Status |\n");
+    report.push_str("| --- | ---: | ---: | ---: | --- |\n");
+    for result in results {
+        let (tokens, status) = match &result.response {
+            Ok(message) => (
+                format!("{}/{}", message.usage.input, message.usage.output),
+                if message.error_message.is_some() {
+                    "error"
+                } else {
+                    "ok"
+                },
+            ),
+            Err(_) => ("-".to_string(), "error"),
+        };
+        report.push_str(&format!(
+            "| {} | {:.1}s | ${:.4} | {tokens} | {status} |\n",
+            result.label(),
+            result.latency.as_secs_f64(),
+            result.cost()
+        ));
+    }
+    for result in results {
+        report.push_str(&format!("\n## {}\n\n{}\n", result.label(), result.body()));
+    }
+    report
+}
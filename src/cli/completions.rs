@@ -0,0 +1,169 @@
+//! `pi completions bash|zsh|fish`: completion scripts built from the flag table in
+//! [`args`](super::args), so new flags are completed without editing the scripts.
+
+use crate::cli::args::{FlagSpec, FLAGS, SUBCOMMANDS};
+
+pub const COMPLETION_SHELLS: [&str; 3] = ["bash", "zsh", "fish"];
+
+pub fn generate_completions(shell: &str) -> Result<String, String> {
+    match shell {
+        "bash" => Ok(bash_completions()),
+        "zsh" => Ok(zsh_completions()),
+        "fish" => Ok(fish_completions()),
+        _ => Err(format!(
+            "Usage: pi completions <{}>",
+            COMPLETION_SHELLS.join("|")
+        )),
+    }
+}
+
+fn flag_names(spec: &FlagSpec) -> Vec<String> {
+    let mut names = vec![format!("--{}", spec.long)];
+    if let Some(short) = spec.short {
+        names.push(format!("-{short}"));
+    }
+    names
+}
+
+fn bash_completions() -> String {
+    let flags = FLAGS.iter().flat_map(flag_names).collect::<Vec<_>>();
+    let subcommands = SUBCOMMANDS
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>();
+
+    let mut cases = String::new();
+    for spec in FLAGS.iter().filter(|spec| spec.value.is_some()) {
+        let action = if !spec.choices.is_empty() {
+            format!(
+                "COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))",
+                spec.choices.join(" ")
+            )
+        } else {
+            match spec.value {
+                Some("file") => "COMPREPLY=($(compgen -f -- \"$cur\"))".to_string(),
+                Some("dir") => "COMPREPLY=($(compgen -d -- \"$cur\"))".to_string(),
+                _ => "COMPREPLY=()".to_string(),
+            }
+        };
+        cases.push_str(&format!(
+            "        {})\n            {action}\n            return\n            ;;\n",
+            flag_names(spec).join("|")
+        ));
+    }
+
+    format!(
+        r#"# bash completion for pi
+_pi() {{
+    local cur prev
+    cur="${{COMP_WORDS[COMP_CWORD]}}"
+    prev="${{COMP_WORDS[COMP_CWORD-1]}}"
+    case "$prev" in
+{cases}    esac
+    if [[ "$cur" == -* ]]; then
+        COMPREPLY=($(compgen -W "{flags}" -- "$cur"))
+    elif [[ $COMP_CWORD -eq 1 ]]; then
+        COMPREPLY=($(compgen -W "{subcommands}" -- "$cur") $(compgen -f -- "$cur"))
+    else
+        COMPREPLY=($(compgen -f -- "$cur"))
+    fi
+}}
+complete -o filenames -F _pi pi
+"#,
+        flags = flags.join(" "),
+        subcommands = subcommands.join(" "),
+    )
+}
+
+/// Escapes text for a zsh `_arguments` spec inside single quotes.
+fn zsh_escape(text: &str) -> String {
+    text.replace('\'', "'\\''")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+        .replace(':', "\\:")
+}
+
+fn zsh_completions() -> String {
+    let mut specs = Vec::new();
+    for spec in FLAGS {
+        let action = match spec.value {
+            None => String::new(),
+            Some(_) if !spec.choices.is_empty() => {
+                format!(":{}:({})", spec.long, spec.choices.join(" "))
+            }
+            Some("file") => ":file:_files".to_string(),
+            Some("dir") => ":directory:_files -/".to_string(),
+            Some(value) => format!(":{value}: "),
+        };
+        let description = zsh_escape(spec.description);
+        match spec.short {
+            Some(short) => specs.push(format!(
+                "'(-{short} --{long})'{{-{short},--{long}}}'[{description}]{action}'",
+                long = spec.long
+            )),
+            None => specs.push(format!("'--{}[{description}]{action}'", spec.long)),
+        }
+    }
+    let subcommands = SUBCOMMANDS
+        .iter()
+        .map(|(name, description)| format!("    '{name}:{}'", zsh_escape(description)))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        r#"#compdef pi
+
+_pi() {{
+  local -a subcommands
+  subcommands=(
+{subcommands}
+  )
+  _arguments -s \
+    {specs} \
+    '1: :->first' \
+    '*:file:_files'
+  if [[ $state == first ]]; then
+    _describe 'command' subcommands
+    _files
+  fi
+}}
+
+_pi "$@"
+"#,
+        specs = specs.join(" \\\n    "),
+    )
+}
+
+/// Escapes text for a single-quoted fish string.
+fn fish_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\'', "\\'")
+}
+
+fn fish_completions() -> String {
+    let mut lines = vec!["# fish completion for pi".to_string()];
+    for (name, description) in SUBCOMMANDS {
+        lines.push(format!(
+            "complete -c pi -n __fish_use_subcommand -a {name} -d '{}'",
+            fish_escape(description)
+        ));
+    }
+    for spec in FLAGS {
+        let mut line = format!("complete -c pi -l {}", spec.long);
+        if let Some(short) = spec.short {
+            line.push_str(&format!(" -s {short}"));
+        }
+        match spec.value {
+            None => {}
+            Some(_) if !spec.choices.is_empty() => {
+                line.push_str(&format!(" -x -a '{}'", spec.choices.join(" ")))
+            }
+            Some("file") => line.push_str(" -r -F"),
+            Some("dir") => line.push_str(" -x -a '(__fish_complete_directories)'"),
+            Some(_) => line.push_str(" -x"),
+        }
+        line.push_str(&format!(" -d '{}'", fish_escape(spec.description)));
+        lines.push(line);
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
@@ -0,0 +1,53 @@
+//! `pi config get|set`: read the merged settings or edit the global or project settings.json.
+
+use crate::cli::args::ConfigCommand;
+use crate::coding_agent::{SettingsManager, SettingsScope};
+use serde_json::Value;
+
+const USAGE: &str = concat!(
+    "Usage: pi config get [key] [--global|--project]\n",
+    "       pi config set <key> <value> [--global|--project]"
+);
+
+pub fn run_config_command(
+    command: &ConfigCommand,
+    settings: &mut SettingsManager,
+) -> Result<(), String> {
+    match command.action.as_deref() {
+        None | Some("get") => {
+            let key = command.key.as_deref().unwrap_or("");
+            let value = settings
+                .get_value(key, command.scope)
+                .ok_or_else(|| format!("{key} is not set"))?;
+            println!("{}", format_config_value(&value));
+            Ok(())
+        }
+        Some("set") => {
+            let (Some(key), Some(raw)) = (&command.key, &command.value) else {
+                return Err(USAGE.to_string());
+            };
+            let scope = command.scope.unwrap_or(SettingsScope::Global);
+            settings.set_value(key, parse_config_value(raw), scope)?;
+            if let Some(path) = settings.settings_file(scope) {
+                println!("Set {key} in {}", path.display());
+            }
+            Ok(())
+        }
+        Some(_) => Err(USAGE.to_string()),
+    }
+}
+
+/// JSON values (`true`, `0.8`, `["read","bash"]`, `null`) as typed; anything else as a string.
+pub fn parse_config_value(raw: &str) -> Value {
+    serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string()))
+}
+
+fn format_config_value(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Object(_) | Value::Array(_) => {
+            serde_json::to_string_pretty(value).unwrap_or_default()
+        }
+        _ => value.to_string(),
+    }
+}
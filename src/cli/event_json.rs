@@ -39,7 +39,7 @@ fn agent_event_value(event: &AgentEvent) -> Value {
             "type": "message_start",
             "message": agent_message_value(message),
         }),
-        AgentEvent::MessageUpdate { message } => json!({
+        AgentEvent::MessageUpdate { message, .. } => json!({
             "type": "message_update",
             "message": agent_message_value(message),
         }),
@@ -81,6 +81,16 @@ fn agent_event_value(event: &AgentEvent) -> Value {
             "result": agent_tool_result_value(result),
             "isError": is_error,
         }),
+        AgentEvent::RunAborted { message } => json!({
+            "type": "run_aborted",
+            "message": agent_message_value(message),
+        }),
+        AgentEvent::LimitReached { message, reason } => json!({
+            "type": "limit_reached",
+            "stopReason": "limit_reached",
+            "reason": reason,
+            "message": agent_message_value(message),
+        }),
     }
 }
 
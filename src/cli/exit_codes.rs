@@ -0,0 +1,159 @@
+//! Print-mode exit codes, so scripts can branch on why a run failed, and the error object
+//! `--mode json` prints for them.
+
+use crate::coding_agent::AgentSessionError;
+use serde_json::{json, Value};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExitReason {
+    Error,
+    Auth,
+    ModelNotFound,
+    Provider,
+    ToolDenied,
+    Aborted,
+    BudgetExceeded,
+}
+
+impl ExitReason {
+    pub const ALL: [ExitReason; 7] = [
+        ExitReason::Error,
+        ExitReason::Auth,
+        ExitReason::ModelNotFound,
+        ExitReason::Provider,
+        ExitReason::ToolDenied,
+        ExitReason::Aborted,
+        ExitReason::BudgetExceeded,
+    ];
+
+    pub fn code(self) -> i32 {
+        match self {
+            ExitReason::Error => 1,
+            ExitReason::Auth => 2,
+            ExitReason::ModelNotFound => 3,
+            ExitReason::Provider => 4,
+            ExitReason::ToolDenied => 5,
+            ExitReason::Aborted => 6,
+            ExitReason::BudgetExceeded => 7,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ExitReason::Error => "error",
+            ExitReason::Auth => "auth_failed",
+            ExitReason::ModelNotFound => "model_not_found",
+            ExitReason::Provider => "provider_error",
+            ExitReason::ToolDenied => "tool_denied",
+            ExitReason::Aborted => "aborted",
+            ExitReason::BudgetExceeded => "budget_exceeded",
+        }
+    }
+}
+
+/// Tool errors that mean a call was refused rather than failed.
+const TOOL_DENIAL_MARKERS: [&str; 5] = [
+    "Command blocked by bash policy",
+    "Command was not approved",
+    "Command needs approval",
+    "Access denied:",
+    "Tool execution was blocked by an extension",
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrintError {
+    pub reason: ExitReason,
+    pub message: String,
+}
+
+impl PrintError {
+    pub fn new(reason: ExitReason, message: impl Into<String>) -> Self {
+        Self {
+            reason,
+            message: message.into(),
+        }
+    }
+
+    /// A provider's error reply: credential and unknown-model failures get their own codes.
+    pub fn from_provider(message: impl Into<String>) -> Self {
+        let message = message.into();
+        let reason = match classify_error(&message) {
+            ExitReason::Error => ExitReason::Provider,
+            reason => reason,
+        };
+        Self { reason, message }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "type": "error",
+            "code": self.reason.as_str(),
+            "exitCode": self.reason.code(),
+            "message": self.message,
+        })
+    }
+}
+
+impl From<String> for PrintError {
+    fn from(message: String) -> Self {
+        Self {
+            reason: classify_error(&message),
+            message,
+        }
+    }
+}
+
+impl From<AgentSessionError> for PrintError {
+    fn from(err: AgentSessionError) -> Self {
+        match err {
+            AgentSessionError::BudgetExceeded(reason) => {
+                Self::new(ExitReason::BudgetExceeded, reason)
+            }
+            AgentSessionError::Agent(err) => Self::from_provider(err.to_string()),
+            err => Self::new(ExitReason::Error, err.to_string()),
+        }
+    }
+}
+
+/// Best guess at the reason behind an error that only has its text.
+pub fn classify_error(message: &str) -> ExitReason {
+    let lower = message.to_lowercase();
+    if is_auth_error(message) || lower.starts_with("no models available") {
+        ExitReason::Auth
+    } else if lower.contains("not_found_error")
+        || lower.starts_with("no models match")
+        || (lower.contains("model")
+            && (lower.contains("not found") || lower.contains("does not exist")))
+    {
+        ExitReason::ModelNotFound
+    } else if lower.starts_with("cost budget exceeded") {
+        ExitReason::BudgetExceeded
+    } else {
+        ExitReason::Error
+    }
+}
+
+/// Whether a provider error message means the credentials were rejected.
+pub fn is_auth_error(message: &str) -> bool {
+    let lower = message.to_lowercase();
+    [
+        " 401",
+        "unauthorized",
+        "authentication_error",
+        "invalid x-api-key",
+        "invalid bearer token",
+        "invalid api key",
+        "incorrect api key",
+        "token has expired",
+        "token expired",
+        "invalid_token",
+    ]
+    .iter()
+    .any(|needle| lower.contains(needle))
+}
+
+pub fn is_tool_denial(message: &str) -> bool {
+    TOOL_DENIAL_MARKERS
+        .iter()
+        .any(|marker| message.starts_with(marker))
+}
@@ -0,0 +1,107 @@
+//! `pi extensions list|reset`: show the capabilities each extension was granted and forget
+//! decisions so the extension is asked again on its next load.
+
+use crate::cli::args::ExtensionsCommand;
+use crate::coding_agent::ExtensionPermissions;
+use std::path::{Path, PathBuf};
+
+const USAGE: &str = concat!(
+    "Usage: pi extensions list\n",
+    "       pi extensions reset <path>"
+);
+
+pub fn run_extensions_command(
+    command: &ExtensionsCommand,
+    discovered: &[PathBuf],
+    permissions: &mut ExtensionPermissions,
+) -> Result<(), String> {
+    match command.action.as_deref() {
+        None | Some("list") => {
+            let lines = format_extension_permissions(discovered, permissions);
+            if lines.is_empty() {
+                println!("No extensions found");
+            }
+            for line in lines {
+                println!("{line}");
+            }
+            Ok(())
+        }
+        Some("reset") => {
+            let target = command.target.as_deref().ok_or_else(|| USAGE.to_string())?;
+            let path = resolve_target(target, discovered, permissions);
+            if !permissions.reset(&path) {
+                return Err(format!("No capability decisions recorded for {path}"));
+            }
+            permissions.save()?;
+            println!("Reset capabilities for {path}; it will be asked again on next load");
+            Ok(())
+        }
+        Some(_) => Err(USAGE.to_string()),
+    }
+}
+
+/// One block per extension, discovered ones first, then any with recorded decisions that
+/// were not found this time.
+pub fn format_extension_permissions(
+    discovered: &[PathBuf],
+    permissions: &ExtensionPermissions,
+) -> Vec<String> {
+    let mut paths = discovered
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>();
+    for (path, _) in permissions.entries() {
+        if !paths.contains(path) {
+            paths.push(path.clone());
+        }
+    }
+
+    let mut lines = Vec::new();
+    for path in paths {
+        let missing = !discovered
+            .iter()
+            .any(|found| found.display().to_string() == path);
+        lines.push(if missing {
+            format!("{path} (not found)")
+        } else {
+            path.clone()
+        });
+        let Some(grant) = permissions.get(&path) else {
+            lines.push("  not approved yet".to_string());
+            continue;
+        };
+        let undecided = permissions.undecided(&path, &grant.requested);
+        for (label, capabilities) in [
+            ("granted", &grant.granted),
+            ("denied", &grant.denied),
+            ("undecided", &undecided),
+        ] {
+            if !capabilities.is_empty() {
+                lines.push(format!("  {label}: {}", capabilities.join(", ")));
+            }
+        }
+    }
+    lines
+}
+
+/// Matches `target` against known extensions by path or file name.
+fn resolve_target(
+    target: &str,
+    discovered: &[PathBuf],
+    permissions: &ExtensionPermissions,
+) -> String {
+    let known = discovered
+        .iter()
+        .map(|path| path.display().to_string())
+        .chain(permissions.entries().map(|(path, _)| path.clone()));
+    for path in known {
+        if path == target
+            || Path::new(&path)
+                .file_name()
+                .is_some_and(|name| name == target)
+        {
+            return path;
+        }
+    }
+    target.to_string()
+}
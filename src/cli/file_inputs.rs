@@ -1,6 +1,10 @@
 use std::env;
+use std::io::Read;
 use std::path::PathBuf;
 
+/// How much piped stdin is kept in print mode; the rest is replaced by a marker.
+pub const STDIN_MAX_BYTES: usize = 200_000;
+
 #[derive(Clone)]
 pub struct FileInputImage {
     pub mime_type: String,
@@ -51,6 +55,58 @@ pub fn build_file_inputs(file_args: &[String]) -> Result<FileInputs, String> {
     })
 }
 
+/// Piped input (`cat error.log | pi -p - "explain this"`) as a `<stdin>` block to put ahead of
+/// the first message, or `None` when nothing was piped. Reads at most `max_bytes` (plus one, to
+/// tell that there was more), so a huge or endless pipe is cut off instead of buffered.
+pub fn read_stdin_input(reader: impl Read, max_bytes: usize) -> Result<Option<String>, String> {
+    let mut data = Vec::new();
+    reader
+        .take(max_bytes as u64 + 1)
+        .read_to_end(&mut data)
+        .map_err(|err| format!("Error: Could not read stdin: {err}"))?;
+    let content = String::from_utf8_lossy(&data);
+    if content.trim().is_empty() {
+        return Ok(None);
+    }
+    let mut end = max_bytes.min(content.len());
+    while !content.is_char_boundary(end) {
+        end -= 1;
+    }
+    let mut text = String::from("<stdin>\n");
+    text.push_str(&content[..end]);
+    if !text.ends_with('\n') {
+        text.push('\n');
+    }
+    if data.len() > max_bytes {
+        text.push_str(&format!("[... stdin truncated after {end} bytes]\n"));
+    }
+    text.push_str("</stdin>\n");
+    Ok(Some(text))
+}
+
+/// Paths written as `@path` in `text` that point at existing files, in order of appearance.
+pub fn extract_file_references(text: &str) -> Vec<String> {
+    let mut references: Vec<String> = Vec::new();
+    for token in text.split_whitespace() {
+        let Some(path) = token.strip_prefix('@') else {
+            continue;
+        };
+        // Allow trailing punctuation such as "look at @src/main.rs."
+        let path = if resolve_file_arg(path).is_file() {
+            path
+        } else {
+            path.trim_end_matches([',', '.', ';', ':', '!', '?', ')'])
+        };
+        if path.is_empty() || !resolve_file_arg(path).is_file() {
+            continue;
+        }
+        if !references.iter().any(|existing| existing == path) {
+            references.push(path.to_string());
+        }
+    }
+    references
+}
+
 fn resolve_file_arg(path: &str) -> PathBuf {
     if let Some(rest) = path.strip_prefix("~/") {
         if let Ok(home) = env::var("HOME") {
@@ -1,7 +1,23 @@
 pub mod args;
 pub mod auth;
+pub mod auth_command;
+pub mod batch;
+pub mod blame;
+pub mod capabilities;
+pub mod compare;
+pub mod completions;
+pub mod config_command;
 pub mod event_json;
+pub mod exit_codes;
+pub mod extensions_command;
 pub mod file_inputs;
 pub mod list_models;
+pub mod models_command;
+pub mod replay;
+pub mod replay_turn;
 pub mod runtime;
 pub mod session;
+pub mod sessions;
+pub mod stats;
+pub mod token_refresh;
+pub mod tool_run;
@@ -0,0 +1,178 @@
+//! `pi models add|remove|show`: edit custom models in models.json without hand-editing it.
+//! Every edit is validated against the schema the model registry loads before it is written.
+
+use crate::cli::args::ModelsCommand;
+use crate::coding_agent::models_config::{
+    add_custom_model, read_models_config, remove_custom_model, validate_models_config,
+    ProviderFields,
+};
+use crate::coding_agent::{is_built_in_provider, ModelRegistry};
+use serde_json::{json, Map, Value};
+use std::path::Path;
+
+const USAGE: &str = concat!(
+    "Usage: pi models add <provider>/<id> --base-url <url> [--api <api>] [--api-key <key>]\n",
+    "         [--name <name>] [--reasoning] [--input text,image] [--context-window <n>]\n",
+    "         [--max-tokens <n>] [--cost <input,output,cacheRead,cacheWrite>]\n",
+    "         [--header <name>=<value> ...]\n",
+    "       pi models remove <provider>/<id>\n",
+    "       pi models show [provider[/id]]"
+);
+
+pub fn run_models_command(
+    command: &ModelsCommand,
+    models_path: &Path,
+    registry: &ModelRegistry,
+) -> Result<(), String> {
+    match command.action.as_deref() {
+        Some("add") => {
+            let (provider, id) = split_target(command.target.as_deref())?;
+            let (fields, model) = model_from_options(id, &command.options)?;
+            add_custom_model(models_path, provider, &fields, model)
+                .map_err(|err| err.to_string())?;
+            if is_built_in_provider(provider) {
+                eprintln!(
+                    "Warning: {provider} is a built-in provider; its custom models replace the \
+                     built-in ones"
+                );
+            }
+            println!("Added {provider}/{id} to {}", models_path.display());
+            Ok(())
+        }
+        Some("remove") => {
+            let (provider, id) = split_target(command.target.as_deref())?;
+            let removed =
+                remove_custom_model(models_path, provider, id).map_err(|err| err.to_string())?;
+            if removed {
+                println!("Removed {provider}/{id} from {}", models_path.display());
+                Ok(())
+            } else {
+                Err(format!(
+                    "No custom model {provider}/{id} in {}",
+                    models_path.display()
+                ))
+            }
+        }
+        None | Some("show") => show(command.target.as_deref(), models_path, registry),
+        Some(_) => Err(USAGE.to_string()),
+    }
+}
+
+/// Builds the models.json entry for `id` from `pi models add` options.
+pub fn model_from_options(
+    id: &str,
+    options: &[(String, String)],
+) -> Result<(ProviderFields, Value), String> {
+    let mut fields = ProviderFields::default();
+    let mut model = Map::new();
+    model.insert("id".to_string(), Value::String(id.to_string()));
+    for (name, value) in options {
+        match name.as_str() {
+            "base-url" => fields.base_url = Some(value.clone()),
+            "api" => fields.api = Some(value.clone()),
+            "api-key" => fields.api_key = Some(value.clone()),
+            "header" => {
+                let (key, value) = value
+                    .split_once('=')
+                    .ok_or_else(|| format!("--header expects <name>=<value>, got {value}"))?;
+                fields
+                    .headers
+                    .push((key.trim().to_string(), value.trim().to_string()));
+            }
+            "name" => {
+                model.insert("name".to_string(), Value::String(value.clone()));
+            }
+            "reasoning" => {
+                let reasoning = value
+                    .parse::<bool>()
+                    .map_err(|_| format!("--reasoning expects true or false, got {value}"))?;
+                model.insert("reasoning".to_string(), Value::Bool(reasoning));
+            }
+            "input" => {
+                let input = value
+                    .split(',')
+                    .map(|modality| Value::String(modality.trim().to_string()))
+                    .collect();
+                model.insert("input".to_string(), Value::Array(input));
+            }
+            "context-window" | "max-tokens" => {
+                let number = value
+                    .parse::<i64>()
+                    .map_err(|_| format!("--{name} expects a number, got {value}"))?;
+                let key = if name == "context-window" {
+                    "contextWindow"
+                } else {
+                    "maxTokens"
+                };
+                model.insert(key.to_string(), json!(number));
+            }
+            "cost" => {
+                model.insert("cost".to_string(), parse_cost(value)?);
+            }
+            _ => return Err(format!("Unknown option --{name}\n{USAGE}")),
+        }
+    }
+    Ok((fields, Value::Object(model)))
+}
+
+/// `input,output,cacheRead,cacheWrite` in dollars per million tokens; missing cache prices
+/// default to zero.
+fn parse_cost(value: &str) -> Result<Value, String> {
+    let prices = value
+        .split(',')
+        .map(|price| price.trim().parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| format!("--cost expects input,output[,cacheRead,cacheWrite], got {value}"))?;
+    if !(2..=4).contains(&prices.len()) {
+        return Err(format!(
+            "--cost expects input,output[,cacheRead,cacheWrite], got {value}"
+        ));
+    }
+    let price = |index: usize| prices.get(index).copied().unwrap_or(0.0);
+    Ok(json!({
+        "input": price(0),
+        "output": price(1),
+        "cacheRead": price(2),
+        "cacheWrite": price(3),
+    }))
+}
+
+fn split_target(target: Option<&str>) -> Result<(&str, &str), String> {
+    target
+        .and_then(|target| target.split_once('/'))
+        .filter(|(provider, id)| !provider.is_empty() && !id.is_empty())
+        .ok_or_else(|| USAGE.to_string())
+}
+
+/// Without a target, prints models.json and whether it is valid; with one, the matching
+/// models as the registry resolved them (built-in and custom).
+fn show(target: Option<&str>, models_path: &Path, registry: &ModelRegistry) -> Result<(), String> {
+    let Some(target) = target else {
+        let config = read_models_config(models_path).map_err(|err| err.to_string())?;
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&config).unwrap_or_default()
+        );
+        return match validate_models_config(&config) {
+            Ok(()) => Ok(()),
+            Err(errors) => Err(format!("{} is invalid:\n{errors}", models_path.display())),
+        };
+    };
+    let (provider, id) = match target.split_once('/') {
+        Some((provider, id)) => (provider, Some(id)),
+        None => (target, None),
+    };
+    let models = registry
+        .get_all()
+        .into_iter()
+        .filter(|model| model.provider == provider && id.is_none_or(|id| model.id == id))
+        .collect::<Vec<_>>();
+    if models.is_empty() {
+        return Err(format!("No models match {target}"));
+    }
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&models).unwrap_or_default()
+    );
+    Ok(())
+}
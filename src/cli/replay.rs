@@ -0,0 +1,154 @@
+//! `pi replay <session-file>` runs a recorded session again without calling a provider: the
+//! recorded prompts are sent in order, the model answers with the recorded responses and the
+//! tools with the recorded results. `pi replay <fixtures-dir> "prompt"` answers from responses
+//! captured with `--record-fixtures` instead, running the tools for real.
+
+use crate::agent::{
+    replay_stream_fn, Agent, AgentOptions, AgentStateOverride, AgentTool, AgentToolResult,
+    Model as AgentModel,
+};
+use crate::coding_agent::agent_session::Settings;
+use crate::coding_agent::{
+    AgentSession, AgentSessionConfig, AuthStorage, ModelRegistry, SettingsManager,
+};
+use crate::config;
+use crate::core::messages::{AgentMessage, AssistantMessage, ContentBlock, UserContent};
+use crate::core::session_manager::{SessionEntry, SessionManager};
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+
+#[derive(Clone, Debug)]
+pub struct SessionReplay {
+    /// The user messages of the session's current branch, in order.
+    pub prompts: Vec<String>,
+    pub responses: Vec<AssistantMessage>,
+    /// One tool per name the session called, answering with the recorded results.
+    pub tools: Vec<AgentTool>,
+}
+
+pub fn load_session_replay(path: &Path) -> Result<SessionReplay, String> {
+    let manager = SessionManager::try_open(path.to_path_buf(), None)?;
+    let mut prompts = Vec::new();
+    let mut responses = Vec::new();
+    let mut results = HashMap::new();
+    let mut tool_names = Vec::new();
+    for entry in manager.get_branch(None) {
+        let SessionEntry::Message(entry) = entry else {
+            continue;
+        };
+        match entry.message {
+            AgentMessage::User(user) => prompts.push(prompt_text(&user.content)),
+            AgentMessage::Assistant(assistant) => {
+                for block in &assistant.content {
+                    if let ContentBlock::ToolCall { name, .. } = block {
+                        if !tool_names.contains(name) {
+                            tool_names.push(name.clone());
+                        }
+                    }
+                }
+                responses.push(assistant);
+            }
+            AgentMessage::ToolResult(result) => {
+                results.insert(result.tool_call_id.clone(), result);
+            }
+            _ => {}
+        }
+    }
+    if prompts.is_empty() {
+        return Err(format!("No messages to replay in {}", path.display()));
+    }
+    let results = Rc::new(results);
+    let tools = tool_names
+        .into_iter()
+        .map(|name| {
+            let results = results.clone();
+            AgentTool {
+                label: name.clone(),
+                description: format!("Replays the recorded results of {name}"),
+                name,
+                parameters: None,
+                execute: Rc::new(move |tool_call_id, _params, _on_update| {
+                    let result = results
+                        .get(tool_call_id)
+                        .ok_or_else(|| "No recorded result for this tool call".to_string())?;
+                    if result.is_error {
+                        return Err(text_of(&result.content).into());
+                    }
+                    Ok(AgentToolResult {
+                        content: result.content.clone(),
+                        details: result.details.clone().unwrap_or_default(),
+                    })
+                }),
+            }
+        })
+        .collect();
+    Ok(SessionReplay {
+        prompts,
+        responses,
+        tools,
+    })
+}
+
+/// A session that never reaches a provider: `responses` answer its turns, in order. It is
+/// kept in memory and does not auto-compact, since compaction would call the model.
+pub fn create_replay_session(
+    tools: Vec<AgentTool>,
+    responses: Vec<AssistantMessage>,
+) -> AgentSession {
+    let model = responses
+        .first()
+        .map(|response| AgentModel {
+            id: response.model.clone(),
+            name: response.model.clone(),
+            api: response.api.clone(),
+            provider: response.provider.clone(),
+        })
+        .unwrap_or_else(|| crate::agent::get_model("replay", "replay"));
+    let agent = Agent::new(AgentOptions {
+        initial_state: Some(AgentStateOverride {
+            model: Some(model),
+            tools: Some(tools),
+            ..Default::default()
+        }),
+        stream_fn: Some(replay_stream_fn(responses)),
+        ..Default::default()
+    });
+    let mut session = AgentSession::new(AgentSessionConfig {
+        agent,
+        session_manager: SessionManager::in_memory(),
+        settings_manager: SettingsManager::in_memory(Settings::default()),
+        model_registry: ModelRegistry::new(AuthStorage::new(config::get_auth_path()), None),
+    });
+    session.set_auto_compaction_enabled(false);
+    // A retry would take the next recorded response for the failed turn.
+    session.set_auto_retry_enabled(false);
+    session
+}
+
+/// Images are not replayed; a placeholder keeps image-only prompts from being skipped.
+fn prompt_text(content: &UserContent) -> String {
+    match content {
+        UserContent::Text(text) => text.clone(),
+        UserContent::Blocks(blocks) => blocks
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text { text, .. } => Some(text.clone()),
+                ContentBlock::Image { .. } => Some("[image]".to_string()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+fn text_of(content: &[ContentBlock]) -> String {
+    content
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text { text, .. } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
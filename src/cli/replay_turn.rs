@@ -0,0 +1,143 @@
+//! `pi replay-turn --session <file> --entry <id>`: rebuild the context a past turn was sent
+//! with and re-issue it against another model, printing a diff of the two responses.
+
+use crate::agent::{AgentMessage as LlmMessage, LlmContext};
+use crate::core::diff::diff_lines;
+use crate::core::messages::{AgentMessage, AssistantMessage, ContentBlock};
+use crate::core::session_manager::{build_session_context, SessionEntry, SessionManager};
+use serde_json::json;
+use std::path::Path;
+
+#[derive(Clone, Debug)]
+pub struct ReplayTurn {
+    pub entry_id: String,
+    pub context: LlmContext,
+    /// The response the session recorded for this turn, if it got one.
+    pub original: Option<AssistantMessage>,
+}
+
+/// `entry_id` is the assistant response to replay, or an entry whose next assistant response
+/// should be replayed (such as the user prompt). The session does not record the system
+/// prompt, so the caller passes the one to send.
+pub fn load_replay_turn(
+    session_path: &Path,
+    entry_id: &str,
+    system_prompt: String,
+) -> Result<ReplayTurn, String> {
+    let manager = SessionManager::try_open(session_path.to_path_buf(), None)?;
+    let entry = manager
+        .get_entry(entry_id)
+        .ok_or_else(|| format!("Entry {entry_id} not found in {}", session_path.display()))?;
+    let entries = manager.get_entries();
+    let (leaf_id, original) = match assistant_message(&entry) {
+        Some(message) => (entry.parent_id().map(str::to_string), Some(message)),
+        None => (
+            Some(entry_id.to_string()),
+            entries
+                .iter()
+                .filter(|child| child.parent_id() == Some(entry_id))
+                .find_map(assistant_message),
+        ),
+    };
+    let messages = match leaf_id {
+        Some(leaf_id) => build_session_context(&entries, Some(&leaf_id)).messages,
+        None => Vec::new(),
+    };
+    Ok(ReplayTurn {
+        entry_id: entry_id.to_string(),
+        context: LlmContext {
+            system_prompt,
+            messages: messages.iter().filter_map(to_llm_message).collect(),
+        },
+        original,
+    })
+}
+
+/// Only these kinds reach the provider when the agent replays a restored session.
+fn to_llm_message(message: &AgentMessage) -> Option<LlmMessage> {
+    match message {
+        AgentMessage::User(user) => Some(LlmMessage::User(user.clone())),
+        AgentMessage::Assistant(assistant) => Some(LlmMessage::Assistant(assistant.clone())),
+        AgentMessage::ToolResult(result) => Some(LlmMessage::ToolResult(result.clone())),
+        _ => None,
+    }
+}
+
+fn from_llm_message(message: &LlmMessage) -> Option<AgentMessage> {
+    match message {
+        LlmMessage::User(user) => Some(AgentMessage::User(user.clone())),
+        LlmMessage::Assistant(assistant) => Some(AgentMessage::Assistant(assistant.clone())),
+        LlmMessage::ToolResult(result) => Some(AgentMessage::ToolResult(result.clone())),
+        LlmMessage::Custom(_) => None,
+    }
+}
+
+fn assistant_message(entry: &SessionEntry) -> Option<AssistantMessage> {
+    match entry {
+        SessionEntry::Message(message) => match &message.message {
+            AgentMessage::Assistant(assistant) => Some(assistant.clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The response as comparable text: text blocks, tool calls and any error.
+pub fn response_text(message: &AssistantMessage) -> String {
+    let mut lines = Vec::new();
+    for block in &message.content {
+        match block {
+            ContentBlock::Text { text, .. } => lines.push(text.clone()),
+            ContentBlock::ToolCall {
+                name, arguments, ..
+            } => lines.push(format!("[tool call] {name} {arguments}")),
+            ContentBlock::Diff { .. } => lines.extend(block.as_text()),
+            ContentBlock::Thinking { .. } | ContentBlock::Image { .. } => {}
+        }
+    }
+    if let Some(error) = &message.error_message {
+        lines.push(format!("[error] {error}"));
+    }
+    lines.join("\n")
+}
+
+/// Prints the context that would be sent and the recorded response.
+pub fn print_replay_dry_run(turn: &ReplayTurn) {
+    let context = json!({
+        "systemPrompt": turn.context.system_prompt,
+        "messages": turn
+            .context
+            .messages
+            .iter()
+            .filter_map(from_llm_message)
+            .collect::<Vec<_>>(),
+    });
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&context).unwrap_or_default()
+    );
+    match &turn.original {
+        Some(original) => println!(
+            "\nRecorded response ({}/{}):\n{}",
+            original.provider,
+            original.model,
+            response_text(original)
+        ),
+        None => println!("\nNo recorded response for entry {}", turn.entry_id),
+    }
+}
+
+pub fn print_replay_diff(turn: &ReplayTurn, replay: &AssistantMessage) {
+    let (label, original) = match &turn.original {
+        Some(original) => (
+            format!("{}/{}", original.provider, original.model),
+            response_text(original),
+        ),
+        None => ("(no recorded response)".to_string(), String::new()),
+    };
+    println!("--- {label} (entry {})", turn.entry_id);
+    println!("+++ {}/{} (replay)", replay.provider, replay.model);
+    for line in diff_lines(&original, &response_text(replay)) {
+        println!("{line}");
+    }
+}
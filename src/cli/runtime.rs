@@ -2,21 +2,23 @@ use crate::cli::args::{ExtensionFlagType, ExtensionFlagValue};
 use crate::cli::auth::apply_env_api_keys_for_availability;
 use crate::coding_agent::extension_host::{ExtensionCommand, ExtensionTool};
 use crate::coding_agent::{
-    discover_extension_paths, ExtensionHost, ExtensionManifest, Model as RegistryModel,
-    ModelRegistry, SettingsManager,
+    capture_environment, discover_extension_paths, find_persona, load_personas,
+    EnvironmentSnapshot, ExtensionHost, ExtensionManifest, ExtensionPermissions,
+    LoadPersonasOptions, Model as RegistryModel, ModelRegistry, PermissionProfile, Persona,
+    PromptOverrides, SandboxPolicy, SettingsManager, Shell,
 };
 use crate::config;
-use crate::core::session_manager::{SessionInfo, SessionManager};
+use crate::core::session_manager::{check_session_version, SessionInfo, SessionManager};
 use crate::tui::SessionSelectorComponent;
 use crate::{Args, ListModels};
 use crossterm::cursor::{Hide, Show};
-use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
 use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
 use crossterm::ExecutableCommand;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::env;
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Write};
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
@@ -26,31 +28,116 @@ pub fn print_help() {
 
 Usage:
   pi [options] [messages...]
+  pi tool run <tool> [--arg value ...]  Run a built-in tool directly and print JSON
+  pi blame <file>  Show git blame with the agent session and turn that wrote each line
+  pi stats [--json]  Tokens and cost per model, provider and day, tool calls, average turn
+                   time and busiest projects across all sessions (or --session-dir)
+  pi auth login [provider]  Log in with OAuth (anthropic, openai-codex, github-copilot)
+  pi auth logout <provider>  Remove stored credentials
+  pi auth status   Show stored credentials and token expiry per provider
+  pi replay <session-file> [--mode json]
+                   Replay a session's turns in the TUI (or as JSON events) from its recorded
+                   responses and tool results, without calling a provider
+  pi replay <fixtures-dir> \"prompt\"
+                   Answer prompts from responses saved with --record-fixtures (tools run)
+  pi replay-turn --session <file> --entry <id> [--model <model>] [--dry-run]
+                   Re-run a past turn's context against a model and diff the responses
+  pi compare --models <a,b,...> \"prompt\" [--output report.md]
+                   Send one prompt to several models in parallel and show the replies side by
+                   side with latency and cost (tool calls are shown, not run)
+  pi config get [key] [--global|--project]
+                   Show merged settings, or one file's, at a dotted key (e.g. compaction.threshold)
+  pi config set <key> <value> [--global|--project]
+                   Write a setting (JSON or a plain string; null removes it) to global settings
+                   or .pi/settings.json
+  pi models add <provider>/<id> --base-url <url> [--api <api>] [--name <name>] [--reasoning]
+                   [--input text,image] [--context-window <n>] [--max-tokens <n>]
+                   [--cost <in,out,cacheRead,cacheWrite>] [--api-key <key>] [--header k=v]
+                   Add or replace a custom model in models.json (validated first)
+  pi models remove <provider>/<id>  Remove a custom model from models.json
+  pi models show [provider[/id]]  Show models.json (and any problems), or resolved models
+  pi extensions list
+                   Show discovered extensions and the capabilities (fs.read, fs.write, network,
+                   ui, flags) they were granted, denied or have not been asked about yet
+  pi extensions reset <path>  Forget an extension's capability decisions so it is asked again
+  pi batch list    Show batch jobs submitted with --batch
+  pi batch status <id>  Show how many of a batch job's prompts have finished
+  pi batch fetch <id> [--output <dir>]  Write a finished batch job's results to files
+  pi completions <bash|zsh|fish>  Print a shell completion script
 
 Options:
   --help, -h       Show this help
   --version, -v    Show version
   --provider       Provider name (anthropic only for now)
   --model          Model id
-  --models         Comma-separated model patterns
+  --models         Comma-separated model patterns; the first is used and a turn that fails
+                   with a provider error (timeout, rate limit, ...) is retried on the next
   --api-key        Override provider API key
+  --auth-profile <name>  Use a named credential profile from auth.json (e.g. work, personal)
   --system-prompt  Custom system prompt (literal or file path)
   --append-system-prompt  Append text to system prompt (literal or file path)
-  --tools          Comma-separated tool allowlist
+  --tools          Comma-separated tool allowlist (`git` adds the git_* tools,
+                   `notebook` adds notebook_read and notebook_edit)
   --thinking       Set thinking level: off, minimal, low, medium, high, xhigh
-  --print, -p      Print mode (single-shot)
+  --seed <n>       Sampling seed for reproducible runs (OpenAI, Gemini)
+  --max-cost <usd>  Stop the agent once this session has cost this much (settings: budget.maxCost;
+                   budget.maxDailyCost caps each day's spend across sessions)
+  --record-fixtures <dir>  Append every provider response to <dir>/responses.jsonl
+  --max-turns <n>  Stop a run that is still calling tools after this many model calls
+  --max-duration <duration>  Stop a run that is still calling tools after this long
+                   (seconds, or 90s, 10m, 1h); the run ends with a limit_reached event
+  --trace-llm <file>  Append each provider request and response, bodies included, to <file>
+                   (settings: logRequests logs redacted summaries to ~/.pi/logs/requests.jsonl)
+  --auto-compact-threshold <percent>  Auto-compact when context usage reaches this share of the window
+  --persona <name>  Use a named persona from personas/ (prompt, model, thinking, tools)
+  --dangerously-allow-all  Let tools read and write outside the workspace (disables the sandbox)
+  --print, -p      Print mode (single-shot). Piped stdin is added to the first message
+  --no-stdin       With --print, ignore piped stdin
+  --batch          With --print, send each message as its own prompt through the provider's
+                   batch API (Anthropic batches, OpenAI background mode; cheaper, no tools),
+                   wait for the results and write one file per prompt
+  --batch-output <dir>  Where --batch writes results (default pi-batch-<id>)
   --list-models    List available models
+  --list-tools     List built-in and extension tools with descriptions
+  --list-skills    List discovered skills with descriptions and arguments
   --export <file>  Export session file to HTML and exit
-  --mode <mode>    Output mode: text (default), json, rpc
+  --export-new-only  With --export, only include entries added since the last export
+  --export-theme <name>  With --export, theme the page: dark (default), light, auto
+                   (follows the reader's system setting) or a custom theme name
+  --sessions [list | search <query>]  List sessions, or search names, tags, and messages
+  --mode <mode>    Output mode: text (default), json, rpc, jsonrpc (RPC as JSON-RPC 2.0
+                   over stdio, with editor code actions), acp (Agent Client Protocol, for
+                   editors such as Zed)
+  --ui <full|line> Force the full-screen or line-based interactive UI (default: detect;
+                   limited terminals such as TERM=dumb or Emacs shells use line)
+  --idle-exit <secs>  RPC mode: exit after this many seconds without commands
+  --parent-pid <pid>  RPC mode: exit when this process exits (e.g. the host editor)
+  --serve          Run RPC mode as a daemon on 127.0.0.1: WebSocket at /rpc, server-sent
+                   events at /events and POST /rpc for commands. Clients authenticate with
+                   PI_SERVE_TOKEN (or the printed token) as a Bearer header or ?token=
+  --mirror         Serve a live-updating HTML view of the session on 127.0.0.1 and open it
+                   in the browser (themed by --export-theme)
+  --port <n>       Port for --serve (default 4141) or --mirror (default 4142)
   --extension, -e  Load an extension file (can be used multiple times)
   --no-skills      Disable skills discovery and loading
   --skills         Comma-separated glob patterns to filter skills
+  --context-dir <dir>
+                   Preload files under <dir> into the session (see contextDir in settings)
+  --context-glob <globs>
+                   Comma-separated globs selecting --context-dir files (repeatable)
+  --no-project-context
+                   Do not load AGENTS.md, CLAUDE.md or .pi/CONTEXT.md into the system prompt
   @file            Include file contents in prompt (text or images)
+  -                With --print, read stdin into the first message even from a terminal
 
 Notes:
   Interactive mode uses a basic TUI (full parity pending).
   Extensions can register additional CLI flags.
-  Extension execution (compaction hooks) is supported for .js files only."
+  Extension execution (compaction hooks) is supported for .js files only.
+
+Exit codes (print mode; --mode json also prints an error object with the code):
+  1 error, 2 auth_failed, 3 model_not_found, 4 provider_error, 5 tool_denied, 6 aborted,
+  7 budget_exceeded"
     );
 }
 
@@ -91,9 +178,22 @@ pub fn collect_unsupported_flags(_parsed: &Args) -> Vec<&'static str> {
 pub fn build_model_registry(
     api_key_override: Option<&str>,
     provider: Option<&str>,
+    auth_profile: Option<&str>,
 ) -> Result<ModelRegistry, String> {
     let auth_path = config::get_auth_path();
     let mut auth_storage = crate::coding_agent::AuthStorage::new(auth_path);
+    // --auth-profile wins over the workspace default from settings.
+    let auth_profile = auth_profile
+        .map(str::to_string)
+        .or_else(|| SettingsManager::create("", "").get_auth_profile());
+    if let Some(profile) = auth_profile.as_deref() {
+        auth_storage.set_active_profile(Some(profile));
+        if !auth_storage.profiles().iter().any(|name| name == profile) {
+            eprintln!(
+                "Warning: auth profile \"{profile}\" has no stored credentials; using defaults"
+            );
+        }
+    }
     apply_env_api_keys_for_availability(&mut auth_storage);
     if let Some(api_key) = api_key_override {
         let provider = provider.unwrap_or("anthropic");
@@ -105,6 +205,29 @@ pub fn build_model_registry(
     ))
 }
 
+/// Tool sandbox for `cwd` from the `sandbox` settings, or no sandbox with
+/// `--dangerously-allow-all`.
+pub fn build_sandbox_policy(allow_all: bool, cwd: &Path) -> SandboxPolicy {
+    if allow_all {
+        return SandboxPolicy::allow_all();
+    }
+    let settings_manager = SettingsManager::create(cwd.to_string_lossy(), "");
+    SandboxPolicy::from_settings(&settings_manager.get_sandbox_settings(), cwd)
+}
+
+/// Environment preamble for the system prompt when the `environmentSnapshot` setting is on.
+pub fn build_environment_snapshot(cwd: &Path) -> Option<EnvironmentSnapshot> {
+    let settings_manager = SettingsManager::create(cwd.to_string_lossy(), "");
+    if !settings_manager.get_environment_snapshot() {
+        return None;
+    }
+    let shell = Shell::configured_or(
+        settings_manager.get_shell_path().as_deref(),
+        Shell::tool_default,
+    );
+    Some(capture_environment(cwd, &shell))
+}
+
 pub fn discover_system_prompt_file() -> Option<PathBuf> {
     let cwd = env::current_dir().ok()?;
     let project_path = cwd.join(config::config_dir_name()).join("SYSTEM.md");
@@ -120,26 +243,33 @@ pub fn discover_system_prompt_file() -> Option<PathBuf> {
     None
 }
 
-pub fn build_session_manager(parsed: &Args, cwd: &Path) -> SessionManager {
+pub fn build_session_manager(parsed: &Args, cwd: &Path) -> Result<SessionManager, String> {
     if parsed.no_session {
-        return SessionManager::in_memory();
+        return Ok(SessionManager::in_memory());
     }
     if let Some(session) = &parsed.session {
-        return SessionManager::open(
+        return SessionManager::try_open(
             PathBuf::from(session),
             parsed.session_dir.as_ref().map(PathBuf::from),
         );
     }
     if parsed.continue_session {
-        return SessionManager::continue_recent(
+        let session_manager = SessionManager::continue_recent(
             cwd.to_path_buf(),
             parsed.session_dir.as_ref().map(PathBuf::from),
         );
+        if let Some(path) = session_manager.get_session_file() {
+            check_session_version(&path)?;
+        }
+        return Ok(session_manager);
     }
     if let Some(session_dir) = &parsed.session_dir {
-        return SessionManager::create_with_dir(cwd.to_path_buf(), PathBuf::from(session_dir));
+        return Ok(SessionManager::create_with_dir(
+            cwd.to_path_buf(),
+            PathBuf::from(session_dir),
+        ));
     }
-    SessionManager::create(cwd.to_path_buf())
+    Ok(SessionManager::create(cwd.to_path_buf()))
 }
 
 pub fn select_resume_session(
@@ -232,6 +362,10 @@ fn run_session_selector_loop(
         // Poll for events
         if event::poll(std::time::Duration::from_millis(100)).map_err(|e| e.to_string())? {
             if let Event::Key(key_event) = event::read().map_err(|e| e.to_string())? {
+                // Windows also reports key releases.
+                if key_event.kind == KeyEventKind::Release {
+                    continue;
+                }
                 // Check for Ctrl+C to exit
                 if key_event.modifiers.contains(KeyModifiers::CONTROL)
                     && key_event.code == KeyCode::Char('c')
@@ -273,7 +407,7 @@ fn run_session_selector_loop(
 fn prompt_for_session_simple(sessions: &[SessionInfo]) -> Result<Option<PathBuf>, String> {
     println!("Select a session to resume:");
     for (idx, session) in sessions.iter().enumerate() {
-        let preview = truncate_preview(&session.first_message, 80);
+        let preview = truncate_preview(&session.preview, 80);
         let modified = format_modified_time(session.modified);
         println!(
             "{:>2}) {} (messages: {}, modified: {})",
@@ -333,7 +467,93 @@ fn truncate_preview(text: &str, max_len: usize) -> String {
     truncated
 }
 
-pub fn select_model(parsed: &Args, registry: &ModelRegistry) -> Result<RegistryModel, String> {
+pub fn load_cli_persona(name: &str, cwd: &Path) -> Result<Persona, String> {
+    let personas = load_personas(LoadPersonasOptions {
+        cwd: Some(cwd.to_path_buf()),
+        agent_dir: Some(config::get_agent_dir()),
+    });
+    find_persona(&personas, name).cloned().ok_or_else(|| {
+        let names = personas
+            .iter()
+            .map(|persona| persona.name.as_str())
+            .collect::<Vec<_>>();
+        if names.is_empty() {
+            format!(
+                "Persona \"{name}\" not found. Add {name}.md to {}",
+                config::get_agent_dir().join("personas").display()
+            )
+        } else {
+            format!(
+                "Persona \"{name}\" not found. Available personas: {}",
+                names.join(", ")
+            )
+        }
+    })
+}
+
+/// Fill model, thinking and tool options from a persona; explicit CLI flags take precedence.
+pub fn apply_persona_to_args(parsed: &mut Args, persona: &Persona) {
+    if let Some(model) = &persona.model {
+        if parsed.model.is_none() && parsed.models.is_none() {
+            match model.split_once('/') {
+                Some((provider, model_id))
+                    if parsed
+                        .provider
+                        .as_deref()
+                        .is_none_or(|value| value == provider) =>
+                {
+                    parsed.provider = Some(provider.to_string());
+                    parsed.model = Some(model_id.to_string());
+                }
+                Some(_) => {}
+                None => parsed.models = Some(vec![model.clone()]),
+            }
+        }
+    }
+    if parsed.thinking.is_none() {
+        parsed.thinking = persona
+            .thinking
+            .and_then(|level| crate::cli::args::ThinkingLevel::parse(level.as_str()));
+    }
+    let base_tools = match (&parsed.tools, &persona.tools) {
+        (Some(tools), _) | (None, Some(tools)) => Some(tools.clone()),
+        (None, None) if persona.permissions == PermissionProfile::ReadOnly => {
+            Some(crate::tools::default_tool_names())
+        }
+        (None, None) => None,
+    };
+    if let Some(tools) = base_tools {
+        parsed.tools = Some(
+            tools
+                .into_iter()
+                .filter(|name| persona.permissions.allows_tool(name))
+                .collect(),
+        );
+    }
+}
+
+/// Fill options the CLI and persona left unset from the merged global and project settings.
+pub fn apply_settings_to_args(parsed: &mut Args, settings: &SettingsManager) {
+    if parsed.tools.is_none() {
+        parsed.tools = settings.get_tools();
+    }
+}
+
+/// Model used for `--provider` without `--model`.
+fn provider_default_model(provider: &str) -> Option<&'static str> {
+    match provider {
+        "anthropic" | "amazon-bedrock" => Some("claude-opus-4-5"),
+        "xai" => Some("grok-code-fast-1"),
+        "mistral" => Some("devstral-medium-latest"),
+        _ => None,
+    }
+}
+
+pub fn select_model(
+    parsed: &Args,
+    registry: &ModelRegistry,
+    settings: &SettingsManager,
+) -> Result<RegistryModel, String> {
     if let (Some(provider), Some(model_id)) = (&parsed.provider, &parsed.model) {
         return registry
             .find(provider, model_id)
@@ -351,6 +571,40 @@ pub fn select_model(parsed: &Args, registry: &ModelRegistry) -> Result<RegistryM
         ));
     }
 
+    // A settings default that is no longer available falls back like no default at all.
+    if let (Some(provider), Some(model_id)) = (
+        settings.get_default_provider(),
+        settings.get_default_model(),
+    ) {
+        if parsed
+            .provider
+            .as_deref()
+            .is_none_or(|value| value == provider)
+        {
+            if let Some(model) = registry
+                .get_available()
+                .into_iter()
+                .find(|model| model.provider == provider && model.id == model_id)
+            {
+                return Ok(model);
+            }
+        }
+    }
+
+    // `--provider` alone picks that provider's preset model, else any of its models.
+    if let Some(provider) = parsed.provider.as_deref() {
+        let available = registry.get_available();
+        let preset = provider_default_model(provider);
+        let mut models = available.iter().filter(|model| model.provider == provider);
+        if let Some(model) = models
+            .clone()
+            .find(|model| Some(model.id.as_str()) == preset)
+            .or_else(|| models.next())
+        {
+            return Ok(model.clone());
+        }
+    }
+
     if let Some(model) = registry
         .get_available()
         .iter()
@@ -366,6 +620,23 @@ pub fn select_model(parsed: &Args, registry: &ModelRegistry) -> Result<RegistryM
         .ok_or_else(|| "No models available. Set an API key in auth.json or env.".to_string())
 }
 
+/// The rest of the `--models` chain after `primary`, in priority order: the models a turn
+/// fails over to when a provider errors.
+pub fn select_fallback_models(
+    parsed: &Args,
+    registry: &ModelRegistry,
+    primary: &RegistryModel,
+) -> Vec<RegistryModel> {
+    let Some(patterns) = &parsed.models else {
+        return Vec::new();
+    };
+    crate::coding_agent::resolve_model_scope(patterns, &registry.get_available())
+        .into_iter()
+        .map(|scoped| scoped.model)
+        .filter(|model| !(model.provider == primary.provider && model.id == primary.id))
+        .collect()
+}
+
 pub fn attach_extensions_with_host(
     session: &mut crate::coding_agent::AgentSession,
     cwd: &Path,
@@ -387,7 +658,7 @@ fn attach_extensions(session: &mut crate::coding_agent::AgentSession, cwd: &Path
     if discovered.is_empty() {
         return;
     }
-    match ExtensionHost::spawn(&discovered, cwd) {
+    match spawn_extension_host(&discovered, cwd) {
         Ok((host, manifest)) => {
             report_extension_manifest(&manifest);
             // Store extension commands for autocomplete
@@ -401,6 +672,48 @@ fn attach_extensions(session: &mut crate::coding_agent::AgentSession, cwd: &Path
     }
 }
 
+/// Starts the extension host with the capabilities the user granted each extension, asking
+/// about newly requested ones when pi runs in a terminal.
+fn spawn_extension_host(
+    paths: &[PathBuf],
+    cwd: &Path,
+) -> Result<(ExtensionHost, ExtensionManifest), String> {
+    let mut permissions = ExtensionPermissions::load(config::get_extension_permissions_path());
+    ExtensionHost::spawn_with_permissions(
+        paths,
+        cwd,
+        &mut permissions,
+        &mut prompt_extension_capabilities,
+    )
+}
+
+fn prompt_extension_capabilities(extension: &str, capabilities: &[String]) -> Option<Vec<String>> {
+    let requested = capabilities.join(", ");
+    if !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+        eprintln!(
+            "Warning: Extension {extension} requests {requested}; run pi in a terminal to approve"
+        );
+        return None;
+    }
+    eprint!("Extension {extension} requests: {requested}\nAllow? [y/N or a list to allow] ");
+    let _ = io::stderr().flush();
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).ok()?;
+    let answer = input.trim();
+    match answer.to_ascii_lowercase().as_str() {
+        "y" | "yes" => Some(capabilities.to_vec()),
+        "" | "n" | "no" => Some(Vec::new()),
+        _ => Some(
+            answer
+                .split(',')
+                .map(str::trim)
+                .filter(|capability| capabilities.iter().any(|requested| requested == capability))
+                .map(str::to_string)
+                .collect(),
+        ),
+    }
+}
+
 pub fn collect_extension_tools(manifest: &ExtensionManifest) -> Vec<ExtensionTool> {
     let mut tools = Vec::new();
     for extension in &manifest.extensions {
@@ -409,6 +722,32 @@ pub fn collect_extension_tools(manifest: &ExtensionManifest) -> Vec<ExtensionToo
     tools
 }
 
+/// Prompt sections and tool descriptions set by extensions; later extensions win.
+pub fn collect_prompt_overrides(manifest: &ExtensionManifest) -> PromptOverrides {
+    let mut overrides = PromptOverrides::default();
+    for extension in &manifest.extensions {
+        overrides.merge(&PromptOverrides {
+            sections: extension.prompt_sections.clone(),
+            tool_descriptions: extension.tool_descriptions.clone(),
+            ..Default::default()
+        });
+    }
+    overrides
+}
+
+/// What `build_system_prompt` and the tool definitions use: extension overrides, with the
+/// `systemPrompt` settings on top.
+pub fn resolve_prompt_overrides(
+    preloaded: Option<&PreloadedExtensions>,
+    settings_manager: &SettingsManager,
+) -> PromptOverrides {
+    let mut overrides = preloaded
+        .map(|preloaded| collect_prompt_overrides(&preloaded.manifest))
+        .unwrap_or_default();
+    overrides.merge(&settings_manager.get_prompt_overrides());
+    overrides
+}
+
 pub fn collect_extension_commands(manifest: &ExtensionManifest) -> Vec<ExtensionCommand> {
     let mut commands = Vec::new();
     for extension in &manifest.extensions {
@@ -447,7 +786,7 @@ pub fn preload_extensions(
     Option<PreloadedExtensions>,
     HashMap<String, ExtensionFlagType>,
 ) {
-    let settings_manager = SettingsManager::create("", "");
+    let settings_manager = SettingsManager::create(cwd.to_string_lossy(), "");
     let mut extension_paths = settings_manager.get_extension_paths();
     if let Some(paths) = parsed.extensions.as_deref() {
         extension_paths.extend(paths.iter().cloned());
@@ -457,7 +796,7 @@ pub fn preload_extensions(
         return (None, HashMap::new());
     }
 
-    match ExtensionHost::spawn(&discovered, cwd) {
+    match spawn_extension_host(&discovered, cwd) {
         Ok((host, manifest)) => {
             let flag_types = collect_extension_flags(&manifest);
             (
@@ -1,31 +1,64 @@
 use crate::agent::{
-    Agent, AgentOptions, AgentStateOverride, AgentTool, AgentToolResult, LlmContext,
-    Model as AgentModel, ThinkingLevel,
+    failover_stream_fn, recording_stream_fn, Agent, AgentOptions, AgentStateOverride, AgentTool,
+    AgentToolResult, CancellationToken, LlmContext, LoopLimits, Model as AgentModel, StreamEvents,
+    ThinkingLevel,
+};
+use crate::api::bedrock::{stream_bedrock, AwsCredentials, BedrockCallOptions, BEDROCK_API};
+use crate::api::google::{
+    stream_google_generative_ai, GoogleCallOptions, GOOGLE_GENERATIVE_AI_API,
 };
 use crate::api::google_gemini_cli::{
     stream_google_gemini_cli, GeminiCliCallOptions, GeminiCliTool,
 };
+use crate::api::mock::{MockProvider, MOCK_API};
 use crate::api::openai_codex::{stream_openai_codex_responses, CodexStreamOptions, CodexTool};
+use crate::api::openai_completions::{
+    stream_openai_completions, CompletionsCallOptions, CompletionsTool, OPENAI_COMPLETIONS_API,
+};
+use crate::api::request_hook::SharedRequestHook;
+use crate::api::request_log::RequestLogger;
 use crate::api::{
     assistant_error_message, build_anthropic_messages, openai_context_to_input_items,
     AnthropicCallOptions, AnthropicTool, OpenAICallOptions, OpenAITool,
 };
 use crate::cli::args::ThinkingLevel as CliThinkingLevel;
+use crate::cli::runtime::{
+    attach_extensions_with_host, build_model_registry, build_session_manager,
+    collect_extension_tools, preload_extensions, resolve_prompt_overrides, select_fallback_models,
+    select_model, PreloadedExtensions,
+};
+use crate::cli::token_refresh::TokenRefresher;
 use crate::coding_agent::extension_host::ExtensionTool;
+use crate::coding_agent::models_config::SUPPORTED_APIS;
 use crate::coding_agent::{
-    load_prompt_templates, AgentSession, AgentSessionConfig, ExtensionHost,
-    LoadPromptTemplatesOptions, Model as RegistryModel, ModelRegistry, SettingsManager,
+    build_output_filter_chain, collect_context_dir, create_telemetry_sink, load_context_packs,
+    load_project_context_files, load_prompt_templates, load_skills, AgentSession,
+    AgentSessionConfig, BashApproval, BashPolicy, CompactionOverrides, ContextDirOptions,
+    ContextDirResult, ContextPlacement, EditReview, ExtensionHost, ExtensionRequestHook,
+    LoadContextFilesOptions, LoadContextPacksOptions, LoadPromptTemplatesOptions,
+    LoadSkillsOptions, Model as RegistryModel, ModelRegistry, ModerationModelFilter, Persona,
+    SandboxPolicy, SettingsManager, SettingsOverrides, SharedChangeJournal, SharedReadTracker,
+    Shell, SpendTracker, DEFAULT_CONTEXT_MAX_BYTES,
 };
-use crate::core::messages::ContentBlock;
+use crate::core::messages::{AssistantMessage, ContentBlock};
 use crate::core::session_manager::SessionManager;
-use crate::tools::{default_tool_names, default_tools};
+use crate::rpc::{SessionFactory, SessionSpec};
+use crate::tools::{
+    default_tool_names, default_tools, tool_verbosity_for_model, ToolVerbosity, GIT_TOOL_NAMES,
+    NOTEBOOK_TOOL_NAMES,
+};
 use crate::{coding_agent::tools as agent_tools, config};
 use serde_json::{json, Value};
 use std::cell::RefCell;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Model APIs RPC sessions can use.
+pub const RPC_MODEL_APIS: [&str; 3] = ["anthropic-messages", "openai-responses", MOCK_API];
 
 const DEFAULT_OAUTH_SYSTEM_PROMPT: &str =
     "You are Claude Code, Anthropic's official CLI for Claude.";
@@ -45,17 +78,25 @@ struct ToolSpec {
     input_schema: Value,
 }
 
+/// `tool_descriptions` replaces the descriptions of the tools it names (see `PromptOverrides`).
 fn build_tool_defs(
     tool_names: Option<&[String]>,
     extension_tools: &[ExtensionTool],
+    tool_descriptions: &HashMap<String, String>,
+    verbosity: ToolVerbosity,
 ) -> Result<Vec<ToolSpec>, String> {
     let mut specs = Vec::new();
     let default_defs = default_tools();
     for tool in default_defs {
+        if tool_names.is_none()
+            && (GIT_TOOL_NAMES.contains(&tool.name) || NOTEBOOK_TOOL_NAMES.contains(&tool.name))
+        {
+            continue;
+        }
         specs.push(ToolSpec {
             name: tool.name.to_string(),
-            description: tool.description.to_string(),
-            input_schema: tool.input_schema.clone(),
+            description: tool.description_for(verbosity).to_string(),
+            input_schema: tool.input_schema_for(verbosity),
         });
     }
 
@@ -97,9 +138,26 @@ fn build_tool_defs(
         }
         specs.retain(|tool| tool_names.iter().any(|name| name == &tool.name));
     }
+    for spec in &mut specs {
+        if let Some(description) = tool_descriptions.get(&spec.name) {
+            spec.description = description.clone();
+        }
+    }
     Ok(specs)
 }
 
+/// Keeps the agent's tool list in step with the definitions `build_tool_defs` sends.
+pub(crate) fn apply_tool_descriptions(
+    tools: &mut [AgentTool],
+    tool_descriptions: &HashMap<String, String>,
+) {
+    for tool in tools {
+        if let Some(description) = tool_descriptions.get(&tool.name) {
+            tool.description = description.clone();
+        }
+    }
+}
+
 pub fn to_agent_model(model: &RegistryModel) -> AgentModel {
     AgentModel {
         id: model.id.clone(),
@@ -109,13 +167,34 @@ pub fn to_agent_model(model: &RegistryModel) -> AgentModel {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn build_agent_tools(
     cwd: &PathBuf,
     tool_names: Option<&[String]>,
     extension_tools: &[ExtensionTool],
     extension_host: Option<Rc<RefCell<ExtensionHost>>>,
+    change_journal: &SharedChangeJournal,
+    sandbox: &SandboxPolicy,
+    bash_approval: &BashApproval,
+    edit_review: &EditReview,
+    cancel: &CancellationToken,
 ) -> Result<Vec<AgentTool>, String> {
-    let available = ["read", "write", "edit", "bash", "grep", "find", "ls"];
+    let available = [
+        "read",
+        "write",
+        "edit",
+        "bash",
+        "grep",
+        "find",
+        "ls",
+        "git_status",
+        "git_diff",
+        "git_log",
+        "git_commit",
+        "git_stash",
+        "notebook_read",
+        "notebook_edit",
+    ];
     let mut available_set = HashSet::new();
     for name in available {
         available_set.insert(name.to_string());
@@ -143,6 +222,9 @@ pub fn build_agent_tools(
     };
 
     let selected_set = selected.iter().cloned().collect::<HashSet<_>>();
+    let reviewed = SettingsManager::create("", "").get_review_edit_tools();
+    // Shared by the file tools so writes can refuse files that changed since they were read.
+    let reads = SharedReadTracker::default();
 
     let mut tools = Vec::new();
     for name in available {
@@ -151,12 +233,15 @@ pub fn build_agent_tools(
         }
         match name {
             "read" => {
-                let tool = agent_tools::ReadTool::new(cwd);
+                let tool = agent_tools::ReadTool::new(cwd)
+                    .with_sandbox(sandbox.clone())
+                    .with_read_tracker(reads.clone());
                 tools.push(AgentTool {
                     name: "read".to_string(),
                     label: "read".to_string(),
                     description: "Read file contents".to_string(),
-                    execute: Rc::new(move |call_id, params| {
+                    parameters: None,
+                    execute: Rc::new(move |call_id, params, _on_update| {
                         let args = parse_read_args(params)?;
                         let result = tool.execute(call_id, args)?;
                         Ok(tool_result_to_agent_result(result))
@@ -164,12 +249,19 @@ pub fn build_agent_tools(
                 });
             }
             "write" => {
-                let tool = agent_tools::WriteTool::new(cwd);
+                let mut tool = agent_tools::WriteTool::new(cwd)
+                    .with_journal(change_journal.clone())
+                    .with_sandbox(sandbox.clone())
+                    .with_read_tracker(reads.clone());
+                if reviewed.iter().any(|tool| tool == "write") {
+                    tool = tool.with_review(edit_review.clone());
+                }
                 tools.push(AgentTool {
                     name: "write".to_string(),
                     label: "write".to_string(),
                     description: "Write file contents".to_string(),
-                    execute: Rc::new(move |call_id, params| {
+                    parameters: None,
+                    execute: Rc::new(move |call_id, params, _on_update| {
                         let args = parse_write_args(params)?;
                         let result = tool.execute(call_id, args)?;
                         Ok(tool_result_to_agent_result(result))
@@ -177,12 +269,19 @@ pub fn build_agent_tools(
                 });
             }
             "edit" => {
-                let tool = agent_tools::EditTool::new(cwd);
+                let mut tool = agent_tools::EditTool::new(cwd)
+                    .with_journal(change_journal.clone())
+                    .with_sandbox(sandbox.clone())
+                    .with_read_tracker(reads.clone());
+                if reviewed.iter().any(|tool| tool == "edit") {
+                    tool = tool.with_review(edit_review.clone());
+                }
                 tools.push(AgentTool {
                     name: "edit".to_string(),
                     label: "edit".to_string(),
                     description: "Edit file contents".to_string(),
-                    execute: Rc::new(move |call_id, params| {
+                    parameters: None,
+                    execute: Rc::new(move |call_id, params, _on_update| {
                         let args = parse_edit_args(params)?;
                         let result = tool.execute(call_id, args)?;
                         Ok(tool_result_to_agent_result(result))
@@ -190,25 +289,40 @@ pub fn build_agent_tools(
                 });
             }
             "bash" => {
-                let tool = agent_tools::BashTool::new(cwd);
+                let settings_manager = SettingsManager::create("", "");
+                let shell_path = settings_manager.get_shell_path();
+                let bash_policy =
+                    BashPolicy::from_settings(&settings_manager.get_bash_policy_settings());
+                let tool = agent_tools::BashTool::new(cwd)
+                    .with_shell(Shell::configured_or(
+                        shell_path.as_deref(),
+                        Shell::tool_default,
+                    ))
+                    .with_sandbox(sandbox.clone())
+                    .with_policy(bash_policy, bash_approval.clone())
+                    .with_cancellation(cancel.clone());
                 tools.push(AgentTool {
                     name: "bash".to_string(),
                     label: "bash".to_string(),
                     description: "Execute bash commands".to_string(),
-                    execute: Rc::new(move |call_id, params| {
+                    parameters: None,
+                    execute: Rc::new(move |call_id, params, on_update| {
                         let args = parse_bash_args(params)?;
-                        let result = tool.execute(call_id, args)?;
+                        let result = tool.execute_with_updates(call_id, args, &mut |partial| {
+                            on_update(tool_result_to_agent_result(partial))
+                        })?;
                         Ok(tool_result_to_agent_result(result))
                     }),
                 });
             }
             "grep" => {
-                let tool = agent_tools::GrepTool::new(cwd);
+                let tool = agent_tools::GrepTool::new(cwd).with_sandbox(sandbox.clone());
                 tools.push(AgentTool {
                     name: "grep".to_string(),
                     label: "grep".to_string(),
                     description: "Search file contents".to_string(),
-                    execute: Rc::new(move |call_id, params| {
+                    parameters: None,
+                    execute: Rc::new(move |call_id, params, _on_update| {
                         let args = parse_grep_args(params)?;
                         let result = tool.execute(call_id, args)?;
                         Ok(tool_result_to_agent_result(result))
@@ -216,12 +330,13 @@ pub fn build_agent_tools(
                 });
             }
             "find" => {
-                let tool = agent_tools::FindTool::new(cwd);
+                let tool = agent_tools::FindTool::new(cwd).with_sandbox(sandbox.clone());
                 tools.push(AgentTool {
                     name: "find".to_string(),
                     label: "find".to_string(),
                     description: "Find files by pattern".to_string(),
-                    execute: Rc::new(move |call_id, params| {
+                    parameters: None,
+                    execute: Rc::new(move |call_id, params, _on_update| {
                         let args = parse_find_args(params)?;
                         let result = tool.execute(call_id, args)?;
                         Ok(tool_result_to_agent_result(result))
@@ -229,18 +344,145 @@ pub fn build_agent_tools(
                 });
             }
             "ls" => {
-                let tool = agent_tools::LsTool::new(cwd);
+                let tool = agent_tools::LsTool::new(cwd).with_sandbox(sandbox.clone());
                 tools.push(AgentTool {
                     name: "ls".to_string(),
                     label: "ls".to_string(),
                     description: "List directory contents".to_string(),
-                    execute: Rc::new(move |call_id, params| {
+                    parameters: None,
+                    execute: Rc::new(move |call_id, params, _on_update| {
                         let args = parse_ls_args(params)?;
                         let result = tool.execute(call_id, args)?;
                         Ok(tool_result_to_agent_result(result))
                     }),
                 });
             }
+            "git_status" => {
+                let tool = agent_tools::GitTool::new(cwd);
+                tools.push(AgentTool {
+                    name: "git_status".to_string(),
+                    label: "git status".to_string(),
+                    description: "Show git status".to_string(),
+                    parameters: None,
+                    execute: Rc::new(move |call_id, _params, _on_update| {
+                        let result = tool.status(call_id)?;
+                        Ok(tool_result_to_agent_result(result))
+                    }),
+                });
+            }
+            "git_diff" => {
+                let tool = agent_tools::GitTool::new(cwd);
+                tools.push(AgentTool {
+                    name: "git_diff".to_string(),
+                    label: "git diff".to_string(),
+                    description: "Show git diff".to_string(),
+                    parameters: None,
+                    execute: Rc::new(move |call_id, params, _on_update| {
+                        let args = agent_tools::GitDiffToolArgs {
+                            path: get_optional_string(params, "path"),
+                            staged: get_optional_bool(params, "staged"),
+                        };
+                        let result = tool.diff(call_id, args)?;
+                        Ok(tool_result_to_agent_result(result))
+                    }),
+                });
+            }
+            "git_log" => {
+                let tool = agent_tools::GitTool::new(cwd);
+                tools.push(AgentTool {
+                    name: "git_log".to_string(),
+                    label: "git log".to_string(),
+                    description: "Show git log".to_string(),
+                    parameters: None,
+                    execute: Rc::new(move |call_id, params, _on_update| {
+                        let args = agent_tools::GitLogToolArgs {
+                            path: get_optional_string(params, "path"),
+                            limit: get_optional_usize(params, "limit"),
+                        };
+                        let result = tool.log(call_id, args)?;
+                        Ok(tool_result_to_agent_result(result))
+                    }),
+                });
+            }
+            "git_commit" => {
+                let tool = agent_tools::GitTool::new(cwd);
+                tools.push(AgentTool {
+                    name: "git_commit".to_string(),
+                    label: "git commit".to_string(),
+                    description: "Create a git commit".to_string(),
+                    parameters: None,
+                    execute: Rc::new(move |call_id, params, _on_update| {
+                        let args = agent_tools::GitCommitToolArgs {
+                            message: get_required_string(params, "message")?,
+                            all: get_optional_bool(params, "all"),
+                        };
+                        let result = tool.commit(call_id, args)?;
+                        Ok(tool_result_to_agent_result(result))
+                    }),
+                });
+            }
+            "git_stash" => {
+                let tool = agent_tools::GitTool::new(cwd);
+                tools.push(AgentTool {
+                    name: "git_stash".to_string(),
+                    label: "git stash".to_string(),
+                    description: "Push, pop, or list git stashes".to_string(),
+                    parameters: None,
+                    execute: Rc::new(move |call_id, params, _on_update| {
+                        let args = agent_tools::GitStashToolArgs {
+                            action: get_optional_string(params, "action"),
+                            message: get_optional_string(params, "message"),
+                        };
+                        let result = tool.stash(call_id, args)?;
+                        Ok(tool_result_to_agent_result(result))
+                    }),
+                });
+            }
+            "notebook_read" => {
+                let tool = agent_tools::NotebookTool::new(cwd)
+                    .with_sandbox(sandbox.clone())
+                    .with_read_tracker(reads.clone());
+                tools.push(AgentTool {
+                    name: "notebook_read".to_string(),
+                    label: "notebook read".to_string(),
+                    description: "Read Jupyter notebook cells".to_string(),
+                    parameters: None,
+                    execute: Rc::new(move |call_id, params, _on_update| {
+                        let args = agent_tools::NotebookReadToolArgs {
+                            path: get_required_string(params, "path")?,
+                            offset: get_optional_usize(params, "offset"),
+                            limit: get_optional_usize(params, "limit"),
+                            outputs: get_optional_bool(params, "outputs"),
+                        };
+                        let result = tool.read(call_id, args)?;
+                        Ok(tool_result_to_agent_result(result))
+                    }),
+                });
+            }
+            "notebook_edit" => {
+                let tool = agent_tools::NotebookTool::new(cwd)
+                    .with_journal(change_journal.clone())
+                    .with_sandbox(sandbox.clone())
+                    .with_read_tracker(reads.clone());
+                tools.push(AgentTool {
+                    name: "notebook_edit".to_string(),
+                    label: "notebook edit".to_string(),
+                    description: "Replace, insert, or delete a notebook cell".to_string(),
+                    parameters: None,
+                    execute: Rc::new(move |call_id, params, _on_update| {
+                        let args = agent_tools::NotebookEditToolArgs {
+                            path: get_required_string(params, "path")?,
+                            action: get_optional_string(params, "action"),
+                            index: get_optional_usize(params, "index"),
+                            cell_id: get_optional_string(params, "cellId"),
+                            source: get_optional_string(params, "source"),
+                            cell_type: get_optional_string(params, "cellType"),
+                        };
+                        let result = tool.edit(call_id, args)?;
+                        Ok(tool_result_to_agent_result(result))
+                    }),
+                });
+            }
             _ => {}
         }
     }
@@ -254,7 +496,7 @@ pub fn build_agent_tools(
                 "Extension tools requested but extension host is not available.".to_string(),
             );
         }
-        return Ok(tools);
+        return Ok(with_parameter_schemas(tools, extension_tools));
     };
 
     for tool in extension_tools {
@@ -273,10 +515,20 @@ pub fn build_agent_tools(
             name: tool_name.clone(),
             label,
             description,
-            execute: Rc::new(move |call_id, params| {
-                let result = host_ref
-                    .borrow_mut()
-                    .call_tool(&tool_name, call_id, params, &[])?;
+            parameters: None,
+            execute: Rc::new(move |call_id, params, on_update| {
+                let result = host_ref.borrow_mut().call_tool_with_updates(
+                    &tool_name,
+                    call_id,
+                    params,
+                    &[],
+                    &mut |partial| {
+                        on_update(AgentToolResult {
+                            content: partial.content,
+                            details: partial.details.unwrap_or(Value::Null),
+                        })
+                    },
+                )?;
                 if result.is_error {
                     let message = result
                         .content
@@ -286,7 +538,7 @@ pub fn build_agent_tools(
                             _ => None,
                         })
                         .unwrap_or_else(|| "Extension tool failed".to_string());
-                    return Err(message);
+                    return Err(message.into());
                 }
                 Ok(AgentToolResult {
                     content: result.content,
@@ -296,7 +548,33 @@ pub fn build_agent_tools(
         });
     }
 
-    Ok(tools)
+    Ok(with_parameter_schemas(tools, extension_tools))
+}
+
+/// Gives each tool its input schema, so the agent loop checks a call's arguments before
+/// running it and the model gets every problem back at once instead of whichever one the
+/// parser hits first.
+fn with_parameter_schemas(
+    tools: Vec<AgentTool>,
+    extension_tools: &[ExtensionTool],
+) -> Vec<AgentTool> {
+    let default_defs = default_tools();
+    tools
+        .into_iter()
+        .map(|tool| {
+            let parameters = default_defs
+                .iter()
+                .find(|def| def.name == tool.name)
+                .map(|def| def.input_schema.clone())
+                .or_else(|| {
+                    extension_tools
+                        .iter()
+                        .find(|extension| extension.name == tool.name)
+                        .and_then(|extension| extension.parameters.clone())
+                });
+            AgentTool { parameters, ..tool }
+        })
+        .collect()
 }
 
 fn parse_read_args(params: &Value) -> Result<agent_tools::ReadToolArgs, String> {
@@ -304,6 +582,9 @@ fn parse_read_args(params: &Value) -> Result<agent_tools::ReadToolArgs, String>
         path: get_required_string(params, "path")?,
         offset: get_optional_usize(params, "offset"),
         limit: get_optional_usize(params, "limit"),
+        symbol: get_optional_string(params, "symbol"),
+        outline: get_optional_bool(params, "outline"),
+        line_numbers: get_optional_bool(params, "lineNumbers"),
     })
 }
 
@@ -338,6 +619,8 @@ fn parse_grep_args(params: &Value) -> Result<agent_tools::GrepToolArgs, String>
         literal: get_optional_bool(params, "literal"),
         context: get_optional_usize(params, "context"),
         limit: get_optional_usize(params, "limit"),
+        multiline: get_optional_bool(params, "multiline"),
+        file_type: get_optional_string(params, "type"),
     })
 }
 
@@ -346,6 +629,10 @@ fn parse_find_args(params: &Value) -> Result<agent_tools::FindToolArgs, String>
         pattern: get_required_string(params, "pattern")?,
         path: get_optional_string(params, "path"),
         limit: get_optional_usize(params, "limit"),
+        max_depth: get_optional_usize(params, "maxDepth"),
+        entry_type: get_optional_string(params, "type"),
+        sort: get_optional_string(params, "sort"),
+        include_ignored: get_optional_bool(params, "includeIgnored"),
     })
 }
 
@@ -353,6 +640,10 @@ fn parse_ls_args(params: &Value) -> Result<agent_tools::LsToolArgs, String> {
     Ok(agent_tools::LsToolArgs {
         path: get_optional_string(params, "path"),
         limit: get_optional_usize(params, "limit"),
+        max_depth: get_optional_usize(params, "maxDepth"),
+        entry_type: get_optional_string(params, "type"),
+        sort: get_optional_string(params, "sort"),
+        include_ignored: get_optional_bool(params, "includeIgnored"),
     })
 }
 
@@ -404,9 +695,10 @@ fn tool_result_to_agent_result(result: agent_tools::ToolResult) -> AgentToolResu
 
 fn build_stream_fn(
     model: RegistryModel,
-    api_key: String,
+    mut api_key: TokenRefresher,
     use_oauth: bool,
     tool_specs: Vec<AnthropicTool>,
+    request_hook: Option<SharedRequestHook>,
 ) -> AgentStreamFn {
     Box::new(move |_agent_model, context, events| {
         // OAuth tokens require the Claude Code identification in the system prompt
@@ -428,149 +720,325 @@ fn build_stream_fn(
             Some(system_with_oauth_prefix.as_str())
         };
 
-        let messages = build_anthropic_messages(context);
-        let response = crate::api::stream_anthropic(
+        let response = api_key.call_with_retry(|api_key| {
+            crate::api::stream_anthropic(
+                &model,
+                build_anthropic_messages(context),
+                AnthropicCallOptions {
+                    model: &model.id,
+                    api_key,
+                    use_oauth,
+                    tools: &tool_specs,
+                    base_url: if model.base_url.is_empty() {
+                        "https://api.anthropic.com/v1"
+                    } else {
+                        model.base_url.as_str()
+                    },
+                    extra_headers: model.headers.as_ref(),
+                    system,
+                    request_hook: request_hook.as_deref(),
+                },
+                events,
+            )
+        });
+
+        match response {
+            Ok(response) => response,
+            Err(err) => failed_reply(&model, err, events),
+        }
+    })
+}
+
+fn build_completions_stream_fn(
+    model: RegistryModel,
+    api_key: String,
+    tool_specs: Vec<CompletionsTool>,
+    seed: Option<u64>,
+    request_hook: Option<SharedRequestHook>,
+) -> AgentStreamFn {
+    Box::new(move |_agent_model, context, events| {
+        let system = Some(context.system_prompt.as_str()).filter(|text| !text.trim().is_empty());
+        let response = stream_openai_completions(
             &model,
-            messages,
-            AnthropicCallOptions {
+            context,
+            CompletionsCallOptions {
                 model: &model.id,
                 api_key: &api_key,
-                use_oauth,
                 tools: &tool_specs,
-                base_url: if model.base_url.is_empty() {
-                    "https://api.anthropic.com/v1"
-                } else {
-                    model.base_url.as_str()
-                },
+                base_url: &model.base_url,
                 extra_headers: model.headers.as_ref(),
                 system,
+                seed,
+                request_hook: request_hook.as_deref(),
             },
             events,
         );
 
         match response {
             Ok(response) => response,
-            Err(err) => assistant_error_message(&model, &err),
+            Err(err) => failed_reply(&model, err, events),
         }
     })
 }
 
-fn build_openai_stream_fn(
+fn build_bedrock_stream_fn(
     model: RegistryModel,
-    api_key: String,
-    tool_specs: Vec<OpenAITool>,
+    credentials: AwsCredentials,
+    tool_specs: Vec<AnthropicTool>,
+    request_hook: Option<SharedRequestHook>,
 ) -> AgentStreamFn {
     Box::new(move |_agent_model, context, events| {
-        let input = openai_context_to_input_items(&model, context);
-        let response = crate::api::stream_openai_responses(
+        let system = Some(context.system_prompt.as_str()).filter(|text| !text.trim().is_empty());
+        let response = stream_bedrock(
             &model,
-            input,
-            OpenAICallOptions {
+            build_anthropic_messages(context),
+            BedrockCallOptions {
                 model: &model.id,
-                api_key: &api_key,
+                credentials: &credentials,
                 tools: &tool_specs,
-                base_url: if model.base_url.is_empty() {
-                    "https://api.openai.com/v1"
-                } else {
-                    model.base_url.as_str()
-                },
+                base_url: &model.base_url,
                 extra_headers: model.headers.as_ref(),
+                system,
+                max_tokens: u32::try_from(model.max_tokens).ok().filter(|max| *max > 0),
+                request_hook: request_hook.as_deref(),
             },
             events,
         );
 
         match response {
             Ok(response) => response,
-            Err(err) => assistant_error_message(&model, &err),
+            Err(err) => failed_reply(&model, err, events),
+        }
+    })
+}
+
+fn build_openai_stream_fn(
+    model: RegistryModel,
+    mut api_key: TokenRefresher,
+    tool_specs: Vec<OpenAITool>,
+    seed: Option<u64>,
+    request_hook: Option<SharedRequestHook>,
+) -> AgentStreamFn {
+    Box::new(move |_agent_model, context, events| {
+        let response = api_key.call_with_retry(|api_key| {
+            crate::api::stream_openai_responses(
+                &model,
+                openai_context_to_input_items(&model, context),
+                OpenAICallOptions {
+                    model: &model.id,
+                    api_key,
+                    tools: &tool_specs,
+                    base_url: if model.base_url.is_empty() {
+                        "https://api.openai.com/v1"
+                    } else {
+                        model.base_url.as_str()
+                    },
+                    extra_headers: model.headers.as_ref(),
+                    seed,
+                    request_hook: request_hook.as_deref(),
+                },
+                events,
+            )
+        });
+
+        match response {
+            Ok(response) => response,
+            Err(err) => failed_reply(&model, err, events),
         }
     })
 }
 
 fn build_codex_stream_fn(
     model: RegistryModel,
-    api_key: String,
+    mut api_key: TokenRefresher,
     tool_specs: Vec<CodexTool>,
+    request_hook: Option<SharedRequestHook>,
 ) -> AgentStreamFn {
     Box::new(move |_agent_model, context, events| {
-        let response = stream_openai_codex_responses(
-            &model,
-            context,
-            &api_key,
-            &tool_specs,
-            CodexStreamOptions {
-                codex_mode: Some(true),
-                extra_headers: model.headers.clone(),
-                ..Default::default()
-            },
-            events,
-        );
+        let response = api_key.call_with_retry(|api_key| {
+            stream_openai_codex_responses(
+                &model,
+                context,
+                api_key,
+                &tool_specs,
+                CodexStreamOptions {
+                    codex_mode: Some(true),
+                    extra_headers: model.headers.clone(),
+                    request_hook: request_hook.clone(),
+                    ..Default::default()
+                },
+                events,
+            )
+        });
+
+        match response {
+            Ok(response) => response,
+            Err(err) => failed_reply(&model, err, events),
+        }
+    })
+}
+
+fn build_google_stream_fn(
+    model: RegistryModel,
+    mut api_key: TokenRefresher,
+    tool_specs: Vec<GeminiCliTool>,
+    seed: Option<u64>,
+    request_hook: Option<SharedRequestHook>,
+) -> AgentStreamFn {
+    Box::new(move |_agent_model, context, events| {
+        let system = Some(context.system_prompt.as_str()).filter(|text| !text.trim().is_empty());
+        let response = api_key.call_with_retry(|api_key| {
+            stream_google_generative_ai(
+                &model,
+                context,
+                GoogleCallOptions {
+                    model: &model.id,
+                    api_key,
+                    tools: &tool_specs,
+                    base_url: &model.base_url,
+                    extra_headers: model.headers.as_ref(),
+                    system,
+                    thinking_enabled: model.reasoning,
+                    seed,
+                    request_hook: request_hook.as_deref(),
+                },
+                events,
+            )
+        });
 
         match response {
             Ok(response) => response,
-            Err(err) => assistant_error_message(&model, &err),
+            Err(err) => failed_reply(&model, err, events),
         }
     })
 }
 
 fn build_gemini_cli_stream_fn(
     model: RegistryModel,
-    access_token: String,
+    mut access_token: TokenRefresher,
     project_id: String,
     tool_specs: Vec<GeminiCliTool>,
+    seed: Option<u64>,
+    request_hook: Option<SharedRequestHook>,
 ) -> AgentStreamFn {
     Box::new(move |_agent_model, context, events| {
-        let response = stream_google_gemini_cli(
-            &model,
-            context,
-            GeminiCliCallOptions {
-                model: &model.id,
-                access_token: &access_token,
-                project_id: &project_id,
-                tools: &tool_specs,
-                base_url: &model.base_url,
-                system: None, // Will be set from context
-                thinking_enabled: model.reasoning,
-            },
-            events,
-        );
+        let response = access_token.call_with_retry(|access_token| {
+            stream_google_gemini_cli(
+                &model,
+                context,
+                GeminiCliCallOptions {
+                    model: &model.id,
+                    access_token,
+                    project_id: &project_id,
+                    tools: &tool_specs,
+                    base_url: &model.base_url,
+                    system: None, // Will be set from context
+                    thinking_enabled: model.reasoning,
+                    seed,
+                    request_hook: request_hook.as_deref(),
+                },
+                events,
+            )
+        });
 
         match response {
             Ok(response) => response,
-            Err(err) => assistant_error_message(&model, &err),
+            Err(err) => failed_reply(&model, err, events),
         }
     })
 }
 
-fn merge_system_prompt(
-    system_prompt: Option<String>,
-    append_system_prompt: Option<String>,
-) -> Option<String> {
-    let mut system = system_prompt;
-    if let Some(append) = append_system_prompt {
-        system = Some(match system {
-            Some(base) => format!("{base}\n\n{append}"),
-            None => append,
-        });
-    }
-    system
+/// The reply for a provider call that failed before it produced a message. The typed error
+/// goes to the agent through `events`.
+fn failed_reply(
+    model: &RegistryModel,
+    err: crate::Error,
+    events: &mut StreamEvents,
+) -> AssistantMessage {
+    let message = assistant_error_message(model, &err.to_string());
+    events.set_error(err);
+    message
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn create_cli_session(
-    model: RegistryModel,
-    registry: ModelRegistry,
-    system_prompt: Option<String>,
-    append_system_prompt: Option<String>,
+/// Scripted replies from the fixture file a mock model's base URL names.
+fn build_mock_stream_fn(model: RegistryModel) -> Result<AgentStreamFn, String> {
+    let mut provider = MockProvider::load(Path::new(&model.base_url))?;
+    Ok(Box::new(move |_agent_model, _context, events| {
+        provider.stream(&model, events)
+    }))
+}
+
+/// Sends `context` to `model` once, with the CLI's tool definitions, and returns the reply
+/// without running any tools it calls. `mode` names the caller in errors.
+pub fn complete_llm_context(
+    model: &RegistryModel,
     tool_names: Option<&[String]>,
     extension_tools: &[ExtensionTool],
-    extension_host: Option<Rc<RefCell<ExtensionHost>>>,
     api_key_override: Option<&str>,
-    session_manager: SessionManager,
-) -> Result<AgentSession, String> {
-    let cwd = env::current_dir().map_err(|err| err.to_string())?;
-    let agent_tools = build_agent_tools(&cwd, tool_names, extension_tools, extension_host)?;
-    let tool_defs = build_tool_defs(tool_names, extension_tools)?;
+    context: &LlmContext,
+    mode: &str,
+) -> Result<AssistantMessage, String> {
+    let verbosity = tool_verbosity_for_model(model.context_window, model.cost.input);
+    let tool_defs = build_tool_defs(tool_names, extension_tools, &HashMap::new(), verbosity)?;
+    let mut stream_fn =
+        build_model_stream_fn(model, &tool_defs, api_key_override, None, None, mode)?;
+    let mut events = StreamEvents::new(Box::new(|_| {}));
+    Ok(stream_fn(&to_agent_model(model), context, &mut events))
+}
+
+/// Hook that lets extensions rewrite outgoing provider requests, if any handle them.
+fn extension_request_hook(
+    extension_host: Option<&Rc<RefCell<ExtensionHost>>>,
+) -> Option<SharedRequestHook> {
+    ExtensionRequestHook::for_host(extension_host?.clone()).map(SharedRequestHook::new)
+}
+
+pub fn api_supports_seed(api: &str) -> bool {
+    matches!(
+        api,
+        "openai-responses"
+            | OPENAI_COMPLETIONS_API
+            | GOOGLE_GENERATIVE_AI_API
+            | "google-gemini-cli"
+    )
+}
 
+/// Picks the seed for this run: the explicit flag, then the seed recorded in a resumed
+/// session, then settings. A newly chosen seed is recorded in the session file.
+fn resolve_seed(
+    model: &RegistryModel,
+    seed: Option<u64>,
+    settings_manager: &SettingsManager,
+    session_manager: &mut SessionManager,
+) -> Option<u64> {
+    if !api_supports_seed(&model.api) {
+        return None;
+    }
+    let recorded = session_manager.get_seed();
+    let seed = seed.or(recorded).or_else(|| settings_manager.get_seed())?;
+    if recorded != Some(seed) {
+        session_manager.append_seed(seed);
+    }
+    Some(seed)
+}
+
+/// Stored OAuth tokens are refreshed as they expire; `--api-key` values are used as given.
+fn token_refresher(provider: &str, api_key_override: Option<&str>, token: &str) -> TokenRefresher {
+    match api_key_override {
+        Some(_) => TokenRefresher::fixed(token),
+        None => TokenRefresher::for_provider(provider, config::get_auth_path(), token),
+    }
+}
+
+/// Build the streaming function for `model`'s API. `mode` names the caller in errors.
+fn build_model_stream_fn(
+    model: &RegistryModel,
+    tool_defs: &[ToolSpec],
+    api_key_override: Option<&str>,
+    seed: Option<u64>,
+    request_hook: Option<SharedRequestHook>,
+    mode: &str,
+) -> Result<AgentStreamFn, String> {
     let stream_fn = match model.api.as_str() {
         "anthropic-messages" => {
             let (api_key, use_oauth) =
@@ -583,7 +1051,20 @@ pub fn create_cli_session(
                     input_schema: tool.input_schema.clone(),
                 })
                 .collect::<Vec<_>>();
-            build_stream_fn(model.clone(), api_key, use_oauth, tool_specs)
+            let api_key = token_refresher("anthropic", api_key_override, &api_key);
+            build_stream_fn(model.clone(), api_key, use_oauth, tool_specs, request_hook)
+        }
+        BEDROCK_API => {
+            let credentials = crate::cli::auth::resolve_bedrock_credentials(api_key_override)?;
+            let tool_specs = tool_defs
+                .iter()
+                .map(|tool| AnthropicTool {
+                    name: tool.name.clone(),
+                    description: tool.description.clone(),
+                    input_schema: tool.input_schema.clone(),
+                })
+                .collect::<Vec<_>>();
+            build_bedrock_stream_fn(model.clone(), credentials, tool_specs, request_hook)
         }
         "openai-responses" => {
             let api_key = crate::cli::auth::resolve_openai_credentials(api_key_override)?;
@@ -596,7 +1077,23 @@ pub fn create_cli_session(
                     parameters: tool.input_schema.clone(),
                 })
                 .collect::<Vec<_>>();
-            build_openai_stream_fn(model.clone(), api_key, tool_specs)
+            let api_key = token_refresher("openai", api_key_override, &api_key);
+            build_openai_stream_fn(model.clone(), api_key, tool_specs, seed, request_hook)
+        }
+        OPENAI_COMPLETIONS_API => {
+            let api_key = crate::cli::auth::resolve_openai_compatible_credentials(
+                &model.provider,
+                api_key_override,
+            )?;
+            let tool_specs = tool_defs
+                .iter()
+                .map(|tool| CompletionsTool {
+                    name: tool.name.clone(),
+                    description: tool.description.clone(),
+                    parameters: tool.input_schema.clone(),
+                })
+                .collect::<Vec<_>>();
+            build_completions_stream_fn(model.clone(), api_key, tool_specs, seed, request_hook)
         }
         "openai-codex-responses" => {
             let api_key = crate::cli::auth::resolve_openai_codex_credentials(api_key_override)?;
@@ -610,7 +1107,21 @@ pub fn create_cli_session(
                     strict: None,
                 })
                 .collect::<Vec<_>>();
-            build_codex_stream_fn(model.clone(), api_key, tool_specs)
+            let api_key = token_refresher("openai-codex", api_key_override, &api_key);
+            build_codex_stream_fn(model.clone(), api_key, tool_specs, request_hook)
+        }
+        GOOGLE_GENERATIVE_AI_API => {
+            let api_key = crate::cli::auth::resolve_google_credentials(api_key_override)?;
+            let tool_specs = tool_defs
+                .iter()
+                .map(|tool| GeminiCliTool {
+                    name: tool.name.clone(),
+                    description: tool.description.clone(),
+                    parameters: tool.input_schema.clone(),
+                })
+                .collect::<Vec<_>>();
+            let api_key = token_refresher("google", api_key_override, &api_key);
+            build_google_stream_fn(model.clone(), api_key, tool_specs, seed, request_hook)
         }
         "google-gemini-cli" => {
             let (access_token, project_id) =
@@ -623,15 +1134,142 @@ pub fn create_cli_session(
                     parameters: tool.input_schema.clone(),
                 })
                 .collect::<Vec<_>>();
-            build_gemini_cli_stream_fn(model.clone(), access_token, project_id, tool_specs)
+            let access_token =
+                token_refresher("google-gemini-cli", api_key_override, &access_token);
+            build_gemini_cli_stream_fn(
+                model.clone(),
+                access_token,
+                project_id,
+                tool_specs,
+                seed,
+                request_hook,
+            )
         }
+        MOCK_API => build_mock_stream_fn(model.clone())?,
         _ => {
             return Err(format!(
-                "Model API \"{}\" is not supported in print mode.",
+                "Model API \"{}\" is not supported in {mode}.",
                 model.api
             ))
         }
     };
+    Ok(stream_fn)
+}
+
+/// `model`'s stream function, failing over to `fallback_models` in order when a turn ends in
+/// a provider error. Fallbacks that cannot be set up (no credentials) are skipped.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn build_session_stream_fn(
+    model: &RegistryModel,
+    fallback_models: &[RegistryModel],
+    tool_names: Option<&[String]>,
+    extension_tools: &[ExtensionTool],
+    tool_descriptions: &HashMap<String, String>,
+    api_key_override: Option<&str>,
+    seed: Option<u64>,
+    request_hook: Option<SharedRequestHook>,
+    mode: &str,
+) -> Result<AgentStreamFn, String> {
+    let verbosity = tool_verbosity_for_model(model.context_window, model.cost.input);
+    let tool_defs = build_tool_defs(tool_names, extension_tools, tool_descriptions, verbosity)?;
+    let primary = build_model_stream_fn(
+        model,
+        &tool_defs,
+        api_key_override,
+        seed,
+        request_hook.clone(),
+        mode,
+    )?;
+    let mut fallbacks = Vec::new();
+    for fallback in fallback_models {
+        let verbosity = tool_verbosity_for_model(fallback.context_window, fallback.cost.input);
+        let tool_defs = build_tool_defs(tool_names, extension_tools, tool_descriptions, verbosity)?;
+        // --api-key belongs to the primary model's provider.
+        let api_key = api_key_override.filter(|_| fallback.provider == model.provider);
+        let seed = seed.filter(|_| api_supports_seed(&fallback.api));
+        match build_model_stream_fn(
+            fallback,
+            &tool_defs,
+            api_key,
+            seed,
+            request_hook.clone(),
+            mode,
+        ) {
+            Ok(stream_fn) => fallbacks.push(stream_fn),
+            Err(err) => eprintln!(
+                "Warning: Skipping failover model {}/{}: {err}",
+                fallback.provider, fallback.id
+            ),
+        }
+    }
+    if fallbacks.is_empty() {
+        return Ok(primary);
+    }
+    Ok(failover_stream_fn(primary, fallbacks))
+}
+
+fn merge_system_prompt(
+    system_prompt: Option<String>,
+    append_system_prompt: Option<String>,
+) -> Option<String> {
+    let mut system = system_prompt;
+    if let Some(append) = append_system_prompt {
+        system = Some(match system {
+            Some(base) => format!("{base}\n\n{append}"),
+            None => append,
+        });
+    }
+    system
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_cli_session(
+    model: RegistryModel,
+    fallback_models: &[RegistryModel],
+    registry: ModelRegistry,
+    system_prompt: Option<String>,
+    append_system_prompt: Option<String>,
+    tool_names: Option<&[String]>,
+    extension_tools: &[ExtensionTool],
+    tool_descriptions: &HashMap<String, String>,
+    extension_host: Option<Rc<RefCell<ExtensionHost>>>,
+    api_key_override: Option<&str>,
+    seed: Option<u64>,
+    mut session_manager: SessionManager,
+    sandbox: &SandboxPolicy,
+) -> Result<AgentSession, String> {
+    let cwd = env::current_dir().map_err(|err| err.to_string())?;
+    let request_hook = extension_request_hook(extension_host.as_ref());
+    let change_journal = SharedChangeJournal::default();
+    let bash_approval = BashApproval::default();
+    let edit_review = EditReview::default();
+    let cancel = CancellationToken::new();
+    let mut agent_tools = build_agent_tools(
+        &cwd,
+        tool_names,
+        extension_tools,
+        extension_host,
+        &change_journal,
+        sandbox,
+        &bash_approval,
+        &edit_review,
+        &cancel,
+    )?;
+    apply_tool_descriptions(&mut agent_tools, tool_descriptions);
+    let settings_manager = SettingsManager::create("", "");
+    let seed = resolve_seed(&model, seed, &settings_manager, &mut session_manager);
+
+    let stream_fn = build_session_stream_fn(
+        &model,
+        fallback_models,
+        tool_names,
+        extension_tools,
+        tool_descriptions,
+        api_key_override,
+        seed,
+        request_hook,
+        "print mode",
+    )?;
 
     let system_value = merge_system_prompt(system_prompt, append_system_prompt).unwrap_or_default();
     let agent_model = to_agent_model(&model);
@@ -643,17 +1281,19 @@ pub fn create_cli_session(
             ..Default::default()
         }),
         stream_fn: Some(stream_fn),
+        abort_flag: Some(cancel),
         ..Default::default()
     });
 
-    let settings_manager = SettingsManager::create("", "");
-
     let mut session = AgentSession::new(AgentSessionConfig {
         agent,
         session_manager,
         settings_manager,
         model_registry: registry,
     });
+    session.set_change_journal(change_journal);
+    session.set_bash_approval(bash_approval);
+    session.set_edit_review(edit_review);
     let templates = load_prompt_templates(LoadPromptTemplatesOptions {
         cwd: Some(cwd),
         agent_dir: Some(config::get_agent_dir()),
@@ -665,79 +1305,50 @@ pub fn create_cli_session(
 #[allow(clippy::too_many_arguments)]
 pub fn create_rpc_session(
     model: RegistryModel,
+    fallback_models: &[RegistryModel],
     registry: ModelRegistry,
     system_prompt: Option<String>,
     append_system_prompt: Option<String>,
     tool_names: Option<&[String]>,
     extension_tools: &[ExtensionTool],
+    tool_descriptions: &HashMap<String, String>,
     extension_host: Option<Rc<RefCell<ExtensionHost>>>,
     api_key_override: Option<&str>,
-    session_manager: SessionManager,
+    seed: Option<u64>,
+    mut session_manager: SessionManager,
+    sandbox: &SandboxPolicy,
 ) -> Result<AgentSession, String> {
     let cwd = env::current_dir().map_err(|err| err.to_string())?;
-    let agent_tools = build_agent_tools(&cwd, tool_names, extension_tools, extension_host)?;
-    let tool_defs = build_tool_defs(tool_names, extension_tools)?;
-    let stream_fn = match model.api.as_str() {
-        "anthropic-messages" => {
-            let (api_key, use_oauth) =
-                crate::cli::auth::resolve_anthropic_credentials(api_key_override)?;
-            let tool_specs = tool_defs
-                .iter()
-                .map(|tool| AnthropicTool {
-                    name: tool.name.clone(),
-                    description: tool.description.clone(),
-                    input_schema: tool.input_schema.clone(),
-                })
-                .collect::<Vec<_>>();
-            build_stream_fn(model.clone(), api_key, use_oauth, tool_specs)
-        }
-        "openai-responses" => {
-            let api_key = crate::cli::auth::resolve_openai_credentials(api_key_override)?;
-            let tool_specs = tool_defs
-                .iter()
-                .map(|tool| OpenAITool {
-                    tool_type: "function".to_string(),
-                    name: tool.name.clone(),
-                    description: tool.description.clone(),
-                    parameters: tool.input_schema.clone(),
-                })
-                .collect::<Vec<_>>();
-            build_openai_stream_fn(model.clone(), api_key, tool_specs)
-        }
-        "openai-codex-responses" => {
-            let api_key = crate::cli::auth::resolve_openai_codex_credentials(api_key_override)?;
-            let tool_specs = tool_defs
-                .iter()
-                .map(|tool| CodexTool {
-                    tool_type: "function".to_string(),
-                    name: tool.name.clone(),
-                    description: tool.description.clone(),
-                    parameters: tool.input_schema.clone(),
-                    strict: None,
-                })
-                .collect::<Vec<_>>();
-            build_codex_stream_fn(model.clone(), api_key, tool_specs)
-        }
-        "google-gemini-cli" => {
-            let (access_token, project_id) =
-                crate::cli::auth::resolve_google_gemini_cli_credentials(api_key_override)?;
-            let tool_specs = tool_defs
-                .iter()
-                .map(|tool| GeminiCliTool {
-                    name: tool.name.clone(),
-                    description: tool.description.clone(),
-                    parameters: tool.input_schema.clone(),
-                })
-                .collect::<Vec<_>>();
-            build_gemini_cli_stream_fn(model.clone(), access_token, project_id, tool_specs)
-        }
-        _ => {
-            return Err(format!(
-                "Model API \"{}\" is not supported in RPC mode.",
-                model.api
-            ))
-        }
-    };
+    let request_hook = extension_request_hook(extension_host.as_ref());
+    let change_journal = SharedChangeJournal::default();
+    let bash_approval = BashApproval::default();
+    let edit_review = EditReview::default();
+    let cancel = CancellationToken::new();
+    let mut agent_tools = build_agent_tools(
+        &cwd,
+        tool_names,
+        extension_tools,
+        extension_host,
+        &change_journal,
+        sandbox,
+        &bash_approval,
+        &edit_review,
+        &cancel,
+    )?;
+    apply_tool_descriptions(&mut agent_tools, tool_descriptions);
+    let settings_manager = SettingsManager::create("", "");
+    let seed = resolve_seed(&model, seed, &settings_manager, &mut session_manager);
+    let stream_fn = build_session_stream_fn(
+        &model,
+        fallback_models,
+        tool_names,
+        extension_tools,
+        tool_descriptions,
+        api_key_override,
+        seed,
+        request_hook,
+        "RPC mode",
+    )?;
 
     let system_value = merge_system_prompt(system_prompt, append_system_prompt).unwrap_or_default();
     let agent_model = to_agent_model(&model);
@@ -749,17 +1360,19 @@ pub fn create_rpc_session(
             ..Default::default()
         }),
         stream_fn: Some(stream_fn),
+        abort_flag: Some(cancel),
         ..Default::default()
     });
 
-    let settings_manager = SettingsManager::create("", "");
-
     let mut session = AgentSession::new(AgentSessionConfig {
         agent,
         session_manager,
         settings_manager,
         model_registry: registry,
     });
+    session.set_change_journal(change_journal);
+    session.set_bash_approval(bash_approval);
+    session.set_edit_review(edit_review);
     let templates = load_prompt_templates(LoadPromptTemplatesOptions {
         cwd: Some(cwd),
         agent_dir: Some(config::get_agent_dir()),
@@ -784,3 +1397,265 @@ pub fn apply_cli_thinking_level(parsed: &crate::Args, session: &mut AgentSession
         session.set_thinking_level(cli_thinking_level(level));
     }
 }
+
+pub fn apply_cli_compaction_threshold(parsed: &crate::Args, session: &mut AgentSession) {
+    if let Some(threshold) = parsed.auto_compact_threshold {
+        session.settings_manager.apply_overrides(SettingsOverrides {
+            compaction: Some(CompactionOverrides {
+                enabled: None,
+                reserve_tokens: None,
+                keep_recent_tokens: None,
+                threshold: Some(threshold),
+            }),
+        });
+    }
+}
+
+/// Re-enable the context packs recorded in a resumed session.
+pub fn restore_session_context_packs(session: &mut AgentSession) {
+    let packs = load_context_packs(LoadContextPacksOptions::default());
+    for name in session.restore_context_packs(&packs) {
+        eprintln!("Warning: Context pack \"{name}\" from this session was not found");
+    }
+}
+
+pub fn attach_output_filters(session: &mut AgentSession) -> Result<(), String> {
+    let settings = session.settings_manager.get_output_filter_settings();
+    let mut chain = build_output_filter_chain(&settings, session.extension_host())?;
+    if let Some(spec) = settings.moderation_model.as_deref() {
+        let (provider, model_id) = spec.split_once('/').ok_or_else(|| {
+            format!("Invalid outputFilters.moderationModel \"{spec}\" (expected provider/model)")
+        })?;
+        let model = session
+            .model_registry
+            .find(provider, model_id)
+            .ok_or_else(|| format!("Unknown moderation model \"{spec}\""))?;
+        let stream_fn = build_model_stream_fn(
+            &model,
+            &[],
+            None,
+            None,
+            extension_request_hook(session.extension_host().as_ref()),
+            "output moderation",
+        )?;
+        chain.push(Box::new(ModerationModelFilter::new(
+            to_agent_model(&model),
+            stream_fn,
+        )));
+    }
+    session.set_output_filters(chain);
+    Ok(())
+}
+
+/// Turns on the provider request log when `logRequests` is set or `--trace-llm` is given; the
+/// trace file gets full bodies on top of the redacted summaries.
+pub fn install_request_logger(parsed: &crate::Args, settings: &SettingsManager) {
+    let trace_path = parsed.trace_llm.as_ref().map(PathBuf::from);
+    if trace_path.is_none() && !settings.get_log_requests() {
+        return;
+    }
+    let logger = RequestLogger {
+        log_path: Some(config::get_request_log_path()),
+        trace_path,
+    };
+    if let Err(err) = logger.install() {
+        eprintln!("Warning: {err}");
+    }
+}
+
+/// Tracks spend in the agent dir, with `--max-cost` taking precedence over `budget.maxCost`.
+pub fn attach_spend_tracking(
+    parsed: &crate::Args,
+    session: &mut AgentSession,
+) -> Result<(), String> {
+    let mut limits = session.settings_manager.get_spend_limits();
+    limits.max_cost = parsed.max_cost.or(limits.max_cost);
+    let tracker = SpendTracker::load(config::get_spend_path()).map_err(|err| err.to_string())?;
+    session.set_spend_tracking(tracker, limits);
+    Ok(())
+}
+
+/// The files to preload from `--context-dir` (or `contextDir.path`), and where they go.
+/// `None` when no context directory is configured.
+pub fn load_context_dir(
+    parsed: &crate::Args,
+    settings: &SettingsManager,
+    cwd: &Path,
+) -> Result<Option<(ContextDirResult, ContextPlacement)>, String> {
+    let config = settings.get_context_dir_settings();
+    let Some(dir) = parsed.context_dir.clone().or(config.path) else {
+        return Ok(None);
+    };
+    let placement = match config.placement.as_deref() {
+        Some(value) => ContextPlacement::parse(value)
+            .ok_or_else(|| format!("Unknown contextDir.placement: {value}"))?,
+        None => ContextPlacement::default(),
+    };
+    let options = ContextDirOptions {
+        dir: cwd.join(dir),
+        include: parsed
+            .context_globs
+            .clone()
+            .or(config.include)
+            .unwrap_or_default(),
+        exclude: config.exclude.unwrap_or_default(),
+        max_bytes: config.max_bytes.unwrap_or(DEFAULT_CONTEXT_MAX_BYTES),
+    };
+    Ok(Some((collect_context_dir(&options)?, placement)))
+}
+
+/// The AGENTS.md, CLAUDE.md and .pi/CONTEXT.md files `build_system_prompt` included, unless
+/// `--no-project-context` left them out.
+pub fn attach_project_context(parsed: &crate::Args, session: &mut AgentSession, cwd: &Path) {
+    if parsed.no_project_context {
+        return;
+    }
+    let files = load_project_context_files(LoadContextFilesOptions {
+        cwd: Some(cwd.to_path_buf()),
+        agent_dir: Some(config::get_agent_dir()),
+    });
+    session.set_context_files(files.into_iter().map(|file| file.path).collect());
+}
+
+/// The skills `/skill` and `run_skill` can run: the same ones the system prompt lists.
+pub fn attach_skills(parsed: &crate::Args, session: &mut AgentSession, cwd: &Path) {
+    if parsed.no_skills {
+        return;
+    }
+    let mut options = LoadSkillsOptions::new();
+    options.cwd = Some(cwd.to_path_buf());
+    options.agent_dir = Some(config::get_agent_dir());
+    options.include_skills = parsed.skills.clone().unwrap_or_default();
+    session.set_skills(load_skills(options).skills);
+}
+
+pub fn attach_telemetry(session: &mut AgentSession) -> Result<(), String> {
+    let settings = session.settings_manager.get_telemetry_settings();
+    if let Some(sink) = create_telemetry_sink(&settings)? {
+        session.set_telemetry_sink(sink);
+    }
+    Ok(())
+}
+
+/// The setup every session gets after it is created, from the command-line options.
+pub fn prepare_session(
+    session: &mut AgentSession,
+    parsed: &crate::Args,
+    persona: Option<&Persona>,
+    cwd: &Path,
+    preloaded: Option<PreloadedExtensions>,
+) -> Result<(), String> {
+    if let Some(paths) = parsed.extensions.as_deref() {
+        session.settings_manager.set_extension_paths(paths.to_vec());
+    }
+    if let Some(persona) = persona {
+        session
+            .apply_persona(persona)
+            .map_err(|err| err.to_string())?;
+    }
+    apply_cli_thinking_level(parsed, session);
+    apply_cli_compaction_threshold(parsed, session);
+    restore_session_context_packs(session);
+    attach_extensions_with_host(session, cwd, preloaded);
+    attach_output_filters(session)?;
+    attach_spend_tracking(parsed, session)?;
+    session.set_loop_limits(LoopLimits {
+        max_turns: parsed.max_turns,
+        max_duration: parsed.max_duration.map(Duration::from_secs),
+    });
+    if let Some(dir) = &parsed.record_fixtures {
+        let dir = cwd.join(dir);
+        session
+            .agent
+            .wrap_stream_fn(|inner| recording_stream_fn(inner, dir));
+    }
+    attach_skills(parsed, session, cwd);
+    attach_project_context(parsed, session, cwd);
+    attach_telemetry(session)
+}
+
+/// Builds the sessions RPC clients open with `create_session` the way pi built its first
+/// one, except that each starts a new session file and runs its own extension host.
+pub fn rpc_session_factory(
+    parsed: &crate::Args,
+    cwd: &Path,
+    system_prompt: String,
+    selected_tools: Vec<String>,
+    sandbox: SandboxPolicy,
+    persona: Option<Persona>,
+) -> SessionFactory {
+    let mut parsed = parsed.clone();
+    parsed.session = None;
+    parsed.continue_session = false;
+    parsed.resume = false;
+    let cwd = cwd.to_path_buf();
+    Arc::new(move |spec: &SessionSpec| {
+        let mut parsed = parsed.clone();
+        if spec.provider.is_some() || spec.model.is_some() {
+            parsed.models = None;
+        }
+        if let Some(provider) = &spec.provider {
+            parsed.provider = Some(provider.clone());
+        }
+        if let Some(model) = &spec.model {
+            match model.split_once('/') {
+                Some((provider, id)) => {
+                    parsed.provider = Some(provider.to_string());
+                    parsed.model = Some(id.to_string());
+                }
+                None => parsed.model = Some(model.clone()),
+            }
+        }
+        let registry = build_model_registry(
+            parsed.api_key.as_deref(),
+            parsed.provider.as_deref(),
+            parsed.auth_profile.as_deref(),
+        )?;
+        if let (None, Some(id)) = (&parsed.provider, &parsed.model) {
+            let model = registry
+                .get_available()
+                .into_iter()
+                .find(|model| &model.id == id)
+                .ok_or_else(|| format!("Model {id} not found"))?;
+            parsed.provider = Some(model.provider);
+        }
+        let settings_manager = SettingsManager::create(cwd.to_string_lossy(), "");
+        let model = select_model(&parsed, &registry, &settings_manager)?;
+        if !RPC_MODEL_APIS.contains(&model.api.as_str()) {
+            return Err(format!(
+                "RPC mode supports only {} models, not {}",
+                RPC_MODEL_APIS.join(", "),
+                model.api
+            ));
+        }
+        let fallback_models = select_fallback_models(&parsed, &registry, &model)
+            .into_iter()
+            .filter(|fallback| SUPPORTED_APIS.contains(&fallback.api.as_str()))
+            .collect::<Vec<_>>();
+        let (preloaded, _) = preload_extensions(&parsed, &cwd);
+        let extension_tools = preloaded
+            .as_ref()
+            .map(|preloaded| collect_extension_tools(&preloaded.manifest))
+            .unwrap_or_default();
+        let extension_host = preloaded.as_ref().map(|preloaded| preloaded.host.clone());
+        let prompt_overrides = resolve_prompt_overrides(preloaded.as_ref(), &settings_manager);
+        let session_manager = build_session_manager(&parsed, &cwd)?;
+        let mut session = create_rpc_session(
+            model,
+            &fallback_models,
+            registry,
+            Some(system_prompt.clone()),
+            None,
+            Some(selected_tools.as_slice()),
+            &extension_tools,
+            &prompt_overrides.tool_descriptions,
+            extension_host,
+            parsed.api_key.as_deref(),
+            parsed.seed,
+            session_manager,
+            &sandbox,
+        )?;
+        prepare_session(&mut session, &parsed, persona.as_ref(), &cwd, preloaded)?;
+        Ok(session)
+    })
+}
@@ -0,0 +1,66 @@
+use crate::cli::args::SessionsCommand;
+use crate::core::session_manager::{SessionFilter, SessionInfo, SessionManager};
+use std::path::{Path, PathBuf};
+
+pub fn list_sessions(command: &SessionsCommand, cwd: &Path, session_dir: Option<&str>) {
+    let session_dir = session_dir.map(PathBuf::from);
+    let (sessions, query) = match command {
+        SessionsCommand::List => (SessionManager::list(cwd, session_dir), None),
+        SessionsCommand::Search(query) => {
+            let filter = SessionFilter {
+                query: Some(query.clone()),
+                tags: Vec::new(),
+            };
+            (
+                SessionManager::search(cwd, session_dir, &filter),
+                Some(query.as_str()),
+            )
+        }
+    };
+
+    if sessions.is_empty() {
+        match query {
+            Some(query) => println!("No sessions matching \"{query}\""),
+            None => println!("No sessions found"),
+        }
+        return;
+    }
+
+    for session in &sessions {
+        print_session(session);
+    }
+}
+
+fn print_session(session: &SessionInfo) {
+    let title = session
+        .name
+        .clone()
+        .unwrap_or_else(|| truncate_preview(&session.preview, 80));
+    println!("{title}");
+    let mut metadata = format!(
+        "  {} · {} message{}",
+        format_modified_time(session.modified),
+        session.message_count,
+        if session.message_count == 1 { "" } else { "s" }
+    );
+    if !session.tags.is_empty() {
+        metadata.push_str(&format!(" · #{}", session.tags.join(" #")));
+    }
+    println!("{metadata}");
+    println!("  {}", session.path.display());
+}
+
+fn format_modified_time(time: std::time::SystemTime) -> String {
+    let datetime: chrono::DateTime<chrono::Local> = time.into();
+    datetime.format("%Y-%m-%d %H:%M").to_string()
+}
+
+fn truncate_preview(text: &str, max_len: usize) -> String {
+    let text = text.replace('\n', " ");
+    if text.chars().count() <= max_len {
+        return text;
+    }
+    let mut truncated = text.chars().take(max_len).collect::<String>();
+    truncated.push_str("...");
+    truncated
+}
@@ -0,0 +1,254 @@
+//! `pi stats [--json]`: token, cost and tool usage aggregated over every saved session.
+
+use crate::core::messages::{parse_timestamp_millis, AgentMessage, ContentBlock, Usage};
+use crate::core::session_manager::{load_entries_from_file, FileEntry};
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How many projects the text report lists.
+const BUSIEST_PROJECTS: usize = 10;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageTotals {
+    /// Assistant responses.
+    pub responses: usize,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_read_tokens: i64,
+    pub cache_write_tokens: i64,
+    pub cost: f64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, usage: &Usage) {
+        self.responses += 1;
+        self.input_tokens += usage.input;
+        self.output_tokens += usage.output;
+        self.cache_read_tokens += usage.cache_read;
+        self.cache_write_tokens += usage.cache_write;
+        self.cost += usage.cost.as_ref().map(|cost| cost.total).unwrap_or(0.0);
+    }
+
+    pub fn total_tokens(&self) -> i64 {
+        self.input_tokens + self.output_tokens + self.cache_read_tokens + self.cache_write_tokens
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectStats {
+    pub cwd: String,
+    pub sessions: usize,
+    /// User prompts.
+    pub turns: usize,
+    pub usage: UsageTotals,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageStats {
+    pub sessions: usize,
+    pub turns: usize,
+    pub total: UsageTotals,
+    /// Keyed by `provider/model`.
+    pub by_model: BTreeMap<String, UsageTotals>,
+    pub by_provider: BTreeMap<String, UsageTotals>,
+    /// Keyed by local date, `YYYY-MM-DD`.
+    pub by_day: BTreeMap<String, UsageTotals>,
+    pub tool_calls: BTreeMap<String, usize>,
+    pub tool_errors: usize,
+    /// Mean time from a prompt to the last response it got, in milliseconds.
+    pub average_turn_latency_ms: Option<u64>,
+    /// Most active first.
+    pub projects: Vec<ProjectStats>,
+}
+
+/// Aggregates every session file under `sessions_dir` (one directory per project, or a flat
+/// `--session-dir`). Entries copied into branched sessions are only counted once.
+pub fn collect_stats(sessions_dir: &Path) -> UsageStats {
+    let mut stats = UsageStats::default();
+    let mut projects: BTreeMap<String, ProjectStats> = BTreeMap::new();
+    let mut seen = HashSet::new();
+    let mut latency_total = 0i64;
+    let mut latency_turns = 0i64;
+
+    for path in session_files(sessions_dir) {
+        let entries = load_entries_from_file(&path);
+        let Some(FileEntry::Session(header)) = entries.first() else {
+            continue;
+        };
+        stats.sessions += 1;
+        let project = projects
+            .entry(header.cwd.clone())
+            .or_insert_with(|| ProjectStats {
+                cwd: header.cwd.clone(),
+                ..ProjectStats::default()
+            });
+        project.sessions += 1;
+
+        // (prompt time, last response time) for the turn in progress.
+        let mut turn: Option<(i64, Option<i64>)> = None;
+        for entry in &entries {
+            let FileEntry::Message(entry) = entry else {
+                continue;
+            };
+            if !seen.insert((entry.id.clone(), entry.timestamp.clone())) {
+                continue;
+            }
+            let timestamp = parse_timestamp_millis(&entry.timestamp);
+            match &entry.message {
+                AgentMessage::User(_) => {
+                    stats.turns += 1;
+                    project.turns += 1;
+                    if let Some((prompt, Some(response))) = turn {
+                        latency_total += (response - prompt).max(0);
+                        latency_turns += 1;
+                    }
+                    turn = Some((timestamp, None));
+                }
+                AgentMessage::Assistant(message) => {
+                    let usage = &message.usage;
+                    stats.total.add(usage);
+                    project.usage.add(usage);
+                    let model = format!("{}/{}", message.provider, message.model);
+                    stats.by_model.entry(model).or_default().add(usage);
+                    let provider = message.provider.clone();
+                    stats.by_provider.entry(provider).or_default().add(usage);
+                    stats
+                        .by_day
+                        .entry(local_day(&entry.timestamp))
+                        .or_default()
+                        .add(usage);
+                    for block in &message.content {
+                        if let ContentBlock::ToolCall { name, .. } = block {
+                            *stats.tool_calls.entry(name.clone()).or_default() += 1;
+                        }
+                    }
+                    if let Some((_, response)) = turn.as_mut() {
+                        *response = Some(timestamp);
+                    }
+                }
+                AgentMessage::ToolResult(result) if result.is_error => stats.tool_errors += 1,
+                _ => {}
+            }
+        }
+        if let Some((prompt, Some(response))) = turn {
+            latency_total += (response - prompt).max(0);
+            latency_turns += 1;
+        }
+    }
+
+    if latency_turns > 0 {
+        stats.average_turn_latency_ms = Some((latency_total / latency_turns) as u64);
+    }
+    stats.projects = projects.into_values().collect();
+    stats.projects.sort_by(|a, b| {
+        b.turns
+            .cmp(&a.turns)
+            .then(b.usage.cost.total_cmp(&a.usage.cost))
+    });
+    stats
+}
+
+/// `*.jsonl` directly in `dir` and one level down.
+fn session_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return files;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if let Ok(children) = fs::read_dir(&path) {
+                files.extend(
+                    children
+                        .flatten()
+                        .map(|child| child.path())
+                        .filter(|path| is_jsonl(path)),
+                );
+            }
+        } else if is_jsonl(&path) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    files
+}
+
+fn is_jsonl(path: &Path) -> bool {
+    path.extension().and_then(|ext| ext.to_str()) == Some("jsonl")
+}
+
+fn local_day(timestamp: &str) -> String {
+    DateTime::parse_from_rfc3339(timestamp)
+        .map(|time| time.with_timezone(&Local).format("%Y-%m-%d").to_string())
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+pub fn print_stats(sessions_dir: &Path, json: bool) -> Result<(), String> {
+    let stats = collect_stats(sessions_dir);
+    if json {
+        let output = serde_json::to_string_pretty(&stats).map_err(|err| err.to_string())?;
+        println!("{output}");
+        return Ok(());
+    }
+    if stats.sessions == 0 {
+        println!("No sessions found in {}", sessions_dir.display());
+        return Ok(());
+    }
+
+    println!(
+        "{} sessions · {} prompts · {} responses",
+        stats.sessions, stats.turns, stats.total.responses
+    );
+    println!(
+        "Tokens: {} ({} in, {} out, {} cache read, {} cache write)",
+        stats.total.total_tokens(),
+        stats.total.input_tokens,
+        stats.total.output_tokens,
+        stats.total.cache_read_tokens,
+        stats.total.cache_write_tokens
+    );
+    println!("Cost: ${:.4}", stats.total.cost);
+    if let Some(latency) = stats.average_turn_latency_ms {
+        println!("Average turn: {:.1}s", latency as f64 / 1000.0);
+    }
+
+    print_usage_table("Models", &stats.by_model);
+    print_usage_table("Providers", &stats.by_provider);
+    print_usage_table("Days", &stats.by_day);
+
+    if !stats.tool_calls.is_empty() {
+        let mut tools = stats.tool_calls.iter().collect::<Vec<_>>();
+        tools.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        println!("\nTool calls ({} failed)", stats.tool_errors);
+        for (name, count) in tools {
+            println!("  {name:<24} {count:>8}");
+        }
+    }
+
+    println!("\nBusiest projects");
+    for project in stats.projects.iter().take(BUSIEST_PROJECTS) {
+        println!(
+            "  {:<48} {:>4} sessions {:>6} prompts  ${:.4}",
+            project.cwd, project.sessions, project.turns, project.usage.cost
+        );
+    }
+    Ok(())
+}
+
+fn print_usage_table(title: &str, rows: &BTreeMap<String, UsageTotals>) {
+    println!("\n{title}");
+    for (key, usage) in rows {
+        println!(
+            "  {key:<40} {:>6} responses {:>12} tokens  ${:.4}",
+            usage.responses,
+            usage.total_tokens(),
+            usage.cost
+        );
+    }
+}
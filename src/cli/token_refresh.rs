@@ -0,0 +1,106 @@
+//! Keeps OAuth tokens fresh for the lifetime of a session: the stored credential is checked
+//! before every request and refreshed (and saved) when it is about to expire, and a request
+//! rejected for bad credentials is retried once with a freshly refreshed token.
+
+use crate::cli::auth::{now_millis, oauth_needs_refresh, refresh_oauth_credential};
+use crate::coding_agent::{AuthCredential, AuthStorage};
+use crate::error::Error;
+use std::path::PathBuf;
+
+pub type RefreshFn = fn(&str, &AuthCredential) -> Result<AuthCredential, String>;
+
+#[derive(Clone, Debug)]
+pub struct TokenRefresher {
+    token: String,
+    /// Provider key and auth.json path when the token came from a stored OAuth credential.
+    stored: Option<(String, PathBuf)>,
+    refresh: RefreshFn,
+}
+
+impl TokenRefresher {
+    /// A token that is used as-is (API keys, `--api-key`, environment variables).
+    pub fn fixed(token: impl Into<String>) -> Self {
+        Self {
+            token: token.into(),
+            stored: None,
+            refresh: refresh_oauth_credential,
+        }
+    }
+
+    /// Refreshes `token` through `provider`'s entry in `auth_path` when that entry is the OAuth
+    /// credential the token was resolved from; otherwise behaves like [`TokenRefresher::fixed`].
+    pub fn for_provider(provider: &str, auth_path: impl Into<PathBuf>, token: &str) -> Self {
+        let auth_path = auth_path.into();
+        let stored = match AuthStorage::new(&auth_path).get(provider) {
+            Some(AuthCredential::OAuth { access, .. }) if access == token => {
+                Some((provider.to_string(), auth_path))
+            }
+            _ => None,
+        };
+        Self {
+            token: token.to_string(),
+            stored,
+            refresh: refresh_oauth_credential,
+        }
+    }
+
+    pub fn with_refresh_fn(mut self, refresh: RefreshFn) -> Self {
+        self.refresh = refresh;
+        self
+    }
+
+    pub fn is_refreshable(&self) -> bool {
+        self.stored.is_some()
+    }
+
+    /// The token to send, picking up tokens saved by other processes and refreshing one
+    /// that is about to expire.
+    pub fn token(&mut self) -> &str {
+        let _ = self.update(false);
+        &self.token
+    }
+
+    /// Refreshes regardless of expiry; `Err` when the token cannot be refreshed.
+    pub fn force_refresh(&mut self) -> Result<&str, String> {
+        self.update(true)?;
+        Ok(&self.token)
+    }
+
+    /// Runs `call` with the current token, retrying once with a refreshed token when the
+    /// provider rejects the credentials.
+    pub fn call_with_retry<T>(
+        &mut self,
+        mut call: impl FnMut(&str) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let result = call(self.token());
+        match result {
+            Err(err @ Error::Auth { .. }) if self.is_refreshable() => match self.force_refresh() {
+                Ok(token) => call(token),
+                Err(_) => Err(err),
+            },
+            result => result,
+        }
+    }
+
+    fn update(&mut self, force: bool) -> Result<(), String> {
+        let Some((provider, auth_path)) = &self.stored else {
+            return Err("Credential is not a stored OAuth token".to_string());
+        };
+        let mut storage = AuthStorage::new(auth_path);
+        let Some(credential @ AuthCredential::OAuth { access, .. }) = storage.get(provider) else {
+            return Err(format!("No stored OAuth credential for {provider}"));
+        };
+        // Another process (or an earlier request) may already have refreshed it.
+        let rejected = force && *access == self.token;
+        if !rejected && !oauth_needs_refresh(credential, now_millis()) {
+            self.token = access.clone();
+            return Ok(());
+        }
+        let refreshed = (self.refresh)(provider, credential)?;
+        if let AuthCredential::OAuth { access, .. } = &refreshed {
+            self.token = access.clone();
+        }
+        storage.set(provider, refreshed);
+        Ok(())
+    }
+}
@@ -0,0 +1,42 @@
+use crate::agent::CancellationToken;
+use crate::cli::args::ToolRunCommand;
+use crate::cli::runtime::build_sandbox_policy;
+use crate::cli::session::build_agent_tools;
+use crate::coding_agent::{BashApproval, EditReview, SharedChangeJournal};
+use crate::error::Error;
+use serde_json::{json, Value};
+use std::path::Path;
+
+const TOOL_RUN_CALL_ID: &str = "pi-tool-run";
+
+/// Run a built-in tool against `cwd` and return its result as JSON (`content`, `details`).
+pub fn run_tool_command(command: &ToolRunCommand, cwd: &Path) -> Result<Value, Error> {
+    if command.tool.is_empty() {
+        return Err(Error::Tool(
+            "Usage: pi tool run <tool> [--arg value ...]".to_string(),
+        ));
+    }
+    let tools = build_agent_tools(
+        &cwd.to_path_buf(),
+        Some(std::slice::from_ref(&command.tool)),
+        &[],
+        None,
+        &SharedChangeJournal::default(),
+        &build_sandbox_policy(command.allow_all, cwd),
+        &BashApproval::default(),
+        &EditReview::default(),
+        &CancellationToken::default(),
+    )
+    .map_err(Error::Tool)?;
+    let tool = tools
+        .first()
+        .ok_or_else(|| Error::Tool(format!("Tool \"{}\" is not supported", command.tool)))?;
+    tool.check_arguments(&command.params).map_err(Error::Tool)?;
+    let result = (tool.execute)(TOOL_RUN_CALL_ID, &command.params, &mut |_| {})
+        .map_err(|err| Error::Tool(err.message))?;
+    Ok(json!({
+        "tool": command.tool,
+        "content": result.content,
+        "details": result.details,
+    }))
+}
@@ -1,31 +1,57 @@
 use crate::agent::{
     Agent, AgentError, AgentEvent, AgentMessage, AgentTool, AgentToolResult, CustomMessage,
-    ThinkingLevel,
+    EventBus, LoopLimits, ThinkingLevel,
+};
+use crate::coding_agent::bash_policy::{BashApproval, BashPolicy, BashPolicyAction};
+use crate::coding_agent::change_journal::{FileChange, SharedChangeJournal};
+use crate::coding_agent::context_packs::{format_context_packs_for_prompt, ContextPack};
+use crate::coding_agent::edit_review::EditReview;
+use crate::coding_agent::export_html::{
+    export_session_to_html, CustomMessageDisplay, ExportOptions,
 };
-use crate::coding_agent::export_html::export_session_to_html;
 use crate::coding_agent::extension_host::{
     ExtensionCommand, ExtensionHost, ExtensionUiRequest, ExtensionUiResponse,
 };
+use crate::coding_agent::git::checkpoint_commit;
 use crate::coding_agent::hooks::{
     CompactionHook, CompactionResult, SessionBeforeCompactEvent, SessionCompactEvent,
 };
-use crate::coding_agent::prompt_templates::{expand_prompt_template, PromptTemplate};
+use crate::coding_agent::mirror::SessionMirror;
+use crate::coding_agent::output_filter::OutputFilterChain;
+use crate::coding_agent::personas::Persona;
+use crate::coding_agent::prompt_templates::{
+    expand_prompt_template, render_prompt_template, try_expand_prompt_template, PromptTemplate,
+};
+use crate::coding_agent::shell::Shell;
+use crate::coding_agent::skills::{expand_skill, Skill};
+use crate::coding_agent::slash_commands::parse_command_args;
+use crate::coding_agent::spend::{spend_day, SpendLimits, SpendSnapshot, SpendTracker};
+use crate::coding_agent::system_prompt::PromptOverrides;
+use crate::coding_agent::telemetry::{TelemetryRecorder, TelemetrySink};
 use crate::coding_agent::ModelRegistry;
 use crate::config;
-use crate::core::compaction::prepare_compaction;
+use crate::core::compaction::{
+    clip_words, collect_file_state, compaction_strategy_for_name, compaction_trigger_tokens,
+    format_file_state, prepare_compaction, CompactionRequest, CompactionStrategy,
+    SummarizeStrategy, COMPACTION_STRATEGY_NAMES, SUMMARIZE_STRATEGY,
+};
 use crate::core::messages::{
     AgentMessage as CoreAgentMessage, BashExecutionMessage, ContentBlock, UserContent, UserMessage,
 };
-use crate::core::session_manager::{BranchSummaryEntry, SessionEntry, SessionManager};
+use crate::core::session_manager::{
+    check_session_version, BranchSummaryEntry, Checkpoint, SessionEntry, SessionManager,
+};
+use crate::core::tokens::{self, Tokenizer};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
-use std::process::Command;
 use std::rc::Rc;
+use std::sync::mpsc;
 
 pub struct AgentSessionConfig {
     pub agent: Agent,
@@ -49,14 +75,40 @@ pub struct AgentSession {
     pub settings_manager: SettingsManager,
     pub model_registry: ModelRegistry,
     prompt_templates: Vec<PromptTemplate>,
+    skills: Vec<Skill>,
+    context_files: Vec<String>,
     extension_commands: Vec<ExtensionCommand>,
     branch_summary_aborted: Cell<bool>,
     compaction_hooks: Vec<CompactionHook>,
+    compaction_strategy: Option<Box<dyn CompactionStrategy>>,
     extension_host: Option<Rc<RefCell<ExtensionHost>>>,
     tools_wrapped_with_extensions: bool,
-    listeners: Rc<RefCell<Vec<(usize, AgentSessionEventListener)>>>,
-    next_listener_id: Rc<RefCell<usize>>,
+    events: EventBus<AgentSessionEvent>,
     unsubscribe_agent: Option<Box<dyn FnOnce()>>,
+    telemetry_sink: Option<Rc<dyn TelemetrySink>>,
+    unsubscribe_telemetry: Option<Box<dyn FnOnce()>>,
+    persona: Option<String>,
+    persona_prompt: Option<String>,
+    context_packs: Vec<ContextPack>,
+    change_journal: SharedChangeJournal,
+    mirror: Option<SessionMirror>,
+    bash_approval: BashApproval,
+    edit_review: EditReview,
+    git_checkpoints: Rc<RefCell<Vec<String>>>,
+    unsubscribe_git_checkpoints: Option<Box<dyn FnOnce()>>,
+    base_system_prompt: Option<String>,
+    base_tools: Option<Vec<crate::agent::AgentTool>>,
+    spend: Option<SpendTracker>,
+    spend_limits: SpendLimits,
+    /// Session and daily totals when the running prompt started.
+    spend_baseline: Rc<Cell<(f64, f64)>>,
+    /// Cost of the running prompt, recorded in `spend` once it ends.
+    prompt_cost: Rc<Cell<f64>>,
+    /// Set when a budget cut the running prompt short.
+    budget_exceeded: Rc<RefCell<Option<String>>>,
+    unsubscribe_spend: Option<Box<dyn FnOnce()>>,
+    /// Runs stopped by `--max-turns` or `--max-duration`.
+    limits_reached: Rc<Cell<usize>>,
 }
 
 const THINKING_LEVELS: [ThinkingLevel; 5] = [
@@ -78,13 +130,16 @@ const THINKING_LEVELS_WITH_XHIGH: [ThinkingLevel; 6] = [
 
 impl AgentSession {
     pub fn new(config: AgentSessionConfig) -> Self {
-        let listeners = Rc::new(RefCell::new(
-            Vec::<(usize, AgentSessionEventListener)>::new(),
-        ));
-        let next_listener_id = Rc::new(RefCell::new(0));
-        let agent = config.agent;
+        let events = EventBus::new();
+        let mut agent = config.agent;
         let session_manager = config.session_manager;
         let settings_manager = config.settings_manager;
+        if let Some(priority) =
+            crate::agent::QueuePriority::parse(&settings_manager.get_queue_priority())
+        {
+            agent.set_queue_priority(priority);
+        }
+        agent.set_tool_update_throttle(Some(settings_manager.get_tool_update_throttle()));
         let model_registry = config.model_registry;
 
         let context = session_manager.build_session_context();
@@ -110,47 +165,105 @@ impl AgentSession {
             agent.set_thinking_level(level);
         }
 
-        let listeners_ref = listeners.clone();
+        let session_events = events.clone();
+        let limits_reached = Rc::new(Cell::new(0));
+        let limits_reached_ref = limits_reached.clone();
         let unsubscribe = agent.subscribe(move |event| {
-            let session_event = AgentSessionEvent::Agent(Box::new(event.clone()));
-            for (_, listener) in listeners_ref.borrow().iter() {
-                listener(&session_event);
+            if matches!(event, AgentEvent::LimitReached { .. }) {
+                limits_reached_ref.set(limits_reached_ref.get() + 1);
             }
+            session_events.emit(&AgentSessionEvent::Agent(Box::new(event.clone())));
         });
 
-        Self {
+        let git_checkpoints_enabled = settings_manager.get_git_checkpoints();
+        let thinking_recorded = session_manager
+            .get_entries()
+            .iter()
+            .any(|entry| matches!(entry, SessionEntry::ThinkingLevelChange(_)));
+        let default_thinking = settings_manager
+            .get_default_thinking_level()
+            .and_then(|level| thinking_level_from_str(&level))
+            .filter(|_| !thinking_recorded);
+        let mut session = Self {
             agent,
             session_manager,
             settings_manager,
             model_registry,
             prompt_templates: Vec::new(),
+            skills: Vec::new(),
+            context_files: Vec::new(),
             extension_commands: Vec::new(),
             branch_summary_aborted: Cell::new(false),
             compaction_hooks: Vec::new(),
+            compaction_strategy: None,
             extension_host: None,
             tools_wrapped_with_extensions: false,
-            listeners,
-            next_listener_id,
+            events,
             unsubscribe_agent: Some(Box::new(unsubscribe)),
+            telemetry_sink: None,
+            unsubscribe_telemetry: None,
+            persona: None,
+            persona_prompt: None,
+            context_packs: Vec::new(),
+            change_journal: SharedChangeJournal::default(),
+            mirror: None,
+            bash_approval: BashApproval::default(),
+            edit_review: EditReview::default(),
+            git_checkpoints: Rc::new(RefCell::new(Vec::new())),
+            unsubscribe_git_checkpoints: None,
+            base_system_prompt: None,
+            base_tools: None,
+            spend: None,
+            spend_limits: SpendLimits::default(),
+            spend_baseline: Rc::new(Cell::new((0.0, 0.0))),
+            prompt_cost: Rc::new(Cell::new(0.0)),
+            budget_exceeded: Rc::new(RefCell::new(None)),
+            unsubscribe_spend: None,
+            limits_reached,
+        };
+        session.set_git_checkpoints(git_checkpoints_enabled);
+        if let Some(level) = default_thinking {
+            let available = session.available_thinking_levels();
+            let effective = if available.contains(&level) {
+                level
+            } else {
+                clamp_thinking_level(level, &available)
+            };
+            session.agent.set_thinking_level(effective);
         }
+        session
     }
 
+    /// Calls `listener` on this thread for every event until the returned closure is called.
     pub fn subscribe<F>(&self, listener: F) -> impl FnOnce()
     where
         F: Fn(&AgentSessionEvent) + 'static,
     {
-        let id = {
-            let mut next_id = self.next_listener_id.borrow_mut();
-            let id = *next_id;
-            *next_id += 1;
-            id
-        };
-        self.listeners.borrow_mut().push((id, Box::new(listener)));
-        let listeners = self.listeners.clone();
-        move || {
-            listeners
-                .borrow_mut()
-                .retain(|(listener_id, _)| *listener_id != id);
+        self.events.subscribe(listener)
+    }
+
+    /// Every event from now on, for a consumer on another thread (a GUI, a server). Events
+    /// are `Send`; dropping the receiver unsubscribes.
+    pub fn subscribe_channel(&self) -> mpsc::Receiver<AgentSessionEvent> {
+        self.events.channel()
+    }
+
+    /// Report LLM requests, tool executions and errors from this session to `sink`,
+    /// replacing any previously attached sink.
+    pub fn set_telemetry_sink(&mut self, sink: Rc<dyn TelemetrySink>) {
+        self.clear_telemetry_sink();
+        let recorder = TelemetryRecorder::new(sink.clone());
+        let unsubscribe = self.subscribe(move |event| recorder.handle_event(event));
+        self.telemetry_sink = Some(sink);
+        self.unsubscribe_telemetry = Some(Box::new(unsubscribe));
+    }
+
+    pub fn clear_telemetry_sink(&mut self) {
+        if let Some(unsubscribe) = self.unsubscribe_telemetry.take() {
+            unsubscribe();
+        }
+        if let Some(sink) = self.telemetry_sink.take() {
+            sink.flush();
         }
     }
 
@@ -158,7 +271,12 @@ impl AgentSession {
         if let Some(unsubscribe) = self.unsubscribe_agent.take() {
             unsubscribe();
         }
-        self.listeners.borrow_mut().clear();
+        self.clear_telemetry_sink();
+        self.set_git_checkpoints(false);
+        if let Some(unsubscribe) = self.unsubscribe_spend.take() {
+            unsubscribe();
+        }
+        self.events.clear();
     }
 
     pub fn is_streaming(&self) -> bool {
@@ -177,6 +295,21 @@ impl AgentSession {
         self.agent.state().messages
     }
 
+    /// The current branch's messages as stored in the session, so custom messages keep their
+    /// type, content and details. Those not meant for display are left out unless
+    /// `include_hidden` is set.
+    pub fn session_messages(&self, include_hidden: bool) -> Vec<CoreAgentMessage> {
+        self.session_manager
+            .build_session_context()
+            .messages
+            .into_iter()
+            .filter(|message| {
+                include_hidden
+                    || !matches!(message, CoreAgentMessage::HookMessage(hook) if !hook.display)
+            })
+            .collect()
+    }
+
     pub fn set_prompt_templates(&mut self, templates: Vec<PromptTemplate>) {
         self.prompt_templates = templates;
     }
@@ -185,6 +318,61 @@ impl AgentSession {
         &self.prompt_templates
     }
 
+    /// The prompt `/template <name> [args]` sends; see [`render_prompt_template`].
+    pub fn expand_template<S: AsRef<str>>(
+        &self,
+        name: &str,
+        args: &[S],
+    ) -> Result<String, AgentSessionError> {
+        let template = self
+            .prompt_templates
+            .iter()
+            .find(|template| template.name == name)
+            .ok_or_else(|| AgentSessionError::Session(format!("Unknown template: {name}")))?;
+        render_prompt_template(template, args).map_err(AgentSessionError::Session)
+    }
+
+    pub fn run_template<S: AsRef<str>>(
+        &mut self,
+        name: &str,
+        args: &[S],
+    ) -> Result<(), AgentSessionError> {
+        let prompt = self.expand_template(name, args)?;
+        self.prompt_expanded(&prompt)
+    }
+
+    /// Records which project context files the system prompt was built with.
+    pub fn set_context_files(&mut self, paths: Vec<String>) {
+        self.context_files = paths;
+    }
+
+    pub fn context_files(&self) -> &[String] {
+        &self.context_files
+    }
+
+    pub fn set_skills(&mut self, skills: Vec<Skill>) {
+        self.skills = skills;
+    }
+
+    pub fn skills(&self) -> &[Skill] {
+        &self.skills
+    }
+
+    /// The prompt `/skill <name> [args]` sends.
+    pub fn expand_skill(&self, name: &str, args: &str) -> Result<String, AgentSessionError> {
+        let skill = self
+            .skills
+            .iter()
+            .find(|skill| skill.name == name)
+            .ok_or_else(|| AgentSessionError::Session(format!("Unknown skill: {name}")))?;
+        expand_skill(skill, args).map_err(AgentSessionError::Session)
+    }
+
+    pub fn run_skill(&mut self, name: &str, args: &str) -> Result<(), AgentSessionError> {
+        let prompt = self.expand_skill(name, args)?;
+        self.prompt_expanded(&prompt)
+    }
+
     pub fn set_extension_commands(&mut self, commands: Vec<ExtensionCommand>) {
         self.extension_commands = commands;
     }
@@ -198,14 +386,34 @@ impl AgentSession {
     }
 
     pub fn prompt(&mut self, text: &str) -> Result<(), AgentSessionError> {
+        if let Some(invocation) = text.strip_prefix("/skill ") {
+            let invocation = invocation.trim_start();
+            let (name, args) = invocation
+                .split_once(char::is_whitespace)
+                .unwrap_or((invocation, ""));
+            return self.run_skill(name, args);
+        }
+        if let Some(invocation) = text.strip_prefix("/template ") {
+            let invocation = invocation.trim_start();
+            let (name, args) = invocation
+                .split_once(char::is_whitespace)
+                .unwrap_or((invocation, ""));
+            return self.run_template(name, &parse_command_args(args));
+        }
+        let expanded_text = try_expand_prompt_template(text, &self.prompt_templates)
+            .map_err(AgentSessionError::Session)?;
+        self.prompt_expanded(&expanded_text)
+    }
+
+    fn prompt_expanded(&mut self, expanded_text: &str) -> Result<(), AgentSessionError> {
         if self.is_streaming() {
             return Err(AgentSessionError::AlreadyStreaming);
         }
+        let day = self.begin_spend()?;
 
         let before_len = self.agent.state().messages.len();
-        let expanded_text = self.expand_prompt_text(text);
         self.agent
-            .prompt(expanded_text.as_str())
+            .prompt(expanded_text)
             .map_err(AgentSessionError::Agent)?;
         let messages = self.agent.state().messages;
         for message in messages.into_iter().skip(before_len) {
@@ -213,13 +421,15 @@ impl AgentSession {
                 self.session_manager.append_message(core_message);
             }
         }
-        Ok(())
+        self.maybe_auto_compact();
+        self.end_spend(&day)
     }
 
     pub fn prompt_content(&mut self, content: UserContent) -> Result<(), AgentSessionError> {
         if self.is_streaming() {
             return Err(AgentSessionError::AlreadyStreaming);
         }
+        let day = self.begin_spend()?;
 
         let before_len = self.agent.state().messages.len();
         let content = self.expand_user_content(content);
@@ -236,7 +446,8 @@ impl AgentSession {
                 self.session_manager.append_message(core_message);
             }
         }
-        Ok(())
+        self.maybe_auto_compact();
+        self.end_spend(&day)
     }
 
     pub fn steer(&self, text: &str) {
@@ -255,11 +466,20 @@ impl AgentSession {
         }));
     }
 
-    fn expand_prompt_text(&self, text: &str) -> String {
-        if self.prompt_templates.is_empty() {
-            return text.to_string();
+    /// Like [`steer`](Self::steer), for a listener that runs while a prompt holds the session.
+    pub fn steering_sender(&self) -> impl Fn(&str) + 'static {
+        let send = self.agent.steering_sender();
+        let templates = self.prompt_templates.clone();
+        move |text| {
+            send(AgentMessage::User(UserMessage {
+                content: UserContent::Text(expand_with_templates(text, &templates)),
+                timestamp: now_millis(),
+            }));
         }
-        expand_prompt_template(text, &self.prompt_templates)
+    }
+
+    fn expand_prompt_text(&self, text: &str) -> String {
+        expand_with_templates(text, &self.prompt_templates)
     }
 
     fn expand_user_content(&self, content: UserContent) -> UserContent {
@@ -293,6 +513,155 @@ impl AgentSession {
             .append_model_change(&model.provider, &model.id);
     }
 
+    pub fn persona(&self) -> Option<&str> {
+        self.persona.as_deref()
+    }
+
+    /// Switch to a persona: its prompt is appended to the session's original system prompt and
+    /// tools are narrowed from the original tool set. Returns requested tools that are unavailable.
+    pub fn apply_persona(&mut self, persona: &Persona) -> Result<Vec<String>, AgentSessionError> {
+        let model = match &persona.model {
+            Some(pattern) => Some(self.find_persona_model(pattern).ok_or_else(|| {
+                AgentSessionError::Session(format!(
+                    "Model \"{pattern}\" for persona \"{}\" not found",
+                    persona.name
+                ))
+            })?),
+            None => None,
+        };
+
+        let state = self.agent.state();
+        self.base_system_prompt
+            .get_or_insert_with(|| state.system_prompt.clone());
+        let base_tools = self
+            .base_tools
+            .get_or_insert_with(|| state.tools.clone())
+            .clone();
+
+        let available = base_tools
+            .iter()
+            .map(|tool| tool.name.clone())
+            .collect::<Vec<_>>();
+        let allowed = persona.filter_tools(&available);
+        let missing = persona
+            .tools
+            .iter()
+            .flatten()
+            .filter(|name| !available.contains(name))
+            .cloned()
+            .collect::<Vec<_>>();
+        self.agent.set_tools(
+            base_tools
+                .into_iter()
+                .filter(|tool| allowed.contains(&tool.name))
+                .collect(),
+        );
+
+        self.persona_prompt = Some(persona.system_prompt.clone());
+        self.rebuild_system_prompt();
+
+        if let Some(model) = model {
+            let current = state.model;
+            if current.provider != model.provider || current.id != model.id {
+                self.set_model(crate::agent::Model {
+                    id: model.id,
+                    name: model.name,
+                    api: model.api,
+                    provider: model.provider,
+                });
+            }
+        }
+        if let Some(level) = persona.thinking {
+            self.set_thinking_level(level);
+        }
+
+        self.persona = Some(persona.name.clone());
+        Ok(missing)
+    }
+
+    pub fn context_packs(&self) -> &[ContextPack] {
+        &self.context_packs
+    }
+
+    /// Add `pack` to the system prompt for the rest of this session, replacing an enabled pack
+    /// with the same name.
+    pub fn enable_context_pack(&mut self, pack: ContextPack) {
+        match self.context_packs.iter_mut().find(|p| p.name == pack.name) {
+            Some(existing) => *existing = pack,
+            None => self.context_packs.push(pack),
+        }
+        self.rebuild_system_prompt();
+        self.record_context_packs();
+    }
+
+    /// Remove the pack called `name`. Returns false if it was not enabled.
+    pub fn disable_context_pack(&mut self, name: &str) -> bool {
+        let before = self.context_packs.len();
+        self.context_packs.retain(|pack| pack.name != name);
+        if self.context_packs.len() == before {
+            return false;
+        }
+        self.rebuild_system_prompt();
+        self.record_context_packs();
+        true
+    }
+
+    /// Re-enable the packs recorded in the session file, looked up in `available`. Returns the
+    /// names that could not be found.
+    pub fn restore_context_packs(&mut self, available: &[ContextPack]) -> Vec<String> {
+        let names = self.session_manager.get_context_packs();
+        if names.is_empty() && self.context_packs.is_empty() {
+            return Vec::new();
+        }
+        let mut missing = Vec::new();
+        self.context_packs.clear();
+        for name in names {
+            match available.iter().find(|pack| pack.name == name) {
+                Some(pack) => self.context_packs.push(pack.clone()),
+                None => missing.push(name),
+            }
+        }
+        self.rebuild_system_prompt();
+        missing
+    }
+
+    fn record_context_packs(&mut self) {
+        let names = self
+            .context_packs
+            .iter()
+            .map(|pack| pack.name.clone())
+            .collect::<Vec<_>>();
+        self.session_manager.append_context_packs(&names);
+    }
+
+    /// Compose the original system prompt with the persona prompt and enabled context packs.
+    fn rebuild_system_prompt(&mut self) {
+        let state = self.agent.state();
+        let mut prompt = self
+            .base_system_prompt
+            .get_or_insert_with(|| state.system_prompt.clone())
+            .clone();
+        if let Some(persona_prompt) = self
+            .persona_prompt
+            .as_deref()
+            .filter(|prompt| !prompt.trim().is_empty())
+        {
+            prompt = format!("{prompt}\n\n{persona_prompt}");
+        }
+        prompt.push_str(&format_context_packs_for_prompt(&self.context_packs));
+        self.agent.set_system_prompt(&prompt);
+    }
+
+    fn find_persona_model(&self, pattern: &str) -> Option<crate::coding_agent::Model> {
+        if let Some((provider, model_id)) = pattern.split_once('/') {
+            return self.model_registry.find(provider, model_id);
+        }
+        self.model_registry
+            .get_available()
+            .into_iter()
+            .find(|model| model.id == pattern)
+    }
+
     pub fn set_steering_mode(&mut self, mode: crate::agent::QueueMode) {
         self.agent.set_steering_mode(mode);
     }
@@ -309,10 +678,82 @@ impl AgentSession {
         self.agent.get_follow_up_mode()
     }
 
+    pub fn set_queue_priority(&mut self, priority: crate::agent::QueuePriority) {
+        self.agent.set_queue_priority(priority);
+    }
+
+    pub fn queue_priority(&self) -> crate::agent::QueuePriority {
+        self.agent.get_queue_priority()
+    }
+
+    pub fn pending_queue(&self) -> Vec<crate::agent::PendingMessage> {
+        self.agent.pending_queue()
+    }
+
+    pub fn remove_pending_message(
+        &self,
+        id: u64,
+    ) -> Result<crate::agent::PendingMessage, AgentSessionError> {
+        self.agent
+            .remove_pending(id)
+            .ok_or_else(|| AgentSessionError::Session(format!("No queued message with id {id}")))
+    }
+
+    /// Replace the text of a queued message, keeping its place in the queue.
+    pub fn edit_pending_message(&self, id: u64, text: &str) -> Result<(), AgentSessionError> {
+        let message = AgentMessage::User(UserMessage {
+            content: UserContent::Text(self.expand_prompt_text(text)),
+            timestamp: now_millis(),
+        });
+        if self.agent.replace_pending(id, message) {
+            Ok(())
+        } else {
+            Err(AgentSessionError::Session(format!(
+                "No queued message with id {id}"
+            )))
+        }
+    }
+
+    /// Drop every queued steering and follow-up message. Returns how many were dropped.
+    pub fn clear_pending(&self) -> usize {
+        let count = self.pending_message_count();
+        self.agent.clear_all_queues();
+        count
+    }
+
     pub fn set_compaction_hooks(&mut self, hooks: Vec<CompactionHook>) {
         self.compaction_hooks = hooks;
     }
 
+    /// Overrides the settings-selected strategy until cleared with `None`.
+    pub fn set_compaction_strategy(&mut self, strategy: Option<Box<dyn CompactionStrategy>>) {
+        self.compaction_strategy = strategy;
+    }
+
+    pub fn set_compaction_strategy_name(&mut self, name: &str) -> Result<(), AgentSessionError> {
+        if compaction_strategy_for_name(name).is_none() {
+            return Err(AgentSessionError::Compaction(format!(
+                "Unknown compaction strategy \"{name}\". Valid strategies: {}",
+                COMPACTION_STRATEGY_NAMES.join(", ")
+            )));
+        }
+        self.compaction_strategy = None;
+        self.settings_manager.set_compaction_strategy(name);
+        Ok(())
+    }
+
+    pub fn compaction_strategy_name(&self) -> String {
+        match self.compaction_strategy.as_deref() {
+            Some(strategy) => strategy.name().to_string(),
+            None => self.settings_manager.get_compaction_strategy(),
+        }
+    }
+
+    fn settings_compaction_strategy(&self) -> Box<dyn CompactionStrategy> {
+        compaction_strategy_for_name(&self.settings_manager.get_compaction_strategy())
+            .unwrap_or_else(|| Box::new(SummarizeStrategy))
+    }
+
     pub fn set_extension_host(&mut self, host: ExtensionHost) {
         self.set_extension_host_shared(Rc::new(RefCell::new(host)));
     }
@@ -336,6 +777,23 @@ impl AgentSession {
         self.wrap_tools_with_extensions();
     }
 
+    pub fn extension_host(&self) -> Option<Rc<RefCell<ExtensionHost>>> {
+        self.extension_host.clone()
+    }
+
+    /// Run assistant output through `chain` before it is displayed or persisted.
+    /// An empty chain removes any filtering.
+    pub fn set_output_filters(&mut self, chain: OutputFilterChain) {
+        if chain.is_empty() {
+            self.agent.set_output_filter(None);
+            return;
+        }
+        self.agent
+            .set_output_filter(Some(Rc::new(move |message, partial| {
+                chain.apply(message, partial)
+            })));
+    }
+
     pub fn set_extension_ui_handler<F>(&mut self, handler: F)
     where
         F: Fn(&ExtensionUiRequest) -> ExtensionUiResponse + 'static,
@@ -412,6 +870,7 @@ impl AgentSession {
         } else {
             self.session_manager.new_session(None);
         }
+        self.follow_in_mirror();
 
         let context = self.session_manager.build_session_context();
         let messages = context
@@ -534,6 +993,287 @@ impl AgentSession {
         })
     }
 
+    /// Pin or unpin a message entry so it survives compaction verbatim.
+    pub fn pin_message(&mut self, entry_id: &str, pinned: bool) -> Result<(), AgentSessionError> {
+        self.session_manager
+            .append_pin(entry_id, pinned)
+            .map(|_| ())
+            .map_err(AgentSessionError::Session)
+    }
+
+    pub fn pinned_entry_ids(&self) -> Vec<String> {
+        self.session_manager.get_pinned_entry_ids()
+    }
+
+    /// Use `journal` (shared with the write and edit tools) as this session's change journal.
+    pub fn set_change_journal(&mut self, journal: SharedChangeJournal) {
+        self.change_journal = journal;
+    }
+
+    /// Keep `mirror` showing this session, including after switching or branching.
+    pub fn set_mirror(&mut self, mirror: SessionMirror) {
+        mirror.follow(self.session_manager.get_session_file());
+        self.mirror = Some(mirror);
+    }
+
+    fn follow_in_mirror(&self) {
+        if let Some(mirror) = &self.mirror {
+            mirror.follow(self.session_manager.get_session_file());
+        }
+    }
+
+    /// Use `approval` (shared with the bash tool) to ask about commands the bash policy
+    /// cannot decide.
+    pub fn set_bash_approval(&mut self, approval: BashApproval) {
+        self.bash_approval = approval;
+    }
+
+    pub fn bash_approval(&self) -> &BashApproval {
+        &self.bash_approval
+    }
+
+    /// Use `review` (shared with the write and edit tools) to ask about file changes.
+    pub fn set_edit_review(&mut self, review: EditReview) {
+        self.edit_review = review;
+    }
+
+    pub fn edit_review(&self) -> &EditReview {
+        &self.edit_review
+    }
+
+    /// File changes made by tools in this session, oldest first.
+    pub fn file_changes(&self) -> Vec<FileChange> {
+        self.change_journal.borrow().changes().to_vec()
+    }
+
+    pub fn undo_last_change(&mut self) -> Result<FileChange, AgentSessionError> {
+        self.change_journal
+            .borrow_mut()
+            .undo_last()
+            .map_err(AgentSessionError::Session)
+    }
+
+    /// Restore `path` (relative to the session cwd) to its content before the session's
+    /// first change to it.
+    pub fn revert_file(&mut self, path: &str) -> Result<Vec<FileChange>, AgentSessionError> {
+        let path = self.session_manager.get_cwd().join(path);
+        self.change_journal
+            .borrow_mut()
+            .revert_file(&path)
+            .map_err(AgentSessionError::Session)
+    }
+
+    /// Name the current point in the conversation and the file changes made so far, so
+    /// `rewind` can return to both. Reusing a name moves it; the newest checkpoint wins.
+    pub fn create_checkpoint(&mut self, name: &str) -> Result<Checkpoint, AgentSessionError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(AgentSessionError::Session(
+                "Checkpoint name is required".to_string(),
+            ));
+        }
+        let (file_changes, last_change_id) = {
+            let journal = self.change_journal.borrow();
+            let changes = journal.changes();
+            (
+                changes.len(),
+                changes.last().map(|change| change.tool_call_id.clone()),
+            )
+        };
+        let entry_id =
+            self.session_manager
+                .append_checkpoint(name, file_changes, last_change_id.as_deref());
+        self.session_manager
+            .get_checkpoints()
+            .into_iter()
+            .find(|checkpoint| checkpoint.entry_id == entry_id)
+            .ok_or_else(|| AgentSessionError::Session("Failed to record checkpoint".to_string()))
+    }
+
+    /// Checkpoints on every branch of the session, oldest first.
+    pub fn checkpoints(&self) -> Vec<Checkpoint> {
+        self.session_manager.get_checkpoints()
+    }
+
+    /// Return the conversation to checkpoint `name` and undo the file changes made after it.
+    pub fn rewind(&mut self, name: &str) -> Result<RewindResult, AgentSessionError> {
+        if self.is_streaming() {
+            return Err(AgentSessionError::AlreadyStreaming);
+        }
+        let checkpoint = self
+            .checkpoints()
+            .into_iter()
+            .rev()
+            .find(|checkpoint| checkpoint.name == name)
+            .ok_or_else(|| AgentSessionError::Session(format!("Unknown checkpoint: {name}")))?;
+
+        let files_restored = {
+            let journal = self.change_journal.borrow();
+            let changes = journal.changes();
+            changes.len() >= checkpoint.file_changes
+                && checkpoint
+                    .file_changes
+                    .checked_sub(1)
+                    .map(|index| &changes[index].tool_call_id)
+                    == checkpoint.last_change_id.as_ref()
+        };
+        let mut undone = Vec::new();
+        if files_restored {
+            while self.change_journal.borrow().changes().len() > checkpoint.file_changes {
+                undone.push(self.undo_last_change()?);
+            }
+        }
+
+        self.navigate_tree(&checkpoint.entry_id, NavigateTreeOptions::default())?;
+        Ok(RewindResult {
+            checkpoint,
+            undone,
+            files_restored,
+        })
+    }
+
+    /// Snapshot the working tree under `refs/pi/checkpoints/` before the first write or edit
+    /// of each turn, so agent changes can be rolled back turn by turn with git. The user's
+    /// branch and index are not touched. Does nothing outside a git repository.
+    pub fn set_git_checkpoints(&mut self, enabled: bool) {
+        if let Some(unsubscribe) = self.unsubscribe_git_checkpoints.take() {
+            unsubscribe();
+        }
+        if !enabled {
+            return;
+        }
+        let cwd = self.session_manager.get_cwd();
+        let commits = self.git_checkpoints.clone();
+        let checkpointed = Cell::new(false);
+        let unsubscribe = self.subscribe(move |event| {
+            let AgentSessionEvent::Agent(event) = event else {
+                return;
+            };
+            match event.as_ref() {
+                AgentEvent::TurnStart => checkpointed.set(false),
+                AgentEvent::ToolExecutionStart { tool_name, .. }
+                    if !checkpointed.get() && matches!(tool_name.as_str(), "write" | "edit") =>
+                {
+                    checkpointed.set(true);
+                    match checkpoint_commit(&cwd, "pi checkpoint before agent changes") {
+                        Ok(Some(commit)) => commits.borrow_mut().push(commit),
+                        Ok(None) => {}
+                        Err(err) => eprintln!("Warning: Git checkpoint failed: {err}"),
+                    }
+                }
+                _ => {}
+            }
+        });
+        self.unsubscribe_git_checkpoints = Some(Box::new(unsubscribe));
+    }
+
+    /// Record the cost of every reply in `tracker` and stop the agent loop before it sends
+    /// another request once a limit in `limits` is reached.
+    pub fn set_spend_tracking(&mut self, tracker: SpendTracker, limits: SpendLimits) {
+        if let Some(unsubscribe) = self.unsubscribe_spend.take() {
+            unsubscribe();
+        }
+        self.spend = Some(tracker);
+        self.spend_limits = limits;
+        let baseline = self.spend_baseline.clone();
+        let prompt_cost = self.prompt_cost.clone();
+        let exceeded = self.budget_exceeded.clone();
+        let abort_flag = self.agent.abort_flag();
+        let unsubscribe = self.subscribe(move |event| {
+            let AgentSessionEvent::Agent(event) = event else {
+                return;
+            };
+            let AgentEvent::MessageEnd {
+                message: AgentMessage::Assistant(message),
+            } = event.as_ref()
+            else {
+                return;
+            };
+            let cost = message.usage.cost.as_ref().map_or(0.0, |cost| cost.total);
+            prompt_cost.set(prompt_cost.get() + cost);
+            // A reply without tool calls ends the loop anyway; the next prompt is refused.
+            let continues = message
+                .content
+                .iter()
+                .any(|block| matches!(block, ContentBlock::ToolCall { .. }));
+            if !continues || exceeded.borrow().is_some() {
+                return;
+            }
+            let (session, today) = baseline.get();
+            let snapshot = SpendSnapshot {
+                session: session + prompt_cost.get(),
+                today: today + prompt_cost.get(),
+                limits,
+            };
+            if let Some(reason) = snapshot.exceeded() {
+                *exceeded.borrow_mut() = Some(reason);
+                abort_flag.cancel();
+            }
+        });
+        self.unsubscribe_spend = Some(Box::new(unsubscribe));
+    }
+
+    /// What this session and today have cost so far, with the limits in force.
+    pub fn get_spend(&self) -> SpendSnapshot {
+        let Some(tracker) = &self.spend else {
+            return SpendSnapshot::default();
+        };
+        let running = if self.is_streaming() {
+            self.prompt_cost.get()
+        } else {
+            0.0
+        };
+        SpendSnapshot {
+            session: tracker.session_total(&self.session_id()) + running,
+            today: tracker.day_total(&spend_day()) + running,
+            limits: self.spend_limits,
+        }
+    }
+
+    /// Refuses to start a prompt once a budget is spent; returns the day to record it under.
+    fn begin_spend(&mut self) -> Result<String, AgentSessionError> {
+        let day = spend_day();
+        self.prompt_cost.set(0.0);
+        *self.budget_exceeded.borrow_mut() = None;
+        if self.spend.is_some() {
+            let snapshot = self.get_spend();
+            if let Some(reason) = snapshot.exceeded() {
+                return Err(AgentSessionError::BudgetExceeded(reason));
+            }
+            self.spend_baseline.set((snapshot.session, snapshot.today));
+        }
+        Ok(day)
+    }
+
+    fn end_spend(&mut self, day: &str) -> Result<(), AgentSessionError> {
+        let cost = self.prompt_cost.replace(0.0);
+        let session_id = self.session_id();
+        if let Some(tracker) = self.spend.as_mut().filter(|_| cost > 0.0) {
+            if let Err(err) = tracker.record(&session_id, day, cost) {
+                eprintln!("Warning: Failed to record spend: {err}");
+            }
+        }
+        match self.budget_exceeded.borrow_mut().take() {
+            Some(reason) => Err(AgentSessionError::BudgetExceeded(reason)),
+            None => Ok(()),
+        }
+    }
+
+    /// Stops each run that has made `limits.max_turns` model calls or has been running for
+    /// `limits.max_duration` and would otherwise keep calling tools.
+    pub fn set_loop_limits(&mut self, limits: LoopLimits) {
+        self.agent.set_loop_limits(limits);
+    }
+
+    pub fn loop_limits(&self) -> LoopLimits {
+        self.agent.loop_limits()
+    }
+
+    /// Checkpoint commits made this session, oldest first.
+    pub fn git_checkpoints(&self) -> Vec<String> {
+        self.git_checkpoints.borrow().clone()
+    }
+
     pub fn compact(&mut self) -> Result<CompactionResult, AgentSessionError> {
         self.compact_with_instructions(None)
     }
@@ -544,7 +1284,7 @@ impl AgentSession {
     ) -> Result<CompactionResult, AgentSessionError> {
         let branch_entries = self.session_manager.get_branch(None);
         let settings = self.settings_manager.get_compaction_settings();
-        let preparation = prepare_compaction(&branch_entries, settings).ok_or_else(|| {
+        let mut preparation = prepare_compaction(&branch_entries, settings).ok_or_else(|| {
             AgentSessionError::Compaction("Compaction not applicable".to_string())
         })?;
 
@@ -554,6 +1294,7 @@ impl AgentSession {
         };
 
         let mut hook_compaction: Option<CompactionResult> = None;
+        let mut hook_pins = Vec::new();
         for hook in &self.compaction_hooks {
             let Some(handler) = &hook.on_before_compact else {
                 continue;
@@ -572,30 +1313,45 @@ impl AgentSession {
             if let Some(compaction) = result.compaction {
                 hook_compaction = Some(compaction);
             }
+            hook_pins.extend(result.pin.unwrap_or_default());
         }
-
-        let mut summary =
-            summarize_compaction_messages(&preparation.messages_to_summarize, custom_instructions);
-        if summary.trim().is_empty() {
-            summary = "Summary.".to_string();
+        let mut branch_entries = branch_entries;
+        if !hook_pins.is_empty() {
+            for entry_id in &hook_pins {
+                self.session_manager
+                    .append_pin(entry_id, true)
+                    .map_err(AgentSessionError::Compaction)?;
+            }
+            branch_entries = self.session_manager.get_branch(None);
+            preparation = prepare_compaction(&branch_entries, settings).ok_or_else(|| {
+                AgentSessionError::Compaction("Compaction not applicable".to_string())
+            })?;
         }
 
-        let mut result = CompactionResult {
-            summary,
-            first_kept_entry_id: preparation.first_kept_entry_id.clone(),
-            tokens_before: preparation.tokens_before,
+        let from_hook = hook_compaction.is_some();
+        let result = match hook_compaction {
+            Some(compaction) => compaction,
+            None => {
+                let request = CompactionRequest {
+                    preparation: &preparation,
+                    branch_entries: &branch_entries,
+                    custom_instructions,
+                };
+                let strategy = match self.compaction_strategy.as_deref() {
+                    Some(strategy) => strategy.compact(&request),
+                    None => self.settings_compaction_strategy().compact(&request),
+                };
+                strategy.map_err(AgentSessionError::Compaction)?
+            }
         };
 
-        let mut from_hook = false;
-        if let Some(compaction) = hook_compaction {
-            result = compaction;
-            from_hook = true;
-        }
-
-        self.session_manager.append_compaction(
-            &result.summary,
+        let file_state = collect_file_state(&preparation.file_ops, &self.session_manager.get_cwd());
+        let summary = format!("{}{}", result.summary, format_file_state(&file_state));
+        self.session_manager.append_compaction_with_details(
+            &summary,
             &result.first_kept_entry_id,
             result.tokens_before,
+            serde_json::to_value(&file_state).ok(),
         );
 
         let compaction_entry = match self.session_manager.get_leaf_entry() {
@@ -755,6 +1511,8 @@ impl AgentSession {
         let mut assistant_messages = 0;
         let mut tool_results = 0;
         let mut tool_calls = 0;
+        let mut lines_added = 0;
+        let mut lines_removed = 0;
 
         let mut input = 0;
         let mut output = 0;
@@ -780,7 +1538,15 @@ impl AgentSession {
                         cost += usage_cost.total;
                     }
                 }
-                AgentMessage::ToolResult(_) => tool_results += 1,
+                AgentMessage::ToolResult(result) => {
+                    tool_results += 1;
+                    for block in &result.content {
+                        if let ContentBlock::Diff { added, removed, .. } = block {
+                            lines_added += added;
+                            lines_removed += removed;
+                        }
+                    }
+                }
                 _ => {}
             }
         }
@@ -793,6 +1559,9 @@ impl AgentSession {
             tool_calls,
             tool_results,
             total_messages: messages.len(),
+            lines_added,
+            lines_removed,
+            limits_reached: self.limits_reached.get(),
             tokens: TokenStats {
                 input,
                 output,
@@ -804,8 +1573,62 @@ impl AgentSession {
         }
     }
 
+    /// Estimated context size for the next request and the current model's context window.
+    pub fn get_context_usage(&self) -> ContextUsage {
+        let state = self.agent.state();
+        let messages = state
+            .messages
+            .iter()
+            .filter_map(convert_message)
+            .collect::<Vec<_>>();
+        let context_window = self
+            .model_registry
+            .context_window(&state.model.provider, &state.model.id);
+        let settings = self.settings_manager.get_compaction_settings();
+        let estimate = tokens::estimate(&messages, &state.model);
+        let total = estimate.total();
+        ContextUsage {
+            tokens: total,
+            reported_tokens: estimate.reported,
+            estimated_tokens: estimate.estimated,
+            tokenizer: estimate.tokenizer,
+            context_window,
+            percent: context_window
+                .filter(|window| *window > 0)
+                .map(|window| total as f64 * 100.0 / window as f64),
+            compaction_threshold: context_window
+                .map(|window| compaction_trigger_tokens(window, settings)),
+        }
+    }
+
+    /// Compact when the estimated context has crossed the configured threshold of the
+    /// model's context window. Called after each prompt completes.
+    fn maybe_auto_compact(&mut self) {
+        if !self.settings_manager.get_compaction_enabled() || self.is_streaming() {
+            return;
+        }
+        let usage = self.get_context_usage();
+        let Some(threshold) = usage.compaction_threshold else {
+            return;
+        };
+        if usage.tokens <= threshold {
+            return;
+        }
+        self.emit_session_event(AgentSessionEvent::AutoCompactionStart {
+            reason: "threshold".to_string(),
+        });
+        let aborted = self.compact().is_err();
+        self.emit_session_event(AgentSessionEvent::AutoCompactionEnd { aborted });
+    }
+
+    fn emit_session_event(&self, event: AgentSessionEvent) {
+        self.events.emit(&event);
+    }
+
     pub fn new_session(&mut self) {
         self.session_manager.new_session(None);
+        self.follow_in_mirror();
+        self.change_journal.borrow_mut().clear();
         self.agent.abort();
         self.agent.clear_messages();
         self.agent.clear_all_queues();
@@ -814,11 +1637,28 @@ impl AgentSession {
     pub fn export_to_html_with_path(
         &self,
         output_path: Option<&PathBuf>,
+    ) -> Result<ExportResult, AgentSessionError> {
+        self.export_to_html_with_theme(output_path, None)
+    }
+
+    pub fn export_to_html_with_theme(
+        &self,
+        output_path: Option<&PathBuf>,
+        theme: Option<&str>,
     ) -> Result<ExportResult, AgentSessionError> {
         let state = self.agent.state();
-        let output_path = output_path.cloned();
-        let path = export_session_to_html(&self.session_manager, Some(&state), output_path)
-            .map_err(AgentSessionError::Session)?;
+        let options = ExportOptions {
+            custom_messages: self.settings_manager.get_export_custom_messages(),
+            theme: theme.map(str::to_string),
+            ..ExportOptions::default()
+        };
+        let path = export_session_to_html(
+            &self.session_manager,
+            Some(&state),
+            output_path.cloned(),
+            &options,
+        )
+        .map_err(AgentSessionError::Session)?;
         Ok(ExportResult { path })
     }
 
@@ -827,9 +1667,12 @@ impl AgentSession {
     }
 
     pub fn execute_bash(&mut self, command: &str) -> Result<BashResult, AgentSessionError> {
-        let output = Command::new("sh")
-            .arg("-c")
-            .arg(command)
+        let shell = Shell::configured_or(
+            self.settings_manager.get_shell_path().as_deref(),
+            Shell::command_default,
+        );
+        let output = shell
+            .command(command)
             .output()
             .map_err(|err| AgentSessionError::Session(err.to_string()))?;
         let mut combined = String::new();
@@ -868,7 +1711,7 @@ impl AgentSession {
         let messages = self.agent.state().messages;
         for message in messages.iter().rev() {
             if let AgentMessage::Assistant(assistant) = message {
-                if assistant.stop_reason == "aborted" && assistant.content.is_empty() {
+                if assistant.is_aborted() && assistant.content.is_empty() {
                     continue;
                 }
                 let mut text = String::new();
@@ -886,10 +1729,41 @@ impl AgentSession {
     }
 
     pub fn switch_session(&mut self, session_path: PathBuf) -> Result<bool, AgentSessionError> {
+        check_session_version(&session_path).map_err(AgentSessionError::Session)?;
         self.agent.abort();
         self.agent.clear_all_queues();
 
         self.session_manager.set_session_file(session_path);
+        self.change_journal.borrow_mut().clear();
+        self.load_session_context();
+        Ok(true)
+    }
+
+    /// Fork the session at `entry_id` into a child session (a new session file when
+    /// persisting) and continue from there. Any entry on the tree can be checked out.
+    pub fn branch_from(&mut self, entry_id: &str) -> Result<BranchCheckout, AgentSessionError> {
+        if self.session_manager.get_entry(entry_id).is_none() {
+            return Err(AgentSessionError::InvalidBranchEntry);
+        }
+        let parent_session = self.session_manager.get_session_file();
+        self.agent.abort();
+        self.agent.clear_all_queues();
+        let session_file = self
+            .session_manager
+            .create_branched_session(entry_id)
+            .map_err(AgentSessionError::Session)?;
+        self.load_session_context();
+        Ok(BranchCheckout {
+            entry_id: entry_id.to_string(),
+            session_id: self.session_manager.get_session_id(),
+            session_file,
+            parent_session,
+        })
+    }
+
+    /// Replace the agent's messages, model and thinking level with the session's current branch.
+    fn load_session_context(&mut self) {
+        self.follow_in_mirror();
         let context = self.session_manager.build_session_context();
         let messages = context
             .messages
@@ -912,8 +1786,36 @@ impl AgentSession {
         if let Some(level) = thinking_level_from_str(&context.thinking_level) {
             self.agent.set_thinking_level(level);
         }
+    }
 
-        Ok(true)
+    /// Assistant replies on the current branch, newest first, as points to check out.
+    pub fn get_branch_checkout_candidates(&self) -> Vec<BranchCandidate> {
+        let mut results = Vec::new();
+        for entry in self.session_manager.get_branch(None) {
+            let SessionEntry::Message(message_entry) = entry else {
+                continue;
+            };
+            let CoreAgentMessage::Assistant(assistant) = &message_entry.message else {
+                continue;
+            };
+            let text = assistant
+                .content
+                .iter()
+                .filter_map(|block| match block {
+                    ContentBlock::Text { text, .. } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            if !text.trim().is_empty() {
+                results.push(BranchCandidate {
+                    entry_id: message_entry.id.clone(),
+                    text,
+                });
+            }
+        }
+        results.reverse();
+        results
     }
 }
 
@@ -925,6 +1827,7 @@ pub enum AgentSessionError {
     InvalidTreeTarget,
     Compaction(String),
     Session(String),
+    BudgetExceeded(String),
 }
 
 impl std::fmt::Display for AgentSessionError {
@@ -939,6 +1842,7 @@ impl std::fmt::Display for AgentSessionError {
             AgentSessionError::InvalidTreeTarget => write!(f, "Entry not found for navigation"),
             AgentSessionError::Compaction(err) => write!(f, "{err}"),
             AgentSessionError::Session(err) => write!(f, "{err}"),
+            AgentSessionError::BudgetExceeded(reason) => write!(f, "{reason}"),
         }
     }
 }
@@ -954,6 +1858,11 @@ pub struct SettingsCompaction {
     pub reserve_tokens: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub keep_recent_tokens: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<String>,
+    /// Auto-compact once the context reaches this percentage of the model's context window.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<f64>,
 }
 
 #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
@@ -990,18 +1899,142 @@ pub struct SettingsSkills {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub enable_pi_project: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub custom_directories: Option<Vec<String>>,
+    pub custom_directories: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ignored_skills: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_skills: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsTerminal {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub show_images: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mouse_scroll: Option<bool>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsTelemetry {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exporter: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub headers: Option<BTreeMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_name: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsOutputFilterRule {
+    pub pattern: String,
+    /// "redact" (default) or "block".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsOutputFilters {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rules: Option<Vec<SettingsOutputFilterRule>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extensions: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub moderation_model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fail_closed: Option<bool>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsToolUpdates {
+    /// Minimum time between `tool_execution_update` events for one tool call; 0 disables.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_bytes: Option<usize>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsBashPolicy {
+    /// Command patterns that run without asking, e.g. `cargo *` or `npm test`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow: Option<Vec<String>>,
+    /// Command patterns that are always refused, e.g. `rm -rf *` or `git push`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deny: Option<Vec<String>>,
+    /// "allow", "ask" or "deny" for commands no allow rule covers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_action: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsSandbox {
+    /// Restrict file tools to the workspace (default true).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// Extra directories tools may access, absolute or relative to the cwd.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_paths: Option<Vec<String>>,
+    /// Run bash under `firejail`/`sandbox-exec` when installed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wrap_bash: Option<bool>,
+}
+
+/// Files preloaded at session start (see `context_dir.rs`).
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsContextDir {
+    /// `--context-dir` overrides it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclude: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_bytes: Option<usize>,
+    /// "systemPrompt" (default) or "firstMessage".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub placement: Option<String>,
+}
+
+/// Changes to the built-in system prompt (see `PromptOverrides`).
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsSystemPrompt {
+    /// Section names to put first, e.g. `["identity", "context"]`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<Vec<String>>,
+    /// Replacement text by section name; `""` removes the section.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub ignored_skills: Option<Vec<String>>,
+    pub sections: Option<HashMap<String, String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub include_skills: Option<Vec<String>>,
+    pub tool_descriptions: Option<HashMap<String, String>>,
 }
 
+/// Spending caps in USD (see `spend.rs`).
 #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct SettingsTerminal {
+pub struct SettingsBudget {
+    /// Per session; `--max-cost` overrides it.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub show_images: Option<bool>,
+    pub max_cost: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_daily_cost: Option<f64>,
 }
 
 #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
@@ -1027,6 +2060,8 @@ pub struct Settings {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub follow_up_mode: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub queue_priority: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub theme: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub compaction: Option<SettingsCompaction>,
@@ -1052,6 +2087,51 @@ pub struct Settings {
     pub enabled_models: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub double_escape_action: Option<String>,
+    /// Custom messages in HTML exports: `displayed` (default), `all` or `hidden`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub export_custom_messages: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub telemetry: Option<SettingsTelemetry>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_filters: Option<SettingsOutputFilters>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth_profile: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_updates: Option<SettingsToolUpdates>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git_checkpoints: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<SettingsSandbox>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bash_policy: Option<SettingsBashPolicy>,
+    /// Let `.pi/settings.json` loosen `sandbox` and `bashPolicy`; without it a project can only
+    /// tighten them. Only read from the global settings.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trust_project_settings: Option<bool>,
+    /// File tools whose changes wait for review in the interactive UI, e.g. `["write", "edit"]`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub review_edits: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub environment_snapshot: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub budget: Option<SettingsBudget>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_requests: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_dir: Option<SettingsContextDir>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<SettingsSystemPrompt>,
+}
+
+fn expand_with_templates(text: &str, templates: &[PromptTemplate]) -> String {
+    if templates.is_empty() {
+        return text.to_string();
+    }
+    expand_prompt_template(text, templates)
 }
 
 fn merge_settings(base: &Settings, overrides: &Settings) -> Settings {
@@ -1080,6 +2160,10 @@ fn merge_settings(base: &Settings, overrides: &Settings) -> Settings {
             .follow_up_mode
             .clone()
             .or_else(|| base.follow_up_mode.clone()),
+        queue_priority: overrides
+            .queue_priority
+            .clone()
+            .or_else(|| base.queue_priority.clone()),
         theme: overrides.theme.clone().or_else(|| base.theme.clone()),
         compaction: merge_optional_nested(
             base.compaction.as_ref(),
@@ -1125,9 +2209,100 @@ fn merge_settings(base: &Settings, overrides: &Settings) -> Settings {
             .double_escape_action
             .clone()
             .or_else(|| base.double_escape_action.clone()),
+        export_custom_messages: overrides
+            .export_custom_messages
+            .clone()
+            .or_else(|| base.export_custom_messages.clone()),
+        seed: overrides.seed.or(base.seed),
+        telemetry: merge_optional_nested(
+            base.telemetry.as_ref(),
+            overrides.telemetry.as_ref(),
+            merge_telemetry,
+        ),
+        output_filters: merge_optional_nested(
+            base.output_filters.as_ref(),
+            overrides.output_filters.as_ref(),
+            merge_output_filters,
+        ),
+        auth_profile: overrides
+            .auth_profile
+            .clone()
+            .or_else(|| base.auth_profile.clone()),
+        tool_updates: merge_optional_nested(
+            base.tool_updates.as_ref(),
+            overrides.tool_updates.as_ref(),
+            merge_tool_updates,
+        ),
+        git_checkpoints: overrides.git_checkpoints.or(base.git_checkpoints),
+        sandbox: merge_optional_nested(
+            base.sandbox.as_ref(),
+            overrides.sandbox.as_ref(),
+            merge_sandbox,
+        ),
+        bash_policy: merge_optional_nested(
+            base.bash_policy.as_ref(),
+            overrides.bash_policy.as_ref(),
+            merge_bash_policy,
+        ),
+        trust_project_settings: overrides
+            .trust_project_settings
+            .or(base.trust_project_settings),
+        review_edits: overrides
+            .review_edits
+            .clone()
+            .or_else(|| base.review_edits.clone()),
+        environment_snapshot: overrides.environment_snapshot.or(base.environment_snapshot),
+        tools: overrides.tools.clone().or_else(|| base.tools.clone()),
+        budget: merge_optional_nested(
+            base.budget.as_ref(),
+            overrides.budget.as_ref(),
+            |base, overrides| SettingsBudget {
+                max_cost: overrides.max_cost.or(base.max_cost),
+                max_daily_cost: overrides.max_daily_cost.or(base.max_daily_cost),
+            },
+        ),
+        log_requests: overrides.log_requests.or(base.log_requests),
+        context_dir: merge_optional_nested(
+            base.context_dir.as_ref(),
+            overrides.context_dir.as_ref(),
+            |base, overrides| SettingsContextDir {
+                path: overrides.path.clone().or_else(|| base.path.clone()),
+                include: overrides.include.clone().or_else(|| base.include.clone()),
+                exclude: overrides.exclude.clone().or_else(|| base.exclude.clone()),
+                max_bytes: overrides.max_bytes.or(base.max_bytes),
+                placement: overrides
+                    .placement
+                    .clone()
+                    .or_else(|| base.placement.clone()),
+            },
+        ),
+        system_prompt: merge_optional_nested(
+            base.system_prompt.as_ref(),
+            overrides.system_prompt.as_ref(),
+            |base, overrides| SettingsSystemPrompt {
+                order: overrides.order.clone().or_else(|| base.order.clone()),
+                sections: merge_string_maps(base.sections.as_ref(), overrides.sections.as_ref()),
+                tool_descriptions: merge_string_maps(
+                    base.tool_descriptions.as_ref(),
+                    overrides.tool_descriptions.as_ref(),
+                ),
+            },
+        ),
     }
 }
 
+/// Project entries are added to the global ones, replacing any with the same key.
+fn merge_string_maps(
+    base: Option<&HashMap<String, String>>,
+    overrides: Option<&HashMap<String, String>>,
+) -> Option<HashMap<String, String>> {
+    merge_optional_nested(base, overrides, |base, overrides| {
+        let mut merged = base.clone();
+        merged.extend(overrides.clone());
+        merged
+    })
+}
+
 fn merge_optional_nested<T, F>(base: Option<&T>, overrides: Option<&T>, merge: F) -> Option<T>
 where
     T: Clone,
@@ -1149,6 +2324,18 @@ fn merge_compaction(
         enabled: overrides.enabled.or(base.enabled),
         reserve_tokens: overrides.reserve_tokens.or(base.reserve_tokens),
         keep_recent_tokens: overrides.keep_recent_tokens.or(base.keep_recent_tokens),
+        strategy: overrides.strategy.clone().or_else(|| base.strategy.clone()),
+        threshold: overrides.threshold.or(base.threshold),
+    }
+}
+
+fn merge_tool_updates(
+    base: &SettingsToolUpdates,
+    overrides: &SettingsToolUpdates,
+) -> SettingsToolUpdates {
+    SettingsToolUpdates {
+        interval_ms: overrides.interval_ms.or(base.interval_ms),
+        max_bytes: overrides.max_bytes.or(base.max_bytes),
     }
 }
 
@@ -1197,15 +2384,145 @@ fn merge_skills(base: &SettingsSkills, overrides: &SettingsSkills) -> SettingsSk
 fn merge_terminal(base: &SettingsTerminal, overrides: &SettingsTerminal) -> SettingsTerminal {
     SettingsTerminal {
         show_images: overrides.show_images.or(base.show_images),
+        mouse_scroll: overrides.mouse_scroll.or(base.mouse_scroll),
+    }
+}
+
+fn merge_telemetry(base: &SettingsTelemetry, overrides: &SettingsTelemetry) -> SettingsTelemetry {
+    SettingsTelemetry {
+        enabled: overrides.enabled.or(base.enabled),
+        exporter: overrides.exporter.clone().or_else(|| base.exporter.clone()),
+        endpoint: overrides.endpoint.clone().or_else(|| base.endpoint.clone()),
+        headers: overrides.headers.clone().or_else(|| base.headers.clone()),
+        service_name: overrides
+            .service_name
+            .clone()
+            .or_else(|| base.service_name.clone()),
+    }
+}
+
+fn merge_output_filters(
+    base: &SettingsOutputFilters,
+    overrides: &SettingsOutputFilters,
+) -> SettingsOutputFilters {
+    // Project rules add to the global ones so a project cannot drop organisation-wide rules.
+    let rules = match (&base.rules, &overrides.rules) {
+        (Some(base), Some(overrides)) => Some([base.clone(), overrides.clone()].concat()),
+        (base, overrides) => overrides.clone().or_else(|| base.clone()),
+    };
+    SettingsOutputFilters {
+        rules,
+        extensions: overrides.extensions.or(base.extensions),
+        moderation_model: overrides
+            .moderation_model
+            .clone()
+            .or_else(|| base.moderation_model.clone()),
+        fail_closed: overrides.fail_closed.or(base.fail_closed),
+    }
+}
+
+fn merge_sandbox(base: &SettingsSandbox, overrides: &SettingsSandbox) -> SettingsSandbox {
+    let allowed_paths = match (&base.allowed_paths, &overrides.allowed_paths) {
+        (Some(base), Some(overrides)) => Some([base.clone(), overrides.clone()].concat()),
+        (base, overrides) => overrides.clone().or_else(|| base.clone()),
+    };
+    SettingsSandbox {
+        enabled: overrides.enabled.or(base.enabled),
+        allowed_paths,
+        wrap_bash: overrides.wrap_bash.or(base.wrap_bash),
+    }
+}
+
+fn merge_bash_policy(
+    base: &SettingsBashPolicy,
+    overrides: &SettingsBashPolicy,
+) -> SettingsBashPolicy {
+    // Project rules add to the global ones; a project cannot drop a global deny rule.
+    let concat =
+        |base: &Option<Vec<String>>, overrides: &Option<Vec<String>>| match (base, overrides) {
+            (Some(base), Some(overrides)) => Some([base.clone(), overrides.clone()].concat()),
+            (base, overrides) => overrides.clone().or_else(|| base.clone()),
+        };
+    SettingsBashPolicy {
+        allow: concat(&base.allow, &overrides.allow),
+        deny: concat(&base.deny, &overrides.deny),
+        default_action: overrides
+            .default_action
+            .clone()
+            .or_else(|| base.default_action.clone()),
     }
 }
 
+/// Project sandbox settings on top of `global`, when the project is not trusted: it can turn
+/// the sandbox and bash wrapping on but not off, and its allowed paths are ignored.
+fn merge_project_sandbox(
+    global: Option<&SettingsSandbox>,
+    project: Option<&SettingsSandbox>,
+) -> Option<SettingsSandbox> {
+    let Some(project) = project else {
+        return global.cloned();
+    };
+    let global = global.cloned().unwrap_or_default();
+    let enabled = match (global.enabled, project.enabled) {
+        (None, None) => None,
+        (global, project) => Some(global.unwrap_or(true) || project.unwrap_or(false)),
+    };
+    let wrap_bash = match (global.wrap_bash, project.wrap_bash) {
+        (None, None) => None,
+        (global, project) => Some(global.unwrap_or(false) || project.unwrap_or(false)),
+    };
+    Some(SettingsSandbox {
+        enabled,
+        allowed_paths: global.allowed_paths,
+        wrap_bash,
+    })
+}
+
+/// Project bash policy on top of `global`, when the project is not trusted: deny rules add
+/// up, and allow rules and `defaultAction` count only where they cannot let through a command
+/// the global policy would stop.
+fn merge_project_bash_policy(
+    global: Option<&SettingsBashPolicy>,
+    project: Option<&SettingsBashPolicy>,
+) -> Option<SettingsBashPolicy> {
+    let Some(project) = project else {
+        return global.cloned();
+    };
+    let global = global.cloned().unwrap_or_default();
+    let global_default = BashPolicy::from_settings(&global).default_action;
+    let mut merged = SettingsBashPolicy {
+        deny: merge_bash_policy(&global, project).deny,
+        ..global.clone()
+    };
+    // Under a global policy that runs everything, an allow list only narrows it.
+    if global_default == BashPolicyAction::Allow {
+        merged.allow = merge_bash_policy(&global, project).allow;
+    }
+    let project_default = project
+        .default_action
+        .as_deref()
+        .and_then(BashPolicyAction::parse);
+    if project_default.is_some_and(|action| action >= global_default) {
+        merged.default_action = project.default_action.clone();
+    }
+    Some(merged)
+}
+
 fn merge_images(base: &SettingsImages, overrides: &SettingsImages) -> SettingsImages {
     SettingsImages {
         auto_resize: overrides.auto_resize.or(base.auto_resize),
     }
 }
 
+/// Which settings.json `pi config` reads or writes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SettingsScope {
+    /// `settings.json` in the agent dir.
+    Global,
+    /// `.pi/settings.json` in the project; overrides global settings.
+    Project,
+}
+
 pub struct SettingsManager {
     settings_path: Option<PathBuf>,
     project_settings_path: Option<PathBuf>,
@@ -1311,6 +2628,18 @@ impl SettingsManager {
         self.save();
     }
 
+    pub fn get_queue_priority(&self) -> String {
+        self.settings
+            .queue_priority
+            .clone()
+            .unwrap_or_else(|| "steering-first".to_string())
+    }
+
+    pub fn set_queue_priority(&mut self, priority: &str) {
+        self.global_settings.queue_priority = Some(priority.to_string());
+        self.save();
+    }
+
     pub fn get_theme(&self) -> Option<String> {
         self.settings.theme.clone()
     }
@@ -1334,6 +2663,7 @@ impl SettingsManager {
             enabled: self.get_compaction_enabled(),
             reserve_tokens: self.get_compaction_reserve_tokens(),
             keep_recent_tokens: self.get_compaction_keep_recent_tokens(),
+            threshold_percent: self.get_compaction_threshold(),
         }
     }
 
@@ -1360,6 +2690,23 @@ impl SettingsManager {
             .unwrap_or(16_384)
     }
 
+    /// Auto-compaction trigger as a percentage of the context window; values outside
+    /// (0, 100] are ignored.
+    pub fn get_compaction_threshold(&self) -> Option<f64> {
+        self.settings
+            .compaction
+            .as_ref()
+            .and_then(|settings| settings.threshold)
+            .filter(|percent| *percent > 0.0 && *percent <= 100.0)
+    }
+
+    pub fn set_compaction_threshold(&mut self, threshold: Option<f64>) {
+        let mut compaction = self.global_settings.compaction.clone().unwrap_or_default();
+        compaction.threshold = threshold;
+        self.global_settings.compaction = Some(compaction);
+        self.save();
+    }
+
     pub fn get_compaction_keep_recent_tokens(&self) -> i64 {
         self.settings
             .compaction
@@ -1368,6 +2715,21 @@ impl SettingsManager {
             .unwrap_or(20_000)
     }
 
+    pub fn get_compaction_strategy(&self) -> String {
+        self.settings
+            .compaction
+            .as_ref()
+            .and_then(|settings| settings.strategy.clone())
+            .unwrap_or_else(|| SUMMARIZE_STRATEGY.to_string())
+    }
+
+    pub fn set_compaction_strategy(&mut self, strategy: &str) {
+        let mut compaction = self.global_settings.compaction.clone().unwrap_or_default();
+        compaction.strategy = Some(strategy.to_string());
+        self.global_settings.compaction = Some(compaction);
+        self.save();
+    }
+
     pub fn get_branch_summary_settings(&self) -> SettingsBranchSummary {
         SettingsBranchSummary {
             reserve_tokens: self
@@ -1423,6 +2785,15 @@ impl SettingsManager {
         self.save();
     }
 
+    pub fn get_git_checkpoints(&self) -> bool {
+        self.settings.git_checkpoints.unwrap_or(false)
+    }
+
+    pub fn set_git_checkpoints(&mut self, enabled: bool) {
+        self.global_settings.git_checkpoints = Some(enabled);
+        self.save();
+    }
+
     pub fn get_shell_path(&self) -> Option<String> {
         self.settings.shell_path.clone()
     }
@@ -1432,6 +2803,100 @@ impl SettingsManager {
         self.save();
     }
 
+    /// Tools enabled when `--tools` is not passed.
+    pub fn get_tools(&self) -> Option<Vec<String>> {
+        self.settings.tools.clone()
+    }
+
+    pub fn settings_file(&self, scope: SettingsScope) -> Option<&Path> {
+        match scope {
+            SettingsScope::Global => self.settings_path.as_deref(),
+            SettingsScope::Project => self.project_settings_path.as_deref(),
+        }
+    }
+
+    /// The value at a dotted camelCase key such as `compaction.threshold` (all settings for an
+    /// empty key): merged across files when `scope` is `None`, otherwise from that file alone.
+    pub fn get_value(&self, key: &str, scope: Option<SettingsScope>) -> Option<Value> {
+        let settings = match scope {
+            None => self.settings.clone(),
+            Some(scope) => self
+                .settings_file(scope)
+                .map(load_settings_from_file)
+                .unwrap_or_default(),
+        };
+        let value = serde_json::to_value(settings).ok()?;
+        lookup_setting(&value, key).cloned()
+    }
+
+    /// Writes `value` at `key` in the `scope` file, keeping the rest of the file as is;
+    /// `null` removes the key.
+    pub fn set_value(
+        &mut self,
+        key: &str,
+        value: Value,
+        scope: SettingsScope,
+    ) -> Result<(), String> {
+        let path = self
+            .settings_file(scope)
+            .ok_or_else(|| "Settings are not backed by a file".to_string())?
+            .to_path_buf();
+        let mut root = if path.exists() {
+            let content = fs::read_to_string(&path)
+                .map_err(|err| format!("Could not read {}: {err}", path.display()))?;
+            serde_json::from_str(&content)
+                .map_err(|err| format!("Could not parse {}: {err}", path.display()))?
+        } else {
+            Value::Object(Default::default())
+        };
+
+        let (parents, last) = match key.rsplit_once('.') {
+            Some((parents, last)) => (parents.split('.').collect::<Vec<_>>(), last),
+            None => (Vec::new(), key),
+        };
+        let mut current = &mut root;
+        for part in parents {
+            current = current
+                .as_object_mut()
+                .ok_or_else(|| format!("{key} is not inside an object setting"))?
+                .entry(part)
+                .or_insert_with(|| Value::Object(Default::default()));
+        }
+        let object = current
+            .as_object_mut()
+            .ok_or_else(|| format!("{key} is not inside an object setting"))?;
+        let removing = value.is_null();
+        if removing {
+            object.remove(last);
+        } else {
+            object.insert(last.to_string(), value);
+        }
+
+        let settings: Settings = serde_json::from_value(migrate_settings_value(root.clone()))
+            .map_err(|err| format!("Invalid value for {key}: {err}"))?;
+        // Unknown keys are dropped when decoding, so a typo would otherwise save silently.
+        let decoded = serde_json::to_value(&settings).map_err(|err| err.to_string())?;
+        if !removing && lookup_setting(&decoded, key).is_none() {
+            return Err(format!("Unknown setting: {key}"));
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+        let contents = serde_json::to_string_pretty(&root).map_err(|err| err.to_string())?;
+        fs::write(&path, contents)
+            .map_err(|err| format!("Could not write {}: {err}", path.display()))?;
+        if scope == SettingsScope::Global {
+            self.global_settings = settings;
+        }
+        self.refresh_settings();
+        Ok(())
+    }
+
+    pub fn get_environment_snapshot(&self) -> bool {
+        self.settings.environment_snapshot.unwrap_or(false)
+    }
+
     pub fn get_collapse_changelog(&self) -> bool {
         self.settings.collapse_changelog.unwrap_or(false)
     }
@@ -1480,6 +2945,65 @@ impl SettingsManager {
         }
     }
 
+    pub fn get_output_filter_settings(&self) -> SettingsOutputFilters {
+        self.settings.output_filters.clone().unwrap_or_default()
+    }
+
+    pub fn get_tool_update_throttle(&self) -> crate::agent::ToolUpdateThrottle {
+        let defaults = crate::agent::ToolUpdateThrottle::default();
+        let settings = self.settings.tool_updates.clone().unwrap_or_default();
+        crate::agent::ToolUpdateThrottle {
+            interval: settings
+                .interval_ms
+                .map(std::time::Duration::from_millis)
+                .unwrap_or(defaults.interval),
+            max_bytes: settings.max_bytes.unwrap_or(defaults.max_bytes),
+        }
+    }
+
+    pub fn get_bash_policy_settings(&self) -> SettingsBashPolicy {
+        self.settings.bash_policy.clone().unwrap_or_default()
+    }
+
+    /// Tools whose changes the interactive UI asks about before they are written.
+    pub fn get_review_edit_tools(&self) -> Vec<String> {
+        self.settings.review_edits.clone().unwrap_or_default()
+    }
+
+    pub fn get_sandbox_settings(&self) -> SettingsSandbox {
+        self.settings.sandbox.clone().unwrap_or_default()
+    }
+
+    pub fn get_telemetry_settings(&self) -> SettingsTelemetry {
+        self.settings.telemetry.clone().unwrap_or_default()
+    }
+
+    pub fn get_spend_limits(&self) -> SpendLimits {
+        let budget = self.settings.budget.clone().unwrap_or_default();
+        SpendLimits {
+            max_cost: budget.max_cost,
+            max_daily_cost: budget.max_daily_cost,
+        }
+    }
+
+    pub fn get_context_dir_settings(&self) -> SettingsContextDir {
+        self.settings.context_dir.clone().unwrap_or_default()
+    }
+
+    pub fn get_prompt_overrides(&self) -> PromptOverrides {
+        let settings = self.settings.system_prompt.clone().unwrap_or_default();
+        PromptOverrides {
+            order: settings.order.unwrap_or_default(),
+            sections: settings.sections.unwrap_or_default(),
+            tool_descriptions: settings.tool_descriptions.unwrap_or_default(),
+        }
+    }
+
+    /// Whether provider requests are logged to `~/.pi/logs/requests.jsonl`.
+    pub fn get_log_requests(&self) -> bool {
+        self.settings.log_requests.unwrap_or(false)
+    }
+
     pub fn get_show_images(&self) -> bool {
         self.settings
             .terminal
@@ -1495,6 +3019,21 @@ impl SettingsManager {
         self.save();
     }
 
+    pub fn get_mouse_scroll(&self) -> bool {
+        self.settings
+            .terminal
+            .as_ref()
+            .and_then(|terminal| terminal.mouse_scroll)
+            .unwrap_or(false)
+    }
+
+    pub fn set_mouse_scroll(&mut self, enabled: bool) {
+        let mut terminal = self.global_settings.terminal.clone().unwrap_or_default();
+        terminal.mouse_scroll = Some(enabled);
+        self.global_settings.terminal = Some(terminal);
+        self.save();
+    }
+
     pub fn get_image_auto_resize(&self) -> bool {
         self.settings
             .images
@@ -1526,6 +3065,32 @@ impl SettingsManager {
         self.save();
     }
 
+    pub fn get_export_custom_messages(&self) -> CustomMessageDisplay {
+        self.settings
+            .export_custom_messages
+            .as_deref()
+            .and_then(CustomMessageDisplay::parse)
+            .unwrap_or_default()
+    }
+
+    pub fn get_seed(&self) -> Option<u64> {
+        self.settings.seed
+    }
+
+    pub fn set_seed(&mut self, seed: Option<u64>) {
+        self.global_settings.seed = seed;
+        self.save();
+    }
+
+    /// Credential profile to use for provider keys; set in a project's settings to give
+    /// each workspace its own default.
+    pub fn get_auth_profile(&self) -> Option<String> {
+        self.settings
+            .auth_profile
+            .clone()
+            .filter(|profile| !profile.trim().is_empty())
+    }
+
     pub fn is_compaction_enabled(&self) -> bool {
         self.get_compaction_enabled()
     }
@@ -1540,7 +3105,20 @@ impl SettingsManager {
             .as_ref()
             .map(|path| load_settings_from_file(path))
             .unwrap_or_default();
-        self.settings = merge_settings(&self.global_settings, &project_settings);
+        let mut settings = merge_settings(&self.global_settings, &project_settings);
+        let trusted = self.global_settings.trust_project_settings;
+        if !trusted.unwrap_or(false) {
+            settings.sandbox = merge_project_sandbox(
+                self.global_settings.sandbox.as_ref(),
+                project_settings.sandbox.as_ref(),
+            );
+            settings.bash_policy = merge_project_bash_policy(
+                self.global_settings.bash_policy.as_ref(),
+                project_settings.bash_policy.as_ref(),
+            );
+        }
+        settings.trust_project_settings = trusted;
+        self.settings = settings;
     }
 
     fn save(&mut self) {
@@ -1579,6 +3157,7 @@ pub struct CompactionOverrides {
     pub enabled: Option<bool>,
     pub reserve_tokens: Option<i64>,
     pub keep_recent_tokens: Option<i64>,
+    pub threshold: Option<f64>,
 }
 
 pub struct SettingsOverrides {
@@ -1592,12 +3171,22 @@ impl SettingsOverrides {
                 enabled: compaction.enabled,
                 reserve_tokens: compaction.reserve_tokens,
                 keep_recent_tokens: compaction.keep_recent_tokens,
+                strategy: None,
+                threshold: compaction.threshold,
             }),
             ..Settings::default()
         }
     }
 }
 
+fn lookup_setting<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    if key.is_empty() {
+        return Some(value);
+    }
+    key.split('.')
+        .try_fold(value, |value, part| value.get(part))
+}
+
 fn normalize_cwd(input: String) -> Option<PathBuf> {
     if !input.trim().is_empty() {
         return Some(PathBuf::from(input));
@@ -1674,6 +3263,16 @@ pub struct BranchCandidate {
     pub text: String,
 }
 
+/// Result of [`AgentSession::branch_from`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct BranchCheckout {
+    pub entry_id: String,
+    pub session_id: String,
+    /// The child session file; `None` for in-memory sessions.
+    pub session_file: Option<PathBuf>,
+    pub parent_session: Option<PathBuf>,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct BranchResult {
     pub selected_text: String,
@@ -1694,6 +3293,16 @@ pub struct NavigateTreeResult {
     pub summary_entry: Option<BranchSummaryEntry>,
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub struct RewindResult {
+    pub checkpoint: Checkpoint,
+    /// File changes undone to get back to the checkpoint, newest first.
+    pub undone: Vec<FileChange>,
+    /// False when the journal no longer holds the changes the checkpoint saw (after a
+    /// restart or a rewind past them), so files were left as they are.
+    pub files_restored: bool,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct AgentSessionState {
     pub model: crate::agent::Model,
@@ -1732,10 +3341,29 @@ pub struct SessionStats {
     pub tool_calls: usize,
     pub tool_results: usize,
     pub total_messages: usize,
+    /// Totals of the diff blocks returned by file-changing tools.
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    /// Runs stopped by a turn or time limit.
+    pub limits_reached: usize,
     pub tokens: TokenStats,
     pub cost: f64,
 }
 
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ContextUsage {
+    pub tokens: i64,
+    /// Part of `tokens` the provider reported with the last reply.
+    pub reported_tokens: i64,
+    /// Part of `tokens` estimated for the messages since.
+    pub estimated_tokens: i64,
+    pub tokenizer: Tokenizer,
+    pub context_window: Option<i64>,
+    pub percent: Option<f64>,
+    /// Context size at which auto-compaction triggers, when the window is known.
+    pub compaction_threshold: Option<i64>,
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct ExportResult {
     pub path: PathBuf,
@@ -1869,83 +3497,6 @@ fn summarize_entries(entries: &[SessionEntry], custom_instructions: Option<&str>
     summary
 }
 
-fn clip_words(text: &str, max_words: usize) -> String {
-    let mut words = text.split_whitespace();
-    let mut kept = Vec::new();
-    for _ in 0..max_words {
-        if let Some(word) = words.next() {
-            kept.push(word);
-        } else {
-            break;
-        }
-    }
-    kept.join(" ")
-}
-
-fn summarize_compaction_messages(
-    messages: &[CoreAgentMessage],
-    custom_instructions: Option<&str>,
-) -> String {
-    let mut parts = Vec::new();
-    for message in messages {
-        match message {
-            CoreAgentMessage::User(user) => {
-                let text = extract_user_text(&user.content);
-                if !text.is_empty() {
-                    parts.push(text);
-                }
-            }
-            CoreAgentMessage::Assistant(assistant) => {
-                let mut text = String::new();
-                for block in &assistant.content {
-                    if let crate::core::messages::ContentBlock::Text { text: chunk, .. } = block {
-                        text.push_str(chunk);
-                    }
-                }
-                if !text.is_empty() {
-                    parts.push(text);
-                }
-            }
-            CoreAgentMessage::ToolResult(result) => {
-                let mut text = String::new();
-                for block in &result.content {
-                    if let crate::core::messages::ContentBlock::Text { text: chunk, .. } = block {
-                        text.push_str(chunk);
-                    }
-                }
-                if !text.is_empty() {
-                    parts.push(text);
-                }
-            }
-            CoreAgentMessage::HookMessage(hook) => {
-                let text = extract_user_text(&hook.content);
-                if !text.is_empty() {
-                    parts.push(text);
-                }
-            }
-            CoreAgentMessage::BranchSummary(summary) => parts.push(summary.summary.clone()),
-            CoreAgentMessage::CompactionSummary(summary) => parts.push(summary.summary.clone()),
-            CoreAgentMessage::BashExecution(bash) => {
-                if !bash.output.is_empty() {
-                    parts.push(bash.output.clone());
-                }
-            }
-        }
-    }
-
-    if parts.is_empty() {
-        return String::new();
-    }
-
-    let merged = parts.join(" ");
-    let mut summary = format!("Summary: {}", clip_words(&merged, 32));
-    if let Some(instructions) = custom_instructions {
-        summary.push(' ');
-        summary.push_str(&clip_words(instructions, 6));
-    }
-    summary
-}
-
 fn wrap_tools_with_extension_host(
     tools: Vec<AgentTool>,
     host: Rc<RefCell<ExtensionHost>>,
@@ -1956,6 +3507,7 @@ fn wrap_tools_with_extension_host(
             let tool_name = tool.name.clone();
             let label = tool.label.clone();
             let description = tool.description.clone();
+            let parameters = tool.parameters.clone();
             let execute = tool.execute.clone();
             let host_ref = host.clone();
 
@@ -1963,7 +3515,8 @@ fn wrap_tools_with_extension_host(
                 name: tool_name.clone(),
                 label,
                 description,
-                execute: Rc::new(move |tool_call_id, args| {
+                parameters,
+                execute: Rc::new(move |tool_call_id, args, on_update| {
                     let call_result = match host_ref
                         .borrow_mut()
                         .emit_tool_call(&tool_name, tool_call_id, args)
@@ -1979,10 +3532,10 @@ fn wrap_tools_with_extension_host(
                         let reason = call_result.reason.unwrap_or_else(|| {
                             "Tool execution was blocked by an extension".to_string()
                         });
-                        return Err(reason);
+                        return Err(reason.into());
                     }
 
-                    match (execute)(tool_call_id, args) {
+                    match (execute)(tool_call_id, args, on_update) {
                         Ok(result) => {
                             let override_result = match host_ref.borrow_mut().emit_tool_result(
                                 &tool_name,
@@ -2004,7 +3557,7 @@ fn wrap_tools_with_extension_host(
                         }
                         Err(err) => {
                             let error_content = vec![ContentBlock::Text {
-                                text: err.clone(),
+                                text: err.message.clone(),
                                 text_signature: None,
                             }];
                             let _ = host_ref.borrow_mut().emit_tool_result(
@@ -2012,7 +3565,7 @@ fn wrap_tools_with_extension_host(
                                 tool_call_id,
                                 args,
                                 &error_content,
-                                &Value::Null,
+                                &err.details,
                                 true,
                             );
                             Err(err)
@@ -38,6 +38,7 @@ pub struct AuthStorage {
     data: HashMap<String, AuthCredential>,
     runtime_overrides: HashMap<String, String>,
     fallback_resolver: Option<FallbackResolver>,
+    active_profile: Option<String>,
 }
 
 impl AuthStorage {
@@ -47,6 +48,7 @@ impl AuthStorage {
             data: HashMap::new(),
             runtime_overrides: HashMap::new(),
             fallback_resolver: None,
+            active_profile: None,
         };
         storage.reload();
         storage
@@ -65,6 +67,59 @@ impl AuthStorage {
         self.fallback_resolver = Some(Box::new(resolver));
     }
 
+    /// Select a named credential profile. Profile credentials are stored in auth.json under
+    /// `provider:profile`; providers without one fall back to the unnamed credential.
+    pub fn set_active_profile(&mut self, profile: Option<&str>) {
+        self.active_profile = profile
+            .map(str::trim)
+            .filter(|profile| !profile.is_empty())
+            .map(str::to_string);
+    }
+
+    pub fn active_profile(&self) -> Option<&str> {
+        self.active_profile.as_deref()
+    }
+
+    /// Names of all stored profiles, sorted.
+    pub fn profiles(&self) -> Vec<String> {
+        let mut profiles = self
+            .data
+            .keys()
+            .filter_map(|key| key.split_once(':').map(|(_, profile)| profile.to_string()))
+            .collect::<Vec<_>>();
+        profiles.sort();
+        profiles.dedup();
+        profiles
+    }
+
+    /// Profiles with a stored credential for `provider`, sorted.
+    pub fn provider_profiles(&self, provider: &str) -> Vec<String> {
+        let mut profiles = self
+            .data
+            .keys()
+            .filter_map(|key| {
+                key.split_once(':')
+                    .filter(|(name, _)| *name == provider)
+                    .map(|(_, profile)| profile.to_string())
+            })
+            .collect::<Vec<_>>();
+        profiles.sort();
+        profiles
+    }
+
+    fn storage_key(&self, provider: &str) -> String {
+        match &self.active_profile {
+            Some(profile) => format!("{provider}:{profile}"),
+            None => provider.to_string(),
+        }
+    }
+
+    fn stored(&self, provider: &str) -> Option<&AuthCredential> {
+        self.data
+            .get(&self.storage_key(provider))
+            .or_else(|| self.data.get(provider))
+    }
+
     pub fn reload(&mut self) {
         let path = self.path.clone();
         if !path.exists() {
@@ -83,16 +138,17 @@ impl AuthStorage {
     }
 
     pub fn get(&self, provider: &str) -> Option<&AuthCredential> {
-        self.data.get(provider)
+        self.stored(provider)
     }
 
+    /// Store a credential for the active profile (or the unnamed one when none is active).
     pub fn set(&mut self, provider: &str, credential: AuthCredential) {
-        self.data.insert(provider.to_string(), credential);
+        self.data.insert(self.storage_key(provider), credential);
         let _ = self.save();
     }
 
     pub fn remove(&mut self, provider: &str) {
-        self.data.remove(provider);
+        self.data.remove(&self.storage_key(provider));
         let _ = self.save();
     }
 
@@ -101,14 +157,14 @@ impl AuthStorage {
     }
 
     pub fn has(&self, provider: &str) -> bool {
-        self.data.contains_key(provider)
+        self.stored(provider).is_some()
     }
 
     pub fn has_auth(&self, provider: &str) -> bool {
         if self.runtime_overrides.contains_key(provider) {
             return true;
         }
-        if self.data.contains_key(provider) {
+        if self.stored(provider).is_some() {
             return true;
         }
         if env_api_key(provider).is_some() {
@@ -125,7 +181,7 @@ impl AuthStorage {
             return Some(key.clone());
         }
 
-        match self.data.get(provider) {
+        match self.stored(provider) {
             Some(AuthCredential::ApiKey { key }) => Some(key.clone()),
             Some(AuthCredential::OAuth { access, .. }) => Some(access.clone()),
             None => {
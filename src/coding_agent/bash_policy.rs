@@ -0,0 +1,201 @@
+//! Allow/deny rules for commands run by the bash tool. Rules are glob patterns (`*` matches
+//! anything) checked against the whole command and against each command in a `&&`, `||`,
+//! `;`, `|`, `&` or newline separated list; a pattern without a wildcard also matches
+//! commands that start with it, so `git push` covers `git push origin main`. Rules cannot see
+//! into subshells or command substitutions, so commands with one need approval.
+
+use crate::coding_agent::agent_session::SettingsBashPolicy;
+use glob::Pattern;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+/// Ordered from least to most strict.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BashPolicyAction {
+    Allow,
+    Ask,
+    Deny,
+}
+
+impl BashPolicyAction {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "allow" => Some(Self::Allow),
+            "ask" => Some(Self::Ask),
+            "deny" => Some(Self::Deny),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BashDecision {
+    Allow,
+    /// Blocked, with the reason shown to the model.
+    Deny(String),
+    /// Needs the user's approval before running.
+    Ask(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BashPolicy {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+    /// What to do with commands that no allow rule covers.
+    pub default_action: BashPolicyAction,
+}
+
+impl BashPolicy {
+    /// Without a `defaultAction`, commands outside the allow list need approval when an
+    /// allow list is configured and run otherwise.
+    pub fn from_settings(settings: &SettingsBashPolicy) -> Self {
+        let allow = settings.allow.clone().unwrap_or_default();
+        let default_action = settings
+            .default_action
+            .as_deref()
+            .and_then(BashPolicyAction::parse)
+            .unwrap_or(if allow.is_empty() {
+                BashPolicyAction::Allow
+            } else {
+                BashPolicyAction::Ask
+            });
+        Self {
+            allow,
+            deny: settings.deny.clone().unwrap_or_default(),
+            default_action,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty()
+            && self.deny.is_empty()
+            && self.default_action == BashPolicyAction::Allow
+    }
+
+    pub fn evaluate(&self, command: &str) -> BashDecision {
+        if self.is_empty() {
+            return BashDecision::Allow;
+        }
+        let full = normalize_whitespace(command);
+        let segments = split_segments(command);
+
+        for pattern in &self.deny {
+            if pattern_matches(pattern, &full)
+                || segments
+                    .iter()
+                    .any(|segment| pattern_matches(pattern, segment))
+            {
+                return BashDecision::Deny(format!("matches deny rule \"{pattern}\""));
+            }
+        }
+
+        let uncovered = segments.iter().find(|segment| {
+            !self
+                .allow
+                .iter()
+                .any(|pattern| pattern_matches(pattern, segment))
+        });
+        if let (Some(segment), BashPolicyAction::Deny) = (uncovered, self.default_action) {
+            return BashDecision::Deny(format!("\"{segment}\" is not covered by an allow rule"));
+        }
+        // Rules vouch for the visible commands, not for what a subshell or substitution runs.
+        if runs_hidden_commands(&full, &segments) {
+            return BashDecision::Ask(
+                "runs a subshell or command substitution, which policy rules cannot see into"
+                    .to_string(),
+            );
+        }
+        match uncovered {
+            Some(segment) if self.default_action == BashPolicyAction::Ask => {
+                BashDecision::Ask(format!("\"{segment}\" is not covered by an allow rule"))
+            }
+            _ => BashDecision::Allow,
+        }
+    }
+}
+
+fn normalize_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The commands in a list: split on `&&`, `||`, `;`, `|`, newlines and a background `&`,
+/// leaving the `&` in redirections such as `2>&1` and `&>` alone.
+fn split_segments(command: &str) -> Vec<String> {
+    let chars = command.chars().collect::<Vec<_>>();
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    for (index, &ch) in chars.iter().enumerate() {
+        let separator = match ch {
+            ';' | '|' | '\n' => true,
+            '&' => {
+                let redirect_before = index
+                    .checked_sub(1)
+                    .is_some_and(|before| matches!(chars[before], '>' | '<'));
+                !redirect_before && chars.get(index + 1) != Some(&'>')
+            }
+            _ => false,
+        };
+        if separator {
+            segments.push(std::mem::take(&mut current));
+        } else {
+            current.push(ch);
+        }
+    }
+    segments.push(current);
+    segments
+        .iter()
+        .map(|segment| normalize_whitespace(segment))
+        .filter(|segment| !segment.is_empty())
+        .collect()
+}
+
+/// Whether the command runs something its segments do not show: `$(…)`, backticks,
+/// `<(…)`/`>(…)` process substitution or a `( … )` subshell.
+fn runs_hidden_commands(full: &str, segments: &[String]) -> bool {
+    ["$(", "`", "<(", ">("]
+        .iter()
+        .any(|marker| full.contains(marker))
+        || segments.iter().any(|segment| segment.starts_with('('))
+}
+
+fn pattern_matches(pattern: &str, text: &str) -> bool {
+    let pattern = normalize_whitespace(pattern);
+    let matches = |pattern: &str| Pattern::new(pattern).is_ok_and(|glob| glob.matches(text));
+    matches(&pattern) || matches(&format!("{pattern} *"))
+}
+
+/// Asks the user whether to run a command; receives the command and the reason it needs
+/// approval, and returns whether it may run.
+pub type BashApprovalHandler = Rc<dyn Fn(&str, &str) -> bool>;
+
+/// Shared between the bash tool and the mode driving the session, which installs a handler
+/// while it can prompt the user.
+#[derive(Clone, Default)]
+pub struct BashApproval {
+    handler: Rc<RefCell<Option<BashApprovalHandler>>>,
+}
+
+impl BashApproval {
+    pub fn set_handler(&self, handler: BashApprovalHandler) {
+        self.handler.replace(Some(handler));
+    }
+
+    pub fn clear_handler(&self) {
+        self.handler.replace(None);
+    }
+
+    /// The user's answer, or `None` when no handler is installed.
+    pub fn request(&self, command: &str, reason: &str) -> Option<bool> {
+        let handler = self.handler.borrow().clone()?;
+        Some(handler(command, reason))
+    }
+}
+
+impl fmt::Debug for BashApproval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BashApproval")
+            .field("has_handler", &self.handler.borrow().is_some())
+            .finish()
+    }
+}
@@ -0,0 +1,102 @@
+//! Per-session record of the file writes and edits made by tools, so a bad change can be
+//! undone without relying on git.
+
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileChange {
+    pub path: PathBuf,
+    pub tool_name: String,
+    pub tool_call_id: String,
+    /// Content before the change, or `None` when the tool created the file.
+    pub original: Option<String>,
+    pub diff: String,
+    pub timestamp: i64,
+}
+
+impl FileChange {
+    pub fn new(
+        path: PathBuf,
+        tool_name: &str,
+        tool_call_id: &str,
+        original: Option<String>,
+        diff: String,
+    ) -> Self {
+        Self {
+            path,
+            tool_name: tool_name.to_string(),
+            tool_call_id: tool_call_id.to_string(),
+            original,
+            diff,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_millis() as i64)
+                .unwrap_or(0),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ChangeJournal {
+    changes: Vec<FileChange>,
+}
+
+pub type SharedChangeJournal = Rc<RefCell<ChangeJournal>>;
+
+impl ChangeJournal {
+    pub fn record(&mut self, change: FileChange) {
+        self.changes.push(change);
+    }
+
+    /// Recorded changes, oldest first.
+    pub fn changes(&self) -> &[FileChange] {
+        &self.changes
+    }
+
+    pub fn clear(&mut self) {
+        self.changes.clear();
+    }
+
+    /// Restore the file touched by the most recent change to its content before that change.
+    pub fn undo_last(&mut self) -> Result<FileChange, String> {
+        let change = self
+            .changes
+            .last()
+            .cloned()
+            .ok_or_else(|| "No file changes to undo".to_string())?;
+        restore_file(&change.path, change.original.as_deref())?;
+        self.changes.pop();
+        Ok(change)
+    }
+
+    /// Restore `path` to its content before the first recorded change and forget its changes.
+    /// Returns the reverted changes, oldest first.
+    pub fn revert_file(&mut self, path: &Path) -> Result<Vec<FileChange>, String> {
+        let first = self
+            .changes
+            .iter()
+            .find(|change| change.path == path)
+            .ok_or_else(|| format!("No recorded changes for {}", path.display()))?;
+        restore_file(path, first.original.as_deref())?;
+        let (reverted, kept) = self
+            .changes
+            .drain(..)
+            .partition(|change| change.path == path);
+        self.changes = kept;
+        Ok(reverted)
+    }
+}
+
+fn restore_file(path: &Path, original: Option<&str>) -> Result<(), String> {
+    match original {
+        Some(content) => fs::write(path, content)
+            .map_err(|err| format!("Failed to restore {}: {err}", path.display())),
+        None if path.exists() => fs::remove_file(path)
+            .map_err(|err| format!("Failed to remove {}: {err}", path.display())),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,240 @@
+//! Project files preloaded at session start (`--context-dir`, the `contextDir` setting), so
+//! that a session can begin with the relevant sources without listing them as @file arguments.
+
+use glob::Pattern;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Used when neither `--context-dir` nor settings give a byte budget.
+pub const DEFAULT_CONTEXT_MAX_BYTES: usize = 100_000;
+
+/// Directories that are never walked.
+const SKIPPED_DIRS: [&str; 3] = [".git", "node_modules", "target"];
+
+/// How many leading bytes are checked for NUL to tell binary files apart.
+const BINARY_SNIFF_BYTES: usize = 8192;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContextDirOptions {
+    pub dir: PathBuf,
+    /// Globs matched against paths relative to `dir`; empty includes every file.
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub max_bytes: usize,
+}
+
+/// Where the preloaded files go.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ContextPlacement {
+    #[default]
+    SystemPrompt,
+    /// Ahead of the first prompt, like @file arguments. Falls back to the system prompt when
+    /// the session starts without one.
+    FirstMessage,
+}
+
+impl ContextPlacement {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "systemPrompt" | "system" => Some(Self::SystemPrompt),
+            "firstMessage" | "message" => Some(Self::FirstMessage),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ContextFileStatus {
+    Included,
+    /// Cut to `kept` bytes because the budget ran out inside it.
+    Truncated {
+        kept: usize,
+    },
+    Binary,
+    /// Left out because the budget was already spent.
+    OverBudget,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContextDirFile {
+    /// Relative to the context directory, with `/` separators.
+    pub path: String,
+    pub bytes: usize,
+    pub status: ContextFileStatus,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ContextDirResult {
+    /// `<file>` blocks for the included files, in path order; empty when nothing matched.
+    pub text: String,
+    pub files: Vec<ContextDirFile>,
+}
+
+impl ContextDirResult {
+    /// One line for the user about what was preloaded and what was left out.
+    pub fn report(&self) -> String {
+        let mut included = 0;
+        let mut bytes = 0;
+        let mut truncated = Vec::new();
+        let mut binary = 0;
+        let mut over_budget = 0;
+        for file in &self.files {
+            match file.status {
+                ContextFileStatus::Included => {
+                    included += 1;
+                    bytes += file.bytes;
+                }
+                ContextFileStatus::Truncated { kept } => {
+                    included += 1;
+                    bytes += kept;
+                    truncated.push(file.path.as_str());
+                }
+                ContextFileStatus::Binary => binary += 1,
+                ContextFileStatus::OverBudget => over_budget += 1,
+            }
+        }
+        let mut report = format!("Context: {included} files ({bytes} bytes) preloaded");
+        if !truncated.is_empty() {
+            report.push_str(&format!("; truncated {}", truncated.join(", ")));
+        }
+        if binary > 0 {
+            report.push_str(&format!("; skipped {binary} binary"));
+        }
+        if over_budget > 0 {
+            report.push_str(&format!("; {over_budget} over the byte budget"));
+        }
+        report
+    }
+}
+
+/// Reads the files under `options.dir` that match the globs, in path order, until
+/// `max_bytes` of content has been taken. The file that crosses the budget is truncated at a
+/// line break and the rest are skipped.
+pub fn collect_context_dir(options: &ContextDirOptions) -> Result<ContextDirResult, String> {
+    if !options.dir.is_dir() {
+        return Err(format!(
+            "Context directory not found: {}",
+            options.dir.display()
+        ));
+    }
+    let include = compile_patterns(&options.include)?;
+    let exclude = compile_patterns(&options.exclude)?;
+
+    let mut paths = Vec::new();
+    walk(&options.dir, &options.dir, &mut paths);
+    paths.sort();
+
+    let mut result = ContextDirResult::default();
+    let mut remaining = options.max_bytes;
+    for relative in paths {
+        let matches = |patterns: &[Pattern]| patterns.iter().any(|p| p.matches(&relative));
+        if (!include.is_empty() && !matches(&include)) || matches(&exclude) {
+            continue;
+        }
+        let path = options.dir.join(&relative);
+        let Ok(data) = fs::read(&path) else {
+            continue;
+        };
+        let bytes = data.len();
+        let content = match String::from_utf8(data) {
+            Ok(content) if !content.as_bytes()[..bytes.min(BINARY_SNIFF_BYTES)].contains(&0) => {
+                content
+            }
+            _ => {
+                result.files.push(ContextDirFile {
+                    path: relative,
+                    bytes,
+                    status: ContextFileStatus::Binary,
+                });
+                continue;
+            }
+        };
+        if content.trim().is_empty() {
+            continue;
+        }
+        let status = if remaining == 0 {
+            ContextFileStatus::OverBudget
+        } else if bytes <= remaining {
+            remaining -= bytes;
+            push_file(&mut result.text, &path, &content, None);
+            ContextFileStatus::Included
+        } else {
+            let kept = truncate_at_line(&content, remaining);
+            remaining = 0;
+            push_file(
+                &mut result.text,
+                &path,
+                &content[..kept],
+                Some(bytes - kept),
+            );
+            ContextFileStatus::Truncated { kept }
+        };
+        result.files.push(ContextDirFile {
+            path: relative,
+            bytes,
+            status,
+        });
+    }
+    Ok(result)
+}
+
+fn compile_patterns(patterns: &[String]) -> Result<Vec<Pattern>, String> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            Pattern::new(pattern).map_err(|err| format!("Invalid context glob {pattern}: {err}"))
+        })
+        .collect()
+}
+
+fn walk(root: &Path, dir: &Path, paths: &mut Vec<String>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            let name = entry.file_name();
+            if !SKIPPED_DIRS.iter().any(|skipped| name == *skipped) {
+                walk(root, &path, paths);
+            }
+        } else if path.is_file() {
+            if let Ok(relative) = path.strip_prefix(root) {
+                let parts = relative
+                    .components()
+                    .map(|part| part.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>();
+                paths.push(parts.join("/"));
+            }
+        }
+    }
+}
+
+/// The longest prefix of `content` within `max` bytes that ends at a line break, or at a
+/// character boundary when the first line alone is longer.
+fn truncate_at_line(content: &str, max: usize) -> usize {
+    let mut end = max.min(content.len());
+    while !content.is_char_boundary(end) {
+        end -= 1;
+    }
+    match content[..end].rfind('\n') {
+        Some(newline) => newline + 1,
+        None => end,
+    }
+}
+
+/// The same `<file>` wrapper as @file arguments.
+fn push_file(text: &mut String, path: &Path, content: &str, truncated: Option<usize>) {
+    text.push_str(&format!("<file name=\"{}\">\n", path.display()));
+    text.push_str(content);
+    if !content.ends_with('\n') {
+        text.push('\n');
+    }
+    if let Some(omitted) = truncated {
+        text.push_str(&format!("[... truncated {omitted} bytes]\n"));
+    }
+    text.push_str("</file>\n");
+}
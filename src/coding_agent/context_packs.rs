@@ -0,0 +1,95 @@
+use crate::config;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Auxiliary context file that can be toggled into the system prompt per session.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContextPack {
+    pub name: String,
+    pub path: PathBuf,
+    pub content: String,
+    /// Estimated token count (4 characters per token, as in compaction).
+    pub tokens: i64,
+    pub source: String,
+}
+
+impl ContextPack {
+    pub fn new(name: &str, path: PathBuf, content: String, source: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            path,
+            tokens: content.len().div_ceil(4) as i64,
+            content,
+            source: source.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct LoadContextPacksOptions {
+    pub cwd: Option<PathBuf>,
+    pub agent_dir: Option<PathBuf>,
+}
+
+/// Load context packs from `<agent dir>/context` and `<cwd>/.pi/context`; project files win on name clashes.
+pub fn load_context_packs(options: LoadContextPacksOptions) -> Vec<ContextPack> {
+    let cwd = options
+        .cwd
+        .or_else(|| env::current_dir().ok())
+        .unwrap_or_else(|| PathBuf::from("."));
+    let agent_dir = options.agent_dir.unwrap_or_else(config::get_agent_dir);
+
+    let mut packs = load_context_packs_from_dir(&agent_dir.join("context"), "user");
+    let project = load_context_packs_from_dir(
+        &cwd.join(config::config_dir_name()).join("context"),
+        "project",
+    );
+    for pack in project {
+        packs.retain(|existing| existing.name != pack.name);
+        packs.push(pack);
+    }
+    packs.sort_by(|a, b| a.name.cmp(&b.name));
+    packs
+}
+
+pub fn find_context_pack<'a>(packs: &'a [ContextPack], name: &str) -> Option<&'a ContextPack> {
+    packs.iter().find(|pack| pack.name == name)
+}
+
+/// System prompt section listing the enabled packs, or an empty string when there are none.
+pub fn format_context_packs_for_prompt(packs: &[ContextPack]) -> String {
+    if packs.is_empty() {
+        return String::new();
+    }
+    let mut section = String::from("\n\n# Additional Context\n\n");
+    section.push_str("The following context files have been enabled for this session:\n\n");
+    for pack in packs {
+        section.push_str(&format!("## {}\n\n{}\n\n", pack.name, pack.content.trim()));
+    }
+    section.trim_end().to_string()
+}
+
+fn load_context_packs_from_dir(dir: &Path, source: &str) -> Vec<ContextPack> {
+    let mut packs = Vec::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return packs;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let Some(name) = file_name.strip_suffix(".md") else {
+            continue;
+        };
+        if !path.is_file() {
+            continue;
+        }
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        packs.push(ContextPack::new(name, path.clone(), content, source));
+    }
+
+    packs
+}
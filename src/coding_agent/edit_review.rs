@@ -0,0 +1,74 @@
+//! Review of file changes before the write and edit tools touch disk. Tools listed in the
+//! `reviewEdits` setting ask the mode driving the session, which shows the diff and lets the
+//! user accept, reject (with a note for the model) or rewrite the change. Modes that cannot
+//! ask, such as print and RPC mode, install no handler, so changes apply as before.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+/// A change a tool wants to make.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EditProposal {
+    pub tool: String,
+    /// The path as the model gave it.
+    pub path: String,
+    /// Unified diff against the current file; empty for a new file.
+    pub diff: String,
+    /// The full file content the tool would write.
+    pub content: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EditDecision {
+    Accept,
+    /// Leave the file alone; the note, if any, is passed on to the model.
+    Reject(Option<String>),
+    /// Write this content instead of the proposed one.
+    Replace(String),
+}
+
+pub type EditReviewHandler = Rc<dyn Fn(&EditProposal) -> EditDecision>;
+
+/// Shared between the file tools and the mode driving the session, which installs a handler
+/// while it can show the user a diff.
+#[derive(Clone, Default)]
+pub struct EditReview {
+    handler: Rc<RefCell<Option<EditReviewHandler>>>,
+}
+
+impl EditReview {
+    pub fn set_handler(&self, handler: EditReviewHandler) {
+        self.handler.replace(Some(handler));
+    }
+
+    pub fn clear_handler(&self) {
+        self.handler.replace(None);
+    }
+
+    /// The user's decision, or `None` when no handler is installed.
+    pub fn request(&self, proposal: &EditProposal) -> Option<EditDecision> {
+        let handler = self.handler.borrow().clone()?;
+        Some(handler(proposal))
+    }
+}
+
+impl fmt::Debug for EditReview {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EditReview")
+            .field("has_handler", &self.handler.borrow().is_some())
+            .finish()
+    }
+}
+
+/// The tool error a rejected change turns into.
+pub fn rejection_message(proposal: &EditProposal, note: Option<&str>) -> String {
+    let mut message = format!(
+        "The user rejected this {} to {}; the file was not changed.",
+        proposal.tool, proposal.path
+    );
+    if let Some(note) = note.map(str::trim).filter(|note| !note.is_empty()) {
+        message.push_str(&format!(" Their note: {note}"));
+    }
+    message
+}
@@ -0,0 +1,148 @@
+//! Opt-in environment preamble for the system prompt (`"environmentSnapshot": true`): the
+//! OS, the shell the bash tool runs, toolchain versions for the project's languages and the
+//! git branch, captured when the session's system prompt is built.
+
+use crate::coding_agent::git::run_git;
+use crate::coding_agent::Shell;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long a single version probe may take before it is left out.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Project marker files and the version commands probed when one is present in the cwd.
+const TOOLCHAIN_PROBES: &[(&[&str], &str, &[&str])] = &[
+    (&["Cargo.toml"], "rustc", &["--version"]),
+    (&["Cargo.toml"], "cargo", &["--version"]),
+    (&["package.json"], "node", &["--version"]),
+    (&["bun.lockb", "bun.lock"], "bun", &["--version"]),
+    (&["deno.json", "deno.jsonc"], "deno", &["--version"]),
+    (
+        &["pyproject.toml", "requirements.txt", "setup.py"],
+        "python3",
+        &["--version"],
+    ),
+    (&["uv.lock"], "uv", &["--version"]),
+    (&["go.mod"], "go", &["version"]),
+    (&["Gemfile"], "ruby", &["--version"]),
+    (
+        &["pom.xml", "build.gradle", "build.gradle.kts"],
+        "java",
+        &["-version"],
+    ),
+    (&["mix.exs"], "elixir", &["--version"]),
+    (&["CMakeLists.txt"], "cmake", &["--version"]),
+];
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EnvironmentSnapshot {
+    pub os: String,
+    pub arch: String,
+    pub shell: String,
+    /// `(command, first line of its version output)` for each toolchain that answered.
+    pub toolchains: Vec<(String, String)>,
+    pub git_branch: Option<String>,
+}
+
+/// Probes run in parallel, so capturing takes at most [`PROBE_TIMEOUT`].
+pub fn capture_environment(cwd: &Path, shell: &Shell) -> EnvironmentSnapshot {
+    let probes = TOOLCHAIN_PROBES
+        .iter()
+        .filter(|(markers, _, _)| markers.iter().any(|marker| cwd.join(marker).exists()))
+        .map(|(_, program, args)| (*program, *args))
+        .collect::<Vec<_>>();
+    let (sender, receiver) = mpsc::channel();
+    for (index, (program, args)) in probes.iter().enumerate() {
+        let sender = sender.clone();
+        let (program, args, cwd) = (*program, *args, cwd.to_path_buf());
+        thread::spawn(move || {
+            let _ = sender.send((index, probe_version(program, args, &cwd)));
+        });
+    }
+    drop(sender);
+
+    let mut versions = vec![None; probes.len()];
+    let deadline = Instant::now() + PROBE_TIMEOUT;
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        match receiver.recv_timeout(remaining) {
+            Ok((index, version)) => versions[index] = version,
+            Err(_) => break,
+        }
+    }
+
+    EnvironmentSnapshot {
+        os: os_name(),
+        arch: std::env::consts::ARCH.to_string(),
+        shell: shell.program.clone(),
+        toolchains: probes
+            .iter()
+            .zip(versions)
+            .filter_map(|((program, _), version)| Some((program.to_string(), version?)))
+            .collect(),
+        git_branch: run_git(cwd, &["branch", "--show-current"])
+            .ok()
+            .map(|branch| branch.trim().to_string())
+            .filter(|branch| !branch.is_empty()),
+    }
+}
+
+/// The `# Environment` section appended to the system prompt.
+pub fn format_environment(snapshot: &EnvironmentSnapshot) -> String {
+    let mut section = String::from("\n\n# Environment\n\n");
+    section.push_str(&format!("- OS: {} ({})\n", snapshot.os, snapshot.arch));
+    section.push_str(&format!("- Shell: {}\n", snapshot.shell));
+    for (program, version) in &snapshot.toolchains {
+        section.push_str(&format!("- {program}: {version}\n"));
+    }
+    if let Some(branch) = &snapshot.git_branch {
+        section.push_str(&format!("- Git branch: {branch}\n"));
+    }
+    section
+}
+
+fn probe_version(program: &str, args: &[&str], cwd: &Path) -> Option<String> {
+    let output = Command::new(program)
+        .args(args)
+        .current_dir(cwd)
+        .stdin(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    // Some tools (java) print their version to stderr.
+    let text = if output.stdout.iter().all(u8::is_ascii_whitespace) {
+        String::from_utf8_lossy(&output.stderr).to_string()
+    } else {
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+    text.lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
+}
+
+fn os_name() -> String {
+    let os = std::env::consts::OS;
+    let release = match os {
+        "linux" => fs::read_to_string("/etc/os-release")
+            .ok()
+            .and_then(|content| {
+                content.lines().find_map(|line| {
+                    line.strip_prefix("PRETTY_NAME=")
+                        .map(|name| name.trim_matches('"').to_string())
+                })
+            }),
+        "macos" => Command::new("sw_vers")
+            .arg("-productVersion")
+            .output()
+            .ok()
+            .map(|output| format!("macOS {}", String::from_utf8_lossy(&output.stdout).trim())),
+        _ => None,
+    };
+    release.unwrap_or_else(|| os.to_string())
+}
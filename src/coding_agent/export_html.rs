@@ -1,9 +1,12 @@
 use base64::{engine::general_purpose, Engine as _};
+use chrono::Utc;
 use serde::Serialize;
+use serde_json::{Map, Value};
 use std::fs;
 use std::path::{Path, PathBuf};
 
 use crate::agent::AgentState;
+use crate::coding_agent::theme::export_css_vars;
 use crate::core::session_manager::{SessionEntry, SessionHeader, SessionManager};
 
 const DEFAULT_APP_NAME: &str = "pi";
@@ -13,6 +16,7 @@ const TEMPLATE_CSS: &str = include_str!("../assets/export-html/template.css");
 const TEMPLATE_JS: &str = include_str!("../assets/export-html/template.js");
 const MARKED_JS: &str = include_str!("../assets/export-html/vendor/marked.min.js");
 const HIGHLIGHT_JS: &str = include_str!("../assets/export-html/vendor/highlight.min.js");
+const KATEX_JS: &str = include_str!("../assets/export-html/vendor/katex.min.js");
 
 #[derive(Serialize)]
 struct ExportTool {
@@ -20,6 +24,36 @@ struct ExportTool {
     description: String,
 }
 
+/// Which custom messages (extension notes, hook output) an export shows.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CustomMessageDisplay {
+    /// Only those marked for display, as in the interactive UI.
+    #[default]
+    Displayed,
+    All,
+    Hidden,
+}
+
+impl CustomMessageDisplay {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "displayed" => Some(Self::Displayed),
+            "all" => Some(Self::All),
+            "hidden" => Some(Self::Hidden),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Displayed => "displayed",
+            Self::All => "all",
+            Self::Hidden => "hidden",
+        }
+    }
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 struct SessionExportData {
@@ -30,102 +64,45 @@ struct SessionExportData {
     system_prompt: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     tools: Option<Vec<ExportTool>>,
+    custom_messages: CustomMessageDisplay,
+}
+
+/// Theme that follows the reader's light/dark preference instead of a fixed palette.
+pub const AUTO_EXPORT_THEME: &str = "auto";
+
+fn root_css(theme: &str) -> Result<String, String> {
+    let vars = export_css_vars(theme)?
+        .into_iter()
+        .map(|(key, value)| format!("{key}: {value};"))
+        .collect::<Vec<_>>();
+    Ok(format!(":root {{\n      {}\n    }}", vars.join("\n      ")))
 }
 
-struct ExportColors {
-    page_bg: &'static str,
-    card_bg: &'static str,
-    info_bg: &'static str,
-}
-
-const DEFAULT_EXPORT_COLORS: ExportColors = ExportColors {
-    page_bg: "#18181e",
-    card_bg: "#1e1e24",
-    info_bg: "#3c3728",
-};
-
-const DEFAULT_TEXT_COLOR: &str = "#e5e5e7";
-
-const DEFAULT_THEME_COLORS: &[(&str, &str)] = &[
-    ("accent", "#8abeb7"),
-    ("border", "#5f87ff"),
-    ("borderAccent", "#00d7ff"),
-    ("borderMuted", "#505050"),
-    ("success", "#b5bd68"),
-    ("error", "#cc6666"),
-    ("warning", "#ffff00"),
-    ("muted", "#808080"),
-    ("dim", "#666666"),
-    ("text", DEFAULT_TEXT_COLOR),
-    ("thinkingText", "#808080"),
-    ("selectedBg", "#3a3a4a"),
-    ("userMessageBg", "#343541"),
-    ("userMessageText", DEFAULT_TEXT_COLOR),
-    ("customMessageBg", "#2d2838"),
-    ("customMessageText", DEFAULT_TEXT_COLOR),
-    ("customMessageLabel", "#9575cd"),
-    ("toolPendingBg", "#282832"),
-    ("toolSuccessBg", "#283228"),
-    ("toolErrorBg", "#3c2828"),
-    ("toolTitle", DEFAULT_TEXT_COLOR),
-    ("toolOutput", "#808080"),
-    ("mdHeading", "#f0c674"),
-    ("mdLink", "#81a2be"),
-    ("mdLinkUrl", "#666666"),
-    ("mdCode", "#8abeb7"),
-    ("mdCodeBlock", "#b5bd68"),
-    ("mdCodeBlockBorder", "#808080"),
-    ("mdQuote", "#808080"),
-    ("mdQuoteBorder", "#808080"),
-    ("mdHr", "#808080"),
-    ("mdListBullet", "#8abeb7"),
-    ("toolDiffAdded", "#b5bd68"),
-    ("toolDiffRemoved", "#cc6666"),
-    ("toolDiffContext", "#808080"),
-    ("syntaxComment", "#6A9955"),
-    ("syntaxKeyword", "#569CD6"),
-    ("syntaxFunction", "#DCDCAA"),
-    ("syntaxVariable", "#9CDCFE"),
-    ("syntaxString", "#CE9178"),
-    ("syntaxNumber", "#B5CEA8"),
-    ("syntaxType", "#4EC9B0"),
-    ("syntaxOperator", "#D4D4D4"),
-    ("syntaxPunctuation", "#D4D4D4"),
-    ("thinkingOff", "#505050"),
-    ("thinkingMinimal", "#6e6e6e"),
-    ("thinkingLow", "#5f87af"),
-    ("thinkingMedium", "#81a2be"),
-    ("thinkingHigh", "#b294bb"),
-    ("thinkingXhigh", "#d183e8"),
-    ("bashMode", "#b5bd68"),
-];
-
-fn default_theme_vars() -> String {
-    let mut lines = Vec::new();
-    for (key, value) in DEFAULT_THEME_COLORS {
-        lines.push(format!("--{key}: {value};"));
+fn theme_css(theme: &str) -> Result<String, String> {
+    if theme != AUTO_EXPORT_THEME {
+        return root_css(theme);
     }
-    lines.join("\n      ")
+    let light = root_css("light")?.replace("\n", "\n  ");
+    Ok(format!(
+        "{}\n\n    @media (prefers-color-scheme: light) {{\n      {light}\n    }}",
+        root_css("dark")?
+    ))
 }
 
-fn generate_html(session_data: &SessionExportData) -> Result<String, String> {
+fn generate_html(session_data: &SessionExportData, theme: &str) -> Result<String, String> {
     let session_json = serde_json::to_string(session_data)
         .map_err(|err| format!("Failed to serialize session data: {err}"))?;
     let session_data_base64 = general_purpose::STANDARD.encode(session_json);
 
-    let theme_vars = default_theme_vars();
-    let css = TEMPLATE_CSS
-        .replace("{{THEME_VARS}}", &theme_vars)
-        .replace("{{BODY_BG}}", DEFAULT_EXPORT_COLORS.page_bg)
-        .replace("{{CONTAINER_BG}}", DEFAULT_EXPORT_COLORS.card_bg)
-        .replace("{{INFO_BG}}", DEFAULT_EXPORT_COLORS.info_bg);
+    let css = TEMPLATE_CSS.replace("{{THEME_CSS}}", &theme_css(theme)?);
 
     Ok(TEMPLATE_HTML
         .replace("{{CSS}}", &css)
         .replace("{{JS}}", TEMPLATE_JS)
         .replace("{{SESSION_DATA}}", &session_data_base64)
         .replace("{{MARKED_JS}}", MARKED_JS)
-        .replace("{{HIGHLIGHT_JS}}", HIGHLIGHT_JS))
+        .replace("{{HIGHLIGHT_JS}}", HIGHLIGHT_JS)
+        .replace("{{KATEX_JS}}", KATEX_JS))
 }
 
 fn default_output_path(input_path: &Path) -> PathBuf {
@@ -144,6 +121,7 @@ pub fn export_session_to_html(
     session_manager: &SessionManager,
     state: Option<&AgentState>,
     output_path: Option<PathBuf>,
+    options: &ExportOptions,
 ) -> Result<PathBuf, String> {
     let session_file = session_manager
         .get_session_file()
@@ -169,26 +147,159 @@ pub fn export_session_to_html(
         leaf_id: session_manager.get_leaf_id(),
         system_prompt: state.map(|agent_state| agent_state.system_prompt.clone()),
         tools,
+        custom_messages: options.custom_messages,
     };
 
-    let html = generate_html(&session_data)?;
+    let html = generate_html(&session_data, options.theme())?;
     let output = output_path.unwrap_or_else(|| default_output_path(&session_file));
+    write_html(&output, &html)?;
+    Ok(output)
+}
 
+/// Renders the export of a session file without writing it anywhere.
+pub fn render_session_html(input_path: &Path, options: &ExportOptions) -> Result<String, String> {
+    let session_manager = SessionManager::try_open(input_path.to_path_buf(), None)?;
+    let session_data = SessionExportData {
+        header: session_manager.get_header(),
+        entries: session_manager.get_entries(),
+        leaf_id: session_manager.get_leaf_id(),
+        system_prompt: None,
+        tools: None,
+        custom_messages: options.custom_messages,
+    };
+    generate_html(&session_data, options.theme())
+}
+
+fn write_html(output: &Path, html: &str) -> Result<(), String> {
     if let Some(parent) = output.parent() {
         fs::create_dir_all(parent)
             .map_err(|err| format!("Failed to create export directory: {err}"))?;
     }
-    fs::write(&output, html).map_err(|err| format!("Failed to write HTML export: {err}"))?;
-    Ok(output)
+    fs::write(output, html).map_err(|err| format!("Failed to write HTML export: {err}"))
 }
 
 pub fn export_from_file(
     input_path: &Path,
     output_path: Option<PathBuf>,
 ) -> Result<PathBuf, String> {
+    export_from_file_with_options(input_path, output_path, &ExportOptions::default())?
+        .ok_or_else(|| "Nothing to export".to_string())
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ExportOptions {
+    /// Only export entries appended since the last recorded export of this session.
+    pub new_only: bool,
+    /// Where the last exported entry id per session is tracked. Without it, exports are
+    /// neither recorded nor able to resume from a previous one.
+    pub state_path: Option<PathBuf>,
+    pub custom_messages: CustomMessageDisplay,
+    /// `dark`, `light`, `auto` or a custom theme name; defaults to `dark`.
+    pub theme: Option<String>,
+}
+
+impl ExportOptions {
+    fn theme(&self) -> &str {
+        self.theme.as_deref().unwrap_or("dark")
+    }
+}
+
+/// Returns `Ok(None)` when `new_only` is set and nothing was added since the last export.
+pub fn export_from_file_with_options(
+    input_path: &Path,
+    output_path: Option<PathBuf>,
+    options: &ExportOptions,
+) -> Result<Option<PathBuf>, String> {
     if !input_path.exists() {
         return Err(format!("File not found: {}", input_path.display()));
     }
-    let session_manager = SessionManager::open(input_path.to_path_buf(), None);
-    export_session_to_html(&session_manager, None, output_path)
+    let session_manager = SessionManager::try_open(input_path.to_path_buf(), None)?;
+    let session_id = session_manager.get_session_id();
+    let entries = session_manager.get_entries();
+    let last_entry_id = entries.last().map(|entry| entry.id().to_string());
+
+    let since = if options.new_only {
+        options
+            .state_path
+            .as_deref()
+            .and_then(|path| get_last_exported_entry_id(path, &session_id))
+    } else {
+        None
+    };
+    let output = match since {
+        Some(since) => {
+            let start = entries
+                .iter()
+                .position(|entry| entry.id() == since)
+                .map(|index| index + 1)
+                .unwrap_or(0);
+            if start >= entries.len() {
+                return Ok(None);
+            }
+            let session_file = session_manager
+                .get_session_file()
+                .unwrap_or_else(|| input_path.to_path_buf());
+            let session_data = SessionExportData {
+                header: session_manager.get_header(),
+                entries: entries[start..].to_vec(),
+                leaf_id: session_manager.get_leaf_id(),
+                system_prompt: None,
+                tools: None,
+                custom_messages: options.custom_messages,
+            };
+            let output = output_path.unwrap_or_else(|| delta_output_path(&session_file));
+            write_html(&output, &generate_html(&session_data, options.theme())?)?;
+            output
+        }
+        None => export_session_to_html(&session_manager, None, output_path, options)?,
+    };
+
+    if let (Some(state_path), Some(entry_id)) = (options.state_path.as_deref(), last_entry_id) {
+        set_last_exported_entry_id(state_path, &session_id, &entry_id)?;
+    }
+    Ok(Some(output))
+}
+
+fn delta_output_path(session_file: &Path) -> PathBuf {
+    let base = default_output_path(session_file);
+    let stem = base
+        .file_stem()
+        .and_then(|value| value.to_str())
+        .unwrap_or("session")
+        .to_string();
+    base.with_file_name(format!(
+        "{stem}-{}.html",
+        Utc::now().format("%Y%m%d-%H%M%S")
+    ))
+}
+
+fn load_export_state(state_path: &Path) -> Map<String, Value> {
+    fs::read_to_string(state_path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+        .and_then(|value| value.as_object().cloned())
+        .unwrap_or_default()
+}
+
+pub fn get_last_exported_entry_id(state_path: &Path, session_id: &str) -> Option<String> {
+    load_export_state(state_path)
+        .get(session_id)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+pub fn set_last_exported_entry_id(
+    state_path: &Path,
+    session_id: &str,
+    entry_id: &str,
+) -> Result<(), String> {
+    let mut state = load_export_state(state_path);
+    state.insert(session_id.to_string(), Value::String(entry_id.to_string()));
+    if let Some(parent) = state_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|err| format!("Failed to create export state directory: {err}"))?;
+    }
+    let content = serde_json::to_string_pretty(&Value::Object(state))
+        .map_err(|err| format!("Failed to serialize export state: {err}"))?;
+    fs::write(state_path, content).map_err(|err| format!("Failed to write export state: {err}"))
 }
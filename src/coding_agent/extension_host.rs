@@ -1,3 +1,7 @@
+use crate::api::request_hook::{ProviderRequest, RequestHook};
+use crate::coding_agent::extension_permissions::{
+    declared_capabilities, ExtensionPermissions, EXTENSION_CAPABILITIES,
+};
 use crate::coding_agent::hooks::{
     CompactionResult, SessionBeforeCompactEvent, SessionBeforeCompactResult, SessionCompactEvent,
 };
@@ -6,11 +10,13 @@ use crate::core::messages::{AgentMessage, ContentBlock};
 use crate::core::session_manager::SessionEntry;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io::{BufRead, BufReader, Write};
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::rc::Rc;
 
 const EXTENSION_HOST_JS: &str = include_str!(concat!(
     env!("CARGO_MANIFEST_DIR"),
@@ -18,6 +24,8 @@ const EXTENSION_HOST_JS: &str = include_str!(concat!(
 ));
 
 type UiHandler = Box<dyn Fn(&ExtensionUiRequest) -> ExtensionUiResponse>;
+/// Given an extension path and its undecided capabilities, returns the ones to grant.
+pub type CapabilityApprover<'a> = dyn FnMut(&str, &[String]) -> Option<Vec<String>> + 'a;
 
 #[derive(Clone, Debug, PartialEq, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -69,6 +77,14 @@ pub struct ExtensionMetadata {
     pub shortcuts: Vec<ExtensionShortcut>,
     pub message_renderers: Vec<ExtensionMessageRenderer>,
     pub handler_counts: HashMap<String, usize>,
+    /// System prompt sections set with `registerPromptSection`.
+    #[serde(default)]
+    pub prompt_sections: HashMap<String, String>,
+    #[serde(default)]
+    pub tool_descriptions: HashMap<String, String>,
+    /// Capabilities the extension was granted for this session.
+    #[serde(default)]
+    pub capabilities: Vec<String>,
 }
 
 #[derive(Clone, Debug, PartialEq, Deserialize)]
@@ -93,6 +109,8 @@ struct InitRequest<'a> {
     #[serde(rename = "type")]
     kind: &'static str,
     extensions: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    grants: Option<&'a HashMap<String, Vec<String>>>,
 }
 
 #[derive(Serialize)]
@@ -101,8 +119,8 @@ struct EmitRequest<'a> {
     id: u64,
     #[serde(rename = "type")]
     kind: &'static str,
-    event: &'a ExtensionEventPayload<'a>,
-    context: ExtensionContextPayload,
+    event: &'a Value,
+    context: &'a ExtensionContextPayload,
 }
 
 #[derive(Serialize)]
@@ -179,6 +197,8 @@ struct ExtensionEventPayload<'a> {
     details: Option<&'a Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     is_error: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request: Option<&'a ProviderRequest>,
 }
 
 #[derive(Serialize)]
@@ -192,6 +212,7 @@ struct ExtensionCompactionPreparation<'a> {
     previous_summary: Option<&'a str>,
     file_ops: ExtensionFileOperations,
     settings: ExtensionCompactionSettings,
+    pinned_entry_ids: &'a [String],
 }
 
 #[derive(Serialize)]
@@ -223,6 +244,7 @@ struct ExtensionCompactionResult {
 struct ExtensionBeforeCompactResult {
     cancel: Option<bool>,
     compaction: Option<ExtensionCompactionResult>,
+    pin: Option<Vec<String>>,
 }
 
 #[derive(Deserialize)]
@@ -240,6 +262,14 @@ pub struct ExtensionToolCallResult {
     pub reason: Option<String>,
 }
 
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtensionOutputResult {
+    pub block: Option<bool>,
+    pub reason: Option<String>,
+    pub content: Option<Vec<ContentBlock>>,
+}
+
 #[derive(Clone, Debug, Default, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ExtensionToolResult {
@@ -261,6 +291,7 @@ pub struct ExtensionUiRequest {
     pub kind: String,
     pub id: String,
     pub method: String,
+    pub extension_path: Option<String>,
     pub title: Option<String>,
     pub message: Option<String>,
     pub options: Option<Vec<String>>,
@@ -285,38 +316,20 @@ pub struct ExtensionUiResponse {
     pub cancelled: Option<bool>,
 }
 
-pub struct ExtensionHost {
+/// A `node` process running some of the extensions.
+struct HostProcess {
     child: Child,
     stdin: ChildStdin,
     stdout: BufReader<ChildStdout>,
-    next_id: u64,
-    script_path: PathBuf,
-    cwd: String,
-    ui_handler: Option<UiHandler>,
+    /// Handlers per event among this process's extensions.
+    handler_counts: HashMap<String, usize>,
 }
 
-impl ExtensionHost {
-    pub fn spawn(paths: &[PathBuf], cwd: &Path) -> Result<(Self, ExtensionManifest), String> {
-        if paths.is_empty() {
-            return Err("No extension paths provided".to_string());
-        }
-
-        let mut skipped_paths = Vec::new();
-        let mut supported = Vec::new();
-        for path in paths {
-            match path.extension().and_then(|ext| ext.to_str()) {
-                Some("js") | Some("ts") | Some("tsx") => supported.push(path.clone()),
-                _ => skipped_paths.push(path.clone()),
-            }
-        }
-
-        if supported.is_empty() {
-            return Err("No supported extension files found (JS/TS only).".to_string());
-        }
-
-        let script_path = write_host_script()?;
+impl HostProcess {
+    fn spawn(script_path: &Path, cwd: &Path, node_args: &[String]) -> Result<Self, String> {
         let mut child = Command::new("node")
-            .arg(&script_path)
+            .args(node_args)
+            .arg(script_path)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::inherit())
@@ -332,39 +345,299 @@ impl ExtensionHost {
             .stdout
             .take()
             .ok_or_else(|| "Failed to capture extension host stdout".to_string())?;
-        let mut host = ExtensionHost {
+        Ok(Self {
             child,
             stdin,
             stdout: BufReader::new(stdout),
-            next_id: 1,
-            script_path: script_path.clone(),
-            cwd: cwd.to_string_lossy().to_string(),
-            ui_handler: Some(Box::new(default_ui_handler)),
+            handler_counts: HashMap::new(),
+        })
+    }
+
+    fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        self.stdin.write_all(line.as_bytes())?;
+        self.stdin.write_all(b"\n")?;
+        self.stdin.flush()
+    }
+}
+
+/// How the installed Node can restrict a host process, from `node --help`.
+struct NodePermissions {
+    /// `--permission`, or `--experimental-permission` before Node 22.13.
+    flag: &'static str,
+    /// Node 25 added `--allow-net`; older versions cannot restrict network access.
+    network: bool,
+    /// Keeps the experimental permission model's warning off the terminal.
+    disable_warning: bool,
+}
+
+impl NodePermissions {
+    fn detect() -> Result<Self, String> {
+        let output = Command::new("node")
+            .arg("--help")
+            .output()
+            .map_err(|err| format!("Failed to start node extension host: {err}"))?;
+        let help = String::from_utf8_lossy(&output.stdout);
+        let options = help
+            .split_whitespace()
+            .map(|option| option.split('=').next().unwrap_or(option))
+            .collect::<HashSet<_>>();
+        let flag = if options.contains("--permission") {
+            "--permission"
+        } else if options.contains("--experimental-permission") {
+            "--experimental-permission"
+        } else {
+            return Err(
+                "Extension capabilities need Node 20 or newer, which can restrict what extensions access"
+                    .to_string(),
+            );
         };
+        Ok(Self {
+            flag,
+            network: options.contains("--allow-net"),
+            disable_warning: flag == "--experimental-permission"
+                && options.contains("--disable-warning"),
+        })
+    }
+
+    /// Node arguments that hold a host process to `granted`; none when it was granted
+    /// everything Node can restrict. Child processes, workers and native addons stay off
+    /// under the permission model, since any of them could get around it. Without `fs.read`
+    /// the process can still read `readable`, what it needs to load its extensions.
+    fn args(&self, granted: &[String], readable: impl FnOnce() -> Vec<String>) -> Vec<String> {
+        let has = |capability: &str| granted.iter().any(|granted| granted == capability);
+        if has("fs.read") && has("fs.write") && (has("network") || !self.network) {
+            return Vec::new();
+        }
+        let mut args = vec![self.flag.to_string()];
+        if self.disable_warning {
+            args.push("--disable-warning=ExperimentalWarning".to_string());
+        }
+        if has("fs.read") {
+            args.push("--allow-fs-read=*".to_string());
+        } else {
+            args.extend(
+                readable()
+                    .into_iter()
+                    .map(|path| format!("--allow-fs-read={path}")),
+            );
+        }
+        if has("fs.write") {
+            args.push("--allow-fs-write=*".to_string());
+        }
+        if self.network && has("network") {
+            args.push("--allow-net".to_string());
+        }
+        args
+    }
+}
+
+pub struct ExtensionHost {
+    /// Extensions granted the same capabilities share a process.
+    processes: Vec<HostProcess>,
+    next_id: u64,
+    script_path: PathBuf,
+    cwd: String,
+    ui_handler: Option<UiHandler>,
+    handler_counts: HashMap<String, usize>,
+    /// Granted capabilities per extension path; `None` grants everything.
+    grants: Option<HashMap<String, Vec<String>>>,
+    extension_paths: Vec<String>,
+    /// The process that runs each extension tool.
+    tool_processes: HashMap<String, usize>,
+}
 
+impl ExtensionHost {
+    pub fn spawn(paths: &[PathBuf], cwd: &Path) -> Result<(Self, ExtensionManifest), String> {
+        let (supported, skipped_paths) = supported_paths(paths)?;
+        let mut host = Self::new(cwd)?;
         let extension_paths = supported
             .iter()
             .map(|path| path.to_string_lossy().to_string())
             .collect::<Vec<_>>();
+        host.processes
+            .push(HostProcess::spawn(&host.script_path, cwd, &[])?);
+        let (extensions, errors) = host.init(0, &extension_paths, None)?;
+        Ok(host.manifest(extensions, errors, skipped_paths))
+    }
+
+    /// Like [`ExtensionHost::spawn`], but each extension only gets the capabilities the user
+    /// granted it. Capabilities an extension declares (see [`declared_capabilities`]) that
+    /// have not been decided yet are passed to `approve`, which returns the ones to grant, or
+    /// `None` to leave them undecided (and ungranted) for now. Nothing an extension does runs
+    /// before then.
+    ///
+    /// Extensions run in Node processes started under Node's permission model with flags
+    /// built from their grants, so `fs.read`, `fs.write` and `network` hold however the
+    /// extension reaches the filesystem or network. Without `fs.read` an extension can still
+    /// read its own directory and the `node_modules` it loads packages from. Node before 25
+    /// cannot restrict network access; there `network` is not asked about and every
+    /// extension has it.
+    pub fn spawn_with_permissions(
+        paths: &[PathBuf],
+        cwd: &Path,
+        permissions: &mut ExtensionPermissions,
+        approve: &mut CapabilityApprover<'_>,
+    ) -> Result<(Self, ExtensionManifest), String> {
+        let (supported, skipped_paths) = supported_paths(paths)?;
+        let node = NodePermissions::detect()?;
+
+        let mut errors = Vec::new();
+        let mut loadable = Vec::new();
+        let mut changed = false;
+        for path in &supported {
+            let extension = resolve_extension_path(path, cwd);
+            let declared = fs::read_to_string(&extension)
+                .map_err(|err| format!("Failed to read extension: {err}"))
+                .and_then(|source| declared_capabilities(&source));
+            let declared = match declared {
+                Ok(declared) => declared,
+                Err(error) => {
+                    errors.push(ExtensionHostError {
+                        extension_path: path.to_string_lossy().to_string(),
+                        error,
+                        event: None,
+                    });
+                    continue;
+                }
+            };
+            let mut requested = declared.unwrap_or_else(|| {
+                EXTENSION_CAPABILITIES
+                    .iter()
+                    .map(|capability| capability.to_string())
+                    .collect()
+            });
+            if !node.network {
+                if requested.iter().any(|capability| capability == "network") {
+                    eprintln!(
+                        "Warning: {extension} can use the network; Node cannot restrict network access before version 25"
+                    );
+                }
+                requested.retain(|capability| capability != "network");
+            }
+            let undecided = permissions.undecided(&extension, &requested);
+            if !undecided.is_empty() {
+                if let Some(approved) = approve(&extension, &undecided) {
+                    permissions.record(&extension, &requested, &undecided, &approved);
+                    changed = true;
+                }
+            }
+            let mut granted = permissions.granted(&extension, &requested);
+            if !node.network {
+                granted.push("network".to_string());
+            }
+            loadable.push((extension, granted));
+        }
+        if changed {
+            if let Err(err) = permissions.save() {
+                eprintln!("Warning: Failed to save extension permissions: {err}");
+            }
+        }
+
+        let mut host = Self::new(cwd)?;
+        let mut extensions = Vec::new();
+        let same_grants = |a: &(String, Vec<String>), b: &(String, Vec<String>)| {
+            a.1.len() == b.1.len() && a.1.iter().all(|capability| b.1.contains(capability))
+        };
+        for group in loadable.chunk_by(same_grants) {
+            let extension_paths = group
+                .iter()
+                .map(|(extension, _)| extension.clone())
+                .collect::<Vec<_>>();
+            let node_args = node.args(&group[0].1, || {
+                loading_paths(&host.script_path, &extension_paths, cwd)
+            });
+            host.processes
+                .push(HostProcess::spawn(&host.script_path, cwd, &node_args)?);
+            let grants = group.iter().cloned().collect::<HashMap<_, _>>();
+            let (loaded, init_errors) =
+                host.init(host.processes.len() - 1, &extension_paths, Some(&grants))?;
+            extensions.extend(loaded);
+            errors.extend(init_errors);
+        }
+        host.grants = Some(loadable.into_iter().collect());
+        Ok(host.manifest(extensions, errors, skipped_paths))
+    }
+
+    fn new(cwd: &Path) -> Result<Self, String> {
+        Ok(ExtensionHost {
+            processes: Vec::new(),
+            next_id: 1,
+            script_path: write_host_script()?,
+            cwd: cwd.to_string_lossy().to_string(),
+            ui_handler: Some(Box::new(default_ui_handler)),
+            handler_counts: HashMap::new(),
+            grants: None,
+            extension_paths: Vec::new(),
+            tool_processes: HashMap::new(),
+        })
+    }
+
+    /// Loads `extension_paths` in process `index`.
+    fn init(
+        &mut self,
+        index: usize,
+        extension_paths: &[String],
+        grants: Option<&HashMap<String, Vec<String>>>,
+    ) -> Result<(Vec<ExtensionMetadata>, Vec<ExtensionHostError>), String> {
         let request = InitRequest {
-            id: host.next_id(),
+            id: self.next_id(),
             kind: "init",
-            extensions: &extension_paths,
+            extensions: extension_paths,
+            grants,
         };
-        let response = host.send_request(request)?;
+        let response = self.send_request(index, request)?;
         if !response.ok {
             return Err(response
                 .error
                 .unwrap_or_else(|| "Extension host init failed".to_string()));
         }
 
+        let extensions = response.extensions.unwrap_or_default();
+        let process = &mut self.processes[index];
+        for extension in &extensions {
+            for (event, count) in &extension.handler_counts {
+                *process.handler_counts.entry(event.clone()).or_default() += count;
+            }
+            for tool in &extension.tools {
+                self.tool_processes.insert(tool.name.clone(), index);
+            }
+        }
+        Ok((extensions, response.errors.unwrap_or_default()))
+    }
+
+    fn manifest(
+        mut self,
+        extensions: Vec<ExtensionMetadata>,
+        errors: Vec<ExtensionHostError>,
+        skipped_paths: Vec<PathBuf>,
+    ) -> (Self, ExtensionManifest) {
+        for extension in &extensions {
+            for (event, count) in &extension.handler_counts {
+                *self.handler_counts.entry(event.clone()).or_default() += count;
+            }
+        }
+        self.extension_paths = extensions
+            .iter()
+            .map(|extension| extension.path.clone())
+            .collect();
         let manifest = ExtensionManifest {
-            extensions: response.extensions.unwrap_or_default(),
-            errors: response.errors.unwrap_or_default(),
+            extensions,
+            errors,
             skipped_paths,
         };
+        (self, manifest)
+    }
+
+    /// Paths of the extensions that loaded.
+    pub fn extension_paths(&self) -> &[String] {
+        &self.extension_paths
+    }
 
-        Ok((host, manifest))
+    /// Whether any loaded extension registered a handler for `event`.
+    pub fn has_handlers(&self, event: &str) -> bool {
+        self.handler_counts
+            .get(event)
+            .is_some_and(|count| *count > 0)
     }
 
     pub fn emit_before_compact(
@@ -384,6 +657,7 @@ impl ExtensionHost {
             content: None,
             details: None,
             is_error: None,
+            request: None,
         };
         let context = self.build_context(&event.branch_entries);
         let response = self.emit_event(&payload, context)?;
@@ -415,6 +689,7 @@ impl ExtensionHost {
             content: None,
             details: None,
             is_error: None,
+            request: None,
         };
         let context = self.build_context(&[]);
         let response = self.emit_event(&payload, context)?;
@@ -443,6 +718,7 @@ impl ExtensionHost {
             content: None,
             details: None,
             is_error: None,
+            request: None,
         };
         let context = self.build_context(&[]);
         let response = self.emit_event(&payload, context)?;
@@ -473,6 +749,7 @@ impl ExtensionHost {
             content: None,
             details: None,
             is_error: None,
+            request: None,
         };
         let context = self.build_context(&[]);
         let response = self.emit_event(&payload, context)?;
@@ -514,6 +791,7 @@ impl ExtensionHost {
             content: Some(content),
             details: Some(details),
             is_error: Some(is_error),
+            request: None,
         };
         let context = self.build_context(&[]);
         let response = self.emit_event(&payload, context)?;
@@ -533,20 +811,96 @@ impl ExtensionHost {
         Ok(result)
     }
 
-    pub fn set_flag_values(&mut self, flags: &HashMap<String, Value>) -> Result<(), String> {
-        if flags.is_empty() {
-            return Ok(());
+    pub fn emit_assistant_output(
+        &mut self,
+        content: &[ContentBlock],
+    ) -> Result<ExtensionOutputResult, String> {
+        let payload = ExtensionEventPayload {
+            kind: "assistant_output",
+            preparation: None,
+            branch_entries: None,
+            compaction_entry: None,
+            from_extension: None,
+            messages: None,
+            tool_name: None,
+            tool_call_id: None,
+            input: None,
+            content: Some(content),
+            details: None,
+            is_error: None,
+            request: None,
+        };
+        let context = self.build_context(&[]);
+        let response = self.emit_event(&payload, context)?;
+        if !response.ok {
+            return Err(response
+                .error
+                .unwrap_or_else(|| "Extension host emit failed".to_string()));
         }
-        let request = SetFlagsRequest {
-            id: self.next_id(),
-            kind: "set_flags",
-            flags,
+        if let Some(errors) = response.errors.as_deref() {
+            report_extension_errors(errors);
+        }
+        match response.result {
+            Some(Value::Null) | None => Ok(ExtensionOutputResult::default()),
+            Some(value) => serde_json::from_value::<ExtensionOutputResult>(value)
+                .map_err(|err| format!("Failed to parse extension result: {err}")),
+        }
+    }
+
+    /// Let `provider_request` handlers rewrite an outgoing provider request. Returns the
+    /// request as the handlers left it.
+    pub fn emit_provider_request(
+        &mut self,
+        request: &ProviderRequest,
+    ) -> Result<ProviderRequest, String> {
+        let payload = ExtensionEventPayload {
+            kind: "provider_request",
+            preparation: None,
+            branch_entries: None,
+            compaction_entry: None,
+            from_extension: None,
+            messages: None,
+            tool_name: None,
+            tool_call_id: None,
+            input: None,
+            content: None,
+            details: None,
+            is_error: None,
+            request: Some(request),
         };
-        let response = self.send_request(request)?;
+        let context = self.build_context(&[]);
+        let response = self.emit_event(&payload, context)?;
         if !response.ok {
             return Err(response
                 .error
-                .unwrap_or_else(|| "Extension host set_flags failed".to_string()));
+                .unwrap_or_else(|| "Extension host emit failed".to_string()));
+        }
+        if let Some(errors) = response.errors.as_deref() {
+            report_extension_errors(errors);
+        }
+        match response.result {
+            Some(Value::Null) | None => Ok(request.clone()),
+            Some(value) => serde_json::from_value::<ProviderRequest>(value)
+                .map_err(|err| format!("Failed to parse extension result: {err}")),
+        }
+    }
+
+    pub fn set_flag_values(&mut self, flags: &HashMap<String, Value>) -> Result<(), String> {
+        if flags.is_empty() {
+            return Ok(());
+        }
+        for index in 0..self.processes.len() {
+            let request = SetFlagsRequest {
+                id: self.next_id(),
+                kind: "set_flags",
+                flags,
+            };
+            let response = self.send_request(index, request)?;
+            if !response.ok {
+                return Err(response
+                    .error
+                    .unwrap_or_else(|| "Extension host set_flags failed".to_string()));
+            }
         }
         Ok(())
     }
@@ -558,6 +912,23 @@ impl ExtensionHost {
         input: &Value,
         session_entries: &[SessionEntry],
     ) -> Result<ExtensionToolExecuteResult, String> {
+        self.call_tool_with_updates(tool_name, tool_call_id, input, session_entries, &mut |_| {})
+    }
+
+    /// Like [`ExtensionHost::call_tool`], passing the partial results the tool reports through
+    /// its `onUpdate` callback to `on_update` while it runs.
+    pub fn call_tool_with_updates(
+        &mut self,
+        tool_name: &str,
+        tool_call_id: &str,
+        input: &Value,
+        session_entries: &[SessionEntry],
+        on_update: &mut dyn FnMut(ExtensionToolExecuteResult),
+    ) -> Result<ExtensionToolExecuteResult, String> {
+        let index = *self
+            .tool_processes
+            .get(tool_name)
+            .ok_or_else(|| format!("Tool {tool_name} not found"))?;
         let request = InvokeToolRequest {
             id: self.next_id(),
             kind: "invoke_tool",
@@ -566,7 +937,7 @@ impl ExtensionHost {
             input,
             context: self.build_context(session_entries),
         };
-        let response = self.send_request(request)?;
+        let response = self.send_request_with_updates(index, request, Some(on_update))?;
         if !response.ok {
             return Err(response
                 .error
@@ -594,20 +965,31 @@ impl ExtensionHost {
         }
     }
 
-    fn send_request<T: Serialize>(&mut self, request: T) -> Result<HostResponse, String> {
+    fn send_request<T: Serialize>(
+        &mut self,
+        index: usize,
+        request: T,
+    ) -> Result<HostResponse, String> {
+        self.send_request_with_updates(index, request, None)
+    }
+
+    /// Sends `request` to process `index` and waits for its response, answering the UI
+    /// requests and passing on the tool updates that arrive first.
+    fn send_request_with_updates<T: Serialize>(
+        &mut self,
+        index: usize,
+        request: T,
+        mut on_update: Option<&mut dyn FnMut(ExtensionToolExecuteResult)>,
+    ) -> Result<HostResponse, String> {
         let line = serde_json::to_string(&request)
             .map_err(|err| format!("Failed to serialize extension request: {err}"))?;
-        self.stdin
-            .write_all(line.as_bytes())
-            .and_then(|_| self.stdin.write_all(b"\n"))
+        self.processes[index]
+            .write_line(&line)
             .map_err(|err| format!("Failed to send extension request: {err}"))?;
-        self.stdin
-            .flush()
-            .map_err(|err| format!("Failed to flush extension request: {err}"))?;
 
         loop {
             let mut response_line = String::new();
-            let bytes = self
+            let bytes = self.processes[index]
                 .stdout
                 .read_line(&mut response_line)
                 .map_err(|err| format!("Failed to read extension response: {err}"))?;
@@ -620,7 +1002,16 @@ impl ExtensionHost {
                 if kind == "extension_ui_request" {
                     let request = serde_json::from_value::<ExtensionUiRequest>(value)
                         .map_err(|err| format!("Failed to parse extension UI request: {err}"))?;
-                    self.handle_ui_request(&request)?;
+                    self.handle_ui_request(index, &request)?;
+                    continue;
+                }
+                if kind == "tool_update" {
+                    let partial = value.get("partial").cloned().unwrap_or(Value::Null);
+                    match (parse_tool_invoke_result(partial), on_update.as_mut()) {
+                        (Ok(partial), Some(on_update)) => on_update(partial),
+                        (Err(err), _) => eprintln!("Warning: Invalid extension tool update: {err}"),
+                        _ => {}
+                    }
                     continue;
                 }
             }
@@ -630,18 +1021,82 @@ impl ExtensionHost {
         }
     }
 
+    /// Sends the event to each process with handlers for it, in load order, combining their
+    /// results the way the host combines its extensions' handlers: a later process sees
+    /// rewritten content and requests, and a block or cancel stops the rest.
     fn emit_event(
         &mut self,
         payload: &ExtensionEventPayload<'_>,
         context: ExtensionContextPayload,
     ) -> Result<HostResponse, String> {
-        let request = EmitRequest {
-            id: self.next_id(),
-            kind: "emit",
-            event: payload,
-            context,
+        let mut event = serde_json::to_value(payload)
+            .map_err(|err| format!("Failed to serialize extension request: {err}"))?;
+        let stop_field = match payload.kind {
+            "session_before_compact" => Some("cancel"),
+            "tool_call" | "assistant_output" => Some("block"),
+            _ => None,
         };
-        self.send_request(request)
+        let mut result: Option<Value> = None;
+        let mut errors = Vec::new();
+        for index in 0..self.processes.len() {
+            let handles = self.processes[index]
+                .handler_counts
+                .get(payload.kind)
+                .is_some_and(|count| *count > 0);
+            if !handles {
+                continue;
+            }
+            let request = EmitRequest {
+                id: self.next_id(),
+                kind: "emit",
+                event: &event,
+                context: &context,
+            };
+            let response = self.send_request(index, request)?;
+            if !response.ok {
+                return Ok(response);
+            }
+            errors.extend(response.errors.unwrap_or_default());
+            let Some(value) = response.result.filter(|value| !value.is_null()) else {
+                continue;
+            };
+            match payload.kind {
+                "provider_request" => event["request"] = value,
+                "assistant_output" => {
+                    if let Some(content) = value.get("content").filter(|content| content.is_array())
+                    {
+                        event["content"] = content.clone();
+                    }
+                    let mut merged = result
+                        .take()
+                        .unwrap_or_else(|| Value::Object(Default::default()));
+                    if let (Some(merged), Value::Object(value)) = (merged.as_object_mut(), value) {
+                        merged.extend(value);
+                    }
+                    result = Some(merged);
+                }
+                _ => result = Some(value),
+            }
+            let stopped = stop_field.is_some_and(|field| {
+                result
+                    .as_ref()
+                    .and_then(|result| result.get(field))
+                    .is_some_and(|value| value.as_bool() == Some(true))
+            });
+            if stopped {
+                break;
+            }
+        }
+        if payload.kind == "provider_request" {
+            result = event.get("request").cloned();
+        }
+        Ok(HostResponse {
+            ok: true,
+            error: None,
+            result,
+            extensions: None,
+            errors: Some(errors),
+        })
     }
 
     fn next_id(&mut self) -> u64 {
@@ -650,8 +1105,21 @@ impl ExtensionHost {
         id
     }
 
-    fn handle_ui_request(&mut self, request: &ExtensionUiRequest) -> Result<(), String> {
-        let response = if let Some(handler) = self.ui_handler.as_ref() {
+    fn handle_ui_request(
+        &mut self,
+        index: usize,
+        request: &ExtensionUiRequest,
+    ) -> Result<(), String> {
+        let ui_denied = self.grants.as_ref().is_some_and(|grants| {
+            request.extension_path.as_ref().is_none_or(|path| {
+                grants
+                    .get(path)
+                    .is_none_or(|granted| !granted.iter().any(|capability| capability == "ui"))
+            })
+        });
+        let response = if ui_denied {
+            default_ui_handler(request)
+        } else if let Some(handler) = self.ui_handler.as_ref() {
             handler(request)
         } else {
             default_ui_handler(request)
@@ -664,14 +1132,9 @@ impl ExtensionHost {
             "cancelled": response.cancelled,
         }))
         .map_err(|err| format!("Failed to serialize extension UI response: {err}"))?;
-        self.stdin
-            .write_all(payload.as_bytes())
-            .and_then(|_| self.stdin.write_all(b"\n"))
-            .map_err(|err| format!("Failed to send extension UI response: {err}"))?;
-        self.stdin
-            .flush()
-            .map_err(|err| format!("Failed to flush extension UI response: {err}"))?;
-        Ok(())
+        self.processes[index]
+            .write_line(&payload)
+            .map_err(|err| format!("Failed to send extension UI response: {err}"))
     }
 }
 
@@ -710,11 +1173,98 @@ fn report_extension_errors(errors: &[ExtensionHostError]) {
 
 impl Drop for ExtensionHost {
     fn drop(&mut self) {
-        let _ = self.child.kill();
+        for process in &mut self.processes {
+            let _ = process.child.kill();
+        }
         let _ = fs::remove_file(&self.script_path);
     }
 }
 
+/// Routes outgoing provider requests through the extensions' `provider_request` handlers.
+pub struct ExtensionRequestHook {
+    host: Rc<RefCell<ExtensionHost>>,
+}
+
+impl ExtensionRequestHook {
+    /// `None` when no loaded extension handles `provider_request`.
+    pub fn for_host(host: Rc<RefCell<ExtensionHost>>) -> Option<Self> {
+        let handles = host.borrow().has_handlers("provider_request");
+        handles.then_some(Self { host })
+    }
+}
+
+impl RequestHook for ExtensionRequestHook {
+    fn on_request(&self, request: &mut ProviderRequest) -> Result<(), String> {
+        *request = self
+            .host
+            .try_borrow_mut()
+            .map_err(|_| "extension host is busy".to_string())?
+            .emit_provider_request(request)?;
+        Ok(())
+    }
+}
+
+/// Splits `paths` into the extensions the host can load (JS/TS) and the rest.
+fn supported_paths(paths: &[PathBuf]) -> Result<(Vec<PathBuf>, Vec<PathBuf>), String> {
+    if paths.is_empty() {
+        return Err("No extension paths provided".to_string());
+    }
+    let (supported, skipped): (Vec<_>, Vec<_>) = paths.iter().cloned().partition(|path| {
+        matches!(
+            path.extension().and_then(|ext| ext.to_str()),
+            Some("js") | Some("ts") | Some("tsx")
+        )
+    });
+    if supported.is_empty() {
+        return Err("No supported extension files found (JS/TS only).".to_string());
+    }
+    Ok((supported, skipped))
+}
+
+/// `path` made absolute against `cwd` with `.` and `..` removed, as the host's
+/// `path.resolve` does; extensions are known by this path.
+fn resolve_extension_path(path: &Path, cwd: &Path) -> String {
+    let mut resolved = PathBuf::new();
+    for component in cwd.join(path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other),
+        }
+    }
+    resolved.to_string_lossy().to_string()
+}
+
+/// What a host process reads to load `extensions`: the host script, each extension's
+/// directory and every `node_modules` a package could be loaded from, in `--allow-fs-read`
+/// form.
+fn loading_paths(script_path: &Path, extensions: &[String], cwd: &Path) -> Vec<String> {
+    let mut directories = Vec::new();
+    for extension in extensions {
+        if let Some(dir) = Path::new(extension).parent() {
+            directories.push(dir.to_path_buf());
+            if let Ok(canonical) = dir.canonicalize() {
+                directories.push(canonical);
+            }
+        }
+    }
+    let mut paths = vec![script_path.to_string_lossy().to_string()];
+    for dir in &directories {
+        paths.push(dir.join("*").to_string_lossy().to_string());
+    }
+    for dir in directories.iter().map(PathBuf::as_path).chain([cwd]) {
+        for ancestor in dir.ancestors() {
+            let node_modules = ancestor.join("node_modules").join("*");
+            paths.push(node_modules.to_string_lossy().to_string());
+        }
+    }
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
 fn write_host_script() -> Result<PathBuf, String> {
     let mut path = std::env::temp_dir();
     path.push(format!("pi-extension-host-{}.js", uuid::Uuid::new_v4()));
@@ -733,6 +1283,7 @@ fn to_extension_preparation(prep: &CompactionPreparation) -> ExtensionCompaction
         previous_summary: prep.previous_summary.as_deref(),
         file_ops: to_extension_file_ops(&prep.file_ops),
         settings: to_extension_settings(prep.settings),
+        pinned_entry_ids: &prep.pinned_entry_ids,
     }
 }
 
@@ -768,6 +1319,7 @@ fn convert_before_compact_result(
             first_kept_entry_id: compaction.first_kept_entry_id,
             tokens_before: compaction.tokens_before,
         }),
+        pin: result.pin,
     }
 }
 
@@ -0,0 +1,182 @@
+//! Capabilities the user granted each extension, kept in `extension-permissions.json` in the
+//! agent dir. Extensions declare what they need (`module.exports.capabilities`); the user
+//! decides on each capability once, the first time an extension asks for it.
+//!
+//! The manifest is read from the source without running it, so nothing an extension does
+//! happens before the user decides. `ui`, `flags` and `prompt` are enforced by the extension
+//! host API; `fs.read`, `fs.write` and `network` by Node's permission model, which the host
+//! process is started under (see [`crate::coding_agent::extension_host`]).
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Everything an extension can be granted. Extensions without a capability manifest
+/// request all of them.
+pub const EXTENSION_CAPABILITIES: [&str; 6] =
+    ["fs.read", "fs.write", "network", "ui", "flags", "prompt"];
+
+/// Ways an extension's source can declare its capabilities.
+const MANIFEST_DECLARATIONS: [&str; 2] = ["exports.capabilities", "export const capabilities"];
+
+/// The capabilities an extension's source declares, read without running it: a literal
+/// array of names assigned to `module.exports.capabilities` (or `exports.capabilities`), or
+/// exported as `export const capabilities`. `None` when it declares none.
+pub fn declared_capabilities(source: &str) -> Result<Option<Vec<String>>, String> {
+    let Some(start) = MANIFEST_DECLARATIONS
+        .iter()
+        .filter_map(|declaration| {
+            source
+                .find(declaration)
+                .map(|index| index + declaration.len())
+        })
+        .min()
+    else {
+        return Ok(None);
+    };
+    let not_literal = || "capabilities must be a literal array of capability names".to_string();
+    // Skip a TypeScript type annotation.
+    let rest = &source[start..];
+    let rest = rest[rest.find('=').ok_or_else(not_literal)? + 1..].trim_start();
+    let mut rest = rest.strip_prefix('[').ok_or_else(not_literal)?;
+    let mut declared = Vec::new();
+    loop {
+        rest = rest.trim_start();
+        if rest.starts_with(']') {
+            break;
+        }
+        let quote = rest
+            .chars()
+            .next()
+            .filter(|quote| matches!(quote, '"' | '\'' | '`'))
+            .ok_or_else(not_literal)?;
+        let end = rest[1..].find(quote).ok_or_else(not_literal)? + 1;
+        declared.push(rest[1..end].to_string());
+        rest = rest[end + 1..].trim_start();
+        match rest.strip_prefix(',') {
+            Some(after) => rest = after,
+            None if rest.starts_with(']') => {}
+            None => return Err(not_literal()),
+        }
+    }
+    let unknown = declared
+        .iter()
+        .filter(|capability| !EXTENSION_CAPABILITIES.contains(&capability.as_str()))
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    if !unknown.is_empty() {
+        return Err(format!(
+            "Unknown capabilities: {} (expected {})",
+            unknown.join(", "),
+            EXTENSION_CAPABILITIES.join(", ")
+        ));
+    }
+    Ok(Some(declared))
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtensionGrant {
+    /// What the extension asked for when it was last loaded.
+    #[serde(default)]
+    pub requested: Vec<String>,
+    #[serde(default)]
+    pub granted: Vec<String>,
+    #[serde(default)]
+    pub denied: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ExtensionPermissions {
+    path: PathBuf,
+    /// Keyed by the extension's absolute path.
+    grants: BTreeMap<String, ExtensionGrant>,
+}
+
+impl ExtensionPermissions {
+    /// A missing or unreadable file is an empty store.
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let grants = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self { path, grants }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get(&self, extension: &str) -> Option<&ExtensionGrant> {
+        self.grants.get(extension)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&String, &ExtensionGrant)> {
+        self.grants.iter()
+    }
+
+    /// Requested capabilities the user has neither granted nor denied yet.
+    pub fn undecided(&self, extension: &str, requested: &[String]) -> Vec<String> {
+        let grant = self.grants.get(extension);
+        requested
+            .iter()
+            .filter(|capability| {
+                grant.is_none_or(|grant| {
+                    !grant.granted.contains(capability) && !grant.denied.contains(capability)
+                })
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// The requested capabilities the user granted.
+    pub fn granted(&self, extension: &str, requested: &[String]) -> Vec<String> {
+        let Some(grant) = self.grants.get(extension) else {
+            return Vec::new();
+        };
+        requested
+            .iter()
+            .filter(|capability| grant.granted.contains(capability))
+            .cloned()
+            .collect()
+    }
+
+    /// Records the user's answer for `decided`: capabilities in `approved` are granted,
+    /// the rest denied.
+    pub fn record(
+        &mut self,
+        extension: &str,
+        requested: &[String],
+        decided: &[String],
+        approved: &[String],
+    ) {
+        let grant = self.grants.entry(extension.to_string()).or_default();
+        grant.requested = requested.to_vec();
+        for capability in decided {
+            let list = if approved.contains(capability) {
+                &mut grant.granted
+            } else {
+                &mut grant.denied
+            };
+            if !list.contains(capability) {
+                list.push(capability.clone());
+            }
+        }
+    }
+
+    /// Forgets the decisions for `extension` so it is asked again; false when there were none.
+    pub fn reset(&mut self, extension: &str) -> bool {
+        self.grants.remove(extension).is_some()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+        let content = serde_json::to_string_pretty(&self.grants).map_err(|err| err.to_string())?;
+        fs::write(&self.path, content)
+            .map_err(|err| format!("Could not write {}: {err}", self.path.display()))
+    }
+}
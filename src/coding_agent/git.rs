@@ -0,0 +1,104 @@
+//! Thin wrappers over the `git` CLI, used by the git tools and by per-turn checkpoint commits.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Refs the checkpoint commits are kept under, numbered from 1.
+pub const CHECKPOINT_REF_PREFIX: &str = "refs/pi/checkpoints/";
+
+/// Run `git <args>` in `cwd` and return stdout, or stderr as the error when git fails.
+pub fn run_git(cwd: &Path, args: &[&str]) -> Result<String, String> {
+    run_git_with_env(cwd, args, &[])
+}
+
+fn run_git_with_env(cwd: &Path, args: &[&str], env: &[(&str, &Path)]) -> Result<String, String> {
+    let output = Command::new("git")
+        .args(args)
+        .envs(env.iter().copied())
+        .current_dir(cwd)
+        .output()
+        .map_err(|err| format!("Failed to run git: {err}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(if stderr.is_empty() {
+            format!("git {} failed", args.first().unwrap_or(&""))
+        } else {
+            stderr
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+pub fn is_git_repo(cwd: &Path) -> bool {
+    run_git(cwd, &["rev-parse", "--is-inside-work-tree"])
+        .map(|output| output.trim() == "true")
+        .unwrap_or(false)
+}
+
+/// Snapshot the working tree (including untracked files) as a commit on top of HEAD, kept at
+/// the next `refs/pi/checkpoints/<n>`. HEAD, the branch and the index are left alone: the tree
+/// is built in a temporary index. Returns the commit hash, or `None` when `cwd` is not a
+/// repository or the tree matches HEAD.
+pub fn checkpoint_commit(cwd: &Path, message: &str) -> Result<Option<String>, String> {
+    if !is_git_repo(cwd) {
+        return Ok(None);
+    }
+    let index = git_path(cwd, &format!("pi-checkpoint-index-{}", std::process::id()))?;
+    let tree = write_worktree_tree(cwd, &index);
+    let _ = fs::remove_file(&index);
+    let tree = tree?;
+    let head = run_git(cwd, &["rev-parse", "--verify", "-q", "HEAD"])
+        .ok()
+        .map(|head| head.trim().to_string());
+    let mut args = vec!["commit-tree", tree.as_str(), "-m", message];
+    if let Some(head) = &head {
+        let head_tree = run_git(cwd, &["rev-parse", &format!("{head}^{{tree}}")])?;
+        if head_tree.trim() == tree {
+            return Ok(None);
+        }
+        args.extend(["-p", head.as_str()]);
+    }
+    let commit = run_git(cwd, &args)?.trim().to_string();
+    let existing = run_git(
+        cwd,
+        &["for-each-ref", "--format=%(refname)", CHECKPOINT_REF_PREFIX],
+    )?;
+    let number = existing
+        .lines()
+        .filter_map(|name| {
+            name.strip_prefix(CHECKPOINT_REF_PREFIX)?
+                .parse::<u64>()
+                .ok()
+        })
+        .max()
+        .unwrap_or(0)
+        + 1;
+    let name = format!("{CHECKPOINT_REF_PREFIX}{number}");
+    run_git(cwd, &["update-ref", &name, &commit])?;
+    Ok(Some(commit))
+}
+
+/// Stage the whole working tree into `index`, seeded from the real index so unchanged files
+/// are not hashed again, and write it out as a tree.
+fn write_worktree_tree(cwd: &Path, index: &Path) -> Result<String, String> {
+    let real_index = git_path(cwd, "index")?;
+    if real_index.exists() {
+        fs::copy(&real_index, index).map_err(|err| format!("Failed to copy the index: {err}"))?;
+    }
+    let env = [("GIT_INDEX_FILE", index)];
+    run_git_with_env(cwd, &["add", "-A"], &env)?;
+    Ok(run_git_with_env(cwd, &["write-tree"], &env)?
+        .trim()
+        .to_string())
+}
+
+/// `git rev-parse --git-path <name>`, absolute.
+fn git_path(cwd: &Path, name: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(run_git(cwd, &["rev-parse", "--git-path", name])?.trim());
+    Ok(if path.is_absolute() {
+        path
+    } else {
+        cwd.join(path)
+    })
+}
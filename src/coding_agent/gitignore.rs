@@ -0,0 +1,181 @@
+//! `.gitignore` matching and a directory walk that honours it, shared by the search tools
+//! and file autocomplete.
+
+use glob::{MatchOptions, Pattern};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub struct IgnoreRule {
+    base: String,
+    pattern: Pattern,
+    anchored: bool,
+    dir_only: bool,
+    negated: bool,
+}
+
+impl IgnoreRule {
+    /// Parses one `.gitignore` line from the file in `base`, a directory relative to the walk
+    /// root (empty for the root itself).
+    pub fn parse(line: &str, base: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let dir_only = line.ends_with('/');
+        let line = line.trim_end_matches('/');
+        let anchored = line.contains('/');
+        let line = line.trim_start_matches('/');
+        let pattern = Pattern::new(line).ok()?;
+        Some(Self {
+            base: base.to_string(),
+            pattern,
+            anchored,
+            dir_only,
+            negated,
+        })
+    }
+
+    pub fn matches(&self, rel_path: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let Some(local) = strip_dir_prefix(rel_path, &self.base) else {
+            return false;
+        };
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..MatchOptions::default()
+        };
+        if self.anchored {
+            self.pattern.matches_with(local, options)
+        } else {
+            let name = local.rsplit('/').next().unwrap_or(local);
+            self.pattern.matches_with(name, options)
+        }
+    }
+}
+
+fn strip_dir_prefix<'a>(path: &'a str, dir: &str) -> Option<&'a str> {
+    if dir.is_empty() {
+        return Some(path);
+    }
+    path.strip_prefix(dir)?.strip_prefix('/')
+}
+
+/// Whether the last rule matching `rel_path` ignores it.
+pub fn is_ignored(rules: &[IgnoreRule], rel_path: &str, is_dir: bool) -> bool {
+    let mut ignored = false;
+    for rule in rules {
+        if rule.matches(rel_path, is_dir) {
+            ignored = !rule.negated;
+        }
+    }
+    ignored
+}
+
+/// The rules in `dir/.gitignore`, where `dir` is `rel_dir` relative to the walk root.
+pub fn read_ignore_rules(dir: &Path, rel_dir: &str) -> Vec<IgnoreRule> {
+    fs::read_to_string(dir.join(".gitignore"))
+        .map(|content| {
+            content
+                .lines()
+                .filter_map(|line| IgnoreRule::parse(line, rel_dir))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub struct WalkEntry {
+    /// Path relative to the walk root, with `/` separators.
+    pub rel_path: String,
+    pub path: PathBuf,
+    pub is_dir: bool,
+    /// 1 for entries directly in the root.
+    pub depth: usize,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WalkOptions {
+    /// Do not enter directories deeper than this; 1 visits only the root's entries.
+    pub max_depth: Option<usize>,
+    /// Visit what `.gitignore` files exclude too.
+    pub include_ignored: bool,
+}
+
+/// Visits everything under `base` that no `.gitignore` on the way excludes, depth first and
+/// sorted by name, skipping `.git`. Directories are visited before their contents. The walk
+/// stops when `visit` returns `false`.
+pub fn walk(base: &Path, options: WalkOptions, visit: &mut dyn FnMut(&WalkEntry) -> bool) {
+    let mut rules = Vec::new();
+    walk_dir(base, "", 1, options, &mut rules, visit);
+}
+
+fn walk_dir(
+    dir: &Path,
+    rel_dir: &str,
+    depth: usize,
+    options: WalkOptions,
+    rules: &mut Vec<IgnoreRule>,
+    visit: &mut dyn FnMut(&WalkEntry) -> bool,
+) -> bool {
+    let rules_before = rules.len();
+    if !options.include_ignored {
+        rules.extend(read_ignore_rules(dir, rel_dir));
+    }
+
+    let mut keep_going = true;
+    if let Ok(entries) = fs::read_dir(dir) {
+        let mut entries = entries.flatten().collect::<Vec<_>>();
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name == ".git" {
+                continue;
+            }
+            // Symlinks are not followed, so a link to a directory is visited but not entered.
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let rel_path = if rel_dir.is_empty() {
+                name
+            } else {
+                format!("{rel_dir}/{name}")
+            };
+            let is_dir = file_type.is_dir();
+            if is_ignored(rules, &rel_path, is_dir) {
+                continue;
+            }
+            let walk_entry = WalkEntry {
+                rel_path,
+                path: entry.path(),
+                is_dir,
+                depth,
+            };
+            if !visit(&walk_entry) {
+                keep_going = false;
+                break;
+            }
+            let descend = is_dir && options.max_depth.is_none_or(|max_depth| depth < max_depth);
+            if descend
+                && !walk_dir(
+                    &walk_entry.path,
+                    &walk_entry.rel_path,
+                    depth + 1,
+                    options,
+                    rules,
+                    visit,
+                )
+            {
+                keep_going = false;
+                break;
+            }
+        }
+    }
+
+    rules.truncate(rules_before);
+    keep_going
+}
@@ -1,7 +1,7 @@
 use crate::coding_agent::ModelRegistry;
 use crate::core::compaction::CompactionPreparation;
+pub use crate::core::compaction::CompactionResult;
 use crate::core::session_manager::{CompactionEntry, SessionEntry, SessionManager};
-use serde::Serialize;
 
 pub struct HookContext<'a> {
     pub session_manager: &'a SessionManager,
@@ -66,15 +66,10 @@ impl CompactionHook {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Serialize)]
-pub struct CompactionResult {
-    pub summary: String,
-    pub first_kept_entry_id: String,
-    pub tokens_before: i64,
-}
-
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct SessionBeforeCompactResult {
     pub cancel: Option<bool>,
     pub compaction: Option<CompactionResult>,
+    /// Message entry ids to pin before compacting, so they are kept verbatim.
+    pub pin: Option<Vec<String>>,
 }
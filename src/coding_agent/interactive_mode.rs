@@ -1,8 +1,9 @@
 use crate::agent::AgentMessage;
+use crate::coding_agent::theme::{get_active_theme, ThemeColor};
 use crate::core::messages::{ContentBlock, UserContent};
 use crate::tui::{
-    get_capabilities, get_image_dimensions, image_fallback, render_image, Container,
-    ImageRenderOptions, Spacer, Text,
+    get_capabilities, get_image_dimensions, image_fallback, render_image, truncate_to_width,
+    visible_width, Container, ImageRenderOptions, Spacer, Text,
 };
 use serde_json::Value;
 
@@ -63,10 +64,18 @@ pub fn format_message_for_interactive(
                 None
             }
         }
-        AgentMessage::Assistant(assistant) => Some(format!(
-            "Assistant:\n{}",
-            format_content_blocks(&assistant.content, hide_thinking, show_images)
-        )),
+        AgentMessage::Assistant(assistant) => {
+            let mut body = format_content_blocks(&assistant.content, hide_thinking, show_images);
+            if assistant.is_aborted() {
+                if body == "[empty message]" {
+                    body.clear();
+                } else {
+                    body.push_str("\n\n");
+                }
+                body.push_str("[aborted]");
+            }
+            Some(format!("Assistant:\n{body}"))
+        }
         AgentMessage::ToolResult(result) => {
             let label = if result.is_error {
                 "Tool result (error)"
@@ -96,6 +105,8 @@ pub fn format_content_blocks(
     hide_thinking: bool,
     show_images: bool,
 ) -> String {
+    let image_names = attached_image_names(blocks);
+    let mut image_index = 0;
     let mut parts = Vec::new();
     for block in blocks {
         match block {
@@ -128,10 +139,19 @@ pub fn format_content_blocks(
             ContentBlock::Image {
                 mime_type, data, ..
             } => {
-                if show_images {
-                    parts.push(format_image_block(mime_type, data));
-                }
+                let filename = image_names.get(image_index).map(String::as_str);
+                image_index += 1;
+                parts.push(format_image_block(mime_type, data, filename, show_images));
             }
+            ContentBlock::Diff {
+                path,
+                diff,
+                added,
+                removed,
+            } => parts.push(format!(
+                "Diff: {path} (+{added} -{removed})\n{}",
+                color_diff(diff)
+            )),
         }
     }
     if parts.is_empty() {
@@ -161,55 +181,172 @@ fn format_json(value: &Value) -> String {
     serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
 }
 
-/// Format an image block for display in the interactive mode.
-/// When the terminal supports inline images (Kitty/iTerm2), renders the image inline.
-/// Otherwise, shows fallback text like "[Image: [image/png] 800x600]".
-fn format_image_block(mime_type: &str, data: &str) -> String {
-    let caps = get_capabilities();
+/// Data shown in the interactive status bar below the editor.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StatusBarInfo {
+    pub provider: String,
+    pub model: String,
+    pub thinking_level: String,
+    pub context_tokens: i64,
+    pub context_window: Option<i64>,
+    pub cost: f64,
+    /// What the agent is doing, or `None` when idle.
+    pub activity: Option<String>,
+}
+
+/// Render the status bar as a single line: model and thinking level on the left, context
+/// usage, cost and streaming state on the right.
+pub fn render_status_bar(info: &StatusBarInfo, width: usize) -> String {
+    let left = format!(
+        "{}/{} · thinking {}",
+        info.provider, info.model, info.thinking_level
+    );
+
+    let mut context = format!("ctx {}", format_token_estimate(info.context_tokens));
+    if let Some(window) = info.context_window.filter(|window| *window > 0) {
+        let percent = info.context_tokens as f64 * 100.0 / window as f64;
+        let color = if percent >= 90.0 {
+            "\x1b[31m"
+        } else if percent >= 70.0 {
+            "\x1b[33m"
+        } else {
+            ""
+        };
+        context = format!(
+            "{context}/{} {color}({percent:.0}%)\x1b[0m\x1b[2m",
+            format_token_estimate(window)
+        );
+    }
+    let state = match &info.activity {
+        Some(activity) => format!("\x1b[0m\x1b[36m● {activity}\x1b[0m\x1b[2m"),
+        None => "idle".to_string(),
+    };
+    let right = format!("{context} · ${:.3} · {state}", info.cost);
+
+    let right_width = visible_width(&right);
+    let left = truncate_to_width(&left, width.saturating_sub(right_width + 1));
+    let gap = width
+        .saturating_sub(visible_width(&left) + right_width)
+        .max(1);
+    let line = format!("{left}{}{right}", " ".repeat(gap));
+    format!("\x1b[2m{}\x1b[0m", truncate_to_width(&line, width))
+}
+
+fn format_token_estimate(tokens: i64) -> String {
+    let (value, suffix) = if tokens >= 1_000_000 {
+        (tokens as f64 / 1_000_000.0, "M")
+    } else if tokens >= 1_000 {
+        (tokens as f64 / 1_000.0, "k")
+    } else {
+        return tokens.to_string();
+    };
+    let formatted = format!("{value:.1}");
+    format!("{}{suffix}", formatted.trim_end_matches(".0"))
+}
+
+const IMAGE_MARKER_PREFIX: &str = "\x1b_pi-image;";
+const IMAGE_MARKER_SUFFIX: &str = "\x1b\\";
 
-    if caps.images.is_none() {
-        // Terminal doesn't support inline images, show fallback
+/// Rows for an inline image preview, plus the placeholder to show when it cannot be drawn.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImagePreview {
+    pub lines: Vec<String>,
+    pub fallback: String,
+}
+
+/// Format an image block for display in the interactive mode.
+/// When the terminal supports inline images (Kitty/iTerm2), emits a single-line marker that
+/// the chat renderer expands with `render_image_preview` once the available width is known.
+/// Otherwise, shows fallback text like "[Image: photo.png [image/png] 800x600]".
+fn format_image_block(
+    mime_type: &str,
+    data: &str,
+    filename: Option<&str>,
+    show_images: bool,
+) -> String {
+    if !show_images || get_capabilities().images.is_none() {
         let dimensions = get_image_dimensions(data, mime_type);
-        return image_fallback(mime_type, dimensions, None);
+        return image_fallback(mime_type, dimensions, filename);
     }
+    format!(
+        "{IMAGE_MARKER_PREFIX}{mime_type};{data};{}{IMAGE_MARKER_SUFFIX}",
+        filename.unwrap_or("")
+    )
+}
 
-    // Try to render the image inline
-    let dimensions = match get_image_dimensions(data, mime_type) {
-        Some(dims) => dims,
-        None => {
-            // Can't parse dimensions, show fallback
-            return image_fallback(mime_type, None, None);
-        }
+/// Colors diff lines with the active theme's tool diff colors; plain when no theme is set.
+fn color_diff(diff: &str) -> String {
+    let Some(theme) = get_active_theme() else {
+        return diff.to_string();
     };
+    diff.lines()
+        .map(|line| {
+            let color = match line.as_bytes().first() {
+                Some(b'+') => ThemeColor::ToolDiffAdded,
+                Some(b'-') => ThemeColor::ToolDiffRemoved,
+                _ => ThemeColor::ToolDiffContext,
+            };
+            theme.fg(color, line)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
 
-    // Render with reasonable defaults for chat display
+/// Expand an image marker line produced by `format_content_blocks` into terminal rows.
+/// Returns `None` when `line` is not an image marker.
+pub fn render_image_preview(line: &str, max_width: usize) -> Option<ImagePreview> {
+    let body = line
+        .strip_prefix(IMAGE_MARKER_PREFIX)?
+        .strip_suffix(IMAGE_MARKER_SUFFIX)?;
+    let mut parts = body.splitn(3, ';');
+    let mime_type = parts.next()?;
+    let data = parts.next()?;
+    let filename = parts.next().filter(|name| !name.is_empty());
+
+    let dimensions = get_image_dimensions(data, mime_type);
+    let fallback = image_fallback(mime_type, dimensions, filename);
     let options = ImageRenderOptions {
-        max_width_cells: Some(60),
+        max_width_cells: Some(max_width.clamp(1, 60) as u32),
         max_height_cells: Some(30),
         preserve_aspect_ratio: Some(true),
     };
-
-    match render_image(data, dimensions, &options) {
+    let lines = match dimensions.and_then(|dims| render_image(data, dims, &options)) {
         Some(result) => {
-            // Build the output: empty lines for height, then cursor up + image sequence
-            let mut lines = Vec::new();
-            for _ in 0..(result.rows.saturating_sub(1)) {
-                lines.push(String::new());
-            }
-            // Move cursor up to first row, then output image
+            // Empty lines for height, then cursor up + image sequence on the last row
+            let mut lines = vec![String::new(); result.rows.saturating_sub(1) as usize];
             let move_up = if result.rows > 1 {
                 format!("\x1b[{}A", result.rows - 1)
             } else {
                 String::new()
             };
             lines.push(format!("{}{}", move_up, result.sequence));
-            lines.join("\n")
+            lines
         }
-        None => {
-            // Rendering failed, show fallback
-            image_fallback(mime_type, Some(dimensions), None)
+        None => vec![fallback.clone()],
+    };
+    Some(ImagePreview { lines, fallback })
+}
+
+/// File names of images attached via `@file`, in attachment order.
+fn attached_image_names(blocks: &[ContentBlock]) -> Vec<String> {
+    let mut names = Vec::new();
+    for block in blocks {
+        let ContentBlock::Text { text, .. } = block else {
+            continue;
+        };
+        let mut rest = text.as_str();
+        while let Some(start) = rest.find("<file name=\"") {
+            rest = &rest[start + "<file name=\"".len()..];
+            let Some(end) = rest.find('"') else {
+                break;
+            };
+            if rest[end..].starts_with("\"></file>") {
+                names.push(rest[..end].to_string());
+            }
+            rest = &rest[end..];
         }
     }
+    names
 }
 
 impl Default for InteractiveMode {
@@ -0,0 +1,255 @@
+//! `pi --mirror`: a live view of the current session in the browser.
+//!
+//! - `GET /` serves the HTML export of the session being mirrored.
+//! - `GET /events` streams server-sent events: `{"type":"entries","entries":[...]}` for
+//!   entries appended to the session file, and `{"type":"reset"}` when the terminal moves to
+//!   another session (`/new`, `/resume`, a branch). The page reloads itself on either.
+//!
+//! Like `pi --serve`, every request needs the token, as `?token=<token>` or a bearer header.
+
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::coding_agent::export_html::{render_session_html, ExportOptions};
+use crate::rpc::server::{authorized, read_request, write_response};
+
+pub const DEFAULT_MIRROR_PORT: u16 = 4142;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Reloads the page on every event, keeping the reader's place unless they were following
+/// the end of the conversation.
+const LIVE_SCRIPT: &str = r#"<script>
+    (function() {
+      const content = document.getElementById('content');
+      const saved = sessionStorage.getItem('pi-mirror-scroll');
+      if (content && saved !== null) {
+        content.scrollTop = saved === 'bottom' ? content.scrollHeight : Number(saved);
+      }
+      const events = new EventSource('/events' + location.search);
+      events.onmessage = () => {
+        if (content) {
+          const atBottom = content.scrollTop + content.clientHeight >= content.scrollHeight - 4;
+          sessionStorage.setItem('pi-mirror-scroll', atBottom ? 'bottom' : content.scrollTop);
+        }
+        location.reload();
+      };
+    })();
+  </script>"#;
+
+const WAITING_PAGE: &str = "<!DOCTYPE html>\n<html lang=\"en\">\n<head><meta charset=\"UTF-8\">\
+<title>pi mirror</title></head>\n<body style=\"font-family: monospace; padding: 2em\">\
+<div id=\"content\">Waiting for the conversation to start...</div>\n</body>\n</html>\n";
+
+/// Serves a live view of whichever session file it is told to [`follow`](Self::follow).
+#[derive(Clone)]
+pub struct SessionMirror {
+    addr: SocketAddr,
+    token: String,
+    session_file: Arc<Mutex<Option<PathBuf>>>,
+}
+
+impl SessionMirror {
+    /// Binds `addr` and starts serving and watching on background threads.
+    pub fn start(
+        addr: impl Into<SocketAddr>,
+        token: impl Into<String>,
+        options: ExportOptions,
+    ) -> Result<Self, String> {
+        let addr = addr.into();
+        let listener =
+            TcpListener::bind(addr).map_err(|err| format!("Failed to listen on {addr}: {err}"))?;
+        let mirror = Self {
+            addr: listener.local_addr().map_err(|err| err.to_string())?,
+            token: token.into(),
+            session_file: Arc::new(Mutex::new(None)),
+        };
+        let clients = Arc::new(Mutex::new(Vec::new()));
+
+        let server = mirror.clone();
+        let accept_clients = clients.clone();
+        let options = Arc::new(options);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                let server = server.clone();
+                let clients = accept_clients.clone();
+                let options = options.clone();
+                thread::spawn(move || {
+                    if let Err(err) = server.handle_connection(stream, &clients, &options) {
+                        eprintln!("Warning: mirror client error: {err}");
+                    }
+                });
+            }
+        });
+
+        let session_file = mirror.session_file.clone();
+        thread::spawn(move || watch_session(&session_file, &clients));
+        Ok(mirror)
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The address to open in a browser, token included.
+    pub fn url(&self) -> String {
+        format!("http://{}/?token={}", self.addr, self.token)
+    }
+
+    /// Mirrors `session_file` from now on; `None` for sessions that are not saved to disk.
+    pub fn follow(&self, session_file: Option<PathBuf>) {
+        if let Ok(mut current) = self.session_file.lock() {
+            *current = session_file;
+        }
+    }
+
+    fn current_file(&self) -> Option<PathBuf> {
+        self.session_file.lock().ok().and_then(|file| file.clone())
+    }
+
+    fn handle_connection(
+        &self,
+        stream: TcpStream,
+        clients: &Mutex<Vec<TcpStream>>,
+        options: &ExportOptions,
+    ) -> Result<(), String> {
+        let mut reader = BufReader::new(stream.try_clone().map_err(|err| err.to_string())?);
+        let mut stream = stream;
+        let Some(request) = read_request(&mut reader)? else {
+            return Ok(());
+        };
+        if !authorized(&request, &self.token) {
+            return write_response(
+                &mut stream,
+                "401 Unauthorized",
+                "Missing or invalid token\n",
+            );
+        }
+
+        match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/") => {
+                let page = match self.current_file().filter(|file| file.exists()) {
+                    Some(file) => render_session_html(&file, options)?,
+                    None => WAITING_PAGE.to_string(),
+                };
+                let page = match page.rfind("</body>") {
+                    Some(end) => format!("{}{LIVE_SCRIPT}\n{}", &page[..end], &page[end..]),
+                    None => page,
+                };
+                let head = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\n\
+                     Content-Length: {}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+                    page.len()
+                );
+                stream
+                    .write_all(head.as_bytes())
+                    .and_then(|_| stream.write_all(page.as_bytes()))
+                    .map_err(|err| err.to_string())
+            }
+            ("GET", "/events") => {
+                let head = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n\
+                            Cache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n: connected\n\n";
+                {
+                    let mut clients = clients
+                        .lock()
+                        .map_err(|_| "Client list poisoned".to_string())?;
+                    stream
+                        .write_all(head.as_bytes())
+                        .map_err(|err| err.to_string())?;
+                    clients.push(stream.try_clone().map_err(|err| err.to_string())?);
+                }
+                // Nothing is read from an event stream; wait for the client to hang up.
+                let mut buffer = [0u8; 1024];
+                while matches!(reader.read(&mut buffer), Ok(read) if read > 0) {}
+                Ok(())
+            }
+            _ => write_response(&mut stream, "404 Not Found", "Not found\n"),
+        }
+    }
+}
+
+/// Polls the followed session file and tells every client what changed.
+fn watch_session(session_file: &Mutex<Option<PathBuf>>, clients: &Mutex<Vec<TcpStream>>) {
+    let mut watched: Option<PathBuf> = None;
+    let mut length = 0;
+    let mut sent = 0;
+    loop {
+        thread::sleep(POLL_INTERVAL);
+        let Ok(current) = session_file.lock().map(|file| file.clone()) else {
+            return;
+        };
+        if current != watched {
+            watched = current;
+            length = file_length(watched.as_deref());
+            sent = watched
+                .as_deref()
+                .map(read_entries)
+                .unwrap_or_default()
+                .len();
+            broadcast(clients, &json!({ "type": "reset" }));
+            continue;
+        }
+        let Some(path) = watched.as_deref() else {
+            continue;
+        };
+        let current_length = file_length(Some(path));
+        if current_length == length {
+            continue;
+        }
+        length = current_length;
+        let entries = read_entries(path);
+        if entries.len() < sent {
+            // Rewritten in place (e.g. a migration); start over.
+            sent = entries.len();
+            broadcast(clients, &json!({ "type": "reset" }));
+        } else if entries.len() > sent {
+            let new_entries = entries[sent..].to_vec();
+            sent = entries.len();
+            broadcast(
+                clients,
+                &json!({ "type": "entries", "entries": new_entries }),
+            );
+        }
+    }
+}
+
+fn file_length(path: Option<&Path>) -> u64 {
+    path.and_then(|path| fs::metadata(path).ok())
+        .map(|metadata| metadata.len())
+        .unwrap_or(0)
+}
+
+/// The session's entries as written, without the header line.
+fn read_entries(path: &Path) -> Vec<Value> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|entry| entry["type"] != "session")
+        .collect()
+}
+
+/// Sends `event` to every client, dropping the ones that have gone away.
+fn broadcast(clients: &Mutex<Vec<TcpStream>>, event: &Value) {
+    let Ok(mut clients) = clients.lock() else {
+        return;
+    };
+    let message = format!("data: {event}\n\n");
+    clients.retain_mut(|stream| {
+        stream
+            .write_all(message.as_bytes())
+            .and_then(|_| stream.flush())
+            .is_ok()
+    });
+}
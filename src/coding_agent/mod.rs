@@ -1,36 +1,82 @@
 pub mod export_html;
 pub mod extension_host;
+pub mod extension_permissions;
 pub mod extension_runner;
 pub mod extensions;
 pub mod fuzzy;
+pub mod git;
+pub mod gitignore;
 pub mod tools;
 
 pub use fuzzy::{fuzzy_filter, fuzzy_match, FuzzyMatch};
+pub use git::{checkpoint_commit, is_git_repo, run_git, CHECKPOINT_REF_PREFIX};
 pub mod agent_session;
 pub mod auth_storage;
+pub mod bash_policy;
+pub mod change_journal;
 pub mod changelog;
+pub mod context_dir;
+pub mod context_packs;
+pub mod edit_review;
+pub mod environment;
 pub mod hooks;
 pub mod interactive_mode;
+pub mod mirror;
 pub mod model_registry;
 pub mod model_resolver;
+pub mod models_config;
+pub mod notebook;
 pub mod oauth;
+pub mod outline;
+pub mod output_filter;
+pub mod personas;
+pub mod prompt_history;
 pub mod prompt_templates;
+pub mod read_tracker;
+pub mod sandbox;
+pub mod shell;
 pub mod skills;
 pub mod slash_commands;
+pub mod spend;
 pub mod system_prompt;
+pub mod telemetry;
 pub mod theme;
 
 pub use agent_session::{
     AgentSession, AgentSessionConfig, AgentSessionError, AgentSessionEvent, AgentSessionState,
-    BashResult, BranchCandidate, BranchResult, CompactionOverrides, ExportResult, ModelCycleResult,
-    NavigateTreeOptions, NavigateTreeResult, SessionStats, SettingsManager, SettingsOverrides,
+    BashResult, BranchCandidate, BranchCheckout, BranchResult, CompactionOverrides, ContextUsage,
+    ExportResult, ModelCycleResult, NavigateTreeOptions, NavigateTreeResult, RewindResult,
+    SessionStats, SettingsBashPolicy, SettingsBudget, SettingsContextDir, SettingsManager,
+    SettingsOutputFilterRule, SettingsOutputFilters, SettingsOverrides, SettingsSandbox,
+    SettingsScope, SettingsSystemPrompt, SettingsTelemetry, SettingsToolUpdates,
     ThinkingLevelCycleResult, TokenStats,
 };
 pub use auth_storage::{AuthCredential, AuthStorage};
+pub use bash_policy::{
+    BashApproval, BashApprovalHandler, BashDecision, BashPolicy, BashPolicyAction,
+};
+pub use change_journal::{ChangeJournal, FileChange, SharedChangeJournal};
 pub use changelog::{get_changelog_path, parse_changelog, ChangelogEntry};
-pub use export_html::{export_from_file, export_session_to_html};
+pub use context_dir::{
+    collect_context_dir, ContextDirFile, ContextDirOptions, ContextDirResult, ContextFileStatus,
+    ContextPlacement, DEFAULT_CONTEXT_MAX_BYTES,
+};
+pub use context_packs::{
+    find_context_pack, format_context_packs_for_prompt, load_context_packs, ContextPack,
+    LoadContextPacksOptions,
+};
+pub use edit_review::{EditDecision, EditProposal, EditReview, EditReviewHandler};
+pub use environment::{capture_environment, format_environment, EnvironmentSnapshot};
+pub use export_html::{
+    export_from_file, export_from_file_with_options, export_session_to_html, CustomMessageDisplay,
+    ExportOptions,
+};
 pub use extension_host::{
-    ExtensionCommand, ExtensionHost, ExtensionManifest, ExtensionUiRequest, ExtensionUiResponse,
+    ExtensionCommand, ExtensionHost, ExtensionManifest, ExtensionOutputResult,
+    ExtensionRequestHook, ExtensionUiRequest, ExtensionUiResponse,
+};
+pub use extension_permissions::{
+    declared_capabilities, ExtensionGrant, ExtensionPermissions, EXTENSION_CAPABILITIES,
 };
 pub use extension_runner::{
     ExtensionRunner, RegisteredCommand, RegisteredFlag, RegisteredMessageRenderer,
@@ -42,7 +88,7 @@ pub use hooks::{
     SessionBeforeCompactResult, SessionCompactEvent,
 };
 pub use interactive_mode::InteractiveMode;
-pub use model_registry::{Model, ModelRegistry};
+pub use model_registry::{is_built_in_provider, Model, ModelRegistry};
 pub use model_resolver::{
     parse_model_pattern, resolve_model_scope, InitialModelResult, ParsedModelResult, ScopedModel,
 };
@@ -54,19 +100,36 @@ pub use oauth::{
     openai_codex_refresh_token, DeviceCodeResponse, OAuthCallbackServer, OAuthCredentials,
     OAuthProviderInfo,
 };
+pub use output_filter::{
+    build_output_filter_chain, ExtensionOutputFilter, ModerationModelFilter, OutputFilter,
+    OutputFilterChain, OutputFilterVerdict, RegexOutputFilter,
+};
+pub use personas::{
+    find_persona, load_personas, parse_persona, LoadPersonasOptions, PermissionProfile, Persona,
+};
+pub use prompt_history::{
+    append_prompt_history, get_prompt_history_path, load_prompt_history, PROMPT_HISTORY_LIMIT,
+};
 pub use prompt_templates::{
-    expand_prompt_template, load_prompt_templates, LoadPromptTemplatesOptions, PromptTemplate,
+    expand_prompt_template, load_prompt_templates, render_prompt_template, template_arguments,
+    try_expand_prompt_template, LoadPromptTemplatesOptions, PromptTemplate, TemplateArgument,
 };
+pub use read_tracker::{ReadTracker, SharedReadTracker};
+pub use sandbox::SandboxPolicy;
+pub use shell::{Shell, ShellKind};
 pub use skills::{
-    format_skills_for_prompt, load_skills, load_skills_from_dir, LoadSkillsFromDirOptions,
-    LoadSkillsOptions, LoadSkillsResult, Skill, SkillWarning,
+    expand_skill, format_skills_for_prompt, load_skills, load_skills_from_dir,
+    LoadSkillsFromDirOptions, LoadSkillsOptions, LoadSkillsResult, Skill, SkillArgument,
+    SkillWarning,
 };
 pub use slash_commands::{parse_command_args, substitute_args};
+pub use spend::{spend_day, SpendLimits, SpendSnapshot, SpendTracker};
 pub use system_prompt::{
     build_system_prompt, load_project_context_files, BuildSystemPromptOptions, ContextFile,
-    LoadContextFilesOptions,
+    LoadContextFilesOptions, PromptOverrides, PROMPT_SECTIONS,
 };
+pub use telemetry::{create_telemetry_sink, TelemetryEvent, TelemetryRecorder, TelemetrySink};
 pub use theme::{
-    available_themes, load_theme, load_theme_or_default, set_active_theme, Theme, ThemeBg,
-    ThemeColor,
+    available_themes, get_active_theme, load_theme, load_theme_or_default, set_active_theme, Theme,
+    ThemeBg, ThemeColor,
 };
@@ -1,11 +1,14 @@
+use crate::api::bedrock::{BEDROCK_API, BEDROCK_MODEL_IDS};
+use crate::api::mock::MOCK_API;
 use crate::coding_agent::auth_storage::AuthStorage;
 use crate::core::messages::Cost;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
-#[derive(Clone, Debug, PartialEq, Serialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Model {
     pub id: String,
     pub name: String,
@@ -24,6 +27,7 @@ pub struct Model {
 struct ProviderOverride {
     base_url: Option<String>,
     headers: Option<HashMap<String, String>>,
+    context_windows: Option<HashMap<String, i64>>,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -47,6 +51,8 @@ struct ProviderConfig {
     api: Option<String>,
     headers: Option<HashMap<String, String>>,
     models: Option<Vec<ModelDefinition>>,
+    /// Context window overrides for built-in models, keyed by model id.
+    context_windows: Option<HashMap<String, i64>>,
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -102,6 +108,8 @@ pub struct ModelRegistry {
     models_json_path: Option<PathBuf>,
     models: Vec<Model>,
     custom_provider_api_keys: HashMap<String, String>,
+    /// Modification time and size of models.json when it was last loaded.
+    models_json_stamp: Option<(SystemTime, u64)>,
 }
 
 impl ModelRegistry {
@@ -111,6 +119,7 @@ impl ModelRegistry {
             models_json_path: models_json_path.into(),
             models: Vec::new(),
             custom_provider_api_keys: HashMap::new(),
+            models_json_stamp: None,
         };
         registry.load_models();
         registry
@@ -121,14 +130,25 @@ impl ModelRegistry {
         self.load_models();
     }
 
+    /// Reloads the models when models.json changed on disk since the last load; true if so.
+    pub fn reload_if_changed(&mut self) -> bool {
+        let stamp = self.models_json_path.as_deref().and_then(file_stamp);
+        if stamp == self.models_json_stamp {
+            return false;
+        }
+        self.refresh();
+        true
+    }
+
     pub fn get_all(&self) -> Vec<Model> {
         self.models.clone()
     }
 
+    /// Models with credentials, plus mock models, which need none.
     pub fn get_available(&self) -> Vec<Model> {
         self.models
             .iter()
-            .filter(|&model| self.auth_storage.has_auth(&model.provider))
+            .filter(|&model| model.api == MOCK_API || self.auth_storage.has_auth(&model.provider))
             .cloned()
             .collect()
     }
@@ -140,6 +160,14 @@ impl ModelRegistry {
             .cloned()
     }
 
+    /// Context window in tokens for a known model.
+    pub fn context_window(&self, provider: &str, model_id: &str) -> Option<i64> {
+        self.models
+            .iter()
+            .find(|model| model.provider == provider && model.id == model_id)
+            .map(|model| model.context_window)
+    }
+
     pub fn get_api_key(&self, model: &Model) -> Option<String> {
         self.auth_storage.get_api_key(&model.provider)
     }
@@ -174,6 +202,7 @@ impl ModelRegistry {
     }
 
     fn load_models(&mut self) {
+        self.models_json_stamp = self.models_json_path.as_deref().and_then(file_stamp);
         let custom = if let Some(path) = self.models_json_path.clone() {
             self.load_custom_models(&path).unwrap_or_default()
         } else {
@@ -205,6 +234,7 @@ impl ModelRegistry {
                     ProviderOverride {
                         base_url: config.base_url,
                         headers: config.headers,
+                        context_windows: config.context_windows,
                     },
                 );
                 if let Some(api_key) = config.api_key {
@@ -229,6 +259,18 @@ impl ModelRegistry {
     }
 }
 
+fn file_stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Whether `provider` has built-in models, which a models.json entry with models replaces.
+pub fn is_built_in_provider(provider: &str) -> bool {
+    load_built_in_models_from_json()
+        .iter()
+        .any(|model| model.provider == provider)
+}
+
 fn model_from_definition(
     provider: &str,
     config: &ProviderConfig,
@@ -318,6 +360,13 @@ fn load_built_in_models(
             if let Some(headers) = &override_cfg.headers {
                 updated.headers = merge_headers(updated.headers, Some(headers.clone()));
             }
+            if let Some(window) = override_cfg
+                .context_windows
+                .as_ref()
+                .and_then(|windows| windows.get(&updated.id))
+            {
+                updated.context_window = *window;
+            }
             models.push(updated);
         } else {
             models.push(model);
@@ -344,6 +393,22 @@ fn load_built_in_models_from_json() -> Vec<Model> {
         }
     }
 
+    // Claude models are also offered through Amazon Bedrock, under the same ids.
+    let bedrock_models = models
+        .iter()
+        .filter(|model| {
+            model.provider == "anthropic" && BEDROCK_MODEL_IDS.iter().any(|(id, _)| *id == model.id)
+        })
+        .map(|model| Model {
+            api: BEDROCK_API.to_string(),
+            provider: "amazon-bedrock".to_string(),
+            base_url: String::new(),
+            headers: None,
+            ..model.clone()
+        })
+        .collect::<Vec<_>>();
+    models.extend(bedrock_models);
+
     models
 }
 
@@ -0,0 +1,310 @@
+//! Validation and editing of models.json for `pi models add|remove|show`. Edits go through
+//! `serde_json::Value` so keys this version does not know about survive a rewrite.
+
+use crate::api::bedrock::BEDROCK_API;
+use crate::api::google::GOOGLE_GENERATIVE_AI_API;
+use crate::api::mock::MOCK_API;
+use crate::api::openai_completions::OPENAI_COMPLETIONS_API;
+use crate::error::{Error, Result};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::Path;
+
+/// APIs the CLI can stream; custom models must use one of these.
+pub const SUPPORTED_APIS: &[&str] = &[
+    "anthropic-messages",
+    "openai-responses",
+    OPENAI_COMPLETIONS_API,
+    "openai-codex-responses",
+    GOOGLE_GENERATIVE_AI_API,
+    "google-gemini-cli",
+    BEDROCK_API,
+    MOCK_API,
+];
+
+const INPUT_MODALITIES: &[&str] = &["text", "image"];
+const COST_FIELDS: &[&str] = &["input", "output", "cacheRead", "cacheWrite"];
+
+/// Provider-level fields `pi models add` may set alongside the model.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProviderFields {
+    pub base_url: Option<String>,
+    pub api: Option<String>,
+    pub api_key: Option<String>,
+    pub headers: Vec<(String, String)>,
+}
+
+/// Reads models.json as JSON; a missing file is an empty config.
+pub fn read_models_config(path: &Path) -> Result<Value> {
+    if !path.exists() {
+        return Ok(serde_json::json!({ "providers": {} }));
+    }
+    let content = fs::read_to_string(path)
+        .map_err(|err| Error::io(format_args!("Could not read {}", path.display()), err))?;
+    serde_json::from_str(&content)
+        .map_err(|err| Error::Config(format!("Invalid JSON in {}: {err}", path.display())))
+}
+
+/// Checks a models.json document against the schema the registry loads, reporting every
+/// problem as `providers.<name>.<field>: <message>`.
+pub fn validate_models_config(config: &Value) -> Result<()> {
+    let mut errors = Vec::new();
+    match config.get("providers") {
+        Some(Value::Object(providers)) => {
+            for (name, provider) in providers {
+                validate_provider(&format!("providers.{name}"), provider, &mut errors);
+            }
+        }
+        _ => errors.push("providers: expected an object".to_string()),
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Config(errors.join("\n")))
+    }
+}
+
+/// Adds `model` under `provider`, replacing a model with the same id, applies
+/// `fields` to the provider, validates the result and writes it.
+pub fn add_custom_model(
+    path: &Path,
+    provider: &str,
+    fields: &ProviderFields,
+    model: Value,
+) -> Result<()> {
+    let mut config = read_models_config(path)?;
+    let providers = providers_mut(&mut config)?;
+    let entry = providers
+        .entry(provider)
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or_else(|| Error::Config(format!("providers.{provider}: expected an object")))?;
+    if let Some(base_url) = &fields.base_url {
+        entry.insert("baseUrl".to_string(), Value::String(base_url.clone()));
+    }
+    if let Some(api) = &fields.api {
+        entry.insert("api".to_string(), Value::String(api.clone()));
+    }
+    if let Some(api_key) = &fields.api_key {
+        entry.insert("apiKey".to_string(), Value::String(api_key.clone()));
+    }
+    if !fields.headers.is_empty() {
+        let headers = entry
+            .entry("headers")
+            .or_insert_with(|| Value::Object(Map::new()));
+        if let Value::Object(headers) = headers {
+            for (key, value) in &fields.headers {
+                headers.insert(key.clone(), Value::String(value.clone()));
+            }
+        }
+    }
+    let models = entry
+        .entry("models")
+        .or_insert_with(|| Value::Array(Vec::new()))
+        .as_array_mut()
+        .ok_or_else(|| Error::Config(format!("providers.{provider}.models: expected an array")))?;
+    let id = model.get("id").cloned();
+    match models
+        .iter_mut()
+        .find(|existing| existing.get("id") == id.as_ref())
+    {
+        Some(existing) => *existing = model,
+        None => models.push(model),
+    }
+    validate_models_config(&config)?;
+    write_models_config(path, &config)
+}
+
+/// Removes a custom model; the provider goes too once it has no models left, so leftover
+/// provider fields do not turn into overrides of a built-in provider. False when absent.
+pub fn remove_custom_model(path: &Path, provider: &str, model_id: &str) -> Result<bool> {
+    let mut config = read_models_config(path)?;
+    let providers = providers_mut(&mut config)?;
+    let Some(models) = providers
+        .get_mut(provider)
+        .and_then(|entry| entry.get_mut("models"))
+        .and_then(Value::as_array_mut)
+    else {
+        return Ok(false);
+    };
+    let before = models.len();
+    models.retain(|model| model.get("id").and_then(Value::as_str) != Some(model_id));
+    if models.len() == before {
+        return Ok(false);
+    }
+    if models.is_empty() {
+        providers.remove(provider);
+    }
+    write_models_config(path, &config)?;
+    Ok(true)
+}
+
+fn providers_mut(config: &mut Value) -> Result<&mut Map<String, Value>> {
+    config
+        .as_object_mut()
+        .ok_or_else(|| Error::Config("models.json: expected an object".to_string()))?
+        .entry("providers")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or_else(|| Error::Config("providers: expected an object".to_string()))
+}
+
+fn write_models_config(path: &Path, config: &Value) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let content = serde_json::to_string_pretty(config)?;
+    fs::write(path, content)
+        .map_err(|err| Error::io(format_args!("Could not write {}", path.display()), err))
+}
+
+fn validate_provider(prefix: &str, provider: &Value, errors: &mut Vec<String>) {
+    let Some(provider) = provider.as_object() else {
+        errors.push(format!("{prefix}: expected an object"));
+        return;
+    };
+    if let Some(base_url) = provider.get("baseUrl") {
+        // Mock models read their responses from the fixture file baseUrl names.
+        let is_mock = provider.get("api").and_then(Value::as_str) == Some(MOCK_API);
+        match base_url.as_str() {
+            Some(url) if url.starts_with("http://") || url.starts_with("https://") => {}
+            Some(path) if is_mock && !path.trim().is_empty() => {}
+            _ if is_mock => errors.push(format!("{prefix}.baseUrl: expected a fixture path")),
+            _ => errors.push(format!("{prefix}.baseUrl: expected an http(s) URL")),
+        }
+    }
+    validate_api(&format!("{prefix}.api"), provider.get("api"), errors);
+    if provider.get("apiKey").is_some_and(|key| !key.is_string()) {
+        errors.push(format!("{prefix}.apiKey: expected a string"));
+    }
+    validate_headers(
+        &format!("{prefix}.headers"),
+        provider.get("headers"),
+        errors,
+    );
+    if let Some(windows) = provider.get("contextWindows") {
+        match windows.as_object() {
+            Some(windows) => {
+                for (id, window) in windows {
+                    validate_positive(&format!("{prefix}.contextWindows.{id}"), window, errors);
+                }
+            }
+            None => errors.push(format!("{prefix}.contextWindows: expected an object")),
+        }
+    }
+
+    let Some(models) = provider.get("models") else {
+        return;
+    };
+    let Some(models) = models.as_array() else {
+        errors.push(format!("{prefix}.models: expected an array"));
+        return;
+    };
+    if !models.is_empty() && !provider.contains_key("baseUrl") {
+        errors.push(format!("{prefix}.baseUrl: required for custom models"));
+    }
+    let mut seen = Vec::new();
+    for (index, model) in models.iter().enumerate() {
+        let model_prefix = format!("{prefix}.models[{index}]");
+        match model.get("id").and_then(Value::as_str) {
+            Some(id) if !id.trim().is_empty() => {
+                if seen.contains(&id) {
+                    errors.push(format!("{model_prefix}.id: duplicate model id {id}"));
+                }
+                seen.push(id);
+            }
+            _ => errors.push(format!("{model_prefix}.id: expected a non-empty string")),
+        }
+        validate_model(&model_prefix, model, errors);
+    }
+}
+
+fn validate_model(prefix: &str, model: &Value, errors: &mut Vec<String>) {
+    let Some(model) = model.as_object() else {
+        errors.push(format!("{prefix}: expected an object"));
+        return;
+    };
+    if model.get("name").is_some_and(|name| !name.is_string()) {
+        errors.push(format!("{prefix}.name: expected a string"));
+    }
+    validate_api(&format!("{prefix}.api"), model.get("api"), errors);
+    if model
+        .get("reasoning")
+        .is_some_and(|value| !value.is_boolean())
+    {
+        errors.push(format!("{prefix}.reasoning: expected true or false"));
+    }
+    if let Some(input) = model.get("input") {
+        let valid = input.as_array().is_some_and(|values| {
+            !values.is_empty()
+                && values.iter().all(|value| {
+                    value
+                        .as_str()
+                        .is_some_and(|value| INPUT_MODALITIES.contains(&value))
+                })
+        });
+        if !valid {
+            errors.push(format!(
+                "{prefix}.input: expected a non-empty list of {}",
+                INPUT_MODALITIES.join(", ")
+            ));
+        }
+    }
+    if let Some(cost) = model.get("cost") {
+        match cost.as_object() {
+            Some(cost) => {
+                for field in COST_FIELDS {
+                    let valid = cost
+                        .get(*field)
+                        .and_then(Value::as_f64)
+                        .is_some_and(|value| value >= 0.0);
+                    if !valid {
+                        errors.push(format!(
+                            "{prefix}.cost.{field}: expected a non-negative number"
+                        ));
+                    }
+                }
+            }
+            None => errors.push(format!("{prefix}.cost: expected an object")),
+        }
+    }
+    for field in ["contextWindow", "maxTokens"] {
+        if let Some(value) = model.get(field) {
+            validate_positive(&format!("{prefix}.{field}"), value, errors);
+        }
+    }
+    validate_headers(&format!("{prefix}.headers"), model.get("headers"), errors);
+}
+
+fn validate_api(prefix: &str, api: Option<&Value>, errors: &mut Vec<String>) {
+    let Some(api) = api else {
+        return;
+    };
+    if !api
+        .as_str()
+        .is_some_and(|api| SUPPORTED_APIS.contains(&api))
+    {
+        errors.push(format!(
+            "{prefix}: expected one of {}",
+            SUPPORTED_APIS.join(", ")
+        ));
+    }
+}
+
+fn validate_headers(prefix: &str, headers: Option<&Value>, errors: &mut Vec<String>) {
+    let Some(headers) = headers else {
+        return;
+    };
+    let valid = headers
+        .as_object()
+        .is_some_and(|headers| headers.values().all(Value::is_string));
+    if !valid {
+        errors.push(format!("{prefix}: expected an object of string values"));
+    }
+}
+
+fn validate_positive(prefix: &str, value: &Value, errors: &mut Vec<String>) {
+    if value.as_i64().is_none_or(|value| value <= 0) {
+        errors.push(format!("{prefix}: expected a positive integer"));
+    }
+}
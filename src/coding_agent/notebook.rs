@@ -0,0 +1,314 @@
+//! Jupyter notebooks (`.ipynb`) for the notebook tools. Cells are edited in the parsed JSON, so
+//! metadata, outputs and fields pi does not know about survive, and the file is written back
+//! the way Jupyter writes it: sorted keys, one-space indent and a trailing newline.
+
+use regex::Regex;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+/// Longest output rendered per output before it is cut.
+const MAX_OUTPUT_CHARS: usize = 2000;
+
+pub const CELL_TYPES: [&str; 3] = ["code", "markdown", "raw"];
+
+#[derive(Clone, Debug)]
+pub struct Notebook {
+    root: Map<String, Value>,
+}
+
+impl Notebook {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let value: Value =
+            serde_json::from_str(text).map_err(|err| format!("Invalid notebook JSON: {err}"))?;
+        let Value::Object(root) = value else {
+            return Err("Invalid notebook: expected a JSON object".to_string());
+        };
+        if !root.get("cells").is_some_and(Value::is_array) {
+            return Err("Invalid notebook: no cells array (nbformat 4 is required)".to_string());
+        }
+        Ok(Self { root })
+    }
+
+    fn cells(&self) -> &[Value] {
+        self.root["cells"].as_array().map_or(&[], Vec::as_slice)
+    }
+
+    fn cells_mut(&mut self) -> &mut Vec<Value> {
+        self.root
+            .get_mut("cells")
+            .and_then(Value::as_array_mut)
+            .expect("checked in parse")
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells().is_empty()
+    }
+
+    /// The kernel language, used to fence code cells.
+    pub fn language(&self) -> &str {
+        let metadata = &self.root.get("metadata").unwrap_or(&Value::Null);
+        metadata["language_info"]["name"]
+            .as_str()
+            .or_else(|| metadata["kernelspec"]["language"].as_str())
+            .unwrap_or("python")
+    }
+
+    /// Index of the cell with this nbformat cell id.
+    pub fn find_cell(&self, id: &str) -> Option<usize> {
+        self.cells()
+            .iter()
+            .position(|cell| cell["id"].as_str() == Some(id))
+    }
+
+    pub fn cell_source(&self, index: usize) -> Result<String, String> {
+        self.check_index(index)?;
+        Ok(source_text(&self.cells()[index]["source"]))
+    }
+
+    pub fn cell_type(&self, index: usize) -> Result<&str, String> {
+        self.check_index(index)?;
+        Ok(self.cells()[index]["cell_type"].as_str().unwrap_or("code"))
+    }
+
+    fn check_index(&self, index: usize) -> Result<(), String> {
+        match self.len() {
+            0 => Err(format!(
+                "Cell {index} does not exist; the notebook is empty"
+            )),
+            len if index >= len => Err(format!(
+                "Cell {index} does not exist; the notebook has cells 0-{}",
+                len - 1
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Renders cells `start..end` for the model: a header per cell, the source, and the
+    /// outputs of code cells when `include_outputs` is set.
+    pub fn render(&self, start: usize, end: usize, include_outputs: bool) -> String {
+        let language = self.language();
+        self.cells()
+            .iter()
+            .enumerate()
+            .take(end)
+            .skip(start)
+            .map(|(index, cell)| render_cell(index, cell, language, include_outputs))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    /// Replaces a cell's source, converting it to `cell_type` when given. A code cell whose
+    /// source changes loses its now stale outputs.
+    pub fn replace_cell(
+        &mut self,
+        index: usize,
+        source: &str,
+        cell_type: Option<&str>,
+    ) -> Result<(), String> {
+        self.check_index(index)?;
+        if let Some(cell_type) = cell_type {
+            check_cell_type(cell_type)?;
+        }
+        let cell = self.cells_mut()[index]
+            .as_object_mut()
+            .ok_or_else(|| format!("Cell {index} is not a JSON object"))?;
+        let old_type = cell
+            .get("cell_type")
+            .and_then(Value::as_str)
+            .unwrap_or("code")
+            .to_string();
+        let new_type = cell_type.unwrap_or(&old_type).to_string();
+        let changed = source_text(cell.get("source").unwrap_or(&Value::Null)) != source;
+        cell.insert("source".to_string(), source_lines(source));
+        cell.insert("cell_type".to_string(), json!(new_type));
+        if new_type == "code" {
+            if changed || old_type != "code" {
+                cell.insert("outputs".to_string(), json!([]));
+                cell.insert("execution_count".to_string(), Value::Null);
+            }
+        } else {
+            cell.remove("outputs");
+            cell.remove("execution_count");
+        }
+        Ok(())
+    }
+
+    /// Inserts a new cell so that it becomes cell `index`; `index` may be the cell count to
+    /// append.
+    pub fn insert_cell(
+        &mut self,
+        index: usize,
+        cell_type: &str,
+        source: &str,
+    ) -> Result<(), String> {
+        check_cell_type(cell_type)?;
+        let len = self.len();
+        if index > len {
+            return Err(format!(
+                "Cannot insert at cell {index}; the notebook has {len} cells"
+            ));
+        }
+        let mut cell = Map::new();
+        cell.insert("cell_type".to_string(), json!(cell_type));
+        cell.insert("metadata".to_string(), json!({}));
+        cell.insert("source".to_string(), source_lines(source));
+        if cell_type == "code" {
+            cell.insert("outputs".to_string(), json!([]));
+            cell.insert("execution_count".to_string(), Value::Null);
+        }
+        // Cell ids are part of nbformat 4.5 and later.
+        let minor = self.root.get("nbformat_minor").and_then(Value::as_u64);
+        if minor.is_some_and(|minor| minor >= 5) {
+            let id = uuid::Uuid::new_v4().simple().to_string()[..8].to_string();
+            cell.insert("id".to_string(), json!(id));
+        }
+        self.cells_mut().insert(index, Value::Object(cell));
+        Ok(())
+    }
+
+    pub fn delete_cell(&mut self, index: usize) -> Result<(), String> {
+        self.check_index(index)?;
+        self.cells_mut().remove(index);
+        Ok(())
+    }
+
+    /// Serializes the notebook the way Jupyter writes it.
+    pub fn to_json(&self) -> Result<String, String> {
+        let mut buffer = Vec::new();
+        let formatter = serde_json::ser::PrettyFormatter::with_indent(b" ");
+        let mut serializer = serde_json::Serializer::with_formatter(&mut buffer, formatter);
+        self.root
+            .serialize(&mut serializer)
+            .map_err(|err| err.to_string())?;
+        let mut text = String::from_utf8(buffer).map_err(|err| err.to_string())?;
+        text.push('\n');
+        Ok(text)
+    }
+}
+
+fn check_cell_type(cell_type: &str) -> Result<(), String> {
+    if CELL_TYPES.contains(&cell_type) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Unknown cell type \"{cell_type}\". Use one of: {}",
+            CELL_TYPES.join(", ")
+        ))
+    }
+}
+
+/// Cell sources and output texts are either a string or a list of lines.
+fn source_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Array(lines) => lines.iter().filter_map(Value::as_str).collect(),
+        _ => String::new(),
+    }
+}
+
+fn source_lines(source: &str) -> Value {
+    Value::Array(
+        source
+            .split_inclusive('\n')
+            .map(|line| json!(line))
+            .collect(),
+    )
+}
+
+fn render_cell(index: usize, cell: &Value, language: &str, include_outputs: bool) -> String {
+    let cell_type = cell["cell_type"].as_str().unwrap_or("code");
+    let mut header = format!("## Cell {index} [{cell_type}]");
+    if let Some(id) = cell["id"].as_str() {
+        header.push_str(&format!(" id={id}"));
+    }
+    if let Some(count) = cell["execution_count"].as_u64() {
+        header.push_str(&format!(" execution_count={count}"));
+    }
+
+    let source = source_text(&cell["source"]);
+    let body = match cell_type {
+        "markdown" => source,
+        "code" => format!("```{language}\n{source}\n```"),
+        _ => format!("```\n{source}\n```"),
+    };
+    let mut rendered = format!("{header}\n{body}");
+
+    let outputs = cell["outputs"].as_array().map_or(&[][..], Vec::as_slice);
+    if include_outputs && !outputs.is_empty() {
+        rendered.push_str("\nOutputs:");
+        for output in outputs {
+            rendered.push('\n');
+            rendered.push_str(&render_output(output));
+        }
+    }
+    rendered
+}
+
+fn render_output(output: &Value) -> String {
+    let text = match output["output_type"].as_str().unwrap_or("") {
+        "stream" => format!(
+            "[{}]\n{}",
+            output["name"].as_str().unwrap_or("stream"),
+            source_text(&output["text"]).trim_end()
+        ),
+        "error" => {
+            let traceback = output["traceback"]
+                .as_array()
+                .map(|lines| {
+                    lines
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                })
+                .unwrap_or_default();
+            format!(
+                "[error] {}: {}\n{}",
+                output["ename"].as_str().unwrap_or("Error"),
+                output["evalue"].as_str().unwrap_or(""),
+                strip_ansi(&traceback).trim_end()
+            )
+        }
+        kind => {
+            let data = output["data"].as_object();
+            let plain = data
+                .and_then(|data| data.get("text/plain"))
+                .map(source_text);
+            let omitted = data
+                .map(|data| {
+                    data.keys()
+                        .filter(|mime| mime.as_str() != "text/plain")
+                        .cloned()
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            let mut text = format!("[{kind}]");
+            if let Some(plain) = plain {
+                text.push('\n');
+                text.push_str(plain.trim_end());
+            }
+            if !omitted.is_empty() {
+                text.push_str(&format!("\n({} not shown)", omitted.join(", ")));
+            }
+            text
+        }
+    };
+    let length = text.chars().count();
+    if length > MAX_OUTPUT_CHARS {
+        let cut: String = text.chars().take(MAX_OUTPUT_CHARS).collect();
+        format!("{cut}\n... [{} more characters]", length - MAX_OUTPUT_CHARS)
+    } else {
+        text
+    }
+}
+
+/// Tracebacks carry terminal colors.
+fn strip_ansi(text: &str) -> String {
+    Regex::new(r"\x1b\[[0-9;]*[A-Za-z]")
+        .map(|ansi| ansi.replace_all(text, "").to_string())
+        .unwrap_or_else(|_| text.to_string())
+}
@@ -0,0 +1,361 @@
+//! Symbol outlines for the read tool. Declarations are found with per-language patterns and
+//! their extent from brace nesting or indentation, which is enough to outline a file or pull
+//! out one function without a full parser.
+
+use regex::Regex;
+use std::path::Path;
+
+const MAX_SIGNATURE_CHARS: usize = 120;
+
+/// Names a declaration pattern can match that are really control flow.
+const KEYWORDS: [&str; 15] = [
+    "if", "for", "while", "switch", "catch", "return", "else", "function", "do", "sizeof",
+    "typeof", "match", "with", "elif", "throw",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Extent {
+    /// The body is the next balanced `{ ... }`, or the declaration ends at a `;`.
+    Braces,
+    /// The body is every following line indented deeper than the declaration.
+    Indent,
+}
+
+struct Language {
+    extent: Extent,
+    /// Each pattern captures the symbol name as `name`.
+    patterns: &'static [&'static str],
+    /// Characters that open a string literal, during which braces do not count.
+    quotes: &'static [char],
+    separator: &'static str,
+}
+
+const RUST: Language = Language {
+    extent: Extent::Braces,
+    patterns: &[
+        r#"^\s*(?:pub(?:\([^)]*\))?\s+)?(?:(?:default|async|const|unsafe|extern\s+"[^"]*")\s+)*(?:fn|struct|enum|union|trait|mod|type)\s+(?P<name>\w+)"#,
+        r"^\s*macro_rules!\s*(?P<name>\w+)",
+        r"^\s*(?:unsafe\s+)?impl\b(?:\s*<.*?>)?\s+(?:.+?\s+for\s+)?&?(?:\w+::)*(?P<name>\w+)",
+        r"^\s*(?:pub(?:\([^)]*\))?\s+)?(?:const|static)\s+(?:mut\s+)?(?P<name>[A-Z_][A-Z0-9_]*)\s*:",
+    ],
+    quotes: &['"'],
+    separator: "::",
+};
+
+const PYTHON: Language = Language {
+    extent: Extent::Indent,
+    patterns: &[r"^\s*(?:async\s+)?(?:def|class)\s+(?P<name>\w+)"],
+    quotes: &['"', '\''],
+    separator: ".",
+};
+
+const JAVASCRIPT: Language = Language {
+    extent: Extent::Braces,
+    patterns: &[
+        r"^\s*(?:export\s+)?(?:default\s+)?(?:declare\s+)?(?:abstract\s+)?(?:async\s+)?(?:function\*?|class|interface|enum|namespace)\s+(?P<name>[\w$]+)",
+        r"^\s*(?:export\s+)?(?:declare\s+)?type\s+(?P<name>[\w$]+)\s*(?:<[^=]*>)?\s*=",
+        r"^\s*(?:export\s+)?(?:const|let|var)\s+(?P<name>[\w$]+)\s*(?::[^=]+)?=\s*(?:async\s+)?(?:function\b|\([^)]*\)\s*(?::[^=]+)?=>|[\w$]+\s*=>)",
+        r"^\s+(?:(?:public|private|protected|static|async|readonly|override|get|set)\s+)*(?P<name>[\w$]+)\s*(?:<[^>]*>)?\s*\([^;]*\)\s*(?::\s*[^={;]+)?\{\s*$",
+    ],
+    quotes: &['"', '\'', '`'],
+    separator: ".",
+};
+
+const GO: Language = Language {
+    extent: Extent::Braces,
+    patterns: &[
+        r"^func\s+(?:\([^)]*\)\s*)?(?P<name>\w+)",
+        r"^\s*type\s+(?P<name>\w+)\s",
+    ],
+    quotes: &['"', '`'],
+    separator: ".",
+};
+
+/// Java, C, C++, C#, Kotlin, Swift, Scala, PHP and Dart.
+const C_LIKE: Language = Language {
+    extent: Extent::Braces,
+    patterns: &[
+        r"^\s*(?:[\w@]+\s+)*?(?:class|interface|struct|enum|record|object|trait|protocol|extension|namespace)\s+(?:class\s+)?(?P<name>\w+)",
+        r"^\s*(?:[\w@]+\s+)*?(?:fun|func|def|function)\s+(?:<[^>]*>\s*)?(?:[\w.]+\.)?(?P<name>\w+)\s*[(<]",
+        r"^\s*(?:[\w:<>,*&\[\]]+\s+)+[*&]*(?:\w+::)*(?P<name>~?\w+)\s*\([^;]*\)\s*(?:const\s*)?(?:override\s*)?(?:noexcept\s*)?(?:throws\s+[\w.,\s]+)?\{?\s*$",
+    ],
+    quotes: &['"'],
+    separator: ".",
+};
+
+fn language_for(path: &Path) -> Option<&'static Language> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "rs" => Some(&RUST),
+        "py" | "pyi" => Some(&PYTHON),
+        "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "mts" | "cts" => Some(&JAVASCRIPT),
+        "go" => Some(&GO),
+        "java" | "kt" | "kts" | "cs" | "swift" | "scala" | "c" | "h" | "cc" | "cpp" | "cxx"
+        | "hpp" | "hh" | "php" | "dart" => Some(&C_LIKE),
+        _ => None,
+    }
+}
+
+/// A declaration found in a file. Line numbers are 1-based.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    /// The name qualified by its enclosing symbols, e.g. `Foo::new` or `Foo.bar`.
+    pub qualified_name: String,
+    /// Names of the enclosing symbols, outermost first.
+    pub parents: Vec<String>,
+    /// The declaration line, trimmed and without its opening brace.
+    pub signature: String,
+    pub line: usize,
+    /// First line of the symbol including leading doc comments, attributes and decorators.
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+/// Every symbol declared in `text`, in file order. Fails for file types without a pattern set.
+pub fn outline(path: &Path, text: &str) -> Result<Vec<Symbol>, String> {
+    let language = language_for(path).ok_or_else(|| {
+        format!(
+            "Symbols are not supported for {}; read it by offset and limit instead",
+            path.display()
+        )
+    })?;
+    let patterns = language
+        .patterns
+        .iter()
+        .map(|pattern| Regex::new(pattern).map_err(|err| err.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let lines: Vec<&str> = text.split('\n').collect();
+
+    let mut symbols: Vec<Symbol> = Vec::new();
+    let mut enclosing: Vec<usize> = Vec::new();
+    let mut in_block_comment = false;
+    for (index, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+        let comment_line =
+            in_block_comment || trimmed.starts_with("//") || trimmed.starts_with('*');
+        if trimmed.starts_with("/*") {
+            in_block_comment = true;
+        }
+        if in_block_comment && line.contains("*/") {
+            in_block_comment = false;
+        }
+        if comment_line || trimmed.starts_with("/*") {
+            continue;
+        }
+        let Some(name) = patterns
+            .iter()
+            .find_map(|pattern| pattern.captures(line))
+            .and_then(|captures| captures.name("name"))
+            .map(|name| name.as_str().to_string())
+        else {
+            continue;
+        };
+        if KEYWORDS.contains(&name.as_str()) || starts_with_keyword(trimmed) {
+            continue;
+        }
+
+        let line_number = index + 1;
+        while enclosing
+            .last()
+            .is_some_and(|&parent| symbols[parent].end_line < line_number)
+        {
+            enclosing.pop();
+        }
+        let parents: Vec<String> = enclosing
+            .iter()
+            .map(|&parent| symbols[parent].name.clone())
+            .collect();
+        let qualified_name = parents
+            .iter()
+            .chain(std::iter::once(&name))
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(language.separator);
+        let end_line = match language.extent {
+            Extent::Braces => brace_end(&lines, index, language.quotes),
+            Extent::Indent => indent_end(&lines, index),
+        } + 1;
+        symbols.push(Symbol {
+            name,
+            qualified_name,
+            parents,
+            signature: signature(trimmed),
+            line: line_number,
+            start_line: leading_comments_start(&lines, index, language.extent) + 1,
+            end_line,
+        });
+        enclosing.push(symbols.len() - 1);
+    }
+    Ok(symbols)
+}
+
+/// Symbols matching `query`, which is a name optionally qualified by enclosing symbols with
+/// `::` or `.` (`Foo::new`, `Foo.new`). A bare name matches at any depth.
+pub fn find_symbols<'a>(symbols: &'a [Symbol], query: &str) -> Vec<&'a Symbol> {
+    let segments: Vec<&str> = query
+        .split([':', '.'])
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    let Some((name, parents)) = segments.split_last() else {
+        return Vec::new();
+    };
+    symbols
+        .iter()
+        .filter(|symbol| symbol.name == *name && ends_with_names(&symbol.parents, parents))
+        .collect()
+}
+
+fn ends_with_names(parents: &[String], names: &[&str]) -> bool {
+    parents.len() >= names.len()
+        && parents[parents.len() - names.len()..]
+            .iter()
+            .zip(names)
+            .all(|(parent, name)| parent == name)
+}
+
+/// One line per symbol: its line range, then the signature indented by nesting depth.
+pub fn render_outline(symbols: &[Symbol]) -> String {
+    let width = symbols
+        .iter()
+        .map(|symbol| symbol.end_line.to_string().len())
+        .max()
+        .unwrap_or(1);
+    symbols
+        .iter()
+        .map(|symbol| {
+            let range = format!("{}-{}", symbol.line, symbol.end_line);
+            format!(
+                "{range:<range_width$}  {}{}",
+                "  ".repeat(symbol.parents.len()),
+                symbol.signature,
+                range_width = width * 2 + 1
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn starts_with_keyword(line: &str) -> bool {
+    let first = line
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .next()
+        .unwrap_or("");
+    [
+        "return", "else", "throw", "case", "await", "yield", "delete", "goto",
+    ]
+    .contains(&first)
+}
+
+fn signature(line: &str) -> String {
+    let signature = line
+        .trim()
+        .trim_end_matches('{')
+        .trim_end()
+        .trim_end_matches(':')
+        .to_string();
+    if signature.chars().count() > MAX_SIGNATURE_CHARS {
+        let cut: String = signature.chars().take(MAX_SIGNATURE_CHARS).collect();
+        format!("{cut}...")
+    } else {
+        signature
+    }
+}
+
+/// Index of the line that closes the declaration starting at `start`.
+fn brace_end(lines: &[&str], start: usize, quotes: &[char]) -> usize {
+    let mut depth = 0usize;
+    let mut nesting = 0usize;
+    let mut opened = false;
+    let mut in_block_comment = false;
+    let mut quote: Option<char> = None;
+    for (index, line) in lines.iter().enumerate().skip(start) {
+        // Only template strings span lines; an unterminated quote is more likely a lifetime or
+        // apostrophe than a multi-line literal.
+        if quote != Some('`') {
+            quote = None;
+        }
+        let mut chars = line.chars().peekable();
+        while let Some(ch) = chars.next() {
+            if in_block_comment {
+                if ch == '*' && chars.peek() == Some(&'/') {
+                    chars.next();
+                    in_block_comment = false;
+                }
+            } else if let Some(open) = quote {
+                if ch == '\\' {
+                    chars.next();
+                } else if ch == open {
+                    quote = None;
+                }
+            } else if ch == '/' && chars.peek() == Some(&'/') {
+                break;
+            } else if ch == '/' && chars.peek() == Some(&'*') {
+                chars.next();
+                in_block_comment = true;
+            } else if quotes.contains(&ch) {
+                quote = Some(ch);
+            } else if ch == '(' || ch == '[' {
+                nesting += 1;
+            } else if ch == ')' || ch == ']' {
+                nesting = nesting.saturating_sub(1);
+            } else if ch == '{' {
+                depth += 1;
+                opened = true;
+            } else if ch == '}' {
+                depth = depth.saturating_sub(1);
+                if opened && depth == 0 {
+                    return index;
+                }
+            } else if ch == ';' && !opened && nesting == 0 {
+                return index;
+            }
+        }
+    }
+    lines.len().saturating_sub(1)
+}
+
+/// Index of the last line indented deeper than the declaration at `start`.
+fn indent_end(lines: &[&str], start: usize) -> usize {
+    let indent = indentation(lines[start]);
+    // A signature can wrap; the body starts after the line ending in `:`.
+    let mut body = start;
+    while body < lines.len() && !lines[body].trim_end().ends_with(':') {
+        body += 1;
+    }
+    let mut end = body.min(lines.len().saturating_sub(1));
+    for (index, line) in lines.iter().enumerate().skip(body + 1) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if indentation(line) <= indent {
+            break;
+        }
+        end = index;
+    }
+    end
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Index of the first doc comment, attribute or decorator line directly above `index`.
+fn leading_comments_start(lines: &[&str], index: usize, extent: Extent) -> usize {
+    let mut start = index;
+    while start > 0 {
+        let previous = lines[start - 1].trim_start();
+        let attached = previous.starts_with("//")
+            || previous.starts_with("#[")
+            || previous.starts_with('@')
+            || previous.starts_with("/*")
+            || previous.starts_with('*')
+            || (extent == Extent::Indent && previous.starts_with('#'));
+        if !attached {
+            break;
+        }
+        start -= 1;
+    }
+    start
+}
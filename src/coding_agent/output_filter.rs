@@ -0,0 +1,325 @@
+use crate::agent::{AgentMessage, LlmContext, Model, StreamEvents, StreamFn};
+use crate::coding_agent::agent_session::{SettingsOutputFilterRule, SettingsOutputFilters};
+use crate::coding_agent::extension_host::ExtensionHost;
+use crate::core::messages::{AssistantMessage, ContentBlock, UserContent, UserMessage};
+use regex::Regex;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+const DEFAULT_REDACTION: &str = "[REDACTED]";
+
+const MODERATION_SYSTEM_PROMPT: &str = concat!(
+    "You are a content moderation classifier. Decide whether the assistant output below may be ",
+    "shown to the user under the acceptable use policy. Reply with exactly ALLOW, or ",
+    "BLOCK: <short reason>. Do not add anything else."
+);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum OutputFilterVerdict {
+    Allow,
+    Replace(Vec<ContentBlock>),
+    Block(String),
+}
+
+/// A policy check over assistant output, run before the output is displayed or persisted.
+pub trait OutputFilter {
+    fn name(&self) -> &str;
+
+    /// Whether the filter also runs on every streaming update. Only cheap local checks should.
+    fn checks_partial(&self) -> bool {
+        false
+    }
+
+    fn check(&self, content: &[ContentBlock]) -> Result<OutputFilterVerdict, String>;
+}
+
+/// Filters applied in order; a block short-circuits the rest.
+pub struct OutputFilterChain {
+    filters: Vec<Box<dyn OutputFilter>>,
+    fail_closed: bool,
+}
+
+impl OutputFilterChain {
+    /// With `fail_closed`, a filter that errors blocks the output instead of letting it through.
+    pub fn new(fail_closed: bool) -> Self {
+        Self {
+            filters: Vec::new(),
+            fail_closed,
+        }
+    }
+
+    pub fn push(&mut self, filter: Box<dyn OutputFilter>) {
+        self.filters.push(filter);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+    }
+
+    pub fn filter_names(&self) -> Vec<String> {
+        self.filters
+            .iter()
+            .map(|filter| filter.name().to_string())
+            .collect()
+    }
+
+    pub fn apply(&self, mut message: AssistantMessage, partial: bool) -> AssistantMessage {
+        for filter in &self.filters {
+            if partial && !filter.checks_partial() {
+                continue;
+            }
+            let verdict = match filter.check(&message.content) {
+                Ok(verdict) => verdict,
+                Err(err) if self.fail_closed => {
+                    OutputFilterVerdict::Block(format!("{} filter failed: {err}", filter.name()))
+                }
+                Err(_) => continue,
+            };
+            match verdict {
+                OutputFilterVerdict::Allow => {}
+                OutputFilterVerdict::Replace(content) => message.content = content,
+                OutputFilterVerdict::Block(reason) => return block_message(message, &reason),
+            }
+        }
+        message
+    }
+}
+
+fn block_message(mut message: AssistantMessage, reason: &str) -> AssistantMessage {
+    message.content = vec![ContentBlock::Text {
+        text: format!("[Response blocked by output policy: {reason}]"),
+        text_signature: None,
+    }];
+    // The blocked content may have carried tool calls; none of them should run.
+    if message.stop_reason == "toolUse" {
+        message.stop_reason = "stop".to_string();
+    }
+    message
+}
+
+struct RegexRule {
+    regex: Regex,
+    block: bool,
+    replacement: String,
+    reason: String,
+}
+
+/// Redacts or blocks text and thinking blocks matching configured patterns.
+pub struct RegexOutputFilter {
+    rules: Vec<RegexRule>,
+}
+
+impl RegexOutputFilter {
+    pub fn from_rules(rules: &[SettingsOutputFilterRule]) -> Result<Self, String> {
+        let mut compiled = Vec::with_capacity(rules.len());
+        for rule in rules {
+            let regex = Regex::new(&rule.pattern).map_err(|err| {
+                format!("Invalid output filter pattern \"{}\": {err}", rule.pattern)
+            })?;
+            let block = match rule.action.as_deref().unwrap_or("redact") {
+                "redact" => false,
+                "block" => true,
+                other => {
+                    return Err(format!(
+                        "Invalid output filter action \"{other}\" (expected redact or block)"
+                    ))
+                }
+            };
+            compiled.push(RegexRule {
+                regex,
+                block,
+                replacement: rule
+                    .replacement
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_REDACTION.to_string()),
+                reason: rule
+                    .reason
+                    .clone()
+                    .unwrap_or_else(|| format!("matched \"{}\"", rule.pattern)),
+            });
+        }
+        Ok(Self { rules: compiled })
+    }
+}
+
+impl OutputFilter for RegexOutputFilter {
+    fn name(&self) -> &str {
+        "regex"
+    }
+
+    fn checks_partial(&self) -> bool {
+        true
+    }
+
+    fn check(&self, content: &[ContentBlock]) -> Result<OutputFilterVerdict, String> {
+        let mut changed = false;
+        let mut filtered = content.to_vec();
+        for block in &mut filtered {
+            let text = match block {
+                ContentBlock::Text { text, .. } => text,
+                ContentBlock::Thinking { thinking, .. } => thinking,
+                _ => continue,
+            };
+            for rule in &self.rules {
+                if !rule.regex.is_match(text) {
+                    continue;
+                }
+                if rule.block {
+                    return Ok(OutputFilterVerdict::Block(rule.reason.clone()));
+                }
+                *text = rule
+                    .regex
+                    .replace_all(text, rule.replacement.as_str())
+                    .into_owned();
+                changed = true;
+            }
+        }
+        Ok(if changed {
+            OutputFilterVerdict::Replace(filtered)
+        } else {
+            OutputFilterVerdict::Allow
+        })
+    }
+}
+
+/// Forwards final output to extensions handling the `assistant_output` event.
+pub struct ExtensionOutputFilter {
+    host: Rc<RefCell<ExtensionHost>>,
+}
+
+impl ExtensionOutputFilter {
+    pub fn new(host: Rc<RefCell<ExtensionHost>>) -> Self {
+        Self { host }
+    }
+}
+
+impl OutputFilter for ExtensionOutputFilter {
+    fn name(&self) -> &str {
+        "extension"
+    }
+
+    fn check(&self, content: &[ContentBlock]) -> Result<OutputFilterVerdict, String> {
+        let result = self
+            .host
+            .try_borrow_mut()
+            .map_err(|_| "extension host is busy".to_string())?
+            .emit_assistant_output(content)?;
+        if result.block.unwrap_or(false) {
+            return Ok(OutputFilterVerdict::Block(
+                result
+                    .reason
+                    .unwrap_or_else(|| "blocked by an extension".to_string()),
+            ));
+        }
+        Ok(match result.content {
+            Some(content) => OutputFilterVerdict::Replace(content),
+            None => OutputFilterVerdict::Allow,
+        })
+    }
+}
+
+/// Asks a (cheap) model to classify the final output as ALLOW or BLOCK.
+pub struct ModerationModelFilter {
+    model: Model,
+    stream_fn: RefCell<Box<StreamFn>>,
+}
+
+impl ModerationModelFilter {
+    pub fn new(model: Model, stream_fn: Box<StreamFn>) -> Self {
+        Self {
+            model,
+            stream_fn: RefCell::new(stream_fn),
+        }
+    }
+}
+
+impl OutputFilter for ModerationModelFilter {
+    fn name(&self) -> &str {
+        "moderation"
+    }
+
+    fn check(&self, content: &[ContentBlock]) -> Result<OutputFilterVerdict, String> {
+        let text = content
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        if text.trim().is_empty() {
+            return Ok(OutputFilterVerdict::Allow);
+        }
+
+        let context = LlmContext {
+            system_prompt: MODERATION_SYSTEM_PROMPT.to_string(),
+            messages: vec![AgentMessage::User(UserMessage {
+                content: UserContent::Text(text),
+                timestamp: 0,
+            })],
+        };
+        let mut events = StreamEvents::new(Box::new(|_| {}));
+        let response = (self
+            .stream_fn
+            .try_borrow_mut()
+            .map_err(|_| "moderation model is busy".to_string())?)(
+            &self.model,
+            &context,
+            &mut events,
+        );
+        if response.stop_reason == "error" {
+            return Err(response
+                .error_message
+                .unwrap_or_else(|| "moderation request failed".to_string()));
+        }
+        parse_moderation_verdict(&response)
+    }
+}
+
+fn parse_moderation_verdict(response: &AssistantMessage) -> Result<OutputFilterVerdict, String> {
+    let reply = response
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text { text, .. } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<String>();
+    let reply = reply.trim();
+    let upper = reply.to_ascii_uppercase();
+    if upper.starts_with("ALLOW") {
+        return Ok(OutputFilterVerdict::Allow);
+    }
+    if upper.starts_with("BLOCK") {
+        let reason = reply["BLOCK".len()..]
+            .trim_start_matches(|ch: char| ch == ':' || ch.is_whitespace())
+            .trim();
+        let reason = if reason.is_empty() {
+            "flagged by moderation model"
+        } else {
+            reason
+        };
+        return Ok(OutputFilterVerdict::Block(reason.to_string()));
+    }
+    Err(format!("unexpected moderation reply \"{reply}\""))
+}
+
+/// Build the regex and extension filters configured in settings. The moderation model filter
+/// needs provider credentials and is added by the caller.
+pub fn build_output_filter_chain(
+    settings: &SettingsOutputFilters,
+    extension_host: Option<Rc<RefCell<ExtensionHost>>>,
+) -> Result<OutputFilterChain, String> {
+    let mut chain = OutputFilterChain::new(settings.fail_closed.unwrap_or(true));
+    if let Some(rules) = settings.rules.as_deref().filter(|rules| !rules.is_empty()) {
+        chain.push(Box::new(RegexOutputFilter::from_rules(rules)?));
+    }
+    if settings.extensions.unwrap_or(true) {
+        if let Some(host) = extension_host {
+            if host.borrow().has_handlers("assistant_output") {
+                chain.push(Box::new(ExtensionOutputFilter::new(host)));
+            }
+        }
+    }
+    Ok(chain)
+}
@@ -0,0 +1,181 @@
+use crate::agent::ThinkingLevel;
+use crate::coding_agent::prompt_templates::parse_frontmatter;
+use crate::config;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const READ_ONLY_TOOLS: [&str; 4] = ["read", "grep", "find", "ls"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PermissionProfile {
+    ReadOnly,
+    Full,
+}
+
+impl PermissionProfile {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "read-only" | "readonly" => Some(Self::ReadOnly),
+            "full" => Some(Self::Full),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ReadOnly => "read-only",
+            Self::Full => "full",
+        }
+    }
+
+    pub fn allows_tool(&self, name: &str) -> bool {
+        match self {
+            Self::ReadOnly => READ_ONLY_TOOLS.contains(&name),
+            Self::Full => true,
+        }
+    }
+}
+
+/// Named preset bundling a system prompt with model, thinking, tool and permission defaults.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Persona {
+    pub name: String,
+    pub description: String,
+    pub system_prompt: String,
+    pub model: Option<String>,
+    pub thinking: Option<ThinkingLevel>,
+    pub tools: Option<Vec<String>>,
+    pub permissions: PermissionProfile,
+    pub source: String,
+}
+
+impl Persona {
+    /// Narrow `available` to the persona's tool list and permission profile.
+    pub fn filter_tools(&self, available: &[String]) -> Vec<String> {
+        available
+            .iter()
+            .filter(|name| {
+                self.tools
+                    .as_ref()
+                    .is_none_or(|tools| tools.iter().any(|tool| tool == *name))
+            })
+            .filter(|name| self.permissions.allows_tool(name))
+            .cloned()
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct LoadPersonasOptions {
+    pub cwd: Option<PathBuf>,
+    pub agent_dir: Option<PathBuf>,
+}
+
+/// Load personas from `<agent dir>/personas` and `<cwd>/.pi/personas`; project files win on name clashes.
+pub fn load_personas(options: LoadPersonasOptions) -> Vec<Persona> {
+    let cwd = options
+        .cwd
+        .or_else(|| env::current_dir().ok())
+        .unwrap_or_else(|| PathBuf::from("."));
+    let agent_dir = options.agent_dir.unwrap_or_else(config::get_agent_dir);
+
+    let mut personas = load_personas_from_dir(&agent_dir.join("personas"), "user");
+    let project = load_personas_from_dir(
+        &cwd.join(config::config_dir_name()).join("personas"),
+        "project",
+    );
+    for persona in project {
+        personas.retain(|existing| existing.name != persona.name);
+        personas.push(persona);
+    }
+    personas.sort_by(|a, b| a.name.cmp(&b.name));
+    personas
+}
+
+pub fn find_persona<'a>(personas: &'a [Persona], name: &str) -> Option<&'a Persona> {
+    personas.iter().find(|persona| persona.name == name)
+}
+
+pub fn parse_persona(name: &str, raw_content: &str, source: &str) -> Result<Persona, String> {
+    let (frontmatter, content) = parse_frontmatter(raw_content);
+    let thinking = match frontmatter.get("thinking") {
+        Some(value) => Some(parse_thinking_level(value).ok_or_else(|| {
+            format!("Persona \"{name}\" has an invalid thinking level \"{value}\"")
+        })?),
+        None => None,
+    };
+    let permissions = match frontmatter.get("permissions") {
+        Some(value) => PermissionProfile::parse(value).ok_or_else(|| {
+            format!(
+                "Persona \"{name}\" has an invalid permission profile \"{value}\". Use read-only or full."
+            )
+        })?,
+        None => PermissionProfile::Full,
+    };
+    let tools = frontmatter.get("tools").map(|value| {
+        value
+            .split(',')
+            .map(|tool| tool.trim().to_string())
+            .filter(|tool| !tool.is_empty())
+            .collect::<Vec<_>>()
+    });
+    let model = frontmatter
+        .get("model")
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+    let description = frontmatter
+        .get("description")
+        .cloned()
+        .unwrap_or_else(|| content.lines().next().unwrap_or("").trim().to_string());
+
+    Ok(Persona {
+        name: name.to_string(),
+        description,
+        system_prompt: content,
+        model,
+        thinking,
+        tools,
+        permissions,
+        source: source.to_string(),
+    })
+}
+
+fn parse_thinking_level(value: &str) -> Option<ThinkingLevel> {
+    match value.trim() {
+        "off" => Some(ThinkingLevel::Off),
+        "minimal" => Some(ThinkingLevel::Minimal),
+        "low" => Some(ThinkingLevel::Low),
+        "medium" => Some(ThinkingLevel::Medium),
+        "high" => Some(ThinkingLevel::High),
+        "xhigh" => Some(ThinkingLevel::XHigh),
+        _ => None,
+    }
+}
+
+fn load_personas_from_dir(dir: &Path, source: &str) -> Vec<Persona> {
+    let mut personas = Vec::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return personas;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let Some(name) = file_name.strip_suffix(".md") else {
+            continue;
+        };
+        if !path.is_file() {
+            continue;
+        }
+        let Ok(raw_content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        match parse_persona(name, &raw_content, source) {
+            Ok(persona) => personas.push(persona),
+            Err(err) => eprintln!("Warning: {err} ({})", path.display()),
+        }
+    }
+
+    personas
+}
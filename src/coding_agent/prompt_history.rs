@@ -0,0 +1,49 @@
+use crate::config;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Maximum number of prompts kept in the history file.
+pub const PROMPT_HISTORY_LIMIT: usize = 1000;
+
+pub fn get_prompt_history_path() -> PathBuf {
+    config::get_agent_dir().join("history")
+}
+
+/// Load saved prompts, most recent first. Each line of the file is a JSON string so
+/// multi-line prompts round-trip; unreadable lines are skipped.
+pub fn load_prompt_history(path: &Path) -> Vec<String> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let mut entries = content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<String>(line).ok())
+        .filter(|entry| !entry.trim().is_empty())
+        .collect::<Vec<_>>();
+    entries.reverse();
+    entries.truncate(PROMPT_HISTORY_LIMIT);
+    entries
+}
+
+/// Append a prompt, dropping earlier copies of it and the oldest entries beyond the limit.
+pub fn append_prompt_history(path: &Path, prompt: &str) -> Result<(), String> {
+    let prompt = prompt.trim();
+    if prompt.is_empty() {
+        return Ok(());
+    }
+    let mut entries = load_prompt_history(path);
+    entries.retain(|entry| entry != prompt);
+    entries.insert(0, prompt.to_string());
+    entries.truncate(PROMPT_HISTORY_LIMIT);
+
+    let mut content = String::new();
+    for entry in entries.iter().rev() {
+        let line = serde_json::to_string(entry).map_err(|err| err.to_string())?;
+        content.push_str(&line);
+        content.push('\n');
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+    fs::write(path, content).map_err(|err| err.to_string())
+}
@@ -13,6 +13,19 @@ pub struct PromptTemplate {
     pub source: String,
 }
 
+/// A `{{name}}` placeholder in a template, or `{{name|default}}` when it may be left out.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TemplateArgument {
+    pub name: String,
+    pub default: Option<String>,
+}
+
+impl TemplateArgument {
+    pub fn is_required(&self) -> bool {
+        self.default.is_none()
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct LoadPromptTemplatesOptions {
     pub cwd: Option<PathBuf>,
@@ -52,9 +65,19 @@ pub fn load_prompt_templates(options: LoadPromptTemplatesOptions) -> Vec<PromptT
     templates
 }
 
+/// Expands `/name args` with the template called `name`. Text that does not name a template,
+/// or that leaves out a required argument, is returned unchanged.
 pub fn expand_prompt_template(text: &str, templates: &[PromptTemplate]) -> String {
+    try_expand_prompt_template(text, templates).unwrap_or_else(|_| text.to_string())
+}
+
+/// Like [`expand_prompt_template`], but reports missing template arguments.
+pub fn try_expand_prompt_template(
+    text: &str,
+    templates: &[PromptTemplate],
+) -> Result<String, String> {
     if !text.starts_with('/') {
-        return text.to_string();
+        return Ok(text.to_string());
     }
 
     let (name, args_string) = match text.find(' ') {
@@ -63,19 +86,133 @@ pub fn expand_prompt_template(text: &str, templates: &[PromptTemplate]) -> Strin
     };
 
     if name.is_empty() {
-        return text.to_string();
+        return Ok(text.to_string());
     }
 
     let template = templates.iter().find(|template| template.name == name);
     if let Some(template) = template {
         let args = parse_command_args(args_string);
-        return substitute_args(&template.content, &args);
+        return render_prompt_template(template, &args);
+    }
+
+    Ok(text.to_string())
+}
+
+/// The `{{name}}` placeholders of a template, in the order they first appear.
+pub fn template_arguments(content: &str) -> Vec<TemplateArgument> {
+    let mut arguments: Vec<TemplateArgument> = Vec::new();
+    for (_, argument) in find_placeholders(content) {
+        if !arguments
+            .iter()
+            .any(|existing| existing.name == argument.name)
+        {
+            arguments.push(argument);
+        }
+    }
+    arguments
+}
+
+/// Fills in `template` from `args`. An argument written `name=value` sets the `{{name}}`
+/// placeholder; the others fill the remaining placeholders in order and are also available
+/// as `$1`, `$ARGUMENTS` and `$@`.
+pub fn render_prompt_template<S: AsRef<str>>(
+    template: &PromptTemplate,
+    args: &[S],
+) -> Result<String, String> {
+    let arguments = template_arguments(&template.content);
+    let mut values: HashMap<&str, String> = HashMap::new();
+    let mut positional = Vec::new();
+    for arg in args {
+        let arg = arg.as_ref();
+        let named = arg.split_once('=').and_then(|(key, value)| {
+            arguments
+                .iter()
+                .find(|argument| argument.name == key)
+                .map(|argument| (argument.name.as_str(), value))
+        });
+        match named {
+            Some((name, value)) => {
+                values.insert(name, value.to_string());
+            }
+            None => positional.push(arg),
+        }
+    }
+
+    let mut remaining = positional.iter();
+    for argument in &arguments {
+        if values.contains_key(argument.name.as_str()) {
+            continue;
+        }
+        let value = match (remaining.next(), &argument.default) {
+            (Some(value), _) => value.to_string(),
+            (None, Some(default)) => default.clone(),
+            (None, None) => {
+                let usage = arguments
+                    .iter()
+                    .map(|argument| match argument.is_required() {
+                        true => format!("<{}>", argument.name),
+                        false => format!("[{}]", argument.name),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                return Err(format!(
+                    "Template \"{}\" is missing required argument \"{}\". Usage: /{} {usage}",
+                    template.name, argument.name, template.name
+                ));
+            }
+        };
+        values.insert(argument.name.as_str(), value);
+    }
+
+    let content = substitute_args(&template.content, &positional);
+    let mut rendered = String::with_capacity(content.len());
+    let mut copied = 0;
+    for ((start, end), argument) in find_placeholders(&content) {
+        rendered.push_str(&content[copied..start]);
+        rendered.push_str(
+            values
+                .get(argument.name.as_str())
+                .map_or("", String::as_str),
+        );
+        copied = end;
     }
+    rendered.push_str(&content[copied..]);
+    Ok(rendered)
+}
 
-    text.to_string()
+fn find_placeholders(content: &str) -> Vec<((usize, usize), TemplateArgument)> {
+    let mut placeholders = Vec::new();
+    let mut offset = 0;
+    while let Some(found) = content[offset..].find("{{") {
+        let start = offset + found;
+        let Some(length) = content[start + 2..].find("}}") else {
+            break;
+        };
+        let end = start + 2 + length + 2;
+        let inner = &content[start + 2..end - 2];
+        let (name, default) = match inner.split_once('|') {
+            Some((name, default)) => (name.trim(), Some(default.trim().to_string())),
+            None => (inner.trim(), None),
+        };
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '-');
+        if valid {
+            let argument = TemplateArgument {
+                name: name.to_string(),
+                default,
+            };
+            placeholders.push(((start, end), argument));
+            offset = end;
+        } else {
+            offset = start + 2;
+        }
+    }
+    placeholders
 }
 
-fn parse_frontmatter(content: &str) -> (HashMap<String, String>, String) {
+pub(crate) fn parse_frontmatter(content: &str) -> (HashMap<String, String>, String) {
     let mut frontmatter = HashMap::new();
     if !content.starts_with("---") {
         return (frontmatter, content.to_string());
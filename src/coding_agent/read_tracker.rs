@@ -0,0 +1,122 @@
+//! What the file tools last saw of each file, so a write or edit can refuse to clobber a file
+//! that changed on disk (say, a concurrent edit by the user) since the agent read it.
+
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::SystemTime;
+
+use serde_json::json;
+
+use crate::agent::ToolError;
+
+/// Lines of the current content shown when a file turns out to be stale.
+const SNIPPET_LINES: usize = 20;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+    hash: u64,
+}
+
+impl FileStamp {
+    fn new(path: &Path, content: &[u8]) -> Self {
+        Self {
+            modified: fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok(),
+            len: content.len() as u64,
+            hash: hash_bytes(content),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ReadTracker {
+    files: HashMap<PathBuf, FileStamp>,
+}
+
+pub type SharedReadTracker = Rc<RefCell<ReadTracker>>;
+
+impl ReadTracker {
+    /// Remember `content` as what the agent last saw of `path`, after a read or the tool's own
+    /// write.
+    pub fn record(&mut self, path: &Path, content: &[u8]) {
+        self.files
+            .insert(path.to_path_buf(), FileStamp::new(path, content));
+    }
+
+    pub fn is_tracked(&self, path: &Path) -> bool {
+        self.files.contains_key(path)
+    }
+
+    /// Errors when `path` was read earlier and has since changed or disappeared. Files never
+    /// read are not checked. A touched file with the same content is fine. The error's
+    /// details are `{"code": "stale_read", "path", "current"}`, `current` being the snippet
+    /// of the new content, or null for a deleted file.
+    pub fn check(&mut self, path: &Path, display_path: &str) -> Result<(), ToolError> {
+        let Some(stamp) = self.files.get(path) else {
+            return Ok(());
+        };
+        let metadata = fs::metadata(path).ok();
+        let modified = metadata
+            .as_ref()
+            .and_then(|metadata| metadata.modified().ok());
+        let len = metadata.as_ref().map(|metadata| metadata.len());
+        if modified.is_some() && modified == stamp.modified && len == Some(stamp.len) {
+            return Ok(());
+        }
+        let Ok(current) = fs::read(path) else {
+            return Err(ToolError::with_details(
+                format!(
+                    "File changed on disk: {display_path} was deleted since you last read it. Nothing was written."
+                ),
+                json!({ "code": "stale_read", "path": display_path, "current": null }),
+            ));
+        };
+        if current.len() as u64 == stamp.len && hash_bytes(&current) == stamp.hash {
+            self.record(path, &current);
+            return Ok(());
+        }
+        Err(stale_error(
+            display_path,
+            &String::from_utf8_lossy(&current),
+        ))
+    }
+}
+
+fn hash_bytes(content: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The tool error for a stale file, with the start of its current content so the model can
+/// see what changed without another read.
+fn stale_error(display_path: &str, current: &str) -> ToolError {
+    let lines: Vec<&str> = current.lines().collect();
+    let width = lines.len().min(SNIPPET_LINES).to_string().len();
+    let mut snippet = lines
+        .iter()
+        .take(SNIPPET_LINES)
+        .enumerate()
+        .map(|(index, line)| format!("{:>width$}\t{line}", index + 1))
+        .collect::<Vec<_>>()
+        .join("\n");
+    if lines.len() > SNIPPET_LINES {
+        snippet.push_str(&format!("\n[{} more lines]", lines.len() - SNIPPET_LINES));
+    }
+    ToolError::with_details(
+        format!(
+            "File changed on disk: {display_path} was modified since you last read it, probably \
+             by someone else. Nothing was written. Read the file again and redo your change \
+             against its current content.\n\nCurrent content:\n{snippet}"
+        ),
+        json!({ "code": "stale_read", "path": display_path, "current": snippet }),
+    )
+}
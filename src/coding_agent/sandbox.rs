@@ -0,0 +1,225 @@
+//! Workspace sandbox for the built-in tools. File tools may only touch the cwd and the
+//! configured `sandbox.allowedPaths`; writes to credentials and dot-directories such as
+//! `.git` and `.pi` are refused. Bash can optionally run under `firejail` (Linux) or
+//! `sandbox-exec` (macOS). `--dangerously-allow-all` turns all of this off.
+
+use crate::coding_agent::agent_session::SettingsSandbox;
+use crate::coding_agent::Shell;
+use crate::config;
+use std::env;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+/// Path components that tools may never write below, wherever they appear.
+const PROTECTED_NAMES: [&str; 6] = [".git", ".pi", ".env", ".ssh", ".aws", ".gnupg"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SandboxPolicy {
+    pub enabled: bool,
+    /// Directories file tools may read and write: the cwd plus allow-listed paths.
+    pub roots: Vec<PathBuf>,
+    pub wrap_bash: bool,
+}
+
+impl SandboxPolicy {
+    /// Restrict tools to `cwd`.
+    pub fn new(cwd: &Path) -> Self {
+        Self {
+            enabled: true,
+            roots: vec![normalize_path(cwd)],
+            wrap_bash: false,
+        }
+    }
+
+    /// No restrictions (`--dangerously-allow-all`).
+    pub fn allow_all() -> Self {
+        Self {
+            enabled: false,
+            roots: Vec::new(),
+            wrap_bash: false,
+        }
+    }
+
+    pub fn from_settings(settings: &SettingsSandbox, cwd: &Path) -> Self {
+        if !settings.enabled.unwrap_or(true) {
+            return Self::allow_all();
+        }
+        let mut policy = Self::new(cwd);
+        for path in settings.allowed_paths.iter().flatten() {
+            policy = policy.with_allowed_path(&cwd.join(expand_home(path)));
+        }
+        policy.wrap_bash = settings.wrap_bash.unwrap_or(false);
+        policy
+    }
+
+    pub fn with_allowed_path(mut self, path: &Path) -> Self {
+        self.roots.push(normalize_path(path));
+        self
+    }
+
+    /// Reads are allowed inside the roots, the temp dir (where truncated bash output is
+    /// saved) and the agent dir (skills, prompts), except for stored credentials.
+    pub fn check_read(&self, path: &Path) -> Result<(), String> {
+        if !self.enabled {
+            return Ok(());
+        }
+        let path = normalize_path(path);
+        if is_credentials_file(&path) {
+            return Err(format!(
+                "Access denied: {} contains credentials",
+                path.display()
+            ));
+        }
+        let readable = self.root_for(&path).is_some()
+            || path.starts_with(normalize_path(&env::temp_dir()))
+            || path.starts_with(normalize_path(&config::get_agent_dir()));
+        if readable {
+            Ok(())
+        } else {
+            Err(outside_workspace_error(&path))
+        }
+    }
+
+    /// Writes are allowed inside the roots and the temp dir, except below protected
+    /// dot-directories (`.git`, `.pi`, `.ssh`, ...) and to stored credentials.
+    pub fn check_write(&self, path: &Path) -> Result<(), String> {
+        if !self.enabled {
+            return Ok(());
+        }
+        let path = normalize_path(path);
+        let temp_dir = normalize_path(&env::temp_dir());
+        let Some(root) = self
+            .root_for(&path)
+            .or_else(|| path.starts_with(&temp_dir).then_some(&temp_dir))
+        else {
+            return Err(outside_workspace_error(&path));
+        };
+        let protected = path
+            .strip_prefix(root)
+            .unwrap_or(&path)
+            .components()
+            .any(|component| {
+                PROTECTED_NAMES.contains(&component.as_os_str().to_string_lossy().as_ref())
+            });
+        if protected || is_credentials_file(&path) {
+            return Err(format!(
+                "Access denied: {} is a protected file",
+                path.display()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Command that runs `command` with `shell`, wrapped in the platform sandbox when
+    /// `wrap_bash` is set and one is installed.
+    pub fn bash_command(&self, shell: &Shell, command: &str) -> Command {
+        if !self.enabled || !self.wrap_bash {
+            return shell.command(command);
+        }
+        let Some((program, mut args)) = self.bash_wrapper() else {
+            return shell.command(command);
+        };
+        args.push(shell.program.clone());
+        args.extend(shell.args(command));
+        let mut process = Command::new(program);
+        process.args(args);
+        process
+    }
+
+    fn bash_wrapper(&self) -> Option<(PathBuf, Vec<String>)> {
+        let temp_dir = normalize_path(&env::temp_dir());
+        let writable = self.roots.iter().chain(std::iter::once(&temp_dir));
+        if cfg!(target_os = "linux") {
+            let program = find_in_path("firejail")?;
+            let mut args = vec![
+                "--quiet".to_string(),
+                "--noprofile".to_string(),
+                "--read-only=/".to_string(),
+            ];
+            args.extend(writable.map(|path| format!("--read-write={}", path.display())));
+            args.push("--".to_string());
+            return Some((program, args));
+        }
+        if cfg!(target_os = "macos") {
+            let program = find_in_path("sandbox-exec")?;
+            let mut profile =
+                String::from("(version 1)(allow default)(deny file-write*)(allow file-write*");
+            for path in writable {
+                profile.push_str(&format!(" (subpath \"{}\")", path.display()));
+            }
+            profile.push_str(" (subpath \"/dev\"))");
+            return Some((program, vec!["-p".to_string(), profile]));
+        }
+        None
+    }
+
+    fn root_for(&self, path: &Path) -> Option<&PathBuf> {
+        self.roots.iter().find(|root| path.starts_with(root))
+    }
+}
+
+fn outside_workspace_error(path: &Path) -> String {
+    format!(
+        "Access denied: {} is outside the workspace. Add it to sandbox.allowedPaths in settings or run with --dangerously-allow-all",
+        path.display()
+    )
+}
+
+fn is_credentials_file(path: &Path) -> bool {
+    path == normalize_path(&config::get_auth_path())
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn find_in_path(program: &str) -> Option<PathBuf> {
+    let paths = env::var_os("PATH")?;
+    env::split_paths(&paths)
+        .map(|dir| dir.join(program))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Absolute form of `path` with symlinks resolved and `.`/`..` removed, so that
+/// containment checks cannot be escaped. Components are resolved one at a time, the way the
+/// kernel walks a path: `..` applies to the real directory behind a symlink, and a dangling
+/// symlink resolves to the file that writing through it would create.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        env::current_dir().unwrap_or_default().join(path)
+    };
+    resolve_path(&absolute, 0)
+}
+
+/// Symlinks followed before giving up, as Linux's `MAXSYMLINKS`.
+const MAX_SYMLINKS: usize = 40;
+
+fn resolve_path(absolute: &Path, links_followed: usize) -> PathBuf {
+    // `resolved` never contains a symlink, so `..` can be applied to it lexically.
+    let mut resolved = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => {
+                resolved.push(other);
+                if let Ok(canonical) = resolved.canonicalize() {
+                    resolved = canonical;
+                } else if let Ok(target) = resolved.read_link() {
+                    if links_followed < MAX_SYMLINKS {
+                        let parent = resolved.parent().map(Path::to_path_buf).unwrap_or_default();
+                        resolved = resolve_path(&parent.join(target), links_followed + 1);
+                    }
+                }
+            }
+        }
+    }
+    resolved
+}
@@ -0,0 +1,97 @@
+//! Shell used for the bash tool and `!` commands. Unix uses bash (`sh` for `!` commands),
+//! Windows uses PowerShell; the `shellPath` setting selects another shell (e.g. `cmd.exe`,
+//! `pwsh`, or Git Bash's `bash.exe`).
+
+use std::process::Command;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShellKind {
+    Posix,
+    PowerShell,
+    Cmd,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Shell {
+    pub program: String,
+    pub kind: ShellKind,
+    login: bool,
+}
+
+impl Shell {
+    /// Shell at `program`, with its kind guessed from the executable name.
+    pub fn from_path(program: &str) -> Self {
+        let name = program
+            .rsplit(['/', '\\'])
+            .next()
+            .unwrap_or(program)
+            .to_lowercase();
+        let kind = match name.strip_suffix(".exe").unwrap_or(&name) {
+            "cmd" => ShellKind::Cmd,
+            "powershell" | "pwsh" => ShellKind::PowerShell,
+            _ => ShellKind::Posix,
+        };
+        Self {
+            program: program.to_string(),
+            kind,
+            login: false,
+        }
+    }
+
+    /// Default for the bash tool: a `bash` login shell on Unix, PowerShell on Windows.
+    pub fn tool_default() -> Self {
+        if cfg!(windows) {
+            Self::from_path("powershell")
+        } else {
+            Self {
+                login: true,
+                ..Self::from_path("bash")
+            }
+        }
+    }
+
+    /// Default for user `!` commands: `sh` on Unix, PowerShell on Windows.
+    pub fn command_default() -> Self {
+        Self::from_path(if cfg!(windows) { "powershell" } else { "sh" })
+    }
+
+    /// The configured `shellPath`, or `default` when unset.
+    pub fn configured_or(shell_path: Option<&str>, default: fn() -> Self) -> Self {
+        shell_path
+            .map(str::trim)
+            .filter(|path| !path.is_empty())
+            .map(Self::from_path)
+            .unwrap_or_else(default)
+    }
+
+    /// Arguments that make the shell run `command` and exit.
+    pub fn args(&self, command: &str) -> Vec<String> {
+        let flags: &[&str] = match self.kind {
+            ShellKind::Posix if self.login => &["-lc"],
+            ShellKind::Posix => &["-c"],
+            ShellKind::PowerShell => &["-NoLogo", "-NoProfile", "-NonInteractive", "-Command"],
+            ShellKind::Cmd => &["/d", "/s", "/c"],
+        };
+        let mut args = flags
+            .iter()
+            .map(|flag| flag.to_string())
+            .collect::<Vec<_>>();
+        args.push(command.to_string());
+        args
+    }
+
+    pub fn command(&self, command: &str) -> Command {
+        let mut process = Command::new(&self.program);
+        // cmd.exe does its own quote parsing; `/s` strips exactly one pair of outer quotes,
+        // so the command is passed verbatim instead of with Rust's argument escaping.
+        #[cfg(windows)]
+        if self.kind == ShellKind::Cmd {
+            use std::os::windows::process::CommandExt;
+            process.args(["/d", "/s", "/c"]);
+            process.raw_arg(format!("\"{command}\""));
+            return process;
+        }
+        process.args(self.args(command));
+        process
+    }
+}
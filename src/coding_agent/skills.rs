@@ -1,3 +1,4 @@
+use crate::coding_agent::slash_commands::{parse_command_args, substitute_args};
 use crate::config;
 use glob::Pattern;
 use std::collections::{HashMap, HashSet};
@@ -5,13 +6,14 @@ use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
 
-const ALLOWED_FRONTMATTER_FIELDS: [&str; 6] = [
+const ALLOWED_FRONTMATTER_FIELDS: [&str; 7] = [
     "name",
     "description",
     "license",
     "compatibility",
     "metadata",
     "allowed-tools",
+    "arguments",
 ];
 const MAX_NAME_LENGTH: usize = 64;
 const MAX_DESCRIPTION_LENGTH: usize = 1024;
@@ -23,6 +25,20 @@ pub struct Skill {
     pub file_path: String,
     pub base_dir: String,
     pub source: String,
+    /// Positional arguments for `/skill <name> [args]`, in order.
+    pub arguments: Vec<SkillArgument>,
+    /// Tools the skill expects to use (`allowed-tools`).
+    pub allowed_tools: Vec<String>,
+}
+
+/// One entry of a skill's `arguments` frontmatter list. Either a bare name (`- path`) or a
+/// map with `name`, `description`, `required` and `default`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SkillArgument {
+    pub name: String,
+    pub description: Option<String>,
+    pub required: bool,
+    pub default: Option<String>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -90,6 +106,8 @@ enum SkillFormat {
 struct SkillFrontmatter {
     name: Option<String>,
     description: Option<String>,
+    arguments: Vec<SkillArgument>,
+    allowed_tools: Vec<String>,
 }
 
 pub fn load_skills_from_dir(options: LoadSkillsFromDirOptions) -> LoadSkillsResult {
@@ -120,6 +138,24 @@ pub fn format_skills_for_prompt(skills: &[Skill]) -> String {
             "    <location>{}</location>",
             escape_xml(&skill.file_path)
         ));
+        if !skill.arguments.is_empty() {
+            let arguments = skill
+                .arguments
+                .iter()
+                .map(format_argument)
+                .collect::<Vec<_>>()
+                .join("; ");
+            lines.push(format!(
+                "    <arguments>{}</arguments>",
+                escape_xml(&arguments)
+            ));
+        }
+        if !skill.allowed_tools.is_empty() {
+            lines.push(format!(
+                "    <allowed_tools>{}</allowed_tools>",
+                escape_xml(&skill.allowed_tools.join(", "))
+            ));
+        }
         lines.push("  </skill>".to_string());
     }
 
@@ -127,6 +163,90 @@ pub fn format_skills_for_prompt(skills: &[Skill]) -> String {
     lines.join("\n")
 }
 
+fn format_argument(argument: &SkillArgument) -> String {
+    let mut text = argument.name.clone();
+    if argument.required {
+        text.push_str(" (required)");
+    } else if let Some(default) = &argument.default {
+        text.push_str(&format!(" (default: {default})"));
+    }
+    if let Some(description) = &argument.description {
+        text.push_str(&format!(": {description}"));
+    }
+    text
+}
+
+/// The prompt that runs `skill` for `/skill <name> [args]`: the skill's instructions with
+/// `args` substituted, positionally, for `${name}` placeholders of its declared arguments and
+/// for `$1`, `$ARGUMENTS` and `$@`. Arguments the instructions do not reference are appended.
+pub fn expand_skill(skill: &Skill, args: &str) -> Result<String, String> {
+    let raw_content = fs::read_to_string(&skill.file_path)
+        .map_err(|err| format!("Failed to read skill {}: {err}", skill.file_path))?;
+    let body = strip_frontmatter(&raw_content);
+
+    let mut values = parse_command_args(args);
+    if values.len() > skill.arguments.len() && !skill.arguments.is_empty() {
+        let last = skill.arguments.len() - 1;
+        let rest = values.split_off(last).join(" ");
+        values.push(rest);
+    }
+    for argument in skill.arguments.iter().skip(values.len()) {
+        match &argument.default {
+            Some(default) => values.push(default.clone()),
+            None if argument.required => {
+                let usage = skill
+                    .arguments
+                    .iter()
+                    .map(|argument| match argument.required {
+                        true => format!("<{}>", argument.name),
+                        false => format!("[{}]", argument.name),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                return Err(format!(
+                    "Skill \"{}\" requires argument \"{}\". Usage: /skill {} {usage}",
+                    skill.name, argument.name, skill.name
+                ));
+            }
+            None => values.push(String::new()),
+        }
+    }
+
+    let mut expanded = body.to_string();
+    for (argument, value) in skill.arguments.iter().zip(&values) {
+        expanded = expanded.replace(&format!("${{{}}}", argument.name), value);
+    }
+    let expanded = substitute_args(&expanded, &values);
+
+    let mut prompt = format!(
+        "<skill name=\"{}\" location=\"{}\">\nReferences are relative to {}.\n",
+        escape_xml(&skill.name),
+        escape_xml(&skill.file_path),
+        skill.base_dir
+    );
+    if !skill.allowed_tools.is_empty() {
+        prompt.push_str(&format!(
+            "Only use these tools for this skill: {}.\n",
+            skill.allowed_tools.join(", ")
+        ));
+    }
+    prompt.push_str(&format!("\n{}\n</skill>", expanded.trim()));
+    if expanded == body && !args.trim().is_empty() {
+        prompt.push_str(&format!("\n\n{}", args.trim()));
+    }
+    Ok(prompt)
+}
+
+fn strip_frontmatter(content: &str) -> &str {
+    let Some(remainder) = content.strip_prefix("---") else {
+        return content;
+    };
+    match remainder.find("\n---") {
+        Some(end) => remainder[end + 4..].trim_start_matches(['\r', '\n']),
+        None => content,
+    }
+}
+
 pub fn load_skills(options: LoadSkillsOptions) -> LoadSkillsResult {
     let cwd = options
         .cwd
@@ -373,6 +493,13 @@ fn load_skill_from_file(file_path: &Path, source: &str) -> LoadSkillResult {
         });
     }
 
+    for error in validate_arguments(&frontmatter.arguments) {
+        warnings.push(SkillWarning {
+            skill_path: file_path.display().to_string(),
+            message: error,
+        });
+    }
+
     let description = match frontmatter.description {
         Some(description) if !description.trim().is_empty() => description,
         _ => {
@@ -390,6 +517,8 @@ fn load_skill_from_file(file_path: &Path, source: &str) -> LoadSkillResult {
             file_path: file_path.display().to_string(),
             base_dir: skill_dir.display().to_string(),
             source: source.to_string(),
+            arguments: frontmatter.arguments,
+            allowed_tools: frontmatter.allowed_tools,
         }),
         warnings,
     }
@@ -414,8 +543,17 @@ fn parse_frontmatter(content: &str) -> (SkillFrontmatter, Vec<String>) {
     let frontmatter_block = &normalized[4..end_index];
     let mut frontmatter = SkillFrontmatter::default();
     let mut all_keys = Vec::new();
+    // Indented lines continue the block of the last top-level key.
+    let mut current_key = String::new();
+    let mut argument_lines = Vec::new();
 
     for line in frontmatter_block.lines() {
+        if line.starts_with([' ', '\t']) {
+            if current_key == "arguments" {
+                argument_lines.push(line);
+            }
+            continue;
+        }
         let mut parts = line.splitn(2, ':');
         let key = match parts.next() {
             Some(key) => key.trim(),
@@ -430,18 +568,81 @@ fn parse_frontmatter(content: &str) -> (SkillFrontmatter, Vec<String>) {
             continue;
         }
 
-        let value = strip_quotes(value);
+        current_key = key.to_string();
         all_keys.push(key.to_string());
-        if key == "name" {
-            frontmatter.name = Some(value);
-        } else if key == "description" {
-            frontmatter.description = Some(value);
+        match key {
+            "name" => frontmatter.name = Some(strip_quotes(value)),
+            "description" => frontmatter.description = Some(strip_quotes(value)),
+            "allowed-tools" => frontmatter.allowed_tools = parse_inline_list(value),
+            "arguments" => {
+                frontmatter.arguments = parse_inline_list(value)
+                    .into_iter()
+                    .map(|name| SkillArgument {
+                        name,
+                        ..SkillArgument::default()
+                    })
+                    .collect()
+            }
+            _ => {}
         }
     }
+    frontmatter
+        .arguments
+        .extend(parse_argument_list(&argument_lines));
 
     (frontmatter, all_keys)
 }
 
+/// `a, b`, `a b` or `[a, b]`.
+fn parse_inline_list(value: &str) -> Vec<String> {
+    let value = value.trim();
+    let value = value
+        .strip_prefix('[')
+        .and_then(|value| value.strip_suffix(']'))
+        .unwrap_or(value);
+    value
+        .split(|ch: char| ch == ',' || ch.is_whitespace())
+        .map(|item| strip_quotes(item.trim()))
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// The YAML list under `arguments:`, one `- ` item per argument.
+fn parse_argument_list(lines: &[&str]) -> Vec<SkillArgument> {
+    let mut arguments: Vec<SkillArgument> = Vec::new();
+    for line in lines {
+        let trimmed = line.trim();
+        let field = match trimmed.strip_prefix('-') {
+            Some(item) => {
+                let item = item.trim();
+                if !item.contains(':') {
+                    arguments.push(SkillArgument {
+                        name: strip_quotes(item),
+                        ..SkillArgument::default()
+                    });
+                    continue;
+                }
+                arguments.push(SkillArgument::default());
+                item
+            }
+            None => trimmed,
+        };
+        let (Some(argument), Some((key, value))) = (arguments.last_mut(), field.split_once(':'))
+        else {
+            continue;
+        };
+        let value = strip_quotes(value.trim());
+        match key.trim() {
+            "name" => argument.name = value,
+            "description" => argument.description = Some(value),
+            "required" => argument.required = value == "true",
+            "default" => argument.default = Some(value),
+            _ => {}
+        }
+    }
+    arguments
+}
+
 fn strip_quotes(value: &str) -> String {
     if value.len() >= 2 {
         let bytes = value.as_bytes();
@@ -505,6 +706,24 @@ fn validate_name(name: &str, parent_dir_name: &str) -> Vec<String> {
     errors
 }
 
+fn validate_arguments(arguments: &[SkillArgument]) -> Vec<String> {
+    let mut errors = Vec::new();
+    let mut seen = HashSet::new();
+    for argument in arguments {
+        if argument.name.is_empty()
+            || !argument
+                .name
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '-')
+        {
+            errors.push(format!("invalid argument name \"{}\"", argument.name));
+        } else if !seen.insert(argument.name.as_str()) {
+            errors.push(format!("duplicate argument \"{}\"", argument.name));
+        }
+    }
+    errors
+}
+
 fn validate_description(description: Option<&str>) -> Vec<String> {
     let mut errors = Vec::new();
 
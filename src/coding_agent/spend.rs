@@ -0,0 +1,188 @@
+//! What pi has spent, kept in `spend.json` in the agent dir: a running total per session and
+//! per local day, fed by the cost of every assistant reply. Budgets (`--max-cost`, the
+//! `budget` settings) are checked against these totals.
+
+use crate::core::session_manager::write_file_atomic;
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SpendLedger {
+    /// USD per local date (`YYYY-MM-DD`).
+    #[serde(default)]
+    days: BTreeMap<String, f64>,
+    /// USD per session id.
+    #[serde(default)]
+    sessions: BTreeMap<String, f64>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SpendTracker {
+    path: Option<PathBuf>,
+    ledger: SpendLedger,
+}
+
+/// Spending caps in USD; `None` is unlimited.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SpendLimits {
+    /// For one session, across every run that resumed it.
+    pub max_cost: Option<f64>,
+    /// For everything pi ran today.
+    pub max_daily_cost: Option<f64>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SpendSnapshot {
+    pub session: f64,
+    pub today: f64,
+    pub limits: SpendLimits,
+}
+
+impl SpendSnapshot {
+    /// Why the next request must not be sent, if a limit has been reached.
+    pub fn exceeded(&self) -> Option<String> {
+        if let Some(limit) = self.limits.max_cost.filter(|limit| self.session >= *limit) {
+            return Some(format!(
+                "Cost budget exceeded: this session has spent ${:.4} of its ${limit:.2} limit",
+                self.session
+            ));
+        }
+        if let Some(limit) = self
+            .limits
+            .max_daily_cost
+            .filter(|limit| self.today >= *limit)
+        {
+            return Some(format!(
+                "Cost budget exceeded: ${:.4} spent today of the ${limit:.2} daily limit",
+                self.today
+            ));
+        }
+        None
+    }
+}
+
+/// Today's local date, the key for daily totals.
+pub fn spend_day() -> String {
+    chrono::Local::now().format("%Y-%m-%d").to_string()
+}
+
+impl SpendTracker {
+    /// A missing file starts from zero. One that cannot be read or parsed is an error rather
+    /// than a fresh start, which would lose the totals the budgets are checked against.
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let ledger = read_ledger(&path)?;
+        Ok(Self {
+            path: Some(path),
+            ledger,
+        })
+    }
+
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            ledger: SpendLedger::default(),
+        }
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn session_total(&self, session_id: &str) -> f64 {
+        self.ledger.sessions.get(session_id).copied().unwrap_or(0.0)
+    }
+
+    pub fn day_total(&self, day: &str) -> f64 {
+        self.ledger.days.get(day).copied().unwrap_or(0.0)
+    }
+
+    /// Adds `cost` to the session's and the day's totals and saves them. The file is re-read
+    /// under a lock so that pi processes running side by side do not overwrite each other's
+    /// spend.
+    pub fn record(&mut self, session_id: &str, day: &str, cost: f64) -> Result<()> {
+        let _lock = match &self.path {
+            Some(path) => {
+                let lock = lock_ledger(path)?;
+                self.ledger = read_ledger(path)?;
+                Some(lock)
+            }
+            None => None,
+        };
+        *self
+            .ledger
+            .sessions
+            .entry(session_id.to_string())
+            .or_default() += cost;
+        *self.ledger.days.entry(day.to_string()).or_default() += cost;
+        self.write()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        match &self.path {
+            Some(path) => {
+                let _lock = lock_ledger(path)?;
+                self.write()
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Replaces the file through a rename, so readers never see it half written.
+    fn write(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let content = serde_json::to_string_pretty(&self.ledger)?;
+        write_file_atomic(path, &content)
+            .map_err(|err| Error::io(format_args!("Failed to write {}", path.display()), err))
+    }
+}
+
+fn read_ledger(path: &Path) -> Result<SpendLedger> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(SpendLedger::default()),
+        Err(err) => {
+            return Err(Error::io(
+                format_args!("Failed to read {}", path.display()),
+                err,
+            ))
+        }
+    };
+    serde_json::from_str(&content).map_err(|err| {
+        Error::Io(io::Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "Spend ledger {} is corrupt ({err}); fix or remove it to reset spend",
+                path.display()
+            ),
+        ))
+    })
+}
+
+/// An exclusive lock on `<path>.lock`, held until the returned file is dropped. The ledger
+/// itself is replaced on every write, so it cannot carry the lock.
+fn lock_ledger(path: &Path) -> Result<File> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|err| Error::io(format_args!("Failed to create {}", parent.display()), err))?;
+    }
+    let mut lock_name = path.file_name().unwrap_or_default().to_os_string();
+    lock_name.push(".lock");
+    let lock_path = path.with_file_name(lock_name);
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .map_err(|err| Error::io(format_args!("Failed to open {}", lock_path.display()), err))?;
+    file.lock()
+        .map_err(|err| Error::io(format_args!("Failed to lock {}", lock_path.display()), err))?;
+    Ok(file)
+}
@@ -1,6 +1,8 @@
+use crate::coding_agent::environment::{format_environment, EnvironmentSnapshot};
 use crate::coding_agent::skills::{
     format_skills_for_prompt, load_skills, LoadSkillsOptions, Skill,
 };
+use crate::config;
 use chrono::Local;
 use std::collections::{HashMap, HashSet};
 use std::env;
@@ -30,6 +32,78 @@ pub struct BuildSystemPromptOptions {
     pub agent_dir: Option<PathBuf>,
     pub context_files: Option<Vec<ContextFile>>,
     pub skills: Option<Vec<Skill>>,
+    pub environment: Option<EnvironmentSnapshot>,
+    pub overrides: PromptOverrides,
+}
+
+/// The named sections of the system prompt, in their default order.
+pub const PROMPT_SECTIONS: [&str; 9] = [
+    "identity",
+    "tools",
+    "safety",
+    "guidelines",
+    "docs",
+    "append",
+    "context",
+    "skills",
+    "environment",
+];
+
+/// Changes to the prompt `build_system_prompt` assembles, from settings, extensions or SDK
+/// callers.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PromptOverrides {
+    /// Sections to put first, in this order; the rest follow in their default order.
+    pub order: Vec<String>,
+    /// Replacement text per section; an empty string leaves the section out. Names outside
+    /// [`PROMPT_SECTIONS`] add a section, after the built-in ones unless `order` places it.
+    pub sections: HashMap<String, String>,
+    /// Replacement descriptions per tool, for the prompt's tool list and the tool definition.
+    pub tool_descriptions: HashMap<String, String>,
+}
+
+impl PromptOverrides {
+    /// Applies `other` on top of these overrides; its entries win.
+    pub fn merge(&mut self, other: &PromptOverrides) {
+        if !other.order.is_empty() {
+            self.order = other.order.clone();
+        }
+        self.sections.extend(other.sections.clone());
+        self.tool_descriptions
+            .extend(other.tool_descriptions.clone());
+    }
+
+    fn assemble(&self, mut sections: Vec<(String, String)>) -> String {
+        for (name, text) in &sections_sorted(&self.sections) {
+            match sections.iter_mut().find(|(section, _)| section == *name) {
+                Some(section) => section.1 = text.to_string(),
+                None => sections.push((name.to_string(), text.to_string())),
+            }
+        }
+        let mut ordered = Vec::new();
+        for name in &self.order {
+            if let Some(index) = sections.iter().position(|(section, _)| section == name) {
+                ordered.push(sections.remove(index));
+            }
+        }
+        ordered.extend(sections);
+        ordered
+            .into_iter()
+            .map(|(_, text)| text.trim().to_string())
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+/// Added sections keep a stable order whatever the map's iteration order.
+fn sections_sorted(sections: &HashMap<String, String>) -> Vec<(&str, &str)> {
+    let mut sorted = sections
+        .iter()
+        .map(|(name, text)| (name.as_str(), text.as_str()))
+        .collect::<Vec<_>>();
+    sorted.sort();
+    sorted
 }
 
 pub fn resolve_prompt_input(input: Option<&str>, description: &str) -> Option<String> {
@@ -72,16 +146,21 @@ pub fn load_project_context_files(options: LoadContextFilesOptions) -> Vec<Conte
         }
     }
 
+    // Outermost directory first, so that instructions closer to cwd come later.
     let mut ancestors = Vec::new();
     let mut current = cwd.clone();
     loop {
-        if let Some(file) = load_context_file_from_dir(&current) {
+        let mut dir_files = Vec::new();
+        let project_file = load_context_file_from_dir(&current);
+        let config_file = load_config_context_file(&current);
+        for file in project_file.into_iter().chain(config_file) {
             let key = canonical_key(&file.path);
             if !seen.contains(&key) {
                 seen.insert(key);
-                ancestors.insert(0, file);
+                dir_files.push(file);
             }
         }
+        ancestors.splice(0..0, dir_files);
 
         let parent = current.parent();
         if parent.is_none() || parent == Some(&current) {
@@ -105,11 +184,6 @@ pub fn build_system_prompt(options: BuildSystemPromptOptions) -> String {
         "append system prompt",
     );
 
-    let append_section = append_prompt
-        .as_deref()
-        .map(|text| format!("\n\n{text}"))
-        .unwrap_or_default();
-
     let selected_tools = options.selected_tools.unwrap_or_else(|| {
         vec![
             "read".to_string(),
@@ -148,36 +222,57 @@ pub fn build_system_prompt(options: BuildSystemPromptOptions) -> String {
     let date_time = Local::now()
         .format("%A, %B %-d, %Y, %I:%M:%S %p %Z")
         .to_string();
-    let cwd_display = cwd.display();
-
-    if let Some(prompt) = custom_prompt {
-        let mut prompt = prompt;
-        prompt.push_str(&append_section);
-
-        if !context_files.is_empty() {
-            prompt.push_str("\n\n# Project Context\n\n");
-            prompt.push_str("The following project context files have been loaded:\n\n");
-            for file in &context_files {
-                prompt.push_str(&format!("## {}\n\n{}\n\n", file.path, file.content));
-            }
-        }
+    let mut environment = options
+        .environment
+        .as_ref()
+        .map(|snapshot| format_environment(snapshot).trim().to_string())
+        .unwrap_or_default();
+    if !environment.is_empty() {
+        environment.push_str("\n\n");
+    }
+    environment.push_str(&format!("Current date and time: {date_time}"));
+    environment.push_str(&format!("\nCurrent working directory: {}", cwd.display()));
 
-        if tools_set.contains("read") && !skills.is_empty() {
-            prompt.push_str(&format_skills_for_prompt(&skills));
+    let mut context = String::new();
+    if !context_files.is_empty() {
+        context.push_str("# Project Context\n\n");
+        context.push_str("The following project context files have been loaded:\n\n");
+        for file in &context_files {
+            context.push_str(&format!("## {}\n\n{}\n\n", file.path, file.content));
         }
+    }
+    let skills = if tools_set.contains("read") {
+        format_skills_for_prompt(&skills)
+    } else {
+        String::new()
+    };
+    let trailing_sections = [
+        ("append", append_prompt.unwrap_or_default()),
+        ("context", context),
+        ("skills", skills),
+        ("environment", environment),
+    ];
 
-        prompt.push_str(&format!("\nCurrent date and time: {date_time}"));
-        prompt.push_str(&format!("\nCurrent working directory: {cwd_display}"));
-        return prompt;
+    if let Some(prompt) = custom_prompt {
+        let mut sections = vec![("identity".to_string(), prompt)];
+        sections.extend(
+            trailing_sections
+                .into_iter()
+                .map(|(name, text)| (name.to_string(), text)),
+        );
+        return options.overrides.assemble(sections);
     }
 
     let tool_descriptions = tool_descriptions();
     let tools_list = selected_tools
         .iter()
         .map(|tool| {
-            let desc = tool_descriptions
-                .get(tool.as_str())
-                .copied()
+            let desc = options
+                .overrides
+                .tool_descriptions
+                .get(tool)
+                .map(String::as_str)
+                .or_else(|| tool_descriptions.get(tool.as_str()).copied())
                 .unwrap_or("Tool");
             format!("- {tool}: {desc}")
         })
@@ -192,19 +287,20 @@ pub fn build_system_prompt(options: BuildSystemPromptOptions) -> String {
     let has_ls = tools_set.contains("ls");
     let has_read = tools_set.contains("read");
 
-    let mut guidelines = Vec::new();
+    let mut safety = Vec::new();
     if !has_bash && !has_edit && !has_write {
-        guidelines.push(
+        safety.push(
             "You are in READ-ONLY mode - you cannot modify files or execute arbitrary commands",
         );
     }
 
     if has_bash && !has_edit && !has_write {
-        guidelines.push(
+        safety.push(
             "Use bash ONLY for read-only operations (git log, gh issue view, curl, etc.) - do NOT modify any files",
         );
     }
 
+    let mut guidelines = Vec::new();
     if has_bash && !has_grep && !has_find && !has_ls {
         guidelines.push("Use bash for file operations like ls, grep, find");
     } else if has_bash && (has_grep || has_find || has_ls) {
@@ -225,6 +321,12 @@ pub fn build_system_prompt(options: BuildSystemPromptOptions) -> String {
         guidelines.push("Use write only for new files or complete rewrites");
     }
 
+    if tools_set.contains("notebook_edit") {
+        guidelines.push(
+            "Use notebook_read and notebook_edit for .ipynb files instead of read, edit or write",
+        );
+    }
+
     if has_edit || has_write {
         guidelines.push(
             "When summarizing your actions, output plain text directly - do NOT use cat or bash to display what you did",
@@ -234,37 +336,65 @@ pub fn build_system_prompt(options: BuildSystemPromptOptions) -> String {
     guidelines.push("Be concise in your responses");
     guidelines.push("Show file paths clearly when working with files");
 
-    let guidelines = guidelines
-        .iter()
-        .map(|line| format!("- {line}"))
-        .collect::<Vec<_>>()
-        .join("\n");
+    let safety = if safety.is_empty() {
+        String::new()
+    } else {
+        format!("Safety:\n{}", bullet_list(&safety))
+    };
+    let guidelines = bullet_list(&guidelines);
 
     let docs = resolve_docs_paths(&cwd);
-    let mut prompt = format!(
-        "You are an expert coding assistant. You help users with coding tasks by reading files, executing commands, editing code, and writing new files.\n\nAvailable tools:\n{tools_list}\n\nIn addition to the tools above, you may have access to other custom tools depending on the project.\n\nGuidelines:\n{guidelines}\n\nDocumentation:\n- Main documentation: {}\n- Additional docs: {}\n- Examples: {} (extensions, custom tools, SDK)\n- When asked to create: custom models/providers (README.md), extensions (docs/extensions.md, examples/extensions/), themes (docs/theme.md), skills (docs/skills.md)\n- Always read the doc, examples, AND follow .md cross-references before implementing",
-        docs.readme, docs.docs, docs.examples
+    let mut sections = vec![
+        (
+            "identity".to_string(),
+            "You are an expert coding assistant. You help users with coding tasks by reading files, executing commands, editing code, and writing new files.".to_string(),
+        ),
+        (
+            "tools".to_string(),
+            format!("Available tools:\n{tools_list}\n\nIn addition to the tools above, you may have access to other custom tools depending on the project."),
+        ),
+        ("safety".to_string(), safety),
+        ("guidelines".to_string(), format!("Guidelines:\n{guidelines}")),
+        (
+            "docs".to_string(),
+            format!(
+                "Documentation:\n- Main documentation: {}\n- Additional docs: {}\n- Examples: {} (extensions, custom tools, SDK)\n- When asked to create: custom models/providers (README.md), extensions (docs/extensions.md, examples/extensions/), themes (docs/theme.md), skills (docs/skills.md)\n- Always read the doc, examples, AND follow .md cross-references before implementing",
+                docs.readme, docs.docs, docs.examples
+            ),
+        ),
+    ];
+    sections.extend(
+        trailing_sections
+            .into_iter()
+            .map(|(name, text)| (name.to_string(), text)),
     );
+    options.overrides.assemble(sections)
+}
 
-    if !append_section.is_empty() {
-        prompt.push_str(&append_section);
-    }
+fn bullet_list(lines: &[&str]) -> String {
+    lines
+        .iter()
+        .map(|line| format!("- {line}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
 
-    if !context_files.is_empty() {
-        prompt.push_str("\n\n# Project Context\n\n");
-        prompt.push_str("The following project context files have been loaded:\n\n");
-        for file in &context_files {
-            prompt.push_str(&format!("## {}\n\n{}\n\n", file.path, file.content));
-        }
+/// `.pi/CONTEXT.md`, read in addition to a directory's AGENTS.md or CLAUDE.md.
+fn load_config_context_file(dir: &Path) -> Option<ContextFile> {
+    let path = dir.join(config::config_dir_name()).join("CONTEXT.md");
+    if !path.is_file() {
+        return None;
     }
-
-    if has_read && !skills.is_empty() {
-        prompt.push_str(&format_skills_for_prompt(&skills));
+    match fs::read_to_string(&path) {
+        Ok(content) => Some(ContextFile {
+            path: path.display().to_string(),
+            content,
+        }),
+        Err(err) => {
+            eprintln!("Warning: Could not read {}: {}", path.display(), err);
+            None
+        }
     }
-
-    prompt.push_str(&format!("\nCurrent date and time: {date_time}"));
-    prompt.push_str(&format!("\nCurrent working directory: {cwd_display}"));
-    prompt
 }
 
 fn load_context_file_from_dir(dir: &Path) -> Option<ContextFile> {
@@ -381,5 +511,10 @@ fn tool_descriptions() -> HashMap<&'static str, &'static str> {
     );
     map.insert("find", "Find files by glob pattern (respects .gitignore)");
     map.insert("ls", "List directory contents");
+    map.insert("notebook_read", "Read Jupyter notebook cells and outputs");
+    map.insert(
+        "notebook_edit",
+        "Replace, insert, or delete Jupyter notebook cells",
+    );
     map
 }
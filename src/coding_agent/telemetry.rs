@@ -0,0 +1,366 @@
+use crate::agent::{AgentEvent, AgentMessage};
+use crate::coding_agent::agent_session::{AgentSessionEvent, SettingsTelemetry};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Instant;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TelemetryEvent {
+    LlmRequest {
+        provider: String,
+        model: String,
+        stop_reason: String,
+        input_tokens: i64,
+        output_tokens: i64,
+        cache_read_tokens: i64,
+        cache_write_tokens: i64,
+        cost: f64,
+        duration_ms: u64,
+    },
+    ToolExecution {
+        tool_name: String,
+        is_error: bool,
+        duration_ms: u64,
+    },
+    Error {
+        source: String,
+        message: String,
+    },
+}
+
+impl TelemetryEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TelemetryEvent::LlmRequest { .. } => "llm_request",
+            TelemetryEvent::ToolExecution { .. } => "tool_execution",
+            TelemetryEvent::Error { .. } => "error",
+        }
+    }
+
+    pub fn duration_ms(&self) -> u64 {
+        match self {
+            TelemetryEvent::LlmRequest { duration_ms, .. }
+            | TelemetryEvent::ToolExecution { duration_ms, .. } => *duration_ms,
+            TelemetryEvent::Error { .. } => 0,
+        }
+    }
+}
+
+/// Destination for session telemetry. `record` runs on the agent thread, so sinks should
+/// buffer and export in batches rather than block on every event.
+pub trait TelemetrySink {
+    fn record(&self, event: &TelemetryEvent);
+
+    fn flush(&self) {}
+}
+
+/// Turns agent session events into telemetry events for a sink.
+pub struct TelemetryRecorder {
+    sink: Rc<dyn TelemetrySink>,
+    request_started: Cell<Option<Instant>>,
+    tools_started: RefCell<HashMap<String, Instant>>,
+}
+
+impl TelemetryRecorder {
+    pub fn new(sink: Rc<dyn TelemetrySink>) -> Self {
+        Self {
+            sink,
+            request_started: Cell::new(None),
+            tools_started: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn handle_event(&self, event: &AgentSessionEvent) {
+        let AgentSessionEvent::Agent(event) = event else {
+            return;
+        };
+        match event.as_ref() {
+            AgentEvent::TurnStart => self.request_started.set(Some(Instant::now())),
+            AgentEvent::MessageEnd {
+                message: AgentMessage::Assistant(message),
+            } => {
+                let duration_ms = elapsed_ms(self.request_started.take());
+                self.sink.record(&TelemetryEvent::LlmRequest {
+                    provider: message.provider.clone(),
+                    model: message.model.clone(),
+                    stop_reason: message.stop_reason.clone(),
+                    input_tokens: message.usage.input,
+                    output_tokens: message.usage.output,
+                    cache_read_tokens: message.usage.cache_read,
+                    cache_write_tokens: message.usage.cache_write,
+                    cost: message.usage.cost.as_ref().map_or(0.0, |cost| cost.total),
+                    duration_ms,
+                });
+                if message.stop_reason == "error" {
+                    self.sink.record(&TelemetryEvent::Error {
+                        source: "llm".to_string(),
+                        message: message
+                            .error_message
+                            .clone()
+                            .unwrap_or_else(|| "Unknown error".to_string()),
+                    });
+                }
+            }
+            AgentEvent::ToolExecutionStart { tool_call_id, .. } => {
+                self.tools_started
+                    .borrow_mut()
+                    .insert(tool_call_id.clone(), Instant::now());
+            }
+            AgentEvent::ToolExecutionEnd {
+                tool_call_id,
+                tool_name,
+                is_error,
+                ..
+            } => {
+                let started = self.tools_started.borrow_mut().remove(tool_call_id);
+                self.sink.record(&TelemetryEvent::ToolExecution {
+                    tool_name: tool_name.clone(),
+                    is_error: *is_error,
+                    duration_ms: elapsed_ms(started),
+                });
+            }
+            AgentEvent::AgentEnd { .. } => self.sink.flush(),
+            _ => {}
+        }
+    }
+}
+
+fn elapsed_ms(started: Option<Instant>) -> u64 {
+    started.map_or(0, |started| started.elapsed().as_millis() as u64)
+}
+
+/// Build the sink configured in settings. Returns `None` when telemetry is disabled.
+pub fn create_telemetry_sink(
+    settings: &SettingsTelemetry,
+) -> Result<Option<Rc<dyn TelemetrySink>>, String> {
+    if !settings.enabled.unwrap_or(false) {
+        return Ok(None);
+    }
+    let exporter = settings.exporter.as_deref().unwrap_or("otlp");
+    match exporter {
+        #[cfg(feature = "otlp")]
+        "otlp" => {
+            let sink = otlp::OtlpTelemetrySink::new(otlp::OtlpConfig::from_settings(settings)?);
+            Ok(Some(Rc::new(sink)))
+        }
+        #[cfg(not(feature = "otlp"))]
+        "otlp" => {
+            Err("Telemetry exporter \"otlp\" requires building with the otlp feature.".to_string())
+        }
+        other => Err(format!("Unknown telemetry exporter \"{other}\"")),
+    }
+}
+
+#[cfg(feature = "otlp")]
+pub mod otlp {
+    //! OpenTelemetry exporter that sends each telemetry event as a span over OTLP/HTTP JSON.
+
+    use super::{SettingsTelemetry, TelemetryEvent, TelemetrySink};
+    use reqwest::blocking::Client;
+    use serde_json::{json, Value};
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    /// Spans buffered before an export is triggered.
+    const BATCH_SIZE: usize = 32;
+
+    #[derive(Clone, Debug)]
+    pub struct OtlpConfig {
+        pub endpoint: String,
+        pub headers: BTreeMap<String, String>,
+        pub service_name: String,
+    }
+
+    impl OtlpConfig {
+        pub fn from_settings(settings: &SettingsTelemetry) -> Result<Self, String> {
+            let endpoint = settings
+                .endpoint
+                .clone()
+                .or_else(|| std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok())
+                .filter(|endpoint| !endpoint.trim().is_empty())
+                .ok_or_else(|| {
+                    "Telemetry is enabled but no OTLP endpoint is configured (telemetry.endpoint)."
+                        .to_string()
+                })?;
+            Ok(Self {
+                endpoint,
+                headers: settings.headers.clone().unwrap_or_default(),
+                service_name: settings
+                    .service_name
+                    .clone()
+                    .unwrap_or_else(|| crate::config::app_name().to_string()),
+            })
+        }
+
+        pub fn traces_url(&self) -> String {
+            let endpoint = self.endpoint.trim_end_matches('/');
+            if endpoint.ends_with("/v1/traces") {
+                endpoint.to_string()
+            } else {
+                format!("{endpoint}/v1/traces")
+            }
+        }
+    }
+
+    pub struct OtlpTelemetrySink {
+        config: OtlpConfig,
+        client: Client,
+        trace_id: String,
+        spans: RefCell<Vec<Value>>,
+        last_error: RefCell<Option<String>>,
+    }
+
+    impl OtlpTelemetrySink {
+        pub fn new(config: OtlpConfig) -> Self {
+            Self {
+                config,
+                client: Client::new(),
+                trace_id: uuid::Uuid::new_v4().simple().to_string(),
+                spans: RefCell::new(Vec::new()),
+                last_error: RefCell::new(None),
+            }
+        }
+
+        /// Error from the most recent failed export, if any. Export failures never interrupt the session.
+        pub fn last_error(&self) -> Option<String> {
+            self.last_error.borrow().clone()
+        }
+
+        fn export(&self, spans: Vec<Value>) -> Result<(), String> {
+            let payload = build_traces_payload(&self.config.service_name, spans);
+            let mut request = self.client.post(self.config.traces_url()).json(&payload);
+            for (name, value) in &self.config.headers {
+                request = request.header(name, value);
+            }
+            let response = request.send().map_err(|err| err.to_string())?;
+            if !response.status().is_success() {
+                return Err(format!("OTLP export failed: {}", response.status()));
+            }
+            Ok(())
+        }
+    }
+
+    impl TelemetrySink for OtlpTelemetrySink {
+        fn record(&self, event: &TelemetryEvent) {
+            let span = build_span(event, &self.trace_id, now_unix_nanos());
+            let full = {
+                let mut spans = self.spans.borrow_mut();
+                spans.push(span);
+                spans.len() >= BATCH_SIZE
+            };
+            if full {
+                self.flush();
+            }
+        }
+
+        fn flush(&self) {
+            let spans = std::mem::take(&mut *self.spans.borrow_mut());
+            if spans.is_empty() {
+                return;
+            }
+            let result = self.export(spans);
+            *self.last_error.borrow_mut() = result.err();
+        }
+    }
+
+    impl Drop for OtlpTelemetrySink {
+        fn drop(&mut self) {
+            self.flush();
+        }
+    }
+
+    fn now_unix_nanos() -> u128 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or(0)
+    }
+
+    /// Build an OTLP span for an event that ended at `end_unix_nanos`.
+    pub fn build_span(event: &TelemetryEvent, trace_id: &str, end_unix_nanos: u128) -> Value {
+        let start = end_unix_nanos.saturating_sub(event.duration_ms() as u128 * 1_000_000);
+        let mut attributes = Vec::new();
+        let is_error = match event {
+            TelemetryEvent::LlmRequest {
+                provider,
+                model,
+                stop_reason,
+                input_tokens,
+                output_tokens,
+                cache_read_tokens,
+                cache_write_tokens,
+                cost,
+                ..
+            } => {
+                attributes.push(string_attribute("gen_ai.system", provider));
+                attributes.push(string_attribute("gen_ai.request.model", model));
+                attributes.push(string_attribute(
+                    "gen_ai.response.finish_reason",
+                    stop_reason,
+                ));
+                attributes.push(int_attribute("gen_ai.usage.input_tokens", *input_tokens));
+                attributes.push(int_attribute("gen_ai.usage.output_tokens", *output_tokens));
+                attributes.push(int_attribute(
+                    "pi.usage.cache_read_tokens",
+                    *cache_read_tokens,
+                ));
+                attributes.push(int_attribute(
+                    "pi.usage.cache_write_tokens",
+                    *cache_write_tokens,
+                ));
+                attributes
+                    .push(json!({ "key": "pi.usage.cost", "value": { "doubleValue": cost } }));
+                stop_reason == "error"
+            }
+            TelemetryEvent::ToolExecution {
+                tool_name,
+                is_error: tool_error,
+                ..
+            } => {
+                attributes.push(string_attribute("pi.tool.name", tool_name));
+                *tool_error
+            }
+            TelemetryEvent::Error { source, message } => {
+                attributes.push(string_attribute("pi.error.source", source));
+                attributes.push(string_attribute("pi.error.message", message));
+                true
+            }
+        };
+        let span_id = uuid::Uuid::new_v4().simple().to_string()[..16].to_string();
+        json!({
+            "traceId": trace_id,
+            "spanId": span_id,
+            "name": event.name(),
+            "kind": 1,
+            "startTimeUnixNano": start.to_string(),
+            "endTimeUnixNano": end_unix_nanos.to_string(),
+            "attributes": attributes,
+            "status": { "code": if is_error { 2 } else { 1 } },
+        })
+    }
+
+    pub fn build_traces_payload(service_name: &str, spans: Vec<Value>) -> Value {
+        json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [string_attribute("service.name", service_name)],
+                },
+                "scopeSpans": [{
+                    "scope": { "name": "pi", "version": env!("CARGO_PKG_VERSION") },
+                    "spans": spans,
+                }],
+            }],
+        })
+    }
+
+    fn string_attribute(key: &str, value: &str) -> Value {
+        json!({ "key": key, "value": { "stringValue": value } })
+    }
+
+    fn int_attribute(key: &str, value: i64) -> Value {
+        // OTLP JSON encodes 64-bit integers as strings.
+        json!({ "key": key, "value": { "intValue": value.to_string() } })
+    }
+}
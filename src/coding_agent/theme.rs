@@ -1,5 +1,5 @@
 use crate::config;
-use crate::tui::{EditorTheme, MarkdownTheme, SelectListTheme};
+use crate::tui::{EditorTheme, MarkdownTheme, SelectListTheme, SyntaxKind};
 use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use serde::Deserialize;
 use std::collections::{HashMap, HashSet};
@@ -280,6 +280,22 @@ impl MarkdownTheme for ThemeMarkdown {
     fn underline(&self, text: &str) -> String {
         self.theme.underline(text)
     }
+
+    fn syntax(&self, kind: SyntaxKind, text: &str) -> String {
+        let color = match kind {
+            SyntaxKind::Plain => ThemeColor::MdCodeBlock,
+            SyntaxKind::Comment => ThemeColor::SyntaxComment,
+            SyntaxKind::Keyword => ThemeColor::SyntaxKeyword,
+            SyntaxKind::Function => ThemeColor::SyntaxFunction,
+            SyntaxKind::Variable => ThemeColor::SyntaxVariable,
+            SyntaxKind::String => ThemeColor::SyntaxString,
+            SyntaxKind::Number => ThemeColor::SyntaxNumber,
+            SyntaxKind::Type => ThemeColor::SyntaxType,
+            SyntaxKind::Operator => ThemeColor::SyntaxOperator,
+            SyntaxKind::Punctuation => ThemeColor::SyntaxPunctuation,
+        };
+        self.theme.fg(color, text)
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -288,6 +304,8 @@ struct ThemeJson {
     #[serde(default)]
     vars: HashMap<String, ColorValue>,
     colors: HashMap<String, ColorValue>,
+    #[serde(default)]
+    export: HashMap<String, ColorValue>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -345,6 +363,72 @@ pub fn set_active_theme(theme: Theme) {
     *lock.write().unwrap() = theme;
 }
 
+/// Resolves a theme to CSS custom properties for the HTML export, e.g. `("--accent", "#8abeb7")`.
+///
+/// Includes the page backgrounds from the theme's `export` section as `--body-bg`,
+/// `--container-bg` and `--info-bg`. Colors left to the terminal default get a text color
+/// that reads on the page background.
+pub fn export_css_vars(name: &str) -> Result<Vec<(String, String)>, String> {
+    let theme_json = load_theme_json(name)?;
+    let resolved = resolve_theme_colors(&theme_json)?;
+
+    let mut page = Vec::new();
+    for (key, css_name, fallback) in [
+        ("pageBg", "--body-bg", "#18181e"),
+        ("cardBg", "--container-bg", "#1e1e24"),
+        ("infoBg", "--info-bg", "#3c3728"),
+    ] {
+        let color = match theme_json.export.get(key) {
+            Some(value) => css_hex(resolve_color_value(
+                value,
+                &theme_json.vars,
+                &mut HashSet::new(),
+            )?),
+            None => None,
+        };
+        let color = color.unwrap_or_else(|| fallback.to_string());
+        page.push((css_name.to_string(), color));
+    }
+    let default_text = if is_light_hex(&page[0].1) {
+        "#1f1f1f"
+    } else {
+        "#e5e5e7"
+    };
+
+    let keys = THEME_COLORS
+        .iter()
+        .map(|(key, _)| *key)
+        .chain(THEME_BACKGROUNDS.iter().map(|(key, _)| *key));
+    let mut vars = Vec::new();
+    for key in keys {
+        let value = resolved
+            .get(key)
+            .and_then(|color| css_hex(*color))
+            .unwrap_or_else(|| default_text.to_string());
+        vars.push((format!("--{key}"), value));
+    }
+    vars.extend(page);
+    Ok(vars)
+}
+
+fn css_hex(color: ResolvedColor) -> Option<String> {
+    let (r, g, b) = match color {
+        ResolvedColor::Hex { r, g, b } => (r, g, b),
+        ResolvedColor::Index(index) => ansi256_to_rgb(index),
+        ResolvedColor::Default => return None,
+    };
+    Some(format!("#{r:02x}{g:02x}{b:02x}"))
+}
+
+fn is_light_hex(hex: &str) -> bool {
+    match hex.strip_prefix('#').map(parse_hex) {
+        Some(Ok(ResolvedColor::Hex { r, g, b })) => {
+            (0.299 * r as f32) + (0.587 * g as f32) + (0.114 * b as f32) > 128.0
+        }
+        _ => false,
+    }
+}
+
 fn load_theme_json(name: &str) -> Result<ThemeJson, String> {
     match name {
         "dark" => serde_json::from_str(BUILTIN_DARK)
@@ -537,6 +621,42 @@ fn rgb_to_256(r: u8, g: u8, b: u8) -> u8 {
     cube_index
 }
 
+fn ansi256_to_rgb(index: u8) -> (u8, u8, u8) {
+    const BASIC: [(u8, u8, u8); 16] = [
+        (0, 0, 0),
+        (128, 0, 0),
+        (0, 128, 0),
+        (128, 128, 0),
+        (0, 0, 128),
+        (128, 0, 128),
+        (0, 128, 128),
+        (192, 192, 192),
+        (128, 128, 128),
+        (255, 0, 0),
+        (0, 255, 0),
+        (255, 255, 0),
+        (0, 0, 255),
+        (255, 0, 255),
+        (0, 255, 255),
+        (255, 255, 255),
+    ];
+    match index {
+        0..=15 => BASIC[index as usize],
+        16..=231 => {
+            let cube = index - 16;
+            (
+                CUBE_VALUES[(cube / 36) as usize],
+                CUBE_VALUES[((cube / 6) % 6) as usize],
+                CUBE_VALUES[(cube % 6) as usize],
+            )
+        }
+        _ => {
+            let gray = gray_values()[(index - 232) as usize];
+            (gray, gray, gray)
+        }
+    }
+}
+
 fn closest_cube_index(value: u8) -> (u8, u8) {
     let mut best_idx = 0;
     let mut best_dist = i32::MAX;
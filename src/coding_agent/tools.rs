@@ -1,12 +1,26 @@
+use crate::agent::{CancellationToken, ToolError};
+use crate::coding_agent::edit_review::{rejection_message, EditDecision, EditProposal};
+use crate::coding_agent::git::run_git;
+use crate::coding_agent::gitignore::{walk, WalkEntry, WalkOptions};
+use crate::coding_agent::notebook::Notebook;
+use crate::coding_agent::outline::{find_symbols, outline, render_outline};
+use crate::coding_agent::read_tracker::SharedReadTracker;
+use crate::coding_agent::{
+    BashApproval, BashDecision, BashPolicy, EditReview, FileChange, SandboxPolicy,
+    SharedChangeJournal, Shell,
+};
+use crate::core::diff::unified_diff;
 use crate::core::messages::ContentBlock;
 use regex::RegexBuilder;
 use serde::Serialize;
 use serde_json::{json, Value};
-use std::collections::HashSet;
+use std::cmp::Reverse;
 use std::fs;
 use std::io::Read;
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
+use std::process::{Child, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
 use std::time::{Duration, Instant};
 use uuid::Uuid;
 
@@ -43,11 +57,17 @@ pub enum TruncatedBy {
     Bytes,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct ReadToolArgs {
     pub path: String,
     pub offset: Option<usize>,
     pub limit: Option<usize>,
+    /// Read only this declaration, e.g. `parse` or `Parser::parse`.
+    pub symbol: Option<String>,
+    /// List the file's declarations with their line ranges instead of its content.
+    pub outline: Option<bool>,
+    /// Prefix each line with its number. Symbol reads are numbered unless this is `false`.
+    pub line_numbers: Option<bool>,
 }
 
 #[derive(Clone, Debug)]
@@ -78,63 +98,181 @@ pub struct GrepToolArgs {
     pub literal: Option<bool>,
     pub context: Option<usize>,
     pub limit: Option<usize>,
+    /// Let matches span lines; `.` then also matches newlines.
+    pub multiline: Option<bool>,
+    /// Only search files of this type, e.g. `rust` or `ts` (see `grep_file_types`).
+    pub file_type: Option<String>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct FindToolArgs {
     pub pattern: String,
     pub path: Option<String>,
     pub limit: Option<usize>,
+    /// How many directory levels to descend; 1 searches only `path` itself.
+    pub max_depth: Option<usize>,
+    /// `file` (default) or `dir`.
+    pub entry_type: Option<String>,
+    /// `path` (default), `modified` (newest first) or `size` (largest first).
+    pub sort: Option<String>,
+    /// Include files that `.gitignore` excludes.
+    pub include_ignored: Option<bool>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct LsToolArgs {
     pub path: Option<String>,
     pub limit: Option<usize>,
+    /// How many directory levels to list. Defaults to 1, the directory's own entries.
+    pub max_depth: Option<usize>,
+    /// `file` or `dir`; both by default.
+    pub entry_type: Option<String>,
+    /// `name` (default), `modified` (newest first) or `size` (largest first).
+    pub sort: Option<String>,
+    /// Include entries that `.gitignore` excludes.
+    pub include_ignored: Option<bool>,
+}
+
+#[derive(Clone, Debug)]
+pub struct GitDiffToolArgs {
+    pub path: Option<String>,
+    pub staged: Option<bool>,
+}
+
+#[derive(Clone, Debug)]
+pub struct GitLogToolArgs {
+    pub path: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+pub struct GitCommitToolArgs {
+    pub message: String,
+    /// Stage all changes (including untracked files) first. Defaults to true.
+    pub all: Option<bool>,
+}
+
+#[derive(Clone, Debug)]
+pub struct GitStashToolArgs {
+    /// `push` (default), `pop` or `list`.
+    pub action: Option<String>,
+    pub message: Option<String>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct NotebookReadToolArgs {
+    pub path: String,
+    /// First cell to show, 0-based.
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+    /// Include cell outputs (default true).
+    pub outputs: Option<bool>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct NotebookEditToolArgs {
+    pub path: String,
+    /// `replace` (default), `insert` or `delete`.
+    pub action: Option<String>,
+    /// 0-based cell index; for `insert`, the index the new cell takes.
+    pub index: Option<usize>,
+    /// Target a cell by its nbformat id instead; `insert` adds the new cell after it.
+    pub cell_id: Option<String>,
+    pub source: Option<String>,
+    /// `code`, `markdown` or `raw`.
+    pub cell_type: Option<String>,
 }
 
 #[derive(Clone, Debug)]
 pub struct ReadTool {
     cwd: PathBuf,
+    sandbox: Option<SandboxPolicy>,
+    reads: Option<SharedReadTracker>,
 }
 
 #[derive(Clone, Debug)]
 pub struct WriteTool {
     cwd: PathBuf,
+    journal: Option<SharedChangeJournal>,
+    sandbox: Option<SandboxPolicy>,
+    review: Option<EditReview>,
+    reads: Option<SharedReadTracker>,
 }
 
 #[derive(Clone, Debug)]
 pub struct EditTool {
     cwd: PathBuf,
+    journal: Option<SharedChangeJournal>,
+    sandbox: Option<SandboxPolicy>,
+    review: Option<EditReview>,
+    reads: Option<SharedReadTracker>,
 }
 
 #[derive(Clone, Debug)]
 pub struct BashTool {
     cwd: PathBuf,
+    shell: Shell,
+    sandbox: Option<SandboxPolicy>,
+    policy: Option<BashPolicy>,
+    approval: BashApproval,
+    cancel: Option<CancellationToken>,
 }
 
 #[derive(Clone, Debug)]
 pub struct GrepTool {
     cwd: PathBuf,
+    sandbox: Option<SandboxPolicy>,
 }
 
 #[derive(Clone, Debug)]
 pub struct FindTool {
     cwd: PathBuf,
+    sandbox: Option<SandboxPolicy>,
 }
 
 #[derive(Clone, Debug)]
 pub struct LsTool {
     cwd: PathBuf,
+    sandbox: Option<SandboxPolicy>,
+}
+
+#[derive(Clone, Debug)]
+pub struct GitTool {
+    cwd: PathBuf,
+}
+
+#[derive(Clone, Debug)]
+pub struct NotebookTool {
+    cwd: PathBuf,
+    journal: Option<SharedChangeJournal>,
+    sandbox: Option<SandboxPolicy>,
+    reads: Option<SharedReadTracker>,
 }
 
 impl ReadTool {
     pub fn new(cwd: impl Into<PathBuf>) -> Self {
-        Self { cwd: cwd.into() }
+        Self {
+            cwd: cwd.into(),
+            sandbox: None,
+            reads: None,
+        }
+    }
+
+    /// Refuse paths that `policy` does not allow reading.
+    pub fn with_sandbox(mut self, policy: SandboxPolicy) -> Self {
+        self.sandbox = Some(policy);
+        self
+    }
+
+    /// Note each file read in `reads`, so later writes can tell if it changed on disk.
+    pub fn with_read_tracker(mut self, reads: SharedReadTracker) -> Self {
+        self.reads = Some(reads);
+        self
     }
 
     pub fn execute(&self, _call_id: &str, args: ReadToolArgs) -> Result<ToolResult, String> {
         let absolute_path = resolve_path(&args.path, &self.cwd);
+        check_sandbox_read(self.sandbox.as_ref(), &absolute_path)?;
         let data = fs::read(&absolute_path).map_err(|err| match err.kind() {
             std::io::ErrorKind::NotFound => format!("File not found: {}", args.path),
             _ => format!("Failed to read {}: {}", args.path, err),
@@ -158,8 +296,15 @@ impl ReadTool {
             });
         }
 
+        if let Some(reads) = self.reads.as_ref() {
+            reads.borrow_mut().record(&absolute_path, &data);
+        }
         let text = String::from_utf8(data)
             .map_err(|err| format!("Failed to read {}: {}", args.path, err))?;
+        let outline_requested = args.outline.unwrap_or(false);
+        if outline_requested || args.symbol.is_some() {
+            return read_symbols(&absolute_path, &text, &args, outline_requested);
+        }
         let all_lines: Vec<&str> = text.split('\n').collect();
         let total_file_lines = all_lines.len();
         let offset_value = args.offset.unwrap_or(1);
@@ -172,15 +317,14 @@ impl ReadTool {
             ));
         }
 
-        let (selected_content, user_limited_lines) = match args.limit {
-            Some(limit) => {
-                let end_line = (start_line + limit).min(total_file_lines);
-                (
-                    all_lines[start_line..end_line].join("\n"),
-                    Some(end_line - start_line),
-                )
-            }
-            None => (all_lines[start_line..].join("\n"), None),
+        let end_line = args.limit.map_or(total_file_lines, |limit| {
+            (start_line + limit).min(total_file_lines)
+        });
+        let user_limited_lines = args.limit.map(|_| end_line - start_line);
+        let selected_content = if args.line_numbers.unwrap_or(false) {
+            number_lines(&all_lines[start_line..end_line], start_line + 1)
+        } else {
+            all_lines[start_line..end_line].join("\n")
         };
 
         let truncation = truncate_head(&selected_content, None);
@@ -249,26 +393,92 @@ impl ReadTool {
 
 impl WriteTool {
     pub fn new(cwd: impl Into<PathBuf>) -> Self {
-        Self { cwd: cwd.into() }
+        Self {
+            cwd: cwd.into(),
+            journal: None,
+            sandbox: None,
+            review: None,
+            reads: None,
+        }
+    }
+
+    /// Refuse paths that `policy` does not allow writing.
+    pub fn with_sandbox(mut self, policy: SandboxPolicy) -> Self {
+        self.sandbox = Some(policy);
+        self
+    }
+
+    /// Record each write in `journal` so it can be undone.
+    pub fn with_journal(mut self, journal: SharedChangeJournal) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Ask `review` before each write touches disk.
+    pub fn with_review(mut self, review: EditReview) -> Self {
+        self.review = Some(review);
+        self
     }
 
-    pub fn execute(&self, _call_id: &str, args: WriteToolArgs) -> Result<ToolResult, String> {
+    /// Refuse to overwrite files in `reads` that changed on disk since they were read.
+    pub fn with_read_tracker(mut self, reads: SharedReadTracker) -> Self {
+        self.reads = Some(reads);
+        self
+    }
+
+    pub fn execute(&self, call_id: &str, args: WriteToolArgs) -> Result<ToolResult, ToolError> {
         let absolute_path = resolve_path(&args.path, &self.cwd);
+        check_sandbox_write(self.sandbox.as_ref(), &absolute_path)?;
+        check_not_stale(self.reads.as_ref(), &absolute_path, &args.path)?;
+        let original = fs::read_to_string(&absolute_path).ok();
+        let (content, rewritten) = review_change(
+            self.review.as_ref(),
+            "write",
+            &args.path,
+            original.as_deref(),
+            args.content,
+        )?;
+        // A new file's diff would only repeat the content back to the model.
+        let diff_block = original
+            .as_deref()
+            .map(|original| ContentBlock::diff(&args.path, unified_diff(original, &content, 3)));
         if let Some(parent) = absolute_path.parent() {
             fs::create_dir_all(parent)
                 .map_err(|err| format!("Failed to create directory for {}: {}", args.path, err))?;
         }
-        fs::write(&absolute_path, args.content.as_bytes())
+        fs::write(&absolute_path, content.as_bytes())
             .map_err(|err| format!("Failed to write {}: {}", args.path, err))?;
+        if let Some(reads) = self.reads.as_ref() {
+            reads
+                .borrow_mut()
+                .record(&absolute_path, content.as_bytes());
+        }
+        if let Some(journal) = self.journal.as_ref() {
+            let diff = generate_diff_string(original.as_deref().unwrap_or(""), &content);
+            journal.borrow_mut().record(FileChange::new(
+                absolute_path,
+                "write",
+                call_id,
+                original,
+                diff,
+            ));
+        }
+        let mut text = format!(
+            "Successfully wrote {} bytes to {}",
+            content.len(),
+            args.path
+        );
+        if rewritten {
+            text.push('.');
+            text.push_str(rewritten_note(rewritten));
+        }
         Ok(ToolResult {
-            content: vec![ContentBlock::Text {
-                text: format!(
-                    "Successfully wrote {} bytes to {}",
-                    args.content.len(),
-                    args.path
-                ),
+            content: std::iter::once(ContentBlock::Text {
+                text,
                 text_signature: None,
-            }],
+            })
+            .chain(diff_block)
+            .collect(),
             details: None,
         })
     }
@@ -276,11 +486,43 @@ impl WriteTool {
 
 impl EditTool {
     pub fn new(cwd: impl Into<PathBuf>) -> Self {
-        Self { cwd: cwd.into() }
+        Self {
+            cwd: cwd.into(),
+            journal: None,
+            sandbox: None,
+            review: None,
+            reads: None,
+        }
+    }
+
+    /// Refuse paths that `policy` does not allow writing.
+    pub fn with_sandbox(mut self, policy: SandboxPolicy) -> Self {
+        self.sandbox = Some(policy);
+        self
+    }
+
+    /// Record each edit in `journal` so it can be undone.
+    pub fn with_journal(mut self, journal: SharedChangeJournal) -> Self {
+        self.journal = Some(journal);
+        self
     }
 
-    pub fn execute(&self, _call_id: &str, args: EditToolArgs) -> Result<ToolResult, String> {
+    /// Ask `review` before each edit touches disk.
+    pub fn with_review(mut self, review: EditReview) -> Self {
+        self.review = Some(review);
+        self
+    }
+
+    /// Refuse to overwrite files in `reads` that changed on disk since they were read.
+    pub fn with_read_tracker(mut self, reads: SharedReadTracker) -> Self {
+        self.reads = Some(reads);
+        self
+    }
+
+    pub fn execute(&self, call_id: &str, args: EditToolArgs) -> Result<ToolResult, ToolError> {
         let absolute_path = resolve_path(&args.path, &self.cwd);
+        check_sandbox_write(self.sandbox.as_ref(), &absolute_path)?;
+        check_not_stale(self.reads.as_ref(), &absolute_path, &args.path)?;
         let raw_content = fs::read_to_string(&absolute_path)
             .map_err(|_| format!("File not found: {}", args.path))?;
 
@@ -294,7 +536,7 @@ impl EditTool {
             return Err(format!(
                 "Could not find the exact text in {}. The old text must match exactly including all whitespace and newlines.",
                 args.path
-            ));
+            ).into());
         }
 
         let occurrences = normalized_content.matches(&normalized_old).count();
@@ -302,7 +544,7 @@ impl EditTool {
             return Err(format!(
                 "Found {} occurrences of the text in {}. The text must be unique. Please provide more context to make it unique.",
                 occurrences, args.path
-            ));
+            ).into());
         }
 
         let index = normalized_content
@@ -319,23 +561,59 @@ impl EditTool {
             return Err(format!(
                 "No changes made to {}. The replacement produced identical content.",
                 args.path
-            ));
+            )
+            .into());
         }
 
         let restored = restore_line_endings(&normalized_new_content, original_ending);
-        let final_content = format!("{bom}{restored}");
+        let (final_content, rewritten) = review_change(
+            self.review.as_ref(),
+            "edit",
+            &args.path,
+            Some(&raw_content),
+            format!("{bom}{restored}"),
+        )?;
+        let normalized_new_content = if rewritten {
+            normalize_to_lf(&strip_bom(&final_content).1)
+        } else {
+            normalized_new_content
+        };
         fs::write(&absolute_path, final_content.as_bytes())
             .map_err(|err| format!("Failed to write {}: {}", args.path, err))?;
+        if let Some(reads) = self.reads.as_ref() {
+            reads
+                .borrow_mut()
+                .record(&absolute_path, final_content.as_bytes());
+        }
 
         let diff = generate_diff_string(&normalized_content, &normalized_new_content);
         let first_changed_line =
             find_first_changed_line(&normalized_content, &normalized_new_content);
+        if let Some(journal) = self.journal.as_ref() {
+            journal.borrow_mut().record(FileChange::new(
+                absolute_path,
+                "edit",
+                call_id,
+                Some(raw_content),
+                diff.clone(),
+            ));
+        }
 
         Ok(ToolResult {
-            content: vec![ContentBlock::Text {
-                text: format!("Successfully replaced text in {}.", args.path),
-                text_signature: None,
-            }],
+            content: vec![
+                ContentBlock::Text {
+                    text: format!(
+                        "Successfully replaced text in {}.{}",
+                        args.path,
+                        rewritten_note(rewritten)
+                    ),
+                    text_signature: None,
+                },
+                ContentBlock::diff(
+                    &args.path,
+                    unified_diff(&normalized_content, &normalized_new_content, 3),
+                ),
+            ],
             details: Some(json!({
                 "diff": diff,
                 "firstChangedLine": first_changed_line,
@@ -346,10 +624,55 @@ impl EditTool {
 
 impl BashTool {
     pub fn new(cwd: impl Into<PathBuf>) -> Self {
-        Self { cwd: cwd.into() }
+        Self {
+            cwd: cwd.into(),
+            shell: Shell::tool_default(),
+            sandbox: None,
+            policy: None,
+            approval: BashApproval::default(),
+            cancel: None,
+        }
+    }
+
+    /// Kill the running command as soon as `cancel` is cancelled.
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Check commands against `policy` before running them, asking `approval` when the
+    /// policy cannot decide.
+    pub fn with_policy(mut self, policy: BashPolicy, approval: BashApproval) -> Self {
+        self.policy = Some(policy);
+        self.approval = approval;
+        self
+    }
+
+    /// Run commands through `policy`, which may wrap them in `firejail`/`sandbox-exec`.
+    pub fn with_sandbox(mut self, policy: SandboxPolicy) -> Self {
+        self.sandbox = Some(policy);
+        self
+    }
+
+    /// Run commands with `shell` instead of the platform default.
+    pub fn with_shell(mut self, shell: Shell) -> Self {
+        self.shell = shell;
+        self
     }
 
-    pub fn execute(&self, _call_id: &str, args: BashToolArgs) -> Result<ToolResult, String> {
+    pub fn execute(&self, call_id: &str, args: BashToolArgs) -> Result<ToolResult, String> {
+        self.execute_with_updates(call_id, args, &mut |_| {})
+    }
+
+    /// Like [`BashTool::execute`], but reports the (tail-truncated) output so far to
+    /// `on_update` whenever the command writes more.
+    pub fn execute_with_updates(
+        &self,
+        _call_id: &str,
+        args: BashToolArgs,
+        on_update: &mut dyn FnMut(ToolResult),
+    ) -> Result<ToolResult, String> {
+        self.check_policy(&args.command)?;
         let cwd = self.cwd.clone();
         if !cwd.exists() {
             return Err(format!(
@@ -358,26 +681,45 @@ impl BashTool {
             ));
         }
 
-        let mut child = Command::new("bash")
-            .arg("-lc")
-            .arg(&args.command)
+        let mut command = match self.sandbox.as_ref() {
+            Some(policy) => policy.bash_command(&self.shell, &args.command),
+            None => self.shell.command(&args.command),
+        };
+        // Its own process group, so an abort or timeout can kill everything the command
+        // started (`cargo build`, `npm test`), not just the shell. Out of the terminal's
+        // foreground group, the command must not read the terminal.
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+        let mut child = command
             .current_dir(&cwd)
+            .stdin(Stdio::null())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()
-            .map_err(|err| format!("Failed to execute bash: {err}"))?;
-
-        let mut stdout = child.stdout.take();
-        let mut stderr = child.stderr.take();
+            .map_err(|err| format!("Failed to execute {}: {err}", self.shell.program))?;
+
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let readers = [
+            child
+                .stdout
+                .take()
+                .map(|out| spawn_output_reader(out, output.clone())),
+            child
+                .stderr
+                .take()
+                .map(|err| spawn_output_reader(err, output.clone())),
+        ];
+        let mut reported_len = 0;
         let start = Instant::now();
         let timeout = args.timeout.map(Duration::from_secs);
         let mut exit_status = None;
         let mut timed_out = false;
+        let mut aborted = false;
 
         loop {
             if let Some(status) = child
                 .try_wait()
-                .map_err(|err| format!("Failed to execute bash: {err}"))?
+                .map_err(|err| format!("Failed to execute {}: {err}", self.shell.program))?
             {
                 exit_status = Some(status);
                 break;
@@ -385,22 +727,33 @@ impl BashTool {
             if let Some(timeout) = timeout {
                 if start.elapsed() >= timeout {
                     timed_out = true;
-                    let _ = child.kill();
+                    kill_process_group(&mut child);
                     let _ = child.wait();
                     break;
                 }
             }
+            if self
+                .cancel
+                .as_ref()
+                .is_some_and(|cancel| cancel.is_cancelled())
+            {
+                aborted = true;
+                kill_process_group(&mut child);
+                let _ = child.wait();
+                break;
+            }
+            report_bash_progress(&output, &mut reported_len, on_update);
             std::thread::sleep(Duration::from_millis(10));
         }
 
-        let mut output = Vec::new();
-        if let Some(mut out) = stdout.take() {
-            let _ = out.read_to_end(&mut output);
-        }
-        if let Some(mut err) = stderr.take() {
-            let _ = err.read_to_end(&mut output);
+        // A process that left the command's group (a daemon) may hold its pipes open; keep
+        // what the readers collected so far rather than waiting for them.
+        if !aborted {
+            for reader in readers.into_iter().flatten() {
+                let _ = reader.join();
+            }
         }
-        let combined = String::from_utf8_lossy(&output).to_string();
+        let combined = String::from_utf8_lossy(&output.lock().unwrap()).to_string();
         let truncation = truncate_tail(&combined, None);
         let mut output_text = if truncation.content.is_empty() {
             "(no output)".to_string()
@@ -459,6 +812,10 @@ impl BashTool {
             let _ = full_output_path;
         }
 
+        if aborted {
+            output_text.push_str("\n\nCommand aborted");
+            return Err(output_text);
+        }
         if timed_out {
             output_text.push_str(&format!(
                 "\n\nCommand timed out after {} seconds",
@@ -484,102 +841,187 @@ impl BashTool {
             details,
         })
     }
+
+    fn check_policy(&self, command: &str) -> Result<(), String> {
+        let Some(policy) = self.policy.as_ref() else {
+            return Ok(());
+        };
+        match policy.evaluate(command) {
+            BashDecision::Allow => Ok(()),
+            BashDecision::Deny(reason) => Err(format!("Command blocked by bash policy: {reason}")),
+            BashDecision::Ask(reason) => match self.approval.request(command, &reason) {
+                Some(true) => Ok(()),
+                Some(false) => Err(format!("Command was not approved by the user: {reason}")),
+                None => Err(format!(
+                    "Command needs approval ({reason}) but cannot be confirmed in this mode. Add an allow rule to bashPolicy in settings"
+                )),
+            },
+        }
+    }
+}
+
+/// Kills the shell and every process in its group.
+fn kill_process_group(child: &mut Child) {
+    #[cfg(unix)]
+    // SAFETY: `kill` has no memory-safety preconditions; a negative pid names the group the
+    // shell leads.
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+    let _ = child.kill();
+}
+
+fn spawn_output_reader(
+    mut source: impl Read + Send + 'static,
+    output: Arc<Mutex<Vec<u8>>>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut buffer = [0u8; 8192];
+        while let Ok(read) = source.read(&mut buffer) {
+            if read == 0 {
+                break;
+            }
+            output.lock().unwrap().extend_from_slice(&buffer[..read]);
+        }
+    })
+}
+
+fn report_bash_progress(
+    output: &Mutex<Vec<u8>>,
+    reported_len: &mut usize,
+    on_update: &mut dyn FnMut(ToolResult),
+) {
+    let text = {
+        let output = output.lock().unwrap();
+        if output.len() == *reported_len {
+            return;
+        }
+        *reported_len = output.len();
+        String::from_utf8_lossy(&output).to_string()
+    };
+    on_update(ToolResult {
+        content: vec![ContentBlock::Text {
+            text: truncate_tail(&text, None).content,
+            text_signature: None,
+        }],
+        details: None,
+    });
 }
 
 impl GrepTool {
     pub fn new(cwd: impl Into<PathBuf>) -> Self {
-        Self { cwd: cwd.into() }
+        Self {
+            cwd: cwd.into(),
+            sandbox: None,
+        }
+    }
+
+    /// Refuse paths that `policy` does not allow reading.
+    pub fn with_sandbox(mut self, policy: SandboxPolicy) -> Self {
+        self.sandbox = Some(policy);
+        self
     }
 
     pub fn execute(&self, _call_id: &str, args: GrepToolArgs) -> Result<ToolResult, String> {
         let search_path = resolve_path(args.path.as_deref().unwrap_or("."), &self.cwd);
+        check_sandbox_read(self.sandbox.as_ref(), &search_path)?;
         let metadata = fs::metadata(&search_path)
             .map_err(|_| format!("Path not found: {}", search_path.display()))?;
         let effective_limit = args.limit.unwrap_or(100).max(1);
         let context = args.context.unwrap_or(0);
-        let ignore_case = args.ignore_case.unwrap_or(false);
-        let literal = args.literal.unwrap_or(false);
-        let matcher = build_grep_matcher(&args.pattern, ignore_case, literal)?;
-
-        let mut matches_output = Vec::new();
-        let mut match_count = 0usize;
-        let mut match_limit_reached = false;
-        let mut lines_truncated = false;
+        let multiline = args.multiline.unwrap_or(false);
+        let matcher = build_grep_matcher(
+            &args.pattern,
+            args.ignore_case.unwrap_or(false),
+            args.literal.unwrap_or(false),
+            multiline,
+        )?;
+        let extensions = match args.file_type.as_deref() {
+            Some(name) => Some(grep_type_extensions(name)?),
+            None => None,
+        };
 
-        if metadata.is_file() {
-            let content = fs::read_to_string(&search_path)
-                .map_err(|err| format!("Failed to read {}: {}", search_path.display(), err))?;
-            let normalized = normalize_to_lf(&content);
-            let lines: Vec<&str> = normalized.split('\n').collect();
+        let files = if metadata.is_file() {
             let file_label = search_path
                 .file_name()
                 .map(|name| name.to_string_lossy().to_string())
                 .unwrap_or_else(|| search_path.display().to_string());
-
-            for (idx, line) in lines.iter().enumerate() {
-                if matcher.is_match(line) {
-                    match_count += 1;
-                    let line_number = idx + 1;
-                    append_grep_block(
-                        &file_label,
-                        &lines,
-                        line_number,
-                        context,
-                        &mut matches_output,
-                        &mut lines_truncated,
-                    );
-                    if match_count >= effective_limit {
-                        match_limit_reached = true;
-                        break;
-                    }
-                }
-            }
+            vec![(file_label, search_path.clone())]
         } else if metadata.is_dir() {
-            let ignore_set = read_gitignore(&search_path);
             let mut files = Vec::new();
-            collect_grep_files(
-                &search_path,
-                &search_path,
-                args.glob.as_deref(),
-                &ignore_set,
-                &mut files,
-            );
-            files.sort();
-            for rel in files {
-                let file_path = search_path.join(&rel);
-                let content = match fs::read_to_string(&file_path) {
-                    Ok(content) => content,
-                    Err(_) => continue,
-                };
-                let normalized = normalize_to_lf(&content);
-                let lines: Vec<&str> = normalized.split('\n').collect();
-                for (idx, line) in lines.iter().enumerate() {
-                    if matcher.is_match(line) {
-                        match_count += 1;
-                        let line_number = idx + 1;
-                        append_grep_block(
-                            &rel,
-                            &lines,
-                            line_number,
-                            context,
-                            &mut matches_output,
-                            &mut lines_truncated,
-                        );
-                        if match_count >= effective_limit {
-                            match_limit_reached = true;
-                            break;
-                        }
-                    }
-                }
-                if match_limit_reached {
-                    break;
+            walk(&search_path, WalkOptions::default(), &mut |entry| {
+                let wanted = !entry.is_dir
+                    && args
+                        .glob
+                        .as_deref()
+                        .is_none_or(|glob| glob_matches(&entry.rel_path, glob))
+                    && extensions
+                        .is_none_or(|extensions| has_extension(&entry.rel_path, extensions));
+                if wanted {
+                    files.push((entry.rel_path.clone(), entry.path.clone()));
                 }
-            }
+                true
+            });
+            files.sort();
+            files
         } else {
             return Err(format!(
                 "Not a file or directory: {}",
                 search_path.display()
             ));
+        };
+
+        let mut matches_output = Vec::new();
+        let mut structured = Vec::new();
+        let mut structured_bytes = 0usize;
+        let mut structured_truncated = false;
+        let mut match_count = 0usize;
+        let mut match_limit_reached = false;
+        let mut lines_truncated = false;
+
+        'files: for (file_label, file_path) in &files {
+            let content = match fs::read_to_string(file_path) {
+                Ok(content) => content,
+                // Only a file named explicitly is worth an error; in a directory, unreadable
+                // and binary files are skipped.
+                Err(err) if metadata.is_file() => {
+                    return Err(format!("Failed to read {}: {}", search_path.display(), err));
+                }
+                Err(_) => continue,
+            };
+            if content.contains('\0') {
+                continue;
+            }
+            let normalized = normalize_to_lf(&content);
+            let lines: Vec<&str> = normalized.split('\n').collect();
+            for found in find_grep_matches(&matcher, &normalized, &lines, multiline) {
+                match_count += 1;
+                append_grep_block(
+                    file_label,
+                    &lines,
+                    found.line,
+                    found.end_line,
+                    context,
+                    &mut matches_output,
+                    &mut lines_truncated,
+                );
+                let (text, _) = truncate_line(&found.text, GREP_MAX_LINE_LENGTH);
+                structured_bytes += file_label.len() + text.len();
+                if structured_bytes <= DEFAULT_MAX_BYTES {
+                    structured.push(json!({
+                        "file": file_label,
+                        "line": found.line,
+                        "column": found.column,
+                        "text": text,
+                    }));
+                } else {
+                    structured_truncated = true;
+                }
+                if match_count >= effective_limit {
+                    match_limit_reached = true;
+                    break 'files;
+                }
+            }
         }
 
         if match_count == 0 {
@@ -598,6 +1040,10 @@ impl GrepTool {
 
         let mut notices = Vec::new();
         let mut details = serde_json::Map::new();
+        details.insert("matches".to_string(), Value::Array(structured));
+        if structured_truncated {
+            details.insert("matchesTruncated".to_string(), json!(true));
+        }
 
         if match_limit_reached {
             notices.push(format!(
@@ -639,22 +1085,38 @@ impl GrepTool {
 
 impl FindTool {
     pub fn new(cwd: impl Into<PathBuf>) -> Self {
-        Self { cwd: cwd.into() }
+        Self {
+            cwd: cwd.into(),
+            sandbox: None,
+        }
+    }
+
+    /// Refuse paths that `policy` does not allow reading.
+    pub fn with_sandbox(mut self, policy: SandboxPolicy) -> Self {
+        self.sandbox = Some(policy);
+        self
     }
 
     pub fn execute(&self, _call_id: &str, args: FindToolArgs) -> Result<ToolResult, String> {
         let search_path = resolve_path(args.path.as_deref().unwrap_or("."), &self.cwd);
+        check_sandbox_read(self.sandbox.as_ref(), &search_path)?;
         let effective_limit = args.limit.unwrap_or(1000);
-        let ignore_set = read_gitignore(&search_path);
+        let wanted_dirs = match parse_entry_type(args.entry_type.as_deref())? {
+            Some(EntryType::Dir) => true,
+            Some(EntryType::File) | None => false,
+        };
+        let options = WalkOptions {
+            max_depth: args.max_depth.map(|depth| depth.max(1)),
+            include_ignored: args.include_ignored.unwrap_or(false),
+        };
 
         let mut results = Vec::new();
-        collect_files(
-            &search_path,
-            &search_path,
-            &args.pattern,
-            &ignore_set,
-            &mut results,
-        );
+        walk(&search_path, options, &mut |entry| {
+            if entry.is_dir == wanted_dirs && glob_matches(&entry.rel_path, &args.pattern) {
+                results.push(ListedEntry::new(entry));
+            }
+            true
+        });
 
         if results.is_empty() {
             return Ok(ToolResult {
@@ -666,13 +1128,17 @@ impl FindTool {
             });
         }
 
-        results.sort();
+        sort_entries(&mut results, args.sort.as_deref(), "path")?;
         let result_limit_reached = results.len() > effective_limit;
         if result_limit_reached {
             results.truncate(effective_limit);
         }
 
-        let raw_output = results.join("\n");
+        let raw_output = results
+            .iter()
+            .map(ListedEntry::display_path)
+            .collect::<Vec<_>>()
+            .join("\n");
         let truncation = truncate_head(raw_output.as_str(), Some((usize::MAX, DEFAULT_MAX_BYTES)));
         let mut output = truncation.content.clone();
 
@@ -686,6 +1152,7 @@ impl FindTool {
             ));
             details.insert("resultLimitReached".to_string(), json!(effective_limit));
         }
+        details.insert("entries".to_string(), entries_details(&results));
 
         if truncation.truncated {
             notices.push(format!("{} limit reached", format_size(DEFAULT_MAX_BYTES)));
@@ -712,11 +1179,21 @@ impl FindTool {
 
 impl LsTool {
     pub fn new(cwd: impl Into<PathBuf>) -> Self {
-        Self { cwd: cwd.into() }
+        Self {
+            cwd: cwd.into(),
+            sandbox: None,
+        }
+    }
+
+    /// Refuse paths that `policy` does not allow reading.
+    pub fn with_sandbox(mut self, policy: SandboxPolicy) -> Self {
+        self.sandbox = Some(policy);
+        self
     }
 
     pub fn execute(&self, _call_id: &str, args: LsToolArgs) -> Result<ToolResult, String> {
         let dir_path = resolve_path(args.path.as_deref().unwrap_or("."), &self.cwd);
+        check_sandbox_read(self.sandbox.as_ref(), &dir_path)?;
         let effective_limit = args.limit.unwrap_or(500);
         let metadata = fs::metadata(&dir_path)
             .map_err(|_| format!("Path not found: {}", dir_path.display()))?;
@@ -724,22 +1201,27 @@ impl LsTool {
             return Err(format!("Not a directory: {}", dir_path.display()));
         }
 
+        let entry_type = parse_entry_type(args.entry_type.as_deref())?;
+        let options = WalkOptions {
+            max_depth: Some(args.max_depth.unwrap_or(1).max(1)),
+            include_ignored: args.include_ignored.unwrap_or(false),
+        };
+        fs::read_dir(&dir_path).map_err(|err| format!("Cannot read directory: {}", err))?;
+
         let mut entries = Vec::new();
-        for entry in
-            fs::read_dir(&dir_path).map_err(|err| format!("Cannot read directory: {}", err))?
-        {
-            let entry = entry.map_err(|err| format!("Cannot read directory: {}", err))?;
-            let file_name = entry.file_name();
-            let name = file_name.to_string_lossy().to_string();
-            let entry_path = entry.path();
-            let suffix = match entry_path.metadata() {
-                Ok(stat) if stat.is_dir() => "/",
-                _ => "",
+        walk(&dir_path, options, &mut |entry| {
+            let wanted = match entry_type {
+                Some(EntryType::Dir) => entry.is_dir,
+                Some(EntryType::File) => !entry.is_dir,
+                None => true,
             };
-            entries.push(format!("{name}{suffix}"));
-        }
+            if wanted {
+                entries.push(ListedEntry::new(entry));
+            }
+            true
+        });
 
-        entries.sort_by_key(|a| a.to_lowercase());
+        sort_entries(&mut entries, args.sort.as_deref(), "name")?;
         let entry_limit_reached = entries.len() > effective_limit;
         if entry_limit_reached {
             entries.truncate(effective_limit);
@@ -748,7 +1230,11 @@ impl LsTool {
         let output = if entries.is_empty() {
             "(empty directory)".to_string()
         } else {
-            entries.join("\n")
+            entries
+                .iter()
+                .map(ListedEntry::display_path)
+                .collect::<Vec<_>>()
+                .join("\n")
         };
 
         if entries.is_empty() {
@@ -773,6 +1259,7 @@ impl LsTool {
             ));
             details.insert("entryLimitReached".to_string(), json!(effective_limit));
         }
+        details.insert("entries".to_string(), entries_details(&entries));
 
         if truncation.truncated {
             notices.push(format!("{} limit reached", format_size(DEFAULT_MAX_BYTES)));
@@ -797,8 +1284,496 @@ impl LsTool {
     }
 }
 
+impl GitTool {
+    pub fn new(cwd: impl Into<PathBuf>) -> Self {
+        Self { cwd: cwd.into() }
+    }
+
+    pub fn status(&self, _call_id: &str) -> Result<ToolResult, String> {
+        let output = run_git(&self.cwd, &["status", "--short", "--branch"])?;
+        Ok(git_tool_result(output, "No changes"))
+    }
+
+    pub fn diff(&self, _call_id: &str, args: GitDiffToolArgs) -> Result<ToolResult, String> {
+        let mut git_args = vec!["diff"];
+        if args.staged.unwrap_or(false) {
+            git_args.push("--cached");
+        }
+        if let Some(path) = args.path.as_deref() {
+            git_args.extend(["--", path]);
+        }
+        let output = run_git(&self.cwd, &git_args)?;
+        Ok(git_tool_result(output, "No differences"))
+    }
+
+    pub fn log(&self, _call_id: &str, args: GitLogToolArgs) -> Result<ToolResult, String> {
+        let limit = format!("-{}", args.limit.unwrap_or(20));
+        let mut git_args = vec!["log", "--oneline", "--decorate", limit.as_str()];
+        if let Some(path) = args.path.as_deref() {
+            git_args.extend(["--", path]);
+        }
+        let output = run_git(&self.cwd, &git_args)?;
+        Ok(git_tool_result(output, "No commits"))
+    }
+
+    pub fn commit(&self, _call_id: &str, args: GitCommitToolArgs) -> Result<ToolResult, String> {
+        if args.message.trim().is_empty() {
+            return Err("Commit message must not be empty".to_string());
+        }
+        if args.all.unwrap_or(true) {
+            run_git(&self.cwd, &["add", "-A"])?;
+        }
+        run_git(&self.cwd, &["commit", "-q", "-m", &args.message])?;
+        let output = run_git(&self.cwd, &["log", "-1", "--stat", "--format=%H%n%s"])?;
+        let commit = output.lines().next().unwrap_or_default().to_string();
+        let mut result = git_tool_result(output, "");
+        result.details = Some(json!({ "commit": commit }));
+        Ok(result)
+    }
+
+    pub fn stash(&self, _call_id: &str, args: GitStashToolArgs) -> Result<ToolResult, String> {
+        let output = match args.action.as_deref().unwrap_or("push") {
+            "push" => {
+                let mut git_args = vec!["stash", "push", "--include-untracked"];
+                if let Some(message) = args.message.as_deref() {
+                    git_args.extend(["-m", message]);
+                }
+                run_git(&self.cwd, &git_args)?
+            }
+            "pop" => run_git(&self.cwd, &["stash", "pop"])?,
+            "list" => run_git(&self.cwd, &["stash", "list"])?,
+            action => {
+                return Err(format!(
+                    "Unknown stash action \"{action}\". Use push, pop, or list"
+                ))
+            }
+        };
+        Ok(git_tool_result(output, "No stash entries"))
+    }
+}
+
+fn git_tool_result(output: String, empty_message: &str) -> ToolResult {
+    let output = output.trim_end();
+    let text = if output.is_empty() {
+        empty_message.to_string()
+    } else {
+        let truncation = truncate_head(output, Some((DEFAULT_MAX_LINES, DEFAULT_MAX_BYTES)));
+        let mut text = truncation.content;
+        if truncation.truncated {
+            text.push_str(&format!(
+                "\n\n[Output truncated: showing {} of {} lines]",
+                truncation.output_lines, truncation.total_lines
+            ));
+        }
+        text
+    };
+    ToolResult {
+        content: vec![ContentBlock::Text {
+            text,
+            text_signature: None,
+        }],
+        details: None,
+    }
+}
+
+impl NotebookTool {
+    pub fn new(cwd: impl Into<PathBuf>) -> Self {
+        Self {
+            cwd: cwd.into(),
+            journal: None,
+            sandbox: None,
+            reads: None,
+        }
+    }
+
+    /// Refuse paths that `policy` does not allow reading or writing.
+    pub fn with_sandbox(mut self, policy: SandboxPolicy) -> Self {
+        self.sandbox = Some(policy);
+        self
+    }
+
+    /// Record each edit in `journal` so it can be undone.
+    pub fn with_journal(mut self, journal: SharedChangeJournal) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Note notebooks read in `reads` and refuse to edit ones that changed on disk since.
+    pub fn with_read_tracker(mut self, reads: SharedReadTracker) -> Self {
+        self.reads = Some(reads);
+        self
+    }
+
+    fn load(&self, path: &str) -> Result<(PathBuf, String, Notebook), String> {
+        let absolute_path = resolve_path(path, &self.cwd);
+        let raw = fs::read_to_string(&absolute_path).map_err(|err| match err.kind() {
+            std::io::ErrorKind::NotFound => format!("File not found: {path}"),
+            _ => format!("Failed to read {path}: {err}"),
+        })?;
+        let notebook = Notebook::parse(&raw).map_err(|err| format!("{path}: {err}"))?;
+        Ok((absolute_path, raw, notebook))
+    }
+
+    pub fn read(&self, _call_id: &str, args: NotebookReadToolArgs) -> Result<ToolResult, String> {
+        check_sandbox_read(self.sandbox.as_ref(), &resolve_path(&args.path, &self.cwd))?;
+        let (absolute_path, raw, notebook) = self.load(&args.path)?;
+        if let Some(reads) = self.reads.as_ref() {
+            reads.borrow_mut().record(&absolute_path, raw.as_bytes());
+        }
+        let total = notebook.len();
+        let start = args.offset.unwrap_or(0);
+        if start >= total.max(1) {
+            return Err(format!(
+                "Offset {start} is beyond the end of the notebook ({total} cells)"
+            ));
+        }
+        let end = args
+            .limit
+            .map_or(total, |limit| start.saturating_add(limit).min(total));
+
+        let mut text = format!("{}: {total} cells ({})", args.path, notebook.language());
+        if total > 0 {
+            text.push_str("\n\n");
+            text.push_str(&notebook.render(start, end, args.outputs.unwrap_or(true)));
+        }
+        let truncation = truncate_head(&text, Some((DEFAULT_MAX_LINES, DEFAULT_MAX_BYTES)));
+        let mut text = truncation.content;
+        if truncation.truncated {
+            text.push_str(&format!(
+                "\n\n[Output truncated: showing {} of {} lines. Use offset and limit to read fewer cells]",
+                truncation.output_lines, truncation.total_lines
+            ));
+        } else if end < total {
+            text.push_str(&format!(
+                "\n\n[Showing cells {start}-{} of {total}. Use offset={end} to continue]",
+                end - 1
+            ));
+        }
+        Ok(ToolResult {
+            content: vec![ContentBlock::Text {
+                text,
+                text_signature: None,
+            }],
+            details: Some(json!({ "cells": total, "language": notebook.language() })),
+        })
+    }
+
+    pub fn edit(&self, call_id: &str, args: NotebookEditToolArgs) -> Result<ToolResult, ToolError> {
+        let absolute_path = resolve_path(&args.path, &self.cwd);
+        check_sandbox_write(self.sandbox.as_ref(), &absolute_path)?;
+        check_not_stale(self.reads.as_ref(), &absolute_path, &args.path)?;
+        let (absolute_path, raw, mut notebook) = self.load(&args.path)?;
+        let target = match args.cell_id.as_deref() {
+            Some(id) => Some(
+                notebook
+                    .find_cell(id)
+                    .ok_or_else(|| format!("No cell with id \"{id}\" in {}", args.path))?,
+            ),
+            None => args.index,
+        };
+        let require_target =
+            || target.ok_or_else(|| "Provide index or cellId to choose a cell".to_string());
+        let require_source = || {
+            args.source
+                .as_deref()
+                .ok_or_else(|| "Provide source for the cell".to_string())
+        };
+
+        let (index, old_source, new_source, message) =
+            match args.action.as_deref().unwrap_or("replace") {
+                "replace" => {
+                    let index = require_target()?;
+                    let source = require_source()?;
+                    let old_source = notebook.cell_source(index)?;
+                    notebook.replace_cell(index, source, args.cell_type.as_deref())?;
+                    let message = format!("Replaced cell {index} in {}", args.path);
+                    (index, old_source, source.to_string(), message)
+                }
+                "insert" => {
+                    let index = match (args.cell_id.as_ref(), target) {
+                        (Some(_), Some(found)) => found + 1,
+                        (_, index) => index.unwrap_or(notebook.len()),
+                    };
+                    let source = require_source()?;
+                    let cell_type = args.cell_type.as_deref().unwrap_or("code");
+                    notebook.insert_cell(index, cell_type, source)?;
+                    let message = format!("Inserted {cell_type} cell {index} in {}", args.path);
+                    (index, String::new(), source.to_string(), message)
+                }
+                "delete" => {
+                    let index = require_target()?;
+                    let old_source = notebook.cell_source(index)?;
+                    notebook.delete_cell(index)?;
+                    let message = format!(
+                        "Deleted cell {index} from {} ({} cells remain)",
+                        args.path,
+                        notebook.len()
+                    );
+                    (index, old_source, String::new(), message)
+                }
+                action => {
+                    return Err(format!(
+                        "Unknown notebook action \"{action}\". Use replace, insert, or delete"
+                    )
+                    .into())
+                }
+            };
+
+        let content = notebook.to_json()?;
+        fs::write(&absolute_path, content.as_bytes())
+            .map_err(|err| format!("Failed to write {}: {}", args.path, err))?;
+        if let Some(reads) = self.reads.as_ref() {
+            reads
+                .borrow_mut()
+                .record(&absolute_path, content.as_bytes());
+        }
+        if let Some(journal) = self.journal.as_ref() {
+            journal.borrow_mut().record(FileChange::new(
+                absolute_path,
+                "notebook_edit",
+                call_id,
+                Some(raw.clone()),
+                generate_diff_string(&raw, &content),
+            ));
+        }
+
+        Ok(ToolResult {
+            content: vec![
+                ContentBlock::Text {
+                    text: format!("{message}."),
+                    text_signature: None,
+                },
+                ContentBlock::diff(
+                    format!("{} (cell {index})", args.path),
+                    unified_diff(&old_source, &new_source, 3),
+                ),
+            ],
+            details: Some(json!({ "cell": index, "cells": notebook.len() })),
+        })
+    }
+}
+
+fn check_not_stale(
+    reads: Option<&SharedReadTracker>,
+    path: &Path,
+    display_path: &str,
+) -> Result<(), ToolError> {
+    match reads {
+        Some(reads) => reads.borrow_mut().check(path, display_path),
+        None => Ok(()),
+    }
+}
+
+/// Shows the change to `review`, if any. Returns the content to write and whether the user
+/// rewrote it; a rejection becomes the tool's error.
+/// Outline or symbol reads for the read tool.
+fn read_symbols(
+    path: &Path,
+    text: &str,
+    args: &ReadToolArgs,
+    outline_requested: bool,
+) -> Result<ToolResult, String> {
+    if args.offset.is_some() || args.limit.is_some() {
+        return Err("offset and limit cannot be combined with symbol or outline".to_string());
+    }
+    let symbols = outline(path, text)?;
+    let output = match args.symbol.as_deref() {
+        Some(_) if outline_requested => {
+            return Err("Use either symbol or outline, not both".to_string());
+        }
+        Some(query) => {
+            let matches = find_symbols(&symbols, query);
+            if matches.is_empty() {
+                return Err(format!(
+                    "Symbol \"{query}\" not found in {}. Use outline to list its symbols.",
+                    args.path
+                ));
+            }
+            let lines: Vec<&str> = text.split('\n').collect();
+            let numbered = args.line_numbers.unwrap_or(true);
+            matches
+                .iter()
+                .map(|symbol| {
+                    let body = &lines[symbol.start_line - 1..symbol.end_line];
+                    if numbered {
+                        number_lines(body, symbol.start_line)
+                    } else {
+                        body.join("\n")
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        }
+        None if symbols.is_empty() => format!("No symbols found in {}.", args.path),
+        None => render_outline(&symbols),
+    };
+
+    let truncation = truncate_head(&output, None);
+    let (text, details) = if truncation.truncated {
+        (
+            format!(
+                "{}\n\n[Output truncated at {} lines. Use offset and limit to read the rest]",
+                truncation.content, truncation.output_lines
+            ),
+            Some(json!({ "truncation": truncation })),
+        )
+    } else {
+        (truncation.content, None)
+    };
+    Ok(ToolResult {
+        content: vec![ContentBlock::Text {
+            text,
+            text_signature: None,
+        }],
+        details,
+    })
+}
+
+/// Prefixes each line with its number, right-aligned and tab-separated like `cat -n`.
+fn number_lines(lines: &[&str], first_line: usize) -> String {
+    let width = (first_line + lines.len())
+        .saturating_sub(1)
+        .to_string()
+        .len();
+    lines
+        .iter()
+        .enumerate()
+        .map(|(index, line)| format!("{:>width$}\t{line}", first_line + index))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn review_change(
+    review: Option<&EditReview>,
+    tool: &str,
+    path: &str,
+    original: Option<&str>,
+    content: String,
+) -> Result<(String, bool), String> {
+    let Some(review) = review else {
+        return Ok((content, false));
+    };
+    let proposal = EditProposal {
+        tool: tool.to_string(),
+        path: path.to_string(),
+        diff: original.map_or_else(String::new, |original| {
+            unified_diff(&normalize_to_lf(original), &normalize_to_lf(&content), 3)
+        }),
+        content,
+    };
+    match review.request(&proposal) {
+        None | Some(EditDecision::Accept) => Ok((proposal.content, false)),
+        Some(EditDecision::Reject(note)) => Err(rejection_message(&proposal, note.as_deref())),
+        Some(EditDecision::Replace(content)) => {
+            let rewritten = content != proposal.content;
+            Ok((content, rewritten))
+        }
+    }
+}
+
+fn rewritten_note(rewritten: bool) -> &'static str {
+    if rewritten {
+        " The user revised the change before applying it; the file differs from your version."
+    } else {
+        ""
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum EntryType {
+    File,
+    Dir,
+}
+
+fn parse_entry_type(value: Option<&str>) -> Result<Option<EntryType>, String> {
+    match value {
+        None => Ok(None),
+        Some("file" | "f") => Ok(Some(EntryType::File)),
+        Some("dir" | "directory" | "d") => Ok(Some(EntryType::Dir)),
+        Some(other) => Err(format!("Unknown type \"{other}\". Use \"file\" or \"dir\"")),
+    }
+}
+
+/// A file or directory found by the find and ls tools, with the metadata reported in details.
+struct ListedEntry {
+    rel_path: String,
+    is_dir: bool,
+    size: u64,
+    /// Milliseconds since the Unix epoch.
+    modified: Option<i64>,
+}
+
+impl ListedEntry {
+    fn new(entry: &WalkEntry) -> Self {
+        let metadata = fs::metadata(&entry.path).ok();
+        Self {
+            rel_path: entry.rel_path.clone(),
+            is_dir: entry.is_dir,
+            size: metadata
+                .as_ref()
+                .filter(|metadata| metadata.is_file())
+                .map_or(0, fs::Metadata::len),
+            modified: metadata
+                .and_then(|metadata| metadata.modified().ok())
+                .and_then(|modified| modified.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|since_epoch| since_epoch.as_millis() as i64),
+        }
+    }
+
+    fn display_path(&self) -> String {
+        if self.is_dir {
+            format!("{}/", self.rel_path)
+        } else {
+            self.rel_path.clone()
+        }
+    }
+}
+
+/// Sorts by `sort`, or by `default` (`path` or `name`, both case-insensitive) when unset.
+fn sort_entries(
+    entries: &mut [ListedEntry],
+    sort: Option<&str>,
+    default: &str,
+) -> Result<(), String> {
+    match sort.unwrap_or(default) {
+        "path" | "name" => entries.sort_by_key(|entry| entry.rel_path.to_lowercase()),
+        "modified" => entries.sort_by_key(|entry| Reverse(entry.modified)),
+        "size" => entries.sort_by_key(|entry| Reverse(entry.size)),
+        other => {
+            return Err(format!(
+                "Unknown sort \"{other}\". Use \"{default}\", \"modified\" or \"size\""
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn entries_details(entries: &[ListedEntry]) -> Value {
+    Value::Array(
+        entries
+            .iter()
+            .map(|entry| {
+                json!({
+                    "path": entry.rel_path,
+                    "type": if entry.is_dir { "dir" } else { "file" },
+                    "size": entry.size,
+                    "modified": entry.modified,
+                })
+            })
+            .collect(),
+    )
+}
+
+fn check_sandbox_read(sandbox: Option<&SandboxPolicy>, path: &Path) -> Result<(), String> {
+    sandbox.map_or(Ok(()), |policy| policy.check_read(path))
+}
+
+fn check_sandbox_write(sandbox: Option<&SandboxPolicy>, path: &Path) -> Result<(), String> {
+    sandbox.map_or(Ok(()), |policy| policy.check_write(path))
+}
+
 fn resolve_path(path: &str, cwd: &Path) -> PathBuf {
-    let path = PathBuf::from(path);
+    let path = PathBuf::from(normalize_tool_path(path, cfg!(windows)));
     if path.is_absolute() {
         path
     } else {
@@ -806,6 +1781,30 @@ fn resolve_path(path: &str, cwd: &Path) -> PathBuf {
     }
 }
 
+/// Normalize a path passed to a file tool. On Windows, MSYS-style drive paths such as
+/// `/c/Users/me` become `C:\Users\me` and forward slashes become backslashes; elsewhere the
+/// path is returned unchanged.
+pub fn normalize_tool_path(path: &str, windows: bool) -> String {
+    if !windows {
+        return path.to_string();
+    }
+    let bytes = path.as_bytes();
+    let path = if bytes.len() >= 2
+        && bytes[0] == b'/'
+        && bytes[1].is_ascii_alphabetic()
+        && (bytes.len() == 2 || bytes[2] == b'/')
+    {
+        format!(
+            "{}:/{}",
+            path[1..2].to_ascii_uppercase(),
+            &path[2..].trim_start_matches('/')
+        )
+    } else {
+        path.to_string()
+    };
+    path.replace('/', "\\")
+}
+
 fn detect_image_mime_type(data: &[u8]) -> Option<&'static str> {
     let png_magic: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
     if data.len() >= png_magic.len() && data[..png_magic.len()] == png_magic {
@@ -1039,66 +2038,163 @@ fn write_bash_output_temp(output: &str) -> Result<PathBuf, String> {
     Ok(path)
 }
 
-enum GrepMatcher {
-    Literal { needle: String, ignore_case: bool },
-    Regex(regex::Regex),
-}
-
-impl GrepMatcher {
-    fn is_match(&self, line: &str) -> bool {
-        match self {
-            GrepMatcher::Literal {
-                needle,
-                ignore_case,
-            } => {
-                if *ignore_case {
-                    line.to_lowercase().contains(&needle.to_lowercase())
-                } else {
-                    line.contains(needle)
-                }
-            }
-            GrepMatcher::Regex(regex) => regex.is_match(line),
-        }
-    }
+struct GrepMatch {
+    line: usize,
+    /// Last line the match spans; the same as `line` unless the search is multiline.
+    end_line: usize,
+    /// 1-based, counted in characters.
+    column: usize,
+    text: String,
 }
 
 fn build_grep_matcher(
     pattern: &str,
     ignore_case: bool,
     literal: bool,
-) -> Result<GrepMatcher, String> {
-    if literal {
-        return Ok(GrepMatcher::Literal {
-            needle: pattern.to_string(),
-            ignore_case,
-        });
-    }
-
-    let regex = RegexBuilder::new(pattern)
+    multiline: bool,
+) -> Result<regex::Regex, String> {
+    let pattern = if literal {
+        regex::escape(pattern)
+    } else {
+        pattern.to_string()
+    };
+    RegexBuilder::new(&pattern)
         .case_insensitive(ignore_case)
+        .multi_line(multiline)
+        .dot_matches_new_line(multiline)
         .build()
-        .map_err(|err| format!("Invalid pattern: {err}"))?;
-    Ok(GrepMatcher::Regex(regex))
+        .map_err(|err| format!("Invalid pattern: {err}"))
+}
+
+/// The first match on each matching line or, in multiline mode, every match in the file.
+fn find_grep_matches(
+    matcher: &regex::Regex,
+    content: &str,
+    lines: &[&str],
+    multiline: bool,
+) -> Vec<GrepMatch> {
+    if !multiline {
+        return lines
+            .iter()
+            .enumerate()
+            .filter_map(|(index, line)| {
+                let found = matcher.find(line)?;
+                Some(GrepMatch {
+                    line: index + 1,
+                    end_line: index + 1,
+                    column: line[..found.start()].chars().count() + 1,
+                    text: (*line).to_string(),
+                })
+            })
+            .collect();
+    }
+    matcher
+        .find_iter(content)
+        .filter(|found| !found.is_empty())
+        .map(|found| {
+            let before = &content[..found.start()];
+            let line = before.matches('\n').count() + 1;
+            let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+            GrepMatch {
+                line,
+                end_line: line + found.as_str().trim_end_matches('\n').matches('\n').count(),
+                column: content[line_start..found.start()].chars().count() + 1,
+                text: found.as_str().to_string(),
+            }
+        })
+        .collect()
+}
+
+/// File types accepted by the grep tool's `type` argument, with their extensions.
+pub fn grep_file_types() -> &'static [(&'static str, &'static [&'static str])] {
+    &[
+        ("c", &["c", "h"]),
+        ("cpp", &["cpp", "cc", "cxx", "hpp", "hh", "hxx", "h"]),
+        ("cs", &["cs"]),
+        ("css", &["css", "scss", "sass", "less"]),
+        ("go", &["go"]),
+        ("html", &["html", "htm"]),
+        ("java", &["java"]),
+        ("js", &["js", "jsx", "mjs", "cjs"]),
+        ("json", &["json", "jsonc"]),
+        ("kotlin", &["kt", "kts"]),
+        ("md", &["md", "markdown"]),
+        ("php", &["php"]),
+        ("py", &["py", "pyi"]),
+        ("ruby", &["rb"]),
+        ("rust", &["rs"]),
+        ("sh", &["sh", "bash", "zsh"]),
+        ("sql", &["sql"]),
+        ("swift", &["swift"]),
+        ("toml", &["toml"]),
+        ("ts", &["ts", "tsx", "mts", "cts"]),
+        ("yaml", &["yaml", "yml"]),
+    ]
+}
+
+fn grep_type_extensions(name: &str) -> Result<&'static [&'static str], String> {
+    let aliases = [
+        ("python", "py"),
+        ("rs", "rust"),
+        ("typescript", "ts"),
+        ("javascript", "js"),
+    ];
+    let name = aliases
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map_or(name, |(_, canonical)| canonical);
+    grep_file_types()
+        .iter()
+        .find(|(type_name, _)| *type_name == name)
+        .map(|(_, extensions)| *extensions)
+        .ok_or_else(|| {
+            let known = grep_file_types()
+                .iter()
+                .map(|(type_name, _)| *type_name)
+                .collect::<Vec<_>>();
+            format!(
+                "Unknown file type \"{name}\". Known types: {}",
+                known.join(", ")
+            )
+        })
+}
+
+fn has_extension(path: &str, extensions: &[&str]) -> bool {
+    Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extensions.contains(&extension))
+}
+
+/// Matches `path` against `glob`: the whole relative path when the glob has a `/`, otherwise
+/// just the file name, so `*.ts` finds TypeScript files at any depth.
+fn glob_matches(path: &str, glob: &str) -> bool {
+    let Ok(pattern) = glob::Pattern::new(glob) else {
+        return false;
+    };
+    let options = glob::MatchOptions {
+        require_literal_separator: true,
+        ..glob::MatchOptions::default()
+    };
+    if glob.contains('/') {
+        pattern.matches_with(path, options)
+    } else {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        pattern.matches_with(name, options)
+    }
 }
 
 fn append_grep_block(
     file_label: &str,
     lines: &[&str],
     line_number: usize,
+    end_line: usize,
     context: usize,
     matches_output: &mut Vec<String>,
     lines_truncated: &mut bool,
 ) {
-    let start = if context > 0 {
-        line_number.saturating_sub(context)
-    } else {
-        line_number
-    };
-    let end = if context > 0 {
-        (line_number + context).min(lines.len())
-    } else {
-        line_number
-    };
+    let start = line_number.saturating_sub(context).max(1);
+    let end = (end_line + context).min(lines.len());
 
     for current in start..=end {
         let text_line = lines.get(current - 1).copied().unwrap_or("");
@@ -1107,7 +2203,7 @@ fn append_grep_block(
         if was_truncated {
             *lines_truncated = true;
         }
-        if current == line_number {
+        if (line_number..=end_line).contains(&current) {
             matches_output.push(format!("{file_label}:{current}: {trimmed}"));
         } else {
             matches_output.push(format!("{file_label}-{current}- {trimmed}"));
@@ -1178,101 +2274,3 @@ fn find_first_changed_line(old_content: &str, new_content: &str) -> Option<usize
     }
     None
 }
-
-fn read_gitignore(base: &Path) -> HashSet<String> {
-    let mut entries = HashSet::new();
-    let path = base.join(".gitignore");
-    if let Ok(content) = fs::read_to_string(path) {
-        for line in content.lines() {
-            let trimmed = line.trim();
-            if trimmed.is_empty() || trimmed.starts_with('#') {
-                continue;
-            }
-            entries.insert(trimmed.to_string());
-        }
-    }
-    entries
-}
-
-fn collect_files(
-    base: &Path,
-    current: &Path,
-    pattern: &str,
-    ignore_set: &HashSet<String>,
-    results: &mut Vec<String>,
-) {
-    let entries = match fs::read_dir(current) {
-        Ok(entries) => entries,
-        Err(_) => return,
-    };
-
-    for entry in entries.flatten() {
-        let path = entry.path();
-        let rel = path.strip_prefix(base).unwrap_or(&path);
-        let rel_string = rel.to_string_lossy().replace('\\', "/");
-        if ignore_set.contains(rel_string.as_str()) {
-            continue;
-        }
-        let metadata = match entry.metadata() {
-            Ok(meta) => meta,
-            Err(_) => continue,
-        };
-        if metadata.is_dir() {
-            collect_files(base, &path, pattern, ignore_set, results);
-        } else if metadata.is_file() && matches_pattern(&rel_string, pattern) {
-            results.push(rel_string);
-        }
-    }
-}
-
-fn collect_grep_files(
-    base: &Path,
-    current: &Path,
-    glob: Option<&str>,
-    ignore_set: &HashSet<String>,
-    results: &mut Vec<String>,
-) {
-    let entries = match fs::read_dir(current) {
-        Ok(entries) => entries,
-        Err(_) => return,
-    };
-
-    for entry in entries.flatten() {
-        let path = entry.path();
-        let rel = path.strip_prefix(base).unwrap_or(&path);
-        let rel_string = rel.to_string_lossy().replace('\\', "/");
-        if rel_string.starts_with(".git/") {
-            continue;
-        }
-        if ignore_set.contains(rel_string.as_str()) {
-            continue;
-        }
-        let metadata = match entry.metadata() {
-            Ok(meta) => meta,
-            Err(_) => continue,
-        };
-        if metadata.is_dir() {
-            collect_grep_files(base, &path, glob, ignore_set, results);
-        } else if metadata.is_file() {
-            if let Some(glob) = glob {
-                if !matches_pattern(&rel_string, glob) {
-                    continue;
-                }
-            }
-            results.push(rel_string);
-        }
-    }
-}
-
-fn matches_pattern(path: &str, pattern: &str) -> bool {
-    if pattern == "**/*.txt" || pattern == "*.txt" {
-        return path.ends_with(".txt");
-    }
-    if let Some(idx) = pattern.rfind('*') {
-        let suffix = &pattern[idx + 1..];
-        if !suffix.is_empty() {
-            return path.ends_with(suffix);
-        }
-    }
-    path == pattern
-}
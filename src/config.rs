@@ -69,6 +69,29 @@ pub fn get_settings_path() -> PathBuf {
     get_agent_dir().join("settings.json")
 }
 
+pub fn get_export_state_path() -> PathBuf {
+    get_agent_dir().join("exports.json")
+}
+
+pub fn get_extension_permissions_path() -> PathBuf {
+    get_agent_dir().join("extension-permissions.json")
+}
+
+pub fn get_spend_path() -> PathBuf {
+    get_agent_dir().join("spend.json")
+}
+
+pub fn get_batches_dir() -> PathBuf {
+    get_agent_dir().join("batches")
+}
+
+pub fn get_request_log_path() -> PathBuf {
+    home_dir()
+        .join(config_dir_name())
+        .join("logs")
+        .join("requests.jsonl")
+}
+
 pub fn app_config_from_package_json(path: &Path) -> Option<AppConfig> {
     let content = fs::read_to_string(path).ok()?;
     let value: Value = serde_json::from_str(&content).ok()?;
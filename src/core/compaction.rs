@@ -2,9 +2,13 @@ use crate::core::messages::{
     create_branch_summary_message, create_hook_message, AgentMessage, ContentBlock, Usage,
     UserContent,
 };
-use crate::core::session_manager::SessionEntry;
+use crate::core::session_manager::{pinned_entry_ids, SessionEntry};
+use crate::core::tokens::{estimate_message, estimate_with, Tokenizer};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct FileOperations {
@@ -34,6 +38,9 @@ impl FileOperations {
 pub struct CompactionDetails {
     pub read_files: Vec<String>,
     pub modified_files: Vec<String>,
+    /// SHA-256 of each file's content at compaction time; files that no longer exist are omitted.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub file_hashes: BTreeMap<String, String>,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -41,12 +48,15 @@ pub struct CompactionSettings {
     pub enabled: bool,
     pub reserve_tokens: i64,
     pub keep_recent_tokens: i64,
+    /// Trigger at this percentage of the context window instead of `reserve_tokens` before it.
+    pub threshold_percent: Option<f64>,
 }
 
 pub const DEFAULT_COMPACTION_SETTINGS: CompactionSettings = CompactionSettings {
     enabled: true,
     reserve_tokens: 16_384,
     keep_recent_tokens: 20_000,
+    threshold_percent: None,
 };
 
 pub fn calculate_context_tokens(usage: &Usage) -> i64 {
@@ -55,10 +65,10 @@ pub fn calculate_context_tokens(usage: &Usage) -> i64 {
         .unwrap_or(usage.input + usage.output + usage.cache_read + usage.cache_write)
 }
 
-fn get_assistant_usage(message: &AgentMessage) -> Option<Usage> {
+pub(crate) fn get_assistant_usage(message: &AgentMessage) -> Option<Usage> {
     match message {
         AgentMessage::Assistant(assistant) => {
-            if assistant.stop_reason != "aborted" && assistant.stop_reason != "error" {
+            if !assistant.is_aborted() && assistant.stop_reason != "error" {
                 return Some(assistant.usage.clone());
             }
             None
@@ -78,6 +88,14 @@ pub fn get_last_assistant_usage(entries: &[SessionEntry]) -> Option<Usage> {
     None
 }
 
+/// Context size above which auto-compaction triggers for a model with `context_window` tokens.
+pub fn compaction_trigger_tokens(context_window: i64, settings: CompactionSettings) -> i64 {
+    match settings.threshold_percent {
+        Some(percent) => (context_window as f64 * percent / 100.0) as i64,
+        None => context_window - settings.reserve_tokens,
+    }
+}
+
 pub fn should_compact(
     context_tokens: i64,
     context_window: i64,
@@ -86,68 +104,19 @@ pub fn should_compact(
     if !settings.enabled {
         return false;
     }
-    context_tokens > context_window - settings.reserve_tokens
+    context_tokens > compaction_trigger_tokens(context_window, settings)
 }
 
+/// Four-characters-per-token estimate of a message; see [`tokens`](crate::core::tokens) for
+/// model-aware counts.
 pub fn estimate_tokens(message: &AgentMessage) -> i64 {
-    let mut chars: usize = 0;
-
-    match message {
-        AgentMessage::User(user) => match &user.content {
-            UserContent::Text(text) => chars += text.len(),
-            UserContent::Blocks(blocks) => {
-                for block in blocks {
-                    if let ContentBlock::Text { text, .. } = block {
-                        chars += text.len();
-                    }
-                }
-            }
-        },
-        AgentMessage::Assistant(assistant) => {
-            for block in &assistant.content {
-                match block {
-                    ContentBlock::Text { text, .. } => chars += text.len(),
-                    ContentBlock::Thinking { thinking, .. } => chars += thinking.len(),
-                    ContentBlock::ToolCall {
-                        name, arguments, ..
-                    } => {
-                        chars += name.len();
-                        chars += serde_json::to_string(arguments).unwrap_or_default().len();
-                    }
-                    ContentBlock::Image { .. } => {}
-                }
-            }
-        }
-        AgentMessage::HookMessage(hook) => match &hook.content {
-            UserContent::Text(text) => chars += text.len(),
-            UserContent::Blocks(blocks) => {
-                for block in blocks {
-                    match block {
-                        ContentBlock::Text { text, .. } => chars += text.len(),
-                        ContentBlock::Image { .. } => chars += 4800,
-                        _ => {}
-                    }
-                }
-            }
-        },
-        AgentMessage::ToolResult(result) => {
-            for block in &result.content {
-                match block {
-                    ContentBlock::Text { text, .. } => chars += text.len(),
-                    ContentBlock::Image { .. } => chars += 4800,
-                    _ => {}
-                }
-            }
-        }
-        AgentMessage::BashExecution(bash) => {
-            chars += bash.command.len();
-            chars += bash.output.len();
-        }
-        AgentMessage::BranchSummary(summary) => chars += summary.summary.len(),
-        AgentMessage::CompactionSummary(summary) => chars += summary.summary.len(),
-    }
+    estimate_message(message, Tokenizer::Heuristic)
+}
 
-    chars.div_ceil(4) as i64
+/// Estimate the tokens currently in context: the usage reported for the last successful
+/// assistant reply plus a character-based estimate for every message after it.
+pub fn estimate_context_tokens(messages: &[AgentMessage]) -> i64 {
+    estimate_with(messages, Tokenizer::Heuristic).total()
 }
 
 fn find_valid_cut_points(
@@ -287,6 +256,9 @@ pub struct CompactionPreparation {
     pub previous_summary: Option<String>,
     pub file_ops: FileOperations,
     pub settings: CompactionSettings,
+    /// Pinned entries before the cut point. They are left out of `messages_to_summarize`
+    /// and kept verbatim after the summary.
+    pub pinned_entry_ids: Vec<String>,
 }
 
 fn get_message_from_entry(entry: &SessionEntry) -> Option<AgentMessage> {
@@ -423,10 +395,18 @@ pub fn prepare_compaction(
         cut_point.first_kept_entry_index
     };
 
+    let pinned = pinned_entry_ids(path_entries);
     let mut messages_to_summarize = Vec::new();
+    let mut pinned_messages = Vec::new();
+    let mut kept_pins = Vec::new();
     for entry in &path_entries[boundary_start..history_end] {
         if let Some(message) = get_message_from_entry(entry) {
-            messages_to_summarize.push(message);
+            if pinned.iter().any(|id| id == entry.id()) {
+                pinned_messages.push(message);
+                kept_pins.push(entry.id().to_string());
+            } else {
+                messages_to_summarize.push(message);
+            }
         }
     }
 
@@ -452,6 +432,9 @@ pub fn prepare_compaction(
 
     let mut file_ops =
         extract_file_operations(&messages_to_summarize, path_entries, prev_compaction_index);
+    for message in &pinned_messages {
+        extract_file_ops_from_message(message, &mut file_ops);
+    }
     if cut_point.is_split_turn {
         for message in &turn_prefix_messages {
             extract_file_ops_from_message(message, &mut file_ops);
@@ -467,9 +450,228 @@ pub fn prepare_compaction(
         previous_summary,
         file_ops,
         settings,
+        pinned_entry_ids: kept_pins,
     })
 }
 
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct CompactionResult {
+    pub summary: String,
+    pub first_kept_entry_id: String,
+    pub tokens_before: i64,
+}
+
+pub struct CompactionRequest<'a> {
+    pub preparation: &'a CompactionPreparation,
+    pub branch_entries: &'a [SessionEntry],
+    pub custom_instructions: Option<&'a str>,
+}
+
+/// Produces the summary that replaces everything before the cut point chosen by
+/// `prepare_compaction`. Implement this to plug a custom strategy into `AgentSession`.
+pub trait CompactionStrategy {
+    fn name(&self) -> &str;
+    fn compact(&self, request: &CompactionRequest<'_>) -> Result<CompactionResult, String>;
+}
+
+pub const SUMMARIZE_STRATEGY: &str = "summarize";
+pub const TRUNCATE_STRATEGY: &str = "truncate";
+pub const HYBRID_STRATEGY: &str = "hybrid";
+
+pub const COMPACTION_STRATEGY_NAMES: [&str; 3] =
+    [SUMMARIZE_STRATEGY, TRUNCATE_STRATEGY, HYBRID_STRATEGY];
+
+pub fn compaction_strategy_for_name(name: &str) -> Option<Box<dyn CompactionStrategy>> {
+    match name {
+        SUMMARIZE_STRATEGY => Some(Box::new(SummarizeStrategy)),
+        TRUNCATE_STRATEGY => Some(Box::new(TruncateStrategy)),
+        HYBRID_STRATEGY => Some(Box::new(HybridStrategy)),
+        _ => None,
+    }
+}
+
+/// Condenses the text of every message before the cut point.
+pub struct SummarizeStrategy;
+
+impl CompactionStrategy for SummarizeStrategy {
+    fn name(&self) -> &str {
+        SUMMARIZE_STRATEGY
+    }
+
+    fn compact(&self, request: &CompactionRequest<'_>) -> Result<CompactionResult, String> {
+        let preparation = request.preparation;
+        let mut summary = summarize_compaction_messages(
+            &preparation.messages_to_summarize,
+            request.custom_instructions,
+        );
+        if summary.trim().is_empty() {
+            summary = "Summary.".to_string();
+        }
+        Ok(CompactionResult {
+            summary,
+            first_kept_entry_id: preparation.first_kept_entry_id.clone(),
+            tokens_before: preparation.tokens_before,
+        })
+    }
+}
+
+/// Drops messages before the cut point, carrying forward only the previous summary.
+pub struct TruncateStrategy;
+
+impl CompactionStrategy for TruncateStrategy {
+    fn name(&self) -> &str {
+        TRUNCATE_STRATEGY
+    }
+
+    fn compact(&self, request: &CompactionRequest<'_>) -> Result<CompactionResult, String> {
+        let preparation = request.preparation;
+        let dropped = preparation.messages_to_summarize.len();
+        let note = format!(
+            "[{dropped} earlier message{} truncated]",
+            if dropped == 1 { "" } else { "s" }
+        );
+        let summary = match preparation.previous_summary.as_deref() {
+            Some(previous) if !previous.trim().is_empty() => format!("{previous}\n\n{note}"),
+            _ => note,
+        };
+        Ok(CompactionResult {
+            summary,
+            first_kept_entry_id: preparation.first_kept_entry_id.clone(),
+            tokens_before: preparation.tokens_before,
+        })
+    }
+}
+
+/// Summarizes the conversation but truncates tool output, then records which files
+/// were read or modified so the agent keeps its bearings.
+pub struct HybridStrategy;
+
+impl CompactionStrategy for HybridStrategy {
+    fn name(&self) -> &str {
+        HYBRID_STRATEGY
+    }
+
+    fn compact(&self, request: &CompactionRequest<'_>) -> Result<CompactionResult, String> {
+        let preparation = request.preparation;
+        let conversation = preparation
+            .messages_to_summarize
+            .iter()
+            .filter(|message| {
+                !matches!(
+                    message,
+                    AgentMessage::ToolResult(_) | AgentMessage::BashExecution(_)
+                )
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut summary = summarize_compaction_messages(&conversation, request.custom_instructions);
+        if summary.trim().is_empty() {
+            summary = "Summary.".to_string();
+        }
+        let (read_files, modified_files) = compute_file_lists(&preparation.file_ops);
+        summary.push_str(&format_file_operations(&read_files, &modified_files));
+        Ok(CompactionResult {
+            summary,
+            first_kept_entry_id: preparation.first_kept_entry_id.clone(),
+            tokens_before: preparation.tokens_before,
+        })
+    }
+}
+
+pub type CompactionDelegate = Box<dyn Fn(&CompactionRequest<'_>) -> Option<CompactionResult>>;
+
+/// Hands compaction to an external implementation (an extension, an SDK callback),
+/// using `fallback` whenever the delegate declines.
+pub struct DelegatedStrategy {
+    name: String,
+    delegate: CompactionDelegate,
+    fallback: Box<dyn CompactionStrategy>,
+}
+
+impl DelegatedStrategy {
+    pub fn new(
+        name: impl Into<String>,
+        delegate: CompactionDelegate,
+        fallback: Box<dyn CompactionStrategy>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            delegate,
+            fallback,
+        }
+    }
+}
+
+impl CompactionStrategy for DelegatedStrategy {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn compact(&self, request: &CompactionRequest<'_>) -> Result<CompactionResult, String> {
+        match (self.delegate)(request) {
+            Some(result) => Ok(result),
+            None => self.fallback.compact(request),
+        }
+    }
+}
+
+pub fn summarize_compaction_messages(
+    messages: &[AgentMessage],
+    custom_instructions: Option<&str>,
+) -> String {
+    let mut parts = Vec::new();
+    for message in messages {
+        let text = message_text(message);
+        if !text.is_empty() {
+            parts.push(text);
+        }
+    }
+
+    if parts.is_empty() {
+        return String::new();
+    }
+
+    let merged = parts.join(" ");
+    let mut summary = format!("Summary: {}", clip_words(&merged, 32));
+    if let Some(instructions) = custom_instructions {
+        summary.push(' ');
+        summary.push_str(&clip_words(instructions, 6));
+    }
+    summary
+}
+
+fn message_text(message: &AgentMessage) -> String {
+    let blocks_text = |blocks: &[ContentBlock]| {
+        blocks
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<String>()
+    };
+    let user_text = |content: &UserContent| match content {
+        UserContent::Text(text) => text.clone(),
+        UserContent::Blocks(blocks) => blocks_text(blocks),
+    };
+    match message {
+        AgentMessage::User(user) => user_text(&user.content),
+        AgentMessage::Assistant(assistant) => blocks_text(&assistant.content),
+        AgentMessage::ToolResult(result) => blocks_text(&result.content),
+        AgentMessage::HookMessage(hook) => user_text(&hook.content),
+        AgentMessage::BranchSummary(summary) => summary.summary.clone(),
+        AgentMessage::CompactionSummary(summary) => summary.summary.clone(),
+        AgentMessage::BashExecution(bash) => bash.output.clone(),
+    }
+}
+
+pub(crate) fn clip_words(text: &str, max_words: usize) -> String {
+    text.split_whitespace()
+        .take(max_words)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 pub fn compute_file_lists(file_ops: &FileOperations) -> (Vec<String>, Vec<String>) {
     let modified: HashSet<String> = file_ops
         .edited
@@ -508,3 +710,40 @@ pub fn format_file_operations(read_files: &[String], modified_files: &[String])
     }
     format!("\n\n{}", sections.join("\n\n"))
 }
+
+/// File lists for a compaction entry, with the current content hash of each file. Relative
+/// paths are resolved against `cwd`.
+pub fn collect_file_state(file_ops: &FileOperations, cwd: &Path) -> CompactionDetails {
+    let (read_files, modified_files) = compute_file_lists(file_ops);
+    let file_hashes = read_files
+        .iter()
+        .chain(modified_files.iter())
+        .filter_map(|path| {
+            let content = fs::read(cwd.join(path)).ok()?;
+            Some((path.clone(), format!("{:x}", Sha256::digest(&content))))
+        })
+        .collect();
+    CompactionDetails {
+        read_files,
+        modified_files,
+        file_hashes,
+    }
+}
+
+/// Summary section listing the files touched so far with their latest content hashes.
+pub fn format_file_state(details: &CompactionDetails) -> String {
+    let line = |path: &String, kind: &str| match details.file_hashes.get(path) {
+        Some(hash) => format!("{path} ({kind}, sha256:{})", &hash[..16.min(hash.len())]),
+        None => format!("{path} ({kind}, missing)"),
+    };
+    let lines = details
+        .modified_files
+        .iter()
+        .map(|path| line(path, "modified"))
+        .chain(details.read_files.iter().map(|path| line(path, "read")))
+        .collect::<Vec<_>>();
+    if lines.is_empty() {
+        return String::new();
+    }
+    format!("\n\n<file-state>\n{}\n</file-state>", lines.join("\n"))
+}
@@ -0,0 +1,112 @@
+//! Line diffs shared by the diff content block and `pi replay-turn`.
+
+/// Line diff of `old` against `new`, each line prefixed with ' ', '-' or '+'.
+pub fn diff_lines(old: &str, new: &str) -> Vec<String> {
+    let old = old.lines().collect::<Vec<_>>();
+    let new = new.lines().collect::<Vec<_>>();
+    // lcs[i][j]: longest common subsequence of old[i..] and new[j..].
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut diff = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            diff.push(format!(" {}", old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            diff.push(format!("-{}", old[i]));
+            i += 1;
+        } else {
+            diff.push(format!("+{}", new[j]));
+            j += 1;
+        }
+    }
+    diff
+}
+
+/// Unified diff hunks (`@@ -a,b +c,d @@`) with `context` unchanged lines around each change.
+pub fn unified_diff(old: &str, new: &str, context: usize) -> String {
+    let lines = diff_lines(old, new);
+    // Line numbers in old and new before each diff line.
+    let mut positions = Vec::with_capacity(lines.len());
+    let (mut old_line, mut new_line) = (1, 1);
+    for line in &lines {
+        positions.push((old_line, new_line));
+        match line.as_bytes()[0] {
+            b'-' => old_line += 1,
+            b'+' => new_line += 1,
+            _ => {
+                old_line += 1;
+                new_line += 1;
+            }
+        }
+    }
+    let changed = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !line.starts_with(' '))
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for index in changed {
+        let start = index.saturating_sub(context);
+        let end = (index + context + 1).min(lines.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut output = Vec::new();
+    for (start, end) in hunks {
+        let hunk = &lines[start..end];
+        let old_count = hunk.iter().filter(|line| !line.starts_with('+')).count();
+        let new_count = hunk.iter().filter(|line| !line.starts_with('-')).count();
+        let (old_start, new_start) = positions[start];
+        // An empty side starts at the line before, as in `diff -u`.
+        let old_start = if old_count == 0 {
+            old_start - 1
+        } else {
+            old_start
+        };
+        let new_start = if new_count == 0 {
+            new_start - 1
+        } else {
+            new_start
+        };
+        output.push(format!(
+            "@@ -{old_start},{old_count} +{new_start},{new_count} @@"
+        ));
+        output.extend(hunk.iter().cloned());
+    }
+    output.join("\n")
+}
+
+/// Added and removed line counts of a unified diff.
+pub fn count_changes(diff: &str) -> (usize, usize) {
+    let (mut added, mut removed) = (0, 0);
+    // `---`/`+++` file headers only appear before the first hunk.
+    let mut in_hunk = false;
+    for line in diff.lines() {
+        if line.starts_with("@@") {
+            in_hunk = true;
+        } else if !in_hunk && (line.starts_with("+++") || line.starts_with("---")) {
+            continue;
+        } else if line.starts_with('+') {
+            added += 1;
+        } else if line.starts_with('-') {
+            removed += 1;
+        }
+    }
+    (added, removed)
+}
@@ -34,6 +34,40 @@ pub enum ContentBlock {
         data: String,
         mime_type: String,
     },
+    /// A file change as a unified diff, so clients can render it and count changed lines
+    /// without parsing tool output. Providers receive it as text.
+    Diff {
+        path: String,
+        diff: String,
+        #[serde(default)]
+        added: usize,
+        #[serde(default)]
+        removed: usize,
+    },
+}
+
+impl ContentBlock {
+    pub fn diff(path: impl Into<String>, diff: impl Into<String>) -> Self {
+        let diff = diff.into();
+        let (added, removed) = crate::core::diff::count_changes(&diff);
+        ContentBlock::Diff {
+            path: path.into(),
+            diff,
+            added,
+            removed,
+        }
+    }
+
+    /// The text a provider sees for this block; `None` for images, thinking and tool calls.
+    pub fn as_text(&self) -> Option<String> {
+        match self {
+            ContentBlock::Text { text, .. } => Some(text.clone()),
+            ContentBlock::Diff { path, diff, .. } => {
+                Some(format!("--- {path}\n+++ {path}\n{diff}"))
+            }
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -80,6 +114,22 @@ pub struct AssistantMessage {
     pub timestamp: i64,
 }
 
+/// Stop reason for a response cut short by an abort, whichever provider produced it.
+pub const STOP_REASON_ABORTED: &str = "aborted";
+
+impl AssistantMessage {
+    pub fn is_aborted(&self) -> bool {
+        self.stop_reason == STOP_REASON_ABORTED
+    }
+
+    /// Mark the message as aborted, keeping whatever content was streamed.
+    pub fn mark_aborted(&mut self) {
+        self.stop_reason = STOP_REASON_ABORTED.to_string();
+        self.error_message
+            .get_or_insert_with(|| "Request was aborted".to_string());
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ToolResultMessage {
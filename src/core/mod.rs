@@ -1,3 +1,5 @@
 pub mod compaction;
+pub mod diff;
 pub mod messages;
 pub mod session_manager;
+pub mod tokens;
@@ -1,18 +1,25 @@
 use crate::core::messages::{
     create_branch_summary_message, create_compaction_summary_message, create_hook_message,
-    AgentMessage, UserContent,
+    AgentMessage, ContentBlock, UserContent,
 };
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
 use std::collections::{HashMap, HashSet};
 use std::fs::{self, File, OpenOptions};
-use std::io::{BufRead, BufReader, Read, Write};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 use uuid::Uuid;
 
 pub const CURRENT_SESSION_VERSION: i64 = 3;
+pub const SEED_CUSTOM_TYPE: &str = "seed";
+pub const CONTEXT_PACKS_CUSTOM_TYPE: &str = "context_packs";
+pub const PIN_CUSTOM_TYPE: &str = "pin";
+pub const CHECKPOINT_CUSTOM_TYPE: &str = "checkpoint";
+/// Superseded label and session info entries a session file may collect before it is
+/// compacted (see [`compact_session_file`]).
+pub const SESSION_COMPACTION_THRESHOLD: usize = 64;
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -24,6 +31,9 @@ pub struct SessionHeader {
     pub version: Option<i64>,
     #[serde(default)]
     pub parent_session: Option<String>,
+    /// Short human-authored preview of the first user message, for session listings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preview: Option<String>,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -137,6 +147,20 @@ pub struct LabelEntry {
     pub label: Option<String>,
 }
 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInfoEntry {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub parent_id: Option<String>,
+    pub timestamp: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum SessionEntry {
@@ -148,6 +172,7 @@ pub enum SessionEntry {
     Custom(CustomEntry),
     CustomMessage(CustomMessageEntry),
     Label(LabelEntry),
+    SessionInfo(SessionInfoEntry),
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -162,6 +187,7 @@ pub enum FileEntry {
     Custom(CustomEntry),
     CustomMessage(CustomMessageEntry),
     Label(LabelEntry),
+    SessionInfo(SessionInfoEntry),
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -186,7 +212,67 @@ pub struct SessionInfo {
     pub modified: SystemTime,
     pub message_count: usize,
     pub first_message: String,
+    /// First human-authored sentence of `first_message`, skipping attached file blocks.
+    pub preview: String,
     pub all_messages_text: String,
+    pub name: Option<String>,
+    pub tags: Vec<String>,
+    /// Session file this one was branched from.
+    pub parent_session: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SessionFilter {
+    pub query: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl SessionFilter {
+    pub fn matches(&self, session: &SessionInfo) -> bool {
+        let has_tags = self.tags.iter().all(|tag| {
+            session
+                .tags
+                .iter()
+                .any(|candidate| candidate.eq_ignore_ascii_case(tag))
+        });
+        if !has_tags {
+            return false;
+        }
+        let Some(query) = self
+            .query
+            .as_deref()
+            .map(str::trim)
+            .filter(|query| !query.is_empty())
+        else {
+            return true;
+        };
+        let query = query.to_lowercase();
+        session
+            .name
+            .as_deref()
+            .is_some_and(|name| name.to_lowercase().contains(&query))
+            || session
+                .tags
+                .iter()
+                .any(|tag| tag.to_lowercase().contains(&query))
+            || session.all_messages_text.to_lowercase().contains(&query)
+    }
+}
+
+pub fn normalize_session_tags<I, S>(tags: I) -> Vec<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.as_ref().trim();
+        if tag.is_empty() || normalized.iter().any(|existing| existing == tag) {
+            continue;
+        }
+        normalized.push(tag.to_string());
+    }
+    normalized
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -204,6 +290,76 @@ pub fn get_latest_compaction_entry(entries: &[SessionEntry]) -> Option<Compactio
     None
 }
 
+/// Ids of the entries pinned on `path`, in pin order. Pinned entries survive compaction.
+pub fn pinned_entry_ids(path: &[SessionEntry]) -> Vec<String> {
+    let mut pinned: Vec<String> = Vec::new();
+    for entry in path {
+        let SessionEntry::Custom(custom) = entry else {
+            continue;
+        };
+        if custom.custom_type != PIN_CUSTOM_TYPE {
+            continue;
+        }
+        let Some(data) = custom.data.as_ref() else {
+            continue;
+        };
+        let Some(target_id) = data.get("targetId").and_then(Value::as_str) else {
+            continue;
+        };
+        pinned.retain(|id| id != target_id);
+        if data.get("pinned").and_then(Value::as_bool).unwrap_or(true) {
+            pinned.push(target_id.to_string());
+        }
+    }
+    pinned
+}
+
+/// A named point in the conversation to rewind to, with the size of the file-change journal
+/// at that point.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Checkpoint {
+    /// The checkpoint's own entry; rewinding makes it the leaf again.
+    pub entry_id: String,
+    pub name: String,
+    pub timestamp: String,
+    /// Number of file changes recorded when the checkpoint was created.
+    pub file_changes: usize,
+    /// Tool call of the last of those changes, to tell whether the journal still holds them.
+    pub last_change_id: Option<String>,
+}
+
+/// A pinned entry as it appears after the compaction summary. Tool calls and results lose
+/// their pairing once the surrounding turn is summarized, so they are kept as plain text.
+fn pinned_message(entry: &SessionEntry) -> Option<AgentMessage> {
+    let SessionEntry::Message(message) = entry else {
+        return None;
+    };
+    match &message.message {
+        AgentMessage::Assistant(assistant) => {
+            let mut assistant = assistant.clone();
+            assistant
+                .content
+                .retain(|block| matches!(block, ContentBlock::Text { .. }));
+            (!assistant.content.is_empty()).then_some(AgentMessage::Assistant(assistant))
+        }
+        AgentMessage::ToolResult(result) => {
+            let mut content = vec![ContentBlock::Text {
+                text: format!("[Pinned {} result]", result.tool_name),
+                text_signature: None,
+            }];
+            content.extend(result.content.iter().cloned());
+            Some(create_hook_message(
+                PIN_CUSTOM_TYPE,
+                UserContent::Blocks(content),
+                true,
+                None,
+                &message.timestamp,
+            ))
+        }
+        other => Some(other.clone()),
+    }
+}
+
 pub fn build_session_context(entries: &[SessionEntry], leaf_id: Option<&str>) -> SessionContext {
     if entries.is_empty() {
         return SessionContext {
@@ -311,6 +467,7 @@ pub fn build_session_context(entries: &[SessionEntry], leaf_id: Option<&str>) ->
         );
 
         if let Some(compaction_idx) = compaction_idx {
+            let pinned = pinned_entry_ids(&path);
             let mut found_first_kept = false;
             for entry in path.iter().take(compaction_idx) {
                 if entry.id() == compaction_entry.first_kept_entry_id {
@@ -318,6 +475,11 @@ pub fn build_session_context(entries: &[SessionEntry], leaf_id: Option<&str>) ->
                 }
                 if found_first_kept {
                     append_entry(entry, &mut messages);
+                } else if pinned.iter().any(|id| id == entry.id()) {
+                    match entry {
+                        SessionEntry::Message(_) => messages.extend(pinned_message(entry)),
+                        _ => append_entry(entry, &mut messages),
+                    }
                 }
             }
             for entry in path.iter().skip(compaction_idx + 1) {
@@ -479,6 +641,9 @@ fn migrate_v1_to_v2(entries: &mut [FileEntry]) {
             FileEntry::Label(label) => {
                 apply_migration_ids(&mut label.id, &mut label.parent_id, &mut prev_id, &mut ids);
             }
+            FileEntry::SessionInfo(info) => {
+                apply_migration_ids(&mut info.id, &mut info.parent_id, &mut prev_id, &mut ids);
+            }
         }
     }
 
@@ -524,6 +689,193 @@ fn apply_migration_ids(
     *prev_id = Some(id.clone());
 }
 
+/// Repairs a session file whose last line was cut short by a crash mid-append. A torn
+/// line that still parses only lost its newline and is kept; anything else is dropped.
+/// Returns whether the file was changed.
+pub fn recover_session_file(path: &Path) -> std::io::Result<bool> {
+    let bytes = fs::read(path)?;
+    if bytes.is_empty() || bytes.ends_with(b"\n") {
+        return Ok(false);
+    }
+    let tail_start = bytes
+        .iter()
+        .rposition(|byte| *byte == b'\n')
+        .map(|index| index + 1)
+        .unwrap_or(0);
+    let tail = String::from_utf8_lossy(&bytes[tail_start..]);
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    if serde_json::from_str::<FileEntry>(&tail).is_ok() {
+        file.seek(SeekFrom::End(0))?;
+        file.write_all(b"\n")?;
+    } else {
+        file.set_len(tail_start as u64)?;
+    }
+    file.sync_all()?;
+    Ok(true)
+}
+
+/// Compacts the entry log: label entries that a later label on the same target replaces, and
+/// session info entries before the last one, are dropped, and their children are attached to
+/// the dropped entry's parent. Entries something else refers to by id are kept, and lines
+/// this build cannot parse are copied as they are. Returns how many entries were dropped.
+pub fn compact_session_file(path: &Path) -> std::io::Result<usize> {
+    let content = fs::read_to_string(path)?;
+    let lines = content.lines().collect::<Vec<_>>();
+    let values = lines
+        .iter()
+        .map(|line| serde_json::from_str::<Value>(line).ok())
+        .collect::<Vec<_>>();
+    let removed = superseded_entry_ids(&values);
+    if removed.is_empty() {
+        return Ok(0);
+    }
+    let parents = values
+        .iter()
+        .flatten()
+        .filter_map(|value| {
+            let id = value.get("id")?.as_str()?;
+            let parent = value.get("parentId").and_then(Value::as_str);
+            Some((id.to_string(), parent.map(str::to_string)))
+        })
+        .collect::<HashMap<_, _>>();
+    let surviving_parent = |parent: &str| {
+        let mut parent = Some(parent.to_string());
+        while let Some(id) = parent.as_ref().filter(|id| removed.contains(*id)) {
+            parent = parents.get(id).cloned().flatten();
+        }
+        parent
+    };
+
+    let mut compacted = String::with_capacity(content.len());
+    for (line, value) in lines.iter().zip(&values) {
+        let Some(value) = value else {
+            compacted.push_str(line);
+            compacted.push('\n');
+            continue;
+        };
+        if value
+            .get("id")
+            .and_then(Value::as_str)
+            .is_some_and(|id| removed.contains(id))
+        {
+            continue;
+        }
+        match value.get("parentId").and_then(Value::as_str) {
+            Some(parent) if removed.contains(parent) => {
+                let mut value = value.clone();
+                value["parentId"] = json!(surviving_parent(parent));
+                compacted.push_str(&value.to_string());
+            }
+            _ => compacted.push_str(line),
+        }
+        compacted.push('\n');
+    }
+    write_file_atomic(path, &compacted)?;
+    Ok(removed.len())
+}
+
+/// Ids of the label and session info entries in `values` that later entries replace and
+/// nothing refers to.
+fn superseded_entry_ids(values: &[Option<Value>]) -> HashSet<String> {
+    let mut labelled = HashSet::new();
+    let mut seen_info = false;
+    let mut superseded = HashSet::new();
+    for value in values.iter().rev().flatten() {
+        let Some(id) = value.get("id").and_then(Value::as_str) else {
+            continue;
+        };
+        match value.get("type").and_then(Value::as_str) {
+            Some("label") => {
+                if let Some(target) = value.get("targetId").and_then(Value::as_str) {
+                    if !labelled.insert(target) {
+                        superseded.insert(id.to_string());
+                    }
+                }
+            }
+            Some("session_info") if std::mem::replace(&mut seen_info, true) => {
+                superseded.insert(id.to_string());
+            }
+            _ => {}
+        }
+    }
+    if !superseded.is_empty() {
+        for value in values.iter().flatten() {
+            if let Value::Object(fields) = value {
+                for (key, field) in fields {
+                    if key != "id" && key != "parentId" {
+                        remove_referenced_ids(field, &mut superseded);
+                    }
+                }
+            }
+        }
+    }
+    superseded
+}
+
+fn remove_referenced_ids(value: &Value, ids: &mut HashSet<String>) {
+    match value {
+        Value::String(text) => {
+            ids.remove(text);
+        }
+        Value::Array(items) => items
+            .iter()
+            .for_each(|item| remove_referenced_ids(item, ids)),
+        Value::Object(fields) => fields
+            .values()
+            .for_each(|field| remove_referenced_ids(field, ids)),
+        _ => {}
+    }
+}
+
+fn serialize_file_entries(entries: &[FileEntry]) -> String {
+    let mut content = String::new();
+    for entry in entries {
+        if let Ok(line) = serde_json::to_string(entry) {
+            content.push_str(&line);
+            content.push('\n');
+        }
+    }
+    content
+}
+
+/// Writes through a temporary sibling and renames it into place, so readers see either
+/// the old file or the complete new one.
+pub(crate) fn write_file_atomic(path: &Path, content: &str) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+    {
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp_path, path)
+}
+
+/// Format version recorded in the header of `path`. Files written before versioning
+/// existed have no `version` field and are treated as version 1.
+pub fn read_session_version(path: &Path) -> Option<i64> {
+    let file = File::open(path).ok()?;
+    let first_line = BufReader::new(file).lines().next()?.ok()?;
+    match serde_json::from_str::<FileEntry>(&first_line).ok()? {
+        FileEntry::Session(header) => Some(header.version.unwrap_or(1)),
+        _ => None,
+    }
+}
+
+pub fn check_session_version(path: &Path) -> Result<(), String> {
+    match read_session_version(path) {
+        Some(version) if version > CURRENT_SESSION_VERSION => Err(format!(
+            "Session file {} uses format version {version}, but this build of pi only supports up to version {CURRENT_SESSION_VERSION}. Upgrade pi to open it.",
+            path.display()
+        )),
+        _ => Ok(()),
+    }
+}
+
 fn is_valid_session_file(path: &Path) -> bool {
     let mut file = match File::open(path) {
         Ok(file) => file,
@@ -595,6 +947,7 @@ impl SessionEntry {
             SessionEntry::Custom(entry) => &entry.id,
             SessionEntry::CustomMessage(entry) => &entry.id,
             SessionEntry::Label(entry) => &entry.id,
+            SessionEntry::SessionInfo(entry) => &entry.id,
         }
     }
 
@@ -608,6 +961,7 @@ impl SessionEntry {
             SessionEntry::Custom(entry) => entry.parent_id.as_deref(),
             SessionEntry::CustomMessage(entry) => entry.parent_id.as_deref(),
             SessionEntry::Label(entry) => entry.parent_id.as_deref(),
+            SessionEntry::SessionInfo(entry) => entry.parent_id.as_deref(),
         }
     }
 
@@ -621,6 +975,7 @@ impl SessionEntry {
             SessionEntry::Custom(entry) => &entry.timestamp,
             SessionEntry::CustomMessage(entry) => &entry.timestamp,
             SessionEntry::Label(entry) => &entry.timestamp,
+            SessionEntry::SessionInfo(entry) => &entry.timestamp,
         }
     }
 }
@@ -636,6 +991,7 @@ impl FileEntry {
             FileEntry::Custom(entry) => Some(&entry.id),
             FileEntry::CustomMessage(entry) => Some(&entry.id),
             FileEntry::Label(entry) => Some(&entry.id),
+            FileEntry::SessionInfo(entry) => Some(&entry.id),
             FileEntry::Session(_) => None,
         }
     }
@@ -652,6 +1008,7 @@ impl FileEntry {
             FileEntry::Custom(entry) => Some(SessionEntry::Custom(entry.clone())),
             FileEntry::CustomMessage(entry) => Some(SessionEntry::CustomMessage(entry.clone())),
             FileEntry::Label(entry) => Some(SessionEntry::Label(entry.clone())),
+            FileEntry::SessionInfo(entry) => Some(SessionEntry::SessionInfo(entry.clone())),
             FileEntry::Session(_) => None,
         }
     }
@@ -702,6 +1059,13 @@ impl SessionManager {
         SessionManager::new(cwd, dir, Some(path), true)
     }
 
+    /// Like `open`, but refuses files written by a newer version of pi instead of
+    /// loading them with unknown entries dropped.
+    pub fn try_open(path: PathBuf, session_dir: Option<PathBuf>) -> Result<Self, String> {
+        check_session_version(&path)?;
+        Ok(SessionManager::open(path, session_dir))
+    }
+
     pub fn continue_recent(cwd: PathBuf, session_dir: Option<PathBuf>) -> Self {
         let dir = session_dir.unwrap_or_else(|| get_default_session_dir(&cwd));
         if let Some(path) = find_most_recent_session(&dir) {
@@ -753,13 +1117,31 @@ impl SessionManager {
             let mut message_count = 0usize;
             let mut first_message = String::new();
             let mut all_messages = Vec::new();
+            let mut name = None;
+            let mut tags = Vec::new();
+            let preview = header_value
+                .get("preview")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            let parent_session = header_value
+                .get("parentSession")
+                .and_then(Value::as_str)
+                .map(str::to_string);
 
             for line in lines {
                 let entry: Value = match serde_json::from_str(line) {
                     Ok(entry) => entry,
                     Err(_) => continue,
                 };
-                if entry.get("type").and_then(Value::as_str) != Some("message") {
+                let entry_type = entry.get("type").and_then(Value::as_str);
+                if entry_type == Some("session_info") {
+                    if let Ok(info) = serde_json::from_value::<SessionInfoEntry>(entry) {
+                        name = info.name;
+                        tags = info.tags;
+                    }
+                    continue;
+                }
+                if entry_type != Some("message") {
                     continue;
                 }
                 message_count += 1;
@@ -788,6 +1170,9 @@ impl SessionManager {
             } else {
                 first_message
             };
+            let preview = preview
+                .filter(|preview| !preview.is_empty())
+                .unwrap_or_else(|| extract_session_preview(&first_message));
 
             sessions.push(SessionInfo {
                 path,
@@ -796,7 +1181,11 @@ impl SessionManager {
                 modified,
                 message_count,
                 first_message,
+                preview,
                 all_messages_text: all_messages.join(" "),
+                name,
+                tags,
+                parent_session,
             });
         }
 
@@ -804,6 +1193,17 @@ impl SessionManager {
         sessions
     }
 
+    pub fn search(
+        cwd: &Path,
+        session_dir: Option<PathBuf>,
+        filter: &SessionFilter,
+    ) -> Vec<SessionInfo> {
+        SessionManager::list(cwd, session_dir)
+            .into_iter()
+            .filter(|session| filter.matches(session))
+            .collect()
+    }
+
     fn new(
         cwd: PathBuf,
         session_dir: PathBuf,
@@ -844,6 +1244,7 @@ impl SessionManager {
             timestamp: timestamp.clone(),
             cwd: self.cwd.to_string_lossy().to_string(),
             parent_session,
+            preview: None,
         };
         let header_entry = FileEntry::Session(header.clone());
         self.file_entries = vec![header_entry.clone()];
@@ -861,11 +1262,8 @@ impl SessionManager {
         if self.persist {
             if let Some(path) = self.session_file.as_ref() {
                 if !path.exists() {
-                    if let Some(parent) = path.parent() {
-                        let _ = fs::create_dir_all(parent);
-                    }
                     if let Ok(line) = serde_json::to_string(&header_entry) {
-                        let _ = fs::write(path, format!("{line}\n"));
+                        let _ = write_file_atomic(path, &format!("{line}\n"));
                     }
                 }
             }
@@ -881,6 +1279,7 @@ impl SessionManager {
     pub fn set_session_file(&mut self, session_file: PathBuf) {
         self.session_file = Some(session_file.clone());
         if session_file.exists() {
+            let _ = recover_session_file(&session_file);
             self.file_entries = load_entries_from_file(&session_file);
             let header_id = self
                 .file_entries
@@ -892,10 +1291,18 @@ impl SessionManager {
                 .unwrap_or_else(|| Uuid::new_v4().simple().to_string());
             self.session_id = header_id;
 
-            migrate_session_entries(&mut self.file_entries);
-            self.rewrite_file();
+            // Never rewrite a file from a newer format: entries this build cannot parse
+            // were skipped on load and would be lost.
+            let needs_migration = read_session_version(&session_file)
+                .map(|version| version < CURRENT_SESSION_VERSION)
+                .unwrap_or(false);
+            if needs_migration {
+                migrate_session_entries(&mut self.file_entries);
+                self.rewrite_file();
+            }
             self.build_index();
             self.flushed = true;
+            self.compact_if_due();
         } else {
             self.new_session(None);
         }
@@ -922,6 +1329,59 @@ impl SessionManager {
         }
     }
 
+    /// Label and session info entries that later ones replace.
+    fn superseded_entry_count(&self) -> usize {
+        let labels = self
+            .file_entries
+            .iter()
+            .filter(|entry| matches!(entry, FileEntry::Label(_)))
+            .count();
+        let infos = self
+            .file_entries
+            .iter()
+            .filter(|entry| matches!(entry, FileEntry::SessionInfo(_)))
+            .count();
+        labels.saturating_sub(self.labels_by_id.len()) + infos.saturating_sub(1)
+    }
+
+    /// Compacts the session file once it holds [`SESSION_COMPACTION_THRESHOLD`] superseded
+    /// entries, then reloads it, staying on the same branch.
+    fn compact_if_due(&mut self) {
+        if !self.persist || !self.flushed {
+            return;
+        }
+        if self.superseded_entry_count() < SESSION_COMPACTION_THRESHOLD {
+            return;
+        }
+        let Some(path) = self.session_file.clone() else {
+            return;
+        };
+        if read_session_version(&path) != Some(CURRENT_SESSION_VERSION) {
+            return;
+        }
+        let parents = self
+            .file_entries
+            .iter()
+            .filter_map(|entry| entry.as_session_entry())
+            .map(|entry| {
+                (
+                    entry.id().to_string(),
+                    entry.parent_id().map(str::to_string),
+                )
+            })
+            .collect::<HashMap<_, _>>();
+        if !matches!(compact_session_file(&path), Ok(count) if count > 0) {
+            return;
+        }
+        let mut leaf = self.leaf_id.clone();
+        self.file_entries = load_entries_from_file(&path);
+        self.build_index();
+        while let Some(id) = leaf.as_deref().filter(|id| !self.by_id.contains_key(*id)) {
+            leaf = parents.get(id).cloned().flatten();
+        }
+        self.leaf_id = leaf;
+    }
+
     fn rewrite_file(&self) {
         if !self.persist {
             return;
@@ -930,14 +1390,7 @@ impl SessionManager {
             Some(path) => path,
             None => return,
         };
-        let mut content = String::new();
-        for entry in &self.file_entries {
-            if let Ok(line) = serde_json::to_string(entry) {
-                content.push_str(&line);
-                content.push('\n');
-            }
-        }
-        let _ = fs::write(path, content);
+        let _ = write_file_atomic(path, &serialize_file_entries(&self.file_entries));
     }
 
     fn persist_entry(&mut self, entry: &FileEntry) {
@@ -957,26 +1410,18 @@ impl SessionManager {
         }
 
         if !self.flushed {
-            let mut content = String::new();
-            for entry in &self.file_entries {
-                if let Ok(line) = serde_json::to_string(entry) {
-                    content.push_str(&line);
-                    content.push('\n');
-                }
-            }
-            if let Some(parent) = path.parent() {
-                let _ = fs::create_dir_all(parent);
-            }
-            if let Ok(mut file) = File::create(path) {
-                let _ = file.write_all(content.as_bytes());
-            }
+            let _ = write_file_atomic(path, &serialize_file_entries(&self.file_entries));
             self.flushed = true;
         } else if let Ok(line) = serde_json::to_string(entry) {
             if let Some(parent) = path.parent() {
                 let _ = fs::create_dir_all(parent);
             }
             if let Ok(mut file) = OpenOptions::new().append(true).create(true).open(path) {
-                let _ = writeln!(file, "{}", line);
+                // One write per entry keeps a crash from interleaving partial lines; the
+                // sync makes the entry durable before the caller moves on.
+                if file.write_all(format!("{line}\n").as_bytes()).is_ok() {
+                    let _ = file.sync_data();
+                }
             }
         }
     }
@@ -996,6 +1441,7 @@ impl SessionManager {
                 FileEntry::CustomMessage(custom_message.clone())
             }
             SessionEntry::Label(label) => FileEntry::Label(label.clone()),
+            SessionEntry::SessionInfo(info) => FileEntry::SessionInfo(info.clone()),
         };
         self.file_entries.push(file_entry.clone());
         self.by_id.insert(id.clone(), entry.clone());
@@ -1010,10 +1456,37 @@ impl SessionManager {
         }
 
         self.persist_entry(&file_entry);
+        if matches!(file_entry, FileEntry::Label(_) | FileEntry::SessionInfo(_)) {
+            self.compact_if_due();
+        }
         id
     }
 
     pub fn append_message(&mut self, message: AgentMessage) -> String {
+        let preview = match &message {
+            AgentMessage::User(user) if self.get_session_preview().is_none() => {
+                let text = match &user.content {
+                    UserContent::Text(text) => text.clone(),
+                    UserContent::Blocks(blocks) => blocks
+                        .iter()
+                        .filter_map(|block| match block {
+                            ContentBlock::Text { text, .. } => Some(text.as_str()),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                };
+                Some(extract_session_preview(&text)).filter(|preview| !preview.is_empty())
+            }
+            _ => None,
+        };
+        // The header is only written once the first reply arrives, so the preview can
+        // still be recorded there.
+        if let (Some(preview), false) = (preview, self.flushed) {
+            if let Some(FileEntry::Session(header)) = self.file_entries.first_mut() {
+                header.preview = Some(preview);
+            }
+        }
         let entry = SessionMessageEntry {
             id: self.next_id(),
             parent_id: self.leaf_id.clone(),
@@ -1049,6 +1522,16 @@ impl SessionManager {
         summary: &str,
         first_kept_entry_id: &str,
         tokens_before: i64,
+    ) -> String {
+        self.append_compaction_with_details(summary, first_kept_entry_id, tokens_before, None)
+    }
+
+    pub fn append_compaction_with_details(
+        &mut self,
+        summary: &str,
+        first_kept_entry_id: &str,
+        tokens_before: i64,
+        details: Option<Value>,
     ) -> String {
         let entry = CompactionEntry {
             id: self.next_id(),
@@ -1058,7 +1541,7 @@ impl SessionManager {
             first_kept_entry_id: first_kept_entry_id.to_string(),
             first_kept_entry_index: None,
             tokens_before,
-            details: None,
+            details,
             from_hook: None,
         };
         self.append_entry(SessionEntry::Compaction(entry))
@@ -1075,6 +1558,107 @@ impl SessionManager {
         self.append_entry(SessionEntry::Custom(entry))
     }
 
+    /// Records the sampling seed used for the following turns so the run can be replayed.
+    pub fn append_seed(&mut self, seed: u64) -> String {
+        self.append_custom_entry(SEED_CUSTOM_TYPE, json!({ "seed": seed }))
+    }
+
+    pub fn get_seed(&self) -> Option<u64> {
+        self.get_branch(None)
+            .iter()
+            .rev()
+            .find_map(|entry| match entry {
+                SessionEntry::Custom(custom) if custom.custom_type == SEED_CUSTOM_TYPE => custom
+                    .data
+                    .as_ref()
+                    .and_then(|data| data.get("seed"))
+                    .and_then(Value::as_u64),
+                _ => None,
+            })
+    }
+
+    /// Records the context packs enabled from this point on, so a resumed session restores them.
+    pub fn append_context_packs(&mut self, names: &[String]) -> String {
+        self.append_custom_entry(CONTEXT_PACKS_CUSTOM_TYPE, json!({ "names": names }))
+    }
+
+    pub fn get_context_packs(&self) -> Vec<String> {
+        self.get_branch(None)
+            .iter()
+            .rev()
+            .find_map(|entry| match entry {
+                SessionEntry::Custom(custom) if custom.custom_type == CONTEXT_PACKS_CUSTOM_TYPE => {
+                    custom
+                        .data
+                        .as_ref()
+                        .and_then(|data| data.get("names"))
+                        .and_then(|names| serde_json::from_value(names.clone()).ok())
+                }
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    /// Pin or unpin a message entry so it is kept verbatim through compaction.
+    pub fn append_pin(&mut self, target_id: &str, pinned: bool) -> Result<String, String> {
+        match self.by_id.get(target_id) {
+            Some(SessionEntry::Message(_)) | Some(SessionEntry::CustomMessage(_)) => {}
+            Some(_) => return Err(format!("Entry {target_id} is not a message")),
+            None => return Err(format!("Entry {target_id} not found")),
+        }
+        Ok(self.append_custom_entry(
+            PIN_CUSTOM_TYPE,
+            json!({ "targetId": target_id, "pinned": pinned }),
+        ))
+    }
+
+    pub fn get_pinned_entry_ids(&self) -> Vec<String> {
+        pinned_entry_ids(&self.get_branch(None))
+    }
+
+    pub fn append_checkpoint(
+        &mut self,
+        name: &str,
+        file_changes: usize,
+        last_change_id: Option<&str>,
+    ) -> String {
+        self.append_custom_entry(
+            CHECKPOINT_CUSTOM_TYPE,
+            json!({
+                "name": name,
+                "fileChanges": file_changes,
+                "lastChangeId": last_change_id,
+            }),
+        )
+    }
+
+    /// Checkpoints on every branch of the session, oldest first.
+    pub fn get_checkpoints(&self) -> Vec<Checkpoint> {
+        self.get_entries()
+            .iter()
+            .filter_map(|entry| {
+                let SessionEntry::Custom(custom) = entry else {
+                    return None;
+                };
+                if custom.custom_type != CHECKPOINT_CUSTOM_TYPE {
+                    return None;
+                }
+                let data = custom.data.as_ref()?;
+                Some(Checkpoint {
+                    entry_id: custom.id.clone(),
+                    name: data.get("name")?.as_str()?.to_string(),
+                    timestamp: custom.timestamp.clone(),
+                    file_changes: data.get("fileChanges").and_then(Value::as_u64).unwrap_or(0)
+                        as usize,
+                    last_change_id: data
+                        .get("lastChangeId")
+                        .and_then(Value::as_str)
+                        .map(str::to_string),
+                })
+            })
+            .collect()
+    }
+
     pub fn append_label_change(
         &mut self,
         target_id: &str,
@@ -1093,6 +1677,55 @@ impl SessionManager {
         Ok(self.append_entry(SessionEntry::Label(entry)))
     }
 
+    pub fn append_session_info(&mut self, name: Option<&str>, tags: &[String]) -> String {
+        let entry = SessionInfoEntry {
+            id: self.next_id(),
+            parent_id: self.leaf_id.clone(),
+            timestamp: Utc::now().to_rfc3339(),
+            name: name
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(|name| name.to_string()),
+            tags: normalize_session_tags(tags),
+        };
+        self.append_entry(SessionEntry::SessionInfo(entry))
+    }
+
+    pub fn get_session_preview(&self) -> Option<String> {
+        self.get_header().and_then(|header| header.preview)
+    }
+
+    pub fn set_session_name(&mut self, name: Option<&str>) -> String {
+        let tags = self.get_session_tags();
+        self.append_session_info(name, &tags)
+    }
+
+    pub fn set_session_tags(&mut self, tags: &[String]) -> String {
+        let name = self.get_session_name();
+        self.append_session_info(name.as_deref(), tags)
+    }
+
+    pub fn get_session_name(&self) -> Option<String> {
+        self.latest_session_info()
+            .and_then(|info| info.name.clone())
+    }
+
+    pub fn get_session_tags(&self) -> Vec<String> {
+        self.latest_session_info()
+            .map(|info| info.tags.clone())
+            .unwrap_or_default()
+    }
+
+    fn latest_session_info(&self) -> Option<&SessionInfoEntry> {
+        self.file_entries
+            .iter()
+            .rev()
+            .find_map(|entry| match entry {
+                FileEntry::SessionInfo(info) => Some(info),
+                _ => None,
+            })
+    }
+
     pub fn get_entries(&self) -> Vec<SessionEntry> {
         self.file_entries
             .iter()
@@ -1270,6 +1903,7 @@ impl SessionManager {
             } else {
                 None
             },
+            preview: self.get_session_preview(),
         };
 
         let path_entry_ids: HashSet<String> = path_without_labels
@@ -1369,6 +2003,29 @@ impl SessionManager {
         Ok(None)
     }
 
+    /// Session file this session was branched from, if any.
+    pub fn get_parent_session(&self) -> Option<PathBuf> {
+        self.get_header()
+            .and_then(|header| header.parent_session)
+            .map(PathBuf::from)
+    }
+
+    /// Sessions in the session directory that were branched from this session's file.
+    pub fn list_child_sessions(&self) -> Vec<SessionInfo> {
+        let Some(session_file) = self.session_file.as_ref() else {
+            return Vec::new();
+        };
+        let session_file = session_file.to_string_lossy();
+        SessionManager::list(&self.cwd, Some(self.get_session_dir()))
+            .into_iter()
+            .filter(|session| session.parent_session.as_deref() == Some(session_file.as_ref()))
+            .collect()
+    }
+
+    pub fn get_cwd(&self) -> PathBuf {
+        self.cwd.clone()
+    }
+
     pub fn get_session_dir(&self) -> PathBuf {
         if self.session_dir.as_os_str().is_empty() {
             get_default_session_dir(&self.cwd)
@@ -1391,8 +2048,72 @@ impl SessionEntry {
             SessionEntry::Custom(entry) => FileEntry::Custom(entry.clone()),
             SessionEntry::CustomMessage(entry) => FileEntry::CustomMessage(entry.clone()),
             SessionEntry::Label(entry) => FileEntry::Label(entry.clone()),
+            SessionEntry::SessionInfo(entry) => FileEntry::SessionInfo(entry.clone()),
+        }
+    }
+}
+
+const SESSION_PREVIEW_MAX_CHARS: usize = 160;
+
+/// Preview for a session listing: the first sentence of `text` written by the user, with
+/// `<file>` attachments and fenced code blocks removed. Falls back to the attached file names.
+pub fn extract_session_preview(text: &str) -> String {
+    let mut prose = String::new();
+    let mut files = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("<file name=\"") {
+        prose.push_str(&rest[..start]);
+        let after = &rest[start + "<file name=\"".len()..];
+        if let Some(name_end) = after.find('"') {
+            files.push(after[..name_end].to_string());
         }
+        rest = match after.find("</file>") {
+            Some(end) => &after[end + "</file>".len()..],
+            None => "",
+        };
+    }
+    prose.push_str(rest);
+
+    let mut in_code = false;
+    let prose = prose
+        .lines()
+        .filter(|line| {
+            if line.trim_start().starts_with("```") {
+                in_code = !in_code;
+                return false;
+            }
+            !in_code
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    let prose = prose.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    let sentence = prose
+        .char_indices()
+        .find(|(index, ch)| {
+            matches!(ch, '.' | '?' | '!')
+                && prose[index + ch.len_utf8()..]
+                    .chars()
+                    .next()
+                    .is_none_or(char::is_whitespace)
+        })
+        .map(|(index, ch)| &prose[..index + ch.len_utf8()])
+        .unwrap_or(&prose);
+    if sentence.is_empty() {
+        return if files.is_empty() {
+            String::new()
+        } else {
+            format!("[files: {}]", files.join(", "))
+        };
+    }
+    if sentence.chars().count() > SESSION_PREVIEW_MAX_CHARS {
+        let clipped = sentence
+            .chars()
+            .take(SESSION_PREVIEW_MAX_CHARS - 3)
+            .collect::<String>();
+        return format!("{}...", clipped.trim_end());
     }
+    sentence.to_string()
 }
 
 fn extract_message_text(content: Option<&Value>) -> String {
@@ -0,0 +1,286 @@
+//! Token counts for context accounting. pi ships no tokenizer vocabularies, so counts are
+//! estimates: OpenAI models split text the way tiktoken's cl100k/o200k pre-tokenizer does and
+//! price each piece, Claude models use Anthropic's published characters-per-token ratio and
+//! everything else the classic four characters per token. Token counts providers reported
+//! for earlier replies always win over an estimate.
+
+use crate::agent::Model;
+use crate::core::compaction::{calculate_context_tokens, get_assistant_usage};
+use crate::core::messages::{AgentMessage, ContentBlock, UserContent};
+use serde::Serialize;
+
+/// Characters an image is counted as by the heuristic tokenizer.
+const IMAGE_CHARS: usize = 4800;
+/// Tokens an image is counted as by the other tokenizers.
+const IMAGE_TOKENS: i64 = 1200;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Tokenizer {
+    /// GPT-4o, GPT-4.1, GPT-5 and the o-series.
+    O200k,
+    /// GPT-4 and GPT-3.5.
+    Cl100k,
+    Claude,
+    /// Four characters per token.
+    Heuristic,
+}
+
+impl Tokenizer {
+    pub fn for_model(model: &Model) -> Self {
+        let id = model.id.to_lowercase();
+        if id.contains("claude") || model.provider == "anthropic" {
+            return Tokenizer::Claude;
+        }
+        if id.starts_with("gpt-4o")
+            || id.starts_with("gpt-4.1")
+            || id.starts_with("gpt-5")
+            || id.starts_with("chatgpt")
+            || ["o1", "o3", "o4"]
+                .iter()
+                .any(|prefix| id == *prefix || id.starts_with(&format!("{prefix}-")))
+        {
+            return Tokenizer::O200k;
+        }
+        if id.starts_with("gpt-4") || id.starts_with("gpt-3.5") {
+            return Tokenizer::Cl100k;
+        }
+        if model.api.starts_with("openai") || model.provider == "openai" {
+            return Tokenizer::O200k;
+        }
+        Tokenizer::Heuristic
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Tokenizer::O200k => "o200k",
+            Tokenizer::Cl100k => "cl100k",
+            Tokenizer::Claude => "claude",
+            Tokenizer::Heuristic => "heuristic",
+        }
+    }
+
+    pub fn count(self, text: &str) -> i64 {
+        match self {
+            Tokenizer::Heuristic => text.len().div_ceil(4) as i64,
+            // Anthropic documents roughly 3.5 characters per token.
+            Tokenizer::Claude => (text.chars().count() * 2).div_ceil(7) as i64,
+            Tokenizer::O200k | Tokenizer::Cl100k => {
+                pieces(text).map(|piece| self.piece_tokens(piece)).sum()
+            }
+        }
+    }
+
+    /// BPE merges a common word into one token and splits rarer, longer runs; a large
+    /// vocabulary keeps longer words and more non-Latin text whole.
+    fn piece_tokens(self, piece: &str) -> i64 {
+        let body = piece.trim_start_matches(|c: char| !c.is_alphanumeric());
+        let Some(first) = body.chars().next() else {
+            // Whitespace and punctuation runs.
+            let length = piece.trim_start_matches(' ').chars().count();
+            return if piece.trim().is_empty() {
+                1
+            } else {
+                length.div_ceil(2).max(1) as i64
+            };
+        };
+        if first.is_numeric() {
+            return 1;
+        }
+        let ascii = body.chars().filter(char::is_ascii).count();
+        let other = body.chars().count() - ascii;
+        let (whole_word, chars_per_token, other_per_token) = match self {
+            Tokenizer::O200k => (8, 5.5, 1.5),
+            _ => (7, 4.5, 1.0),
+        };
+        let ascii_tokens = if ascii == 0 {
+            0
+        } else if ascii <= whole_word {
+            1
+        } else {
+            (ascii as f64 / chars_per_token).ceil() as i64
+        };
+        ascii_tokens + (other as f64 / other_per_token).ceil() as i64
+    }
+}
+
+/// Splits text like tiktoken's pre-tokenizer: contractions, words with one leading
+/// non-letter (usually a space), numbers of up to three digits, punctuation runs with an
+/// optional leading space, and whitespace (a run before a word leaves its last space to
+/// the word).
+fn pieces(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        let piece_len = next_piece(rest)?;
+        let (piece, tail) = rest.split_at(piece_len);
+        rest = tail;
+        Some(piece)
+    })
+}
+
+fn next_piece(text: &str) -> Option<usize> {
+    let chars: Vec<(usize, char)> = text.char_indices().take(64).collect();
+    let &(_, first) = chars.first()?;
+    let end = |index: usize| chars.get(index).map_or(text.len(), |(offset, _)| *offset);
+    let char_at = |index: usize| chars.get(index).map(|(_, c)| *c);
+    let run = |from: usize, test: &dyn Fn(char) -> bool| {
+        from + chars[from..].iter().take_while(|(_, c)| test(*c)).count()
+    };
+
+    if first == '\'' {
+        let next = text[1..].to_lowercase();
+        for contraction in ["s", "t", "m", "d", "re", "ve", "ll"] {
+            if next.starts_with(contraction)
+                && !next[contraction.len()..]
+                    .chars()
+                    .next()
+                    .is_some_and(char::is_alphabetic)
+            {
+                return Some(1 + contraction.len());
+            }
+        }
+    }
+    if first.is_alphabetic() {
+        return Some(end(run(0, &|c| c.is_alphabetic())));
+    }
+    if first.is_numeric() {
+        return Some(end(run(0, &|c| c.is_numeric()).min(3)));
+    }
+    let second = char_at(1);
+    if first != '\n' && first != '\r' && second.is_some_and(char::is_alphabetic) {
+        return Some(end(run(1, &|c| c.is_alphabetic())));
+    }
+    if first.is_whitespace() {
+        let spaces = run(0, &|c| c.is_whitespace());
+        let through_newline = chars[..spaces]
+            .iter()
+            .rposition(|(_, c)| *c == '\n' || *c == '\r');
+        if let Some(newline) = through_newline {
+            return Some(end(newline + 1));
+        }
+        // Leave the last space to the word or punctuation that follows.
+        if spaces > 1 && spaces < chars.len() {
+            return Some(end(spaces - 1));
+        }
+        if spaces == 1 && spaces < chars.len() {
+            return Some(end(run(1, &|c| !c.is_whitespace() && !c.is_alphanumeric())));
+        }
+        return Some(end(spaces));
+    }
+    let punctuation = run(0, &|c| !c.is_whitespace() && !c.is_alphanumeric());
+    Some(end(run(punctuation, &|c| c == '\n' || c == '\r')))
+}
+
+/// Tokens a message adds to the context.
+pub fn estimate_message(message: &AgentMessage, tokenizer: Tokenizer) -> i64 {
+    let mut texts: Vec<String> = Vec::new();
+    let mut images = 0;
+
+    match message {
+        AgentMessage::User(user) => match &user.content {
+            UserContent::Text(text) => texts.push(text.clone()),
+            UserContent::Blocks(blocks) => {
+                for block in blocks {
+                    if let ContentBlock::Text { text, .. } = block {
+                        texts.push(text.clone());
+                    }
+                }
+            }
+        },
+        AgentMessage::Assistant(assistant) => {
+            for block in &assistant.content {
+                match block {
+                    ContentBlock::Text { text, .. } => texts.push(text.clone()),
+                    ContentBlock::Thinking { thinking, .. } => texts.push(thinking.clone()),
+                    ContentBlock::ToolCall {
+                        name, arguments, ..
+                    } => {
+                        texts.push(name.clone());
+                        texts.push(serde_json::to_string(arguments).unwrap_or_default());
+                    }
+                    ContentBlock::Image { .. } | ContentBlock::Diff { .. } => {}
+                }
+            }
+        }
+        AgentMessage::HookMessage(hook) => match &hook.content {
+            UserContent::Text(text) => texts.push(text.clone()),
+            UserContent::Blocks(blocks) => {
+                for block in blocks {
+                    match block {
+                        ContentBlock::Text { text, .. } => texts.push(text.clone()),
+                        ContentBlock::Image { .. } => images += 1,
+                        _ => {}
+                    }
+                }
+            }
+        },
+        AgentMessage::ToolResult(result) => {
+            for block in &result.content {
+                match block {
+                    ContentBlock::Text { text, .. } => texts.push(text.clone()),
+                    ContentBlock::Diff { diff, .. } => texts.push(diff.clone()),
+                    ContentBlock::Image { .. } => images += 1,
+                    _ => {}
+                }
+            }
+        }
+        AgentMessage::BashExecution(bash) => {
+            texts.push(bash.command.clone());
+            texts.push(bash.output.clone());
+        }
+        AgentMessage::BranchSummary(summary) => texts.push(summary.summary.clone()),
+        AgentMessage::CompactionSummary(summary) => texts.push(summary.summary.clone()),
+    }
+
+    match tokenizer {
+        Tokenizer::Heuristic => {
+            let chars = texts.iter().map(String::len).sum::<usize>() + images * IMAGE_CHARS;
+            chars.div_ceil(4) as i64
+        }
+        _ => {
+            texts.iter().map(|text| tokenizer.count(text)).sum::<i64>()
+                + images as i64 * IMAGE_TOKENS
+        }
+    }
+}
+
+/// The tokens in a context, split into what the provider reported and what was estimated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct TokenEstimate {
+    /// Context size the provider reported with the last successful assistant reply.
+    pub reported: i64,
+    /// Estimated tokens of the messages after that reply (or all of them, without one).
+    pub estimated: i64,
+    pub tokenizer: Tokenizer,
+}
+
+impl TokenEstimate {
+    pub fn total(&self) -> i64 {
+        self.reported + self.estimated
+    }
+}
+
+/// Tokens `messages` take up in `model`'s context.
+pub fn estimate(messages: &[AgentMessage], model: &Model) -> TokenEstimate {
+    estimate_with(messages, Tokenizer::for_model(model))
+}
+
+pub fn estimate_with(messages: &[AgentMessage], tokenizer: Tokenizer) -> TokenEstimate {
+    let last_usage = messages
+        .iter()
+        .enumerate()
+        .rev()
+        .find_map(|(index, message)| get_assistant_usage(message).map(|usage| (index, usage)));
+    let (reported, rest) = match last_usage {
+        Some((index, usage)) => (calculate_context_tokens(&usage), &messages[index + 1..]),
+        None => (0, messages),
+    };
+    TokenEstimate {
+        reported,
+        estimated: rest
+            .iter()
+            .map(|message| estimate_message(message, tokenizer))
+            .sum(),
+        tokenizer,
+    }
+}
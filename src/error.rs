@@ -0,0 +1,136 @@
+//! `pi::Error`: failures typed so library callers can branch on them, e.g. wait and retry on
+//! a rate limit but stop on bad credentials. Provider calls return it directly, and the agent
+//! keeps the one behind a failed reply (see [`Agent::take_error`](crate::agent::Agent::take_error)).
+
+use crate::agent::AgentError;
+use crate::coding_agent::AgentSessionError;
+use std::time::Duration;
+
+/// `Result` with [`Error`] as the default error type.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Missing credentials, or the provider rejected them (401/403).
+    #[error("{message}")]
+    Auth { message: String },
+    /// The provider answered 429. `retry_after` is its `retry-after` header, when it sent one.
+    #[error("{message}")]
+    RateLimited {
+        retry_after: Option<Duration>,
+        message: String,
+    },
+    /// Any other provider failure. `status` is `None` when no HTTP response arrived or the
+    /// stream broke after it started.
+    #[error("{message}")]
+    Provider {
+        status: Option<u16>,
+        message: String,
+    },
+    /// The request could not be built, so nothing was sent: a header HTTP does not allow, a
+    /// token without the claims the provider needs, or a request hook that failed.
+    #[error("{0}")]
+    Request(String),
+    #[error("{0}")]
+    Tool(String),
+    #[error("{0}")]
+    Session(String),
+    /// A prompt arrived while the session was streaming, without saying whether to steer or
+    /// follow up.
+    #[error("{}", AgentSessionError::AlreadyStreaming)]
+    AlreadyStreaming,
+    /// The entry to branch from is missing, or `branch` was given one that is not a user
+    /// message.
+    #[error("{}", AgentSessionError::InvalidBranchEntry)]
+    InvalidBranchEntry,
+    /// The entry to navigate to is not in the session tree.
+    #[error("{}", AgentSessionError::InvalidTreeTarget)]
+    InvalidTreeTarget,
+    #[error("{0}")]
+    Compaction(String),
+    /// The agent refused the call, e.g. a prompt while one is already streaming.
+    #[error(transparent)]
+    Agent(AgentError),
+    /// A configured cost budget ran out before the prompt was sent.
+    #[error("{0}")]
+    BudgetExceeded(String),
+    /// The run was aborted before the model finished.
+    #[error("{0}")]
+    Aborted(String),
+    /// No model to talk to: none matched, its API is not supported, or the model list could
+    /// not be loaded.
+    #[error("{0}")]
+    Config(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Parse(#[from] serde_json::Error),
+}
+
+impl Error {
+    /// An I/O failure with what pi was doing in front of it; the kind is kept, so callers can
+    /// still tell a missing file from a permission error.
+    pub fn io(context: impl std::fmt::Display, err: std::io::Error) -> Self {
+        Error::Io(std::io::Error::new(err.kind(), format!("{context}: {err}")))
+    }
+
+    /// Classifies a failed HTTP reply by its status code.
+    pub fn from_status(status: u16, retry_after: Option<Duration>, message: String) -> Self {
+        match status {
+            401 | 403 => Error::Auth { message },
+            429 => Error::RateLimited {
+                retry_after,
+                message,
+            },
+            _ => Error::Provider {
+                status: Some(status),
+                message,
+            },
+        }
+    }
+
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Error::RateLimited { .. } => Some(429),
+            Error::Provider { status, .. } => *status,
+            _ => None,
+        }
+    }
+
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Error::RateLimited { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// Whether the same request might succeed later: rate limits, server errors, and
+    /// failures where no response arrived.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::RateLimited { .. } => true,
+            Error::Provider { status, .. } => status.is_none_or(|status| status >= 500),
+            _ => false,
+        }
+    }
+}
+
+impl From<AgentError> for Error {
+    fn from(err: AgentError) -> Self {
+        Error::Agent(err)
+    }
+}
+
+impl From<AgentSessionError> for Error {
+    fn from(err: AgentSessionError) -> Self {
+        match err {
+            AgentSessionError::AlreadyStreaming => Error::AlreadyStreaming,
+            AgentSessionError::Agent(err) => Error::Agent(err),
+            AgentSessionError::InvalidBranchEntry => Error::InvalidBranchEntry,
+            AgentSessionError::InvalidTreeTarget => Error::InvalidTreeTarget,
+            AgentSessionError::Compaction(message) => Error::Compaction(message),
+            AgentSessionError::Session(message) => Error::Session(message),
+            AgentSessionError::BudgetExceeded(reason) => Error::BudgetExceeded(reason),
+        }
+    }
+}
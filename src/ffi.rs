@@ -0,0 +1,314 @@
+//! C ABI for embedding pi from runtimes that cannot link Rust directly (Swift, C#, ...).
+//! Built with the `ffi` feature; `include/pi.h` declares these functions. The crate is an
+//! rlib by default, so the shared library is built on request:
+//!
+//! ```text
+//! cargo rustc --release --lib --features ffi --crate-type cdylib
+//! ```
+//!
+//! Values cross the boundary as UTF-8 JSON strings. Strings returned by pi belong to the
+//! caller and are released with `pi_string_free`. Failed calls return null (or -1) and leave
+//! a message for `pi_last_error`. A session must only be used from the thread that created it.
+
+use crate::agent::CancellationToken;
+use crate::cli::event_json::{serialize_agent_message, serialize_session_event};
+use crate::coding_agent::{AuthStorage, ModelRegistry};
+use crate::config;
+use crate::sdk::{PiClient, PromptResult};
+use serde::Deserialize;
+use serde_json::json;
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::ptr;
+use std::rc::Rc;
+
+/// Bumped whenever a declaration in `include/pi.h` changes incompatibly.
+pub const PI_FFI_VERSION: u32 = 1;
+
+/// Called with each event as a JSON object (the `--mode json` event format) and the
+/// `user_data` given at registration. The string is only valid during the call.
+pub type PiEventCallback = extern "C" fn(event_json: *const c_char, user_data: *mut c_void);
+
+#[derive(Clone, Copy)]
+struct EventCallback {
+    callback: PiEventCallback,
+    user_data: *mut c_void,
+}
+
+pub struct PiSession {
+    client: PiClient,
+    callback: Rc<RefCell<Option<EventCallback>>>,
+    /// Shared with the client's agent, so an event callback can abort the running prompt.
+    abort: CancellationToken,
+}
+
+/// The JSON object `pi_session_new` takes; every field is optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+struct SessionConfig {
+    provider: Option<String>,
+    model: Option<String>,
+    api_key: Option<String>,
+    tools: Option<Vec<String>>,
+    system_prompt: Option<String>,
+    append_system_prompt: Option<String>,
+    /// A models.json to choose from instead of the user's.
+    models_path: Option<String>,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: impl Into<String>) {
+    let message = message.into().replace('\0', " ");
+    LAST_ERROR.with(|error| *error.borrow_mut() = CString::new(message).ok());
+}
+
+/// Runs `body`, turning an error or a panic into `fallback` and a `pi_last_error` message.
+fn guard<T>(fallback: T, body: impl FnOnce() -> Result<T, String>) -> T {
+    LAST_ERROR.with(|error| *error.borrow_mut() = None);
+    match panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(value)) => value,
+        Ok(Err(message)) => {
+            set_last_error(message);
+            fallback
+        }
+        Err(_) => {
+            set_last_error("pi panicked; the session should not be used further");
+            fallback
+        }
+    }
+}
+
+/// # Safety
+/// `text` must be null or a valid NUL-terminated string.
+unsafe fn read_str<'a>(text: *const c_char, name: &str) -> Result<&'a str, String> {
+    if text.is_null() {
+        return Err(format!("{name} is null"));
+    }
+    CStr::from_ptr(text)
+        .to_str()
+        .map_err(|_| format!("{name} is not valid UTF-8"))
+}
+
+/// # Safety
+/// `session` must be null or a pointer returned by `pi_session_new` and not yet freed.
+unsafe fn session_mut<'a>(session: *mut PiSession) -> Result<&'a mut PiSession, String> {
+    session
+        .as_mut()
+        .ok_or_else(|| "session is null".to_string())
+}
+
+/// Borrows only the session's client, so the abort token stays reachable while it runs.
+///
+/// # Safety
+/// As for `session_mut`.
+unsafe fn session_client<'a>(session: *mut PiSession) -> Result<&'a mut PiClient, String> {
+    if session.is_null() {
+        return Err("session is null".to_string());
+    }
+    Ok(&mut *ptr::addr_of_mut!((*session).client))
+}
+
+/// Borrows only the session's abort token, which a running prompt does not hold.
+///
+/// # Safety
+/// As for `session_mut`.
+unsafe fn session_abort<'a>(session: *mut PiSession) -> Result<&'a CancellationToken, String> {
+    if session.is_null() {
+        return Err("session is null".to_string());
+    }
+    Ok(&*ptr::addr_of!((*session).abort))
+}
+
+fn into_c_string(text: String) -> *mut c_char {
+    CString::new(text.replace('\0', " "))
+        .map(CString::into_raw)
+        .unwrap_or(ptr::null_mut())
+}
+
+fn build_session(config: SessionConfig) -> Result<PiSession, String> {
+    let mut builder = PiClient::builder();
+    if let Some(path) = config.models_path {
+        let auth = AuthStorage::new(config::get_auth_path());
+        builder = builder.model_registry(ModelRegistry::new(auth, PathBuf::from(path)));
+    }
+    if let Some(provider) = config.provider {
+        builder = builder.provider(provider);
+    }
+    if let Some(model) = config.model {
+        builder = builder.model(model);
+    }
+    if let Some(api_key) = config.api_key {
+        builder = builder.api_key(api_key);
+    }
+    if let Some(tools) = config.tools {
+        builder = builder.tools(tools);
+    }
+    if let Some(prompt) = config.system_prompt {
+        builder = builder.system_prompt(prompt);
+    }
+    if let Some(prompt) = config.append_system_prompt {
+        builder = builder.append_system_prompt(prompt);
+    }
+
+    let callback: Rc<RefCell<Option<EventCallback>>> = Rc::default();
+    let registered = callback.clone();
+    let client = builder.build()?;
+    let _ = client.session().subscribe(move |event| {
+        let Some(EventCallback {
+            callback,
+            user_data,
+        }) = *registered.borrow()
+        else {
+            return;
+        };
+        let Some(value) = serialize_session_event(event) else {
+            return;
+        };
+        if let Ok(text) = CString::new(value.to_string()) {
+            callback(text.as_ptr(), user_data);
+        }
+    });
+    let abort = client.session().agent.abort_flag();
+    Ok(PiSession {
+        client,
+        callback,
+        abort,
+    })
+}
+
+fn prompt_result_json(result: &PromptResult) -> String {
+    json!({
+        "text": result.text,
+        "stopReason": result.stop_reason,
+        "usage": result.usage,
+        "messages": result.messages.iter().map(serialize_agent_message).collect::<Vec<_>>(),
+    })
+    .to_string()
+}
+
+#[no_mangle]
+pub extern "C" fn pi_ffi_version() -> u32 {
+    PI_FFI_VERSION
+}
+
+/// The message of the last failed call on this thread, or null. Valid until the next call.
+#[no_mangle]
+pub extern "C" fn pi_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| {
+        error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Creates a session from a JSON config (null for defaults). Null on failure.
+///
+/// # Safety
+/// `config_json` must be null or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn pi_session_new(config_json: *const c_char) -> *mut PiSession {
+    guard(ptr::null_mut(), || {
+        let config = if config_json.is_null() {
+            SessionConfig::default()
+        } else {
+            let text = read_str(config_json, "config_json")?;
+            serde_json::from_str(text).map_err(|err| format!("Invalid session config: {err}"))?
+        };
+        Ok(Box::into_raw(Box::new(build_session(config)?)))
+    })
+}
+
+/// Sends a prompt and runs the agent until it stops. Returns a JSON object with `text`,
+/// `stopReason`, `usage` and `messages`, or null when the run failed.
+///
+/// # Safety
+/// `session` must come from `pi_session_new`; `text` must be a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn pi_session_prompt(
+    session: *mut PiSession,
+    text: *const c_char,
+) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        let client = session_client(session)?;
+        let text = read_str(text, "text")?;
+        let result = client.prompt(text)?;
+        Ok(into_c_string(prompt_result_json(&result)))
+    })
+}
+
+/// Registers the callback events are sent to, replacing any earlier one; null removes it.
+/// Returns 0, or -1 when `session` is null.
+///
+/// # Safety
+/// `session` must come from `pi_session_new`. `user_data` is passed through untouched and
+/// must stay valid while the callback is registered.
+#[no_mangle]
+pub unsafe extern "C" fn pi_session_set_event_callback(
+    session: *mut PiSession,
+    callback: Option<PiEventCallback>,
+    user_data: *mut c_void,
+) -> c_int {
+    guard(-1, || {
+        let session = session_mut(session)?;
+        *session.callback.borrow_mut() = callback.map(|callback| EventCallback {
+            callback,
+            user_data,
+        });
+        Ok(0)
+    })
+}
+
+/// The conversation so far as a JSON array of messages.
+///
+/// # Safety
+/// `session` must come from `pi_session_new`.
+#[no_mangle]
+pub unsafe extern "C" fn pi_session_messages(session: *mut PiSession) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        let session = session_mut(session)?;
+        let messages = session
+            .client
+            .messages()
+            .iter()
+            .map(serialize_agent_message)
+            .collect::<Vec<_>>();
+        Ok(into_c_string(serde_json::Value::from(messages).to_string()))
+    })
+}
+
+/// Stops the running prompt: the provider stream stops reading and a running bash command
+/// is killed. Unlike the other calls, this one may be made from an event callback.
+///
+/// # Safety
+/// `session` must come from `pi_session_new`.
+#[no_mangle]
+pub unsafe extern "C" fn pi_session_abort(session: *mut PiSession) -> c_int {
+    guard(-1, || {
+        session_abort(session)?.cancel();
+        Ok(0)
+    })
+}
+
+/// # Safety
+/// `session` must be null or come from `pi_session_new`, and is invalid afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn pi_session_free(session: *mut PiSession) {
+    if !session.is_null() {
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| drop(Box::from_raw(session))));
+    }
+}
+
+/// # Safety
+/// `text` must be null or a string returned by pi, and is invalid afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn pi_string_free(text: *mut c_char) {
+    if !text.is_null() {
+        drop(CString::from_raw(text));
+    }
+}
@@ -5,8 +5,12 @@ pub mod cli;
 pub mod coding_agent;
 pub mod config;
 pub mod core;
+pub mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 pub mod modes;
 pub mod rpc;
+pub mod sdk;
 pub mod test_port;
 pub mod tools;
 pub mod tui;
@@ -15,3 +19,4 @@ pub use cli::args::*;
 pub use core::compaction::*;
 pub use core::messages::*;
 pub use core::session_manager::*;
+pub use error::{Error, Result};
@@ -1,20 +1,54 @@
-use pi::cli::file_inputs::build_file_inputs;
+use pi::agent::{load_fixture_responses, CancellationToken};
+use pi::cli::auth_command::run_auth_command;
+use pi::cli::batch::{run_batch_command, run_batch_prompts};
+use pi::cli::blame::print_blame;
+use pi::cli::capabilities::{format_skill_list, format_tool_list};
+use pi::cli::compare::{compare_context, render_columns, render_markdown, run_compare};
+use pi::cli::completions::generate_completions;
+use pi::cli::config_command::run_config_command;
+use pi::cli::exit_codes::PrintError;
+use pi::cli::extensions_command::run_extensions_command;
+use pi::cli::file_inputs::{build_file_inputs, read_stdin_input, STDIN_MAX_BYTES};
 use pi::cli::list_models::list_models;
+use pi::cli::models_command::run_models_command;
+use pi::cli::replay::{create_replay_session, load_session_replay};
+use pi::cli::replay_turn::{load_replay_turn, print_replay_diff, print_replay_dry_run};
 use pi::cli::runtime::{
-    attach_extensions_with_host, build_model_registry, build_session_manager,
-    collect_extension_tools, collect_unsupported_flags, discover_system_prompt_file,
-    extension_flag_values_to_json, preload_extensions, print_help, select_model,
-    select_resume_session,
+    apply_persona_to_args, apply_settings_to_args, build_environment_snapshot,
+    build_model_registry, build_sandbox_policy, build_session_manager, collect_extension_tools,
+    collect_unsupported_flags, discover_system_prompt_file, extension_flag_values_to_json,
+    load_cli_persona, preload_extensions, print_help, resolve_prompt_overrides,
+    select_fallback_models, select_model, select_resume_session,
+};
+use pi::cli::session::{
+    api_supports_seed, build_agent_tools, complete_llm_context, create_cli_session,
+    create_rpc_session, install_request_logger, load_context_dir, prepare_session,
+    rpc_session_factory, RPC_MODEL_APIS,
+};
+use pi::cli::sessions::list_sessions;
+use pi::cli::stats::print_stats;
+use pi::cli::tool_run::run_tool_command;
+use pi::coding_agent::mirror::{SessionMirror, DEFAULT_MIRROR_PORT};
+use pi::coding_agent::models_config::SUPPORTED_APIS;
+use pi::coding_agent::{
+    build_system_prompt, discover_extension_paths, export_from_file_with_options, load_skills,
+    open_browser, resolve_model_scope, AgentSession, AuthStorage, BashApproval,
+    BuildSystemPromptOptions, ContextPlacement, EditReview, ExportOptions, ExtensionPermissions,
+    LoadSkillsOptions, SettingsManager, SharedChangeJournal,
 };
-use pi::cli::session::{apply_cli_thinking_level, create_cli_session, create_rpc_session};
-use pi::coding_agent::{build_system_prompt, export_from_file, BuildSystemPromptOptions};
 use pi::config;
 use pi::modes::{run_interactive_mode_session, run_print_mode_session};
-use pi::rpc::run_rpc_mode;
+use pi::rpc::server::generate_token;
+use pi::rpc::{
+    run_acp_mode, run_jsonrpc_mode, run_rpc_mode_with_sessions, run_serve_mode, RpcModeOptions,
+    RpcServer, DEFAULT_SERVE_PORT,
+};
 use pi::{parse_args, ListModels, Mode};
 use std::env;
+use std::io::{self, IsTerminal};
 use std::path::{Path, PathBuf};
 use std::process;
+use std::time::Duration;
 
 fn main() {
     let args: Vec<String> = env::args().skip(1).collect();
@@ -30,7 +64,7 @@ fn main() {
 
     let (mut preloaded_extension, extension_flag_types) = preload_extensions(&first_pass, &cwd);
 
-    let parsed = parse_args(&args, Some(&extension_flag_types));
+    let mut parsed = parse_args(&args, Some(&extension_flag_types));
     if let Some(preloaded) = preloaded_extension.as_ref() {
         let flag_values = extension_flag_values_to_json(&parsed.extension_flags);
         if let Err(err) = preloaded.host.borrow_mut().set_flag_values(&flag_values) {
@@ -48,8 +82,94 @@ fn main() {
         return;
     }
 
+    if let Some(shell) = &parsed.completions {
+        match generate_completions(shell) {
+            Ok(script) => print!("{script}"),
+            Err(message) => {
+                eprintln!("{message}");
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if parsed.list_tools {
+        let extension_tools = preloaded_extension
+            .as_ref()
+            .map(|preloaded| collect_extension_tools(&preloaded.manifest))
+            .unwrap_or_default();
+        print!("{}", format_tool_list(&extension_tools));
+        return;
+    }
+
+    if parsed.list_skills {
+        let mut options = LoadSkillsOptions::new();
+        options.cwd = Some(cwd.clone());
+        options.include_skills = parsed.skills.clone().unwrap_or_default();
+        print!("{}", format_skill_list(&load_skills(options).skills));
+        return;
+    }
+
+    if let Some(auth_command) = &parsed.auth {
+        let mut storage = AuthStorage::new(config::get_auth_path());
+        storage.set_active_profile(parsed.auth_profile.as_deref());
+        if let Err(message) = run_auth_command(auth_command, &mut storage) {
+            eprintln!("Error: {message}");
+            process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(config_command) = &parsed.config {
+        let mut settings = SettingsManager::create(cwd.to_string_lossy(), "");
+        if let Err(message) = run_config_command(config_command, &mut settings) {
+            eprintln!("Error: {message}");
+            process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(models_command) = &parsed.models_command {
+        let result =
+            build_model_registry(None, None, parsed.auth_profile.as_deref()).and_then(|registry| {
+                run_models_command(models_command, &config::get_models_path(), &registry)
+            });
+        if let Err(message) = result {
+            eprintln!("Error: {message}");
+            process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(extensions_command) = &parsed.extensions_command {
+        let settings = SettingsManager::create(cwd.to_string_lossy(), "");
+        let discovered = discover_extension_paths(
+            &settings.get_extension_paths(),
+            &cwd,
+            &config::get_agent_dir(),
+        );
+        let mut permissions = ExtensionPermissions::load(config::get_extension_permissions_path());
+        if let Err(message) =
+            run_extensions_command(extensions_command, &discovered, &mut permissions)
+        {
+            eprintln!("Error: {message}");
+            process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(batch_command) = &parsed.batch_command {
+        let api_key = parsed.api_key.as_deref();
+        if let Err(message) = run_batch_command(batch_command, &config::get_batches_dir(), api_key)
+        {
+            eprintln!("Error: {message}");
+            process::exit(1);
+        }
+        return;
+    }
+
     if let Some(list_models_mode) = &parsed.list_models {
-        let registry = match build_model_registry(None, None) {
+        let registry = match build_model_registry(None, None, parsed.auth_profile.as_deref()) {
             Ok(registry) => registry,
             Err(message) => {
                 eprintln!("Error: {message}");
@@ -64,13 +184,67 @@ fn main() {
         return;
     }
 
+    if let Some(sessions_command) = &parsed.sessions {
+        list_sessions(sessions_command, &cwd, parsed.session_dir.as_deref());
+        return;
+    }
+
+    if let Some(stats) = &parsed.stats {
+        let sessions_dir = parsed
+            .session_dir
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| config::get_agent_dir().join("sessions"));
+        if let Err(message) = print_stats(&sessions_dir, stats.json) {
+            eprintln!("Error: {message}");
+            process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(path) = &parsed.blame {
+        if let Err(message) = print_blame(Path::new(path), &cwd, parsed.session_dir.as_deref()) {
+            eprintln!("Error: {message}");
+            process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(tool_run) = &parsed.tool_run {
+        match run_tool_command(tool_run, &cwd) {
+            Ok(output) => {
+                println!("{output}");
+                return;
+            }
+            Err(err) => {
+                println!(
+                    "{}",
+                    serde_json::json!({ "tool": tool_run.tool, "error": err.to_string() })
+                );
+                process::exit(1);
+            }
+        }
+    }
+
     if let Some(export_path) = &parsed.export {
         let output_path = parsed.messages.first().map(PathBuf::from);
-        match export_from_file(Path::new(export_path), output_path) {
-            Ok(path) => {
+        let options = ExportOptions {
+            new_only: parsed.export_new_only,
+            custom_messages: parsed.export_custom_messages.unwrap_or_else(|| {
+                SettingsManager::create(cwd.to_string_lossy(), "").get_export_custom_messages()
+            }),
+            state_path: Some(config::get_export_state_path()),
+            theme: parsed.export_theme.clone(),
+        };
+        match export_from_file_with_options(Path::new(export_path), output_path, &options) {
+            Ok(Some(path)) => {
                 println!("Exported to: {}", path.display());
                 return;
             }
+            Ok(None) => {
+                println!("No new entries since the last export.");
+                return;
+            }
             Err(message) => {
                 eprintln!("Error: {message}");
                 process::exit(1);
@@ -78,6 +252,24 @@ fn main() {
         }
     }
 
+    let persona = match parsed.persona.as_deref() {
+        Some(name) => match load_cli_persona(name, &cwd) {
+            Ok(persona) => Some(persona),
+            Err(message) => {
+                eprintln!("Error: {message}");
+                process::exit(1);
+            }
+        },
+        None => None,
+    };
+    if let Some(persona) = &persona {
+        apply_persona_to_args(&mut parsed, persona);
+    }
+    // Precedence: CLI flags, then the persona, then project settings, then global settings.
+    let settings_manager = SettingsManager::create(cwd.to_string_lossy(), "");
+    apply_settings_to_args(&mut parsed, &settings_manager);
+    install_request_logger(&parsed, &settings_manager);
+
     let unsupported = collect_unsupported_flags(&parsed);
     if !unsupported.is_empty() {
         eprintln!(
@@ -88,15 +280,38 @@ fn main() {
     }
     let is_interactive = !parsed.print && parsed.mode.is_none();
 
-    let mode = parsed.mode.clone().unwrap_or(Mode::Text);
+    let mode = if parsed.serve {
+        Mode::Rpc
+    } else {
+        parsed.mode.clone().unwrap_or(Mode::Text)
+    };
+
+    if let Some(target) = &parsed.replay {
+        let result = run_replay(&parsed, target, &cwd).and_then(|(mut session, prompts)| {
+            if is_interactive {
+                run_interactive_mode_session(&mut session, &prompts, None, &[], parsed.ui)
+                    .map_err(PrintError::from)
+            } else {
+                run_print_mode_session(mode.clone(), &mut session, &prompts, None, &[])
+            }
+        });
+        if let Err(error) = result {
+            exit_with_error(&mode, error);
+        }
+        return;
+    }
 
     let provider = parsed.provider.as_deref().unwrap_or("anthropic");
     let supported_providers = [
         "anthropic",
         "openai",
         "openai-codex",
+        "google",
         "google-gemini-cli",
         "google-antigravity",
+        "amazon-bedrock",
+        "xai",
+        "mistral",
     ];
     if !supported_providers.contains(&provider) {
         eprintln!(
@@ -106,33 +321,30 @@ fn main() {
         process::exit(1);
     }
 
-    let registry = match build_model_registry(parsed.api_key.as_deref(), Some(provider)) {
+    let registry = match build_model_registry(
+        parsed.api_key.as_deref(),
+        Some(provider),
+        parsed.auth_profile.as_deref(),
+    ) {
         Ok(registry) => registry,
-        Err(message) => {
-            eprintln!("Error: {message}");
-            process::exit(1);
-        }
+        Err(message) => exit_with_error(&mode, message.into()),
     };
 
-    let model = match select_model(&parsed, &registry) {
+    let model = match select_model(&parsed, &registry, &settings_manager) {
         Ok(model) => model,
-        Err(message) => {
-            eprintln!("Error: {message}");
-            process::exit(1);
-        }
+        Err(message) => exit_with_error(&mode, message.into()),
     };
 
-    let supported_apis = [
-        "anthropic-messages",
-        "openai-responses",
-        "openai-codex-responses",
-        "google-gemini-cli",
-    ];
-    if !supported_apis.contains(&model.api.as_str()) {
+    let fallback_models = select_fallback_models(&parsed, &registry, &model)
+        .into_iter()
+        .filter(|fallback| SUPPORTED_APIS.contains(&fallback.api.as_str()))
+        .collect::<Vec<_>>();
+
+    if !SUPPORTED_APIS.contains(&model.api.as_str()) {
         eprintln!(
             "Error: unsupported model API \"{}\". Supported APIs: {}",
             model.api,
-            supported_apis.join(", ")
+            SUPPORTED_APIS.join(", ")
         );
         process::exit(1);
     }
@@ -150,6 +362,7 @@ fn main() {
     let extension_host = preloaded_extension
         .as_ref()
         .map(|preloaded| preloaded.host.clone());
+    let sandbox = build_sandbox_policy(parsed.dangerously_allow_all, &cwd);
     let mut selected_tools = parsed
         .tools
         .clone()
@@ -159,50 +372,234 @@ fn main() {
             selected_tools.push(tool.name.clone());
         }
     }
-    let system_prompt = build_system_prompt(BuildSystemPromptOptions {
+    let prompt_overrides =
+        resolve_prompt_overrides(preloaded_extension.as_ref(), &settings_manager);
+    let mut system_prompt = build_system_prompt(BuildSystemPromptOptions {
         custom_prompt: system_prompt_source,
         append_system_prompt: parsed.append_system_prompt.clone(),
         selected_tools: Some(selected_tools.clone()),
         skills_enabled: !parsed.no_skills,
         skills_include: skill_patterns,
+        context_files: parsed.no_project_context.then(Vec::new),
         cwd: Some(cwd.clone()),
         agent_dir: Some(config::get_agent_dir()),
+        environment: build_environment_snapshot(&cwd),
+        overrides: prompt_overrides.clone(),
         ..Default::default()
     });
-    let session_manager = if parsed.resume {
-        match select_resume_session(&cwd, parsed.session_dir.as_deref()) {
-            Ok(Some(path)) => pi::core::session_manager::SessionManager::open(path, None),
-            Ok(None) => return,
+    // In print mode, piped stdin (or stdin after `-`) goes ahead of the first message.
+    let stdin_prefix = if !is_interactive
+        && !matches!(mode, Mode::Rpc | Mode::JsonRpc | Mode::Acp)
+        && (parsed.stdin || (!parsed.no_stdin && !io::stdin().is_terminal()))
+    {
+        match read_stdin_input(io::stdin().lock(), STDIN_MAX_BYTES) {
+            Ok(text) => text.unwrap_or_default(),
+            Err(message) => {
+                eprintln!("{message}");
+                process::exit(1);
+            }
+        }
+    } else {
+        String::new()
+    };
+    // --context-dir files go ahead of the first prompt, or into the system prompt.
+    let mut context_prefix = String::new();
+    match load_context_dir(&parsed, &settings_manager, &cwd) {
+        Ok(Some((context, placement))) => {
+            eprintln!("{}", context.report());
+            let has_first_message = !parsed.messages.is_empty()
+                || !parsed.file_args.is_empty()
+                || !stdin_prefix.is_empty();
+            if placement == ContextPlacement::FirstMessage && has_first_message {
+                context_prefix = context.text;
+            } else if !context.text.is_empty() {
+                system_prompt.push_str("\n\n# Preloaded Files\n\n");
+                system_prompt.push_str(&context.text);
+            }
+        }
+        Ok(None) => {}
+        Err(message) => {
+            eprintln!("Error: {message}");
+            process::exit(1);
+        }
+    }
+    context_prefix.push_str(&stdin_prefix);
+    if let Some(replay) = &parsed.replay_turn {
+        let (Some(session_path), Some(entry)) = (&parsed.session, &replay.entry) else {
+            eprintln!(
+                "Usage: pi replay-turn --session <file> --entry <id> [--model <model>] [--dry-run]"
+            );
+            process::exit(1);
+        };
+        let turn = match load_replay_turn(Path::new(session_path), entry, system_prompt) {
+            Ok(turn) => turn,
             Err(message) => {
                 eprintln!("Error: {message}");
                 process::exit(1);
             }
+        };
+        if replay.dry_run {
+            print_replay_dry_run(&turn);
+            return;
+        }
+        match complete_llm_context(
+            &model,
+            Some(selected_tools.as_slice()),
+            &extension_tools,
+            parsed.api_key.as_deref(),
+            &turn.context,
+            "replay-turn",
+        ) {
+            Ok(response) => print_replay_diff(&turn, &response),
+            Err(message) => {
+                eprintln!("Error: {message}");
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Some(compare) = &parsed.compare {
+        let patterns = parsed.models.clone().unwrap_or_default();
+        let prompt = parsed.messages.join("\n\n");
+        if patterns.is_empty() || prompt.trim().is_empty() {
+            eprintln!("Usage: pi compare --models <a,b,...> \"prompt\" [--output report.md]");
+            process::exit(1);
+        }
+        let models = resolve_model_scope(&patterns, &registry.get_available())
+            .into_iter()
+            .map(|scoped| scoped.model)
+            .collect::<Vec<_>>();
+        let context = compare_context(system_prompt, &prompt);
+        let results = run_compare(&models, |model| {
+            if !SUPPORTED_APIS.contains(&model.api.as_str()) {
+                return Err(format!("unsupported model API \"{}\"", model.api));
+            }
+            // --api-key belongs to --provider, so other providers use their own credentials.
+            let api_key = parsed
+                .api_key
+                .as_deref()
+                .filter(|_| model.provider == provider);
+            complete_llm_context(
+                model,
+                Some(selected_tools.as_slice()),
+                &extension_tools,
+                api_key,
+                &context,
+                "compare",
+            )
+        });
+        let width = crossterm::terminal::size()
+            .map(|(width, _)| width.max(1) as usize)
+            .unwrap_or(100);
+        for line in render_columns(&results, width) {
+            println!("{line}");
+        }
+        if let Some(path) = &compare.output {
+            if let Err(err) = std::fs::write(path, render_markdown(&prompt, &results)) {
+                eprintln!("Error: Failed to write {path}: {err}");
+                process::exit(1);
+            }
+            println!("\nWrote {path}");
+        }
+        return;
+    }
+
+    if parsed.batch {
+        if is_interactive || matches!(mode, Mode::Rpc | Mode::JsonRpc | Mode::Acp) {
+            eprintln!("Error: --batch requires --print.");
+            process::exit(1);
+        }
+        // Every prompt is a separate request, so @file text is included in each of them.
+        let prefix = match build_file_inputs(&parsed.file_args) {
+            Ok(inputs) if !inputs.images.is_empty() => {
+                eprintln!("Error: images are not supported with --batch.");
+                process::exit(1);
+            }
+            Ok(inputs) => format!("{context_prefix}{}", inputs.text_prefix),
+            Err(message) => {
+                eprintln!("{message}");
+                process::exit(1);
+            }
+        };
+        let prompts = parsed
+            .messages
+            .iter()
+            .map(|message| format!("{prefix}{message}"))
+            .collect::<Vec<_>>();
+        if let Err(message) = run_batch_prompts(
+            &model,
+            &system_prompt,
+            &prompts,
+            parsed.batch_output.as_deref(),
+            parsed.api_key.as_deref(),
+            &config::get_batches_dir(),
+        ) {
+            eprintln!("Error: {message}");
+            process::exit(1);
+        }
+        return;
+    }
+
+    let session_manager = if parsed.resume {
+        match select_resume_session(&cwd, parsed.session_dir.as_deref()) {
+            Ok(Some(path)) => pi::core::session_manager::SessionManager::try_open(path, None),
+            Ok(None) => return,
+            Err(message) => Err(message),
         }
     } else {
         build_session_manager(&parsed, &cwd)
     };
+    let session_manager = match session_manager {
+        Ok(session_manager) => session_manager,
+        Err(message) => {
+            eprintln!("Error: {message}");
+            process::exit(1);
+        }
+    };
+
+    if parsed.seed.is_some() && !api_supports_seed(&model.api) {
+        eprintln!(
+            "Warning: --seed is not supported for \"{}\" models and will be ignored.",
+            model.api
+        );
+    }
 
-    if matches!(mode, Mode::Rpc) {
+    if matches!(mode, Mode::Rpc | Mode::JsonRpc | Mode::Acp) {
         if !parsed.file_args.is_empty() {
             eprintln!("Error: @file arguments are not supported in RPC mode.");
             process::exit(1);
         }
-        if model.api != "anthropic-messages" && model.api != "openai-responses" {
+        if !RPC_MODEL_APIS.contains(&model.api.as_str()) {
             eprintln!(
-                "Error: RPC mode currently supports only \"anthropic-messages\" and \"openai-responses\" models."
+                "Error: RPC mode supports only {} models, not {}",
+                RPC_MODEL_APIS.join(", "),
+                model.api
             );
             process::exit(1);
         }
+        let factory = rpc_session_factory(
+            &parsed,
+            &cwd,
+            system_prompt.clone(),
+            selected_tools.clone(),
+            sandbox.clone(),
+            persona.clone(),
+        );
         let mut session = match create_rpc_session(
             model,
+            &fallback_models,
             registry,
             Some(system_prompt),
             None,
             Some(selected_tools.as_slice()),
             &extension_tools,
+            &prompt_overrides.tool_descriptions,
             extension_host.clone(),
             parsed.api_key.as_deref(),
+            parsed.seed,
             session_manager,
+            &sandbox,
         ) {
             Ok(session) => session,
             Err(message) => {
@@ -210,12 +607,42 @@ fn main() {
                 process::exit(1);
             }
         };
-        if let Some(paths) = parsed.extensions.as_deref() {
-            session.settings_manager.set_extension_paths(paths.to_vec());
+        if let Err(message) = prepare_session(
+            &mut session,
+            &parsed,
+            persona.as_ref(),
+            &cwd,
+            preloaded_extension.take(),
+        ) {
+            eprintln!("Error: {message}");
+            process::exit(1);
         }
-        apply_cli_thinking_level(&parsed, &mut session);
-        attach_extensions_with_host(&mut session, &cwd, preloaded_extension.take());
-        if let Err(message) = run_rpc_mode(session) {
+        let options = RpcModeOptions {
+            idle_exit: parsed.idle_exit.map(Duration::from_secs),
+            parent_pid: parsed.parent_pid,
+        };
+        let result = if parsed.serve {
+            let configured = env::var("PI_SERVE_TOKEN")
+                .ok()
+                .filter(|token| !token.trim().is_empty());
+            let token = configured.clone().unwrap_or_else(generate_token);
+            let port = parsed.port.unwrap_or(DEFAULT_SERVE_PORT);
+            RpcServer::bind(([127, 0, 0, 1], port), token.clone()).and_then(|server| {
+                let addr = server.local_addr()?;
+                eprintln!("Serving RPC on ws://{addr}/rpc (events: http://{addr}/events)");
+                if configured.is_none() {
+                    eprintln!("Token: {token}");
+                }
+                run_serve_mode(session, options, server, Some(factory))
+            })
+        } else if matches!(mode, Mode::Acp) {
+            run_acp_mode(session, Some(factory))
+        } else if matches!(mode, Mode::JsonRpc) {
+            run_jsonrpc_mode(session, options, Some(factory))
+        } else {
+            run_rpc_mode_with_sessions(session, options, Some(factory))
+        };
+        if let Err(message) = result {
             eprintln!("Error: {message}");
             process::exit(1);
         }
@@ -225,6 +652,7 @@ fn main() {
     let mut messages = parsed.messages.clone();
     let mut initial_message = None;
     let mut initial_images = Vec::new();
+    let mut prefix = context_prefix;
     if !parsed.file_args.is_empty() {
         let inputs = match build_file_inputs(&parsed.file_args) {
             Ok(inputs) => inputs,
@@ -233,47 +661,61 @@ fn main() {
                 process::exit(1);
             }
         };
-        if !inputs.text_prefix.is_empty() || !inputs.images.is_empty() {
-            initial_message = if messages.is_empty() {
-                Some(inputs.text_prefix)
-            } else {
-                let first = messages.remove(0);
-                Some(format!("{}{}", inputs.text_prefix, first))
-            };
-            if !inputs.images.is_empty() {
-                initial_images = inputs.images;
-            }
-        }
+        prefix.push_str(&inputs.text_prefix);
+        initial_images = inputs.images;
+    }
+    if !prefix.is_empty() || !initial_images.is_empty() {
+        initial_message = if messages.is_empty() {
+            Some(prefix)
+        } else {
+            let first = messages.remove(0);
+            Some(format!("{prefix}{first}"))
+        };
     }
 
     let mut session = match create_cli_session(
         model,
+        &fallback_models,
         registry,
         Some(system_prompt),
         None,
         Some(selected_tools.as_slice()),
         &extension_tools,
+        &prompt_overrides.tool_descriptions,
         extension_host.clone(),
         parsed.api_key.as_deref(),
+        parsed.seed,
         session_manager,
+        &sandbox,
     ) {
         Ok(session) => session,
-        Err(message) => {
-            eprintln!("Error: {message}");
-            process::exit(1);
-        }
+        Err(message) => exit_with_error(&mode, message.into()),
     };
-    if let Some(paths) = parsed.extensions.as_deref() {
-        session.settings_manager.set_extension_paths(paths.to_vec());
+    if let Err(message) = prepare_session(
+        &mut session,
+        &parsed,
+        persona.as_ref(),
+        &cwd,
+        preloaded_extension.take(),
+    ) {
+        exit_with_error(&mode, message.into());
+    }
+    if parsed.mirror {
+        start_mirror(&mut session, &parsed, &cwd);
     }
-    apply_cli_thinking_level(&parsed, &mut session);
-    attach_extensions_with_host(&mut session, &cwd, preloaded_extension.take());
 
     let result = if is_interactive {
-        run_interactive_mode_session(&mut session, &messages, initial_message, &initial_images)
+        run_interactive_mode_session(
+            &mut session,
+            &messages,
+            initial_message,
+            &initial_images,
+            parsed.ui,
+        )
+        .map_err(PrintError::from)
     } else {
         run_print_mode_session(
-            mode,
+            mode.clone(),
             &mut session,
             &messages,
             initial_message,
@@ -281,8 +723,77 @@ fn main() {
         )
     };
 
-    if let Err(message) = result {
-        eprintln!("Error: {message}");
-        process::exit(1);
+    if let Err(error) = result {
+        exit_with_error(&mode, error);
+    }
+}
+
+/// Serves the session for `--mirror` and opens it in the browser. Failing to start is only
+/// a warning; the session itself works the same without the mirror.
+fn start_mirror(session: &mut AgentSession, parsed: &pi::Args, cwd: &Path) {
+    if session.session_manager.get_session_file().is_none() {
+        eprintln!("Warning: --mirror needs a saved session; ignoring it with --no-session");
+        return;
+    }
+    let options = ExportOptions {
+        custom_messages: SettingsManager::create(cwd.to_string_lossy(), "")
+            .get_export_custom_messages(),
+        theme: parsed.export_theme.clone(),
+        ..ExportOptions::default()
+    };
+    let port = parsed.port.unwrap_or(DEFAULT_MIRROR_PORT);
+    match SessionMirror::start(([127, 0, 0, 1], port), generate_token(), options) {
+        Ok(mirror) => {
+            let url = mirror.url();
+            eprintln!("Mirroring the session at {url}");
+            open_browser(&url);
+            session.set_mirror(mirror);
+        }
+        Err(message) => eprintln!("Warning: {message}"),
+    }
+}
+
+/// A session answering from a recorded session file, or from a fixtures directory with the
+/// prompts given on the command line, and the prompts to send it.
+fn run_replay(
+    parsed: &pi::Args,
+    target: &str,
+    cwd: &Path,
+) -> Result<(AgentSession, Vec<String>), PrintError> {
+    let path = cwd.join(target);
+    if !path.is_dir() {
+        let replay = load_session_replay(&path)?;
+        let session = create_replay_session(replay.tools, replay.responses);
+        return Ok((session, replay.prompts));
+    }
+    if parsed.messages.is_empty() {
+        return Err("Usage: pi replay <fixtures-dir> \"prompt\""
+            .to_string()
+            .into());
+    }
+    let responses = load_fixture_responses(&path)?;
+    let tools = build_agent_tools(
+        &cwd.to_path_buf(),
+        parsed.tools.as_deref(),
+        &[],
+        None,
+        &SharedChangeJournal::default(),
+        &build_sandbox_policy(parsed.dangerously_allow_all, cwd),
+        &BashApproval::default(),
+        &EditReview::default(),
+        &CancellationToken::default(),
+    )?;
+    Ok((
+        create_replay_session(tools, responses),
+        parsed.messages.clone(),
+    ))
+}
+
+/// Exits with the code for the failure; `--mode json` also writes it as an error object.
+fn exit_with_error(mode: &Mode, error: PrintError) -> ! {
+    if matches!(mode, Mode::Json) {
+        println!("{}", error.to_json());
     }
+    eprintln!("Error: {}", error.message);
+    process::exit(error.reason.code());
 }
@@ -0,0 +1,669 @@
+//! Slash commands that only produce text, shared by the full-screen and line-based
+//! interactive modes.
+
+use crate::agent::{QueueKind, ThinkingLevel};
+use crate::cli::session::to_agent_model;
+use crate::coding_agent::{
+    find_context_pack, find_persona, load_context_packs, load_personas, parse_model_pattern,
+    template_arguments, AgentSession, LoadContextPacksOptions, LoadPersonasOptions,
+    Model as RegistryModel,
+};
+use crate::core::session_manager::{SessionEntry, SessionManager};
+use crate::tui::{AutocompleteItem, CombinedAutocompleteProvider, SlashCommand};
+use std::path::PathBuf;
+
+/// Built-in slash commands, as offered by autocompletion.
+pub fn builtin_slash_commands() -> Vec<SlashCommand> {
+    vec![
+        SlashCommand::new(
+            "branch",
+            Some("Continue from an earlier reply in a new branch".to_string()),
+        ),
+        SlashCommand::new("changelog", Some("Show version changelog".to_string())),
+        SlashCommand::new(
+            "checkpoint",
+            Some("Name this point to rewind to later".to_string()),
+        ),
+        SlashCommand::new("clear", Some("Clear the screen".to_string())),
+        SlashCommand::new("compact", Some("Compact the session".to_string())),
+        SlashCommand::new("context", Some("List or toggle context packs".to_string())),
+        SlashCommand::new("copy", Some("Copy last message to clipboard".to_string())),
+        SlashCommand::new("exit", Some("Exit the session".to_string())),
+        SlashCommand::new("export", Some("Export session as HTML".to_string())),
+        SlashCommand::new("help", Some("Show available commands".to_string())),
+        SlashCommand::new("hotkeys", Some("Show keyboard shortcuts".to_string())),
+        SlashCommand::new("login", Some("Login to OAuth provider".to_string())),
+        SlashCommand::new("logout", Some("Logout from OAuth provider".to_string())),
+        SlashCommand::new("model", Some("Select AI model".to_string())),
+        SlashCommand::new("new", Some("Start new session".to_string())),
+        SlashCommand::new("persona", Some("List or switch personas".to_string())),
+        SlashCommand::new("pin", Some("Keep a message through compaction".to_string())),
+        SlashCommand::new(
+            "queue",
+            Some("Show, edit or clear queued messages".to_string()),
+        ),
+        SlashCommand::new("quit", Some("Exit the session".to_string())),
+        SlashCommand::new("reset", Some("Reset session".to_string())),
+        SlashCommand::new("resume", Some("Resume different session".to_string())),
+        SlashCommand::new(
+            "rewind",
+            Some("Return to a checkpoint, undoing file changes".to_string()),
+        ),
+        SlashCommand::new(
+            "revert",
+            Some("Restore a file changed by tools".to_string()),
+        ),
+        SlashCommand::new("session", Some("Show session info".to_string())),
+        SlashCommand::new("sessions", Some("List and resume sessions".to_string())),
+        SlashCommand::new("settings", Some("Configure settings".to_string())),
+        SlashCommand::new("share", Some("Share session as GitHub Gist".to_string())),
+        SlashCommand::new("show", Some("Open a message in the pager".to_string())),
+        SlashCommand::new("skill", Some("List skills or run one".to_string())),
+        SlashCommand::new(
+            "template",
+            Some("List prompt templates or run one".to_string()),
+        ),
+        SlashCommand::new("theme", Some("Change theme".to_string())),
+        SlashCommand::new("thinking", Some("Set or cycle thinking level".to_string())),
+        SlashCommand::new("tools", Some("List active tools".to_string())),
+        SlashCommand::new("tree", Some("Navigate session tree".to_string())),
+        SlashCommand::new("undo", Some("Undo the last file change".to_string())),
+        SlashCommand::new("unpin", Some("Unpin a pinned message".to_string())),
+    ]
+}
+
+/// Autocompletion for `session`: slash commands (built-in, prompt templates, extension
+/// commands), model ids after `/model`, session files after `/resume`, skill and template
+/// names after `/skill` and `/template`, and file paths.
+/// Shared by the TUI editor and the RPC `complete` command.
+pub fn session_autocomplete_provider(
+    session: &AgentSession,
+    cwd: PathBuf,
+) -> CombinedAutocompleteProvider {
+    let mut commands = builtin_slash_commands();
+    for template in session.prompt_templates() {
+        commands.push(SlashCommand::new(
+            template.name.clone(),
+            Some(template.description.clone()),
+        ));
+    }
+    for command in session.extension_commands() {
+        commands.push(SlashCommand::new(
+            command.name.clone(),
+            command.description.clone(),
+        ));
+    }
+
+    let models = session
+        .get_available_models()
+        .into_iter()
+        .map(|model| {
+            let value = format!("{}/{}", model.provider, model.id);
+            AutocompleteItem {
+                label: value.clone(),
+                value,
+                description: Some(model.name),
+            }
+        })
+        .collect();
+    let session_dir = Some(session.session_manager.get_session_dir());
+    let sessions = SessionManager::list(&cwd, session_dir)
+        .into_iter()
+        .map(|info| {
+            let value = info.path.display().to_string();
+            AutocompleteItem {
+                label: info.name.clone().unwrap_or_else(|| value.clone()),
+                value,
+                description: Some(info.preview.replace('\n', " ")),
+            }
+        })
+        .collect();
+
+    let skills = session
+        .skills()
+        .iter()
+        .map(|skill| AutocompleteItem {
+            label: skill.name.clone(),
+            value: skill.name.clone(),
+            description: Some(skill.description.clone()),
+        })
+        .collect();
+    let templates = session
+        .prompt_templates()
+        .iter()
+        .map(|template| AutocompleteItem {
+            label: template.name.clone(),
+            value: template.name.clone(),
+            description: Some(template.description.clone()),
+        })
+        .collect();
+
+    CombinedAutocompleteProvider::new(commands, cwd)
+        .with_argument_completions("model", models)
+        .with_argument_completions("resume", sessions)
+        .with_argument_completions("skill", skills)
+        .with_argument_completions("template", templates)
+}
+
+/// What the caller should do after a shared command ran.
+pub(crate) enum CommandOutcome {
+    /// Show a status message.
+    Message(String),
+    /// The conversation was replaced (reset or new session); drop the displayed history.
+    Cleared(String),
+    /// The conversation was rewritten (compaction); redraw it from the session.
+    Rebuilt(String),
+}
+
+/// Run `input` if it is a shell escape (`!cmd`, `!!cmd`) or a text-only slash command.
+/// Returns `None` for anything else, including bare `/model`, which each mode presents
+/// its own way.
+pub(crate) fn run_shared_command(
+    session: &mut AgentSession,
+    input: &str,
+) -> Option<CommandOutcome> {
+    let (command, rest) = match input.split_once(char::is_whitespace) {
+        Some((command, rest)) => (command, rest.trim()),
+        None => (input, ""),
+    };
+    if let Some(shell) = input.strip_prefix('!') {
+        let shell = shell.strip_prefix('!').unwrap_or(shell).trim();
+        if shell.is_empty() {
+            return None;
+        }
+        return Some(CommandOutcome::Message(run_bash(session, shell)));
+    }
+    let outcome = match command {
+        "/export" => {
+            let output_path = (!rest.is_empty()).then(|| PathBuf::from(rest));
+            CommandOutcome::Message(
+                match session.export_to_html_with_path(output_path.as_ref()) {
+                    Ok(result) => format!("Session exported to: {}", result.path.display()),
+                    Err(err) => format!("Failed to export session: {err}"),
+                },
+            )
+        }
+        "/compact" => {
+            if session.messages().len() < 2 {
+                return Some(CommandOutcome::Message(
+                    "Nothing to compact (no messages yet)".to_string(),
+                ));
+            }
+            let custom_instructions = (!rest.is_empty()).then_some(rest);
+            match session.compact_with_instructions(custom_instructions) {
+                Ok(result) => CommandOutcome::Rebuilt(format!(
+                    "Compaction complete (tokens before: {})",
+                    result.tokens_before
+                )),
+                Err(err) => CommandOutcome::Message(format!("Compaction failed: {err}")),
+            }
+        }
+        "/model" if !rest.is_empty() => CommandOutcome::Message(select_model(session, rest)),
+        "/thinking" => CommandOutcome::Message(set_thinking(session, rest)),
+        "/persona" => CommandOutcome::Message(switch_persona(session, rest)),
+        "/context" => CommandOutcome::Message(toggle_context_pack(session, rest)),
+        "/skill" if rest.is_empty() => CommandOutcome::Message(list_skills(session)),
+        "/template" if rest.is_empty() => CommandOutcome::Message(list_templates(session)),
+        "/pin" => CommandOutcome::Message(pin_message(session, rest, true)),
+        "/unpin" => CommandOutcome::Message(pin_message(session, rest, false)),
+        "/undo" if rest.is_empty() => CommandOutcome::Message(match session.undo_last_change() {
+            Ok(change) => format!("Undid {} of {}", change.tool_name, change.path.display()),
+            Err(err) => format!("Undo failed: {err}"),
+        }),
+        "/revert" => CommandOutcome::Message(revert_file(session, rest)),
+        "/queue" => CommandOutcome::Message(edit_queue(session, rest)),
+        "/checkpoint" => CommandOutcome::Message(create_checkpoint(session, rest)),
+        "/rewind" if rest.is_empty() => CommandOutcome::Message(list_checkpoints(session)),
+        "/rewind" => match session.rewind(rest) {
+            Ok(result) => {
+                let mut message = format!(
+                    "Rewound to {} ({} file change{} undone)",
+                    result.checkpoint.name,
+                    result.undone.len(),
+                    if result.undone.len() == 1 { "" } else { "s" }
+                );
+                if !result.files_restored {
+                    message.push_str(
+                        "\nFile changes made after the checkpoint are no longer recorded; \
+                         files were left as they are.",
+                    );
+                }
+                CommandOutcome::Rebuilt(message)
+            }
+            Err(err) => CommandOutcome::Message(format!("Rewind failed: {err}")),
+        },
+        "/resume" if !rest.is_empty() => {
+            let path = PathBuf::from(rest);
+            if !path.is_file() {
+                return Some(CommandOutcome::Message(format!(
+                    "Session file not found: {rest}"
+                )));
+            }
+            match session.switch_session(path) {
+                Ok(_) => CommandOutcome::Rebuilt(format!("Resumed session {rest}")),
+                Err(err) => CommandOutcome::Message(format!("Failed to resume session: {err}")),
+            }
+        }
+        "/tools" if rest.is_empty() => {
+            let tools = session.agent.state().tools;
+            CommandOutcome::Message(if tools.is_empty() {
+                "No tools active.".to_string()
+            } else {
+                let mut lines = vec!["Active tools:".to_string()];
+                for tool in &tools {
+                    let summary = tool.description.lines().next().unwrap_or("");
+                    lines.push(format!("  {} - {}", tool.name, summary));
+                }
+                lines.join("\n")
+            })
+        }
+        "/session" if rest.is_empty() => {
+            let model = session.agent.state().model;
+            CommandOutcome::Message(format!(
+                "Session Info:\n  ID: {}\n  Messages: {}\n  Model: {}/{}",
+                session.session_id(),
+                session.messages().len(),
+                model.provider,
+                model.id
+            ))
+        }
+        "/reset" if rest.is_empty() => {
+            session.new_session();
+            CommandOutcome::Cleared("Session reset.".to_string())
+        }
+        "/new" if rest.is_empty() => {
+            session.new_session();
+            CommandOutcome::Cleared(format!("New session started: {}", session.session_id()))
+        }
+        _ => return None,
+    };
+    Some(outcome)
+}
+
+fn run_bash(session: &mut AgentSession, command: &str) -> String {
+    match session.execute_bash(command) {
+        Ok(result) => {
+            // Format output like a shell
+            let mut display = format!("$ {command}\n");
+            if !result.output.is_empty() {
+                display.push_str(&result.output);
+                if !result.output.ends_with('\n') {
+                    display.push('\n');
+                }
+            }
+            if let Some(code) = result.exit_code {
+                if code != 0 {
+                    display.push_str(&format!("[exit code: {code}]\n"));
+                }
+            }
+            if result.cancelled {
+                display.push_str("[cancelled]\n");
+            }
+            display.trim_end().to_string()
+        }
+        Err(err) => format!("Bash error: {err}"),
+    }
+}
+
+fn select_model(session: &mut AgentSession, rest: &str) -> String {
+    let available = session.get_available_models();
+    if available.is_empty() {
+        return "No models available. Set an API key in auth.json or env.".to_string();
+    }
+    let current_model = session.agent.state().model;
+    let (selected, warning) = if let Ok(index) = rest.parse::<usize>() {
+        let choices = sort_models_for_display(&available, &current_model);
+        if index == 0 || index > choices.len() {
+            return "Model index out of range. Run /model to see options.".to_string();
+        }
+        (choices[index - 1].clone(), None)
+    } else {
+        let parsed = parse_model_pattern(rest, &available);
+        let Some(model) = parsed.model else {
+            return "No model matched that pattern. Run /model to see options.".to_string();
+        };
+        (model, parsed.warning)
+    };
+
+    session.set_model(to_agent_model(&selected));
+    session
+        .settings_manager
+        .set_default_model_and_provider(&selected.provider, &selected.id);
+    let mut message = format!("Model set to {}/{}", selected.provider, selected.id);
+    if let Some(level) = extract_thinking_level_suffix(rest) {
+        session.set_thinking_level(level);
+        message.push_str(&format!(" (thinking: {})", level.as_str()));
+    }
+    if let Some(warning) = warning {
+        message.push('\n');
+        message.push_str(&warning);
+    }
+    message
+}
+
+fn set_thinking(session: &mut AgentSession, rest: &str) -> String {
+    let level = if rest.is_empty() {
+        session.cycle_thinking_level().level
+    } else if let Some(level) = parse_thinking_level_value(rest) {
+        session.set_thinking_level(level);
+        session.agent.state().thinking_level
+    } else {
+        return format!(
+            "Unknown thinking level: {rest}. Use off, minimal, low, medium, high or xhigh."
+        );
+    };
+    format!("Thinking level: {}", level.as_str())
+}
+
+fn list_skills(session: &AgentSession) -> String {
+    if session.skills().is_empty() {
+        return "No skills found. Add a SKILL.md to a skills directory.".to_string();
+    }
+    let mut lines = vec!["Skills (run with /skill <name> [args]):".to_string()];
+    for skill in session.skills() {
+        let arguments = skill
+            .arguments
+            .iter()
+            .map(|argument| match argument.required {
+                true => format!(" <{}>", argument.name),
+                false => format!(" [{}]", argument.name),
+            })
+            .collect::<String>();
+        lines.push(format!(
+            "  {}{arguments} - {}",
+            skill.name, skill.description
+        ));
+    }
+    lines.join("\n")
+}
+
+fn list_templates(session: &AgentSession) -> String {
+    if session.prompt_templates().is_empty() {
+        return "No prompt templates found. Add <name>.md files to a prompts directory."
+            .to_string();
+    }
+    let mut lines = vec!["Prompt templates (run with /template <name> [args]):".to_string()];
+    for template in session.prompt_templates() {
+        let arguments = template_arguments(&template.content)
+            .iter()
+            .map(|argument| match argument.is_required() {
+                true => format!(" <{}>", argument.name),
+                false => format!(" [{}]", argument.name),
+            })
+            .collect::<String>();
+        lines.push(format!(
+            "  {}{arguments} - {}",
+            template.name, template.description
+        ));
+    }
+    lines.join("\n")
+}
+
+fn switch_persona(session: &mut AgentSession, rest: &str) -> String {
+    let personas = load_personas(LoadPersonasOptions::default());
+    if rest.is_empty() {
+        if personas.is_empty() {
+            return "No personas found. Add <name>.md files to the personas directory.".to_string();
+        }
+        let mut lines = vec!["Personas:".to_string()];
+        for persona in &personas {
+            let marker = if session.persona() == Some(persona.name.as_str()) {
+                "*"
+            } else {
+                " "
+            };
+            lines.push(format!(
+                "  {marker} {} - {}",
+                persona.name, persona.description
+            ));
+        }
+        return lines.join("\n");
+    }
+    match find_persona(&personas, rest) {
+        Some(persona) => match session.apply_persona(persona) {
+            Ok(missing) if missing.is_empty() => format!("Persona: {}", persona.name),
+            Ok(missing) => format!(
+                "Persona: {} (unavailable tools: {}; restart with --persona {} to enable them)",
+                persona.name,
+                missing.join(", "),
+                persona.name
+            ),
+            Err(err) => format!("Failed to apply persona: {err}"),
+        },
+        None => format!("Unknown persona: {rest}"),
+    }
+}
+
+/// Pin (or unpin) entry `rest`; without an id, the latest message or the latest pin.
+fn pin_message(session: &mut AgentSession, rest: &str, pinned: bool) -> String {
+    let entry_id = if !rest.is_empty() {
+        Some(rest.to_string())
+    } else if pinned {
+        session
+            .session_manager
+            .get_branch(None)
+            .iter()
+            .rev()
+            .find(|entry| matches!(entry, SessionEntry::Message(_)))
+            .map(|entry| entry.id().to_string())
+    } else {
+        session.pinned_entry_ids().pop()
+    };
+    let Some(entry_id) = entry_id else {
+        return if pinned {
+            "No messages to pin yet".to_string()
+        } else {
+            "No pinned messages".to_string()
+        };
+    };
+    match session.pin_message(&entry_id, pinned) {
+        Ok(()) if pinned => format!(
+            "Pinned {entry_id}; it will be kept through compaction ({} pinned)",
+            session.pinned_entry_ids().len()
+        ),
+        Ok(()) => format!("Unpinned {entry_id}"),
+        Err(err) => format!("Failed to pin message: {err}"),
+    }
+}
+
+fn revert_file(session: &mut AgentSession, path: &str) -> String {
+    if path.is_empty() {
+        let changes = session.file_changes();
+        if changes.is_empty() {
+            return "No file changes recorded in this session.".to_string();
+        }
+        let mut lines = vec!["Changed files:".to_string()];
+        for change in &changes {
+            lines.push(format!(
+                "  {} ({})",
+                change.path.display(),
+                change.tool_name
+            ));
+        }
+        lines.push("Usage: /revert <path>".to_string());
+        return lines.join("\n");
+    }
+    match session.revert_file(path) {
+        Ok(changes) => format!(
+            "Reverted {path} ({} change{} undone)",
+            changes.len(),
+            if changes.len() == 1 { "" } else { "s" }
+        ),
+        Err(err) => format!("Revert failed: {err}"),
+    }
+}
+
+/// `/queue` lists queued messages in delivery order, numbered from 1; `remove <n>`,
+/// `edit <n> <text>` and `clear` change the queue.
+fn edit_queue(session: &AgentSession, rest: &str) -> String {
+    let (action, args) = match rest.split_once(char::is_whitespace) {
+        Some((action, args)) => (action, args.trim()),
+        None => (rest, ""),
+    };
+    let pending = session.pending_queue();
+    let (number, text) = match args.split_once(char::is_whitespace) {
+        Some((number, text)) => (number, text.trim()),
+        None => (args, ""),
+    };
+    let target = number
+        .parse::<usize>()
+        .ok()
+        .and_then(|number| number.checked_sub(1))
+        .and_then(|index| pending.get(index));
+    match action {
+        "" | "list" => {
+            if pending.is_empty() {
+                return "No queued messages.".to_string();
+            }
+            let mut lines = vec!["Queued messages (next first):".to_string()];
+            for (index, message) in pending.iter().enumerate() {
+                let kind = match message.kind {
+                    QueueKind::Steering => "steer",
+                    QueueKind::FollowUp => "follow-up",
+                };
+                let text = message.message.user_text().unwrap_or("[non-text message]");
+                let first_line = text.lines().next().unwrap_or("");
+                lines.push(format!("  {}. [{kind}] {first_line}", index + 1));
+            }
+            lines.join("\n")
+        }
+        "clear" if args.is_empty() => {
+            let cleared = session.clear_pending();
+            format!(
+                "Cleared {cleared} queued message{}",
+                if cleared == 1 { "" } else { "s" }
+            )
+        }
+        "remove" if text.is_empty() => match target {
+            Some(message) => match session.remove_pending_message(message.id) {
+                Ok(_) => format!("Removed queued message {number}"),
+                Err(err) => format!("Failed to remove queued message: {err}"),
+            },
+            None => format!("No queued message {number}. Run /queue to see the queue."),
+        },
+        "edit" if !text.is_empty() => match target {
+            Some(message) => match session.edit_pending_message(message.id, text) {
+                Ok(()) => format!("Edited queued message {number}"),
+                Err(err) => format!("Failed to edit queued message: {err}"),
+            },
+            None => format!("No queued message {number}. Run /queue to see the queue."),
+        },
+        _ => "Usage: /queue [list | remove <n> | edit <n> <text> | clear]".to_string(),
+    }
+}
+
+fn create_checkpoint(session: &mut AgentSession, name: &str) -> String {
+    if name.is_empty() {
+        return list_checkpoints(session);
+    }
+    match session.create_checkpoint(name) {
+        Ok(checkpoint) => format!(
+            "Checkpoint {} created ({} file changes so far)",
+            checkpoint.name, checkpoint.file_changes
+        ),
+        Err(err) => format!("Failed to create checkpoint: {err}"),
+    }
+}
+
+fn list_checkpoints(session: &AgentSession) -> String {
+    let checkpoints = session.checkpoints();
+    if checkpoints.is_empty() {
+        return "No checkpoints yet. Create one with /checkpoint <name>.".to_string();
+    }
+    let mut lines = vec!["Checkpoints (rewind with /rewind <name>):".to_string()];
+    for checkpoint in &checkpoints {
+        lines.push(format!(
+            "  {} - {} ({} file changes)",
+            checkpoint.name, checkpoint.timestamp, checkpoint.file_changes
+        ));
+    }
+    lines.join("\n")
+}
+
+fn toggle_context_pack(session: &mut AgentSession, rest: &str) -> String {
+    let (action, name) = match rest.split_once(char::is_whitespace) {
+        Some((action, name)) => (action, name.trim()),
+        None => (rest, ""),
+    };
+    match action {
+        "" | "list" => {
+            let packs = load_context_packs(LoadContextPacksOptions::default());
+            if packs.is_empty() && session.context_packs().is_empty() {
+                return "No context packs found. Add <name>.md files to .pi/context.".to_string();
+            }
+            let mut lines = vec!["Context packs:".to_string()];
+            for pack in &packs {
+                let marker = if session.context_packs().iter().any(|p| p.name == pack.name) {
+                    "*"
+                } else {
+                    " "
+                };
+                lines.push(format!("  {marker} {} ({} tokens)", pack.name, pack.tokens));
+            }
+            let enabled = session
+                .context_packs()
+                .iter()
+                .map(|pack| pack.tokens)
+                .sum::<i64>();
+            lines.push(format!("Enabled: {enabled} tokens"));
+            lines.join("\n")
+        }
+        "enable" if !name.is_empty() => {
+            let packs = load_context_packs(LoadContextPacksOptions::default());
+            match find_context_pack(&packs, name) {
+                Some(pack) => {
+                    let tokens = pack.tokens;
+                    session.enable_context_pack(pack.clone());
+                    format!("Context pack enabled: {name} ({tokens} tokens)")
+                }
+                None => format!("Unknown context pack: {name}"),
+            }
+        }
+        "disable" if !name.is_empty() => {
+            if session.disable_context_pack(name) {
+                format!("Context pack disabled: {name}")
+            } else {
+                format!("Context pack not enabled: {name}")
+            }
+        }
+        _ => "Usage: /context [list | enable <name> | disable <name>]".to_string(),
+    }
+}
+
+pub(crate) fn parse_thinking_level_value(value: &str) -> Option<ThinkingLevel> {
+    match value {
+        "off" => Some(ThinkingLevel::Off),
+        "minimal" => Some(ThinkingLevel::Minimal),
+        "low" => Some(ThinkingLevel::Low),
+        "medium" => Some(ThinkingLevel::Medium),
+        "high" => Some(ThinkingLevel::High),
+        "xhigh" => Some(ThinkingLevel::XHigh),
+        _ => None,
+    }
+}
+
+fn extract_thinking_level_suffix(pattern: &str) -> Option<ThinkingLevel> {
+    let idx = pattern.rfind(':')?;
+    parse_thinking_level_value(pattern[idx + 1..].trim())
+}
+
+/// Available models with the current one first, then by provider and id. `/model <n>`
+/// indexes into this order.
+pub(crate) fn sort_models_for_display(
+    models: &[RegistryModel],
+    current: &crate::agent::Model,
+) -> Vec<RegistryModel> {
+    let mut choices = models.to_vec();
+    choices.sort_by(|a, b| {
+        let a_current = a.provider == current.provider && a.id == current.id;
+        let b_current = b.provider == current.provider && b.id == current.id;
+        if a_current && !b_current {
+            return std::cmp::Ordering::Less;
+        }
+        if !a_current && b_current {
+            return std::cmp::Ordering::Greater;
+        }
+        a.provider.cmp(&b.provider).then_with(|| a.id.cmp(&b.id))
+    });
+    choices
+}
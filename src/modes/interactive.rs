@@ -1,44 +1,72 @@
-use crate::agent::{QueueMode, ThinkingLevel};
-use crate::cli::file_inputs::FileInputImage;
+use crate::agent::{
+    AgentEvent, AgentMessage, AgentToolResult, CancellationToken, QueueMode, QueuePriority,
+};
+use crate::cli::args::UiMode;
+use crate::cli::file_inputs::{build_file_inputs, extract_file_references, FileInputImage};
 use crate::cli::session::to_agent_model;
-use crate::coding_agent::interactive_mode::format_message_for_interactive;
+use crate::coding_agent::interactive_mode::{
+    format_content_blocks, format_message_for_interactive, render_image_preview, render_status_bar,
+    StatusBarInfo,
+};
 use crate::coding_agent::{
-    anthropic_exchange_code, anthropic_get_auth_url, available_themes, get_changelog_path,
-    get_oauth_providers, load_theme_or_default, open_browser, openai_codex_get_auth_url,
-    openai_codex_login_with_input, parse_changelog, parse_model_pattern, set_active_theme,
-    AgentSession, AuthCredential, BranchCandidate, OAuthCallbackServer,
+    anthropic_exchange_code, anthropic_get_auth_url, append_prompt_history, available_themes,
+    get_active_theme, get_changelog_path, get_oauth_providers, get_prompt_history_path,
+    load_prompt_history, load_theme_or_default, open_browser, openai_codex_get_auth_url,
+    openai_codex_login_with_input, parse_changelog, set_active_theme, AgentSession,
+    AgentSessionEvent, AuthCredential, BranchCandidate, EditDecision, EditProposal,
+    OAuthCallbackServer, Theme, PROMPT_HISTORY_LIMIT,
+};
+use crate::core::compaction::calculate_context_tokens;
+use crate::core::messages::{
+    AgentMessage as CoreAgentMessage, AssistantMessage, ContentBlock, UserContent,
 };
-use crate::core::messages::UserContent;
 use crate::core::session_manager::SessionManager;
+use crate::core::tokens::{estimate_message, Tokenizer};
 use crate::tui::{
-    bool_values, double_escape_action_values, matches_key, queue_mode_values,
-    thinking_level_values, truncate_to_width, wrap_text_with_ansi, CombinedAutocompleteProvider,
-    Editor, LoginDialogComponent, LoginDialogResult, ModelItem, ModelSelectorComponent,
-    ModelSelectorResult, OAuthSelectorComponent, OAuthSelectorMode, OAuthSelectorResult,
-    SessionSelectorComponent, SettingItem, SettingValue, SettingsSelectorComponent,
-    SettingsSelectorResult, SlashCommand, TreeSelectorComponent,
+    bool_values, double_escape_action_values, get_capabilities, is_image_line, matches_key,
+    progress_sequence, queue_mode_values, queue_priority_values, supports_progress,
+    thinking_level_values, title_sequence, truncate_to_width, wrap_text_with_ansi, Editor,
+    EditorTheme, ImageProtocol, LoginDialogComponent, LoginDialogResult, Markdown, ModelItem,
+    ModelSelectorComponent, ModelSelectorResult, OAuthSelectorComponent, OAuthSelectorMode,
+    OAuthSelectorResult, Pager, PagerResult, SessionSelectorComponent, SettingItem, SettingValue,
+    SettingsSelectorComponent, SettingsSelectorResult, TaskbarProgress, TreeSelectorComponent,
 };
-use std::io::{self, Write};
-use std::path::PathBuf;
+use std::io::{self, IsTerminal, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
 use std::process;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use crossterm::cursor::{Hide, MoveTo, Show};
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{
+    self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind,
+    KeyModifiers, MouseEventKind,
+};
 use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
 use crossterm::ExecutableCommand;
 
 use super::build_user_content_from_files;
+use super::commands::{
+    parse_thinking_level_value, run_shared_command, session_autocomplete_provider, CommandOutcome,
+};
+use super::line::run_line_mode_session;
 
 struct TerminalGuard;
 
 impl TerminalGuard {
     fn enter(stdout: &mut impl Write) -> Result<Self, String> {
         terminal::enable_raw_mode().map_err(|err| err.to_string())?;
+        // From here on, dropping the guard restores the terminal if a later step fails.
+        let guard = Self;
         stdout
             .execute(EnterAlternateScreen)
             .map_err(|err| err.to_string())?;
         stdout.execute(Hide).map_err(|err| err.to_string())?;
-        Ok(Self)
+        Ok(guard)
     }
 }
 
@@ -46,16 +74,149 @@ impl Drop for TerminalGuard {
     fn drop(&mut self) {
         let _ = terminal::disable_raw_mode();
         let mut stdout = io::stdout();
+        set_terminal_activity(&mut stdout, None, TaskbarProgress::Hidden);
+        let _ = write!(stdout, "{}", title_sequence(""));
+        let _ = stdout.execute(DisableMouseCapture);
         let _ = stdout.execute(LeaveAlternateScreen);
         let _ = stdout.execute(Show);
     }
 }
 
+/// Reflect agent activity in the window title and, where supported, the taskbar progress,
+/// so a backgrounded window still shows whether the agent is busy.
+fn set_terminal_activity(
+    stdout: &mut impl Write,
+    activity: Option<&str>,
+    progress: TaskbarProgress,
+) {
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let dir = cwd
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| cwd.display().to_string());
+    let title = match activity {
+        Some(activity) => format!("{} - {dir} ({activity})", crate::config::app_name()),
+        None => format!("{} - {dir}", crate::config::app_name()),
+    };
+    let _ = write!(stdout, "{}", title_sequence(&title));
+    if supports_progress() {
+        let _ = write!(stdout, "{}", progress_sequence(progress));
+    }
+    let _ = stdout.flush();
+}
+
+/// Windows reports key releases as separate events; only presses drive the UI.
+fn is_key_press(key: &KeyEvent) -> bool {
+    key.kind != KeyEventKind::Release
+}
+
+fn is_ctrl_c(key: &KeyEvent) -> bool {
+    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL)
+}
+
+/// Why the full-screen UI would garble this terminal, judging by `TERM` and `INSIDE_EMACS`.
+pub fn limited_terminal_reason(term: Option<&str>, inside_emacs: Option<&str>) -> Option<String> {
+    if term == Some("dumb") {
+        return Some("TERM=dumb".to_string());
+    }
+    // Emacs sets INSIDE_EMACS in shell-mode, eshell and term; vterm is a full emulator.
+    match inside_emacs {
+        Some(value) if !value.contains("vterm") => Some("running inside Emacs".to_string()),
+        _ => None,
+    }
+}
+
+/// Why the full-screen UI cannot run here; `None` when it can.
+fn full_screen_unavailable_reason() -> Option<String> {
+    // Raw mode and the alternate screen need a terminal on both ends, and the renderer writes
+    // ANSI sequences, which legacy Windows consoles may not support.
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return Some("stdin or stdout is not a terminal".to_string());
+    }
+    if !supports_ansi() {
+        return Some("the console does not support ANSI sequences".to_string());
+    }
+    let term = std::env::var("TERM").ok();
+    let inside_emacs = std::env::var("INSIDE_EMACS").ok();
+    if let Some(reason) = limited_terminal_reason(term.as_deref(), inside_emacs.as_deref()) {
+        return Some(reason);
+    }
+    match terminal::size() {
+        Ok((width, height)) if width > 0 && height > 0 => None,
+        _ => Some("the terminal does not report its size".to_string()),
+    }
+}
+
+#[cfg(windows)]
+fn supports_ansi() -> bool {
+    crossterm::ansi_support::supports_ansi()
+}
+
+#[cfg(not(windows))]
+fn supports_ansi() -> bool {
+    true
+}
+
 enum EditorAction {
     Submit,
     Exit,
     Continue,
     PasteImage,
+    Scroll(ScrollAction),
+}
+
+/// Lines scrolled per mouse wheel notch.
+const MOUSE_SCROLL_LINES: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ScrollAction {
+    PageUp,
+    PageDown,
+    HalfPageUp,
+    HalfPageDown,
+    LinesUp(usize),
+    LinesDown(usize),
+}
+
+/// Scroll position of the chat view, counted in lines up from the bottom.
+/// Any change in the number of entries jumps back to the bottom.
+#[derive(Clone, Copy, Debug, Default)]
+struct ChatScroll {
+    offset: usize,
+    page: usize,
+    entry_count: usize,
+}
+
+impl ChatScroll {
+    fn apply(&mut self, action: ScrollAction) {
+        let page = self.page.saturating_sub(1).max(1);
+        let half_page = (self.page / 2).max(1);
+        self.offset = match action {
+            ScrollAction::PageUp => self.offset.saturating_add(page),
+            ScrollAction::PageDown => self.offset.saturating_sub(page),
+            ScrollAction::HalfPageUp => self.offset.saturating_add(half_page),
+            ScrollAction::HalfPageDown => self.offset.saturating_sub(half_page),
+            ScrollAction::LinesUp(lines) => self.offset.saturating_add(lines),
+            ScrollAction::LinesDown(lines) => self.offset.saturating_sub(lines),
+        };
+    }
+}
+
+fn mouse_scroll_action(kind: MouseEventKind) -> Option<ScrollAction> {
+    match kind {
+        MouseEventKind::ScrollUp => Some(ScrollAction::LinesUp(MOUSE_SCROLL_LINES)),
+        MouseEventKind::ScrollDown => Some(ScrollAction::LinesDown(MOUSE_SCROLL_LINES)),
+        _ => None,
+    }
+}
+
+fn set_mouse_capture(enabled: bool) {
+    let mut stdout = io::stdout();
+    let _ = if enabled {
+        stdout.execute(EnableMouseCapture)
+    } else {
+        stdout.execute(DisableMouseCapture)
+    };
 }
 
 /// Modal UI state for selectors
@@ -68,6 +229,7 @@ enum ModalState {
     SettingsSelector(SettingsSelectorState),
     OAuthSelector(OAuthSelectorState),
     LoginDialog(LoginDialogSelectorState),
+    Pager(Pager),
 }
 
 struct TreeSelectorState {
@@ -159,8 +321,8 @@ impl BranchSelectorState {
     fn render(&self, width: usize) -> Vec<String> {
         let mut lines = vec![
             "─".repeat(width.min(80)),
-            "  Branch from Message".to_string(),
-            "  Select a message to create a new branch from that point".to_string(),
+            "  Check Out Branch".to_string(),
+            "  Select a reply to continue from in a new branched session".to_string(),
             String::new(),
         ];
 
@@ -276,18 +438,74 @@ enum OAuthFlowState {
     Failed(String),
 }
 
+/// Position of an inline image within the chat lines, so a preview clipped by scrolling can
+/// be swapped for its placeholder.
+struct ImageRows {
+    last_line: usize,
+    rows: usize,
+    fallback: String,
+}
+
+/// Assistant replies are rendered as markdown, other entries are wrapped verbatim, and image
+/// markers expand to terminal graphics rows.
+fn push_entry_lines(
+    chat_lines: &mut Vec<String>,
+    images: &mut Vec<ImageRows>,
+    entry: &str,
+    width: usize,
+    theme: Option<&Theme>,
+) {
+    let (body, theme) = match (entry.strip_prefix("Assistant:\n"), theme) {
+        (Some(body), Some(theme)) => {
+            chat_lines.push("Assistant:".to_string());
+            (body, Some(theme))
+        }
+        _ => (entry, None),
+    };
+    let render_text = |text: &str| match theme {
+        Some(theme) => Markdown::new(text, 0, 0, theme.markdown_theme(), None).render(width),
+        None => wrap_text_with_ansi(text, width),
+    };
+
+    let mut pending = Vec::new();
+    for line in body.split('\n') {
+        let Some(preview) = render_image_preview(line, width.saturating_sub(2)) else {
+            pending.push(line);
+            continue;
+        };
+        if !pending.is_empty() {
+            chat_lines.extend(render_text(&pending.join("\n")));
+            pending.clear();
+        }
+        let rows = preview.lines.len();
+        chat_lines.extend(preview.lines);
+        images.push(ImageRows {
+            last_line: chat_lines.len() - 1,
+            rows,
+            fallback: preview.fallback,
+        });
+    }
+    if !pending.is_empty() {
+        chat_lines.extend(render_text(&pending.join("\n")));
+    }
+}
+
 fn render_interactive_ui(
     entries: &[String],
     editor: &mut Editor,
+    scroll: &mut ChatScroll,
+    status: &StatusBarInfo,
     stdout: &mut impl Write,
 ) -> Result<(), String> {
     let (width, height) = terminal::size().map_err(|err| err.to_string())?;
     let width = width.max(1) as usize;
     let height = height.max(1) as usize;
 
+    let theme = get_active_theme();
     let mut chat_lines = Vec::new();
+    let mut images = Vec::new();
     for (idx, entry) in entries.iter().enumerate() {
-        chat_lines.extend(wrap_text_with_ansi(entry, width));
+        push_entry_lines(&mut chat_lines, &mut images, entry, width, theme.as_ref());
         if idx + 1 < entries.len() {
             chat_lines.push(String::new());
         }
@@ -297,9 +515,33 @@ fn render_interactive_ui(
     }
 
     let editor_lines = editor.render(width);
-    let available_chat = height.saturating_sub(editor_lines.len());
-    let start = chat_lines.len().saturating_sub(available_chat);
-    let mut visible_chat = chat_lines[start..].to_vec();
+    let status_line = render_status_bar(status, width);
+    let available_chat = height.saturating_sub(editor_lines.len() + 1);
+    if entries.len() != scroll.entry_count {
+        scroll.entry_count = entries.len();
+        scroll.offset = 0;
+    }
+    scroll.page = available_chat;
+    scroll.offset = scroll
+        .offset
+        .min(chat_lines.len().saturating_sub(available_chat));
+    let end = chat_lines.len() - scroll.offset;
+    let start = end.saturating_sub(available_chat);
+    let mut visible_chat = chat_lines[start..end].to_vec();
+    for image in &images {
+        // The image is drawn upwards from its last row; if its top is scrolled off, show the placeholder.
+        if (start..end).contains(&image.last_line) && image.last_line + 1 < start + image.rows {
+            visible_chat[image.last_line - start] = image.fallback.clone();
+        }
+    }
+    if scroll.offset > 0 {
+        if let Some(last) = visible_chat.last_mut() {
+            *last = format!(
+                "\x1b[2m-- {} more lines below (PageDown / Ctrl+D) --\x1b[0m",
+                scroll.offset
+            );
+        }
+    }
     while visible_chat.len() < available_chat {
         visible_chat.push(String::new());
     }
@@ -307,6 +549,7 @@ fn render_interactive_ui(
     let mut lines = Vec::new();
     lines.extend(visible_chat);
     lines.extend(editor_lines);
+    lines.push(status_line);
     if lines.len() > height {
         lines.truncate(height);
     }
@@ -317,9 +560,17 @@ fn render_interactive_ui(
     stdout
         .execute(Clear(ClearType::All))
         .map_err(|err| err.to_string())?;
+    if get_capabilities().images == Some(ImageProtocol::Kitty) {
+        // Kitty placements survive a screen clear; drop them before redrawing.
+        write!(stdout, "\x1b_Ga=d\x1b\\").map_err(|err| err.to_string())?;
+    }
 
     for (index, line) in lines.iter().enumerate() {
-        let truncated = truncate_to_width(line, width);
+        let truncated = if is_image_line(line) {
+            line.clone()
+        } else {
+            truncate_to_width(line, width)
+        };
         if index + 1 == lines.len() {
             write!(stdout, "{truncated}").map_err(|err| err.to_string())?;
         } else {
@@ -404,6 +655,8 @@ fn key_event_to_data(key: &KeyEvent) -> String {
         KeyCode::Home => "\x1b[H".to_string(),
         KeyCode::End => "\x1b[F".to_string(),
         KeyCode::Delete => "\x1b[3~".to_string(),
+        KeyCode::PageUp => "\x1b[5~".to_string(),
+        KeyCode::PageDown => "\x1b[6~".to_string(),
         KeyCode::Char(ch) => {
             if ctrl && ch.is_ascii_alphabetic() {
                 // Ctrl+letter produces control character (a=1, b=2, ..., z=26)
@@ -434,6 +687,7 @@ fn render_modal_ui(modal: &ModalState, stdout: &mut impl Write) -> Result<(), St
         ModalState::SettingsSelector(state) => state.selector.render(width),
         ModalState::OAuthSelector(state) => state.selector.render(width),
         ModalState::LoginDialog(state) => state.dialog.render(width),
+        ModalState::Pager(pager) => pager.render_page(width, height),
     };
 
     // Truncate modal to fit screen
@@ -472,6 +726,28 @@ fn render_modal_ui(modal: &ModalState, stdout: &mut impl Write) -> Result<(), St
     Ok(())
 }
 
+/// Resolve a 1-based `/show` argument (negative counts from the end) to a message index.
+fn resolve_message_index(arg: &str, len: usize) -> Result<usize, String> {
+    if len == 0 {
+        return Err("No messages to show.".to_string());
+    }
+    if arg.is_empty() {
+        return Ok(len - 1);
+    }
+    let value: i64 = arg
+        .parse()
+        .map_err(|_| format!("Invalid message index \"{arg}\". Usage: /show [n]"))?;
+    let index = if value < 0 {
+        len as i64 + value
+    } else {
+        value - 1
+    };
+    if index < 0 || index >= len as i64 {
+        return Err(format!("Message index {value} out of range (1-{len})."));
+    }
+    Ok(index as usize)
+}
+
 fn build_user_entry(message: Option<&str>, images: &[FileInputImage]) -> String {
     let mut lines = Vec::new();
     if let Some(message) = message {
@@ -489,69 +765,604 @@ fn build_user_entry(message: Option<&str>, images: &[FileInputImage]) -> String
     }
 }
 
-fn prompt_and_append_text(
-    session: &mut AgentSession,
-    entries: &mut Vec<String>,
-    editor: &mut Editor,
-    stdout: &mut impl Write,
-    prompt: &str,
-) -> Result<(), String> {
-    let start_index = session.messages().len();
-    entries.push(format!("You:\n{prompt}"));
-    entries.push("Assistant:\n...".to_string());
-    render_interactive_ui(entries, editor, stdout)?;
+/// Minimum delay between redraws triggered by streaming deltas.
+const STREAM_RENDER_INTERVAL: Duration = Duration::from_millis(30);
+/// Number of trailing output lines shown for a running tool.
+const TOOL_PROGRESS_TAIL_LINES: usize = 5;
+/// How long the turn's input thread waits for a key before checking whether to stop.
+const INPUT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Lets a prompt that reads keys itself (an approval, a review, `$EDITOR`) take the terminal
+/// from the turn's input thread.
+#[derive(Clone, Default)]
+struct InputPause {
+    paused: Arc<AtomicBool>,
+    reader: Arc<Mutex<()>>,
+}
 
-    if let Err(err) = session.prompt(prompt) {
-        let last = entries.len().saturating_sub(1);
-        if let Some(entry) = entries.get_mut(last) {
-            *entry = format!("Assistant:\nError: {}", err);
+impl InputPause {
+    /// The input thread reads nothing until the returned guard is dropped.
+    fn pause(&self) -> PausedInput<'_> {
+        self.paused.store(true, Ordering::SeqCst);
+        PausedInput {
+            pause: self,
+            _reader: self.reader.lock().unwrap_or_else(|err| err.into_inner()),
         }
-        render_interactive_ui(entries, editor, stdout)?;
-        return Err(err.to_string());
     }
+}
 
-    let new_entries = collect_new_interactive_entries(session, start_index);
-    entries.pop();
-    if new_entries.is_empty() {
-        entries.push("Assistant:\n[no response]".to_string());
-    } else {
-        entries.extend(new_entries);
+struct PausedInput<'a> {
+    pause: &'a InputPause,
+    _reader: MutexGuard<'a, ()>,
+}
+
+impl Drop for PausedInput<'_> {
+    fn drop(&mut self) {
+        self.pause.paused.store(false, Ordering::SeqCst);
     }
-    render_interactive_ui(entries, editor, stdout)?;
-    Ok(())
 }
 
-fn prompt_and_append_content(
+/// Reads the terminal on its own thread while a turn runs, so typing and Ctrl-C are handled
+/// while the agent is silent too: waiting for the first token, or running a quiet tool.
+struct TurnInput {
+    stop: Arc<AtomicBool>,
+    handle: thread::JoinHandle<()>,
+}
+
+impl TurnInput {
+    fn spawn(live: Arc<Mutex<LiveTurn>>, pause: InputPause) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        let handle = thread::spawn(move || {
+            while !stopped.load(Ordering::SeqCst) {
+                if pause.paused.load(Ordering::SeqCst) {
+                    thread::sleep(INPUT_POLL_INTERVAL);
+                    continue;
+                }
+                // The reader lock is released before `live` is locked: a paused prompt holds
+                // `live` while it waits for the reader.
+                let event = {
+                    let _reader = pause.reader.lock().unwrap_or_else(|err| err.into_inner());
+                    match event::poll(INPUT_POLL_INTERVAL) {
+                        Ok(true) => event::read(),
+                        Ok(false) => continue,
+                        Err(err) => Err(err),
+                    }
+                };
+                let Ok(event) = event else {
+                    break;
+                };
+                if let Ok(mut live) = live.lock() {
+                    live.handle_input(event);
+                }
+            }
+        });
+        Self { stop, handle }
+    }
+
+    fn stop(self) {
+        self.stop.store(true, Ordering::SeqCst);
+        let _ = self.handle.join();
+    }
+}
+
+/// UI state shared with the session subscriber while a prompt is streaming.
+struct LiveTurn {
+    entries: Vec<String>,
+    streaming: Option<String>,
+    tools: Vec<(String, String)>,
+    editor: Editor,
+    scroll: ChatScroll,
+    hide_thinking: bool,
+    show_images: bool,
+    abort_flag: CancellationToken,
+    abort_requested: bool,
+    exit_requested: bool,
+    /// Messages submitted during the turn, waiting for the session listener to steer them.
+    steering: Vec<String>,
+    input: InputPause,
+    activity: String,
+    status: StatusBarInfo,
+    /// Estimates tool results for the status bar until the next reply reports usage.
+    tokenizer: Tokenizer,
+    last_render: Option<Instant>,
+}
+
+impl LiveTurn {
+    fn handle_event(&mut self, event: &AgentSessionEvent) {
+        if let Some(activity) = activity_for_event(event) {
+            self.set_activity(&activity);
+        }
+        let mut force = true;
+        match event {
+            AgentSessionEvent::Agent(event) => match event.as_ref() {
+                AgentEvent::MessageStart {
+                    message: message @ AgentMessage::Assistant(_),
+                }
+                | AgentEvent::MessageUpdate {
+                    message: message @ AgentMessage::Assistant(_),
+                    ..
+                } => {
+                    force = matches!(event.as_ref(), AgentEvent::MessageStart { .. });
+                    self.streaming = format_message_for_interactive(
+                        message,
+                        false,
+                        self.hide_thinking,
+                        self.show_images,
+                    );
+                }
+                AgentEvent::MessageEnd { message } => {
+                    match message {
+                        AgentMessage::Assistant(assistant) => {
+                            self.streaming = None;
+                            self.update_status_usage(assistant);
+                        }
+                        // Tool results grow the context before the next reply reports it.
+                        AgentMessage::ToolResult(result) => {
+                            self.status.context_tokens += estimate_message(
+                                &CoreAgentMessage::ToolResult(result.clone()),
+                                self.tokenizer,
+                            );
+                        }
+                        _ => {}
+                    }
+                    if let Some(entry) = format_message_for_interactive(
+                        message,
+                        false,
+                        self.hide_thinking,
+                        self.show_images,
+                    ) {
+                        self.entries.push(entry);
+                    }
+                }
+                AgentEvent::ToolExecutionStart {
+                    tool_call_id,
+                    tool_name,
+                    ..
+                } => {
+                    let line = format_tool_progress(tool_name, None, self.show_images);
+                    self.tools.push((tool_call_id.clone(), line));
+                }
+                AgentEvent::ToolExecutionUpdate {
+                    tool_call_id,
+                    tool_name,
+                    partial_result,
+                    ..
+                } => {
+                    force = false;
+                    let line =
+                        format_tool_progress(tool_name, Some(partial_result), self.show_images);
+                    match self.tools.iter_mut().find(|(id, _)| id == tool_call_id) {
+                        Some((_, existing)) => *existing = line,
+                        None => self.tools.push((tool_call_id.clone(), line)),
+                    }
+                }
+                AgentEvent::ToolExecutionEnd { tool_call_id, .. } => {
+                    self.tools.retain(|(id, _)| id != tool_call_id);
+                }
+                AgentEvent::LimitReached { reason, .. } => {
+                    append_status_entry(&mut self.entries, &format!("Stopped: {reason}"));
+                }
+                _ => return,
+            },
+            AgentSessionEvent::AutoCompactionStart { reason } => {
+                append_status_entry(&mut self.entries, &format!("Auto-compacting ({reason})..."));
+            }
+            AgentSessionEvent::AutoCompactionEnd { aborted } => {
+                let message = if *aborted {
+                    "Auto-compaction aborted"
+                } else {
+                    "Auto-compaction complete"
+                };
+                append_status_entry(&mut self.entries, message);
+            }
+        }
+        self.render(force);
+    }
+
+    fn update_status_usage(&mut self, message: &AssistantMessage) {
+        if let Some(cost) = &message.usage.cost {
+            self.status.cost += cost.total;
+        }
+        if !message.is_aborted() && message.stop_reason != "error" {
+            self.status.context_tokens = calculate_context_tokens(&message.usage);
+        }
+    }
+
+    fn set_activity(&mut self, activity: &str) {
+        if self.activity != activity {
+            self.activity = activity.to_string();
+            self.status.activity = Some(activity.to_string());
+            set_terminal_activity(
+                &mut io::stdout(),
+                Some(activity),
+                TaskbarProgress::Indeterminate,
+            );
+        }
+    }
+
+    /// Shows a bash command the policy could not decide and waits for `y` (run it) or any
+    /// other key (refuse). Ctrl-C refuses and aborts the turn.
+    fn confirm_bash(&mut self, command: &str, reason: &str) -> bool {
+        append_status_entry(
+            &mut self.entries,
+            &format!("Bash command needs approval: {reason}\n  {command}\nRun it? [y/N]"),
+        );
+        self.set_activity("waiting for approval");
+        self.render(true);
+        let input = self.input.clone();
+        let paused = input.pause();
+        let approved = loop {
+            match event::read() {
+                Ok(Event::Key(key)) if is_key_press(&key) && is_ctrl_c(&key) => {
+                    self.request_abort();
+                    break false;
+                }
+                Ok(Event::Key(key)) if is_key_press(&key) => {
+                    break matches!(key.code, KeyCode::Char('y') | KeyCode::Char('Y'));
+                }
+                Ok(_) => {}
+                Err(_) => break false,
+            }
+        };
+        drop(paused);
+        append_status_entry(
+            &mut self.entries,
+            if approved {
+                "Command approved."
+            } else {
+                "Command refused."
+            },
+        );
+        self.set_activity("running bash");
+        self.render(true);
+        approved
+    }
+
+    /// Shows a change the write or edit tool wants to make and waits for `y` (apply it), `n`
+    /// (reject it, optionally with a note for the model) or `e` (rewrite it in `$EDITOR`).
+    /// Ctrl-C rejects it and aborts the turn.
+    fn review_edit(&mut self, proposal: &EditProposal) -> EditDecision {
+        let preview = if proposal.diff.is_empty() {
+            format!("New file:\n{}", proposal.content)
+        } else {
+            format_content_blocks(
+                &[ContentBlock::diff(&proposal.path, &proposal.diff)],
+                false,
+                false,
+            )
+        };
+        append_status_entry(
+            &mut self.entries,
+            &format!(
+                "Review {} to {}:\n{preview}\nApply it? [y]es / [n]o / [e]dit",
+                proposal.tool, proposal.path
+            ),
+        );
+        self.set_activity("waiting for review");
+        self.render(true);
+        let input = self.input.clone();
+        let paused = input.pause();
+        let decision = loop {
+            match event::read() {
+                Ok(Event::Key(key)) if is_key_press(&key) && is_ctrl_c(&key) => {
+                    self.request_abort();
+                    break EditDecision::Reject(None);
+                }
+                Ok(Event::Key(key)) if is_key_press(&key) => match key.code {
+                    KeyCode::Char('y') | KeyCode::Char('Y') => break EditDecision::Accept,
+                    KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                        break EditDecision::Reject(self.read_rejection_note());
+                    }
+                    KeyCode::Char('e') | KeyCode::Char('E') => {
+                        match edit_in_external_editor(&proposal.path, &proposal.content) {
+                            Ok(content) if content == proposal.content => {
+                                break EditDecision::Accept;
+                            }
+                            Ok(content) => break EditDecision::Replace(content),
+                            Err(err) => {
+                                append_status_entry(
+                                    &mut self.entries,
+                                    &format!("Could not open an editor: {err}\n[y]es / [n]o"),
+                                );
+                                self.render(true);
+                            }
+                        }
+                    }
+                    _ => {}
+                },
+                Ok(_) => {}
+                Err(_) => break EditDecision::Reject(None),
+            }
+        };
+        drop(paused);
+        append_status_entry(
+            &mut self.entries,
+            match decision {
+                EditDecision::Accept => "Change applied.",
+                EditDecision::Reject(_) => "Change rejected.",
+                EditDecision::Replace(_) => "Applying your version of the change.",
+            },
+        );
+        self.set_activity(&format!("running {}", proposal.tool));
+        self.render(true);
+        decision
+    }
+
+    /// Reads an optional note to send back with a rejected change. Enter sends it, Esc skips.
+    fn read_rejection_note(&mut self) -> Option<String> {
+        let prompt = "Note for the model (Enter to send, Esc to skip):";
+        let mut note = String::new();
+        append_status_entry(&mut self.entries, prompt);
+        self.render(true);
+        loop {
+            match event::read() {
+                Ok(Event::Key(key)) if is_key_press(&key) && is_ctrl_c(&key) => {
+                    self.request_abort();
+                    return None;
+                }
+                Ok(Event::Key(key)) if is_key_press(&key) => match key.code {
+                    KeyCode::Enter => break,
+                    KeyCode::Esc => return None,
+                    KeyCode::Backspace => {
+                        note.pop();
+                    }
+                    KeyCode::Char(ch) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        note.push(ch);
+                    }
+                    _ => continue,
+                },
+                Ok(_) => continue,
+                Err(_) => return None,
+            }
+            self.entries.pop();
+            append_status_entry(&mut self.entries, &format!("{prompt}\n{note}"));
+            self.render(true);
+        }
+        Some(note).filter(|note| !note.trim().is_empty())
+    }
+
+    /// Handles an event from the turn's input thread. Submitted text steers the turn; commands
+    /// stay in the editor until the turn finishes.
+    fn handle_input(&mut self, event: Event) {
+        let changed = match event {
+            Event::Key(key) if is_key_press(&key) => {
+                match handle_key_event(key, &mut self.editor) {
+                    // First Ctrl-C aborts the turn; a second one exits.
+                    EditorAction::Exit if self.abort_requested => self.exit_requested = true,
+                    EditorAction::Exit => self.request_abort(),
+                    EditorAction::PasteImage => {
+                        if let Some(path) = paste_image_from_clipboard() {
+                            self.editor.insert_text_at_cursor(&path);
+                        }
+                    }
+                    EditorAction::Scroll(action) => self.scroll.apply(action),
+                    EditorAction::Submit => self.queue_steering(),
+                    EditorAction::Continue => {}
+                }
+                true
+            }
+            Event::Mouse(mouse) => match mouse_scroll_action(mouse.kind) {
+                Some(action) => {
+                    self.scroll.apply(action);
+                    true
+                }
+                None => false,
+            },
+            Event::Resize(_, _) => true,
+            _ => false,
+        };
+        if changed {
+            self.render(true);
+        }
+    }
+
+    /// Takes a submitted message out of the editor so it is delivered to the running turn.
+    fn queue_steering(&mut self) {
+        let text = self.editor.get_text();
+        let text = text.trim();
+        if text.is_empty() || text.starts_with('/') || text.starts_with('!') {
+            return;
+        }
+        append_status_entry(&mut self.entries, &format!("Queued: {text}"));
+        self.steering.push(text.to_string());
+        self.editor.set_text("");
+    }
+
+    /// Cancels the turn: the provider stream stops reading and a running command is killed.
+    fn request_abort(&mut self) {
+        self.abort_flag.cancel();
+        self.abort_requested = true;
+        self.set_activity("aborting");
+        append_status_entry(&mut self.entries, "Aborting... press Ctrl-C again to exit.");
+    }
+
+    fn render(&mut self, force: bool) {
+        let due = self
+            .last_render
+            .is_none_or(|last| last.elapsed() >= STREAM_RENDER_INTERVAL);
+        if !force && !due {
+            return;
+        }
+        let mut entries = self.entries.clone();
+        entries.extend(self.streaming.iter().cloned());
+        entries.extend(self.tools.iter().map(|(_, line)| line.clone()));
+        let _ = render_interactive_ui(
+            &entries,
+            &mut self.editor,
+            &mut self.scroll,
+            &self.status,
+            &mut io::stdout(),
+        );
+        self.last_render = Some(Instant::now());
+    }
+}
+
+fn status_bar_info(session: &AgentSession) -> StatusBarInfo {
+    let state = session.agent.state();
+    let context = session.get_context_usage();
+    StatusBarInfo {
+        provider: state.model.provider,
+        model: state.model.id,
+        thinking_level: state.thinking_level.as_str().to_string(),
+        context_tokens: context.tokens,
+        context_window: context.context_window,
+        cost: session.get_session_stats().cost,
+        activity: None,
+    }
+}
+
+fn activity_for_event(event: &AgentSessionEvent) -> Option<String> {
+    match event {
+        AgentSessionEvent::Agent(event) => match event.as_ref() {
+            AgentEvent::MessageStart {
+                message: AgentMessage::Assistant(_),
+            } => Some("responding".to_string()),
+            AgentEvent::ToolExecutionStart { tool_name, .. } => {
+                Some(format!("running {tool_name}"))
+            }
+            AgentEvent::ToolExecutionEnd { .. } => Some("thinking".to_string()),
+            _ => None,
+        },
+        AgentSessionEvent::AutoCompactionStart { .. } => Some("compacting".to_string()),
+        AgentSessionEvent::AutoCompactionEnd { .. } => Some("thinking".to_string()),
+    }
+}
+
+fn format_tool_progress(
+    tool_name: &str,
+    partial: Option<&AgentToolResult>,
+    show_images: bool,
+) -> String {
+    let mut entry = format!("Tool: {tool_name} (running...)");
+    if let Some(partial) = partial {
+        let output = format_content_blocks(&partial.content, false, show_images);
+        let lines: Vec<&str> = output.lines().collect();
+        let tail = &lines[lines.len().saturating_sub(TOOL_PROGRESS_TAIL_LINES)..];
+        if !tail.is_empty() {
+            entry.push('\n');
+            entry.push_str(&tail.join("\n"));
+        }
+    }
+    entry
+}
+
+/// Inline `@file` references the same way `@file` CLI arguments are handled in print mode.
+pub(super) fn expand_file_references(prompt: &str) -> Result<Option<UserContent>, String> {
+    let references = extract_file_references(prompt);
+    if references.is_empty() {
+        return Ok(None);
+    }
+    let inputs = build_file_inputs(&references)?;
+    let message = format!("{}{}", inputs.text_prefix, prompt);
+    build_user_content_from_files(Some(&message), &inputs.images).map(Some)
+}
+
+/// Runs a prompt while rendering agent events as they stream in.
+/// Returns `true` when the user asked to exit during the turn.
+fn prompt_and_append(
     session: &mut AgentSession,
     entries: &mut Vec<String>,
     editor: &mut Editor,
+    scroll: &mut ChatScroll,
     stdout: &mut impl Write,
     prompt: &str,
-    content: UserContent,
-) -> Result<(), String> {
+    content: Option<UserContent>,
+) -> Result<bool, String> {
     let start_index = session.messages().len();
     entries.push(format!("You:\n{prompt}"));
-    entries.push("Assistant:\n...".to_string());
-    render_interactive_ui(entries, editor, stdout)?;
 
-    if let Err(err) = session.prompt_content(content) {
-        let last = entries.len().saturating_sub(1);
-        if let Some(entry) = entries.get_mut(last) {
-            *entry = format!("Assistant:\nError: {}", err);
+    let input = InputPause::default();
+    let live = Arc::new(Mutex::new(LiveTurn {
+        entries: entries.clone(),
+        streaming: Some("Assistant:\n...".to_string()),
+        tools: Vec::new(),
+        editor: mem::replace(editor, Editor::new(EditorTheme::default())),
+        scroll: *scroll,
+        hide_thinking: session.settings_manager.get_hide_thinking_block(),
+        show_images: session.settings_manager.get_show_images(),
+        abort_flag: session.agent.abort_flag(),
+        abort_requested: false,
+        exit_requested: false,
+        steering: Vec::new(),
+        input: input.clone(),
+        activity: String::new(),
+        status: status_bar_info(session),
+        tokenizer: Tokenizer::for_model(&session.agent.state().model),
+        last_render: None,
+    }));
+    if let Ok(mut live) = live.lock() {
+        live.set_activity("thinking");
+        live.render(true);
+    }
+    let turn_input = TurnInput::spawn(live.clone(), input);
+
+    let listener_state = live.clone();
+    let steer = session.steering_sender();
+    let unsubscribe = session.subscribe(move |event| {
+        if let Ok(mut live) = listener_state.lock() {
+            live.handle_event(event);
+            for text in live.steering.drain(..) {
+                steer(&text);
+            }
         }
-        render_interactive_ui(entries, editor, stdout)?;
+    });
+    let approval_state = live.clone();
+    session
+        .bash_approval()
+        .set_handler(Rc::new(move |command, reason| {
+            approval_state
+                .lock()
+                .is_ok_and(|mut live| live.confirm_bash(command, reason))
+        }));
+    let review_state = live.clone();
+    session
+        .edit_review()
+        .set_handler(Rc::new(move |proposal| match review_state.lock() {
+            Ok(mut live) => live.review_edit(proposal),
+            Err(_) => EditDecision::Reject(None),
+        }));
+    let result = match content {
+        Some(content) => session.prompt_content(content),
+        None => session.prompt(prompt),
+    };
+    session.bash_approval().clear_handler();
+    session.edit_review().clear_handler();
+    unsubscribe();
+    turn_input.stop();
+
+    let live = match Arc::try_unwrap(live) {
+        Ok(live) => live.into_inner().map_err(|err| err.to_string())?,
+        Err(_) => return Err("Interactive stream state is still in use".to_string()),
+    };
+    *editor = live.editor;
+    *scroll = live.scroll;
+    // Submitted after the last event: wait in the queue (see /queue) for the next prompt.
+    for text in &live.steering {
+        session.steer(text);
+    }
+
+    let progress = if result.is_err() {
+        TaskbarProgress::Error
+    } else {
+        TaskbarProgress::Hidden
+    };
+    set_terminal_activity(stdout, None, progress);
+
+    if let Err(err) = result {
+        entries.push(format!("Assistant:\nError: {}", err));
+        render_interactive_ui(entries, editor, scroll, &status_bar_info(session), stdout)?;
         return Err(err.to_string());
     }
 
     let new_entries = collect_new_interactive_entries(session, start_index);
-    entries.pop();
     if new_entries.is_empty() {
         entries.push("Assistant:\n[no response]".to_string());
     } else {
         entries.extend(new_entries);
     }
-    render_interactive_ui(entries, editor, stdout)?;
-    Ok(())
+    render_interactive_ui(entries, editor, scroll, &status_bar_info(session), stdout)?;
+    Ok(live.exit_requested)
 }
 
 fn collect_new_interactive_entries(session: &AgentSession, start_index: usize) -> Vec<String> {
@@ -584,18 +1395,6 @@ fn rebuild_interactive_entries(session: &AgentSession, include_user: bool) -> Ve
     entries
 }
 
-fn parse_thinking_level_value(value: &str) -> Option<ThinkingLevel> {
-    match value {
-        "off" => Some(ThinkingLevel::Off),
-        "minimal" => Some(ThinkingLevel::Minimal),
-        "low" => Some(ThinkingLevel::Low),
-        "medium" => Some(ThinkingLevel::Medium),
-        "high" => Some(ThinkingLevel::High),
-        "xhigh" => Some(ThinkingLevel::XHigh),
-        _ => None,
-    }
-}
-
 fn parse_bool(value: &str) -> Option<bool> {
     match value {
         "true" | "1" | "yes" | "on" => Some(true),
@@ -612,28 +1411,45 @@ fn parse_queue_mode(value: &str) -> Option<QueueMode> {
     }
 }
 
-fn extract_thinking_level_suffix(pattern: &str) -> Option<ThinkingLevel> {
-    let idx = pattern.rfind(':')?;
-    parse_thinking_level_value(pattern[idx + 1..].trim())
-}
+/// Opens `$VISUAL` or `$EDITOR` (falling back to `vi`) on a copy of `content`, with the
+/// terminal handed over for the duration, and returns what the user saved.
+fn edit_in_external_editor(path: &str, content: &str) -> Result<String, String> {
+    let extension = Path::new(path)
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    let file = std::env::temp_dir().join(format!("pi-review-{}{extension}", uuid::Uuid::new_v4()));
+    std::fs::write(&file, content).map_err(|err| err.to_string())?;
 
-fn sort_models_for_display(
-    models: &[crate::coding_agent::Model],
-    current: &crate::agent::Model,
-) -> Vec<crate::coding_agent::Model> {
-    let mut choices = models.to_vec();
-    choices.sort_by(|a, b| {
-        let a_current = a.provider == current.provider && a.id == current.id;
-        let b_current = b.provider == current.provider && b.id == current.id;
-        if a_current && !b_current {
-            return std::cmp::Ordering::Less;
-        }
-        if !a_current && b_current {
-            return std::cmp::Ordering::Greater;
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or("vi");
+
+    let mut stdout = io::stdout();
+    let _ = terminal::disable_raw_mode();
+    let _ = stdout.execute(LeaveAlternateScreen);
+    let _ = stdout.execute(Show);
+    let status = process::Command::new(program)
+        .args(parts)
+        .arg(&file)
+        .status();
+    let _ = terminal::enable_raw_mode();
+    let _ = stdout.execute(EnterAlternateScreen);
+    let _ = stdout.execute(Hide);
+
+    let result = match status {
+        Ok(status) if status.success() => {
+            std::fs::read_to_string(&file).map_err(|err| err.to_string())
         }
-        a.provider.cmp(&b.provider).then_with(|| a.id.cmp(&b.id))
-    });
-    choices
+        Ok(status) => Err(format!("{program} exited with {status}")),
+        Err(err) => Err(format!("{program}: {err}")),
+    };
+    let _ = std::fs::remove_file(&file);
+    result
 }
 
 fn append_status_entry(entries: &mut Vec<String>, message: &str) {
@@ -659,6 +1475,12 @@ fn apply_setting_change(
                 *rebuild = true;
             }
         }
+        "mouse-scroll" => {
+            if let Some(enabled) = parse_bool(value) {
+                session.settings_manager.set_mouse_scroll(enabled);
+                set_mouse_capture(enabled);
+            }
+        }
         "auto-resize-images" => {
             if let Some(enabled) = parse_bool(value) {
                 session.settings_manager.set_image_auto_resize(enabled);
@@ -676,6 +1498,12 @@ fn apply_setting_change(
                 session.settings_manager.set_follow_up_mode(value);
             }
         }
+        "queue-priority" => {
+            if let Some(priority) = QueuePriority::parse(value) {
+                session.set_queue_priority(priority);
+                session.settings_manager.set_queue_priority(value);
+            }
+        }
         "thinking-level" => {
             if let Some(level) = parse_thinking_level_value(value) {
                 session.set_thinking_level(level);
@@ -738,6 +1566,14 @@ fn build_settings_items(session: &AgentSession) -> Vec<SettingItem> {
             current_value: session.settings_manager.get_show_images().to_string(),
             values: bool_values(),
         },
+        SettingItem {
+            id: "mouse-scroll".to_string(),
+            label: "Mouse scroll".to_string(),
+            description: "Scroll the chat with the mouse wheel (disables text selection)"
+                .to_string(),
+            current_value: session.settings_manager.get_mouse_scroll().to_string(),
+            values: bool_values(),
+        },
         SettingItem {
             id: "auto-resize-images".to_string(),
             label: "Auto-resize images".to_string(),
@@ -759,6 +1595,13 @@ fn build_settings_items(session: &AgentSession) -> Vec<SettingItem> {
             current_value: session.settings_manager.get_follow_up_mode().to_string(),
             values: queue_mode_values(),
         },
+        SettingItem {
+            id: "queue-priority".to_string(),
+            label: "Queue priority".to_string(),
+            description: "Order when steering and follow-up messages are both queued".to_string(),
+            current_value: session.settings_manager.get_queue_priority(),
+            values: queue_priority_values(),
+        },
         SettingItem {
             id: "thinking-level".to_string(),
             label: "Thinking level".to_string(),
@@ -876,7 +1719,25 @@ fn create_share_links(session: &AgentSession) -> Result<(String, String), String
     Ok((preview_url, gist_url))
 }
 
+fn remember_prompt(editor: &mut Editor, history_path: &Path, prompt: &str) {
+    editor.add_to_history(prompt);
+    // History is a convenience; a failed write must not interrupt the prompt.
+    let _ = append_prompt_history(history_path, prompt);
+}
+
 fn handle_key_event(key: KeyEvent, editor: &mut Editor) -> EditorAction {
+    // Ctrl-R history search consumes keys until it is accepted or cancelled
+    if editor.is_searching_history() {
+        let data = match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                "\x1b".to_string()
+            }
+            _ => key_event_to_data(&key),
+        };
+        editor.handle_input(&data);
+        return EditorAction::Continue;
+    }
+
     // Handle autocomplete mode first
     if editor.is_autocompleting() {
         match key.code {
@@ -893,10 +1754,13 @@ fn handle_key_event(key: KeyEvent, editor: &mut Editor) -> EditorAction {
                 return EditorAction::Continue;
             }
             KeyCode::Tab | KeyCode::Enter => {
-                editor.apply_autocomplete();
+                let applied = editor.apply_autocomplete();
                 // If it's a Tab, continue editing
                 // If it's Enter and the text starts with a command, let it submit
+                // (@file completions keep editing so the message can be finished)
+                let is_file_reference = applied.is_some_and(|value| value.starts_with('@'));
                 if key.code == KeyCode::Enter
+                    && !is_file_reference
                     && !key
                         .modifiers
                         .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT)
@@ -926,6 +1790,14 @@ fn handle_key_event(key: KeyEvent, editor: &mut Editor) -> EditorAction {
         KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
             return EditorAction::Exit;
         }
+        KeyCode::PageUp => return EditorAction::Scroll(ScrollAction::PageUp),
+        KeyCode::PageDown => return EditorAction::Scroll(ScrollAction::PageDown),
+        KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            return EditorAction::Scroll(ScrollAction::HalfPageUp);
+        }
+        KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            return EditorAction::Scroll(ScrollAction::HalfPageDown);
+        }
         KeyCode::Enter => {
             if key
                 .modifiers
@@ -978,13 +1850,16 @@ fn handle_key_event(key: KeyEvent, editor: &mut Editor) -> EditorAction {
         KeyCode::Char('v') if key.modifiers.contains(KeyModifiers::CONTROL) => {
             return EditorAction::PasteImage;
         }
+        KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            editor.handle_input("\x12");
+        }
         KeyCode::Char(ch) => {
             if key.modifiers.contains(KeyModifiers::CONTROL) {
                 return EditorAction::Continue;
             }
             editor.handle_input(&ch.to_string());
-            // Auto-trigger autocomplete for / at line start
-            if ch == '/' {
+            // Auto-trigger autocomplete for / at line start and @file references
+            if ch == '/' || ch == '@' {
                 editor.try_trigger_autocomplete();
             }
         }
@@ -993,87 +1868,96 @@ fn handle_key_event(key: KeyEvent, editor: &mut Editor) -> EditorAction {
     EditorAction::Continue
 }
 
-fn get_slash_commands() -> Vec<SlashCommand> {
-    vec![
-        SlashCommand::new("branch", Some("Create branch from message".to_string())),
-        SlashCommand::new("changelog", Some("Show version changelog".to_string())),
-        SlashCommand::new("clear", Some("Clear the screen".to_string())),
-        SlashCommand::new("compact", Some("Compact the session".to_string())),
-        SlashCommand::new("copy", Some("Copy last message to clipboard".to_string())),
-        SlashCommand::new("exit", Some("Exit the session".to_string())),
-        SlashCommand::new("export", Some("Export session as HTML".to_string())),
-        SlashCommand::new("help", Some("Show available commands".to_string())),
-        SlashCommand::new("hotkeys", Some("Show keyboard shortcuts".to_string())),
-        SlashCommand::new("login", Some("Login to OAuth provider".to_string())),
-        SlashCommand::new("logout", Some("Logout from OAuth provider".to_string())),
-        SlashCommand::new("model", Some("Select AI model".to_string())),
-        SlashCommand::new("new", Some("Start new session".to_string())),
-        SlashCommand::new("quit", Some("Exit the session".to_string())),
-        SlashCommand::new("reset", Some("Reset session".to_string())),
-        SlashCommand::new("resume", Some("Resume different session".to_string())),
-        SlashCommand::new("session", Some("Show session info".to_string())),
-        SlashCommand::new("settings", Some("Configure settings".to_string())),
-        SlashCommand::new("share", Some("Share session as GitHub Gist".to_string())),
-        SlashCommand::new("theme", Some("Change theme".to_string())),
-        SlashCommand::new("tree", Some("Navigate session tree".to_string())),
-    ]
-}
-
 pub fn run_interactive_mode_session(
     session: &mut AgentSession,
     messages: &[String],
     initial_message: Option<String>,
     initial_images: &[FileInputImage],
+    ui: Option<UiMode>,
 ) -> Result<(), String> {
+    match ui {
+        Some(UiMode::Line) => {
+            return run_line_mode_session(session, messages, initial_message, initial_images);
+        }
+        Some(UiMode::Full) => {}
+        None => {
+            if let Some(reason) = full_screen_unavailable_reason() {
+                // Piped use expects line mode; only explain the choice to someone at a terminal.
+                if io::stdin().is_terminal() && io::stdout().is_terminal() {
+                    eprintln!("Using line mode: {reason} (pass --ui full to override)");
+                }
+                return run_line_mode_session(session, messages, initial_message, initial_images);
+            }
+        }
+    }
     let mut entries = Vec::new();
     let theme = load_theme_or_default(session.settings_manager.get_theme().as_deref());
     set_active_theme(theme.clone());
     let mut editor = Editor::new(theme.editor_theme());
 
-    // Set up autocomplete with slash commands + prompt templates + extension commands
+    // Set up autocomplete with slash commands, prompt templates, extension commands,
+    // model ids and session paths
     let cwd = std::env::current_dir().unwrap_or_default();
-    let mut all_commands = get_slash_commands();
-
-    // Add prompt templates as slash commands for autocomplete
-    for template in session.prompt_templates() {
-        all_commands.push(SlashCommand::new(
-            template.name.clone(),
-            Some(template.description.clone()),
-        ));
-    }
-
-    // Add extension commands for autocomplete
-    for cmd in session.extension_commands() {
-        all_commands.push(SlashCommand::new(cmd.name.clone(), cmd.description.clone()));
-    }
-
-    let autocomplete_provider = CombinedAutocompleteProvider::new(all_commands, cwd);
-    editor.set_autocomplete_provider(autocomplete_provider);
+    editor.set_autocomplete_provider(session_autocomplete_provider(session, cwd));
+    let history_path = get_prompt_history_path();
+    editor.set_history_limit(PROMPT_HISTORY_LIMIT);
+    editor.set_history(load_prompt_history(&history_path));
 
     let mut stdout = io::stdout();
-    let _guard = TerminalGuard::enter(&mut stdout)?;
+    let _guard = match TerminalGuard::enter(&mut stdout) {
+        Ok(guard) => guard,
+        Err(err) if ui.is_none() => {
+            eprintln!("Warning: full-screen UI unavailable ({err}); using line mode");
+            return run_line_mode_session(session, messages, initial_message, initial_images);
+        }
+        Err(err) => return Err(err),
+    };
+    set_terminal_activity(&mut stdout, None, TaskbarProgress::Hidden);
+    if session.settings_manager.get_mouse_scroll() {
+        set_mouse_capture(true);
+    }
+    let mut scroll = ChatScroll::default();
 
     if initial_message.is_some() || !initial_images.is_empty() {
         let prompt = build_user_entry(initial_message.as_deref(), initial_images);
         let content = build_user_content_from_files(initial_message.as_deref(), initial_images)?;
-        prompt_and_append_content(
+        if prompt_and_append(
             session,
             &mut entries,
             &mut editor,
+            &mut scroll,
             &mut stdout,
             &prompt,
-            content,
-        )?;
+            Some(content),
+        )? {
+            return Ok(());
+        }
     }
 
     for message in messages {
         if message.trim().is_empty() {
             continue;
         }
-        prompt_and_append_text(session, &mut entries, &mut editor, &mut stdout, message)?;
+        if prompt_and_append(
+            session,
+            &mut entries,
+            &mut editor,
+            &mut scroll,
+            &mut stdout,
+            message,
+            None,
+        )? {
+            return Ok(());
+        }
     }
 
-    render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+    render_interactive_ui(
+        &entries,
+        &mut editor,
+        &mut scroll,
+        &status_bar_info(session),
+        &mut stdout,
+    )?;
 
     let mut modal_state = ModalState::None;
 
@@ -1083,7 +1967,7 @@ pub fn run_interactive_mode_session(
             render_modal_ui(&modal_state, &mut stdout)?;
 
             match event::read().map_err(|err| err.to_string())? {
-                Event::Key(key) => {
+                Event::Key(key) if is_key_press(&key) => {
                     // Check for Ctrl+C to exit regardless of modal state
                     if key.code == KeyCode::Char('c')
                         && key.modifiers.contains(KeyModifiers::CONTROL)
@@ -1143,12 +2027,24 @@ pub fn run_interactive_mode_session(
                                                 &format!("Model set to {}/{}", provider, model_id),
                                             );
                                         }
-                                        render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                                        render_interactive_ui(
+                                            &entries,
+                                            &mut editor,
+                                            &mut scroll,
+                                            &status_bar_info(session),
+                                            &mut stdout,
+                                        )?;
                                         continue;
                                     }
                                     ModelSelectorResult::Cancelled => {
                                         modal_state = ModalState::None;
-                                        render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                                        render_interactive_ui(
+                                            &entries,
+                                            &mut editor,
+                                            &mut scroll,
+                                            &status_bar_info(session),
+                                            &mut stdout,
+                                        )?;
                                         continue;
                                     }
                                 }
@@ -1179,7 +2075,13 @@ pub fn run_interactive_mode_session(
                                     }
                                     SettingsSelectorResult::Cancelled => {
                                         modal_state = ModalState::None;
-                                        render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                                        render_interactive_ui(
+                                            &entries,
+                                            &mut editor,
+                                            &mut scroll,
+                                            &status_bar_info(session),
+                                            &mut stdout,
+                                        )?;
                                         continue;
                                     }
                                 }
@@ -1204,6 +2106,8 @@ pub fn run_interactive_mode_session(
                                             render_interactive_ui(
                                                 &entries,
                                                 &mut editor,
+                                                &mut scroll,
+                                                &status_bar_info(session),
                                                 &mut stdout,
                                             )?;
                                             continue;
@@ -1229,7 +2133,13 @@ pub fn run_interactive_mode_session(
                                     }
                                     OAuthSelectorResult::Cancelled => {
                                         modal_state = ModalState::None;
-                                        render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                                        render_interactive_ui(
+                                            &entries,
+                                            &mut editor,
+                                            &mut scroll,
+                                            &status_bar_info(session),
+                                            &mut stdout,
+                                        )?;
                                         continue;
                                     }
                                 }
@@ -1261,6 +2171,8 @@ pub fn run_interactive_mode_session(
                                                         render_interactive_ui(
                                                             &entries,
                                                             &mut editor,
+                                                            &mut scroll,
+                                                            &status_bar_info(session),
                                                             &mut stdout,
                                                         )?;
                                                         continue;
@@ -1297,6 +2209,8 @@ pub fn run_interactive_mode_session(
                                                         render_interactive_ui(
                                                             &entries,
                                                             &mut editor,
+                                                            &mut scroll,
+                                                            &status_bar_info(session),
                                                             &mut stdout,
                                                         )?;
                                                         continue;
@@ -1311,12 +2225,31 @@ pub fn run_interactive_mode_session(
                                     }
                                     LoginDialogResult::Cancelled => {
                                         modal_state = ModalState::None;
-                                        render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                                        render_interactive_ui(
+                                            &entries,
+                                            &mut editor,
+                                            &mut scroll,
+                                            &status_bar_info(session),
+                                            &mut stdout,
+                                        )?;
                                         continue;
                                     }
                                 }
                             }
                         }
+                        ModalState::Pager(pager) => {
+                            if let Some(PagerResult::Closed) = pager.handle_input(&key_data) {
+                                modal_state = ModalState::None;
+                                render_interactive_ui(
+                                    &entries,
+                                    &mut editor,
+                                    &mut scroll,
+                                    &status_bar_info(session),
+                                    &mut stdout,
+                                )?;
+                                continue;
+                            }
+                        }
                         ModalState::None => {}
                     }
 
@@ -1350,7 +2283,13 @@ pub fn run_interactive_mode_session(
                                     modal_state = ModalState::None;
                                 }
                             }
-                            render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                            render_interactive_ui(
+                                &entries,
+                                &mut editor,
+                                &mut scroll,
+                                &status_bar_info(session),
+                                &mut stdout,
+                            )?;
                             continue;
                         }
                     }
@@ -1384,7 +2323,13 @@ pub fn run_interactive_mode_session(
                                     modal_state = ModalState::None;
                                 }
                             }
-                            render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                            render_interactive_ui(
+                                &entries,
+                                &mut editor,
+                                &mut scroll,
+                                &status_bar_info(session),
+                                &mut stdout,
+                            )?;
                             continue;
                         }
                     }
@@ -1395,16 +2340,19 @@ pub fn run_interactive_mode_session(
                             match result {
                                 BranchSelectorResult::Selected(entry_id) => {
                                     modal_state = ModalState::None;
-                                    match session.branch(&entry_id) {
-                                        Ok(result) => {
+                                    match session.branch_from(&entry_id) {
+                                        Ok(checkout) => {
                                             entries = rebuild_interactive_entries(session, true);
-                                            let msg = if result.selected_text.is_empty() {
-                                                "Created new branch.".to_string()
-                                            } else {
-                                                format!(
-                                                    "Created branch from: {}",
-                                                    truncate_to_width(&result.selected_text, 50)
-                                                )
+                                            let msg = match &checkout.session_file {
+                                                Some(path) => format!(
+                                                    "Checked out branch at {} ({})",
+                                                    checkout.entry_id,
+                                                    path.display()
+                                                ),
+                                                None => format!(
+                                                    "Checked out branch at {}",
+                                                    checkout.entry_id
+                                                ),
                                             };
                                             append_status_entry(&mut entries, &msg);
                                         }
@@ -1420,7 +2368,13 @@ pub fn run_interactive_mode_session(
                                     modal_state = ModalState::None;
                                 }
                             }
-                            render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                            render_interactive_ui(
+                                &entries,
+                                &mut editor,
+                                &mut scroll,
+                                &status_bar_info(session),
+                                &mut stdout,
+                            )?;
                             continue;
                         }
                     }
@@ -1434,7 +2388,7 @@ pub fn run_interactive_mode_session(
         }
 
         match event::read().map_err(|err| err.to_string())? {
-            Event::Key(key) => match handle_key_event(key, &mut editor) {
+            Event::Key(key) if is_key_press(&key) => match handle_key_event(key, &mut editor) {
                 EditorAction::Exit => break,
                 EditorAction::Submit => {
                     let text = editor.get_text();
@@ -1442,102 +2396,40 @@ pub fn run_interactive_mode_session(
                     let trimmed = prompt.trim();
                     editor.set_text("");
                     if trimmed.is_empty() {
-                        render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                        render_interactive_ui(
+                            &entries,
+                            &mut editor,
+                            &mut scroll,
+                            &status_bar_info(session),
+                            &mut stdout,
+                        )?;
                         continue;
                     }
                     // Handle bash command (! for normal, !! for excluded from context)
-                    if let Some(rest) = trimmed.strip_prefix('!') {
-                        let is_excluded = rest.starts_with('!');
-                        let command = if is_excluded {
-                            rest[1..].trim()
-                        } else {
-                            rest.trim()
-                        };
-                        if !command.is_empty() {
-                            editor.add_to_history(&prompt);
-                            match session.execute_bash(command) {
-                                Ok(result) => {
-                                    // Format output like a shell
-                                    let mut display = format!("$ {command}\n");
-                                    if !result.output.is_empty() {
-                                        display.push_str(&result.output);
-                                        if !result.output.ends_with('\n') {
-                                            display.push('\n');
-                                        }
-                                    }
-                                    if let Some(code) = result.exit_code {
-                                        if code != 0 {
-                                            display.push_str(&format!("[exit code: {code}]\n"));
-                                        }
-                                    }
-                                    if result.cancelled {
-                                        display.push_str("[cancelled]\n");
-                                    }
-                                    if is_excluded {
-                                        // For !!, just show output without adding to context
-                                        append_status_entry(&mut entries, display.trim_end());
-                                    } else {
-                                        // For !, show output and potentially add to context
-                                        // (though execute_bash doesn't add to session context)
-                                        append_status_entry(&mut entries, display.trim_end());
-                                    }
-                                }
-                                Err(err) => {
-                                    append_status_entry(
-                                        &mut entries,
-                                        &format!("Bash error: {err}"),
-                                    );
-                                }
-                            }
-                            render_interactive_ui(&entries, &mut editor, &mut stdout)?;
-                            continue;
+                    if let Some(outcome) = run_shared_command(session, trimmed) {
+                        if trimmed.starts_with('!') {
+                            remember_prompt(&mut editor, &history_path, &prompt);
                         }
-                    }
-                    if trimmed.starts_with("/export") {
-                        let rest = trimmed.trim_start_matches("/export").trim();
-                        let output_path = if rest.is_empty() { None } else { Some(rest) };
-                        let output_path = output_path.map(PathBuf::from);
-                        match session.export_to_html_with_path(output_path.as_ref()) {
-                            Ok(result) => append_status_entry(
-                                &mut entries,
-                                &format!("Session exported to: {}", result.path.display()),
-                            ),
-                            Err(err) => append_status_entry(
-                                &mut entries,
-                                &format!("Failed to export session: {err}"),
-                            ),
-                        }
-                        render_interactive_ui(&entries, &mut editor, &mut stdout)?;
-                        continue;
-                    }
-                    if trimmed.starts_with("/compact") {
-                        let rest = trimmed.trim_start_matches("/compact").trim();
-                        let custom_instructions = if rest.is_empty() { None } else { Some(rest) };
-                        if session.messages().len() < 2 {
-                            append_status_entry(
-                                &mut entries,
-                                "Nothing to compact (no messages yet)",
-                            );
-                            render_interactive_ui(&entries, &mut editor, &mut stdout)?;
-                            continue;
-                        }
-                        match session.compact_with_instructions(custom_instructions) {
-                            Ok(result) => {
+                        match outcome {
+                            CommandOutcome::Message(message) => {
+                                append_status_entry(&mut entries, &message);
+                            }
+                            CommandOutcome::Cleared(message) => {
+                                entries.clear();
+                                append_status_entry(&mut entries, &message);
+                            }
+                            CommandOutcome::Rebuilt(message) => {
                                 entries = rebuild_interactive_entries(session, true);
-                                append_status_entry(
-                                    &mut entries,
-                                    &format!(
-                                        "Compaction complete (tokens before: {})",
-                                        result.tokens_before
-                                    ),
-                                );
+                                append_status_entry(&mut entries, &message);
                             }
-                            Err(err) => append_status_entry(
-                                &mut entries,
-                                &format!("Compaction failed: {err}"),
-                            ),
                         }
-                        render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                        render_interactive_ui(
+                            &entries,
+                            &mut editor,
+                            &mut scroll,
+                            &status_bar_info(session),
+                            &mut stdout,
+                        )?;
                         continue;
                     }
                     if trimmed.starts_with("/share") {
@@ -1548,90 +2440,42 @@ pub fn run_interactive_mode_session(
                             ),
                             Err(err) => append_status_entry(&mut entries, &err),
                         }
-                        render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                        render_interactive_ui(
+                            &entries,
+                            &mut editor,
+                            &mut scroll,
+                            &status_bar_info(session),
+                            &mut stdout,
+                        )?;
                         continue;
                     }
-                    if trimmed.starts_with("/model") {
-                        let rest = trimmed.trim_start_matches("/model").trim();
+                    if trimmed == "/model" {
                         let available = session.get_available_models();
-                        let current_model = session.agent.state().model;
-                        if rest.is_empty() {
-                            // Open model selector UI
-                            if available.is_empty() {
-                                append_status_entry(
-                                    &mut entries,
-                                    "No models available. Set an API key in auth.json or env.",
-                                );
-                                render_interactive_ui(&entries, &mut editor, &mut stdout)?;
-                                continue;
-                            }
-                            let (_, height) = terminal::size().unwrap_or((80, 24));
-                            let max_visible = ((height as usize).saturating_sub(10)).clamp(5, 15);
-                            let model_items: Vec<ModelItem> = available
-                                .iter()
-                                .map(|m| {
-                                    ModelItem::from_model(
-                                        m,
-                                        &current_model.provider,
-                                        &current_model.id,
-                                    )
-                                })
-                                .collect();
-                            let selector = ModelSelectorComponent::new(model_items, max_visible);
-                            modal_state =
-                                ModalState::ModelSelector(ModelSelectorState { selector });
-                            continue;
-                        }
                         if available.is_empty() {
                             append_status_entry(
                                 &mut entries,
                                 "No models available. Set an API key in auth.json or env.",
                             );
-                            render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                            render_interactive_ui(
+                                &entries,
+                                &mut editor,
+                                &mut scroll,
+                                &status_bar_info(session),
+                                &mut stdout,
+                            )?;
                             continue;
                         }
-
-                        let (selected, warning) = if let Ok(index) = rest.parse::<usize>() {
-                            let choices = sort_models_for_display(&available, &current_model);
-                            if index > 0 && index <= choices.len() {
-                                (Some(choices[index - 1].clone()), None)
-                            } else {
-                                append_status_entry(
-                                    &mut entries,
-                                    "Model index out of range. Run /model to see options.",
-                                );
-                                render_interactive_ui(&entries, &mut editor, &mut stdout)?;
-                                continue;
-                            }
-                        } else {
-                            let parsed = parse_model_pattern(rest, &available);
-                            if parsed.model.is_none() {
-                                append_status_entry(
-                                    &mut entries,
-                                    "No model matched that pattern. Run /model to see options.",
-                                );
-                                render_interactive_ui(&entries, &mut editor, &mut stdout)?;
-                                continue;
-                            }
-                            (parsed.model, parsed.warning)
-                        };
-
-                        let selected = selected.expect("model should be selected");
-                        session.set_model(to_agent_model(&selected));
-                        session
-                            .settings_manager
-                            .set_default_model_and_provider(&selected.provider, &selected.id);
-                        let mut message =
-                            format!("Model set to {}/{}", selected.provider, selected.id);
-                        if let Some(level) = extract_thinking_level_suffix(rest) {
-                            session.set_thinking_level(level);
-                            message.push_str(&format!(" (thinking: {})", level.as_str()));
-                        }
-                        append_status_entry(&mut entries, &message);
-                        if let Some(warning) = warning {
-                            append_status_entry(&mut entries, &warning);
-                        }
-                        render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                        let current_model = session.agent.state().model;
+                        let (_, height) = terminal::size().unwrap_or((80, 24));
+                        let max_visible = ((height as usize).saturating_sub(10)).clamp(5, 15);
+                        let model_items: Vec<ModelItem> = available
+                            .iter()
+                            .map(|m| {
+                                ModelItem::from_model(m, &current_model.provider, &current_model.id)
+                            })
+                            .collect();
+                        let selector = ModelSelectorComponent::new(model_items, max_visible);
+                        modal_state = ModalState::ModelSelector(ModelSelectorState { selector });
                         continue;
                     }
                     if trimmed.starts_with("/settings") {
@@ -1652,7 +2496,13 @@ pub fn run_interactive_mode_session(
                             Some(key) => key.to_ascii_lowercase(),
                             None => {
                                 append_status_entry(&mut entries, "Usage: /settings <key> <value>");
-                                render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                                render_interactive_ui(
+                                    &entries,
+                                    &mut editor,
+                                    &mut scroll,
+                                    &status_bar_info(session),
+                                    &mut stdout,
+                                )?;
                                 continue;
                             }
                         };
@@ -1660,7 +2510,13 @@ pub fn run_interactive_mode_session(
                             Some(value) => value,
                             None => {
                                 append_status_entry(&mut entries, "Usage: /settings <key> <value>");
-                                render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                                render_interactive_ui(
+                                    &entries,
+                                    &mut editor,
+                                    &mut scroll,
+                                    &status_bar_info(session),
+                                    &mut stdout,
+                                )?;
                                 continue;
                             }
                         };
@@ -1681,6 +2537,13 @@ pub fn run_interactive_mode_session(
                                 }
                                 None => error = Some("Expected true/false for show-images."),
                             },
+                            "mouse-scroll" => match parse_bool(value) {
+                                Some(enabled) => {
+                                    session.settings_manager.set_mouse_scroll(enabled);
+                                    set_mouse_capture(enabled);
+                                }
+                                None => error = Some("Expected true/false for mouse-scroll."),
+                            },
                             "auto-resize-images" => match parse_bool(value) {
                                 Some(enabled) => {
                                     session.settings_manager.set_image_auto_resize(enabled);
@@ -1704,6 +2567,16 @@ pub fn run_interactive_mode_session(
                                         Some("follow-up-mode must be 'all' or 'one-at-a-time'.");
                                 }
                             }
+                            "queue-priority" => match QueuePriority::parse(value) {
+                                Some(priority) => {
+                                    session.set_queue_priority(priority);
+                                    session.settings_manager.set_queue_priority(value);
+                                }
+                                None => {
+                                    error =
+                                        Some("queue-priority must be 'steering-first' or 'fifo'.");
+                                }
+                            },
                             "thinking-level" => match parse_thinking_level_value(value) {
                                 Some(level) => session.set_thinking_level(level),
                                 None => {
@@ -1749,14 +2622,26 @@ pub fn run_interactive_mode_session(
 
                         if let Some(error) = error {
                             append_status_entry(&mut entries, error);
-                            render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                            render_interactive_ui(
+                                &entries,
+                                &mut editor,
+                                &mut scroll,
+                                &status_bar_info(session),
+                                &mut stdout,
+                            )?;
                             continue;
                         }
                         if rebuild {
                             entries = rebuild_interactive_entries(session, true);
                         }
                         append_status_entry(&mut entries, &format!("Updated {} to {}", key, value));
-                        render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                        render_interactive_ui(
+                            &entries,
+                            &mut editor,
+                            &mut scroll,
+                            &status_bar_info(session),
+                            &mut stdout,
+                        )?;
                         continue;
                     }
                     if trimmed == "/changelog" {
@@ -1778,19 +2663,28 @@ pub fn run_interactive_mode_session(
                             None => "No CHANGELOG.md found.".to_string(),
                         };
                         append_status_entry(&mut entries, &message);
-                        render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                        render_interactive_ui(
+                            &entries,
+                            &mut editor,
+                            &mut scroll,
+                            &status_bar_info(session),
+                            &mut stdout,
+                        )?;
                         continue;
                     }
                     if trimmed == "/hotkeys" {
                         let hotkeys = [
-                            "Enter: send message",
+                            "Enter: send message (while a response runs: steer it)",
                             "Ctrl/Alt/Shift+Enter: new line",
-                            "Ctrl+C: exit",
+                            "Ctrl+C: abort the running response (twice to exit), exit when idle",
                             "Ctrl+V: paste image from clipboard",
+                            "Ctrl+R: search prompt history",
                             "Arrow keys: move cursor / history",
                             "Ctrl+Left/Right: move by word",
                             "Ctrl+A: start of line",
                             "Ctrl+W or Alt+Backspace: delete word",
+                            "PageUp/PageDown: scroll chat by a page",
+                            "Ctrl+U/Ctrl+D: scroll chat by half a page",
                             "Tab: file autocomplete",
                             "! command: run shell command",
                             "!! command: run shell command (excluded from context)",
@@ -1798,7 +2692,13 @@ pub fn run_interactive_mode_session(
                         ]
                         .join("\n");
                         append_status_entry(&mut entries, &hotkeys);
-                        render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                        render_interactive_ui(
+                            &entries,
+                            &mut editor,
+                            &mut scroll,
+                            &status_bar_info(session),
+                            &mut stdout,
+                        )?;
                         continue;
                     }
                     if matches!(trimmed, "/exit" | "/quit") {
@@ -1806,16 +2706,24 @@ pub fn run_interactive_mode_session(
                     }
                     if trimmed == "/clear" {
                         entries.clear();
-                        render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                        render_interactive_ui(
+                            &entries,
+                            &mut editor,
+                            &mut scroll,
+                            &status_bar_info(session),
+                            &mut stdout,
+                        )?;
                         continue;
                     }
                     if trimmed == "/help" {
                         let help_text = [
                             "Available commands:",
-                            "  /branch       - Create branch from message",
+                            "  /branch       - Continue from an earlier reply in a new branch",
                             "  /changelog    - Show version changelog",
+                            "  /checkpoint [name] - Name this point for /rewind (no name: list)",
                             "  /clear        - Clear the screen",
                             "  /compact      - Compact the session",
+                            "  /context [enable|disable <name>] - List or toggle context packs",
                             "  /copy         - Copy last assistant message to clipboard",
                             "  /export       - Export session as HTML",
                             "  /help         - Show this help",
@@ -1824,20 +2732,37 @@ pub fn run_interactive_mode_session(
                             "  /logout       - Logout from OAuth provider",
                             "  /model        - Select AI model",
                             "  /new          - Start new session",
+                            "  /persona [name] - List or switch personas",
+                            "  /pin [id]     - Keep a message (default: last) through compaction",
+                            "  /queue [remove|edit|clear] - Show or edit queued messages",
                             "  /reset        - Reset/clear the session",
-                            "  /resume       - Resume different session",
+                            "  /resume [path] - Resume different session",
+                            "  /revert [path] - Restore a file changed by tools (no path: list)",
+                            "  /rewind [name] - Return to a checkpoint and undo later file changes",
                             "  /session      - Show session information",
+                            "  /sessions     - List and resume sessions",
                             "  /settings     - Configure settings",
                             "  /share        - Share session as GitHub Gist",
+                            "  /show [n]     - Open message n (default: last) in the pager",
                             "  /theme <name> - Change theme",
+                            "  /thinking [level] - Set or cycle thinking level",
+                            "  /tools        - List active tools",
                             "  /tree         - Navigate session tree",
+                            "  /undo         - Undo the last file change made by a tool",
+                            "  /unpin [id]   - Unpin a message (default: last pinned)",
                             "  /exit, /quit  - Exit the session",
                             "",
                             "Type / to see autocomplete suggestions.",
                         ]
                         .join("\n");
                         append_status_entry(&mut entries, &help_text);
-                        render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                        render_interactive_ui(
+                            &entries,
+                            &mut editor,
+                            &mut scroll,
+                            &status_bar_info(session),
+                            &mut stdout,
+                        )?;
                         continue;
                     }
                     if trimmed.starts_with("/theme") {
@@ -1877,26 +2802,13 @@ pub fn run_interactive_mode_session(
                                 ),
                             );
                         }
-                        render_interactive_ui(&entries, &mut editor, &mut stdout)?;
-                        continue;
-                    }
-                    if trimmed == "/reset" {
-                        session.new_session();
-                        entries.clear();
-                        append_status_entry(&mut entries, "Session reset.");
-                        render_interactive_ui(&entries, &mut editor, &mut stdout)?;
-                        continue;
-                    }
-                    if trimmed == "/session" {
-                        let session_id = session.session_id();
-                        let message_count = session.messages().len();
-                        let model = &session.agent.state().model;
-                        let info = format!(
-                            "Session Info:\n  ID: {session_id}\n  Messages: {message_count}\n  Model: {}/{}",
-                            model.provider, model.id
-                        );
-                        append_status_entry(&mut entries, &info);
-                        render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                        render_interactive_ui(
+                            &entries,
+                            &mut editor,
+                            &mut scroll,
+                            &status_bar_info(session),
+                            &mut stdout,
+                        )?;
                         continue;
                     }
                     if trimmed == "/copy" {
@@ -1921,25 +2833,51 @@ pub fn run_interactive_mode_session(
                         } else {
                             append_status_entry(&mut entries, "No assistant messages to copy.");
                         }
-                        render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                        render_interactive_ui(
+                            &entries,
+                            &mut editor,
+                            &mut scroll,
+                            &status_bar_info(session),
+                            &mut stdout,
+                        )?;
                         continue;
                     }
-                    if trimmed == "/new" {
-                        // Create a new session by resetting and generating a new ID
-                        session.new_session();
-                        entries.clear();
-                        append_status_entry(
-                            &mut entries,
-                            &format!("New session started: {}", session.session_id()),
-                        );
-                        render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                    if trimmed == "/show" || trimmed.starts_with("/show ") {
+                        let arg = trimmed.trim_start_matches("/show").trim();
+                        let messages = session.messages();
+                        let index = match resolve_message_index(arg, messages.len()) {
+                            Ok(index) => index,
+                            Err(err) => {
+                                append_status_entry(&mut entries, &err);
+                                render_interactive_ui(
+                                    &entries,
+                                    &mut editor,
+                                    &mut scroll,
+                                    &status_bar_info(session),
+                                    &mut stdout,
+                                )?;
+                                continue;
+                            }
+                        };
+                        // Images stay as placeholders; the pager only shows text.
+                        let text =
+                            format_message_for_interactive(&messages[index], true, false, false)
+                                .unwrap_or_else(|| "[no text content]".to_string());
+                        let title = format!("Message {} of {}", index + 1, messages.len());
+                        modal_state = ModalState::Pager(Pager::new(title, &text));
                         continue;
                     }
                     if trimmed == "/tree" {
                         let tree = session.session_manager.get_tree();
                         if tree.is_empty() {
                             append_status_entry(&mut entries, "Session tree is empty.");
-                            render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                            render_interactive_ui(
+                                &entries,
+                                &mut editor,
+                                &mut scroll,
+                                &status_bar_info(session),
+                                &mut stdout,
+                            )?;
                             continue;
                         }
                         let (_, height) = terminal::size().unwrap_or((80, 24));
@@ -1952,23 +2890,35 @@ pub fn run_interactive_mode_session(
                         continue;
                     }
                     if trimmed == "/branch" {
-                        let candidates = session.get_user_messages_for_branching();
+                        let candidates = session.get_branch_checkout_candidates();
                         if candidates.is_empty() {
-                            append_status_entry(&mut entries, "No user messages to branch from.");
-                            render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                            append_status_entry(&mut entries, "No replies to branch from.");
+                            render_interactive_ui(
+                                &entries,
+                                &mut editor,
+                                &mut scroll,
+                                &status_bar_info(session),
+                                &mut stdout,
+                            )?;
                             continue;
                         }
                         modal_state =
                             ModalState::BranchSelector(BranchSelectorState::new(candidates));
                         continue;
                     }
-                    if trimmed == "/resume" {
+                    if matches!(trimmed, "/resume" | "/sessions") {
                         let cwd = std::env::current_dir().unwrap_or_default();
                         let session_dir = Some(session.session_manager.get_session_dir());
                         let sessions = SessionManager::list(&cwd, session_dir);
                         if sessions.is_empty() {
                             append_status_entry(&mut entries, "No sessions found.");
-                            render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                            render_interactive_ui(
+                                &entries,
+                                &mut editor,
+                                &mut scroll,
+                                &status_bar_info(session),
+                                &mut stdout,
+                            )?;
                             continue;
                         }
                         let (_, height) = terminal::size().unwrap_or((80, 24));
@@ -2005,7 +2955,13 @@ pub fn run_interactive_mode_session(
                                 &mut entries,
                                 "No OAuth providers logged in. Use /login first.",
                             );
-                            render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                            render_interactive_ui(
+                                &entries,
+                                &mut editor,
+                                &mut scroll,
+                                &status_bar_info(session),
+                                &mut stdout,
+                            )?;
                             continue;
                         }
 
@@ -2020,28 +2976,86 @@ pub fn run_interactive_mode_session(
                         });
                         continue;
                     }
-                    editor.add_to_history(&prompt);
-                    prompt_and_append_text(
+                    remember_prompt(&mut editor, &history_path, &prompt);
+                    let content = match expand_file_references(&prompt) {
+                        Ok(content) => content,
+                        Err(message) => {
+                            append_status_entry(&mut entries, &message);
+                            render_interactive_ui(
+                                &entries,
+                                &mut editor,
+                                &mut scroll,
+                                &status_bar_info(session),
+                                &mut stdout,
+                            )?;
+                            continue;
+                        }
+                    };
+                    if prompt_and_append(
                         session,
                         &mut entries,
                         &mut editor,
+                        &mut scroll,
                         &mut stdout,
                         &prompt,
-                    )?
+                        content,
+                    )? {
+                        break;
+                    }
                 }
                 EditorAction::Continue => {
-                    render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                    render_interactive_ui(
+                        &entries,
+                        &mut editor,
+                        &mut scroll,
+                        &status_bar_info(session),
+                        &mut stdout,
+                    )?;
                 }
                 EditorAction::PasteImage => {
                     // Handle Ctrl+V image paste
                     if let Some(path) = paste_image_from_clipboard() {
                         editor.insert_text_at_cursor(&path);
                     }
-                    render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                    render_interactive_ui(
+                        &entries,
+                        &mut editor,
+                        &mut scroll,
+                        &status_bar_info(session),
+                        &mut stdout,
+                    )?;
+                }
+                EditorAction::Scroll(action) => {
+                    scroll.apply(action);
+                    render_interactive_ui(
+                        &entries,
+                        &mut editor,
+                        &mut scroll,
+                        &status_bar_info(session),
+                        &mut stdout,
+                    )?;
                 }
             },
+            Event::Mouse(mouse) => {
+                if let Some(action) = mouse_scroll_action(mouse.kind) {
+                    scroll.apply(action);
+                    render_interactive_ui(
+                        &entries,
+                        &mut editor,
+                        &mut scroll,
+                        &status_bar_info(session),
+                        &mut stdout,
+                    )?;
+                }
+            }
             Event::Resize(_, _) => {
-                render_interactive_ui(&entries, &mut editor, &mut stdout)?;
+                render_interactive_ui(
+                    &entries,
+                    &mut editor,
+                    &mut scroll,
+                    &status_bar_info(session),
+                    &mut stdout,
+                )?;
             }
             _ => {}
         }
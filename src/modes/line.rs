@@ -0,0 +1,313 @@
+//! Line-based interactive mode for when stdin or stdout is not a terminal (pipes,
+//! `docker exec` without `-t`, ssh without a pty). Reads one prompt per line, streams
+//! the reply as plain text and supports the text-only slash commands.
+
+use crate::agent::{AgentEvent, AgentMessage};
+use crate::cli::file_inputs::FileInputImage;
+use crate::coding_agent::{AgentSession, AgentSessionEvent};
+use crate::core::messages::{AssistantMessage, ContentBlock, UserContent};
+use std::cell::RefCell;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+
+use super::build_user_content_from_files;
+use super::commands::{run_shared_command, sort_models_for_display, CommandOutcome};
+use super::interactive::expand_file_references;
+
+/// Commands that need the full-screen interface (pickers, pager, clipboard, browser).
+const TERMINAL_ONLY_COMMANDS: &[&str] = &[
+    "/branch",
+    "/changelog",
+    "/copy",
+    "/hotkeys",
+    "/login",
+    "/logout",
+    "/resume",
+    "/sessions",
+    "/settings",
+    "/share",
+    "/show",
+    "/theme",
+    "/tree",
+];
+
+const HELP_TEXT: &str = "Available commands:
+  /checkpoint [name]      - Name this point for /rewind (no name: list)
+  /compact [instructions] - Compact the session
+  /context [enable|disable <name>] - List or toggle context packs
+  /export [path]          - Export session as HTML
+  /model [n|pattern]      - List models or switch model
+  /new                    - Start new session
+  /persona [name]         - List or switch personas
+  /pin [id]               - Keep a message (default: last) through compaction
+  /queue [remove|edit|clear] - Show or edit queued messages
+  /reset                  - Reset the session
+  /resume <path>          - Resume a session file
+  /revert [path]          - Restore a file changed by tools (no path: list)
+  /rewind [name]          - Return to a checkpoint and undo later file changes
+  /session                - Show session information
+  /thinking [level]       - Set or cycle thinking level
+  /tools                  - List active tools
+  /undo                   - Undo the last file change made by a tool
+  /unpin [id]             - Unpin a message (default: last pinned)
+  /help                   - Show this help
+  /exit, /quit            - Exit
+  !command                - Run a shell command
+
+End a line with \\ to continue the prompt on the next line.";
+
+pub fn run_line_mode_session(
+    session: &mut AgentSession,
+    messages: &[String],
+    initial_message: Option<String>,
+    initial_images: &[FileInputImage],
+) -> Result<(), String> {
+    let output = Rc::new(RefCell::new(io::stdout()));
+    if initial_message.is_some() || !initial_images.is_empty() {
+        let content = build_user_content_from_files(initial_message.as_deref(), initial_images)?;
+        run_prompt(session, &output, "", Some(content))?;
+    }
+    for message in messages {
+        if !message.trim().is_empty() {
+            run_prompt(session, &output, message, None)?;
+        }
+    }
+    run_line_loop(session, io::stdin().lock(), output)
+}
+
+/// Read prompts and commands from `input` until EOF or `/exit`.
+pub fn run_line_loop<R: BufRead, W: Write + 'static>(
+    session: &mut AgentSession,
+    mut input: R,
+    output: Rc<RefCell<W>>,
+) -> Result<(), String> {
+    let model = session.agent.state().model;
+    write_line(
+        &output,
+        &format!(
+            "pi (line mode) · {}/{} · /help for commands, /exit to quit",
+            model.provider, model.id
+        ),
+    )?;
+
+    loop {
+        let Some(prompt) = read_prompt(&mut input, &output)? else {
+            return Ok(());
+        };
+        let trimmed = prompt.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if matches!(trimmed, "/exit" | "/quit") {
+            return Ok(());
+        }
+        if trimmed == "/help" {
+            write_line(&output, HELP_TEXT)?;
+            continue;
+        }
+        if trimmed == "/clear" {
+            continue;
+        }
+        if trimmed == "/model" {
+            write_line(&output, &list_models(session))?;
+            continue;
+        }
+        let command = trimmed.split_whitespace().next().unwrap_or_default();
+        // `/resume <path>` needs no picker.
+        let resumes_path = command == "/resume" && trimmed.len() > command.len();
+        if TERMINAL_ONLY_COMMANDS.contains(&command) && !resumes_path {
+            write_line(
+                &output,
+                &format!("{command} needs an interactive terminal (run pi with a TTY)."),
+            )?;
+            continue;
+        }
+        if let Some(outcome) = run_shared_command(session, trimmed) {
+            let (CommandOutcome::Message(message)
+            | CommandOutcome::Cleared(message)
+            | CommandOutcome::Rebuilt(message)) = outcome;
+            write_line(&output, &message)?;
+            continue;
+        }
+
+        let content = match expand_file_references(&prompt) {
+            Ok(content) => content,
+            Err(message) => {
+                write_line(&output, &message)?;
+                continue;
+            }
+        };
+        if let Err(err) = run_prompt(session, &output, &prompt, content) {
+            write_line(&output, &format!("Error: {err}"))?;
+        }
+    }
+}
+
+/// Read one prompt, joining lines that end in a backslash. `None` at end of input.
+fn read_prompt<R: BufRead, W: Write>(
+    input: &mut R,
+    output: &Rc<RefCell<W>>,
+) -> Result<Option<String>, String> {
+    let mut prompt = String::new();
+    let mut marker = "> ";
+    loop {
+        {
+            let mut output = output.borrow_mut();
+            write!(output, "{marker}").map_err(|err| err.to_string())?;
+            output.flush().map_err(|err| err.to_string())?;
+        }
+        let mut line = String::new();
+        let read = input
+            .read_line(&mut line)
+            .map_err(|err| format!("Failed to read input: {err}"))?;
+        if read == 0 {
+            write_line(output, "")?;
+            return Ok((!prompt.is_empty()).then_some(prompt));
+        }
+        let line = line.trim_end_matches(['\n', '\r']);
+        match line.strip_suffix('\\') {
+            Some(continued) => {
+                prompt.push_str(continued);
+                prompt.push('\n');
+                marker = ". ";
+            }
+            None => {
+                prompt.push_str(line);
+                return Ok(Some(prompt));
+            }
+        }
+    }
+}
+
+fn list_models(session: &AgentSession) -> String {
+    let available = session.get_available_models();
+    if available.is_empty() {
+        return "No models available. Set an API key in auth.json or env.".to_string();
+    }
+    let current = session.agent.state().model;
+    let mut lines = vec!["Models:".to_string()];
+    for (index, model) in sort_models_for_display(&available, &current)
+        .iter()
+        .enumerate()
+    {
+        let marker = if model.provider == current.provider && model.id == current.id {
+            "*"
+        } else {
+            " "
+        };
+        lines.push(format!(
+            "  {marker} {:>2}) {}/{}",
+            index + 1,
+            model.provider,
+            model.id
+        ));
+    }
+    lines.push("Use /model <n> or /model <pattern> to switch.".to_string());
+    lines.join("\n")
+}
+
+/// Run a prompt, printing assistant text as it streams and a line per tool call.
+fn run_prompt<W: Write + 'static>(
+    session: &mut AgentSession,
+    output: &Rc<RefCell<W>>,
+    prompt: &str,
+    content: Option<UserContent>,
+) -> Result<(), String> {
+    let printer = Rc::new(RefCell::new(StreamPrinter {
+        output: output.clone(),
+        printed: 0,
+    }));
+    let listener = printer.clone();
+    let unsubscribe = session.subscribe(move |event| {
+        if let Ok(mut printer) = listener.try_borrow_mut() {
+            printer.handle_event(event);
+        }
+    });
+    let result = match content {
+        Some(content) => session.prompt_content(content),
+        None => session.prompt(prompt),
+    };
+    unsubscribe();
+    result.map_err(|err| err.to_string())
+}
+
+struct StreamPrinter<W: Write> {
+    output: Rc<RefCell<W>>,
+    /// Bytes of the current assistant message's text already written.
+    printed: usize,
+}
+
+impl<W: Write> StreamPrinter<W> {
+    fn handle_event(&mut self, event: &AgentSessionEvent) {
+        let AgentSessionEvent::Agent(event) = event else {
+            return;
+        };
+        match event.as_ref() {
+            AgentEvent::MessageStart {
+                message: AgentMessage::Assistant(_),
+            } => self.printed = 0,
+            AgentEvent::MessageUpdate {
+                message: AgentMessage::Assistant(message),
+                ..
+            } => self.write_new_text(message),
+            AgentEvent::MessageEnd {
+                message: AgentMessage::Assistant(message),
+            } => {
+                self.write_new_text(message);
+                let mut output = self.output.borrow_mut();
+                if self.printed > 0 {
+                    let _ = writeln!(output);
+                }
+                if message.stop_reason == "error" || message.is_aborted() {
+                    let reason = message
+                        .error_message
+                        .clone()
+                        .unwrap_or_else(|| format!("Request {}", message.stop_reason));
+                    let _ = writeln!(output, "Error: {reason}");
+                }
+                let _ = output.flush();
+                self.printed = 0;
+            }
+            AgentEvent::ToolExecutionStart { tool_name, .. } => {
+                let _ = writeln!(self.output.borrow_mut(), "[{tool_name}]");
+            }
+            AgentEvent::ToolExecutionEnd {
+                tool_name,
+                is_error: true,
+                ..
+            } => {
+                let _ = writeln!(self.output.borrow_mut(), "[{tool_name} failed]");
+            }
+            AgentEvent::LimitReached { reason, .. } => {
+                let _ = writeln!(self.output.borrow_mut(), "Stopped: {reason}");
+            }
+            _ => {}
+        }
+    }
+
+    fn write_new_text(&mut self, message: &AssistantMessage) {
+        let text = message
+            .content
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        // Output filters can rewrite earlier text; only append when it still extends what was shown.
+        if text.len() <= self.printed || !text.is_char_boundary(self.printed) {
+            return;
+        }
+        let mut output = self.output.borrow_mut();
+        let _ = write!(output, "{}", &text[self.printed..]);
+        let _ = output.flush();
+        self.printed = text.len();
+    }
+}
+
+fn write_line<W: Write>(output: &Rc<RefCell<W>>, text: &str) -> Result<(), String> {
+    let mut output = output.borrow_mut();
+    writeln!(output, "{text}").map_err(|err| err.to_string())?;
+    output.flush().map_err(|err| err.to_string())
+}
@@ -1,10 +1,14 @@
 use crate::cli::file_inputs::FileInputImage;
 use crate::core::messages::{ContentBlock, UserContent};
 
+mod commands;
 pub mod interactive;
+pub mod line;
 pub mod print;
 
-pub use interactive::run_interactive_mode_session;
+pub use commands::{builtin_slash_commands, session_autocomplete_provider};
+pub use interactive::{limited_terminal_reason, run_interactive_mode_session};
+pub use line::{run_line_loop, run_line_mode_session};
 pub use print::run_print_mode_session;
 
 pub(crate) fn build_user_content_from_files(
@@ -1,11 +1,13 @@
-use crate::agent::AgentMessage;
+use crate::agent::{AgentEvent, AgentMessage};
 use crate::cli::event_json::serialize_session_event;
+use crate::cli::exit_codes::{is_tool_denial, ExitReason, PrintError};
 use crate::cli::file_inputs::FileInputImage;
-use crate::coding_agent::AgentSession;
-use crate::core::messages::ContentBlock;
+use crate::coding_agent::{load_theme_or_default, AgentSession, AgentSessionEvent};
+use crate::core::messages::{AssistantMessage, ContentBlock};
+use crate::tui::Markdown;
 use crate::Mode;
 use serde_json::Value;
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Write};
 
 use super::build_user_content_from_files;
 
@@ -15,21 +17,28 @@ pub fn run_print_mode_session(
     messages: &[String],
     initial_message: Option<String>,
     initial_images: &[FileInputImage],
-) -> Result<(), String> {
+) -> Result<(), PrintError> {
     if matches!(mode, Mode::Json) {
         let _ = session.subscribe(|event| {
             if let Some(value) = serialize_session_event(event) {
                 emit_json(&value);
             }
         });
+    } else {
+        let _ = session.subscribe(|event| {
+            if let AgentSessionEvent::Agent(event) = event {
+                if let AgentEvent::LimitReached { reason, .. } = event.as_ref() {
+                    eprintln!("Stopped: {reason}");
+                }
+            }
+        });
     }
 
+    let first_new = session.messages().len();
     let mut sent_any = false;
     if initial_message.is_some() || !initial_images.is_empty() {
         let content = build_user_content_from_files(initial_message.as_deref(), initial_images)?;
-        session
-            .prompt_content(content)
-            .map_err(|err| err.to_string())?;
+        session.prompt_content(content)?;
         sent_any = true;
     }
 
@@ -37,22 +46,26 @@ pub fn run_print_mode_session(
         if message.trim().is_empty() {
             continue;
         }
-        session.prompt(message).map_err(|err| err.to_string())?;
+        session.prompt(message)?;
         sent_any = true;
     }
 
     if !sent_any {
-        return Err("No messages provided.".to_string());
+        return Err("No messages provided.".to_string().into());
     }
 
+    let assistant = last_assistant_reply(session)?;
     if matches!(mode, Mode::Text) {
-        print_last_assistant_text(session)?;
+        print_assistant_text(session, &assistant);
+    }
+    // The reply is still printed, but a refused tool call fails the run.
+    match first_tool_denial(&session.messages()[first_new..]) {
+        Some(denial) => Err(PrintError::new(ExitReason::ToolDenied, denial)),
+        None => Ok(()),
     }
-
-    Ok(())
 }
 
-fn print_last_assistant_text(session: &AgentSession) -> Result<(), String> {
+fn last_assistant_reply(session: &AgentSession) -> Result<AssistantMessage, PrintError> {
     let messages = session.messages();
     let assistant = messages.iter().rev().find_map(|message| {
         if let AgentMessage::Assistant(assistant) = message {
@@ -63,18 +76,56 @@ fn print_last_assistant_text(session: &AgentSession) -> Result<(), String> {
     });
 
     let assistant = assistant.ok_or_else(|| "No assistant response.".to_string())?;
-    if assistant.stop_reason == "error" || assistant.stop_reason == "aborted" {
-        return Err(assistant
+    let message = || {
+        assistant
             .error_message
             .clone()
-            .unwrap_or_else(|| format!("Request {}", assistant.stop_reason)));
+            .unwrap_or_else(|| format!("Request {}", assistant.stop_reason))
+    };
+    if assistant.is_aborted() {
+        return Err(PrintError::new(ExitReason::Aborted, message()));
+    }
+    if assistant.stop_reason == "error" {
+        return Err(PrintError::from_provider(message()));
     }
+    Ok(assistant.clone())
+}
+
+fn first_tool_denial(messages: &[AgentMessage]) -> Option<String> {
+    messages.iter().find_map(|message| match message {
+        AgentMessage::ToolResult(result) if result.is_error => {
+            result.content.iter().find_map(|block| match block {
+                ContentBlock::Text { text, .. } if is_tool_denial(text) => {
+                    Some(format!("{}: {text}", result.tool_name))
+                }
+                _ => None,
+            })
+        }
+        _ => None,
+    })
+}
+
+fn print_assistant_text(session: &AgentSession, assistant: &AssistantMessage) {
+    // Only style the reply for a terminal; piped output stays raw markdown.
+    let theme = io::stdout()
+        .is_terminal()
+        .then(|| load_theme_or_default(session.settings_manager.get_theme().as_deref()));
+    let width = crossterm::terminal::size()
+        .map(|(width, _)| width.max(1) as usize)
+        .unwrap_or(80);
     for block in &assistant.content {
         if let ContentBlock::Text { text, .. } = block {
-            println!("{text}");
+            match &theme {
+                Some(theme) => {
+                    let markdown = Markdown::new(text.as_str(), 0, 0, theme.markdown_theme(), None);
+                    for line in markdown.render(width) {
+                        println!("{}", line.trim_end());
+                    }
+                }
+                None => println!("{text}"),
+            }
         }
     }
-    Ok(())
 }
 
 fn emit_json(value: &Value) {
@@ -0,0 +1,704 @@
+//! `--mode acp`: the Agent Client Protocol (JSON-RPC 2.0 over stdio), so editors that support
+//! external ACP agents, such as Zed, can drive pi.
+//!
+//! Every `session/new` gets its own session on its own thread: the first uses the session pi
+//! started with (so `--continue` and friends apply), later ones come from the session factory.
+//! `session/prompt` runs a prompt to completion, streaming `session/update` notifications for
+//! message and thought chunks and for tool calls. Bash commands that need approval under the
+//! bash policy become `session/request_permission` requests, and `session/cancel` stops the
+//! prompt after the current step.
+
+use super::jsonrpc::{
+    error_response, success_response, COMMAND_FAILED, INVALID_PARAMS, INVALID_REQUEST,
+    METHOD_NOT_FOUND, PARSE_ERROR,
+};
+use super::{spawn_stdin_reader, RpcOutput, SessionFactory, SessionSpec, SUPERVISOR_POLL_INTERVAL};
+use crate::agent::{AgentEvent, AgentMessage, CancellationToken};
+use crate::coding_agent::{AgentSession, AgentSessionEvent};
+use crate::core::messages::{ContentBlock, UserContent};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+pub const ACP_PROTOCOL_VERSION: u64 = 1;
+
+struct PromptRequest {
+    id: Value,
+    content: UserContent,
+}
+
+struct SessionHandle {
+    prompts: mpsc::Sender<PromptRequest>,
+    cancelled: Arc<AtomicBool>,
+    /// The session's abort token, cancelled straight from the reader thread so a prompt
+    /// blocked on the provider or a command stops at once.
+    abort: CancellationToken,
+}
+
+/// State the reader thread shares with the session threads.
+struct Shared {
+    output: RpcOutput,
+    sessions: Mutex<HashMap<String, SessionHandle>>,
+    /// `session/request_permission` requests waiting for the client's answer.
+    permissions: Mutex<HashMap<String, mpsc::Sender<Value>>>,
+    next_request: AtomicU64,
+}
+
+impl Shared {
+    fn send(&self, message: &Value) {
+        (self.output)(&message.to_string());
+    }
+
+    fn notify_update(&self, session_id: &str, update: Value) {
+        self.send(&json!({
+            "jsonrpc": "2.0",
+            "method": "session/update",
+            "params": { "sessionId": session_id, "update": update },
+        }));
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NewSessionParams {
+    cwd: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PromptParams {
+    session_id: String,
+    prompt: Vec<PromptBlock>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CancelParams {
+    session_id: String,
+}
+
+/// The ACP content blocks a prompt may carry.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum PromptBlock {
+    Text {
+        text: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Image {
+        data: String,
+        mime_type: String,
+    },
+    ResourceLink {
+        uri: String,
+    },
+    Resource {
+        resource: EmbeddedResource,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddedResource {
+    uri: String,
+    text: Option<String>,
+}
+
+/// ACP over stdio. With a `factory`, clients can open more than one session.
+pub fn run_acp_mode(session: AgentSession, factory: Option<SessionFactory>) -> Result<(), String> {
+    let stdout: RpcOutput = Arc::new(|line: &str| {
+        println!("{line}");
+        let _ = io::stdout().flush();
+    });
+    run_acp(session, spawn_stdin_reader(), stdout, factory)
+}
+
+/// Serves ACP messages from `input` until it closes. `session` runs on this thread once a
+/// client opens a session.
+pub fn run_acp(
+    session: AgentSession,
+    input: mpsc::Receiver<io::Result<String>>,
+    output: RpcOutput,
+    factory: Option<SessionFactory>,
+) -> Result<(), String> {
+    let shared = Arc::new(Shared {
+        output,
+        sessions: Mutex::new(HashMap::new()),
+        permissions: Mutex::new(HashMap::new()),
+        next_request: AtomicU64::new(0),
+    });
+    let (prompts, default_prompts) = mpsc::channel();
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let session_id = session.session_id();
+    let reader = Reader {
+        shared: shared.clone(),
+        factory,
+        default: Some((
+            session_id.clone(),
+            SessionHandle {
+                prompts,
+                cancelled: cancelled.clone(),
+                abort: session.agent.abort_flag(),
+            },
+        )),
+    };
+    thread::spawn(move || reader.run(input));
+    serve_session(session, session_id, default_prompts, cancelled, shared);
+    Ok(())
+}
+
+struct Reader {
+    shared: Arc<Shared>,
+    factory: Option<SessionFactory>,
+    /// The session pi started with, until a client opens it.
+    default: Option<(String, SessionHandle)>,
+}
+
+impl Reader {
+    fn run(mut self, input: mpsc::Receiver<io::Result<String>>) {
+        for line in input {
+            let Ok(line) = line else {
+                break;
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let message: Value = match serde_json::from_str(line.trim()) {
+                Ok(message) => message,
+                Err(err) => {
+                    let error = format!("Invalid JSON: {err}");
+                    self.shared
+                        .send(&error_response(Value::Null, PARSE_ERROR, &error));
+                    continue;
+                }
+            };
+            let id = message.get("id").cloned();
+            let params = message.get("params").cloned().unwrap_or(Value::Null);
+            match message.get("method").and_then(Value::as_str) {
+                Some(method) => self.handle_request(method, id, params),
+                None => self.handle_response(id, &message),
+            }
+        }
+        // Dropping the handles ends every session thread, the default one included.
+        if let Ok(mut sessions) = self.shared.sessions.lock() {
+            sessions.clear();
+        }
+    }
+
+    fn reply(&self, id: Option<Value>, result: Result<Value, (i64, String)>) {
+        let Some(id) = id else {
+            return;
+        };
+        let message = match result {
+            Ok(result) => success_response(id, result),
+            Err((code, error)) => error_response(id, code, &error),
+        };
+        self.shared.send(&message);
+    }
+
+    fn handle_request(&mut self, method: &str, id: Option<Value>, params: Value) {
+        match method {
+            "initialize" => self.reply(id, Ok(initialize_result(&params))),
+            "authenticate" => self.reply(id, Ok(json!({}))),
+            "session/new" => self.new_session(id, params),
+            "session/prompt" => self.prompt(id, params),
+            "session/cancel" => {
+                let Ok(params) = serde_json::from_value::<CancelParams>(params) else {
+                    return;
+                };
+                if let Ok(sessions) = self.shared.sessions.lock() {
+                    if let Some(session) = sessions.get(&params.session_id) {
+                        session.cancelled.store(true, Ordering::SeqCst);
+                        session.abort.cancel();
+                    }
+                }
+            }
+            _ => self.reply(
+                id,
+                Err((METHOD_NOT_FOUND, format!("Unknown method {method}"))),
+            ),
+        }
+    }
+
+    /// The client's answer to a `session/request_permission` request.
+    fn handle_response(&self, id: Option<Value>, message: &Value) {
+        let Some(id) = id.as_ref().and_then(Value::as_str) else {
+            return;
+        };
+        let sender = self
+            .shared
+            .permissions
+            .lock()
+            .ok()
+            .and_then(|mut permissions| permissions.remove(id));
+        if let Some(sender) = sender {
+            let _ = sender.send(message.get("result").cloned().unwrap_or(Value::Null));
+        }
+    }
+
+    fn new_session(&mut self, id: Option<Value>, params: Value) {
+        let params: NewSessionParams = match serde_json::from_value(params) {
+            Ok(params) => params,
+            Err(err) => {
+                let error = format!("Invalid params: {err}");
+                return self.reply(id, Err((INVALID_PARAMS, error)));
+            }
+        };
+        if let Err(err) = check_cwd(&params.cwd) {
+            return self.reply(id, Err((INVALID_PARAMS, err)));
+        }
+        if let Some((session_id, handle)) = self.default.take() {
+            if let Ok(mut sessions) = self.shared.sessions.lock() {
+                sessions.insert(session_id.clone(), handle);
+            }
+            return self.reply(id, Ok(json!({ "sessionId": session_id })));
+        }
+        let Some(factory) = self.factory.clone() else {
+            let error = "This agent cannot open more sessions".to_string();
+            return self.reply(id, Err((COMMAND_FAILED, error)));
+        };
+        let shared = self.shared.clone();
+        thread::spawn(move || {
+            let session = match factory(&SessionSpec::default()) {
+                Ok(session) => session,
+                Err(err) => {
+                    if let Some(id) = id {
+                        shared.send(&error_response(id, COMMAND_FAILED, &err));
+                    }
+                    return;
+                }
+            };
+            let session_id = session.session_id();
+            let (prompts, receiver) = mpsc::channel();
+            let cancelled = Arc::new(AtomicBool::new(false));
+            if let Ok(mut sessions) = shared.sessions.lock() {
+                let handle = SessionHandle {
+                    prompts,
+                    cancelled: cancelled.clone(),
+                    abort: session.agent.abort_flag(),
+                };
+                sessions.insert(session_id.clone(), handle);
+            }
+            if let Some(id) = id {
+                shared.send(&success_response(id, json!({ "sessionId": session_id })));
+            }
+            serve_session(session, session_id, receiver, cancelled, shared);
+        });
+    }
+
+    fn prompt(&self, id: Option<Value>, params: Value) {
+        let Some(id) = id else {
+            return;
+        };
+        let params: PromptParams = match serde_json::from_value(params) {
+            Ok(params) => params,
+            Err(err) => {
+                let error = format!("Invalid params: {err}");
+                return self.reply(Some(id), Err((INVALID_PARAMS, error)));
+            }
+        };
+        let content = match prompt_content(params.prompt) {
+            Ok(content) => content,
+            Err(err) => return self.reply(Some(id), Err((INVALID_PARAMS, err))),
+        };
+        let sent = self.shared.sessions.lock().is_ok_and(|sessions| {
+            sessions.get(&params.session_id).is_some_and(|session| {
+                session
+                    .prompts
+                    .send(PromptRequest {
+                        id: id.clone(),
+                        content,
+                    })
+                    .is_ok()
+            })
+        });
+        if !sent {
+            let error = format!("Unknown session {}", params.session_id);
+            self.reply(Some(id), Err((INVALID_REQUEST, error)));
+        }
+    }
+}
+
+fn initialize_result(params: &Value) -> Value {
+    let requested = params
+        .get("protocolVersion")
+        .and_then(Value::as_u64)
+        .unwrap_or(ACP_PROTOCOL_VERSION);
+    json!({
+        "protocolVersion": requested.min(ACP_PROTOCOL_VERSION),
+        "agentCapabilities": {
+            "loadSession": false,
+            "promptCapabilities": { "image": true, "audio": false, "embeddedContext": true },
+        },
+        "authMethods": [],
+        "agentInfo": { "name": "pi", "version": env!("CARGO_PKG_VERSION") },
+    })
+}
+
+/// pi's tools work in the directory it was started in, so sessions must share it.
+fn check_cwd(cwd: &Path) -> Result<(), String> {
+    let current = std::env::current_dir().map_err(|err| err.to_string())?;
+    let same = match (cwd.canonicalize(), current.canonicalize()) {
+        (Ok(cwd), Ok(current)) => cwd == current,
+        _ => cwd == current,
+    };
+    if same {
+        Ok(())
+    } else {
+        Err(format!(
+            "pi is running in {}; start it in {} to work there",
+            current.display(),
+            cwd.display()
+        ))
+    }
+}
+
+fn prompt_content(blocks: Vec<PromptBlock>) -> Result<UserContent, String> {
+    let mut content = Vec::new();
+    for block in blocks {
+        let block = match block {
+            PromptBlock::Text { text } => ContentBlock::Text {
+                text,
+                text_signature: None,
+            },
+            PromptBlock::Image { data, mime_type } => ContentBlock::Image { data, mime_type },
+            PromptBlock::ResourceLink { uri } => ContentBlock::Text {
+                text: format!("<file name=\"{}\"></file>", uri_path(&uri)),
+                text_signature: None,
+            },
+            PromptBlock::Resource { resource } => {
+                let text = resource.text.unwrap_or_default();
+                let newline = if text.ends_with('\n') { "" } else { "\n" };
+                ContentBlock::Text {
+                    text: format!(
+                        "<file name=\"{}\">\n{text}{newline}</file>",
+                        uri_path(&resource.uri)
+                    ),
+                    text_signature: None,
+                }
+            }
+        };
+        content.push(block);
+    }
+    if content.is_empty() {
+        return Err("The prompt is empty".to_string());
+    }
+    Ok(UserContent::Blocks(content))
+}
+
+fn uri_path(uri: &str) -> &str {
+    uri.strip_prefix("file://").unwrap_or(uri)
+}
+
+/// Runs prompts for one ACP session until its handle is dropped.
+fn serve_session(
+    mut session: AgentSession,
+    session_id: String,
+    prompts: mpsc::Receiver<PromptRequest>,
+    cancelled: Arc<AtomicBool>,
+    shared: Arc<Shared>,
+) {
+    let updates = Rc::new(RefCell::new(UpdateStream::default()));
+    let abort = session.agent.abort_flag();
+    let listener_updates = updates.clone();
+    let listener_cancelled = cancelled.clone();
+    let listener_shared = shared.clone();
+    let listener_session_id = session_id.clone();
+    let _subscription = session.subscribe(move |event| {
+        if listener_cancelled.load(Ordering::SeqCst) {
+            abort.cancel();
+        }
+        for update in listener_updates.borrow_mut().updates(event) {
+            listener_shared.notify_update(&listener_session_id, update);
+        }
+    });
+
+    let approval_updates = updates.clone();
+    let approval_cancelled = cancelled.clone();
+    let approval_shared = shared.clone();
+    let approval_session_id = session_id.clone();
+    let always_allowed = RefCell::new(HashSet::new());
+    session
+        .bash_approval()
+        .set_handler(Rc::new(move |command, reason| {
+            if always_allowed.borrow().contains(command) {
+                return true;
+            }
+            let tool_call = approval_updates.borrow().running_tool.clone();
+            let outcome = request_permission(
+                &approval_shared,
+                &approval_session_id,
+                &approval_cancelled,
+                tool_call,
+                command,
+                reason,
+            );
+            match outcome.as_deref() {
+                Some("allow_always") => {
+                    always_allowed.borrow_mut().insert(command.to_string());
+                    true
+                }
+                Some("allow_once") => true,
+                _ => false,
+            }
+        }));
+
+    for request in prompts {
+        cancelled.store(false, Ordering::SeqCst);
+        updates.borrow_mut().limit_reached = false;
+        let result = session.prompt_content(request.content);
+        let was_cancelled = cancelled.load(Ordering::SeqCst);
+        let response = match result {
+            Ok(()) => match stop_reason(&session, &updates.borrow(), was_cancelled) {
+                Ok(reason) => success_response(request.id, json!({ "stopReason": reason })),
+                Err(err) => error_response(request.id, COMMAND_FAILED, &err),
+            },
+            Err(_) if was_cancelled => {
+                success_response(request.id, json!({ "stopReason": "cancelled" }))
+            }
+            Err(err) => error_response(request.id, COMMAND_FAILED, &err.to_string()),
+        };
+        shared.send(&response);
+    }
+    session.bash_approval().clear_handler();
+    session.dispose();
+}
+
+/// The ACP stop reason for the prompt that just finished, or the error it failed with.
+fn stop_reason(
+    session: &AgentSession,
+    updates: &UpdateStream,
+    cancelled: bool,
+) -> Result<&'static str, String> {
+    if cancelled {
+        return Ok("cancelled");
+    }
+    if updates.limit_reached {
+        return Ok("max_turn_requests");
+    }
+    let last = session
+        .messages()
+        .into_iter()
+        .rev()
+        .find_map(|message| match message {
+            AgentMessage::Assistant(message) => Some(message),
+            _ => None,
+        });
+    let Some(last) = last else {
+        return Ok("end_turn");
+    };
+    match last.stop_reason.as_str() {
+        "aborted" => Ok("cancelled"),
+        "length" => Ok("max_tokens"),
+        "error" => Err(last
+            .error_message
+            .unwrap_or_else(|| "The model request failed".to_string())),
+        _ => Ok("end_turn"),
+    }
+}
+
+/// Asks the client whether `command` may run; the chosen option's kind, or `None` when the
+/// client cancelled, the prompt was cancelled or the client went away.
+fn request_permission(
+    shared: &Shared,
+    session_id: &str,
+    cancelled: &AtomicBool,
+    tool_call: Option<(String, String)>,
+    command: &str,
+    reason: &str,
+) -> Option<String> {
+    let id = format!(
+        "permission-{}",
+        shared.next_request.fetch_add(1, Ordering::SeqCst) + 1
+    );
+    let (sender, receiver) = mpsc::channel();
+    shared.permissions.lock().ok()?.insert(id.clone(), sender);
+    let (tool_call_id, title) = tool_call.unwrap_or_else(|| (id.clone(), command.to_string()));
+    shared.send(&json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": "session/request_permission",
+        "params": {
+            "sessionId": session_id,
+            "toolCall": {
+                "toolCallId": tool_call_id,
+                "title": title,
+                "kind": "execute",
+                "status": "pending",
+                "rawInput": { "command": command, "reason": reason },
+            },
+            "options": [
+                { "optionId": "allow_once", "name": "Allow", "kind": "allow_once" },
+                { "optionId": "allow_always", "name": "Always allow", "kind": "allow_always" },
+                { "optionId": "reject_once", "name": "Reject", "kind": "reject_once" },
+            ],
+        },
+    }));
+    let result = loop {
+        match receiver.recv_timeout(SUPERVISOR_POLL_INTERVAL) {
+            Ok(result) => break Some(result),
+            Err(RecvTimeoutError::Timeout) if !cancelled.load(Ordering::SeqCst) => continue,
+            Err(_) => break None,
+        }
+    };
+    if let Ok(mut permissions) = shared.permissions.lock() {
+        permissions.remove(&id);
+    }
+    let outcome = result?.get("outcome")?.clone();
+    if outcome.get("outcome").and_then(Value::as_str) != Some("selected") {
+        return None;
+    }
+    outcome
+        .get("optionId")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+/// Turns session events into ACP `session/update` payloads.
+#[derive(Default)]
+struct UpdateStream {
+    /// Bytes of each content block of the streaming message already sent as chunks.
+    sent: Vec<usize>,
+    /// The running tool call's id and title, for permission requests.
+    running_tool: Option<(String, String)>,
+    limit_reached: bool,
+}
+
+impl UpdateStream {
+    fn updates(&mut self, event: &AgentSessionEvent) -> Vec<Value> {
+        let AgentSessionEvent::Agent(event) = event else {
+            return Vec::new();
+        };
+        match event.as_ref() {
+            AgentEvent::MessageStart {
+                message: AgentMessage::Assistant(_),
+            } => {
+                self.sent.clear();
+                Vec::new()
+            }
+            AgentEvent::MessageUpdate {
+                message: AgentMessage::Assistant(message),
+                ..
+            }
+            | AgentEvent::MessageEnd {
+                message: AgentMessage::Assistant(message),
+            } => self.chunks(&message.content),
+            AgentEvent::ToolExecutionStart {
+                tool_call_id,
+                tool_name,
+                args,
+            } => {
+                let title = tool_title(tool_name, args);
+                self.running_tool = Some((tool_call_id.clone(), title.clone()));
+                let mut update = json!({
+                    "sessionUpdate": "tool_call",
+                    "toolCallId": tool_call_id,
+                    "title": title,
+                    "kind": tool_kind(tool_name),
+                    "status": "in_progress",
+                    "rawInput": args,
+                });
+                if let Some(path) = args.get("path").and_then(Value::as_str) {
+                    update["locations"] = json!([{ "path": path }]);
+                }
+                vec![update]
+            }
+            AgentEvent::ToolExecutionUpdate {
+                tool_call_id,
+                partial_result,
+                ..
+            } => vec![json!({
+                "sessionUpdate": "tool_call_update",
+                "toolCallId": tool_call_id,
+                "content": tool_content(&partial_result.content),
+            })],
+            AgentEvent::ToolExecutionEnd {
+                tool_call_id,
+                result,
+                is_error,
+                ..
+            } => {
+                self.running_tool = None;
+                vec![json!({
+                    "sessionUpdate": "tool_call_update",
+                    "toolCallId": tool_call_id,
+                    "status": if *is_error { "failed" } else { "completed" },
+                    "content": tool_content(&result.content),
+                })]
+            }
+            AgentEvent::LimitReached { .. } => {
+                self.limit_reached = true;
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Text and thinking streamed since the last update.
+    fn chunks(&mut self, content: &[ContentBlock]) -> Vec<Value> {
+        let mut updates = Vec::new();
+        for (index, block) in content.iter().enumerate() {
+            let (kind, text) = match block {
+                ContentBlock::Text { text, .. } => ("agent_message_chunk", text),
+                ContentBlock::Thinking { thinking, .. } => ("agent_thought_chunk", thinking),
+                _ => continue,
+            };
+            if self.sent.len() <= index {
+                self.sent.resize(index + 1, 0);
+            }
+            let Some(delta) = text
+                .get(self.sent[index]..)
+                .filter(|delta| !delta.is_empty())
+            else {
+                continue;
+            };
+            self.sent[index] = text.len();
+            updates.push(json!({
+                "sessionUpdate": kind,
+                "content": { "type": "text", "text": delta },
+            }));
+        }
+        updates
+    }
+}
+
+fn tool_kind(name: &str) -> &'static str {
+    match name {
+        "read" | "notebook_read" => "read",
+        "write" | "edit" | "notebook_edit" => "edit",
+        "bash" => "execute",
+        "grep" | "find" | "ls" => "search",
+        _ => "other",
+    }
+}
+
+fn tool_title(name: &str, args: &Value) -> String {
+    let arg = |key: &str| args.get(key).and_then(Value::as_str);
+    match (name, arg("command"), arg("path"), arg("pattern")) {
+        ("bash", Some(command), _, _) => command.to_string(),
+        (_, _, Some(path), _) => format!("{name} {path}"),
+        (_, _, _, Some(pattern)) => format!("{name} {pattern}"),
+        _ => name.to_string(),
+    }
+}
+
+fn tool_content(content: &[ContentBlock]) -> Vec<Value> {
+    content
+        .iter()
+        .filter_map(|block| {
+            let text = match block {
+                ContentBlock::Text { text, .. } => text.clone(),
+                ContentBlock::Diff { diff, .. } => format!("```diff\n{diff}\n```"),
+                _ => return None,
+            };
+            Some(json!({ "type": "content", "content": { "type": "text", "text": text } }))
+        })
+        .collect()
+}
@@ -0,0 +1,92 @@
+//! `{"type":"batch","commands":[...]}` runs its commands one after another, exactly as if
+//! they had arrived on their own lines: each answers with its usual response, after the
+//! events it caused. A final `batch` response summarizes them. After a failed command the
+//! rest are skipped, unless the batch sets `continueOnError`.
+
+use super::{response_error, response_success};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+
+pub(super) struct Batch {
+    id: Option<String>,
+    continue_on_error: bool,
+    remaining: VecDeque<Value>,
+    /// One entry per command taken from the batch, in order.
+    results: Vec<Value>,
+}
+
+impl Batch {
+    pub(super) fn new(id: Option<String>, commands: Vec<Value>, continue_on_error: bool) -> Self {
+        Self {
+            id,
+            continue_on_error,
+            remaining: commands.into(),
+            results: Vec::new(),
+        }
+    }
+
+    /// The next command to run, or `None` once the batch is done.
+    pub(super) fn next_command(&mut self) -> Option<Value> {
+        if !self.continue_on_error && self.failed() > 0 {
+            for command in self.remaining.drain(..) {
+                let mut result = result_entry(&command);
+                result["skipped"] = json!(true);
+                self.results.push(result);
+            }
+            return None;
+        }
+        let command = self.remaining.pop_front()?;
+        self.results.push(result_entry(&command));
+        Some(command)
+    }
+
+    /// Notes how the running command went from the first response it sends.
+    pub(super) fn record(&mut self, response: &Value) {
+        let Some(result) = self.results.last_mut() else {
+            return;
+        };
+        if result.get("success").is_some() {
+            return;
+        }
+        result["success"] = response["success"].clone();
+        if let Some(error) = response.get("error") {
+            result["error"] = error.clone();
+        }
+    }
+
+    pub(super) fn summary(self) -> Value {
+        let failed = self.failed();
+        let skipped = self
+            .results
+            .iter()
+            .filter(|result| result.get("skipped").is_some())
+            .count();
+        let data = json!({
+            "results": self.results,
+            "failed": failed,
+            "skipped": skipped,
+        });
+        if failed == 0 {
+            return response_success(self.id.as_deref(), "batch", Some(data));
+        }
+        let error = format!("{failed} of {} commands failed", self.results.len());
+        let mut response = response_error(self.id.as_deref(), "batch", &error);
+        response["data"] = data;
+        response
+    }
+
+    fn failed(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|result| result["success"] == false)
+            .count()
+    }
+}
+
+fn result_entry(command: &Value) -> Value {
+    let mut result = json!({ "command": command.get("type").cloned().unwrap_or(Value::Null) });
+    if let Some(id) = command.get("id") {
+        result["id"] = id.clone();
+    }
+    result
+}
@@ -0,0 +1,149 @@
+//! `message_deltas`: instead of a `message_update` carrying the whole partial message on
+//! every token, send only what was appended since the last update. Clients build the message
+//! from `message_start` and the deltas, and get the complete message again in `message_end`.
+//!
+//! - `{"type":"text_delta","contentIndex":0,"delta":"Hel"}` appends to a text block.
+//! - `{"type":"thinking_delta","contentIndex":0,"delta":"..."}` appends to a thinking block.
+//! - `{"type":"tool_call_start","contentIndex":1,"id":"...","name":"read"}` adds a tool call.
+//! - `{"type":"tool_call_delta","contentIndex":1,"delta":"{\"pa"}` appends to the tool call's
+//!   arguments JSON text, as the provider streams it. The parsed arguments are in
+//!   `message_end`.
+//!
+//! An update that does more than append goes out as a plain `message_update`, so clients
+//! replace the message they have built. Output filters do that: a redaction rewrites text
+//! already sent, and blocking the reply changes a block's kind.
+
+use crate::agent::{AgentEvent, AgentMessage, ArgumentsDelta};
+use crate::cli::event_json::serialize_session_event;
+use crate::coding_agent::AgentSessionEvent;
+use crate::core::messages::ContentBlock;
+use serde_json::{json, Value};
+
+pub const MESSAGE_DELTAS: &str = "message_deltas";
+
+/// What clients have of one content block.
+enum SentBlock {
+    Text(String),
+    Thinking(String),
+    ToolCall(String),
+    Other,
+}
+
+/// Tracks the content clients have seen for the assistant message being streamed.
+#[derive(Default)]
+pub(super) struct MessageDeltas {
+    sent: Vec<SentBlock>,
+}
+
+impl MessageDeltas {
+    /// The lines to send for `event`: deltas in place of a `message_update`, the usual
+    /// serialization for everything else.
+    pub(super) fn encode(&mut self, event: &AgentSessionEvent) -> Vec<Value> {
+        let AgentSessionEvent::Agent(agent_event) = event else {
+            return serialize_session_event(event).into_iter().collect();
+        };
+        match agent_event.as_ref() {
+            AgentEvent::MessageStart {
+                message: AgentMessage::Assistant(message),
+            } => self.sent = message.content.iter().map(sent_block).collect(),
+            AgentEvent::MessageUpdate {
+                message: AgentMessage::Assistant(message),
+                arguments_delta,
+            } => {
+                if let Some(deltas) = self.deltas(&message.content, arguments_delta.as_ref()) {
+                    return deltas;
+                }
+                self.sent = message.content.iter().map(sent_block).collect();
+            }
+            AgentEvent::MessageEnd {
+                message: AgentMessage::Assistant(_),
+            } => self.sent.clear(),
+            _ => {}
+        }
+        serialize_session_event(event).into_iter().collect()
+    }
+
+    /// What `content` appends to the content sent so far, or `None` when it does more.
+    fn deltas(
+        &mut self,
+        content: &[ContentBlock],
+        arguments: Option<&ArgumentsDelta>,
+    ) -> Option<Vec<Value>> {
+        if content.len() < self.sent.len() {
+            return None;
+        }
+        let mut deltas = Vec::new();
+        let mut sent = Vec::with_capacity(content.len());
+        for (index, block) in content.iter().enumerate() {
+            let previous = self.sent.get(index);
+            let block_sent = match block {
+                ContentBlock::Text { text, .. } => {
+                    let before = match previous {
+                        Some(SentBlock::Text(sent)) => sent.as_str(),
+                        None => "",
+                        Some(_) => return None,
+                    };
+                    deltas.extend(appended("text_delta", index, text, before)?);
+                    SentBlock::Text(text.clone())
+                }
+                ContentBlock::Thinking { thinking, .. } => {
+                    let before = match previous {
+                        Some(SentBlock::Thinking(sent)) => sent.as_str(),
+                        None => "",
+                        Some(_) => return None,
+                    };
+                    deltas.extend(appended("thinking_delta", index, thinking, before)?);
+                    SentBlock::Thinking(thinking.clone())
+                }
+                ContentBlock::ToolCall { id, name, .. } => {
+                    match previous {
+                        Some(SentBlock::ToolCall(sent_id)) if sent_id == id => {}
+                        None => deltas.push(json!({
+                            "type": "tool_call_start",
+                            "contentIndex": index,
+                            "id": id,
+                            "name": name,
+                        })),
+                        Some(_) => return None,
+                    }
+                    let appended = arguments
+                        .filter(|arguments| arguments.content_index == index)
+                        .filter(|arguments| !arguments.json.is_empty());
+                    if let Some(arguments) = appended {
+                        deltas.push(json!({
+                            "type": "tool_call_delta",
+                            "contentIndex": index,
+                            "delta": arguments.json,
+                        }));
+                    }
+                    SentBlock::ToolCall(id.clone())
+                }
+                _ => match previous {
+                    Some(SentBlock::Other) => SentBlock::Other,
+                    _ => return None,
+                },
+            };
+            sent.push(block_sent);
+        }
+        self.sent = sent;
+        Some(deltas)
+    }
+}
+
+fn sent_block(block: &ContentBlock) -> SentBlock {
+    match block {
+        ContentBlock::Text { text, .. } => SentBlock::Text(text.clone()),
+        ContentBlock::Thinking { thinking, .. } => SentBlock::Thinking(thinking.clone()),
+        ContentBlock::ToolCall { id, .. } => SentBlock::ToolCall(id.clone()),
+        _ => SentBlock::Other,
+    }
+}
+
+/// The delta for `text` past the `before` text already sent: `Some(None)` when nothing was
+/// appended, `None` when `text` no longer starts with what was sent.
+fn appended(kind: &str, index: usize, text: &str, before: &str) -> Option<Option<Value>> {
+    let delta = text.strip_prefix(before)?;
+    Some(
+        (!delta.is_empty()).then(|| json!({ "type": kind, "contentIndex": index, "delta": delta })),
+    )
+}
@@ -0,0 +1,462 @@
+//! `--mode jsonrpc`: the RPC commands as JSON-RPC 2.0 over stdio, for editor plugins.
+//!
+//! A request's `method` is an RPC command type (`prompt`, `get_state`, ...) and its `params`
+//! are that command's fields; the response's `result` is the command's `data`. Requests
+//! without an `id` are notifications and get no response. Events arrive as `pi/event`
+//! notifications, and extension dialogs as `pi/extensionUi` requests for the client to answer
+//! with `{ value?, confirmed?, cancelled? }`.
+//!
+//! Two code actions prompt the agent about part of a file, taking LSP-style (zero-based)
+//! ranges:
+//!
+//! ```json
+//! { "jsonrpc": "2.0", "id": 7, "method": "pi/fixDiagnostics", "params": {
+//!     "path": "src/lib.rs",
+//!     "range": { "start": { "line": 9, "character": 0 }, "end": { "line": 12, "character": 0 } },
+//!     "diagnostics": [{ "range": { ... }, "severity": 1, "message": "mismatched types" }]
+//! } }
+//! ```
+//!
+//! `pi/explainSelection` takes the same `path` and `range`. Both accept the selected `text`
+//! (read from the file otherwise) and extra `instructions`.
+
+use super::{sessions, spawn_stdin_reader, RpcModeOptions, RpcOutput, SessionFactory};
+use crate::coding_agent::AgentSession;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+/// Any other failure reported by an RPC command.
+pub const COMMAND_FAILED: i64 = -32000;
+
+pub const EXPLAIN_SELECTION: &str = "pi/explainSelection";
+pub const FIX_DIAGNOSTICS: &str = "pi/fixDiagnostics";
+pub const EVENT_NOTIFICATION: &str = "pi/event";
+pub const EXTENSION_UI_REQUEST: &str = "pi/extensionUi";
+
+const COMMAND_ID_PREFIX: &str = "jsonrpc-";
+const UI_ID_PREFIX: &str = "ui-";
+
+/// What to do with a line from the client.
+#[derive(Debug, PartialEq)]
+pub enum JsonRpcInput {
+    /// An RPC command line for the session loop.
+    Command(String),
+    /// Answered without the session, e.g. `initialize` or a malformed request.
+    Reply(Value),
+    /// The client sent `exit`.
+    Exit,
+    Ignore,
+}
+
+#[derive(Default)]
+struct PendingIds {
+    next: u64,
+    /// Command ids handed to the loop, mapped back to the client's request ids.
+    requests: HashMap<String, Value>,
+    /// Extension UI requests sent to the client, with the session that asked.
+    ui: HashMap<String, (String, Option<String>)>,
+}
+
+/// Translates between JSON-RPC messages and RPC command and output lines. Clones share the
+/// ids in flight, so one can read the client while another writes to it.
+#[derive(Clone, Default)]
+pub struct JsonRpcAdapter {
+    pending: Arc<Mutex<PendingIds>>,
+}
+
+impl JsonRpcAdapter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn handle_input(&self, line: &str) -> JsonRpcInput {
+        let value: Value = match serde_json::from_str(line.trim()) {
+            Ok(value) => value,
+            Err(err) => {
+                let message = format!("Invalid JSON: {err}");
+                return JsonRpcInput::Reply(error_response(Value::Null, PARSE_ERROR, &message));
+            }
+        };
+        let Value::Object(mut message) = value else {
+            let error = "Expected a JSON-RPC request object";
+            return JsonRpcInput::Reply(error_response(Value::Null, INVALID_REQUEST, error));
+        };
+        let id = message.remove("id");
+        if message.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+            let error = "Expected \"jsonrpc\": \"2.0\"";
+            return reply(id, INVALID_REQUEST, error);
+        }
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            if message.contains_key("result") || message.contains_key("error") {
+                return self.ui_response(id, &message);
+            }
+            return reply(id, INVALID_REQUEST, "Missing method");
+        };
+        let params = match message.get("params") {
+            None | Some(Value::Null) => Map::new(),
+            Some(Value::Object(params)) => params.clone(),
+            Some(_) => return reply(id, INVALID_PARAMS, "params must be an object"),
+        };
+
+        let command = match method {
+            "initialize" => {
+                return match id {
+                    Some(id) => JsonRpcInput::Reply(success_response(id, initialize_result())),
+                    None => JsonRpcInput::Ignore,
+                }
+            }
+            "shutdown" => {
+                return match id {
+                    Some(id) => JsonRpcInput::Reply(success_response(id, Value::Null)),
+                    None => JsonRpcInput::Ignore,
+                }
+            }
+            "exit" => return JsonRpcInput::Exit,
+            EXPLAIN_SELECTION | FIX_DIAGNOSTICS => {
+                let params = match serde_json::from_value::<CodeActionParams>(params.into()) {
+                    Ok(params) => params,
+                    Err(err) => {
+                        return reply(id, INVALID_PARAMS, &format!("Invalid params: {err}"))
+                    }
+                };
+                let message = match code_action_prompt(method, &params) {
+                    Ok(message) => message,
+                    Err(err) => return reply(id, INVALID_PARAMS, &err),
+                };
+                let mut command = Map::new();
+                command.insert("message".to_string(), Value::String(message));
+                if let Some(session_id) = params.session_id {
+                    command.insert("sessionId".to_string(), Value::String(session_id));
+                }
+                command.insert("type".to_string(), Value::String("prompt".to_string()));
+                command
+            }
+            _ => {
+                let mut command = params;
+                command.insert("type".to_string(), Value::String(method.to_string()));
+                command
+            }
+        };
+        JsonRpcInput::Command(self.command_line(command, id))
+    }
+
+    /// Turns a line the RPC loop emitted into a message for the client, or `None` when the
+    /// client should not see it (responses to notifications).
+    pub fn handle_output(&self, line: &str) -> Option<Value> {
+        let value: Value = serde_json::from_str(line).ok()?;
+        match value.get("type").and_then(Value::as_str) {
+            Some("response") => self.command_response(&value),
+            Some("extension_ui_request") => {
+                let method = value.get("method").and_then(Value::as_str);
+                if !matches!(method, Some("select" | "confirm" | "input" | "editor")) {
+                    return Some(notification(value));
+                }
+                let request_id = value.get("id").and_then(Value::as_str)?.to_string();
+                let session_id = value
+                    .get("sessionId")
+                    .and_then(Value::as_str)
+                    .map(str::to_string);
+                let id = format!("{UI_ID_PREFIX}{request_id}");
+                if let Ok(mut pending) = self.pending.lock() {
+                    pending.ui.insert(id.clone(), (request_id, session_id));
+                }
+                Some(json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "method": EXTENSION_UI_REQUEST,
+                    "params": value,
+                }))
+            }
+            _ => Some(notification(value)),
+        }
+    }
+
+    fn command_line(&self, mut command: Map<String, Value>, id: Option<Value>) -> String {
+        command.remove("id");
+        if let Some(id) = id {
+            if let Ok(mut pending) = self.pending.lock() {
+                pending.next += 1;
+                let command_id = format!("{COMMAND_ID_PREFIX}{}", pending.next);
+                pending.requests.insert(command_id.clone(), id);
+                command.insert("id".to_string(), Value::String(command_id));
+            }
+        }
+        Value::Object(command).to_string()
+    }
+
+    fn command_response(&self, value: &Value) -> Option<Value> {
+        let command_id = value.get("id").and_then(Value::as_str)?;
+        let id = self.pending.lock().ok()?.requests.remove(command_id)?;
+        if value.get("success").and_then(Value::as_bool) == Some(true) {
+            let result = value.get("data").cloned().unwrap_or(Value::Null);
+            return Some(success_response(id, result));
+        }
+        let error = value
+            .get("error")
+            .and_then(Value::as_str)
+            .unwrap_or("Command failed");
+        let code = if error.starts_with("Unknown command") {
+            METHOD_NOT_FOUND
+        } else if error.starts_with("Invalid payload") {
+            INVALID_PARAMS
+        } else {
+            COMMAND_FAILED
+        };
+        let mut response = error_response(id, code, error);
+        let mut data = Map::new();
+        for key in ["command", "sessionId"] {
+            if let Some(field) = value.get(key) {
+                data.insert(key.to_string(), field.clone());
+            }
+        }
+        response["error"]["data"] = Value::Object(data);
+        Some(response)
+    }
+
+    /// The client's answer to a `pi/extensionUi` request, as an `extension_ui_response`.
+    fn ui_response(&self, id: Option<Value>, message: &Map<String, Value>) -> JsonRpcInput {
+        let Some(id) = id.as_ref().and_then(Value::as_str) else {
+            return JsonRpcInput::Ignore;
+        };
+        let Some((request_id, session_id)) = self
+            .pending
+            .lock()
+            .ok()
+            .and_then(|mut pending| pending.ui.remove(id))
+        else {
+            return JsonRpcInput::Ignore;
+        };
+        let mut command = match message.get("result") {
+            Some(Value::Object(result)) => result.clone(),
+            _ => Map::new(),
+        };
+        if message.contains_key("error") {
+            command.insert("cancelled".to_string(), Value::Bool(true));
+        }
+        command.insert(
+            "type".to_string(),
+            Value::String("extension_ui_response".to_string()),
+        );
+        command.insert("id".to_string(), Value::String(request_id));
+        if let Some(session_id) = session_id {
+            command.insert("sessionId".to_string(), Value::String(session_id));
+        }
+        JsonRpcInput::Command(Value::Object(command).to_string())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct Position {
+    pub line: usize,
+    #[serde(default)]
+    pub character: usize,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostic {
+    pub range: Option<Range>,
+    /// LSP severity: 1 error, 2 warning, 3 information, 4 hint.
+    pub severity: Option<u8>,
+    pub source: Option<String>,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CodeActionParams {
+    pub path: String,
+    pub range: Range,
+    /// The selected text; read from `path` when missing.
+    pub text: Option<String>,
+    #[serde(default)]
+    pub diagnostics: Vec<Diagnostic>,
+    pub instructions: Option<String>,
+    pub session_id: Option<String>,
+}
+
+/// The prompt a code action sends to the agent.
+pub fn code_action_prompt(method: &str, params: &CodeActionParams) -> Result<String, String> {
+    let Range { start, end } = params.range;
+    if end.line < start.line {
+        return Err("range ends before it starts".to_string());
+    }
+    let excerpt = match &params.text {
+        Some(text) => text.clone(),
+        None => read_lines(&params.path, start.line, end.line)?,
+    };
+    let lines = if start.line == end.line {
+        format!("line {}", start.line + 1)
+    } else {
+        format!("lines {}-{}", start.line + 1, end.line + 1)
+    };
+    let mut prompt = match method {
+        EXPLAIN_SELECTION => format!(
+            "Explain this code from {} ({lines}):\n\n```\n{}\n```",
+            params.path,
+            excerpt.trim_end_matches('\n')
+        ),
+        FIX_DIAGNOSTICS => {
+            if params.diagnostics.is_empty() {
+                return Err("diagnostics must not be empty".to_string());
+            }
+            let mut prompt = format!("Fix these diagnostics in {}:\n", params.path);
+            for diagnostic in &params.diagnostics {
+                prompt.push_str(&format!("- {}\n", describe_diagnostic(diagnostic)));
+            }
+            prompt.push_str(&format!(
+                "\nThe affected code ({lines}):\n\n```\n{}\n```\n\nEdit the file to fix them.",
+                excerpt.trim_end_matches('\n')
+            ));
+            prompt
+        }
+        _ => return Err(format!("Unknown code action {method}")),
+    };
+    if let Some(instructions) = params.instructions.as_deref() {
+        if !instructions.trim().is_empty() {
+            prompt.push_str("\n\n");
+            prompt.push_str(instructions.trim());
+        }
+    }
+    Ok(prompt)
+}
+
+fn describe_diagnostic(diagnostic: &Diagnostic) -> String {
+    let mut text = String::new();
+    if let Some(range) = diagnostic.range {
+        text.push_str(&format!("line {}: ", range.start.line + 1));
+    }
+    let severity = match diagnostic.severity {
+        Some(1) => Some("error"),
+        Some(2) => Some("warning"),
+        Some(3) => Some("info"),
+        Some(4) => Some("hint"),
+        _ => None,
+    };
+    if let Some(severity) = severity {
+        text.push_str(&format!("{severity}: "));
+    }
+    text.push_str(diagnostic.message.trim());
+    if let Some(source) = diagnostic.source.as_deref() {
+        text.push_str(&format!(" ({source})"));
+    }
+    text
+}
+
+/// Lines `start..=end` (zero-based) of the file at `path`.
+fn read_lines(path: &str, start: usize, end: usize) -> Result<String, String> {
+    let content =
+        std::fs::read_to_string(path).map_err(|err| format!("Failed to read {path}: {err}"))?;
+    let lines: Vec<&str> = content.lines().collect();
+    if start >= lines.len() {
+        return Err(format!(
+            "{path} has {} lines; the range starts at line {}",
+            lines.len(),
+            start + 1
+        ));
+    }
+    Ok(lines[start..=end.min(lines.len() - 1)].join("\n"))
+}
+
+fn initialize_result() -> Value {
+    json!({
+        "serverInfo": { "name": "pi", "version": env!("CARGO_PKG_VERSION") },
+        "capabilities": {
+            "codeActions": [EXPLAIN_SELECTION, FIX_DIAGNOSTICS],
+            "notifications": [EVENT_NOTIFICATION],
+            "requests": [EXTENSION_UI_REQUEST],
+        },
+    })
+}
+
+pub(super) fn success_response(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+pub(super) fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// An error reply to a request; notifications never get one.
+fn reply(id: Option<Value>, code: i64, message: &str) -> JsonRpcInput {
+    match id {
+        Some(id) => JsonRpcInput::Reply(error_response(id, code, message)),
+        None => JsonRpcInput::Ignore,
+    }
+}
+
+fn notification(params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": EVENT_NOTIFICATION, "params": params })
+}
+
+/// JSON-RPC over stdio. With a `factory`, clients can also `create_session` more sessions.
+pub fn run_jsonrpc_mode(
+    session: AgentSession,
+    options: RpcModeOptions,
+    factory: Option<SessionFactory>,
+) -> Result<(), String> {
+    let stdout: RpcOutput = Arc::new(|line: &str| {
+        println!("{line}");
+        let _ = io::stdout().flush();
+    });
+    run_jsonrpc(session, options, spawn_stdin_reader(), stdout, factory)
+}
+
+/// Serves JSON-RPC messages from `input`, writing replies, responses and notifications to
+/// `output`, until the input closes, the client sends `exit` or the supervisor exits.
+pub fn run_jsonrpc(
+    session: AgentSession,
+    options: RpcModeOptions,
+    input: mpsc::Receiver<io::Result<String>>,
+    output: RpcOutput,
+    factory: Option<SessionFactory>,
+) -> Result<(), String> {
+    let adapter = JsonRpcAdapter::new();
+    let (sender, lines) = mpsc::channel();
+    let reader = adapter.clone();
+    let replies = output.clone();
+    thread::spawn(move || {
+        for line in input {
+            let line = match line {
+                Ok(line) => line,
+                Err(err) => {
+                    let _ = sender.send(Err(err));
+                    break;
+                }
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            match reader.handle_input(&line) {
+                JsonRpcInput::Command(command) => {
+                    if sender.send(Ok(command)).is_err() {
+                        break;
+                    }
+                }
+                JsonRpcInput::Reply(reply) => replies(&reply.to_string()),
+                JsonRpcInput::Exit => break,
+                JsonRpcInput::Ignore => {}
+            }
+        }
+    });
+    let translated: RpcOutput = Arc::new(move |line: &str| {
+        if let Some(message) = adapter.handle_output(line) {
+            output(&message.to_string());
+        }
+    });
+    sessions::run_sessions(session, options, lines, translated, factory)
+}
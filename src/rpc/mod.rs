@@ -1,14 +1,37 @@
-use crate::agent::{QueueMode, ThinkingLevel};
+use crate::agent::{QueueKind, QueueMode, QueuePriority, ThinkingLevel};
 use crate::cli::event_json::{serialize_agent_message, serialize_session_event};
 use crate::coding_agent::extension_host::{ExtensionUiRequest, ExtensionUiResponse};
-use crate::coding_agent::AgentSession;
+use crate::coding_agent::{AgentSession, FileChange};
 use crate::core::messages::{ContentBlock, UserContent};
+use crate::core::session_manager::{Checkpoint, SessionFilter, SessionInfo, SessionManager};
+use crate::modes::session_autocomplete_provider;
+use batch::Batch;
+use deltas::MessageDeltas;
 use serde::Deserialize;
 use serde_json::{json, Map, Value};
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
 use std::io::{self, BufRead, Write};
 use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::mpsc::RecvTimeoutError;
 use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+pub mod acp;
+mod batch;
+mod deltas;
+pub mod jsonrpc;
+pub mod server;
+pub mod sessions;
+pub mod supervisor;
+
+pub use acp::{run_acp, run_acp_mode};
+pub use deltas::MESSAGE_DELTAS;
+pub use jsonrpc::{run_jsonrpc, run_jsonrpc_mode, JsonRpcAdapter, JsonRpcInput};
+pub use server::{RpcOutput, RpcServer, DEFAULT_SERVE_PORT};
+pub use sessions::{SessionFactory, SessionSpec, DEFAULT_SESSION_ID};
+pub use supervisor::{RpcModeOptions, RpcSupervisor, SUPERVISOR_POLL_INTERVAL};
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -65,6 +88,20 @@ struct RpcCompactCommand {
     pub custom_instructions: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcSetQueuePriorityCommand {
+    pub id: Option<String>,
+    pub priority: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcSetCompactionStrategyCommand {
+    pub id: Option<String>,
+    pub strategy: String,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct RpcSetAutoCommand {
@@ -79,12 +116,36 @@ struct RpcBashCommand {
     pub command: String,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcRunSkillCommand {
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub args: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcRunTemplateCommand {
+    pub id: Option<String>,
+    pub name: String,
+    /// Positional arguments, and `name=value` pairs.
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Named arguments, for `{{name}}` placeholders.
+    #[serde(default)]
+    pub arguments: BTreeMap<String, String>,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct RpcExportHtmlCommand {
     pub id: Option<String>,
     #[serde(default)]
     pub output_path: Option<String>,
+    #[serde(default)]
+    pub theme: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -101,6 +162,130 @@ struct RpcBranchCommand {
     pub entry_id: String,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcBranchCheckoutCommand {
+    pub id: Option<String>,
+    pub entry_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcPinMessageCommand {
+    pub id: Option<String>,
+    pub entry_id: String,
+    #[serde(default = "default_pinned")]
+    pub pinned: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcCompleteCommand {
+    pub id: Option<String>,
+    pub text: String,
+    /// Cursor position in characters; defaults to the end of `text`.
+    pub cursor: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcRevertFileCommand {
+    pub id: Option<String>,
+    pub path: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcGetMessagesCommand {
+    pub id: Option<String>,
+    /// Also return custom messages marked `display: false`.
+    #[serde(default)]
+    pub include_hidden: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcPendingMessageCommand {
+    pub id: Option<String>,
+    pub message_id: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcEditPendingMessageCommand {
+    pub id: Option<String>,
+    pub message_id: u64,
+    pub text: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcCheckpointCommand {
+    pub id: Option<String>,
+    pub name: String,
+}
+
+fn default_pinned() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcRenameSessionCommand {
+    pub id: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcTagSessionCommand {
+    pub id: Option<String>,
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+    #[serde(default)]
+    pub add: Vec<String>,
+    #[serde(default)]
+    pub remove: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcListSessionsCommand {
+    pub id: Option<String>,
+    #[serde(default)]
+    pub query: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcHelloCommand {
+    pub id: Option<String>,
+    /// Features to enable, as with `capabilities`; omitted leaves them as they are.
+    #[serde(default)]
+    pub enable: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcCapabilitiesCommand {
+    pub id: Option<String>,
+    /// Features the client understands; pi enables the ones it supports.
+    #[serde(default)]
+    pub enable: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcBatchCommand {
+    pub id: Option<String>,
+    pub commands: Vec<Value>,
+    #[serde(default)]
+    pub continue_on_error: bool,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct RpcExtensionUiResponse {
@@ -113,12 +298,136 @@ struct RpcExtensionUiResponse {
     pub cancelled: Option<bool>,
 }
 
-pub fn run_rpc_mode(mut session: AgentSession) -> Result<(), String> {
+/// Reported by `hello`. Bumped only for changes that break existing clients; new commands
+/// and features are discovered from the `hello` response instead.
+pub const RPC_PROTOCOL_VERSION: u32 = 1;
+
+/// Every command RPC mode accepts, session routing included.
+pub const RPC_COMMANDS: &[&str] = &[
+    "hello",
+    "capabilities",
+    "batch",
+    "prompt",
+    "steer",
+    "follow_up",
+    "run_skill",
+    "run_template",
+    "abort",
+    "new_session",
+    "get_state",
+    "set_model",
+    "cycle_model",
+    "get_available_models",
+    "set_thinking_level",
+    "cycle_thinking_level",
+    "set_steering_mode",
+    "set_follow_up_mode",
+    "compact",
+    "set_queue_priority",
+    "get_pending_queue",
+    "get_pending_messages",
+    "remove_pending_message",
+    "edit_pending_message",
+    "clear_pending",
+    "set_compaction_strategy",
+    "set_auto_compaction",
+    "set_auto_retry",
+    "abort_retry",
+    "bash",
+    "abort_bash",
+    "get_session_stats",
+    "get_context_usage",
+    "get_spend",
+    "export_html",
+    "switch_session",
+    "branch",
+    "branch_checkout",
+    "get_branches",
+    "pin_message",
+    "undo_last_change",
+    "revert_file",
+    "create_checkpoint",
+    "list_checkpoints",
+    "rewind",
+    "complete",
+    "get_file_changes",
+    "get_branch_messages",
+    "get_last_assistant_text",
+    "get_messages",
+    "rename_session",
+    "tag_session",
+    "list_sessions",
+    "extension_ui_response",
+    "create_session",
+    "close_session",
+    "list_active_sessions",
+];
+
+/// Optional protocol features a client can turn on with `capabilities`.
+pub const RPC_FEATURES: &[&str] = &[MESSAGE_DELTAS];
+
+pub fn run_rpc_mode(session: AgentSession) -> Result<(), String> {
+    run_rpc_mode_with_options(session, RpcModeOptions::default())
+}
+
+pub fn run_rpc_mode_with_options(
+    session: AgentSession,
+    options: RpcModeOptions,
+) -> Result<(), String> {
+    run_rpc_mode_with_sessions(session, options, None)
+}
+
+/// RPC over stdio. With a `factory`, clients can also `create_session` more sessions.
+pub fn run_rpc_mode_with_sessions(
+    session: AgentSession,
+    options: RpcModeOptions,
+    factory: Option<SessionFactory>,
+) -> Result<(), String> {
+    let stdout: RpcOutput = Arc::new(|line: &str| {
+        println!("{line}");
+        let _ = io::stdout().flush();
+    });
+    sessions::run_sessions(session, options, spawn_stdin_reader(), stdout, factory)
+}
+
+/// `pi --serve`: the same protocol for every client of `server` until the supervisor exits.
+pub fn run_serve_mode(
+    session: AgentSession,
+    options: RpcModeOptions,
+    server: RpcServer,
+    factory: Option<SessionFactory>,
+) -> Result<(), String> {
+    let (lines, output) = server.start();
+    sessions::run_sessions(session, options, lines, output, factory)
+}
+
+/// Runs commands from `lines` against `session` until the channel closes or `supervisor`
+/// asks to exit. Output from sessions other than the default carries their `session_id`.
+fn run_rpc_loop(
+    mut session: AgentSession,
+    mut supervisor: RpcSupervisor,
+    lines: mpsc::Receiver<io::Result<String>>,
+    output: RpcOutput,
+    session_id: Option<String>,
+) -> Result<(), String> {
+    // The batch being run, which notes the responses to its commands.
+    let batch: Rc<RefCell<Option<Batch>>> = Rc::new(RefCell::new(None));
+    let batch_recorder = batch.clone();
+    let emit_json = move |value: &Value| {
+        if value["type"] == "response" {
+            if let Some(batch) = batch_recorder.borrow_mut().as_mut() {
+                batch.record(value);
+            }
+        }
+        emit_value(&output, value, session_id.as_deref());
+    };
     let pending_ui: Arc<Mutex<HashMap<String, mpsc::Sender<ExtensionUiResponse>>>> =
         Arc::new(Mutex::new(HashMap::new()));
 
     let pending_ui_handler = pending_ui.clone();
+    let ui_emit_json = emit_json.clone();
     session.set_extension_ui_handler(move |request| {
+        let emit_json = &ui_emit_json;
         let value = extension_ui_request_to_value(request);
         let needs_response = matches!(
             request.method.as_str(),
@@ -141,36 +450,78 @@ pub fn run_rpc_mode(mut session: AgentSession) -> Result<(), String> {
         response
     });
 
-    let _subscription = session.subscribe(|event| {
-        if let Some(value) = serialize_session_event(event) {
+    // Set once the client enables `message_deltas` with `capabilities`.
+    let deltas: Rc<RefCell<Option<MessageDeltas>>> = Rc::new(RefCell::new(None));
+    let event_deltas = deltas.clone();
+    let event_emit_json = emit_json.clone();
+    let _subscription = session.subscribe(move |event| {
+        let emit_json = &event_emit_json;
+        if let Some(deltas) = event_deltas.borrow_mut().as_mut() {
+            for value in deltas.encode(event) {
+                emit_json(&value);
+            }
+        } else if let Some(value) = serialize_session_event(event) {
             emit_json(&value);
         }
     });
 
-    let mut stdin = io::stdin().lock();
     loop {
-        let mut line = String::new();
-        let bytes = stdin.read_line(&mut line).map_err(|err| err.to_string())?;
-        if bytes == 0 {
-            break;
-        }
-        let trimmed = line.trim();
-        if trimmed.is_empty() {
-            continue;
+        supervisor.finish_command();
+        // Pick up models added with `pi models add` (or edits to models.json) mid-session.
+        if session.model_registry.reload_if_changed() {
+            emit_json(&json!({
+                "type": "models_reloaded",
+                "count": session.model_registry.get_all().len(),
+            }));
         }
-        let value: Value = match serde_json::from_str(trimmed) {
-            Ok(value) => value,
-            Err(err) => {
-                let error = response_error(None, "parse", &format!("Invalid JSON: {err}"));
-                emit_json(&error);
+        // A batch's commands run before the next line is read.
+        let batched = batch.borrow_mut().as_mut().map(Batch::next_command);
+        let value = match batched {
+            Some(Some(value)) => {
+                supervisor.start_command();
+                value
+            }
+            Some(None) => {
+                let finished = batch.borrow_mut().take();
+                if let Some(finished) = finished {
+                    emit_json(&finished.summary());
+                }
                 continue;
             }
+            None => {
+                let line = match lines.recv_timeout(SUPERVISOR_POLL_INTERVAL) {
+                    Ok(line) => line.map_err(|err| err.to_string())?,
+                    Err(RecvTimeoutError::Timeout) => {
+                        if let Some(reason) = supervisor.exit_reason() {
+                            emit_json(&json!({ "type": "shutdown", "reason": reason }));
+                            break;
+                        }
+                        continue;
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
+                supervisor.start_command();
+                let trimmed = line.trim();
+                if trimmed.is_empty() {
+                    continue;
+                }
+                match serde_json::from_str(trimmed) {
+                    Ok(value) => value,
+                    Err(err) => {
+                        let error = response_error(None, "parse", &format!("Invalid JSON: {err}"));
+                        emit_json(&error);
+                        continue;
+                    }
+                }
+            }
         };
         let kind = value
             .get("type")
             .and_then(|value| value.as_str())
             .unwrap_or("")
             .to_string();
+        // Echoed on errors for payloads that do not parse, so clients can match them up.
+        let request_id = value.get("id").and_then(Value::as_str).map(str::to_string);
 
         if kind == "extension_ui_response" {
             if let Ok(response) = serde_json::from_value::<RpcExtensionUiResponse>(value.clone()) {
@@ -189,12 +540,107 @@ pub fn run_rpc_mode(mut session: AgentSession) -> Result<(), String> {
         }
 
         match kind.as_str() {
+            "batch" => {
+                if batch.borrow().is_some() {
+                    emit_json(&response_error(
+                        request_id.as_deref(),
+                        "batch",
+                        "Batches cannot be nested",
+                    ));
+                    continue;
+                }
+                let command: RpcBatchCommand = match serde_json::from_value(value) {
+                    Ok(command) => command,
+                    Err(err) => {
+                        emit_json(&response_error(
+                            request_id.as_deref(),
+                            "batch",
+                            &format!("Invalid payload: {err}"),
+                        ));
+                        continue;
+                    }
+                };
+                *batch.borrow_mut() = Some(Batch::new(
+                    command.id,
+                    command.commands,
+                    command.continue_on_error,
+                ));
+            }
+            "hello" => {
+                let command: RpcHelloCommand = match serde_json::from_value(value) {
+                    Ok(command) => command,
+                    Err(err) => {
+                        emit_json(&response_error(
+                            request_id.as_deref(),
+                            "hello",
+                            &format!("Invalid payload: {err}"),
+                        ));
+                        continue;
+                    }
+                };
+                if let Some(enable) = command.enable.as_deref() {
+                    *deltas.borrow_mut() = enable_features(enable)
+                        .contains(&MESSAGE_DELTAS)
+                        .then(MessageDeltas::default);
+                }
+                let enabled = if deltas.borrow().is_some() {
+                    vec![MESSAGE_DELTAS]
+                } else {
+                    Vec::new()
+                };
+                let state = session.get_state();
+                let extensions = session
+                    .extension_host()
+                    .map(|host| host.borrow().extension_paths().to_vec())
+                    .unwrap_or_default();
+                let extension_commands = session
+                    .extension_commands()
+                    .iter()
+                    .map(|command| command.name.clone())
+                    .collect::<Vec<_>>();
+                emit_json(&response_success(
+                    command.id.as_deref(),
+                    "hello",
+                    Some(json!({
+                        "protocolVersion": RPC_PROTOCOL_VERSION,
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "commands": RPC_COMMANDS,
+                        "features": { "supported": RPC_FEATURES, "enabled": enabled },
+                        "model": agent_model_value(&state.model),
+                        "thinkingLevel": state.thinking_level.as_str(),
+                        "extensions": extensions,
+                        "extensionCommands": extension_commands,
+                    })),
+                ));
+            }
+            "capabilities" => {
+                let command: RpcCapabilitiesCommand = match serde_json::from_value(value) {
+                    Ok(command) => command,
+                    Err(err) => {
+                        emit_json(&response_error(
+                            request_id.as_deref(),
+                            "capabilities",
+                            &format!("Invalid payload: {err}"),
+                        ));
+                        continue;
+                    }
+                };
+                let enabled = enable_features(&command.enable);
+                *deltas.borrow_mut() = enabled
+                    .contains(&MESSAGE_DELTAS)
+                    .then(MessageDeltas::default);
+                emit_json(&response_success(
+                    command.id.as_deref(),
+                    "capabilities",
+                    Some(json!({ "supported": RPC_FEATURES, "enabled": enabled })),
+                ));
+            }
             "prompt" => {
                 let command: RpcPromptCommand = match serde_json::from_value(value) {
                     Ok(command) => command,
                     Err(err) => {
                         emit_json(&response_error(
-                            None,
+                            request_id.as_deref(),
                             "prompt",
                             &format!("Invalid payload: {err}"),
                         ));
@@ -236,7 +682,7 @@ pub fn run_rpc_mode(mut session: AgentSession) -> Result<(), String> {
                     Ok(command) => command,
                     Err(err) => {
                         emit_json(&response_error(
-                            None,
+                            request_id.as_deref(),
                             "steer",
                             &format!("Invalid payload: {err}"),
                         ));
@@ -251,7 +697,7 @@ pub fn run_rpc_mode(mut session: AgentSession) -> Result<(), String> {
                     Ok(command) => command,
                     Err(err) => {
                         emit_json(&response_error(
-                            None,
+                            request_id.as_deref(),
                             "follow_up",
                             &format!("Invalid payload: {err}"),
                         ));
@@ -261,12 +707,55 @@ pub fn run_rpc_mode(mut session: AgentSession) -> Result<(), String> {
                 session.follow_up(&command.message);
                 emit_json(&response_success(command.id.as_deref(), "follow_up", None));
             }
+            "run_skill" => {
+                let command: RpcRunSkillCommand = match serde_json::from_value(value) {
+                    Ok(command) => command,
+                    Err(err) => {
+                        emit_json(&response_error(
+                            request_id.as_deref(),
+                            "run_skill",
+                            &format!("Invalid payload: {err}"),
+                        ));
+                        continue;
+                    }
+                };
+                let id = command.id.as_deref();
+                match session.run_skill(&command.name, &command.args) {
+                    Ok(()) => emit_json(&response_success(id, "run_skill", None)),
+                    Err(err) => emit_json(&response_error(id, "run_skill", &err.to_string())),
+                }
+            }
+            "run_template" => {
+                let command: RpcRunTemplateCommand = match serde_json::from_value(value) {
+                    Ok(command) => command,
+                    Err(err) => {
+                        emit_json(&response_error(
+                            request_id.as_deref(),
+                            "run_template",
+                            &format!("Invalid payload: {err}"),
+                        ));
+                        continue;
+                    }
+                };
+                let id = command.id.as_deref();
+                let mut args = command.args;
+                args.extend(
+                    command
+                        .arguments
+                        .iter()
+                        .map(|(name, value)| format!("{name}={value}")),
+                );
+                match session.run_template(&command.name, &args) {
+                    Ok(()) => emit_json(&response_success(id, "run_template", None)),
+                    Err(err) => emit_json(&response_error(id, "run_template", &err.to_string())),
+                }
+            }
             "abort" => {
                 let command: RpcSimpleCommand = match serde_json::from_value(value) {
                     Ok(command) => command,
                     Err(err) => {
                         emit_json(&response_error(
-                            None,
+                            request_id.as_deref(),
                             "abort",
                             &format!("Invalid payload: {err}"),
                         ));
@@ -281,7 +770,7 @@ pub fn run_rpc_mode(mut session: AgentSession) -> Result<(), String> {
                     Ok(command) => command,
                     Err(err) => {
                         emit_json(&response_error(
-                            None,
+                            request_id.as_deref(),
                             "new_session",
                             &format!("Invalid payload: {err}"),
                         ));
@@ -300,7 +789,7 @@ pub fn run_rpc_mode(mut session: AgentSession) -> Result<(), String> {
                     Ok(command) => command,
                     Err(err) => {
                         emit_json(&response_error(
-                            None,
+                            request_id.as_deref(),
                             "get_state",
                             &format!("Invalid payload: {err}"),
                         ));
@@ -308,6 +797,7 @@ pub fn run_rpc_mode(mut session: AgentSession) -> Result<(), String> {
                     }
                 };
                 let state = session.get_state();
+                let usage = session.get_context_usage();
                 let data = json!({
                     "model": agent_model_value(&state.model),
                     "thinkingLevel": state.thinking_level.as_str(),
@@ -315,11 +805,19 @@ pub fn run_rpc_mode(mut session: AgentSession) -> Result<(), String> {
                     "isCompacting": false,
                     "steeringMode": queue_mode_to_str(session.steering_mode()),
                     "followUpMode": queue_mode_to_str(session.follow_up_mode()),
+                    "queuePriority": session.queue_priority().as_str(),
                     "sessionFile": session.session_file().map(|path| path.to_string_lossy().to_string()),
                     "sessionId": session.session_id(),
+                    "sessionName": session.session_manager.get_session_name(),
                     "autoCompactionEnabled": session.auto_compaction_enabled(),
+                    "compactionStrategy": session.compaction_strategy_name(),
+                    "autoCompactionThreshold": session.settings_manager.get_compaction_threshold(),
+                    "contextTokens": usage.tokens,
+                    "contextWindow": usage.context_window,
+                    "compactionTriggerTokens": usage.compaction_threshold,
                     "messageCount": state.message_count,
                     "pendingMessageCount": session.pending_message_count(),
+                    "contextFiles": session.context_files(),
                 });
                 emit_json(&response_success(
                     command.id.as_deref(),
@@ -332,7 +830,7 @@ pub fn run_rpc_mode(mut session: AgentSession) -> Result<(), String> {
                     Ok(command) => command,
                     Err(err) => {
                         emit_json(&response_error(
-                            None,
+                            request_id.as_deref(),
                             "set_model",
                             &format!("Invalid payload: {err}"),
                         ));
@@ -370,7 +868,7 @@ pub fn run_rpc_mode(mut session: AgentSession) -> Result<(), String> {
                     Ok(command) => command,
                     Err(err) => {
                         emit_json(&response_error(
-                            None,
+                            request_id.as_deref(),
                             "cycle_model",
                             &format!("Invalid payload: {err}"),
                         ));
@@ -396,7 +894,7 @@ pub fn run_rpc_mode(mut session: AgentSession) -> Result<(), String> {
                     Ok(command) => command,
                     Err(err) => {
                         emit_json(&response_error(
-                            None,
+                            request_id.as_deref(),
                             "get_available_models",
                             &format!("Invalid payload: {err}"),
                         ));
@@ -415,7 +913,7 @@ pub fn run_rpc_mode(mut session: AgentSession) -> Result<(), String> {
                     Ok(command) => command,
                     Err(err) => {
                         emit_json(&response_error(
-                            None,
+                            request_id.as_deref(),
                             "set_thinking_level",
                             &format!("Invalid payload: {err}"),
                         ));
@@ -445,7 +943,7 @@ pub fn run_rpc_mode(mut session: AgentSession) -> Result<(), String> {
                     Ok(command) => command,
                     Err(err) => {
                         emit_json(&response_error(
-                            None,
+                            request_id.as_deref(),
                             "cycle_thinking_level",
                             &format!("Invalid payload: {err}"),
                         ));
@@ -464,7 +962,7 @@ pub fn run_rpc_mode(mut session: AgentSession) -> Result<(), String> {
                     Ok(command) => command,
                     Err(err) => {
                         emit_json(&response_error(
-                            None,
+                            request_id.as_deref(),
                             &kind,
                             &format!("Invalid payload: {err}"),
                         ));
@@ -494,7 +992,7 @@ pub fn run_rpc_mode(mut session: AgentSession) -> Result<(), String> {
                     Ok(command) => command,
                     Err(err) => {
                         emit_json(&response_error(
-                            None,
+                            request_id.as_deref(),
                             "compact",
                             &format!("Invalid payload: {err}"),
                         ));
@@ -518,60 +1016,218 @@ pub fn run_rpc_mode(mut session: AgentSession) -> Result<(), String> {
                     )),
                 }
             }
-            "set_auto_compaction" => {
-                let command: RpcSetAutoCommand = match serde_json::from_value(value) {
+            "set_queue_priority" => {
+                let command: RpcSetQueuePriorityCommand = match serde_json::from_value(value) {
                     Ok(command) => command,
                     Err(err) => {
                         emit_json(&response_error(
-                            None,
-                            "set_auto_compaction",
+                            request_id.as_deref(),
+                            "set_queue_priority",
                             &format!("Invalid payload: {err}"),
                         ));
                         continue;
                     }
                 };
-                session.set_auto_compaction_enabled(command.enabled);
-                emit_json(&response_success(
-                    command.id.as_deref(),
-                    "set_auto_compaction",
-                    None,
-                ));
+                match QueuePriority::parse(&command.priority) {
+                    Some(priority) => {
+                        session.set_queue_priority(priority);
+                        emit_json(&response_success(
+                            command.id.as_deref(),
+                            "set_queue_priority",
+                            None,
+                        ));
+                    }
+                    None => emit_json(&response_error(
+                        command.id.as_deref(),
+                        "set_queue_priority",
+                        "Invalid queue priority",
+                    )),
+                }
             }
-            "set_auto_retry" => {
-                let command: RpcSetAutoCommand = match serde_json::from_value(value) {
+            "get_pending_queue" | "get_pending_messages" => {
+                let command: RpcSimpleCommand = match serde_json::from_value(value) {
                     Ok(command) => command,
                     Err(err) => {
                         emit_json(&response_error(
-                            None,
-                            "set_auto_retry",
+                            request_id.as_deref(),
+                            kind.as_str(),
                             &format!("Invalid payload: {err}"),
                         ));
                         continue;
                     }
                 };
-                session.set_auto_retry_enabled(command.enabled);
+                let messages = session
+                    .pending_queue()
+                    .iter()
+                    .map(|pending| {
+                        json!({
+                            "id": pending.id,
+                            "queue": queue_kind_to_str(pending.kind),
+                            "message": serialize_agent_message(&pending.message),
+                        })
+                    })
+                    .collect::<Vec<_>>();
                 emit_json(&response_success(
                     command.id.as_deref(),
-                    "set_auto_retry",
-                    None,
+                    kind.as_str(),
+                    Some(json!({
+                        "priority": session.queue_priority().as_str(),
+                        "messages": messages,
+                    })),
                 ));
             }
-            "abort_retry" => {
-                let command: RpcSimpleCommand = match serde_json::from_value(value) {
+            "remove_pending_message" => {
+                let command: RpcPendingMessageCommand = match serde_json::from_value(value) {
                     Ok(command) => command,
                     Err(err) => {
                         emit_json(&response_error(
-                            None,
-                            "abort_retry",
+                            request_id.as_deref(),
+                            "remove_pending_message",
                             &format!("Invalid payload: {err}"),
                         ));
                         continue;
                     }
                 };
-                session.abort_retry();
-                emit_json(&response_success(
-                    command.id.as_deref(),
-                    "abort_retry",
+                match session.remove_pending_message(command.message_id) {
+                    Ok(pending) => emit_json(&response_success(
+                        command.id.as_deref(),
+                        "remove_pending_message",
+                        Some(json!({
+                            "id": pending.id,
+                            "queue": queue_kind_to_str(pending.kind),
+                            "message": serialize_agent_message(&pending.message),
+                        })),
+                    )),
+                    Err(err) => emit_json(&response_error(
+                        command.id.as_deref(),
+                        "remove_pending_message",
+                        &err.to_string(),
+                    )),
+                }
+            }
+            "edit_pending_message" => {
+                let command: RpcEditPendingMessageCommand = match serde_json::from_value(value) {
+                    Ok(command) => command,
+                    Err(err) => {
+                        emit_json(&response_error(
+                            request_id.as_deref(),
+                            "edit_pending_message",
+                            &format!("Invalid payload: {err}"),
+                        ));
+                        continue;
+                    }
+                };
+                match session.edit_pending_message(command.message_id, &command.text) {
+                    Ok(()) => emit_json(&response_success(
+                        command.id.as_deref(),
+                        "edit_pending_message",
+                        None,
+                    )),
+                    Err(err) => emit_json(&response_error(
+                        command.id.as_deref(),
+                        "edit_pending_message",
+                        &err.to_string(),
+                    )),
+                }
+            }
+            "clear_pending" => {
+                let command: RpcSimpleCommand = match serde_json::from_value(value) {
+                    Ok(command) => command,
+                    Err(err) => {
+                        emit_json(&response_error(
+                            request_id.as_deref(),
+                            "clear_pending",
+                            &format!("Invalid payload: {err}"),
+                        ));
+                        continue;
+                    }
+                };
+                let cleared = session.clear_pending();
+                emit_json(&response_success(
+                    command.id.as_deref(),
+                    "clear_pending",
+                    Some(json!({ "cleared": cleared })),
+                ));
+            }
+            "set_compaction_strategy" => {
+                let command: RpcSetCompactionStrategyCommand = match serde_json::from_value(value) {
+                    Ok(command) => command,
+                    Err(err) => {
+                        emit_json(&response_error(
+                            request_id.as_deref(),
+                            "set_compaction_strategy",
+                            &format!("Invalid payload: {err}"),
+                        ));
+                        continue;
+                    }
+                };
+                match session.set_compaction_strategy_name(&command.strategy) {
+                    Ok(()) => emit_json(&response_success(
+                        command.id.as_deref(),
+                        "set_compaction_strategy",
+                        None,
+                    )),
+                    Err(err) => emit_json(&response_error(
+                        command.id.as_deref(),
+                        "set_compaction_strategy",
+                        &err.to_string(),
+                    )),
+                }
+            }
+            "set_auto_compaction" => {
+                let command: RpcSetAutoCommand = match serde_json::from_value(value) {
+                    Ok(command) => command,
+                    Err(err) => {
+                        emit_json(&response_error(
+                            request_id.as_deref(),
+                            "set_auto_compaction",
+                            &format!("Invalid payload: {err}"),
+                        ));
+                        continue;
+                    }
+                };
+                session.set_auto_compaction_enabled(command.enabled);
+                emit_json(&response_success(
+                    command.id.as_deref(),
+                    "set_auto_compaction",
+                    None,
+                ));
+            }
+            "set_auto_retry" => {
+                let command: RpcSetAutoCommand = match serde_json::from_value(value) {
+                    Ok(command) => command,
+                    Err(err) => {
+                        emit_json(&response_error(
+                            request_id.as_deref(),
+                            "set_auto_retry",
+                            &format!("Invalid payload: {err}"),
+                        ));
+                        continue;
+                    }
+                };
+                session.set_auto_retry_enabled(command.enabled);
+                emit_json(&response_success(
+                    command.id.as_deref(),
+                    "set_auto_retry",
+                    None,
+                ));
+            }
+            "abort_retry" => {
+                let command: RpcSimpleCommand = match serde_json::from_value(value) {
+                    Ok(command) => command,
+                    Err(err) => {
+                        emit_json(&response_error(
+                            request_id.as_deref(),
+                            "abort_retry",
+                            &format!("Invalid payload: {err}"),
+                        ));
+                        continue;
+                    }
+                };
+                session.abort_retry();
+                emit_json(&response_success(
+                    command.id.as_deref(),
+                    "abort_retry",
                     None,
                 ));
             }
@@ -580,7 +1236,7 @@ pub fn run_rpc_mode(mut session: AgentSession) -> Result<(), String> {
                     Ok(command) => command,
                     Err(err) => {
                         emit_json(&response_error(
-                            None,
+                            request_id.as_deref(),
                             "bash",
                             &format!("Invalid payload: {err}"),
                         ));
@@ -610,7 +1266,7 @@ pub fn run_rpc_mode(mut session: AgentSession) -> Result<(), String> {
                     Ok(command) => command,
                     Err(err) => {
                         emit_json(&response_error(
-                            None,
+                            request_id.as_deref(),
                             "abort_bash",
                             &format!("Invalid payload: {err}"),
                         ));
@@ -625,7 +1281,7 @@ pub fn run_rpc_mode(mut session: AgentSession) -> Result<(), String> {
                     Ok(command) => command,
                     Err(err) => {
                         emit_json(&response_error(
-                            None,
+                            request_id.as_deref(),
                             "get_session_stats",
                             &format!("Invalid payload: {err}"),
                         ));
@@ -639,12 +1295,56 @@ pub fn run_rpc_mode(mut session: AgentSession) -> Result<(), String> {
                     Some(serde_json::to_value(stats).unwrap_or(Value::Null)),
                 ));
             }
+            "get_context_usage" => {
+                let command: RpcSimpleCommand = match serde_json::from_value(value) {
+                    Ok(command) => command,
+                    Err(err) => {
+                        emit_json(&response_error(
+                            request_id.as_deref(),
+                            "get_context_usage",
+                            &format!("Invalid payload: {err}"),
+                        ));
+                        continue;
+                    }
+                };
+                let usage = session.get_context_usage();
+                emit_json(&response_success(
+                    command.id.as_deref(),
+                    "get_context_usage",
+                    Some(serde_json::to_value(usage).unwrap_or(Value::Null)),
+                ));
+            }
+            "get_spend" => {
+                let command: RpcSimpleCommand = match serde_json::from_value(value) {
+                    Ok(command) => command,
+                    Err(err) => {
+                        emit_json(&response_error(
+                            request_id.as_deref(),
+                            "get_spend",
+                            &format!("Invalid payload: {err}"),
+                        ));
+                        continue;
+                    }
+                };
+                let spend = session.get_spend();
+                emit_json(&response_success(
+                    command.id.as_deref(),
+                    "get_spend",
+                    Some(json!({
+                        "session": spend.session,
+                        "today": spend.today,
+                        "maxCost": spend.limits.max_cost,
+                        "maxDailyCost": spend.limits.max_daily_cost,
+                        "exceeded": spend.exceeded(),
+                    })),
+                ));
+            }
             "export_html" => {
                 let command: RpcExportHtmlCommand = match serde_json::from_value(value) {
                     Ok(command) => command,
                     Err(err) => {
                         emit_json(&response_error(
-                            None,
+                            request_id.as_deref(),
                             "export_html",
                             &format!("Invalid payload: {err}"),
                         ));
@@ -652,7 +1352,9 @@ pub fn run_rpc_mode(mut session: AgentSession) -> Result<(), String> {
                     }
                 };
                 let output_path = command.output_path.map(PathBuf::from);
-                match session.export_to_html_with_path(output_path.as_ref()) {
+                match session
+                    .export_to_html_with_theme(output_path.as_ref(), command.theme.as_deref())
+                {
                     Ok(result) => emit_json(&response_success(
                         command.id.as_deref(),
                         "export_html",
@@ -670,7 +1372,7 @@ pub fn run_rpc_mode(mut session: AgentSession) -> Result<(), String> {
                     Ok(command) => command,
                     Err(err) => {
                         emit_json(&response_error(
-                            None,
+                            request_id.as_deref(),
                             "switch_session",
                             &format!("Invalid payload: {err}"),
                         ));
@@ -695,7 +1397,7 @@ pub fn run_rpc_mode(mut session: AgentSession) -> Result<(), String> {
                     Ok(command) => command,
                     Err(err) => {
                         emit_json(&response_error(
-                            None,
+                            request_id.as_deref(),
                             "branch",
                             &format!("Invalid payload: {err}"),
                         ));
@@ -718,12 +1420,280 @@ pub fn run_rpc_mode(mut session: AgentSession) -> Result<(), String> {
                     )),
                 }
             }
+            "branch_checkout" => {
+                let command: RpcBranchCheckoutCommand = match serde_json::from_value(value) {
+                    Ok(command) => command,
+                    Err(err) => {
+                        emit_json(&response_error(
+                            request_id.as_deref(),
+                            "branch_checkout",
+                            &format!("Invalid payload: {err}"),
+                        ));
+                        continue;
+                    }
+                };
+                match session.branch_from(&command.entry_id) {
+                    Ok(checkout) => emit_json(&response_success(
+                        command.id.as_deref(),
+                        "branch_checkout",
+                        Some(json!({
+                            "entryId": checkout.entry_id,
+                            "sessionId": checkout.session_id,
+                            "sessionFile": checkout.session_file.map(|path| path.to_string_lossy().to_string()),
+                            "parentSession": checkout.parent_session.map(|path| path.to_string_lossy().to_string()),
+                            "messageCount": session.messages().len(),
+                        })),
+                    )),
+                    Err(err) => emit_json(&response_error(
+                        command.id.as_deref(),
+                        "branch_checkout",
+                        &err.to_string(),
+                    )),
+                }
+            }
+            "get_branches" => {
+                let command: RpcSimpleCommand = match serde_json::from_value(value) {
+                    Ok(command) => command,
+                    Err(err) => {
+                        emit_json(&response_error(
+                            request_id.as_deref(),
+                            "get_branches",
+                            &format!("Invalid payload: {err}"),
+                        ));
+                        continue;
+                    }
+                };
+                let children = session
+                    .session_manager
+                    .list_child_sessions()
+                    .iter()
+                    .map(session_info_value)
+                    .collect::<Vec<_>>();
+                emit_json(&response_success(
+                    command.id.as_deref(),
+                    "get_branches",
+                    Some(json!({
+                        "parentSession": session
+                            .session_manager
+                            .get_parent_session()
+                            .map(|path| path.to_string_lossy().to_string()),
+                        "children": children,
+                    })),
+                ));
+            }
+            "pin_message" => {
+                let command: RpcPinMessageCommand = match serde_json::from_value(value) {
+                    Ok(command) => command,
+                    Err(err) => {
+                        emit_json(&response_error(
+                            request_id.as_deref(),
+                            "pin_message",
+                            &format!("Invalid payload: {err}"),
+                        ));
+                        continue;
+                    }
+                };
+                match session.pin_message(&command.entry_id, command.pinned) {
+                    Ok(()) => emit_json(&response_success(
+                        command.id.as_deref(),
+                        "pin_message",
+                        Some(json!({ "pinnedEntryIds": session.pinned_entry_ids() })),
+                    )),
+                    Err(err) => emit_json(&response_error(
+                        command.id.as_deref(),
+                        "pin_message",
+                        &err.to_string(),
+                    )),
+                }
+            }
+            "undo_last_change" => {
+                let command: RpcSimpleCommand = match serde_json::from_value(value) {
+                    Ok(command) => command,
+                    Err(err) => {
+                        emit_json(&response_error(
+                            request_id.as_deref(),
+                            "undo_last_change",
+                            &format!("Invalid payload: {err}"),
+                        ));
+                        continue;
+                    }
+                };
+                match session.undo_last_change() {
+                    Ok(change) => emit_json(&response_success(
+                        command.id.as_deref(),
+                        "undo_last_change",
+                        Some(file_change_value(&change)),
+                    )),
+                    Err(err) => emit_json(&response_error(
+                        command.id.as_deref(),
+                        "undo_last_change",
+                        &err.to_string(),
+                    )),
+                }
+            }
+            "revert_file" => {
+                let command: RpcRevertFileCommand = match serde_json::from_value(value) {
+                    Ok(command) => command,
+                    Err(err) => {
+                        emit_json(&response_error(
+                            request_id.as_deref(),
+                            "revert_file",
+                            &format!("Invalid payload: {err}"),
+                        ));
+                        continue;
+                    }
+                };
+                match session.revert_file(&command.path) {
+                    Ok(changes) => {
+                        let changes = changes.iter().map(file_change_value).collect::<Vec<_>>();
+                        emit_json(&response_success(
+                            command.id.as_deref(),
+                            "revert_file",
+                            Some(json!({ "path": command.path, "changes": changes })),
+                        ))
+                    }
+                    Err(err) => emit_json(&response_error(
+                        command.id.as_deref(),
+                        "revert_file",
+                        &err.to_string(),
+                    )),
+                }
+            }
+            "create_checkpoint" => {
+                let command: RpcCheckpointCommand = match serde_json::from_value(value) {
+                    Ok(command) => command,
+                    Err(err) => {
+                        emit_json(&response_error(
+                            request_id.as_deref(),
+                            "create_checkpoint",
+                            &format!("Invalid payload: {err}"),
+                        ));
+                        continue;
+                    }
+                };
+                match session.create_checkpoint(&command.name) {
+                    Ok(checkpoint) => emit_json(&response_success(
+                        command.id.as_deref(),
+                        "create_checkpoint",
+                        Some(checkpoint_value(&checkpoint)),
+                    )),
+                    Err(err) => emit_json(&response_error(
+                        command.id.as_deref(),
+                        "create_checkpoint",
+                        &err.to_string(),
+                    )),
+                }
+            }
+            "list_checkpoints" => {
+                let command: RpcSimpleCommand = match serde_json::from_value(value) {
+                    Ok(command) => command,
+                    Err(err) => {
+                        emit_json(&response_error(
+                            request_id.as_deref(),
+                            "list_checkpoints",
+                            &format!("Invalid payload: {err}"),
+                        ));
+                        continue;
+                    }
+                };
+                let checkpoints = session
+                    .checkpoints()
+                    .iter()
+                    .map(checkpoint_value)
+                    .collect::<Vec<_>>();
+                emit_json(&response_success(
+                    command.id.as_deref(),
+                    "list_checkpoints",
+                    Some(json!({
+                        "checkpoints": checkpoints,
+                        "leafId": session.session_manager.get_leaf_id(),
+                    })),
+                ));
+            }
+            "rewind" => {
+                let command: RpcCheckpointCommand = match serde_json::from_value(value) {
+                    Ok(command) => command,
+                    Err(err) => {
+                        emit_json(&response_error(
+                            request_id.as_deref(),
+                            "rewind",
+                            &format!("Invalid payload: {err}"),
+                        ));
+                        continue;
+                    }
+                };
+                match session.rewind(&command.name) {
+                    Ok(result) => {
+                        let undone = result
+                            .undone
+                            .iter()
+                            .map(file_change_value)
+                            .collect::<Vec<_>>();
+                        emit_json(&response_success(
+                            command.id.as_deref(),
+                            "rewind",
+                            Some(json!({
+                                "checkpoint": checkpoint_value(&result.checkpoint),
+                                "undone": undone,
+                                "filesRestored": result.files_restored,
+                            })),
+                        ))
+                    }
+                    Err(err) => emit_json(&response_error(
+                        command.id.as_deref(),
+                        "rewind",
+                        &err.to_string(),
+                    )),
+                }
+            }
+            "complete" => {
+                let command: RpcCompleteCommand = match serde_json::from_value(value) {
+                    Ok(command) => command,
+                    Err(err) => {
+                        emit_json(&response_error(
+                            request_id.as_deref(),
+                            "complete",
+                            &format!("Invalid payload: {err}"),
+                        ));
+                        continue;
+                    }
+                };
+                let data = completion_value(&session, &command.text, command.cursor);
+                emit_json(&response_success(
+                    command.id.as_deref(),
+                    "complete",
+                    Some(data),
+                ));
+            }
+            "get_file_changes" => {
+                let command: RpcSimpleCommand = match serde_json::from_value(value) {
+                    Ok(command) => command,
+                    Err(err) => {
+                        emit_json(&response_error(
+                            request_id.as_deref(),
+                            "get_file_changes",
+                            &format!("Invalid payload: {err}"),
+                        ));
+                        continue;
+                    }
+                };
+                let changes = session
+                    .file_changes()
+                    .iter()
+                    .map(file_change_value)
+                    .collect::<Vec<_>>();
+                emit_json(&response_success(
+                    command.id.as_deref(),
+                    "get_file_changes",
+                    Some(json!({ "changes": changes })),
+                ));
+            }
             "get_branch_messages" => {
                 let command: RpcSimpleCommand = match serde_json::from_value(value) {
                     Ok(command) => command,
                     Err(err) => {
                         emit_json(&response_error(
-                            None,
+                            request_id.as_deref(),
                             "get_branch_messages",
                             &format!("Invalid payload: {err}"),
                         ));
@@ -751,7 +1721,7 @@ pub fn run_rpc_mode(mut session: AgentSession) -> Result<(), String> {
                     Ok(command) => command,
                     Err(err) => {
                         emit_json(&response_error(
-                            None,
+                            request_id.as_deref(),
                             "get_last_assistant_text",
                             &format!("Invalid payload: {err}"),
                         ));
@@ -766,11 +1736,11 @@ pub fn run_rpc_mode(mut session: AgentSession) -> Result<(), String> {
                 ));
             }
             "get_messages" => {
-                let command: RpcSimpleCommand = match serde_json::from_value(value) {
+                let command: RpcGetMessagesCommand = match serde_json::from_value(value) {
                     Ok(command) => command,
                     Err(err) => {
                         emit_json(&response_error(
-                            None,
+                            request_id.as_deref(),
                             "get_messages",
                             &format!("Invalid payload: {err}"),
                         ));
@@ -778,9 +1748,9 @@ pub fn run_rpc_mode(mut session: AgentSession) -> Result<(), String> {
                     }
                 };
                 let messages = session
-                    .messages()
-                    .iter()
-                    .map(serialize_agent_message)
+                    .session_messages(command.include_hidden)
+                    .into_iter()
+                    .map(|message| serde_json::to_value(message).unwrap_or(Value::Null))
                     .collect::<Vec<_>>();
                 emit_json(&response_success(
                     command.id.as_deref(),
@@ -788,15 +1758,142 @@ pub fn run_rpc_mode(mut session: AgentSession) -> Result<(), String> {
                     Some(json!({ "messages": messages })),
                 ));
             }
+            "rename_session" => {
+                let command: RpcRenameSessionCommand = match serde_json::from_value(value) {
+                    Ok(command) => command,
+                    Err(err) => {
+                        emit_json(&response_error(
+                            request_id.as_deref(),
+                            "rename_session",
+                            &format!("Invalid payload: {err}"),
+                        ));
+                        continue;
+                    }
+                };
+                session
+                    .session_manager
+                    .set_session_name(command.name.as_deref());
+                emit_json(&response_success(
+                    command.id.as_deref(),
+                    "rename_session",
+                    Some(json!({ "name": session.session_manager.get_session_name() })),
+                ));
+            }
+            "tag_session" => {
+                let command: RpcTagSessionCommand = match serde_json::from_value(value) {
+                    Ok(command) => command,
+                    Err(err) => {
+                        emit_json(&response_error(
+                            request_id.as_deref(),
+                            "tag_session",
+                            &format!("Invalid payload: {err}"),
+                        ));
+                        continue;
+                    }
+                };
+                let mut tags = command
+                    .tags
+                    .unwrap_or_else(|| session.session_manager.get_session_tags());
+                tags.extend(command.add);
+                tags.retain(|tag| !command.remove.iter().any(|removed| removed.trim() == tag));
+                session.session_manager.set_session_tags(&tags);
+                emit_json(&response_success(
+                    command.id.as_deref(),
+                    "tag_session",
+                    Some(json!({ "tags": session.session_manager.get_session_tags() })),
+                ));
+            }
+            "list_sessions" => {
+                let command: RpcListSessionsCommand = match serde_json::from_value(value) {
+                    Ok(command) => command,
+                    Err(err) => {
+                        emit_json(&response_error(
+                            request_id.as_deref(),
+                            "list_sessions",
+                            &format!("Invalid payload: {err}"),
+                        ));
+                        continue;
+                    }
+                };
+                let filter = SessionFilter {
+                    query: command.query,
+                    tags: command.tags,
+                };
+                let sessions = SessionManager::search(
+                    &session.session_manager.get_cwd(),
+                    Some(session.session_manager.get_session_dir()),
+                    &filter,
+                )
+                .iter()
+                .map(session_info_value)
+                .collect::<Vec<_>>();
+                emit_json(&response_success(
+                    command.id.as_deref(),
+                    "list_sessions",
+                    Some(json!({ "sessions": sessions })),
+                ));
+            }
             _ => {
-                emit_json(&response_error(None, &kind, "Unknown command"));
+                let mut error = response_error(request_id.as_deref(), &kind, "Unknown command");
+                error["supportedCommands"] = json!(RPC_COMMANDS);
+                emit_json(&error);
             }
         }
     }
 
+    session.dispose();
     Ok(())
 }
 
+/// Read stdin lines on a background thread so the command loop can wake up to check the
+/// supervisor. The channel closes at EOF.
+fn spawn_stdin_reader() -> mpsc::Receiver<io::Result<String>> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut stdin = io::stdin().lock();
+        loop {
+            let mut line = String::new();
+            match stdin.read_line(&mut line) {
+                Ok(0) => break,
+                Ok(_) => {
+                    if tx.send(Ok(line)).is_err() {
+                        break;
+                    }
+                }
+                Err(err) => {
+                    let _ = tx.send(Err(err));
+                    break;
+                }
+            }
+        }
+    });
+    rx
+}
+
+fn emit_value(output: &RpcOutput, value: &Value, session_id: Option<&str>) {
+    let line = match (session_id, value) {
+        (Some(session_id), Value::Object(map)) => {
+            let mut map = map.clone();
+            map.insert(
+                "sessionId".to_string(),
+                Value::String(session_id.to_string()),
+            );
+            serde_json::to_string(&map)
+        }
+        _ => serde_json::to_string(value),
+    };
+    output(&line.unwrap_or_else(|_| "{}".to_string()));
+}
+
+/// The supported features among `requested`.
+fn enable_features(requested: &[String]) -> Vec<&'static str> {
+    RPC_FEATURES
+        .iter()
+        .copied()
+        .filter(|feature| requested.iter().any(|name| name == feature))
+        .collect()
+}
+
 fn response_success(id: Option<&str>, command: &str, data: Option<Value>) -> Value {
     let mut map = Map::new();
     map.insert("type".to_string(), Value::String("response".to_string()));
@@ -823,12 +1920,6 @@ fn response_error(id: Option<&str>, command: &str, error: &str) -> Value {
     Value::Object(map)
 }
 
-fn emit_json(value: &Value) {
-    let output = serde_json::to_string(value).unwrap_or_else(|_| "{}".to_string());
-    println!("{output}");
-    let _ = io::stdout().flush();
-}
-
 fn extension_ui_request_to_value(request: &ExtensionUiRequest) -> Value {
     let mut map = Map::new();
     map.insert(
@@ -920,6 +2011,13 @@ fn queue_mode_from_str(mode: &str) -> Option<QueueMode> {
     }
 }
 
+fn queue_kind_to_str(kind: QueueKind) -> &'static str {
+    match kind {
+        QueueKind::Steering => "steer",
+        QueueKind::FollowUp => "followUp",
+    }
+}
+
 fn queue_mode_to_str(mode: QueueMode) -> &'static str {
     match mode {
         QueueMode::All => "all",
@@ -947,3 +2045,73 @@ fn agent_model_value(model: &crate::agent::Model) -> Value {
         "provider": model.provider,
     })
 }
+
+/// Completions for `text` with the cursor at character `cursor`, as the TUI editor offers them.
+fn completion_value(session: &AgentSession, text: &str, cursor: Option<usize>) -> Value {
+    let cursor = cursor.unwrap_or(usize::MAX);
+    let before_cursor = match text.char_indices().nth(cursor) {
+        Some((index, _)) => &text[..index],
+        None => text,
+    };
+    let lines = text.split('\n').map(str::to_string).collect::<Vec<_>>();
+    let cursor_line = before_cursor.matches('\n').count();
+    let cursor_col = before_cursor.len() - before_cursor.rfind('\n').map_or(0, |index| index + 1);
+
+    let cwd = session.session_manager.get_cwd();
+    let suggestions = session_autocomplete_provider(session, cwd).get_suggestions(
+        &lines,
+        cursor_line,
+        cursor_col,
+    );
+    let Some(suggestions) = suggestions else {
+        return json!({ "prefix": "", "items": [] });
+    };
+    let items = suggestions
+        .items
+        .iter()
+        .map(|item| {
+            json!({
+                "value": item.value,
+                "label": item.label,
+                "description": item.description,
+            })
+        })
+        .collect::<Vec<_>>();
+    json!({ "prefix": suggestions.prefix, "items": items })
+}
+
+fn file_change_value(change: &FileChange) -> Value {
+    json!({
+        "path": change.path.to_string_lossy(),
+        "toolName": change.tool_name,
+        "toolCallId": change.tool_call_id,
+        "created": change.original.is_none(),
+        "diff": change.diff,
+        "timestamp": change.timestamp,
+    })
+}
+
+fn checkpoint_value(checkpoint: &Checkpoint) -> Value {
+    json!({
+        "entryId": checkpoint.entry_id,
+        "name": checkpoint.name,
+        "timestamp": checkpoint.timestamp,
+        "fileChanges": checkpoint.file_changes,
+    })
+}
+
+fn session_info_value(session: &SessionInfo) -> Value {
+    let modified: chrono::DateTime<chrono::Utc> = session.modified.into();
+    json!({
+        "path": session.path.to_string_lossy(),
+        "id": session.id,
+        "name": session.name,
+        "tags": session.tags,
+        "created": session.created,
+        "modified": modified.to_rfc3339(),
+        "messageCount": session.message_count,
+        "firstMessage": session.first_message,
+        "preview": session.preview,
+        "parentSession": session.parent_session,
+    })
+}
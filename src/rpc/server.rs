@@ -0,0 +1,461 @@
+//! `pi --serve`: the RPC protocol for a long-lived daemon, over HTTP instead of stdio.
+//!
+//! - `GET /rpc` upgrades to a WebSocket; each text frame is one command, and every response
+//!   and event is sent back as a text frame.
+//! - `GET /events` streams the same responses and events as server-sent events.
+//! - `POST /rpc` takes one command as the body (for SSE clients) and answers `202`.
+//!
+//! Every request needs the token, as `Authorization: Bearer <token>` or `?token=<token>`
+//! (browsers cannot set headers on WebSockets or EventSource). All clients share one session.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+pub const DEFAULT_SERVE_PORT: u16 = 4141;
+
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+const MAX_BODY_BYTES: usize = 16 * 1024 * 1024;
+/// Messages a client may fall behind by before it is dropped.
+const CLIENT_QUEUE_LEN: usize = 1024;
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+/// Receives every line the RPC loop emits.
+pub type RpcOutput = Arc<dyn Fn(&str) + Send + Sync>;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ClientKind {
+    WebSocket,
+    Events,
+}
+
+/// A connected client. Its own thread writes what is queued, so a slow client never holds
+/// up the others or the client list.
+struct Client {
+    kind: ClientKind,
+    queue: mpsc::SyncSender<Vec<u8>>,
+    stream: TcpStream,
+}
+
+impl Client {
+    /// Queues `bytes` without blocking; false when the client is gone or too far behind.
+    fn send(&self, bytes: Vec<u8>) -> bool {
+        self.queue.try_send(bytes).is_ok()
+    }
+}
+
+/// Connected clients that receive every response and event.
+#[derive(Default)]
+struct Clients {
+    next_id: u64,
+    clients: HashMap<u64, Client>,
+}
+
+impl Clients {
+    /// Registers a client, starting its writer with `head` as the first thing it sends.
+    fn add(&mut self, kind: ClientKind, stream: TcpStream, head: &str) -> io::Result<u64> {
+        let mut writer = stream.try_clone()?;
+        let (queue, queued) = mpsc::sync_channel::<Vec<u8>>(CLIENT_QUEUE_LEN);
+        let _ = queue.try_send(head.as_bytes().to_vec());
+        thread::spawn(move || {
+            for bytes in queued {
+                if writer
+                    .write_all(&bytes)
+                    .and_then(|_| writer.flush())
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+        self.next_id += 1;
+        self.clients.insert(
+            self.next_id,
+            Client {
+                kind,
+                queue,
+                stream,
+            },
+        );
+        Ok(self.next_id)
+    }
+
+    /// Queues `line` for every client, dropping the ones that have gone away or whose queue
+    /// is full.
+    fn broadcast(&mut self, line: &str) {
+        let mut frame = Vec::new();
+        let _ = write_frame(&mut frame, OPCODE_TEXT, line.as_bytes());
+        let event = format!("data: {line}\n\n").into_bytes();
+        self.clients.retain(|_, client| {
+            let bytes = match client.kind {
+                ClientKind::WebSocket => frame.clone(),
+                ClientKind::Events => event.clone(),
+            };
+            let sent = client.send(bytes);
+            if !sent {
+                // Ends the connection, so its reader and writer threads stop too.
+                let _ = client.stream.shutdown(Shutdown::Both);
+            }
+            sent
+        });
+    }
+}
+
+pub struct RpcServer {
+    listener: TcpListener,
+    token: String,
+}
+
+impl RpcServer {
+    pub fn bind(addr: impl Into<SocketAddr>, token: impl Into<String>) -> Result<Self, String> {
+        let addr = addr.into();
+        let listener =
+            TcpListener::bind(addr).map_err(|err| format!("Failed to listen on {addr}: {err}"))?;
+        Ok(Self {
+            listener,
+            token: token.into(),
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, String> {
+        self.listener.local_addr().map_err(|err| err.to_string())
+    }
+
+    /// Accepts clients on a background thread. Commands from every client arrive on the
+    /// returned channel, and the returned output sends a line to every client.
+    pub fn start(self) -> (mpsc::Receiver<io::Result<String>>, RpcOutput) {
+        let (commands, lines) = mpsc::channel();
+        let clients = Arc::new(Mutex::new(Clients::default()));
+        let token = Arc::new(self.token);
+        let accept_clients = clients.clone();
+        let listener = self.listener;
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                let commands = commands.clone();
+                let clients = accept_clients.clone();
+                let token = token.clone();
+                thread::spawn(move || {
+                    if let Err(err) = handle_connection(stream, &token, &commands, &clients) {
+                        eprintln!("Warning: RPC client error: {err}");
+                    }
+                });
+            }
+        });
+        let output: RpcOutput = Arc::new(move |line: &str| {
+            if let Ok(mut clients) = clients.lock() {
+                clients.broadcast(line);
+            }
+        });
+        (lines, output)
+    }
+}
+
+/// A random token for `pi --serve` when `PI_SERVE_TOKEN` is not set.
+pub fn generate_token() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+/// The `Sec-WebSocket-Accept` value for a client's `Sec-WebSocket-Key`.
+pub fn websocket_accept_key(key: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(key.trim().as_bytes());
+    hasher.update(WEBSOCKET_GUID.as_bytes());
+    STANDARD.encode(hasher.finalize())
+}
+
+pub(crate) struct HttpRequest {
+    pub(crate) method: String,
+    pub(crate) path: String,
+    query: HashMap<String, String>,
+    headers: HashMap<String, String>,
+}
+
+impl HttpRequest {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(String::as_str)
+    }
+}
+
+fn handle_connection(
+    stream: TcpStream,
+    token: &str,
+    commands: &mpsc::Sender<io::Result<String>>,
+    clients: &Mutex<Clients>,
+) -> Result<(), String> {
+    let mut reader = BufReader::new(stream.try_clone().map_err(|err| err.to_string())?);
+    let mut stream = stream;
+    let Some(request) = read_request(&mut reader)? else {
+        return Ok(());
+    };
+
+    if request.method == "OPTIONS" {
+        return write_response(&mut stream, "204 No Content", "");
+    }
+    if !authorized(&request, token) {
+        return write_response(
+            &mut stream,
+            "401 Unauthorized",
+            "Missing or invalid token\n",
+        );
+    }
+
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/rpc") => {
+            let upgrade = request.header("upgrade").unwrap_or("");
+            let Some(key) = request.header("sec-websocket-key") else {
+                return write_response(&mut stream, "400 Bad Request", "Expected a WebSocket\n");
+            };
+            if !upgrade.eq_ignore_ascii_case("websocket") {
+                return write_response(&mut stream, "400 Bad Request", "Expected a WebSocket\n");
+            }
+            let handshake = format!(
+                "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                 Sec-WebSocket-Accept: {}\r\n\r\n",
+                websocket_accept_key(key)
+            );
+            let id = add_client(clients, ClientKind::WebSocket, &stream, &handshake)?;
+            let result = read_websocket(&mut reader, commands, clients, id);
+            remove_client(clients, id);
+            result
+        }
+        ("GET", "/events") => {
+            let headers = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n\
+                           Cache-Control: no-cache\r\nConnection: keep-alive\r\n\
+                           Access-Control-Allow-Origin: *\r\n\r\n: connected\n\n";
+            let id = add_client(clients, ClientKind::Events, &stream, headers)?;
+            // Nothing is read from an event stream; wait for the client to hang up.
+            let mut buffer = [0u8; 1024];
+            while matches!(reader.read(&mut buffer), Ok(read) if read > 0) {}
+            remove_client(clients, id);
+            Ok(())
+        }
+        ("POST", "/rpc") => {
+            let length = request
+                .header("content-length")
+                .and_then(|length| length.parse::<usize>().ok())
+                .unwrap_or(0);
+            if length > MAX_BODY_BYTES {
+                return write_response(&mut stream, "413 Payload Too Large", "");
+            }
+            let mut body = vec![0u8; length];
+            reader
+                .read_exact(&mut body)
+                .map_err(|err| err.to_string())?;
+            let body = String::from_utf8(body).map_err(|err| err.to_string())?;
+            let _ = commands.send(Ok(body));
+            write_response(&mut stream, "202 Accepted", "")
+        }
+        _ => write_response(&mut stream, "404 Not Found", "Not found\n"),
+    }
+}
+
+/// Queues the response head and registers the client in one step, so no broadcast can slip
+/// in before the head or be missed after it.
+fn add_client(
+    clients: &Mutex<Clients>,
+    kind: ClientKind,
+    stream: &TcpStream,
+    head: &str,
+) -> Result<u64, String> {
+    let stream = stream.try_clone().map_err(|err| err.to_string())?;
+    let mut clients = clients
+        .lock()
+        .map_err(|_| "Client list poisoned".to_string())?;
+    clients
+        .add(kind, stream, head)
+        .map_err(|err| err.to_string())
+}
+
+fn remove_client(clients: &Mutex<Clients>, id: u64) {
+    if let Ok(mut clients) = clients.lock() {
+        clients.clients.remove(&id);
+    }
+}
+
+pub(crate) fn authorized(request: &HttpRequest, token: &str) -> bool {
+    let provided = request
+        .header("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or_else(|| request.query.get("token").map(String::as_str));
+    provided.is_some_and(|provided| constant_time_eq(provided.trim().as_bytes(), token.as_bytes()))
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+pub(crate) fn read_request(reader: &mut impl BufRead) -> Result<Option<HttpRequest>, String> {
+    let mut line = String::new();
+    if reader.read_line(&mut line).map_err(|err| err.to_string())? == 0 {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or("").to_string();
+    let target = parts.next().unwrap_or("/");
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query = url::form_urlencoded::parse(query.as_bytes())
+        .into_owned()
+        .collect();
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).map_err(|err| err.to_string())? == 0 {
+            break;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+    Ok(Some(HttpRequest {
+        method,
+        path: path.to_string(),
+        query,
+        headers,
+    }))
+}
+
+pub(crate) fn write_response(
+    stream: &mut TcpStream,
+    status: &str,
+    body: &str,
+) -> Result<(), String> {
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Access-Control-Allow-Headers: Authorization, Content-Type\r\n\
+         Connection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream
+        .write_all(response.as_bytes())
+        .map_err(|err| err.to_string())
+}
+
+/// Forwards text messages as commands until the client closes the socket.
+fn read_websocket(
+    reader: &mut impl Read,
+    commands: &mpsc::Sender<io::Result<String>>,
+    clients: &Mutex<Clients>,
+    id: u64,
+) -> Result<(), String> {
+    let mut message = Vec::new();
+    loop {
+        let Some((fin, opcode, payload)) = read_frame(reader).map_err(|err| err.to_string())?
+        else {
+            return Ok(());
+        };
+        match opcode {
+            OPCODE_TEXT | OPCODE_BINARY | OPCODE_CONTINUATION => {
+                message.extend_from_slice(&payload);
+                if fin {
+                    let text = String::from_utf8_lossy(&message).to_string();
+                    message.clear();
+                    let _ = commands.send(Ok(text));
+                }
+            }
+            OPCODE_PING | OPCODE_CLOSE => {
+                // Through the client's queue, so the reply never interleaves with a broadcast.
+                let mut clients = clients
+                    .lock()
+                    .map_err(|_| "Client list poisoned".to_string())?;
+                let reply = if opcode == OPCODE_PING {
+                    OPCODE_PONG
+                } else {
+                    OPCODE_CLOSE
+                };
+                let mut frame = Vec::new();
+                let _ = write_frame(&mut frame, reply, &payload);
+                if let Some(client) = clients.clients.get(&id) {
+                    client.send(frame);
+                }
+                if opcode == OPCODE_CLOSE {
+                    clients.clients.remove(&id);
+                    return Ok(());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Reads one frame as `(fin, opcode, unmasked payload)`; `None` at end of stream.
+pub fn read_frame(reader: &mut impl Read) -> io::Result<Option<(bool, u8, Vec<u8>)>> {
+    let mut header = [0u8; 2];
+    match reader.read_exact(&mut header) {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err),
+    }
+    let fin = header[0] & 0x80 != 0;
+    let opcode = header[0] & 0x0F;
+    let masked = header[1] & 0x80 != 0;
+    let length = match header[1] & 0x7F {
+        126 => {
+            let mut bytes = [0u8; 2];
+            reader.read_exact(&mut bytes)?;
+            u16::from_be_bytes(bytes) as usize
+        }
+        127 => {
+            let mut bytes = [0u8; 8];
+            reader.read_exact(&mut bytes)?;
+            u64::from_be_bytes(bytes) as usize
+        }
+        length => length as usize,
+    };
+    if length > MAX_BODY_BYTES {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "WebSocket frame too large",
+        ));
+    }
+    let mut mask = [0u8; 4];
+    if masked {
+        reader.read_exact(&mut mask)?;
+    }
+    let mut payload = vec![0u8; length];
+    reader.read_exact(&mut payload)?;
+    if masked {
+        for (index, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[index % 4];
+        }
+    }
+    Ok(Some((fin, opcode, payload)))
+}
+
+/// Writes one unmasked, unfragmented frame (servers never mask).
+pub fn write_frame(writer: &mut impl Write, opcode: u8, payload: &[u8]) -> io::Result<()> {
+    let mut frame = Vec::with_capacity(payload.len() + 10);
+    frame.push(0x80 | opcode);
+    match payload.len() {
+        length if length < 126 => frame.push(length as u8),
+        length if length <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend_from_slice(&(length as u16).to_be_bytes());
+        }
+        length => {
+            frame.push(127);
+            frame.extend_from_slice(&(length as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    writer.write_all(&frame)
+}
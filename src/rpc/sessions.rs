@@ -0,0 +1,303 @@
+//! Several sessions behind one RPC connection, so a daemon can serve many editor tabs.
+//!
+//! Commands go to the session named by their `sessionId`, or to the default session (the one
+//! pi started with) when there is none. `create_session` starts another session with its own
+//! model and history on its own thread, so a long prompt in one tab does not hold up the
+//! others; every line it emits carries its `sessionId`. `close_session` and
+//! `list_active_sessions` manage them.
+
+use super::{
+    agent_model_value, emit_value, response_error, response_success, run_rpc_loop, RpcModeOptions,
+    RpcOutput, RpcSupervisor, SUPERVISOR_POLL_INTERVAL,
+};
+use crate::agent::CancellationToken;
+use crate::coding_agent::AgentSession;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+pub const DEFAULT_SESSION_ID: &str = "default";
+
+/// Builds the session for a `create_session` request, on the thread that will own it.
+pub type SessionFactory = Arc<dyn Fn(&SessionSpec) -> Result<AgentSession, String> + Send + Sync>;
+
+/// What a client asked for in `create_session`; unset fields fall back to pi's own options.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionSpec {
+    pub provider: Option<String>,
+    /// A model id, or `provider/id`.
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcCreateSessionCommand {
+    id: Option<String>,
+    session_id: Option<String>,
+    #[serde(flatten)]
+    spec: SessionSpec,
+}
+
+struct RoutedSession {
+    sender: mpsc::Sender<io::Result<String>>,
+    /// Set once the session has been built.
+    model: Option<Value>,
+    /// Cancelled by the router as soon as an `abort` arrives, since the session's own loop
+    /// only reads it once the prompt it is running returns.
+    abort: Option<CancellationToken>,
+}
+
+type Sessions = Arc<Mutex<BTreeMap<String, RoutedSession>>>;
+
+/// Runs `default` on this thread and routes commands from `lines` to it and to any sessions
+/// clients create, until the input closes or the supervisor asks to exit.
+pub(super) fn run_sessions(
+    default: AgentSession,
+    options: RpcModeOptions,
+    lines: mpsc::Receiver<io::Result<String>>,
+    output: RpcOutput,
+    factory: Option<SessionFactory>,
+) -> Result<(), String> {
+    let supervisor = RpcSupervisor::new(options);
+    let default_supervisor = supervisor.share();
+    let (default_sender, default_lines) = mpsc::channel();
+    let sessions: Sessions = Arc::new(Mutex::new(BTreeMap::new()));
+    if let Ok(mut sessions) = sessions.lock() {
+        sessions.insert(
+            DEFAULT_SESSION_ID.to_string(),
+            RoutedSession {
+                sender: default_sender,
+                model: Some(agent_model_value(&default.get_state().model)),
+                abort: Some(default.agent.abort_flag()),
+            },
+        );
+    }
+    let router = Router {
+        sessions,
+        output: output.clone(),
+        factory,
+        supervisor,
+    };
+    thread::spawn(move || router.run(lines));
+    run_rpc_loop(default, default_supervisor, default_lines, output, None)
+}
+
+struct Router {
+    sessions: Sessions,
+    output: RpcOutput,
+    factory: Option<SessionFactory>,
+    supervisor: RpcSupervisor,
+}
+
+impl Router {
+    fn run(mut self, lines: mpsc::Receiver<io::Result<String>>) {
+        loop {
+            self.supervisor.finish_command();
+            let line = match lines.recv_timeout(SUPERVISOR_POLL_INTERVAL) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => {
+                    if let Some(reason) = self.supervisor.exit_reason() {
+                        self.emit(&json!({ "type": "shutdown", "reason": reason }), None);
+                        break;
+                    }
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            self.supervisor.start_command();
+            // Unreadable input and invalid JSON are the default session's to report.
+            let value = match &line {
+                Ok(text) => serde_json::from_str::<Value>(text.trim()).ok(),
+                Err(_) => None,
+            };
+            let Some(value) = value else {
+                self.forward(DEFAULT_SESSION_ID, line, None);
+                continue;
+            };
+            let kind = value.get("type").and_then(Value::as_str).unwrap_or("");
+            let id = value.get("id").and_then(Value::as_str).map(str::to_string);
+            let session_id = value
+                .get("sessionId")
+                .and_then(Value::as_str)
+                .unwrap_or(DEFAULT_SESSION_ID)
+                .to_string();
+            match kind {
+                "create_session" => self.create_session(value),
+                "close_session" => self.close_session(id.as_deref(), &session_id),
+                "list_active_sessions" => self.list_sessions(id.as_deref()),
+                "abort" => {
+                    self.abort(&session_id);
+                    self.forward(&session_id, line, Some((id.as_deref(), kind)));
+                }
+                _ => self.forward(&session_id, line, Some((id.as_deref(), kind))),
+            }
+        }
+        // Dropping the senders ends every session's loop, the default one included.
+        if let Ok(mut sessions) = self.sessions.lock() {
+            sessions.clear();
+        }
+    }
+
+    fn emit(&self, value: &Value, session_id: Option<&str>) {
+        emit_value(&self.output, value, session_id);
+    }
+
+    fn forward(
+        &self,
+        session_id: &str,
+        line: io::Result<String>,
+        command: Option<(Option<&str>, &str)>,
+    ) {
+        let Ok(mut sessions) = self.sessions.lock() else {
+            return;
+        };
+        let sent = match sessions.get(session_id) {
+            Some(session) => session.sender.send(line).is_ok(),
+            None => false,
+        };
+        if !sent {
+            sessions.remove(session_id);
+            if let Some((id, kind)) = command {
+                let error = format!("Unknown session {session_id}");
+                self.emit(&response_error(id, kind, &error), Some(session_id));
+            }
+        }
+    }
+
+    fn abort(&self, session_id: &str) {
+        if let Ok(sessions) = self.sessions.lock() {
+            if let Some(abort) = sessions
+                .get(session_id)
+                .and_then(|session| session.abort.as_ref())
+            {
+                abort.cancel();
+            }
+        }
+    }
+
+    fn create_session(&self, value: Value) {
+        let request_id = value.get("id").and_then(Value::as_str).map(str::to_string);
+        let command: RpcCreateSessionCommand = match serde_json::from_value(value) {
+            Ok(command) => command,
+            Err(err) => {
+                let error = format!("Invalid payload: {err}");
+                self.emit(
+                    &response_error(request_id.as_deref(), "create_session", &error),
+                    None,
+                );
+                return;
+            }
+        };
+        let id = command.id;
+        let Some(factory) = self.factory.clone() else {
+            let error = "This RPC server cannot create sessions";
+            self.emit(
+                &response_error(id.as_deref(), "create_session", error),
+                None,
+            );
+            return;
+        };
+        let session_id = command
+            .session_id
+            .unwrap_or_else(|| uuid::Uuid::new_v4().simple().to_string());
+        let (sender, lines) = mpsc::channel();
+        {
+            let Ok(mut sessions) = self.sessions.lock() else {
+                return;
+            };
+            if sessions.contains_key(&session_id) {
+                let error = format!("Session {session_id} already exists");
+                self.emit(
+                    &response_error(id.as_deref(), "create_session", &error),
+                    Some(&session_id),
+                );
+                return;
+            }
+            sessions.insert(
+                session_id.clone(),
+                RoutedSession {
+                    sender,
+                    model: None,
+                    abort: None,
+                },
+            );
+        }
+
+        let sessions = self.sessions.clone();
+        let output = self.output.clone();
+        let supervisor = self.supervisor.share();
+        let spec = command.spec;
+        thread::spawn(move || {
+            let session = match factory(&spec) {
+                Ok(session) => session,
+                Err(err) => {
+                    if let Ok(mut sessions) = sessions.lock() {
+                        sessions.remove(&session_id);
+                    }
+                    let response = response_error(id.as_deref(), "create_session", &err);
+                    emit_value(&output, &response, Some(&session_id));
+                    return;
+                }
+            };
+            let model = agent_model_value(&session.get_state().model);
+            if let Ok(mut sessions) = sessions.lock() {
+                if let Some(routed) = sessions.get_mut(&session_id) {
+                    routed.model = Some(model.clone());
+                    routed.abort = Some(session.agent.abort_flag());
+                }
+            }
+            let data = json!({ "sessionId": session_id, "model": model });
+            let response = response_success(id.as_deref(), "create_session", Some(data));
+            emit_value(&output, &response, Some(&session_id));
+            if let Err(err) = run_rpc_loop(session, supervisor, lines, output, Some(session_id)) {
+                eprintln!("Warning: RPC session ended: {err}");
+            }
+        });
+    }
+
+    fn close_session(&self, id: Option<&str>, session_id: &str) {
+        let response = if session_id == DEFAULT_SESSION_ID {
+            response_error(id, "close_session", "The default session cannot be closed")
+        } else {
+            let removed = self
+                .sessions
+                .lock()
+                .is_ok_and(|mut sessions| sessions.remove(session_id).is_some());
+            if removed {
+                response_success(id, "close_session", None)
+            } else {
+                response_error(
+                    id,
+                    "close_session",
+                    &format!("Unknown session {session_id}"),
+                )
+            }
+        };
+        self.emit(&response, Some(session_id));
+    }
+
+    fn list_sessions(&self, id: Option<&str>) {
+        let sessions = self
+            .sessions
+            .lock()
+            .map(|sessions| {
+                sessions
+                    .iter()
+                    .map(|(session_id, session)| {
+                        json!({ "sessionId": session_id, "model": session.model })
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let data = json!({ "sessions": sessions });
+        self.emit(
+            &response_success(id, "list_active_sessions", Some(data)),
+            None,
+        );
+    }
+}
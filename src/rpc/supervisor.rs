@@ -0,0 +1,122 @@
+//! Lets an embedded RPC process exit on its own when its host goes away: after
+//! `--idle-exit` seconds without commands, or once the `--parent-pid` process has exited.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How often the RPC loop checks the supervisor while waiting for input.
+pub const SUPERVISOR_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RpcModeOptions {
+    pub idle_exit: Option<Duration>,
+    pub parent_pid: Option<u32>,
+}
+
+/// Commands in flight and when the last one finished, across every session's loop.
+#[derive(Debug)]
+struct Activity {
+    in_flight: AtomicUsize,
+    last_finished: Mutex<Instant>,
+}
+
+#[derive(Debug)]
+pub struct RpcSupervisor {
+    options: RpcModeOptions,
+    activity: Arc<Activity>,
+    busy: bool,
+}
+
+impl RpcSupervisor {
+    pub fn new(options: RpcModeOptions) -> Self {
+        Self {
+            options,
+            activity: Arc::new(Activity {
+                in_flight: AtomicUsize::new(0),
+                last_finished: Mutex::new(Instant::now()),
+            }),
+            busy: false,
+        }
+    }
+
+    /// A supervisor for another session's command loop: its commands keep this one from
+    /// going idle, but it never asks to exit on its own.
+    pub fn share(&self) -> Self {
+        Self {
+            options: RpcModeOptions::default(),
+            activity: self.activity.clone(),
+            busy: false,
+        }
+    }
+
+    pub fn start_command(&mut self) {
+        if !self.busy {
+            self.busy = true;
+            self.activity.in_flight.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Idle time counts from when the last command finished, so long prompts never time out.
+    pub fn finish_command(&mut self) {
+        if self.busy {
+            self.busy = false;
+            if let Ok(mut last_finished) = self.activity.last_finished.lock() {
+                *last_finished = Instant::now();
+            }
+            self.activity.in_flight.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    /// `"parent_exited"` or `"idle"` when the process should shut down.
+    pub fn exit_reason(&self) -> Option<&'static str> {
+        if let Some(pid) = self.options.parent_pid {
+            if !process_alive(pid) {
+                return Some("parent_exited");
+            }
+        }
+        let limit = self.options.idle_exit?;
+        let idle = self.activity.in_flight.load(Ordering::SeqCst) == 0
+            && self
+                .activity
+                .last_finished
+                .lock()
+                .is_ok_and(|last_finished| last_finished.elapsed() >= limit);
+        idle.then_some("idle")
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn process_alive(pid: u32) -> bool {
+    // Zombies keep their /proc entry until reaped but are already gone.
+    match std::fs::read_to_string(format!("/proc/{pid}/stat")) {
+        Ok(stat) => stat
+            .rsplit_once(") ")
+            .is_none_or(|(_, rest)| !rest.starts_with('Z')),
+        Err(_) => false,
+    }
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn process_alive(pid: u32) -> bool {
+    std::process::Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(true)
+}
+
+#[cfg(windows)]
+fn process_alive(pid: u32) -> bool {
+    std::process::Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {pid}"), "/NH"])
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).contains(&pid.to_string()))
+        .unwrap_or(true)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn process_alive(_pid: u32) -> bool {
+    true
+}
@@ -0,0 +1,492 @@
+//! `PiClient`: pi as a library. The builder picks the model, credentials, tools and system
+//! prompt the way the CLI does, so callers don't assemble the registry, session and stream
+//! functions by hand.
+//!
+//! ```no_run
+//! use pi::sdk::PiClient;
+//!
+//! let mut client = PiClient::builder()
+//!     .provider("anthropic")
+//!     .model("claude-sonnet-4-5")
+//!     .tools(["read", "grep"])
+//!     .system_prompt("You review Rust code. Be brief.")
+//!     .on_event(|event| eprintln!("{}", event.kind()))
+//!     .build()?;
+//! let result = client.prompt_stream("Review src/lib.rs", |delta| print!("{delta}"))?;
+//! println!("\n{} tokens", result.usage.total_tokens.unwrap_or(0));
+//! # Ok::<(), pi::Error>(())
+//! ```
+
+use crate::agent::{
+    Agent, AgentEvent, AgentMessage, AgentOptions, AgentStateOverride, AgentTool, AgentToolResult,
+    CancellationToken,
+};
+use crate::cli::args::parse_args;
+use crate::cli::runtime::{build_model_registry, build_sandbox_policy, select_model};
+use crate::cli::session::{
+    apply_tool_descriptions, build_agent_tools, build_session_stream_fn, to_agent_model,
+};
+use crate::coding_agent::extension_host::ExtensionTool;
+use crate::coding_agent::models_config::SUPPORTED_APIS;
+use crate::coding_agent::{
+    build_system_prompt, AgentSession, AgentSessionConfig, AgentSessionEvent, BashApproval,
+    BuildSystemPromptOptions, EditReview, Model as RegistryModel, ModelRegistry, PromptOverrides,
+    SettingsManager, SharedChangeJournal,
+};
+use crate::config;
+use crate::core::messages::{AssistantMessage, ContentBlock, Cost, Usage};
+use crate::core::session_manager::SessionManager;
+use crate::error::Error;
+use crate::tools::default_tool_names;
+use serde_json::Value;
+use std::cell::RefCell;
+use std::env;
+use std::rc::Rc;
+
+type EventHandler = Box<dyn Fn(&AgentEvent)>;
+type ToolFn = Rc<dyn Fn(&Value) -> Result<String, String>>;
+
+struct CustomTool {
+    name: String,
+    description: String,
+    parameters: Value,
+    execute: ToolFn,
+}
+
+#[derive(Default)]
+pub struct PiClientBuilder {
+    provider: Option<String>,
+    model: Option<String>,
+    api_key: Option<String>,
+    tools: Option<Vec<String>>,
+    custom_tools: Vec<CustomTool>,
+    system_prompt: Option<String>,
+    append_system_prompt: Option<String>,
+    prompt_overrides: PromptOverrides,
+    session_manager: Option<SessionManager>,
+    model_registry: Option<ModelRegistry>,
+    event_handlers: Vec<EventHandler>,
+}
+
+impl PiClientBuilder {
+    pub fn provider(mut self, provider: impl Into<String>) -> Self {
+        self.provider = Some(provider.into());
+        self
+    }
+
+    /// Model id; with no provider, the first provider that has it.
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Credential for the chosen provider instead of auth.json and the environment.
+    pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Built-in tools to enable, replacing the defaults.
+    pub fn tools<I, S>(mut self, tools: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tools = Some(tools.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// A tool the model can call. `parameters` is its JSON schema; `execute` gets the
+    /// call's arguments and returns the text result, or an error the model sees.
+    pub fn tool(
+        mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        parameters: Value,
+        execute: impl Fn(&Value) -> Result<String, String> + 'static,
+    ) -> Self {
+        self.custom_tools.push(CustomTool {
+            name: name.into(),
+            description: description.into(),
+            parameters,
+            execute: Rc::new(execute),
+        });
+        self
+    }
+
+    /// Replaces pi's coding-agent prompt. Text, or the path of a file holding it.
+    pub fn system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.system_prompt = Some(prompt.into());
+        self
+    }
+
+    pub fn append_system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.append_system_prompt = Some(prompt.into());
+        self
+    }
+
+    /// Reorders, replaces or removes sections of pi's prompt, and rewords tool descriptions.
+    pub fn prompt_overrides(mut self, overrides: PromptOverrides) -> Self {
+        self.prompt_overrides = overrides;
+        self
+    }
+
+    /// Where the conversation is saved; in memory by default.
+    pub fn session_manager(mut self, session_manager: SessionManager) -> Self {
+        self.session_manager = Some(session_manager);
+        self
+    }
+
+    /// Models and credentials to choose from instead of the user's models.json and auth.json.
+    pub fn model_registry(mut self, registry: ModelRegistry) -> Self {
+        self.model_registry = Some(registry);
+        self
+    }
+
+    /// Called with every agent event, for the life of the client.
+    pub fn on_event(mut self, handler: impl Fn(&AgentEvent) + 'static) -> Self {
+        self.event_handlers.push(Box::new(handler));
+        self
+    }
+
+    pub fn build(self) -> Result<PiClient, Error> {
+        let registry = match self.model_registry {
+            Some(registry) => registry,
+            None => build_model_registry(self.api_key.as_deref(), self.provider.as_deref(), None)
+                .map_err(Error::Config)?,
+        };
+        let model = resolve_model(&registry, self.provider.as_deref(), self.model.as_deref())
+            .map_err(Error::Config)?;
+        if !SUPPORTED_APIS.contains(&model.api.as_str()) {
+            return Err(Error::Config(format!(
+                "Unsupported model API \"{}\". Supported APIs: {}",
+                model.api,
+                SUPPORTED_APIS.join(", ")
+            )));
+        }
+
+        let cwd = env::current_dir()?;
+        let builtin_tools = self.tools.unwrap_or_else(default_tool_names);
+        let mut tool_names = builtin_tools.clone();
+        tool_names.extend(self.custom_tools.iter().map(|tool| tool.name.clone()));
+        let tool_defs = self
+            .custom_tools
+            .iter()
+            .map(|tool| ExtensionTool {
+                name: tool.name.clone(),
+                label: None,
+                description: Some(tool.description.clone()),
+                parameters: Some(tool.parameters.clone()),
+            })
+            .collect::<Vec<_>>();
+        let stream_fn = build_session_stream_fn(
+            &model,
+            &[],
+            Some(&tool_names),
+            &tool_defs,
+            &self.prompt_overrides.tool_descriptions,
+            self.api_key.as_deref(),
+            None,
+            None,
+            "the SDK",
+        )
+        .map_err(credentials_error)?;
+
+        let change_journal = SharedChangeJournal::default();
+        let bash_approval = BashApproval::default();
+        let cancel = CancellationToken::new();
+        let mut agent_tools = build_agent_tools(
+            &cwd,
+            Some(&builtin_tools),
+            &[],
+            None,
+            &change_journal,
+            &build_sandbox_policy(false, &cwd),
+            &bash_approval,
+            &EditReview::default(),
+            &cancel,
+        )
+        .map_err(Error::Config)?;
+        agent_tools.extend(self.custom_tools.into_iter().map(custom_agent_tool));
+        apply_tool_descriptions(&mut agent_tools, &self.prompt_overrides.tool_descriptions);
+
+        let system_prompt = build_system_prompt(BuildSystemPromptOptions {
+            custom_prompt: self.system_prompt,
+            append_system_prompt: self.append_system_prompt,
+            selected_tools: Some(tool_names),
+            cwd: Some(cwd),
+            agent_dir: Some(config::get_agent_dir()),
+            overrides: self.prompt_overrides,
+            ..Default::default()
+        });
+        let agent = Agent::new(AgentOptions {
+            initial_state: Some(AgentStateOverride {
+                system_prompt: Some(system_prompt),
+                model: Some(to_agent_model(&model)),
+                tools: Some(agent_tools),
+                ..Default::default()
+            }),
+            stream_fn: Some(stream_fn),
+            abort_flag: Some(cancel),
+            ..Default::default()
+        });
+        let mut session = AgentSession::new(AgentSessionConfig {
+            agent,
+            session_manager: self
+                .session_manager
+                .unwrap_or_else(SessionManager::in_memory),
+            settings_manager: SettingsManager::create("", ""),
+            model_registry: registry,
+        });
+        session.set_change_journal(change_journal);
+        session.set_bash_approval(bash_approval);
+        for handler in self.event_handlers {
+            // The handlers live as long as the session, so the unsubscribe hook is dropped.
+            let _ = session.subscribe(move |event| {
+                if let AgentSessionEvent::Agent(event) = event {
+                    handler(event);
+                }
+            });
+        }
+        Ok(PiClient { session, model })
+    }
+}
+
+/// Credential lookups fail with "Missing <provider> credentials" messages.
+fn credentials_error(message: String) -> Error {
+    if message.starts_with("Missing ") && message.contains("credentials") {
+        Error::Auth { message }
+    } else {
+        Error::Config(message)
+    }
+}
+
+fn resolve_model(
+    registry: &ModelRegistry,
+    provider: Option<&str>,
+    model: Option<&str>,
+) -> Result<RegistryModel, String> {
+    match (provider, model) {
+        (Some(provider), Some(id)) => registry
+            .find(provider, id)
+            .ok_or_else(|| format!("Model {provider}/{id} not found")),
+        (None, Some(id)) => registry
+            .get_all()
+            .into_iter()
+            .find(|model| model.id == id)
+            .ok_or_else(|| format!("Model {id} not found")),
+        (Some(provider), None) => registry
+            .get_available()
+            .into_iter()
+            .find(|model| model.provider == provider)
+            .ok_or_else(|| format!("No models available for provider {provider}")),
+        (None, None) => select_model(
+            &parse_args(&[], None),
+            registry,
+            &SettingsManager::create("", ""),
+        ),
+    }
+}
+
+fn custom_agent_tool(tool: CustomTool) -> AgentTool {
+    let execute = tool.execute;
+    AgentTool {
+        label: tool.name.clone(),
+        name: tool.name,
+        description: tool.description,
+        parameters: Some(tool.parameters),
+        execute: Rc::new(move |_call_id, params, _on_update| {
+            let text = execute(params)?;
+            Ok(AgentToolResult {
+                content: vec![ContentBlock::Text {
+                    text,
+                    text_signature: None,
+                }],
+                details: Value::Null,
+            })
+        }),
+    }
+}
+
+/// What one prompt produced.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PromptResult {
+    /// Text of the final assistant message.
+    pub text: String,
+    pub stop_reason: String,
+    /// The messages the prompt added: the user message, replies and tool results.
+    pub messages: Vec<AgentMessage>,
+    /// Tokens and cost summed over the prompt's assistant messages.
+    pub usage: Usage,
+}
+
+pub struct PiClient {
+    session: AgentSession,
+    model: RegistryModel,
+}
+
+impl PiClient {
+    pub fn builder() -> PiClientBuilder {
+        PiClientBuilder::default()
+    }
+
+    pub fn model(&self) -> &RegistryModel {
+        &self.model
+    }
+
+    /// Sends `text` and runs the agent until it stops calling tools. A provider error is
+    /// returned as the provider reported it, and an aborted run as [`Error::Aborted`].
+    pub fn prompt(&mut self, text: &str) -> Result<PromptResult, Error> {
+        let before = self.session.messages().len();
+        self.session.prompt(text)?;
+        let messages = self.session.messages().split_off(before);
+        prompt_result(messages, self.session.agent.take_error())
+    }
+
+    /// Like [`prompt`](Self::prompt), calling `on_text` with assistant text as it streams.
+    pub fn prompt_stream(
+        &mut self,
+        text: &str,
+        on_text: impl FnMut(&str) + 'static,
+    ) -> Result<PromptResult, Error> {
+        let on_text = RefCell::new(on_text);
+        // Bytes of the current assistant message's text already passed to `on_text`.
+        let sent = Rc::new(RefCell::new(0usize));
+        let unsubscribe = self.session.subscribe(move |event| {
+            let AgentSessionEvent::Agent(event) = event else {
+                return;
+            };
+            let message = match event.as_ref() {
+                AgentEvent::MessageStart {
+                    message: AgentMessage::Assistant(_),
+                } => {
+                    *sent.borrow_mut() = 0;
+                    return;
+                }
+                AgentEvent::MessageUpdate {
+                    message: AgentMessage::Assistant(message),
+                    ..
+                }
+                | AgentEvent::MessageEnd {
+                    message: AgentMessage::Assistant(message),
+                } => message,
+                _ => return,
+            };
+            let text = assistant_text(message);
+            let mut sent = sent.borrow_mut();
+            // Output filters can rewrite earlier text; only pass on text that extends it.
+            if text.len() > *sent && text.is_char_boundary(*sent) {
+                (on_text.borrow_mut())(&text[*sent..]);
+                *sent = text.len();
+            }
+        });
+        let result = self.prompt(text);
+        unsubscribe();
+        result
+    }
+
+    /// Stops the running prompt after the current step.
+    pub fn abort(&self) {
+        self.session.abort();
+    }
+
+    pub fn messages(&self) -> Vec<AgentMessage> {
+        self.session.messages()
+    }
+
+    /// The underlying session, for everything the client does not wrap.
+    pub fn session(&self) -> &AgentSession {
+        &self.session
+    }
+
+    pub fn session_mut(&mut self) -> &mut AgentSession {
+        &mut self.session
+    }
+
+    pub fn into_session(self) -> AgentSession {
+        self.session
+    }
+}
+
+fn prompt_result(messages: Vec<AgentMessage>, error: Option<Error>) -> Result<PromptResult, Error> {
+    let assistants = messages
+        .iter()
+        .filter_map(|message| match message {
+            AgentMessage::Assistant(assistant) => Some(assistant),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let last = assistants
+        .last()
+        .ok_or_else(|| Error::Session("No assistant response.".to_string()))?;
+    if last.is_aborted() || last.stop_reason == "error" {
+        let message = last.error_message.clone();
+        return Err(error.unwrap_or_else(|| {
+            if last.is_aborted() {
+                Error::Aborted(message.unwrap_or_else(|| "Request aborted".to_string()))
+            } else {
+                Error::Provider {
+                    status: None,
+                    message: message.unwrap_or_else(|| "Request error".to_string()),
+                }
+            }
+        }));
+    }
+    let usage = sum_usage(&assistants);
+    Ok(PromptResult {
+        text: assistant_text(last),
+        stop_reason: last.stop_reason.clone(),
+        messages,
+        usage,
+    })
+}
+
+fn assistant_text(message: &AssistantMessage) -> String {
+    message
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text { text, .. } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn sum_usage(messages: &[&AssistantMessage]) -> Usage {
+    let mut total = Usage {
+        input: 0,
+        output: 0,
+        cache_read: 0,
+        cache_write: 0,
+        total_tokens: Some(0),
+        cost: Some(Cost {
+            input: 0.0,
+            output: 0.0,
+            cache_read: 0.0,
+            cache_write: 0.0,
+            total: 0.0,
+        }),
+    };
+    for message in messages {
+        let usage = &message.usage;
+        total.input += usage.input;
+        total.output += usage.output;
+        total.cache_read += usage.cache_read;
+        total.cache_write += usage.cache_write;
+        total.total_tokens = Some(
+            total.total_tokens.unwrap_or(0)
+                + usage
+                    .total_tokens
+                    .unwrap_or(usage.input + usage.output + usage.cache_read + usage.cache_write),
+        );
+        if let (Some(sum), Some(cost)) = (total.cost.as_mut(), usage.cost.as_ref()) {
+            sum.input += cost.input;
+            sum.output += cost.output;
+            sum.cache_read += cost.cache_read;
+            sum.cache_write += cost.cache_write;
+            sum.total += cost.total;
+        }
+    }
+    total
+}
@@ -3,6 +3,8 @@ use crate::core::messages::ContentBlock;
 use serde_json::{json, Value};
 use std::path::PathBuf;
 
+pub mod schema;
+
 pub struct ToolContext {
     pub cwd: PathBuf,
 }
@@ -10,12 +12,74 @@ pub struct ToolContext {
 pub struct ToolDefinition {
     pub name: &'static str,
     pub description: &'static str,
+    pub terse_description: &'static str,
     pub input_schema: Value,
     pub execute: fn(&Value, &ToolContext) -> Result<String, String>,
 }
 
+/// How much guidance tool definitions carry in provider requests.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToolVerbosity {
+    /// Short descriptions and schemas without per-parameter descriptions.
+    Terse,
+    /// Full descriptions and documented parameters.
+    Verbose,
+}
+
+/// Models with a context window below this many tokens get terse tool definitions.
+const TERSE_TOOLS_CONTEXT_WINDOW: i64 = 64_000;
+/// Models with an input price (USD per million tokens) at or below this get terse tool definitions.
+const TERSE_TOOLS_INPUT_COST: f64 = 0.2;
+
+/// Picks the tool verbosity for a model. Unknown (zero) context windows and prices
+/// are ignored, so local and custom models keep the verbose definitions.
+pub fn tool_verbosity_for_model(context_window: i64, input_cost: f64) -> ToolVerbosity {
+    let small_context = context_window > 0 && context_window < TERSE_TOOLS_CONTEXT_WINDOW;
+    let cheap = input_cost > 0.0 && input_cost <= TERSE_TOOLS_INPUT_COST;
+    if small_context || cheap {
+        ToolVerbosity::Terse
+    } else {
+        ToolVerbosity::Verbose
+    }
+}
+
+impl ToolDefinition {
+    pub fn description_for(&self, verbosity: ToolVerbosity) -> &'static str {
+        match verbosity {
+            ToolVerbosity::Terse => self.terse_description,
+            ToolVerbosity::Verbose => self.description,
+        }
+    }
+
+    pub fn input_schema_for(&self, verbosity: ToolVerbosity) -> Value {
+        let mut schema = self.input_schema.clone();
+        if verbosity == ToolVerbosity::Terse {
+            if let Some(properties) = schema.get_mut("properties").and_then(Value::as_object_mut) {
+                for property in properties.values_mut() {
+                    if let Some(property) = property.as_object_mut() {
+                        property.remove("description");
+                    }
+                }
+            }
+        }
+        schema
+    }
+}
+
 const DEFAULT_TOOL_NAMES: [&str; 4] = ["read", "bash", "edit", "write"];
 
+/// The `git` tool group. These are only offered to the model when requested (e.g. `--tools git`).
+pub const GIT_TOOL_NAMES: [&str; 5] = [
+    "git_status",
+    "git_diff",
+    "git_log",
+    "git_commit",
+    "git_stash",
+];
+
+/// The `notebook` tool group for Jupyter notebooks, also opt-in (`--tools notebook`).
+pub const NOTEBOOK_TOOL_NAMES: [&str; 2] = ["notebook_read", "notebook_edit"];
+
 pub fn default_tool_names() -> Vec<String> {
     DEFAULT_TOOL_NAMES
         .iter()
@@ -28,12 +92,16 @@ pub fn default_tools() -> Vec<ToolDefinition> {
         ToolDefinition {
             name: "read",
             description: "Read the contents of a file.",
+            terse_description: "Read a file.",
             input_schema: json!({
                 "type": "object",
                 "properties": {
                     "path": { "type": "string", "description": "Path to the file to read (relative or absolute)" },
                     "offset": { "type": "integer", "description": "Line number to start reading from (1-indexed)" },
-                    "limit": { "type": "integer", "description": "Maximum number of lines to read" }
+                    "limit": { "type": "integer", "description": "Maximum number of lines to read" },
+                    "symbol": { "type": "string", "description": "Read only this function, type or other declaration, e.g. parse or Parser::parse" },
+                    "outline": { "type": "boolean", "description": "List the file's declarations with line ranges instead of its content" },
+                    "lineNumbers": { "type": "boolean", "description": "Prefix each line with its line number" }
                 },
                 "required": ["path"],
                 "additionalProperties": false
@@ -43,6 +111,7 @@ pub fn default_tools() -> Vec<ToolDefinition> {
         ToolDefinition {
             name: "write",
             description: "Write content to a file, creating it if needed.",
+            terse_description: "Write a file.",
             input_schema: json!({
                 "type": "object",
                 "properties": {
@@ -57,6 +126,7 @@ pub fn default_tools() -> Vec<ToolDefinition> {
         ToolDefinition {
             name: "edit",
             description: "Replace exact text in a file.",
+            terse_description: "Replace exact text in a file.",
             input_schema: json!({
                 "type": "object",
                 "properties": {
@@ -72,6 +142,7 @@ pub fn default_tools() -> Vec<ToolDefinition> {
         ToolDefinition {
             name: "bash",
             description: "Execute a bash command in the current working directory. Returns stdout and stderr. Output is truncated to last 2000 lines or 50KB (whichever is hit first). If truncated, full output is saved to a temp file. Optionally provide a timeout in seconds.",
+            terse_description: "Run a bash command. Output is truncated.",
             input_schema: json!({
                 "type": "object",
                 "properties": {
@@ -86,6 +157,7 @@ pub fn default_tools() -> Vec<ToolDefinition> {
         ToolDefinition {
             name: "grep",
             description: "Search file contents for a pattern.",
+            terse_description: "Search file contents.",
             input_schema: json!({
                 "type": "object",
                 "properties": {
@@ -95,7 +167,9 @@ pub fn default_tools() -> Vec<ToolDefinition> {
                     "ignoreCase": { "type": "boolean", "description": "Case-insensitive search (default: false)" },
                     "literal": { "type": "boolean", "description": "Treat pattern as literal string instead of regex (default: false)" },
                     "context": { "type": "integer", "description": "Number of lines to show before and after each match (default: 0)" },
-                    "limit": { "type": "integer", "description": "Maximum number of matches to return (default: 100)" }
+                    "limit": { "type": "integer", "description": "Maximum number of matches to return (default: 100)" },
+                    "multiline": { "type": "boolean", "description": "Allow matches to span lines; . also matches newlines (default: false)" },
+                    "type": { "type": "string", "description": "Only search files of this type, e.g. rust, py, ts, go, md" }
                 },
                 "required": ["pattern"],
                 "additionalProperties": false
@@ -105,12 +179,17 @@ pub fn default_tools() -> Vec<ToolDefinition> {
         ToolDefinition {
             name: "find",
             description: "Search for files by glob pattern.",
+            terse_description: "Find files by glob.",
             input_schema: json!({
                 "type": "object",
                 "properties": {
                     "pattern": { "type": "string", "description": "Glob pattern to match files, e.g. '*.ts' or '**/*.json'" },
                     "path": { "type": "string", "description": "Directory to search in (default: current directory)" },
-                    "limit": { "type": "integer", "description": "Maximum number of results (default: 1000)" }
+                    "limit": { "type": "integer", "description": "Maximum number of results (default: 1000)" },
+                    "maxDepth": { "type": "integer", "description": "Directory levels to descend; 1 searches only path itself (default: unlimited)" },
+                    "type": { "type": "string", "enum": ["file", "dir"], "description": "Match files or directories (default: file)" },
+                    "sort": { "type": "string", "enum": ["path", "modified", "size"], "description": "Order results by path, newest first, or largest first (default: path)" },
+                    "includeIgnored": { "type": "boolean", "description": "Include files excluded by .gitignore (default: false)" }
                 },
                 "required": ["pattern"],
                 "additionalProperties": false
@@ -120,16 +199,125 @@ pub fn default_tools() -> Vec<ToolDefinition> {
         ToolDefinition {
             name: "ls",
             description: "List directory contents.",
+            terse_description: "List a directory.",
             input_schema: json!({
                 "type": "object",
                 "properties": {
                     "path": { "type": "string", "description": "Directory to list (default: current directory)" },
-                    "limit": { "type": "integer", "description": "Maximum number of entries to return (default: 500)" }
+                    "limit": { "type": "integer", "description": "Maximum number of entries to return (default: 500)" },
+                    "maxDepth": { "type": "integer", "description": "Directory levels to list (default: 1)" },
+                    "type": { "type": "string", "enum": ["file", "dir"], "description": "List only files or only directories" },
+                    "sort": { "type": "string", "enum": ["name", "modified", "size"], "description": "Order entries by name, newest first, or largest first (default: name)" },
+                    "includeIgnored": { "type": "boolean", "description": "Include entries excluded by .gitignore (default: false)" }
                 },
                 "additionalProperties": false
             }),
             execute: ls_tool,
         },
+        ToolDefinition {
+            name: "git_status",
+            description: "Show the current branch and the working tree status (short format).",
+            terse_description: "Show git status.",
+            input_schema: json!({
+                "type": "object",
+                "properties": {},
+                "additionalProperties": false
+            }),
+            execute: git_status_tool,
+        },
+        ToolDefinition {
+            name: "git_diff",
+            description: "Show unstaged changes, or staged changes with staged=true, optionally limited to a path.",
+            terse_description: "Show git diff.",
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Limit the diff to this file or directory" },
+                    "staged": { "type": "boolean", "description": "Show staged changes instead of unstaged (default: false)" }
+                },
+                "additionalProperties": false
+            }),
+            execute: git_diff_tool,
+        },
+        ToolDefinition {
+            name: "git_log",
+            description: "Show recent commits (one line each), optionally limited to a path.",
+            terse_description: "Show git log.",
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Only show commits touching this file or directory" },
+                    "limit": { "type": "integer", "description": "Maximum number of commits (default: 20)" }
+                },
+                "additionalProperties": false
+            }),
+            execute: git_log_tool,
+        },
+        ToolDefinition {
+            name: "git_commit",
+            description: "Commit changes with a message. Stages all changes, including untracked files, unless all=false.",
+            terse_description: "Create a git commit.",
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "message": { "type": "string", "description": "Commit message" },
+                    "all": { "type": "boolean", "description": "Stage all changes before committing (default: true)" }
+                },
+                "required": ["message"],
+                "additionalProperties": false
+            }),
+            execute: git_commit_tool,
+        },
+        ToolDefinition {
+            name: "git_stash",
+            description: "Stash working tree changes (push), restore the latest stash (pop), or list stashes.",
+            terse_description: "Push, pop, or list git stashes.",
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "action": { "type": "string", "enum": ["push", "pop", "list"], "description": "Stash action (default: push)" },
+                    "message": { "type": "string", "description": "Message for a pushed stash" }
+                },
+                "additionalProperties": false
+            }),
+            execute: git_stash_tool,
+        },
+        ToolDefinition {
+            name: "notebook_read",
+            description: "Read a Jupyter notebook (.ipynb) as numbered cells with their type, id, source and outputs. Use offset/limit to page through large notebooks.",
+            terse_description: "Read a Jupyter notebook's cells.",
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Path to the .ipynb file (relative or absolute)" },
+                    "offset": { "type": "integer", "description": "First cell to show, 0-based (default: 0)" },
+                    "limit": { "type": "integer", "description": "Maximum number of cells to show" },
+                    "outputs": { "type": "boolean", "description": "Include cell outputs (default: true)" }
+                },
+                "required": ["path"],
+                "additionalProperties": false
+            }),
+            execute: notebook_read_tool,
+        },
+        ToolDefinition {
+            name: "notebook_edit",
+            description: "Edit a Jupyter notebook (.ipynb) cell by cell: replace a cell's source, insert a new cell, or delete one. Other cells, outputs and metadata are kept. Replacing a code cell clears its outputs.",
+            terse_description: "Replace, insert, or delete a notebook cell.",
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Path to the .ipynb file (relative or absolute)" },
+                    "action": { "type": "string", "enum": ["replace", "insert", "delete"], "description": "What to do (default: replace)" },
+                    "index": { "type": "integer", "description": "0-based cell index; for insert, the position of the new cell (default: end)" },
+                    "cellId": { "type": "string", "description": "Choose the cell by id instead of index; insert adds the new cell after it" },
+                    "source": { "type": "string", "description": "New cell source, for replace and insert" },
+                    "cellType": { "type": "string", "enum": ["code", "markdown", "raw"], "description": "Cell type for insert (default: code), or to convert a replaced cell" }
+                },
+                "required": ["path"],
+                "additionalProperties": false
+            }),
+            execute: notebook_edit_tool,
+        },
     ]
 }
 
@@ -144,6 +332,9 @@ fn read_tool(args: &Value, ctx: &ToolContext) -> Result<String, String> {
             path,
             offset,
             limit,
+            symbol: get_optional_string_arg(args, "symbol"),
+            outline: get_optional_bool_arg(args, "outline"),
+            line_numbers: get_optional_bool_arg(args, "lineNumbers"),
         },
     )?;
     Ok(tool_result_to_text(result))
@@ -153,7 +344,9 @@ fn write_tool(args: &Value, ctx: &ToolContext) -> Result<String, String> {
     let path = get_string_arg(args, "path")?;
     let content = get_string_arg(args, "content")?;
     let tool = agent_tools::WriteTool::new(&ctx.cwd);
-    let result = tool.execute("tool-call", agent_tools::WriteToolArgs { path, content })?;
+    let result = tool
+        .execute("tool-call", agent_tools::WriteToolArgs { path, content })
+        .map_err(|err| err.message)?;
     Ok(tool_result_to_text(result))
 }
 
@@ -162,14 +355,16 @@ fn edit_tool(args: &Value, ctx: &ToolContext) -> Result<String, String> {
     let old_text = get_string_arg(args, "oldText")?;
     let new_text = get_string_arg(args, "newText")?;
     let tool = agent_tools::EditTool::new(&ctx.cwd);
-    let result = tool.execute(
-        "tool-call",
-        agent_tools::EditToolArgs {
-            path,
-            old_text,
-            new_text,
-        },
-    )?;
+    let result = tool
+        .execute(
+            "tool-call",
+            agent_tools::EditToolArgs {
+                path,
+                old_text,
+                new_text,
+            },
+        )
+        .map_err(|err| err.message)?;
     Ok(tool_result_to_text(result))
 }
 
@@ -194,6 +389,8 @@ fn grep_tool(args: &Value, ctx: &ToolContext) -> Result<String, String> {
             literal: get_optional_bool_arg(args, "literal"),
             context: get_optional_usize_arg(args, "context"),
             limit: get_optional_usize_arg(args, "limit"),
+            multiline: get_optional_bool_arg(args, "multiline"),
+            file_type: get_optional_string_arg(args, "type"),
         },
     )?;
     Ok(tool_result_to_text(result))
@@ -208,6 +405,10 @@ fn find_tool(args: &Value, ctx: &ToolContext) -> Result<String, String> {
             pattern,
             path: get_optional_string_arg(args, "path"),
             limit: get_optional_usize_arg(args, "limit"),
+            max_depth: get_optional_usize_arg(args, "maxDepth"),
+            entry_type: get_optional_string_arg(args, "type"),
+            sort: get_optional_string_arg(args, "sort"),
+            include_ignored: get_optional_bool_arg(args, "includeIgnored"),
         },
     )?;
     Ok(tool_result_to_text(result))
@@ -220,11 +421,97 @@ fn ls_tool(args: &Value, ctx: &ToolContext) -> Result<String, String> {
         agent_tools::LsToolArgs {
             path: get_optional_string_arg(args, "path"),
             limit: get_optional_usize_arg(args, "limit"),
+            max_depth: get_optional_usize_arg(args, "maxDepth"),
+            entry_type: get_optional_string_arg(args, "type"),
+            sort: get_optional_string_arg(args, "sort"),
+            include_ignored: get_optional_bool_arg(args, "includeIgnored"),
+        },
+    )?;
+    Ok(tool_result_to_text(result))
+}
+
+fn git_status_tool(_args: &Value, ctx: &ToolContext) -> Result<String, String> {
+    let result = agent_tools::GitTool::new(&ctx.cwd).status("tool-call")?;
+    Ok(tool_result_to_text(result))
+}
+
+fn git_diff_tool(args: &Value, ctx: &ToolContext) -> Result<String, String> {
+    let result = agent_tools::GitTool::new(&ctx.cwd).diff(
+        "tool-call",
+        agent_tools::GitDiffToolArgs {
+            path: get_optional_string_arg(args, "path"),
+            staged: get_optional_bool_arg(args, "staged"),
+        },
+    )?;
+    Ok(tool_result_to_text(result))
+}
+
+fn git_log_tool(args: &Value, ctx: &ToolContext) -> Result<String, String> {
+    let result = agent_tools::GitTool::new(&ctx.cwd).log(
+        "tool-call",
+        agent_tools::GitLogToolArgs {
+            path: get_optional_string_arg(args, "path"),
+            limit: get_optional_usize_arg(args, "limit"),
         },
     )?;
     Ok(tool_result_to_text(result))
 }
 
+fn git_commit_tool(args: &Value, ctx: &ToolContext) -> Result<String, String> {
+    let message = get_string_arg(args, "message")?;
+    let result = agent_tools::GitTool::new(&ctx.cwd).commit(
+        "tool-call",
+        agent_tools::GitCommitToolArgs {
+            message,
+            all: get_optional_bool_arg(args, "all"),
+        },
+    )?;
+    Ok(tool_result_to_text(result))
+}
+
+fn git_stash_tool(args: &Value, ctx: &ToolContext) -> Result<String, String> {
+    let result = agent_tools::GitTool::new(&ctx.cwd).stash(
+        "tool-call",
+        agent_tools::GitStashToolArgs {
+            action: get_optional_string_arg(args, "action"),
+            message: get_optional_string_arg(args, "message"),
+        },
+    )?;
+    Ok(tool_result_to_text(result))
+}
+
+fn notebook_read_tool(args: &Value, ctx: &ToolContext) -> Result<String, String> {
+    let path = get_string_arg(args, "path")?;
+    let result = agent_tools::NotebookTool::new(&ctx.cwd).read(
+        "tool-call",
+        agent_tools::NotebookReadToolArgs {
+            path,
+            offset: get_optional_usize_arg(args, "offset"),
+            limit: get_optional_usize_arg(args, "limit"),
+            outputs: get_optional_bool_arg(args, "outputs"),
+        },
+    )?;
+    Ok(tool_result_to_text(result))
+}
+
+fn notebook_edit_tool(args: &Value, ctx: &ToolContext) -> Result<String, String> {
+    let path = get_string_arg(args, "path")?;
+    let result = agent_tools::NotebookTool::new(&ctx.cwd)
+        .edit(
+            "tool-call",
+            agent_tools::NotebookEditToolArgs {
+                path,
+                action: get_optional_string_arg(args, "action"),
+                index: get_optional_usize_arg(args, "index"),
+                cell_id: get_optional_string_arg(args, "cellId"),
+                source: get_optional_string_arg(args, "source"),
+                cell_type: get_optional_string_arg(args, "cellType"),
+            },
+        )
+        .map_err(|err| err.message)?;
+    Ok(tool_result_to_text(result))
+}
+
 fn get_string_arg(args: &Value, key: &str) -> Result<String, String> {
     args.get(key)
         .and_then(|value| value.as_str())
@@ -0,0 +1,239 @@
+use serde_json::{Map, Value};
+
+/// One way a value failed its schema. `path` is a JSON pointer to the offending value
+/// (empty for the root).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SchemaError {
+    pub path: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+/// Checks `value` against the subset of JSON Schema that tool definitions use: `type`,
+/// `properties`, `required`, `additionalProperties`, `items`, `enum`, `const`, `minimum`,
+/// `maximum`, `minLength`, `maxLength`, `minItems`, `maxItems`, `anyOf`, `oneOf` and
+/// `allOf`. Unknown keywords are ignored.
+pub fn validate(schema: &Value, value: &Value) -> Vec<SchemaError> {
+    let mut errors = Vec::new();
+    validate_at(schema, value, "", &mut errors);
+    errors
+}
+
+/// The tool result text sent back to the model when its arguments fail validation.
+pub fn format_errors(tool_name: &str, errors: &[SchemaError]) -> String {
+    let mut text = format!("Invalid arguments for tool \"{tool_name}\":");
+    for error in errors {
+        text.push_str(&format!("\n- {error}"));
+    }
+    text
+}
+
+fn validate_at(schema: &Value, value: &Value, path: &str, errors: &mut Vec<SchemaError>) {
+    let Some(schema) = schema.as_object() else {
+        if schema == &Value::Bool(false) {
+            push(errors, path, "no value is allowed here".to_string());
+        }
+        return;
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let types = match expected {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !types.is_empty() && !types.iter().any(|name| matches_type(name, value)) {
+            push(
+                errors,
+                path,
+                format!("expected {}, got {}", types.join(" or "), type_name(value)),
+            );
+            return;
+        }
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            let choices = allowed
+                .iter()
+                .map(Value::to_string)
+                .collect::<Vec<_>>()
+                .join(", ");
+            push(errors, path, format!("must be one of {choices}"));
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            push(errors, path, format!("must be {expected}"));
+        }
+    }
+
+    match value {
+        Value::Object(object) => validate_object(schema, object, path, errors),
+        Value::Array(items) => validate_array(schema, items, path, errors),
+        Value::String(text) => {
+            let length = text.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+                if length < min {
+                    push(errors, path, format!("must be at least {min} characters"));
+                }
+            }
+            if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+                if length > max {
+                    push(errors, path, format!("must be at most {max} characters"));
+                }
+            }
+        }
+        Value::Number(number) => {
+            let number = number.as_f64().unwrap_or_default();
+            if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+                if number < min {
+                    push(errors, path, format!("must be >= {min}"));
+                }
+            }
+            if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+                if number > max {
+                    push(errors, path, format!("must be <= {max}"));
+                }
+            }
+        }
+        _ => {}
+    }
+
+    if let Some(all) = schema.get("allOf").and_then(Value::as_array) {
+        for sub in all {
+            validate_at(sub, value, path, errors);
+        }
+    }
+    if let Some(any) = schema.get("anyOf").and_then(Value::as_array) {
+        if !any.iter().any(|sub| validate(sub, value).is_empty()) {
+            push(
+                errors,
+                path,
+                "does not match any of the allowed schemas".to_string(),
+            );
+        }
+    }
+    if let Some(one) = schema.get("oneOf").and_then(Value::as_array) {
+        let matching = one
+            .iter()
+            .filter(|sub| validate(sub, value).is_empty())
+            .count();
+        if matching != 1 {
+            push(
+                errors,
+                path,
+                format!("must match exactly one allowed schema (matched {matching})"),
+            );
+        }
+    }
+}
+
+fn validate_object(
+    schema: &Map<String, Value>,
+    object: &Map<String, Value>,
+    path: &str,
+    errors: &mut Vec<SchemaError>,
+) {
+    if let Some(required) = schema.get("required").and_then(Value::as_array) {
+        for key in required.iter().filter_map(Value::as_str) {
+            if !object.contains_key(key) {
+                push(errors, &child_path(path, key), "is required".to_string());
+            }
+        }
+    }
+
+    let properties = schema.get("properties").and_then(Value::as_object);
+    for (key, property) in object {
+        let key_path = child_path(path, key);
+        if let Some(property_schema) = properties.and_then(|properties| properties.get(key)) {
+            validate_at(property_schema, property, &key_path, errors);
+            continue;
+        }
+        match schema.get("additionalProperties") {
+            Some(Value::Bool(false)) => {
+                push(errors, &key_path, "is not an allowed property".to_string());
+            }
+            Some(extra @ Value::Object(_)) => validate_at(extra, property, &key_path, errors),
+            _ => {}
+        }
+    }
+}
+
+fn validate_array(
+    schema: &Map<String, Value>,
+    items: &[Value],
+    path: &str,
+    errors: &mut Vec<SchemaError>,
+) {
+    let count = items.len() as u64;
+    if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+        if count < min {
+            push(errors, path, format!("must have at least {min} items"));
+        }
+    }
+    if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+        if count > max {
+            push(errors, path, format!("must have at most {max} items"));
+        }
+    }
+    if let Some(item_schema) = schema.get("items") {
+        for (index, item) in items.iter().enumerate() {
+            validate_at(
+                item_schema,
+                item,
+                &child_path(path, &index.to_string()),
+                errors,
+            );
+        }
+    }
+}
+
+fn matches_type(name: &str, value: &Value) -> bool {
+    match name {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64()
+                || value.is_u64()
+                || value.as_f64().is_some_and(|number| number.fract() == 0.0)
+        }
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(number) if number.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn child_path(path: &str, key: &str) -> String {
+    format!("{path}/{}", key.replace('~', "~0").replace('/', "~1"))
+}
+
+fn push(errors: &mut Vec<SchemaError>, path: &str, message: String) {
+    errors.push(SchemaError {
+        path: path.to_string(),
+        message,
+    });
+}
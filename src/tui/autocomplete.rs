@@ -1,5 +1,15 @@
+use crate::coding_agent::fuzzy_match;
+use crate::coding_agent::gitignore::{walk, WalkOptions};
+use std::cell::RefCell;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Upper bound on files indexed for `@` fuzzy search, to keep huge trees responsive.
+const MAX_INDEXED_FILES: usize = 20_000;
+const MAX_FUZZY_RESULTS: usize = 50;
+/// How long the project file index is reused before walking the tree again.
+const FILE_INDEX_TTL: Duration = Duration::from_secs(10);
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct AutocompleteItem {
@@ -32,17 +42,32 @@ impl SlashCommand {
 
 pub struct CombinedAutocompleteProvider {
     commands: Vec<SlashCommand>,
+    /// Values offered after `/<command> `, keyed by command name.
+    argument_completions: Vec<(String, Vec<AutocompleteItem>)>,
     base_path: PathBuf,
+    file_index: RefCell<Option<(Instant, Vec<String>)>>,
 }
 
 impl CombinedAutocompleteProvider {
     pub fn new(commands: Vec<SlashCommand>, base_path: impl Into<PathBuf>) -> Self {
         Self {
             commands,
+            argument_completions: Vec::new(),
             base_path: base_path.into(),
+            file_index: RefCell::new(None),
         }
     }
 
+    /// Offer `items` (fuzzy-matched on their value) for the argument of `/<command>`.
+    pub fn with_argument_completions(
+        mut self,
+        command: impl Into<String>,
+        items: Vec<AutocompleteItem>,
+    ) -> Self {
+        self.argument_completions.push((command.into(), items));
+        self
+    }
+
     /// Get autocomplete suggestions for the current editor state.
     /// Returns suggestions for slash commands when at the start of input with `/`,
     /// or file path suggestions otherwise.
@@ -97,9 +122,16 @@ impl CombinedAutocompleteProvider {
                 });
             }
 
-            // Space found - could complete command arguments in the future
-            // For now, just return None for command arguments
-            return None;
+            // Space found - complete the argument for commands that have known values
+            let (command, argument) = prefix.split_once(' ')?;
+            let items = self.get_argument_suggestions(command, argument);
+            if items.is_empty() {
+                return None;
+            }
+            return Some(AutocompleteSuggestions {
+                items,
+                prefix: argument.to_string(),
+            });
         }
 
         // Check for file paths
@@ -160,6 +192,29 @@ impl CombinedAutocompleteProvider {
         (new_lines, cursor_line, new_col)
     }
 
+    fn get_argument_suggestions(&self, command: &str, argument: &str) -> Vec<AutocompleteItem> {
+        let Some((_, items)) = self
+            .argument_completions
+            .iter()
+            .find(|(name, _)| name == command)
+        else {
+            return Vec::new();
+        };
+        let mut scored = items
+            .iter()
+            .filter_map(|item| {
+                let matched = fuzzy_match(argument, &item.value);
+                matched.matches.then_some((matched.score, item))
+            })
+            .collect::<Vec<_>>();
+        scored.sort_by(|a, b| a.0.total_cmp(&b.0));
+        scored
+            .into_iter()
+            .take(MAX_FUZZY_RESULTS)
+            .map(|(_, item)| item.clone())
+            .collect()
+    }
+
     pub fn get_force_file_suggestions(
         &self,
         lines: &[String],
@@ -234,6 +289,12 @@ impl CombinedAutocompleteProvider {
     }
 
     fn get_file_suggestions(&self, prefix: &str) -> Vec<AutocompleteItem> {
+        if let Some(query) = prefix.strip_prefix('@') {
+            if !query.is_empty() && !query.ends_with('/') && !query.starts_with(['~', '/', '.']) {
+                return self.get_fuzzy_file_suggestions(query);
+            }
+        }
+
         let (fs_prefix, value_prefix) = if let Some(rest) = prefix.strip_prefix('@') {
             (rest, prefix)
         } else {
@@ -294,6 +355,68 @@ impl CombinedAutocompleteProvider {
 
         items
     }
+
+    /// Fuzzy-match `query` against every non-ignored file under the base path,
+    /// preferring matches on the file name over matches on the directory.
+    fn get_fuzzy_file_suggestions(&self, query: &str) -> Vec<AutocompleteItem> {
+        let mut index = self.file_index.borrow_mut();
+        let stale = index
+            .as_ref()
+            .is_none_or(|(built, _)| built.elapsed() > FILE_INDEX_TTL);
+        if stale {
+            *index = Some((Instant::now(), collect_project_files(&self.base_path)));
+        }
+        let files = index
+            .as_ref()
+            .map(|(_, files)| files.as_slice())
+            .unwrap_or(&[]);
+
+        let mut scored = Vec::new();
+        for path in files {
+            let name = path.rsplit('/').next().unwrap_or(path);
+            let name_match = fuzzy_match(query, name);
+            let score = if name_match.matches {
+                name_match.score - 100.0
+            } else {
+                let path_match = fuzzy_match(query, path);
+                if !path_match.matches {
+                    continue;
+                }
+                path_match.score
+            };
+            scored.push((score, path));
+        }
+        scored.sort_by(|a, b| a.0.total_cmp(&b.0).then_with(|| a.1.cmp(b.1)));
+
+        scored
+            .into_iter()
+            .take(MAX_FUZZY_RESULTS)
+            .map(|(_, path)| {
+                let (dir, name) = match path.rsplit_once('/') {
+                    Some((dir, name)) => (Some(dir.to_string()), name.to_string()),
+                    None => (None, path.clone()),
+                };
+                AutocompleteItem {
+                    value: format!("@{path}"),
+                    label: name,
+                    description: dir,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Relative paths of files under `base`, skipping `.git` and anything matched by `.gitignore` files.
+fn collect_project_files(base: &Path) -> Vec<String> {
+    let mut files = Vec::new();
+    walk(base, WalkOptions::default(), &mut |entry| {
+        if !entry.is_dir {
+            files.push(entry.rel_path.clone());
+        }
+        files.len() < MAX_INDEXED_FILES
+    });
+    files.sort();
+    files
 }
 
 fn resolve_search_dir(base_path: &Path, prefix: &str) -> (PathBuf, String) {
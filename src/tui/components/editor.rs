@@ -26,6 +26,14 @@ struct EditorState {
     cursor_col: usize,
 }
 
+/// Ctrl-R reverse search over history; `saved` restores the draft on cancel.
+struct HistorySearch {
+    query: String,
+    match_index: Option<usize>,
+    failed: bool,
+    saved: EditorState,
+}
+
 struct TextChunk {
     text: String,
     start_index: usize,
@@ -44,6 +52,8 @@ pub struct Editor {
     last_width: usize,
     history: Vec<String>,
     history_index: i32,
+    history_limit: usize,
+    history_search: Option<HistorySearch>,
     // Bracketed paste mode buffering
     paste_buffer: String,
     is_in_paste: bool,
@@ -66,6 +76,8 @@ impl Editor {
             last_width: 80,
             history: Vec::new(),
             history_index: -1,
+            history_limit: 100,
+            history_search: None,
             paste_buffer: String::new(),
             is_in_paste: false,
             autocomplete_provider: None,
@@ -96,11 +108,27 @@ impl Editor {
             return;
         }
         self.history.insert(0, trimmed.to_string());
-        if self.history.len() > 100 {
+        if self.history.len() > self.history_limit {
             self.history.pop();
         }
     }
 
+    pub fn set_history_limit(&mut self, limit: usize) {
+        self.history_limit = limit;
+        self.history.truncate(limit);
+    }
+
+    /// Replace the history with `entries`, most recent first.
+    pub fn set_history(&mut self, entries: Vec<String>) {
+        self.history = entries;
+        self.history.truncate(self.history_limit);
+        self.history_index = -1;
+    }
+
+    pub fn is_searching_history(&self) -> bool {
+        self.history_search.is_some()
+    }
+
     pub fn get_text(&self) -> String {
         self.state.lines.join("\n")
     }
@@ -135,6 +163,15 @@ impl Editor {
 
         result.push(border);
 
+        if let Some(search) = &self.history_search {
+            let label = if search.failed {
+                "failed reverse-i-search"
+            } else {
+                "reverse-i-search"
+            };
+            result.push(format!("({label})`{}': ", search.query));
+        }
+
         // Add autocomplete list if active
         if self.is_autocompleting {
             if let Some(ref list) = self.autocomplete_list {
@@ -182,6 +219,20 @@ impl Editor {
             return;
         }
 
+        if self.history_search.is_some() {
+            if self.handle_history_search_input(&input) {
+                return;
+            }
+        } else if input == "\x12" {
+            self.history_search = Some(HistorySearch {
+                query: String::new(),
+                match_index: None,
+                failed: false,
+                saved: self.state.clone(),
+            });
+            return;
+        }
+
         match input.as_str() {
             "\x1b[A" => {
                 if self.is_editor_empty()
@@ -623,6 +674,71 @@ impl Editor {
         }
     }
 
+    /// Returns false when the key ends the search and should still be handled normally.
+    fn handle_history_search_input(&mut self, input: &str) -> bool {
+        let Some(search) = self.history_search.as_mut() else {
+            return false;
+        };
+        match input {
+            "\x12" => {
+                let start = search.match_index.map(|index| index + 1).unwrap_or(0);
+                self.search_history_from(start);
+            }
+            "\x7f" => {
+                search.query.pop();
+                self.search_history_from(0);
+            }
+            "\x1b" | "\x03" | "\x07" => {
+                if let Some(search) = self.history_search.take() {
+                    self.state = search.saved;
+                }
+                self.history_index = -1;
+            }
+            "\r" => {
+                self.history_search = None;
+            }
+            _ if !input.is_empty() && !has_control_chars(input) => {
+                search.query.push_str(input);
+                let start = search.match_index.unwrap_or(0);
+                self.search_history_from(start);
+            }
+            _ => {
+                self.history_search = None;
+                return false;
+            }
+        }
+        true
+    }
+
+    fn search_history_from(&mut self, start: usize) {
+        let Some(search) = self.history_search.as_mut() else {
+            return;
+        };
+        if search.query.is_empty() {
+            search.match_index = None;
+            search.failed = false;
+            return;
+        }
+        let found = self
+            .history
+            .iter()
+            .enumerate()
+            .skip(start)
+            .find(|(_, entry)| entry.contains(&search.query))
+            .map(|(index, _)| index);
+        match found {
+            Some(index) => {
+                search.match_index = Some(index);
+                search.failed = false;
+                let value = self.history[index].clone();
+                self.set_text_internal(&value);
+                self.history_index = index as i32;
+            }
+            // Keep the previous match on screen; the prompt shows the search failed.
+            None => search.failed = true,
+        }
+    }
+
     // =========================================================================
     // Autocomplete Methods
     // =========================================================================
@@ -0,0 +1,217 @@
+//! Reusable selection list with a fuzzy search query, arrow-key navigation and
+//! enter to select. Pickers supply the items and how each one is drawn.
+
+use crate::coding_agent::fuzzy_match;
+use crate::tui::keys::matches_key;
+use crate::tui::utils::truncate_to_width;
+use std::ops::Range;
+
+/// An entry that can be searched in a [`FilterList`].
+pub trait FilterItem {
+    /// Text the search query is matched against.
+    fn search_text(&self) -> String;
+
+    /// Score `query` against the item, lower is better; `None` hides the item.
+    /// Every whitespace-separated token must fuzzy-match the search text.
+    fn match_score(&self, query: &str) -> Option<f64> {
+        let text = self.search_text();
+        let mut total = 0.0;
+        for token in query.split_whitespace() {
+            let matched = fuzzy_match(token, &text);
+            if !matched.matches {
+                return None;
+            }
+            total += matched.score;
+        }
+        Some(total)
+    }
+}
+
+/// Outcome of a key press that ends the interaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FilterListAction {
+    /// Index of the chosen item in the original item list.
+    Selected(usize),
+    Cancelled,
+}
+
+pub struct FilterList<T> {
+    items: Vec<T>,
+    filtered: Vec<usize>,
+    selected: usize,
+    query: String,
+    max_visible: usize,
+    wrap: bool,
+}
+
+impl<T: FilterItem> FilterList<T> {
+    pub fn new(items: Vec<T>, max_visible: usize) -> Self {
+        let filtered = (0..items.len()).collect();
+        Self {
+            items,
+            filtered,
+            selected: 0,
+            query: String::new(),
+            max_visible: max_visible.max(1),
+            wrap: false,
+        }
+    }
+
+    /// Wrap around when moving past either end of the list.
+    pub fn wrapping(mut self) -> Self {
+        self.wrap = true;
+        self
+    }
+
+    pub fn items(&self) -> &[T] {
+        &self.items
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn set_query(&mut self, query: &str) {
+        self.query = query.to_string();
+        self.refilter();
+    }
+
+    pub fn filtered_len(&self) -> usize {
+        self.filtered.len()
+    }
+
+    /// Position of the highlighted entry among the filtered items.
+    pub fn selected_index(&self) -> usize {
+        self.selected
+    }
+
+    pub fn selected_item(&self) -> Option<&T> {
+        self.filtered
+            .get(self.selected)
+            .map(|&index| &self.items[index])
+    }
+
+    /// Re-run the filter; matches are ordered best first, ties keep item order.
+    fn refilter(&mut self) {
+        if self.query.trim().is_empty() {
+            self.filtered = (0..self.items.len()).collect();
+        } else {
+            let mut scored = self
+                .items
+                .iter()
+                .enumerate()
+                .filter_map(|(index, item)| item.match_score(&self.query).map(|s| (s, index)))
+                .collect::<Vec<_>>();
+            scored.sort_by(|a, b| a.0.total_cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+            self.filtered = scored.into_iter().map(|(_, index)| index).collect();
+        }
+        self.selected = 0;
+    }
+
+    pub fn move_up(&mut self) {
+        if self.filtered.is_empty() {
+            return;
+        }
+        if self.selected > 0 {
+            self.selected -= 1;
+        } else if self.wrap {
+            self.selected = self.filtered.len() - 1;
+        }
+    }
+
+    pub fn move_down(&mut self) {
+        if self.filtered.is_empty() {
+            return;
+        }
+        if self.selected + 1 < self.filtered.len() {
+            self.selected += 1;
+        } else if self.wrap {
+            self.selected = 0;
+        }
+    }
+
+    /// Handle a key; typing edits the query, arrows move, enter selects, escape cancels.
+    pub fn handle_input(&mut self, key_data: &str) -> Option<FilterListAction> {
+        if matches_key(key_data, "up") {
+            self.move_up();
+        } else if matches_key(key_data, "down") {
+            self.move_down();
+        } else if matches_key(key_data, "enter") {
+            return self
+                .filtered
+                .get(self.selected)
+                .map(|&index| FilterListAction::Selected(index));
+        } else if matches_key(key_data, "escape") {
+            return Some(FilterListAction::Cancelled);
+        } else if matches_key(key_data, "backspace") {
+            if self.query.pop().is_some() {
+                self.refilter();
+            }
+        } else {
+            let mut chars = key_data.chars();
+            if let (Some(ch), None) = (chars.next(), chars.next()) {
+                if !ch.is_control() {
+                    self.query.push(ch);
+                    self.refilter();
+                }
+            }
+        }
+        None
+    }
+
+    /// Range of filtered positions currently on screen, keeping the selection centred.
+    pub fn visible_range(&self) -> Range<usize> {
+        let len = self.filtered.len();
+        let start = if len <= self.max_visible {
+            0
+        } else {
+            self.selected
+                .saturating_sub(self.max_visible / 2)
+                .min(len - self.max_visible)
+        };
+        start..(start + self.max_visible).min(len)
+    }
+
+    pub fn render_search(&self, width: usize) -> String {
+        let cursor = if self.query.is_empty() {
+            "\x1b[2m_\x1b[0m"
+        } else {
+            "_"
+        };
+        truncate_to_width(
+            &format!("\x1b[2mSearch:\x1b[0m {}{cursor}", self.query),
+            width,
+        )
+    }
+
+    /// Render the visible items with `render_item(item, is_selected)`, followed by a
+    /// scroll indicator when not everything fits.
+    pub fn render_items(
+        &self,
+        width: usize,
+        empty_message: &str,
+        mut render_item: impl FnMut(&T, bool) -> Vec<String>,
+    ) -> Vec<String> {
+        if self.filtered.is_empty() {
+            return vec![format!("\x1b[2m  {empty_message}\x1b[0m")];
+        }
+        let range = self.visible_range();
+        let mut lines = Vec::new();
+        for position in range.clone() {
+            let item = &self.items[self.filtered[position]];
+            lines.extend(render_item(item, position == self.selected));
+        }
+        if range.start > 0 || range.end < self.filtered.len() {
+            let scroll = format!("  ({}/{})", self.selected + 1, self.filtered.len());
+            lines.push(format!(
+                "\x1b[2m{}\x1b[0m",
+                truncate_to_width(&scroll, width)
+            ));
+        }
+        lines
+    }
+}
@@ -1,3 +1,4 @@
+use crate::tui::syntax::{Highlighter, SyntaxKind};
 use crate::tui::utils::{apply_background_to_line, visible_width, wrap_text_with_ansi};
 use crate::tui::Component;
 use std::any::Any;
@@ -29,6 +30,11 @@ pub trait MarkdownTheme {
     fn italic(&self, text: &str) -> String;
     fn strikethrough(&self, text: &str) -> String;
     fn underline(&self, text: &str) -> String;
+    /// Style a highlighted span inside a fenced code block.
+    fn syntax(&self, kind: SyntaxKind, text: &str) -> String {
+        let _ = kind;
+        self.code_block(text)
+    }
 }
 
 pub struct Markdown {
@@ -390,6 +396,7 @@ impl Markdown {
                     .trim()
                     .to_string();
                 rendered_lines.push(self.theme.code_block_border(&format!("```{lang}")));
+                let mut highlighter = Highlighter::new(&lang);
                 i += 1;
                 while i < lines.len() {
                     let code_line = lines[i];
@@ -398,7 +405,16 @@ impl Markdown {
                         i += 1;
                         break;
                     }
-                    rendered_lines.push(format!("  {}", self.theme.code_block(code_line)));
+                    let styled = if highlighter.is_supported() {
+                        highlighter
+                            .highlight_line(code_line)
+                            .iter()
+                            .map(|(kind, text)| self.theme.syntax(*kind, text))
+                            .collect::<String>()
+                    } else {
+                        self.theme.code_block(code_line)
+                    };
+                    rendered_lines.push(format!("  {styled}"));
                     i += 1;
                 }
                 let after_block = lines.get(i).copied().unwrap_or("");
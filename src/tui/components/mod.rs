@@ -2,11 +2,13 @@ mod component;
 mod container;
 mod editor;
 mod expandable;
+mod filter_list;
 mod image;
 mod login_dialog;
 mod markdown;
 mod model_selector;
 mod oauth_selector;
+mod pager;
 mod select_list;
 mod session_selector;
 mod settings_selector;
@@ -19,16 +21,19 @@ pub use component::Component;
 pub use container::Container;
 pub use editor::{Editor, EditorTheme};
 pub use expandable::{Expandable, ExpandableText, ToolPreviewConfig};
+pub use filter_list::{FilterItem, FilterList, FilterListAction};
 pub use image::{Image, ImageOptions, ImageTheme};
 pub use login_dialog::{LoginDialogComponent, LoginDialogResult, LoginDialogState};
 pub use markdown::{DefaultTextStyle, Markdown, MarkdownTheme};
 pub use model_selector::{ModelItem, ModelSelectorComponent, ModelSelectorResult};
 pub use oauth_selector::{OAuthSelectorComponent, OAuthSelectorMode, OAuthSelectorResult};
+pub use pager::{Pager, PagerResult};
 pub use select_list::{SelectList, SelectListTheme};
 pub use session_selector::{SessionList, SessionSelectorComponent};
 pub use settings_selector::{
-    bool_values, double_escape_action_values, queue_mode_values, thinking_level_values,
-    SettingItem, SettingValue, SettingsSelectorComponent, SettingsSelectorResult,
+    bool_values, double_escape_action_values, queue_mode_values, queue_priority_values,
+    thinking_level_values, SettingItem, SettingValue, SettingsSelectorComponent,
+    SettingsSelectorResult,
 };
 pub use spacer::Spacer;
 pub use text::Text;
@@ -0,0 +1,328 @@
+use super::Component;
+use crate::tui::matches_key;
+use crate::tui::utils::truncate_to_width;
+use std::any::Any;
+use std::cell::Cell;
+use unicode_width::UnicodeWidthChar;
+
+/// Columns moved per horizontal scroll step.
+const HORIZONTAL_STEP: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PagerResult {
+    Closed,
+}
+
+/// Full-screen, less-style viewer for long text with line numbers, search and horizontal scroll.
+/// Lines are never wrapped; use left/right to see long lines.
+pub struct Pager {
+    title: String,
+    lines: Vec<String>,
+    top: usize,
+    left: usize,
+    body_height: Cell<usize>,
+    search_input: Option<String>,
+    query: String,
+    matches: Vec<usize>,
+    status: Option<String>,
+}
+
+impl Pager {
+    pub fn new(title: impl Into<String>, text: &str) -> Self {
+        let text = text.replace('\t', "    ");
+        let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+        if lines.is_empty() {
+            lines.push(String::new());
+        }
+        Self {
+            title: title.into(),
+            lines,
+            top: 0,
+            left: 0,
+            body_height: Cell::new(20),
+            search_input: None,
+            query: String::new(),
+            matches: Vec::new(),
+            status: None,
+        }
+    }
+
+    /// Index of the first visible line.
+    pub fn top_line(&self) -> usize {
+        self.top
+    }
+
+    /// Column offset of the horizontal scroll.
+    pub fn left_column(&self) -> usize {
+        self.left
+    }
+
+    /// Line indexes containing the current search query.
+    pub fn matches(&self) -> &[usize] {
+        &self.matches
+    }
+
+    pub fn render_page(&self, width: usize, height: usize) -> Vec<String> {
+        let body_height = height.saturating_sub(2).max(1);
+        self.body_height.set(body_height);
+        let top = self.top.min(self.max_top());
+
+        let gutter_width = self.lines.len().to_string().len();
+        let content_width = width.saturating_sub(gutter_width + 3);
+
+        let mut output = Vec::with_capacity(height);
+        output.push(truncate_to_width(
+            &format!("\x1b[1m{}\x1b[0m", self.title),
+            width,
+        ));
+        for index in top..top + body_height {
+            let Some(line) = self.lines.get(index) else {
+                output.push("\x1b[2m~\x1b[0m".to_string());
+                continue;
+            };
+            let visible = slice_columns(line, self.left, content_width);
+            let visible = if self.matches.binary_search(&index).is_ok() {
+                highlight_matches(&visible, &self.query)
+            } else {
+                visible
+            };
+            output.push(format!(
+                "\x1b[2m{:>gutter_width$} │\x1b[0m {visible}",
+                index + 1
+            ));
+        }
+        output.push(truncate_to_width(
+            &self.status_line(top, body_height),
+            width,
+        ));
+        output
+    }
+
+    pub fn handle_input(&mut self, key_data: &str) -> Option<PagerResult> {
+        if let Some(input) = self.search_input.as_mut() {
+            if matches_key(key_data, "escape") {
+                self.search_input = None;
+            } else if matches_key(key_data, "enter") {
+                let query = self.search_input.take().unwrap_or_default();
+                self.search(query);
+            } else if matches_key(key_data, "backspace") {
+                input.pop();
+            } else if key_data.chars().count() == 1 {
+                let ch = key_data.chars().next().unwrap_or(' ');
+                if !ch.is_control() {
+                    input.push(ch);
+                }
+            }
+            return None;
+        }
+
+        self.status = None;
+        let page = self.body_height.get().max(1);
+        match key_data {
+            "q" => return Some(PagerResult::Closed),
+            "/" => self.search_input = Some(String::new()),
+            "n" => self.jump_to_match(true),
+            "N" => self.jump_to_match(false),
+            "j" => self.scroll_down(1),
+            "k" => self.scroll_up(1),
+            " " | "\x1b[6~" => self.scroll_down(page),
+            "b" | "\x1b[5~" => self.scroll_up(page),
+            "g" => self.top = 0,
+            "G" => self.top = self.max_top(),
+            "h" => self.left = self.left.saturating_sub(HORIZONTAL_STEP),
+            "l" => self.scroll_right(),
+            _ if matches_key(key_data, "escape") => return Some(PagerResult::Closed),
+            _ if matches_key(key_data, "down") => self.scroll_down(1),
+            _ if matches_key(key_data, "up") => self.scroll_up(1),
+            _ if matches_key(key_data, "home") => self.top = 0,
+            _ if matches_key(key_data, "end") => self.top = self.max_top(),
+            _ if matches_key(key_data, "left") => {
+                self.left = self.left.saturating_sub(HORIZONTAL_STEP)
+            }
+            _ if matches_key(key_data, "right") => self.scroll_right(),
+            _ => {}
+        }
+        None
+    }
+
+    fn max_top(&self) -> usize {
+        self.lines
+            .len()
+            .saturating_sub(self.body_height.get().max(1))
+    }
+
+    fn scroll_down(&mut self, amount: usize) {
+        self.top = (self.top + amount).min(self.max_top());
+    }
+
+    fn scroll_up(&mut self, amount: usize) {
+        self.top = self.top.min(self.max_top()).saturating_sub(amount);
+    }
+
+    fn scroll_right(&mut self) {
+        let longest = self
+            .lines
+            .iter()
+            .map(|line| {
+                line.chars()
+                    .filter_map(UnicodeWidthChar::width)
+                    .sum::<usize>()
+            })
+            .max()
+            .unwrap_or(0);
+        if self.left + HORIZONTAL_STEP < longest {
+            self.left += HORIZONTAL_STEP;
+        }
+    }
+
+    fn search(&mut self, query: String) {
+        self.query = query;
+        let needle = self.query.to_lowercase();
+        self.matches = if needle.is_empty() {
+            Vec::new()
+        } else {
+            self.lines
+                .iter()
+                .enumerate()
+                .filter(|(_, line)| line.to_lowercase().contains(&needle))
+                .map(|(index, _)| index)
+                .collect()
+        };
+        match self.matches.iter().find(|index| **index >= self.top) {
+            Some(index) => self.show_match(*index),
+            None if !self.matches.is_empty() => self.show_match(self.matches[0]),
+            None if !needle.is_empty() => {
+                self.status = Some(format!("Pattern not found: {}", self.query));
+            }
+            None => {}
+        }
+    }
+
+    fn jump_to_match(&mut self, forward: bool) {
+        if self.matches.is_empty() {
+            if !self.query.is_empty() {
+                self.status = Some(format!("Pattern not found: {}", self.query));
+            }
+            return;
+        }
+        let top = self.top.min(self.max_top());
+        let next = if forward {
+            self.matches
+                .iter()
+                .find(|index| **index > top)
+                .or(self.matches.first())
+        } else {
+            self.matches
+                .iter()
+                .rev()
+                .find(|index| **index < top)
+                .or(self.matches.last())
+        };
+        if let Some(index) = next.copied() {
+            self.show_match(index);
+        }
+    }
+
+    fn show_match(&mut self, index: usize) {
+        self.top = index.min(self.max_top());
+        // Bring the first occurrence into view when it sits past the right edge.
+        let needle = self.query.to_lowercase();
+        if let Some(byte) = self.lines[index].to_lowercase().find(&needle) {
+            let column = self.lines[index]
+                .get(..byte)
+                .unwrap_or("")
+                .chars()
+                .filter_map(UnicodeWidthChar::width)
+                .sum::<usize>();
+            if column < self.left {
+                self.left = column - column % HORIZONTAL_STEP;
+            }
+        }
+    }
+
+    fn status_line(&self, top: usize, body_height: usize) -> String {
+        if let Some(input) = &self.search_input {
+            return format!("/{input}\x1b[7m \x1b[0m");
+        }
+        if let Some(status) = &self.status {
+            return format!("\x1b[33m{status}\x1b[0m");
+        }
+        let last = (top + body_height).min(self.lines.len());
+        let mut parts = vec![format!(
+            "lines {}-{} of {}",
+            top + 1,
+            last,
+            self.lines.len()
+        )];
+        if self.left > 0 {
+            parts.push(format!("col {}", self.left + 1));
+        }
+        if !self.matches.is_empty() {
+            let current = self
+                .matches
+                .iter()
+                .position(|index| *index >= top)
+                .map(|position| position + 1)
+                .unwrap_or(self.matches.len());
+            parts.push(format!(
+                "match {current}/{} for \"{}\"",
+                self.matches.len(),
+                self.query
+            ));
+        }
+        parts.push("q close · / search · n/N next/prev · ←/→ scroll".to_string());
+        format!("\x1b[2m{}\x1b[0m", parts.join("  "))
+    }
+}
+
+impl Component for Pager {
+    fn render(&self, width: usize) -> Vec<String> {
+        self.render_page(width, self.body_height.get() + 2)
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Plain-text slice of `line` covering display columns `start..start + width`.
+fn slice_columns(line: &str, start: usize, width: usize) -> String {
+    let mut column = 0;
+    let mut output = String::new();
+    for ch in line.chars() {
+        let ch_width = ch.width().unwrap_or(0);
+        if column >= start && column + ch_width <= start + width {
+            output.push(ch);
+        }
+        column += ch_width;
+        if column >= start + width {
+            break;
+        }
+    }
+    output
+}
+
+fn highlight_matches(text: &str, query: &str) -> String {
+    if query.is_empty() {
+        return text.to_string();
+    }
+    let lower = text.to_lowercase();
+    let needle = query.to_lowercase();
+    // Lowercasing can change byte lengths for some scripts; skip highlighting rather than mis-slice.
+    if lower.len() != text.len() {
+        return text.to_string();
+    }
+    let mut output = String::new();
+    let mut last = 0;
+    for (start, _) in lower.match_indices(&needle) {
+        let end = start + needle.len();
+        if !text.is_char_boundary(start) || !text.is_char_boundary(end) {
+            continue;
+        }
+        output.push_str(&text[last..start]);
+        output.push_str(&format!("\x1b[7m{}\x1b[27m", &text[start..end]));
+        last = end;
+    }
+    output.push_str(&text[last..]);
+    output
+}
@@ -1,12 +1,13 @@
 //! Session selector component for interactive mode.
 //!
 //! Provides a TUI-based session picker with:
-//! - Search filtering
+//! - Fuzzy search filtering
 //! - Multi-line session display (message + metadata)
 //! - Keyboard navigation (up/down, enter, escape)
 
-use crate::core::session_manager::SessionInfo;
-use crate::tui::keys::matches_key;
+use crate::coding_agent::fuzzy_match;
+use crate::core::session_manager::{SessionFilter, SessionInfo};
+use crate::tui::components::filter_list::{FilterItem, FilterList, FilterListAction};
 use crate::tui::utils::truncate_to_width;
 use std::path::PathBuf;
 use std::time::SystemTime;
@@ -15,18 +16,49 @@ use std::time::SystemTime;
 type SelectCallback = Box<dyn FnMut(PathBuf)>;
 type CancelCallback = Box<dyn FnMut()>;
 
+/// Score given to sessions that only match on their full transcript, so fuzzy hits on
+/// the name or first message sort first.
+const TRANSCRIPT_MATCH_SCORE: f64 = 1000.0;
+
+impl FilterItem for SessionInfo {
+    fn search_text(&self) -> String {
+        let mut text = self.name.clone().unwrap_or_default();
+        text.push(' ');
+        text.push_str(&self.first_message);
+        for tag in &self.tags {
+            text.push_str(" #");
+            text.push_str(tag);
+        }
+        text
+    }
+
+    fn match_score(&self, query: &str) -> Option<f64> {
+        let text = self.search_text();
+        let mut total = 0.0;
+        let mut fuzzy = true;
+        for token in query.split_whitespace() {
+            let matched = fuzzy_match(token, &text);
+            if !matched.matches {
+                fuzzy = false;
+                break;
+            }
+            total += matched.score;
+        }
+        if fuzzy {
+            return Some(total);
+        }
+        let filter = SessionFilter {
+            query: Some(query.to_string()),
+            tags: Vec::new(),
+        };
+        filter.matches(self).then_some(TRANSCRIPT_MATCH_SCORE)
+    }
+}
+
 /// Session list component with selection and search
 pub struct SessionList {
-    /// All sessions
-    all_sessions: Vec<SessionInfo>,
-    /// Filtered sessions (after applying search)
-    filtered_sessions: Vec<SessionInfo>,
-    /// Currently selected index in filtered_sessions
-    selected_index: usize,
-    /// Current search query
-    search_query: String,
-    /// Maximum visible sessions
-    max_visible: usize,
+    /// Sessions with the search query and selection
+    list: FilterList<SessionInfo>,
     /// Callback when a session is selected
     pub on_select: Option<SelectCallback>,
     /// Callback when selection is cancelled
@@ -36,42 +68,13 @@ pub struct SessionList {
 impl SessionList {
     /// Create a new session list
     pub fn new(sessions: Vec<SessionInfo>, max_visible: usize) -> Self {
-        let filtered = sessions.clone();
         Self {
-            all_sessions: sessions,
-            filtered_sessions: filtered,
-            selected_index: 0,
-            search_query: String::new(),
-            max_visible,
+            list: FilterList::new(sessions, max_visible),
             on_select: None,
             on_cancel: None,
         }
     }
 
-    /// Filter sessions by search query
-    fn filter_sessions(&mut self) {
-        let query = self.search_query.to_lowercase();
-        if query.is_empty() {
-            self.filtered_sessions = self.all_sessions.clone();
-        } else {
-            self.filtered_sessions = self
-                .all_sessions
-                .iter()
-                .filter(|session| {
-                    session.all_messages_text.to_lowercase().contains(&query)
-                        || session.first_message.to_lowercase().contains(&query)
-                })
-                .cloned()
-                .collect();
-        }
-        // Clamp selected index
-        if self.filtered_sessions.is_empty() {
-            self.selected_index = 0;
-        } else if self.selected_index >= self.filtered_sessions.len() {
-            self.selected_index = self.filtered_sessions.len() - 1;
-        }
-    }
-
     /// Format relative time from SystemTime
     fn format_relative_time(time: SystemTime) -> String {
         let now = SystemTime::now();
@@ -105,148 +108,92 @@ impl SessionList {
 
     /// Render the session list
     pub fn render(&self, width: usize) -> Vec<String> {
-        let mut lines = Vec::new();
-
-        // Render search input
-        let search_label = "\x1b[2mSearch:\x1b[0m ";
-        let cursor_indicator = if self.search_query.is_empty() {
-            "\x1b[2m_\x1b[0m"
-        } else {
-            "_"
-        };
-        let search_line = format!("{}{}{}", search_label, self.search_query, cursor_indicator);
-        lines.push(truncate_to_width(&search_line, width));
-        lines.push(String::new()); // Blank line after search
-
-        if self.filtered_sessions.is_empty() {
-            lines.push("\x1b[2m  No sessions found\x1b[0m".to_string());
-            return lines;
-        }
-
-        // Calculate visible range with scrolling
-        let start_index = if self.filtered_sessions.len() <= self.max_visible {
-            0
-        } else {
-            let half = self.max_visible / 2;
-            let max_start = self.filtered_sessions.len() - self.max_visible;
-            self.selected_index.saturating_sub(half).min(max_start)
-        };
-        let end_index = (start_index + self.max_visible).min(self.filtered_sessions.len());
+        let mut lines = vec![
+            self.list.render_search(width),
+            String::new(), // Blank line after search
+        ];
 
         // Render visible sessions (2 lines per session + blank line)
-        for i in start_index..end_index {
-            let session = &self.filtered_sessions[i];
-            let is_selected = i == self.selected_index;
-
-            // Normalize first message to single line
-            let normalized_message = Self::normalize_message(&session.first_message);
-
-            // First line: cursor + message (truncate to visible width)
-            let cursor = if is_selected {
-                "\x1b[36m› \x1b[0m" // cyan accent
-            } else {
-                "  "
-            };
-            let max_msg_width = width.saturating_sub(2); // Account for cursor (2 visible chars)
-            let truncated_msg = truncate_to_width(&normalized_message, max_msg_width);
-            let message_line = if is_selected {
-                format!("{}\x1b[1m{}\x1b[0m", cursor, truncated_msg) // bold
-            } else {
-                format!("{}{}", cursor, truncated_msg)
-            };
-
-            // Second line: metadata (dimmed)
-            let modified = Self::format_relative_time(session.modified);
-            let msg_count = format!(
-                "{} message{}",
-                session.message_count,
-                if session.message_count != 1 { "s" } else { "" }
-            );
-            let metadata = format!("  {} · {}", modified, msg_count);
-            let metadata_line = format!("\x1b[2m{}\x1b[0m", truncate_to_width(&metadata, width));
-
-            lines.push(message_line);
-            lines.push(metadata_line);
-            lines.push(String::new()); // Blank line between sessions
-        }
-
-        // Add scroll indicator if needed
-        if start_index > 0 || end_index < self.filtered_sessions.len() {
-            let scroll_text = format!(
-                "  ({}/{})",
-                self.selected_index + 1,
-                self.filtered_sessions.len()
-            );
-            let scroll_info = format!("\x1b[2m{}\x1b[0m", truncate_to_width(&scroll_text, width));
-            lines.push(scroll_info);
-        }
+        lines.extend(
+            self.list
+                .render_items(width, "No sessions found", |session, is_selected| {
+                    // Prefer the user-assigned name, falling back to the first message preview
+                    let normalized_message = Self::normalize_message(
+                        session.name.as_deref().unwrap_or(&session.preview),
+                    );
+
+                    // First line: cursor + message (truncate to visible width)
+                    let cursor = if is_selected {
+                        "\x1b[36m› \x1b[0m" // cyan accent
+                    } else {
+                        "  "
+                    };
+                    let max_msg_width = width.saturating_sub(2); // Account for cursor (2 visible chars)
+                    let truncated_msg = truncate_to_width(&normalized_message, max_msg_width);
+                    let message_line = if is_selected {
+                        format!("{}\x1b[1m{}\x1b[0m", cursor, truncated_msg) // bold
+                    } else {
+                        format!("{}{}", cursor, truncated_msg)
+                    };
+
+                    // Second line: metadata (dimmed)
+                    let modified = Self::format_relative_time(session.modified);
+                    let msg_count = format!(
+                        "{} message{}",
+                        session.message_count,
+                        if session.message_count != 1 { "s" } else { "" }
+                    );
+                    let mut metadata = format!("  {} · {}", modified, msg_count);
+                    if !session.tags.is_empty() {
+                        metadata.push_str(&format!(" · #{}", session.tags.join(" #")));
+                    }
+                    let metadata_line =
+                        format!("\x1b[2m{}\x1b[0m", truncate_to_width(&metadata, width));
+
+                    // Blank line between sessions
+                    vec![message_line, metadata_line, String::new()]
+                }),
+        );
 
         lines
     }
 
     /// Handle keyboard input
     pub fn handle_input(&mut self, key_data: &str) {
-        // Up arrow
-        if matches_key(key_data, "up") {
-            if self.selected_index > 0 {
-                self.selected_index -= 1;
-            }
-        }
-        // Down arrow
-        else if matches_key(key_data, "down") {
-            if self.selected_index + 1 < self.filtered_sessions.len() {
-                self.selected_index += 1;
-            }
-        }
-        // Enter - select
-        else if matches_key(key_data, "enter") {
-            if let Some(session) = self.filtered_sessions.get(self.selected_index) {
+        match self.list.handle_input(key_data) {
+            Some(FilterListAction::Selected(index)) => {
+                let path = self.list.items()[index].path.clone();
                 if let Some(ref mut on_select) = self.on_select {
-                    on_select(session.path.clone());
+                    on_select(path);
                 }
             }
-        }
-        // Escape - cancel
-        else if matches_key(key_data, "escape") {
-            if let Some(ref mut on_cancel) = self.on_cancel {
-                on_cancel();
-            }
-        }
-        // Backspace - remove character from search
-        else if matches_key(key_data, "backspace") {
-            self.search_query.pop();
-            self.filter_sessions();
-        }
-        // Printable characters - add to search
-        else if key_data.len() == 1 {
-            let ch = key_data.chars().next().unwrap();
-            if ch.is_ascii_graphic() || ch == ' ' {
-                self.search_query.push(ch);
-                self.filter_sessions();
+            Some(FilterListAction::Cancelled) => {
+                if let Some(ref mut on_cancel) = self.on_cancel {
+                    on_cancel();
+                }
             }
+            None => {}
         }
     }
 
     /// Get the current search query
     pub fn search_query(&self) -> &str {
-        &self.search_query
+        self.list.query()
     }
 
     /// Get the number of filtered sessions
     pub fn filtered_count(&self) -> usize {
-        self.filtered_sessions.len()
+        self.list.filtered_len()
     }
 
     /// Check if there are any sessions
     pub fn is_empty(&self) -> bool {
-        self.all_sessions.is_empty()
+        self.list.is_empty()
     }
 
     /// Get the currently selected session path (if any)
     pub fn get_selected(&self) -> Option<PathBuf> {
-        self.filtered_sessions
-            .get(self.selected_index)
-            .map(|s| s.path.clone())
+        self.list.selected_item().map(|s| s.path.clone())
     }
 }
 
@@ -333,7 +280,11 @@ mod tests {
             modified: SystemTime::now(),
             message_count,
             first_message: first_message.to_string(),
+            preview: first_message.to_string(),
             all_messages_text: first_message.to_string(),
+            name: None,
+            tags: Vec::new(),
+            parent_session: None,
         }
     }
 
@@ -361,18 +312,15 @@ mod tests {
         assert_eq!(list.filtered_count(), 3);
 
         // Filter by "hello"
-        list.search_query = "hello".to_string();
-        list.filter_sessions();
+        list.list.set_query("hello");
         assert_eq!(list.filtered_count(), 1);
 
         // Filter by "world" - matches two
-        list.search_query = "world".to_string();
-        list.filter_sessions();
+        list.list.set_query("world");
         assert_eq!(list.filtered_count(), 2);
 
         // Filter by non-existent text
-        list.search_query = "nonexistent".to_string();
-        list.filter_sessions();
+        list.list.set_query("nonexistent");
         assert_eq!(list.filtered_count(), 0);
     }
 
@@ -385,22 +333,22 @@ mod tests {
         ];
         let mut list = SessionList::new(sessions, 5);
 
-        assert_eq!(list.selected_index, 0);
+        assert_eq!(list.list.selected_index(), 0);
 
         // Move down
         list.handle_input("\x1b[B"); // Down arrow (legacy sequence)
-        assert_eq!(list.selected_index, 1);
+        assert_eq!(list.list.selected_index(), 1);
 
         list.handle_input("\x1b[B");
-        assert_eq!(list.selected_index, 2);
+        assert_eq!(list.list.selected_index(), 2);
 
         // Can't go past end
         list.handle_input("\x1b[B");
-        assert_eq!(list.selected_index, 2);
+        assert_eq!(list.list.selected_index(), 2);
 
         // Move up
         list.handle_input("\x1b[A"); // Up arrow
-        assert_eq!(list.selected_index, 1);
+        assert_eq!(list.list.selected_index(), 1);
     }
 
     #[test]
@@ -354,6 +354,22 @@ pub fn queue_mode_values() -> Vec<SettingValue> {
     ]
 }
 
+/// Helper to create queue priority setting values.
+pub fn queue_priority_values() -> Vec<SettingValue> {
+    vec![
+        SettingValue {
+            value: "steering-first".to_string(),
+            label: "steering-first".to_string(),
+            description: Some("Deliver steering before waiting follow-ups".to_string()),
+        },
+        SettingValue {
+            value: "fifo".to_string(),
+            label: "fifo".to_string(),
+            description: Some("Deliver messages in the order they were queued".to_string()),
+        },
+    ]
+}
+
 /// Helper to create thinking level setting values.
 pub fn thinking_level_values() -> Vec<SettingValue> {
     vec![
@@ -365,6 +365,7 @@ impl TreeList {
             SessionEntry::ModelChange(_) => "model_change".to_string(),
             SessionEntry::ThinkingLevelChange(_) => "thinking_level_change".to_string(),
             SessionEntry::Label(_) => "label".to_string(),
+            SessionEntry::SessionInfo(_) => "session_info".to_string(),
             SessionEntry::Custom(_) => "custom".to_string(),
             SessionEntry::CustomMessage(e) => format!("custom_message:{}", e.custom_type),
         }
@@ -418,6 +419,9 @@ impl TreeList {
             SessionEntry::Label(e) => {
                 format!("[label: {}]", e.label.as_deref().unwrap_or("(cleared)"))
             }
+            SessionEntry::SessionInfo(e) => {
+                format!("[session: {}]", e.name.as_deref().unwrap_or("(unnamed)"))
+            }
             SessionEntry::Custom(e) => {
                 format!("[custom: {}]", e.custom_type)
             }
@@ -517,7 +521,11 @@ impl TreeList {
                         // Hide settings/bookkeeping entries
                         !matches!(
                             node.entry_type.as_str(),
-                            "label" | "custom" | "model_change" | "thinking_level_change"
+                            "label"
+                                | "custom"
+                                | "model_change"
+                                | "thinking_level_change"
+                                | "session_info"
                         )
                     }
                     FilterMode::NoTools => {
@@ -528,6 +536,7 @@ impl TreeList {
                                 | "custom"
                                 | "model_change"
                                 | "thinking_level_change"
+                                | "session_info"
                                 | "message:toolResult"
                         )
                     }
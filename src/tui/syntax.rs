@@ -0,0 +1,367 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyntaxKind {
+    Plain,
+    Comment,
+    Keyword,
+    Function,
+    Variable,
+    String,
+    Number,
+    Type,
+    Operator,
+    Punctuation,
+}
+
+struct Language {
+    keywords: &'static [&'static str],
+    line_comment: Option<&'static str>,
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+}
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while",
+];
+
+const JS_KEYWORDS: &[&str] = &[
+    "async",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "from",
+    "function",
+    "if",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "new",
+    "null",
+    "of",
+    "return",
+    "static",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "type",
+    "typeof",
+    "undefined",
+    "var",
+    "void",
+    "while",
+    "yield",
+];
+
+const PYTHON_KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "self", "try",
+    "while", "with", "yield",
+];
+
+const GO_KEYWORDS: &[&str] = &[
+    "break",
+    "case",
+    "chan",
+    "const",
+    "continue",
+    "default",
+    "defer",
+    "else",
+    "false",
+    "for",
+    "func",
+    "go",
+    "if",
+    "import",
+    "interface",
+    "map",
+    "nil",
+    "package",
+    "range",
+    "return",
+    "select",
+    "struct",
+    "switch",
+    "true",
+    "type",
+    "var",
+];
+
+const C_KEYWORDS: &[&str] = &[
+    "auto",
+    "bool",
+    "break",
+    "case",
+    "char",
+    "class",
+    "const",
+    "continue",
+    "default",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "extends",
+    "false",
+    "final",
+    "float",
+    "for",
+    "if",
+    "implements",
+    "import",
+    "int",
+    "long",
+    "namespace",
+    "new",
+    "null",
+    "nullptr",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "short",
+    "static",
+    "struct",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typedef",
+    "unsigned",
+    "using",
+    "void",
+    "while",
+];
+
+const SHELL_KEYWORDS: &[&str] = &[
+    "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if", "in",
+    "local", "return", "then", "until", "while",
+];
+
+fn language_for(lang: &str) -> Option<Language> {
+    let lang = lang.trim().to_ascii_lowercase();
+    let language = match lang.as_str() {
+        "rust" | "rs" => Language {
+            keywords: RUST_KEYWORDS,
+            line_comment: Some("//"),
+            block_comment: Some(("/*", "*/")),
+            quotes: &['"'],
+        },
+        "js" | "javascript" | "jsx" | "mjs" | "ts" | "typescript" | "tsx" => Language {
+            keywords: JS_KEYWORDS,
+            line_comment: Some("//"),
+            block_comment: Some(("/*", "*/")),
+            quotes: &['"', '\'', '`'],
+        },
+        "python" | "py" => Language {
+            keywords: PYTHON_KEYWORDS,
+            line_comment: Some("#"),
+            block_comment: None,
+            quotes: &['"', '\''],
+        },
+        "go" | "golang" => Language {
+            keywords: GO_KEYWORDS,
+            line_comment: Some("//"),
+            block_comment: Some(("/*", "*/")),
+            quotes: &['"', '\'', '`'],
+        },
+        "c" | "h" | "cpp" | "c++" | "hpp" | "cc" | "java" | "kotlin" | "kt" | "cs" | "csharp" => {
+            Language {
+                keywords: C_KEYWORDS,
+                line_comment: Some("//"),
+                block_comment: Some(("/*", "*/")),
+                quotes: &['"', '\''],
+            }
+        }
+        "sh" | "bash" | "shell" | "zsh" | "console" => Language {
+            keywords: SHELL_KEYWORDS,
+            line_comment: Some("#"),
+            block_comment: None,
+            quotes: &['"', '\''],
+        },
+        "json" | "jsonc" => Language {
+            keywords: &["true", "false", "null"],
+            line_comment: Some("//"),
+            block_comment: None,
+            quotes: &['"'],
+        },
+        "toml" | "yaml" | "yml" | "ini" => Language {
+            keywords: &["true", "false", "null"],
+            line_comment: Some("#"),
+            block_comment: None,
+            quotes: &['"', '\''],
+        },
+        _ => return None,
+    };
+    Some(language)
+}
+
+/// Minimal line-oriented highlighter for fenced code blocks.
+pub struct Highlighter {
+    language: Option<Language>,
+    in_block_comment: bool,
+}
+
+impl Highlighter {
+    pub fn new(lang: &str) -> Self {
+        Self {
+            language: language_for(lang),
+            in_block_comment: false,
+        }
+    }
+
+    pub fn is_supported(&self) -> bool {
+        self.language.is_some()
+    }
+
+    /// Split a line into highlighted spans. Block comment state carries over between calls.
+    pub fn highlight_line(&mut self, line: &str) -> Vec<(SyntaxKind, String)> {
+        let Some(language) = &self.language else {
+            return vec![(SyntaxKind::Plain, line.to_string())];
+        };
+
+        let chars: Vec<char> = line.chars().collect();
+        let mut spans: Vec<(SyntaxKind, String)> = Vec::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            let rest: String = chars[i..].iter().collect();
+
+            if self.in_block_comment {
+                let end = language.block_comment.map(|(_, end)| end).unwrap_or("*/");
+                match rest.find(end) {
+                    Some(pos) => {
+                        let text = &rest[..pos + end.len()];
+                        i += text.chars().count();
+                        push_span(&mut spans, SyntaxKind::Comment, text);
+                        self.in_block_comment = false;
+                    }
+                    None => {
+                        push_span(&mut spans, SyntaxKind::Comment, &rest);
+                        i = chars.len();
+                    }
+                }
+                continue;
+            }
+
+            if let Some(prefix) = language.line_comment {
+                if rest.starts_with(prefix) {
+                    push_span(&mut spans, SyntaxKind::Comment, &rest);
+                    break;
+                }
+            }
+
+            if let Some((start, _)) = language.block_comment {
+                if rest.starts_with(start) {
+                    push_span(&mut spans, SyntaxKind::Comment, start);
+                    i += start.chars().count();
+                    self.in_block_comment = true;
+                    continue;
+                }
+            }
+
+            let ch = chars[i];
+            if language.quotes.contains(&ch) {
+                let start = i;
+                i += 1;
+                while i < chars.len() {
+                    if chars[i] == '\\' {
+                        i += 2;
+                        continue;
+                    }
+                    if chars[i] == ch {
+                        i += 1;
+                        break;
+                    }
+                    i += 1;
+                }
+                let end = i.min(chars.len());
+                let text: String = chars[start..end].iter().collect();
+                push_span(&mut spans, SyntaxKind::String, &text);
+                i = end;
+                continue;
+            }
+
+            if ch.is_ascii_digit() {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_ascii_alphanumeric() || chars[i] == '_' || chars[i] == '.')
+                {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                push_span(&mut spans, SyntaxKind::Number, &text);
+                continue;
+            }
+
+            if ch.is_alphabetic() || ch == '_' || ch == '$' {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$')
+                {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                let next = chars[i..].iter().find(|c| !c.is_whitespace()).copied();
+                let kind = if language.keywords.contains(&word.as_str()) {
+                    SyntaxKind::Keyword
+                } else if next == Some('(')
+                    || (chars.get(i) == Some(&'!')
+                        && matches!(chars.get(i + 1), Some('(') | Some('[') | Some('{')))
+                {
+                    SyntaxKind::Function
+                } else if word.chars().next().is_some_and(|c| c.is_uppercase()) {
+                    SyntaxKind::Type
+                } else {
+                    SyntaxKind::Variable
+                };
+                push_span(&mut spans, kind, &word);
+                continue;
+            }
+
+            let kind = if "+-*/%=<>!&|^~?:".contains(ch) {
+                SyntaxKind::Operator
+            } else if "()[]{},;.".contains(ch) {
+                SyntaxKind::Punctuation
+            } else {
+                SyntaxKind::Plain
+            };
+            push_span(&mut spans, kind, &ch.to_string());
+            i += 1;
+        }
+
+        spans
+    }
+}
+
+fn push_span(spans: &mut Vec<(SyntaxKind, String)>, kind: SyntaxKind, text: &str) {
+    if let Some((last_kind, last_text)) = spans.last_mut() {
+        if *last_kind == kind {
+            last_text.push_str(text);
+            return;
+        }
+    }
+    spans.push((kind, text.to_string()));
+}
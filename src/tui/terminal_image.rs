@@ -423,6 +423,11 @@ pub fn render_image(
     Some(ImageRenderResult { sequence, rows })
 }
 
+/// Whether a rendered line carries a Kitty or iTerm2 image sequence.
+pub fn is_image_line(line: &str) -> bool {
+    line.contains("\x1b_G") || line.contains("\x1b]1337;File=")
+}
+
 /// Generate fallback text for unsupported terminals.
 pub fn image_fallback(
     mime_type: &str,
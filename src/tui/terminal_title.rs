@@ -0,0 +1,48 @@
+//! Window title (OSC 0) and taskbar progress (OSC 9;4) escape sequences.
+
+use std::sync::OnceLock;
+
+/// Taskbar progress state reported through OSC 9;4.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskbarProgress {
+    Hidden,
+    Indeterminate,
+    Percent(u8),
+    Error,
+}
+
+static PROGRESS_SUPPORTED: OnceLock<bool> = OnceLock::new();
+
+/// Build the sequence that sets the terminal window/tab title.
+pub fn title_sequence(title: &str) -> String {
+    let title: String = title.chars().filter(|ch| !ch.is_control()).collect();
+    format!("\x1b]0;{title}\x07")
+}
+
+/// Build the OSC 9;4 sequence for a taskbar progress state.
+pub fn progress_sequence(progress: TaskbarProgress) -> String {
+    let (state, percent) = match progress {
+        TaskbarProgress::Hidden => (0, 0),
+        TaskbarProgress::Percent(percent) => (1, percent.min(100)),
+        TaskbarProgress::Error => (2, 100),
+        TaskbarProgress::Indeterminate => (3, 0),
+    };
+    format!("\x1b]9;4;{state};{percent}\x07")
+}
+
+/// Detect terminals known to understand OSC 9;4 (Windows Terminal, ConEmu, iTerm2).
+/// Other terminals may print the sequence verbatim, so it is only sent when detected.
+pub fn detect_progress_support() -> bool {
+    let term_program = std::env::var("TERM_PROGRAM")
+        .unwrap_or_default()
+        .to_lowercase();
+    std::env::var("WT_SESSION").is_ok()
+        || std::env::var("ConEmuANSI").is_ok_and(|value| value.eq_ignore_ascii_case("on"))
+        || std::env::var("ITERM_SESSION_ID").is_ok()
+        || term_program == "iterm.app"
+}
+
+/// Whether taskbar progress sequences should be emitted (cached).
+pub fn supports_progress() -> bool {
+    *PROGRESS_SUPPORTED.get_or_init(detect_progress_support)
+}
@@ -1,10 +1,13 @@
 use std::cell::{Cell, RefCell};
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 use pi::agent::{
     agent_loop, agent_loop_continue, AgentContext, AgentEvent, AgentLoopConfig, AgentMessage,
-    AgentTool, AgentToolResult, CustomMessage, LlmContext, Model,
+    AgentTool, AgentToolResult, CancellationToken, CustomMessage, LlmContext, LoopLimits, Model,
+    ToolUpdateThrottle,
 };
+use pi::ai::AssistantMessageEvent;
 use pi::{AssistantMessage, ContentBlock, Cost, Usage, UserContent, UserMessage};
 use serde_json::json;
 
@@ -26,6 +29,10 @@ fn should_emit_events_with_agentmessage_types() {
         transform_context: None,
         get_steering_messages: None,
         get_follow_up_messages: None,
+        abort_flag: None,
+        output_filter: None,
+        limits: LoopLimits::default(),
+        tool_update_throttle: None,
     };
 
     let mut stream_fn: Box<pi::agent::StreamFn> =
@@ -87,6 +94,10 @@ fn should_handle_custom_message_types_via_converttollm() {
         transform_context: None,
         get_steering_messages: None,
         get_follow_up_messages: None,
+        abort_flag: None,
+        output_filter: None,
+        limits: LoopLimits::default(),
+        tool_update_throttle: None,
     };
 
     let mut stream_fn: Box<pi::agent::StreamFn> =
@@ -168,6 +179,10 @@ fn should_apply_transformcontext_before_converttollm() {
         }),
         get_steering_messages: None,
         get_follow_up_messages: None,
+        abort_flag: None,
+        output_filter: None,
+        limits: LoopLimits::default(),
+        tool_update_throttle: None,
     };
 
     let mut stream_fn: Box<pi::agent::StreamFn> =
@@ -195,7 +210,8 @@ fn should_handle_tool_calls_and_results() {
         name: "echo".to_string(),
         label: "Echo".to_string(),
         description: "Echo tool".to_string(),
-        execute: Rc::new(move |_tool_call_id, params| {
+        parameters: None,
+        execute: Rc::new(move |_tool_call_id, params, _on_update| {
             let value = params
                 .get("value")
                 .and_then(|v| v.as_str())
@@ -226,6 +242,10 @@ fn should_handle_tool_calls_and_results() {
         transform_context: None,
         get_steering_messages: None,
         get_follow_up_messages: None,
+        abort_flag: None,
+        output_filter: None,
+        limits: LoopLimits::default(),
+        tool_update_throttle: None,
     };
 
     let call_index = Rc::new(Cell::new(0));
@@ -284,7 +304,8 @@ fn should_inject_queued_messages_and_skip_remaining_tool_calls() {
         name: "echo".to_string(),
         label: "Echo".to_string(),
         description: "Echo tool".to_string(),
-        execute: Rc::new(move |_tool_call_id, params| {
+        parameters: None,
+        execute: Rc::new(move |_tool_call_id, params, _on_update| {
             let value = params
                 .get("value")
                 .and_then(|v| v.as_str())
@@ -329,6 +350,10 @@ fn should_inject_queued_messages_and_skip_remaining_tool_calls() {
             }
         })),
         get_follow_up_messages: None,
+        abort_flag: None,
+        output_filter: None,
+        limits: LoopLimits::default(),
+        tool_update_throttle: None,
     };
 
     let call_index_ref = call_index.clone();
@@ -405,6 +430,258 @@ fn should_inject_queued_messages_and_skip_remaining_tool_calls() {
     assert!(saw_interrupt_in_context.get());
 }
 
+#[test]
+fn should_skip_remaining_tool_calls_and_stop_after_abort() {
+    let abort_flag = CancellationToken::new();
+    let executed: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(Vec::new()));
+    let executed_ref = executed.clone();
+    let abort_from_tool = abort_flag.clone();
+    let tool = AgentTool {
+        name: "echo".to_string(),
+        label: "Echo".to_string(),
+        description: "Echo tool".to_string(),
+        parameters: None,
+        execute: Rc::new(move |_tool_call_id, params, _on_update| {
+            let value = params
+                .get("value")
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string();
+            executed_ref.borrow_mut().push(value.clone());
+            abort_from_tool.cancel();
+            Ok(AgentToolResult {
+                content: vec![ContentBlock::Text {
+                    text: format!("ok:{value}"),
+                    text_signature: None,
+                }],
+                details: json!({ "value": value }),
+            })
+        }),
+    };
+
+    let context = AgentContext {
+        system_prompt: String::new(),
+        messages: Vec::new(),
+        tools: vec![tool],
+    };
+
+    let config = AgentLoopConfig {
+        model: create_model(),
+        convert_to_llm: Box::new(identity_converter),
+        transform_context: None,
+        get_steering_messages: None,
+        get_follow_up_messages: None,
+        abort_flag: Some(abort_flag.clone()),
+        output_filter: None,
+        limits: LoopLimits::default(),
+        tool_update_throttle: None,
+    };
+
+    let calls = Rc::new(Cell::new(0));
+    let calls_ref = calls.clone();
+    let mut stream_fn: Box<pi::agent::StreamFn> =
+        Box::new(move |_model: &Model, _ctx: &LlmContext, _events| {
+            calls_ref.set(calls_ref.get() + 1);
+            create_assistant_message(
+                vec![
+                    ContentBlock::ToolCall {
+                        id: "tool-1".to_string(),
+                        name: "echo".to_string(),
+                        arguments: json!({ "value": "first" }),
+                        thought_signature: None,
+                    },
+                    ContentBlock::ToolCall {
+                        id: "tool-2".to_string(),
+                        name: "echo".to_string(),
+                        arguments: json!({ "value": "second" }),
+                        thought_signature: None,
+                    },
+                ],
+                "toolUse",
+            )
+        });
+
+    let stream = agent_loop(
+        vec![create_user_message("start")],
+        context,
+        config,
+        &mut stream_fn,
+    );
+
+    assert_eq!(executed.borrow().as_slice(), ["first"]);
+    assert_eq!(calls.get(), 1);
+    let skipped = stream.result().iter().find_map(|message| match message {
+        AgentMessage::ToolResult(result) if result.tool_call_id == "tool-2" => Some(result),
+        _ => None,
+    });
+    let skipped = skipped.expect("second tool call should be recorded as skipped");
+    assert!(skipped.is_error);
+    assert!(matches!(
+        skipped.content.first(),
+        Some(ContentBlock::Text { text, .. }) if text == "Tool execution aborted."
+    ));
+    assert!(matches!(
+        stream.events().last(),
+        Some(AgentEvent::AgentEnd { .. })
+    ));
+}
+
+#[test]
+fn should_mark_streamed_response_as_aborted() {
+    let abort_flag = CancellationToken::new();
+    let executed = Rc::new(Cell::new(false));
+    let executed_ref = executed.clone();
+    let tool = AgentTool {
+        name: "echo".to_string(),
+        label: "Echo".to_string(),
+        description: "Echo tool".to_string(),
+        parameters: None,
+        execute: Rc::new(move |_tool_call_id, _params, _on_update| {
+            executed_ref.set(true);
+            Ok(AgentToolResult {
+                content: Vec::new(),
+                details: json!(null),
+            })
+        }),
+    };
+    let context = AgentContext {
+        system_prompt: String::new(),
+        messages: Vec::new(),
+        tools: vec![tool],
+    };
+    let config = AgentLoopConfig {
+        model: create_model(),
+        convert_to_llm: Box::new(identity_converter),
+        transform_context: None,
+        get_steering_messages: None,
+        get_follow_up_messages: None,
+        abort_flag: Some(abort_flag.clone()),
+        output_filter: None,
+        limits: LoopLimits::default(),
+        tool_update_throttle: None,
+    };
+
+    let abort_in_stream = abort_flag.clone();
+    let mut stream_fn: Box<pi::agent::StreamFn> =
+        Box::new(move |_model: &Model, _ctx: &LlmContext, events| {
+            assert!(!events.is_aborted());
+            abort_in_stream.cancel();
+            assert!(events.is_aborted());
+            create_assistant_message(
+                vec![
+                    ContentBlock::Text {
+                        text: "partial".to_string(),
+                        text_signature: None,
+                    },
+                    ContentBlock::ToolCall {
+                        id: "tool-1".to_string(),
+                        name: "echo".to_string(),
+                        arguments: json!({}),
+                        thought_signature: None,
+                    },
+                ],
+                "toolUse",
+            )
+        });
+
+    let stream = agent_loop(
+        vec![create_user_message("start")],
+        context,
+        config,
+        &mut stream_fn,
+    );
+
+    assert!(!executed.get());
+    let Some(AgentMessage::Assistant(message)) = stream.result().last() else {
+        panic!("expected assistant message");
+    };
+    assert_eq!(message.stop_reason, "aborted");
+    assert_eq!(
+        message.error_message.as_deref(),
+        Some("Request was aborted")
+    );
+}
+
+#[test]
+fn should_keep_partial_content_and_emit_run_aborted_when_connection_drops() {
+    let abort_flag = CancellationToken::new();
+    let context = AgentContext {
+        system_prompt: String::new(),
+        messages: Vec::new(),
+        tools: Vec::new(),
+    };
+    let config = AgentLoopConfig {
+        model: create_model(),
+        convert_to_llm: Box::new(identity_converter),
+        transform_context: None,
+        get_steering_messages: None,
+        get_follow_up_messages: None,
+        abort_flag: Some(abort_flag.clone()),
+        output_filter: None,
+        limits: LoopLimits::default(),
+        tool_update_throttle: None,
+    };
+
+    let abort_in_stream = abort_flag.clone();
+    let mut stream_fn: Box<pi::agent::StreamFn> =
+        Box::new(move |_model: &Model, _ctx: &LlmContext, events| {
+            let partial = create_assistant_message(
+                vec![ContentBlock::Text {
+                    text: "Hello".to_string(),
+                    text_signature: None,
+                }],
+                "stop",
+            );
+            events.emit(AssistantMessageEvent::TextDelta {
+                content_index: 0,
+                delta: "Hello".to_string(),
+                partial,
+            });
+            abort_in_stream.cancel();
+            // The provider surfaces the closed connection as a plain error with no content.
+            let mut failed = create_assistant_message(Vec::new(), "error");
+            failed.error_message = Some("connection reset".to_string());
+            events.emit(AssistantMessageEvent::Error {
+                message: failed.clone(),
+            });
+            failed
+        });
+
+    let stream = agent_loop(
+        vec![create_user_message("start")],
+        context,
+        config,
+        &mut stream_fn,
+    );
+
+    let Some(AgentMessage::Assistant(message)) = stream.result().last() else {
+        panic!("expected assistant message");
+    };
+    assert!(message.is_aborted());
+    assert_eq!(
+        message.content,
+        vec![ContentBlock::Text {
+            text: "Hello".to_string(),
+            text_signature: None,
+        }]
+    );
+    assert_eq!(message.error_message.as_deref(), Some("connection reset"));
+
+    let kinds = stream
+        .events()
+        .iter()
+        .map(AgentEvent::kind)
+        .collect::<Vec<_>>();
+    assert_eq!(kinds[kinds.len() - 2..], ["run_aborted", "agent_end"]);
+    let Some(AgentEvent::RunAborted {
+        message: AgentMessage::Assistant(aborted),
+    }) = stream.events().iter().rev().nth(1)
+    else {
+        panic!("expected run_aborted event");
+    };
+    assert_eq!(aborted, message);
+}
+
 #[test]
 fn should_throw_when_context_has_no_messages() {
     let context = AgentContext {
@@ -419,6 +696,10 @@ fn should_throw_when_context_has_no_messages() {
         transform_context: None,
         get_steering_messages: None,
         get_follow_up_messages: None,
+        abort_flag: None,
+        output_filter: None,
+        limits: LoopLimits::default(),
+        tool_update_throttle: None,
     };
 
     let mut stream_fn: Box<pi::agent::StreamFn> =
@@ -451,6 +732,10 @@ fn should_continue_from_existing_context_without_emitting_user_message_events()
         transform_context: None,
         get_steering_messages: None,
         get_follow_up_messages: None,
+        abort_flag: None,
+        output_filter: None,
+        limits: LoopLimits::default(),
+        tool_update_throttle: None,
     };
 
     let mut stream_fn: Box<pi::agent::StreamFn> =
@@ -523,6 +808,10 @@ fn should_allow_custom_message_types_as_last_message_caller_responsibility() {
         transform_context: None,
         get_steering_messages: None,
         get_follow_up_messages: None,
+        abort_flag: None,
+        output_filter: None,
+        limits: LoopLimits::default(),
+        tool_update_throttle: None,
     };
 
     let mut stream_fn: Box<pi::agent::StreamFn> =
@@ -543,6 +832,85 @@ fn should_allow_custom_message_types_as_last_message_caller_responsibility() {
     assert!(matches!(messages[0], AgentMessage::Assistant(_)));
 }
 
+#[test]
+fn should_apply_output_filter_to_streamed_and_final_messages() {
+    let context = AgentContext {
+        system_prompt: String::new(),
+        messages: Vec::new(),
+        tools: Vec::new(),
+    };
+    let partial_flags = Rc::new(RefCell::new(Vec::new()));
+    let partial_flags_ref = partial_flags.clone();
+    let config = AgentLoopConfig {
+        model: create_model(),
+        convert_to_llm: Box::new(identity_converter),
+        transform_context: None,
+        get_steering_messages: None,
+        get_follow_up_messages: None,
+        abort_flag: None,
+        output_filter: Some(Rc::new(move |mut message: AssistantMessage, partial| {
+            partial_flags_ref.borrow_mut().push(partial);
+            for block in &mut message.content {
+                if let ContentBlock::Text { text, .. } = block {
+                    *text = text.replace("hunter2", "[REDACTED]");
+                }
+            }
+            message
+        })),
+        limits: LoopLimits::default(),
+        tool_update_throttle: None,
+    };
+
+    let mut stream_fn: Box<pi::agent::StreamFn> =
+        Box::new(|_model: &Model, _ctx: &LlmContext, events| {
+            let message = create_assistant_message(
+                vec![ContentBlock::Text {
+                    text: "the password is hunter2".to_string(),
+                    text_signature: None,
+                }],
+                "stop",
+            );
+            events.emit(AssistantMessageEvent::TextDelta {
+                delta: "the password is hunter2".to_string(),
+                partial: message.clone(),
+                content_index: 0,
+            });
+            message
+        });
+
+    let stream = agent_loop(
+        vec![create_user_message("secret?")],
+        context,
+        config,
+        &mut stream_fn,
+    );
+
+    for event in stream.events() {
+        if let AgentEvent::MessageStart { message }
+        | AgentEvent::MessageUpdate { message, .. }
+        | AgentEvent::MessageEnd { message } = event
+        {
+            if let AgentMessage::Assistant(assistant) = message {
+                assert_eq!(
+                    assistant.content,
+                    vec![ContentBlock::Text {
+                        text: "the password is [REDACTED]".to_string(),
+                        text_signature: None,
+                    }]
+                );
+            }
+        }
+    }
+    let Some(AgentMessage::Assistant(message)) = stream.result().last() else {
+        panic!("expected assistant message");
+    };
+    assert!(matches!(
+        &message.content[0],
+        ContentBlock::Text { text, .. } if text == "the password is [REDACTED]"
+    ));
+    assert_eq!(*partial_flags.borrow(), vec![true, false]);
+}
+
 fn create_usage() -> Usage {
     Usage {
         input: 0,
@@ -609,3 +977,297 @@ fn now_millis() -> i64 {
         .map(|duration| duration.as_millis() as i64)
         .unwrap_or(0)
 }
+
+#[test]
+fn should_throttle_tool_updates_and_keep_the_final_result() {
+    let tool = AgentTool {
+        name: "stream".to_string(),
+        label: "Stream".to_string(),
+        description: "Streams lines".to_string(),
+        parameters: None,
+        execute: Rc::new(|_tool_call_id, _params, on_update| {
+            let mut output = String::new();
+            for line in 0..50 {
+                output.push_str(&format!("line {line}\n"));
+                on_update(AgentToolResult {
+                    content: vec![ContentBlock::Text {
+                        text: output.clone(),
+                        text_signature: None,
+                    }],
+                    details: json!({}),
+                });
+            }
+            Ok(AgentToolResult {
+                content: vec![ContentBlock::Text {
+                    text: output,
+                    text_signature: None,
+                }],
+                details: json!({}),
+            })
+        }),
+    };
+    let context = AgentContext {
+        system_prompt: String::new(),
+        messages: Vec::new(),
+        tools: vec![tool],
+    };
+    let config = AgentLoopConfig {
+        model: create_model(),
+        convert_to_llm: Box::new(identity_converter),
+        transform_context: None,
+        get_steering_messages: None,
+        get_follow_up_messages: None,
+        abort_flag: None,
+        output_filter: None,
+        limits: LoopLimits::default(),
+        tool_update_throttle: Some(ToolUpdateThrottle {
+            interval: Duration::from_secs(60),
+            max_bytes: 16,
+        }),
+    };
+
+    let call_index = Rc::new(Cell::new(0));
+    let mut stream_fn: Box<pi::agent::StreamFn> =
+        Box::new(move |_model: &Model, _ctx: &LlmContext, _events| {
+            let index = call_index.get();
+            call_index.set(index + 1);
+            if index == 0 {
+                create_assistant_message(
+                    vec![ContentBlock::ToolCall {
+                        id: "tool-1".to_string(),
+                        name: "stream".to_string(),
+                        arguments: json!({}),
+                        thought_signature: None,
+                    }],
+                    "toolUse",
+                )
+            } else {
+                create_assistant_message(Vec::new(), "stop")
+            }
+        });
+
+    let stream = agent_loop(
+        vec![create_user_message("stream")],
+        context,
+        config,
+        &mut stream_fn,
+    );
+
+    let updates = stream
+        .events()
+        .iter()
+        .filter_map(|event| match event {
+            AgentEvent::ToolExecutionUpdate { partial_result, .. } => Some(partial_result),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(updates.len(), 1);
+    assert_eq!(
+        updates[0].content,
+        vec![ContentBlock::Text {
+            text: "line 0\n".to_string(),
+            text_signature: None,
+        }]
+    );
+
+    let final_text = stream.events().iter().find_map(|event| match event {
+        AgentEvent::ToolExecutionEnd { result, .. } => match &result.content[0] {
+            ContentBlock::Text { text, .. } => Some(text.clone()),
+            _ => None,
+        },
+        _ => None,
+    });
+    let final_text = final_text.unwrap();
+    assert!(final_text.starts_with("line 0\n"));
+    assert!(final_text.ends_with("line 49\n"));
+}
+
+#[test]
+fn tool_update_throttle_keeps_the_tail_of_large_updates() {
+    let throttle = ToolUpdateThrottle {
+        interval: Duration::ZERO,
+        max_bytes: 8,
+    };
+    let limited = throttle.limit(AgentToolResult {
+        content: vec![ContentBlock::Text {
+            text: "first line\nlast line".to_string(),
+            text_signature: None,
+        }],
+        details: json!({}),
+    });
+    assert_eq!(
+        limited.content,
+        vec![ContentBlock::Text {
+            text: "...ast line".to_string(),
+            text_signature: None,
+        }]
+    );
+}
+
+#[test]
+fn max_turns_stops_a_run_that_keeps_calling_tools() {
+    let tool = AgentTool {
+        name: "echo".to_string(),
+        label: "Echo".to_string(),
+        description: "Echo tool".to_string(),
+        parameters: None,
+        execute: Rc::new(|_tool_call_id, _params, _on_update| {
+            Ok(AgentToolResult {
+                content: vec![ContentBlock::Text {
+                    text: "ok".to_string(),
+                    text_signature: None,
+                }],
+                details: json!({}),
+            })
+        }),
+    };
+    let context = AgentContext {
+        system_prompt: String::new(),
+        messages: Vec::new(),
+        tools: vec![tool],
+    };
+    let config = AgentLoopConfig {
+        model: create_model(),
+        convert_to_llm: Box::new(identity_converter),
+        transform_context: None,
+        get_steering_messages: None,
+        get_follow_up_messages: None,
+        abort_flag: None,
+        output_filter: None,
+        tool_update_throttle: None,
+        limits: LoopLimits {
+            max_turns: Some(3),
+            max_duration: None,
+        },
+    };
+
+    let calls = Rc::new(Cell::new(0));
+    let calls_ref = calls.clone();
+    let mut stream_fn: Box<pi::agent::StreamFn> =
+        Box::new(move |_model: &Model, _ctx: &LlmContext, _events| {
+            calls_ref.set(calls_ref.get() + 1);
+            create_assistant_message(
+                vec![ContentBlock::ToolCall {
+                    id: format!("tool-{}", calls_ref.get()),
+                    name: "echo".to_string(),
+                    arguments: json!({}),
+                    thought_signature: None,
+                }],
+                "toolUse",
+            )
+        });
+
+    let stream = agent_loop(
+        vec![create_user_message("loop")],
+        context,
+        config,
+        &mut stream_fn,
+    );
+
+    assert_eq!(calls.get(), 3);
+    let kinds = stream
+        .events()
+        .iter()
+        .map(AgentEvent::kind)
+        .collect::<Vec<_>>();
+    assert_eq!(kinds[kinds.len() - 2..], ["limit_reached", "agent_end"]);
+    let Some(AgentEvent::LimitReached { reason, .. }) = stream.events().iter().rev().nth(1) else {
+        panic!("expected limit_reached event");
+    };
+    assert_eq!(reason, "Turn limit reached (3 turns)");
+    // The last tool call still got its result, so the conversation can be resumed.
+    assert!(matches!(
+        stream.result().last(),
+        Some(AgentMessage::ToolResult(_))
+    ));
+}
+
+#[test]
+fn max_duration_cancels_a_tool_that_blocks_past_it() {
+    let cancel = CancellationToken::new();
+    let tool_cancel = cancel.clone();
+    let tool = AgentTool {
+        name: "wait".to_string(),
+        label: "Wait".to_string(),
+        description: "Blocks until cancelled".to_string(),
+        parameters: None,
+        execute: Rc::new(move |_tool_call_id, _params, _on_update| {
+            let started = Instant::now();
+            while !tool_cancel.is_cancelled() && started.elapsed() < Duration::from_secs(30) {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            Err("cancelled".into())
+        }),
+    };
+    let context = AgentContext {
+        system_prompt: String::new(),
+        messages: Vec::new(),
+        tools: vec![tool],
+    };
+    let config = AgentLoopConfig {
+        model: create_model(),
+        convert_to_llm: Box::new(identity_converter),
+        transform_context: None,
+        get_steering_messages: None,
+        get_follow_up_messages: None,
+        abort_flag: Some(cancel.clone()),
+        output_filter: None,
+        tool_update_throttle: None,
+        limits: LoopLimits {
+            max_turns: None,
+            max_duration: Some(Duration::from_secs(1)),
+        },
+    };
+
+    let mut stream_fn: Box<pi::agent::StreamFn> =
+        Box::new(|_model: &Model, _ctx: &LlmContext, _events| {
+            create_assistant_message(
+                vec![ContentBlock::ToolCall {
+                    id: "tool-1".to_string(),
+                    name: "wait".to_string(),
+                    arguments: json!({}),
+                    thought_signature: None,
+                }],
+                "toolUse",
+            )
+        });
+
+    let started = Instant::now();
+    let stream = agent_loop(
+        vec![create_user_message("wait")],
+        context,
+        config,
+        &mut stream_fn,
+    );
+
+    assert!(started.elapsed() < Duration::from_secs(10));
+    assert!(cancel.is_cancelled());
+    let kinds = stream
+        .events()
+        .iter()
+        .map(AgentEvent::kind)
+        .collect::<Vec<_>>();
+    assert_eq!(kinds[kinds.len() - 2..], ["limit_reached", "agent_end"]);
+    let Some(AgentEvent::LimitReached { reason, .. }) = stream.events().iter().rev().nth(1) else {
+        panic!("expected limit_reached event");
+    };
+    assert_eq!(reason, "Time limit reached (1s)");
+}
+
+#[test]
+fn loop_limits_report_turns_before_duration() {
+    let limits = LoopLimits {
+        max_turns: Some(5),
+        max_duration: Some(Duration::from_secs(60)),
+    };
+    assert_eq!(limits.exceeded(4, Duration::from_secs(59)), None);
+    assert_eq!(
+        limits.exceeded(4, Duration::from_secs(60)).as_deref(),
+        Some("Time limit reached (60s)")
+    );
+    assert_eq!(
+        limits.exceeded(5, Duration::from_secs(60)).as_deref(),
+        Some("Turn limit reached (5 turns)")
+    );
+    assert_eq!(LoopLimits::default().exceeded(1000, Duration::MAX), None);
+}
@@ -1,9 +1,12 @@
 use std::cell::Cell;
 use std::rc::Rc;
+use std::thread;
 
 use pi::agent::{
-    get_model, Agent, AgentError, AgentOptions, AgentStateOverride, AgentTool, ThinkingLevel,
+    get_model, Agent, AgentError, AgentEvent, AgentOptions, AgentStateOverride, AgentTool,
+    QueueKind, QueuePriority, ThinkingLevel,
 };
+use pi::ai::AssistantMessageEvent;
 use pi::{AssistantMessage, ContentBlock, Cost, Usage, UserContent, UserMessage};
 use serde_json::Value;
 
@@ -67,6 +70,65 @@ fn should_subscribe_to_events() {
     assert_eq!(event_count.get(), 0);
 }
 
+#[test]
+fn should_deliver_stream_updates_to_listeners_before_prompt_returns() {
+    let seen_update = Rc::new(Cell::new(false));
+    let seen_during_stream = Rc::new(Cell::new(false));
+    let seen_update_ref = seen_update.clone();
+    let seen_during_stream_ref = seen_during_stream.clone();
+    let agent = Agent::new(AgentOptions {
+        stream_fn: Some(Box::new(move |_model, _ctx, events| {
+            events.emit(AssistantMessageEvent::TextDelta {
+                delta: "Hel".to_string(),
+                partial: assistant_message("Hel"),
+                content_index: 0,
+            });
+            seen_during_stream_ref.set(seen_update_ref.get());
+            assistant_message("Hello")
+        })),
+        ..AgentOptions::default()
+    });
+
+    let seen_update_ref = seen_update.clone();
+    let _unsubscribe = agent.subscribe(move |event| {
+        if matches!(event, AgentEvent::MessageUpdate { .. }) {
+            seen_update_ref.set(true);
+        }
+    });
+
+    agent.prompt("Hi").expect("prompt");
+    assert!(seen_during_stream.get());
+}
+
+#[test]
+fn should_deliver_events_to_channel_subscribers_on_other_threads() {
+    let agent = Agent::new(AgentOptions {
+        stream_fn: Some(Box::new(|_model, _ctx, _events| assistant_message("Hello"))),
+        ..AgentOptions::default()
+    });
+    let events = agent.subscribe_channel();
+    let dropped = agent.subscribe_channel();
+    drop(dropped);
+
+    let consumer = thread::spawn(move || {
+        let mut names = Vec::new();
+        while let Ok(event) = events.recv() {
+            let done = matches!(event, AgentEvent::AgentEnd { .. });
+            names.push(event.kind());
+            if done {
+                break;
+            }
+        }
+        names
+    });
+    agent.prompt("Hi").expect("prompt");
+
+    let names = consumer.join().unwrap();
+    assert_eq!(names.first(), Some(&"agent_start"));
+    assert!(names.contains(&"message_end"));
+    assert_eq!(names.last(), Some(&"agent_end"));
+}
+
 #[test]
 fn should_update_state_with_mutators() {
     let agent = Agent::new(AgentOptions::default());
@@ -85,7 +147,8 @@ fn should_update_state_with_mutators() {
         name: "test".to_string(),
         label: "Test".to_string(),
         description: "test tool".to_string(),
-        execute: Rc::new(|_id, _params| {
+        parameters: None,
+        execute: Rc::new(|_id, _params, _on_update| {
             Ok(pi::agent::AgentToolResult {
                 content: vec![ContentBlock::Text {
                     text: "ok".to_string(),
@@ -140,6 +203,139 @@ fn should_support_follow_up_message_queue() {
     assert!(agent.state().messages.is_empty());
 }
 
+fn user_text(text: &str) -> pi::agent::AgentMessage {
+    pi::agent::AgentMessage::User(UserMessage {
+        content: UserContent::Text(text.to_string()),
+        timestamp: now_millis(),
+    })
+}
+
+fn queued_conversation(priority: QueuePriority) -> (Vec<String>, Vec<(QueueKind, String)>) {
+    let agent = Agent::new(AgentOptions {
+        queue_priority: Some(priority),
+        stream_fn: Some(Box::new(|_model, _ctx, _events| assistant_message("ok"))),
+        ..AgentOptions::default()
+    });
+    agent.follow_up(user_text("F1"));
+    agent.steer(user_text("S1"));
+    agent.follow_up(user_text("F2"));
+    let pending = agent
+        .pending_queue()
+        .into_iter()
+        .map(|pending| match pending.message {
+            pi::agent::AgentMessage::User(UserMessage {
+                content: UserContent::Text(text),
+                ..
+            }) => (pending.kind, text),
+            other => panic!("unexpected queued message: {other:?}"),
+        })
+        .collect();
+
+    agent.prompt("P").expect("prompt");
+    let order = agent
+        .state()
+        .messages
+        .into_iter()
+        .filter_map(|message| match message {
+            pi::agent::AgentMessage::User(UserMessage {
+                content: UserContent::Text(text),
+                ..
+            }) => Some(text),
+            pi::agent::AgentMessage::Assistant(_) => Some("A".to_string()),
+            _ => None,
+        })
+        .collect();
+    (order, pending)
+}
+
+#[test]
+fn steering_first_priority_delivers_steering_before_follow_ups() {
+    let (order, pending) = queued_conversation(QueuePriority::SteeringFirst);
+    assert_eq!(
+        pending,
+        vec![
+            (QueueKind::Steering, "S1".to_string()),
+            (QueueKind::FollowUp, "F1".to_string()),
+            (QueueKind::FollowUp, "F2".to_string()),
+        ]
+    );
+    assert_eq!(order, vec!["P", "S1", "A", "F1", "A", "F2", "A"]);
+}
+
+#[test]
+fn fifo_priority_delivers_messages_in_queue_order() {
+    let (order, pending) = queued_conversation(QueuePriority::Fifo);
+    assert_eq!(
+        pending,
+        vec![
+            (QueueKind::FollowUp, "F1".to_string()),
+            (QueueKind::Steering, "S1".to_string()),
+            (QueueKind::FollowUp, "F2".to_string()),
+        ]
+    );
+    assert_eq!(order, vec!["P", "A", "F1", "A", "S1", "A", "F2", "A"]);
+}
+
+#[test]
+fn queued_messages_can_be_removed_and_replaced_by_id() {
+    let agent = Agent::new(AgentOptions::default());
+    agent.steer(user_text("S1"));
+    agent.follow_up(user_text("F1"));
+    agent.follow_up(user_text("F2"));
+    let ids: Vec<u64> = agent
+        .pending_queue()
+        .iter()
+        .map(|pending| pending.id)
+        .collect();
+
+    let removed = agent.remove_pending(ids[1]).expect("queued");
+    assert_eq!(removed.kind, QueueKind::FollowUp);
+    assert!(agent.remove_pending(ids[1]).is_none());
+
+    assert!(agent.replace_pending(ids[2], user_text("F2 edited")));
+    let texts: Vec<String> = agent
+        .pending_queue()
+        .iter()
+        .filter_map(|pending| pending.message.user_text().map(str::to_string))
+        .collect();
+    assert_eq!(texts, vec!["S1", "F2 edited"]);
+    assert_eq!(agent.pending_queue()[1].id, ids[2]);
+}
+
+#[test]
+fn steering_sent_from_a_listener_during_a_run_reaches_that_run() {
+    let agent = Agent::new(AgentOptions {
+        stream_fn: Some(Box::new(|_model, _ctx, _events| assistant_message("A"))),
+        ..AgentOptions::default()
+    });
+    let steer = agent.steering_sender();
+    let sent = Rc::new(Cell::new(false));
+    let _unsubscribe = agent.subscribe(move |event| {
+        let replying = matches!(
+            event,
+            AgentEvent::MessageStart {
+                message: pi::agent::AgentMessage::Assistant(_)
+            }
+        );
+        if replying && !sent.replace(true) {
+            steer(user_text("S1"));
+        }
+    });
+
+    agent.prompt("P").expect("prompt");
+    let texts: Vec<String> = agent
+        .state()
+        .messages
+        .iter()
+        .filter_map(|message| match message {
+            pi::agent::AgentMessage::Assistant(_) => Some("A".to_string()),
+            other => other.user_text().map(str::to_string),
+        })
+        .collect();
+    assert_eq!(texts, vec!["P", "A", "S1", "A"]);
+    assert!(agent.pending_queue().is_empty());
+}
+
 #[test]
 fn should_handle_abort_controller() {
     let agent = Agent::new(AgentOptions::default());
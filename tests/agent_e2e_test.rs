@@ -459,7 +459,8 @@ fn calculate_tool() -> AgentTool {
         name: "calculate".to_string(),
         label: "Calculator".to_string(),
         description: "Evaluate mathematical expressions".to_string(),
-        execute: Rc::new(|_tool_call_id, args| {
+        parameters: None,
+        execute: Rc::new(|_tool_call_id, args, _on_update| {
             let expression = args
                 .get("expression")
                 .and_then(|value| value.as_str())
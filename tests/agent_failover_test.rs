@@ -0,0 +1,111 @@
+use pi::agent::{failover_stream_fn, LlmContext, Model, StreamEvents, StreamFn};
+use pi::core::messages::{AssistantMessage, ContentBlock, Usage};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+fn message(provider: &str, stop_reason: &str, error: Option<&str>) -> AssistantMessage {
+    AssistantMessage {
+        content: vec![ContentBlock::Text {
+            text: error.unwrap_or("answer").to_string(),
+            text_signature: None,
+        }],
+        api: "openai-responses".to_string(),
+        provider: provider.to_string(),
+        model: format!("{provider}-model"),
+        usage: Usage {
+            input: 0,
+            output: 0,
+            cache_read: 0,
+            cache_write: 0,
+            total_tokens: None,
+            cost: None,
+        },
+        stop_reason: stop_reason.to_string(),
+        error_message: error.map(str::to_string),
+        timestamp: 0,
+    }
+}
+
+/// A stream function that records its call in `calls` and returns `reply`.
+fn fake(
+    calls: &Rc<RefCell<Vec<String>>>,
+    provider: &'static str,
+    reply: AssistantMessage,
+) -> Box<StreamFn> {
+    let calls = calls.clone();
+    Box::new(move |_model, _context, _events| {
+        calls.borrow_mut().push(provider.to_string());
+        reply.clone()
+    })
+}
+
+fn run(stream_fn: &mut Box<StreamFn>) -> AssistantMessage {
+    let model = Model {
+        id: "primary-model".to_string(),
+        name: "Primary".to_string(),
+        api: "openai-responses".to_string(),
+        provider: "primary".to_string(),
+    };
+    let context = LlmContext {
+        system_prompt: String::new(),
+        messages: Vec::new(),
+    };
+    let mut events = StreamEvents::new(Box::new(|_| {}));
+    stream_fn(&model, &context, &mut events)
+}
+
+#[test]
+fn retries_the_turn_on_the_next_model_after_a_provider_error() {
+    let calls = Rc::new(RefCell::new(Vec::new()));
+    let mut stream_fn = failover_stream_fn(
+        fake(
+            &calls,
+            "anthropic",
+            message("anthropic", "error", Some("429 rate_limit_error")),
+        ),
+        vec![
+            fake(&calls, "openai", message("openai", "stop", None)),
+            fake(&calls, "google", message("google", "stop", None)),
+        ],
+    );
+    let answer = run(&mut stream_fn);
+    assert_eq!(*calls.borrow(), ["anthropic", "openai"]);
+    assert_eq!(answer.provider, "openai");
+    assert_eq!(answer.model, "openai-model");
+    assert_eq!(answer.error_message, None);
+
+    // Every turn starts again from the top of the chain.
+    calls.borrow_mut().clear();
+    run(&mut stream_fn);
+    assert_eq!(*calls.borrow(), ["anthropic", "openai"]);
+}
+
+#[test]
+fn does_not_fail_over_aborted_turns_and_reports_every_failure() {
+    let calls = Rc::new(RefCell::new(Vec::new()));
+    let mut aborted = failover_stream_fn(
+        fake(&calls, "anthropic", message("anthropic", "aborted", None)),
+        vec![fake(&calls, "openai", message("openai", "stop", None))],
+    );
+    assert_eq!(run(&mut aborted).stop_reason, "aborted");
+    assert_eq!(*calls.borrow(), ["anthropic"]);
+
+    let mut failing = failover_stream_fn(
+        fake(
+            &calls,
+            "anthropic",
+            message("anthropic", "error", Some("timed out")),
+        ),
+        vec![fake(
+            &calls,
+            "openai",
+            message("openai", "error", Some("503")),
+        )],
+    );
+    let answer = run(&mut failing);
+    assert_eq!(answer.provider, "openai");
+    assert_eq!(
+        answer.error_message.as_deref(),
+        Some("All models failed. anthropic/anthropic-model: timed out; openai/openai-model: 503")
+    );
+}
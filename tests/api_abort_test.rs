@@ -0,0 +1,299 @@
+use base64::Engine;
+use pi::agent::{AgentMessage, CancellationToken, LlmContext, StreamEvents};
+use pi::ai::AssistantMessageEvent;
+use pi::api::google_gemini_cli::{stream_google_gemini_cli, GeminiCliCallOptions};
+use pi::api::openai_codex::{stream_openai_codex_responses, CodexStreamOptions};
+use pi::api::{
+    build_anthropic_messages, openai_context_to_input_items, stream_anthropic,
+    stream_openai_responses, AnthropicCallOptions, OpenAICallOptions,
+};
+use pi::coding_agent::Model;
+use pi::{AssistantMessage, ContentBlock, Cost, UserContent, UserMessage, STOP_REASON_ABORTED};
+use std::cell::RefCell;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Accept one request and stream `first` as SSE, then hold the connection open before
+/// sending `rest`. The client should abort in between.
+fn serve_sse(first: &'static str, rest: &'static str) -> (String, thread::JoinHandle<()>) {
+    serve_sse_stalling(first, rest, Duration::from_millis(300))
+}
+
+fn serve_sse_stalling(
+    first: &'static str,
+    rest: &'static str,
+    stall: Duration,
+) -> (String, thread::JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://127.0.0.1:{}", listener.local_addr().unwrap().port());
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).unwrap();
+
+        let mut stream = stream;
+        let _ = write!(
+            stream,
+            "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n{first}"
+        );
+        let _ = stream.flush();
+        thread::sleep(stall);
+        let _ = stream.write_all(rest.as_bytes());
+    });
+    (base_url, server)
+}
+
+fn test_model(api: &str, provider: &str, base_url: &str) -> Model {
+    Model {
+        id: "test-model".to_string(),
+        name: "Test Model".to_string(),
+        api: api.to_string(),
+        provider: provider.to_string(),
+        base_url: base_url.to_string(),
+        reasoning: false,
+        input: vec!["text".to_string()],
+        cost: Cost {
+            input: 0.0,
+            output: 0.0,
+            cache_read: 0.0,
+            cache_write: 0.0,
+            total: 0.0,
+        },
+        context_window: 128_000,
+        max_tokens: 4096,
+        headers: None,
+    }
+}
+
+fn test_context() -> LlmContext {
+    LlmContext {
+        system_prompt: "Be brief.".to_string(),
+        messages: vec![AgentMessage::User(UserMessage {
+            content: UserContent::Text("Say hello".to_string()),
+            timestamp: 0,
+        })],
+    }
+}
+
+/// Events that abort the run on the first text delta and record the final event.
+fn aborting_events() -> (StreamEvents, Rc<RefCell<Option<AssistantMessageEvent>>>) {
+    let abort_flag = CancellationToken::new();
+    let last_event = Rc::new(RefCell::new(None));
+    let flag = abort_flag.clone();
+    let last = last_event.clone();
+    let events = StreamEvents::new(Box::new(move |event| {
+        if matches!(event, AssistantMessageEvent::TextDelta { .. }) {
+            flag.cancel();
+        }
+        last.replace(Some(event));
+    }))
+    .with_abort_flag(Some(abort_flag));
+    (events, last_event)
+}
+
+fn assert_aborted_with_partial(
+    message: AssistantMessage,
+    last_event: &RefCell<Option<AssistantMessageEvent>>,
+) {
+    assert_eq!(message.stop_reason, STOP_REASON_ABORTED);
+    assert_eq!(
+        message.error_message.as_deref(),
+        Some("Request was aborted")
+    );
+    let text = message
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text { text, .. } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<String>();
+    assert_eq!(text, "Hello");
+    match last_event.borrow().as_ref() {
+        Some(AssistantMessageEvent::Error { message: last }) => assert_eq!(last, &message),
+        other => panic!("expected a final error event, got {other:?}"),
+    }
+}
+
+#[test]
+fn anthropic_stream_abort_keeps_partial_content() {
+    let (base_url, server) = serve_sse(
+        "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{}}\n\n\
+         event: content_block_start\ndata: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n\n\
+         event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hello\"}}\n\n",
+        "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\" world\"}}\n\n\
+         event: message_delta\ndata: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"end_turn\"}}\n\n",
+    );
+    let model = test_model("anthropic-messages", "anthropic", &base_url);
+    let (mut events, last_event) = aborting_events();
+
+    let message = stream_anthropic(
+        &model,
+        build_anthropic_messages(&test_context()),
+        AnthropicCallOptions {
+            model: &model.id,
+            api_key: "test-key",
+            use_oauth: false,
+            tools: &[],
+            base_url: &base_url,
+            extra_headers: None,
+            system: Some("Be brief."),
+            request_hook: None,
+        },
+        &mut events,
+    )
+    .unwrap();
+
+    assert_aborted_with_partial(message, &last_event);
+    server.join().unwrap();
+}
+
+#[test]
+fn abort_from_another_thread_interrupts_a_stalled_stream() {
+    let (base_url, _server) = serve_sse_stalling(
+        "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{}}\n\n\
+         event: content_block_start\ndata: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n\n\
+         event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hello\"}}\n\n",
+        "event: message_delta\ndata: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"end_turn\"}}\n\n",
+        Duration::from_secs(10),
+    );
+    let model = test_model("anthropic-messages", "anthropic", &base_url);
+    let abort_flag = CancellationToken::new();
+    let mut events = StreamEvents::new(Box::new(|_| {})).with_abort_flag(Some(abort_flag.clone()));
+    let canceller = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(200));
+        abort_flag.cancel();
+    });
+    let started = Instant::now();
+
+    let message = stream_anthropic(
+        &model,
+        build_anthropic_messages(&test_context()),
+        AnthropicCallOptions {
+            model: &model.id,
+            api_key: "test-key",
+            use_oauth: false,
+            tools: &[],
+            base_url: &base_url,
+            extra_headers: None,
+            system: Some("Be brief."),
+            request_hook: None,
+        },
+        &mut events,
+    )
+    .unwrap();
+
+    assert!(started.elapsed() < Duration::from_secs(2));
+    assert_eq!(message.stop_reason, STOP_REASON_ABORTED);
+    assert!(matches!(
+        message.content.as_slice(),
+        [ContentBlock::Text { text, .. }] if text == "Hello"
+    ));
+    canceller.join().unwrap();
+}
+
+#[test]
+fn openai_stream_abort_keeps_partial_content() {
+    let (base_url, server) = serve_sse(
+        "event: response.output_item.added\ndata: {\"item\":{\"type\":\"message\"}}\n\n\
+         event: response.output_text.delta\ndata: {\"delta\":\"Hello\"}\n\n",
+        "event: response.output_text.delta\ndata: {\"delta\":\" world\"}\n\n",
+    );
+    let model = test_model("openai-responses", "openai", &base_url);
+    let (mut events, last_event) = aborting_events();
+
+    let message = stream_openai_responses(
+        &model,
+        openai_context_to_input_items(&model, &test_context()),
+        OpenAICallOptions {
+            model: &model.id,
+            api_key: "test-key",
+            tools: &[],
+            base_url: &base_url,
+            extra_headers: None,
+            seed: None,
+            request_hook: None,
+        },
+        &mut events,
+    )
+    .unwrap();
+
+    assert_aborted_with_partial(message, &last_event);
+    server.join().unwrap();
+}
+
+#[test]
+fn codex_stream_abort_keeps_partial_content() {
+    let (base_url, server) = serve_sse(
+        "event: response.output_item.added\ndata: {\"type\":\"response.output_item.added\",\"item\":{\"type\":\"message\"}}\n\n\
+         event: response.output_text.delta\ndata: {\"type\":\"response.output_text.delta\",\"delta\":\"Hello\"}\n\n",
+        "event: response.output_text.delta\ndata: {\"type\":\"response.output_text.delta\",\"delta\":\" world\"}\n\n",
+    );
+    let model = test_model("openai-codex-responses", "openai-codex", &base_url);
+    let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .encode(r#"{"https://api.openai.com/auth":{"chatgpt_account_id":"acct-test"}}"#);
+    let token = format!("header.{payload}.signature");
+    let (mut events, last_event) = aborting_events();
+
+    let message = stream_openai_codex_responses(
+        &model,
+        &test_context(),
+        &token,
+        &[],
+        CodexStreamOptions::default(),
+        &mut events,
+    )
+    .unwrap();
+
+    assert_aborted_with_partial(message, &last_event);
+    server.join().unwrap();
+}
+
+#[test]
+fn gemini_stream_abort_keeps_partial_content() {
+    let (base_url, server) = serve_sse(
+        "data: {\"response\":{\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"Hello\"}]}}]}}\n\n",
+        "data: {\"response\":{\"candidates\":[{\"content\":{\"parts\":[{\"text\":\" world\"}]}}]}}\n\n",
+    );
+    let model = test_model("google-gemini-cli", "google-gemini-cli", &base_url);
+    let (mut events, last_event) = aborting_events();
+
+    let message = stream_google_gemini_cli(
+        &model,
+        &test_context(),
+        GeminiCliCallOptions {
+            model: &model.id,
+            access_token: "test-token",
+            project_id: "test-project",
+            tools: &[],
+            base_url: &base_url,
+            system: Some("Be brief."),
+            thinking_enabled: false,
+            seed: None,
+            request_hook: None,
+        },
+        &mut events,
+    )
+    .unwrap();
+
+    assert_aborted_with_partial(message, &last_event);
+    server.join().unwrap();
+}
@@ -0,0 +1,321 @@
+mod common;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{TimeZone, Utc};
+use common::serve_once;
+use pi::agent::{AgentMessage, LlmContext, StreamEvents};
+use pi::api::bedrock::{
+    bedrock_model_id, sign_request, stream_bedrock, AwsCredentials, BedrockCallOptions,
+};
+use pi::api::request_hook::ProviderRequest;
+use pi::api::{build_anthropic_messages, AnthropicTool};
+use pi::coding_agent::Model;
+use pi::{ContentBlock, Cost, UserContent, UserMessage};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// One AWS event-stream frame with string headers.
+fn frame(headers: &[(&str, &str)], payload: &[u8]) -> Vec<u8> {
+    let mut encoded_headers = Vec::new();
+    for (name, value) in headers {
+        encoded_headers.push(name.len() as u8);
+        encoded_headers.extend_from_slice(name.as_bytes());
+        encoded_headers.push(7);
+        encoded_headers.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        encoded_headers.extend_from_slice(value.as_bytes());
+    }
+    let total = 16 + encoded_headers.len() + payload.len();
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&(total as u32).to_be_bytes());
+    bytes.extend_from_slice(&(encoded_headers.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(&crc32(&bytes).to_be_bytes());
+    bytes.extend_from_slice(&encoded_headers);
+    bytes.extend_from_slice(payload);
+    bytes.extend_from_slice(&crc32(&bytes).to_be_bytes());
+    bytes
+}
+
+fn chunk(event: Value) -> Vec<u8> {
+    let payload = json!({ "bytes": STANDARD.encode(event.to_string()) });
+    frame(
+        &[
+            (":message-type", "event"),
+            (":event-type", "chunk"),
+            (":content-type", "application/json"),
+        ],
+        payload.to_string().as_bytes(),
+    )
+}
+
+fn http_response(status: &str, content_type: &str, body: &[u8]) -> Vec<u8> {
+    let mut response =
+        format!("HTTP/1.1 {status}\r\ncontent-type: {content_type}\r\nconnection: close\r\n\r\n")
+            .into_bytes();
+    response.extend_from_slice(body);
+    response
+}
+
+fn bedrock_model(base_url: &str) -> Model {
+    Model {
+        id: "claude-sonnet-4-5".to_string(),
+        name: "Claude Sonnet 4.5".to_string(),
+        api: "bedrock".to_string(),
+        provider: "amazon-bedrock".to_string(),
+        base_url: base_url.to_string(),
+        reasoning: true,
+        input: vec!["text".to_string(), "image".to_string()],
+        cost: Cost {
+            input: 3.0,
+            output: 15.0,
+            cache_read: 0.3,
+            cache_write: 3.75,
+            total: 0.0,
+        },
+        context_window: 200_000,
+        max_tokens: 64_000,
+        headers: None,
+    }
+}
+
+fn credentials() -> AwsCredentials {
+    AwsCredentials {
+        access_key_id: "AKIDEXAMPLE".to_string(),
+        secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+        session_token: Some("session-token".to_string()),
+        region: "us-east-1".to_string(),
+    }
+}
+
+fn user_context(text: &str) -> LlmContext {
+    LlmContext {
+        system_prompt: String::new(),
+        messages: vec![AgentMessage::User(UserMessage {
+            content: UserContent::Text(text.to_string()),
+            timestamp: 0,
+        })],
+    }
+}
+
+#[test]
+fn signs_the_aws_get_vanilla_example() {
+    let mut request = ProviderRequest {
+        provider: "test".to_string(),
+        api: "bedrock".to_string(),
+        model: "test".to_string(),
+        method: "GET".to_string(),
+        url: "https://example.amazonaws.com/".to_string(),
+        headers: BTreeMap::new(),
+        body: String::new(),
+    };
+    let credentials = AwsCredentials {
+        session_token: None,
+        ..credentials()
+    };
+    let now = Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap();
+    sign_request(&mut request, &credentials, "service", now).unwrap();
+
+    assert_eq!(request.headers["x-amz-date"], "20150830T123600Z");
+    assert_eq!(
+        request.headers["authorization"],
+        "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+         SignedHeaders=host;x-amz-date, \
+         Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+    );
+}
+
+#[test]
+fn maps_anthropic_model_ids_to_bedrock() {
+    assert_eq!(
+        bedrock_model_id("claude-sonnet-4-5", "us-west-2"),
+        "us.anthropic.claude-sonnet-4-5-20250929-v1:0"
+    );
+    assert_eq!(
+        bedrock_model_id("claude-opus-4-1-20250805", "eu-central-1"),
+        "eu.anthropic.claude-opus-4-1-20250805-v1:0"
+    );
+    assert_eq!(
+        bedrock_model_id("claude-haiku-4-5", "ap-northeast-1"),
+        "apac.anthropic.claude-haiku-4-5-20251001-v1:0"
+    );
+    assert_eq!(
+        bedrock_model_id("claude-haiku-4-5", "ca-central-1"),
+        "anthropic.claude-haiku-4-5-20251001-v1:0"
+    );
+    assert_eq!(
+        bedrock_model_id("anthropic.claude-3-haiku-20240307-v1:0", "us-east-1"),
+        "anthropic.claude-3-haiku-20240307-v1:0"
+    );
+}
+
+#[test]
+fn streams_anthropic_events_from_event_stream_frames() {
+    let mut body = Vec::new();
+    for event in [
+        json!({"type": "message_start", "message": {"usage": {"input_tokens": 1000, "output_tokens": 1}}}),
+        json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+        json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Reading"}}),
+        json!({"type": "content_block_stop", "index": 0}),
+        json!({"type": "content_block_start", "index": 1, "content_block": {"type": "tool_use", "id": "toolu_1", "name": "read"}}),
+        json!({"type": "content_block_delta", "index": 1, "delta": {"type": "input_json_delta", "partial_json": "{\"path\":\"a.txt\"}"}}),
+        json!({"type": "content_block_stop", "index": 1}),
+        json!({"type": "message_delta", "delta": {"stop_reason": "tool_use"}, "usage": {"input_tokens": 1000, "output_tokens": 200}}),
+        json!({"type": "message_stop", "amazon-bedrock-invocationMetrics": {"inputTokenCount": 1000}}),
+    ] {
+        body.extend(chunk(event));
+    }
+    let (base_url, server) = serve_once(http_response(
+        "200 OK",
+        "application/vnd.amazon.eventstream",
+        &body,
+    ));
+    let model = bedrock_model(&base_url);
+    let credentials = credentials();
+    let tools = [AnthropicTool {
+        name: "read".to_string(),
+        description: "Read a file".to_string(),
+        input_schema: json!({ "type": "object" }),
+    }];
+
+    let mut events = StreamEvents::new(Box::new(|_| {}));
+    let message = stream_bedrock(
+        &model,
+        build_anthropic_messages(&user_context("Read a.txt")),
+        BedrockCallOptions {
+            model: &model.id,
+            credentials: &credentials,
+            tools: &tools,
+            base_url: &model.base_url,
+            extra_headers: None,
+            system: Some("Be brief."),
+            max_tokens: Some(1024),
+            request_hook: None,
+        },
+        &mut events,
+    )
+    .unwrap();
+
+    let (request_line, headers, body) = server.join().unwrap();
+    assert_eq!(
+        request_line,
+        "POST /model/us.anthropic.claude-sonnet-4-5-20250929-v1%3A0/invoke-with-response-stream HTTP/1.1"
+    );
+    let authorization = headers
+        .iter()
+        .find_map(|header| header.strip_prefix("authorization:"))
+        .unwrap();
+    assert!(authorization.starts_with("AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/"));
+    assert!(authorization.contains("/us-east-1/bedrock/aws4_request"));
+    assert!(authorization.contains("x-amz-security-token"));
+    assert!(headers.contains(&"x-amz-security-token:session-token".to_string()));
+    assert_eq!(body["anthropic_version"], "bedrock-2023-05-31");
+    assert_eq!(body["max_tokens"], 1024);
+    assert!(body.get("model").is_none());
+    assert_eq!(body["system"][0]["text"], "Be brief.");
+    assert_eq!(body["tools"][0]["name"], "read");
+    assert_eq!(body["messages"][0]["content"][0]["text"], "Read a.txt");
+
+    assert_eq!(message.stop_reason, "toolUse");
+    assert!(matches!(
+        &message.content[0],
+        ContentBlock::Text { text, .. } if text == "Reading"
+    ));
+    match &message.content[1] {
+        ContentBlock::ToolCall {
+            id,
+            name,
+            arguments,
+            ..
+        } => {
+            assert_eq!(id, "toolu_1");
+            assert_eq!(name, "read");
+            assert_eq!(arguments["path"], "a.txt");
+        }
+        other => panic!("expected a tool call, got {other:?}"),
+    }
+    assert_eq!(message.usage.input, 1000);
+    assert_eq!(message.usage.output, 200);
+    assert_eq!(message.api, "bedrock");
+    assert_eq!(message.provider, "amazon-bedrock");
+}
+
+fn haiku_options<'a>(base_url: &'a str, credentials: &'a AwsCredentials) -> BedrockCallOptions<'a> {
+    BedrockCallOptions {
+        model: "anthropic.claude-3-haiku-20240307-v1:0",
+        credentials,
+        tools: &[],
+        base_url,
+        extra_headers: None,
+        system: None,
+        max_tokens: None,
+        request_hook: None,
+    }
+}
+
+#[test]
+fn reports_stream_exceptions_and_http_errors() {
+    let exception = frame(
+        &[
+            (":message-type", "exception"),
+            (":exception-type", "throttlingException"),
+        ],
+        br#"{"message":"Too many requests"}"#,
+    );
+    let (base_url, server) = serve_once(http_response(
+        "200 OK",
+        "application/vnd.amazon.eventstream",
+        &exception,
+    ));
+    let model = bedrock_model(&base_url);
+    let credentials = credentials();
+    let mut events = StreamEvents::new(Box::new(|_| {}));
+    let message = stream_bedrock(
+        &model,
+        build_anthropic_messages(&user_context("Hi")),
+        haiku_options(&model.base_url, &credentials),
+        &mut events,
+    )
+    .unwrap();
+    server.join().unwrap();
+    assert_eq!(message.stop_reason, "error");
+    assert_eq!(
+        message.error_message.as_deref(),
+        Some("throttlingException: Too many requests")
+    );
+
+    let (base_url, server) = serve_once(http_response(
+        "403 Forbidden",
+        "application/json",
+        br#"{"message":"The security token included in the request is invalid."}"#,
+    ));
+    let model = bedrock_model(&base_url);
+    let mut events = StreamEvents::new(Box::new(|_| {}));
+    let err = stream_bedrock(
+        &model,
+        build_anthropic_messages(&user_context("Hi")),
+        haiku_options(&model.base_url, &credentials),
+        &mut events,
+    )
+    .unwrap_err();
+    server.join().unwrap();
+    assert_eq!(
+        err.to_string(),
+        "Bedrock error (403): The security token included in the request is invalid."
+    );
+    assert!(matches!(err, pi::Error::Auth { .. }));
+}
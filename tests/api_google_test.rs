@@ -0,0 +1,196 @@
+mod common;
+
+use common::serve_once;
+use pi::agent::{AgentMessage, LlmContext, StreamEvents};
+use pi::api::google::{stream_google_generative_ai, GoogleCallOptions};
+use pi::api::google_gemini_cli::GeminiCliTool;
+use pi::coding_agent::Model;
+use pi::{
+    AssistantMessage, ContentBlock, Cost, ToolResultMessage, Usage, UserContent, UserMessage,
+};
+use serde_json::json;
+
+fn gemini_model(base_url: &str) -> Model {
+    Model {
+        id: "gemini-test".to_string(),
+        name: "Gemini Test".to_string(),
+        api: "google-generative-ai".to_string(),
+        provider: "google".to_string(),
+        base_url: base_url.to_string(),
+        reasoning: false,
+        input: vec!["text".to_string(), "image".to_string()],
+        cost: Cost {
+            input: 1.0,
+            output: 2.0,
+            cache_read: 0.0,
+            cache_write: 0.0,
+            total: 0.0,
+        },
+        context_window: 1_000_000,
+        max_tokens: 8192,
+        headers: None,
+    }
+}
+
+fn image() -> ContentBlock {
+    ContentBlock::Image {
+        data: "aGVsbG8=".to_string(),
+        mime_type: "image/png".to_string(),
+    }
+}
+
+#[test]
+fn streams_text_and_tool_calls_with_an_api_key() {
+    let (base_url, server) = serve_once(concat!(
+        "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n",
+        "data: {\"candidates\":[{\"content\":{\"role\":\"model\",\"parts\":[{\"text\":\"Let me look\"}]}}]}\n\n",
+        "data: {\"candidates\":[{\"content\":{\"role\":\"model\",\"parts\":[{\"functionCall\":{\"name\":\"read\",\"args\":{\"path\":\"a.png\"}}}]},\"finishReason\":\"STOP\"}],",
+        "\"usageMetadata\":{\"promptTokenCount\":1000,\"candidatesTokenCount\":500,\"totalTokenCount\":1500}}\n\n",
+    ));
+    let model = gemini_model(&format!("{base_url}/v1beta"));
+    let context = LlmContext {
+        system_prompt: String::new(),
+        messages: vec![
+            AgentMessage::User(UserMessage {
+                content: UserContent::Blocks(vec![
+                    ContentBlock::Text {
+                        text: "What is this?".to_string(),
+                        text_signature: None,
+                    },
+                    image(),
+                ]),
+                timestamp: 0,
+            }),
+            AgentMessage::Assistant(AssistantMessage {
+                content: vec![ContentBlock::ToolCall {
+                    id: "call-1".to_string(),
+                    name: "read".to_string(),
+                    arguments: json!({ "path": "b.png" }),
+                    thought_signature: None,
+                }],
+                api: "google-generative-ai".to_string(),
+                provider: "google".to_string(),
+                model: "gemini-test".to_string(),
+                usage: Usage {
+                    input: 0,
+                    output: 0,
+                    cache_read: 0,
+                    cache_write: 0,
+                    total_tokens: None,
+                    cost: None,
+                },
+                stop_reason: "toolUse".to_string(),
+                error_message: None,
+                timestamp: 0,
+            }),
+            AgentMessage::ToolResult(ToolResultMessage {
+                tool_call_id: "call-1".to_string(),
+                tool_name: "read".to_string(),
+                content: vec![image()],
+                details: None,
+                is_error: false,
+                timestamp: 0,
+            }),
+        ],
+    };
+    let tools = [GeminiCliTool {
+        name: "read".to_string(),
+        description: "Read a file".to_string(),
+        parameters: json!({ "type": "object", "properties": { "path": { "type": "string" } } }),
+    }];
+
+    let mut events = StreamEvents::new(Box::new(|_| {}));
+    let message = stream_google_generative_ai(
+        &model,
+        &context,
+        GoogleCallOptions {
+            model: &model.id,
+            api_key: "gemini-key",
+            tools: &tools,
+            base_url: &model.base_url,
+            extra_headers: None,
+            system: Some("Be brief."),
+            thinking_enabled: false,
+            seed: Some(7),
+            request_hook: None,
+        },
+        &mut events,
+    )
+    .unwrap();
+
+    let (request_line, headers, body) = server.join().unwrap();
+    assert_eq!(
+        request_line,
+        "POST /v1beta/models/gemini-test:streamGenerateContent?alt=sse HTTP/1.1"
+    );
+    assert!(headers.contains(&"x-goog-api-key:gemini-key".to_string()));
+    assert!(!headers
+        .iter()
+        .any(|header| header.starts_with("authorization:")));
+    assert_eq!(body["systemInstruction"]["parts"][0]["text"], "Be brief.");
+    assert_eq!(body["generationConfig"]["seed"], 7);
+    assert_eq!(body["tools"][0]["functionDeclarations"][0]["name"], "read");
+    let contents = body["contents"].as_array().unwrap();
+    assert_eq!(contents.len(), 3);
+    assert_eq!(
+        contents[0]["parts"][1]["inlineData"]["mimeType"],
+        "image/png"
+    );
+    assert_eq!(contents[1]["role"], "model");
+    assert_eq!(contents[2]["parts"][0]["functionResponse"]["name"], "read");
+    assert_eq!(contents[2]["parts"][1]["inlineData"]["data"], "aGVsbG8=");
+
+    assert_eq!(message.stop_reason, "toolUse");
+    assert!(matches!(
+        &message.content[0],
+        ContentBlock::Text { text, .. } if text == "Let me look"
+    ));
+    match &message.content[1] {
+        ContentBlock::ToolCall {
+            name, arguments, ..
+        } => {
+            assert_eq!(name, "read");
+            assert_eq!(arguments["path"], "a.png");
+        }
+        other => panic!("expected a tool call, got {other:?}"),
+    }
+    assert_eq!(message.usage.input, 1000);
+    assert_eq!(message.usage.output, 500);
+    assert!((message.usage.cost.unwrap().total - 0.002).abs() < 1e-9);
+}
+
+#[test]
+fn reports_api_errors() {
+    let (base_url, server) = serve_once(concat!(
+        "HTTP/1.1 400 Bad Request\r\ncontent-length: 41\r\nconnection: close\r\n\r\n",
+        r#"{"error":{"message":"API key not valid"}}"#,
+    ));
+    let model = gemini_model(&format!("{base_url}/v1beta"));
+    let mut events = StreamEvents::new(Box::new(|_| {}));
+    let err = stream_google_generative_ai(
+        &model,
+        &LlmContext {
+            system_prompt: String::new(),
+            messages: Vec::new(),
+        },
+        GoogleCallOptions {
+            model: &model.id,
+            api_key: "bad",
+            tools: &[],
+            base_url: &model.base_url,
+            extra_headers: None,
+            system: None,
+            thinking_enabled: false,
+            seed: None,
+            request_hook: None,
+        },
+        &mut events,
+    )
+    .unwrap_err();
+    server.join().unwrap();
+    let message = err.to_string();
+    assert!(message.starts_with("Gemini API error (400)"), "{message}");
+    assert!(message.contains("API key not valid"));
+    assert_eq!(err.status(), Some(400));
+    assert!(!err.is_retryable());
+}
@@ -0,0 +1,205 @@
+use pi::agent::{
+    get_model, Agent, AgentMessage, AgentOptions, AgentStateOverride, AgentTool, AgentToolResult,
+    LlmContext, StreamEvents,
+};
+use pi::ai::AssistantMessageEvent;
+use pi::api::mock::{MockFixture, MockProvider, MOCK_API};
+use pi::coding_agent::models_config::validate_models_config;
+use pi::coding_agent::{AuthStorage, Model, ModelRegistry};
+use pi::{ContentBlock, Cost};
+use serde_json::json;
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use uuid::Uuid;
+
+fn mock_model(fixture: &Path) -> Model {
+    Model {
+        id: "scripted".to_string(),
+        name: "Scripted".to_string(),
+        api: MOCK_API.to_string(),
+        provider: "mock".to_string(),
+        base_url: fixture.display().to_string(),
+        reasoning: false,
+        input: vec!["text".to_string()],
+        cost: Cost {
+            input: 1.0,
+            output: 2.0,
+            cache_read: 0.0,
+            cache_write: 0.0,
+            total: 0.0,
+        },
+        context_window: 128_000,
+        max_tokens: 4096,
+        headers: None,
+    }
+}
+
+fn fixture(value: serde_json::Value) -> MockFixture {
+    serde_json::from_value(value).unwrap()
+}
+
+fn temp_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pi-mock-{}", Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn streams_scripted_deltas_and_tool_calls() {
+    let model = mock_model(Path::new("fixture.json"));
+    let mut provider = MockProvider::new(
+        Path::new("fixture.json"),
+        fixture(json!({
+            "responses": [
+                {
+                    "content": [
+                        { "type": "thinking", "thinking": ["Check ", "the file."] },
+                        { "type": "text", "text": "Reading it now." },
+                        { "type": "toolCall", "name": "read", "arguments": { "path": "a.txt" } }
+                    ],
+                    "usage": { "input": 1000, "output": 500 }
+                },
+                { "error": "overloaded_error: try again later" }
+            ]
+        })),
+    );
+
+    let deltas = Rc::new(RefCell::new(Vec::new()));
+    let seen = deltas.clone();
+    let mut events = StreamEvents::new(Box::new(move |event| match event {
+        AssistantMessageEvent::TextDelta { delta, .. }
+        | AssistantMessageEvent::ThinkingDelta { delta, .. }
+        | AssistantMessageEvent::ToolCallDelta { delta, .. } => seen.borrow_mut().push(delta),
+        _ => {}
+    }));
+
+    let message = provider.stream(&model, &mut events);
+    assert_eq!(
+        *deltas.borrow(),
+        vec![
+            "Check ",
+            "the file.",
+            "Reading ",
+            "it ",
+            "now.",
+            "{\"path\":\"a.txt\"}"
+        ]
+    );
+    assert_eq!(message.stop_reason, "toolUse");
+    assert!(matches!(
+        &message.content[2],
+        ContentBlock::ToolCall { id, name, .. } if id == "mock_1_2" && name == "read"
+    ));
+    assert_eq!(message.usage.total_tokens, Some(1500));
+    assert_eq!(message.usage.cost.as_ref().unwrap().total, 0.002);
+
+    let message = provider.stream(&model, &mut events);
+    assert_eq!(message.stop_reason, "error");
+    assert_eq!(
+        message.error_message.as_deref(),
+        Some("overloaded_error: try again later")
+    );
+
+    let message = provider.stream(&model, &mut events);
+    assert_eq!(
+        message.error_message.as_deref(),
+        Some("Mock fixture fixture.json has no response for turn 3 (it scripts 2)")
+    );
+}
+
+#[test]
+fn drives_the_agent_loop_through_a_tool_call() {
+    let dir = temp_dir();
+    let path = dir.join("fixture.json");
+    fs::write(
+        &path,
+        json!({
+            "delayMs": 1,
+            "responses": [
+                { "content": [{ "type": "toolCall", "id": "call-1", "name": "echo",
+                                "arguments": { "text": "hi" } }] },
+                { "content": [{ "type": "text", "text": "The tool said hi." }] }
+            ]
+        })
+        .to_string(),
+    )
+    .unwrap();
+    let model = mock_model(&path);
+    let mut provider = MockProvider::load(&path).unwrap();
+
+    let echo = AgentTool {
+        name: "echo".to_string(),
+        label: "echo".to_string(),
+        description: "Echo text".to_string(),
+        parameters: None,
+        execute: Rc::new(|_id, params, _on_update| {
+            Ok(AgentToolResult {
+                content: vec![ContentBlock::Text {
+                    text: params["text"].as_str().unwrap_or("").to_string(),
+                    text_signature: None,
+                }],
+                details: json!({}),
+            })
+        }),
+    };
+    let stream_model = model.clone();
+    let agent = Agent::new(AgentOptions {
+        initial_state: Some(AgentStateOverride {
+            model: Some(get_model("mock", "scripted")),
+            tools: Some(vec![echo]),
+            ..Default::default()
+        }),
+        stream_fn: Some(Box::new(
+            move |_model: &pi::agent::Model, _context: &LlmContext, events: &mut StreamEvents| {
+                provider.stream(&stream_model, events)
+            },
+        )),
+        ..Default::default()
+    });
+
+    agent.prompt("Echo hi").unwrap();
+    let messages = agent.state().messages;
+    let roles = messages.iter().map(|m| m.role()).collect::<Vec<_>>();
+    assert_eq!(roles, vec!["user", "assistant", "toolResult", "assistant"]);
+    let AgentMessage::ToolResult(result) = &messages[2] else {
+        panic!("expected a tool result");
+    };
+    assert_eq!(result.tool_call_id, "call-1");
+    assert!(matches!(&result.content[0], ContentBlock::Text { text, .. } if text == "hi"));
+}
+
+#[test]
+fn mock_models_need_no_credentials() {
+    let dir = temp_dir();
+    let models_json = dir.join("models.json");
+    let config = json!({
+        "providers": {
+            "demo": {
+                "api": "mock",
+                "baseUrl": "fixtures/demo.json",
+                "models": [{ "id": "scripted" }]
+            }
+        }
+    });
+    validate_models_config(&config).unwrap();
+    fs::write(&models_json, config.to_string()).unwrap();
+
+    let registry = ModelRegistry::new(AuthStorage::new(dir.join("auth.json")), models_json);
+    let available = registry.get_available();
+    let model = available
+        .iter()
+        .find(|model| model.provider == "demo")
+        .expect("mock model available");
+    assert_eq!(model.api, MOCK_API);
+    assert_eq!(model.base_url, "fixtures/demo.json");
+
+    let invalid = json!({
+        "providers": { "demo": { "api": "mock", "baseUrl": "", "models": [{ "id": "x" }] } }
+    });
+    assert_eq!(
+        validate_models_config(&invalid).unwrap_err().to_string(),
+        "providers.demo.baseUrl: expected a fixture path"
+    );
+}
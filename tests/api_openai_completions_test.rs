@@ -0,0 +1,234 @@
+mod common;
+
+use common::serve_once;
+use pi::agent::{AgentMessage, LlmContext, StreamEvents};
+use pi::api::openai_completions::{
+    build_completions_request, stream_openai_completions, CompletionsCallOptions,
+    CompletionsCompat, CompletionsTool,
+};
+use pi::coding_agent::{AuthStorage, Model, ModelRegistry};
+use pi::{AssistantMessage, ContentBlock, ToolResultMessage, Usage, UserContent, UserMessage};
+use serde_json::json;
+use std::path::PathBuf;
+
+fn registry_model(provider: &str, id: &str) -> Model {
+    ModelRegistry::new(AuthStorage::new(PathBuf::from("auth.json")), None)
+        .find(provider, id)
+        .unwrap()
+}
+
+fn read_tool() -> CompletionsTool {
+    CompletionsTool {
+        name: "read".to_string(),
+        description: "Read a file".to_string(),
+        parameters: json!({ "type": "object", "properties": { "path": { "type": "string" } } }),
+    }
+}
+
+fn options<'a>(model: &'a Model, tools: &'a [CompletionsTool]) -> CompletionsCallOptions<'a> {
+    CompletionsCallOptions {
+        model: &model.id,
+        api_key: "test-key",
+        tools,
+        base_url: &model.base_url,
+        extra_headers: None,
+        system: Some("Be brief."),
+        seed: Some(7),
+        request_hook: None,
+    }
+}
+
+/// A turn that called a tool, got its result, then a new user prompt.
+fn tool_context(tool_call_id: &str) -> LlmContext {
+    LlmContext {
+        system_prompt: String::new(),
+        messages: vec![
+            AgentMessage::User(UserMessage {
+                content: UserContent::Text("Read a.txt".to_string()),
+                timestamp: 0,
+            }),
+            AgentMessage::Assistant(AssistantMessage {
+                content: vec![ContentBlock::ToolCall {
+                    id: tool_call_id.to_string(),
+                    name: "read".to_string(),
+                    arguments: json!({ "path": "a.txt" }),
+                    thought_signature: None,
+                }],
+                api: "openai-completions".to_string(),
+                provider: "mistral".to_string(),
+                model: "devstral-medium-latest".to_string(),
+                usage: Usage {
+                    input: 0,
+                    output: 0,
+                    cache_read: 0,
+                    cache_write: 0,
+                    total_tokens: None,
+                    cost: None,
+                },
+                stop_reason: "toolUse".to_string(),
+                error_message: None,
+                timestamp: 0,
+            }),
+            AgentMessage::ToolResult(ToolResultMessage {
+                tool_call_id: tool_call_id.to_string(),
+                tool_name: "read".to_string(),
+                content: vec![ContentBlock::Text {
+                    text: "hello".to_string(),
+                    text_signature: None,
+                }],
+                details: None,
+                is_error: false,
+                timestamp: 0,
+            }),
+            AgentMessage::User(UserMessage {
+                content: UserContent::Text("Thanks".to_string()),
+                timestamp: 0,
+            }),
+        ],
+    }
+}
+
+#[test]
+fn xai_and_mistral_presets_use_chat_completions() {
+    let grok = registry_model("xai", "grok-code-fast-1");
+    assert_eq!(grok.api, "openai-completions");
+    assert_eq!(grok.base_url, "https://api.x.ai/v1");
+    assert_eq!(grok.context_window, 256_000);
+    assert!(grok.cost.input > 0.0);
+
+    let devstral = registry_model("mistral", "devstral-medium-latest");
+    assert_eq!(devstral.api, "openai-completions");
+    assert_eq!(devstral.base_url, "https://api.mistral.ai/v1");
+}
+
+#[test]
+fn streams_text_reasoning_and_tool_calls_from_xai() {
+    let (base_url, server) = serve_once(concat!(
+        "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n",
+        "data: {\"choices\":[{\"index\":0,\"delta\":{\"role\":\"assistant\",\"reasoning_content\":\"Need the file\"}}]}\n\n",
+        "data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Let me \"}}]}\n\n",
+        "data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"look\"}}]}\n\n",
+        "data: {\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_1\",\"type\":\"function\",\"function\":{\"name\":\"read\",\"arguments\":\"{\\\"path\\\":\"}}]}}]}\n\n",
+        "data: {\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\"\\\"a.txt\\\"}\"}}]},\"finish_reason\":\"tool_calls\"}]}\n\n",
+        "data: {\"choices\":[],\"usage\":{\"prompt_tokens\":1000,\"completion_tokens\":100,\"prompt_tokens_details\":{\"cached_tokens\":200}}}\n\n",
+        "data: [DONE]\n\n",
+    ));
+    let model = Model {
+        base_url: format!("{base_url}/v1"),
+        ..registry_model("xai", "grok-code-fast-1")
+    };
+    let tools = [read_tool()];
+    let context = LlmContext {
+        system_prompt: String::new(),
+        messages: vec![AgentMessage::User(UserMessage {
+            content: UserContent::Text("Read a.txt".to_string()),
+            timestamp: 0,
+        })],
+    };
+
+    let mut events = StreamEvents::new(Box::new(|_| {}));
+    let message =
+        stream_openai_completions(&model, &context, options(&model, &tools), &mut events).unwrap();
+
+    let (request_line, headers, body) = server.join().unwrap();
+    assert_eq!(request_line, "POST /v1/chat/completions HTTP/1.1");
+    assert!(headers.contains(&"authorization:Bearer test-key".to_string()));
+    assert_eq!(body["model"], "grok-code-fast-1");
+    assert_eq!(body["messages"][0]["role"], "system");
+    assert_eq!(body["messages"][1]["content"], "Read a.txt");
+    assert_eq!(body["stream_options"]["include_usage"], true);
+    assert_eq!(body["max_tokens"], model.max_tokens);
+    assert_eq!(body["seed"], 7);
+    assert!(body.get("store").is_none());
+    assert_eq!(body["tools"][0]["type"], "function");
+    assert_eq!(body["tools"][0]["function"]["name"], "read");
+
+    assert_eq!(message.stop_reason, "toolUse");
+    assert!(matches!(
+        &message.content[0],
+        ContentBlock::Thinking { thinking, .. } if thinking == "Need the file"
+    ));
+    assert!(matches!(
+        &message.content[1],
+        ContentBlock::Text { text, .. } if text == "Let me look"
+    ));
+    match &message.content[2] {
+        ContentBlock::ToolCall {
+            id,
+            name,
+            arguments,
+            ..
+        } => {
+            assert_eq!(id, "call_1");
+            assert_eq!(name, "read");
+            assert_eq!(arguments["path"], "a.txt");
+        }
+        other => panic!("expected a tool call, got {other:?}"),
+    }
+    assert_eq!(message.usage.input, 800);
+    assert_eq!(message.usage.cache_read, 200);
+    assert_eq!(message.usage.output, 100);
+    assert!(message.usage.cost.unwrap().total > 0.0);
+}
+
+#[test]
+fn mistral_requests_follow_its_message_rules() {
+    let model = registry_model("mistral", "devstral-medium-latest");
+    let tools = [read_tool()];
+    let compat = CompletionsCompat::for_model(&model);
+    let request = build_completions_request(
+        &model,
+        &tool_context("call_0123456789|fc_1"),
+        &options(&model, &tools),
+        &compat,
+    );
+
+    assert_eq!(request["random_seed"], 7);
+    assert!(request.get("seed").is_none());
+    assert!(request.get("stream_options").is_none());
+    assert!(request.get("store").is_none());
+    assert_eq!(request["max_tokens"], model.max_tokens);
+
+    let messages = request["messages"].as_array().unwrap();
+    let roles = messages
+        .iter()
+        .map(|message| message["role"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        roles,
+        ["system", "user", "assistant", "tool", "assistant", "user"]
+    );
+    let call_id = messages[2]["tool_calls"][0]["id"].as_str().unwrap();
+    assert_eq!(call_id.len(), 9);
+    assert!(call_id.chars().all(|c| c.is_ascii_alphanumeric()));
+    assert_eq!(messages[3]["tool_call_id"], call_id);
+    assert_eq!(messages[3]["name"], "read");
+    assert_eq!(messages[3]["content"], "hello");
+    assert_eq!(
+        messages[2]["tool_calls"][0]["function"]["arguments"],
+        r#"{"path":"a.txt"}"#
+    );
+}
+
+#[test]
+fn reports_provider_error_messages() {
+    let (base_url, server) = serve_once(concat!(
+        "HTTP/1.1 401 Unauthorized\r\ncontent-type: application/json\r\ncontent-length: 61\r\nconnection: close\r\n\r\n",
+        r#"{"code":"Client specified an invalid argument","error":"Bad"}"#,
+    ));
+    let model = Model {
+        base_url: format!("{base_url}/v1"),
+        ..registry_model("xai", "grok-code-fast-1")
+    };
+    let mut events = StreamEvents::new(Box::new(|_| {}));
+    let err = stream_openai_completions(
+        &model,
+        &tool_context("call_1"),
+        options(&model, &[]),
+        &mut events,
+    )
+    .unwrap_err();
+    server.join().unwrap();
+    assert_eq!(err.to_string(), "xai API error (401): Bad");
+    assert!(matches!(err, pi::Error::Auth { .. }));
+}
@@ -0,0 +1,108 @@
+use pi::api::request_hook::ProviderRequest;
+use pi::api::{call_openai, OpenAICallOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::thread;
+
+/// Accept one HTTP request and answer with an empty OpenAI response. Returns the request
+/// line, headers (lowercased names) and body.
+fn serve_once(listener: TcpListener) -> thread::JoinHandle<(String, Vec<String>, String)> {
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+        let mut headers = Vec::new();
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let line = line.trim_end().to_string();
+            if line.is_empty() {
+                break;
+            }
+            let (name, value) = line.split_once(':').unwrap();
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap();
+            }
+            headers.push(format!("{}:{}", name.to_lowercase(), value.trim()));
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).unwrap();
+
+        let reply = r#"{"output":[],"status":"completed"}"#;
+        let mut stream = stream;
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{reply}",
+            reply.len()
+        )
+        .unwrap();
+        (
+            request_line.trim_end().to_string(),
+            headers,
+            String::from_utf8(body).unwrap(),
+        )
+    })
+}
+
+#[test]
+fn request_hook_can_sign_and_rewrite_provider_requests() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = serve_once(listener);
+
+    let hook = |request: &mut ProviderRequest| -> Result<(), String> {
+        assert_eq!(request.provider, "openai");
+        assert_eq!(request.model, "gpt-test");
+        assert_eq!(request.headers["content-type"], "application/json");
+        assert!(request.body.contains("\"model\":\"gpt-test\""));
+        request.url = request.url.replace("/v1/responses", "/gateway/responses");
+        request.headers.remove("authorization");
+        request.headers.insert(
+            "x-signature".to_string(),
+            format!("len={}", request.body.len()),
+        );
+        Ok(())
+    };
+    call_openai(
+        Vec::new(),
+        OpenAICallOptions {
+            model: "gpt-test",
+            api_key: "secret",
+            tools: &[],
+            base_url: &format!("http://127.0.0.1:{port}/v1"),
+            extra_headers: None,
+            seed: None,
+            request_hook: Some(&hook),
+        },
+    )
+    .unwrap();
+
+    let (request_line, headers, body) = server.join().unwrap();
+    assert_eq!(request_line, "POST /gateway/responses HTTP/1.1");
+    assert!(headers.contains(&format!("x-signature:len={}", body.len())));
+    assert!(!headers
+        .iter()
+        .any(|header| header.starts_with("authorization:")));
+}
+
+#[test]
+fn request_hook_errors_abort_the_request() {
+    let hook =
+        |_: &mut ProviderRequest| -> Result<(), String> { Err("missing signing key".into()) };
+    let err = call_openai(
+        Vec::new(),
+        OpenAICallOptions {
+            model: "gpt-test",
+            api_key: "secret",
+            tools: &[],
+            base_url: "http://127.0.0.1:9/v1",
+            extra_headers: None,
+            seed: None,
+            request_hook: Some(&hook),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err.to_string(), "Request hook failed: missing signing key");
+}
@@ -1,4 +1,10 @@
-use pi::{parse_args, Args, ExtensionFlagType, ExtensionFlagValue, Mode, ThinkingLevel};
+use pi::coding_agent::SettingsScope;
+use pi::{
+    parse_args, Args, AuthCommand, BatchCommand, CompareCommand, ConfigCommand, ExtensionFlagType,
+    ExtensionFlagValue, ExtensionsCommand, Mode, ModelsCommand, ReplayTurnCommand, SessionsCommand,
+    StatsCommand, ThinkingLevel, ToolRunCommand, UiMode,
+};
+use serde_json::json;
 use std::collections::HashMap;
 
 fn parse(input: &[&str]) -> Args {
@@ -150,6 +156,12 @@ fn parses_messages_and_file_args() {
     );
     assert_eq!(result.messages, vec!["explain this".to_string()]);
 
+    let result = parse(&["-p", "-", "explain this"]);
+    assert!(result.stdin);
+    assert_eq!(result.messages, vec!["explain this".to_string()]);
+    assert!(!parse(&["-p", "explain this"]).stdin);
+    assert!(parse(&["-p", "--no-stdin", "explain this"]).no_stdin);
+
     let result = parse(&["--unknown-flag", "message"]);
     assert_eq!(result.messages, vec!["message".to_string()]);
 }
@@ -199,3 +211,403 @@ fn parses_complex_combinations() {
     assert_eq!(result.file_args, vec!["prompt.md".to_string()]);
     assert_eq!(result.messages, vec!["Do the task".to_string()]);
 }
+
+#[test]
+fn parses_sessions_commands() {
+    let result = parse(&["--sessions"]);
+    assert_eq!(result.sessions, Some(SessionsCommand::List));
+
+    let result = parse(&["--sessions", "list"]);
+    assert_eq!(result.sessions, Some(SessionsCommand::List));
+    assert!(result.messages.is_empty());
+
+    let result = parse(&["--sessions", "search", "flaky test"]);
+    assert_eq!(
+        result.sessions,
+        Some(SessionsCommand::Search("flaky test".to_string()))
+    );
+    assert!(result.messages.is_empty());
+}
+
+#[test]
+fn parses_seed_flag() {
+    let result = parse(&["--seed", "42", "hello"]);
+    assert_eq!(result.seed, Some(42));
+    assert_eq!(result.messages, vec!["hello".to_string()]);
+
+    let result = parse(&["--seed", "-1"]);
+    assert_eq!(result.seed, None);
+}
+
+#[test]
+fn parses_persona_flag() {
+    let result = parse(&["--persona", "reviewer", "check this"]);
+    assert_eq!(result.persona.as_deref(), Some("reviewer"));
+    assert_eq!(result.messages, vec!["check this".to_string()]);
+}
+
+#[test]
+fn parses_export_new_only_flag() {
+    let result = parse(&["--export", "session.jsonl", "--export-new-only"]);
+    assert_eq!(result.export.as_deref(), Some("session.jsonl"));
+    assert!(result.export_new_only);
+}
+
+#[test]
+fn parses_tool_run_subcommand() {
+    let result = parse(&[
+        "tool",
+        "run",
+        "grep",
+        "--pattern",
+        "foo",
+        "--path",
+        "src",
+        "--ignore-case",
+        "--limit",
+        "5",
+    ]);
+    assert_eq!(
+        result.tool_run,
+        Some(ToolRunCommand {
+            tool: "grep".to_string(),
+            params: json!({ "pattern": "foo", "path": "src", "ignoreCase": true, "limit": 5 }),
+            allow_all: false,
+        })
+    );
+    assert!(result.messages.is_empty());
+
+    let result = parse(&[
+        "tool",
+        "run",
+        "write",
+        "--json",
+        r#"{"path":"a.txt","content":"42"}"#,
+    ]);
+    assert_eq!(
+        result.tool_run.unwrap().params,
+        json!({ "path": "a.txt", "content": "42" })
+    );
+
+    let result = parse(&["tool", "call"]);
+    assert_eq!(result.tool_run, None);
+    assert_eq!(
+        result.messages,
+        vec!["tool".to_string(), "call".to_string()]
+    );
+}
+
+#[test]
+fn parses_dangerously_allow_all() {
+    assert!(!parse(&["hello"]).dangerously_allow_all);
+    assert!(parse(&["--dangerously-allow-all", "hello"]).dangerously_allow_all);
+
+    let tool_run = parse(&[
+        "tool",
+        "run",
+        "read",
+        "--path",
+        "/etc/hosts",
+        "--dangerously-allow-all",
+    ])
+    .tool_run
+    .unwrap();
+    assert!(tool_run.allow_all);
+    assert_eq!(tool_run.params, json!({ "path": "/etc/hosts" }));
+}
+
+#[test]
+fn parses_blame_subcommand() {
+    let result = parse(&["blame", "src/lib.rs", "--session-dir", "/tmp/sessions"]);
+    assert_eq!(result.blame.as_deref(), Some("src/lib.rs"));
+    assert_eq!(result.session_dir.as_deref(), Some("/tmp/sessions"));
+    assert!(result.messages.is_empty());
+
+    assert_eq!(parse(&["blame"]).blame, None);
+}
+
+#[test]
+fn parses_auth_subcommand() {
+    assert_eq!(
+        parse(&["auth", "login", "anthropic"]).auth,
+        Some(AuthCommand::Login(Some("anthropic".to_string())))
+    );
+    assert_eq!(
+        parse(&["auth", "login"]).auth,
+        Some(AuthCommand::Login(None))
+    );
+    let result = parse(&["auth", "logout", "openai-codex", "--auth-profile", "work"]);
+    assert_eq!(
+        result.auth,
+        Some(AuthCommand::Logout(Some("openai-codex".to_string())))
+    );
+    assert_eq!(result.auth_profile.as_deref(), Some("work"));
+    assert!(result.messages.is_empty());
+    assert_eq!(parse(&["auth", "status"]).auth, Some(AuthCommand::Status));
+    assert_eq!(parse(&["auth"]).auth, Some(AuthCommand::Status));
+    assert_eq!(parse(&["hello"]).auth, None);
+}
+
+#[test]
+fn parses_config_subcommand() {
+    assert_eq!(
+        parse(&["config", "set", "compaction.threshold", "0.9", "--project"]).config,
+        Some(ConfigCommand {
+            action: Some("set".to_string()),
+            key: Some("compaction.threshold".to_string()),
+            value: Some("0.9".to_string()),
+            scope: Some(SettingsScope::Project),
+        })
+    );
+    assert_eq!(
+        parse(&["config"]).config,
+        Some(ConfigCommand {
+            action: None,
+            key: None,
+            value: None,
+            scope: None,
+        })
+    );
+}
+
+#[test]
+fn parses_replay_turn_subcommand() {
+    let result = parse(&[
+        "replay-turn",
+        "--session",
+        "s.jsonl",
+        "--entry",
+        "abc123",
+        "--model",
+        "gpt-5",
+        "--dry-run",
+    ]);
+    assert_eq!(
+        result.replay_turn,
+        Some(ReplayTurnCommand {
+            entry: Some("abc123".to_string()),
+            dry_run: true,
+        })
+    );
+    assert_eq!(result.session.as_deref(), Some("s.jsonl"));
+    assert_eq!(result.model.as_deref(), Some("gpt-5"));
+    assert!(result.messages.is_empty());
+}
+
+#[test]
+fn parses_ui_flag() {
+    assert_eq!(parse(&["--ui", "line"]).ui, Some(UiMode::Line));
+    assert_eq!(parse(&["--ui", "full", "hi"]).ui, Some(UiMode::Full));
+    assert_eq!(parse(&["--ui", "auto"]).ui, None);
+    assert_eq!(parse(&["hi"]).ui, None);
+}
+
+#[test]
+fn parses_rpc_supervision_flags() {
+    let result = parse(&[
+        "--mode",
+        "rpc",
+        "--idle-exit",
+        "300",
+        "--parent-pid",
+        "4242",
+    ]);
+    assert_eq!(result.idle_exit, Some(300));
+    assert_eq!(result.parent_pid, Some(4242));
+
+    let result = parse(&["--idle-exit", "0", "--parent-pid", "self"]);
+    assert_eq!(result.idle_exit, None);
+    assert_eq!(result.parent_pid, None);
+}
+
+#[test]
+fn parses_tools_with_git_group() {
+    let result = parse(&["--tools", "read,git,bogus"]);
+    assert_eq!(
+        result.tools,
+        Some(vec![
+            "read".to_string(),
+            "git_status".to_string(),
+            "git_diff".to_string(),
+            "git_log".to_string(),
+            "git_commit".to_string(),
+            "git_stash".to_string(),
+        ])
+    );
+}
+
+#[test]
+fn parses_tools_with_notebook_group() {
+    let result = parse(&["--tools", "notebook,read"]);
+    assert_eq!(
+        result.tools,
+        Some(vec![
+            "notebook_read".to_string(),
+            "notebook_edit".to_string(),
+            "read".to_string(),
+        ])
+    );
+}
+
+#[test]
+fn parses_models_subcommand() {
+    assert_eq!(
+        parse(&[
+            "models",
+            "add",
+            "local/qwen",
+            "--base-url",
+            "http://localhost:11434/v1",
+            "--reasoning",
+            "--input",
+            "text,image",
+        ])
+        .models_command,
+        Some(ModelsCommand {
+            action: Some("add".to_string()),
+            target: Some("local/qwen".to_string()),
+            options: vec![
+                (
+                    "base-url".to_string(),
+                    "http://localhost:11434/v1".to_string()
+                ),
+                ("reasoning".to_string(), "true".to_string()),
+                ("input".to_string(), "text,image".to_string()),
+            ],
+        })
+    );
+}
+
+#[test]
+fn parses_serve_flags() {
+    let parsed = parse(&["--serve", "--port", "8080"]);
+    assert!(parsed.serve);
+    assert_eq!(parsed.port, Some(8080));
+    assert_eq!(parse(&["--port", "http"]).port, None);
+}
+
+#[test]
+fn parses_batch_flags_and_subcommand() {
+    let parsed = parse(&["-p", "--batch", "--batch-output", "out", "one", "two"]);
+    assert!(parsed.batch);
+    assert_eq!(parsed.batch_output.as_deref(), Some("out"));
+    assert_eq!(parsed.messages, vec!["one", "two"]);
+
+    let parsed = parse(&[
+        "batch",
+        "fetch",
+        "abc123",
+        "--output",
+        "results",
+        "--api-key",
+        "k",
+    ]);
+    assert_eq!(
+        parsed.batch_command,
+        Some(BatchCommand {
+            action: Some("fetch".to_string()),
+            target: Some("abc123".to_string()),
+            output: Some("results".to_string()),
+        })
+    );
+    assert_eq!(parsed.api_key.as_deref(), Some("k"));
+}
+
+#[test]
+fn parses_max_cost() {
+    assert_eq!(parse(&["--max-cost", "$2.50"]).max_cost, Some(2.5));
+    assert_eq!(parse(&["--max-cost", "0.1"]).max_cost, Some(0.1));
+    assert_eq!(parse(&["--max-cost", "0"]).max_cost, None);
+    assert_eq!(parse(&["--max-cost", "lots"]).max_cost, None);
+}
+
+#[test]
+fn parses_trace_llm() {
+    let parsed = parse(&["--trace-llm", "trace.jsonl", "-p", "hi"]);
+    assert_eq!(parsed.trace_llm.as_deref(), Some("trace.jsonl"));
+    assert_eq!(parsed.messages, vec!["hi"]);
+}
+
+#[test]
+fn parses_no_project_context() {
+    assert!(parse(&["--no-project-context", "-p", "hi"]).no_project_context);
+    assert!(!parse(&["-p", "hi"]).no_project_context);
+}
+
+#[test]
+fn parses_context_dir_and_globs() {
+    let parsed = parse(&[
+        "--context-dir",
+        "src",
+        "--context-glob",
+        "**/*.rs, *.toml",
+        "--context-glob",
+        "docs/*.md",
+    ]);
+    assert_eq!(parsed.context_dir.as_deref(), Some("src"));
+    assert_eq!(
+        parsed.context_globs,
+        Some(vec![
+            "**/*.rs".to_string(),
+            "*.toml".to_string(),
+            "docs/*.md".to_string()
+        ])
+    );
+}
+
+#[test]
+fn parses_extensions_subcommand() {
+    assert_eq!(
+        parse(&["extensions", "reset", "guard.js"]).extensions_command,
+        Some(ExtensionsCommand {
+            action: Some("reset".to_string()),
+            target: Some("guard.js".to_string()),
+        })
+    );
+    assert!(parse(&["extensions"]).extensions_command.is_some());
+}
+
+#[test]
+fn parses_stats_subcommand() {
+    let parsed = parse(&["stats", "--json", "--session-dir", "/tmp/sessions"]);
+    assert_eq!(parsed.stats, Some(StatsCommand { json: true }));
+    assert_eq!(parsed.session_dir.as_deref(), Some("/tmp/sessions"));
+    assert_eq!(parse(&["stats"]).stats, Some(StatsCommand::default()));
+}
+
+#[test]
+fn parses_compare_subcommand() {
+    let parsed = parse(&[
+        "compare",
+        "--models",
+        "openai/gpt-5.1,anthropic/claude-opus-4-5",
+        "Which map?",
+        "--output",
+        "report.md",
+    ]);
+    assert_eq!(
+        parsed.compare,
+        Some(CompareCommand {
+            output: Some("report.md".to_string()),
+        })
+    );
+    assert_eq!(
+        parsed.models,
+        Some(vec![
+            "openai/gpt-5.1".to_string(),
+            "anthropic/claude-opus-4-5".to_string(),
+        ])
+    );
+    assert_eq!(parsed.messages, vec!["Which map?".to_string()]);
+}
+
+#[test]
+fn parses_max_turns_and_duration() {
+    let parsed = parse(&["--max-turns", "20", "--max-duration", "10m"]);
+    assert_eq!(parsed.max_turns, Some(20));
+    assert_eq!(parsed.max_duration, Some(600));
+    assert_eq!(parse(&["--max-duration", "90"]).max_duration, Some(90));
+    assert_eq!(parse(&["--max-duration", "1h"]).max_duration, Some(3600));
+    assert_eq!(parse(&["--max-duration", "soon"]).max_duration, None);
+    assert_eq!(parse(&["--max-turns", "0"]).max_turns, None);
+}
@@ -0,0 +1,170 @@
+use pi::cli::batch::{batch_progress, fetch_batch, submit_batch, write_batch_results, BatchJob};
+use pi::coding_agent::Model as RegistryModel;
+use pi::core::messages::Cost;
+use serde_json::Value;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Answers every request with `respond(request line, body)` and records the request lines.
+fn serve<F>(respond: F) -> (String, Arc<Mutex<Vec<String>>>)
+where
+    F: Fn(&str, &str, &str) -> String + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}/v1", listener.local_addr().unwrap());
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let recorded = seen.clone();
+    let origin = base_url.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            let request_line = request_line.trim_end().to_string();
+            let reply = respond(&request_line, &String::from_utf8(body).unwrap(), &origin);
+            recorded.lock().unwrap().push(request_line);
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\
+                 connection: close\r\n\r\n{reply}",
+                reply.len()
+            )
+            .unwrap();
+        }
+    });
+    (base_url, seen)
+}
+
+fn model(api: &str, provider: &str, base_url: &str) -> RegistryModel {
+    RegistryModel {
+        id: "test-model".to_string(),
+        name: "Test".to_string(),
+        api: api.to_string(),
+        provider: provider.to_string(),
+        base_url: base_url.to_string(),
+        reasoning: false,
+        input: vec!["text".to_string()],
+        cost: Cost {
+            input: 0.0,
+            output: 0.0,
+            cache_read: 0.0,
+            cache_write: 0.0,
+            total: 0.0,
+        },
+        context_window: 200_000,
+        max_tokens: 8_192,
+        headers: None,
+    }
+}
+
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("pi-batch-{name}-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn submits_polls_and_fetches_an_anthropic_batch() {
+    let (base_url, seen) = serve(|request_line, body, origin| {
+        if request_line.starts_with("POST /v1/messages/batches") {
+            let body: Value = serde_json::from_str(body).unwrap();
+            let requests = body["requests"].as_array().unwrap();
+            assert_eq!(requests[0]["custom_id"], "prompt-001");
+            assert_eq!(
+                requests[1]["params"]["messages"][0]["content"][0]["text"],
+                "two"
+            );
+            assert_eq!(requests[0]["params"]["system"][0]["text"], "Be brief");
+            assert_eq!(requests[0]["params"]["max_tokens"], 8192);
+            return r#"{"id":"msgbatch_1","processing_status":"in_progress"}"#.to_string();
+        }
+        if request_line.starts_with("GET /v1/messages/batches/msgbatch_1/results") {
+            return [
+                r#"{"custom_id":"prompt-002","result":{"type":"errored","error":{"type":"error","error":{"type":"invalid_request_error","message":"too long"}}}}"#,
+                r#"{"custom_id":"prompt-001","result":{"type":"succeeded","message":{"content":[{"type":"text","text":"first"}],"stop_reason":"end_turn"}}}"#,
+            ]
+            .join("\n");
+        }
+        assert!(request_line.starts_with("GET /v1/messages/batches/msgbatch_1 "));
+        format!(
+            r#"{{"id":"msgbatch_1","processing_status":"ended","request_counts":{{"succeeded":1,"errored":1}},"results_url":"{origin}/messages/batches/msgbatch_1/results"}}"#
+        )
+    });
+    let model = model("anthropic-messages", "anthropic", &base_url);
+    let prompts = vec!["one".to_string(), "two".to_string()];
+    let job = submit_batch(&model, "Be brief", &prompts, None, Some("key")).unwrap();
+    assert_eq!(job.batch_id.as_deref(), Some("msgbatch_1"));
+    assert_eq!(job.output_dir, format!("pi-batch-{}", job.id));
+
+    let jobs_dir = temp_dir("jobs");
+    job.save(&jobs_dir).unwrap();
+    assert_eq!(BatchJob::load(&jobs_dir, &job.id).unwrap(), job);
+    assert_eq!(BatchJob::list(&jobs_dir), vec![job.clone()]);
+
+    let progress = batch_progress(&job, Some("key")).unwrap();
+    assert!(progress.finished);
+    assert_eq!((progress.succeeded, progress.failed), (1, 1));
+
+    let results = fetch_batch(&job, Some("key")).unwrap();
+    assert_eq!(
+        results,
+        vec![
+            ("prompt-001".to_string(), Ok("first".to_string())),
+            ("prompt-002".to_string(), Err("too long".to_string())),
+        ]
+    );
+    let output_dir = temp_dir("results");
+    let paths = write_batch_results(&output_dir, &results).unwrap();
+    assert_eq!(fs::read_to_string(&paths[0]).unwrap(), "first");
+    assert!(paths[1].ends_with("prompt-002.error.txt"));
+    assert_eq!(seen.lock().unwrap().len(), 4);
+}
+
+#[test]
+fn runs_openai_prompts_as_background_responses() {
+    let (base_url, _) = serve(|request_line, body, _| {
+        if request_line.starts_with("POST /v1/responses") {
+            let body: Value = serde_json::from_str(body).unwrap();
+            assert_eq!(body["background"], true);
+            assert_eq!(body["input"][0]["role"], "system");
+            let prompt = body["input"][1]["content"][0]["text"].as_str().unwrap();
+            return format!(r#"{{"id":"resp_{prompt}","status":"queued","output":[]}}"#);
+        }
+        if request_line.starts_with("GET /v1/responses/resp_one") {
+            return r#"{"id":"resp_one","status":"completed","output":[{"type":"message","role":"assistant","content":[{"type":"output_text","text":"done"}]}]}"#.to_string();
+        }
+        r#"{"id":"resp_two","status":"in_progress","output":[]}"#.to_string()
+    });
+    let model = model("openai-responses", "openai", &base_url);
+    let prompts = vec!["one".to_string(), "two".to_string()];
+    let job = submit_batch(&model, "Be brief", &prompts, Some("out"), Some("key")).unwrap();
+    assert_eq!(job.requests[1].response_id.as_deref(), Some("resp_two"));
+    assert_eq!(job.output_dir, "out");
+
+    let progress = batch_progress(&job, Some("key")).unwrap();
+    assert!(!progress.finished);
+    assert_eq!((progress.succeeded, progress.failed), (1, 0));
+    assert!(fetch_batch(&job, Some("key"))
+        .unwrap_err()
+        .contains("still running"));
+}
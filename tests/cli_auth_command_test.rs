@@ -0,0 +1,96 @@
+use pi::cli::auth::oauth_needs_refresh;
+use pi::cli::auth_command::{auth_status, logout, AuthState, AuthStatus};
+use pi::coding_agent::{AuthCredential, AuthStorage};
+use serde_json::json;
+use std::fs;
+
+fn oauth(refresh: Option<&str>, expires: Option<i64>) -> AuthCredential {
+    AuthCredential::OAuth {
+        access: "access".to_string(),
+        refresh: refresh.map(str::to_string),
+        expires,
+        enterprise_url: None,
+        project_id: None,
+        email: None,
+        account_id: None,
+    }
+}
+
+#[test]
+fn status_lists_stored_credentials_and_logout_removes_them() {
+    let dir = std::env::temp_dir().join(format!("pi-auth-command-{}", uuid::Uuid::new_v4()));
+    let path = dir.join("auth.json");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        &path,
+        json!({
+            "anthropic": { "type": "oauth", "access": "a", "refresh": "r", "expires": 1000 },
+            "acme": { "type": "api_key", "key": "k" },
+            "acme:work": { "type": "api_key", "key": "w" }
+        })
+        .to_string(),
+    )
+    .unwrap();
+
+    let mut storage = AuthStorage::new(&path);
+    let statuses = auth_status(&storage);
+    let providers = statuses
+        .iter()
+        .map(|status| status.provider.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        providers,
+        vec![
+            "acme",
+            "acme:work",
+            "anthropic",
+            "github-copilot",
+            "openai-codex"
+        ]
+    );
+    assert_eq!(
+        statuses[2],
+        AuthStatus {
+            provider: "anthropic".to_string(),
+            state: AuthState::OAuth {
+                expires: Some(1000)
+            },
+        }
+    );
+    assert_eq!(statuses[0].state, AuthState::ApiKey);
+
+    assert!(logout(&mut storage, "anthropic"));
+    assert!(!logout(&mut storage, "anthropic"));
+    let reloaded = AuthStorage::new(&path);
+    assert!(!reloaded.has("anthropic"));
+    assert!(reloaded.has("acme"));
+
+    storage.set_active_profile(Some("work"));
+    assert!(logout(&mut storage, "acme"));
+    let reloaded = AuthStorage::new(&path);
+    assert_eq!(reloaded.list(), vec!["acme".to_string()]);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn refreshes_only_expiring_oauth_tokens_with_a_refresh_token() {
+    let now = 1_000_000;
+    assert!(oauth_needs_refresh(&oauth(Some("r"), Some(now - 1)), now));
+    assert!(oauth_needs_refresh(
+        &oauth(Some("r"), Some(now + 30_000)),
+        now
+    ));
+    assert!(!oauth_needs_refresh(
+        &oauth(Some("r"), Some(now + 3_600_000)),
+        now
+    ));
+    assert!(!oauth_needs_refresh(&oauth(None, Some(now - 1)), now));
+    assert!(!oauth_needs_refresh(&oauth(Some("r"), None), now));
+    assert!(!oauth_needs_refresh(
+        &AuthCredential::ApiKey {
+            key: "k".to_string()
+        },
+        now
+    ));
+}
@@ -0,0 +1,150 @@
+use pi::cli::blame::blame_file;
+use pi::coding_agent::run_git;
+use pi::core::messages::{
+    AgentMessage, AssistantMessage, ContentBlock, ToolResultMessage, Usage, UserContent,
+    UserMessage,
+};
+use pi::core::session_manager::SessionManager;
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+use uuid::Uuid;
+
+fn tool_call(id: &str, name: &str, arguments: serde_json::Value) -> AgentMessage {
+    AgentMessage::Assistant(AssistantMessage {
+        content: vec![ContentBlock::ToolCall {
+            id: id.to_string(),
+            name: name.to_string(),
+            arguments,
+            thought_signature: None,
+        }],
+        api: "anthropic-messages".to_string(),
+        provider: "anthropic".to_string(),
+        model: "mock".to_string(),
+        usage: Usage {
+            input: 0,
+            output: 0,
+            cache_read: 0,
+            cache_write: 0,
+            total_tokens: None,
+            cost: None,
+        },
+        stop_reason: "toolUse".to_string(),
+        error_message: None,
+        timestamp: 0,
+    })
+}
+
+fn tool_result(id: &str, name: &str, is_error: bool) -> AgentMessage {
+    AgentMessage::ToolResult(ToolResultMessage {
+        tool_call_id: id.to_string(),
+        tool_name: name.to_string(),
+        content: Vec::new(),
+        details: None,
+        is_error,
+        timestamp: 0,
+    })
+}
+
+fn user(text: &str) -> AgentMessage {
+    AgentMessage::User(UserMessage {
+        content: UserContent::Text(text.to_string()),
+        timestamp: 0,
+    })
+}
+
+fn git(repo: &Path, args: &[&str]) {
+    run_git(repo, args).unwrap();
+}
+
+#[test]
+fn attributes_lines_to_agent_sessions_and_humans() {
+    let repo = std::env::temp_dir().join(format!("pi-blame-{}", Uuid::new_v4()));
+    let sessions = repo.join(".sessions");
+    fs::create_dir_all(repo.join("src")).unwrap();
+    git(&repo, &["init", "-q"]);
+    git(&repo, &["config", "user.name", "Pi Test"]);
+    git(&repo, &["config", "user.email", "pi@example.com"]);
+    git(&repo, &["config", "commit.gpgsign", "false"]);
+    fs::write(repo.join(".gitignore"), ".sessions/\n").unwrap();
+    fs::write(repo.join("src/lib.rs"), "// header\nfn human() {}\n").unwrap();
+    git(&repo, &["add", "-A"]);
+    git(
+        &repo,
+        &[
+            "commit",
+            "-q",
+            "-m",
+            "human",
+            "--date",
+            "2020-01-01T00:00:00",
+        ],
+    );
+
+    let mut session = SessionManager::create_with_dir(repo.clone(), sessions.clone());
+    session.append_message(user("first"));
+    session.append_message(user("add a helper"));
+    session.append_message(tool_call(
+        "call-1",
+        "edit",
+        json!({
+            "path": "src/lib.rs",
+            "oldText": "fn human() {}",
+            "newText": "fn human() {}\nfn helper() -> u32 {\n    42\n}",
+        }),
+    ));
+    session.append_message(tool_result("call-1", "edit", false));
+    session.append_message(tool_call(
+        "call-2",
+        "write",
+        json!({ "path": "src/lib.rs", "content": "fn later() {}\n" }),
+    ));
+    session.append_message(tool_result("call-2", "write", true));
+    let session_id = session.get_session_id();
+
+    fs::write(
+        repo.join("src/lib.rs"),
+        "// header\nfn human() {}\nfn helper() -> u32 {\n    42\n}\n",
+    )
+    .unwrap();
+    git(&repo, &["commit", "-q", "-am", "agent change"]);
+    fs::write(
+        repo.join("src/lib.rs"),
+        "// header\nfn human() {}\nfn helper() -> u32 {\n    42\n}\nfn later() {}\n",
+    )
+    .unwrap();
+
+    let lines = blame_file(
+        Path::new("src/lib.rs"),
+        &repo,
+        Some(&sessions.to_string_lossy()),
+    )
+    .unwrap();
+    assert_eq!(
+        lines
+            .iter()
+            .map(|line| line.content.as_str())
+            .collect::<Vec<_>>(),
+        vec![
+            "// header",
+            "fn human() {}",
+            "fn helper() -> u32 {",
+            "    42",
+            "}",
+            "fn later() {}"
+        ]
+    );
+    assert_eq!(lines[0].author, "Pi Test");
+    assert_eq!(lines[0].agent, None);
+    assert_eq!(lines[1].agent, None);
+    for line in &lines[2..5] {
+        let agent = line.agent.as_ref().unwrap();
+        assert_eq!(agent.session_id, session_id);
+        assert_eq!(agent.turn, 2);
+        assert_eq!(agent.tool_call_id, "call-1");
+    }
+    assert_eq!(lines[5].author, "Not Committed Yet");
+    assert_eq!(lines[5].agent, None);
+
+    let _ = fs::remove_dir_all(&repo);
+}
@@ -0,0 +1,121 @@
+use pi::cli::compare::{render_columns, render_markdown, run_compare, CompareResult};
+use pi::coding_agent::Model;
+use pi::core::messages::{AssistantMessage, ContentBlock, Cost, Usage};
+use std::thread;
+use std::time::Duration;
+
+fn model(provider: &str, id: &str, input_price: f64) -> Model {
+    Model {
+        id: id.to_string(),
+        name: id.to_string(),
+        api: "openai-responses".to_string(),
+        provider: provider.to_string(),
+        base_url: "https://example.com".to_string(),
+        reasoning: false,
+        input: vec!["text".to_string()],
+        cost: Cost {
+            input: input_price,
+            output: 0.0,
+            cache_read: 0.0,
+            cache_write: 0.0,
+            total: 0.0,
+        },
+        context_window: 128_000,
+        max_tokens: 4096,
+        headers: None,
+    }
+}
+
+fn reply(model: &Model, text: &str) -> AssistantMessage {
+    AssistantMessage {
+        content: vec![ContentBlock::Text {
+            text: text.to_string(),
+            text_signature: None,
+        }],
+        api: model.api.clone(),
+        provider: model.provider.clone(),
+        model: model.id.clone(),
+        usage: Usage {
+            input: 1_000_000,
+            output: 10,
+            cache_read: 0,
+            cache_write: 0,
+            total_tokens: None,
+            cost: None,
+        },
+        stop_reason: "stop".to_string(),
+        error_message: None,
+        timestamp: 0,
+    }
+}
+
+fn sample_results() -> Vec<CompareResult> {
+    let fast = model("openai", "fast", 0.5);
+    let slow = model("anthropic", "slow", 3.0);
+    vec![
+        CompareResult {
+            response: Ok(reply(&fast, "Use a HashMap.")),
+            model: fast,
+            latency: Duration::from_millis(1200),
+        },
+        CompareResult {
+            model: slow,
+            response: Err("rate limited".to_string()),
+            latency: Duration::from_millis(300),
+        },
+    ]
+}
+
+#[test]
+fn runs_models_in_parallel_and_keeps_their_order() {
+    let models = [
+        model("a", "slow", 0.0),
+        model("b", "fast", 0.0),
+        model("c", "broken", 0.0),
+    ];
+    let results = run_compare(&models, |model| {
+        if model.id == "slow" {
+            thread::sleep(Duration::from_millis(200));
+        }
+        if model.id == "broken" {
+            return Err("boom".to_string());
+        }
+        Ok(reply(model, &format!("from {}", model.id)))
+    });
+    let labels = results.iter().map(CompareResult::label).collect::<Vec<_>>();
+    assert_eq!(labels, ["a/slow", "b/fast", "c/broken"]);
+    assert!(results[0].latency > results[1].latency);
+    assert_eq!(results[1].response.as_ref().unwrap().model, "fast");
+    assert_eq!(results[2].response, Err("boom".to_string()));
+    // No provider-reported cost, so the registry price applies: 1M input tokens at $0.50.
+    assert_eq!(results[0].cost(), 0.0);
+    assert!((sample_results()[0].cost() - 0.5).abs() < 1e-9);
+}
+
+#[test]
+fn renders_side_by_side_columns_or_stacks_when_narrow() {
+    let results = sample_results();
+    let columns = render_columns(&results, 80);
+    assert!(columns[0].starts_with("openai/fast"));
+    assert!(columns[0].contains(" │ anthropic/slow"));
+    assert!(columns[1].contains("1.2s · $0.5000"));
+    assert!(columns
+        .iter()
+        .any(|line| line.contains("Use a HashMap.") && line.contains("[error] rate limited")));
+    assert!(columns.iter().all(|line| line.chars().count() <= 80));
+
+    let stacked = render_columns(&results, 40);
+    assert_eq!(stacked[0], "── openai/fast (1.2s · $0.5000)");
+    assert_eq!(stacked[1], "Use a HashMap.");
+    assert_eq!(stacked[3], "── anthropic/slow (0.3s · $0.0000)");
+}
+
+#[test]
+fn markdown_report_lists_latency_cost_and_responses() {
+    let report = render_markdown("Which map?", &sample_results());
+    assert!(report.contains("> Which map?"));
+    assert!(report.contains("| openai/fast | 1.2s | $0.5000 | 1000000/10 | ok |"));
+    assert!(report.contains("| anthropic/slow | 0.3s | $0.0000 | - | error |"));
+    assert!(report.contains("## openai/fast\n\nUse a HashMap.\n"));
+    assert!(report.contains("## anthropic/slow\n\n[error] rate limited\n"));
+}
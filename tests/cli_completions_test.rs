@@ -0,0 +1,112 @@
+use pi::cli::capabilities::{format_skill_list, format_tool_list};
+use pi::cli::completions::generate_completions;
+use pi::coding_agent::extension_host::ExtensionTool;
+use pi::coding_agent::{Skill, SkillArgument};
+use pi::{parse_args, FLAGS, SUBCOMMANDS};
+use std::process::Command;
+
+fn parse(input: &[&str]) -> pi::Args {
+    let args = input
+        .iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>();
+    parse_args(&args, None)
+}
+
+#[test]
+fn flag_table_matches_the_parser() {
+    for spec in FLAGS {
+        let long = format!("--{}", spec.long);
+        // --list-models takes an optional pattern, so a following word is not a message.
+        if spec.long == "list-models" {
+            continue;
+        }
+        let value = spec.choices.first().copied().unwrap_or("1");
+        let parsed = match spec.value {
+            Some(_) => parse(&[&long, value, "hello"]),
+            None => parse(&[&long, "hello"]),
+        };
+        assert_eq!(parsed.messages, vec!["hello"], "{long}");
+        assert_ne!(parsed, parse(&["hello"]), "{long} is not parsed");
+    }
+}
+
+#[test]
+fn completion_scripts_cover_every_flag_and_subcommand() {
+    for shell in ["bash", "zsh", "fish"] {
+        let script = generate_completions(shell).unwrap();
+        for spec in FLAGS {
+            assert!(
+                script.contains(spec.long),
+                "{shell} is missing --{}",
+                spec.long
+            );
+        }
+        for (name, _) in SUBCOMMANDS {
+            assert!(script.contains(name), "{shell} is missing {name}");
+        }
+    }
+    let fish = generate_completions("fish").unwrap();
+    assert!(fish.contains("complete -c pi -l thinking -x -a 'off minimal low medium high xhigh'"));
+    assert!(fish.contains("complete -c pi -l print -s p -d 'Print mode (single-shot)'"));
+    assert!(generate_completions("powershell").is_err());
+}
+
+#[test]
+fn bash_completion_script_is_valid_bash() {
+    let script = generate_completions("bash").unwrap();
+    let output = Command::new("bash")
+        .arg("-n")
+        .arg("-c")
+        .arg(&script)
+        .output();
+    if let Ok(output) = output {
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+}
+
+#[test]
+fn parses_completions_and_list_flags() {
+    assert_eq!(
+        parse(&["completions", "zsh"]).completions.as_deref(),
+        Some("zsh")
+    );
+    assert!(parse(&["--list-tools"]).list_tools);
+    assert!(parse(&["--list-skills"]).list_skills);
+}
+
+#[test]
+fn lists_tools_and_skills_with_descriptions() {
+    let tools = format_tool_list(&[ExtensionTool {
+        name: "deploy".to_string(),
+        label: None,
+        description: Some("Deploy the app.".to_string()),
+        parameters: None,
+    }]);
+    assert!(tools.starts_with("read           Read the contents of a file. (default)\n"));
+    assert!(tools.contains("git_diff       "));
+    assert!(tools.ends_with("deploy         Deploy the app. (extension)\n"));
+
+    let skills = format_skill_list(&[Skill {
+        name: "review".to_string(),
+        description: "Review a file.".to_string(),
+        file_path: "/skills/review/SKILL.md".to_string(),
+        base_dir: "/skills/review".to_string(),
+        source: "user".to_string(),
+        arguments: vec![SkillArgument {
+            name: "path".to_string(),
+            required: true,
+            ..SkillArgument::default()
+        }],
+        allowed_tools: Vec::new(),
+    }]);
+    assert_eq!(
+        skills,
+        "review <path>  Review a file. (/skills/review/SKILL.md)\n"
+    );
+    assert_eq!(format_skill_list(&[]), "No skills found.\n");
+}
@@ -0,0 +1,116 @@
+use pi::cli::config_command::parse_config_value;
+use pi::cli::runtime::apply_settings_to_args;
+use pi::coding_agent::{SettingsManager, SettingsScope};
+use pi::parse_args;
+use serde_json::json;
+use std::fs;
+use uuid::Uuid;
+
+struct Dirs {
+    root: std::path::PathBuf,
+}
+
+impl Dirs {
+    fn new() -> Self {
+        let root = std::env::temp_dir().join(format!("pi-config-{}", Uuid::new_v4()));
+        fs::create_dir_all(root.join("project")).unwrap();
+        fs::create_dir_all(root.join("agent")).unwrap();
+        Self { root }
+    }
+
+    fn manager(&self) -> SettingsManager {
+        SettingsManager::create(
+            self.root.join("project").to_string_lossy(),
+            self.root.join("agent").to_string_lossy(),
+        )
+    }
+}
+
+impl Drop for Dirs {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+#[test]
+fn project_settings_override_global_settings() {
+    let dirs = Dirs::new();
+    let mut manager = dirs.manager();
+    manager
+        .set_value("defaultModel", json!("global-model"), SettingsScope::Global)
+        .unwrap();
+    manager
+        .set_value("compaction.threshold", json!(0.5), SettingsScope::Global)
+        .unwrap();
+    manager
+        .set_value("compaction.threshold", json!(0.9), SettingsScope::Project)
+        .unwrap();
+    manager
+        .set_value("tools", json!(["read", "grep"]), SettingsScope::Project)
+        .unwrap();
+
+    let manager = dirs.manager();
+    assert_eq!(
+        manager.get_value("defaultModel", None),
+        Some(json!("global-model"))
+    );
+    assert_eq!(
+        manager.get_value("compaction.threshold", None),
+        Some(json!(0.9))
+    );
+    assert_eq!(
+        manager.get_value("compaction.threshold", Some(SettingsScope::Global)),
+        Some(json!(0.5))
+    );
+    assert_eq!(
+        manager.get_value("tools", Some(SettingsScope::Global)),
+        None
+    );
+    assert!(dirs.root.join("project/.pi/settings.json").exists());
+
+    let mut parsed = parse_args(&[], None);
+    apply_settings_to_args(&mut parsed, &manager);
+    assert_eq!(
+        parsed.tools,
+        Some(vec!["read".to_string(), "grep".to_string()])
+    );
+    let mut parsed = parse_args(&["--tools".to_string(), "bash".to_string()], None);
+    apply_settings_to_args(&mut parsed, &manager);
+    assert_eq!(parsed.tools, Some(vec!["bash".to_string()]));
+}
+
+#[test]
+fn set_value_validates_keys_and_preserves_the_rest_of_the_file() {
+    let dirs = Dirs::new();
+    let global = dirs.root.join("agent/settings.json");
+    fs::write(&global, r#"{"customKey": 1, "theme": "dark"}"#).unwrap();
+    let mut manager = dirs.manager();
+
+    let err = manager
+        .set_value("defaultModle", json!("x"), SettingsScope::Global)
+        .unwrap_err();
+    assert!(err.contains("Unknown setting"), "{err}");
+    let err = manager
+        .set_value("compaction.enabled", json!("yes"), SettingsScope::Global)
+        .unwrap_err();
+    assert!(err.contains("Invalid value"), "{err}");
+
+    manager
+        .set_value(
+            "hideThinkingBlock",
+            parse_config_value("true"),
+            SettingsScope::Global,
+        )
+        .unwrap();
+    manager
+        .set_value("theme", parse_config_value("null"), SettingsScope::Global)
+        .unwrap();
+    assert!(manager.get_hide_thinking_block());
+    assert_eq!(manager.get_theme(), None);
+    let saved: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&global).unwrap()).unwrap();
+    assert_eq!(saved, json!({ "customKey": 1, "hideThinkingBlock": true }));
+
+    assert_eq!(parse_config_value("light"), json!("light"));
+    assert_eq!(parse_config_value("0.8"), json!(0.8));
+}
@@ -0,0 +1,163 @@
+use pi::agent::{get_model, Agent, AgentOptions, AgentStateOverride, AgentTool};
+use pi::cli::exit_codes::{classify_error, is_tool_denial, ExitReason, PrintError};
+use pi::coding_agent::{
+    AgentSession, AgentSessionConfig, AgentSessionError, AuthStorage, ModelRegistry,
+    SettingsManager,
+};
+use pi::core::messages::{AssistantMessage, ContentBlock, Usage};
+use pi::core::session_manager::SessionManager;
+use pi::modes::run_print_mode_session;
+use pi::Mode;
+use serde_json::json;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+fn reply(content: Vec<ContentBlock>, stop_reason: &str, error: Option<&str>) -> AssistantMessage {
+    AssistantMessage {
+        content,
+        api: "anthropic-messages".to_string(),
+        provider: "anthropic".to_string(),
+        model: "mock".to_string(),
+        usage: Usage {
+            input: 0,
+            output: 0,
+            cache_read: 0,
+            cache_write: 0,
+            total_tokens: None,
+            cost: None,
+        },
+        stop_reason: stop_reason.to_string(),
+        error_message: error.map(str::to_string),
+        timestamp: 0,
+    }
+}
+
+fn text(value: &str) -> ContentBlock {
+    ContentBlock::Text {
+        text: value.to_string(),
+        text_signature: None,
+    }
+}
+
+/// A session whose model gives `replies` in turn and whose only tool, `read`, is refused by
+/// the sandbox.
+fn create_session(replies: Vec<AssistantMessage>) -> AgentSession {
+    let tool = AgentTool {
+        name: "read".to_string(),
+        label: "read".to_string(),
+        description: "Read".to_string(),
+        parameters: None,
+        execute: Rc::new(|_call_id, _params, _on_update| {
+            Err("Access denied: /etc/shadow is outside the workspace".into())
+        }),
+    };
+    let replies = std::cell::RefCell::new(replies.into_iter());
+    let stream_fn: Box<pi::agent::StreamFn> = Box::new(move |_model, _context, _events| {
+        replies
+            .borrow_mut()
+            .next()
+            .unwrap_or_else(|| reply(vec![text("done")], "stop", None))
+    });
+    let agent = Agent::new(AgentOptions {
+        initial_state: Some(AgentStateOverride {
+            model: Some(get_model("anthropic", "claude-sonnet-4-5")),
+            system_prompt: Some("Test".to_string()),
+            tools: Some(vec![tool]),
+            ..Default::default()
+        }),
+        stream_fn: Some(stream_fn),
+        ..Default::default()
+    });
+    AgentSession::new(AgentSessionConfig {
+        agent,
+        session_manager: SessionManager::in_memory(),
+        settings_manager: SettingsManager::create("", ""),
+        model_registry: ModelRegistry::new(AuthStorage::new(PathBuf::from("auth.json")), None),
+    })
+}
+
+fn run(replies: Vec<AssistantMessage>) -> Result<(), PrintError> {
+    let mut session = create_session(replies);
+    run_print_mode_session(Mode::Json, &mut session, &["hi".to_string()], None, &[])
+}
+
+#[test]
+fn exit_codes_are_distinct_and_named() {
+    let codes = ExitReason::ALL
+        .iter()
+        .map(|reason| reason.code())
+        .collect::<HashSet<_>>();
+    assert_eq!(codes.len(), ExitReason::ALL.len());
+    assert_eq!(ExitReason::Error.code(), 1);
+
+    let error = PrintError::new(ExitReason::BudgetExceeded, "over");
+    assert_eq!(
+        error.to_json(),
+        json!({"type": "error", "code": "budget_exceeded", "exitCode": 7, "message": "over"})
+    );
+}
+
+#[test]
+fn classifies_error_text() {
+    assert_eq!(
+        classify_error("HTTP 401: invalid x-api-key"),
+        ExitReason::Auth
+    );
+    assert_eq!(
+        classify_error("No models available. Set an API key in auth.json or env."),
+        ExitReason::Auth
+    );
+    assert_eq!(
+        classify_error("Model anthropic/claude-9 not found"),
+        ExitReason::ModelNotFound
+    );
+    assert_eq!(
+        classify_error("No models match pattern(s): gpt-9"),
+        ExitReason::ModelNotFound
+    );
+    assert_eq!(
+        classify_error("Failed to read cwd: gone"),
+        ExitReason::Error
+    );
+    assert_eq!(
+        PrintError::from_provider("529 overloaded_error").reason,
+        ExitReason::Provider
+    );
+    assert_eq!(
+        PrintError::from(AgentSessionError::BudgetExceeded("spent".to_string())).reason,
+        ExitReason::BudgetExceeded
+    );
+    assert!(is_tool_denial("Command blocked by bash policy: rm -rf"));
+    assert!(!is_tool_denial("Command exited with code 1"));
+}
+
+#[test]
+fn print_mode_reports_provider_failures_by_kind() {
+    let auth = run(vec![reply(Vec::new(), "error", Some("401 Unauthorized"))]);
+    assert_eq!(auth.unwrap_err().reason, ExitReason::Auth);
+
+    let provider = run(vec![reply(Vec::new(), "error", Some("500 Internal"))]);
+    assert_eq!(provider.unwrap_err().reason, ExitReason::Provider);
+
+    let aborted = run(vec![reply(vec![text("part")], "aborted", None)]);
+    assert_eq!(aborted.unwrap_err().reason, ExitReason::Aborted);
+
+    assert_eq!(run(Vec::new()), Ok(()));
+}
+
+#[test]
+fn print_mode_fails_when_a_tool_call_was_denied() {
+    let call = ContentBlock::ToolCall {
+        id: "call-1".to_string(),
+        name: "read".to_string(),
+        arguments: json!({"path": "/etc/shadow"}),
+        thought_signature: None,
+    };
+    let error = run(vec![reply(vec![call], "toolUse", None)]).unwrap_err();
+    assert_eq!(error.reason, ExitReason::ToolDenied);
+    assert_eq!(
+        error.message,
+        "read: Access denied: /etc/shadow is outside the workspace"
+    );
+}
@@ -0,0 +1,44 @@
+use pi::cli::file_inputs::{build_file_inputs, extract_file_references, read_stdin_input};
+
+#[test]
+fn extracts_existing_file_references_from_prompt() {
+    let dir = std::env::temp_dir().join(format!("pi-file-refs-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let notes = dir.join("notes.md");
+    std::fs::write(&notes, "remember this").unwrap();
+    let notes = notes.display().to_string();
+
+    let prompt = format!("summarize @{notes}, then compare with @{notes} and @missing.txt");
+    let references = extract_file_references(&prompt);
+    assert_eq!(references, vec![notes.clone()]);
+
+    let inputs = build_file_inputs(&references).unwrap();
+    assert_eq!(
+        inputs.text_prefix,
+        format!("<file name=\"{notes}\">\nremember this\n</file>\n")
+    );
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn wraps_piped_stdin_and_truncates_past_the_limit() {
+    let text = read_stdin_input("error: boom\n".as_bytes(), 100).unwrap();
+    assert_eq!(text.as_deref(), Some("<stdin>\nerror: boom\n</stdin>\n"));
+
+    let text = read_stdin_input("héllo world".as_bytes(), 2).unwrap();
+    assert_eq!(
+        text.as_deref(),
+        Some("<stdin>\nh\n[... stdin truncated after 1 bytes]\n</stdin>\n")
+    );
+
+    // Only the limit (and one byte past it) is read, whatever is still in the pipe.
+    let mut endless = std::io::Read::chain("a".as_bytes(), std::io::repeat(b'b'));
+    let text = read_stdin_input(&mut endless, 4).unwrap();
+    assert_eq!(
+        text.as_deref(),
+        Some("<stdin>\nabbb\n[... stdin truncated after 4 bytes]\n</stdin>\n")
+    );
+
+    assert_eq!(read_stdin_input(" \n".as_bytes(), 100).unwrap(), None);
+}
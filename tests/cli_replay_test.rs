@@ -0,0 +1,193 @@
+use pi::agent::{
+    get_model, load_fixture_responses, recording_stream_fn, replay_stream_fn,
+    AgentMessage as Message, StreamEvents,
+};
+use pi::cli::replay::{create_replay_session, load_session_replay};
+use pi::core::messages::{
+    AgentMessage, AssistantMessage, ContentBlock, ToolResultMessage, Usage, UserContent,
+    UserMessage,
+};
+use pi::core::session_manager::SessionManager;
+use pi::modes::run_print_mode_session;
+use pi::{parse_args, Mode};
+use serde_json::json;
+use std::fs;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+fn temp_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pi-replay-{}", Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn user(text: &str) -> AgentMessage {
+    AgentMessage::User(UserMessage {
+        content: UserContent::Text(text.to_string()),
+        timestamp: 0,
+    })
+}
+
+fn response(content: Vec<ContentBlock>, stop_reason: &str) -> AssistantMessage {
+    AssistantMessage {
+        content,
+        api: "anthropic-messages".to_string(),
+        provider: "anthropic".to_string(),
+        model: "claude-recorded".to_string(),
+        usage: Usage {
+            input: 0,
+            output: 0,
+            cache_read: 0,
+            cache_write: 0,
+            total_tokens: None,
+            cost: None,
+        },
+        stop_reason: stop_reason.to_string(),
+        error_message: None,
+        timestamp: 0,
+    }
+}
+
+fn text(value: &str) -> ContentBlock {
+    ContentBlock::Text {
+        text: value.to_string(),
+        text_signature: None,
+    }
+}
+
+fn texts(messages: &[Message]) -> Vec<String> {
+    messages
+        .iter()
+        .map(|message| {
+            let content = match message {
+                Message::User(user) => match &user.content {
+                    UserContent::Text(text) => return format!("user: {text}"),
+                    UserContent::Blocks(blocks) => blocks.clone(),
+                },
+                Message::Assistant(assistant) => assistant.content.clone(),
+                Message::ToolResult(result) => result.content.clone(),
+                Message::Custom(custom) => return custom.text.clone(),
+            };
+            let text = content
+                .iter()
+                .map(|block| match block {
+                    ContentBlock::Text { text, .. } => text.clone(),
+                    ContentBlock::ToolCall { name, .. } => format!("call {name}"),
+                    _ => String::new(),
+                })
+                .collect::<String>();
+            format!("{}: {text}", message.role())
+        })
+        .collect()
+}
+
+#[test]
+fn replays_a_recorded_session_without_a_provider() {
+    let dir = temp_dir();
+    let mut recorded = SessionManager::create_with_dir(dir.clone(), dir.clone());
+    recorded.append_message(user("list files"));
+    recorded.append_message(AgentMessage::Assistant(response(
+        vec![ContentBlock::ToolCall {
+            id: "call-1".to_string(),
+            name: "ls".to_string(),
+            arguments: json!({}),
+            thought_signature: None,
+        }],
+        "toolUse",
+    )));
+    recorded.append_message(AgentMessage::ToolResult(ToolResultMessage {
+        tool_call_id: "call-1".to_string(),
+        tool_name: "ls".to_string(),
+        content: vec![text("Cargo.toml\nsrc")],
+        details: None,
+        is_error: false,
+        timestamp: 0,
+    }));
+    recorded.append_message(AgentMessage::Assistant(response(
+        vec![text("Two entries.")],
+        "stop",
+    )));
+    recorded.append_message(user("thanks"));
+    recorded.append_message(AgentMessage::Assistant(response(
+        vec![text("You're welcome.")],
+        "stop",
+    )));
+
+    let replay = load_session_replay(&recorded.get_session_file().unwrap()).unwrap();
+    assert_eq!(replay.prompts, vec!["list files", "thanks"]);
+    assert_eq!(replay.responses.len(), 3);
+    assert_eq!(replay.tools.len(), 1);
+
+    let mut session = create_replay_session(replay.tools, replay.responses);
+    assert_eq!(session.agent.state().model.id, "claude-recorded");
+    run_print_mode_session(Mode::Json, &mut session, &replay.prompts, None, &[]).unwrap();
+    assert_eq!(
+        texts(&session.messages()),
+        vec![
+            "user: list files",
+            "assistant: call ls",
+            "toolResult: Cargo.toml\nsrc",
+            "assistant: Two entries.",
+            "user: thanks",
+            "assistant: You're welcome.",
+        ]
+    );
+}
+
+#[test]
+fn records_fixtures_and_replays_them_in_order() {
+    let dir = temp_dir().join("fixtures");
+    let model = get_model("anthropic", "claude-sonnet-4-5");
+    let context = pi::agent::LlmContext {
+        system_prompt: String::new(),
+        messages: Vec::new(),
+    };
+    let mut events = StreamEvents::new(Box::new(|_| {}));
+
+    let answers = std::cell::Cell::new(0);
+    let mut recording = recording_stream_fn(
+        Box::new(move |_model, _context, _events| {
+            answers.set(answers.get() + 1);
+            response(vec![text(&format!("answer {}", answers.get()))], "stop")
+        }),
+        dir.clone(),
+    );
+    recording(&model, &context, &mut events);
+    recording(&model, &context, &mut events);
+
+    let responses = load_fixture_responses(&dir).unwrap();
+    assert_eq!(responses.len(), 2);
+    assert_eq!(responses[1].content, vec![text("answer 2")]);
+
+    let mut replay = replay_stream_fn(responses);
+    assert_eq!(
+        replay(&model, &context, &mut events).content,
+        vec![text("answer 1")]
+    );
+    replay(&model, &context, &mut events);
+    let exhausted = replay(&model, &context, &mut events);
+    assert_eq!(exhausted.stop_reason, "error");
+    assert_eq!(
+        exhausted.error_message.as_deref(),
+        Some("No more recorded responses to replay")
+    );
+
+    assert!(load_fixture_responses(&temp_dir()).is_err());
+}
+
+#[test]
+fn parses_replay_and_record_fixtures() {
+    let args = ["replay", "session.jsonl", "--mode", "json"]
+        .map(String::from)
+        .to_vec();
+    let parsed = parse_args(&args, None);
+    assert_eq!(parsed.replay.as_deref(), Some("session.jsonl"));
+    assert_eq!(parsed.mode, Some(Mode::Json));
+
+    let args = ["--record-fixtures", "fixtures", "-p", "hi"]
+        .map(String::from)
+        .to_vec();
+    let parsed = parse_args(&args, None);
+    assert_eq!(parsed.record_fixtures.as_deref(), Some("fixtures"));
+    assert_eq!(parsed.messages, vec!["hi"]);
+}
@@ -0,0 +1,95 @@
+use pi::agent::AgentMessage as LlmMessage;
+use pi::cli::replay_turn::{load_replay_turn, response_text};
+use pi::core::diff::diff_lines;
+use pi::core::messages::{
+    AgentMessage, AssistantMessage, ContentBlock, Usage, UserContent, UserMessage,
+};
+use pi::core::session_manager::SessionManager;
+use std::fs;
+use uuid::Uuid;
+
+fn user(text: &str) -> AgentMessage {
+    AgentMessage::User(UserMessage {
+        content: UserContent::Text(text.to_string()),
+        timestamp: 0,
+    })
+}
+
+fn assistant(text: &str) -> AgentMessage {
+    AgentMessage::Assistant(AssistantMessage {
+        content: vec![ContentBlock::Text {
+            text: text.to_string(),
+            text_signature: None,
+        }],
+        api: "anthropic-messages".to_string(),
+        provider: "anthropic".to_string(),
+        model: "claude-old".to_string(),
+        usage: Usage {
+            input: 0,
+            output: 0,
+            cache_read: 0,
+            cache_write: 0,
+            total_tokens: None,
+            cost: None,
+        },
+        stop_reason: "stop".to_string(),
+        error_message: None,
+        timestamp: 0,
+    })
+}
+
+#[test]
+fn rebuilds_the_context_a_turn_was_sent_with() {
+    let dir = std::env::temp_dir().join(format!("pi-replay-turn-{}", Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    let mut session = SessionManager::create_with_dir(dir.clone(), dir.clone());
+    session.append_message(user("first question"));
+    session.append_message(assistant("first answer"));
+    let prompt_id = session.append_message(user("second question"));
+    let response_id = session.append_message(assistant("second answer\nwith detail"));
+    session.append_message(user("third question"));
+    let path = session.get_session_file().unwrap();
+
+    let turn = load_replay_turn(&path, &response_id, "system".to_string()).unwrap();
+    assert_eq!(turn.context.system_prompt, "system");
+    let texts = turn
+        .context
+        .messages
+        .iter()
+        .map(|message| match message {
+            LlmMessage::User(user) => match &user.content {
+                UserContent::Text(text) => text.clone(),
+                UserContent::Blocks(_) => String::new(),
+            },
+            LlmMessage::Assistant(assistant) => response_text(assistant),
+            _ => String::new(),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        texts,
+        vec!["first question", "first answer", "second question"]
+    );
+    let original = turn.original.unwrap();
+    assert_eq!(response_text(&original), "second answer\nwith detail");
+
+    // Naming the prompt replays the response that followed it.
+    let from_prompt = load_replay_turn(&path, &prompt_id, String::new()).unwrap();
+    assert_eq!(from_prompt.context.messages.len(), 3);
+    assert_eq!(from_prompt.original, Some(original));
+
+    assert!(load_replay_turn(&path, "missing", String::new())
+        .unwrap_err()
+        .contains("Entry missing not found"));
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn diffs_responses_line_by_line() {
+    assert_eq!(
+        diff_lines("a\nb\nc", "a\nx\nc\nd"),
+        vec![" a", "-b", "+x", " c", "+d"]
+    );
+    assert_eq!(diff_lines("", "new"), vec!["+new"]);
+    assert!(diff_lines("", "").is_empty());
+}
@@ -0,0 +1,119 @@
+use pi::cli::stats::collect_stats;
+use pi::core::messages::{
+    AgentMessage, AssistantMessage, ContentBlock, Cost, ToolResultMessage, Usage, UserContent,
+    UserMessage,
+};
+use pi::core::session_manager::SessionManager;
+use serde_json::json;
+use std::fs;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+fn user(text: &str) -> AgentMessage {
+    AgentMessage::User(UserMessage {
+        content: UserContent::Text(text.to_string()),
+        timestamp: 0,
+    })
+}
+
+fn assistant(provider: &str, model: &str, tool: Option<&str>, cost: f64) -> AgentMessage {
+    let content = match tool {
+        Some(name) => vec![ContentBlock::ToolCall {
+            id: "call-1".to_string(),
+            name: name.to_string(),
+            arguments: json!({}),
+            thought_signature: None,
+        }],
+        None => vec![ContentBlock::Text {
+            text: "done".to_string(),
+            text_signature: None,
+        }],
+    };
+    AgentMessage::Assistant(AssistantMessage {
+        content,
+        api: "test".to_string(),
+        provider: provider.to_string(),
+        model: model.to_string(),
+        usage: Usage {
+            input: 100,
+            output: 20,
+            cache_read: 10,
+            cache_write: 0,
+            total_tokens: None,
+            cost: Some(Cost {
+                input: 0.0,
+                output: 0.0,
+                cache_read: 0.0,
+                cache_write: 0.0,
+                total: cost,
+            }),
+        },
+        stop_reason: "stop".to_string(),
+        error_message: None,
+        timestamp: 0,
+    })
+}
+
+fn failed_tool_result(name: &str) -> AgentMessage {
+    AgentMessage::ToolResult(ToolResultMessage {
+        tool_call_id: "call-1".to_string(),
+        tool_name: name.to_string(),
+        content: Vec::new(),
+        details: None,
+        is_error: true,
+        timestamp: 0,
+    })
+}
+
+#[test]
+fn aggregates_usage_across_projects_without_double_counting_branches() {
+    let root = std::env::temp_dir().join(format!("pi-stats-{}", Uuid::new_v4()));
+    let busy = PathBuf::from("/work/busy");
+    let quiet = PathBuf::from("/work/quiet");
+
+    let mut first = SessionManager::create_with_dir(busy.clone(), root.join("busy"));
+    first.append_message(user("fix the build"));
+    first.append_message(assistant("anthropic", "claude", Some("bash"), 0.02));
+    first.append_message(failed_tool_result("bash"));
+    let answer = first.append_message(assistant("openai", "gpt", None, 0.01));
+    first.append_message(user("and the tests"));
+    first.append_message(assistant("anthropic", "claude", None, 0.02));
+    // The branch copies the first turn into a new file.
+    first.create_branched_session(&answer).unwrap();
+
+    let mut second = SessionManager::create_with_dir(quiet.clone(), root.join("quiet"));
+    second.append_message(user("hello"));
+    second.append_message(assistant("anthropic", "claude", None, 0.01));
+
+    let stats = collect_stats(&root);
+    assert_eq!(stats.sessions, 3);
+    assert_eq!(stats.turns, 3);
+    assert_eq!(stats.total.responses, 4);
+    assert_eq!(stats.total.total_tokens(), 4 * 130);
+    assert!((stats.total.cost - 0.06).abs() < 1e-9);
+    assert_eq!(stats.by_model["anthropic/claude"].responses, 3);
+    assert_eq!(stats.by_model["openai/gpt"].responses, 1);
+    assert_eq!(stats.by_provider["anthropic"].responses, 3);
+    assert_eq!(
+        stats
+            .by_day
+            .values()
+            .map(|day| day.responses)
+            .sum::<usize>(),
+        4
+    );
+    assert_eq!(stats.tool_calls["bash"], 1);
+    assert_eq!(stats.tool_errors, 1);
+    assert!(stats.average_turn_latency_ms.is_some());
+
+    assert_eq!(stats.projects[0].cwd, busy.to_string_lossy());
+    assert_eq!(stats.projects[0].sessions, 2);
+    assert_eq!(stats.projects[0].turns, 2);
+    assert_eq!(stats.projects[1].cwd, quiet.to_string_lossy());
+
+    let json = serde_json::to_value(&stats).unwrap();
+    assert_eq!(json["byModel"]["openai/gpt"]["inputTokens"], 100);
+    assert_eq!(json["projects"][1]["turns"], 1);
+
+    let _ = fs::remove_dir_all(root);
+}
@@ -0,0 +1,138 @@
+use pi::cli::exit_codes::is_auth_error;
+use pi::cli::token_refresh::TokenRefresher;
+use pi::coding_agent::{AuthCredential, AuthStorage};
+use pi::Error;
+use serde_json::json;
+use std::fs;
+use std::path::PathBuf;
+
+const FAR_FUTURE: i64 = 4_102_444_800_000;
+
+fn auth_error(message: &str) -> Error {
+    Error::from_status(401, None, message.to_string())
+}
+
+fn fake_refresh(_provider: &str, credential: &AuthCredential) -> Result<AuthCredential, String> {
+    let AuthCredential::OAuth { access, .. } = credential else {
+        return Err("not oauth".to_string());
+    };
+    Ok(AuthCredential::OAuth {
+        access: format!("{access}+"),
+        refresh: Some("refresh-2".to_string()),
+        expires: Some(FAR_FUTURE),
+        enterprise_url: None,
+        project_id: None,
+        email: None,
+        account_id: None,
+    })
+}
+
+fn auth_file(access: &str, expires: i64) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pi-token-refresh-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("auth.json");
+    fs::write(
+        &path,
+        json!({
+            "anthropic": {
+                "type": "oauth",
+                "access": access,
+                "refresh": "refresh-1",
+                "expires": expires
+            }
+        })
+        .to_string(),
+    )
+    .unwrap();
+    path
+}
+
+fn stored_access(path: &PathBuf) -> String {
+    match AuthStorage::new(path).get("anthropic") {
+        Some(AuthCredential::OAuth { access, .. }) => access.clone(),
+        other => panic!("unexpected credential: {other:?}"),
+    }
+}
+
+#[test]
+fn refreshes_expired_tokens_before_requests() {
+    let path = auth_file("token", 1000);
+    let mut refresher =
+        TokenRefresher::for_provider("anthropic", &path, "token").with_refresh_fn(fake_refresh);
+    assert!(refresher.is_refreshable());
+
+    assert_eq!(refresher.token(), "token+");
+    assert_eq!(stored_access(&path), "token+");
+    // Still valid, so no further refresh.
+    assert_eq!(refresher.token(), "token+");
+
+    let _ = fs::remove_dir_all(path.parent().unwrap());
+}
+
+#[test]
+fn retries_once_with_a_refreshed_token_after_an_auth_error() {
+    let path = auth_file("token", FAR_FUTURE);
+    let mut refresher =
+        TokenRefresher::for_provider("anthropic", &path, "token").with_refresh_fn(fake_refresh);
+
+    let mut sent = Vec::new();
+    let result = refresher.call_with_retry(|token| {
+        sent.push(token.to_string());
+        if token == "token" {
+            Err(auth_error("Anthropic error: OAuth token has expired."))
+        } else {
+            Ok("done")
+        }
+    });
+    assert_eq!(result.unwrap(), "done");
+    assert_eq!(sent, vec!["token".to_string(), "token+".to_string()]);
+    assert_eq!(stored_access(&path), "token+");
+
+    let mut attempts = 0;
+    let result: Result<(), Error> = refresher.call_with_retry(|_| {
+        attempts += 1;
+        Err(auth_error("Anthropic error: 401 unauthorized"))
+    });
+    assert!(result.is_err());
+    assert_eq!(attempts, 2);
+
+    let mut attempts = 0;
+    let result: Result<(), Error> = refresher.call_with_retry(|_| {
+        attempts += 1;
+        Err(Error::from_status(
+            529,
+            None,
+            "Anthropic error: Overloaded".to_string(),
+        ))
+    });
+    assert!(result.is_err());
+    assert_eq!(attempts, 1);
+
+    let _ = fs::remove_dir_all(path.parent().unwrap());
+}
+
+#[test]
+fn api_keys_and_overrides_are_not_refreshed() {
+    let path = auth_file("token", 1000);
+    let mut other = TokenRefresher::for_provider("anthropic", &path, "sk-ant-override")
+        .with_refresh_fn(fake_refresh);
+    assert!(!other.is_refreshable());
+    assert_eq!(other.token(), "sk-ant-override");
+
+    let mut fixed = TokenRefresher::fixed("sk-key").with_refresh_fn(fake_refresh);
+    let mut attempts = 0;
+    let result: Result<(), Error> = fixed.call_with_retry(|token| {
+        attempts += 1;
+        assert_eq!(token, "sk-key");
+        Err(auth_error("OpenAI error: Incorrect API key provided"))
+    });
+    assert!(result.is_err());
+    assert_eq!(attempts, 1);
+    assert_eq!(stored_access(&path), "token");
+
+    assert!(is_auth_error("Anthropic error: invalid x-api-key"));
+    assert!(is_auth_error("OpenAI error: 401 {}"));
+    assert!(!is_auth_error("Anthropic error: prompt is too long"));
+
+    let _ = fs::remove_dir_all(path.parent().unwrap());
+}
@@ -0,0 +1,60 @@
+use pi::cli::tool_run::run_tool_command;
+use pi::ToolRunCommand;
+use serde_json::json;
+use std::fs;
+use uuid::Uuid;
+
+#[test]
+fn runs_built_in_tools_without_a_model() {
+    let root = std::env::temp_dir().join(format!("pi-tool-run-{}", Uuid::new_v4()));
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(
+        root.join("src").join("lib.rs"),
+        "fn foo() {}\nfn bar() {}\n",
+    )
+    .unwrap();
+
+    let output = run_tool_command(
+        &ToolRunCommand {
+            tool: "read".to_string(),
+            params: json!({ "path": "src/lib.rs" }),
+            allow_all: false,
+        },
+        &root,
+    )
+    .unwrap();
+    assert_eq!(output["tool"], "read");
+    assert_eq!(output["content"][0]["type"], "text");
+    assert!(output["content"][0]["text"]
+        .as_str()
+        .unwrap()
+        .contains("fn bar() {}"));
+
+    let error = run_tool_command(
+        &ToolRunCommand {
+            tool: "read".to_string(),
+            params: json!({}),
+            allow_all: false,
+        },
+        &root,
+    )
+    .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Invalid arguments for tool \"read\":\n- /path: is required"
+    );
+    assert!(matches!(error, pi::Error::Tool(_)));
+
+    let error = run_tool_command(
+        &ToolRunCommand {
+            tool: "deploy".to_string(),
+            params: json!({}),
+            allow_all: false,
+        },
+        &root,
+    )
+    .unwrap_err();
+    assert_eq!(error.to_string(), "Tool \"deploy\" is not supported");
+
+    let _ = fs::remove_dir_all(&root);
+}
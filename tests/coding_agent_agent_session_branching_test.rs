@@ -163,3 +163,41 @@ fn should_branch_from_middle_of_conversation() {
     session.dispose();
     let _ = fs::remove_dir_all(&temp_dir);
 }
+
+#[test]
+fn should_check_out_an_earlier_reply_into_a_child_session() {
+    let temp_dir = create_temp_dir("pi-branching-test");
+    let mut session = create_session(true, &temp_dir);
+    let _unsubscribe = session.subscribe(|_| {});
+
+    session.prompt("Say one").unwrap();
+    session.prompt("Say two").unwrap();
+    let original_file = session.session_file().expect("session file");
+
+    let candidates = session.get_branch_checkout_candidates();
+    assert_eq!(candidates.len(), 2);
+    let first_reply = candidates.last().unwrap().entry_id.clone();
+
+    let checkout = session.branch_from(&first_reply).unwrap();
+    assert_eq!(checkout.entry_id, first_reply);
+    assert_eq!(checkout.parent_session.as_ref(), Some(&original_file));
+    let child_file = checkout.session_file.clone().expect("child session file");
+    assert_ne!(child_file, original_file);
+    assert_eq!(session.messages().len(), 2);
+    assert_eq!(
+        session.session_manager.get_parent_session(),
+        Some(original_file.clone())
+    );
+
+    session.prompt("Say three").unwrap();
+    assert!(child_file.exists());
+    let parent = SessionManager::open(original_file, Some(temp_dir.clone()));
+    let children = parent.list_child_sessions();
+    assert_eq!(children.len(), 1);
+    assert_eq!(children[0].path, child_file);
+
+    assert!(session.branch_from("missing").is_err());
+
+    session.dispose();
+    let _ = fs::remove_dir_all(&temp_dir);
+}
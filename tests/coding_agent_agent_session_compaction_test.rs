@@ -82,6 +82,7 @@ fn create_session(persist: bool, temp_dir: Option<&Path>) -> AgentSession {
             enabled: Some(true),
             reserve_tokens: None,
             keep_recent_tokens: Some(1),
+            threshold: None,
         }),
     });
 
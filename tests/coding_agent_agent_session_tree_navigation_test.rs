@@ -80,6 +80,7 @@ fn entry_type(entry: &SessionEntry) -> &'static str {
         SessionEntry::Custom(_) => "custom",
         SessionEntry::CustomMessage(_) => "custom_message",
         SessionEntry::Label(_) => "label",
+        SessionEntry::SessionInfo(_) => "session_info",
     }
 }
 
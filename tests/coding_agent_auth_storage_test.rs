@@ -0,0 +1,65 @@
+use pi::coding_agent::{AuthCredential, AuthStorage};
+use serde_json::json;
+use std::fs;
+
+fn api_key(key: &str) -> AuthCredential {
+    AuthCredential::ApiKey {
+        key: key.to_string(),
+    }
+}
+
+#[test]
+fn auth_profiles_select_credentials_and_fall_back_to_unnamed_entry() {
+    let dir = std::env::temp_dir().join(format!("pi-auth-{}", uuid::Uuid::new_v4()));
+    let path = dir.join("auth.json");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        &path,
+        json!({
+            "acme": { "type": "api_key", "key": "default-acme" },
+            "acme:work": { "type": "api_key", "key": "work-acme" },
+            "other": { "type": "api_key", "key": "default-other" }
+        })
+        .to_string(),
+    )
+    .unwrap();
+
+    let mut storage = AuthStorage::new(&path);
+    assert_eq!(storage.get_api_key("acme").as_deref(), Some("default-acme"));
+    assert_eq!(storage.profiles(), vec!["work".to_string()]);
+
+    storage.set_active_profile(Some("work"));
+    assert_eq!(storage.active_profile(), Some("work"));
+    assert_eq!(storage.get_api_key("acme").as_deref(), Some("work-acme"));
+    // No work credential for this provider, so the unnamed one is used.
+    assert_eq!(
+        storage.get_api_key("other").as_deref(),
+        Some("default-other")
+    );
+
+    storage.set_active_profile(Some("personal"));
+    storage.set("acme", api_key("personal-acme"));
+    assert_eq!(
+        storage.get_api_key("acme").as_deref(),
+        Some("personal-acme")
+    );
+    assert_eq!(
+        storage.provider_profiles("acme"),
+        vec!["personal".to_string(), "work".to_string()]
+    );
+
+    // Writes land under the profile key and leave the unnamed credential alone.
+    let reloaded = AuthStorage::new(&path);
+    assert_eq!(
+        reloaded.get_api_key("acme").as_deref(),
+        Some("default-acme")
+    );
+    assert!(reloaded.list().contains(&"acme:personal".to_string()));
+
+    storage.remove("acme");
+    assert_eq!(storage.get_api_key("acme").as_deref(), Some("default-acme"));
+    storage.set_active_profile(None);
+    assert_eq!(storage.get_api_key("acme").as_deref(), Some("default-acme"));
+
+    let _ = fs::remove_dir_all(&dir);
+}
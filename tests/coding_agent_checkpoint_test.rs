@@ -0,0 +1,153 @@
+use pi::agent::{get_model, Agent, AgentOptions, AgentStateOverride};
+use pi::coding_agent::{
+    AgentSession, AgentSessionConfig, AuthStorage, FileChange, ModelRegistry, SettingsManager,
+    SharedChangeJournal,
+};
+use pi::core::messages::{AssistantMessage, ContentBlock, Cost, Usage};
+use pi::core::session_manager::SessionManager;
+use std::fs;
+use std::path::PathBuf;
+
+type StreamFn = Box<pi::agent::StreamFn>;
+
+fn make_assistant_message(text: &str) -> AssistantMessage {
+    AssistantMessage {
+        content: vec![ContentBlock::Text {
+            text: text.to_string(),
+            text_signature: None,
+        }],
+        api: "anthropic-messages".to_string(),
+        provider: "anthropic".to_string(),
+        model: "mock".to_string(),
+        usage: Usage {
+            input: 0,
+            output: 0,
+            cache_read: 0,
+            cache_write: 0,
+            total_tokens: Some(0),
+            cost: Some(Cost {
+                input: 0.0,
+                output: 0.0,
+                cache_read: 0.0,
+                cache_write: 0.0,
+                total: 0.0,
+            }),
+        },
+        stop_reason: "stop".to_string(),
+        error_message: None,
+        timestamp: 0,
+    }
+}
+
+fn create_session() -> AgentSession {
+    let model = get_model("anthropic", "claude-sonnet-4-5");
+    let stream_fn: StreamFn =
+        Box::new(move |_model, _context, _events| make_assistant_message("ok"));
+
+    let agent = Agent::new(AgentOptions {
+        initial_state: Some(AgentStateOverride {
+            model: Some(model),
+            system_prompt: Some("Test".to_string()),
+            tools: Some(Vec::new()),
+            ..Default::default()
+        }),
+        stream_fn: Some(stream_fn),
+        ..Default::default()
+    });
+
+    let mut auth_storage = AuthStorage::new(PathBuf::from("auth.json"));
+    auth_storage.set_runtime_api_key("anthropic", "test-key");
+    AgentSession::new(AgentSessionConfig {
+        agent,
+        session_manager: SessionManager::in_memory(),
+        settings_manager: SettingsManager::create("", ""),
+        model_registry: ModelRegistry::new(auth_storage, None),
+    })
+}
+
+/// Writes `content` to `path` the way the write tool would, recording the change.
+fn write_file(journal: &SharedChangeJournal, path: &PathBuf, content: &str, call_id: &str) {
+    let original = fs::read_to_string(path).ok();
+    fs::write(path, content).unwrap();
+    journal.borrow_mut().record(FileChange::new(
+        path.clone(),
+        "write",
+        call_id,
+        original,
+        String::new(),
+    ));
+}
+
+#[test]
+fn rewind_restores_conversation_and_files() {
+    let dir = std::env::temp_dir().join(format!("pi-checkpoint-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    let notes = dir.join("notes.txt");
+    let scratch = dir.join("scratch.txt");
+
+    let mut session = create_session();
+    let journal = SharedChangeJournal::default();
+    session.set_change_journal(journal.clone());
+
+    session.prompt("First").unwrap();
+    write_file(&journal, &notes, "one", "call-1");
+    assert!(session.create_checkpoint("  ").is_err());
+    let checkpoint = session.create_checkpoint("before-refactor").unwrap();
+    assert_eq!(checkpoint.file_changes, 1);
+
+    session.prompt("Second").unwrap();
+    write_file(&journal, &notes, "two", "call-2");
+    write_file(&journal, &scratch, "new", "call-3");
+    assert_eq!(session.messages().len(), 4);
+
+    let result = session.rewind("before-refactor").unwrap();
+    assert!(result.files_restored);
+    assert_eq!(result.undone.len(), 2);
+    assert_eq!(fs::read_to_string(&notes).unwrap(), "one");
+    assert!(!scratch.exists());
+    assert_eq!(session.messages().len(), 2);
+    assert_eq!(
+        session.session_manager.get_leaf_id(),
+        Some(checkpoint.entry_id)
+    );
+    assert!(session.rewind("missing").is_err());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn checkpoints_on_other_branches_rewind_the_conversation_only() {
+    let dir = std::env::temp_dir().join(format!("pi-checkpoint-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    let notes = dir.join("notes.txt");
+
+    let mut session = create_session();
+    let journal = SharedChangeJournal::default();
+    session.set_change_journal(journal.clone());
+
+    session.create_checkpoint("start").unwrap();
+    session.prompt("First").unwrap();
+    write_file(&journal, &notes, "one", "call-1");
+    session.create_checkpoint("after-first").unwrap();
+
+    session.rewind("start").unwrap();
+    assert!(!notes.exists());
+    session.prompt("Other").unwrap();
+    write_file(&journal, &notes, "other", "call-2");
+
+    // The journal now holds a different change than the one "after-first" saw.
+    let result = session.rewind("after-first").unwrap();
+    assert!(!result.files_restored);
+    assert!(result.undone.is_empty());
+    assert_eq!(fs::read_to_string(&notes).unwrap(), "other");
+    assert_eq!(session.messages().len(), 2);
+
+    let names: Vec<String> = session
+        .checkpoints()
+        .into_iter()
+        .map(|checkpoint| checkpoint.name)
+        .collect();
+    assert_eq!(names, vec!["start", "after-first"]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
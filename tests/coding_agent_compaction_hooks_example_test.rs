@@ -45,6 +45,7 @@ fn custom_compaction_example_should_type_check_correctly() {
                     first_kept_entry_id: preparation.first_kept_entry_id.clone(),
                     tokens_before: preparation.tokens_before,
                 }),
+                pin: None,
             }
         });
     };
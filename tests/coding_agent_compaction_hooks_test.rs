@@ -66,6 +66,7 @@ fn create_session() -> AgentSession {
             enabled: Some(true),
             reserve_tokens: None,
             keep_recent_tokens: Some(1),
+            threshold: None,
         }),
     });
     let mut auth_storage = AuthStorage::new(PathBuf::from("auth.json"));
@@ -126,6 +127,7 @@ fn should_allow_hooks_to_cancel_compaction() {
         Some(Box::new(|_event| SessionBeforeCompactResult {
             cancel: Some(true),
             compaction: None,
+            pin: None,
         })),
         None,
     );
@@ -150,6 +152,7 @@ fn should_allow_hooks_to_provide_custom_compaction() {
                 first_kept_entry_id: event.preparation.first_kept_entry_id.clone(),
                 tokens_before: event.preparation.tokens_before,
             }),
+            pin: None,
         })),
         None,
     );
@@ -286,6 +289,7 @@ fn should_use_hook_compaction_even_with_different_values() {
                 first_kept_entry_id: event.preparation.first_kept_entry_id.clone(),
                 tokens_before: 999,
             }),
+            pin: None,
         })),
         None,
     );
@@ -296,3 +300,19 @@ fn should_use_hook_compaction_even_with_different_values() {
     assert_eq!(result.summary, "Custom summary with modified values");
     assert_eq!(result.tokens_before, 999);
 }
+
+#[test]
+fn should_use_configured_compaction_strategy_when_hooks_decline() {
+    let mut session = create_session();
+
+    assert_eq!(session.compaction_strategy_name(), "summarize");
+    assert!(session.set_compaction_strategy_name("bogus").is_err());
+    session.set_compaction_strategy(Some(Box::new(pi::core::compaction::TruncateStrategy)));
+    assert_eq!(session.compaction_strategy_name(), "truncate");
+
+    session.prompt("What is 2+2?").unwrap();
+    session.prompt("What is 3+3?").unwrap();
+
+    let result = session.compact().unwrap();
+    assert!(result.summary.contains("earlier message"));
+}
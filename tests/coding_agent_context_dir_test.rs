@@ -0,0 +1,102 @@
+use pi::coding_agent::{
+    collect_context_dir, ContextDirOptions, ContextFileStatus, ContextPlacement,
+};
+use std::fs;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+fn project() -> PathBuf {
+    let root = std::env::temp_dir().join(format!("pi-context-dir-{}", Uuid::new_v4()));
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::create_dir_all(root.join(".git")).unwrap();
+    fs::write(root.join("src").join("a.rs"), "fn a() {}\n").unwrap();
+    fs::write(root.join("src").join("b.rs"), "fn b() {}\nfn c() {}\n").unwrap();
+    fs::write(
+        root.join("src").join("logo.png"),
+        [0x89, b'P', b'N', b'G', 0, 1],
+    )
+    .unwrap();
+    fs::write(root.join("README.md"), "# Demo\n").unwrap();
+    fs::write(root.join(".git").join("HEAD"), "ref: refs/heads/main\n").unwrap();
+    root
+}
+
+#[test]
+fn preloads_matching_files_and_skips_binaries() {
+    let root = project();
+    let result = collect_context_dir(&ContextDirOptions {
+        dir: root.clone(),
+        include: vec!["src/*".to_string()],
+        exclude: vec!["**/b.rs".to_string()],
+        max_bytes: 1000,
+    })
+    .unwrap();
+
+    let paths = result
+        .files
+        .iter()
+        .map(|file| (file.path.as_str(), file.status.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        vec![
+            ("src/a.rs", ContextFileStatus::Included),
+            ("src/logo.png", ContextFileStatus::Binary),
+        ]
+    );
+    assert_eq!(
+        result.text,
+        format!(
+            "<file name=\"{}\">\nfn a() {{}}\n</file>\n",
+            root.join("src/a.rs").display()
+        )
+    );
+    assert_eq!(
+        result.report(),
+        "Context: 1 files (10 bytes) preloaded; skipped 1 binary"
+    );
+    let _ = fs::remove_dir_all(root);
+}
+
+#[test]
+fn truncates_the_file_that_crosses_the_byte_budget() {
+    let root = project();
+    let result = collect_context_dir(&ContextDirOptions {
+        dir: root.clone(),
+        include: vec!["**/*.rs".to_string(), "*.md".to_string()],
+        exclude: Vec::new(),
+        max_bytes: 27,
+    })
+    .unwrap();
+
+    // README.md (7) and src/a.rs (10) fit; src/b.rs is cut after its first line.
+    let statuses = result
+        .files
+        .iter()
+        .map(|file| file.status.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        statuses,
+        vec![
+            ContextFileStatus::Included,
+            ContextFileStatus::Included,
+            ContextFileStatus::Truncated { kept: 10 },
+        ]
+    );
+    assert!(result.text.contains("[... truncated 10 bytes]"));
+    assert!(!result.text.contains("HEAD"));
+    let _ = fs::remove_dir_all(root);
+}
+
+#[test]
+fn parses_context_placement() {
+    assert_eq!(
+        ContextPlacement::parse("firstMessage"),
+        Some(ContextPlacement::FirstMessage)
+    );
+    assert_eq!(
+        ContextPlacement::parse("systemPrompt"),
+        Some(ContextPlacement::SystemPrompt)
+    );
+    assert_eq!(ContextPlacement::parse("elsewhere"), None);
+}
@@ -0,0 +1,103 @@
+use pi::agent::{get_model, Agent, AgentOptions, AgentStateOverride};
+use pi::coding_agent::{
+    find_context_pack, load_context_packs, AgentSession, AgentSessionConfig, AuthStorage,
+    LoadContextPacksOptions, ModelRegistry, SettingsManager,
+};
+use pi::core::session_manager::SessionManager;
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+fn write_pack(path: &Path, contents: &str) {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).unwrap();
+    }
+    fs::write(path, contents).unwrap();
+}
+
+fn create_session(session_manager: SessionManager) -> AgentSession {
+    let agent = Agent::new(AgentOptions {
+        initial_state: Some(AgentStateOverride {
+            model: Some(get_model("anthropic", "claude-sonnet-4-5")),
+            system_prompt: Some("Base prompt".to_string()),
+            tools: Some(Vec::new()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    });
+    let mut auth_storage = AuthStorage::new(PathBuf::from("auth.json"));
+    auth_storage.set_runtime_api_key("anthropic", "test-key");
+
+    AgentSession::new(AgentSessionConfig {
+        agent,
+        session_manager,
+        settings_manager: SettingsManager::create("", ""),
+        model_registry: ModelRegistry::new(auth_storage, None),
+    })
+}
+
+#[test]
+fn context_packs_toggle_into_system_prompt_and_restore() {
+    let root = std::env::temp_dir().join(format!("pi-context-packs-{}", Uuid::new_v4()));
+    let agent_dir = root.join("agent");
+    let project_dir = root.join("project");
+    write_pack(
+        &agent_dir.join("context").join("api-notes.md"),
+        "Global API notes.",
+    );
+    write_pack(&agent_dir.join("context").join("style.md"), "Use tabs.");
+    write_pack(
+        &project_dir.join(".pi").join("context").join("api-notes.md"),
+        "Project API notes: the v2 endpoints are paginated.",
+    );
+    write_pack(
+        &project_dir.join(".pi").join("context").join("notes.txt"),
+        "ignored",
+    );
+
+    let packs = load_context_packs(LoadContextPacksOptions {
+        cwd: Some(project_dir),
+        agent_dir: Some(agent_dir),
+    });
+    assert_eq!(
+        packs
+            .iter()
+            .map(|pack| pack.name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["api-notes", "style"]
+    );
+    let api_notes = find_context_pack(&packs, "api-notes").unwrap();
+    assert_eq!(api_notes.source, "project");
+    assert_eq!(api_notes.tokens, 13);
+
+    let mut session = create_session(SessionManager::in_memory());
+    session.enable_context_pack(api_notes.clone());
+    session.enable_context_pack(find_context_pack(&packs, "style").unwrap().clone());
+    let prompt = session.agent.state().system_prompt;
+    assert!(prompt.starts_with("Base prompt"));
+    assert!(prompt.contains("## api-notes\n\nProject API notes"));
+    assert!(prompt.contains("## style\n\nUse tabs."));
+
+    assert!(session.disable_context_pack("style"));
+    assert!(!session.disable_context_pack("style"));
+    let prompt = session.agent.state().system_prompt;
+    assert!(!prompt.contains("Use tabs."));
+    assert_eq!(
+        session.session_manager.get_context_packs(),
+        vec!["api-notes"]
+    );
+
+    // A session built from the same entries re-enables the recorded packs.
+    let mut session_manager = SessionManager::in_memory();
+    session_manager.append_context_packs(&["api-notes".to_string(), "gone".to_string()]);
+    let mut resumed = create_session(session_manager);
+    assert_eq!(resumed.restore_context_packs(&packs), vec!["gone"]);
+    assert_eq!(resumed.context_packs().len(), 1);
+    assert!(resumed
+        .agent
+        .state()
+        .system_prompt
+        .contains("Project API notes"));
+
+    let _ = fs::remove_dir_all(&root);
+}
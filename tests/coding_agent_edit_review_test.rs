@@ -0,0 +1,160 @@
+use pi::coding_agent::tools::{EditTool, EditToolArgs, ToolResult, WriteTool, WriteToolArgs};
+use pi::coding_agent::{EditDecision, EditProposal, EditReview};
+use pi::ContentBlock;
+use std::cell::RefCell;
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+fn temp_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pi-edit-review-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// A review that records every proposal and answers with `decision`.
+fn review(decision: EditDecision) -> (EditReview, Rc<RefCell<Vec<EditProposal>>>) {
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let review = EditReview::default();
+    let recorded = seen.clone();
+    review.set_handler(Rc::new(move |proposal| {
+        recorded.borrow_mut().push(proposal.clone());
+        decision.clone()
+    }));
+    (review, seen)
+}
+
+fn text(result: &ToolResult) -> String {
+    result
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text { text, .. } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn edit_args(path: &str) -> EditToolArgs {
+    EditToolArgs {
+        path: path.to_string(),
+        old_text: "world".to_string(),
+        new_text: "there".to_string(),
+    }
+}
+
+#[test]
+fn accepted_edit_is_applied_after_showing_the_diff() {
+    let dir = temp_dir();
+    fs::write(dir.join("a.txt"), "hello\r\nworld\r\n").unwrap();
+    let (review, seen) = review(EditDecision::Accept);
+
+    EditTool::new(&dir)
+        .with_review(review)
+        .execute("call-1", edit_args("a.txt"))
+        .expect("edit");
+
+    assert_eq!(
+        fs::read_to_string(dir.join("a.txt")).unwrap(),
+        "hello\r\nthere\r\n"
+    );
+    let seen = seen.borrow();
+    assert_eq!(seen.len(), 1);
+    assert_eq!(seen[0].tool, "edit");
+    assert!(seen[0].diff.contains("-world\n+there"), "{}", seen[0].diff);
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn rejected_change_leaves_the_file_and_passes_the_note_on() {
+    let dir = temp_dir();
+    fs::write(dir.join("a.txt"), "hello\nworld\n").unwrap();
+    let (review, _) = review(EditDecision::Reject(Some("keep world".to_string())));
+
+    let err = EditTool::new(&dir)
+        .with_review(review.clone())
+        .execute("call-1", edit_args("a.txt"))
+        .expect_err("rejected");
+    assert_eq!(
+        err.message,
+        "The user rejected this edit to a.txt; the file was not changed. Their note: keep world"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.join("a.txt")).unwrap(),
+        "hello\nworld\n"
+    );
+
+    let err = WriteTool::new(&dir)
+        .with_review(review)
+        .execute(
+            "call-2",
+            WriteToolArgs {
+                path: "b.txt".to_string(),
+                content: "new".to_string(),
+            },
+        )
+        .expect_err("rejected");
+    assert!(
+        err.message
+            .starts_with("The user rejected this write to b.txt"),
+        "{}",
+        err.message
+    );
+    assert!(!dir.join("b.txt").exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn replaced_content_is_written_and_reported() {
+    let dir = temp_dir();
+    let (review, seen) = review(EditDecision::Replace("mine\n".to_string()));
+
+    let result = WriteTool::new(&dir)
+        .with_review(review)
+        .execute(
+            "call-1",
+            WriteToolArgs {
+                path: "b.txt".to_string(),
+                content: "theirs\n".to_string(),
+            },
+        )
+        .expect("write");
+
+    assert_eq!(fs::read_to_string(dir.join("b.txt")).unwrap(), "mine\n");
+    assert!(
+        text(&result).contains("The user revised the change"),
+        "{}",
+        text(&result)
+    );
+    // A new file has nothing to diff against.
+    assert!(seen.borrow()[0].diff.is_empty());
+    assert_eq!(seen.borrow()[0].content, "theirs\n");
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn changes_apply_without_a_handler() {
+    let dir = temp_dir();
+    fs::write(dir.join("a.txt"), "hello world").unwrap();
+    let review = EditReview::default();
+    let (handled, _) = self::review(EditDecision::Reject(None));
+    handled.clear_handler();
+
+    EditTool::new(&dir)
+        .with_review(review)
+        .execute("call-1", edit_args("a.txt"))
+        .expect("edit");
+    EditTool::new(&dir)
+        .with_review(handled)
+        .execute(
+            "call-2",
+            EditToolArgs {
+                path: "a.txt".to_string(),
+                old_text: "hello".to_string(),
+                new_text: "hi".to_string(),
+            },
+        )
+        .expect("edit");
+    assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "hi there");
+    fs::remove_dir_all(&dir).ok();
+}
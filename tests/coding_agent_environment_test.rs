@@ -0,0 +1,61 @@
+use pi::coding_agent::{
+    build_system_prompt, capture_environment, format_environment, run_git,
+    BuildSystemPromptOptions, EnvironmentSnapshot, Shell,
+};
+use std::fs;
+use uuid::Uuid;
+
+#[test]
+fn captures_toolchains_for_project_markers_and_git_branch() {
+    let dir = std::env::temp_dir().join(format!("pi-env-{}", Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("Cargo.toml"), "[package]\nname = \"demo\"\n").unwrap();
+    run_git(&dir, &["init", "-q", "-b", "feature/env"]).unwrap();
+
+    let snapshot = capture_environment(&dir, &Shell::from_path("/bin/bash"));
+    assert_eq!(snapshot.shell, "/bin/bash");
+    assert_eq!(snapshot.arch, std::env::consts::ARCH);
+    assert!(!snapshot.os.is_empty());
+    let rustc = snapshot
+        .toolchains
+        .iter()
+        .find(|(program, _)| program == "rustc")
+        .expect("rustc probed for a Cargo project");
+    assert!(rustc.1.starts_with("rustc "));
+    assert!(snapshot
+        .toolchains
+        .iter()
+        .all(|(program, _)| program == "rustc" || program == "cargo"));
+    assert_eq!(snapshot.git_branch.as_deref(), Some("feature/env"));
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn system_prompt_includes_environment_section_only_when_given() {
+    let snapshot = EnvironmentSnapshot {
+        os: "Ubuntu 24.04".to_string(),
+        arch: "x86_64".to_string(),
+        shell: "bash".to_string(),
+        toolchains: vec![("node".to_string(), "v22.1.0".to_string())],
+        git_branch: Some("main".to_string()),
+    };
+    let section = format_environment(&snapshot);
+    assert_eq!(
+        section,
+        "\n\n# Environment\n\n- OS: Ubuntu 24.04 (x86_64)\n- Shell: bash\n- node: v22.1.0\n- Git branch: main\n"
+    );
+
+    let options = BuildSystemPromptOptions {
+        custom_prompt: Some("Custom prompt".to_string()),
+        context_files: Some(Vec::new()),
+        ..Default::default()
+    };
+    let without = build_system_prompt(options.clone());
+    assert!(!without.contains("# Environment"));
+    let with = build_system_prompt(BuildSystemPromptOptions {
+        environment: Some(snapshot),
+        ..options
+    });
+    assert!(with.contains(&section));
+}
@@ -0,0 +1,176 @@
+mod test_utils;
+
+use base64::{engine::general_purpose, Engine as _};
+use pi::coding_agent::export_html::get_last_exported_entry_id;
+use pi::coding_agent::{
+    export_from_file, export_from_file_with_options, CustomMessageDisplay, ExportOptions,
+};
+use pi::core::messages::create_hook_message;
+use pi::{SessionManager, UserContent};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use test_utils::{assistant_msg, user_msg};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let mut dir = std::env::temp_dir();
+    dir.push(format!(
+        "pi-export-test-{name}-{}",
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos()
+    ));
+    fs::create_dir_all(&dir).expect("create temp dir");
+    dir
+}
+
+fn exported_data(path: &Path) -> Value {
+    let html = fs::read_to_string(path).unwrap();
+    let start = html
+        .find("id=\"session-data\" type=\"application/json\">")
+        .unwrap();
+    let data = &html[start..];
+    let data = &data[data.find('>').unwrap() + 1..data.find("</script>").unwrap()];
+    let json = general_purpose::STANDARD.decode(data).unwrap();
+    serde_json::from_slice(&json).unwrap()
+}
+
+fn exported_entry_texts(path: &Path) -> Vec<String> {
+    exported_data(path)["entries"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|entry| entry["message"]["content"].as_str().map(str::to_string))
+        .collect()
+}
+
+#[test]
+fn export_new_only_includes_entries_since_last_export() {
+    let dir = temp_dir("delta");
+    let mut session = SessionManager::create_with_dir(dir.clone(), dir.join("sessions"));
+    session.append_message(user_msg("first question"));
+    session.append_message(assistant_msg("first answer"));
+    let session_file = session.get_session_file().unwrap();
+    let options = ExportOptions {
+        new_only: true,
+        state_path: Some(dir.join("exports.json")),
+        ..ExportOptions::default()
+    };
+
+    let first = export_from_file_with_options(&session_file, Some(dir.join("a.html")), &options)
+        .unwrap()
+        .expect("first export");
+    assert_eq!(exported_entry_texts(&first), vec!["first question"]);
+
+    let unchanged =
+        export_from_file_with_options(&session_file, Some(dir.join("b.html")), &options).unwrap();
+    assert!(unchanged.is_none());
+    assert!(!dir.join("b.html").exists());
+
+    let last_id = session.append_message(user_msg("second question"));
+    let delta = export_from_file_with_options(&session_file, Some(dir.join("c.html")), &options)
+        .unwrap()
+        .expect("delta export");
+    assert_eq!(exported_entry_texts(&delta), vec!["second question"]);
+    assert_eq!(
+        get_last_exported_entry_id(&dir.join("exports.json"), &session.get_session_id()),
+        Some(last_id)
+    );
+
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn export_bundles_katex_for_math_rendering() {
+    let dir = temp_dir("katex");
+    let mut session = SessionManager::create_with_dir(dir.clone(), dir.join("sessions"));
+    session.append_message(user_msg("Euler?"));
+    session.append_message(assistant_msg("$e^{i\\pi} + 1 = 0$"));
+    let session_file = session.get_session_file().unwrap();
+
+    let output = export_from_file(&session_file, Some(dir.join("math.html"))).unwrap();
+    let html = fs::read_to_string(output).unwrap();
+    assert!(html.contains("KaTeX v0.16.4"));
+    assert!(html.contains("katex.renderToString"));
+    assert!(!html.contains("{{KATEX_JS}}"));
+
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn export_carries_custom_message_display_setting() {
+    let dir = temp_dir("custom");
+    let mut session = SessionManager::create_with_dir(dir.clone(), dir.join("sessions"));
+    session.append_message(user_msg("question"));
+    session.append_message(assistant_msg("answer"));
+    session.append_message(create_hook_message(
+        "note",
+        UserContent::Text("hidden note".to_string()),
+        false,
+        None,
+        "2025-01-01T00:00:00Z",
+    ));
+    let session_file = session.get_session_file().unwrap();
+
+    let output = export_from_file(&session_file, Some(dir.join("default.html"))).unwrap();
+    assert_eq!(exported_data(&output)["customMessages"], "displayed");
+    let html = fs::read_to_string(&output).unwrap();
+    assert!(html.contains("function renderCustomMessage"));
+
+    let options = ExportOptions {
+        custom_messages: CustomMessageDisplay::All,
+        ..ExportOptions::default()
+    };
+    let output = export_from_file_with_options(&session_file, Some(dir.join("all.html")), &options)
+        .unwrap()
+        .expect("export");
+    let data = exported_data(&output);
+    assert_eq!(data["customMessages"], "all");
+    assert!(data["entries"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|entry| entry["message"]["customType"] == "note"));
+
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn export_applies_the_requested_theme() {
+    let dir = temp_dir("theme");
+    let mut session = SessionManager::create_with_dir(dir.clone(), dir.join("sessions"));
+    session.append_message(user_msg("question"));
+    session.append_message(assistant_msg("answer"));
+    let session_file = session.get_session_file().unwrap();
+    let export = |theme: &str, file: &str| {
+        let options = ExportOptions {
+            theme: Some(theme.to_string()),
+            ..ExportOptions::default()
+        };
+        export_from_file_with_options(&session_file, Some(dir.join(file)), &options)
+            .map(|path| fs::read_to_string(path.expect("export")).unwrap())
+    };
+
+    let dark =
+        fs::read_to_string(export_from_file(&session_file, Some(dir.join("d.html"))).unwrap())
+            .unwrap();
+    assert!(dark.contains("--body-bg: #18181e;"));
+    assert!(dark.contains("--text: #e5e5e7;"));
+    assert!(!dark.contains("{{THEME_CSS}}"));
+
+    let light = export("light", "l.html").unwrap();
+    assert!(light.contains("--body-bg: #f8f8f8;"));
+    assert!(light.contains("--accent: #5f8787;"));
+    assert!(light.contains("--text: #1f1f1f;"));
+
+    let auto = export("auto", "a.html").unwrap();
+    assert!(auto.contains("--body-bg: #18181e;"));
+    assert!(auto.contains("@media (prefers-color-scheme: light)"));
+    assert!(auto.contains("--body-bg: #f8f8f8;"));
+
+    assert!(export("no-such-theme", "x.html").is_err());
+
+    let _ = fs::remove_dir_all(dir);
+}
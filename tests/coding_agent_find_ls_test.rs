@@ -0,0 +1,124 @@
+use pi::coding_agent::tools::{FindTool, FindToolArgs, LsTool, LsToolArgs, ToolResult};
+use pi::ContentBlock;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn project() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pi-find-ls-{}", uuid::Uuid::new_v4()));
+    for (rel, content) in [
+        (".gitignore", "build/\n"),
+        ("README.md", "readme"),
+        ("src/main.rs", "fn main() { println!(\"hello, world\"); }"),
+        ("src/util/mod.rs", "//"),
+        ("build/out.rs", "generated"),
+    ] {
+        let path = dir.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    dir
+}
+
+fn lines(result: &ToolResult) -> Vec<String> {
+    result
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text { text, .. } => Some(text.clone()),
+            _ => None,
+        })
+        .collect::<String>()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+fn find(dir: &Path, args: FindToolArgs) -> ToolResult {
+    FindTool::new(dir).execute("call-1", args).unwrap()
+}
+
+fn ls(dir: &Path, args: LsToolArgs) -> ToolResult {
+    LsTool::new(dir).execute("call-1", args).unwrap()
+}
+
+#[test]
+fn find_limits_depth_filters_type_and_sorts_by_size() {
+    let dir = project();
+    let pattern = |pattern: &str| FindToolArgs {
+        pattern: pattern.to_string(),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        lines(&find(&dir, pattern("*.rs"))),
+        ["src/main.rs", "src/util/mod.rs"]
+    );
+    let shallow = FindToolArgs {
+        max_depth: Some(2),
+        ..pattern("*.rs")
+    };
+    assert_eq!(lines(&find(&dir, shallow)), ["src/main.rs"]);
+
+    let dirs = FindToolArgs {
+        entry_type: Some("dir".to_string()),
+        ..pattern("*")
+    };
+    assert_eq!(lines(&find(&dir, dirs)), ["src/", "src/util/"]);
+
+    let everything = FindToolArgs {
+        sort: Some("size".to_string()),
+        include_ignored: Some(true),
+        ..pattern("**/*")
+    };
+    let result = find(&dir, everything);
+    assert_eq!(lines(&result)[0], "src/main.rs");
+    assert!(lines(&result).contains(&"build/out.rs".to_string()));
+    let entries = &result.details.as_ref().unwrap()["entries"];
+    assert_eq!(entries[0]["path"], "src/main.rs");
+    assert_eq!(entries[0]["type"], "file");
+    assert_eq!(entries[0]["size"], 39);
+    assert!(entries[0]["modified"].as_i64().unwrap() > 0);
+
+    let bad = FindTool::new(&dir).execute(
+        "call-2",
+        FindToolArgs {
+            sort: Some("age".to_string()),
+            ..pattern("*")
+        },
+    );
+    assert!(bad.unwrap_err().starts_with("Unknown sort \"age\""));
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn ls_skips_ignored_entries_and_lists_deeper_levels() {
+    let dir = project();
+
+    assert_eq!(
+        lines(&ls(&dir, LsToolArgs::default())),
+        [".gitignore", "README.md", "src/"]
+    );
+    let with_ignored = LsToolArgs {
+        include_ignored: Some(true),
+        ..Default::default()
+    };
+    assert!(lines(&ls(&dir, with_ignored)).contains(&"build/".to_string()));
+
+    let deep = LsToolArgs {
+        path: Some("src".to_string()),
+        max_depth: Some(2),
+        ..Default::default()
+    };
+    assert_eq!(lines(&ls(&dir, deep)), ["main.rs", "util/", "util/mod.rs"]);
+
+    let files = LsToolArgs {
+        entry_type: Some("file".to_string()),
+        ..Default::default()
+    };
+    let result = ls(&dir, files);
+    assert_eq!(lines(&result), [".gitignore", "README.md"]);
+    let entries = result.details.unwrap()["entries"].clone();
+    assert_eq!(entries[1]["path"], "README.md");
+    assert_eq!(entries[1]["size"], 6);
+    fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,294 @@
+use pi::agent::{get_model, Agent, AgentOptions, AgentStateOverride, AgentTool, AgentToolResult};
+use pi::coding_agent::tools::{
+    GitCommitToolArgs, GitDiffToolArgs, GitLogToolArgs, GitStashToolArgs, GitTool, WriteTool,
+    WriteToolArgs,
+};
+use pi::coding_agent::{
+    run_git, AgentSession, AgentSessionConfig, AuthStorage, ModelRegistry, SettingsManager,
+};
+use pi::core::messages::{AssistantMessage, ContentBlock, Usage};
+use pi::core::session_manager::SessionManager;
+use serde_json::json;
+use std::cell::Cell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use uuid::Uuid;
+
+fn init_repo(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("{name}-{}", Uuid::new_v4()));
+    fs::create_dir_all(&root).unwrap();
+    run_git(&root, &["init", "-q"]).unwrap();
+    run_git(&root, &["config", "user.name", "Pi Test"]).unwrap();
+    run_git(&root, &["config", "user.email", "pi@example.com"]).unwrap();
+    run_git(&root, &["config", "commit.gpgsign", "false"]).unwrap();
+    root
+}
+
+fn text(result: &pi::coding_agent::tools::ToolResult) -> String {
+    match result.content.first() {
+        Some(ContentBlock::Text { text, .. }) => text.clone(),
+        _ => String::new(),
+    }
+}
+
+#[test]
+fn git_tools_report_status_and_commit_changes() {
+    let repo = init_repo("pi-git-tools");
+    let git = GitTool::new(&repo);
+    fs::write(repo.join("a.txt"), "one\n").unwrap();
+
+    assert!(text(&git.status("call-1").unwrap()).contains("?? a.txt"));
+
+    let commit = git
+        .commit(
+            "call-2",
+            GitCommitToolArgs {
+                message: "Add a.txt".to_string(),
+                all: None,
+            },
+        )
+        .unwrap();
+    assert!(text(&commit).contains("Add a.txt"));
+    let hash = commit.details.unwrap()["commit"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert_eq!(run_git(&repo, &["rev-parse", "HEAD"]).unwrap().trim(), hash);
+
+    let log = git
+        .log(
+            "call-3",
+            GitLogToolArgs {
+                path: None,
+                limit: Some(5),
+            },
+        )
+        .unwrap();
+    assert!(text(&log).contains("Add a.txt"));
+
+    fs::write(repo.join("a.txt"), "two\n").unwrap();
+    let diff = git
+        .diff(
+            "call-4",
+            GitDiffToolArgs {
+                path: Some("a.txt".to_string()),
+                staged: None,
+            },
+        )
+        .unwrap();
+    assert!(text(&diff).contains("+two"));
+
+    git.stash(
+        "call-5",
+        GitStashToolArgs {
+            action: None,
+            message: Some("wip".to_string()),
+        },
+    )
+    .unwrap();
+    assert_eq!(fs::read_to_string(repo.join("a.txt")).unwrap(), "one\n");
+    let list = git
+        .stash(
+            "call-6",
+            GitStashToolArgs {
+                action: Some("list".to_string()),
+                message: None,
+            },
+        )
+        .unwrap();
+    assert!(text(&list).contains("wip"));
+    git.stash(
+        "call-7",
+        GitStashToolArgs {
+            action: Some("pop".to_string()),
+            message: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(fs::read_to_string(repo.join("a.txt")).unwrap(), "two\n");
+
+    let err = git
+        .stash(
+            "call-8",
+            GitStashToolArgs {
+                action: Some("drop".to_string()),
+                message: None,
+            },
+        )
+        .unwrap_err();
+    assert!(err.contains("Unknown stash action"));
+
+    let _ = fs::remove_dir_all(&repo);
+}
+
+fn assistant(content: Vec<ContentBlock>, stop_reason: &str) -> AssistantMessage {
+    AssistantMessage {
+        content,
+        api: "anthropic-messages".to_string(),
+        provider: "anthropic".to_string(),
+        model: "mock".to_string(),
+        usage: Usage {
+            input: 0,
+            output: 0,
+            cache_read: 0,
+            cache_write: 0,
+            total_tokens: None,
+            cost: None,
+        },
+        stop_reason: stop_reason.to_string(),
+        error_message: None,
+        timestamp: 0,
+    }
+}
+
+fn write_call(id: &str, content: &str) -> ContentBlock {
+    ContentBlock::ToolCall {
+        id: id.to_string(),
+        name: "write".to_string(),
+        arguments: json!({ "path": "a.txt", "content": content }),
+        thought_signature: None,
+    }
+}
+
+fn create_session(repo: &Path, session_dir: &Path) -> AgentSession {
+    let write = WriteTool::new(repo);
+    let tool = AgentTool {
+        name: "write".to_string(),
+        label: "write".to_string(),
+        description: "Write file contents".to_string(),
+        parameters: None,
+        execute: Rc::new(move |call_id, params, _on_update| {
+            let result = write.execute(
+                call_id,
+                WriteToolArgs {
+                    path: params["path"].as_str().unwrap().to_string(),
+                    content: params["content"].as_str().unwrap().to_string(),
+                },
+            )?;
+            Ok(AgentToolResult {
+                content: result.content,
+                details: json!(null),
+            })
+        }),
+    };
+    // Each prompt writes twice in its first turn, then stops.
+    let calls = Rc::new(Cell::new(0));
+    let stream_fn: Box<pi::agent::StreamFn> = Box::new(move |_model, _context, _events| {
+        let call = calls.get();
+        calls.set(call + 1);
+        if call % 2 == 0 {
+            let id = format!("call-{call}");
+            assistant(
+                vec![
+                    write_call(&format!("{id}-a"), &format!("agent {call} draft\n")),
+                    write_call(&format!("{id}-b"), &format!("agent {call}\n")),
+                ],
+                "toolUse",
+            )
+        } else {
+            assistant(
+                vec![ContentBlock::Text {
+                    text: "Done".to_string(),
+                    text_signature: None,
+                }],
+                "stop",
+            )
+        }
+    });
+    let agent = Agent::new(AgentOptions {
+        initial_state: Some(AgentStateOverride {
+            model: Some(get_model("anthropic", "claude-sonnet-4-5")),
+            system_prompt: Some("Test".to_string()),
+            tools: Some(vec![tool]),
+            ..Default::default()
+        }),
+        stream_fn: Some(stream_fn),
+        ..Default::default()
+    });
+    let mut auth_storage = AuthStorage::new(PathBuf::from("auth.json"));
+    auth_storage.set_runtime_api_key("anthropic", "test-key");
+    AgentSession::new(AgentSessionConfig {
+        agent,
+        session_manager: SessionManager::create_with_dir(
+            repo.to_path_buf(),
+            session_dir.to_path_buf(),
+        ),
+        settings_manager: SettingsManager::create("", ""),
+        model_registry: ModelRegistry::new(auth_storage, None),
+    })
+}
+
+#[test]
+fn git_checkpoints_commit_once_before_the_first_write_of_each_turn() {
+    let repo = init_repo("pi-git-checkpoints");
+    let session_dir = std::env::temp_dir().join(format!("pi-git-sessions-{}", Uuid::new_v4()));
+    fs::write(repo.join("a.txt"), "committed\n").unwrap();
+    run_git(&repo, &["add", "-A"]).unwrap();
+    run_git(&repo, &["commit", "-q", "-m", "Initial"]).unwrap();
+    let initial = run_git(&repo, &["rev-parse", "HEAD"]).unwrap();
+    fs::write(repo.join("a.txt"), "user edit\n").unwrap();
+    fs::write(repo.join("staged.txt"), "staged\n").unwrap();
+    run_git(&repo, &["add", "staged.txt"]).unwrap();
+    fs::write(repo.join("notes.txt"), "untracked\n").unwrap();
+
+    let mut session = create_session(&repo, &session_dir);
+    session.set_git_checkpoints(true);
+
+    session.prompt("change a.txt").unwrap();
+    assert_eq!(fs::read_to_string(repo.join("a.txt")).unwrap(), "agent 0\n");
+    let checkpoints = session.git_checkpoints();
+    assert_eq!(checkpoints.len(), 1);
+    let show = format!("{}:a.txt", checkpoints[0]);
+    assert_eq!(run_git(&repo, &["show", &show]).unwrap(), "user edit\n");
+
+    session.prompt("change it again").unwrap();
+    let checkpoints = session.git_checkpoints();
+    assert_eq!(checkpoints.len(), 2);
+    let show = format!("{}:a.txt", checkpoints[1]);
+    assert_eq!(run_git(&repo, &["show", &show]).unwrap(), "agent 0\n");
+    assert_eq!(fs::read_to_string(repo.join("a.txt")).unwrap(), "agent 2\n");
+
+    let show = format!("{}:notes.txt", checkpoints[1]);
+    assert_eq!(run_git(&repo, &["show", &show]).unwrap(), "untracked\n");
+
+    // The checkpoints live under their own refs; the branch and the index are untouched.
+    let refs = run_git(
+        &repo,
+        &[
+            "for-each-ref",
+            "--format=%(refname) %(objectname)",
+            "refs/pi/",
+        ],
+    )
+    .unwrap();
+    assert_eq!(
+        refs,
+        format!(
+            "refs/pi/checkpoints/1 {}\nrefs/pi/checkpoints/2 {}\n",
+            checkpoints[0], checkpoints[1]
+        )
+    );
+    assert_eq!(run_git(&repo, &["rev-parse", "HEAD"]).unwrap(), initial);
+    assert_eq!(
+        run_git(&repo, &["status", "--porcelain"]).unwrap(),
+        " M a.txt\nA  staged.txt\n?? notes.txt\n"
+    );
+
+    // Rolling back the last turn restores the working tree from its checkpoint.
+    run_git(
+        &repo,
+        &[
+            "restore",
+            "--source",
+            "refs/pi/checkpoints/2",
+            "--",
+            "a.txt",
+        ],
+    )
+    .unwrap();
+    assert_eq!(fs::read_to_string(repo.join("a.txt")).unwrap(), "agent 0\n");
+
+    let _ = fs::remove_dir_all(&repo);
+    let _ = fs::remove_dir_all(&session_dir);
+}
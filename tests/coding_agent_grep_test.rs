@@ -0,0 +1,134 @@
+use pi::coding_agent::tools::{GrepTool, GrepToolArgs, ToolResult};
+use pi::ContentBlock;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn temp_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pi-grep-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write(dir: &Path, rel: &str, content: &str) {
+    let path = dir.join(rel);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+fn args(pattern: &str) -> GrepToolArgs {
+    GrepToolArgs {
+        pattern: pattern.to_string(),
+        path: None,
+        glob: None,
+        ignore_case: None,
+        literal: None,
+        context: None,
+        limit: None,
+        multiline: None,
+        file_type: None,
+    }
+}
+
+fn text(result: &ToolResult) -> String {
+    result
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text { text, .. } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn matched_files(result: &ToolResult) -> Vec<String> {
+    let details = result.details.as_ref().expect("details");
+    details["matches"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|found| found["file"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn respects_nested_gitignores_and_negations() {
+    let dir = temp_dir();
+    write(&dir, ".gitignore", "target/\n*.log\n!keep.log\n");
+    write(&dir, "src/lib.rs", "needle\n");
+    write(&dir, "src/gen/.gitignore", "/out.rs\n");
+    write(&dir, "src/gen/out.rs", "needle\n");
+    write(&dir, "src/gen/in.rs", "needle\n");
+    write(&dir, "target/debug.rs", "needle\n");
+    write(&dir, "debug.log", "needle\n");
+    write(&dir, "keep.log", "needle\n");
+    write(&dir, ".git/HEAD", "needle\n");
+
+    let result = GrepTool::new(&dir)
+        .execute("call-1", args("needle"))
+        .unwrap();
+    assert_eq!(
+        matched_files(&result),
+        vec!["keep.log", "src/gen/in.rs", "src/lib.rs"]
+    );
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn filters_by_type_and_glob_at_any_depth() {
+    let dir = temp_dir();
+    write(&dir, "a.rs", "fn main() {}\n");
+    write(&dir, "web/b.ts", "function main() {}\n");
+    write(&dir, "web/c.tsx", "function main() {}\n");
+    write(&dir, "notes.md", "main\n");
+
+    let mut typed = args("main");
+    typed.file_type = Some("typescript".to_string());
+    let result = GrepTool::new(&dir).execute("call-1", typed).unwrap();
+    assert_eq!(matched_files(&result), vec!["web/b.ts", "web/c.tsx"]);
+
+    let mut globbed = args("main");
+    globbed.glob = Some("*.rs".to_string());
+    let result = GrepTool::new(&dir).execute("call-2", globbed).unwrap();
+    assert_eq!(matched_files(&result), vec!["a.rs"]);
+
+    let mut unknown = args("main");
+    unknown.file_type = Some("cobol".to_string());
+    let err = GrepTool::new(&dir).execute("call-3", unknown).unwrap_err();
+    assert!(err.starts_with("Unknown file type \"cobol\""), "{err}");
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn reports_columns_and_matches_across_lines() {
+    let dir = temp_dir();
+    write(
+        &dir,
+        "lib.rs",
+        "fn a() {}\nfn b(\n    x: u8,\n) {}\nlet é = b(1);\n",
+    );
+    write(&dir, "blob.bin", "b(\0");
+
+    let mut single = args("b\\(");
+    single.literal = Some(false);
+    let result = GrepTool::new(&dir).execute("call-1", single).unwrap();
+    let details = result.details.unwrap();
+    assert_eq!(
+        details["matches"],
+        json!([
+            { "file": "lib.rs", "line": 2, "column": 4, "text": "fn b(" },
+            { "file": "lib.rs", "line": 5, "column": 9, "text": "let é = b(1);" },
+        ])
+    );
+
+    let mut multiline = args(r"fn b\(.*?\)");
+    multiline.multiline = Some(true);
+    let result = GrepTool::new(&dir).execute("call-2", multiline).unwrap();
+    assert_eq!(
+        text(&result),
+        "lib.rs:2: fn b(\nlib.rs:3:     x: u8,\nlib.rs:4: ) {}"
+    );
+    let found: &Value = &result.details.as_ref().unwrap()["matches"][0];
+    assert_eq!(found["text"], "fn b(\n    x: u8,\n)");
+    fs::remove_dir_all(&dir).ok();
+}
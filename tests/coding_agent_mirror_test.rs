@@ -0,0 +1,71 @@
+mod test_utils;
+
+use pi::coding_agent::mirror::SessionMirror;
+use pi::coding_agent::ExportOptions;
+use pi::SessionManager;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::time::Duration;
+use test_utils::{assistant_msg, user_msg};
+
+const TOKEN: &str = "mirror-token";
+
+fn connect(addr: SocketAddr, target: &str) -> BufReader<TcpStream> {
+    let mut stream = TcpStream::connect(addr).expect("connect");
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .expect("set timeout");
+    stream
+        .write_all(format!("GET {target} HTTP/1.1\r\nHost: pi\r\n\r\n").as_bytes())
+        .expect("send request");
+    BufReader::new(stream)
+}
+
+fn read_line(reader: &mut BufReader<TcpStream>) -> String {
+    let mut line = String::new();
+    reader.read_line(&mut line).expect("read line");
+    line.trim_end().to_string()
+}
+
+#[test]
+fn serves_the_session_and_streams_new_entries() {
+    let dir = std::env::temp_dir().join(format!("pi-mirror-{}", uuid::Uuid::new_v4()));
+    let mut session = SessionManager::create_with_dir(dir.clone(), dir.join("sessions"));
+    session.append_message(user_msg("first question"));
+    session.append_message(assistant_msg("first answer"));
+
+    let mirror =
+        SessionMirror::start(([127, 0, 0, 1], 0), TOKEN, ExportOptions::default()).expect("start");
+    mirror.follow(session.get_session_file());
+    assert!(mirror.url().ends_with(&format!("/?token={TOKEN}")));
+    let addr = mirror.local_addr();
+
+    let mut rejected = connect(addr, "/");
+    assert_eq!(read_line(&mut rejected), "HTTP/1.1 401 Unauthorized");
+
+    let mut page = connect(addr, &format!("/?token={TOKEN}"));
+    assert_eq!(read_line(&mut page), "HTTP/1.1 200 OK");
+    let mut body = String::new();
+    page.read_to_string(&mut body).expect("read page");
+    assert!(body.contains("id=\"session-data\""));
+    assert!(body.contains("new EventSource('/events' + location.search)"));
+
+    // Let the watcher pick up the followed file before subscribing.
+    std::thread::sleep(Duration::from_millis(600));
+    let mut events = connect(addr, &format!("/events?token={TOKEN}"));
+    assert_eq!(read_line(&mut events), "HTTP/1.1 200 OK");
+    while !read_line(&mut events).starts_with(": connected") {}
+
+    session.append_message(user_msg("second question"));
+    let event = loop {
+        let line = read_line(&mut events);
+        if let Some(data) = line.strip_prefix("data: ") {
+            break serde_json::from_str::<serde_json::Value>(data).unwrap();
+        }
+    };
+    assert_eq!(event["type"], "entries");
+    assert_eq!(event["entries"][0]["message"]["content"], "second question");
+
+    let _ = fs::remove_dir_all(dir);
+}
@@ -48,6 +48,32 @@ fn overriding_baseurl_changes_url_on_all_built_in_models() {
     }
 }
 
+#[test]
+fn context_windows_override_built_in_model_limits() {
+    let harness = TestHarness::new();
+    write_models_json(
+        &harness.models_json_path,
+        json!({
+            "anthropic": { "contextWindows": { "claude-sonnet-4-5": 1_000_000 } }
+        }),
+    );
+
+    let registry = ModelRegistry::new(
+        harness.auth_storage(),
+        Some(harness.models_json_path.clone()),
+    );
+    assert_eq!(
+        registry.context_window("anthropic", "claude-sonnet-4-5"),
+        Some(1_000_000)
+    );
+    let other = get_models_for_provider(&registry, "anthropic")
+        .into_iter()
+        .find(|model| model.id != "claude-sonnet-4-5")
+        .expect("expected another anthropic model");
+    assert_ne!(other.context_window, 1_000_000);
+    assert_eq!(registry.context_window("anthropic", "missing"), None);
+}
+
 #[test]
 fn overriding_headers_merges_with_model_headers() {
     let harness = TestHarness::new();
@@ -0,0 +1,159 @@
+use pi::cli::models_command::model_from_options;
+use pi::coding_agent::models_config::{
+    add_custom_model, read_models_config, remove_custom_model, validate_models_config,
+    ProviderFields,
+};
+use pi::coding_agent::{AuthStorage, ModelRegistry};
+use serde_json::json;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[test]
+fn adds_replaces_and_removes_custom_models_and_the_registry_reloads_them() {
+    let dir = make_temp_dir();
+    let models_path = dir.join("models.json");
+    let mut registry = ModelRegistry::new(
+        AuthStorage::new(dir.join("auth.json")),
+        Some(models_path.clone()),
+    );
+    assert!(!registry.reload_if_changed());
+
+    let fields = ProviderFields {
+        base_url: Some("http://localhost:11434/v1".to_string()),
+        api: Some("openai-responses".to_string()),
+        headers: vec![("X-Team".to_string(), "infra".to_string())],
+        ..ProviderFields::default()
+    };
+    add_custom_model(
+        &models_path,
+        "local",
+        &fields,
+        json!({ "id": "qwen", "contextWindow": 32_000 }),
+    )
+    .unwrap();
+    assert!(registry.reload_if_changed());
+    let model = registry.find("local", "qwen").expect("custom model loaded");
+    assert_eq!(model.base_url, "http://localhost:11434/v1");
+    assert_eq!(model.context_window, 32_000);
+
+    add_custom_model(
+        &models_path,
+        "local",
+        &ProviderFields::default(),
+        json!({ "id": "qwen", "contextWindow": 64_000 }),
+    )
+    .unwrap();
+    let config = read_models_config(&models_path).unwrap();
+    assert_eq!(
+        config["providers"]["local"]["models"]
+            .as_array()
+            .unwrap()
+            .len(),
+        1
+    );
+    assert_eq!(config["providers"]["local"]["headers"]["X-Team"], "infra");
+    assert!(registry.reload_if_changed());
+    assert_eq!(
+        registry.find("local", "qwen").unwrap().context_window,
+        64_000
+    );
+
+    assert!(!remove_custom_model(&models_path, "local", "missing").unwrap());
+    assert!(remove_custom_model(&models_path, "local", "qwen").unwrap());
+    let config = read_models_config(&models_path).unwrap();
+    assert!(config["providers"].get("local").is_none());
+    assert!(registry.reload_if_changed());
+    assert!(registry.find("local", "qwen").is_none());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn rejects_invalid_entries_without_writing_them() {
+    let dir = make_temp_dir();
+    let models_path = dir.join("models.json");
+    let error = add_custom_model(
+        &models_path,
+        "local",
+        &ProviderFields {
+            base_url: Some("localhost:8080".to_string()),
+            api: Some("carrier-pigeon".to_string()),
+            ..ProviderFields::default()
+        },
+        json!({
+            "id": "broken",
+            "input": ["text", "audio"],
+            "cost": { "input": -1, "output": 2, "cacheRead": 0, "cacheWrite": 0 },
+            "maxTokens": 0,
+        }),
+    )
+    .unwrap_err()
+    .to_string();
+    for expected in [
+        "providers.local.baseUrl: expected an http(s) URL",
+        "providers.local.api: expected one of",
+        "providers.local.models[0].input:",
+        "providers.local.models[0].cost.input:",
+        "providers.local.models[0].maxTokens:",
+    ] {
+        assert!(error.contains(expected), "{expected} missing from {error}");
+    }
+    assert!(!models_path.exists());
+
+    let missing_base_url = json!({ "providers": { "local": { "models": [{ "id": "a" }] } } });
+    assert!(validate_models_config(&missing_base_url)
+        .unwrap_err()
+        .to_string()
+        .contains("providers.local.baseUrl: required for custom models"));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn builds_model_entries_from_cli_options() {
+    let options = [
+        ("base-url", "https://llm.example.com/v1"),
+        ("header", "X-Key = secret"),
+        ("reasoning", "true"),
+        ("input", "text,image"),
+        ("max-tokens", "8192"),
+        ("cost", "1.5,6"),
+    ]
+    .map(|(name, value)| (name.to_string(), value.to_string()));
+    let (fields, model) = model_from_options("big", &options).unwrap();
+    assert_eq!(
+        fields.base_url.as_deref(),
+        Some("https://llm.example.com/v1")
+    );
+    assert_eq!(
+        fields.headers,
+        vec![("X-Key".to_string(), "secret".to_string())]
+    );
+    assert_eq!(
+        model,
+        json!({
+            "id": "big",
+            "reasoning": true,
+            "input": ["text", "image"],
+            "maxTokens": 8192,
+            "cost": { "input": 1.5, "output": 6.0, "cacheRead": 0.0, "cacheWrite": 0.0 },
+        })
+    );
+
+    let unknown = [("temperature".to_string(), "1".to_string())];
+    assert!(model_from_options("big", &unknown)
+        .unwrap_err()
+        .starts_with("Unknown option --temperature"));
+}
+
+fn make_temp_dir() -> PathBuf {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis())
+        .unwrap_or(0);
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let dir = std::env::temp_dir().join(format!("pi-test-models-config-{now}-{count}"));
+    let _ = fs::create_dir_all(&dir);
+    dir
+}
@@ -0,0 +1,234 @@
+use pi::coding_agent::tools::{
+    NotebookEditToolArgs, NotebookReadToolArgs, NotebookTool, ToolResult,
+};
+use pi::coding_agent::SharedChangeJournal;
+use pi::ContentBlock;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::PathBuf;
+
+fn notebook() -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir().join(format!("pi-notebook-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("analysis.ipynb");
+    let content = json!({
+        "cells": [
+            {
+                "cell_type": "markdown",
+                "id": "intro",
+                "metadata": {},
+                "source": ["# Analysis\n", "Load the data."]
+            },
+            {
+                "cell_type": "code",
+                "execution_count": 2,
+                "id": "load",
+                "metadata": { "tags": ["setup"] },
+                "outputs": [
+                    { "name": "stdout", "output_type": "stream", "text": ["rows: 3\n"] },
+                    {
+                        "data": { "image/png": "iVBORw0K", "text/plain": ["<Figure size 640x480>"] },
+                        "metadata": {},
+                        "output_type": "display_data"
+                    },
+                    {
+                        "ename": "KeyError",
+                        "evalue": "'price'",
+                        "output_type": "error",
+                        "traceback": ["\u{1b}[0;31mKeyError\u{1b}[0m: 'price'"]
+                    }
+                ],
+                "source": ["import pandas as pd\n", "df = pd.read_csv('data.csv')"]
+            }
+        ],
+        "metadata": {
+            "kernelspec": { "display_name": "Python 3", "language": "python", "name": "python3" }
+        },
+        "nbformat": 4,
+        "nbformat_minor": 5
+    });
+    fs::write(&path, serde_json::to_string_pretty(&content).unwrap()).unwrap();
+    (dir, path)
+}
+
+fn text(result: &ToolResult) -> String {
+    result
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text { text, .. } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn cells(path: &PathBuf) -> Vec<Value> {
+    let value: Value = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+    value["cells"].as_array().unwrap().clone()
+}
+
+fn edit(args: NotebookEditToolArgs) -> NotebookEditToolArgs {
+    NotebookEditToolArgs {
+        path: "analysis.ipynb".to_string(),
+        ..args
+    }
+}
+
+#[test]
+fn reads_cells_with_sources_and_outputs() {
+    let (dir, _) = notebook();
+    let tool = NotebookTool::new(&dir);
+    let read = |args: NotebookReadToolArgs| {
+        let args = NotebookReadToolArgs {
+            path: "analysis.ipynb".to_string(),
+            ..args
+        };
+        text(&tool.read("call-1", args).unwrap())
+    };
+
+    let output = read(NotebookReadToolArgs::default());
+    assert!(
+        output.starts_with("analysis.ipynb: 2 cells (python)"),
+        "{output}"
+    );
+    assert!(output.contains("## Cell 0 [markdown] id=intro\n# Analysis\nLoad the data."));
+    assert!(output.contains(
+        "## Cell 1 [code] id=load execution_count=2\n```python\nimport pandas as pd\ndf = pd.read_csv('data.csv')\n```"
+    ));
+    assert!(output.contains("[stdout]\nrows: 3"), "{output}");
+    assert!(output.contains("<Figure size 640x480>\n(image/png not shown)"));
+    assert!(
+        output.contains("[error] KeyError: 'price'\nKeyError: 'price'"),
+        "{output}"
+    );
+
+    let output = read(NotebookReadToolArgs {
+        limit: Some(1),
+        outputs: Some(false),
+        ..Default::default()
+    });
+    assert!(!output.contains("## Cell 1"), "{output}");
+    assert!(output.ends_with("[Showing cells 0-0 of 2. Use offset=1 to continue]"));
+
+    let output = read(NotebookReadToolArgs {
+        offset: Some(1),
+        outputs: Some(false),
+        ..Default::default()
+    });
+    assert!(
+        !output.contains("Outputs:") && !output.contains("## Cell 0"),
+        "{output}"
+    );
+    fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn replaces_inserts_and_deletes_cells_keeping_the_rest() {
+    let (dir, path) = notebook();
+    let journal = SharedChangeJournal::default();
+    let tool = NotebookTool::new(&dir).with_journal(journal.clone());
+
+    let result = tool
+        .edit(
+            "call-1",
+            edit(NotebookEditToolArgs {
+                cell_id: Some("load".to_string()),
+                source: Some("df = pd.read_csv('data.csv')\ndf.head()".to_string()),
+                ..Default::default()
+            }),
+        )
+        .unwrap();
+    assert_eq!(text(&result), "Replaced cell 1 in analysis.ipynb.");
+    let cell = &cells(&path)[1];
+    assert_eq!(
+        cell["source"],
+        json!(["df = pd.read_csv('data.csv')\n", "df.head()"])
+    );
+    assert_eq!(cell["outputs"], json!([]));
+    assert_eq!(cell["execution_count"], Value::Null);
+    assert_eq!(cell["metadata"], json!({ "tags": ["setup"] }));
+    assert_eq!(cell["id"], "load");
+
+    tool.edit(
+        "call-2",
+        edit(NotebookEditToolArgs {
+            action: Some("insert".to_string()),
+            cell_id: Some("intro".to_string()),
+            cell_type: Some("markdown".to_string()),
+            source: Some("## Setup".to_string()),
+            ..Default::default()
+        }),
+    )
+    .unwrap();
+    let all = cells(&path);
+    assert_eq!(all.len(), 3);
+    assert_eq!(all[1]["cell_type"], "markdown");
+    assert_eq!(all[1]["source"], json!(["## Setup"]));
+    assert!(all[1].get("outputs").is_none());
+    assert_eq!(all[1]["id"].as_str().unwrap().len(), 8);
+
+    let result = tool
+        .edit(
+            "call-3",
+            edit(NotebookEditToolArgs {
+                action: Some("delete".to_string()),
+                index: Some(0),
+                ..Default::default()
+            }),
+        )
+        .unwrap();
+    assert_eq!(
+        text(&result),
+        "Deleted cell 0 from analysis.ipynb (2 cells remain)."
+    );
+    let written = fs::read_to_string(&path).unwrap();
+    assert!(written.starts_with("{\n \"cells\": [\n  {\n"), "{written}");
+    assert!(written.ends_with("}\n"));
+    assert_eq!(cells(&path)[0]["source"], json!(["## Setup"]));
+    assert_eq!(journal.borrow().changes().len(), 3);
+    fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn rejects_bad_targets_without_touching_the_file() {
+    let (dir, path) = notebook();
+    let before = fs::read_to_string(&path).unwrap();
+    let tool = NotebookTool::new(&dir);
+    let error = |args: NotebookEditToolArgs| tool.edit("call-1", edit(args)).unwrap_err().message;
+
+    assert_eq!(
+        error(NotebookEditToolArgs {
+            index: Some(5),
+            source: Some("x".to_string()),
+            ..Default::default()
+        }),
+        "Cell 5 does not exist; the notebook has cells 0-1"
+    );
+    assert!(error(NotebookEditToolArgs {
+        cell_id: Some("missing".to_string()),
+        source: Some("x".to_string()),
+        ..Default::default()
+    })
+    .contains("No cell with id \"missing\""));
+    assert!(error(NotebookEditToolArgs {
+        action: Some("insert".to_string()),
+        cell_type: Some("sql".to_string()),
+        source: Some("select 1".to_string()),
+        ..Default::default()
+    })
+    .starts_with("Unknown cell type \"sql\""));
+    assert_eq!(fs::read_to_string(&path).unwrap(), before);
+
+    fs::write(&path, "{\"nbformat\": 4}").unwrap();
+    let message = tool
+        .read(
+            "call-2",
+            NotebookReadToolArgs {
+                path: "analysis.ipynb".to_string(),
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+    assert!(message.contains("no cells array"), "{message}");
+    fs::remove_dir_all(dir).ok();
+}
@@ -0,0 +1,177 @@
+use pi::coding_agent::outline::{find_symbols, outline, render_outline};
+use pi::coding_agent::tools::{ReadTool, ReadToolArgs};
+use pi::ContentBlock;
+use std::fs;
+use std::path::Path;
+
+const RUST_SOURCE: &str = r#"use std::fmt;
+
+/// A parser.
+#[derive(Debug)]
+pub struct Parser {
+    input: String,
+}
+
+impl Parser {
+    pub fn new(input: &str) -> Self {
+        Self { input: input.to_string() }
+    }
+
+    pub fn parse(&self) -> Result<[u8; 2], String> {
+        if self.input.is_empty() {
+            return Err("empty: }".to_string());
+        }
+        Ok([0; 2])
+    }
+}
+
+impl fmt::Display for Parser {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.input)
+    }
+}
+
+pub const LIMIT: usize = 3;
+"#;
+
+fn read_text(path: &Path, args: ReadToolArgs) -> Result<String, String> {
+    let result = ReadTool::new(path.parent().unwrap()).execute("call-1", args)?;
+    Ok(result
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text { text, .. } => Some(text.clone()),
+            _ => None,
+        })
+        .collect())
+}
+
+#[test]
+fn outlines_rust_declarations_with_their_extent() {
+    let symbols = outline(Path::new("lib.rs"), RUST_SOURCE).unwrap();
+    let summary: Vec<_> = symbols
+        .iter()
+        .map(|symbol| (symbol.qualified_name.as_str(), symbol.line, symbol.end_line))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("Parser", 5, 7),
+            ("Parser", 9, 20),
+            ("Parser::new", 10, 12),
+            ("Parser::parse", 14, 19),
+            ("Parser", 22, 26),
+            ("Parser::fmt", 23, 25),
+            ("LIMIT", 28, 28),
+        ]
+    );
+    assert_eq!(
+        symbols[0].start_line, 3,
+        "doc comment and attribute belong to the struct"
+    );
+    assert_eq!(
+        symbols[3].signature,
+        "pub fn parse(&self) -> Result<[u8; 2], String>"
+    );
+
+    let rendered = render_outline(&symbols);
+    assert!(
+        rendered.starts_with("5-7    pub struct Parser\n"),
+        "{rendered}"
+    );
+    assert!(
+        rendered.contains("\n10-12    pub fn new(input: &str) -> Self\n"),
+        "{rendered}"
+    );
+}
+
+#[test]
+fn finds_symbols_by_bare_or_qualified_name() {
+    let symbols = outline(Path::new("lib.rs"), RUST_SOURCE).unwrap();
+    assert_eq!(find_symbols(&symbols, "Parser").len(), 3);
+    let parse = find_symbols(&symbols, "Parser::parse");
+    assert_eq!(parse.len(), 1);
+    assert_eq!(parse[0].line, 14);
+    assert_eq!(find_symbols(&symbols, "Parser.fmt")[0].line, 23);
+    assert!(find_symbols(&symbols, "Other::parse").is_empty());
+}
+
+#[test]
+fn outlines_python_and_typescript() {
+    let python = "import os\n\n@dataclass\nclass Point:\n    x: int\n\n    def norm(\n        self,\n    ):\n        return 0\n\ndef main():\n    pass\n";
+    let symbols = outline(Path::new("point.py"), python).unwrap();
+    let summary: Vec<_> = symbols
+        .iter()
+        .map(|symbol| {
+            (
+                symbol.qualified_name.as_str(),
+                symbol.start_line,
+                symbol.end_line,
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![("Point", 3, 10), ("Point.norm", 7, 10), ("main", 12, 13)]
+    );
+
+    let typescript = "export class Store {\n  private items: string[] = [];\n\n  add(item: string): void {\n    if (item) {\n      this.items.push(`${item}}`);\n    }\n  }\n}\n\nexport const load = async (path: string) => {\n  return path;\n};\n";
+    let symbols = outline(Path::new("store.ts"), typescript).unwrap();
+    let summary: Vec<_> = symbols
+        .iter()
+        .map(|symbol| (symbol.qualified_name.as_str(), symbol.line, symbol.end_line))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![("Store", 1, 9), ("Store.add", 4, 8), ("load", 11, 13)]
+    );
+
+    assert!(outline(Path::new("notes.txt"), "text").is_err());
+}
+
+#[test]
+fn read_tool_reads_outlines_symbols_and_numbered_lines() {
+    let dir = std::env::temp_dir().join(format!("pi-outline-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("lib.rs");
+    fs::write(&path, RUST_SOURCE).unwrap();
+    let args = |symbol: Option<&str>, outline: Option<bool>| ReadToolArgs {
+        path: "lib.rs".to_string(),
+        symbol: symbol.map(str::to_string),
+        outline,
+        ..Default::default()
+    };
+
+    let outlined = read_text(&path, args(None, Some(true))).unwrap();
+    assert!(outlined.contains("  pub fn parse(&self)"), "{outlined}");
+    assert!(!outlined.contains("is_empty"), "{outlined}");
+
+    let symbol = read_text(&path, args(Some("Parser::new"), None)).unwrap();
+    assert_eq!(
+        symbol,
+        "10\t    pub fn new(input: &str) -> Self {\n11\t        Self { input: input.to_string() }\n12\t    }"
+    );
+
+    let numbered = read_text(
+        &path,
+        ReadToolArgs {
+            path: "lib.rs".to_string(),
+            offset: Some(9),
+            limit: Some(2),
+            line_numbers: Some(true),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert!(
+        numbered.starts_with(" 9\timpl Parser {\n10\t    pub fn new"),
+        "{numbered}"
+    );
+
+    let missing = read_text(&path, args(Some("Lexer"), None)).unwrap_err();
+    assert!(missing.contains("Symbol \"Lexer\" not found"), "{missing}");
+    let mut combined = args(Some("Parser"), None);
+    combined.offset = Some(1);
+    assert!(read_text(&path, combined).is_err());
+    fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,178 @@
+use pi::agent::{LlmContext, Model, StreamFn};
+use pi::coding_agent::{
+    build_output_filter_chain, ModerationModelFilter, OutputFilter, OutputFilterChain,
+    OutputFilterVerdict, RegexOutputFilter, SettingsOutputFilterRule, SettingsOutputFilters,
+};
+use pi::{AssistantMessage, ContentBlock, Usage, UserContent};
+use serde_json::json;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+fn text(text: &str) -> ContentBlock {
+    ContentBlock::Text {
+        text: text.to_string(),
+        text_signature: None,
+    }
+}
+
+fn assistant(content: Vec<ContentBlock>, stop_reason: &str) -> AssistantMessage {
+    AssistantMessage {
+        content,
+        api: "openai-responses".to_string(),
+        provider: "openai".to_string(),
+        model: "mock".to_string(),
+        usage: Usage {
+            input: 0,
+            output: 0,
+            cache_read: 0,
+            cache_write: 0,
+            total_tokens: None,
+            cost: None,
+        },
+        stop_reason: stop_reason.to_string(),
+        error_message: None,
+        timestamp: 0,
+    }
+}
+
+fn rule(pattern: &str, action: Option<&str>) -> SettingsOutputFilterRule {
+    SettingsOutputFilterRule {
+        pattern: pattern.to_string(),
+        action: action.map(str::to_string),
+        ..Default::default()
+    }
+}
+
+struct FailingFilter;
+
+impl OutputFilter for FailingFilter {
+    fn name(&self) -> &str {
+        "failing"
+    }
+
+    fn check(&self, _content: &[ContentBlock]) -> Result<OutputFilterVerdict, String> {
+        Err("service unavailable".to_string())
+    }
+}
+
+#[test]
+fn regex_filter_redacts_and_blocks() {
+    let filter = RegexOutputFilter::from_rules(&[
+        rule(r"sk-[A-Za-z0-9]{8,}", None),
+        rule(r"(?i)internal use only", Some("block")),
+    ])
+    .unwrap();
+
+    assert_eq!(
+        filter.check(&[text("key: sk-abcdef123456")]).unwrap(),
+        OutputFilterVerdict::Replace(vec![text("key: [REDACTED]")])
+    );
+    assert_eq!(
+        filter.check(&[text("nothing to see")]).unwrap(),
+        OutputFilterVerdict::Allow
+    );
+    assert_eq!(
+        filter.check(&[text("INTERNAL USE ONLY")]).unwrap(),
+        OutputFilterVerdict::Block("matched \"(?i)internal use only\"".to_string())
+    );
+
+    let err = RegexOutputFilter::from_rules(&[rule("x", Some("drop"))])
+        .err()
+        .unwrap();
+    assert!(err.contains("Invalid output filter action \"drop\""));
+    assert!(RegexOutputFilter::from_rules(&[rule("(", None)]).is_err());
+}
+
+#[test]
+fn chain_blocks_tool_calls_and_honours_fail_mode() {
+    let settings = SettingsOutputFilters {
+        rules: Some(vec![rule("rm -rf /", Some("block"))]),
+        ..Default::default()
+    };
+    let chain = build_output_filter_chain(&settings, None).unwrap();
+    let message = assistant(
+        vec![
+            text("running rm -rf / now"),
+            ContentBlock::ToolCall {
+                id: "call-1".to_string(),
+                name: "bash".to_string(),
+                arguments: json!({ "command": "rm -rf /" }),
+                thought_signature: None,
+            },
+        ],
+        "toolUse",
+    );
+    let filtered = chain.apply(message, false);
+    assert_eq!(filtered.stop_reason, "stop");
+    assert_eq!(
+        filtered.content,
+        vec![text(
+            "[Response blocked by output policy: matched \"rm -rf /\"]"
+        )]
+    );
+
+    let mut closed = OutputFilterChain::new(true);
+    closed.push(Box::new(FailingFilter));
+    // Filters that only check final output are skipped for streaming partials.
+    let partial = closed.apply(assistant(vec![text("hi")], "stop"), true);
+    assert_eq!(partial.content, vec![text("hi")]);
+    let blocked = closed.apply(assistant(vec![text("hi")], "stop"), false);
+    assert_eq!(
+        blocked.content,
+        vec![text(
+            "[Response blocked by output policy: failing filter failed: service unavailable]"
+        )]
+    );
+
+    let mut open = OutputFilterChain::new(false);
+    open.push(Box::new(FailingFilter));
+    let allowed = open.apply(assistant(vec![text("hi")], "stop"), false);
+    assert_eq!(allowed.content, vec![text("hi")]);
+}
+
+#[test]
+fn moderation_filter_parses_model_verdict() {
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let seen_ref = seen.clone();
+    let stream_fn: Box<StreamFn> =
+        Box::new(move |_model: &Model, context: &LlmContext, _events| {
+            let prompt = match &context.messages[0] {
+                pi::agent::AgentMessage::User(user) => match &user.content {
+                    UserContent::Text(text) => text.clone(),
+                    UserContent::Blocks(_) => String::new(),
+                },
+                _ => String::new(),
+            };
+            seen_ref.borrow_mut().push(prompt.clone());
+            let reply = if prompt.contains("exploit") {
+                "BLOCK: weaponised exploit code"
+            } else {
+                "ALLOW"
+            };
+            assistant(vec![text(reply)], "stop")
+        });
+    let filter = ModerationModelFilter::new(
+        Model {
+            id: "mini".to_string(),
+            name: "mini".to_string(),
+            api: "openai-responses".to_string(),
+            provider: "openai".to_string(),
+        },
+        stream_fn,
+    );
+
+    assert!(!filter.checks_partial());
+    assert_eq!(
+        filter.check(&[text("hello")]).unwrap(),
+        OutputFilterVerdict::Allow
+    );
+    assert_eq!(
+        filter.check(&[text("here is the exploit")]).unwrap(),
+        OutputFilterVerdict::Block("weaponised exploit code".to_string())
+    );
+    assert_eq!(filter.check(&[]).unwrap(), OutputFilterVerdict::Allow);
+    assert_eq!(
+        *seen.borrow(),
+        vec!["hello".to_string(), "here is the exploit".to_string()]
+    );
+}
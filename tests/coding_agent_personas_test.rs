@@ -0,0 +1,170 @@
+use pi::agent::{
+    get_model, Agent, AgentOptions, AgentStateOverride, AgentTool, AgentToolResult, ThinkingLevel,
+};
+use pi::coding_agent::{
+    find_persona, load_personas, parse_persona, AgentSession, AgentSessionConfig, AuthStorage,
+    LoadPersonasOptions, ModelRegistry, PermissionProfile, SettingsManager,
+};
+use pi::core::session_manager::SessionManager;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use uuid::Uuid;
+
+fn write_persona(path: &Path, contents: &str) {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).unwrap();
+    }
+    fs::write(path, contents).unwrap();
+}
+
+fn make_tool(name: &str) -> AgentTool {
+    AgentTool {
+        name: name.to_string(),
+        label: name.to_string(),
+        description: format!("{name} tool"),
+        parameters: None,
+        execute: Rc::new(|_tool_call_id, _params, _on_update| {
+            Ok(AgentToolResult {
+                content: Vec::new(),
+                details: serde_json::Value::Null,
+            })
+        }),
+    }
+}
+
+fn create_session(tools: &[&str]) -> AgentSession {
+    let agent = Agent::new(AgentOptions {
+        initial_state: Some(AgentStateOverride {
+            model: Some(get_model("anthropic", "claude-sonnet-4-5")),
+            system_prompt: Some("Base prompt".to_string()),
+            tools: Some(tools.iter().map(|name| make_tool(name)).collect()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    });
+    let mut auth_storage = AuthStorage::new(PathBuf::from("auth.json"));
+    auth_storage.set_runtime_api_key("anthropic", "test-key");
+
+    AgentSession::new(AgentSessionConfig {
+        agent,
+        session_manager: SessionManager::in_memory(),
+        settings_manager: SettingsManager::create("", ""),
+        model_registry: ModelRegistry::new(auth_storage, None),
+    })
+}
+
+fn tool_names(session: &AgentSession) -> Vec<String> {
+    session
+        .agent
+        .state()
+        .tools
+        .iter()
+        .map(|tool| tool.name.clone())
+        .collect()
+}
+
+#[test]
+fn load_personas_reads_user_and_project_personas() {
+    let root = std::env::temp_dir().join(format!("pi-personas-{}", Uuid::new_v4()));
+    let agent_dir = root.join("agent");
+    let project_dir = root.join("project");
+
+    write_persona(
+        &agent_dir.join("personas").join("reviewer.md"),
+        "---\ndescription: Global reviewer\nthinking: high\npermissions: read-only\n---\nReview carefully.",
+    );
+    write_persona(
+        &agent_dir.join("personas").join("docs-writer.md"),
+        "---\nmodel: anthropic/claude-sonnet-4-5\ntools: read, write\n---\nWrite clear docs.",
+    );
+    write_persona(
+        &project_dir.join(".pi").join("personas").join("reviewer.md"),
+        "---\ndescription: Project reviewer\n---\nFollow the project checklist.",
+    );
+
+    let personas = load_personas(LoadPersonasOptions {
+        cwd: Some(project_dir),
+        agent_dir: Some(agent_dir),
+    });
+    let names = personas
+        .iter()
+        .map(|persona| persona.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["docs-writer", "reviewer"]);
+
+    let reviewer = find_persona(&personas, "reviewer").unwrap();
+    assert_eq!(reviewer.description, "Project reviewer");
+    assert_eq!(reviewer.source, "project");
+    assert_eq!(reviewer.system_prompt, "Follow the project checklist.");
+
+    let docs = find_persona(&personas, "docs-writer").unwrap();
+    assert_eq!(docs.model.as_deref(), Some("anthropic/claude-sonnet-4-5"));
+    assert_eq!(
+        docs.tools,
+        Some(vec!["read".to_string(), "write".to_string()])
+    );
+    assert_eq!(docs.description, "Write clear docs.");
+
+    let _ = fs::remove_dir_all(root);
+}
+
+#[test]
+fn parse_persona_rejects_invalid_fields() {
+    let err = parse_persona("bad", "---\nthinking: extreme\n---\nBody", "user").unwrap_err();
+    assert!(err.contains("invalid thinking level"));
+
+    let err = parse_persona("bad", "---\npermissions: admin\n---\nBody", "user").unwrap_err();
+    assert!(err.contains("invalid permission profile"));
+}
+
+#[test]
+fn persona_filters_tools_by_list_and_permissions() {
+    let persona = parse_persona(
+        "reviewer",
+        "---\ntools: read,bash,grep\npermissions: read-only\n---\nReview.",
+        "user",
+    )
+    .unwrap();
+    assert_eq!(persona.permissions, PermissionProfile::ReadOnly);
+    let available = ["read", "write", "bash", "grep", "ls"].map(String::from);
+    assert_eq!(
+        persona.filter_tools(&available),
+        vec!["read".to_string(), "grep".to_string()]
+    );
+}
+
+#[test]
+fn apply_persona_updates_prompt_tools_and_thinking() {
+    let mut session = create_session(&["read", "write", "bash", "grep"]);
+    let reviewer = parse_persona(
+        "reviewer",
+        "---\nthinking: low\npermissions: read-only\ntools: read,grep,lsp\n---\nReview only.",
+        "user",
+    )
+    .unwrap();
+
+    let missing = session.apply_persona(&reviewer).unwrap();
+    assert_eq!(missing, vec!["lsp".to_string()]);
+    assert_eq!(session.persona(), Some("reviewer"));
+    assert_eq!(tool_names(&session), vec!["read", "grep"]);
+    assert_eq!(session.agent.state().thinking_level, ThinkingLevel::Low);
+    assert_eq!(
+        session.agent.state().system_prompt,
+        "Base prompt\n\nReview only."
+    );
+
+    // Switching personas starts again from the original prompt and tool set.
+    let refactorer = parse_persona("refactorer", "Refactor freely.", "user").unwrap();
+    session.apply_persona(&refactorer).unwrap();
+    assert_eq!(tool_names(&session), vec!["read", "write", "bash", "grep"]);
+    assert_eq!(
+        session.agent.state().system_prompt,
+        "Base prompt\n\nRefactor freely."
+    );
+
+    let unknown_model =
+        parse_persona("fast", "---\nmodel: nope/missing\n---\nBe quick.", "user").unwrap();
+    assert!(session.apply_persona(&unknown_model).is_err());
+    assert_eq!(session.persona(), Some("refactorer"));
+}
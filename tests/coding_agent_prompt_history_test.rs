@@ -0,0 +1,43 @@
+use pi::coding_agent::{append_prompt_history, load_prompt_history, PROMPT_HISTORY_LIMIT};
+use std::fs;
+use uuid::Uuid;
+
+#[test]
+fn prompt_history_round_trips_multi_line_prompts_and_dedups() {
+    let dir = std::env::temp_dir().join(format!("pi-history-{}", Uuid::new_v4()));
+    let path = dir.join("history");
+    assert!(load_prompt_history(&path).is_empty());
+
+    append_prompt_history(&path, "first").unwrap();
+    append_prompt_history(&path, "line one\nline two").unwrap();
+    append_prompt_history(&path, "  ").unwrap();
+    append_prompt_history(&path, "first").unwrap();
+
+    assert_eq!(
+        load_prompt_history(&path),
+        vec!["first".to_string(), "line one\nline two".to_string()]
+    );
+    assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 2);
+
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn prompt_history_drops_oldest_entries_past_limit() {
+    let dir = std::env::temp_dir().join(format!("pi-history-{}", Uuid::new_v4()));
+    let path = dir.join("history");
+    fs::create_dir_all(&dir).unwrap();
+    let content = (0..PROMPT_HISTORY_LIMIT)
+        .map(|i| format!("\"prompt {i}\"\n"))
+        .collect::<String>();
+    fs::write(&path, content).unwrap();
+
+    append_prompt_history(&path, "latest").unwrap();
+
+    let entries = load_prompt_history(&path);
+    assert_eq!(entries.len(), PROMPT_HISTORY_LIMIT);
+    assert_eq!(entries[0], "latest");
+    assert_eq!(entries.last().map(String::as_str), Some("prompt 1"));
+
+    let _ = fs::remove_dir_all(dir);
+}
@@ -1,5 +1,6 @@
 use pi::coding_agent::{
-    expand_prompt_template, load_prompt_templates, LoadPromptTemplatesOptions, PromptTemplate,
+    expand_prompt_template, load_prompt_templates, render_prompt_template, template_arguments,
+    try_expand_prompt_template, LoadPromptTemplatesOptions, PromptTemplate, TemplateArgument,
 };
 use std::fs;
 use std::path::Path;
@@ -44,6 +45,67 @@ fn expand_prompt_template_returns_original_when_missing() {
     assert_eq!(expanded, "/missing arg1 arg2");
 }
 
+fn review_template() -> PromptTemplate {
+    PromptTemplate {
+        name: "review".to_string(),
+        description: "Review a file (user)".to_string(),
+        content: "Review {{path}} focusing on {{focus|correctness}}. Notes: $ARGUMENTS".to_string(),
+        source: "(user)".to_string(),
+    }
+}
+
+#[test]
+fn lists_template_placeholders_in_order() {
+    assert_eq!(
+        template_arguments("{{ a }} {{b|x y}} {{a}} {{not valid}} {{"),
+        vec![
+            TemplateArgument {
+                name: "a".to_string(),
+                default: None,
+            },
+            TemplateArgument {
+                name: "b".to_string(),
+                default: Some("x y".to_string()),
+            },
+        ]
+    );
+}
+
+#[test]
+fn renders_named_and_positional_template_arguments() {
+    let template = review_template();
+
+    let rendered = render_prompt_template(&template, &["src/lib.rs"]).unwrap();
+    assert_eq!(
+        rendered,
+        "Review src/lib.rs focusing on correctness. Notes: src/lib.rs"
+    );
+
+    let rendered = render_prompt_template(&template, &["focus=error handling", "main.rs"]).unwrap();
+    assert_eq!(
+        rendered,
+        "Review main.rs focusing on error handling. Notes: main.rs"
+    );
+
+    let expanded = expand_prompt_template("/review path=a.rs \"focus=speed\"", &[template]);
+    assert_eq!(expanded, "Review a.rs focusing on speed. Notes: ");
+}
+
+#[test]
+fn reports_missing_required_template_arguments() {
+    let templates = vec![review_template()];
+
+    let err = try_expand_prompt_template("/review focus=speed", &templates).unwrap_err();
+    assert_eq!(
+        err,
+        "Template \"review\" is missing required argument \"path\". Usage: /review <path> [focus]"
+    );
+    assert_eq!(
+        expand_prompt_template("/review focus=speed", &templates),
+        "/review focus=speed"
+    );
+}
+
 #[test]
 fn load_prompt_templates_reads_user_and_project_prompts() {
     let root = std::env::temp_dir().join(format!("pi-prompts-{}", Uuid::new_v4()));
@@ -0,0 +1,162 @@
+use pi::coding_agent::tools::{
+    EditTool, EditToolArgs, ReadTool, ReadToolArgs, WriteTool, WriteToolArgs,
+};
+use pi::coding_agent::SharedReadTracker;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+struct Tools {
+    dir: PathBuf,
+    read: ReadTool,
+    write: WriteTool,
+    edit: EditTool,
+}
+
+fn tools() -> Tools {
+    let dir = std::env::temp_dir().join(format!("pi-read-tracker-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    let reads = SharedReadTracker::default();
+    Tools {
+        read: ReadTool::new(&dir).with_read_tracker(reads.clone()),
+        write: WriteTool::new(&dir).with_read_tracker(reads.clone()),
+        edit: EditTool::new(&dir).with_read_tracker(reads),
+        dir,
+    }
+}
+
+impl Tools {
+    fn read(&self, path: &str) {
+        let args = ReadToolArgs {
+            path: path.to_string(),
+            ..Default::default()
+        };
+        self.read.execute("call-1", args).unwrap();
+    }
+
+    fn edit(&self, path: &str, old_text: &str, new_text: &str) -> Result<(), String> {
+        let args = EditToolArgs {
+            path: path.to_string(),
+            old_text: old_text.to_string(),
+            new_text: new_text.to_string(),
+        };
+        self.edit
+            .execute("call-1", args)
+            .map(|_| ())
+            .map_err(|err| err.message)
+    }
+
+    fn write(&self, path: &str, content: &str) -> Result<(), String> {
+        let args = WriteToolArgs {
+            path: path.to_string(),
+            content: content.to_string(),
+        };
+        self.write
+            .execute("call-1", args)
+            .map(|_| ())
+            .map_err(|err| err.message)
+    }
+}
+
+#[test]
+fn refuses_to_edit_a_file_changed_since_it_was_read() {
+    let tools = tools();
+    let path = tools.dir.join("notes.txt");
+    fs::write(&path, "alpha\nbeta\n").unwrap();
+    tools.read("notes.txt");
+
+    fs::write(&path, "alpha\nbeta\ngamma from the user\n").unwrap();
+    let error = tools.edit("notes.txt", "beta", "BETA").unwrap_err();
+    assert!(
+        error.starts_with("File changed on disk: notes.txt was modified since you last read it"),
+        "{error}"
+    );
+    assert!(
+        error.ends_with("Current content:\n1\talpha\n2\tbeta\n3\tgamma from the user"),
+        "{error}"
+    );
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "alpha\nbeta\ngamma from the user\n"
+    );
+    let error = tools.write("notes.txt", "replaced").unwrap_err();
+    assert!(error.starts_with("File changed on disk"), "{error}");
+
+    tools.read("notes.txt");
+    tools.edit("notes.txt", "beta", "BETA").unwrap();
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "alpha\nBETA\ngamma from the user\n"
+    );
+    fs::remove_dir_all(&tools.dir).ok();
+}
+
+#[test]
+fn allows_own_writes_touches_and_unread_files() {
+    let tools = tools();
+    let path = tools.dir.join("lib.rs");
+    fs::write(&path, "fn a() {}\n").unwrap();
+    tools.read("lib.rs");
+
+    // The tools' own changes count as seen.
+    tools.edit("lib.rs", "a", "b").unwrap();
+    tools.edit("lib.rs", "b", "c").unwrap();
+    tools.write("lib.rs", "fn d() {}\n").unwrap();
+
+    // A newer mtime with the same content is not a conflict.
+    let file = fs::File::options().write(true).open(&path).unwrap();
+    file.set_modified(SystemTime::now() + Duration::from_secs(60))
+        .unwrap();
+    tools.edit("lib.rs", "d", "e").unwrap();
+
+    // Files never read are written as before.
+    let other = tools.dir.join("other.rs");
+    fs::write(&other, "old").unwrap();
+    tools.write("other.rs", "new").unwrap();
+
+    tools.read("other.rs");
+    fs::remove_file(&other).unwrap();
+    let error = tools.write("other.rs", "again").unwrap_err();
+    assert!(
+        error.contains("was deleted since you last read it"),
+        "{error}"
+    );
+    fs::remove_dir_all(&tools.dir).ok();
+}
+
+#[test]
+fn stale_error_details_name_the_file_and_its_current_content() {
+    let tools = tools();
+    let path = tools.dir.join("notes.txt");
+    fs::write(&path, "alpha\n").unwrap();
+    tools.read("notes.txt");
+    fs::write(&path, "alpha\nbeta\n").unwrap();
+
+    let error = tools
+        .write
+        .execute(
+            "call-1",
+            WriteToolArgs {
+                path: "notes.txt".to_string(),
+                content: "replaced".to_string(),
+            },
+        )
+        .unwrap_err();
+    assert_eq!(error.details["code"], "stale_read");
+    assert_eq!(error.details["path"], "notes.txt");
+    assert_eq!(error.details["current"], "1\talpha\n2\tbeta");
+
+    fs::remove_file(&path).unwrap();
+    let error = tools.edit.execute(
+        "call-2",
+        EditToolArgs {
+            path: "notes.txt".to_string(),
+            old_text: "alpha".to_string(),
+            new_text: "ALPHA".to_string(),
+        },
+    );
+    let details = error.unwrap_err().details;
+    assert_eq!(details["code"], "stale_read");
+    assert!(details["current"].is_null());
+    fs::remove_dir_all(&tools.dir).ok();
+}
@@ -289,6 +289,37 @@ fn should_get_available_models() {
     }
 }
 
+#[test]
+fn should_complete_slash_commands_and_model_ids() {
+    let session = create_session(false, None);
+    let cwd = create_temp_dir("pi-rpc-complete");
+    let provider = pi::modes::session_autocomplete_provider(&session, cwd.clone());
+
+    let result = provider
+        .get_suggestions(&["/mo".to_string()], 0, 3)
+        .unwrap();
+    assert_eq!(result.prefix, "/mo");
+    assert_eq!(
+        result
+            .items
+            .iter()
+            .map(|item| item.value.as_str())
+            .collect::<Vec<_>>(),
+        vec!["model"]
+    );
+
+    let line = "/model sonnet".to_string();
+    let result = provider.get_suggestions(&[line], 0, 13).unwrap();
+    assert_eq!(result.prefix, "sonnet");
+    assert!(!result.items.is_empty());
+    assert!(result
+        .items
+        .iter()
+        .all(|item| item.value.starts_with("anthropic/") && item.value.contains("sonnet")));
+
+    fs::remove_dir_all(&cwd).unwrap();
+}
+
 #[test]
 fn should_get_session_stats() {
     let mut session = create_session(false, None);
@@ -316,7 +347,9 @@ fn should_export_to_html() {
     let mut session = create_session(true, Some(&temp_dir));
     session.prompt("Hello").unwrap();
 
-    let result = session.export_to_html().unwrap();
+    let output = temp_dir.join("session.html");
+    let result = session.export_to_html_with_path(Some(&output)).unwrap();
+    assert_eq!(result.path, output);
     assert_eq!(
         result.path.extension().and_then(|value| value.to_str()),
         Some("html")
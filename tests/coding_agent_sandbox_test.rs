@@ -0,0 +1,246 @@
+use pi::coding_agent::tools::{ReadTool, ReadToolArgs, WriteTool, WriteToolArgs};
+use pi::coding_agent::{SandboxPolicy, SettingsManager, SettingsSandbox};
+use std::fs;
+use std::path::Path;
+use uuid::Uuid;
+
+#[test]
+fn restricts_reads_and_writes_to_the_workspace() {
+    let root = std::env::temp_dir().join(format!("pi-sandbox-{}", Uuid::new_v4()));
+    let project = root.join("project");
+    fs::create_dir_all(project.join("src")).unwrap();
+    let policy = SandboxPolicy::new(&project);
+
+    assert!(policy.check_read(&project.join("src/lib.rs")).is_ok());
+    assert!(policy.check_write(&project.join("src/new/mod.rs")).is_ok());
+    assert!(policy.check_read(Path::new("/etc/passwd")).is_err());
+    assert!(policy
+        .check_read(&project.join("../../../../../../../etc/passwd"))
+        .is_err());
+    let error = policy.check_write(Path::new("/etc/passwd")).unwrap_err();
+    assert!(error.contains("outside the workspace"));
+    assert!(error.contains("--dangerously-allow-all"));
+
+    for protected in [".pi/auth.json", ".git/config", "nested/.env", ".ssh/id_rsa"] {
+        let error = policy.check_write(&project.join(protected)).unwrap_err();
+        assert!(error.contains("protected"), "{protected}: {error}");
+    }
+    assert!(policy.check_write(&project.join(".gitignore")).is_ok());
+    assert!(policy.check_read(&project.join(".git/config")).is_ok());
+
+    let open = SandboxPolicy::allow_all();
+    assert!(open.check_read(Path::new("/etc/passwd")).is_ok());
+    assert!(open.check_write(&project.join(".git/config")).is_ok());
+
+    let _ = fs::remove_dir_all(&root);
+}
+
+#[test]
+fn settings_add_allowed_paths_or_disable_the_sandbox() {
+    let root = std::env::temp_dir().join(format!("pi-sandbox-{}", Uuid::new_v4()));
+    let project = root.join("project");
+    fs::create_dir_all(&project).unwrap();
+
+    let policy = SandboxPolicy::from_settings(
+        &SettingsSandbox {
+            allowed_paths: Some(vec!["/etc".to_string()]),
+            ..Default::default()
+        },
+        &project,
+    );
+    assert!(policy.enabled);
+    assert!(policy.check_read(Path::new("/etc/passwd")).is_ok());
+    assert!(policy.check_read(Path::new("/usr/bin")).is_err());
+
+    let disabled = SandboxPolicy::from_settings(
+        &SettingsSandbox {
+            enabled: Some(false),
+            ..Default::default()
+        },
+        &project,
+    );
+    assert_eq!(disabled, SandboxPolicy::allow_all());
+
+    let _ = fs::remove_dir_all(&root);
+}
+
+/// Settings for a project whose `.pi/settings.json` is `project`, under global `global`.
+fn project_settings(global: &str, project: &str) -> (SettingsManager, std::path::PathBuf) {
+    let root = std::env::temp_dir().join(format!("pi-sandbox-settings-{}", Uuid::new_v4()));
+    let agent_dir = root.join("agent");
+    let project_dir = root.join("project");
+    fs::create_dir_all(&agent_dir).unwrap();
+    fs::create_dir_all(project_dir.join(".pi")).unwrap();
+    fs::write(agent_dir.join("settings.json"), global).unwrap();
+    fs::write(project_dir.join(".pi").join("settings.json"), project).unwrap();
+    let settings =
+        SettingsManager::create(project_dir.to_string_lossy(), agent_dir.to_string_lossy());
+    (settings, root)
+}
+
+#[test]
+fn project_settings_can_only_tighten_the_sandbox() {
+    let (settings, root) = project_settings(
+        "{}",
+        r#"{"sandbox": {"enabled": false, "wrapBash": false, "allowedPaths": ["/"]}}"#,
+    );
+    let sandbox = settings.get_sandbox_settings();
+    assert_eq!(sandbox.enabled, Some(true));
+    assert_eq!(sandbox.wrap_bash, Some(false));
+    assert_eq!(sandbox.allowed_paths, None);
+    let _ = fs::remove_dir_all(root);
+
+    let (settings, root) = project_settings(
+        r#"{"sandbox": {"enabled": false, "allowedPaths": ["/opt/data"]}}"#,
+        r#"{"sandbox": {"enabled": true, "wrapBash": true, "allowedPaths": ["/"]}}"#,
+    );
+    let sandbox = settings.get_sandbox_settings();
+    assert_eq!(sandbox.enabled, Some(true));
+    assert_eq!(sandbox.wrap_bash, Some(true));
+    assert_eq!(sandbox.allowed_paths, Some(vec!["/opt/data".to_string()]));
+    let _ = fs::remove_dir_all(root);
+
+    let (settings, root) = project_settings(
+        r#"{"sandbox": {"wrapBash": true}}"#,
+        r#"{"sandbox": {"wrapBash": false}}"#,
+    );
+    assert_eq!(settings.get_sandbox_settings().wrap_bash, Some(true));
+    let _ = fs::remove_dir_all(root);
+}
+
+#[test]
+fn project_settings_can_only_tighten_the_bash_policy() {
+    let (settings, root) = project_settings(
+        r#"{"bashPolicy": {"allow": ["cargo *"], "deny": ["git push"], "defaultAction": "ask"}}"#,
+        r#"{"bashPolicy": {"allow": ["rm *"], "deny": ["curl *"], "defaultAction": "allow"}}"#,
+    );
+    let policy = settings.get_bash_policy_settings();
+    assert_eq!(policy.allow, Some(vec!["cargo *".to_string()]));
+    assert_eq!(
+        policy.deny,
+        Some(vec!["git push".to_string(), "curl *".to_string()])
+    );
+    assert_eq!(policy.default_action.as_deref(), Some("ask"));
+    let _ = fs::remove_dir_all(root);
+
+    // A global policy that runs everything can be narrowed by the project's rules.
+    let (settings, root) = project_settings(
+        "{}",
+        r#"{"bashPolicy": {"allow": ["npm test"], "defaultAction": "deny"}}"#,
+    );
+    let policy = settings.get_bash_policy_settings();
+    assert_eq!(policy.allow, Some(vec!["npm test".to_string()]));
+    assert_eq!(policy.default_action.as_deref(), Some("deny"));
+    let _ = fs::remove_dir_all(root);
+}
+
+#[test]
+fn trusted_project_settings_can_loosen_the_sandbox_and_bash_policy() {
+    let (settings, root) = project_settings(
+        r#"{"trustProjectSettings": true, "bashPolicy": {"defaultAction": "ask"}}"#,
+        r#"{"sandbox": {"enabled": false, "allowedPaths": ["/opt"]},
+            "bashPolicy": {"defaultAction": "allow"}}"#,
+    );
+    let sandbox = settings.get_sandbox_settings();
+    assert_eq!(sandbox.enabled, Some(false));
+    assert_eq!(sandbox.allowed_paths, Some(vec!["/opt".to_string()]));
+    assert_eq!(
+        settings
+            .get_bash_policy_settings()
+            .default_action
+            .as_deref(),
+        Some("allow")
+    );
+    let _ = fs::remove_dir_all(root);
+
+    // A project cannot trust itself.
+    let (settings, root) = project_settings(
+        "{}",
+        r#"{"trustProjectSettings": true, "sandbox": {"enabled": false}}"#,
+    );
+    assert_eq!(settings.get_sandbox_settings().enabled, Some(true));
+    let _ = fs::remove_dir_all(root);
+}
+
+#[test]
+fn sandboxed_tools_refuse_paths_outside_the_policy() {
+    let root = std::env::temp_dir().join(format!("pi-sandbox-{}", Uuid::new_v4()));
+    let project = root.join("project");
+    fs::create_dir_all(&project).unwrap();
+    fs::write(project.join("notes.txt"), "hello\n").unwrap();
+    let policy = SandboxPolicy::new(&project);
+
+    let read = ReadTool::new(&project).with_sandbox(policy.clone());
+    assert!(read
+        .execute(
+            "call-1",
+            ReadToolArgs {
+                path: "notes.txt".to_string(),
+                offset: None,
+                limit: None,
+                ..Default::default()
+            },
+        )
+        .is_ok());
+    assert!(read
+        .execute(
+            "call-2",
+            ReadToolArgs {
+                path: "/etc/passwd".to_string(),
+                offset: None,
+                limit: None,
+                ..Default::default()
+            },
+        )
+        .unwrap_err()
+        .starts_with("Access denied"));
+
+    let write = WriteTool::new(&project).with_sandbox(policy);
+    let error = write
+        .execute(
+            "call-3",
+            WriteToolArgs {
+                path: ".pi/settings.json".to_string(),
+                content: "{}".to_string(),
+            },
+        )
+        .unwrap_err();
+    assert!(error.message.contains("protected"));
+    assert!(!project.join(".pi").exists());
+
+    let _ = fs::remove_dir_all(&root);
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks_cannot_lead_writes_out_of_the_workspace() {
+    use std::os::unix::fs::symlink;
+
+    let root = std::env::temp_dir().join(format!("pi-sandbox-{}", Uuid::new_v4()));
+    let project = root.join("project");
+    fs::create_dir_all(project.join("src")).unwrap();
+    // Outside the temp dir too, which the sandbox allows on its own.
+    symlink("/usr/share", project.join("link_to_outside")).unwrap();
+    symlink("/usr/pi-sandbox-missing/file", project.join("dangling")).unwrap();
+    symlink("src", project.join("link_inside")).unwrap();
+    let policy = SandboxPolicy::new(&project);
+
+    // The kernel resolves `..` after following the link: this is /usr/newfile.
+    let error = policy
+        .check_write(&project.join("link_to_outside/../newfile"))
+        .unwrap_err();
+    assert!(error.contains("/usr/newfile"), "{error}");
+    assert!(policy
+        .check_write(&project.join("link_to_outside/new/file"))
+        .is_err());
+    assert!(policy.check_write(&project.join("dangling")).is_err());
+
+    assert!(policy
+        .check_write(&project.join("link_inside/../new.rs"))
+        .is_ok());
+    assert!(policy
+        .check_write(&project.join("link_inside/new/../lib.rs"))
+        .is_ok());
+
+    let _ = fs::remove_dir_all(&root);
+}
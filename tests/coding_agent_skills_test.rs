@@ -1,9 +1,11 @@
 use pi::coding_agent::{
-    format_skills_for_prompt, load_skills, load_skills_from_dir, LoadSkillsFromDirOptions,
-    LoadSkillsOptions, Skill,
+    expand_skill, format_skills_for_prompt, load_skills, load_skills_from_dir,
+    LoadSkillsFromDirOptions, LoadSkillsOptions, Skill, SkillArgument,
 };
 use std::env;
+use std::fs;
 use std::path::PathBuf;
+use uuid::Uuid;
 
 // Source: packages/coding-agent/test/skills.test.ts
 
@@ -212,6 +214,8 @@ fn should_format_skills_as_xml() {
         file_path: "/path/to/skill/SKILL.md".to_string(),
         base_dir: "/path/to/skill".to_string(),
         source: "test".to_string(),
+        arguments: Vec::new(),
+        allowed_tools: Vec::new(),
     }];
 
     let result = format_skills_for_prompt(&skills);
@@ -232,6 +236,8 @@ fn should_include_intro_text_before_xml() {
         file_path: "/path/to/skill/SKILL.md".to_string(),
         base_dir: "/path/to/skill".to_string(),
         source: "test".to_string(),
+        arguments: Vec::new(),
+        allowed_tools: Vec::new(),
     }];
 
     let result = format_skills_for_prompt(&skills);
@@ -250,6 +256,8 @@ fn should_escape_xml_special_characters() {
         file_path: "/path/to/skill/SKILL.md".to_string(),
         base_dir: "/path/to/skill".to_string(),
         source: "test".to_string(),
+        arguments: Vec::new(),
+        allowed_tools: Vec::new(),
     }];
 
     let result = format_skills_for_prompt(&skills);
@@ -268,6 +276,8 @@ fn should_format_multiple_skills() {
             file_path: "/path/one/SKILL.md".to_string(),
             base_dir: "/path/one".to_string(),
             source: "test".to_string(),
+            arguments: Vec::new(),
+            allowed_tools: Vec::new(),
         },
         Skill {
             name: "skill-two".to_string(),
@@ -275,6 +285,8 @@ fn should_format_multiple_skills() {
             file_path: "/path/two/SKILL.md".to_string(),
             base_dir: "/path/two".to_string(),
             source: "test".to_string(),
+            arguments: Vec::new(),
+            allowed_tools: Vec::new(),
         },
     ];
 
@@ -500,3 +512,97 @@ fn should_detect_name_collisions_and_keep_first_skill() {
     assert_eq!(collision_warnings.len(), 1);
     assert!(collision_warnings[0].contains("name collision"));
 }
+
+fn write_review_skill() -> PathBuf {
+    let root = env::temp_dir().join(format!("pi-skills-{}", Uuid::new_v4()));
+    let dir = root.join("review");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("SKILL.md"),
+        "---\nname: review\ndescription: Review a file.\nallowed-tools: read, grep\narguments:\n  - name: path\n    description: File to review\n    required: true\n  - name: focus\n    default: correctness\n---\n# Review\n\nReview ${path} for ${focus}.\n",
+    )
+    .unwrap();
+    root
+}
+
+#[test]
+fn should_parse_arguments_and_allowed_tools() {
+    let root = write_review_skill();
+    let result = load_skills_from_dir(LoadSkillsFromDirOptions {
+        dir: root.clone(),
+        source: "test".to_string(),
+    });
+
+    assert!(result.warnings.is_empty(), "{:?}", result.warnings);
+    let skill = &result.skills[0];
+    assert_eq!(skill.allowed_tools, vec!["read", "grep"]);
+    assert_eq!(
+        skill.arguments,
+        vec![
+            SkillArgument {
+                name: "path".to_string(),
+                description: Some("File to review".to_string()),
+                required: true,
+                default: None,
+            },
+            SkillArgument {
+                name: "focus".to_string(),
+                description: None,
+                required: false,
+                default: Some("correctness".to_string()),
+            },
+        ]
+    );
+
+    let prompt = format_skills_for_prompt(&result.skills);
+    assert!(prompt.contains(
+        "<arguments>path (required): File to review; focus (default: correctness)</arguments>"
+    ));
+    assert!(prompt.contains("<allowed_tools>read, grep</allowed_tools>"));
+    let _ = fs::remove_dir_all(root);
+}
+
+#[test]
+fn should_expand_skill_with_arguments() {
+    let root = write_review_skill();
+    let skill = load_skills_from_dir(LoadSkillsFromDirOptions {
+        dir: root.clone(),
+        source: "test".to_string(),
+    })
+    .skills
+    .remove(0);
+
+    let prompt = expand_skill(&skill, "src/main.rs").unwrap();
+    assert!(prompt.starts_with("<skill name=\"review\""));
+    assert!(prompt.contains("Only use these tools for this skill: read, grep."));
+    assert!(prompt.contains("Review src/main.rs for correctness."));
+    assert!(!prompt.contains("name: review"));
+
+    let prompt = expand_skill(&skill, "lib.rs error handling").unwrap();
+    assert!(prompt.contains("Review lib.rs for error handling."));
+
+    let err = expand_skill(&skill, "").unwrap_err();
+    assert!(err.contains("requires argument \"path\""));
+    assert!(err.contains("/skill review <path> [focus]"));
+    let _ = fs::remove_dir_all(root);
+}
+
+#[test]
+fn should_append_arguments_the_skill_does_not_reference() {
+    let root = write_review_skill();
+    let file_path = root.join("review").join("SKILL.md");
+    fs::write(&file_path, "Summarize the work so far.\n").unwrap();
+    let skill = Skill {
+        name: "notes".to_string(),
+        description: "Take notes.".to_string(),
+        file_path: file_path.display().to_string(),
+        base_dir: root.display().to_string(),
+        source: "test".to_string(),
+        arguments: Vec::new(),
+        allowed_tools: Vec::new(),
+    };
+
+    let prompt = expand_skill(&skill, "in three bullets").unwrap();
+    assert!(prompt.ends_with("</skill>\n\nin three bullets"));
+    let _ = fs::remove_dir_all(root);
+}
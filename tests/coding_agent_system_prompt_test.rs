@@ -0,0 +1,144 @@
+use pi::coding_agent::{
+    build_system_prompt, load_project_context_files, BuildSystemPromptOptions,
+    LoadContextFilesOptions, PromptOverrides, SettingsManager,
+};
+use std::collections::HashMap;
+use std::fs;
+use uuid::Uuid;
+
+#[test]
+fn discovers_project_context_files_walking_up_from_cwd() {
+    let root = std::env::temp_dir().join(format!("pi-context-{}", Uuid::new_v4()));
+    let agent_dir = root.join("agent");
+    let project = root.join("project");
+    let package = project.join("packages").join("app");
+    fs::create_dir_all(&agent_dir).unwrap();
+    fs::create_dir_all(package.join(".pi")).unwrap();
+    fs::write(project.join("AGENTS.md"), "Use tabs.").unwrap();
+    fs::write(project.join("CLAUDE.md"), "Ignored next to AGENTS.md.").unwrap();
+    fs::write(package.join("CLAUDE.md"), "Run the app tests.").unwrap();
+    fs::write(package.join(".pi").join("CONTEXT.md"), "App context.").unwrap();
+
+    let files = load_project_context_files(LoadContextFilesOptions {
+        cwd: Some(package.clone()),
+        agent_dir: Some(agent_dir.clone()),
+    });
+    let paths = files
+        .iter()
+        .map(|file| file.path.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        vec![
+            project.join("AGENTS.md").display().to_string(),
+            package.join("CLAUDE.md").display().to_string(),
+            package.join(".pi").join("CONTEXT.md").display().to_string(),
+        ]
+    );
+
+    let prompt = build_system_prompt(BuildSystemPromptOptions {
+        custom_prompt: Some("Custom prompt".to_string()),
+        cwd: Some(package.clone()),
+        agent_dir: Some(agent_dir.clone()),
+        ..Default::default()
+    });
+    assert!(prompt.contains("# Project Context"));
+    let agents = prompt.find("Use tabs.").unwrap();
+    let app = prompt.find("App context.").unwrap();
+    assert!(agents < app);
+
+    let opted_out = build_system_prompt(BuildSystemPromptOptions {
+        custom_prompt: Some("Custom prompt".to_string()),
+        cwd: Some(package),
+        agent_dir: Some(agent_dir),
+        context_files: Some(Vec::new()),
+        ..Default::default()
+    });
+    assert!(!opted_out.contains("# Project Context"));
+
+    let _ = fs::remove_dir_all(root);
+}
+
+#[test]
+fn overrides_reorder_replace_and_drop_sections() {
+    let cwd = std::env::temp_dir().join(format!("pi-prompt-{}", Uuid::new_v4()));
+    fs::create_dir_all(&cwd).unwrap();
+    let overrides = PromptOverrides {
+        order: vec!["brand".to_string(), "tools".to_string()],
+        sections: HashMap::from([
+            ("identity".to_string(), "You are Acme's agent.".to_string()),
+            ("docs".to_string(), String::new()),
+            ("brand".to_string(), "Answer in Acme's voice.".to_string()),
+        ]),
+        tool_descriptions: HashMap::from([("read".to_string(), "Open a file".to_string())]),
+    };
+
+    let prompt = build_system_prompt(BuildSystemPromptOptions {
+        selected_tools: Some(vec!["read".to_string(), "bash".to_string()]),
+        cwd: Some(cwd.clone()),
+        context_files: Some(Vec::new()),
+        overrides,
+        ..Default::default()
+    });
+    assert!(prompt.starts_with("Answer in Acme's voice.\n\nAvailable tools:\n- read: Open a file"));
+    assert!(prompt.contains("- bash: Execute bash commands"));
+    let identity = prompt.find("You are Acme's agent.").unwrap();
+    let safety = prompt
+        .find("Safety:\n- Use bash ONLY for read-only operations")
+        .unwrap();
+    let guidelines = prompt.find("Guidelines:").unwrap();
+    assert!(identity < safety && safety < guidelines);
+    assert!(!prompt[guidelines..].contains("read-only"));
+    assert!(!prompt.contains("You are an expert coding assistant"));
+    assert!(!prompt.contains("Documentation:"));
+    assert!(prompt.ends_with(&format!("Current working directory: {}", cwd.display())));
+
+    let safety_first = build_system_prompt(BuildSystemPromptOptions {
+        selected_tools: Some(vec!["read".to_string()]),
+        cwd: Some(cwd.clone()),
+        context_files: Some(Vec::new()),
+        overrides: PromptOverrides {
+            order: vec!["safety".to_string()],
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    assert!(safety_first.starts_with("Safety:\n- You are in READ-ONLY mode"));
+
+    let writable = build_system_prompt(BuildSystemPromptOptions {
+        cwd: Some(cwd.clone()),
+        context_files: Some(Vec::new()),
+        ..Default::default()
+    });
+    assert!(!writable.contains("Safety:"));
+
+    let _ = fs::remove_dir_all(cwd);
+}
+
+#[test]
+fn project_settings_add_to_global_prompt_overrides() {
+    let root = std::env::temp_dir().join(format!("pi-prompt-settings-{}", Uuid::new_v4()));
+    let agent_dir = root.join("agent");
+    let project = root.join("project");
+    fs::create_dir_all(&agent_dir).unwrap();
+    fs::create_dir_all(project.join(".pi")).unwrap();
+    fs::write(
+        agent_dir.join("settings.json"),
+        r#"{"systemPrompt": {"order": ["context"], "sections": {"identity": "Global", "docs": ""}}}"#,
+    )
+    .unwrap();
+    fs::write(
+        project.join(".pi").join("settings.json"),
+        r#"{"systemPrompt": {"sections": {"identity": "Project"}, "toolDescriptions": {"bash": "Run it"}}}"#,
+    )
+    .unwrap();
+
+    let settings = SettingsManager::create(project.to_string_lossy(), agent_dir.to_string_lossy());
+    let overrides = settings.get_prompt_overrides();
+    assert_eq!(overrides.order, vec!["context"]);
+    assert_eq!(overrides.sections["identity"], "Project");
+    assert_eq!(overrides.sections["docs"], "");
+    assert_eq!(overrides.tool_descriptions["bash"], "Run it");
+
+    let _ = fs::remove_dir_all(root);
+}
@@ -0,0 +1,206 @@
+use pi::agent::{AgentEvent, AgentMessage, AgentToolResult};
+use pi::coding_agent::{
+    create_telemetry_sink, AgentSessionEvent, SettingsTelemetry, TelemetryEvent, TelemetryRecorder,
+    TelemetrySink,
+};
+use pi::{AssistantMessage, Cost, Usage};
+use serde_json::Value;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+#[derive(Default)]
+struct RecordingSink {
+    events: RefCell<Vec<TelemetryEvent>>,
+    flushes: Cell<usize>,
+}
+
+impl TelemetrySink for RecordingSink {
+    fn record(&self, event: &TelemetryEvent) {
+        self.events.borrow_mut().push(event.clone());
+    }
+
+    fn flush(&self) {
+        self.flushes.set(self.flushes.get() + 1);
+    }
+}
+
+fn agent_event(event: AgentEvent) -> AgentSessionEvent {
+    AgentSessionEvent::Agent(Box::new(event))
+}
+
+fn assistant_message(stop_reason: &str, error_message: Option<&str>) -> AgentMessage {
+    AgentMessage::Assistant(AssistantMessage {
+        content: Vec::new(),
+        api: "anthropic-messages".to_string(),
+        provider: "anthropic".to_string(),
+        model: "claude-sonnet-4-5".to_string(),
+        usage: Usage {
+            input: 120,
+            output: 30,
+            cache_read: 10,
+            cache_write: 5,
+            total_tokens: Some(165),
+            cost: Some(Cost {
+                input: 0.1,
+                output: 0.2,
+                cache_read: 0.0,
+                cache_write: 0.0,
+                total: 0.3,
+            }),
+        },
+        stop_reason: stop_reason.to_string(),
+        error_message: error_message.map(str::to_string),
+        timestamp: 0,
+    })
+}
+
+#[test]
+fn recorder_reports_llm_requests_tools_and_errors() {
+    let sink = Rc::new(RecordingSink::default());
+    let recorder = TelemetryRecorder::new(sink.clone());
+
+    recorder.handle_event(&agent_event(AgentEvent::TurnStart));
+    recorder.handle_event(&agent_event(AgentEvent::MessageEnd {
+        message: assistant_message("toolUse", None),
+    }));
+    recorder.handle_event(&agent_event(AgentEvent::ToolExecutionStart {
+        tool_call_id: "call-1".to_string(),
+        tool_name: "bash".to_string(),
+        args: Value::Null,
+    }));
+    recorder.handle_event(&agent_event(AgentEvent::ToolExecutionEnd {
+        tool_call_id: "call-1".to_string(),
+        tool_name: "bash".to_string(),
+        result: AgentToolResult {
+            content: Vec::new(),
+            details: Value::Null,
+        },
+        is_error: true,
+    }));
+    recorder.handle_event(&agent_event(AgentEvent::TurnStart));
+    recorder.handle_event(&agent_event(AgentEvent::MessageEnd {
+        message: assistant_message("error", Some("overloaded")),
+    }));
+    recorder.handle_event(&AgentSessionEvent::AutoCompactionEnd { aborted: false });
+    recorder.handle_event(&agent_event(AgentEvent::AgentEnd {
+        messages: Vec::new(),
+    }));
+
+    let events = sink.events.borrow();
+    let names = events.iter().map(TelemetryEvent::name).collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        vec!["llm_request", "tool_execution", "llm_request", "error"]
+    );
+    match &events[0] {
+        TelemetryEvent::LlmRequest {
+            provider,
+            model,
+            stop_reason,
+            input_tokens,
+            output_tokens,
+            cache_read_tokens,
+            cache_write_tokens,
+            cost,
+            ..
+        } => {
+            assert_eq!(provider, "anthropic");
+            assert_eq!(model, "claude-sonnet-4-5");
+            assert_eq!(stop_reason, "toolUse");
+            assert_eq!(
+                (
+                    *input_tokens,
+                    *output_tokens,
+                    *cache_read_tokens,
+                    *cache_write_tokens
+                ),
+                (120, 30, 10, 5)
+            );
+            assert!((cost - 0.3).abs() < f64::EPSILON);
+        }
+        other => panic!("unexpected event: {other:?}"),
+    }
+    match &events[1] {
+        TelemetryEvent::ToolExecution {
+            tool_name,
+            is_error,
+            ..
+        } => {
+            assert_eq!(tool_name, "bash");
+            assert!(*is_error);
+        }
+        other => panic!("unexpected event: {other:?}"),
+    }
+    assert_eq!(
+        events[3],
+        TelemetryEvent::Error {
+            source: "llm".to_string(),
+            message: "overloaded".to_string(),
+        }
+    );
+    assert_eq!(sink.flushes.get(), 1);
+}
+
+#[test]
+fn create_telemetry_sink_respects_settings() {
+    let disabled = SettingsTelemetry::default();
+    assert!(create_telemetry_sink(&disabled).unwrap().is_none());
+
+    let settings: SettingsTelemetry = serde_json::from_value(serde_json::json!({
+        "enabled": true,
+        "exporter": "statsd",
+    }))
+    .unwrap();
+    let err = create_telemetry_sink(&settings).err().unwrap();
+    assert!(err.contains("Unknown telemetry exporter \"statsd\""));
+
+    let otlp = SettingsTelemetry {
+        enabled: Some(true),
+        endpoint: Some("http://127.0.0.1:4318".to_string()),
+        ..Default::default()
+    };
+    let result = create_telemetry_sink(&otlp);
+    if cfg!(feature = "otlp") {
+        assert!(result.unwrap().is_some());
+    } else {
+        assert!(result.err().unwrap().contains("otlp feature"));
+    }
+}
+
+#[cfg(feature = "otlp")]
+#[test]
+fn otlp_spans_carry_event_attributes() {
+    use pi::coding_agent::telemetry::otlp::{build_span, build_traces_payload, OtlpConfig};
+
+    let event = TelemetryEvent::ToolExecution {
+        tool_name: "read".to_string(),
+        is_error: false,
+        duration_ms: 25,
+    };
+    let span = build_span(&event, "0123456789abcdef0123456789abcdef", 1_000_000_000);
+    assert_eq!(span["name"], "tool_execution");
+    assert_eq!(span["startTimeUnixNano"], "975000000");
+    assert_eq!(span["endTimeUnixNano"], "1000000000");
+    assert_eq!(span["attributes"][0]["key"], "pi.tool.name");
+    assert_eq!(span["attributes"][0]["value"]["stringValue"], "read");
+    assert_eq!(span["status"]["code"], 1);
+
+    let payload = build_traces_payload("fleet-agent", vec![span]);
+    let resource = &payload["resourceSpans"][0];
+    assert_eq!(
+        resource["resource"]["attributes"][0]["value"]["stringValue"],
+        "fleet-agent"
+    );
+    assert_eq!(
+        resource["scopeSpans"][0]["spans"][0]["name"],
+        "tool_execution"
+    );
+
+    let config = OtlpConfig::from_settings(&SettingsTelemetry {
+        enabled: Some(true),
+        endpoint: Some("https://otel.example.com/".to_string()),
+        ..Default::default()
+    })
+    .unwrap();
+    assert_eq!(config.traces_url(), "https://otel.example.com/v1/traces");
+}
@@ -1,11 +1,14 @@
+use pi::agent::CancellationToken;
 use pi::coding_agent::tools::{
-    BashTool, BashToolArgs, EditTool, EditToolArgs, FindTool, FindToolArgs, GrepTool, GrepToolArgs,
-    LsTool, LsToolArgs, ReadTool, ReadToolArgs, ToolResult, WriteTool, WriteToolArgs,
+    normalize_tool_path, BashTool, BashToolArgs, EditTool, EditToolArgs, FindTool, FindToolArgs,
+    GrepTool, GrepToolArgs, LsTool, LsToolArgs, ReadTool, ReadToolArgs, ToolResult, WriteTool,
+    WriteToolArgs,
 };
+use pi::coding_agent::{SharedChangeJournal, Shell, ShellKind};
 use pi::ContentBlock;
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 // Source: packages/coding-agent/test/tools.test.ts
 
@@ -67,6 +70,7 @@ fn should_read_file_contents_that_fit_within_limits() {
                 path: test_file.to_string_lossy().to_string(),
                 offset: None,
                 limit: None,
+                ..Default::default()
             },
         )
         .expect("read tool");
@@ -90,6 +94,7 @@ fn should_handle_non_existent_files() {
                 path: test_file.to_string_lossy().to_string(),
                 offset: None,
                 limit: None,
+                ..Default::default()
             },
         )
         .expect_err("expected error");
@@ -111,6 +116,7 @@ fn should_truncate_files_exceeding_line_limit() {
                 path: test_file.to_string_lossy().to_string(),
                 offset: None,
                 limit: None,
+                ..Default::default()
             },
         )
         .expect("read tool");
@@ -139,6 +145,7 @@ fn should_truncate_when_byte_limit_exceeded() {
                 path: test_file.to_string_lossy().to_string(),
                 offset: None,
                 limit: None,
+                ..Default::default()
             },
         )
         .expect("read tool");
@@ -166,6 +173,7 @@ fn should_handle_offset_parameter() {
                 path: test_file.to_string_lossy().to_string(),
                 offset: Some(51),
                 limit: None,
+                ..Default::default()
             },
         )
         .expect("read tool");
@@ -192,6 +200,7 @@ fn should_handle_limit_parameter() {
                 path: test_file.to_string_lossy().to_string(),
                 offset: None,
                 limit: Some(10),
+                ..Default::default()
             },
         )
         .expect("read tool");
@@ -218,6 +227,7 @@ fn should_handle_offset_limit_together() {
                 path: test_file.to_string_lossy().to_string(),
                 offset: Some(41),
                 limit: Some(20),
+                ..Default::default()
             },
         )
         .expect("read tool");
@@ -244,6 +254,7 @@ fn should_show_error_when_offset_is_beyond_file_length() {
                 path: test_file.to_string_lossy().to_string(),
                 offset: Some(100),
                 limit: None,
+                ..Default::default()
             },
         )
         .expect_err("expected error");
@@ -265,6 +276,7 @@ fn should_include_truncation_details_when_truncated() {
                 path: test_file.to_string_lossy().to_string(),
                 offset: None,
                 limit: None,
+                ..Default::default()
             },
         )
         .expect("read tool");
@@ -308,6 +320,7 @@ fn should_detect_image_mime_type_from_file_magic_not_extension() {
                 path: test_file.to_string_lossy().to_string(),
                 offset: None,
                 limit: None,
+                ..Default::default()
             },
         )
         .expect("read tool");
@@ -354,6 +367,7 @@ fn should_detect_additional_image_mime_types() {
                 path: jpeg_file.to_string_lossy().to_string(),
                 offset: None,
                 limit: None,
+                ..Default::default()
             },
         )
         .expect("read jpeg");
@@ -369,6 +383,7 @@ fn should_detect_additional_image_mime_types() {
                 path: gif_file.to_string_lossy().to_string(),
                 offset: None,
                 limit: None,
+                ..Default::default()
             },
         )
         .expect("read gif");
@@ -384,6 +399,7 @@ fn should_detect_additional_image_mime_types() {
                 path: webp_file.to_string_lossy().to_string(),
                 offset: None,
                 limit: None,
+                ..Default::default()
             },
         )
         .expect("read webp");
@@ -407,6 +423,7 @@ fn should_treat_files_with_image_extension_but_non_image_content_as_text() {
                 path: test_file.to_string_lossy().to_string(),
                 offset: None,
                 limit: None,
+                ..Default::default()
             },
         )
         .expect("read tool");
@@ -486,6 +503,11 @@ fn should_replace_text_in_file() {
     let details = result.details.expect("details");
     let diff = details.get("diff").and_then(|v| v.as_str()).unwrap_or("");
     assert!(diff.contains("testing"));
+    assert!(result.content.iter().any(|block| matches!(
+        block,
+        ContentBlock::Diff { diff, added: 1, removed: 1, .. }
+            if diff == "@@ -1,1 +1,1 @@\n-Hello, world!\n+Hello, testing!"
+    )));
 }
 
 #[test]
@@ -506,7 +528,7 @@ fn should_fail_if_text_not_found() {
         )
         .expect_err("expected error");
 
-    assert!(err.contains("Could not find the exact text"));
+    assert!(err.message.contains("Could not find the exact text"));
 }
 
 #[test]
@@ -527,7 +549,7 @@ fn should_fail_if_text_appears_multiple_times() {
         )
         .expect_err("expected error");
 
-    assert!(err.contains("Found 3 occurrences"));
+    assert!(err.message.contains("Found 3 occurrences"));
 }
 
 #[test]
@@ -583,6 +605,75 @@ fn should_respect_timeout() {
     assert!(err.to_lowercase().contains("timed out"));
 }
 
+#[test]
+fn should_kill_command_when_cancelled() {
+    let temp = TempDir::new("coding-agent-test");
+    let cancel = CancellationToken::new();
+    let tool = BashTool::new(&temp.path)
+        .with_shell(Shell::from_path("/bin/sh"))
+        .with_cancellation(cancel.clone());
+    let started = Instant::now();
+    let err = tool
+        .execute_with_updates(
+            "test-call-10c",
+            BashToolArgs {
+                command: "echo started; sleep 5".to_string(),
+                timeout: None,
+            },
+            &mut |_| cancel.cancel(),
+        )
+        .expect_err("expected error");
+
+    assert!(started.elapsed() < Duration::from_secs(3));
+    assert_eq!(err, "started\n\n\nCommand aborted");
+}
+
+#[cfg(unix)]
+#[test]
+fn should_kill_processes_the_command_started_when_cancelled() {
+    let temp = TempDir::new("coding-agent-test");
+    let marker = temp.path.join("marker");
+    let cancel = CancellationToken::new();
+    let tool = BashTool::new(&temp.path)
+        .with_shell(Shell::from_path("/bin/sh"))
+        .with_cancellation(cancel.clone());
+    tool.execute_with_updates(
+        "test-call-10d",
+        BashToolArgs {
+            command: "(sleep 1; touch marker) & echo started; wait".to_string(),
+            timeout: None,
+        },
+        &mut |_| cancel.cancel(),
+    )
+    .expect_err("expected error");
+
+    std::thread::sleep(Duration::from_millis(1500));
+    assert!(!marker.exists(), "the background job outlived the abort");
+}
+
+#[test]
+fn should_report_partial_output_while_command_runs() {
+    let temp = TempDir::new("coding-agent-test");
+    let tool = BashTool::new(&temp.path).with_shell(Shell::from_path("/bin/sh"));
+    let mut partials = Vec::new();
+    let result = tool
+        .execute_with_updates(
+            "test-call-10b",
+            BashToolArgs {
+                command: "echo first; sleep 0.3; echo second".to_string(),
+                timeout: None,
+            },
+            &mut |partial| partials.push(get_text_output(&partial)),
+        )
+        .expect("bash tool");
+
+    assert_eq!(get_text_output(&result), "first\nsecond\n");
+    assert_eq!(partials.first().map(String::as_str), Some("first\n"));
+    assert!(partials
+        .iter()
+        .all(|partial| "first\nsecond\n".starts_with(partial.as_str())));
+}
+
 #[test]
 fn should_include_filename_when_searching_a_single_file() {
     let temp = TempDir::new("coding-agent-test");
@@ -601,6 +692,8 @@ fn should_include_filename_when_searching_a_single_file() {
                 literal: None,
                 context: None,
                 limit: None,
+                multiline: None,
+                file_type: None,
             },
         )
         .expect("grep tool");
@@ -636,6 +729,8 @@ fn should_respect_global_limit_and_include_context_lines() {
                 literal: None,
                 context: Some(1),
                 limit: Some(1),
+                multiline: None,
+                file_type: None,
             },
         )
         .expect("grep tool");
@@ -664,6 +759,7 @@ fn should_include_hidden_files_that_are_not_gitignored() {
                 pattern: "**/*.txt".to_string(),
                 path: Some(temp.path.to_string_lossy().to_string()),
                 limit: None,
+                ..Default::default()
             },
         )
         .expect("find tool");
@@ -693,6 +789,7 @@ fn should_respect_gitignore() {
                 pattern: "**/*.txt".to_string(),
                 path: Some(temp.path.to_string_lossy().to_string()),
                 limit: None,
+                ..Default::default()
             },
         )
         .expect("find tool");
@@ -715,6 +812,7 @@ fn should_list_dotfiles_and_directories() {
             LsToolArgs {
                 path: Some(temp.path.to_string_lossy().to_string()),
                 limit: None,
+                ..Default::default()
             },
         )
         .expect("ls tool");
@@ -806,7 +904,7 @@ fn should_detect_duplicates_across_crlf_lf_variants() {
         )
         .expect_err("expected error");
 
-    assert!(err.contains("Found 2 occurrences"));
+    assert!(err.message.contains("Found 2 occurrences"));
 }
 
 #[test]
@@ -829,3 +927,119 @@ fn should_preserve_utf_8_bom_after_edit() {
     let content = fs::read_to_string(&test_file).expect("read file");
     assert_eq!(content, "\u{feff}first\r\nREPLACED\r\nthird\r\n");
 }
+
+#[test]
+fn should_run_bash_tool_with_configured_shell() {
+    let temp = TempDir::new("coding-agent-shell-test");
+    let tool = BashTool::new(&temp.path).with_shell(Shell::from_path("/bin/sh"));
+    let result = tool
+        .execute(
+            "test-shell",
+            BashToolArgs {
+                command: "echo \"$0\"".to_string(),
+                timeout: None,
+            },
+        )
+        .expect("bash tool");
+
+    assert!(get_text_output(&result).contains("/bin/sh"));
+}
+
+#[test]
+fn should_pick_shell_arguments_by_shell_kind() {
+    let pwsh = Shell::configured_or(
+        Some("C:\\Program Files\\PowerShell\\7\\pwsh.exe"),
+        Shell::tool_default,
+    );
+    assert_eq!(pwsh.kind, ShellKind::PowerShell);
+    assert_eq!(
+        pwsh.args("dir"),
+        [
+            "-NoLogo",
+            "-NoProfile",
+            "-NonInteractive",
+            "-Command",
+            "dir"
+        ]
+    );
+    let cmd = Shell::from_path("cmd.exe");
+    assert_eq!(cmd.kind, ShellKind::Cmd);
+    assert_eq!(cmd.args("dir"), ["/d", "/s", "/c", "dir"]);
+    assert_eq!(Shell::from_path("zsh").args("ls"), ["-c", "ls"]);
+    assert_eq!(
+        Shell::configured_or(Some(" "), Shell::tool_default),
+        Shell::tool_default()
+    );
+}
+
+#[test]
+fn should_normalize_tool_paths_for_windows() {
+    assert_eq!(
+        normalize_tool_path("/c/Users/me/file.txt", true),
+        "C:\\Users\\me\\file.txt"
+    );
+    assert_eq!(normalize_tool_path("/d", true), "D:\\");
+    assert_eq!(normalize_tool_path("src/main.rs", true), "src\\main.rs");
+    assert_eq!(normalize_tool_path("C:/repo/a.rs", true), "C:\\repo\\a.rs");
+    assert_eq!(normalize_tool_path("/usr/lib", true), "\\usr\\lib");
+    assert_eq!(normalize_tool_path("/c/Users", false), "/c/Users");
+}
+
+#[test]
+fn should_journal_writes_and_edits_for_undo_and_revert() {
+    let temp = TempDir::new("coding-agent-journal-test");
+    let journal = SharedChangeJournal::default();
+    let write = WriteTool::new(&temp.path).with_journal(journal.clone());
+    let edit = EditTool::new(&temp.path).with_journal(journal.clone());
+    let notes = temp.join("notes.txt");
+    let created = temp.join("new.txt");
+    fs::write(&notes, "one\ntwo\n").expect("write file");
+
+    edit.execute(
+        "call-1",
+        EditToolArgs {
+            path: "notes.txt".to_string(),
+            old_text: "two".to_string(),
+            new_text: "TWO".to_string(),
+        },
+    )
+    .expect("edit");
+    write
+        .execute(
+            "call-2",
+            WriteToolArgs {
+                path: "new.txt".to_string(),
+                content: "fresh".to_string(),
+            },
+        )
+        .expect("write");
+    write
+        .execute(
+            "call-3",
+            WriteToolArgs {
+                path: "notes.txt".to_string(),
+                content: "replaced\n".to_string(),
+            },
+        )
+        .expect("write");
+
+    let changes = journal.borrow().changes().to_vec();
+    assert_eq!(changes.len(), 3);
+    assert_eq!(changes[0].tool_name, "edit");
+    assert_eq!(changes[0].original.as_deref(), Some("one\ntwo\n"));
+    assert!(changes[0].diff.contains("TWO"));
+    assert_eq!(changes[1].original, None);
+
+    let undone = journal.borrow_mut().undo_last().expect("undo");
+    assert_eq!(undone.tool_call_id, "call-3");
+    assert_eq!(fs::read_to_string(&notes).unwrap(), "one\nTWO\n");
+
+    let reverted = journal.borrow_mut().revert_file(&notes).expect("revert");
+    assert_eq!(reverted.len(), 1);
+    assert_eq!(fs::read_to_string(&notes).unwrap(), "one\ntwo\n");
+
+    journal.borrow_mut().undo_last().expect("undo create");
+    assert!(!created.exists());
+    assert!(journal.borrow_mut().undo_last().is_err());
+    assert!(journal.borrow_mut().revert_file(&notes).is_err());
+}
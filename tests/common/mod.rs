@@ -0,0 +1,49 @@
+//! Helpers shared by the integration tests that talk to a fake provider over HTTP.
+
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::thread;
+
+/// Accept one request on a local port and write `response` back. Returns the server's
+/// `http://127.0.0.1:<port>` address and a handle yielding the request line, headers
+/// (lowercased names) and JSON body.
+pub fn serve_once(
+    response: impl Into<Vec<u8>>,
+) -> (String, thread::JoinHandle<(String, Vec<String>, Value)>) {
+    let response = response.into();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://127.0.0.1:{}", listener.local_addr().unwrap().port());
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+        let mut headers = Vec::new();
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let line = line.trim_end().to_string();
+            if line.is_empty() {
+                break;
+            }
+            let (name, value) = line.split_once(':').unwrap();
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap();
+            }
+            headers.push(format!("{}:{}", name.to_lowercase(), value.trim()));
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).unwrap();
+
+        let mut stream = stream;
+        stream.write_all(&response).unwrap();
+        (
+            request_line.trim_end().to_string(),
+            headers,
+            serde_json::from_slice(&body).unwrap(),
+        )
+    });
+    (base_url, server)
+}
@@ -1,8 +1,11 @@
+use pi::core::session_manager::SessionManager;
 use pi::{
-    build_session_context, calculate_context_tokens, find_cut_point, get_last_assistant_usage,
-    load_entries_from_file, migrate_session_entries, should_compact, AgentMessage,
-    AssistantMessage, CompactionSettings, ContentBlock, Cost, SessionEntry, SessionMessageEntry,
-    Usage, UserContent, UserMessage, DEFAULT_COMPACTION_SETTINGS,
+    build_session_context, calculate_context_tokens, collect_file_state, compaction_trigger_tokens,
+    estimate_context_tokens, find_cut_point, format_file_state, get_last_assistant_usage,
+    load_entries_from_file, migrate_session_entries, prepare_compaction, should_compact,
+    AgentMessage, AssistantMessage, CompactionSettings, ContentBlock, Cost, FileOperations,
+    SessionEntry, SessionMessageEntry, Usage, UserContent, UserMessage,
+    DEFAULT_COMPACTION_SETTINGS,
 };
 use std::path::PathBuf;
 
@@ -153,12 +156,28 @@ fn last_assistant_usage_none_when_missing() {
     assert!(get_last_assistant_usage(&entries).is_none());
 }
 
+#[test]
+fn estimate_context_tokens_adds_messages_after_last_usage() {
+    let messages = vec![
+        create_user_message("Hello"),
+        create_assistant_message("Hi", create_mock_usage(100, 50, 0, 0), "stop"),
+        create_user_message(&"x".repeat(40)),
+        create_assistant_message("Oops", create_mock_usage(900, 0, 0, 0), "error"),
+    ];
+    // 150 from the usage, 10 for the user message and 1 for the errored reply.
+    assert_eq!(estimate_context_tokens(&messages), 161);
+
+    let without_usage = vec![create_user_message(&"y".repeat(400))];
+    assert_eq!(estimate_context_tokens(&without_usage), 100);
+}
+
 #[test]
 fn should_compact_honors_settings() {
     let settings = CompactionSettings {
         enabled: true,
         reserve_tokens: 10_000,
         keep_recent_tokens: 20_000,
+        threshold_percent: None,
     };
 
     assert!(should_compact(95_000, 100_000, settings));
@@ -171,11 +190,116 @@ fn should_compact_disabled() {
         enabled: false,
         reserve_tokens: 10_000,
         keep_recent_tokens: 20_000,
+        threshold_percent: None,
     };
 
     assert!(!should_compact(95_000, 100_000, settings));
 }
 
+#[test]
+fn should_compact_uses_threshold_percent_of_window() {
+    let settings = CompactionSettings {
+        enabled: true,
+        reserve_tokens: 10_000,
+        keep_recent_tokens: 20_000,
+        threshold_percent: Some(75.0),
+    };
+
+    assert_eq!(compaction_trigger_tokens(200_000, settings), 150_000);
+    assert!(should_compact(151_000, 200_000, settings));
+    assert!(!should_compact(149_000, 200_000, settings));
+    assert_eq!(
+        compaction_trigger_tokens(200_000, DEFAULT_COMPACTION_SETTINGS),
+        200_000 - DEFAULT_COMPACTION_SETTINGS.reserve_tokens
+    );
+}
+
+#[test]
+fn pinned_messages_survive_compaction() {
+    let mut session = SessionManager::in_memory();
+    session.append_message(create_user_message("Always use the staging database"));
+    let pinned_id = session.append_message(create_assistant_message(
+        "Noted: staging only.",
+        create_mock_usage(100, 50, 0, 0),
+        "stop",
+    ));
+    session.append_message(create_user_message("Old question"));
+    session.append_message(create_assistant_message(
+        "Old answer",
+        create_mock_usage(200, 50, 0, 0),
+        "stop",
+    ));
+    session.append_message(create_user_message("Recent question"));
+    let kept_id = session.append_message(create_assistant_message(
+        "Recent answer",
+        create_mock_usage(300, 50, 0, 0),
+        "stop",
+    ));
+    assert!(session.append_pin("missing", true).is_err());
+    session.append_pin(&pinned_id, true).unwrap();
+    assert_eq!(session.get_pinned_entry_ids(), vec![pinned_id.clone()]);
+
+    let settings = CompactionSettings {
+        keep_recent_tokens: 1,
+        ..DEFAULT_COMPACTION_SETTINGS
+    };
+    let preparation = prepare_compaction(&session.get_branch(None), settings).unwrap();
+    assert_eq!(preparation.pinned_entry_ids, vec![pinned_id.clone()]);
+    assert!(!preparation
+        .messages_to_summarize
+        .iter()
+        .any(|message| matches!(
+            message,
+            AgentMessage::Assistant(assistant) if matches!(
+                &assistant.content[0],
+                ContentBlock::Text { text, .. } if text.contains("staging only")
+            )
+        )));
+
+    session.append_compaction("Summary of earlier work", &kept_id, 350);
+    let messages = session.build_session_context().messages;
+    assert_eq!(messages.len(), 3);
+    assert!(matches!(&messages[0], AgentMessage::CompactionSummary(_)));
+    match &messages[1] {
+        AgentMessage::Assistant(assistant) => assert!(matches!(
+            &assistant.content[0],
+            ContentBlock::Text { text, .. } if text == "Noted: staging only."
+        )),
+        other => panic!("expected pinned assistant message, got {other:?}"),
+    }
+
+    session.append_pin(&pinned_id, false).unwrap();
+    assert!(session.get_pinned_entry_ids().is_empty());
+    assert_eq!(session.build_session_context().messages.len(), 2);
+}
+
+#[test]
+fn file_state_records_latest_content_hashes() {
+    let dir = std::env::temp_dir().join(format!("pi-file-state-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("main.rs"), "fn main() {}").unwrap();
+    std::fs::write(dir.join("lib.rs"), "pub fn lib() {}").unwrap();
+
+    let mut file_ops = FileOperations::new();
+    file_ops.read.insert("lib.rs".to_string());
+    file_ops.read.insert("gone.rs".to_string());
+    file_ops.edited.insert("main.rs".to_string());
+    let details = collect_file_state(&file_ops, &dir);
+    assert_eq!(details.read_files, vec!["gone.rs", "lib.rs"]);
+    assert_eq!(details.modified_files, vec!["main.rs"]);
+    assert_eq!(
+        details.file_hashes["main.rs"],
+        "ef32637cb9c3ec2e3968c9cbdf26a5e9c172be94f88af533e14bd43f892d5297"
+    );
+    assert!(!details.file_hashes.contains_key("gone.rs"));
+
+    let section = format_file_state(&details);
+    assert!(section.contains("<file-state>\nmain.rs (modified, sha256:ef32637cb9c3ec2e)"));
+    assert!(section.contains("gone.rs (read, missing)"));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
 #[test]
 fn find_cut_point_returns_message() {
     let mut builder = EntryBuilder::new();
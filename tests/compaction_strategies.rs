@@ -0,0 +1,125 @@
+use pi::{
+    compaction_strategy_for_name, AgentMessage, CompactionPreparation, CompactionRequest,
+    CompactionResult, CompactionStrategy, ContentBlock, DelegatedStrategy, FileOperations,
+    HybridStrategy, SummarizeStrategy, ToolResultMessage, TruncateStrategy, UserContent,
+    UserMessage, COMPACTION_STRATEGY_NAMES, DEFAULT_COMPACTION_SETTINGS,
+};
+
+fn create_user_message(text: &str) -> AgentMessage {
+    AgentMessage::User(UserMessage {
+        content: UserContent::Text(text.to_string()),
+        timestamp: 1,
+    })
+}
+
+fn create_tool_result(text: &str) -> AgentMessage {
+    AgentMessage::ToolResult(ToolResultMessage {
+        tool_call_id: "call-1".to_string(),
+        tool_name: "read".to_string(),
+        content: vec![ContentBlock::Text {
+            text: text.to_string(),
+            text_signature: None,
+        }],
+        details: None,
+        is_error: false,
+        timestamp: 1,
+    })
+}
+
+fn create_preparation(previous_summary: Option<&str>) -> CompactionPreparation {
+    let mut file_ops = FileOperations::new();
+    file_ops.read.insert("src/lib.rs".to_string());
+    file_ops.edited.insert("src/main.rs".to_string());
+    CompactionPreparation {
+        first_kept_entry_id: "entry-3".to_string(),
+        messages_to_summarize: vec![
+            create_user_message("Refactor the parser"),
+            create_tool_result("fn parse_tokens() {}"),
+        ],
+        turn_prefix_messages: Vec::new(),
+        is_split_turn: false,
+        tokens_before: 1200,
+        previous_summary: previous_summary.map(str::to_string),
+        file_ops,
+        settings: DEFAULT_COMPACTION_SETTINGS,
+        pinned_entry_ids: Vec::new(),
+    }
+}
+
+fn request(preparation: &CompactionPreparation) -> CompactionRequest<'_> {
+    CompactionRequest {
+        preparation,
+        branch_entries: &[],
+        custom_instructions: None,
+    }
+}
+
+#[test]
+fn builtin_strategies_resolve_by_name() {
+    for name in COMPACTION_STRATEGY_NAMES {
+        let strategy = compaction_strategy_for_name(name).expect("builtin strategy");
+        assert_eq!(strategy.name(), name);
+    }
+    assert!(compaction_strategy_for_name("unknown").is_none());
+}
+
+#[test]
+fn summarize_strategy_keeps_cut_point() {
+    let preparation = create_preparation(None);
+    let result = SummarizeStrategy.compact(&request(&preparation)).unwrap();
+    assert!(result.summary.contains("Refactor the parser"));
+    assert_eq!(result.first_kept_entry_id, "entry-3");
+    assert_eq!(result.tokens_before, 1200);
+}
+
+#[test]
+fn truncate_strategy_carries_previous_summary() {
+    let preparation = create_preparation(Some("Earlier work"));
+    let result = TruncateStrategy.compact(&request(&preparation)).unwrap();
+    assert_eq!(
+        result.summary,
+        "Earlier work\n\n[2 earlier messages truncated]"
+    );
+}
+
+#[test]
+fn hybrid_strategy_drops_tool_output_and_lists_files() {
+    let preparation = create_preparation(None);
+    let result = HybridStrategy.compact(&request(&preparation)).unwrap();
+    assert!(result.summary.contains("Refactor the parser"));
+    assert!(!result.summary.contains("parse_tokens"));
+    assert!(result
+        .summary
+        .contains("<read-files>\nsrc/lib.rs\n</read-files>"));
+    assert!(result
+        .summary
+        .contains("<modified-files>\nsrc/main.rs\n</modified-files>"));
+}
+
+#[test]
+fn delegated_strategy_falls_back_when_delegate_declines() {
+    let preparation = create_preparation(None);
+
+    let declining = DelegatedStrategy::new(
+        "extension",
+        Box::new(|_request| None),
+        Box::new(TruncateStrategy),
+    );
+    let result = declining.compact(&request(&preparation)).unwrap();
+    assert_eq!(declining.name(), "extension");
+    assert_eq!(result.summary, "[2 earlier messages truncated]");
+
+    let accepting = DelegatedStrategy::new(
+        "extension",
+        Box::new(|request| {
+            Some(CompactionResult {
+                summary: "From extension".to_string(),
+                first_kept_entry_id: request.preparation.first_kept_entry_id.clone(),
+                tokens_before: request.preparation.tokens_before,
+            })
+        }),
+        Box::new(TruncateStrategy),
+    );
+    let result = accepting.compact(&request(&preparation)).unwrap();
+    assert_eq!(result.summary, "From extension");
+}
@@ -0,0 +1,47 @@
+use pi::core::diff::{count_changes, unified_diff};
+use pi::core::messages::ContentBlock;
+use serde_json::json;
+
+#[test]
+fn unified_diff_emits_hunks_with_context() {
+    let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+    let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\n";
+    assert_eq!(
+        unified_diff(old, new, 1),
+        "@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n@@ -10,1 +10,2 @@\n j\n+k"
+    );
+    assert_eq!(unified_diff("same\n", "same\n", 3), "");
+    assert_eq!(unified_diff("", "x\n", 3), "@@ -0,0 +1,1 @@\n+x");
+}
+
+#[test]
+fn count_changes_skips_file_headers_only_before_hunks() {
+    assert_eq!(
+        count_changes("--- a\n+++ b\n@@ -1,2 +1,2 @@\n-x\n--- y\n+z"),
+        (1, 2)
+    );
+}
+
+#[test]
+fn diff_block_round_trips_and_renders_as_text() {
+    let block = ContentBlock::diff("src/lib.rs", unified_diff("a\nb\n", "a\nc\n", 3));
+    let value = serde_json::to_value(&block).unwrap();
+    assert_eq!(
+        value,
+        json!({
+            "type": "diff",
+            "path": "src/lib.rs",
+            "diff": "@@ -1,2 +1,2 @@\n a\n-b\n+c",
+            "added": 1,
+            "removed": 1,
+        })
+    );
+    assert_eq!(
+        serde_json::from_value::<ContentBlock>(value).unwrap(),
+        block
+    );
+    assert_eq!(
+        block.as_text().unwrap(),
+        "--- src/lib.rs\n+++ src/lib.rs\n@@ -1,2 +1,2 @@\n a\n-b\n+c"
+    );
+}
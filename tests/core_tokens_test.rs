@@ -0,0 +1,106 @@
+use pi::agent::get_model;
+use pi::core::tokens::{estimate, estimate_message, Tokenizer};
+use pi::{
+    AgentMessage, AssistantMessage, ContentBlock, ToolResultMessage, Usage, UserContent,
+    UserMessage,
+};
+
+fn user(text: &str) -> AgentMessage {
+    AgentMessage::User(UserMessage {
+        content: UserContent::Text(text.to_string()),
+        timestamp: 0,
+    })
+}
+
+fn assistant(text: &str, total_tokens: i64) -> AgentMessage {
+    AgentMessage::Assistant(AssistantMessage {
+        content: vec![ContentBlock::Text {
+            text: text.to_string(),
+            text_signature: None,
+        }],
+        api: "openai-responses".to_string(),
+        provider: "openai".to_string(),
+        model: "gpt-4o".to_string(),
+        usage: Usage {
+            input: total_tokens,
+            output: 0,
+            cache_read: 0,
+            cache_write: 0,
+            total_tokens: Some(total_tokens),
+            cost: None,
+        },
+        stop_reason: "stop".to_string(),
+        error_message: None,
+        timestamp: 0,
+    })
+}
+
+#[test]
+fn picks_a_tokenizer_per_model() {
+    let tokenizer = |provider: &str, id: &str| Tokenizer::for_model(&get_model(provider, id));
+    assert_eq!(tokenizer("openai", "gpt-4o-mini"), Tokenizer::O200k);
+    assert_eq!(tokenizer("openai", "o3-mini"), Tokenizer::O200k);
+    assert_eq!(tokenizer("openrouter", "gpt-5"), Tokenizer::O200k);
+    assert_eq!(tokenizer("openai", "gpt-4-turbo"), Tokenizer::Cl100k);
+    assert_eq!(
+        tokenizer("anthropic", "claude-sonnet-4-5"),
+        Tokenizer::Claude
+    );
+    assert_eq!(
+        tokenizer("bedrock", "anthropic.claude-3-haiku"),
+        Tokenizer::Claude
+    );
+    assert_eq!(tokenizer("google", "gemini-2.5-pro"), Tokenizer::Heuristic);
+}
+
+#[test]
+fn counts_text_like_the_provider_tokenizers() {
+    // Matches tiktoken: "Hello" "," " world" "!".
+    assert_eq!(Tokenizer::Cl100k.count("Hello, world!"), 4);
+    assert_eq!(
+        Tokenizer::O200k.count("The quick brown fox jumps over the lazy dog."),
+        10
+    );
+    assert_eq!(Tokenizer::Cl100k.count("don't stop"), 3);
+    // Long words split; the larger vocabulary splits them less.
+    assert_eq!(Tokenizer::Cl100k.count("internationalization"), 5);
+    assert_eq!(Tokenizer::O200k.count("internationalization"), 4);
+    assert_eq!(Tokenizer::Cl100k.count("你好世界"), 4);
+    assert_eq!(Tokenizer::O200k.count("你好世界"), 3);
+    assert_eq!(Tokenizer::Claude.count(&"x".repeat(70)), 20);
+    assert_eq!(Tokenizer::Heuristic.count(&"x".repeat(70)), 18);
+    assert_eq!(Tokenizer::O200k.count(""), 0);
+}
+
+#[test]
+fn estimates_messages_after_the_last_reported_usage() {
+    let model = get_model("openai", "gpt-4o");
+    let messages = vec![
+        user("Summarize the file."),
+        assistant("Reading it.", 1_500),
+        AgentMessage::ToolResult(ToolResultMessage {
+            tool_call_id: "call-1".to_string(),
+            tool_name: "read".to_string(),
+            content: vec![ContentBlock::Text {
+                text: "Hello, world!".to_string(),
+                text_signature: None,
+            }],
+            details: None,
+            is_error: false,
+            timestamp: 0,
+        }),
+        user("don't stop"),
+    ];
+    let usage = estimate(&messages, &model);
+    assert_eq!(usage.tokenizer, Tokenizer::O200k);
+    assert_eq!(usage.reported, 1_500);
+    assert_eq!(usage.estimated, 7);
+    assert_eq!(usage.total(), 1_507);
+
+    let without_usage = estimate(&messages[..1], &model);
+    assert_eq!(without_usage.reported, 0);
+    assert_eq!(
+        without_usage.estimated,
+        estimate_message(&messages[0], Tokenizer::O200k)
+    );
+}
@@ -0,0 +1,170 @@
+mod common;
+
+use common::serve_once;
+use pi::agent::{Agent, AgentError, AgentLoopError, AgentOptions};
+use pi::api::{call_openai, OpenAICallOptions};
+use pi::coding_agent::AgentSessionError;
+use pi::{AssistantMessage, Error, Usage};
+use std::time::Duration;
+
+fn call(base_url: &str) -> Result<pi::api::OpenAIResponse, Error> {
+    call_openai(
+        Vec::new(),
+        OpenAICallOptions {
+            model: "gpt-test",
+            api_key: "secret",
+            tools: &[],
+            base_url,
+            extra_headers: None,
+            seed: None,
+            request_hook: None,
+        },
+    )
+}
+
+#[test]
+fn provider_replies_are_classified_by_status() {
+    let (base_url, server) = serve_once(concat!(
+        "HTTP/1.1 429 Too Many Requests\r\ncontent-type: application/json\r\nretry-after: 12\r\n",
+        "content-length: 46\r\nconnection: close\r\n\r\n",
+        r#"{"error":{"message":"Rate limit reached 123"}}"#,
+    ));
+    let err = call(&format!("{base_url}/v1")).unwrap_err();
+    server.join().unwrap();
+    assert_eq!(err.to_string(), "OpenAI error: Rate limit reached 123");
+    assert!(matches!(err, Error::RateLimited { .. }));
+    assert_eq!(err.retry_after(), Some(Duration::from_secs(12)));
+    assert_eq!(err.status(), Some(429));
+    assert!(err.is_retryable());
+
+    let (base_url, server) = serve_once(concat!(
+        "HTTP/1.1 401 Unauthorized\r\ncontent-type: application/json\r\n",
+        "content-length: 42\r\nconnection: close\r\n\r\n",
+        r#"{"error":{"message":"Incorrect API key."}}"#,
+    ));
+    let err = call(&format!("{base_url}/v1")).unwrap_err();
+    server.join().unwrap();
+    assert!(matches!(err, Error::Auth { .. }), "{err:?}");
+    assert!(!err.is_retryable());
+
+    let err = call("http://127.0.0.1:9/v1").unwrap_err();
+    assert!(err.to_string().starts_with("Request failed:"), "{err}");
+    assert!(matches!(err, Error::Provider { status: None, .. }));
+    assert!(err.is_retryable());
+}
+
+#[test]
+fn the_agent_keeps_the_typed_error_behind_a_failed_reply() {
+    let agent = Agent::new(AgentOptions {
+        stream_fn: Some(Box::new(|_model, _context, events| {
+            events.set_error(Error::RateLimited {
+                retry_after: Some(Duration::from_secs(3)),
+                message: "Anthropic error: Rate limited".to_string(),
+            });
+            failed_reply("Anthropic error: Rate limited")
+        })),
+        ..AgentOptions::default()
+    });
+    agent.prompt("Hi").unwrap();
+    assert_eq!(
+        agent.state().error.as_deref(),
+        Some("Anthropic error: Rate limited")
+    );
+    let err = agent.take_error().expect("typed error");
+    assert!(matches!(err, Error::RateLimited { .. }), "{err:?}");
+    assert_eq!(err.retry_after(), Some(Duration::from_secs(3)));
+    assert!(agent.take_error().is_none());
+
+    // A stream function that only reports text still gets a provider error.
+    let agent = Agent::new(AgentOptions {
+        stream_fn: Some(Box::new(|_model, _context, _events| {
+            failed_reply("Anthropic error: 401 unauthorized")
+        })),
+        ..AgentOptions::default()
+    });
+    agent.prompt("Hi").unwrap();
+    assert!(matches!(
+        agent.take_error(),
+        Some(Error::Provider { status: None, message }) if message == "Anthropic error: 401 unauthorized"
+    ));
+}
+
+#[test]
+fn an_aborted_run_is_an_aborted_error() {
+    let agent = Agent::new(AgentOptions::default());
+    let abort = agent.abort_flag();
+    agent.wrap_stream_fn(|_| {
+        Box::new(move |_model, _context, events| {
+            abort.cancel();
+            events.set_error(Error::Provider {
+                status: None,
+                message: "Stream read failed".to_string(),
+            });
+            failed_reply("Stream read failed")
+        })
+    });
+    agent.prompt("Hi").unwrap();
+    assert!(matches!(agent.take_error(), Some(Error::Aborted(_))));
+}
+
+#[test]
+fn maps_session_errors_one_to_one() {
+    // Each kind has a variant of the same name and payload.
+    let cases = [
+        AgentSessionError::AlreadyStreaming,
+        AgentSessionError::Agent(AgentError::NoMessages),
+        AgentSessionError::InvalidBranchEntry,
+        AgentSessionError::InvalidTreeTarget,
+        AgentSessionError::Compaction("x".to_string()),
+        AgentSessionError::Session("x".to_string()),
+        AgentSessionError::BudgetExceeded("x".to_string()),
+    ];
+    for session_err in cases {
+        let (debug, message) = (format!("{session_err:?}"), session_err.to_string());
+        let err = Error::from(session_err);
+        assert_eq!(format!("{err:?}"), debug);
+        assert_eq!(err.to_string(), message);
+    }
+
+    let err: Error = serde_json::from_str::<serde_json::Value>("{")
+        .unwrap_err()
+        .into();
+    assert!(matches!(err, Error::Parse(_)));
+}
+
+#[test]
+fn keeps_agent_and_budget_failures_typed() {
+    let err: Error = AgentSessionError::Agent(AgentError::AlreadyStreaming).into();
+    assert!(matches!(err, Error::Agent(AgentError::AlreadyStreaming)));
+
+    let err: Error = AgentError::Loop(AgentLoopError::EmptyContext).into();
+    assert!(matches!(
+        err,
+        Error::Agent(AgentError::Loop(AgentLoopError::EmptyContext))
+    ));
+    assert_eq!(err.to_string(), "Cannot continue: no messages in context");
+
+    let err: Error = AgentSessionError::BudgetExceeded("Cost budget exceeded".to_string()).into();
+    assert!(matches!(err, Error::BudgetExceeded(_)));
+    assert!(!err.is_retryable());
+}
+
+fn failed_reply(message: &str) -> AssistantMessage {
+    AssistantMessage {
+        content: Vec::new(),
+        api: "anthropic-messages".to_string(),
+        provider: "anthropic".to_string(),
+        model: "mock".to_string(),
+        usage: Usage {
+            input: 0,
+            output: 0,
+            cache_read: 0,
+            cache_write: 0,
+            total_tokens: None,
+            cost: None,
+        },
+        stop_reason: "error".to_string(),
+        error_message: Some(message.to_string()),
+        timestamp: 0,
+    }
+}
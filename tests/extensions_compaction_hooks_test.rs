@@ -89,6 +89,7 @@ fn create_session() -> AgentSession {
             enabled: Some(true),
             reserve_tokens: None,
             keep_recent_tokens: Some(1),
+            threshold: None,
         }),
     });
     let mut auth_storage = AuthStorage::new(PathBuf::from("auth.json"));
@@ -0,0 +1,391 @@
+use pi::api::request_hook::ProviderRequest;
+use pi::cli::extensions_command::format_extension_permissions;
+use pi::coding_agent::{
+    declared_capabilities, ExtensionHost, ExtensionPermissions, ExtensionUiResponse,
+};
+use pi::core::messages::ContentBlock;
+use serde_json::json;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    fn new(prefix: &str) -> Self {
+        let mut path = std::env::temp_dir();
+        path.push(format!("{}-{}", prefix, uuid::Uuid::new_v4()));
+        fs::create_dir_all(&path).expect("create temp dir");
+        Self { path }
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+fn text(content: &[ContentBlock]) -> String {
+    match content.first() {
+        Some(ContentBlock::Text { text, .. }) => text.clone(),
+        other => panic!("expected text content, got {other:?}"),
+    }
+}
+
+#[test]
+fn records_decisions_once_and_persists_them() {
+    let temp = TempDir::new("pi-extension-permissions");
+    let store = temp.path().join("extension-permissions.json");
+    let requested = strings(&["fs.read", "network", "ui"]);
+
+    let mut permissions = ExtensionPermissions::load(&store);
+    assert_eq!(permissions.undecided("/ext.js", &requested), requested);
+    permissions.record(
+        "/ext.js",
+        &requested,
+        &requested,
+        &strings(&["fs.read", "ui"]),
+    );
+    permissions.save().expect("save permissions");
+
+    let mut permissions = ExtensionPermissions::load(&store);
+    assert!(permissions.undecided("/ext.js", &requested).is_empty());
+    assert_eq!(
+        permissions.granted("/ext.js", &requested),
+        strings(&["fs.read", "ui"])
+    );
+    assert_eq!(
+        permissions.undecided("/ext.js", &strings(&["network", "flags"])),
+        strings(&["flags"])
+    );
+
+    let lines = format_extension_permissions(&[PathBuf::from("/other.js")], &permissions);
+    assert_eq!(
+        lines,
+        [
+            "/other.js",
+            "  not approved yet",
+            "/ext.js (not found)",
+            "  granted: fs.read, ui",
+            "  denied: network",
+        ]
+    );
+
+    assert!(permissions.reset("/ext.js"));
+    assert!(!permissions.reset("/ext.js"));
+}
+
+#[test]
+fn extensions_only_get_the_capabilities_the_user_approved() {
+    let temp = TempDir::new("pi-extension-capabilities");
+    let secret = temp.path().join("secret.txt");
+    fs::write(&secret, "hunter2").expect("write secret");
+    let ext = temp.path().join("reader.js");
+    fs::write(
+        &ext,
+        r#"
+        const fs = require("node:fs");
+        module.exports = function(pi) {
+            pi.registerTool({
+                name: "read_secret",
+                description: "Reads a file",
+                parameters: { type: "object", properties: { path: { type: "string" } } },
+                async execute(_callId, params, _unused, ctx) {
+                    const answer = await ctx.ui.input("Why?", "");
+                    return `${answer}:${fs.readFileSync(params.path, "utf8")}`;
+                },
+            });
+        };
+        module.exports.capabilities = ["fs.read", "ui"];
+        "#,
+    )
+    .expect("write extension");
+
+    let store = temp.path().join("extension-permissions.json");
+    let mut permissions = ExtensionPermissions::load(&store);
+    let mut asked = Vec::new();
+    let (mut host, manifest) = ExtensionHost::spawn_with_permissions(
+        std::slice::from_ref(&ext),
+        temp.path(),
+        &mut permissions,
+        &mut |_path: &str, capabilities: &[String]| {
+            asked.push(capabilities.to_vec());
+            Some(strings(&["ui"]))
+        },
+    )
+    .expect("spawn extension host");
+    assert_eq!(asked, [strings(&["fs.read", "ui"])]);
+    // Node before 25 cannot restrict network access, so every extension has it there.
+    let granted = &manifest.extensions[0].capabilities;
+    assert!(granted.contains(&"ui".to_string()), "{granted:?}");
+    assert!(!granted.contains(&"fs.read".to_string()), "{granted:?}");
+    host.set_ui_handler(|_| ExtensionUiResponse {
+        value: Some("audit".to_string()),
+        ..Default::default()
+    });
+    let input = json!({ "path": secret.to_string_lossy() });
+    let denied = host.call_tool("read_secret", "call-1", &input, &[]);
+    let message = match denied {
+        Ok(result) => text(&result.content),
+        Err(err) => err,
+    };
+    assert!(
+        message.contains("was not granted the \"fs.read\" capability"),
+        "{message}"
+    );
+    drop(host);
+
+    // The decision was saved, so the next load does not ask again.
+    let mut permissions = ExtensionPermissions::load(&store);
+    permissions.record(
+        &manifest.extensions[0].path,
+        &strings(&["fs.read", "ui"]),
+        &strings(&["fs.read"]),
+        &strings(&["fs.read"]),
+    );
+    let (mut host, _manifest) = ExtensionHost::spawn_with_permissions(
+        &[ext],
+        temp.path(),
+        &mut permissions,
+        &mut |_: &str, _: &[String]| panic!("nothing left to approve"),
+    )
+    .expect("spawn extension host");
+    host.set_ui_handler(|_| ExtensionUiResponse {
+        value: Some("audit".to_string()),
+        ..Default::default()
+    });
+    let result = host
+        .call_tool("read_secret", "call-2", &input, &[])
+        .expect("call extension tool");
+    assert_eq!(text(&result.content), "audit:hunter2");
+}
+
+#[test]
+fn undeclared_extensions_without_approval_get_no_ui() {
+    let temp = TempDir::new("pi-extension-legacy");
+    let ext = temp.path().join("legacy.js");
+    fs::write(
+        &ext,
+        r#"
+        module.exports = function(pi) {
+            pi.registerTool({
+                name: "ask",
+                description: "Asks",
+                parameters: { type: "object", properties: {} },
+                async execute(_callId, _params, _unused, ctx) {
+                    const answer = await ctx.ui.input("Name?", "");
+                    return `${ctx.hasUI}:${answer}`;
+                },
+            });
+        };
+        "#,
+    )
+    .expect("write extension");
+
+    let mut permissions = ExtensionPermissions::load(temp.path().join("permissions.json"));
+    let mut asked = Vec::new();
+    let (mut host, manifest) = ExtensionHost::spawn_with_permissions(
+        &[ext],
+        temp.path(),
+        &mut permissions,
+        &mut |_: &str, capabilities: &[String]| {
+            asked.push(capabilities.len());
+            None
+        },
+    )
+    .expect("spawn extension host");
+    // No manifest means every capability is requested; declining to answer grants none.
+    assert!(asked == [6] || asked == [5], "{asked:?}");
+    assert!(manifest.extensions[0]
+        .capabilities
+        .iter()
+        .all(|capability| capability == "network"));
+    assert!(!temp.path().join("permissions.json").exists());
+    host.set_ui_handler(|_| panic!("ui was not granted"));
+    let result = host
+        .call_tool("ask", "call-1", &json!({}), &[])
+        .expect("call extension tool");
+    assert_eq!(text(&result.content), "false:undefined");
+}
+
+#[test]
+fn reads_declared_capabilities_without_running_the_extension() {
+    assert_eq!(
+        declared_capabilities(
+            "module.exports = (pi) => {};\nmodule.exports.capabilities = [\"fs.read\", 'ui',];"
+        ),
+        Ok(Some(strings(&["fs.read", "ui"])))
+    );
+    assert_eq!(
+        declared_capabilities("export const capabilities: string[] = [`network`] as const;"),
+        Ok(Some(strings(&["network"])))
+    );
+    assert_eq!(
+        declared_capabilities("exports.capabilities = [];"),
+        Ok(Some(Vec::new()))
+    );
+    assert_eq!(
+        declared_capabilities("module.exports = () => {};"),
+        Ok(None)
+    );
+    assert!(declared_capabilities("exports.capabilities = CAPS;").is_err());
+    assert!(declared_capabilities("exports.capabilities = [\"ui\" + x];").is_err());
+    let unknown = declared_capabilities("exports.capabilities = [\"root\"];").unwrap_err();
+    assert!(
+        unknown.starts_with("Unknown capabilities: root"),
+        "{unknown}"
+    );
+
+    // The extension's code does not run before the user decides.
+    let temp = TempDir::new("pi-extension-consent");
+    let marker = temp.path().join("ran.txt");
+    let ext = temp.path().join("eager.js");
+    fs::write(
+        &ext,
+        format!(
+            "require(\"node:fs\").writeFileSync({:?}, \"yes\");\n\
+             module.exports = function() {{}};\n\
+             module.exports.capabilities = [\"fs.write\"];\n",
+            marker.to_string_lossy()
+        ),
+    )
+    .expect("write extension");
+    let mut permissions = ExtensionPermissions::load(temp.path().join("permissions.json"));
+    let (_host, manifest) = ExtensionHost::spawn_with_permissions(
+        &[ext],
+        temp.path(),
+        &mut permissions,
+        &mut |_: &str, capabilities: &[String]| {
+            assert!(!marker.exists(), "extension ran before approval");
+            Some(capabilities.to_vec())
+        },
+    )
+    .expect("spawn extension host");
+    assert!(manifest.errors.is_empty(), "{:?}", manifest.errors);
+    assert_eq!(fs::read_to_string(&marker).expect("marker"), "yes");
+}
+
+#[test]
+fn denied_filesystem_access_holds_outside_require() {
+    let temp = TempDir::new("pi-extension-enforced");
+    // An extension can always read its own directory, so the secret lives elsewhere.
+    let elsewhere = TempDir::new("pi-extension-secret");
+    let secret = elsewhere.path().join("secret.txt");
+    fs::write(&secret, "hunter2").expect("write secret");
+    let ext = temp.path().join("sneaky.js");
+    fs::write(
+        &ext,
+        r#"
+        module.exports = function(pi) {
+            pi.registerTool({
+                name: "sneak",
+                description: "Reads a file past the require() check",
+                parameters: { type: "object", properties: { path: { type: "string" } } },
+                async execute(_callId, params) {
+                    const fs = await import("node:fs");
+                    return fs.readFileSync(params.path, "utf8");
+                },
+            });
+        };
+        module.exports.capabilities = ["ui"];
+        "#,
+    )
+    .expect("write extension");
+
+    let mut permissions = ExtensionPermissions::load(temp.path().join("permissions.json"));
+    let (mut host, _manifest) = ExtensionHost::spawn_with_permissions(
+        &[ext],
+        temp.path(),
+        &mut permissions,
+        &mut |_: &str, capabilities: &[String]| Some(capabilities.to_vec()),
+    )
+    .expect("spawn extension host");
+    let input = json!({ "path": secret.to_string_lossy() });
+    let err = match host.call_tool("sneak", "call-1", &input, &[]) {
+        Ok(result) => panic!("read went through: {}", text(&result.content)),
+        Err(err) => err,
+    };
+    assert!(
+        err.contains("Access to this API has been restricted"),
+        "{err}"
+    );
+}
+
+#[test]
+fn events_pass_through_extensions_with_different_grants_in_order() {
+    let temp = TempDir::new("pi-extension-chain");
+    let first = temp.path().join("first.js");
+    fs::write(
+        &first,
+        r#"
+        module.exports = function(pi) {
+            pi.on("provider_request", () => ({ headers: { "x-first": "1" } }));
+            pi.on("tool_call", (event) => event.toolName === "bash" ? { block: true, reason: "first" } : undefined);
+        };
+        module.exports.capabilities = ["ui"];
+        "#,
+    )
+    .expect("write extension");
+    let second = temp.path().join("second.js");
+    fs::write(
+        &second,
+        r#"
+        module.exports = function(pi) {
+            pi.on("provider_request", (event) => ({
+                headers: { "x-second": event.request.headers["x-first"] ? "saw first" : "alone" },
+            }));
+            pi.on("tool_call", () => ({ block: true, reason: "second" }));
+        };
+        module.exports.capabilities = ["fs.read"];
+        "#,
+    )
+    .expect("write extension");
+
+    let mut permissions = ExtensionPermissions::load(temp.path().join("permissions.json"));
+    let (mut host, manifest) = ExtensionHost::spawn_with_permissions(
+        &[first, second],
+        temp.path(),
+        &mut permissions,
+        &mut |_: &str, capabilities: &[String]| Some(capabilities.to_vec()),
+    )
+    .expect("spawn extension host");
+    assert_eq!(manifest.extensions.len(), 2, "{:?}", manifest.errors);
+
+    let request = ProviderRequest {
+        provider: "anthropic".to_string(),
+        api: "anthropic-messages".to_string(),
+        model: "claude".to_string(),
+        method: "POST".to_string(),
+        url: "https://example.com".to_string(),
+        headers: Default::default(),
+        body: "{}".to_string(),
+    };
+    let request = host.emit_provider_request(&request).expect("emit");
+    assert_eq!(
+        request.headers.get("x-first").map(String::as_str),
+        Some("1")
+    );
+    assert_eq!(
+        request.headers.get("x-second").map(String::as_str),
+        Some("saw first")
+    );
+
+    let blocked = host
+        .emit_tool_call("bash", "call-1", &json!({}))
+        .expect("emit");
+    assert_eq!(blocked.reason.as_deref(), Some("first"));
+    let blocked = host
+        .emit_tool_call("read", "call-2", &json!({}))
+        .expect("emit");
+    assert_eq!(blocked.reason.as_deref(), Some("second"));
+}
@@ -78,3 +78,77 @@ fn executes_extension_tool() {
     );
     assert_eq!(result.details, Some(json!({ "greeted": "Rust" })));
 }
+
+#[test]
+fn runs_manifest_declared_tools_and_streams_updates() {
+    let temp = TempDir::new("pi-extension-manifest-tool");
+    let ext = write_extension(
+        temp.path(),
+        "count.js",
+        r#"
+        module.exports = {
+            tools: [
+                {
+                    name: "count_to",
+                    description: "Counts up to n",
+                    parameters: {
+                        type: "object",
+                        properties: { n: { type: "number" } },
+                        required: ["n"],
+                    },
+                    async execute(_callId, params, onUpdate) {
+                        let text = "";
+                        for (let i = 1; i <= params.n; i++) {
+                            text += `${i}\n`;
+                            onUpdate({ content: [{ type: "text", text }], details: { at: i } });
+                        }
+                        return { content: [{ type: "text", text: "done" }] };
+                    },
+                },
+            ],
+        };
+        "#,
+    );
+    let broken = write_extension(
+        temp.path(),
+        "broken.js",
+        r#"
+        module.exports = { tools: [{ name: "no_schema", description: "x", parameters: "?" }] };
+        "#,
+    );
+
+    let (mut host, manifest) =
+        ExtensionHost::spawn(&[ext, broken], temp.path()).expect("spawn extension host");
+    let declared = &manifest.extensions[0].tools[0];
+    assert_eq!(declared.name, "count_to");
+    assert_eq!(
+        declared.parameters.as_ref().unwrap()["required"],
+        json!(["n"])
+    );
+    assert_eq!(manifest.errors.len(), 1);
+    assert!(manifest.errors[0].error.contains("no_schema"));
+
+    let mut updates = Vec::new();
+    let result = host
+        .call_tool_with_updates(
+            "count_to",
+            "call-1",
+            &json!({ "n": 3 }),
+            &[],
+            &mut |partial| {
+                updates.push(partial.details.unwrap());
+            },
+        )
+        .expect("call extension tool");
+    assert_eq!(
+        updates,
+        vec![json!({ "at": 1 }), json!({ "at": 2 }), json!({ "at": 3 })]
+    );
+    assert_eq!(
+        result.content,
+        vec![ContentBlock::Text {
+            text: "done".to_string(),
+            text_signature: None,
+        }]
+    );
+}
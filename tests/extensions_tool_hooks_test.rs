@@ -110,7 +110,8 @@ fn build_test_tool() -> AgentTool {
         name: "test_tool".to_string(),
         label: "Test Tool".to_string(),
         description: "Test tool".to_string(),
-        execute: std::rc::Rc::new(|_tool_call_id, _args| {
+        parameters: None,
+        execute: std::rc::Rc::new(|_tool_call_id, _args, _on_update| {
             Ok(AgentToolResult {
                 content: vec![ContentBlock::Text {
                     text: "RESULT".to_string(),
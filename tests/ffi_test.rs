@@ -0,0 +1,153 @@
+#![cfg(feature = "ffi")]
+
+use pi::ffi::{
+    pi_ffi_version, pi_last_error, pi_session_abort, pi_session_free, pi_session_messages,
+    pi_session_new, pi_session_prompt, pi_session_set_event_callback, pi_string_free, PiSession,
+    PI_FFI_VERSION,
+};
+use serde_json::{json, Value};
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::fs;
+use std::ptr;
+use uuid::Uuid;
+
+fn mock_config(responses: Value) -> CString {
+    let dir = std::env::temp_dir().join(format!("pi-ffi-{}", Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    let fixture = dir.join("fixture.json");
+    fs::write(&fixture, json!({ "responses": responses }).to_string()).unwrap();
+    let models_path = dir.join("models.json");
+    let models = json!({
+        "providers": {
+            "scripted": {
+                "api": "mock",
+                "baseUrl": fixture.display().to_string(),
+                "models": [{ "id": "demo" }]
+            }
+        }
+    });
+    fs::write(&models_path, models.to_string()).unwrap();
+    let config = json!({
+        "provider": "scripted",
+        "model": "demo",
+        "tools": [],
+        "systemPrompt": "Be brief.",
+        "modelsPath": models_path.display().to_string(),
+    });
+    CString::new(config.to_string()).unwrap()
+}
+
+fn take_json(text: *mut c_char) -> Value {
+    assert!(!text.is_null(), "{}", last_error());
+    let value = unsafe { serde_json::from_str(CStr::from_ptr(text).to_str().unwrap()).unwrap() };
+    unsafe { pi_string_free(text) };
+    value
+}
+
+fn last_error() -> String {
+    let error = pi_last_error();
+    if error.is_null() {
+        return String::new();
+    }
+    unsafe { CStr::from_ptr(error).to_string_lossy().into_owned() }
+}
+
+extern "C" fn collect_event(event_json: *const c_char, user_data: *mut c_void) {
+    let events = unsafe { &mut *(user_data as *mut Vec<String>) };
+    let event: Value =
+        serde_json::from_str(unsafe { CStr::from_ptr(event_json) }.to_str().unwrap()).unwrap();
+    events.push(event["type"].as_str().unwrap().to_string());
+}
+
+#[test]
+fn prompts_through_the_c_abi() {
+    assert_eq!(pi_ffi_version(), PI_FFI_VERSION);
+    let config = mock_config(json!([
+        { "content": [{ "type": "text", "text": "Hello from C." }] },
+        { "error": "overloaded_error: try again later" }
+    ]));
+    let session = unsafe { pi_session_new(config.as_ptr()) };
+    assert!(!session.is_null(), "{}", last_error());
+
+    let mut events = Vec::<String>::new();
+    let registered = unsafe {
+        pi_session_set_event_callback(
+            session,
+            Some(collect_event),
+            &mut events as *mut Vec<String> as *mut c_void,
+        )
+    };
+    assert_eq!(registered, 0);
+
+    let prompt = CString::new("Hi").unwrap();
+    let result = take_json(unsafe { pi_session_prompt(session, prompt.as_ptr()) });
+    assert_eq!(result["text"], "Hello from C.");
+    assert_eq!(result["stopReason"], "stop");
+    assert_eq!(result["messages"].as_array().unwrap().len(), 2);
+    assert_eq!(events.first().map(String::as_str), Some("agent_start"));
+    assert_eq!(events.last().map(String::as_str), Some("agent_end"));
+
+    unsafe { pi_session_set_event_callback(session, None, ptr::null_mut()) };
+    let count = events.len();
+    let failed = unsafe { pi_session_prompt(session, prompt.as_ptr()) };
+    assert!(failed.is_null());
+    assert_eq!(last_error(), "overloaded_error: try again later");
+    assert_eq!(events.len(), count);
+
+    let messages = take_json(unsafe { pi_session_messages(session) });
+    assert_eq!(messages.as_array().unwrap().len(), 4);
+    unsafe { pi_session_free(session) };
+}
+
+#[test]
+fn reports_invalid_input() {
+    let config = CString::new(r#"{"modle": "typo"}"#).unwrap();
+    assert!(unsafe { pi_session_new(config.as_ptr()) }.is_null());
+    assert!(last_error().starts_with("Invalid session config: unknown field `modle`"));
+
+    assert!(unsafe { pi_session_prompt(ptr::null_mut(), ptr::null()) }.is_null());
+    assert_eq!(last_error(), "session is null");
+}
+
+struct AbortOnTurn {
+    session: *mut PiSession,
+    aborted: c_int,
+}
+
+extern "C" fn abort_on_turn_start(event_json: *const c_char, user_data: *mut c_void) {
+    let state = unsafe { &mut *(user_data as *mut AbortOnTurn) };
+    let event: Value =
+        serde_json::from_str(unsafe { CStr::from_ptr(event_json) }.to_str().unwrap()).unwrap();
+    if event["type"] == "turn_start" {
+        state.aborted = unsafe { pi_session_abort(state.session) };
+    }
+}
+
+#[test]
+fn aborts_from_an_event_callback() {
+    let config = mock_config(json!([
+        { "content": [{ "type": "text", "text": "Never seen." }] }
+    ]));
+    let session = unsafe { pi_session_new(config.as_ptr()) };
+    assert!(!session.is_null(), "{}", last_error());
+    let mut state = AbortOnTurn {
+        session,
+        aborted: -1,
+    };
+    unsafe {
+        pi_session_set_event_callback(
+            session,
+            Some(abort_on_turn_start),
+            &mut state as *mut AbortOnTurn as *mut c_void,
+        )
+    };
+
+    let prompt = CString::new("Hi").unwrap();
+    let result = unsafe { pi_session_prompt(session, prompt.as_ptr()) };
+    assert!(result.is_null());
+    assert_eq!(state.aborted, 0);
+    assert_eq!(last_error(), "Request was aborted");
+
+    assert_eq!(unsafe { pi_session_abort(ptr::null_mut()) }, -1);
+    unsafe { pi_session_free(session) };
+}
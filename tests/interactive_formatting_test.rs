@@ -1,8 +1,12 @@
 use pi::agent::AgentMessage;
-use pi::coding_agent::interactive_mode::format_message_for_interactive;
+use pi::coding_agent::interactive_mode::{
+    format_message_for_interactive, render_image_preview, render_status_bar, StatusBarInfo,
+};
 use pi::core::messages::{
     AssistantMessage, ContentBlock, ToolResultMessage, Usage, UserContent, UserMessage,
 };
+use pi::modes::limited_terminal_reason;
+use pi::tui::visible_width;
 use serde_json::json;
 
 fn usage() -> Usage {
@@ -52,6 +56,26 @@ fn formats_assistant_with_tool_call() {
     assert!(formatted.contains("\"a\": 1"));
 }
 
+#[test]
+fn marks_aborted_assistant_messages() {
+    let mut partial = assistant_message(vec![ContentBlock::Text {
+        text: "Half an answer".to_string(),
+        text_signature: None,
+    }]);
+    partial.stop_reason = "aborted".to_string();
+    let formatted =
+        format_message_for_interactive(&AgentMessage::Assistant(partial), true, false, true)
+            .expect("expected output");
+    assert_eq!(formatted, "Assistant:\nHalf an answer\n\n[aborted]");
+
+    let mut empty = assistant_message(Vec::new());
+    empty.stop_reason = "aborted".to_string();
+    let formatted =
+        format_message_for_interactive(&AgentMessage::Assistant(empty), true, false, true)
+            .expect("expected output");
+    assert_eq!(formatted, "Assistant:\n[aborted]");
+}
+
 #[test]
 fn formats_tool_result_error_with_details() {
     let message = AgentMessage::ToolResult(ToolResultMessage {
@@ -103,3 +127,84 @@ fn hides_thinking_blocks_when_disabled() {
     assert!(!formatted.contains("Thinking:"));
     assert!(formatted.contains("Hello"));
 }
+
+#[test]
+fn formats_attached_images_as_placeholders_with_file_names() {
+    let png = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg==";
+    let message = AgentMessage::User(UserMessage {
+        content: UserContent::Blocks(vec![
+            ContentBlock::Text {
+                text: "<file name=\"notes.md\">\nhi\n</file>\n<file name=\"shot.png\"></file>\nWhat is this?".to_string(),
+                text_signature: None,
+            },
+            ContentBlock::Image {
+                data: png.to_string(),
+                mime_type: "image/png".to_string(),
+            },
+        ]),
+        timestamp: 0,
+    });
+
+    let formatted = format_message_for_interactive(&message, true, false, false).unwrap();
+    assert!(formatted.ends_with("[Image: shot.png [image/png] 1x1]"));
+    assert!(render_image_preview("[Image: shot.png [image/png] 1x1]", 80).is_none());
+}
+
+fn strip_ansi(text: &str) -> String {
+    let mut output = String::new();
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '\x1b' {
+            for next in chars.by_ref() {
+                if next.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+            continue;
+        }
+        output.push(ch);
+    }
+    output
+}
+
+#[test]
+fn status_bar_shows_model_context_cost_and_state() {
+    let mut info = StatusBarInfo {
+        provider: "anthropic".to_string(),
+        model: "claude-sonnet-4-5".to_string(),
+        thinking_level: "medium".to_string(),
+        context_tokens: 45_200,
+        context_window: Some(200_000),
+        cost: 0.1234,
+        activity: None,
+    };
+    let line = render_status_bar(&info, 100);
+    assert_eq!(visible_width(&line), 100);
+    let plain = strip_ansi(&line);
+    assert!(plain.starts_with("anthropic/claude-sonnet-4-5 · thinking medium"));
+    assert!(plain.ends_with("ctx 45.2k/200k (23%) · $0.123 · idle"));
+
+    info.activity = Some("running bash".to_string());
+    info.context_window = None;
+    let plain = strip_ansi(&render_status_bar(&info, 100));
+    assert!(plain.ends_with("ctx 45.2k · $0.123 · ● running bash"));
+
+    let narrow = render_status_bar(&info, 40);
+    assert!(visible_width(&narrow) <= 40);
+    assert!(strip_ansi(&narrow).contains("● running bash"));
+}
+
+#[test]
+fn detects_terminals_too_limited_for_the_full_screen_ui() {
+    assert_eq!(
+        limited_terminal_reason(Some("dumb"), None).as_deref(),
+        Some("TERM=dumb")
+    );
+    assert!(limited_terminal_reason(Some("xterm-256color"), Some("29.1,comint")).is_some());
+    assert_eq!(
+        limited_terminal_reason(Some("xterm-256color"), Some("vterm")),
+        None
+    );
+    assert_eq!(limited_terminal_reason(Some("xterm-256color"), None), None);
+    assert_eq!(limited_terminal_reason(None, None), None);
+}
@@ -0,0 +1,98 @@
+use pi::agent::{get_model, Agent, AgentOptions, AgentStateOverride, LlmContext, Model};
+use pi::coding_agent::{
+    AgentSession, AgentSessionConfig, AuthStorage, ModelRegistry, SettingsManager,
+};
+use pi::core::messages::{AssistantMessage, ContentBlock, Usage};
+use pi::core::session_manager::SessionManager;
+use pi::modes::run_line_loop;
+use std::cell::RefCell;
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+fn create_session(prompts: Rc<RefCell<Vec<usize>>>) -> AgentSession {
+    let stream_fn: Box<pi::agent::StreamFn> =
+        Box::new(move |_model: &Model, context: &LlmContext, _events| {
+            prompts.borrow_mut().push(context.messages.len());
+            AssistantMessage {
+                content: vec![ContentBlock::Text {
+                    text: "Hello from the model".to_string(),
+                    text_signature: None,
+                }],
+                api: "anthropic-messages".to_string(),
+                provider: "anthropic".to_string(),
+                model: "mock".to_string(),
+                usage: Usage {
+                    input: 0,
+                    output: 0,
+                    cache_read: 0,
+                    cache_write: 0,
+                    total_tokens: None,
+                    cost: None,
+                },
+                stop_reason: "stop".to_string(),
+                error_message: None,
+                timestamp: 0,
+            }
+        });
+    let agent = Agent::new(AgentOptions {
+        initial_state: Some(AgentStateOverride {
+            model: Some(get_model("anthropic", "claude-sonnet-4-5")),
+            system_prompt: Some("Test".to_string()),
+            tools: Some(Vec::new()),
+            ..Default::default()
+        }),
+        stream_fn: Some(stream_fn),
+        ..Default::default()
+    });
+    let mut auth_storage = AuthStorage::new(PathBuf::from("auth.json"));
+    auth_storage.set_runtime_api_key("anthropic", "test-key");
+
+    AgentSession::new(AgentSessionConfig {
+        agent,
+        session_manager: SessionManager::in_memory(),
+        settings_manager: SettingsManager::create("", ""),
+        model_registry: ModelRegistry::new(auth_storage, None),
+    })
+}
+
+fn run(session: &mut AgentSession, input: &str) -> String {
+    let output = Rc::new(RefCell::new(Vec::new()));
+    run_line_loop(session, Cursor::new(input.to_string()), output.clone()).unwrap();
+    let bytes = output.borrow().clone();
+    String::from_utf8(bytes).unwrap()
+}
+
+#[test]
+fn line_loop_streams_replies_and_runs_commands() {
+    let prompts = Rc::new(RefCell::new(Vec::new()));
+    let mut session = create_session(prompts.clone());
+
+    let output = run(
+        &mut session,
+        "hi there\n\n/session\n/tree\nfirst line \\\nsecond line\n/exit\nnever sent\n",
+    );
+
+    assert!(output.starts_with("pi (line mode) · anthropic/claude-sonnet-4-5"));
+    assert!(output.contains("> Hello from the model\n"));
+    assert!(output.contains("Session Info:\n"));
+    assert!(output.contains("/tree needs an interactive terminal"));
+    // The continued prompt is sent once, as two lines.
+    assert_eq!(*prompts.borrow(), vec![1, 3]);
+    let messages = session.messages();
+    assert_eq!(messages.len(), 4);
+    assert_eq!(messages[2].user_text(), Some("first line \nsecond line"));
+}
+
+#[test]
+fn line_loop_stops_at_end_of_input_and_handles_reset() {
+    let prompts = Rc::new(RefCell::new(Vec::new()));
+    let mut session = create_session(prompts.clone());
+
+    let output = run(&mut session, "question\n/reset\n/thinking nope");
+
+    assert!(output.contains("Session reset."));
+    assert!(output.contains("Unknown thinking level: nope."));
+    assert!(session.messages().is_empty());
+    assert_eq!(prompts.borrow().len(), 1);
+}
@@ -0,0 +1,166 @@
+use pi::agent::{AgentMessage, LlmContext, StreamEvents};
+use pi::api::request_log::RequestLogger;
+use pi::api::{build_anthropic_messages, stream_anthropic, AnthropicCallOptions};
+use pi::coding_agent::Model;
+use pi::{Cost, UserContent, UserMessage};
+use serde_json::Value;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::thread;
+
+const STREAM: &str = "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_tokens\":12,\"output_tokens\":1}}}\n\n\
+     event: content_block_start\ndata: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n\n\
+     event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hi\"}}\n\n\
+     event: message_delta\ndata: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"end_turn\"},\"usage\":{\"input_tokens\":12,\"output_tokens\":3}}\n\n";
+
+/// Answer each request with the next `(status line, content type, body)`.
+fn serve(replies: Vec<(&'static str, &'static str, &'static str)>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!(
+        "http://127.0.0.1:{}/v1",
+        listener.local_addr().unwrap().port()
+    );
+    thread::spawn(move || {
+        for (status, content_type, body) in replies {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut request = vec![0; content_length];
+            reader.read_exact(&mut request).unwrap();
+            let mut stream = stream;
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {status}\r\ncontent-type: {content_type}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+        }
+    });
+    base_url
+}
+
+fn test_model(base_url: &str) -> Model {
+    Model {
+        id: "test-model".to_string(),
+        name: "Test Model".to_string(),
+        api: "anthropic-messages".to_string(),
+        provider: "anthropic".to_string(),
+        base_url: base_url.to_string(),
+        reasoning: false,
+        input: vec!["text".to_string()],
+        cost: Cost {
+            input: 0.0,
+            output: 0.0,
+            cache_read: 0.0,
+            cache_write: 0.0,
+            total: 0.0,
+        },
+        context_window: 128_000,
+        max_tokens: 4096,
+        headers: None,
+    }
+}
+
+fn read_records(path: &std::path::Path) -> Vec<Value> {
+    fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[test]
+fn logs_redacted_summaries_and_full_traces() {
+    let dir = std::env::temp_dir().join(format!("pi-request-log-{}", uuid::Uuid::new_v4()));
+    let log_path = dir.join("logs/requests.jsonl");
+    let trace_path = dir.join("trace.jsonl");
+    RequestLogger {
+        log_path: Some(log_path.clone()),
+        trace_path: Some(trace_path.clone()),
+    }
+    .install()
+    .unwrap();
+    assert!(RequestLogger::default().install().is_err());
+
+    let base_url = serve(vec![
+        ("200 OK", "text/event-stream", STREAM),
+        (
+            "400 Bad Request",
+            "application/json",
+            r#"{"error":{"message":"prompt is too long"}}"#,
+        ),
+    ]);
+    let model = test_model(&base_url);
+    let context = LlmContext {
+        system_prompt: String::new(),
+        messages: vec![AgentMessage::User(UserMessage {
+            content: UserContent::Text("Say hello".to_string()),
+            timestamp: 0,
+        })],
+    };
+    let call = || {
+        let mut events = StreamEvents::new(Box::new(|_| {}));
+        stream_anthropic(
+            &model,
+            build_anthropic_messages(&context),
+            AnthropicCallOptions {
+                model: &model.id,
+                api_key: "sk-secret",
+                use_oauth: false,
+                tools: &[],
+                base_url: &base_url,
+                extra_headers: None,
+                system: Some("Be brief."),
+                request_hook: None,
+            },
+            &mut events,
+        )
+    };
+    call().unwrap();
+    assert_eq!(
+        call().unwrap_err().to_string(),
+        "Anthropic error: prompt is too long"
+    );
+
+    let records = read_records(&log_path);
+    assert_eq!(records.len(), 2);
+    let ok = &records[0];
+    assert_eq!(ok["endpoint"], format!("{base_url}/messages"));
+    assert_eq!(ok["model"], "test-model");
+    assert_eq!(ok["status"], 200);
+    assert_eq!(ok["inputTokens"], 12);
+    assert_eq!(ok["outputTokens"], 3);
+    assert_eq!(ok["stopReason"], "stop");
+    assert!(ok["error"].is_null());
+    assert!(ok.get("requestBody").is_none());
+    assert!(!fs::read_to_string(&log_path).unwrap().contains("sk-secret"));
+    let failed = &records[1];
+    assert_eq!(failed["status"], 400);
+    assert_eq!(failed["error"], "Anthropic error: prompt is too long");
+    assert!(failed["stopReason"].is_null());
+
+    let traces = read_records(&trace_path);
+    assert_eq!(traces.len(), 2);
+    assert_eq!(traces[0]["requestHeaders"]["x-api-key"], "[redacted]");
+    assert_eq!(
+        traces[0]["requestBody"]["messages"][0]["content"][0]["text"],
+        "Say hello"
+    );
+    assert_eq!(traces[0]["responseBody"], STREAM);
+    assert!(!fs::read_to_string(&trace_path)
+        .unwrap()
+        .contains("sk-secret"));
+}
@@ -0,0 +1,216 @@
+use pi::agent::{get_model, Agent, AgentMessage, AgentOptions, AgentStateOverride};
+use pi::coding_agent::{
+    AgentSession, AgentSessionConfig, AuthStorage, ModelRegistry, SettingsManager,
+};
+use pi::core::messages::{AssistantMessage, ContentBlock, Usage, UserContent};
+use pi::core::session_manager::SessionManager;
+use pi::rpc::{run_acp, RpcOutput};
+use serde_json::{json, Value};
+use std::io;
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+fn build_session(prompts: Arc<Mutex<Vec<String>>>) -> AgentSession {
+    let model = get_model("anthropic", "claude-sonnet-4-5");
+    let agent = Agent::new(AgentOptions {
+        initial_state: Some(AgentStateOverride {
+            model: Some(model),
+            system_prompt: Some("Test".to_string()),
+            tools: Some(Vec::new()),
+            ..Default::default()
+        }),
+        convert_to_llm: Some(Box::new(|messages| messages.to_vec())),
+        stream_fn: Some(Box::new(move |_model, context, _events| {
+            if let Some(AgentMessage::User(user)) = context.messages.last() {
+                if let UserContent::Blocks(blocks) = &user.content {
+                    let text = blocks
+                        .iter()
+                        .filter_map(|block| match block {
+                            ContentBlock::Text { text, .. } => Some(text.as_str()),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    prompts.lock().unwrap().push(text);
+                }
+            }
+            AssistantMessage {
+                content: vec![
+                    ContentBlock::Thinking {
+                        thinking: "Hmm.".to_string(),
+                        thinking_signature: None,
+                    },
+                    ContentBlock::Text {
+                        text: "All good.".to_string(),
+                        text_signature: None,
+                    },
+                ],
+                api: "anthropic-messages".to_string(),
+                provider: "anthropic".to_string(),
+                model: "mock".to_string(),
+                usage: Usage {
+                    input: 1,
+                    output: 1,
+                    cache_read: 0,
+                    cache_write: 0,
+                    total_tokens: Some(2),
+                    cost: None,
+                },
+                stop_reason: "stop".to_string(),
+                error_message: None,
+                timestamp: 0,
+            }
+        })),
+        ..Default::default()
+    });
+    let mut auth_storage = AuthStorage::new(PathBuf::from("auth.json"));
+    auth_storage.set_runtime_api_key("anthropic", "test-key");
+    AgentSession::new(AgentSessionConfig {
+        agent,
+        session_manager: SessionManager::in_memory(),
+        settings_manager: SettingsManager::create("", ""),
+        model_registry: ModelRegistry::new(auth_storage, None),
+    })
+}
+
+struct Client {
+    input: mpsc::Sender<io::Result<String>>,
+    output: mpsc::Receiver<String>,
+    /// Notifications received while waiting for responses.
+    updates: Vec<Value>,
+}
+
+impl Client {
+    fn start(prompts: Arc<Mutex<Vec<String>>>) -> (Self, thread::JoinHandle<Result<(), String>>) {
+        let (input, lines) = mpsc::channel();
+        let (sender, output) = mpsc::channel();
+        let sender = Mutex::new(sender);
+        let writer: RpcOutput = Arc::new(move |line: &str| {
+            let _ = sender.lock().unwrap().send(line.to_string());
+        });
+        let server = thread::spawn(move || run_acp(build_session(prompts), lines, writer, None));
+        let client = Self {
+            input,
+            output,
+            updates: Vec::new(),
+        };
+        (client, server)
+    }
+
+    fn request(&mut self, id: u64, method: &str, params: Value) -> Value {
+        let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        self.input.send(Ok(request.to_string())).unwrap();
+        loop {
+            let line = self
+                .output
+                .recv_timeout(Duration::from_secs(10))
+                .expect("response");
+            let message: Value = serde_json::from_str(&line).unwrap();
+            assert_eq!(message["jsonrpc"], "2.0", "{message}");
+            if message["id"] == id {
+                return message;
+            }
+            self.updates.push(message);
+        }
+    }
+}
+
+fn cwd() -> String {
+    std::env::current_dir()
+        .unwrap()
+        .to_string_lossy()
+        .to_string()
+}
+
+#[test]
+fn runs_prompts_and_streams_session_updates() {
+    let prompts = Arc::new(Mutex::new(Vec::new()));
+    let (mut client, server) = Client::start(prompts.clone());
+
+    let initialized = client.request(1, "initialize", json!({ "protocolVersion": 1 }));
+    assert_eq!(initialized["result"]["protocolVersion"], 1, "{initialized}");
+    assert_eq!(
+        initialized["result"]["agentCapabilities"]["promptCapabilities"]["embeddedContext"],
+        true
+    );
+
+    let created = client.request(2, "session/new", json!({ "cwd": cwd(), "mcpServers": [] }));
+    let session_id = created["result"]["sessionId"]
+        .as_str()
+        .expect("session id")
+        .to_string();
+
+    let prompted = client.request(
+        3,
+        "session/prompt",
+        json!({
+            "sessionId": session_id,
+            "prompt": [
+                { "type": "text", "text": "What does this do?" },
+                {
+                    "type": "resource",
+                    "resource": { "uri": "file:///work/lib.rs", "text": "fn main() {}" },
+                },
+            ],
+        }),
+    );
+    assert_eq!(prompted["result"]["stopReason"], "end_turn", "{prompted}");
+    assert_eq!(
+        prompts.lock().unwrap().last().unwrap(),
+        "What does this do?\n<file name=\"/work/lib.rs\">\nfn main() {}\n</file>"
+    );
+
+    let updates: Vec<_> = client
+        .updates
+        .iter()
+        .filter(|message| message["method"] == "session/update")
+        .map(|message| {
+            assert_eq!(message["params"]["sessionId"], session_id.as_str());
+            &message["params"]["update"]
+        })
+        .collect();
+    let chunks = |kind: &str| {
+        updates
+            .iter()
+            .filter(|update| update["sessionUpdate"] == kind)
+            .map(|update| update["content"]["text"].as_str().unwrap())
+            .collect::<String>()
+    };
+    assert_eq!(chunks("agent_thought_chunk"), "Hmm.");
+    assert_eq!(chunks("agent_message_chunk"), "All good.");
+
+    drop(client);
+    server.join().unwrap().expect("server exits cleanly");
+}
+
+#[test]
+fn rejects_unknown_sessions_methods_and_directories() {
+    let (mut client, server) = Client::start(Arc::default());
+
+    let prompted = client.request(
+        1,
+        "session/prompt",
+        json!({ "sessionId": "nope", "prompt": [{ "type": "text", "text": "hi" }] }),
+    );
+    assert_eq!(prompted["error"]["code"], -32600, "{prompted}");
+
+    let unknown = client.request(2, "session/set_mode", json!({}));
+    assert_eq!(unknown["error"]["code"], -32601, "{unknown}");
+
+    let elsewhere = std::env::temp_dir().join(format!("pi-acp-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&elsewhere).unwrap();
+    let created = client.request(3, "session/new", json!({ "cwd": elsewhere }));
+    assert_eq!(created["error"]["code"], -32602, "{created}");
+    std::fs::remove_dir_all(&elsewhere).ok();
+
+    // Only the session pi started with is available without a factory.
+    let created = client.request(4, "session/new", json!({ "cwd": cwd() }));
+    assert!(created["result"]["sessionId"].is_string(), "{created}");
+    let second = client.request(5, "session/new", json!({ "cwd": cwd() }));
+    assert_eq!(second["error"]["code"], -32000, "{second}");
+
+    drop(client);
+    server.join().unwrap().expect("server exits cleanly");
+}
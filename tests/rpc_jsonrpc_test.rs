@@ -0,0 +1,352 @@
+use pi::agent::{get_model, Agent, AgentOptions, AgentStateOverride};
+use pi::coding_agent::{
+    AgentSession, AgentSessionConfig, AuthStorage, ModelRegistry, SettingsManager,
+};
+use pi::core::messages::{create_hook_message, AssistantMessage, ContentBlock, Usage, UserContent};
+use pi::core::session_manager::SessionManager;
+use pi::rpc::jsonrpc::{code_action_prompt, CodeActionParams, INVALID_PARAMS, PARSE_ERROR};
+use pi::rpc::{run_jsonrpc, JsonRpcAdapter, JsonRpcInput, RpcModeOptions, RpcOutput};
+use serde_json::{json, Value};
+use std::io;
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+fn build_session(prompts: Arc<Mutex<Vec<String>>>) -> AgentSession {
+    let model = get_model("anthropic", "claude-sonnet-4-5");
+    let agent = Agent::new(AgentOptions {
+        initial_state: Some(AgentStateOverride {
+            model: Some(model),
+            system_prompt: Some("Test".to_string()),
+            tools: Some(Vec::new()),
+            ..Default::default()
+        }),
+        convert_to_llm: Some(Box::new(|messages| messages.to_vec())),
+        stream_fn: Some(Box::new(move |_model, context, _events| {
+            if let Some(pi::agent::AgentMessage::User(user)) = context.messages.last() {
+                if let UserContent::Blocks(blocks) = &user.content {
+                    for block in blocks {
+                        if let ContentBlock::Text { text, .. } = block {
+                            prompts.lock().unwrap().push(text.clone());
+                        }
+                    }
+                }
+            }
+            AssistantMessage {
+                content: vec![ContentBlock::Text {
+                    text: "ok".to_string(),
+                    text_signature: None,
+                }],
+                api: "anthropic-messages".to_string(),
+                provider: "anthropic".to_string(),
+                model: "mock".to_string(),
+                usage: Usage {
+                    input: 1,
+                    output: 1,
+                    cache_read: 0,
+                    cache_write: 0,
+                    total_tokens: Some(2),
+                    cost: None,
+                },
+                stop_reason: "stop".to_string(),
+                error_message: None,
+                timestamp: 0,
+            }
+        })),
+        ..Default::default()
+    });
+    let mut auth_storage = AuthStorage::new(PathBuf::from("auth.json"));
+    auth_storage.set_runtime_api_key("anthropic", "test-key");
+    AgentSession::new(AgentSessionConfig {
+        agent,
+        session_manager: SessionManager::in_memory(),
+        settings_manager: SettingsManager::create("", ""),
+        model_registry: ModelRegistry::new(auth_storage, None),
+    })
+}
+
+/// Waits for the response to the request with `id`, skipping notifications.
+fn response(output: &mpsc::Receiver<String>, id: Value) -> Value {
+    loop {
+        let line = output
+            .recv_timeout(Duration::from_secs(10))
+            .expect("response");
+        let value: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["jsonrpc"], "2.0", "{value}");
+        if value.get("method").is_none() && value["id"] == id {
+            return value;
+        }
+    }
+}
+
+fn command(input: JsonRpcInput) -> Value {
+    match input {
+        JsonRpcInput::Command(line) => serde_json::from_str(&line).unwrap(),
+        other => panic!("expected a command, got {other:?}"),
+    }
+}
+
+#[test]
+fn serves_requests_and_code_actions_over_jsonrpc() {
+    let prompts = Arc::new(Mutex::new(Vec::new()));
+    let recorded = prompts.clone();
+    let (input, lines) = mpsc::channel::<io::Result<String>>();
+    let (sender, output) = mpsc::channel();
+    let sender = Mutex::new(sender);
+    let writer: RpcOutput = Arc::new(move |line: &str| {
+        let _ = sender.lock().unwrap().send(line.to_string());
+    });
+    let server = thread::spawn(move || {
+        let session = build_session(recorded);
+        run_jsonrpc(session, RpcModeOptions::default(), lines, writer, None)
+    });
+    let send = |message: Value| input.send(Ok(message.to_string())).unwrap();
+
+    send(json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize" }));
+    let initialized = response(&output, json!(1));
+    assert_eq!(initialized["result"]["serverInfo"]["name"], "pi");
+    assert_eq!(
+        initialized["result"]["capabilities"]["codeActions"],
+        json!(["pi/explainSelection", "pi/fixDiagnostics"])
+    );
+
+    send(json!({ "jsonrpc": "2.0", "id": "state", "method": "get_state" }));
+    let state = response(&output, json!("state"));
+    assert_eq!(
+        state["result"]["model"]["id"], "claude-sonnet-4-5",
+        "{state}"
+    );
+
+    send(json!({ "jsonrpc": "2.0", "id": "usage", "method": "get_context_usage" }));
+    let usage = response(&output, json!("usage"));
+    assert_eq!(usage["result"]["tokenizer"], "claude", "{usage}");
+    assert_eq!(usage["result"]["tokens"], 0, "{usage}");
+    assert_eq!(usage["result"]["context_window"], 200_000, "{usage}");
+
+    send(json!({
+        "jsonrpc": "2.0",
+        "id": "checkpoint",
+        "method": "create_checkpoint",
+        "params": { "name": "start" },
+    }));
+    let checkpoint = response(&output, json!("checkpoint"));
+    assert_eq!(checkpoint["result"]["name"], "start", "{checkpoint}");
+    send(
+        json!({ "jsonrpc": "2.0", "id": "rewind", "method": "rewind", "params": { "name": "start" } }),
+    );
+    let rewound = response(&output, json!("rewind"));
+    assert_eq!(rewound["result"]["filesRestored"], true, "{rewound}");
+    send(
+        json!({ "jsonrpc": "2.0", "id": "missing", "method": "rewind", "params": { "name": "nope" } }),
+    );
+    let missing = response(&output, json!("missing"));
+    assert!(
+        missing["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("Unknown checkpoint")),
+        "{missing}"
+    );
+
+    for (id, method, message) in [("s1", "steer", "first"), ("f1", "follow_up", "second")] {
+        send(
+            json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": { "message": message } }),
+        );
+        response(&output, json!(id));
+    }
+    send(json!({ "jsonrpc": "2.0", "id": "queue", "method": "get_pending_messages" }));
+    let queue = response(&output, json!("queue"));
+    let queued = queue["result"]["messages"].as_array().unwrap().clone();
+    assert_eq!(queued.len(), 2, "{queue}");
+    send(json!({
+        "jsonrpc": "2.0",
+        "id": "remove",
+        "method": "remove_pending_message",
+        "params": { "messageId": queued[0]["id"] },
+    }));
+    let removed = response(&output, json!("remove"));
+    assert_eq!(removed["result"]["queue"], "steer", "{removed}");
+    send(json!({ "jsonrpc": "2.0", "id": "clear", "method": "clear_pending" }));
+    let cleared = response(&output, json!("clear"));
+    assert_eq!(cleared["result"]["cleared"], 1, "{cleared}");
+
+    send(json!({ "jsonrpc": "2.0", "id": 2, "method": "no_such_command" }));
+    let unknown = response(&output, json!(2));
+    assert_eq!(unknown["error"]["code"], -32601, "{unknown}");
+
+    send(json!({
+        "jsonrpc": "2.0",
+        "id": 3,
+        "method": "pi/explainSelection",
+        "params": {
+            "path": "src/lib.rs",
+            "range": { "start": { "line": 4, "character": 0 }, "end": { "line": 5, "character": 1 } },
+            "text": "fn main() {\n}",
+        },
+    }));
+    let explained = response(&output, json!(3));
+    assert_eq!(explained["result"], Value::Null, "{explained}");
+    let prompt = prompts.lock().unwrap().last().cloned().expect("prompted");
+    assert!(prompt.contains("src/lib.rs (lines 5-6)"), "{prompt}");
+    assert!(prompt.contains("fn main() {\n}"), "{prompt}");
+
+    send(json!({ "jsonrpc": "2.0", "method": "exit" }));
+    server.join().unwrap().expect("server exits cleanly");
+    let events: Vec<Value> = output
+        .try_iter()
+        .map(|line| serde_json::from_str(&line).unwrap())
+        .collect();
+    assert!(events.iter().all(|event| event["method"] == "pi/event"));
+}
+
+#[test]
+fn get_messages_includes_custom_messages_and_respects_display() {
+    let (input, lines) = mpsc::channel::<io::Result<String>>();
+    let (sender, output) = mpsc::channel();
+    let sender = Mutex::new(sender);
+    let writer: RpcOutput = Arc::new(move |line: &str| {
+        let _ = sender.lock().unwrap().send(line.to_string());
+    });
+    let server = thread::spawn(move || {
+        let mut session = build_session(Arc::new(Mutex::new(Vec::new())));
+        for (custom_type, display) in [("note", true), ("hook-context", false)] {
+            session.session_manager.append_message(create_hook_message(
+                custom_type,
+                UserContent::Text(format!("{custom_type} text")),
+                display,
+                Some(json!({ "source": "test" })),
+                "2025-01-01T00:00:00Z",
+            ));
+        }
+        run_jsonrpc(session, RpcModeOptions::default(), lines, writer, None)
+    });
+    let send = |message: Value| input.send(Ok(message.to_string())).unwrap();
+
+    send(json!({ "jsonrpc": "2.0", "id": 1, "method": "get_messages" }));
+    let shown = response(&output, json!(1));
+    let messages = shown["result"]["messages"].as_array().unwrap();
+    assert_eq!(messages.len(), 1, "{shown}");
+    assert_eq!(messages[0]["role"], "custom");
+    assert_eq!(messages[0]["customType"], "note");
+    assert_eq!(messages[0]["details"]["source"], "test");
+
+    send(json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "get_messages",
+        "params": { "includeHidden": true },
+    }));
+    let all = response(&output, json!(2));
+    let messages = all["result"]["messages"].as_array().unwrap();
+    assert_eq!(messages.len(), 2, "{all}");
+    assert_eq!(messages[1]["display"], false);
+
+    drop(input);
+    server.join().unwrap().expect("server exits cleanly");
+}
+
+#[test]
+fn rejects_malformed_requests_and_drops_notification_responses() {
+    let adapter = JsonRpcAdapter::new();
+    match adapter.handle_input("{not json") {
+        JsonRpcInput::Reply(reply) => {
+            assert_eq!(reply["id"], Value::Null);
+            assert_eq!(reply["error"]["code"], PARSE_ERROR);
+        }
+        other => panic!("unexpected {other:?}"),
+    }
+    match adapter.handle_input(r#"{"jsonrpc":"2.0","id":9,"method":"prompt","params":[1]}"#) {
+        JsonRpcInput::Reply(reply) => assert_eq!(reply["error"]["code"], INVALID_PARAMS),
+        other => panic!("unexpected {other:?}"),
+    }
+    assert_eq!(
+        adapter.handle_input(r#"{"jsonrpc":"2.0","method":"prompt","params":[1]}"#),
+        JsonRpcInput::Ignore
+    );
+
+    let notified = command(
+        adapter
+            .handle_input(r#"{"jsonrpc":"2.0","method":"abort","params":{"sessionId":"tab-2"}}"#),
+    );
+    assert_eq!(notified, json!({ "type": "abort", "sessionId": "tab-2" }));
+    let response = json!({ "type": "response", "command": "abort", "success": true });
+    assert_eq!(adapter.handle_output(&response.to_string()), None);
+
+    let event = json!({ "type": "agent_start" });
+    assert_eq!(
+        adapter.handle_output(&event.to_string()),
+        Some(json!({ "jsonrpc": "2.0", "method": "pi/event", "params": event }))
+    );
+}
+
+#[test]
+fn round_trips_extension_ui_requests() {
+    let adapter = JsonRpcAdapter::new();
+    let request = json!({
+        "type": "extension_ui_request",
+        "id": "abc",
+        "method": "confirm",
+        "title": "Delete?",
+        "sessionId": "tab-2",
+    });
+    let sent = adapter.handle_output(&request.to_string()).unwrap();
+    assert_eq!(sent["method"], "pi/extensionUi");
+    assert_eq!(sent["id"], "ui-abc");
+    assert_eq!(sent["params"]["title"], "Delete?");
+
+    let answer = json!({ "jsonrpc": "2.0", "id": "ui-abc", "result": { "confirmed": true } });
+    assert_eq!(
+        command(adapter.handle_input(&answer.to_string())),
+        json!({
+            "type": "extension_ui_response",
+            "id": "abc",
+            "confirmed": true,
+            "sessionId": "tab-2",
+        })
+    );
+    assert_eq!(
+        adapter.handle_input(&answer.to_string()),
+        JsonRpcInput::Ignore
+    );
+}
+
+#[test]
+fn fix_diagnostics_prompt_quotes_the_file_range() {
+    let dir = std::env::temp_dir().join(format!("pi-jsonrpc-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("lib.rs");
+    std::fs::write(
+        &path,
+        "fn a() {}\nfn b() -> u8 {\n    \"x\"\n}\nfn c() {}\n",
+    )
+    .unwrap();
+    let params: CodeActionParams = serde_json::from_value(json!({
+        "path": path.to_string_lossy(),
+        "range": { "start": { "line": 1, "character": 0 }, "end": { "line": 3, "character": 1 } },
+        "diagnostics": [{
+            "range": { "start": { "line": 2, "character": 4 }, "end": { "line": 2, "character": 7 } },
+            "severity": 1,
+            "source": "rustc",
+            "message": "mismatched types",
+        }],
+        "instructions": "Keep the signature.",
+    }))
+    .unwrap();
+
+    let prompt = code_action_prompt("pi/fixDiagnostics", &params).unwrap();
+    assert!(
+        prompt.contains("- line 3: error: mismatched types (rustc)"),
+        "{prompt}"
+    );
+    assert!(
+        prompt.contains("(lines 2-4):\n\n```\nfn b() -> u8 {\n    \"x\"\n}\n```"),
+        "{prompt}"
+    );
+    assert!(!prompt.contains("fn c()"), "{prompt}");
+    assert!(prompt.ends_with("Keep the signature."), "{prompt}");
+
+    let mut empty = params.clone();
+    empty.diagnostics.clear();
+    assert!(code_action_prompt("pi/fixDiagnostics", &empty).is_err());
+    std::fs::remove_dir_all(&dir).ok();
+}
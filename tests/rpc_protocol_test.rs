@@ -0,0 +1,430 @@
+use pi::agent::{get_model, Agent, AgentOptions, AgentStateOverride, OutputFilterFn, StreamFn};
+use pi::ai::AssistantMessageEvent;
+use pi::coding_agent::{
+    AgentSession, AgentSessionConfig, AuthStorage, ModelRegistry, SettingsManager,
+};
+use pi::core::messages::{AssistantMessage, ContentBlock, Usage};
+use pi::core::session_manager::SessionManager;
+use pi::rpc::{
+    run_serve_mode, RpcModeOptions, RpcServer, RPC_COMMANDS, RPC_FEATURES, RPC_PROTOCOL_VERSION,
+};
+use serde_json::{json, Value};
+use std::cell::Cell;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::thread;
+use std::time::Duration;
+
+const TOKEN: &str = "secret-token";
+
+fn assistant_message(content: Vec<ContentBlock>, stop_reason: &str) -> AssistantMessage {
+    AssistantMessage {
+        content,
+        api: "anthropic-messages".to_string(),
+        provider: "anthropic".to_string(),
+        model: "mock".to_string(),
+        usage: Usage {
+            input: 1,
+            output: 1,
+            cache_read: 0,
+            cache_write: 0,
+            total_tokens: Some(2),
+            cost: None,
+        },
+        stop_reason: stop_reason.to_string(),
+        error_message: None,
+        timestamp: 0,
+    }
+}
+
+fn text(text: &str) -> ContentBlock {
+    ContentBlock::Text {
+        text: text.to_string(),
+        text_signature: None,
+    }
+}
+
+fn tool_call(arguments: Value) -> ContentBlock {
+    ContentBlock::ToolCall {
+        id: "call-1".to_string(),
+        name: "read".to_string(),
+        arguments,
+        thought_signature: None,
+    }
+}
+
+/// Streams "Hello world" and a `read` call token by token, then answers the tool result
+/// with "Done".
+fn streaming_fn() -> Box<StreamFn> {
+    let calls = Rc::new(Cell::new(0));
+    Box::new(move |_model, _context, events| {
+        calls.set(calls.get() + 1);
+        if calls.get() % 2 == 0 {
+            return assistant_message(vec![text("Done")], "stop");
+        }
+        for partial in ["Hel", "Hello", "Hello world"] {
+            events.emit(AssistantMessageEvent::TextDelta {
+                delta: String::new(),
+                partial: assistant_message(vec![text(partial)], "stop"),
+                content_index: 0,
+            });
+        }
+        for (delta, arguments) in [
+            (r#"{"path":"#, json!({})),
+            (r#" "a"#, json!({})),
+            (r#".txt"}"#, json!({ "path": "a.txt" })),
+        ] {
+            events.emit(AssistantMessageEvent::ToolCallDelta {
+                delta: delta.to_string(),
+                partial: assistant_message(
+                    vec![text("Hello world"), tool_call(arguments)],
+                    "toolUse",
+                ),
+                content_index: 1,
+            });
+        }
+        assistant_message(
+            vec![text("Hello world"), tool_call(json!({ "path": "a.txt" }))],
+            "toolUse",
+        )
+    })
+}
+
+fn build_session(stream_fn: Box<StreamFn>) -> AgentSession {
+    build_session_with_filter(stream_fn, None)
+}
+
+fn build_session_with_filter(
+    stream_fn: Box<StreamFn>,
+    output_filter: Option<Rc<OutputFilterFn>>,
+) -> AgentSession {
+    let mut agent = Agent::new(AgentOptions {
+        initial_state: Some(AgentStateOverride {
+            model: Some(get_model("anthropic", "claude-sonnet-4-5")),
+            system_prompt: Some("Test".to_string()),
+            tools: Some(Vec::new()),
+            ..Default::default()
+        }),
+        convert_to_llm: Some(Box::new(|messages| messages.to_vec())),
+        stream_fn: Some(stream_fn),
+        ..Default::default()
+    });
+    agent.set_output_filter(output_filter);
+    let mut auth_storage = AuthStorage::new(PathBuf::from("auth.json"));
+    auth_storage.set_runtime_api_key("anthropic", "test-key");
+    AgentSession::new(AgentSessionConfig {
+        agent,
+        session_manager: SessionManager::in_memory(),
+        settings_manager: SettingsManager::create("", ""),
+        model_registry: ModelRegistry::new(auth_storage, None),
+    })
+}
+
+/// Serves a session built on the server thread and subscribes to its events.
+fn serve(build: fn() -> AgentSession) -> (SocketAddr, BufReader<TcpStream>) {
+    let server = RpcServer::bind(([127, 0, 0, 1], 0), TOKEN).expect("bind");
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || run_serve_mode(build(), RpcModeOptions::default(), server, None));
+
+    let mut stream = TcpStream::connect(addr).expect("connect");
+    stream
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    write!(
+        stream,
+        "GET /events?token={TOKEN} HTTP/1.1\r\nHost: pi\r\n\r\n"
+    )
+    .unwrap();
+    let mut events = BufReader::new(stream);
+    let mut status = String::new();
+    events.read_line(&mut status).unwrap();
+    assert!(status.starts_with("HTTP/1.1 200"), "{status}");
+    (addr, events)
+}
+
+fn post(addr: SocketAddr, body: &str) {
+    let mut stream = TcpStream::connect(addr).expect("connect");
+    let request = format!(
+        "POST /rpc HTTP/1.1\r\nHost: pi\r\nAuthorization: Bearer {TOKEN}\r\n\
+         Content-Length: {}\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(request.as_bytes()).expect("post");
+    let mut status = String::new();
+    BufReader::new(stream).read_line(&mut status).unwrap();
+    assert!(status.starts_with("HTTP/1.1 202"), "{status}");
+}
+
+/// Reads SSE events up to and including the response to `command`.
+fn read_until_response(events: &mut BufReader<TcpStream>, command: &str) -> Vec<Value> {
+    let mut values = Vec::new();
+    loop {
+        let mut line = String::new();
+        events.read_line(&mut line).expect("read event");
+        let Some(data) = line.trim_end().strip_prefix("data: ") else {
+            continue;
+        };
+        let value: Value = serde_json::from_str(data).unwrap();
+        let done = value["type"] == "response" && value["command"] == command;
+        values.push(value);
+        if done {
+            return values;
+        }
+    }
+}
+
+fn of_type<'a>(values: &'a [Value], kind: &str) -> Vec<&'a Value> {
+    values
+        .iter()
+        .filter(|value| value["type"] == kind)
+        .collect()
+}
+
+#[test]
+fn message_deltas_replace_full_message_updates_once_enabled() {
+    let (addr, mut events) = serve(|| build_session(streaming_fn()));
+
+    post(addr, r#"{"type":"prompt","message":"hi"}"#);
+    let full = read_until_response(&mut events, "prompt");
+    assert_eq!(of_type(&full, "message_update").len(), 7);
+    assert!(of_type(&full, "text_delta").is_empty());
+
+    post(
+        addr,
+        r#"{"type":"capabilities","id":"c1","enable":["message_deltas","telepathy"]}"#,
+    );
+    let response = read_until_response(&mut events, "capabilities");
+    let response = response.last().unwrap();
+    assert_eq!(response["success"], true);
+    assert_eq!(response["data"]["supported"], json!(["message_deltas"]));
+    assert_eq!(response["data"]["enabled"], json!(["message_deltas"]));
+
+    post(addr, r#"{"type":"prompt","message":"again"}"#);
+    let streamed = read_until_response(&mut events, "prompt");
+    assert!(of_type(&streamed, "message_update").is_empty());
+
+    let start = of_type(&streamed, "message_start")
+        .into_iter()
+        .find(|value| value["message"]["role"] == "assistant")
+        .unwrap();
+    assert_eq!(start["message"]["content"][0]["text"], "Hel");
+    let text_deltas = of_type(&streamed, "text_delta")
+        .iter()
+        .map(|value| (value["contentIndex"].clone(), value["delta"].clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        text_deltas,
+        [(json!(0), json!("lo")), (json!(0), json!(" world"))]
+    );
+    let starts = of_type(&streamed, "tool_call_start");
+    assert_eq!(starts.len(), 1);
+    assert_eq!(starts[0]["contentIndex"], 1);
+    assert_eq!(starts[0]["id"], "call-1");
+    assert_eq!(starts[0]["name"], "read");
+    let tool_deltas = of_type(&streamed, "tool_call_delta");
+    assert!(tool_deltas.iter().all(|value| value["contentIndex"] == 1));
+    let arguments = tool_deltas
+        .iter()
+        .map(|value| value["delta"].as_str().unwrap())
+        .collect::<String>();
+    assert_eq!(arguments, r#"{"path": "a.txt"}"#);
+
+    let end = of_type(&streamed, "message_end")
+        .into_iter()
+        .find(|value| value["message"]["role"] == "assistant")
+        .unwrap();
+    assert_eq!(end["message"]["content"][0]["text"], "Hello world");
+
+    post(addr, r#"{"type":"capabilities","enable":[]}"#);
+    let response = read_until_response(&mut events, "capabilities");
+    assert_eq!(response.last().unwrap()["data"]["enabled"], json!([]));
+}
+
+/// Streams "token sk-live" through a filter that redacts "sk-", which rewrites text
+/// already sent into something longer.
+fn redacting_session() -> AgentSession {
+    let stream_fn: Box<StreamFn> = Box::new(|_model, _context, events| {
+        for partial in ["token s", "token sk-", "token sk-live"] {
+            events.emit(AssistantMessageEvent::TextDelta {
+                delta: String::new(),
+                partial: assistant_message(vec![text(partial)], "stop"),
+                content_index: 0,
+            });
+        }
+        assistant_message(vec![text("token sk-live")], "stop")
+    });
+    let redact: Rc<OutputFilterFn> = Rc::new(|mut message: AssistantMessage, _partial| {
+        for block in &mut message.content {
+            if let ContentBlock::Text { text, .. } = block {
+                *text = text.replace("sk-", "[REDACTED]");
+            }
+        }
+        message
+    });
+    build_session_with_filter(stream_fn, Some(redact))
+}
+
+#[test]
+fn message_deltas_fall_back_to_full_updates_when_sent_text_is_rewritten() {
+    let (addr, mut events) = serve(redacting_session);
+    post(
+        addr,
+        r#"{"type":"capabilities","enable":["message_deltas"]}"#,
+    );
+    read_until_response(&mut events, "capabilities");
+
+    post(addr, r#"{"type":"prompt","message":"hi"}"#);
+    let streamed = read_until_response(&mut events, "prompt");
+
+    let updates = of_type(&streamed, "message_update");
+    assert_eq!(updates.len(), 1);
+    assert_eq!(
+        updates[0]["message"]["content"][0]["text"],
+        "token [REDACTED]"
+    );
+    let text_deltas = of_type(&streamed, "text_delta")
+        .iter()
+        .map(|value| value["delta"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(text_deltas, [json!("live")]);
+}
+
+#[test]
+fn hello_reports_protocol_commands_and_model() {
+    let (addr, mut events) = serve(|| build_session(streaming_fn()));
+
+    post(
+        addr,
+        r#"{"type":"hello","id":"h1","enable":["message_deltas"]}"#,
+    );
+    let response = read_until_response(&mut events, "hello");
+    let hello = response.last().unwrap();
+    assert_eq!(hello["success"], true, "{hello}");
+    assert_eq!(hello["id"], "h1");
+    let data = &hello["data"];
+    assert_eq!(data["protocolVersion"], RPC_PROTOCOL_VERSION);
+    assert_eq!(data["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(data["commands"], json!(RPC_COMMANDS));
+    for command in ["hello", "prompt", "capabilities", "create_session"] {
+        assert!(RPC_COMMANDS.contains(&command), "{command}");
+    }
+    assert_eq!(data["features"]["supported"], json!(RPC_FEATURES));
+    assert_eq!(data["features"]["enabled"], json!(["message_deltas"]));
+    assert_eq!(data["model"]["id"], "claude-sonnet-4-5");
+    assert_eq!(data["model"]["provider"], "anthropic");
+    assert_eq!(data["extensions"], json!([]));
+
+    // A hello without `enable` leaves the features as they are.
+    post(addr, r#"{"type":"hello"}"#);
+    let response = read_until_response(&mut events, "hello");
+    let data = &response.last().unwrap()["data"];
+    assert_eq!(data["features"]["enabled"], json!(["message_deltas"]));
+}
+
+#[test]
+fn unknown_commands_list_the_supported_ones() {
+    let (addr, mut events) = serve(|| build_session(streaming_fn()));
+
+    post(addr, r#"{"type":"teleport","id":"t1"}"#);
+    let response = read_until_response(&mut events, "teleport");
+    let error = response.last().unwrap();
+    assert_eq!(error["success"], false);
+    assert_eq!(error["id"], "t1");
+    assert_eq!(error["error"], "Unknown command");
+    assert_eq!(error["supportedCommands"], json!(RPC_COMMANDS));
+}
+
+#[test]
+fn batch_runs_commands_in_order_and_summarizes_them() {
+    let (addr, mut events) = serve(|| build_session(streaming_fn()));
+
+    post(
+        addr,
+        r#"{"type":"batch","id":"b1","commands":[
+            {"type":"set_thinking_level","id":"c1","level":"high"},
+            {"type":"prompt","id":"c2","message":"hi"},
+            {"type":"get_state","id":"c3"}
+        ]}"#,
+    );
+    let values = read_until_response(&mut events, "batch");
+    let responses = of_type(&values, "response");
+    let order = responses
+        .iter()
+        .map(|response| response["command"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        order,
+        ["set_thinking_level", "prompt", "get_state", "batch"]
+    );
+    assert_eq!(responses[2]["data"]["thinkingLevel"], "high");
+
+    // The prompt's events all come before its response, and nothing follows it until the
+    // next command's response.
+    let position =
+        |key: &str, value: &str| values.iter().position(|event| event[key] == value).unwrap();
+    let prompt_response = position("command", "prompt");
+    let agent_end = position("type", "agent_end");
+    assert!(agent_end < prompt_response);
+    assert_eq!(values[prompt_response + 1]["command"], "get_state");
+
+    let summary = responses[3];
+    assert_eq!(summary["id"], "b1");
+    assert_eq!(summary["success"], true, "{summary}");
+    assert_eq!(summary["data"]["failed"], 0);
+    assert_eq!(
+        summary["data"]["results"],
+        json!([
+            { "command": "set_thinking_level", "id": "c1", "success": true },
+            { "command": "prompt", "id": "c2", "success": true },
+            { "command": "get_state", "id": "c3", "success": true },
+        ])
+    );
+}
+
+#[test]
+fn batch_skips_the_rest_after_a_failure_unless_told_otherwise() {
+    let (addr, mut events) = serve(|| build_session(streaming_fn()));
+
+    post(
+        addr,
+        r#"{"type":"batch","commands":[{"type":"teleport"},{"type":"get_state","id":"s"}]}"#,
+    );
+    let values = read_until_response(&mut events, "batch");
+    assert!(of_type(&values, "response")
+        .iter()
+        .all(|response| response["command"] != "get_state"));
+    let summary = values.last().unwrap();
+    assert_eq!(summary["success"], false);
+    assert_eq!(summary["error"], "1 of 2 commands failed");
+    assert_eq!(summary["data"]["skipped"], 1);
+    assert_eq!(
+        summary["data"]["results"],
+        json!([
+            { "command": "teleport", "success": false, "error": "Unknown command" },
+            { "command": "get_state", "id": "s", "skipped": true },
+        ])
+    );
+
+    post(
+        addr,
+        r#"{"type":"batch","id":"outer","continueOnError":true,"commands":[
+            {"type":"teleport"},
+            {"type":"batch","commands":[]},
+            {"type":"get_state"}
+        ]}"#,
+    );
+    // The nested batch's error comes first.
+    let nested = read_until_response(&mut events, "batch");
+    assert_eq!(nested.last().unwrap()["error"], "Batches cannot be nested");
+    let values = read_until_response(&mut events, "batch");
+    let summary = values.last().unwrap();
+    assert_eq!(summary["id"], "outer");
+    assert_eq!(summary["error"], "2 of 3 commands failed");
+    assert_eq!(
+        summary["data"]["results"][1]["error"],
+        "Batches cannot be nested"
+    );
+    assert_eq!(summary["data"]["results"][2]["success"], true);
+}
@@ -0,0 +1,179 @@
+use pi::rpc::server::{read_frame, websocket_accept_key, write_frame};
+use pi::rpc::RpcServer;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::time::{Duration, Instant};
+
+const TOKEN: &str = "secret-token";
+
+fn connect(addr: SocketAddr, request: &str) -> BufReader<TcpStream> {
+    let mut stream = TcpStream::connect(addr).expect("connect");
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .expect("set timeout");
+    stream.write_all(request.as_bytes()).expect("send request");
+    BufReader::new(stream)
+}
+
+/// Reads the status line and headers.
+fn read_head(reader: &mut BufReader<TcpStream>) -> Vec<String> {
+    let mut lines = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).expect("read head");
+        let line = line.trim_end().to_string();
+        if line.is_empty() {
+            return lines;
+        }
+        lines.push(line);
+    }
+}
+
+/// A masked client frame, as browsers send them.
+fn client_frame(text: &str) -> Vec<u8> {
+    let mask = [0x12, 0x34, 0x56, 0x78];
+    let mut frame = vec![0x81, 0x80 | text.len() as u8];
+    frame.extend_from_slice(&mask);
+    frame.extend(
+        text.bytes()
+            .enumerate()
+            .map(|(index, byte)| byte ^ mask[index % 4]),
+    );
+    frame
+}
+
+#[test]
+fn computes_the_rfc_6455_accept_key() {
+    assert_eq!(
+        websocket_accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+        "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+    );
+}
+
+#[test]
+fn frames_round_trip_at_every_length_encoding() {
+    for length in [0, 125, 126, 70_000] {
+        let payload = vec![b'x'; length];
+        let mut frame = Vec::new();
+        write_frame(&mut frame, 0x1, &payload).unwrap();
+        let (fin, opcode, read) = read_frame(&mut frame.as_slice()).unwrap().unwrap();
+        assert!(fin);
+        assert_eq!(opcode, 0x1);
+        assert_eq!(read.len(), length);
+    }
+    assert!(read_frame(&mut [].as_slice()).unwrap().is_none());
+}
+
+#[test]
+fn serves_commands_and_events_over_websocket_and_sse() {
+    let server = RpcServer::bind(([127, 0, 0, 1], 0), TOKEN).expect("bind");
+    let addr = server.local_addr().unwrap();
+    let (commands, output) = server.start();
+
+    let mut rejected = connect(addr, "GET /events HTTP/1.1\r\nHost: pi\r\n\r\n");
+    assert_eq!(read_head(&mut rejected)[0], "HTTP/1.1 401 Unauthorized");
+
+    let mut socket = connect(
+        addr,
+        &format!(
+            "GET /rpc HTTP/1.1\r\nHost: pi\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\
+             Authorization: Bearer {TOKEN}\r\n\r\n"
+        ),
+    );
+    let head = read_head(&mut socket);
+    assert_eq!(head[0], "HTTP/1.1 101 Switching Protocols");
+    assert!(head.contains(&"Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=".to_string()));
+
+    let mut events = connect(
+        addr,
+        &format!("GET /events?token={TOKEN} HTTP/1.1\r\nHost: pi\r\n\r\n"),
+    );
+    let head = read_head(&mut events);
+    assert_eq!(head[0], "HTTP/1.1 200 OK");
+    assert!(head.contains(&"Content-Type: text/event-stream".to_string()));
+
+    socket
+        .get_mut()
+        .write_all(&client_frame(r#"{"type":"get_state"}"#))
+        .unwrap();
+    let command = commands.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(command.unwrap(), r#"{"type":"get_state"}"#);
+
+    let body = r#"{"type":"abort"}"#;
+    let mut post = connect(
+        addr,
+        &format!(
+            "POST /rpc HTTP/1.1\r\nHost: pi\r\nAuthorization: Bearer {TOKEN}\r\n\
+             Content-Length: {}\r\n\r\n{body}",
+            body.len()
+        ),
+    );
+    assert_eq!(read_head(&mut post)[0], "HTTP/1.1 202 Accepted");
+    let command = commands.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(command.unwrap(), body);
+
+    output(r#"{"type":"agent_start"}"#);
+    let (_, opcode, payload) = read_frame(&mut socket).unwrap().unwrap();
+    assert_eq!(opcode, 0x1);
+    assert_eq!(payload, br#"{"type":"agent_start"}"#);
+    let mut event = String::new();
+    while !event.starts_with("data:") {
+        event.clear();
+        events.read_line(&mut event).unwrap();
+    }
+    assert_eq!(event.trim_end(), r#"data: {"type":"agent_start"}"#);
+
+    // Pings are answered with the same payload.
+    let mut ping = client_frame("hi");
+    ping[0] = 0x89;
+    socket.get_mut().write_all(&ping).unwrap();
+    let (_, opcode, payload) = read_frame(&mut socket).unwrap().unwrap();
+    assert_eq!((opcode, payload.as_slice()), (0xA, b"hi".as_slice()));
+
+    let mut rest = Vec::new();
+    let mut close = client_frame("");
+    close[0] = 0x88;
+    socket.get_mut().write_all(&close).unwrap();
+    let (_, opcode, _) = read_frame(&mut socket).unwrap().unwrap();
+    assert_eq!(opcode, 0x8);
+    let _ = socket.read_to_end(&mut rest);
+}
+
+#[test]
+fn drops_a_client_that_stops_reading_without_blocking_the_rest() {
+    let server = RpcServer::bind(([127, 0, 0, 1], 0), TOKEN).expect("bind");
+    let addr = server.local_addr().unwrap();
+    let (_commands, output) = server.start();
+    let request = format!("GET /events?token={TOKEN} HTTP/1.1\r\nHost: pi\r\n\r\n");
+
+    let mut stalled = connect(addr, &request);
+    assert_eq!(read_head(&mut stalled)[0], "HTTP/1.1 200 OK");
+
+    // Far more than the socket buffers and the client's queue hold.
+    let line = format!(
+        r#"{{"type":"message_update","text":"{}"}}"#,
+        "x".repeat(16 * 1024)
+    );
+    let started = Instant::now();
+    for _ in 0..4000 {
+        output(&line);
+    }
+    assert!(started.elapsed() < Duration::from_secs(10));
+
+    // The server hung up on the stalled client after it fell behind.
+    let mut received = Vec::new();
+    stalled.read_to_end(&mut received).expect("stream ends");
+    assert!(received.len() < 4000 * line.len());
+
+    // Clients that keep up are still served.
+    let mut events = connect(addr, &request);
+    assert_eq!(read_head(&mut events)[0], "HTTP/1.1 200 OK");
+    output(r#"{"type":"agent_end"}"#);
+    let mut event = String::new();
+    while !event.starts_with("data:") {
+        event.clear();
+        events.read_line(&mut event).unwrap();
+    }
+    assert_eq!(event.trim_end(), r#"data: {"type":"agent_end"}"#);
+}
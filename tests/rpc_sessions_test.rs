@@ -0,0 +1,199 @@
+use pi::agent::{get_model, Agent, AgentOptions, AgentStateOverride, StreamFn};
+use pi::coding_agent::{
+    AgentSession, AgentSessionConfig, AuthStorage, ModelRegistry, SettingsManager,
+};
+use pi::core::messages::{AssistantMessage, ContentBlock, Usage};
+use pi::core::session_manager::SessionManager;
+use pi::rpc::{run_serve_mode, RpcModeOptions, RpcServer, SessionFactory, SessionSpec};
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const TOKEN: &str = "secret-token";
+
+fn build_session(provider: &str, model: &str) -> AgentSession {
+    build_session_with(
+        provider,
+        model,
+        Box::new(|_model, _context, _events| reply()),
+    )
+}
+
+fn reply() -> AssistantMessage {
+    AssistantMessage {
+        content: vec![ContentBlock::Text {
+            text: "ok".to_string(),
+            text_signature: None,
+        }],
+        api: "anthropic-messages".to_string(),
+        provider: "anthropic".to_string(),
+        model: "mock".to_string(),
+        usage: Usage {
+            input: 1,
+            output: 1,
+            cache_read: 0,
+            cache_write: 0,
+            total_tokens: Some(2),
+            cost: None,
+        },
+        stop_reason: "stop".to_string(),
+        error_message: None,
+        timestamp: 0,
+    }
+}
+
+fn build_session_with(provider: &str, model: &str, stream_fn: Box<StreamFn>) -> AgentSession {
+    let model = get_model(provider, model);
+    let agent = Agent::new(AgentOptions {
+        initial_state: Some(AgentStateOverride {
+            model: Some(model),
+            system_prompt: Some("Test".to_string()),
+            tools: Some(Vec::new()),
+            ..Default::default()
+        }),
+        convert_to_llm: Some(Box::new(|messages| messages.to_vec())),
+        stream_fn: Some(stream_fn),
+        ..Default::default()
+    });
+    let mut auth_storage = AuthStorage::new(PathBuf::from("auth.json"));
+    auth_storage.set_runtime_api_key("anthropic", "test-key");
+    AgentSession::new(AgentSessionConfig {
+        agent,
+        session_manager: SessionManager::in_memory(),
+        settings_manager: SettingsManager::create("", ""),
+        model_registry: ModelRegistry::new(auth_storage, None),
+    })
+}
+
+fn post(addr: SocketAddr, body: &str) {
+    let mut stream = TcpStream::connect(addr).expect("connect");
+    let request = format!(
+        "POST /rpc HTTP/1.1\r\nHost: pi\r\nAuthorization: Bearer {TOKEN}\r\n\
+         Content-Length: {}\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(request.as_bytes()).expect("post");
+    let mut status = String::new();
+    BufReader::new(stream).read_line(&mut status).unwrap();
+    assert!(status.starts_with("HTTP/1.1 202"), "{status}");
+}
+
+/// Connects to the server's event stream.
+fn subscribe(addr: SocketAddr) -> BufReader<TcpStream> {
+    let mut stream = TcpStream::connect(addr).expect("connect");
+    stream
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    write!(
+        stream,
+        "GET /events?token={TOKEN} HTTP/1.1\r\nHost: pi\r\n\r\n"
+    )
+    .unwrap();
+    let mut events = BufReader::new(stream);
+    let mut status = String::new();
+    events.read_line(&mut status).unwrap();
+    assert!(status.starts_with("HTTP/1.1 200"), "{status}");
+    events
+}
+
+/// Reads SSE events until a response to `command` arrives.
+fn response(events: &mut BufReader<TcpStream>, command: &str) -> Value {
+    loop {
+        let mut line = String::new();
+        events.read_line(&mut line).expect("read event");
+        let Some(data) = line.trim_end().strip_prefix("data: ") else {
+            continue;
+        };
+        let value: Value = serde_json::from_str(data).unwrap();
+        if value["type"] == "response" && value["command"] == command {
+            return value;
+        }
+    }
+}
+
+#[test]
+fn routes_commands_to_sessions_by_id() {
+    let server = RpcServer::bind(([127, 0, 0, 1], 0), TOKEN).expect("bind");
+    let addr = server.local_addr().unwrap();
+    let factory: SessionFactory = Arc::new(|spec: &SessionSpec| {
+        let model = spec.model.as_deref().ok_or("model required")?;
+        Ok(build_session("anthropic", model))
+    });
+    thread::spawn(move || {
+        let session = build_session("anthropic", "claude-sonnet-4-5");
+        run_serve_mode(session, RpcModeOptions::default(), server, Some(factory))
+    });
+
+    let mut events = subscribe(addr);
+
+    post(
+        addr,
+        r#"{"type":"create_session","sessionId":"tab-2","model":"claude-haiku-4-5"}"#,
+    );
+    let created = response(&mut events, "create_session");
+    assert_eq!(created["success"], true, "{created}");
+    assert_eq!(created["sessionId"], "tab-2");
+    assert_eq!(created["data"]["model"]["id"], "claude-haiku-4-5");
+
+    post(addr, r#"{"type":"get_state","sessionId":"tab-2"}"#);
+    let state = response(&mut events, "get_state");
+    assert_eq!(state["sessionId"], "tab-2");
+    assert_eq!(state["data"]["model"]["id"], "claude-haiku-4-5");
+
+    post(addr, r#"{"type":"get_state"}"#);
+    let state = response(&mut events, "get_state");
+    assert!(state.get("sessionId").is_none());
+    assert_eq!(state["data"]["model"]["id"], "claude-sonnet-4-5");
+
+    post(addr, r#"{"type":"list_active_sessions"}"#);
+    let listed = response(&mut events, "list_active_sessions");
+    let ids: Vec<_> = listed["data"]["sessions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|session| session["sessionId"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(ids, ["default", "tab-2"]);
+
+    post(addr, r#"{"type":"close_session","sessionId":"default"}"#);
+    assert_eq!(response(&mut events, "close_session")["success"], false);
+    post(addr, r#"{"type":"close_session","sessionId":"tab-2"}"#);
+    assert_eq!(response(&mut events, "close_session")["success"], true);
+
+    post(addr, r#"{"type":"get_state","sessionId":"tab-2"}"#);
+    let missing = response(&mut events, "get_state");
+    assert_eq!(missing["success"], false);
+    assert_eq!(missing["error"], "Unknown session tab-2");
+}
+
+#[test]
+fn abort_interrupts_a_prompt_that_is_still_running() {
+    let server = RpcServer::bind(([127, 0, 0, 1], 0), TOKEN).expect("bind");
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || {
+        // A provider that never answers until the turn is aborted.
+        let stream_fn: Box<StreamFn> = Box::new(|_model, _context, events| {
+            let started = Instant::now();
+            while !events.is_aborted() && started.elapsed() < Duration::from_secs(10) {
+                thread::sleep(Duration::from_millis(5));
+            }
+            events.finish(reply())
+        });
+        let session = build_session_with("anthropic", "claude-sonnet-4-5", stream_fn);
+        run_serve_mode(session, RpcModeOptions::default(), server, None)
+    });
+    let mut events = subscribe(addr);
+
+    let started = Instant::now();
+    post(addr, r#"{"type":"prompt","message":"hi"}"#);
+    thread::sleep(Duration::from_millis(100));
+    post(addr, r#"{"type":"abort"}"#);
+
+    assert_eq!(response(&mut events, "prompt")["success"], true);
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(response(&mut events, "abort")["success"], true);
+}
@@ -0,0 +1,67 @@
+use pi::rpc::{RpcModeOptions, RpcSupervisor};
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn exits_after_idle_time_without_counting_busy_commands() {
+    let mut supervisor = RpcSupervisor::new(RpcModeOptions {
+        idle_exit: Some(Duration::from_millis(50)),
+        parent_pid: None,
+    });
+    assert_eq!(supervisor.exit_reason(), None);
+
+    supervisor.start_command();
+    thread::sleep(Duration::from_millis(80));
+    assert_eq!(supervisor.exit_reason(), None);
+    supervisor.finish_command();
+    assert_eq!(supervisor.exit_reason(), None);
+
+    thread::sleep(Duration::from_millis(80));
+    assert_eq!(supervisor.exit_reason(), Some("idle"));
+
+    let unlimited = RpcSupervisor::new(RpcModeOptions::default());
+    assert_eq!(unlimited.exit_reason(), None);
+}
+
+#[cfg(unix)]
+#[test]
+fn exits_when_the_parent_process_is_gone() {
+    let alive = RpcSupervisor::new(RpcModeOptions {
+        idle_exit: None,
+        parent_pid: Some(std::process::id()),
+    });
+    assert_eq!(alive.exit_reason(), None);
+
+    let mut child = Command::new("/bin/sh")
+        .args(["-c", "exit 0"])
+        .spawn()
+        .unwrap();
+    let pid = child.id();
+    child.wait().unwrap();
+    let orphaned = RpcSupervisor::new(RpcModeOptions {
+        idle_exit: None,
+        parent_pid: Some(pid),
+    });
+    assert_eq!(orphaned.exit_reason(), Some("parent_exited"));
+}
+
+#[test]
+fn shared_supervisors_keep_the_owner_busy_and_never_exit() {
+    let mut owner = RpcSupervisor::new(RpcModeOptions {
+        idle_exit: Some(Duration::from_millis(50)),
+        parent_pid: None,
+    });
+    let mut shared = owner.share();
+
+    shared.start_command();
+    thread::sleep(Duration::from_millis(80));
+    assert_eq!(owner.exit_reason(), None);
+    assert_eq!(shared.exit_reason(), None);
+
+    shared.finish_command();
+    owner.finish_command();
+    thread::sleep(Duration::from_millis(80));
+    assert_eq!(owner.exit_reason(), Some("idle"));
+    assert_eq!(shared.exit_reason(), None);
+}
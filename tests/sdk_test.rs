@@ -0,0 +1,214 @@
+use pi::agent::{AgentEvent, AgentMessage};
+use pi::coding_agent::{AuthStorage, ModelRegistry};
+use pi::core::messages::ContentBlock;
+use pi::sdk::PiClient;
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
+use uuid::Uuid;
+
+/// A registry whose only extra model is a mock one answering with `responses`.
+fn mock_registry(responses: Value) -> (ModelRegistry, PathBuf) {
+    let dir = std::env::temp_dir().join(format!("pi-sdk-{}", Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    let fixture = dir.join("fixture.json");
+    fs::write(&fixture, json!({ "responses": responses }).to_string()).unwrap();
+    let models_json = dir.join("models.json");
+    let config = json!({
+        "providers": {
+            "scripted": {
+                "api": "mock",
+                "baseUrl": fixture.display().to_string(),
+                "models": [{
+                    "id": "demo",
+                    "cost": { "input": 1, "output": 2, "cacheRead": 0, "cacheWrite": 0 }
+                }]
+            }
+        }
+    });
+    fs::write(&models_json, config.to_string()).unwrap();
+    let registry = ModelRegistry::new(AuthStorage::new(dir.join("auth.json")), models_json);
+    (registry, dir)
+}
+
+#[test]
+fn prompts_with_custom_tools_and_events() {
+    let (registry, _dir) = mock_registry(json!([
+        { "content": [
+            { "type": "text", "text": "Looking it up." },
+            { "type": "toolCall", "name": "weather", "arguments": { "city": "Oslo" } }
+        ], "usage": { "input": 100, "output": 10 } },
+        { "content": [{ "type": "text", "text": "It is 4 degrees in Oslo." }],
+          "usage": { "input": 150, "output": 20 } }
+    ]));
+    let calls = Rc::new(RefCell::new(Vec::new()));
+    let seen_calls = calls.clone();
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let seen_events = events.clone();
+
+    let mut client = PiClient::builder()
+        .model_registry(registry)
+        .provider("scripted")
+        .model("demo")
+        .tools(Vec::<String>::new())
+        .system_prompt("You report the weather.")
+        .tool(
+            "weather",
+            "Current weather for a city",
+            json!({ "type": "object", "properties": { "city": { "type": "string" } } }),
+            move |args| {
+                let city = args["city"].as_str().unwrap_or_default().to_string();
+                seen_calls.borrow_mut().push(city.clone());
+                Ok(format!("{city}: 4C"))
+            },
+        )
+        .on_event(move |event| seen_events.borrow_mut().push(event.kind()))
+        .build()
+        .unwrap();
+    assert_eq!(client.model().id, "demo");
+    assert!(client
+        .session()
+        .agent
+        .state()
+        .system_prompt
+        .starts_with("You report the weather."));
+
+    let result = client.prompt("Weather in Oslo?").unwrap();
+    assert_eq!(result.text, "It is 4 degrees in Oslo.");
+    assert_eq!(result.stop_reason, "stop");
+    assert_eq!(*calls.borrow(), vec!["Oslo"]);
+    let roles = result
+        .messages
+        .iter()
+        .map(AgentMessage::role)
+        .collect::<Vec<_>>();
+    assert_eq!(roles, vec!["user", "assistant", "toolResult", "assistant"]);
+    assert_eq!(result.usage.input, 250);
+    assert_eq!(result.usage.output, 30);
+    assert_eq!(result.usage.total_tokens, Some(280));
+    assert!((result.usage.cost.unwrap().total - 0.00031).abs() < 1e-12);
+
+    let events = events.borrow();
+    assert_eq!(events.first(), Some(&"agent_start"));
+    assert_eq!(events.last(), Some(&"agent_end"));
+    assert!(events.contains(&"tool_execution_end"));
+}
+
+#[test]
+fn custom_tool_arguments_are_checked_against_its_schema() {
+    let (registry, _dir) = mock_registry(json!([
+        { "content": [
+            { "type": "toolCall", "name": "weather", "arguments": { "town": 7 } }
+        ] },
+        { "content": [{ "type": "text", "text": "Sorry." }] }
+    ]));
+    let calls = Rc::new(RefCell::new(0));
+    let counted = calls.clone();
+
+    let mut client = PiClient::builder()
+        .model_registry(registry)
+        .provider("scripted")
+        .model("demo")
+        .tools(Vec::<String>::new())
+        .tool(
+            "weather",
+            "Current weather for a city",
+            json!({
+                "type": "object",
+                "properties": { "city": { "type": "string" } },
+                "required": ["city"],
+                "additionalProperties": false
+            }),
+            move |_args| {
+                *counted.borrow_mut() += 1;
+                Ok("4C".to_string())
+            },
+        )
+        .build()
+        .unwrap();
+
+    let result = client.prompt("Weather?").unwrap();
+    assert_eq!(*calls.borrow(), 0);
+    let tool_result = result
+        .messages
+        .iter()
+        .find_map(|message| match message {
+            AgentMessage::ToolResult(result) => Some(result),
+            _ => None,
+        })
+        .expect("tool result");
+    assert!(tool_result.is_error);
+    match tool_result.content.first() {
+        Some(ContentBlock::Text { text, .. }) => assert!(
+            text.starts_with("Invalid arguments for tool \"weather\":\n- /city: is required"),
+            "{text}"
+        ),
+        other => panic!("expected a text error, got {other:?}"),
+    }
+}
+
+#[test]
+fn prompt_stream_passes_text_deltas_and_errors_are_returned() {
+    let (registry, _dir) = mock_registry(json!([
+        { "content": [{ "type": "text", "text": ["Hel", "lo", " there"] }] },
+        { "error": "overloaded_error: try again later" }
+    ]));
+    let mut client = PiClient::builder()
+        .model_registry(registry)
+        .model("demo")
+        .tools(["read"])
+        .build()
+        .unwrap();
+
+    let deltas = Rc::new(RefCell::new(Vec::new()));
+    let seen = deltas.clone();
+    let result = client
+        .prompt_stream("Hi", move |delta| seen.borrow_mut().push(delta.to_string()))
+        .unwrap();
+    assert_eq!(result.text, "Hello there");
+    assert_eq!(*deltas.borrow(), vec!["Hel", "lo", " there"]);
+
+    let err = client.prompt("Again").unwrap_err();
+    assert_eq!(err.to_string(), "overloaded_error: try again later");
+    assert!(err.is_retryable());
+    assert_eq!(client.messages().len(), 4);
+}
+
+#[test]
+fn build_reports_unknown_models_and_tools() {
+    let (registry, _dir) = mock_registry(json!([]));
+    let err = PiClient::builder()
+        .model_registry(registry)
+        .provider("scripted")
+        .model("missing")
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(err.to_string(), "Model scripted/missing not found");
+
+    let (registry, _dir) = mock_registry(json!([]));
+    let err = PiClient::builder()
+        .model_registry(registry)
+        .model("demo")
+        .tools(["teleport"])
+        .build()
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("teleport"), "{err}");
+}
+
+#[test]
+fn events_are_agent_events() {
+    fn is_agent_event(_: &AgentEvent) {}
+    let (registry, _dir) =
+        mock_registry(json!([{ "content": [{ "type": "text", "text": "ok" }] }]));
+    let mut client = PiClient::builder()
+        .model_registry(registry)
+        .model("demo")
+        .on_event(is_agent_event)
+        .build()
+        .unwrap();
+    assert_eq!(client.prompt("ping").unwrap().text, "ok");
+}
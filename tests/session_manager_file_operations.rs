@@ -1,5 +1,7 @@
 use pi::{
-    find_most_recent_session, load_entries_from_file, FileEntry, SessionHeader, SessionManager,
+    compact_session_file, find_most_recent_session, load_entries_from_file, recover_session_file,
+    AgentMessage, AssistantMessage, ContentBlock, FileEntry, SessionHeader, SessionManager, Usage,
+    UserContent, UserMessage, SESSION_COMPACTION_THRESHOLD,
 };
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -64,6 +66,7 @@ fn load_entries_loads_valid_session_file() {
         cwd: "/tmp".to_string(),
         version: None,
         parent_session: None,
+        preview: None,
     });
     let header_line = serde_json::to_string(&header).unwrap();
     let message_line = "{\"type\":\"message\",\"id\":\"1\",\"parentId\":null,\"timestamp\":\"2025-01-01T00:00:01Z\",\"message\":{\"role\":\"user\",\"content\":\"hi\",\"timestamp\":1}}";
@@ -174,3 +177,210 @@ fn create_with_dir_uses_custom_session_dir() {
     let _ = fs::remove_dir_all(session_dir);
     let _ = fs::remove_dir_all(cwd);
 }
+
+const HEADER_LINE: &str = "{\"type\":\"session\",\"version\":3,\"id\":\"abc\",\"timestamp\":\"2025-01-01T00:00:00Z\",\"cwd\":\"/tmp\"}";
+const MESSAGE_LINE: &str = "{\"type\":\"message\",\"id\":\"m1\",\"parentId\":null,\"timestamp\":\"2025-01-01T00:00:01Z\",\"message\":{\"role\":\"user\",\"content\":\"hi\",\"timestamp\":1}}";
+
+#[test]
+fn recover_drops_torn_trailing_line() {
+    let dir = temp_dir("torn");
+    let file = dir.join("torn.jsonl");
+    write_file(
+        &file,
+        &format!("{HEADER_LINE}\n{MESSAGE_LINE}\n{{\"type\":\"message\",\"id\":\"m2\""),
+    );
+
+    assert!(recover_session_file(&file).unwrap());
+    assert_eq!(
+        fs::read_to_string(&file).unwrap(),
+        format!("{HEADER_LINE}\n{MESSAGE_LINE}\n")
+    );
+    assert!(!recover_session_file(&file).unwrap());
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn recover_keeps_complete_line_missing_newline() {
+    let dir = temp_dir("no-newline");
+    let file = dir.join("no-newline.jsonl");
+    write_file(&file, &format!("{HEADER_LINE}\n{MESSAGE_LINE}"));
+
+    assert!(recover_session_file(&file).unwrap());
+    assert_eq!(load_entries_from_file(&file).len(), 2);
+    assert!(fs::read_to_string(&file).unwrap().ends_with('\n'));
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn open_recovers_and_appends_without_rewriting() {
+    let dir = temp_dir("append-only");
+    let file = dir.join("session.jsonl");
+    let unknown_line = "{\"type\":\"from_a_fork\",\"id\":\"x1\"}";
+    write_file(
+        &file,
+        &format!("{HEADER_LINE}\n{unknown_line}\n{MESSAGE_LINE}\n{{\"type\":\"mess"),
+    );
+
+    let mut session = SessionManager::open(file.clone(), None);
+    session.append_message(AgentMessage::Assistant(AssistantMessage {
+        content: vec![ContentBlock::Text {
+            text: "hello".to_string(),
+            text_signature: None,
+        }],
+        api: "test".to_string(),
+        provider: "test".to_string(),
+        model: "test".to_string(),
+        usage: Usage {
+            input: 1,
+            output: 1,
+            cache_read: 0,
+            cache_write: 0,
+            total_tokens: None,
+            cost: None,
+        },
+        stop_reason: "stop".to_string(),
+        error_message: None,
+        timestamp: 2,
+    }));
+    session.append_message(AgentMessage::User(UserMessage {
+        content: UserContent::Text("again".to_string()),
+        timestamp: 3,
+    }));
+
+    let content = fs::read_to_string(&file).unwrap();
+    let lines = content.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 5);
+    assert_eq!(lines[..3], [HEADER_LINE, unknown_line, MESSAGE_LINE]);
+    assert_eq!(load_entries_from_file(&file).len(), 4);
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn compaction_drops_superseded_labels_and_session_info() {
+    let dir = temp_dir("compact");
+    let file = dir.join("session.jsonl");
+    let line = |value: serde_json::Value| value.to_string();
+    let unknown_line = "{\"type\":\"from_a_fork\",\"id\":\"x1\",\"parentId\":\"l1\"}";
+    let lines = [
+        HEADER_LINE.to_string(),
+        MESSAGE_LINE.to_string(),
+        line(
+            serde_json::json!({"type": "label", "id": "l1", "parentId": "m1",
+            "timestamp": "t", "targetId": "m1", "label": "first"}),
+        ),
+        line(
+            serde_json::json!({"type": "session_info", "id": "i1", "parentId": "l1",
+            "timestamp": "t", "name": "Old"}),
+        ),
+        line(
+            serde_json::json!({"type": "label", "id": "l2", "parentId": "i1",
+            "timestamp": "t", "targetId": "m1", "label": "second"}),
+        ),
+        line(
+            serde_json::json!({"type": "session_info", "id": "i2", "parentId": "l2",
+            "timestamp": "t", "name": "New"}),
+        ),
+        unknown_line.to_string(),
+    ];
+    write_file(&file, &(lines.join("\n") + "\n"));
+
+    // x1 refers to l1 only as its parent, so both l1 and i1 go.
+    assert_eq!(compact_session_file(&file).unwrap(), 2);
+    let content = fs::read_to_string(&file).unwrap();
+    let compacted = content.lines().collect::<Vec<_>>();
+    assert_eq!(compacted.len(), 5);
+    assert_eq!(compacted[..2], [HEADER_LINE, MESSAGE_LINE]);
+    let l2: serde_json::Value = serde_json::from_str(compacted[2]).unwrap();
+    assert_eq!(
+        (l2["id"].as_str(), l2["parentId"].as_str()),
+        (Some("l2"), Some("m1"))
+    );
+    assert_eq!(compacted[3], lines[5]);
+    let x1: serde_json::Value = serde_json::from_str(compacted[4]).unwrap();
+    assert_eq!(x1["parentId"], "m1");
+
+    let session = SessionManager::open(file.clone(), None);
+    assert_eq!(session.get_label("m1").as_deref(), Some("second"));
+    assert_eq!(session.get_session_name().as_deref(), Some("New"));
+    assert_eq!(compact_session_file(&file).unwrap(), 0);
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn compaction_keeps_entries_other_entries_refer_to() {
+    let dir = temp_dir("compact-refs");
+    let file = dir.join("session.jsonl");
+    let content = [
+        HEADER_LINE.to_string(),
+        MESSAGE_LINE.to_string(),
+        serde_json::json!({"type": "label", "id": "l1", "parentId": "m1",
+            "timestamp": "t", "targetId": "m1", "label": "first"})
+        .to_string(),
+        serde_json::json!({"type": "label", "id": "l2", "parentId": "l1",
+            "timestamp": "t", "targetId": "l1", "label": "about l1"})
+        .to_string(),
+        serde_json::json!({"type": "label", "id": "l3", "parentId": "l2",
+            "timestamp": "t", "targetId": "m1", "label": "second"})
+        .to_string(),
+    ]
+    .join("\n")
+        + "\n";
+    write_file(&file, &content);
+
+    assert_eq!(compact_session_file(&file).unwrap(), 0);
+    assert_eq!(fs::read_to_string(&file).unwrap(), content);
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn session_file_is_compacted_as_superseded_entries_pile_up() {
+    let dir = temp_dir("compact-periodic");
+    let mut session = SessionManager::create_with_dir(dir.clone(), dir.clone());
+    let user = session.append_message(AgentMessage::User(UserMessage {
+        content: UserContent::Text("hi".to_string()),
+        timestamp: 1,
+    }));
+    let reply = session.append_message(AgentMessage::Assistant(AssistantMessage {
+        content: vec![ContentBlock::Text {
+            text: "hello".to_string(),
+            text_signature: None,
+        }],
+        api: "test".to_string(),
+        provider: "test".to_string(),
+        model: "test".to_string(),
+        usage: Usage {
+            input: 1,
+            output: 1,
+            cache_read: 0,
+            cache_write: 0,
+            total_tokens: None,
+            cost: None,
+        },
+        stop_reason: "stop".to_string(),
+        error_message: None,
+        timestamp: 2,
+    }));
+    let file = session.get_session_file().unwrap();
+
+    for round in 0..=SESSION_COMPACTION_THRESHOLD {
+        session
+            .append_label_change(&user, Some(&format!("label {round}")))
+            .unwrap();
+    }
+    let lines = fs::read_to_string(&file).unwrap().lines().count();
+    assert_eq!(lines, 4);
+    assert_eq!(
+        session.get_label(&user),
+        Some(format!("label {SESSION_COMPACTION_THRESHOLD}"))
+    );
+    let branch = session.get_branch(None);
+    assert_eq!(branch.len(), 3);
+    assert_eq!(branch[1].id(), reply);
+    assert_eq!(branch[2].parent_id(), Some(reply.as_str()));
+
+    session.set_session_name(Some("Renamed"));
+    let reopened = SessionManager::open(file, None);
+    assert_eq!(reopened.get_session_name().as_deref(), Some("Renamed"));
+    assert_eq!(reopened.get_leaf_id(), session.get_leaf_id());
+    let _ = fs::remove_dir_all(dir);
+}
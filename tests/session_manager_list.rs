@@ -1,4 +1,6 @@
-use pi::core::session_manager::{FileEntry, SessionHeader, SessionManager, SessionMessageEntry};
+use pi::core::session_manager::{
+    extract_session_preview, FileEntry, SessionHeader, SessionManager, SessionMessageEntry,
+};
 use pi::{AgentMessage, AssistantMessage, ContentBlock, Cost, Usage, UserContent, UserMessage};
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -75,6 +77,7 @@ fn write_session_file(path: &Path, id: &str, messages: Vec<AgentMessage>) {
         cwd: ".".to_string(),
         version: Some(2),
         parent_session: None,
+        preview: None,
     };
     let mut entries = Vec::new();
     entries.push(FileEntry::Session(header));
@@ -119,3 +122,54 @@ fn list_sessions_returns_message_info() {
     assert!(sessions[0].all_messages_text.contains("Second session"));
     assert!(sessions[0].all_messages_text.contains("Another reply"));
 }
+
+#[test]
+fn extract_session_preview_skips_file_blocks() {
+    let message = "<file name=\"src/main.rs\">\nfn main() {\n    println!(\"hi.\");\n}\n</file>\n\
+                   <file name=\"notes.md\"></file>\n\
+                   Why does the build fail on Windows? It works on Linux.";
+    assert_eq!(
+        extract_session_preview(message),
+        "Why does the build fail on Windows?"
+    );
+    assert_eq!(
+        extract_session_preview("```\nlet x = 1;\n```\nExplain this snippet"),
+        "Explain this snippet"
+    );
+    assert_eq!(
+        extract_session_preview("<file name=\"a.rs\">x</file><file name=\"b.rs\">y</file>"),
+        "[files: a.rs, b.rs]"
+    );
+    assert_eq!(
+        extract_session_preview("Use v1.2 of the API please"),
+        "Use v1.2 of the API please"
+    );
+    assert_eq!(
+        extract_session_preview(&"word ".repeat(100))
+            .chars()
+            .count(),
+        160
+    );
+}
+
+#[test]
+fn list_sessions_uses_stored_preview() {
+    let temp = TempDir::new("pi-session-preview");
+    let mut session = SessionManager::create_with_dir(temp.path.clone(), temp.path.clone());
+    session.append_message(user_msg(
+        "<file name=\"big.log\">\nlots of output\n</file>\nSummarize the errors in this log. Thanks!",
+    ));
+    session.append_message(assistant_msg("Done"));
+    session.append_message(user_msg("Second question"));
+    assert_eq!(
+        session.get_session_preview().as_deref(),
+        Some("Summarize the errors in this log.")
+    );
+
+    let sessions = SessionManager::list(&temp.path, Some(temp.path.clone()));
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].preview, "Summarize the errors in this log.");
+    assert!(sessions[0]
+        .first_message
+        .starts_with("<file name=\"big.log\">"));
+}
@@ -1,4 +1,23 @@
-use pi::{migrate_session_entries, FileEntry, SessionHeader, SessionMessageEntry};
+use pi::{
+    check_session_version, migrate_session_entries, read_session_version, FileEntry, SessionHeader,
+    SessionManager, SessionMessageEntry, CURRENT_SESSION_VERSION,
+};
+use std::fs;
+use std::path::PathBuf;
+
+fn temp_file(name: &str) -> PathBuf {
+    let mut dir = std::env::temp_dir();
+    dir.push(format!(
+        "pi-session-migration-{}-{}",
+        name,
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    ));
+    let _ = fs::create_dir_all(&dir);
+    dir.join("session.jsonl")
+}
 
 #[test]
 fn adds_id_and_parent_id_to_v1_entries() {
@@ -9,6 +28,7 @@ fn adds_id_and_parent_id_to_v1_entries() {
             timestamp: "2025-01-01T00:00:00Z".to_string(),
             cwd: "/tmp".to_string(),
             parent_session: None,
+            preview: None,
         }),
         FileEntry::Message(SessionMessageEntry {
             id: String::new(),
@@ -81,6 +101,7 @@ fn migration_is_idempotent() {
             timestamp: "2025-01-01T00:00:00Z".to_string(),
             cwd: "/tmp".to_string(),
             parent_session: None,
+            preview: None,
         }),
         FileEntry::Message(SessionMessageEntry {
             id: "abc12345".to_string(),
@@ -143,6 +164,7 @@ fn migrates_v2_header_to_v3() {
             timestamp: "2025-01-01T00:00:00Z".to_string(),
             cwd: "/tmp".to_string(),
             parent_session: None,
+            preview: None,
         }),
         FileEntry::Message(SessionMessageEntry {
             id: "abc12345".to_string(),
@@ -163,3 +185,43 @@ fn migrates_v2_header_to_v3() {
     };
     assert_eq!(header.version, Some(3));
 }
+
+#[test]
+fn opening_v1_file_upgrades_it_on_disk() {
+    let path = temp_file("v1");
+    fs::write(
+        &path,
+        concat!(
+            r#"{"type":"session","id":"sess-1","timestamp":"2025-01-01T00:00:00Z","cwd":"/tmp"}"#,
+            "\n",
+            r#"{"type":"message","timestamp":"2025-01-01T00:00:01Z","message":{"role":"user","content":"hi","timestamp":1}}"#,
+            "\n"
+        ),
+    )
+    .unwrap();
+    assert_eq!(read_session_version(&path), Some(1));
+
+    let manager = SessionManager::try_open(path.clone(), None).unwrap();
+    assert_eq!(manager.get_entries().len(), 1);
+    assert_eq!(read_session_version(&path), Some(CURRENT_SESSION_VERSION));
+}
+
+#[test]
+fn refuses_sessions_from_newer_versions() {
+    let path = temp_file("newer");
+    let content = concat!(
+        r#"{"type":"session","id":"sess-1","version":99,"timestamp":"2025-01-01T00:00:00Z","cwd":"/tmp"}"#,
+        "\n",
+        r#"{"type":"hologram","id":"a1b2c3d4","timestamp":"2025-01-01T00:00:01Z"}"#,
+        "\n"
+    );
+    fs::write(&path, content).unwrap();
+
+    let err = check_session_version(&path).unwrap_err();
+    assert!(err.contains("version 99"));
+    assert!(SessionManager::try_open(path.clone(), None).is_err());
+
+    // Opening without the check must not rewrite (and drop) unknown entries.
+    let _ = SessionManager::open(path.clone(), None);
+    assert_eq!(fs::read_to_string(&path).unwrap(), content);
+}
@@ -34,3 +34,19 @@ fn saves_custom_entries_and_includes_them_in_tree_traversal() {
     let ctx = session.build_session_context();
     assert_eq!(ctx.messages.len(), 2);
 }
+
+#[test]
+fn records_latest_seed_on_current_branch() {
+    let mut session = SessionManager::in_memory();
+    assert_eq!(session.get_seed(), None);
+
+    session.append_message(user_msg("hello"));
+    let first_seed_id = session.append_seed(7);
+    session.append_message(assistant_msg("hi"));
+    session.append_seed(42);
+    assert_eq!(session.get_seed(), Some(42));
+
+    session.branch(&first_seed_id).unwrap();
+    assert_eq!(session.get_seed(), Some(7));
+    assert_eq!(session.build_session_context().messages.len(), 1);
+}
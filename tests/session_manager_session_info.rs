@@ -0,0 +1,128 @@
+mod test_utils;
+
+use pi::{SessionEntry, SessionFilter, SessionManager};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use test_utils::{assistant_msg, user_msg};
+
+struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    fn new(prefix: &str) -> Self {
+        let mut path = std::env::temp_dir();
+        let since_epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        path.push(format!("{prefix}-{since_epoch}-{}", std::process::id()));
+        fs::create_dir_all(&path).expect("create temp dir");
+        Self { path }
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+#[test]
+fn sets_name_and_tags() {
+    let mut session = SessionManager::in_memory();
+    session.append_message(user_msg("hello"));
+    assert!(session.get_session_name().is_none());
+    assert!(session.get_session_tags().is_empty());
+
+    session.set_session_name(Some("  Refactor parser  "));
+    session.set_session_tags(&[
+        "rust".to_string(),
+        " parser ".to_string(),
+        "rust".to_string(),
+        String::new(),
+    ]);
+
+    assert_eq!(
+        session.get_session_name().as_deref(),
+        Some("Refactor parser")
+    );
+    assert_eq!(session.get_session_tags(), vec!["rust", "parser"]);
+
+    session.set_session_name(None);
+    assert!(session.get_session_name().is_none());
+    assert_eq!(session.get_session_tags(), vec!["rust", "parser"]);
+}
+
+#[test]
+fn session_info_entries_do_not_affect_context() {
+    let mut session = SessionManager::in_memory();
+    session.append_message(user_msg("hello"));
+    session.set_session_name(Some("greeting"));
+    session.append_message(assistant_msg("hi"));
+
+    let context = session.build_session_context();
+    assert_eq!(context.messages.len(), 2);
+    assert!(session
+        .get_entries()
+        .iter()
+        .any(|entry| matches!(entry, SessionEntry::SessionInfo(_))));
+}
+
+#[test]
+fn list_and_search_include_name_and_tags() {
+    let temp = TempDir::new("pi-session-info");
+    let cwd = temp.path.clone();
+
+    let mut tagged = SessionManager::create_with_dir(cwd.clone(), temp.path.clone());
+    tagged.append_message(user_msg("fix the flaky test"));
+    tagged.append_message(assistant_msg("done"));
+    tagged.set_session_name(Some("Flaky test"));
+    tagged.set_session_tags(&["ci".to_string()]);
+
+    let mut other = SessionManager::create_with_dir(cwd.clone(), temp.path.clone());
+    other.append_message(user_msg("write docs"));
+    other.append_message(assistant_msg("ok"));
+
+    let sessions = SessionManager::list(&cwd, Some(temp.path.clone()));
+    assert_eq!(sessions.len(), 2);
+    let named = sessions
+        .iter()
+        .find(|session| session.name.is_some())
+        .expect("named session");
+    assert_eq!(named.name.as_deref(), Some("Flaky test"));
+    assert_eq!(named.tags, vec!["ci"]);
+    assert_eq!(named.message_count, 2);
+
+    let by_content = SessionManager::search(
+        &cwd,
+        Some(temp.path.clone()),
+        &SessionFilter {
+            query: Some("DOCS".to_string()),
+            tags: Vec::new(),
+        },
+    );
+    assert_eq!(by_content.len(), 1);
+    assert_eq!(by_content[0].first_message, "write docs");
+
+    let by_name = SessionManager::search(
+        &cwd,
+        Some(temp.path.clone()),
+        &SessionFilter {
+            query: Some("flaky".to_string()),
+            tags: vec!["CI".to_string()],
+        },
+    );
+    assert_eq!(by_name.len(), 1);
+
+    let by_missing_tag = SessionManager::search(
+        &cwd,
+        Some(temp.path.clone()),
+        &SessionFilter {
+            query: None,
+            tags: vec!["release".to_string()],
+        },
+    );
+    assert!(by_missing_tag.is_empty());
+}
@@ -0,0 +1,190 @@
+use pi::agent::{get_model, Agent, AgentOptions, AgentStateOverride, AgentTool, AgentToolResult};
+use pi::coding_agent::{
+    spend_day, AgentSession, AgentSessionConfig, AgentSessionError, AuthStorage, ModelRegistry,
+    SettingsManager, SpendLimits, SpendSnapshot, SpendTracker,
+};
+use pi::core::messages::{AssistantMessage, ContentBlock, Cost, Usage};
+use pi::core::session_manager::SessionManager;
+use serde_json::json;
+use std::cell::Cell;
+use std::path::PathBuf;
+use std::rc::Rc;
+use uuid::Uuid;
+
+fn spend_path() -> PathBuf {
+    std::env::temp_dir().join(format!("pi-spend-{}/spend.json", Uuid::new_v4()))
+}
+
+/// Every reply costs $0.30 and asks for another tool call, so only a budget ends the loop.
+fn create_session(replies: Rc<Cell<usize>>, tool_runs: Rc<Cell<usize>>) -> AgentSession {
+    let tool = AgentTool {
+        name: "echo".to_string(),
+        label: "echo".to_string(),
+        description: "Echo".to_string(),
+        parameters: None,
+        execute: Rc::new(move |_call_id, _params, _on_update| {
+            tool_runs.set(tool_runs.get() + 1);
+            Ok(AgentToolResult {
+                content: vec![ContentBlock::Text {
+                    text: "ok".to_string(),
+                    text_signature: None,
+                }],
+                details: json!(null),
+            })
+        }),
+    };
+    let stream_fn: Box<pi::agent::StreamFn> = Box::new(move |_model, _context, _events| {
+        replies.set(replies.get() + 1);
+        AssistantMessage {
+            content: vec![ContentBlock::ToolCall {
+                id: format!("call-{}", replies.get()),
+                name: "echo".to_string(),
+                arguments: json!({}),
+                thought_signature: None,
+            }],
+            api: "anthropic-messages".to_string(),
+            provider: "anthropic".to_string(),
+            model: "mock".to_string(),
+            usage: Usage {
+                input: 100,
+                output: 10,
+                cache_read: 0,
+                cache_write: 0,
+                total_tokens: Some(110),
+                cost: Some(Cost {
+                    input: 0.2,
+                    output: 0.1,
+                    cache_read: 0.0,
+                    cache_write: 0.0,
+                    total: 0.3,
+                }),
+            },
+            stop_reason: "toolUse".to_string(),
+            error_message: None,
+            timestamp: 0,
+        }
+    });
+    let agent = Agent::new(AgentOptions {
+        initial_state: Some(AgentStateOverride {
+            model: Some(get_model("anthropic", "claude-sonnet-4-5")),
+            system_prompt: Some("Test".to_string()),
+            tools: Some(vec![tool]),
+            ..Default::default()
+        }),
+        stream_fn: Some(stream_fn),
+        ..Default::default()
+    });
+    AgentSession::new(AgentSessionConfig {
+        agent,
+        session_manager: SessionManager::in_memory(),
+        settings_manager: SettingsManager::create("", ""),
+        model_registry: ModelRegistry::new(AuthStorage::new(PathBuf::from("auth.json")), None),
+    })
+}
+
+#[test]
+fn tracker_accumulates_per_session_and_day() {
+    let path = spend_path();
+    let mut tracker = SpendTracker::load(&path).unwrap();
+    tracker.record("a", "2026-01-01", 0.25).unwrap();
+    tracker.record("b", "2026-01-01", 0.5).unwrap();
+    tracker.record("a", "2026-01-02", 1.0).unwrap();
+
+    let reloaded = SpendTracker::load(&path).unwrap();
+    assert_eq!(reloaded.session_total("a"), 1.25);
+    assert_eq!(reloaded.day_total("2026-01-01"), 0.75);
+    assert_eq!(reloaded.day_total("2026-01-03"), 0.0);
+
+    let snapshot = SpendSnapshot {
+        session: 1.25,
+        today: 1.0,
+        limits: SpendLimits {
+            max_cost: Some(2.0),
+            max_daily_cost: Some(1.0),
+        },
+    };
+    assert!(snapshot.exceeded().unwrap().contains("daily limit"));
+    let under = SpendSnapshot {
+        today: 0.5,
+        ..snapshot
+    };
+    assert_eq!(under.exceeded(), None);
+}
+
+#[test]
+fn concurrent_records_all_land_in_the_ledger() {
+    let path = spend_path();
+    let threads = (0..8)
+        .map(|index| {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                let mut tracker = SpendTracker::load(&path).unwrap();
+                for _ in 0..10 {
+                    tracker
+                        .record(&format!("s{index}"), "2026-01-01", 0.5)
+                        .unwrap();
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    let ledger = SpendTracker::load(&path).unwrap();
+    assert_eq!(ledger.day_total("2026-01-01"), 40.0);
+    assert_eq!(ledger.session_total("s3"), 5.0);
+}
+
+#[test]
+fn corrupt_ledger_is_an_error_and_is_left_alone() {
+    let path = spend_path();
+    let mut tracker = SpendTracker::load(&path).unwrap();
+    tracker.record("a", "2026-01-01", 1.0).unwrap();
+
+    std::fs::write(&path, "{\"days\": {\"2026-01-01\": 1.0").unwrap();
+    let err = SpendTracker::load(&path).unwrap_err();
+    assert!(
+        matches!(&err, pi::Error::Io(io) if io.kind() == std::io::ErrorKind::InvalidData),
+        "{err:?}"
+    );
+    assert!(err.to_string().contains("corrupt"), "{err}");
+    assert!(tracker.record("a", "2026-01-01", 1.0).is_err());
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "{\"days\": {\"2026-01-01\": 1.0"
+    );
+}
+
+#[test]
+fn max_cost_stops_the_agent_loop_and_refuses_further_prompts() {
+    let replies = Rc::new(Cell::new(0));
+    let tool_runs = Rc::new(Cell::new(0));
+    let mut session = create_session(replies.clone(), tool_runs.clone());
+    let path = spend_path();
+    session.set_spend_tracking(
+        SpendTracker::load(&path).unwrap(),
+        SpendLimits {
+            max_cost: Some(0.5),
+            max_daily_cost: None,
+        },
+    );
+
+    let err = session.prompt("loop forever").unwrap_err();
+    assert!(matches!(err, AgentSessionError::BudgetExceeded(_)));
+    assert!(err.to_string().contains("$0.50 limit"), "{err}");
+    assert_eq!(replies.get(), 2);
+    assert_eq!(tool_runs.get(), 1);
+
+    let spend = session.get_spend();
+    assert!((spend.session - 0.6).abs() < 1e-9);
+    let recorded = SpendTracker::load(&path).unwrap();
+    assert!((recorded.session_total(&session.session_id()) - 0.6).abs() < 1e-9);
+    assert!((recorded.day_total(&spend_day()) - 0.6).abs() < 1e-9);
+
+    assert!(matches!(
+        session.prompt("again"),
+        Err(AgentSessionError::BudgetExceeded(_))
+    ));
+    assert_eq!(replies.get(), 2);
+}
@@ -194,6 +194,7 @@ fn anthropic_live_streaming_text() {
             base_url: &model.base_url,
             extra_headers: model.headers.as_ref(),
             system: Some(&context.system_prompt),
+            request_hook: None,
         },
         &mut events,
     )
@@ -263,6 +264,7 @@ fn anthropic_live_tool_call() {
             base_url: &model.base_url,
             extra_headers: model.headers.as_ref(),
             system: Some(&context.system_prompt),
+            request_hook: None,
         },
         &mut events,
     )
@@ -511,6 +513,8 @@ fn gemini_cli_live_streaming_text() {
             base_url: &model.base_url,
             system: Some(&context.system_prompt),
             thinking_enabled: model.reasoning,
+            seed: None,
+            request_hook: None,
         },
         &mut events,
     )
@@ -594,6 +598,8 @@ fn gemini_cli_live_tool_call() {
             base_url: &model.base_url,
             system: Some(&context.system_prompt),
             thinking_enabled: model.reasoning,
+            seed: None,
+            request_hook: None,
         },
         &mut events,
     )
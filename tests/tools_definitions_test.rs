@@ -0,0 +1,34 @@
+use pi::tools::{default_tools, tool_verbosity_for_model, ToolVerbosity};
+
+#[test]
+fn small_or_cheap_models_get_terse_tool_definitions() {
+    assert_eq!(tool_verbosity_for_model(32_000, 3.0), ToolVerbosity::Terse);
+    assert_eq!(
+        tool_verbosity_for_model(1_000_000, 0.1),
+        ToolVerbosity::Terse
+    );
+    assert_eq!(
+        tool_verbosity_for_model(200_000, 3.0),
+        ToolVerbosity::Verbose
+    );
+    assert_eq!(tool_verbosity_for_model(0, 0.0), ToolVerbosity::Verbose);
+}
+
+#[test]
+fn terse_definitions_are_shorter_and_drop_parameter_descriptions() {
+    for tool in default_tools() {
+        let terse = tool.description_for(ToolVerbosity::Terse);
+        assert!(terse.len() <= tool.description_for(ToolVerbosity::Verbose).len());
+
+        let schema = tool.input_schema_for(ToolVerbosity::Terse);
+        for property in schema["properties"].as_object().unwrap().values() {
+            assert!(property.get("description").is_none(), "{}", tool.name);
+            assert!(property.get("type").is_some());
+        }
+        assert_eq!(schema["required"], tool.input_schema["required"]);
+        assert_eq!(
+            tool.input_schema_for(ToolVerbosity::Verbose),
+            tool.input_schema
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use pi::agent::CancellationToken;
+use pi::cli::session::build_agent_tools;
+use pi::coding_agent::{BashApproval, EditReview, SandboxPolicy, SharedChangeJournal};
+use pi::tools::default_tools;
+use pi::tools::schema::{format_errors, validate};
+use serde_json::json;
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[test]
+fn reports_every_problem_with_its_path() {
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "path": { "type": "string" },
+            "limit": { "type": "integer", "minimum": 1 },
+            "mode": { "type": "string", "enum": ["fast", "slow"] },
+            "tags": { "type": "array", "items": { "type": "string" } }
+        },
+        "required": ["path"],
+        "additionalProperties": false
+    });
+
+    assert!(validate(&schema, &json!({ "path": "a.txt", "limit": 5 })).is_empty());
+
+    let errors = validate(
+        &schema,
+        &json!({ "limit": "10", "mode": "medium", "tags": ["a", 2], "extra": true }),
+    )
+    .into_iter()
+    .map(|error| error.to_string())
+    .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        [
+            "/path: is required",
+            "/extra: is not an allowed property",
+            "/limit: expected integer, got string",
+            "/mode: must be one of \"fast\", \"slow\"",
+            "/tags/1: expected string, got integer",
+        ]
+    );
+    assert_eq!(
+        validate(&schema, &json!({ "path": "a", "limit": 0 }))[0].to_string(),
+        "/limit: must be >= 1"
+    );
+    assert_eq!(
+        validate(&schema, &json!("a.txt"))[0].to_string(),
+        "expected object, got string"
+    );
+}
+
+#[test]
+fn supports_type_lists_and_combinators() {
+    let schema = json!({
+        "anyOf": [
+            { "type": ["string", "null"] },
+            { "type": "integer", "maximum": 3 }
+        ]
+    });
+    assert!(validate(&schema, &json!(null)).is_empty());
+    assert!(validate(&schema, &json!(2.0)).is_empty());
+    assert_eq!(
+        validate(&schema, &json!(4))[0].to_string(),
+        "does not match any of the allowed schemas"
+    );
+
+    let one_of = json!({ "oneOf": [{ "type": "number" }, { "type": "integer" }] });
+    assert!(validate(&one_of, &json!(1.5)).is_empty());
+    assert_eq!(
+        validate(&one_of, &json!(1))[0].message,
+        "must match exactly one allowed schema (matched 2)"
+    );
+}
+
+#[test]
+fn built_in_tool_schemas_accept_typical_arguments() {
+    let samples = [
+        ("read", json!({ "path": "a.txt", "offset": 1, "limit": 10 })),
+        (
+            "edit",
+            json!({ "path": "a.txt", "oldText": "a", "newText": "b" }),
+        ),
+        ("bash", json!({ "command": "ls", "timeout": 5 })),
+        (
+            "notebook_edit",
+            json!({ "path": "a.ipynb", "action": "insert" }),
+        ),
+    ];
+    let tools = default_tools();
+    for (name, args) in samples {
+        let tool = tools.iter().find(|tool| tool.name == name).unwrap();
+        assert!(validate(&tool.input_schema, &args).is_empty(), "{name}");
+    }
+}
+
+#[test]
+fn agent_tools_return_validation_errors_to_the_model() {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let dir = std::env::temp_dir().join(format!("pi-tool-schema-{nanos}"));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("a.txt"), "hello\n").unwrap();
+
+    let tools = build_agent_tools(
+        &dir,
+        Some(&["read".to_string()]),
+        &[],
+        None,
+        &SharedChangeJournal::default(),
+        &SandboxPolicy::allow_all(),
+        &BashApproval::default(),
+        &EditReview::default(),
+        &CancellationToken::default(),
+    )
+    .unwrap();
+    let read = &tools[0];
+
+    let err = read
+        .check_arguments(&json!({ "file": "a.txt", "limit": "2" }))
+        .unwrap_err();
+    let errors = validate(
+        &default_tools()
+            .into_iter()
+            .find(|tool| tool.name == "read")
+            .unwrap()
+            .input_schema,
+        &json!({ "file": "a.txt", "limit": "2" }),
+    );
+    assert_eq!(err, format_errors("read", &errors));
+    assert!(err.starts_with("Invalid arguments for tool \"read\":\n- /path: is required"));
+    assert!(err.contains("\n- /file: is not an allowed property"));
+    assert!(err.contains("\n- /limit: expected integer, got string"));
+
+    assert!(read.check_arguments(&json!({ "path": "a.txt" })).is_ok());
+    fs::remove_dir_all(&dir).ok();
+}
@@ -1,4 +1,4 @@
-use pi::tui::{CombinedAutocompleteProvider, SlashCommand};
+use pi::tui::{AutocompleteItem, CombinedAutocompleteProvider, SlashCommand};
 
 // Source: packages/tui/test/autocomplete.test.ts
 
@@ -147,3 +147,85 @@ fn apply_completion_for_slash_command() {
     assert_eq!(new_line, 0);
     assert_eq!(new_col, 7);
 }
+
+#[test]
+fn fuzzy_file_suggestions_for_at_references_respect_gitignore() {
+    let root = std::env::temp_dir().join(format!("pi-autocomplete-{}", uuid::Uuid::new_v4()));
+    for file in [
+        "src/main.rs",
+        "src/tui/editor.rs",
+        "target/debug/main.rs",
+        "logs/app.log",
+        "docs/readme.md",
+    ] {
+        let path = root.join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, "x").unwrap();
+    }
+    std::fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+
+    let provider = CombinedAutocompleteProvider::new(vec![], &root);
+    let lines = vec![String::from("look at @mainrs")];
+    let result = provider.get_suggestions(&lines, 0, 15).unwrap();
+    assert_eq!(result.prefix, "@mainrs");
+    let values = result
+        .items
+        .iter()
+        .map(|item| item.value.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(values, vec!["@src/main.rs"]);
+    assert_eq!(result.items[0].label, "main.rs");
+    assert_eq!(result.items[0].description.as_deref(), Some("src"));
+
+    let lines = vec![String::from("@app")];
+    assert!(provider.get_suggestions(&lines, 0, 4).is_none());
+
+    let lines = vec![String::from("@tuied")];
+    let result = provider.get_suggestions(&lines, 0, 6).unwrap();
+    assert_eq!(result.items[0].value, "@src/tui/editor.rs");
+
+    let _ = std::fs::remove_dir_all(root);
+}
+
+#[test]
+fn completes_known_command_arguments() {
+    let item = |value: &str| AutocompleteItem {
+        value: value.to_string(),
+        label: value.to_string(),
+        description: None,
+    };
+    let provider = CombinedAutocompleteProvider::new(
+        vec![
+            SlashCommand::new("model", None),
+            SlashCommand::new("tools", None),
+        ],
+        "/tmp",
+    )
+    .with_argument_completions(
+        "model",
+        vec![
+            item("openai/gpt-5"),
+            item("anthropic/claude-sonnet-4-5"),
+            item("anthropic/claude-opus-4-1"),
+        ],
+    );
+
+    let lines = vec![String::from("/model sonn")];
+    let result = provider.get_suggestions(&lines, 0, 11).unwrap();
+    assert_eq!(result.prefix, "sonn");
+    assert_eq!(result.items, vec![item("anthropic/claude-sonnet-4-5")]);
+
+    let (lines, _, col) =
+        provider.apply_completion(&lines, 0, 11, &result.items[0], &result.prefix);
+    assert_eq!(lines[0], "/model anthropic/claude-sonnet-4-5");
+    assert_eq!(col, lines[0].len());
+
+    let lines = vec![String::from("/model ")];
+    assert_eq!(
+        provider.get_suggestions(&lines, 0, 7).unwrap().items.len(),
+        3
+    );
+
+    let lines = vec![String::from("/tools x")];
+    assert!(provider.get_suggestions(&lines, 0, 8).is_none());
+}
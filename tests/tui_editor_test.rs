@@ -184,6 +184,69 @@ fn limits_history_to_100_entries() {
     assert_eq!(editor.get_text(), "prompt 5");
 }
 
+#[test]
+fn set_history_loads_entries_most_recent_first() {
+    let mut editor = Editor::new(default_editor_theme());
+    editor.set_history_limit(2);
+    editor.set_history(vec!["newest".into(), "older".into(), "oldest".into()]);
+
+    editor.handle_input("\x1b[A");
+    assert_eq!(editor.get_text(), "newest");
+    editor.handle_input("\x1b[A");
+    assert_eq!(editor.get_text(), "older");
+    editor.handle_input("\x1b[A");
+    assert_eq!(editor.get_text(), "older");
+}
+
+#[test]
+fn ctrl_r_searches_history_backwards() {
+    let mut editor = Editor::new(default_editor_theme());
+    editor.add_to_history("cargo build");
+    editor.add_to_history("git status");
+    editor.add_to_history("cargo test --workspace");
+    editor.handle_input("draft");
+
+    editor.handle_input("\x12");
+    assert!(editor.is_searching_history());
+    for ch in ["c", "a", "r", "g", "o"] {
+        editor.handle_input(ch);
+    }
+    assert_eq!(editor.get_text(), "cargo test --workspace");
+    let rendered = editor.render(40);
+    assert!(strip_vt_control_characters(rendered.last().unwrap())
+        .starts_with("(reverse-i-search)`cargo'"));
+
+    editor.handle_input("\x12");
+    assert_eq!(editor.get_text(), "cargo build");
+
+    editor.handle_input("\x12");
+    assert_eq!(editor.get_text(), "cargo build");
+    let rendered = editor.render(40);
+    assert!(rendered
+        .last()
+        .unwrap()
+        .starts_with("(failed reverse-i-search)"));
+
+    editor.handle_input("\r");
+    assert!(!editor.is_searching_history());
+    assert_eq!(editor.get_text(), "cargo build");
+}
+
+#[test]
+fn escape_cancels_history_search_and_restores_draft() {
+    let mut editor = Editor::new(default_editor_theme());
+    editor.add_to_history("git status");
+    editor.handle_input("draft");
+
+    editor.handle_input("\x12");
+    editor.handle_input("g");
+    assert_eq!(editor.get_text(), "git status");
+
+    editor.handle_input("\x1b");
+    assert!(!editor.is_searching_history());
+    assert_eq!(editor.get_text(), "draft");
+}
+
 #[test]
 fn allows_cursor_movement_within_multi_line_history_entry_with_down() {
     let mut editor = Editor::new(default_editor_theme());
@@ -0,0 +1,80 @@
+use pi::tui::{FilterItem, FilterList, FilterListAction};
+
+struct Entry(&'static str);
+
+impl FilterItem for Entry {
+    fn search_text(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+fn list(names: &[&'static str], max_visible: usize) -> FilterList<Entry> {
+    FilterList::new(names.iter().map(|name| Entry(name)).collect(), max_visible)
+}
+
+fn type_query(list: &mut FilterList<Entry>, query: &str) {
+    for ch in query.chars() {
+        assert_eq!(list.handle_input(&ch.to_string()), None);
+    }
+}
+
+#[test]
+fn filter_list_fuzzy_filters_and_selects_original_index() {
+    let mut list = list(
+        &[
+            "anthropic/claude-sonnet-4-5",
+            "openai/gpt-4o",
+            "openai/gpt-4o-mini",
+            "google/gemini-2.5-pro",
+        ],
+        10,
+    );
+
+    type_query(&mut list, "gpt mini");
+    assert_eq!(list.query(), "gpt mini");
+    assert_eq!(list.filtered_len(), 1);
+    assert_eq!(list.handle_input("\r"), Some(FilterListAction::Selected(2)));
+
+    list.set_query("o4o");
+    assert_eq!(list.filtered_len(), 2);
+    assert_eq!(
+        list.selected_item().map(|entry| entry.0),
+        Some("openai/gpt-4o")
+    );
+    list.handle_input("\x1b[B");
+    assert_eq!(list.handle_input("\r"), Some(FilterListAction::Selected(2)));
+
+    assert_eq!(list.handle_input("\x7f"), None);
+    assert_eq!(list.query(), "o4");
+    list.set_query("zzz");
+    assert_eq!(list.filtered_len(), 0);
+    assert_eq!(list.handle_input("\r"), None);
+    assert_eq!(list.handle_input("\x1b"), Some(FilterListAction::Cancelled));
+}
+
+#[test]
+fn filter_list_scrolls_to_keep_selection_visible() {
+    let mut list = list(&["a1", "a2", "a3", "a4", "a5", "a6"], 3);
+    assert_eq!(list.visible_range(), 0..3);
+
+    for _ in 0..4 {
+        list.move_down();
+    }
+    assert_eq!(list.selected_index(), 4);
+    assert_eq!(list.visible_range(), 3..6);
+
+    // Without wrapping the selection stops at the ends.
+    list.move_down();
+    list.move_down();
+    assert_eq!(list.selected_index(), 5);
+
+    let lines = list.render_items(40, "Nothing", |entry, selected| {
+        vec![format!("{}{}", if selected { "> " } else { "  " }, entry.0)]
+    });
+    assert_eq!(lines[..3], ["  a4", "  a5", "> a6"]);
+    assert!(lines[3].contains("(6/6)"));
+
+    let mut wrapping = FilterList::new(vec![Entry("x"), Entry("y")], 5).wrapping();
+    wrapping.move_up();
+    assert_eq!(wrapping.selected_index(), 1);
+}
@@ -1,4 +1,4 @@
-use pi::tui::{visible_width, DefaultTextStyle, Markdown, MarkdownTheme};
+use pi::tui::{visible_width, DefaultTextStyle, Highlighter, Markdown, MarkdownTheme, SyntaxKind};
 
 struct TestMarkdownTheme;
 
@@ -576,3 +576,35 @@ fn should_render_html_tags_in_code_blocks_correctly() {
     let joined = strip_ansi(&lines.join("\n"));
     assert!(joined.contains("<div>") && joined.contains("</div>"));
 }
+
+#[test]
+fn should_highlight_known_languages_in_code_blocks() {
+    let mut highlighter = Highlighter::new("rust");
+    let spans = highlighter.highlight_line("let name = format!(\"hi\"); // greet 42");
+    assert!(spans.contains(&(SyntaxKind::Keyword, "let".to_string())));
+    assert!(spans.contains(&(SyntaxKind::Function, "format".to_string())));
+    assert!(spans.contains(&(SyntaxKind::String, "\"hi\"".to_string())));
+    assert!(spans.contains(&(SyntaxKind::Comment, "// greet 42".to_string())));
+
+    let mut highlighter = Highlighter::new("js");
+    assert_eq!(
+        highlighter.highlight_line("/* start"),
+        vec![(SyntaxKind::Comment, "/* start".to_string())]
+    );
+    let spans = highlighter.highlight_line("end */ const x = 1.5;");
+    assert_eq!(spans[0], (SyntaxKind::Comment, "end */".to_string()));
+    assert!(spans.contains(&(SyntaxKind::Keyword, "const".to_string())));
+    assert!(spans.contains(&(SyntaxKind::Number, "1.5".to_string())));
+
+    assert!(!Highlighter::new("brainfuck").is_supported());
+
+    let markdown = Markdown::new(
+        "```rust\nfn main() {}\n```",
+        0,
+        0,
+        Box::new(TestMarkdownTheme),
+        None,
+    );
+    let lines = markdown.render(80);
+    assert_eq!(strip_ansi(&lines[1]).trim_end(), "  fn main() {}");
+}
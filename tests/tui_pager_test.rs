@@ -0,0 +1,99 @@
+use pi::tui::{visible_width, Pager, PagerResult};
+
+fn strip_ansi(text: &str) -> String {
+    let mut output = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch == '\x1b' {
+            for next in chars.by_ref() {
+                if next.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+            continue;
+        }
+        output.push(ch);
+    }
+    output
+}
+
+fn numbered_text(count: usize) -> String {
+    (1..=count)
+        .map(|n| format!("line {n}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+fn pager_renders_line_numbers_and_scrolls_by_page() {
+    let mut pager = Pager::new("Message 3 of 3", &numbered_text(50));
+    let lines = pager.render_page(40, 12);
+    assert_eq!(lines.len(), 12);
+    assert!(strip_ansi(&lines[0]).contains("Message 3 of 3"));
+    assert_eq!(strip_ansi(&lines[1]), " 1 │ line 1");
+    assert!(strip_ansi(&lines[11]).contains("lines 1-10 of 50"));
+
+    pager.handle_input("\x1b[6~");
+    assert_eq!(pager.top_line(), 10);
+    pager.handle_input("\x1b[B");
+    assert_eq!(pager.top_line(), 11);
+    pager.handle_input("G");
+    assert_eq!(pager.top_line(), 40);
+    pager.handle_input("j");
+    assert_eq!(pager.top_line(), 40);
+    pager.handle_input("g");
+    assert_eq!(pager.top_line(), 0);
+
+    assert_eq!(pager.handle_input("q"), Some(PagerResult::Closed));
+    assert_eq!(pager.handle_input("\x1b"), Some(PagerResult::Closed));
+}
+
+#[test]
+fn pager_search_jumps_between_matches() {
+    let mut text = numbered_text(40);
+    text.push_str("\nneedle here\n");
+    text.push_str(&numbered_text(40));
+    text.push_str("\nanother NEEDLE");
+    let mut pager = Pager::new("Tool result", &text);
+    pager.render_page(40, 12);
+
+    for key in ["/", "n", "e", "e", "d", "l", "e", "\r"] {
+        assert_eq!(pager.handle_input(key), None);
+    }
+    assert_eq!(pager.matches(), &[40, 81]);
+    assert_eq!(pager.top_line(), 40);
+    let rendered = pager.render_page(40, 12);
+    assert!(rendered[1].contains("\x1b[7mneedle\x1b[27m"));
+    assert!(strip_ansi(&rendered[11]).contains("match 1/2"));
+
+    pager.handle_input("n");
+    assert_eq!(pager.top_line(), 72);
+    pager.handle_input("N");
+    assert_eq!(pager.top_line(), 40);
+
+    for key in ["/", "z", "z", "\r"] {
+        pager.handle_input(key);
+    }
+    assert!(pager.matches().is_empty());
+    assert!(strip_ansi(&pager.render_page(40, 12)[11]).contains("Pattern not found: zz"));
+}
+
+#[test]
+fn pager_scrolls_long_lines_horizontally() {
+    let long = format!("{}END", "x".repeat(60));
+    let mut pager = Pager::new("Long", &long);
+    let lines = pager.render_page(30, 5);
+    assert!(lines.iter().all(|line| visible_width(line) <= 30));
+    assert!(!strip_ansi(&lines[1]).contains("END"));
+
+    for _ in 0..8 {
+        pager.handle_input("\x1b[C");
+    }
+    assert_eq!(pager.left_column(), 56);
+    let lines = pager.render_page(30, 5);
+    assert!(strip_ansi(&lines[1]).ends_with("xxxxEND"));
+    assert!(strip_ansi(&lines[4]).contains("col 57"));
+
+    pager.handle_input("h");
+    assert_eq!(pager.left_column(), 48);
+}
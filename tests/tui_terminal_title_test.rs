@@ -0,0 +1,29 @@
+use pi::tui::{progress_sequence, title_sequence, TaskbarProgress};
+
+#[test]
+fn title_sequence_strips_control_characters() {
+    assert_eq!(
+        title_sequence("pi - repo\x07\x1b (running bash)"),
+        "\x1b]0;pi - repo (running bash)\x07"
+    );
+}
+
+#[test]
+fn progress_sequence_encodes_osc_9_4_states() {
+    assert_eq!(
+        progress_sequence(TaskbarProgress::Hidden),
+        "\x1b]9;4;0;0\x07"
+    );
+    assert_eq!(
+        progress_sequence(TaskbarProgress::Indeterminate),
+        "\x1b]9;4;3;0\x07"
+    );
+    assert_eq!(
+        progress_sequence(TaskbarProgress::Percent(150)),
+        "\x1b]9;4;1;100\x07"
+    );
+    assert_eq!(
+        progress_sequence(TaskbarProgress::Error),
+        "\x1b]9;4;2;100\x07"
+    );
+}